@@ -2,22 +2,106 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use azure::azure::AzFloat;
+use app_units::Au;
+use azure::azure::{AzFloat, struct__AzGlyph, struct__AzGlyphBuffer, struct__AzPoint};
 use azure::azure_hl::{AntialiasMode, CapStyle, CompositionOp, JoinStyle};
 use azure::azure_hl::{BackendType, DrawOptions, DrawTarget, Pattern, StrokeOptions, SurfaceFormat};
 use azure::azure_hl::{Color, ColorPattern, DrawSurfaceOptions, Filter, PathBuilder};
 use azure::azure_hl::{ExtendMode, GradientStop, LinearGradientPattern, RadialGradientPattern};
 use azure::azure_hl::SurfacePattern;
+use azure::scaled_font::{FontInfo, ScaledFont};
 use canvas_traits::canvas::*;
 use cssparser::RGBA;
 use euclid::{Transform2D, Point2D, Vector2D, Rect, Size2D};
+use gfx::font::{FontHandleMethods, FontRef, ShapingFlags, ShapingOptions};
+use gfx::font_cache_thread::FontCacheThread;
+use gfx::font_context::FontContext;
+use gfx::font_template::FontTemplateData;
+use gfx::text::glyph::{ByteIndex, GlyphId};
 use ipc_channel::ipc::{IpcBytesSender, IpcSender};
 use num_traits::ToPrimitive;
+use ordered_float::NotNan;
+use range::Range;
 use serde_bytes::ByteBuf;
+use servo_arc::Arc as ServoArc;
+use servo_atoms::Atom;
 use std::mem;
+use std::ptr;
+use std::rc::Rc;
 use std::sync::Arc;
+use style::computed_values::font_variant_caps::T as FontVariantCaps;
+use style::properties::ComputedValues;
+use style::properties::style_structs::Font as FontStyleStruct;
+use style::values::computed::{Angle, NonNegativeLength, Percentage};
+use style::values::computed::font::{FamilyName, FamilyNameSyntax, FontFamily, FontFamilyList};
+use style::values::computed::font::{FontSize, FontStretch, FontStyleAngle, FontWeight};
+use style::values::computed::font::SingleFontFamily;
+use style::values::generics::font::FontStyle as GenericFontStyle;
+use style::values::generics::NonNegative;
+use uluru::{Entry, LRUCache};
+use unicode_bidi::{BidiInfo, Level};
+use unicode_script::{Script, get_script};
 use webrender_api;
 
+/// How many Azure fonts a paint thread keeps around.
+const SCALED_FONT_CACHE_SIZE: usize = 16;
+
+/// The font state shared by all the canvases of a paint thread.
+pub struct CanvasFontContext {
+    font_context: FontContext<FontCacheThread>,
+    /// The most recently used Azure fonts, keyed by font identifier and size, so that the
+    /// font data is not handed over to the backend every time some text is drawn.
+    scaled_fonts: LRUCache<[Entry<((Atom, Au), ScaledFont)>; SCALED_FONT_CACHE_SIZE]>,
+}
+
+impl CanvasFontContext {
+    pub fn new(font_cache_thread: FontCacheThread) -> CanvasFontContext {
+        CanvasFontContext {
+            font_context: FontContext::new(font_cache_thread),
+            scaled_fonts: LRUCache::default(),
+        }
+    }
+
+    fn scaled_font(&mut self, font: &FontRef) -> &ScaledFont {
+        let font = font.borrow();
+        let key = (font.identifier(), font.actual_pt_size);
+        if self.scaled_fonts.find(|entry| entry.0 == key).is_none() {
+            let scaled_font = create_scaled_font(&font.handle.template(), font.actual_pt_size);
+            self.scaled_fonts.insert((key.clone(), scaled_font));
+        }
+        &self.scaled_fonts.find(|entry| entry.0 == key).unwrap().1
+    }
+}
+
+/// A run of glyphs in visual order, all of which use the same font.
+struct GlyphRun {
+    font: FontRef,
+    /// The glyphs and their positions relative to the start of the text, on the
+    /// alphabetic baseline.
+    glyphs: Vec<(GlyphId, Point2D<f32>)>,
+}
+
+/// The result of the text preparation algorithm.
+///
+/// <https://html.spec.whatwg.org/multipage/#text-preparation-algorithm>
+struct PreparedText {
+    runs: Vec<GlyphRun>,
+    /// The physical position of the anchor point relative to the start of the text, on the
+    /// alphabetic baseline.
+    anchor: Point2D<f32>,
+    /// The width of the text in CSS pixels, before any `maxWidth` scaling.
+    width: f32,
+    ascent: f32,
+    descent: f32,
+    em_size: f32,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum TextOperation {
+    Fill,
+    Stroke,
+}
+
 pub struct CanvasData<'a> {
     drawtarget: DrawTarget,
     /// TODO(pcwalton): Support multiple paths.
@@ -69,7 +153,7 @@ impl<'a> CanvasData<'a> {
         // It discards the extra pixels (if any) that won't be painted
         let image_data = crop_image(image_data, image_size, source_rect);
 
-        let writer = |draw_target: &DrawTarget| {
+        let mut writer = |draw_target: &DrawTarget| {
             write_image(&draw_target, image_data, source_rect.size, dest_rect,
                         smoothing_enabled, self.state.draw_options.composition,
                         self.state.draw_options.alpha);
@@ -112,8 +196,296 @@ impl<'a> CanvasData<'a> {
         }
     }
 
-    pub fn fill_text(&self, text: String, x: f64, y: f64, max_width: Option<f64>) {
-        error!("Unimplemented canvas2d.fillText. Values received: {}, {}, {}, {:?}.", text, x, y, max_width);
+    // https://html.spec.whatwg.org/multipage/#dom-context-2d-filltext
+    pub fn fill_text(
+        &self,
+        font_context: &mut CanvasFontContext,
+        text: String,
+        x: f64,
+        y: f64,
+        max_width: Option<f64>,
+    ) {
+        if is_zero_size_gradient(&self.state.fill_style) {
+            return; // Paint nothing if gradient size is zero.
+        }
+        self.draw_text(font_context, text, x, y, max_width, TextOperation::Fill);
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-context-2d-stroketext
+    pub fn stroke_text(
+        &self,
+        font_context: &mut CanvasFontContext,
+        text: String,
+        x: f64,
+        y: f64,
+        max_width: Option<f64>,
+    ) {
+        if is_zero_size_gradient(&self.state.stroke_style) {
+            return; // Paint nothing if gradient size is zero.
+        }
+        self.draw_text(font_context, text, x, y, max_width, TextOperation::Stroke);
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-context-2d-measuretext
+    pub fn measure_text(
+        &self,
+        font_context: &mut CanvasFontContext,
+        text: String,
+        chan: IpcSender<TextMetrics>,
+    ) {
+        let prepared = self.prepare_text(font_context, &text);
+
+        let baseline_offset = self.baseline_offset(prepared.ascent, prepared.descent) as f64;
+        let ascent = prepared.ascent as f64;
+        let descent = prepared.descent as f64;
+        let anchor_x = prepared.anchor.x as f64;
+
+        // The actual bounding box is the union of the ink bounds of the glyphs, on the
+        // alphabetic baseline.
+        let mut ink_bounds: Option<Rect<f64>> = None;
+        for run in &prepared.runs {
+            let font = run.font.borrow();
+            for &(id, position) in &run.glyphs {
+                let bounds = match font.glyph_bounds(id) {
+                    Some(ref bounds) if bounds.size.width > 0. && bounds.size.height > 0. => {
+                        bounds.translate(&Vector2D::new(position.x as f64, position.y as f64))
+                    },
+                    _ => continue,
+                };
+                ink_bounds = Some(match ink_bounds {
+                    Some(union) => union.union(&bounds),
+                    None => bounds,
+                });
+            }
+        }
+        // Text without ink has an empty bounding box at the anchor point.
+        let ink_bounds = ink_bounds.unwrap_or(Rect::new(
+            Point2D::new(anchor_x, -baseline_offset),
+            Size2D::zero(),
+        ));
+
+        // The em square is split above and below the baseline in the same ratio as the
+        // ascent and descent.
+        let em_size = prepared.em_size as f64;
+        let (em_ascent, em_descent) = if ascent + descent > 0. {
+            (em_size * ascent / (ascent + descent), em_size * descent / (ascent + descent))
+        } else {
+            (em_size, 0.)
+        };
+
+        let metrics = TextMetrics {
+            width: prepared.width as f64,
+            actual_bounding_box_left: anchor_x - ink_bounds.min_x(),
+            actual_bounding_box_right: ink_bounds.max_x() - anchor_x,
+            font_bounding_box_ascent: ascent - baseline_offset,
+            font_bounding_box_descent: descent + baseline_offset,
+            actual_bounding_box_ascent: -ink_bounds.min_y() - baseline_offset,
+            actual_bounding_box_descent: ink_bounds.max_y() + baseline_offset,
+            em_height_ascent: em_ascent - baseline_offset,
+            em_height_descent: em_descent + baseline_offset,
+            // TODO: Read the hanging and ideographic baselines from the font's BASE table.
+            hanging_baseline: ascent * HANGING_BASELINE_RATIO - baseline_offset,
+            alphabetic_baseline: -baseline_offset,
+            ideographic_baseline: -descent - baseline_offset,
+        };
+        chan.send(metrics).unwrap();
+    }
+
+    fn draw_text(
+        &self,
+        font_context: &mut CanvasFontContext,
+        text: String,
+        x: f64,
+        y: f64,
+        max_width: Option<f64>,
+        operation: TextOperation,
+    ) {
+        // Step 1.
+        if let Some(max_width) = max_width {
+            if !(max_width > 0.) {
+                return;
+            }
+        }
+
+        let prepared = self.prepare_text(font_context, &text);
+        if prepared.runs.is_empty() {
+            return;
+        }
+
+        // Step 12: If maxWidth was provided and the hypothetical width of the inline box is
+        // greater than maxWidth, squeeze the text horizontally so that it fits.
+        let scale_x = match max_width {
+            Some(max_width) if prepared.width as f64 > max_width => {
+                (max_width / prepared.width as f64) as f32
+            },
+            _ => 1.,
+        };
+
+        let baseline_offset = self.baseline_offset(prepared.ascent, prepared.descent);
+        let origin = Point2D::new(
+            x as f32 - prepared.anchor.x * scale_x,
+            y as f32 + baseline_offset,
+        );
+        let text_transform = Transform2D::create_scale(scale_x, 1.).post_translate(origin.to_vector());
+
+        let mut writer = |draw_target: &DrawTarget| {
+            let transform = draw_target.get_transform();
+            draw_target.set_transform(&text_transform.post_mul(&transform));
+            for run in &prepared.runs {
+                self.draw_glyph_run(draw_target, font_context, run, operation);
+            }
+            draw_target.set_transform(&transform);
+        };
+
+        if self.need_to_draw_shadow() {
+            let rect = Rect::new(
+                Point2D::new(0., -prepared.ascent),
+                Size2D::new(prepared.width, prepared.ascent + prepared.descent),
+            );
+            let rect = text_transform.transform_rect(&rect);
+            self.draw_with_shadow(&rect, writer);
+        } else {
+            writer(&self.drawtarget);
+        }
+    }
+
+    fn draw_glyph_run(
+        &self,
+        draw_target: &DrawTarget,
+        font_context: &mut CanvasFontContext,
+        run: &GlyphRun,
+        operation: TextOperation,
+    ) {
+        let mut glyphs: Vec<struct__AzGlyph> = run.glyphs.iter().map(|&(id, position)| {
+            struct__AzGlyph {
+                mIndex: id as u32,
+                mPosition: struct__AzPoint {
+                    x: position.x as AzFloat,
+                    y: position.y as AzFloat,
+                },
+            }
+        }).collect();
+        if glyphs.is_empty() {
+            return; // Otherwise the Quartz backend will assert.
+        }
+        let glyph_buffer = struct__AzGlyphBuffer {
+            mGlyphs: glyphs.as_mut_ptr(),
+            mNumGlyphs: glyphs.len() as u32,
+        };
+
+        let scaled_font = font_context.scaled_font(&run.font);
+        match operation {
+            TextOperation::Fill => {
+                draw_target.fill_glyphs(
+                    scaled_font.get_ref(),
+                    glyph_buffer,
+                    self.state.fill_style.to_pattern_ref().as_azure_pattern(),
+                    self.state.draw_options.as_azure_draw_options(),
+                    ptr::null_mut(),
+                );
+            },
+            TextOperation::Stroke => {
+                let path = scaled_font.get_path_for_glyphs(draw_target, &glyph_buffer);
+                draw_target.stroke(
+                    &path,
+                    self.state.stroke_style.to_pattern_ref(),
+                    &self.state.stroke_opts,
+                    &self.state.draw_options,
+                );
+            },
+        }
+    }
+
+    /// The vertical offset from the anchor point to the alphabetic baseline for the
+    /// current `textBaseline`.
+    fn baseline_offset(&self, ascent: f32, descent: f32) -> f32 {
+        match self.state.text_baseline {
+            TextBaseline::Top => ascent,
+            TextBaseline::Hanging => ascent * HANGING_BASELINE_RATIO as f32,
+            TextBaseline::Middle => (ascent - descent) / 2.,
+            TextBaseline::Alphabetic => 0.,
+            TextBaseline::Ideographic | TextBaseline::Bottom => -descent,
+        }
+    }
+
+    fn is_rtl(&self) -> bool {
+        self.state.direction == Direction::Rtl
+    }
+
+    /// Shapes `text` with the current font and works out where its anchor point lies.
+    ///
+    /// <https://html.spec.whatwg.org/multipage/#text-preparation-algorithm>
+    fn prepare_text(&self, font_context: &mut CanvasFontContext, text: &str) -> PreparedText {
+        // Step 2: Replace all ASCII whitespace in text with U+0020 SPACE characters.
+        let text: String = text.chars().map(|c| match c {
+            '\u{9}' | '\u{A}' | '\u{C}' | '\u{D}' => ' ',
+            c => c,
+        }).collect();
+
+        let font_group = font_context.font_context.font_group(self.state.font_style.clone());
+        let mut font_group = font_group.borrow_mut();
+        let (ascent, descent, em_size) = match font_group.first(&mut font_context.font_context) {
+            Some(font) => {
+                let font = font.borrow();
+                let metrics = &font.metrics;
+                (metrics.ascent.to_f32_px(), metrics.descent.to_f32_px(), metrics.em_size.to_f32_px())
+            },
+            None => (0., 0., 0.),
+        };
+
+        // Steps 5-8: Lay out the text as a single line, using the bidi algorithm with the
+        // paragraph direction taken from the `direction` attribute.
+        let paragraph_level = if self.is_rtl() { Level::rtl() } else { Level::ltr() };
+        let bidi_info = BidiInfo::new(&text, Some(paragraph_level));
+        let mut runs = vec![];
+        let mut advance = 0.;
+        for paragraph in &bidi_info.paragraphs {
+            let (levels, visual_runs) = bidi_info.visual_runs(paragraph, paragraph.range.clone());
+            for visual_run in visual_runs {
+                let is_rtl = levels[visual_run.start].is_rtl();
+                let mut font_runs = vec![];
+                for (i, c) in text[visual_run.clone()].char_indices() {
+                    let font = match font_group.find_by_codepoint(&mut font_context.font_context, c) {
+                        Some(font) => font,
+                        None => continue,
+                    };
+                    let start = visual_run.start + i;
+                    let end = start + c.len_utf8();
+                    match font_runs.last_mut() {
+                        Some(&mut (ref run_font, ref mut range)) if Rc::ptr_eq(run_font, &font) => {
+                            *range = (*range).start..end;
+                        },
+                        _ => font_runs.push((font, start..end)),
+                    }
+                }
+                if is_rtl {
+                    font_runs.reverse();
+                }
+
+                for (font, range) in font_runs {
+                    let glyphs = shape_run(&font, &text[range], is_rtl, &mut advance);
+                    runs.push(GlyphRun { font, glyphs });
+                }
+            }
+        }
+
+        // Step 10: Let the anchor point be a point on the inline box, determined by the
+        // `textAlign` and `direction` attributes.
+        let width = advance;
+        let anchor_x = match (self.state.text_align, self.is_rtl()) {
+            (TextAlign::Left, _) | (TextAlign::Start, false) | (TextAlign::End, true) => 0.,
+            (TextAlign::Right, _) | (TextAlign::End, false) | (TextAlign::Start, true) => width,
+            (TextAlign::Center, _) => width / 2.,
+        };
+
+        PreparedText {
+            runs,
+            anchor: Point2D::new(anchor_x, 0.),
+            width,
+            ascent,
+            descent,
+            em_size,
+        }
     }
 
     pub fn fill_rect(&self, rect: &Rect<f32>) {
@@ -384,6 +756,23 @@ impl<'a> CanvasData<'a> {
         self.state.draw_options.set_composition_op(op.to_azure_style());
     }
 
+    pub fn set_font(&mut self, font_style: CanvasFontStyle) {
+        self.state.font_style = font_style.to_font_style_struct();
+    }
+
+    pub fn set_text_align(&mut self, text_align: TextAlign) {
+        self.state.text_align = text_align;
+    }
+
+    pub fn set_text_baseline(&mut self, text_baseline: TextBaseline) {
+        self.state.text_baseline = text_baseline;
+    }
+
+    pub fn set_text_direction(&mut self, direction: Direction) {
+        debug_assert!(direction != Direction::Inherit, "The direction of canvas text wasn't resolved");
+        self.state.direction = direction;
+    }
+
     pub fn create(size: Size2D<i32>) -> DrawTarget {
         DrawTarget::new(BackendType::Skia, size, SurfaceFormat::B8G8R8A8)
     }
@@ -661,6 +1050,10 @@ struct CanvasPaintState<'a> {
     shadow_offset_y: f64,
     shadow_blur: f64,
     shadow_color: Color,
+    font_style: ServoArc<FontStyleStruct>,
+    text_align: TextAlign,
+    text_baseline: TextBaseline,
+    direction: Direction,
 }
 
 impl<'a> CanvasPaintState<'a> {
@@ -675,10 +1068,92 @@ impl<'a> CanvasPaintState<'a> {
             shadow_offset_y: 0.0,
             shadow_blur: 0.0,
             shadow_color: Color::transparent(),
+            font_style: CanvasFontStyle::default().to_font_style_struct(),
+            text_align: TextAlign::default(),
+            text_baseline: TextBaseline::default(),
+            // Script resolves `inherit` before sending the direction.
+            direction: Direction::Ltr,
         }
     }
 }
 
+/// The position of the hanging baseline as a fraction of the ascent, for fonts which
+/// don't provide one.
+const HANGING_BASELINE_RATIO: f64 = 0.8;
+
+/// Shapes `text` with a single font, advancing `advance` by the width of the resulting glyphs.
+fn shape_run(font: &FontRef, text: &str, is_rtl: bool, advance: &mut f32) -> Vec<(GlyphId, Point2D<f32>)> {
+    let script = text.chars()
+        .map(get_script)
+        .find(|script| *script != Script::Common && *script != Script::Inherited)
+        .unwrap_or(Script::Common);
+    let mut flags = ShapingFlags::empty();
+    if is_rtl {
+        flags.insert(ShapingFlags::RTL_FLAG);
+    }
+    let options = ShapingOptions {
+        letter_spacing: None,
+        word_spacing: (Au(0), NotNan::new(0.).unwrap()),
+        script,
+        flags,
+    };
+
+    let glyph_store = font.borrow_mut().shape_text(text, &options);
+    let range = Range::new(ByteIndex(0), glyph_store.len());
+    let mut glyphs = vec![];
+    if range.is_empty() {
+        return glyphs;
+    }
+    for glyph in glyph_store.iter_glyphs_for_byte_range(&range) {
+        let offset = glyph.offset().unwrap_or(Point2D::zero());
+        let position = Point2D::new(*advance + offset.x.to_f32_px(), offset.y.to_f32_px());
+        glyphs.push((glyph.id(), position));
+        *advance += glyph.advance().to_f32_px();
+    }
+    glyphs
+}
+
+fn create_scaled_font(template: &Arc<FontTemplateData>, pt_size: Au) -> ScaledFont {
+    let bytes = template.bytes();
+    ScaledFont::new(BackendType::Skia, FontInfo::FontData(&bytes), pt_size.to_f32_px())
+}
+
+trait ToFontStyleStruct {
+    fn to_font_style_struct(&self) -> ServoArc<FontStyleStruct>;
+}
+
+impl ToFontStyleStruct for CanvasFontStyle {
+    fn to_font_style_struct(&self) -> ServoArc<FontStyleStruct> {
+        let mut font = ComputedValues::initial_values().get_font().clone();
+        let families: Vec<SingleFontFamily> = self.families.iter().map(|family| match *family {
+            CanvasFontFamily::Specific(ref name) => SingleFontFamily::FamilyName(FamilyName {
+                name: Atom::from(&**name),
+                syntax: FamilyNameSyntax::Quoted,
+            }),
+            CanvasFontFamily::Generic(ref name) => SingleFontFamily::Generic(Atom::from(&**name)),
+        }).collect();
+        font.set_font_family(FontFamily(FontFamilyList::new(families.into_boxed_slice())));
+        font.set_font_size(FontSize {
+            size: NonNegativeLength::new(self.size as f32),
+            keyword_info: None,
+        });
+        font.set_font_weight(FontWeight(self.weight));
+        font.set_font_variant_caps(if self.small_caps {
+            FontVariantCaps::SmallCaps
+        } else {
+            FontVariantCaps::Normal
+        });
+        font.set_font_stretch(FontStretch(NonNegative(Percentage(self.stretch / 100.))));
+        font.set_font_style(match self.oblique_angle {
+            _ if self.italic => GenericFontStyle::Italic,
+            None => GenericFontStyle::Normal,
+            Some(angle) => GenericFontStyle::Oblique(FontStyleAngle(Angle::Deg(angle))),
+        });
+        font.compute_font_hash();
+        ServoArc::new(font)
+    }
+}
+
 fn is_zero_size_gradient(pattern: &Pattern) -> bool {
     if let &Pattern::LinearGradient(ref gradient) = pattern {
         if gradient.is_zero_size() {
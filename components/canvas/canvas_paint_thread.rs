@@ -6,6 +6,7 @@ use azure::azure_hl::AntialiasMode;
 use canvas_data::*;
 use canvas_traits::canvas::*;
 use euclid::Size2D;
+use gfx::font_cache_thread::FontCacheThread;
 use ipc_channel::ipc::{self, IpcSender};
 use std::borrow::ToOwned;
 use std::collections::HashMap;
@@ -15,22 +16,24 @@ use webrender_api;
 pub struct CanvasPaintThread <'a> {
     canvases: HashMap<CanvasId, CanvasData<'a>>,
     next_canvas_id: CanvasId,
+    font_context: CanvasFontContext,
 }
 
 impl<'a> CanvasPaintThread <'a> {
-    fn new() -> CanvasPaintThread <'a> {
+    fn new(font_cache_thread: FontCacheThread) -> CanvasPaintThread <'a> {
         CanvasPaintThread {
             canvases: HashMap::new(),
             next_canvas_id: CanvasId(0),
+            font_context: CanvasFontContext::new(font_cache_thread),
         }
     }
 
     /// Creates a new `CanvasPaintThread` and returns an `IpcSender` to
     /// communicate with it.
-    pub fn start() -> IpcSender<CanvasMsg> {
+    pub fn start(font_cache_thread: FontCacheThread) -> IpcSender<CanvasMsg> {
         let (sender, receiver) = ipc::channel::<CanvasMsg>().unwrap();
         thread::Builder::new().name("CanvasThread".to_owned()).spawn(move || {
-            let mut canvas_paint_thread = CanvasPaintThread::new();
+            let mut canvas_paint_thread = CanvasPaintThread::new(font_cache_thread);
             loop {
                 match receiver.recv() {
                     Ok(msg) => {
@@ -102,7 +105,19 @@ impl<'a> CanvasPaintThread <'a> {
     fn process_canvas_2d_message(&mut self, message: Canvas2dMsg, canvas_id: CanvasId) {
         match message {
             Canvas2dMsg::FillText(text, x, y, max_width) => {
-                self.canvas(canvas_id).fill_text(text, x, y, max_width)
+                let font_context = &mut self.font_context;
+                self.canvases.get_mut(&canvas_id).expect("Bogus canvas id")
+                    .fill_text(font_context, text, x, y, max_width)
+            },
+            Canvas2dMsg::StrokeText(text, x, y, max_width) => {
+                let font_context = &mut self.font_context;
+                self.canvases.get_mut(&canvas_id).expect("Bogus canvas id")
+                    .stroke_text(font_context, text, x, y, max_width)
+            },
+            Canvas2dMsg::MeasureText(text, chan) => {
+                let font_context = &mut self.font_context;
+                self.canvases.get_mut(&canvas_id).expect("Bogus canvas id")
+                    .measure_text(font_context, text, chan)
             },
             Canvas2dMsg::FillRect(ref rect) => {
                 self.canvas(canvas_id).fill_rect(rect)
@@ -271,6 +286,18 @@ impl<'a> CanvasPaintThread <'a> {
             Canvas2dMsg::SetShadowColor(ref color) => {
                 self.canvas(canvas_id).set_shadow_color(color.to_azure_style())
             },
+            Canvas2dMsg::SetFont(font_style) => {
+                self.canvas(canvas_id).set_font(font_style)
+            },
+            Canvas2dMsg::SetTextAlign(text_align) => {
+                self.canvas(canvas_id).set_text_align(text_align)
+            },
+            Canvas2dMsg::SetTextBaseline(text_baseline) => {
+                self.canvas(canvas_id).set_text_baseline(text_baseline)
+            },
+            Canvas2dMsg::SetTextDirection(direction) => {
+                self.canvas(canvas_id).set_text_direction(direction)
+            },
         }
     }
 
@@ -54,6 +54,7 @@ pub enum Canvas2dMsg {
     GetImageData(Rect<i32>, Size2D<f64>, IpcBytesSender),
     IsPointInPath(f64, f64, FillRule, IpcSender<bool>),
     LineTo(Point2D<f32>),
+    MeasureText(String, IpcSender<TextMetrics>),
     MoveTo(Point2D<f32>),
     PutImageData(ByteBuf, Vector2D<f64>, Size2D<f64>, Rect<f64>),
     QuadraticCurveTo(Point2D<f32>, Point2D<f32>),
@@ -61,6 +62,7 @@ pub enum Canvas2dMsg {
     RestoreContext,
     SaveContext,
     StrokeRect(Rect<f32>),
+    StrokeText(String, f64, f64, Option<f64>),
     Stroke,
     SetFillStyle(FillOrStrokeStyle),
     SetStrokeStyle(FillOrStrokeStyle),
@@ -75,6 +77,11 @@ pub enum Canvas2dMsg {
     SetShadowOffsetY(f64),
     SetShadowBlur(f64),
     SetShadowColor(RGBA),
+    SetFont(CanvasFontStyle),
+    SetTextAlign(TextAlign),
+    SetTextBaseline(TextBaseline),
+    /// The direction to lay text out in, with `inherit` resolved against the canvas element.
+    SetTextDirection(Direction),
 }
 
 #[derive(Clone, Deserialize, Serialize)]
@@ -384,6 +391,113 @@ impl FromStr for CompositionOrBlending {
     }
 }
 
+/// A font family name, as found in the computed value of the `font` attribute.
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub enum CanvasFontFamily {
+    /// A specific name such as `"Arial"`.
+    Specific(String),
+    /// A generic name such as `sans-serif`.
+    Generic(String),
+}
+
+/// The computed value of the `font` attribute of a 2D context, in a form which can be sent
+/// to the canvas paint thread.
+///
+/// <https://html.spec.whatwg.org/multipage/#dom-context-2d-font>
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub struct CanvasFontStyle {
+    pub families: Vec<CanvasFontFamily>,
+    /// The font size in CSS pixels.
+    pub size: f64,
+    /// The font weight, between 1 and 1000.
+    pub weight: f32,
+    /// The font stretch, as a percentage.
+    pub stretch: f32,
+    /// The oblique angle in degrees, or `None` for a `normal` font style.
+    pub oblique_angle: Option<f32>,
+    /// Whether the style is `italic` rather than `oblique`.
+    pub italic: bool,
+    pub small_caps: bool,
+}
+
+impl Default for CanvasFontStyle {
+    /// The default font, `10px sans-serif`.
+    fn default() -> CanvasFontStyle {
+        CanvasFontStyle {
+            families: vec![CanvasFontFamily::Generic("sans-serif".to_owned())],
+            size: 10.,
+            weight: 400.,
+            stretch: 100.,
+            oblique_angle: None,
+            italic: false,
+            small_caps: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub enum TextAlign {
+    Start,
+    End,
+    Left,
+    Right,
+    Center,
+}
+
+impl Default for TextAlign {
+    fn default() -> TextAlign {
+        TextAlign::Start
+    }
+}
+
+#[derive(Clone, Copy, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub enum TextBaseline {
+    Top,
+    Hanging,
+    Middle,
+    Alphabetic,
+    Ideographic,
+    Bottom,
+}
+
+impl Default for TextBaseline {
+    fn default() -> TextBaseline {
+        TextBaseline::Alphabetic
+    }
+}
+
+#[derive(Clone, Copy, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub enum Direction {
+    Ltr,
+    Rtl,
+    Inherit,
+}
+
+impl Default for Direction {
+    fn default() -> Direction {
+        Direction::Inherit
+    }
+}
+
+/// The result of `measureText`, all values in CSS pixels.
+///
+/// <https://html.spec.whatwg.org/multipage/#textmetrics>
+#[derive(Clone, Debug, Default, Deserialize, MallocSizeOf, Serialize)]
+pub struct TextMetrics {
+    pub width: f64,
+    pub actual_bounding_box_left: f64,
+    pub actual_bounding_box_right: f64,
+    pub font_bounding_box_ascent: f64,
+    pub font_bounding_box_descent: f64,
+    pub actual_bounding_box_ascent: f64,
+    pub actual_bounding_box_descent: f64,
+    pub em_height_ascent: f64,
+    pub em_height_descent: f64,
+    pub hanging_baseline: f64,
+    pub alphabetic_baseline: f64,
+    pub ideographic_baseline: f64,
+}
+
 // TODO(pcwalton): Speed up with SIMD, or better yet, find some way to not do this.
 pub fn byte_swap(data: &mut [u8]) {
     let length = data.len();
@@ -6,31 +6,35 @@ use CompositionPipeline;
 use SendableFrameTree;
 use compositor_thread::{CompositorProxy, CompositorReceiver};
 use compositor_thread::{InitialCompositorState, Msg};
-use euclid::{TypedPoint2D, TypedVector2D, TypedScale};
+use euclid::{TypedPoint2D, TypedSize2D, TypedVector2D, TypedScale};
 use gfx_traits::Epoch;
 #[cfg(feature = "gleam")]
 use gl;
 #[cfg(feature = "gleam")]
-use image::{DynamicImage, ImageFormat};
+use image::{DynamicImage, ImageFormat, Rgb, RgbImage};
 use ipc_channel::ipc;
 use libc::c_void;
 use msg::constellation_msg::{PipelineId, PipelineIndex, PipelineNamespaceId};
 use net_traits::image::base::Image;
 #[cfg(feature = "gleam")]
 use net_traits::image::base::PixelFormat;
+use pdf;
 use profile_traits::time::{self, ProfilerCategory, profile};
 use script_traits::{AnimationState, AnimationTickType, ConstellationMsg, LayoutControlMsg};
-use script_traits::{MouseButton, MouseEventType, ScrollState, TouchEventType, TouchId};
+use script_traits::{MouseButton, MouseEventType, PrintLayout, ScrollState, TouchEventType};
+use script_traits::TouchId;
 use script_traits::{UntrustedNodeAddress, WindowSizeData, WindowSizeType};
 use script_traits::CompositorEvent::{MouseMoveEvent, MouseButtonEvent, TouchEvent};
 use servo_channel::Sender;
 use servo_config::opts;
 use servo_geometry::DeviceIndependentPixel;
+use std::cmp;
 use std::collections::HashMap;
 use std::env;
 use std::fs::{File, create_dir_all};
-use std::io::Write;
+use std::io::{self, BufWriter, Write};
 use std::num::NonZeroU32;
+use std::path::PathBuf;
 use std::rc::Rc;
 use std::time::Instant;
 use style_traits::{CSSPixel, DevicePixel, PinchZoomFactor};
@@ -39,9 +43,12 @@ use style_traits::viewport::ViewportConstraints;
 use time::{now, precise_time_ns, precise_time_s};
 use touch::{TouchHandler, TouchAction};
 use webrender;
-use webrender_api::{self, DeviceIntPoint, DevicePoint, HitTestFlags, HitTestResult};
-use webrender_api::{LayoutVector2D, ScrollLocation};
-use windowing::{self, EmbedderCoordinates, MouseWindowEvent, WebRenderDebugOption, WindowMethods};
+use webrender_api::{self, DeviceIntPoint, DevicePoint, DeviceUintPoint, DeviceUintRect, DeviceUintSize};
+use webrender_api::{HitTestFlags, HitTestResult};
+use webrender_api::{LayoutPoint, LayoutVector2D, ScrollClamping, ScrollLocation};
+use windowing::{self, EmbedderCoordinates, MouseWindowEvent, ScreenshotArea, ScreenshotError};
+use windowing::WebRenderDebugOption;
+use windowing::WindowMethods;
 
 #[derive(Debug, PartialEq)]
 enum UnableToComposite {
@@ -54,8 +61,12 @@ enum NotReadyToPaint {
     AnimationsActive,
     JustNotifiedConstellation,
     WaitingOnConstellation,
+    WaitingOnWebRender,
 }
 
+/// The largest width or height of a full-page screenshot, in device pixels.
+const MAX_SCREENSHOT_SIZE: u32 = 16384;
+
 // Default viewport constraints
 const MAX_ZOOM: f32 = 8.0;
 const MIN_ZOOM: f32 = 0.1;
@@ -189,6 +200,48 @@ pub struct IOCompositor<Window: WindowMethods> {
 
     /// The coordinates of the native window, its view and the screen.
     embedder_coordinates: EmbedderCoordinates,
+
+    /// The next stable image of the page to save, if any.
+    pending_screenshot: Option<PendingScreenshot>,
+
+    /// The document being printed to a PDF file, if any.
+    pending_print: Option<PendingPrint>,
+}
+
+/// A PNG of the page to save once it is stable.
+struct PendingScreenshot {
+    /// Where to write the PNG file.
+    path: PathBuf,
+    /// Which part of the page to save.
+    area: ScreenshotArea,
+    /// The image of the whole page, once a full-page screenshot has started.
+    full_page: Option<FullPageScreenshot>,
+}
+
+/// A full-page screenshot, put together from framebuffer-sized tiles of the page.
+struct FullPageScreenshot {
+    /// The image of the whole page.
+    image: RgbImage,
+    /// The origins of the tiles, in device pixels.
+    tiles: Vec<DeviceUintPoint>,
+    /// The tile the page is scrolled to.
+    current_tile: usize,
+    /// Whether WebRender hasn't drawn the page scrolled to the current tile yet.
+    waiting_for_frame: bool,
+    /// Where the page was scrolled to before the screenshot started.
+    original_scroll_origin: LayoutPoint,
+}
+
+/// A document being printed to a PDF file.
+struct PendingPrint {
+    /// Where to write the PDF file.
+    path: PathBuf,
+    /// How the document is split into pages, once it has been laid out for printing.
+    layout: Option<PrintLayout>,
+    /// The pages printed so far.
+    pages: Vec<pdf::Page>,
+    /// Whether WebRender hasn't drawn the document scrolled to the next page yet.
+    waiting_for_frame: bool,
 }
 
 #[derive(Clone, Copy)]
@@ -228,6 +281,9 @@ struct PipelineDetails {
 
     /// Whether this pipeline is visible
     visible: bool,
+
+    /// The size of the contents of this pipeline, if it is a top-level one.
+    content_size: TypedSize2D<f32, CSSPixel>,
 }
 
 impl PipelineDetails {
@@ -237,6 +293,7 @@ impl PipelineDetails {
             animations_running: false,
             animation_callbacks_running: false,
             visible: true,
+            content_size: TypedSize2D::zero(),
         }
     }
 }
@@ -249,8 +306,13 @@ enum CompositeTarget {
     /// Compose as normal, but also return a PNG of the composed output
     WindowAndPng,
 
-    /// Compose to a PNG, write it to disk, and then exit the browser (used for reftests)
+    /// Compose to a PNG and write it to disk. The browser then exits if the file was
+    /// requested on the command line (used for reftests)
     PngFile,
+
+    /// Compose each page of a printed document and add it to a PDF file. The browser then
+    /// exits if the file was requested on the command line.
+    PdfFile,
 }
 
 #[derive(Clone)]
@@ -294,7 +356,19 @@ impl webrender_api::RenderNotifier for RenderNotifier {
 
 impl<Window: WindowMethods> IOCompositor<Window> {
     fn new(window: Rc<Window>, state: InitialCompositorState) -> Self {
-        let composite_target = match opts::get().output_file {
+        let pending_screenshot = opts::get().output_file.as_ref().map(|path| {
+            let area = if opts::get().output_full_page {
+                ScreenshotArea::FullPage
+            } else {
+                ScreenshotArea::Viewport
+            };
+            PendingScreenshot {
+                path: PathBuf::from(path),
+                area: area,
+                full_page: None,
+            }
+        });
+        let composite_target = match pending_screenshot {
             Some(_) => CompositeTarget::PngFile,
             None => CompositeTarget::Window,
         };
@@ -329,6 +403,8 @@ impl<Window: WindowMethods> IOCompositor<Window> {
             webrender_document: state.webrender_document,
             webrender_api: state.webrender_api,
             pending_paint_metrics: HashMap::new(),
+            pending_screenshot,
+            pending_print: None,
         }
     }
 
@@ -406,6 +482,9 @@ impl<Window: WindowMethods> IOCompositor<Window> {
             },
 
             (Msg::Recomposite(reason), ShutdownState::NotShuttingDown) => {
+                if reason == CompositingReason::NewWebRenderFrame {
+                    self.capture_frame_ready();
+                }
                 self.composition_request = CompositionRequest::CompositeNow(reason)
             },
 
@@ -424,6 +503,23 @@ impl<Window: WindowMethods> IOCompositor<Window> {
                 }
             },
 
+            (
+                Msg::WebDriverMouseButtonEvent(mouse_event_type, mouse_button, x, y),
+                ShutdownState::NotShuttingDown,
+            ) => {
+                let point = TypedPoint2D::new(x, y) * self.device_pixels_per_page_px();
+                self.on_mouse_window_event_class(match mouse_event_type {
+                    MouseEventType::Click => MouseWindowEvent::Click(mouse_button, point),
+                    MouseEventType::MouseDown => MouseWindowEvent::MouseDown(mouse_button, point),
+                    MouseEventType::MouseUp => MouseWindowEvent::MouseUp(mouse_button, point),
+                });
+            },
+
+            (Msg::WebDriverMouseMoveEvent(x, y), ShutdownState::NotShuttingDown) => {
+                let point = TypedPoint2D::new(x, y) * self.device_pixels_per_page_px();
+                self.on_mouse_window_move_event_class(point);
+            },
+
             (
                 Msg::ViewportConstrained(pipeline_id, constraints),
                 ShutdownState::NotShuttingDown,
@@ -431,6 +527,14 @@ impl<Window: WindowMethods> IOCompositor<Window> {
                 self.constrain_viewport(pipeline_id, constraints);
             },
 
+            (Msg::ContentSizeChanged(pipeline_id, size), ShutdownState::NotShuttingDown) => {
+                self.pipeline_details(pipeline_id).content_size = size;
+            },
+
+            (Msg::PrintLayout(pipeline_id, print_layout), ShutdownState::NotShuttingDown) => {
+                self.start_printing_pages(pipeline_id, print_layout);
+            },
+
             (Msg::IsReadyToSaveImageReply(is_ready), ShutdownState::NotShuttingDown) => {
                 assert_eq!(
                     self.ready_to_save_state,
@@ -468,6 +572,7 @@ impl<Window: WindowMethods> IOCompositor<Window> {
 
             (Msg::NewScrollFrameReady(recomposite_needed), ShutdownState::NotShuttingDown) => {
                 self.waiting_for_results_of_scroll = false;
+                self.capture_frame_ready();
                 if recomposite_needed {
                     self.composition_request = CompositionRequest::CompositeNow(
                         CompositingReason::NewWebRenderScrollFrame,
@@ -483,9 +588,16 @@ impl<Window: WindowMethods> IOCompositor<Window> {
 
             (Msg::LoadComplete(_), ShutdownState::NotShuttingDown) => {
                 // If we're painting in headless mode, schedule a recomposite.
-                if opts::get().output_file.is_some() || opts::get().exit_after_load {
+                if self.composite_target == CompositeTarget::PngFile ||
+                    opts::get().exit_after_load
+                {
                     self.composite_if_necessary(CompositingReason::Headless);
                 }
+                if let Some(ref path) = opts::get().output_pdf {
+                    if self.pending_print.is_none() {
+                        self.print_to_pdf(PathBuf::from(path));
+                    }
+                }
             },
 
             (Msg::PendingPaintMetric(pipeline_id, epoch), _) => {
@@ -1185,12 +1297,372 @@ impl<Window: WindowMethods> IOCompositor<Window> {
         }
     }
 
+    /// Saves a PNG of the page to the given path, once the page is stable.
+    pub fn save_screenshot(&mut self, path: PathBuf, area: ScreenshotArea) {
+        if self.pending_screenshot.is_some() {
+            return warn!("Already saving a screenshot, not saving {}.", path.display());
+        }
+        self.pending_screenshot = Some(PendingScreenshot {
+            path: path,
+            area: area,
+            full_page: None,
+        });
+        self.composite_target = CompositeTarget::PngFile;
+        self.composite_if_necessary(CompositingReason::Screenshot);
+    }
+
+    /// Checks whether the current framebuffer can be added to the pending screenshot. For a
+    /// full-page one, that is once the page has been scrolled to the tile being captured.
+    fn is_ready_to_capture_screenshot(&mut self) -> Result<(), NotReadyToPaint> {
+        let start_full_page = match self.pending_screenshot {
+            Some(PendingScreenshot {
+                area: ScreenshotArea::FullPage,
+                full_page: Some(ref full_page),
+                ..
+            }) => {
+                if full_page.waiting_for_frame {
+                    return Err(NotReadyToPaint::WaitingOnWebRender);
+                }
+                return Ok(());
+            },
+            Some(PendingScreenshot {
+                area: ScreenshotArea::FullPage,
+                full_page: None,
+                ..
+            }) => true,
+            _ => false,
+        };
+        if !start_full_page {
+            return Ok(());
+        }
+        match self.start_full_page_screenshot() {
+            Ok(true) => Err(NotReadyToPaint::WaitingOnWebRender),
+            Ok(false) => Ok(()),
+            Err(e) => {
+                if let Some(screenshot) = self.pending_screenshot.take() {
+                    warn!("Not saving {}: {:?}.", screenshot.path.display(), e);
+                }
+                Ok(())
+            },
+        }
+    }
+
+    /// Starts putting a full-page screenshot together, by scrolling to its first tile.
+    /// Returns false if the page has no contents beyond the viewport, in which case the
+    /// screenshot is that of the viewport.
+    ///
+    /// Since the page is scrolled rather than laid out again, fixed position elements are
+    /// drawn in every tile, as they would be seen while scrolling through the page.
+    fn start_full_page_screenshot(&mut self) -> Result<bool, ScreenshotError> {
+        let root_pipeline_id = match self.get_root_pipeline_id() {
+            Some(root_pipeline_id) => root_pipeline_id,
+            None => return Ok(false),
+        };
+        let content_size = match self.pipeline_details.get(&root_pipeline_id) {
+            Some(details) => details.content_size,
+            None => return Ok(false),
+        };
+        let view_size = self.embedder_coordinates.framebuffer;
+        let content_size = (content_size * self.device_pixels_per_page_px()).ceil().to_u32();
+        let page_size = DeviceUintSize::new(
+            cmp::min(cmp::max(view_size.width, content_size.width), MAX_SCREENSHOT_SIZE),
+            cmp::min(cmp::max(view_size.height, content_size.height), MAX_SCREENSHOT_SIZE),
+        );
+        if page_size == view_size {
+            return Ok(false);
+        }
+        let tiles = windowing::full_page_screenshot_tiles(page_size, view_size)?;
+
+        let root_scroll_id = root_pipeline_id.root_scroll_id();
+        let original_scroll_origin = self
+            .webrender_api
+            .get_scroll_node_state(self.webrender_document)
+            .into_iter()
+            .find(|state| state.id == root_scroll_id)
+            .map_or(LayoutPoint::zero(), |state| LayoutPoint::zero() - state.scroll_offset);
+        if let Some(ref mut screenshot) = self.pending_screenshot {
+            screenshot.full_page = Some(FullPageScreenshot {
+                image: RgbImage::new(page_size.width, page_size.height),
+                tiles: tiles,
+                current_tile: 0,
+                waiting_for_frame: false,
+                original_scroll_origin: original_scroll_origin,
+            });
+        }
+        self.scroll_to_screenshot_tile();
+        Ok(true)
+    }
+
+    /// Scrolls the page to the tile of the full-page screenshot that is captured next.
+    fn scroll_to_screenshot_tile(&mut self) {
+        let scale = self.device_pixels_per_page_px().get();
+        let origin = match self.pending_screenshot {
+            Some(PendingScreenshot {
+                full_page: Some(ref mut full_page),
+                ..
+            }) => {
+                full_page.waiting_for_frame = true;
+                let tile = full_page.tiles[full_page.current_tile];
+                LayoutPoint::new(tile.x as f32 / scale, tile.y as f32 / scale)
+            },
+            _ => return,
+        };
+        self.scroll_root_pipeline_to(origin);
+    }
+
+    /// Scrolls the root pipeline to the given origin without telling its document about it.
+    fn scroll_root_pipeline_to(&mut self, origin: LayoutPoint) {
+        let root_pipeline_id = match self.get_root_pipeline_id() {
+            Some(root_pipeline_id) => root_pipeline_id,
+            None => return,
+        };
+        let mut txn = webrender_api::Transaction::new();
+        txn.scroll_node_with_id(
+            origin,
+            root_pipeline_id.root_scroll_id(),
+            ScrollClamping::NoClamping,
+        );
+        txn.generate_frame();
+        self.webrender_api
+            .send_transaction(self.webrender_document, txn);
+    }
+
+    /// Notes that WebRender drew a new frame, which shows the tile of the pending full-page
+    /// screenshot or the page of the printed document the page was scrolled to, if any.
+    fn capture_frame_ready(&mut self) {
+        if let Some(PendingScreenshot {
+            full_page: Some(ref mut full_page),
+            ..
+        }) = self.pending_screenshot
+        {
+            full_page.waiting_for_frame = false;
+        }
+        if let Some(ref mut print) = self.pending_print {
+            print.waiting_for_frame = false;
+        }
+    }
+
+    /// Adds the image of the framebuffer to the pending screenshot, and writes the PNG file
+    /// once the screenshot is complete.
+    #[cfg(feature = "gleam")]
+    fn add_screenshot_image(&mut self, framebuffer: RgbImage) {
+        let complete = match self.pending_screenshot {
+            Some(PendingScreenshot {
+                full_page: Some(ref mut full_page),
+                ..
+            }) => {
+                let tile = full_page.tiles[full_page.current_tile];
+                for (x, y, pixel) in framebuffer.enumerate_pixels() {
+                    let (x, y) = (tile.x + x, tile.y + y);
+                    if x < full_page.image.width() && y < full_page.image.height() {
+                        full_page.image.put_pixel(x, y, *pixel);
+                    }
+                }
+                full_page.current_tile += 1;
+                full_page.current_tile == full_page.tiles.len()
+            },
+            Some(_) => true,
+            None => return,
+        };
+        if !complete {
+            return self.scroll_to_screenshot_tile();
+        }
+
+        let screenshot = match self.pending_screenshot.take() {
+            Some(screenshot) => screenshot,
+            None => return,
+        };
+        let image = match screenshot.full_page {
+            Some(full_page) => {
+                self.scroll_root_pipeline_to(full_page.original_scroll_origin);
+                full_page.image
+            },
+            None => framebuffer,
+        };
+        let path = screenshot.path;
+        profile(
+            ProfilerCategory::ImageSaving,
+            None,
+            self.time_profiler_chan.clone(),
+            || match File::create(&path) {
+                Ok(mut file) => {
+                    let dynamic_image = DynamicImage::ImageRgb8(image);
+                    if let Err(e) = dynamic_image.write_to(&mut file, ImageFormat::PNG) {
+                        error!("Failed to save {} ({}).", path.display(), e);
+                    }
+                },
+                Err(e) => error!("Failed to create {} ({}).", path.display(), e),
+            },
+        );
+    }
+
+    /// Prints the page to a PDF file at the given path: lays it out for printing, saves each
+    /// of its pages, then lays it out for the window again.
+    #[cfg(feature = "gleam")]
+    pub fn print_to_pdf(&mut self, path: PathBuf) {
+        let top_level_browsing_context_id = match self.root_pipeline {
+            Some(ref pipeline) => pipeline.top_level_browsing_context_id,
+            None => return warn!("No page to print to {}.", path.display()),
+        };
+        if self.pending_print.is_some() {
+            return warn!("Already printing, not printing to {}.", path.display());
+        }
+        self.pending_print = Some(PendingPrint {
+            path,
+            layout: None,
+            pages: vec![],
+            waiting_for_frame: false,
+        });
+        self.composite_target = CompositeTarget::PdfFile;
+        let msg = ConstellationMsg::SetPrinting(top_level_browsing_context_id, true);
+        if let Err(e) = self.constellation_chan.send(msg) {
+            warn!("Sending print request to constellation failed ({:?}).", e);
+        }
+    }
+
+    #[cfg(not(feature = "gleam"))]
+    pub fn print_to_pdf(&mut self, path: PathBuf) {
+        warn!("Printing to {} is not supported without OpenGL.", path.display());
+    }
+
+    /// Starts saving the pages of a document that has been laid out for printing, by making
+    /// the framebuffer the size of the area of a page the document is drawn in.
+    fn start_printing_pages(&mut self, pipeline_id: PipelineId, print_layout: PrintLayout) {
+        if self.get_root_pipeline_id() != Some(pipeline_id) {
+            return;
+        }
+        let size = (print_layout.content_rect.size * self.device_pixels_per_page_px())
+            .ceil()
+            .to_u32();
+        match self.pending_print {
+            Some(ref mut print) => {
+                // The document was laid out again, so pages printed so far may be outdated.
+                print.layout = Some(print_layout);
+                print.pages.clear();
+            },
+            None => return,
+        }
+        self.embedder_coordinates.framebuffer = size;
+        self.embedder_coordinates.viewport = DeviceUintRect::new(TypedPoint2D::zero(), size);
+        self.webrender_api.set_window_parameters(
+            self.webrender_document,
+            self.embedder_coordinates.framebuffer,
+            self.embedder_coordinates.viewport,
+            self.embedder_coordinates.hidpi_factor.get(),
+        );
+        self.print_next_page();
+    }
+
+    /// Scrolls to the next page of the document being printed, or writes the PDF file once
+    /// all of them have been saved. The page is captured once WebRender has drawn it.
+    fn print_next_page(&mut self) {
+        let page_start = match self.pending_print {
+            Some(PendingPrint {
+                layout: Some(ref layout),
+                ref pages,
+                ref mut waiting_for_frame,
+                ..
+            }) => {
+                let page_start = layout.page_starts.get(pages.len()).cloned();
+                *waiting_for_frame = page_start.is_some();
+                page_start
+            },
+            _ => return,
+        };
+        let page_start = match page_start {
+            Some(page_start) => page_start,
+            None => return self.finish_print(),
+        };
+        let root_pipeline_id = match self.get_root_pipeline_id() {
+            Some(root_pipeline_id) => root_pipeline_id,
+            None => return,
+        };
+        let mut txn = webrender_api::Transaction::new();
+        txn.scroll_node_with_id(
+            LayoutPoint::new(0., page_start),
+            root_pipeline_id.root_scroll_id(),
+            ScrollClamping::NoClamping,
+        );
+        txn.generate_frame();
+        self.webrender_api
+            .send_transaction(self.webrender_document, txn);
+        self.composite_if_necessary(CompositingReason::Printing);
+    }
+
+    /// Adds the image of the next page of the document being printed. Whatever is shown
+    /// below the point where the following page starts is left out.
+    #[cfg(feature = "gleam")]
+    fn add_print_page(&mut self, mut image: RgbImage) {
+        let scale = self.device_pixels_per_page_px().get();
+        let print = match self.pending_print {
+            Some(ref mut print) => print,
+            None => return,
+        };
+        let layout = match print.layout {
+            Some(ref layout) => layout,
+            None => return,
+        };
+        let index = print.pages.len();
+        if let Some(next_page_start) = layout.page_starts.get(index + 1) {
+            let height = ((next_page_start - layout.page_starts[index]) * scale).round() as u32;
+            for (_, y, pixel) in image.enumerate_pixels_mut() {
+                if y >= height {
+                    *pixel = Rgb([255, 255, 255]);
+                }
+            }
+        }
+        print.pages.push(pdf::Page {
+            size: layout.page_size,
+            content_rect: layout.content_rect,
+            image,
+        });
+    }
+
+    /// Writes the PDF (or PNG) file once all the pages of the printed document have been saved, then
+    /// goes back to showing the document in the window.
+    fn finish_print(&mut self) {
+        let print = match self.pending_print.take() {
+            Some(print) => print,
+            None => return,
+        };
+        let result = File::create(&print.path).and_then(|file| {
+            if print.path.extension().map_or(false, |extension| extension == "png") {
+                write_pages_png(file, &print.pages)
+            } else {
+                pdf::write_pdf(BufWriter::new(file), &print.pages)
+            }
+        });
+        if let Err(e) = result {
+            error!("Failed to save {} ({}).", print.path.display(), e);
+        }
+
+        if opts::get().output_pdf.is_some() {
+            println!("Shutting down the Constellation after printing to a PDF file");
+            return self.start_shutting_down();
+        }
+
+        self.composite_target = CompositeTarget::Window;
+        if let Some(ref pipeline) = self.root_pipeline {
+            let msg = ConstellationMsg::SetPrinting(pipeline.top_level_browsing_context_id, false);
+            if let Err(e) = self.constellation_chan.send(msg) {
+                warn!("Sending print end to constellation failed ({:?}).", e);
+            }
+        }
+        self.on_resize_window_event();
+    }
+
     pub fn composite(&mut self) {
         let target = self.composite_target;
         match self.composite_specific_target(target) {
-            Ok(_) => if opts::get().output_file.is_some() || opts::get().exit_after_load {
+            Ok(_) => if target == CompositeTarget::PdfFile {
+                self.print_next_page();
+            } else if target == CompositeTarget::PngFile && self.pending_screenshot.is_some() {
+                // The rest of a full-page screenshot is captured in later composites.
+            } else if opts::get().output_file.is_some() || opts::get().exit_after_load {
                 println!("Shutting down the Constellation after generating an output file or exit flag specified");
                 self.start_shutting_down();
+            } else if target == CompositeTarget::PngFile {
+                self.composite_target = CompositeTarget::Window;
             },
             Err(e) => if opts::get().is_running_problem_test {
                 if e != UnableToComposite::NotReadyToPaintImage(
@@ -1220,7 +1692,9 @@ impl<Window: WindowMethods> IOCompositor<Window> {
         self.webrender.update();
 
         let wait_for_stable_image = match target {
-            CompositeTarget::WindowAndPng | CompositeTarget::PngFile => true,
+            CompositeTarget::WindowAndPng |
+            CompositeTarget::PngFile |
+            CompositeTarget::PdfFile => true,
             CompositeTarget::Window => opts::get().exit_after_load,
         };
 
@@ -1239,13 +1713,44 @@ impl<Window: WindowMethods> IOCompositor<Window> {
             }
         }
 
+        if target == CompositeTarget::PngFile {
+            if let Err(result) = self.is_ready_to_capture_screenshot() {
+                return Err(UnableToComposite::NotReadyToPaintImage(result));
+            }
+        }
+
+        // Pages can only be printed once the document has been laid out for printing, and
+        // WebRender has drawn it scrolled to the page.
+        if target == CompositeTarget::PdfFile {
+            match self.pending_print {
+                Some(PendingPrint {
+                    layout: Some(_),
+                    waiting_for_frame: true,
+                    ..
+                }) => {
+                    return Err(UnableToComposite::NotReadyToPaintImage(
+                        NotReadyToPaint::WaitingOnWebRender,
+                    ));
+                },
+                Some(PendingPrint {
+                    layout: Some(_),
+                    ..
+                }) => {},
+                _ => {
+                    return Err(UnableToComposite::NotReadyToPaintImage(
+                        NotReadyToPaint::WaitingOnConstellation,
+                    ));
+                },
+            }
+        }
+
         let rt_info = match target {
             #[cfg(feature = "gleam")]
             CompositeTarget::Window => gl::RenderTargetInfo::default(),
             #[cfg(feature = "gleam")]
-            CompositeTarget::WindowAndPng | CompositeTarget::PngFile => {
-                gl::initialize_png(&*self.window.gl(), width, height)
-            },
+            CompositeTarget::WindowAndPng |
+            CompositeTarget::PngFile |
+            CompositeTarget::PdfFile => gl::initialize_png(&*self.window.gl(), width, height),
             #[cfg(not(feature = "gleam"))]
             _ => (),
         };
@@ -1310,30 +1815,19 @@ impl<Window: WindowMethods> IOCompositor<Window> {
                     format: PixelFormat::RGB8,
                     bytes: ipc::IpcSharedMemory::from_bytes(&*img),
                     id: None,
+                    frames: vec![],
                 })
             },
             #[cfg(feature = "gleam")]
             CompositeTarget::PngFile => {
-                let gl = &*self.window.gl();
-                profile(
-                    ProfilerCategory::ImageSaving,
-                    None,
-                    self.time_profiler_chan.clone(),
-                    || match opts::get().output_file.as_ref() {
-                        Some(path) => match File::create(path) {
-                            Ok(mut file) => {
-                                let img = gl::draw_img(gl, rt_info, width, height);
-                                let dynamic_image = DynamicImage::ImageRgb8(img);
-                                if let Err(e) = dynamic_image.write_to(&mut file, ImageFormat::PNG)
-                                {
-                                    error!("Failed to save {} ({}).", path, e);
-                                }
-                            },
-                            Err(e) => error!("Failed to create {} ({}).", path, e),
-                        },
-                        None => error!("No file specified."),
-                    },
-                );
+                let img = gl::draw_img(&*self.window.gl(), rt_info, width, height);
+                self.add_screenshot_image(img);
+                None
+            },
+            #[cfg(feature = "gleam")]
+            CompositeTarget::PdfFile => {
+                let img = gl::draw_img(&*self.window.gl(), rt_info, width, height);
+                self.add_print_page(img);
                 None
             },
             #[cfg(not(feature = "gleam"))]
@@ -1515,6 +2009,24 @@ impl<Window: WindowMethods> IOCompositor<Window> {
     }
 }
 
+/// Writes the images of printed pages one below the other to a PNG file, which print reftests compare
+/// instead of a PDF file.
+fn write_pages_png(mut file: File, pages: &[pdf::Page]) -> io::Result<()> {
+    let width = pages.iter().map(|page| page.image.width()).max().unwrap_or(0);
+    let height = pages.iter().map(|page| page.image.height()).sum();
+    let mut image = RgbImage::from_pixel(width, height, Rgb([255, 255, 255]));
+    let mut top = 0;
+    for page in pages {
+        for (x, y, pixel) in page.image.enumerate_pixels() {
+            image.put_pixel(x, top + y, *pixel);
+        }
+        top += page.image.height();
+    }
+    DynamicImage::ImageRgb8(image)
+        .write_to(&mut file, ImageFormat::PNG)
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+}
+
 /// Why we performed a composite. This is used for debugging.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum CompositingReason {
@@ -1526,6 +2038,10 @@ pub enum CompositingReason {
     ContinueScroll,
     /// We're performing the single composite in headless mode.
     Headless,
+    /// A screenshot of the page was requested.
+    Screenshot,
+    /// The next page of a printed document has to be saved.
+    Printing,
     /// We're performing a composite to run an animation.
     Animation,
     /// A new frame tree has been loaded.
@@ -7,15 +7,18 @@
 use SendableFrameTree;
 use compositor::CompositingReason;
 use embedder_traits::EventLoopWaker;
+use euclid::TypedSize2D;
 use gfx_traits::Epoch;
 use ipc_channel::ipc::IpcSender;
 use msg::constellation_msg::{PipelineId, TopLevelBrowsingContextId};
 use net_traits::image::base::Image;
 use profile_traits::mem;
 use profile_traits::time;
-use script_traits::{AnimationState, ConstellationMsg, EventResult};
+use script_traits::{AnimationState, ConstellationMsg, EventResult, MouseButton, MouseEventType};
+use script_traits::PrintLayout;
 use servo_channel::{Receiver, Sender};
 use std::fmt::{Debug, Error, Formatter};
+use style_traits::CSSPixel;
 use style_traits::viewport::ViewportConstraints;
 use webrender;
 use webrender_api::{self, DeviceIntPoint, DeviceUintSize};
@@ -82,6 +85,10 @@ pub enum Msg {
     CreatePng(IpcSender<Option<Image>>),
     /// Alerts the compositor that the viewport has been constrained in some manner
     ViewportConstrained(PipelineId, ViewportConstraints),
+    /// The size of the contents of a top-level pipeline has changed.
+    ContentSizeChanged(PipelineId, TypedSize2D<f32, CSSPixel>),
+    /// A top-level pipeline has been laid out to be printed.
+    PrintLayout(PipelineId, PrintLayout),
     /// A reply to the compositor asking if the output image is stable.
     IsReadyToSaveImageReply(bool),
     /// Pipeline visibility changed
@@ -112,6 +119,13 @@ pub enum Msg {
     GetScreenSize(IpcSender<DeviceUintSize>),
     /// Get screen available size.
     GetScreenAvailSize(IpcSender<DeviceUintSize>),
+
+    /// Dispatch a mouse button event on behalf of WebDriver, at the given point of the
+    /// viewport in CSS pixels.
+    WebDriverMouseButtonEvent(MouseEventType, MouseButton, f32, f32),
+    /// Dispatch a mouse move event on behalf of WebDriver, to the given point of the
+    /// viewport in CSS pixels.
+    WebDriverMouseMoveEvent(f32, f32),
 }
 
 impl Debug for Msg {
@@ -124,6 +138,8 @@ impl Debug for Msg {
             Msg::TouchEventProcessed(..) => write!(f, "TouchEventProcessed"),
             Msg::CreatePng(..) => write!(f, "CreatePng"),
             Msg::ViewportConstrained(..) => write!(f, "ViewportConstrained"),
+            Msg::ContentSizeChanged(..) => write!(f, "ContentSizeChanged"),
+            Msg::PrintLayout(..) => write!(f, "PrintLayout"),
             Msg::IsReadyToSaveImageReply(..) => write!(f, "IsReadyToSaveImageReply"),
             Msg::PipelineVisibilityChanged(..) => write!(f, "PipelineVisibilityChanged"),
             Msg::PipelineExited(..) => write!(f, "PipelineExited"),
@@ -134,6 +150,8 @@ impl Debug for Msg {
             Msg::GetClientWindow(..) => write!(f, "GetClientWindow"),
             Msg::GetScreenSize(..) => write!(f, "GetScreenSize"),
             Msg::GetScreenAvailSize(..) => write!(f, "GetScreenAvailSize"),
+            Msg::WebDriverMouseButtonEvent(..) => write!(f, "WebDriverMouseButtonEvent"),
+            Msg::WebDriverMouseMoveEvent(..) => write!(f, "WebDriverMouseMoveEvent"),
         }
     }
 }
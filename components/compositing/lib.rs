@@ -6,10 +6,10 @@
 
 extern crate embedder_traits;
 extern crate euclid;
+extern crate flate2;
 extern crate gfx_traits;
 #[cfg(feature = "gleam")]
 extern crate gleam;
-#[cfg(feature = "gleam")]
 extern crate image;
 extern crate ipc_channel;
 extern crate libc;
@@ -43,6 +43,7 @@ mod compositor;
 pub mod compositor_thread;
 #[cfg(feature = "gleam")]
 mod gl;
+pub mod pdf;
 mod touch;
 pub mod windowing;
 
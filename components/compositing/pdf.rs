@@ -0,0 +1,150 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! A minimal PDF writer for printed pages, each of which is a single image.
+//!
+//! Pages are only rasterized: the text in them can't be selected, searched or copied, links
+//! don't work, and zooming in on them shows the pixels of the rendering.
+
+use euclid::{TypedRect, TypedSize2D};
+use flate2::Compression;
+use flate2::write::ZlibEncoder;
+use image::RgbImage;
+use std::io::{self, Write};
+use style_traits::CSSPixel;
+
+/// The number of PDF points in a CSS pixel.
+const POINTS_PER_PX: f32 = 0.75;
+
+/// A printed page.
+pub struct Page {
+    /// The size of the page.
+    pub size: TypedSize2D<f32, CSSPixel>,
+    /// Where the image is drawn on the page.
+    pub content_rect: TypedRect<f32, CSSPixel>,
+    /// The rendering of the contents of the page.
+    pub image: RgbImage,
+}
+
+/// Writes a PDF body object by object, keeping track of their offsets for the
+/// cross-reference table.
+struct PdfWriter<W: Write> {
+    out: W,
+    position: usize,
+    offsets: Vec<usize>,
+}
+
+impl<W: Write> PdfWriter<W> {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.out.write_all(bytes)?;
+        self.position += bytes.len();
+        Ok(())
+    }
+
+    /// Writes the object with the given number, which has to be the next one.
+    fn object(
+        &mut self,
+        number: usize,
+        dictionary: &str,
+        stream: Option<&[u8]>,
+    ) -> io::Result<()> {
+        debug_assert_eq!(number, self.offsets.len() + 1);
+        self.offsets.push(self.position);
+        self.write(format!("{} 0 obj\n{}\n", number, dictionary).as_bytes())?;
+        if let Some(stream) = stream {
+            self.write(b"stream\n")?;
+            self.write(stream)?;
+            self.write(b"\nendstream\n")?;
+        }
+        self.write(b"endobj\n")
+    }
+}
+
+fn compress(bytes: &[u8]) -> io::Result<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+    encoder.write_all(bytes)?;
+    encoder.finish()
+}
+
+/// Writes a PDF document with the given pages to `out`.
+pub fn write_pdf<W: Write>(out: W, pages: &[Page]) -> io::Result<()> {
+    let mut writer = PdfWriter {
+        out,
+        position: 0,
+        offsets: vec![],
+    };
+    writer.write(b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n")?;
+
+    // The catalog and the page tree come first, then three objects per page: the page, its
+    // contents and its image.
+    let page_object = |index: usize| 3 + index * 3;
+    let kids = (0..pages.len())
+        .map(|index| format!("{} 0 R", page_object(index)))
+        .collect::<Vec<_>>()
+        .join(" ");
+    writer.object(1, "<< /Type /Catalog /Pages 2 0 R >>", None)?;
+    writer.object(
+        2,
+        &format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids, pages.len()),
+        None,
+    )?;
+
+    for (index, page) in pages.iter().enumerate() {
+        let number = page_object(index);
+        let size = page.size * POINTS_PER_PX;
+        let rect = page.content_rect * POINTS_PER_PX;
+        writer.object(
+            number,
+            &format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+                 /Resources << /XObject << /Im0 {} 0 R >> >> /Contents {} 0 R >>",
+                size.width,
+                size.height,
+                number + 2,
+                number + 1,
+            ),
+            None,
+        )?;
+
+        // PDF coordinates go up from the bottom left corner of the page.
+        let contents = format!(
+            "q {} 0 0 {} {} {} cm /Im0 Do Q",
+            rect.size.width,
+            rect.size.height,
+            rect.origin.x,
+            size.height - rect.max_y(),
+        );
+        let contents = compress(contents.as_bytes())?;
+        writer.object(
+            number + 1,
+            &format!("<< /Length {} /Filter /FlateDecode >>", contents.len()),
+            Some(&contents),
+        )?;
+
+        let image = compress(&page.image)?;
+        writer.object(
+            number + 2,
+            &format!(
+                "<< /Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceRGB \
+                 /BitsPerComponent 8 /Length {} /Filter /FlateDecode >>",
+                page.image.width(),
+                page.image.height(),
+                image.len(),
+            ),
+            Some(&image),
+        )?;
+    }
+
+    let xref_position = writer.position;
+    let mut xref = format!("xref\n0 {}\n0000000000 65535 f \n", writer.offsets.len() + 1);
+    for offset in &writer.offsets {
+        xref.push_str(&format!("{:010} 00000 n \n", offset));
+    }
+    xref.push_str(&format!(
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        writer.offsets.len() + 1,
+        xref_position,
+    ));
+    writer.write(xref.as_bytes())
+}
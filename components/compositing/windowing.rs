@@ -9,14 +9,17 @@ use euclid::TypedScale;
 #[cfg(feature = "gleam")]
 use gleam::gl;
 use msg::constellation_msg::{Key, KeyModifiers, KeyState, TopLevelBrowsingContextId, TraversalDirection};
-use script_traits::{MouseButton, TouchEventType, TouchId};
+use script_traits::{MouseButton, SessionHistorySnapshot, TouchEventType, TouchId};
 use servo_geometry::{DeviceIndependentPixel, DeviceUintLength};
 use servo_url::ServoUrl;
+use std::cmp;
 use std::fmt::{Debug, Error, Formatter};
+use std::path::PathBuf;
 #[cfg(feature = "gleam")]
 use std::rc::Rc;
 use style_traits::DevicePixel;
-use webrender_api::{DeviceIntPoint, DevicePoint, DeviceUintSize, DeviceUintRect, ScrollLocation};
+use webrender_api::{DeviceIntPoint, DevicePoint, DeviceUintPoint, DeviceUintSize, DeviceUintRect};
+use webrender_api::ScrollLocation;
 
 #[derive(Clone)]
 pub enum MouseWindowEvent {
@@ -75,6 +78,8 @@ pub enum WindowEvent {
     Reload(TopLevelBrowsingContextId),
     /// Create a new top level browsing context
     NewBrowser(ServoUrl, TopLevelBrowsingContextId),
+    /// Create a new top level browsing context from a persisted session history snapshot
+    RestoreBrowser(SessionHistorySnapshot, TopLevelBrowsingContextId),
     /// Close a top level browsing context
     CloseBrowser(TopLevelBrowsingContextId),
     /// Panic a top level browsing context.
@@ -86,6 +91,12 @@ pub enum WindowEvent {
     ToggleWebRenderDebug(WebRenderDebugOption),
     /// Capture current WebRender
     CaptureWebRender,
+    /// Save a PNG of the page to the given path once it is stable
+    SaveScreenshot(PathBuf, ScreenshotArea),
+    /// Print the page to a PDF file at the given path
+    PrintToPdf(PathBuf),
+    /// Remove all the responses stored in the HTTP cache
+    ClearCache,
 }
 
 impl Debug for WindowEvent {
@@ -107,15 +118,59 @@ impl Debug for WindowEvent {
             WindowEvent::Quit => write!(f, "Quit"),
             WindowEvent::Reload(..) => write!(f, "Reload"),
             WindowEvent::NewBrowser(..) => write!(f, "NewBrowser"),
+            WindowEvent::RestoreBrowser(..) => write!(f, "RestoreBrowser"),
             WindowEvent::SendError(..) => write!(f, "SendError"),
             WindowEvent::CloseBrowser(..) => write!(f, "CloseBrowser"),
             WindowEvent::SelectBrowser(..) => write!(f, "SelectBrowser"),
             WindowEvent::ToggleWebRenderDebug(..) => write!(f, "ToggleWebRenderDebug"),
             WindowEvent::CaptureWebRender => write!(f, "CaptureWebRender"),
+            WindowEvent::SaveScreenshot(..) => write!(f, "SaveScreenshot"),
+            WindowEvent::PrintToPdf(..) => write!(f, "PrintToPdf"),
+            WindowEvent::ClearCache => write!(f, "ClearCache"),
         }
     }
 }
 
+/// The part of the page that a screenshot shows.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScreenshotArea {
+    /// What the viewport shows.
+    Viewport,
+    /// The whole contents of the page. The viewport keeps its size, so the page isn't laid out
+    /// again: the image is put together from one framebuffer-sized tile at a time, scrolling
+    /// the page to each of them. Fixed position elements show up in every tile.
+    FullPage,
+}
+
+/// Why a full-page screenshot can't be taken.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScreenshotError {
+    /// The framebuffer has no pixels, so no part of the page can be captured.
+    EmptyFramebuffer,
+}
+
+/// The origins of the framebuffer-sized tiles that make up a full-page screenshot of a page
+/// of the given size, row by row. The last tile of each row and column is moved back so
+/// that it ends where the page does, rather than past it.
+pub fn full_page_screenshot_tiles(
+    page_size: DeviceUintSize,
+    framebuffer_size: DeviceUintSize,
+) -> Result<Vec<DeviceUintPoint>, ScreenshotError> {
+    if framebuffer_size.width == 0 || framebuffer_size.height == 0 {
+        return Err(ScreenshotError::EmptyFramebuffer);
+    }
+    let origins = |page_length: u32, tile_length: u32| {
+        let last = page_length.saturating_sub(tile_length);
+        let count = (last + tile_length - 1) / tile_length + 1;
+        (0..count).map(move |i| cmp::min(i * tile_length, last))
+    };
+    Ok(origins(page_size.height, framebuffer_size.height)
+        .flat_map(|y| {
+            origins(page_size.width, framebuffer_size.width).map(move |x| DeviceUintPoint::new(x, y))
+        })
+        .collect())
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum AnimationState {
     Idle,
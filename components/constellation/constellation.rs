@@ -102,7 +102,7 @@ use compositing::SendableFrameTree;
 use compositing::compositor_thread::CompositorProxy;
 use compositing::compositor_thread::Msg as ToCompositorMsg;
 use debugger;
-use devtools_traits::{ChromeToDevtoolsControlMsg, DevtoolsControlMsg};
+use devtools_traits::{ChromeToDevtoolsControlMsg, DevtoolsControlMsg, WorkerId};
 use embedder_traits::{EmbedderMsg, EmbedderProxy};
 use euclid::{Size2D, TypedSize2D, TypedScale};
 use event_loop::EventLoop;
@@ -113,10 +113,13 @@ use ipc_channel::ipc::{self, IpcSender, IpcReceiver};
 use ipc_channel::router::ROUTER;
 use layout_traits::LayoutThreadFactory;
 use log::{Log, Level, LevelFilter, Metadata, Record};
-use msg::constellation_msg::{BrowsingContextId, PipelineId, HistoryStateId, TopLevelBrowsingContextId};
-use msg::constellation_msg::{Key, KeyModifiers, KeyState};
+use msg::constellation_msg::{BroadcastChannelRouterId, BrowsingContextId, PipelineId, HistoryStateId};
+use msg::constellation_msg::TopLevelBrowsingContextId;
+use msg::constellation_msg::{Key, KeyModifiers, KeyState, MessagePortId};
 use msg::constellation_msg::{PipelineNamespace, PipelineNamespaceId, TraversalDirection};
 use net_traits::{self, IpcSend, FetchResponseMsg, ResourceThreads};
+use net_traits::cache_storage_thread::CacheStorageThreadMsg;
+use net_traits::indexeddb_thread::IndexedDBThreadMsg;
 use net_traits::pub_domains::reg_host;
 use net_traits::request::RequestInit;
 use net_traits::storage_thread::{StorageThreadMsg, StorageType};
@@ -124,14 +127,17 @@ use network_listener::NetworkListener;
 use pipeline::{InitialPipelineState, Pipeline};
 use profile_traits::mem;
 use profile_traits::time;
-use script_traits::{AnimationState, AuxiliaryBrowsingContextLoadInfo, AnimationTickType, CompositorEvent};
+use script_traits::{AnimationState, AuxiliaryBrowsingContextLoadInfo, AnimationTickType, BroadcastMsg};
+use script_traits::CompositorEvent;
 use script_traits::{ConstellationControlMsg, ConstellationMsg as FromCompositorMsg, DiscardBrowsingContext};
 use script_traits::{DocumentActivity, DocumentState, LayoutControlMsg, LoadData};
 use script_traits::{IFrameLoadInfo, IFrameLoadInfoWithData, IFrameSandboxState, TimerSchedulerMsg};
 use script_traits::{LayoutMsg as FromLayoutMsg, ScriptMsg as FromScriptMsg, ScriptThreadFactory};
 use script_traits::{LogEntry, ScriptToConstellationChan, ServiceWorkerMsg, webdriver_msg};
+use script_traits::{MessagePortMsg, PortMessageTask, TransferredPort};
 use script_traits::{SWManagerMsg, ScopeThings, UpdatePipelineIdReason, WebDriverCommandMsg};
-use script_traits::{WindowSizeData, WindowSizeType};
+use script_traits::{DocumentSessionState, SessionHistorySnapshot};
+use script_traits::{WindowSizeData, WindowSizeType, WorkerGlobalScopeInit};
 use serde::{Deserialize, Serialize};
 use servo_channel::{Receiver, Sender, channel};
 use servo_config::opts;
@@ -139,7 +145,9 @@ use servo_config::prefs::PREFS;
 use servo_rand::{Rng, SeedableRng, ServoRng, random};
 use servo_remutex::ReentrantMutex;
 use servo_url::{Host, ImmutableOrigin, ServoUrl};
-use session_history::{JointSessionHistory, NeedsToReload, SessionHistoryChange, SessionHistoryDiff};
+use session_history::{JointSessionHistory, NeedsToReload, PendingSessionHistoryEntry};
+use session_history::{PendingSessionHistorySnapshot, SessionHistorySaver};
+use session_history::{SessionHistoryChange, SessionHistoryDiff};
 use std::borrow::ToOwned;
 use std::collections::{HashMap, VecDeque};
 use std::marker::PhantomData;
@@ -275,6 +283,9 @@ pub struct Constellation<Message, LTF, STF> {
 
     joint_session_histories: HashMap<TopLevelBrowsingContextId, JointSessionHistory>,
 
+    /// Writes the session histories to the config directory, if there is one.
+    session_history_saver: Option<SessionHistorySaver>,
+
     /// The set of all the pipelines in the browser.
     /// (See the `pipeline` module for more details.)
     pipelines: HashMap<PipelineId, Pipeline>,
@@ -308,6 +319,12 @@ pub struct Constellation<Message, LTF, STF> {
     /// Document states for loaded pipelines (used only when writing screenshots).
     document_states: HashMap<PipelineId, DocumentState>,
 
+    /// The MessagePorts that are alive in any global, and where to route their messages.
+    message_ports: HashMap<MessagePortId, MessagePortInfo>,
+
+    /// The routers of the globals with open BroadcastChannels, along with their origin.
+    broadcast_routers: HashMap<BroadcastChannelRouterId, (ImmutableOrigin, IpcSender<BroadcastMsg>)>,
+
     /// Are we shutting down?
     shutting_down: bool,
 
@@ -381,6 +398,42 @@ pub struct InitialConstellationState {
 struct WebDriverData {
     load_channel: Option<(PipelineId, IpcSender<webdriver_msg::LoadStatus>)>,
     resize_channel: Option<IpcSender<WindowSizeData>>,
+    /// The user prompts shown by top-level browsing contexts, which stay open until
+    /// WebDriver closes them.
+    alerts: HashMap<TopLevelBrowsingContextId, (String, UserPrompt)>,
+}
+
+/// A user prompt waiting for WebDriver, with the channel unblocking the script thread
+/// that opened it.
+enum UserPrompt {
+    Alert(IpcSender<()>),
+    Confirm(IpcSender<bool>),
+    /// A prompt, with its default value.
+    Prompt(String, IpcSender<Option<String>>),
+}
+
+impl UserPrompt {
+    /// Extract the message and the prompt from an embedder message showing a user prompt,
+    /// or give back any other embedder message.
+    fn from_embedder_msg(msg: EmbedderMsg) -> Result<(String, UserPrompt), EmbedderMsg> {
+        match msg {
+            EmbedderMsg::Alert(message, sender) => Ok((message, UserPrompt::Alert(sender))),
+            EmbedderMsg::Confirm(message, sender) => Ok((message, UserPrompt::Confirm(sender))),
+            EmbedderMsg::Prompt(message, default, sender) => {
+                Ok((message, UserPrompt::Prompt(default, sender)))
+            },
+            msg => Err(msg),
+        }
+    }
+
+    /// Close the prompt, as accepted or dismissed by the user.
+    fn close(self, accept: bool) {
+        let _ = match self {
+            UserPrompt::Alert(sender) => sender.send(()),
+            UserPrompt::Confirm(sender) => sender.send(accept),
+            UserPrompt::Prompt(default, sender) => sender.send(if accept { Some(default) } else { None }),
+        };
+    }
 }
 
 impl WebDriverData {
@@ -388,6 +441,32 @@ impl WebDriverData {
         WebDriverData {
             load_channel: None,
             resize_channel: None,
+            alerts: HashMap::new(),
+        }
+    }
+}
+
+/// Routing information for a MessagePort.
+struct MessagePortInfo {
+    /// The pipeline of the global owning the port and the channel to that global,
+    /// or `None` while the port is being transferred.
+    owner: Option<(PipelineId, IpcSender<MessagePortMsg>)>,
+    /// The port this port is entangled with, if any.
+    entangled_with: Option<MessagePortId>,
+    /// The messages sent to the port while it was being transferred.
+    buffered: VecDeque<MessagePortMsg>,
+}
+
+impl MessagePortInfo {
+    /// Sends a message to the global owning the port, or buffers it while the port is being
+    /// transferred.
+    fn send(&mut self, msg: MessagePortMsg) -> Result<(), IpcError> {
+        match self.owner {
+            Some((_, ref sender)) => sender.send(msg),
+            None => {
+                self.buffered.push_back(msg);
+                Ok(())
+            },
         }
     }
 }
@@ -578,6 +657,12 @@ where
                 // Zero is reserved for the embedder.
                 PipelineNamespace::install(PipelineNamespaceId(1));
 
+                let canvas_chan = CanvasPaintThread::start(state.font_cache_thread.clone());
+
+                let session_history_saver = opts::get().config_dir.clone().map(|config_dir| {
+                    SessionHistorySaver::new(config_dir, state.public_resource_threads.clone())
+                });
+
                 let mut constellation: Constellation<Message, LTF, STF> = Constellation {
                     script_sender: ipc_script_sender,
                     layout_sender: ipc_layout_sender,
@@ -600,6 +685,7 @@ where
                     swmanager_sender: sw_mgr_clone,
                     event_loops: HashMap::new(),
                     joint_session_histories: HashMap::new(),
+                    session_history_saver: session_history_saver,
                     pipelines: HashMap::new(),
                     browsing_contexts: HashMap::new(),
                     pending_changes: vec![],
@@ -627,6 +713,8 @@ where
                     webdriver: WebDriverData::new(),
                     scheduler_chan: TimerScheduler::start(),
                     document_states: HashMap::new(),
+                    message_ports: HashMap::new(),
+                    broadcast_routers: HashMap::new(),
                     webrender_document: state.webrender_document,
                     webrender_api_sender: state.webrender_api_sender,
                     shutting_down: false,
@@ -644,7 +732,7 @@ where
                     ),
                     webgl_threads: state.webgl_threads,
                     webvr_chan: state.webvr_chan,
-                    canvas_chan: CanvasPaintThread::start(),
+                    canvas_chan: canvas_chan,
                 };
 
                 constellation.run();
@@ -942,6 +1030,25 @@ where
     fn handle_request_from_swmanager(&mut self, message: SWManagerMsg) {
         match message {
             SWManagerMsg::OwnSender(sw_sender) => {
+                // The service workers restored from a previous session don't belong to any
+                // document, so they get a pipeline id of their own.
+                let pipeline_id = PipelineId::new();
+                let init = WorkerGlobalScopeInit {
+                    resource_threads: self.public_resource_threads.clone(),
+                    mem_profiler_chan: self.mem_profiler_chan.clone(),
+                    time_profiler_chan: self.time_profiler_chan.clone(),
+                    to_devtools_sender: None,
+                    from_devtools_sender: None,
+                    script_to_constellation_chan: ScriptToConstellationChan {
+                        sender: self.script_sender.clone(),
+                        pipeline_id: pipeline_id,
+                    },
+                    scheduler_chan: self.scheduler_chan.clone(),
+                    worker_id: WorkerId(0),
+                    pipeline_id: pipeline_id,
+                    origin: ImmutableOrigin::new_opaque(),
+                };
+                let _ = sw_sender.send(ServiceWorkerMsg::RestoredWorkersInit(init));
                 // store service worker manager for communicating with it.
                 self.swmanager_chan = Some(sw_sender);
             },
@@ -1015,6 +1122,13 @@ where
             FromCompositorMsg::NewBrowser(url, top_level_browsing_context_id) => {
                 self.handle_new_top_level_browsing_context(url, top_level_browsing_context_id);
             },
+            // Create a new top level browsing context from a session history snapshot.
+            FromCompositorMsg::RestoreBrowser(snapshot, top_level_browsing_context_id) => {
+                self.handle_restore_top_level_browsing_context(
+                    snapshot,
+                    top_level_browsing_context_id,
+                );
+            },
             // Close a top level browsing context.
             FromCompositorMsg::CloseBrowser(top_level_browsing_context_id) => {
                 self.handle_close_top_level_browsing_context(top_level_browsing_context_id);
@@ -1058,6 +1172,10 @@ where
                 self.forward_event(destination_pipeline_id, event);
             },
             FromCompositorMsg::SetCursor(cursor) => self.handle_set_cursor_msg(cursor),
+            FromCompositorMsg::ClearCache => self.handle_clear_cache_msg(),
+            FromCompositorMsg::SetPrinting(top_level_browsing_context_id, printing) => {
+                self.handle_set_printing_msg(top_level_browsing_context_id, printing);
+            },
         }
     }
 
@@ -1079,8 +1197,21 @@ where
 
         match content {
             FromScriptMsg::ForwardToEmbedder(embedder_msg) => {
-                self.embedder_proxy
-                    .send((Some(source_top_ctx_id), embedder_msg));
+                if opts::get().webdriver_port.is_some() {
+                    // Under WebDriver control, user prompts are left for the WebDriver client to handle.
+                    match UserPrompt::from_embedder_msg(embedder_msg) {
+                        Ok(prompt) => {
+                            self.webdriver.alerts.insert(source_top_ctx_id, prompt);
+                        },
+                        Err(embedder_msg) => {
+                            self.embedder_proxy
+                                .send((Some(source_top_ctx_id), embedder_msg));
+                        },
+                    }
+                } else {
+                    self.embedder_proxy
+                        .send((Some(source_top_ctx_id), embedder_msg));
+                }
             },
             FromScriptMsg::PipelineExited => {
                 self.handle_pipeline_exited(source_pipeline_id);
@@ -1139,6 +1270,9 @@ where
             FromScriptMsg::JointSessionHistoryLength(sender) => {
                 self.handle_joint_session_history_length(source_top_ctx_id, sender);
             },
+            FromScriptMsg::SetSessionState(session_state) => {
+                self.handle_set_session_state_msg(source_pipeline_id, session_state);
+            },
             // Notification that the new document is ready to become active
             FromScriptMsg::ActivateDocument => {
                 self.handle_activate_document_msg(source_pipeline_id);
@@ -1152,8 +1286,34 @@ where
                     warn!("constellation got set final url message for dead pipeline");
                 }
             },
-            FromScriptMsg::PostMessage(browsing_context_id, origin, data) => {
-                self.handle_post_message_msg(browsing_context_id, origin, data);
+            FromScriptMsg::PostMessage(browsing_context_id, origin, data, ports) => {
+                self.handle_post_message_msg(browsing_context_id, origin, data, ports);
+            },
+            FromScriptMsg::NewMessagePort(port_id, sender) => {
+                self.handle_new_message_port(source_pipeline_id, port_id, sender);
+            },
+            FromScriptMsg::MessagePortShipped(port_id) => {
+                if let Some(info) = self.message_ports.get_mut(&port_id) {
+                    info.owner = None;
+                }
+            },
+            FromScriptMsg::EntangleMessagePorts(port_id, other_id) => {
+                self.handle_entangle_message_ports(port_id, other_id);
+            },
+            FromScriptMsg::PostMessageToPort(port_id, task) => {
+                self.handle_post_message_to_port(port_id, task);
+            },
+            FromScriptMsg::RemoveMessagePort(port_id) => {
+                self.remove_message_port(port_id);
+            },
+            FromScriptMsg::NewBroadcastChannelRouter(router_id, sender, origin) => {
+                self.broadcast_routers.insert(router_id, (origin, sender));
+            },
+            FromScriptMsg::RemoveBroadcastChannelRouter(router_id) => {
+                self.broadcast_routers.remove(&router_id);
+            },
+            FromScriptMsg::ScheduleBroadcast(router_id, msg) => {
+                self.handle_schedule_broadcast(router_id, msg);
             },
             FromScriptMsg::Focus => {
                 self.handle_focus_msg(source_pipeline_id);
@@ -1283,6 +1443,14 @@ where
             FromLayoutMsg::IFrameSizes(iframe_sizes) => {
                 self.handle_iframe_size_msg(iframe_sizes);
             },
+            FromLayoutMsg::ContentSizeChanged(pipeline_id, size) => {
+                self.compositor_proxy
+                    .send(ToCompositorMsg::ContentSizeChanged(pipeline_id, size));
+            },
+            FromLayoutMsg::PrintLayout(pipeline_id, print_layout) => {
+                self.compositor_proxy
+                    .send(ToCompositorMsg::PrintLayout(pipeline_id, print_layout));
+            },
             FromLayoutMsg::PendingPaintMetric(pipeline_id, epoch) => {
                 self.handle_pending_paint_metric(pipeline_id, epoch);
             },
@@ -1338,6 +1506,9 @@ where
         }
         self.shutting_down = true;
 
+        // Persist the final state of the session histories before the pipelines go away.
+        self.save_session_histories();
+
         self.mem_profiler_chan.send(mem::ProfilerMsg::Exit);
 
         // Close the top-level browsing contexts
@@ -1400,6 +1571,16 @@ where
         let (core_sender, core_receiver) = ipc::channel().expect("Failed to create IPC channel!");
         let (storage_sender, storage_receiver) =
             ipc::channel().expect("Failed to create IPC channel!");
+        let (indexeddb_sender, indexeddb_receiver) =
+            ipc::channel().expect("Failed to create IPC channel!");
+        let (cache_storage_sender, cache_storage_receiver) =
+            ipc::channel().expect("Failed to create IPC channel!");
+
+        // The session history saver reads history states from the resource thread.
+        if let Some(ref mut saver) = self.session_history_saver {
+            debug!("Exiting session history saver.");
+            saver.exit();
+        }
 
         debug!("Exiting core resource threads.");
         if let Err(e) = self
@@ -1429,6 +1610,22 @@ where
             warn!("Exit storage thread failed ({})", e);
         }
 
+        debug!("Exiting IndexedDB thread.");
+        if let Err(e) = self
+            .public_resource_threads
+            .send(IndexedDBThreadMsg::Exit(indexeddb_sender))
+        {
+            warn!("Exit IndexedDB thread failed ({})", e);
+        }
+
+        debug!("Exiting cache storage thread.");
+        if let Err(e) = self
+            .public_resource_threads
+            .send(CacheStorageThreadMsg::Exit(cache_storage_sender))
+        {
+            warn!("Exit cache storage thread failed ({})", e);
+        }
+
         debug!("Exiting bluetooth thread.");
         if let Err(e) = self.bluetooth_thread.send(BluetoothRequest::Exit) {
             warn!("Exit bluetooth thread failed ({})", e);
@@ -1470,6 +1667,12 @@ where
         if let Err(e) = storage_receiver.recv() {
             warn!("Exit storage thread failed ({})", e);
         }
+        if let Err(e) = indexeddb_receiver.recv() {
+            warn!("Exit IndexedDB thread failed ({})", e);
+        }
+        if let Err(e) = cache_storage_receiver.recv() {
+            warn!("Exit cache storage thread failed ({})", e);
+        }
 
         debug!("Asking compositor to complete shutdown.");
         self.compositor_proxy
@@ -1479,6 +1682,20 @@ where
     fn handle_pipeline_exited(&mut self, pipeline_id: PipelineId) {
         debug!("Pipeline {:?} exited.", pipeline_id);
         self.pipelines.remove(&pipeline_id);
+
+        // The ports owned by the globals of the pipeline went away with them.
+        let ports: Vec<MessagePortId> = self
+            .message_ports
+            .iter()
+            .filter(|&(_, info)| match info.owner {
+                Some((owner, _)) => owner == pipeline_id,
+                None => false,
+            })
+            .map(|(port_id, _)| *port_id)
+            .collect();
+        for port_id in ports {
+            self.remove_message_port(port_id);
+        }
     }
 
     fn handle_send_error(&mut self, pipeline_id: PipelineId, err: IpcError) {
@@ -1634,12 +1851,85 @@ where
         url: ServoUrl,
         top_level_browsing_context_id: TopLevelBrowsingContextId
     ) {
-        let window_size = self.window_size.initial_viewport;
+        let load_data = LoadData::new(url, None, None, None);
+        self.new_top_level_browsing_context(
+            PipelineId::new(),
+            load_data,
+            top_level_browsing_context_id,
+            JointSessionHistory::new(),
+        );
+    }
+
+    fn handle_restore_top_level_browsing_context(
+        &mut self,
+        snapshot: SessionHistorySnapshot,
+        top_level_browsing_context_id: TopLevelBrowsingContextId,
+    ) {
+        if snapshot.current_index >= snapshot.entries.len() {
+            return warn!("Session history snapshot has no current entry.");
+        }
+
+        // Every entry other than the current one is restored as a discarded pipeline, which
+        // gets reloaded from its `LoadData` if it is ever traversed to.
+        let browsing_context_id = BrowsingContextId::from(top_level_browsing_context_id);
         let pipeline_id = PipelineId::new();
+        let mut current_load_data = None;
+        let mut reloaders = vec![];
+        for (index, entry) in snapshot.entries.into_iter().enumerate() {
+            let mut load_data = LoadData::new(entry.url, None, None, None);
+            if let Some(history_state) = entry.history_state {
+                let history_state_id = HistoryStateId::new();
+                let msg = net_traits::CoreResourceMsg::SetHistoryState(history_state_id, history_state);
+                if let Err(e) = self.public_resource_threads.send(msg) {
+                    warn!("Sending SetHistoryState to resource thread failed ({})", e);
+                }
+                load_data.history_state_id = Some(history_state_id);
+            }
+            load_data.session_state = entry.document_state;
+            if index == snapshot.current_index {
+                current_load_data = Some(load_data);
+                reloaders.push(NeedsToReload::No(pipeline_id));
+            } else {
+                reloaders.push(NeedsToReload::Yes(PipelineId::new(), load_data));
+            }
+        }
+
+        let mut session_history = JointSessionHistory::new();
+        for index in 1..reloaders.len() {
+            let diff = SessionHistoryDiff::BrowsingContextDiff {
+                browsing_context_id: browsing_context_id,
+                old_reloader: reloaders[index - 1].clone(),
+                new_reloader: reloaders[index].clone(),
+            };
+            if index <= snapshot.current_index {
+                session_history.past.push(diff);
+            } else {
+                // The future is traversed from the back, so the next entry goes last.
+                session_history.future.insert(0, diff);
+            }
+        }
+
+        if let Some(load_data) = current_load_data {
+            self.new_top_level_browsing_context(
+                pipeline_id,
+                load_data,
+                top_level_browsing_context_id,
+                session_history,
+            );
+        }
+    }
+
+    fn new_top_level_browsing_context(
+        &mut self,
+        pipeline_id: PipelineId,
+        load_data: LoadData,
+        top_level_browsing_context_id: TopLevelBrowsingContextId,
+        session_history: JointSessionHistory,
+    ) {
+        let window_size = self.window_size.initial_viewport;
         let msg = (Some(top_level_browsing_context_id), EmbedderMsg::BrowserCreated(top_level_browsing_context_id));
         self.embedder_proxy.send(msg);
         let browsing_context_id = BrowsingContextId::from(top_level_browsing_context_id);
-        let load_data = LoadData::new(url.clone(), None, None, None);
         let sandbox = IFrameSandboxState::IFrameUnsandboxed;
         let is_private = false;
         let is_visible = true;
@@ -1648,7 +1938,7 @@ where
             self.focus_pipeline_id = Some(pipeline_id);
         }
         self.joint_session_histories
-            .insert(top_level_browsing_context_id, JointSessionHistory::new());
+            .insert(top_level_browsing_context_id, session_history);
         self.new_pipeline(
             pipeline_id,
             browsing_context_id,
@@ -1656,7 +1946,7 @@ where
             None,
             None,
             Some(window_size),
-            load_data.clone(),
+            load_data,
             sandbox,
             is_private,
             is_visible,
@@ -1680,6 +1970,7 @@ where
     ) {
         let browsing_context_id = BrowsingContextId::from(top_level_browsing_context_id);
         self.close_browsing_context(browsing_context_id, ExitPipelineMode::Normal);
+        self.save_session_histories();
     }
 
     fn handle_iframe_size_msg(
@@ -1984,6 +2275,14 @@ where
             .send((None, EmbedderMsg::SetCursor(cursor)))
     }
 
+    fn handle_clear_cache_msg(&mut self) {
+        for resource_threads in &[&self.public_resource_threads, &self.private_resource_threads] {
+            if let Err(e) = resource_threads.send(net_traits::CoreResourceMsg::ClearCache) {
+                warn!("Sending ClearCache to resource thread failed ({})", e);
+            }
+        }
+    }
+
     fn handle_change_running_animations_state(
         &mut self,
         pipeline_id: PipelineId,
@@ -2558,6 +2857,169 @@ where
         let _ = sender.send(length as u32);
     }
 
+    fn handle_set_session_state_msg(
+        &mut self,
+        pipeline_id: PipelineId,
+        session_state: DocumentSessionState,
+    ) {
+        match self.pipelines.get_mut(&pipeline_id) {
+            Some(pipeline) => pipeline.session_state = Some(session_state),
+            None => warn!("Session state set for closed pipeline {}.", pipeline_id),
+        }
+    }
+
+    /// Take a snapshot of the session history of a top-level browsing context, so that it can
+    /// be restored later. Only navigations of the top-level browsing context are recorded.
+    fn session_history_snapshot(
+        &self,
+        top_level_browsing_context_id: TopLevelBrowsingContextId,
+    ) -> Option<PendingSessionHistorySnapshot> {
+        let session_history = self.joint_session_histories.get(&top_level_browsing_context_id)?;
+        let browsing_context_id = BrowsingContextId::from(top_level_browsing_context_id);
+        let pipeline_id = self.browsing_contexts.get(&browsing_context_id)?.pipeline_id;
+
+        // The URL, history state and document state of an entry, resolved from its reloader.
+        let resolve_entry = |reloader: &NeedsToReload| match *reloader {
+            NeedsToReload::No(id) => self.pipelines.get(&id).map(|pipeline| {
+                (
+                    reloader.clone(),
+                    pipeline.url.clone(),
+                    pipeline.history_state_id,
+                    pipeline.session_state.clone(),
+                )
+            }),
+            NeedsToReload::Yes(_, ref load_data) => Some((
+                reloader.clone(),
+                load_data.url.clone(),
+                load_data.history_state_id,
+                load_data.session_state.clone(),
+            )),
+        };
+        let current_entry = resolve_entry(&NeedsToReload::No(pipeline_id))?;
+
+        let mut past = vec![];
+        let mut entry = current_entry.clone();
+        for diff in session_history.past.iter().rev() {
+            match *diff {
+                SessionHistoryDiff::BrowsingContextDiff {
+                    browsing_context_id: diff_browsing_context_id,
+                    ref old_reloader,
+                    ..
+                } => {
+                    if diff_browsing_context_id != browsing_context_id {
+                        continue;
+                    }
+                    match resolve_entry(old_reloader) {
+                        Some(old_entry) => entry = old_entry,
+                        None => break,
+                    }
+                },
+                SessionHistoryDiff::PipelineDiff {
+                    ref pipeline_reloader,
+                    old_history_state_id,
+                    ref old_url,
+                    ..
+                } => {
+                    if *pipeline_reloader != entry.0 {
+                        continue;
+                    }
+                    entry.1 = old_url.clone();
+                    entry.2 = old_history_state_id;
+                },
+                SessionHistoryDiff::HashDiff {
+                    ref pipeline_reloader,
+                    ref old_url,
+                    ..
+                } => {
+                    if *pipeline_reloader != entry.0 {
+                        continue;
+                    }
+                    entry.1 = old_url.clone();
+                },
+            }
+            past.push(entry.clone());
+        }
+
+        let mut future = vec![];
+        let mut entry = current_entry.clone();
+        for diff in session_history.future.iter().rev() {
+            match *diff {
+                SessionHistoryDiff::BrowsingContextDiff {
+                    browsing_context_id: diff_browsing_context_id,
+                    ref new_reloader,
+                    ..
+                } => {
+                    if diff_browsing_context_id != browsing_context_id {
+                        continue;
+                    }
+                    match resolve_entry(new_reloader) {
+                        Some(new_entry) => entry = new_entry,
+                        None => break,
+                    }
+                },
+                SessionHistoryDiff::PipelineDiff {
+                    ref pipeline_reloader,
+                    new_history_state_id,
+                    ref new_url,
+                    ..
+                } => {
+                    if *pipeline_reloader != entry.0 {
+                        continue;
+                    }
+                    entry.1 = new_url.clone();
+                    entry.2 = Some(new_history_state_id);
+                },
+                SessionHistoryDiff::HashDiff {
+                    ref pipeline_reloader,
+                    ref new_url,
+                    ..
+                } => {
+                    if *pipeline_reloader != entry.0 {
+                        continue;
+                    }
+                    entry.1 = new_url.clone();
+                },
+            }
+            future.push(entry.clone());
+        }
+
+        let current_index = past.len();
+        let entries = past
+            .into_iter()
+            .rev()
+            .chain(Some(current_entry))
+            .chain(future)
+            .map(|(_, url, history_state_id, document_state)| {
+                PendingSessionHistoryEntry {
+                    url: url,
+                    history_state_id: history_state_id,
+                    document_state: document_state,
+                }
+            })
+            .collect();
+        Some(PendingSessionHistorySnapshot {
+            entries: entries,
+            current_index: current_index,
+        })
+    }
+
+    /// Save a snapshot of the session history of every top-level browsing context to the
+    /// config directory, if there is one.
+    fn save_session_histories(&self) {
+        let saver = match self.session_history_saver {
+            Some(ref saver) => saver,
+            None => return,
+        };
+        let mut top_level_browsing_context_ids: Vec<TopLevelBrowsingContextId> =
+            self.joint_session_histories.keys().cloned().collect();
+        top_level_browsing_context_ids.sort();
+        let snapshots = top_level_browsing_context_ids
+            .into_iter()
+            .filter_map(|id| self.session_history_snapshot(id))
+            .collect();
+        saver.save(snapshots);
+    }
+
     fn handle_push_history_state_msg(
         &mut self,
         pipeline_id: PipelineId,
@@ -2666,11 +3128,37 @@ where
         }
     }
 
+    fn handle_set_printing_msg(
+        &mut self,
+        top_level_browsing_context_id: TopLevelBrowsingContextId,
+        printing: bool,
+    ) {
+        let browsing_context_id = BrowsingContextId::from(top_level_browsing_context_id);
+        let pipeline_id = match self.browsing_contexts.get(&browsing_context_id) {
+            Some(browsing_context) => browsing_context.pipeline_id,
+            None => {
+                return warn!(
+                    "Browsing context {} got print request after closure.",
+                    browsing_context_id
+                )
+            },
+        };
+        let msg = ConstellationControlMsg::SetPrinting(pipeline_id, printing);
+        let result = match self.pipelines.get(&pipeline_id) {
+            None => return warn!("Pipeline {} got print request after closure.", pipeline_id),
+            Some(pipeline) => pipeline.event_loop.send(msg),
+        };
+        if let Err(e) = result {
+            self.handle_send_error(pipeline_id, e);
+        }
+    }
+
     fn handle_post_message_msg(
         &mut self,
         browsing_context_id: BrowsingContextId,
         origin: Option<ImmutableOrigin>,
         data: Vec<u8>,
+        ports: Vec<TransferredPort>,
     ) {
         let pipeline_id = match self.browsing_contexts.get(&browsing_context_id) {
             None => {
@@ -2681,7 +3169,7 @@ where
             },
             Some(browsing_context) => browsing_context.pipeline_id,
         };
-        let msg = ConstellationControlMsg::PostMessage(pipeline_id, origin, data);
+        let msg = ConstellationControlMsg::PostMessage(pipeline_id, origin, data, ports);
         let result = match self.pipelines.get(&pipeline_id) {
             Some(pipeline) => pipeline.event_loop.send(msg),
             None => return warn!("postMessage to closed pipeline {}.", pipeline_id),
@@ -2691,6 +3179,83 @@ where
         }
     }
 
+    fn handle_new_message_port(
+        &mut self,
+        pipeline_id: PipelineId,
+        port_id: MessagePortId,
+        sender: IpcSender<MessagePortMsg>,
+    ) {
+        let info = self.message_ports.entry(port_id).or_insert(MessagePortInfo {
+            owner: None,
+            entangled_with: None,
+            buffered: VecDeque::new(),
+        });
+        // Deliver the messages that were sent while the port was in transit.
+        for msg in info.buffered.drain(..) {
+            if let Err(e) = sender.send(msg) {
+                warn!("Failed to deliver buffered message to port {} ({:?}).", port_id, e);
+            }
+        }
+        info.owner = Some((pipeline_id, sender));
+    }
+
+    fn handle_entangle_message_ports(&mut self, port_id: MessagePortId, other_id: MessagePortId) {
+        if let Some(info) = self.message_ports.get_mut(&port_id) {
+            info.entangled_with = Some(other_id);
+        }
+        if let Some(info) = self.message_ports.get_mut(&other_id) {
+            info.entangled_with = Some(port_id);
+        }
+    }
+
+    fn handle_post_message_to_port(&mut self, port_id: MessagePortId, task: PortMessageTask) {
+        let result = match self.message_ports.get_mut(&port_id) {
+            None => return warn!("PostMessage to closed port {}.", port_id),
+            Some(info) => info.send(MessagePortMsg::Message(port_id, task)),
+        };
+        if let Err(e) = result {
+            // The global owning the port went away.
+            warn!("Failed to post message to port {} ({:?}).", port_id, e);
+            self.remove_message_port(port_id);
+        }
+    }
+
+    /// Forgets about a port that was closed or whose global went away,
+    /// and lets the port it was entangled with know.
+    fn remove_message_port(&mut self, port_id: MessagePortId) {
+        let other_id = match self.message_ports.remove(&port_id) {
+            Some(MessagePortInfo { entangled_with: Some(other_id), .. }) => other_id,
+            _ => return,
+        };
+        if let Some(other) = self.message_ports.get_mut(&other_id) {
+            other.entangled_with = None;
+            if let Err(e) = other.send(MessagePortMsg::Disentangle(other_id)) {
+                warn!("Failed to disentangle port {} ({:?}).", other_id, e);
+            }
+        }
+    }
+
+    fn handle_schedule_broadcast(&mut self, source: BroadcastChannelRouterId, msg: BroadcastMsg) {
+        match self.broadcast_routers.get(&source) {
+            Some(&(ref origin, _)) if *origin == msg.origin => {},
+            _ => return warn!("Broadcast from unknown router {} or with unexpected origin.", source),
+        }
+        let mut closed = vec![];
+        for (router_id, &(ref origin, ref sender)) in &self.broadcast_routers {
+            if *router_id == source || *origin != msg.origin {
+                continue;
+            }
+            if let Err(e) = sender.send(msg.clone()) {
+                // The global owning the router went away.
+                warn!("Failed to broadcast to router {} ({:?}).", router_id, e);
+                closed.push(*router_id);
+            }
+        }
+        for router_id in closed {
+            self.broadcast_routers.remove(&router_id);
+        }
+    }
+
     fn handle_get_pipeline(
         &mut self,
         browsing_context_id: BrowsingContextId,
@@ -2937,6 +3502,52 @@ where
                 self.compositor_proxy
                     .send(ToCompositorMsg::CreatePng(reply));
             },
+            WebDriverCommandMsg::MouseMoveAction(x, y) => {
+                self.compositor_proxy
+                    .send(ToCompositorMsg::WebDriverMouseMoveEvent(x, y));
+            },
+            WebDriverCommandMsg::MouseButtonAction(mouse_event_type, mouse_button, x, y) => {
+                self.compositor_proxy.send(ToCompositorMsg::WebDriverMouseButtonEvent(
+                    mouse_event_type,
+                    mouse_button,
+                    x,
+                    y,
+                ));
+            },
+            WebDriverCommandMsg::NewWindow(reply) => {
+                let top_level_browsing_context_id = TopLevelBrowsingContextId::new();
+                let url = ServoUrl::parse("about:blank").expect("infallible");
+                self.handle_new_top_level_browsing_context(url, top_level_browsing_context_id);
+                let _ = reply.send(top_level_browsing_context_id);
+            },
+            WebDriverCommandMsg::CloseWindow(top_level_browsing_context_id, reply) => {
+                self.webdriver.alerts.remove(&top_level_browsing_context_id);
+                self.embedder_proxy.send((
+                    Some(top_level_browsing_context_id),
+                    EmbedderMsg::CloseBrowser,
+                ));
+                self.handle_close_top_level_browsing_context(top_level_browsing_context_id);
+                let _ = reply.send(());
+            },
+            WebDriverCommandMsg::GetAlertText(top_level_browsing_context_id, reply) => {
+                let text = self
+                    .webdriver
+                    .alerts
+                    .get(&top_level_browsing_context_id)
+                    .map(|&(ref text, _)| text.clone());
+                let _ = reply.send(text);
+            },
+            WebDriverCommandMsg::CloseAlert(top_level_browsing_context_id, accept, reply) => {
+                let result = match self.webdriver.alerts.remove(&top_level_browsing_context_id) {
+                    Some((_, prompt)) => {
+                        // Unblock the script thread waiting for the prompt to be closed.
+                        prompt.close(accept);
+                        Ok(())
+                    },
+                    None => Err(()),
+                };
+                let _ = reply.send(result);
+            },
         }
     }
 
@@ -3060,6 +3671,8 @@ where
             EmbedderMsg::HistoryChanged(urls, current_index),
         );
         self.embedder_proxy.send(msg);
+
+        self.save_session_histories();
     }
 
     fn load_url_for_webdriver(
@@ -3285,6 +3898,7 @@ where
                 Some(pipeline) => {
                     let mut load_data = pipeline.load_data.clone();
                     load_data.url = pipeline.url.clone();
+                    load_data.session_state = pipeline.session_state.clone();
                     load_data
                 },
                 None => continue,
@@ -3687,7 +4301,11 @@ where
         pipeline_id: PipelineId,
     ) {
         let load_data = match self.pipelines.get(&pipeline_id) {
-            Some(pipeline) => pipeline.load_data.clone(),
+            Some(pipeline) => {
+                let mut load_data = pipeline.load_data.clone();
+                load_data.session_state = pipeline.session_state.clone();
+                load_data
+            },
             None => return,
         };
         self.joint_session_histories
@@ -56,3 +56,4 @@ pub use constellation::{Constellation, FromCompositorLogger, FromScriptLogger, I
 pub use pipeline::UnprivilegedPipelineContent;
 #[cfg(all(not(target_os = "windows"), not(target_os = "ios")))]
 pub use sandboxing::content_process_sandbox_profile;
+pub use session_history::read_session_history_snapshots;
@@ -68,7 +68,7 @@ impl NetworkListener {
 
                 CoreResourceMsg::Fetch(
                     listener.req_init.clone(),
-                    FetchChannels::ResponseMsg(ipc_sender, cancel_chan),
+                    FetchChannels::ResponseMsg(ipc_sender, cancel_chan, None),
                 )
             },
         };
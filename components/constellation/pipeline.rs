@@ -24,7 +24,7 @@ use net_traits::image_cache::ImageCache;
 use profile_traits::mem as profile_mem;
 use profile_traits::time;
 use script_traits::{ConstellationControlMsg, DiscardBrowsingContext, ScriptToConstellationChan};
-use script_traits::{DocumentActivity, InitialScriptState};
+use script_traits::{DocumentActivity, DocumentSessionState, InitialScriptState};
 use script_traits::{LayoutControlMsg, LayoutMsg, LoadData};
 use script_traits::{NewLayoutInfo, SWManagerMsg, SWManagerSenders};
 use script_traits::{ScriptThreadFactory, TimerSchedulerMsg, WindowSizeData};
@@ -89,6 +89,10 @@ pub struct Pipeline {
 
     /// The history states owned by this pipeline.
     pub history_states: HashSet<HistoryStateId>,
+
+    /// The most recently reported state of this pipeline's document, which is persisted
+    /// along with its session history entry.
+    pub session_state: Option<DocumentSessionState>,
 }
 
 /// Initial setup data needed to construct a pipeline.
@@ -329,6 +333,8 @@ impl Pipeline {
         is_visible: bool,
         load_data: LoadData,
     ) -> Pipeline {
+        let history_state_id = load_data.history_state_id;
+        let session_state = load_data.session_state.clone();
         let pipeline = Pipeline {
             id: id,
             browsing_context_id: browsing_context_id,
@@ -341,8 +347,9 @@ impl Pipeline {
             children: vec![],
             running_animations: false,
             load_data: load_data,
-            history_state_id: None,
-            history_states: HashSet::new(),
+            history_state_id: history_state_id,
+            history_states: history_state_id.into_iter().collect(),
+            session_state: session_state,
         };
 
         pipeline.notify_visibility(is_visible);
@@ -3,11 +3,175 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use browsingcontext::NewBrowsingContextInfo;
+use ipc_channel::ipc;
 use msg::constellation_msg::{BrowsingContextId, HistoryStateId, PipelineId, TopLevelBrowsingContextId};
-use script_traits::LoadData;
+use net::resource_thread::{read_json_from_file, write_json_to_file_atomically};
+use net_traits::{CoreResourceMsg, IpcSend, ResourceThreads};
+use script_traits::{DocumentSessionState, LoadData, SessionHistoryEntrySnapshot};
+use script_traits::SessionHistorySnapshot;
 use servo_url::ServoUrl;
 use std::{fmt, mem};
 use std::cmp::PartialEq;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// The file in the config directory that holds the session history snapshots of the
+/// top-level browsing contexts.
+pub const SESSION_HISTORY_FILE: &'static str = "session_history.json";
+
+/// How long the session history saver waits for further changes after a change to the
+/// session histories, before it writes them.
+const SAVE_DELAY_MS: u64 = 1000;
+
+/// Read the session history snapshots that were last saved to `config_dir`, ordered by the
+/// creation of their top-level browsing contexts. Each of them can be restored with
+/// `ConstellationMsg::RestoreBrowser`.
+pub fn read_session_history_snapshots(config_dir: &Path) -> Vec<SessionHistorySnapshot> {
+    let mut snapshots = vec![];
+    read_json_from_file(&mut snapshots, config_dir, SESSION_HISTORY_FILE);
+    snapshots
+}
+
+/// A session history snapshot whose entries still refer to their `history.state` by id,
+/// as the constellation doesn't hold the serialized states.
+pub struct PendingSessionHistorySnapshot {
+    pub entries: Vec<PendingSessionHistoryEntry>,
+    pub current_index: usize,
+}
+
+pub struct PendingSessionHistoryEntry {
+    pub url: ServoUrl,
+    pub history_state_id: Option<HistoryStateId>,
+    pub document_state: Option<DocumentSessionState>,
+}
+
+enum SessionHistorySaverMsg {
+    Save(Vec<PendingSessionHistorySnapshot>),
+    Exit,
+}
+
+/// Writes the session history snapshots to the config directory from a thread of its own, so
+/// that neither reading the history states from the resource thread nor the file IO blocks
+/// the constellation. Snapshots which are replaced within `SAVE_DELAY_MS` are never written.
+pub struct SessionHistorySaver {
+    sender: mpsc::Sender<SessionHistorySaverMsg>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl SessionHistorySaver {
+    pub fn new(config_dir: PathBuf, resource_threads: ResourceThreads) -> SessionHistorySaver {
+        let (sender, receiver) = mpsc::channel();
+        let thread = thread::Builder::new()
+            .name("SessionHistorySaver".to_owned())
+            .spawn(move || run_session_history_saver(receiver, config_dir, resource_threads))
+            .expect("Thread spawning failed");
+        SessionHistorySaver {
+            sender: sender,
+            thread: Some(thread),
+        }
+    }
+
+    /// Save these snapshots, unless newer ones are saved before they are written.
+    pub fn save(&self, snapshots: Vec<PendingSessionHistorySnapshot>) {
+        if self.sender.send(SessionHistorySaverMsg::Save(snapshots)).is_err() {
+            warn!("Session history saver exited.");
+        }
+    }
+
+    /// Write the last saved snapshots right away, and wait until they are written. This must
+    /// be called before the resource threads exit.
+    pub fn exit(&mut self) {
+        let _ = self.sender.send(SessionHistorySaverMsg::Exit);
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                warn!("Session history saver panicked.");
+            }
+        }
+    }
+}
+
+fn run_session_history_saver(
+    receiver: mpsc::Receiver<SessionHistorySaverMsg>,
+    config_dir: PathBuf,
+    resource_threads: ResourceThreads,
+) {
+    let mut pending = None;
+    let mut deadline = Instant::now();
+    loop {
+        let msg = match pending {
+            Some(_) => {
+                let now = Instant::now();
+                if deadline <= now {
+                    Err(RecvTimeoutError::Timeout)
+                } else {
+                    receiver.recv_timeout(deadline - now)
+                }
+            },
+            None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match msg {
+            Ok(SessionHistorySaverMsg::Save(snapshots)) => {
+                if pending.is_none() {
+                    deadline = Instant::now() + Duration::from_millis(SAVE_DELAY_MS);
+                }
+                pending = Some(snapshots);
+            },
+            Err(RecvTimeoutError::Timeout) => {
+                if let Some(snapshots) = pending.take() {
+                    write_session_history_snapshots(snapshots, &config_dir, &resource_threads);
+                }
+            },
+            Ok(SessionHistorySaverMsg::Exit) | Err(RecvTimeoutError::Disconnected) => {
+                if let Some(snapshots) = pending.take() {
+                    write_session_history_snapshots(snapshots, &config_dir, &resource_threads);
+                }
+                return;
+            },
+        }
+    }
+}
+
+fn write_session_history_snapshots(
+    snapshots: Vec<PendingSessionHistorySnapshot>,
+    config_dir: &Path,
+    resource_threads: &ResourceThreads,
+) {
+    let snapshots: Vec<SessionHistorySnapshot> = snapshots
+        .into_iter()
+        .map(|snapshot| SessionHistorySnapshot {
+            entries: snapshot
+                .entries
+                .into_iter()
+                .map(|entry| SessionHistoryEntrySnapshot {
+                    url: entry.url,
+                    history_state: entry
+                        .history_state_id
+                        .and_then(|id| read_history_state(resource_threads, id)),
+                    document_state: entry.document_state,
+                })
+                .collect(),
+            current_index: snapshot.current_index,
+        })
+        .collect();
+    if let Err(e) = write_json_to_file_atomically(&snapshots, config_dir, SESSION_HISTORY_FILE) {
+        warn!("Writing the session history failed ({}).", e);
+    }
+}
+
+fn read_history_state(
+    resource_threads: &ResourceThreads,
+    history_state_id: HistoryStateId,
+) -> Option<Vec<u8>> {
+    let (sender, receiver) = ipc::channel().expect("Failed to create IPC channel!");
+    let msg = CoreResourceMsg::GetHistoryState(history_state_id, sender);
+    if let Err(e) = resource_threads.send(msg) {
+        warn!("Sending GetHistoryState to resource thread failed ({})", e);
+        return None;
+    }
+    receiver.recv().unwrap_or(None)
+}
 
 /// Represents the joint session history
 /// https://html.spec.whatwg.org/multipage/#joint-session-history
@@ -84,6 +84,11 @@ pub enum EmbedderMsg {
     ResizeTo(DeviceUintSize),
     // Show an alert message.
     Alert(String, IpcSender<()>),
+    /// Ask the user to confirm a message, replying whether they accepted it.
+    Confirm(String, IpcSender<bool>),
+    /// Ask the user for a string, given a message and a default value, replying with
+    /// their input or `None` if they dismissed the prompt.
+    Prompt(String, String, IpcSender<Option<String>>),
     /// Wether or not to follow a link
     AllowNavigation(ServoUrl, IpcSender<bool>),
     /// Whether or not to allow script to open a new tab/browser
@@ -132,6 +137,8 @@ impl Debug for EmbedderMsg {
             EmbedderMsg::MoveTo(..) => write!(f, "MoveTo"),
             EmbedderMsg::ResizeTo(..) => write!(f, "ResizeTo"),
             EmbedderMsg::Alert(..) => write!(f, "Alert"),
+            EmbedderMsg::Confirm(..) => write!(f, "Confirm"),
+            EmbedderMsg::Prompt(..) => write!(f, "Prompt"),
             EmbedderMsg::AllowUnload(..) => write!(f, "AllowUnload"),
             EmbedderMsg::AllowNavigation(..) => write!(f, "AllowNavigation"),
             EmbedderMsg::KeyEvent(..) => write!(f, "KeyEvent"),
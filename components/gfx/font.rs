@@ -66,6 +66,9 @@ pub trait FontHandleMethods: Sized {
     fn glyph_index(&self, codepoint: char) -> Option<GlyphId>;
     fn glyph_h_advance(&self, GlyphId) -> Option<FractionalPixel>;
     fn glyph_h_kerning(&self, glyph0: GlyphId, glyph1: GlyphId) -> FractionalPixel;
+    /// The ink bounds of a glyph, relative to its origin on the baseline, with the y axis
+    /// pointing down.
+    fn glyph_bounds(&self, GlyphId) -> Option<Rect<FractionalPixel>>;
 
     /// Can this font do basic horizontal LTR shaping without Harfbuzz?
     fn can_do_fast_shaping(&self) -> bool;
@@ -336,6 +339,10 @@ impl Font {
         self.handle.glyph_h_kerning(first_glyph, second_glyph)
     }
 
+    pub fn glyph_bounds(&self, glyph: GlyphId) -> Option<Rect<FractionalPixel>> {
+        self.handle.glyph_bounds(glyph)
+    }
+
     pub fn glyph_h_advance(&self, glyph: GlyphId) -> FractionalPixel {
         *self
             .glyph_advance_cache
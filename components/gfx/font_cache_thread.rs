@@ -8,7 +8,9 @@ use font_context::FontSource;
 use font_template::{FontTemplate, FontTemplateDescriptor};
 use fontsan;
 use ipc_channel::ipc::{self, IpcReceiver, IpcSender};
+use msg::constellation_msg::PipelineId;
 use net_traits::{CoreResourceThread, FetchResponseMsg, fetch_async};
+use net_traits::csp::CspList;
 use net_traits::request::{Destination, RequestInit};
 use platform::font_context::FontContextHandle;
 use platform::font_list::SANS_SERIF_FONT_FAMILY;
@@ -102,12 +104,29 @@ impl FontTemplates {
     }
 }
 
+/// A font face created by script, which its document can only use while the
+/// font face is in its `FontFaceSet`.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct ScriptFontFace {
+    pub document: PipelineId,
+    pub id: u64,
+}
+
+/// The fonts a font face created by script loaded, which are kept around
+/// while it isn't in the `FontFaceSet` of its document.
+struct ScriptFontFaceTemplates {
+    family_name: LowercaseString,
+    templates: Vec<(Atom, Option<Vec<u8>>)>,
+    in_font_face_set: bool,
+}
+
 /// Commands that the FontContext sends to the font cache thread.
 #[derive(Debug, Deserialize, Serialize)]
 pub enum Command {
     GetFontTemplate(
         FontTemplateDescriptor,
         FontFamilyDescriptor,
+        Option<PipelineId>,
         IpcSender<Reply>,
     ),
     GetFontInstance(
@@ -115,8 +134,19 @@ pub enum Command {
         Au,
         IpcSender<webrender_api::FontInstanceKey>,
     ),
-    AddWebFont(LowercaseString, EffectiveSources, IpcSender<()>),
-    AddDownloadedWebFont(LowercaseString, ServoUrl, Vec<u8>, IpcSender<()>),
+    AddWebFont(
+        LowercaseString,
+        EffectiveSources,
+        Option<CspList>,
+        Option<ScriptFontFace>,
+        IpcSender<bool>,
+    ),
+    AddDownloadedWebFont(LowercaseString, ServoUrl, Vec<u8>, Option<ScriptFontFace>, IpcSender<bool>),
+    AddWebFontData(LowercaseString, Vec<u8>, ScriptFontFace, IpcSender<bool>),
+    AddScriptFontFaceToSet(ScriptFontFace, LowercaseString),
+    RemoveScriptFontFaceFromSet(ScriptFontFace),
+    RemoveDocumentFonts(PipelineId),
+    HasFamily(LowercaseString, IpcSender<bool>),
     Exit(IpcSender<()>),
     Ping,
 }
@@ -135,11 +165,19 @@ struct FontCache {
     generic_fonts: HashMap<FontFamilyName, LowercaseString>,
     local_families: HashMap<LowercaseString, FontTemplates>,
     web_families: HashMap<LowercaseString, FontTemplates>,
+    /// The font faces created by script, whether or not they are in the
+    /// `FontFaceSet` of their document.
+    script_font_faces: HashMap<ScriptFontFace, ScriptFontFaceTemplates>,
+    /// The families of the font faces in the `FontFaceSet` of each document.
+    document_families: HashMap<PipelineId, HashMap<LowercaseString, FontTemplates>>,
     font_context: FontContextHandle,
     core_resource_thread: CoreResourceThread,
     webrender_api: webrender_api::RenderApi,
     webrender_fonts: HashMap<Atom, webrender_api::FontKey>,
     font_instances: HashMap<(webrender_api::FontKey, Au), webrender_api::FontInstanceKey>,
+    /// The number of Web fonts added from data rather than from a URL, used to
+    /// give them unique identifiers.
+    web_font_data_count: usize,
 }
 
 fn populate_generic_fonts() -> HashMap<FontFamilyName, LowercaseString> {
@@ -175,9 +213,9 @@ impl FontCache {
             let msg = self.port.recv().unwrap();
 
             match msg {
-                Command::GetFontTemplate(template_descriptor, family_descriptor, result) => {
+                Command::GetFontTemplate(template_descriptor, family_descriptor, document, result) => {
                     let maybe_font_template =
-                        self.find_font_template(&template_descriptor, &family_descriptor);
+                        self.find_font_template(&template_descriptor, &family_descriptor, document);
                     let _ = result.send(Reply::GetFontTemplateReply(maybe_font_template));
                 },
                 Command::GetFontInstance(font_key, size, result) => {
@@ -197,13 +235,35 @@ impl FontCache {
 
                     let _ = result.send(instance_key);
                 },
-                Command::AddWebFont(family_name, sources, result) => {
-                    self.handle_add_web_font(family_name, sources, result);
+                Command::AddWebFont(family_name, sources, csp_list, font_face, result) => {
+                    self.handle_add_web_font(family_name, sources, csp_list, font_face, result);
+                },
+                Command::AddDownloadedWebFont(family_name, url, bytes, font_face, result) => {
+                    let identifier = Atom::from(url.to_string());
+                    self.add_web_font_template(family_name, font_face, identifier, Some(bytes));
+                    drop(result.send(true));
+                },
+                Command::AddWebFontData(family_name, bytes, font_face, result) => {
+                    self.handle_add_web_font_data(family_name, bytes, font_face, result);
+                },
+                Command::AddScriptFontFaceToSet(font_face, family_name) => {
+                    self.script_font_face(font_face, family_name).in_font_face_set = true;
+                    self.refresh_document_families(font_face.document);
+                },
+                Command::RemoveScriptFontFaceFromSet(font_face) => {
+                    if let Some(templates) = self.script_font_faces.get_mut(&font_face) {
+                        templates.in_font_face_set = false;
+                    }
+                    self.refresh_document_families(font_face.document);
+                },
+                Command::RemoveDocumentFonts(document) => {
+                    self.script_font_faces.retain(|font_face, _| font_face.document != document);
+                    self.document_families.remove(&document);
                 },
-                Command::AddDownloadedWebFont(family_name, url, bytes, result) => {
-                    let templates = &mut self.web_families.get_mut(&family_name).unwrap();
-                    templates.add_template(Atom::from(url.to_string()), Some(bytes));
-                    drop(result.send(()));
+                Command::HasFamily(family_name, result) => {
+                    let has_family = self.web_families.contains_key(&family_name) ||
+                        self.local_families.contains_key(&family_name);
+                    drop(result.send(has_family));
                 },
                 Command::Ping => (),
                 Command::Exit(result) => {
@@ -218,16 +278,18 @@ impl FontCache {
         &mut self,
         family_name: LowercaseString,
         mut sources: EffectiveSources,
-        sender: IpcSender<()>,
+        csp_list: Option<CspList>,
+        font_face: Option<ScriptFontFace>,
+        sender: IpcSender<bool>,
     ) {
         let src = if let Some(src) = sources.next() {
             src
         } else {
-            sender.send(()).unwrap();
+            sender.send(false).unwrap();
             return;
         };
 
-        if !self.web_families.contains_key(&family_name) {
+        if font_face.is_none() && !self.web_families.contains_key(&family_name) {
             let templates = FontTemplates::new();
             self.web_families.insert(family_name.clone(), templates);
         }
@@ -237,12 +299,15 @@ impl FontCache {
                 // https://drafts.csswg.org/css-fonts/#font-fetching-requirements
                 let url = match url_source.url.url() {
                     Some(url) => url.clone(),
-                    None => return,
+                    None => {
+                        return self.handle_add_web_font(family_name, sources, csp_list, font_face, sender)
+                    },
                 };
 
                 let request = RequestInit {
                     url: url.clone(),
                     destination: Destination::Font,
+                    csp_list: csp_list.clone(),
                     // TODO: Add a proper origin - Can't import GlobalScope from gfx
                     // We can leave origin to be set by default
                     ..RequestInit::default()
@@ -255,7 +320,8 @@ impl FontCache {
                 fetch_async(request, &self.core_resource_thread, move |response| {
                     match response {
                         FetchResponseMsg::ProcessRequestBody |
-                        FetchResponseMsg::ProcessRequestEOF => (),
+                        FetchResponseMsg::ProcessRequestEOF |
+                        FetchResponseMsg::ProcessCspViolations(_) => (),
                         FetchResponseMsg::ProcessResponse(meta_result) => {
                             trace!(
                                 "@font-face {} metadata ok={:?}",
@@ -276,6 +342,8 @@ impl FontCache {
                                 let msg = Command::AddWebFont(
                                     family_name.clone(),
                                     sources.clone(),
+                                    csp_list.clone(),
+                                    font_face,
                                     sender.clone(),
                                 );
                                 channel_to_self.send(msg).unwrap();
@@ -295,6 +363,8 @@ impl FontCache {
                                     let msg = Command::AddWebFont(
                                         family_name.clone(),
                                         sources.clone(),
+                                        csp_list.clone(),
+                                        font_face,
                                         sender.clone(),
                                     );
                                     channel_to_self.send(msg).unwrap();
@@ -305,6 +375,7 @@ impl FontCache {
                                 family_name.clone(),
                                 url.clone(),
                                 bytes,
+                                font_face,
                                 sender.clone(),
                             );
                             channel_to_self.send(command).unwrap();
@@ -314,22 +385,105 @@ impl FontCache {
             },
             Source::Local(ref font) => {
                 let font_face_name = LowercaseString::new(&font.name);
-                let templates = &mut self.web_families.get_mut(&family_name).unwrap();
-                let mut found = false;
-                for_each_variation(&font_face_name, |path| {
-                    found = true;
-                    templates.add_template(Atom::from(&*path), None);
-                });
-                if found {
-                    sender.send(()).unwrap();
-                } else {
-                    let msg = Command::AddWebFont(family_name, sources, sender);
+                let mut paths = vec![];
+                for_each_variation(&font_face_name, |path| paths.push(path));
+                if paths.is_empty() {
+                    let msg = Command::AddWebFont(family_name, sources, csp_list, font_face, sender);
                     self.channel_to_self.send(msg).unwrap();
+                    return;
+                }
+                for path in paths {
+                    self.add_web_font_template(family_name.clone(), font_face, Atom::from(&*path), None);
                 }
+                sender.send(true).unwrap();
             },
         }
     }
 
+    fn handle_add_web_font_data(
+        &mut self,
+        family_name: LowercaseString,
+        bytes: Vec<u8>,
+        font_face: ScriptFontFace,
+        sender: IpcSender<bool>,
+    ) {
+        let bytes = match fontsan::process(&bytes) {
+            Ok(san) => san,
+            Err(_) => {
+                debug!("Sanitiser rejected web font data: family={}", family_name);
+                return sender.send(false).unwrap();
+            },
+        };
+
+        self.web_font_data_count += 1;
+        let identifier = Atom::from(format!("{}#data-{}", family_name, self.web_font_data_count));
+        self.add_web_font_template(family_name, Some(font_face), identifier, Some(bytes));
+        sender.send(true).unwrap();
+    }
+
+    /// Adds a font to a family of `@font-face` rules, or to a font face
+    /// created by script.
+    fn add_web_font_template(
+        &mut self,
+        family_name: LowercaseString,
+        font_face: Option<ScriptFontFace>,
+        identifier: Atom,
+        maybe_data: Option<Vec<u8>>,
+    ) {
+        match font_face {
+            None => self.web_families
+                .entry(family_name)
+                .or_insert_with(FontTemplates::new)
+                .add_template(identifier, maybe_data),
+            Some(font_face) => {
+                let in_font_face_set = {
+                    let templates = self.script_font_face(font_face, family_name);
+                    templates.templates.push((identifier, maybe_data));
+                    templates.in_font_face_set
+                };
+                if in_font_face_set {
+                    self.refresh_document_families(font_face.document);
+                }
+            },
+        }
+    }
+
+    /// Returns the fonts of a font face created by script, making sure they
+    /// are in the given family, as the family of a font face can change.
+    fn script_font_face(
+        &mut self,
+        font_face: ScriptFontFace,
+        family_name: LowercaseString,
+    ) -> &mut ScriptFontFaceTemplates {
+        let templates = self.script_font_faces
+            .entry(font_face)
+            .or_insert_with(|| ScriptFontFaceTemplates {
+                family_name: family_name.clone(),
+                templates: vec![],
+                in_font_face_set: false,
+            });
+        templates.family_name = family_name;
+        templates
+    }
+
+    /// Gathers the fonts of the font faces in the `FontFaceSet` of a document
+    /// by family, after one of them was added, removed or loaded.
+    fn refresh_document_families(&mut self, document: PipelineId) {
+        let mut families = HashMap::new();
+        let font_faces = self.script_font_faces
+            .iter()
+            .filter(|&(font_face, templates)| font_face.document == document && templates.in_font_face_set);
+        for (_, font_face_templates) in font_faces {
+            let templates = families
+                .entry(font_face_templates.family_name.clone())
+                .or_insert_with(FontTemplates::new);
+            for &(ref identifier, ref maybe_data) in &font_face_templates.templates {
+                templates.add_template(identifier.clone(), maybe_data.clone());
+            }
+        }
+        self.document_families.insert(document, families);
+    }
+
     fn refresh_local_families(&mut self) {
         self.local_families.clear();
         for_each_available_family(|family_name| {
@@ -395,6 +549,17 @@ impl FontCache {
         }
     }
 
+    fn find_font_in_document_family(
+        &mut self,
+        template_descriptor: &FontTemplateDescriptor,
+        family_name: &FontFamilyName,
+        document: Option<PipelineId>,
+    ) -> Option<Arc<FontTemplateData>> {
+        let family_name = LowercaseString::from(family_name);
+        let templates = self.document_families.get_mut(&document?)?.get_mut(&family_name)?;
+        templates.find_font_for_style(template_descriptor, &self.font_context)
+    }
+
     fn get_font_template_info(&mut self, template: Arc<FontTemplateData>) -> FontTemplateInfo {
         let webrender_api = &self.webrender_api;
         let webrender_fonts = &mut self.webrender_fonts;
@@ -423,10 +588,12 @@ impl FontCache {
         &mut self,
         template_descriptor: &FontTemplateDescriptor,
         family_descriptor: &FontFamilyDescriptor,
+        document: Option<PipelineId>,
     ) -> Option<FontTemplateInfo> {
         match family_descriptor.scope {
             FontSearchScope::Any => self
-                .find_font_in_web_family(&template_descriptor, &family_descriptor.name)
+                .find_font_in_document_family(&template_descriptor, &family_descriptor.name, document)
+                .or_else(|| self.find_font_in_web_family(&template_descriptor, &family_descriptor.name))
                 .or_else(|| {
                     self.find_font_in_local_family(&template_descriptor, &family_descriptor.name)
                 }),
@@ -443,6 +610,8 @@ impl FontCache {
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct FontCacheThread {
     chan: IpcSender<Command>,
+    /// The document whose font faces created by script can be used, if any.
+    document: Option<PipelineId>,
 }
 
 impl FontCacheThread {
@@ -465,34 +634,122 @@ impl FontCacheThread {
                     generic_fonts,
                     local_families: HashMap::new(),
                     web_families: HashMap::new(),
+                    script_font_faces: HashMap::new(),
+                    document_families: HashMap::new(),
                     font_context: FontContextHandle::new(),
                     core_resource_thread,
                     webrender_api,
                     webrender_fonts: HashMap::new(),
                     font_instances: HashMap::new(),
+                    web_font_data_count: 0,
                 };
 
                 cache.refresh_local_families();
                 cache.run();
             }).expect("Thread spawning failed");
 
-        FontCacheThread { chan: chan }
+        FontCacheThread {
+            chan: chan,
+            document: None,
+        }
+    }
+
+    /// Returns an interface to the font cache thread which can use the font
+    /// faces in the `FontFaceSet` of the given document.
+    pub fn for_document(&self, document: PipelineId) -> FontCacheThread {
+        FontCacheThread {
+            chan: self.chan.clone(),
+            document: Some(document),
+        }
     }
 
     pub fn add_web_font(
         &self,
         family: FamilyName,
         sources: EffectiveSources,
-        sender: IpcSender<()>,
+        csp_list: Option<CspList>,
+        sender: IpcSender<bool>,
+    ) {
+        self.chan
+            .send(Command::AddWebFont(
+                LowercaseString::new(&family.name),
+                sources,
+                csp_list,
+                None,
+                sender,
+            )).unwrap();
+    }
+
+    /// Loads a font face created by script from a list of sources.
+    pub fn add_script_font_face(
+        &self,
+        font_face: ScriptFontFace,
+        family: FamilyName,
+        sources: EffectiveSources,
+        csp_list: Option<CspList>,
+        sender: IpcSender<bool>,
     ) {
         self.chan
             .send(Command::AddWebFont(
                 LowercaseString::new(&family.name),
                 sources,
+                csp_list,
+                Some(font_face),
                 sender,
             )).unwrap();
     }
 
+    /// Loads a font face created by script from its data.
+    pub fn add_script_font_face_data(
+        &self,
+        font_face: ScriptFontFace,
+        family: FamilyName,
+        bytes: Vec<u8>,
+        sender: IpcSender<bool>,
+    ) {
+        self.chan
+            .send(Command::AddWebFontData(
+                LowercaseString::new(&family.name),
+                bytes,
+                font_face,
+                sender,
+            )).unwrap();
+    }
+
+    /// Lets the document of a font face created by script use it, as it was
+    /// added to its `FontFaceSet`.
+    pub fn add_script_font_face_to_set(&self, font_face: ScriptFontFace, family: FamilyName) {
+        self.chan
+            .send(Command::AddScriptFontFaceToSet(
+                font_face,
+                LowercaseString::new(&family.name),
+            )).unwrap();
+    }
+
+    /// Stops the document of a font face created by script from using it, as
+    /// it was removed from its `FontFaceSet`.
+    pub fn remove_script_font_face_from_set(&self, font_face: ScriptFontFace) {
+        self.chan
+            .send(Command::RemoveScriptFontFaceFromSet(font_face))
+            .unwrap();
+    }
+
+    /// Forgets about the font faces created by script for a document which is
+    /// going away.
+    pub fn remove_document_fonts(&self, document: PipelineId) {
+        let _ = self.chan.send(Command::RemoveDocumentFonts(document));
+    }
+
+    /// Whether there are system fonts or fonts of `@font-face` rules in the
+    /// given family.
+    pub fn has_family(&self, family: &Atom) -> bool {
+        let (response_chan, response_port) = ipc::channel().expect("failed to create IPC channel");
+        self.chan
+            .send(Command::HasFamily(LowercaseString::new(family), response_chan))
+            .expect("failed to send message to font cache thread");
+        response_port.recv().unwrap_or(false)
+    }
+
     pub fn exit(&self) {
         let (response_chan, response_port) = ipc::channel().unwrap();
         self.chan
@@ -537,6 +794,7 @@ impl FontSource for FontCacheThread {
             .send(Command::GetFontTemplate(
                 template_descriptor,
                 family_descriptor,
+                self.document,
                 response_chan,
             )).expect("failed to send message to font cache thread");
 
@@ -5,7 +5,7 @@
 use font::FontHandleMethods;
 use platform::font::FontHandle;
 use platform::font_context::FontContextHandle;
-use platform::font_template::FontTemplateData;
+pub use platform::font_template::FontTemplateData;
 use servo_atoms::Atom;
 use std::fmt::{Debug, Error, Formatter};
 use std::io::Error as IoError;
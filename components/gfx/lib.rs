@@ -45,6 +45,7 @@ extern crate libc;
 extern crate log;
 #[cfg_attr(target_os = "windows", macro_use)]
 extern crate malloc_size_of;
+extern crate msg;
 extern crate net_traits;
 extern crate ordered_float;
 #[cfg(all(
@@ -3,6 +3,7 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use app_units::Au;
+use euclid::{Point2D, Rect, Size2D};
 use font::{FontHandleMethods, FontMetrics, FontTableMethods};
 use font::{FontTableTag, FractionalPixel, GPOS, GSUB, KERN};
 use freetype::freetype::{FT_Done_Face, FT_New_Face, FT_New_Memory_Face};
@@ -274,6 +275,31 @@ impl FontHandleMethods for FontHandle {
         }
     }
 
+    fn glyph_bounds(&self, glyph: GlyphId) -> Option<Rect<FractionalPixel>> {
+        assert!(!self.face.is_null());
+        unsafe {
+            let res = FT_Load_Glyph(self.face, glyph as FT_UInt, GLYPH_LOAD_FLAGS);
+            if !succeeded(res) {
+                debug!("Unable to load glyph {}. reason: {:?}", glyph, res);
+                return None;
+            }
+            let void_glyph = (*self.face).glyph;
+            let slot: FT_GlyphSlot = mem::transmute(void_glyph);
+            assert!(!slot.is_null());
+            let metrics = &(*slot).metrics;
+            Some(Rect::new(
+                Point2D::new(
+                    fixed_to_float_ft(metrics.horiBearingX as i32),
+                    -fixed_to_float_ft(metrics.horiBearingY as i32),
+                ),
+                Size2D::new(
+                    fixed_to_float_ft(metrics.width as i32),
+                    fixed_to_float_ft(metrics.height as i32),
+                ),
+            ))
+        }
+    }
+
     fn metrics(&self) -> FontMetrics {
         /* TODO(Issue #76): complete me */
         let face = self.face_rec_mut();
@@ -13,6 +13,7 @@ use core_graphics::geometry::CGRect;
 use core_text::font::CTFont;
 use core_text::font_descriptor::{SymbolicTraitAccessors, TraitAccessors};
 use core_text::font_descriptor::kCTFontDefaultOrientation;
+use euclid::{Point2D, Rect, Size2D};
 use font::{FontHandleMethods, FontMetrics, FontTableMethods, FontTableTag, FractionalPixel};
 use font::{GPOS, GSUB, KERN};
 use platform::font_template::FontTemplateData;
@@ -272,6 +273,24 @@ impl FontHandleMethods for FontHandle {
         Some(advance as FractionalPixel)
     }
 
+    fn glyph_bounds(&self, glyph: GlyphId) -> Option<Rect<FractionalPixel>> {
+        let glyphs = [glyph as CGGlyph];
+        // Core Text's y axis points up.
+        let rect = self
+            .ctfont
+            .get_bounding_rects_for_glyphs(kCTFontDefaultOrientation, &glyphs);
+        Some(Rect::new(
+            Point2D::new(
+                rect.origin.x as FractionalPixel,
+                -(rect.origin.y + rect.size.height) as FractionalPixel,
+            ),
+            Size2D::new(
+                rect.size.width as FractionalPixel,
+                rect.size.height as FractionalPixel,
+            ),
+        ))
+    }
+
     fn metrics(&self) -> FontMetrics {
         let bounding_rect: CGRect = self.ctfont.bounding_box();
         let ascent = self.ctfont.ascent() as f64;
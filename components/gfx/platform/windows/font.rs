@@ -10,6 +10,7 @@ use app_units::Au;
 use dwrote;
 use dwrote::{Font, FontFace, FontFile};
 use dwrote::{FontWeight, FontStretch, FontStyle};
+use euclid::{Point2D, Rect, Size2D};
 use font::{FontHandleMethods, FontMetrics, FontTableMethods};
 use font::{FontTableTag, FractionalPixel};
 use platform::font_template::FontTemplateData;
@@ -350,6 +351,23 @@ impl FontHandleMethods for FontHandle {
         Some(f)
     }
 
+    fn glyph_bounds(&self, glyph: GlyphId) -> Option<Rect<FractionalPixel>> {
+        if glyph == 0 {
+            return None;
+        }
+
+        let gm = self.face.get_design_glyph_metrics(&[glyph as u16], false)[0];
+        let px = |du: i32| (du as f32 * self.scaled_du_to_px) as FractionalPixel;
+        let left = px(gm.leftSideBearing);
+        let right = px(gm.advanceWidth as i32 - gm.rightSideBearing);
+        let top = px(gm.verticalOriginY - gm.topSideBearing);
+        let bottom = px(gm.verticalOriginY - gm.advanceHeight as i32 + gm.bottomSideBearing);
+        Some(Rect::new(
+            Point2D::new(left, -top),
+            Size2D::new(right - left, top - bottom),
+        ))
+    }
+
     /// Can this font do basic horizontal LTR shaping without Harfbuzz?
     fn can_do_fast_shaping(&self) -> bool {
         // TODO copy CachedKernTable from the MacOS X implementation to
@@ -32,6 +32,7 @@ pub fn update_animation_state<E>(
     new_animations_receiver: &Receiver<Animation>,
     pipeline_id: PipelineId,
     timer: &Timer,
+    has_animated_images: bool,
 ) where
     E: TElement,
 {
@@ -87,6 +88,9 @@ pub fn update_animation_state<E>(
                     // iterating.
                     now < state.started_at + state.duration || state.tick()
                 },
+                // Script animations are only removed when script cancels
+                // them or they stop applying.
+                Animation::Script(..) => true,
             };
 
             if still_running {
@@ -139,7 +143,8 @@ pub fn update_animation_state<E>(
             .push(new_running_animation)
     }
 
-    let animation_state = if running_animations.is_empty() {
+    // Animated images also need the compositor to keep ticking us.
+    let animation_state = if running_animations.is_empty() && !has_animated_images {
         AnimationState::NoAnimationsPresent
     } else {
         AnimationState::AnimationsPresent
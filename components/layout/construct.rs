@@ -28,7 +28,8 @@ use fragment::{Fragment, GeneratedContentInfo, IframeFragmentInfo, FragmentFlags
 use fragment::{InlineAbsoluteHypotheticalFragmentInfo, TableColumnFragmentInfo};
 use fragment::{InlineBlockFragmentInfo, SpecificFragmentInfo, UnscannedTextFragmentInfo};
 use fragment::WhitespaceStrippingResult;
-use inline::{InlineFlow, InlineFragmentNodeInfo, InlineFragmentNodeFlags};
+use grid::GridFlow;
+use inline::{FirstLineStyle, InlineFlow, InlineFragmentNodeInfo, InlineFragmentNodeFlags};
 use linked_list::prepend_from;
 use list_item::{ListItemFlow, ListStyleTypeContent};
 use multicol::{MulticolColumnFlow, MulticolFlow};
@@ -48,8 +49,9 @@ use style::computed_values::empty_cells::T as EmptyCells;
 use style::computed_values::float::T as Float;
 use style::computed_values::list_style_position::T as ListStylePosition;
 use style::computed_values::position::T as Position;
-use style::context::SharedStyleContext;
+use style::context::{CascadeInputs, SharedStyleContext};
 use style::dom::TElement;
+use style::font_metrics::ServoMetricsProvider;
 use style::logical_geometry::Direction;
 use style::properties::ComputedValues;
 use style::selector_parser::{PseudoElement, RestyleDamage};
@@ -391,6 +393,81 @@ impl<'a, ConcreteThreadSafeLayoutNode: ThreadSafeLayoutNode>
         node.set_flow_construction_result(result);
     }
 
+    /// Returns the style of the given eager pseudo-element of `node`, like `::first-letter`, if
+    /// the node is an element that has one.
+    fn eager_pseudo_style(
+        &self,
+        node: &ConcreteThreadSafeLayoutNode,
+        pseudo: &PseudoElement,
+    ) -> Option<ServoArc<ComputedValues>> {
+        if node.get_pseudo_element_type() != PseudoElementType::Normal {
+            return None;
+        }
+        node.as_element().and_then(|element| element.eager_pseudo_style(pseudo))
+    }
+
+    /// Builds a fragment for the marker of a list item, styled with its `::marker` style if there
+    /// is one.
+    fn build_fragment_for_list_marker(
+        &self,
+        node: &ConcreteThreadSafeLayoutNode,
+        specific: SpecificFragmentInfo,
+    ) -> Fragment {
+        let mut fragment = Fragment::new(node, specific, self.layout_context);
+        if let Some(style) = self.eager_pseudo_style(node, &PseudoElement::Marker) {
+            fragment.style = style;
+        }
+        fragment
+    }
+
+    /// Builds a float holding the given `::first-letter` fragment, for a first letter whose style
+    /// floats it out of the first line of its block.
+    fn build_flow_for_floated_first_letter(
+        &self,
+        mut first_letter: Fragment,
+        float_kind: FloatKind,
+    ) -> FlowRef {
+        let block_fragment = first_letter.create_similar_anonymous_fragment(
+            first_letter.style.clone(),
+            SpecificFragmentInfo::Generic,
+        );
+
+        // The float already has the margins, borders and padding of the first letter, so the text
+        // inside it only inherits from it.
+        let context = self.style_context();
+        first_letter.style = context
+            .stylist
+            .style_for_anonymous::<ConcreteThreadSafeLayoutNode::ConcreteElement>(
+                &context.guards,
+                &PseudoElement::ServoText,
+                &block_fragment.style,
+            );
+        first_letter.inline_context = None;
+
+        let mut fragments = LinkedList::new();
+        fragments.push_back(first_letter);
+        let scanned_fragments =
+            with_thread_local_font_context(self.layout_context, |font_context| {
+                TextRunScanner::new().scan_for_runs(font_context, fragments)
+            });
+        let mut inline_flow =
+            InlineFlow::from_fragments(scanned_fragments, block_fragment.style.writing_mode);
+        inline_flow.minimum_line_metrics =
+            with_thread_local_font_context(self.layout_context, |font_context| {
+                inline_flow.minimum_line_metrics(font_context, &block_fragment.style)
+            });
+        let mut inline_flow_ref = FlowRef::new(Arc::new(inline_flow));
+        inline_flow_ref.finish();
+
+        let mut flow = FlowRef::new(Arc::new(BlockFlow::from_fragment_and_float_kind(
+            block_fragment,
+            Some(float_kind),
+        )));
+        flow.add_new_child(inline_flow_ref);
+        flow.finish();
+        flow
+    }
+
     /// Builds the fragment for the given block or subclass thereof.
     fn build_fragment_for_block(&self, node: &ConcreteThreadSafeLayoutNode) -> Fragment {
         let specific_fragment_info = match node.type_id() {
@@ -434,7 +511,7 @@ impl<'a, ConcreteThreadSafeLayoutNode: ThreadSafeLayoutNode>
             },
             Some(LayoutNodeType::Element(LayoutElementType::SVGSVGElement)) => {
                 let data = node.svg_data().unwrap();
-                SpecificFragmentInfo::Svg(Box::new(SvgFragmentInfo::new(data)))
+                SpecificFragmentInfo::Svg(Box::new(SvgFragmentInfo::new(node, data)))
             },
             _ => {
                 // This includes pseudo-elements.
@@ -472,6 +549,50 @@ impl<'a, ConcreteThreadSafeLayoutNode: ThreadSafeLayoutNode>
             return;
         }
 
+        // `::first-letter` and `::first-line` only apply to the first formatted line of the block,
+        // which can only be in its first inline flow.
+        let block_style = node.style(self.style_context());
+        let mut first_line_style = None;
+        if flow.base().children.is_empty() {
+            let mut first_letter_style = None;
+            if let Some(style) = self.eager_pseudo_style(node, &PseudoElement::FirstLetter) {
+                let first_letter = split_first_letter::<
+                    ConcreteThreadSafeLayoutNode::ConcreteElement,
+                >(self.style_context(), &mut fragments.fragments, style);
+                if let Some(first_letter) = first_letter {
+                    let float_kind = FloatKind::from_property(first_letter.style.get_box().float);
+                    match float_kind {
+                        Some(float_kind) => {
+                            let float_flow =
+                                self.build_flow_for_floated_first_letter(first_letter, float_kind);
+                            legalizer.add_child::<ConcreteThreadSafeLayoutNode::ConcreteElement>(
+                                self.style_context(),
+                                flow,
+                                float_flow,
+                            );
+                            strip_ignorable_whitespace_from_start(&mut fragments.fragments);
+                        },
+                        None => {
+                            if first_letter.inline_context.is_none() {
+                                first_letter_style = Some(first_letter.style.clone());
+                            }
+                            fragments.fragments.push_front(first_letter)
+                        },
+                    }
+                }
+            }
+            first_line_style = self
+                .eager_pseudo_style(node, &PseudoElement::FirstLine)
+                .map(|style| FirstLineStyle {
+                    style: style,
+                    first_letter_style: first_letter_style,
+                });
+        }
+        if fragments.fragments.is_empty() {
+            absolute_descendants.push_descendants(fragments.absolute_descendants);
+            return;
+        }
+
         // Build a list of all the inline-block fragments before fragments is moved.
         let mut inline_block_flows = vec![];
         for fragment in &fragments.fragments {
@@ -499,10 +620,10 @@ impl<'a, ConcreteThreadSafeLayoutNode: ThreadSafeLayoutNode>
                     mem::replace(&mut fragments.fragments, LinkedList::new()),
                 )
             });
-        let mut inline_flow_ref = FlowRef::new(Arc::new(InlineFlow::from_fragments(
-            scanned_fragments,
-            node.style(self.style_context()).writing_mode,
-        )));
+        let mut inline_flow =
+            InlineFlow::from_fragments(scanned_fragments, block_style.writing_mode);
+        inline_flow.first_line_style = first_line_style;
+        let mut inline_flow_ref = FlowRef::new(Arc::new(inline_flow));
 
         // Add all the inline-block fragments as children of the inline flow.
         for inline_block_flow in &inline_block_flows {
@@ -1109,7 +1230,8 @@ impl<'a, ConcreteThreadSafeLayoutNode: ThreadSafeLayoutNode>
         ConstructionResult::ConstructionItem(construction_item)
     }
 
-    /// Build the fragment for an inline-block or inline-flex, based on the `display` flag
+    /// Build the fragment for an inline-block, inline-flex or inline-grid, based on the `display`
+    /// flag
     fn build_fragment_for_inline_block_or_inline_flex(
         &mut self,
         node: &ConcreteThreadSafeLayoutNode,
@@ -1118,7 +1240,8 @@ impl<'a, ConcreteThreadSafeLayoutNode: ThreadSafeLayoutNode>
         let block_flow_result = match display {
             Display::InlineBlock => self.build_flow_for_block(node, None),
             Display::InlineFlex => self.build_flow_for_flex(node, None),
-            _ => panic!("The flag should be inline-block or inline-flex"),
+            Display::InlineGrid => self.build_flow_for_grid(node, None),
+            _ => panic!("The flag should be inline-block, inline-flex or inline-grid"),
         };
         let (block_flow, abs_descendants) = match block_flow_result {
             ConstructionResult::Flow(block_flow, abs_descendants) => (block_flow, abs_descendants),
@@ -1477,10 +1600,9 @@ impl<'a, ConcreteThreadSafeLayoutNode: ThreadSafeLayoutNode>
                     node,
                     &self.layout_context,
                 ));
-                vec![Fragment::new(
+                vec![self.build_fragment_for_list_marker(
                     node,
                     SpecificFragmentInfo::Image(image_info),
-                    self.layout_context,
                 )]
             },
             ImageUrlOrNone::None => match ListStyleTypeContent::from_list_style_type(
@@ -1490,12 +1612,11 @@ impl<'a, ConcreteThreadSafeLayoutNode: ThreadSafeLayoutNode>
                 ListStyleTypeContent::StaticText(ch) => {
                     let text = format!("{}\u{a0}", ch);
                     let mut unscanned_marker_fragments = LinkedList::new();
-                    unscanned_marker_fragments.push_back(Fragment::new(
+                    unscanned_marker_fragments.push_back(self.build_fragment_for_list_marker(
                         node,
                         SpecificFragmentInfo::UnscannedText(Box::new(
                             UnscannedTextFragmentInfo::new(Box::<str>::from(text), None),
                         )),
-                        self.layout_context,
                     ));
                     let marker_fragments =
                         with_thread_local_font_context(self.layout_context, |mut font_context| {
@@ -1504,11 +1625,12 @@ impl<'a, ConcreteThreadSafeLayoutNode: ThreadSafeLayoutNode>
                         });
                     marker_fragments.fragments
                 },
-                ListStyleTypeContent::GeneratedContent(info) => vec![Fragment::new(
-                    node,
-                    SpecificFragmentInfo::GeneratedContent(info),
-                    self.layout_context,
-                )],
+                ListStyleTypeContent::GeneratedContent(info) => {
+                    vec![self.build_fragment_for_list_marker(
+                        node,
+                        SpecificFragmentInfo::GeneratedContent(info),
+                    )]
+                },
             },
         };
 
@@ -1614,6 +1736,17 @@ impl<'a, ConcreteThreadSafeLayoutNode: ThreadSafeLayoutNode>
         self.build_flow_for_block_like(flow, node)
     }
 
+    /// Builds a flow for a node with 'display: grid'.
+    fn build_flow_for_grid(
+        &mut self,
+        node: &ConcreteThreadSafeLayoutNode,
+        float_kind: Option<FloatKind>,
+    ) -> ConstructionResult {
+        let fragment = self.build_fragment_for_block(node);
+        let flow = FlowRef::new(Arc::new(GridFlow::from_fragment(fragment, float_kind)));
+        self.build_flow_for_block_like(flow, node)
+    }
+
     /// Attempts to perform incremental repair to account for recent changes to this node. This
     /// can fail and return false, indicating that flows will need to be reconstructed.
     ///
@@ -1921,6 +2054,19 @@ where
                 self.set_flow_construction_result(node, construction_result)
             },
 
+            // Grid items contribute grid flow construction results.
+            (Display::Grid, float_value, _) => {
+                let float_kind = FloatKind::from_property(float_value);
+                let construction_result = self.build_flow_for_grid(node, float_kind);
+                self.set_flow_construction_result(node, construction_result)
+            },
+
+            (Display::InlineGrid, _, _) => {
+                let construction_result =
+                    self.build_fragment_for_inline_block_or_inline_flex(node, Display::InlineGrid);
+                self.set_flow_construction_result(node, construction_result)
+            },
+
             // Block flows that are not floated contribute block flow construction results.
             //
             // TODO(pcwalton): Make this only trigger for blocks and handle the other `display`
@@ -2138,6 +2284,110 @@ pub fn strip_ignorable_whitespace_from_end(this: &mut LinkedList<Fragment>) {
     this.append(&mut trailing_fragments_consisting_of_solely_bidi_control_characters);
 }
 
+/// Splits the first letter of the text at the start of a block, along with its surrounding
+/// punctuation, off the first fragment of the given list, and returns it as a fragment of its own
+/// with the given `::first-letter` style. The caller places the returned fragment, since a floated
+/// first letter leaves the line.
+///
+/// When the text is inside inline descendants of the block, the first letter stays inside them and
+/// inherits from the innermost one. Generated content is not considered.
+fn split_first_letter<E>(
+    context: &SharedStyleContext,
+    this: &mut LinkedList<Fragment>,
+    first_letter_style: ServoArc<ComputedValues>,
+) -> Option<Fragment>
+where
+    E: TElement,
+{
+    let (mut first_letter, rest_is_empty) = {
+        let fragment = match this.front_mut() {
+            Some(fragment) => fragment,
+            None => return None,
+        };
+        if fragment.pseudo != PseudoElementType::Normal {
+            return None;
+        }
+
+        let (first_letter, rest) = match fragment.specific {
+            SpecificFragmentInfo::UnscannedText(ref info) if info.selection.is_none() => {
+                match first_letter_length(&info.text) {
+                    Some(length) => (
+                        Box::<str>::from(&info.text[..length]),
+                        Box::<str>::from(&info.text[length..]),
+                    ),
+                    None => return None,
+                }
+            },
+            _ => return None,
+        };
+
+        // The style was cascaded with the block as its parent, so cascade it again for text inside
+        // inline descendants.
+        let style = match fragment.inline_context {
+            Some(ref inline_context) if !inline_context.nodes.is_empty() => context
+                .stylist
+                .compute_pseudo_element_style_with_inputs::<E>(
+                    CascadeInputs::new_from_style(&first_letter_style),
+                    &PseudoElement::FirstLetter,
+                    &context.guards,
+                    Some(&inline_context.nodes[0].style),
+                    &ServoMetricsProvider,
+                    None,
+                ),
+            _ => first_letter_style,
+        };
+        let mut first_letter = fragment.create_similar_anonymous_fragment(
+            style,
+            SpecificFragmentInfo::UnscannedText(Box::new(UnscannedTextFragmentInfo::new(
+                first_letter,
+                None,
+            ))),
+        );
+        first_letter.inline_context = fragment.inline_context.clone();
+        let rest_is_empty = rest.is_empty();
+        fragment.specific =
+            SpecificFragmentInfo::UnscannedText(Box::new(UnscannedTextFragmentInfo::new(
+                rest, None,
+            )));
+        (first_letter, rest_is_empty)
+    };
+
+    if rest_is_empty {
+        this.pop_front();
+    } else if first_letter.style.get_box().float == Float::None {
+        // The inline descendants now start with the first letter and end after the rest.
+        if let Some(ref mut inline_context) = first_letter.inline_context {
+            for node in &mut inline_context.nodes {
+                node.flags
+                    .remove(InlineFragmentNodeFlags::LAST_FRAGMENT_OF_ELEMENT);
+            }
+        }
+        if let Some(ref mut inline_context) = this.front_mut().unwrap().inline_context {
+            for node in &mut inline_context.nodes {
+                node.flags
+                    .remove(InlineFragmentNodeFlags::FIRST_FRAGMENT_OF_ELEMENT);
+            }
+        }
+    }
+    Some(first_letter)
+}
+
+/// Returns the length in bytes of the first letter of `text` and the punctuation around it, as
+/// defined in CSS Pseudo-Elements § 2.2, or `None` if the text doesn't start with a letter.
+fn first_letter_length(text: &str) -> Option<usize> {
+    let is_punctuation = |c: char| !c.is_alphanumeric() && !c.is_whitespace();
+    let mut chars = text.char_indices().skip_while(|&(_, c)| is_punctuation(c));
+    match chars.next() {
+        Some((_, c)) if !c.is_whitespace() => {},
+        _ => return None,
+    }
+    Some(
+        chars
+            .find(|&(_, c)| !is_punctuation(c))
+            .map_or(text.len(), |(index, _)| index),
+    )
+}
+
 /// If the 'unicode-bidi' property has a value other than 'normal', return the bidi control codes
 /// to inject before and after the text content of the element.
 fn bidi_control_chars(style: &ServoArc<ComputedValues>) -> Option<(&'static str, &'static str)> {
@@ -2348,6 +2598,40 @@ impl Legalizer {
                 true
             },
 
+            (FlowClass::Grid, FlowClass::Inline) => {
+                FlowRef::deref_mut(child)
+                    .mut_base()
+                    .flags
+                    .insert(FlowFlags::MARGINS_CANNOT_COLLAPSE);
+                let mut block_wrapper = Legalizer::create_anonymous_flow::<E, _>(
+                    context,
+                    parent,
+                    &[PseudoElement::ServoAnonymousBlock],
+                    SpecificFragmentInfo::Generic,
+                    BlockFlow::from_fragment,
+                );
+
+                {
+                    let block = FlowRef::deref_mut(&mut block_wrapper).as_mut_block();
+                    block.base.flags.insert(FlowFlags::MARGINS_CANNOT_COLLAPSE);
+                    block.fragment.flags.insert(FragmentFlags::IS_GRID_ITEM);
+                }
+                block_wrapper.add_new_child((*child).clone());
+                block_wrapper.finish();
+                parent.add_new_child(block_wrapper);
+                true
+            },
+
+            (FlowClass::Grid, _) => {
+                {
+                    let block = FlowRef::deref_mut(child).as_mut_block();
+                    block.base.flags.insert(FlowFlags::MARGINS_CANNOT_COLLAPSE);
+                    block.fragment.flags.insert(FragmentFlags::IS_GRID_ITEM);
+                }
+                parent.add_new_child((*child).clone());
+                true
+            },
+
             _ => {
                 parent.add_new_child((*child).clone());
                 true
@@ -10,6 +10,7 @@ use gfx::font_cache_thread::FontCacheThread;
 use gfx::font_context::FontContext;
 use malloc_size_of::{MallocSizeOf, MallocSizeOfOps};
 use msg::constellation_msg::PipelineId;
+use net_traits::image::base::Image;
 use net_traits::image_cache::{CanRequestImages, ImageCache, ImageState};
 use net_traits::image_cache::{ImageOrMetadataAvailable, UsePlaceholder};
 use opaque_node::OpaqueNodeMethods;
@@ -26,6 +27,8 @@ use std::sync::{Arc, Mutex};
 use std::thread;
 use style::context::RegisteredSpeculativePainter;
 use style::context::SharedStyleContext;
+use svg::SvgImages;
+use webrender_api::ImageKey;
 
 pub type LayoutFontContext = FontContext<FontCacheThread>;
 
@@ -76,6 +79,18 @@ pub struct LayoutContext<'a> {
         >,
     >,
 
+    /// The times at which the animated images painted by this layout thread
+    /// started animating, keyed by the key of their first frame.
+    pub animated_images: Arc<RwLock<HashMap<ImageKey, f64, BuildHasherDefault<FnvHasher>>>>,
+
+    /// The earliest time at which one of the animated images painted by this
+    /// layout shows its next frame.
+    pub next_image_frame_at: Mutex<Option<f64>>,
+
+    /// The images the inline `<svg>` elements painted by this layout thread
+    /// were rasterized into.
+    pub svg_images: Arc<Mutex<SvgImages>>,
+
     /// Paint worklets
     pub registered_painters: &'a RegisteredPainters,
 
@@ -177,8 +192,12 @@ impl<'a> LayoutContext<'a> {
 
         match self.get_or_request_image_or_meta(node, url.clone(), use_placeholder) {
             Some(ImageOrMetadataAvailable::ImageAvailable(image, _)) => {
-                let image_info = WebRenderImageInfo::from_image(&*image);
-                if image_info.key.is_none() {
+                let image_info = WebRenderImageInfo {
+                    key: self.image_key_for_painting(&*image),
+                    ..WebRenderImageInfo::from_image(&*image)
+                };
+                // The key of an animated image changes with each of its frames.
+                if image_info.key.is_none() || image.is_animated() {
                     Some(image_info)
                 } else {
                     let mut webrender_image_cache = self.webrender_image_cache.write();
@@ -189,6 +208,33 @@ impl<'a> LayoutContext<'a> {
             None | Some(ImageOrMetadataAvailable::MetadataAvailable(_)) => None,
         }
     }
+
+    /// Returns the key of the frame of the given image to paint now. Animated
+    /// images start animating the first time they are painted.
+    pub fn image_key_for_painting(&self, image: &Image) -> Option<ImageKey> {
+        let first_frame_key = image.id?;
+        if !image.is_animated() {
+            return Some(first_frame_key);
+        }
+
+        let now = self.style_context.timer.seconds();
+        let started_at = *self
+            .animated_images
+            .write()
+            .entry(first_frame_key)
+            .or_insert(now);
+        let (index, remaining) = image.frame_at(((now - started_at) * 1000.) as u64);
+
+        // Images whose animation is over don't need painting again.
+        if let Some(remaining) = remaining {
+            let next_frame_at = now + remaining as f64 / 1000.;
+            let mut next_image_frame_at = self.next_image_frame_at.lock().unwrap();
+            if next_image_frame_at.map_or(true, |time| next_frame_at < time) {
+                *next_image_frame_at = Some(next_frame_at);
+            }
+        }
+        image.frames[index].id
+    }
 }
 
 /// A registered painter
@@ -28,7 +28,7 @@ use display_list::items::{IframeDisplayItem, LineDisplayItem, OpaqueNode};
 use display_list::items::{PopAllTextShadowsDisplayItem, PushTextShadowDisplayItem};
 use display_list::items::{StackingContext, StackingContextType, StickyFrameData};
 use display_list::items::{TextOrientation, WebRenderImageInfo};
-use euclid::{rect, Point2D, Rect, SideOffsets2D, Size2D, TypedSize2D, Vector2D};
+use euclid::{rect, Point2D, Rect, SideOffsets2D, Size2D, Transform2D, TypedSize2D, Vector2D};
 use flex::FlexFlow;
 use flow::{BaseFlow, Flow, FlowFlags};
 use flow_ref::FlowRef;
@@ -38,6 +38,7 @@ use fragment::SpecificFragmentInfo;
 use gfx::text::TextRun;
 use gfx::text::glyph::ByteIndex;
 use gfx_traits::{combine_id_with_fragment_type, FragmentType, StackingContextId};
+use grid::GridFlow;
 use inline::{InlineFlow, InlineFragmentNodeFlags};
 use ipc_channel::ipc;
 use list_item::ListItemFlow;
@@ -1888,8 +1889,7 @@ impl FragmentDisplayListBuilding for Fragment {
             SpecificFragmentInfo::InlineBlock(_) |
             SpecificFragmentInfo::InlineAbsoluteHypothetical(_) |
             SpecificFragmentInfo::InlineAbsolute(_) |
-            SpecificFragmentInfo::TruncatedFragment(_) |
-            SpecificFragmentInfo::Svg(_) => {
+            SpecificFragmentInfo::TruncatedFragment(_) => {
                 if opts::get().show_debug_fragment_borders {
                     self.build_debug_borders_around_fragment(
                         state,
@@ -1926,7 +1926,7 @@ impl FragmentDisplayListBuilding for Fragment {
             SpecificFragmentInfo::Image(ref image_fragment) => {
                 // Place the image into the display list.
                 if let Some(ref image) = image_fragment.image {
-                    if let Some(id) = image.id {
+                    if let Some(id) = state.layout_context.image_key_for_painting(image) {
                         let base = create_base_display_item(state);
                         state.add_image_item(
                             base,
@@ -1945,6 +1945,67 @@ impl FragmentDisplayListBuilding for Fragment {
                     }
                 }
             },
+            SpecificFragmentInfo::Svg(ref svg_fragment) => {
+                if stacking_relative_content_box.is_empty() {
+                    return;
+                }
+
+                let layout_context = state.layout_context;
+                let content = &svg_fragment.content;
+                let size = Size2D::new(
+                    stacking_relative_content_box.size.width.to_f32_px(),
+                    stacking_relative_content_box.size.height.to_f32_px(),
+                );
+                let image_key = layout_context.svg_images.lock().unwrap().image_key(
+                    self.node,
+                    content,
+                    size,
+                    layout_context.style_context.device_pixel_ratio().get(),
+                    &*layout_context.image_cache,
+                );
+                if let Some(image_key) = image_key {
+                    let base = create_base_display_item(state);
+                    state.add_image_item(
+                        base,
+                        webrender_api::ImageDisplayItem {
+                            image_key,
+                            stretch_size: stacking_relative_content_box.size.to_layout(),
+                            tile_spacing: LayoutSize::zero(),
+                            image_rendering: ImageRendering::Auto,
+                            alpha_type: webrender_api::AlphaType::PremultipliedAlpha,
+                        },
+                    );
+                }
+
+                // Text isn't rasterized with the shapes, but painted over them.
+                let origin = stacking_relative_content_box.origin;
+                let transform = content.transform(size).post_mul(
+                    &Transform2D::create_translation(origin.x.to_f32_px(), origin.y.to_f32_px()),
+                );
+                for text in &content.texts {
+                    let (run, baseline_origin) = match text.shape(layout_context, &transform) {
+                        Some(shaped_text) => shaped_text,
+                        None => continue,
+                    };
+                    let font_key = run.font_key;
+                    let range = Range::new(ByteIndex(0), ByteIndex(run.text.len() as isize));
+                    let glyphs = convert_text_run_to_glyphs(Arc::new(run), range, baseline_origin);
+                    if glyphs.is_empty() {
+                        continue;
+                    }
+
+                    let base = create_base_display_item(state);
+                    state.add_display_item(DisplayItem::Text(CommonDisplayItem::with_data(
+                        base,
+                        webrender_api::TextDisplayItem {
+                            font_key,
+                            color: text.color.to_layout(),
+                            glyph_options: None,
+                        },
+                        glyphs,
+                    )));
+                }
+            },
             SpecificFragmentInfo::Canvas(ref canvas_fragment_info) => {
                 let image_key = match canvas_fragment_info.source {
                     CanvasFragmentSource::WebGL(image_key) => image_key,
@@ -3047,7 +3108,18 @@ impl InlineFlowDisplayListBuilding for InlineFlow {
                 state.current_stacking_context_id = stacking_context_id;
             }
 
-            self.build_display_list_for_inline_fragment_at_index(state, index);
+            // Text on the first line is painted with the `::first-line` style, if any.
+            match self.first_line_style_for_fragment(index) {
+                Some(first_line_style) => {
+                    let style = mem::replace(
+                        &mut self.fragments.fragments[index].style,
+                        first_line_style,
+                    );
+                    self.build_display_list_for_inline_fragment_at_index(state, index);
+                    self.fragments.fragments[index].style = style;
+                },
+                None => self.build_display_list_for_inline_fragment_at_index(state, index),
+            }
 
             if establishes_stacking_context {
                 state.current_stacking_context_id = parent_stacking_context_id
@@ -3100,6 +3172,18 @@ impl FlexFlowDisplayListBuilding for FlexFlow {
     }
 }
 
+pub trait GridFlowDisplayListBuilding {
+    fn build_display_list_for_grid(&mut self, state: &mut DisplayListBuildState);
+}
+
+impl GridFlowDisplayListBuilding for GridFlow {
+    fn build_display_list_for_grid(&mut self, state: &mut DisplayListBuildState) {
+        // Draw the rest of the block.
+        self.as_mut_block()
+            .build_display_list_for_block(state, BorderPaintingMode::Separate)
+    }
+}
+
 trait BaseFlowDisplayListBuilding {
     fn build_display_items_for_debugging_tint(
         &self,
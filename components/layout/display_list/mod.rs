@@ -6,6 +6,7 @@ pub use self::builder::BlockFlowDisplayListBuilding;
 pub use self::builder::BorderPaintingMode;
 pub use self::builder::DisplayListBuildState;
 pub use self::builder::FlexFlowDisplayListBuilding;
+pub use self::builder::GridFlowDisplayListBuilding;
 pub use self::builder::IndexableText;
 pub use self::builder::InlineFlowDisplayListBuilding;
 pub use self::builder::ListItemFlowDisplayListBuilding;
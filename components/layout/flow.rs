@@ -38,6 +38,7 @@ use flow_ref::{FlowRef, WeakFlowRef};
 use fragment::{CoordinateSystem, Fragment, FragmentBorderBoxIterator, Overflow};
 use gfx_traits::StackingContextId;
 use gfx_traits::print_tree::PrintTree;
+use grid::GridFlow;
 use inline::InlineFlow;
 use model::{CollapsibleMargins, IntrinsicISizes, MarginCollapseInfo};
 use multicol::MulticolFlow;
@@ -134,6 +135,16 @@ pub trait Flow: HasBaseFlow + fmt::Debug + Sync + Send + 'static {
         panic!("called as_mut_flex() on a non-flex flow")
     }
 
+    /// If this is a grid flow, returns the underlying object. Fails otherwise.
+    fn as_grid(&self) -> &GridFlow {
+        panic!("called as_grid() on a non-grid flow")
+    }
+
+    /// If this is a grid flow, returns the underlying object, borrowed mutably. Fails otherwise.
+    fn as_mut_grid(&mut self) -> &mut GridFlow {
+        panic!("called as_mut_grid() on a non-grid flow")
+    }
+
     /// If this is an inline flow, returns the underlying object. Fails otherwise.
     fn as_inline(&self) -> &InlineFlow {
         panic!("called as_inline() on a non-inline flow")
@@ -618,6 +629,7 @@ pub enum FlowClass {
     Multicol,
     MulticolColumn,
     Flex,
+    Grid,
 }
 
 impl FlowClass {
@@ -631,7 +643,8 @@ impl FlowClass {
             FlowClass::TableCaption |
             FlowClass::TableCell |
             FlowClass::TableWrapper |
-            FlowClass::Flex => true,
+            FlowClass::Flex |
+            FlowClass::Grid => true,
             _ => false,
         }
     }
@@ -63,6 +63,7 @@ use style::values::computed::{Length, LengthOrPercentage, LengthOrPercentageOrAu
 use style::values::computed::counters::ContentItem;
 use style::values::generics::box_::{Perspective, VerticalAlign};
 use style::values::generics::transform;
+use svg::SvgContent;
 use text;
 use text::TextRunScanner;
 use webrender_api::{self, LayoutTransform};
@@ -368,13 +369,16 @@ impl CanvasFragmentInfo {
 pub struct SvgFragmentInfo {
     pub dom_width: Au,
     pub dom_height: Au,
+    pub content: Arc<SvgContent>,
 }
 
 impl SvgFragmentInfo {
-    pub fn new(data: SVGSVGData) -> SvgFragmentInfo {
+    pub fn new<N: ThreadSafeLayoutNode>(node: &N, data: SVGSVGData) -> SvgFragmentInfo {
+        let viewport = Size2D::new(data.width as f32, data.height as f32);
         SvgFragmentInfo {
             dom_width: Au::from_px(data.width as i32),
             dom_height: Au::from_px(data.height as i32),
+            content: Arc::new(SvgContent::new(node, viewport)),
         }
     }
 }
@@ -3318,6 +3322,8 @@ bitflags! {
         const IS_BLOCK_FLEX_ITEM = 0b0000_0010;
         /// Whether this fragment represents the generated text from a text-overflow clip.
         const IS_ELLIPSIS = 0b0000_0100;
+        /// Whether this fragment represents a child in a grid container.
+        const IS_GRID_ITEM = 0b0000_1000;
     }
 }
 
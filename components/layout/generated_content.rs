@@ -191,8 +191,17 @@ impl<'a, 'b> ResolveGeneratedContentFragmentMutator<'a, 'b> {
             self.reset_and_increment_counters_as_necessary(fragment);
         }
 
+        // List markers may have a `::marker` style of their own, which inherits the
+        // `list-style-type` of the list item but not its `display`.
+        let is_list_marker = match fragment.specific {
+            SpecificFragmentInfo::GeneratedContent(ref info) => match **info {
+                GeneratedContentInfo::ListItem => true,
+                _ => false,
+            },
+            _ => false,
+        };
         let mut list_style_type = fragment.style().get_list().list_style_type;
-        if fragment.style().get_box().display != Display::ListItem {
+        if !is_list_marker && fragment.style().get_box().display != Display::ListItem {
             list_style_type = ListStyleType::None
         }
 
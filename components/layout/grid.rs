@@ -0,0 +1,1632 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Layout for elements with a CSS `display` property of `grid`.
+
+#![deny(unsafe_code)]
+
+use app_units::Au;
+use block::{AbsoluteAssignBSizesTraversal, BlockFlow};
+use context::LayoutContext;
+use display_list::{DisplayListBuildState, GridFlowDisplayListBuilding};
+use display_list::StackingContextCollectionState;
+use euclid::Point2D;
+use floats::FloatKind;
+use flow::{Flow, FlowClass, GetBaseFlow, OpaqueFlow, FlowFlags};
+use fragment::{Fragment, FragmentBorderBoxIterator, Overflow};
+use layout_debug;
+use model::{AdjoiningMargins, CollapsibleMargins, SizeConstraint};
+use servo_atoms::Atom;
+use std::cmp::{max, min};
+use std::ops::Range;
+use style::computed_values::align_content::T as AlignContent;
+use style::computed_values::align_items::T as AlignItems;
+use style::computed_values::align_self::T as AlignSelf;
+use style::computed_values::justify_content::T as JustifyContent;
+use style::logical_geometry::{Direction, LogicalSize};
+use style::properties::ComputedValues;
+use style::servo::restyle_damage::ServoRestyleDamage;
+use style::values::{CustomIdent, Either};
+use style::values::computed::{GridLine, LengthOrPercentage};
+use style::values::computed::{LengthOrPercentageOrAuto, TrackBreadth, TrackList, TrackSize};
+use style::values::computed::length::NonNegativeLengthOrPercentageOrNormal;
+use style::values::generics::grid::{GridTemplateComponent, TrackBreadth as GenericTrackBreadth};
+use style::values::generics::grid::{TrackKeyword, TrackListType, TrackListValue};
+use style::values::generics::grid::{TrackRepeat, TrackSize as GenericTrackSize};
+use style::values::specified::position::AutoFlow;
+use traversal::PreorderFlowTraversal;
+
+/// The largest number of repetitions an `auto-fill` or `auto-fit` repeat can expand into.
+const MAX_AUTO_REPETITIONS: i32 = 10000;
+
+/// The largest line number, positive or negative, a grid item can be placed at. Placements
+/// beyond it are clamped, so that a huge line number doesn't make the implicit grid huge.
+///
+/// <https://drafts.csswg.org/css-grid/#overlarge-grids>
+const MAX_GRID_LINE: i32 = 10000;
+
+fn clamp_grid_line(line: i32) -> i32 {
+    max(min(line, MAX_GRID_LINE), -MAX_GRID_LINE)
+}
+
+/// The minimum track sizing function of a grid track, resolved against the grid container.
+///
+/// <https://drafts.csswg.org/css-grid/#min-track-sizing-function>
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+enum MinTrackSizing {
+    Fixed(Au),
+    MinContent,
+    MaxContent,
+    Auto,
+}
+
+impl MinTrackSizing {
+    fn from_breadth(breadth: &TrackBreadth, containing_size: Option<Au>) -> MinTrackSizing {
+        match *breadth {
+            GenericTrackBreadth::Breadth(ref length) => {
+                match resolve_length(length, containing_size) {
+                    Some(length) => MinTrackSizing::Fixed(length),
+                    None => MinTrackSizing::Auto,
+                }
+            },
+            // A flexible minimum sizing function is treated as `auto`.
+            GenericTrackBreadth::Fr(_) => MinTrackSizing::Auto,
+            GenericTrackBreadth::Keyword(TrackKeyword::Auto) => MinTrackSizing::Auto,
+            GenericTrackBreadth::Keyword(TrackKeyword::MinContent) => MinTrackSizing::MinContent,
+            GenericTrackBreadth::Keyword(TrackKeyword::MaxContent) => MinTrackSizing::MaxContent,
+        }
+    }
+
+    fn is_intrinsic(&self) -> bool {
+        match *self {
+            MinTrackSizing::Fixed(_) => false,
+            _ => true,
+        }
+    }
+}
+
+/// The maximum track sizing function of a grid track, resolved against the grid container.
+///
+/// <https://drafts.csswg.org/css-grid/#max-track-sizing-function>
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+enum MaxTrackSizing {
+    Fixed(Au),
+    MinContent,
+    MaxContent,
+    Auto,
+    FitContent(Au),
+    Flex(f32),
+}
+
+impl MaxTrackSizing {
+    fn from_breadth(breadth: &TrackBreadth, containing_size: Option<Au>) -> MaxTrackSizing {
+        match *breadth {
+            GenericTrackBreadth::Breadth(ref length) => {
+                match resolve_length(length, containing_size) {
+                    Some(length) => MaxTrackSizing::Fixed(length),
+                    None => MaxTrackSizing::Auto,
+                }
+            },
+            GenericTrackBreadth::Fr(factor) => MaxTrackSizing::Flex(factor),
+            GenericTrackBreadth::Keyword(TrackKeyword::Auto) => MaxTrackSizing::Auto,
+            GenericTrackBreadth::Keyword(TrackKeyword::MinContent) => MaxTrackSizing::MinContent,
+            GenericTrackBreadth::Keyword(TrackKeyword::MaxContent) => MaxTrackSizing::MaxContent,
+        }
+    }
+
+    fn is_intrinsic(&self) -> bool {
+        match *self {
+            MaxTrackSizing::Fixed(_) | MaxTrackSizing::Flex(_) => false,
+            _ => true,
+        }
+    }
+}
+
+/// A row or column of the grid.
+#[derive(Clone, Debug, Serialize)]
+struct GridTrack {
+    min_sizing: MinTrackSizing,
+    max_sizing: MaxTrackSizing,
+    /// The used size of the track once the track sizing algorithm has run.
+    base_size: Au,
+    /// The size the track may grow up to, or `None` while it is infinite.
+    growth_limit: Option<Au>,
+    /// The offset of the start edge of the track from the content edge of the grid container.
+    position: Au,
+}
+
+impl GridTrack {
+    fn new(size: &TrackSize, containing_size: Option<Au>) -> GridTrack {
+        let (min_sizing, max_sizing) = match *size {
+            GenericTrackSize::Breadth(ref breadth) => (
+                MinTrackSizing::from_breadth(breadth, containing_size),
+                MaxTrackSizing::from_breadth(breadth, containing_size),
+            ),
+            GenericTrackSize::Minmax(ref min, ref max) => (
+                MinTrackSizing::from_breadth(min, containing_size),
+                MaxTrackSizing::from_breadth(max, containing_size),
+            ),
+            GenericTrackSize::FitContent(ref limit) => (
+                MinTrackSizing::Auto,
+                match resolve_length(limit, containing_size) {
+                    Some(limit) => MaxTrackSizing::FitContent(limit),
+                    None => MaxTrackSizing::MaxContent,
+                },
+            ),
+        };
+        GridTrack {
+            min_sizing: min_sizing,
+            max_sizing: max_sizing,
+            base_size: Au(0),
+            growth_limit: None,
+            position: Au(0),
+        }
+    }
+
+    fn flex_factor(&self) -> Option<f32> {
+        match self.max_sizing {
+            MaxTrackSizing::Flex(factor) => Some(factor),
+            _ => None,
+        }
+    }
+
+    fn is_flexible(&self) -> bool {
+        self.flex_factor().is_some()
+    }
+
+    /// The size of this track if it doesn't depend on the contents of the grid.
+    fn definite_size(&self) -> Option<Au> {
+        match (self.min_sizing, self.max_sizing) {
+            (MinTrackSizing::Fixed(min_size), MaxTrackSizing::Fixed(max_size)) => {
+                Some(max(min_size, max_size))
+            },
+            _ => None,
+        }
+    }
+
+    /// The size this track is assumed to have when working out the number of repetitions of
+    /// an `auto-fill` or `auto-fit` repeat.
+    ///
+    /// <https://drafts.csswg.org/css-grid/#auto-repeat>
+    fn size_for_auto_repeat(&self) -> Au {
+        match (self.min_sizing, self.max_sizing) {
+            (MinTrackSizing::Fixed(min_size), MaxTrackSizing::Fixed(max_size)) => {
+                max(min_size, max_size)
+            },
+            (_, MaxTrackSizing::Fixed(size)) | (MinTrackSizing::Fixed(size), _) => size,
+            _ => Au(0),
+        }
+    }
+}
+
+/// Resolves a `<length-percentage>` against the size of the grid container, if it's definite.
+fn resolve_length(length: &LengthOrPercentage, containing_size: Option<Au>) -> Option<Au> {
+    match *length {
+        LengthOrPercentage::Length(length) => Some(Au::from(length)),
+        LengthOrPercentage::Percentage(percent) => {
+            containing_size.map(|size| size.scale_by(percent.0))
+        },
+        LengthOrPercentage::Calc(ref calc) => calc.to_used_value(containing_size),
+    }
+}
+
+/// Resolves a `row-gap` or `column-gap` value. `normal` is zero for grid containers.
+fn resolve_gap(gap: &NonNegativeLengthOrPercentageOrNormal, containing_size: Option<Au>) -> Au {
+    match *gap {
+        Either::First(ref length) => resolve_length(&length.0, containing_size).unwrap_or(Au(0)),
+        Either::Second(_normal) => Au(0),
+    }
+}
+
+/// The sum of the sizes of `tracks` and of the gaps between them.
+fn used_size(tracks: &[GridTrack], gap: Au) -> Au {
+    let gaps = gap * max(tracks.len() as i32 - 1, 0);
+    tracks.iter().fold(gaps, |size, track| size + track.base_size)
+}
+
+/// The size of the grid area covering the tracks in `span`, once they have been positioned.
+fn area_size(tracks: &[GridTrack], span: &Range<usize>) -> Au {
+    let last = &tracks[span.end - 1];
+    last.position + last.base_size - tracks[span.start].position
+}
+
+/// The size of the grid area covering the tracks in `span`, if it doesn't depend on the
+/// contents of the grid.
+fn definite_area_size(tracks: &[GridTrack], span: &Range<usize>, gap: Au) -> Option<Au> {
+    let gaps = gap * (span.len() as i32 - 1);
+    tracks[span.clone()]
+        .iter()
+        .fold(Some(gaps), |size, track| match (size, track.definite_size()) {
+            (Some(size), Some(track_size)) => Some(size + track_size),
+            _ => None,
+        })
+}
+
+/// The axes of the grid.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum GridAxis {
+    Row,
+    Column,
+}
+
+/// Appends `names` to the names of the last line in `line_names`.
+fn push_line_names(line_names: &mut Vec<Vec<Atom>>, names: &[CustomIdent]) {
+    line_names
+        .last_mut()
+        .unwrap()
+        .extend(names.iter().map(|name| name.0.clone()));
+}
+
+/// The number of times an `auto-fill` or `auto-fit` repeat is repeated.
+///
+/// <https://drafts.csswg.org/css-grid/#auto-repeat>
+fn auto_repetitions(
+    track_list: &TrackList,
+    repeat: &TrackRepeat<LengthOrPercentage, i32>,
+    containing_size: Option<Au>,
+    gap: Au,
+) -> usize {
+    let containing_size = match containing_size {
+        Some(size) => size,
+        None => return 1,
+    };
+    let track_size =
+        |size: &TrackSize| GridTrack::new(size, Some(containing_size)).size_for_auto_repeat();
+
+    let mut other_size = Au(0);
+    for value in &track_list.values {
+        if let TrackListValue::TrackSize(ref size) = *value {
+            other_size += track_size(size) + gap;
+        }
+    }
+    let repetition_size = repeat
+        .track_sizes
+        .iter()
+        .fold(Au(0), |total, size| total + track_size(size) + gap);
+    if repetition_size <= Au(0) {
+        return 1;
+    }
+
+    // The last track isn't followed by a gap.
+    let repetitions = (containing_size - other_size + gap).0 / repetition_size.0;
+    max(min(repetitions, MAX_AUTO_REPETITIONS), 1) as usize
+}
+
+/// Computes the tracks of the explicit grid in `axis`, along with the names of the lines
+/// around them. There is always one more line than there are tracks.
+///
+/// <https://drafts.csswg.org/css-grid/#explicit-grids>
+fn explicit_grid(
+    style: &ComputedValues,
+    axis: GridAxis,
+    containing_size: Option<Au>,
+    gap: Au,
+) -> (Vec<GridTrack>, Vec<Vec<Atom>>) {
+    let position = style.get_position();
+    let (template, auto_size) = match axis {
+        GridAxis::Row => (&position.grid_template_rows, &position.grid_auto_rows),
+        GridAxis::Column => (&position.grid_template_columns, &position.grid_auto_columns),
+    };
+
+    let mut tracks = vec![];
+    let mut line_names = vec![vec![]];
+    if let GridTemplateComponent::TrackList(ref track_list) = *template {
+        let auto_repeat_index = match track_list.list_type {
+            TrackListType::Auto(index) => Some(index as usize),
+            _ => None,
+        };
+        let mut slot_names = track_list.line_names.iter();
+        let mut values = track_list.values.iter();
+        let mut slot = 0;
+        loop {
+            if let Some(names) = slot_names.next() {
+                push_line_names(&mut line_names, names);
+            }
+            if Some(slot) == auto_repeat_index {
+                if let Some(ref repeat) = track_list.auto_repeat {
+                    for _ in 0..auto_repetitions(track_list, repeat, containing_size, gap) {
+                        let repeated = repeat.track_sizes.iter().zip(repeat.line_names.iter());
+                        for (size, names) in repeated {
+                            push_line_names(&mut line_names, names);
+                            tracks.push(GridTrack::new(size, containing_size));
+                            line_names.push(vec![]);
+                        }
+                        if let Some(names) = repeat.line_names.get(repeat.track_sizes.len()) {
+                            push_line_names(&mut line_names, names);
+                        }
+                    }
+                }
+            } else {
+                match values.next() {
+                    Some(&TrackListValue::TrackSize(ref size)) => {
+                        tracks.push(GridTrack::new(size, containing_size));
+                        line_names.push(vec![]);
+                    },
+                    // Integer repeats are expanded when computing the value.
+                    Some(&TrackListValue::TrackRepeat(_)) => {},
+                    None => break,
+                }
+            }
+            slot += 1;
+        }
+    }
+
+    // Named areas define implicitly-named lines, and may make the explicit grid larger than
+    // its template.
+    //
+    // https://drafts.csswg.org/css-grid/#implicit-named-lines
+    if let Either::First(ref areas) = position.grid_template_areas {
+        let track_count = match axis {
+            GridAxis::Row => areas.0.strings.len(),
+            GridAxis::Column => areas.0.width as usize,
+        };
+        while tracks.len() < track_count {
+            tracks.push(GridTrack::new(auto_size, containing_size));
+            line_names.push(vec![]);
+        }
+        for area in areas.0.areas.iter() {
+            let lines = match axis {
+                GridAxis::Row => &area.rows,
+                GridAxis::Column => &area.columns,
+            };
+            let start = (lines.start - 1) as usize;
+            let end = (lines.end - 1) as usize;
+            line_names[start].push(Atom::from(format!("{}-start", area.name)));
+            line_names[end].push(Atom::from(format!("{}-end", area.name)));
+        }
+    }
+
+    (tracks, line_names)
+}
+
+/// Which edge of its grid area a `<grid-line>` places.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum LineSide {
+    Start,
+    End,
+}
+
+/// The index of the `n`th line named `name`, counting backwards from the end if `n` is
+/// negative. All implicit lines are assumed to have every name.
+///
+/// <https://drafts.csswg.org/css-grid/#grid-placement-int>
+fn nth_named_line(line_names: &[Vec<Atom>], name: &Atom, n: i32) -> i32 {
+    let n = clamp_grid_line(n);
+    let line_count = line_names.len() as i32;
+    let matching: Vec<i32> = line_names
+        .iter()
+        .enumerate()
+        .filter(|&(_, names)| names.contains(name))
+        .map(|(index, _)| index as i32)
+        .collect();
+    let matching_count = matching.len() as i32;
+    if n > 0 {
+        if n <= matching_count {
+            matching[(n - 1) as usize]
+        } else {
+            line_count - 1 + n - matching_count
+        }
+    } else if -n <= matching_count {
+        matching[(matching_count + n) as usize]
+    } else {
+        matching_count + n
+    }
+}
+
+/// Resolves a `<grid-line>` that isn't `auto` or a span to the index of a line of the
+/// explicit grid. Lines before the explicit grid have negative indices.
+///
+/// <https://drafts.csswg.org/css-grid/#line-placement>
+fn resolve_line(line: &GridLine, line_names: &[Vec<Atom>], side: LineSide) -> Option<i32> {
+    if line.is_span {
+        return None;
+    }
+    match (line.ident.as_ref(), line.line_num) {
+        (None, Some(n)) => {
+            let n = clamp_grid_line(n);
+            Some(if n > 0 {
+                n - 1
+            } else {
+                line_names.len() as i32 + n
+            })
+        },
+        (Some(ident), Some(n)) => Some(nth_named_line(line_names, &ident.0, n)),
+        (Some(ident), None) => {
+            let suffix = match side {
+                LineSide::Start => "start",
+                LineSide::End => "end",
+            };
+            let area_line = Atom::from(format!("{}-{}", ident.0, suffix));
+            match line_names.iter().position(|names| names.contains(&area_line)) {
+                Some(index) => Some(index as i32),
+                None => Some(nth_named_line(line_names, &ident.0, 1)),
+            }
+        },
+        (None, None) => None,
+    }
+}
+
+/// Resolves a span `<grid-line>` against the line `from`, searching forwards or backwards.
+///
+/// <https://drafts.csswg.org/css-grid/#grid-placement-span-int>
+fn resolve_span(line: &GridLine, line_names: &[Vec<Atom>], from: i32, side: LineSide) -> i32 {
+    let n = clamp_grid_line(line.line_num.unwrap_or(1));
+    let step = match side {
+        LineSide::Start => -1,
+        LineSide::End => 1,
+    };
+    let ident = match line.ident {
+        Some(ref ident) => ident,
+        None => return from + step * n,
+    };
+
+    let mut remaining = n;
+    let mut index = from;
+    loop {
+        index += step;
+        let has_name = match line_names.get(index as usize) {
+            Some(names) if index >= 0 => names.contains(&ident.0),
+            _ => true,
+        };
+        if has_name {
+            remaining -= 1;
+            if remaining == 0 {
+                return index;
+            }
+        }
+    }
+}
+
+/// The placement of a grid item in one axis, before auto-placement.
+#[derive(Clone, Debug)]
+enum LinePlacement {
+    /// The item lies between these lines of the explicit grid, which may be negative.
+    Definite(i32, i32),
+    /// The item needs to be auto-placed, spanning this many tracks.
+    Auto(usize),
+}
+
+impl LinePlacement {
+    /// <https://drafts.csswg.org/css-grid/#line-placement>
+    fn new(start: &GridLine, end: &GridLine, line_names: &[Vec<Atom>]) -> LinePlacement {
+        match LinePlacement::unclamped(start, end, line_names) {
+            // https://drafts.csswg.org/css-grid/#overlarge-grids
+            LinePlacement::Definite(start_line, end_line) => {
+                let start_line = max(min(start_line, MAX_GRID_LINE - 1), -MAX_GRID_LINE);
+                let end_line = max(min(end_line, MAX_GRID_LINE), start_line + 1);
+                LinePlacement::Definite(start_line, end_line)
+            },
+            LinePlacement::Auto(span) => LinePlacement::Auto(min(span, MAX_GRID_LINE as usize)),
+        }
+    }
+
+    fn unclamped(start: &GridLine, end: &GridLine, line_names: &[Vec<Atom>]) -> LinePlacement {
+        let start_line = resolve_line(start, line_names, LineSide::Start);
+        let end_line = resolve_line(end, line_names, LineSide::End);
+        match (start_line, end_line) {
+            // https://drafts.csswg.org/css-grid/#grid-placement-errors
+            (Some(start_line), Some(end_line)) if start_line < end_line => {
+                LinePlacement::Definite(start_line, end_line)
+            },
+            (Some(start_line), Some(end_line)) if start_line > end_line => {
+                LinePlacement::Definite(end_line, start_line)
+            },
+            (Some(start_line), Some(_)) => LinePlacement::Definite(start_line, start_line + 1),
+            (Some(start_line), None) => {
+                let end_line = if end.is_span {
+                    resolve_span(end, line_names, start_line, LineSide::End)
+                } else {
+                    start_line + 1
+                };
+                LinePlacement::Definite(start_line, end_line)
+            },
+            (None, Some(end_line)) => {
+                let start_line = if start.is_span {
+                    resolve_span(start, line_names, end_line, LineSide::Start)
+                } else {
+                    end_line - 1
+                };
+                LinePlacement::Definite(start_line, end_line)
+            },
+            (None, None) => {
+                // Spans for named lines count as a single track during auto-placement.
+                let span = if start.is_span { start } else { end };
+                if span.is_span && span.ident.is_none() {
+                    LinePlacement::Auto(span.line_num.unwrap_or(1) as usize)
+                } else {
+                    LinePlacement::Auto(1)
+                }
+            },
+        }
+    }
+
+    fn start(&self) -> Option<i32> {
+        match *self {
+            LinePlacement::Definite(start, _) => Some(start),
+            LinePlacement::Auto(_) => None,
+        }
+    }
+
+    /// Turns this placement into a range of tracks of the implicit grid, given the number of
+    /// implicit tracks before the explicit grid.
+    fn to_tracks(&self, leading_tracks: i32) -> TrackPlacement {
+        match *self {
+            LinePlacement::Definite(start, end) => TrackPlacement::Definite(
+                (start + leading_tracks) as usize..(end + leading_tracks) as usize,
+            ),
+            LinePlacement::Auto(span) => TrackPlacement::Auto(span),
+        }
+    }
+}
+
+/// The placement of a grid item in one axis of the implicit grid.
+#[derive(Clone, Debug)]
+enum TrackPlacement {
+    Definite(Range<usize>),
+    Auto(usize),
+}
+
+impl TrackPlacement {
+    fn span(&self) -> usize {
+        match *self {
+            TrackPlacement::Definite(ref range) => range.len(),
+            TrackPlacement::Auto(span) => span,
+        }
+    }
+}
+
+/// The cells of the grid that are already covered by grid items, used by the auto-placement
+/// algorithm. Cells are indexed first by the axis the auto-placement cursor moves across,
+/// then by the axis it moves along.
+struct OccupancyGrid {
+    cells: Vec<Vec<bool>>,
+}
+
+impl OccupancyGrid {
+    fn is_free(&self, across: &Range<usize>, along: &Range<usize>) -> bool {
+        across.clone().all(|i| {
+            along.clone().all(|j| {
+                !self.cells.get(i).and_then(|cells| cells.get(j)).cloned().unwrap_or(false)
+            })
+        })
+    }
+
+    fn occupy(&mut self, across: &Range<usize>, along: &Range<usize>) {
+        for i in across.clone() {
+            while self.cells.len() <= i {
+                self.cells.push(vec![]);
+            }
+            let cells = &mut self.cells[i];
+            while cells.len() < along.end {
+                cells.push(false);
+            }
+            for j in along.clone() {
+                cells[j] = true;
+            }
+        }
+    }
+}
+
+/// An item of a grid container, and the area of the grid it occupies.
+#[derive(Clone, Debug, Serialize)]
+struct GridItem {
+    /// The index of the item's flow among the children of the grid container.
+    index: usize,
+    row: Range<usize>,
+    column: Range<usize>,
+}
+
+/// The result of placing the items of a grid container into its grid.
+struct GridPlacement {
+    items: Vec<GridItem>,
+    rows: Vec<GridTrack>,
+    columns: Vec<GridTrack>,
+}
+
+/// The sizes a grid item contributes to the tracks it spans in one axis.
+///
+/// <https://drafts.csswg.org/css-grid/#algo-content>
+struct TrackContribution {
+    span: Range<usize>,
+    min_content: Au,
+    max_content: Au,
+}
+
+/// The space the tracks of an axis are sized into.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum AvailableSpace {
+    Definite(Au),
+    MinContent,
+    MaxContent,
+}
+
+/// How free space is distributed between and around the tracks of an axis.
+///
+/// <https://drafts.csswg.org/css-align/#distribution-values>
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ContentDistribution {
+    Start,
+    End,
+    Center,
+    SpaceBetween,
+    SpaceAround,
+    Stretch,
+}
+
+impl ContentDistribution {
+    fn from_justify_content(justify_content: JustifyContent) -> ContentDistribution {
+        match justify_content {
+            // There is no `normal` value in Servo yet, so the initial `flex-start` value acts as
+            // `normal`, which behaves as `stretch` in grid containers.
+            JustifyContent::FlexStart | JustifyContent::Stretch => ContentDistribution::Stretch,
+            JustifyContent::FlexEnd => ContentDistribution::End,
+            JustifyContent::Center => ContentDistribution::Center,
+            JustifyContent::SpaceBetween => ContentDistribution::SpaceBetween,
+            JustifyContent::SpaceAround => ContentDistribution::SpaceAround,
+        }
+    }
+
+    fn from_align_content(align_content: AlignContent) -> ContentDistribution {
+        match align_content {
+            AlignContent::Stretch => ContentDistribution::Stretch,
+            AlignContent::FlexStart => ContentDistribution::Start,
+            AlignContent::FlexEnd => ContentDistribution::End,
+            AlignContent::Center => ContentDistribution::Center,
+            AlignContent::SpaceBetween => ContentDistribution::SpaceBetween,
+            AlignContent::SpaceAround => ContentDistribution::SpaceAround,
+        }
+    }
+}
+
+/// Grows the base sizes of the tracks matching `is_affected` equally by `extra_space`.
+///
+/// <https://drafts.csswg.org/css-grid/#extra-space>
+fn distribute_to_base_sizes<F>(tracks: &mut [GridTrack], extra_space: Au, is_affected: F)
+where
+    F: Fn(&GridTrack) -> bool,
+{
+    let affected_count = tracks.iter().filter(|track| is_affected(track)).count() as i32;
+    if extra_space <= Au(0) || affected_count == 0 {
+        return;
+    }
+    for track in tracks.iter_mut().filter(|track| is_affected(track)) {
+        track.base_size += extra_space / affected_count;
+    }
+}
+
+/// Runs the track sizing algorithm for one axis, leaving the used size of each track in its
+/// `base_size`.
+///
+/// <https://drafts.csswg.org/css-grid/#algo-track-sizing>
+fn size_tracks(
+    tracks: &mut [GridTrack],
+    contributions: &[TrackContribution],
+    available_space: AvailableSpace,
+    gap: Au,
+    stretch_auto_tracks: bool,
+) {
+    // https://drafts.csswg.org/css-grid/#algo-init
+    for track in tracks.iter_mut() {
+        track.base_size = match track.min_sizing {
+            MinTrackSizing::Fixed(size) => size,
+            _ => Au(0),
+        };
+        track.growth_limit = match track.max_sizing {
+            MaxTrackSizing::Fixed(size) => Some(max(size, track.base_size)),
+            _ => None,
+        };
+    }
+
+    // https://drafts.csswg.org/css-grid/#algo-content
+    //
+    // Items spanning a single track are handled first.
+    for contribution in contributions.iter().filter(|c| c.span.len() == 1) {
+        let track = &mut tracks[contribution.span.start];
+        match track.min_sizing {
+            MinTrackSizing::MinContent | MinTrackSizing::Auto => {
+                track.base_size = max(track.base_size, contribution.min_content)
+            },
+            MinTrackSizing::MaxContent => {
+                track.base_size = max(track.base_size, contribution.max_content)
+            },
+            MinTrackSizing::Fixed(_) => {},
+        }
+        let limit = match track.max_sizing {
+            MaxTrackSizing::MinContent => Some(contribution.min_content),
+            MaxTrackSizing::MaxContent | MaxTrackSizing::Auto => Some(contribution.max_content),
+            MaxTrackSizing::FitContent(limit) => {
+                Some(max(contribution.min_content, min(limit, contribution.max_content)))
+            },
+            MaxTrackSizing::Fixed(_) | MaxTrackSizing::Flex(_) => None,
+        };
+        if let Some(limit) = limit {
+            track.growth_limit =
+                Some(track.growth_limit.map_or(limit, |growth| max(growth, limit)));
+        }
+    }
+
+    // Then items spanning several tracks, none of them flexible, from the smallest span up.
+    let mut spanning: Vec<&TrackContribution> = contributions
+        .iter()
+        .filter(|c| c.span.len() > 1 && !tracks[c.span.clone()].iter().any(GridTrack::is_flexible))
+        .collect();
+    spanning.sort_by_key(|c| c.span.len());
+    for contribution in spanning {
+        let spanned = &mut tracks[contribution.span.clone()];
+        let gaps = gap * (spanned.len() as i32 - 1);
+
+        let base_sizes = spanned.iter().fold(gaps, |size, track| size + track.base_size);
+        distribute_to_base_sizes(spanned, contribution.min_content - base_sizes, |track| {
+            track.min_sizing.is_intrinsic()
+        });
+
+        let growth_limits = spanned.iter().fold(gaps, |size, track| {
+            size + track.growth_limit.unwrap_or(track.base_size)
+        });
+        let extra_space = contribution.max_content - growth_limits;
+        let affected_count =
+            spanned.iter().filter(|track| track.max_sizing.is_intrinsic()).count() as i32;
+        if extra_space > Au(0) && affected_count > 0 {
+            for track in spanned.iter_mut().filter(|track| track.max_sizing.is_intrinsic()) {
+                let growth_limit = track.growth_limit.unwrap_or(track.base_size);
+                track.growth_limit = Some(growth_limit + extra_space / affected_count);
+            }
+        }
+    }
+
+    // Then items spanning flexible tracks, which only grow the flexible tracks.
+    let spanning_flexible: Vec<&TrackContribution> = contributions
+        .iter()
+        .filter(|c| c.span.len() > 1 && tracks[c.span.clone()].iter().any(GridTrack::is_flexible))
+        .collect();
+    for contribution in spanning_flexible {
+        let spanned = &mut tracks[contribution.span.clone()];
+        let gaps = gap * (spanned.len() as i32 - 1);
+        let base_sizes = spanned.iter().fold(gaps, |size, track| size + track.base_size);
+        distribute_to_base_sizes(spanned, contribution.min_content - base_sizes, |track| {
+            track.is_flexible()
+        });
+    }
+
+    for track in tracks.iter_mut() {
+        let growth_limit = track.growth_limit.unwrap_or(track.base_size);
+        track.growth_limit = Some(max(growth_limit, track.base_size));
+    }
+
+    // https://drafts.csswg.org/css-grid/#algo-grow-tracks
+    match available_space {
+        AvailableSpace::Definite(size) => {
+            let mut free_space = size - used_size(tracks, gap);
+            loop {
+                let growable_count = tracks
+                    .iter()
+                    .filter(|track| Some(track.base_size) < track.growth_limit)
+                    .count() as i32;
+                if growable_count == 0 || free_space / growable_count <= Au(0) {
+                    break;
+                }
+                let share = free_space / growable_count;
+                for track in tracks.iter_mut() {
+                    let growth_limit = track.growth_limit.unwrap();
+                    let growth = min(share, growth_limit - track.base_size);
+                    if growth > Au(0) {
+                        track.base_size += growth;
+                        free_space -= growth;
+                    }
+                }
+            }
+        },
+        AvailableSpace::MaxContent => {
+            for track in tracks.iter_mut() {
+                track.base_size = track.growth_limit.unwrap();
+            }
+        },
+        AvailableSpace::MinContent => {},
+    }
+
+    // https://drafts.csswg.org/css-grid/#algo-flex-tracks
+    if tracks.iter().any(GridTrack::is_flexible) {
+        let flex_fraction = match available_space {
+            AvailableSpace::Definite(size) => {
+                let gaps = gap * max(tracks.len() as i32 - 1, 0);
+                find_fr_size(tracks, size - gaps)
+            },
+            AvailableSpace::MaxContent => {
+                let mut flex_fraction = Au(0);
+                for track in tracks.iter() {
+                    if let Some(factor) = track.flex_factor() {
+                        let size = if factor > 1. {
+                            track.base_size.scale_by(1. / factor)
+                        } else {
+                            track.base_size
+                        };
+                        flex_fraction = max(flex_fraction, size);
+                    }
+                }
+                for contribution in contributions {
+                    let spanned = &tracks[contribution.span.clone()];
+                    if spanned.iter().any(GridTrack::is_flexible) {
+                        let gaps = gap * (spanned.len() as i32 - 1);
+                        let fr_size = find_fr_size(spanned, contribution.max_content - gaps);
+                        flex_fraction = max(flex_fraction, fr_size);
+                    }
+                }
+                flex_fraction
+            },
+            AvailableSpace::MinContent => Au(0),
+        };
+        for track in tracks.iter_mut() {
+            if let Some(factor) = track.flex_factor() {
+                track.base_size = max(track.base_size, flex_fraction.scale_by(factor));
+            }
+        }
+    }
+
+    // https://drafts.csswg.org/css-grid/#algo-stretch
+    if let AvailableSpace::Definite(size) = available_space {
+        let auto_count = tracks
+            .iter()
+            .filter(|track| track.max_sizing == MaxTrackSizing::Auto)
+            .count() as i32;
+        let free_space = size - used_size(tracks, gap);
+        if stretch_auto_tracks && auto_count > 0 && free_space > Au(0) {
+            for track in tracks.iter_mut() {
+                if track.max_sizing == MaxTrackSizing::Auto {
+                    track.base_size += free_space / auto_count;
+                }
+            }
+        }
+    }
+}
+
+/// Finds the size of `1fr` when sizing `tracks` to fill `space_to_fill`.
+///
+/// <https://drafts.csswg.org/css-grid/#algo-find-fr-size>
+fn find_fr_size(tracks: &[GridTrack], space_to_fill: Au) -> Au {
+    let mut inflexible: Vec<bool> = tracks.iter().map(|track| !track.is_flexible()).collect();
+    loop {
+        let mut leftover_space = space_to_fill;
+        let mut flex_factor_sum = 0.;
+        for (track, &is_inflexible) in tracks.iter().zip(inflexible.iter()) {
+            match track.flex_factor() {
+                Some(factor) if !is_inflexible => flex_factor_sum += factor,
+                _ => leftover_space -= track.base_size,
+            }
+        }
+        let fr_size = max(leftover_space, Au(0)).scale_by(1. / f32::max(flex_factor_sum, 1.));
+
+        // Flexible tracks whose base size is larger than their share are treated as
+        // inflexible, and the size is worked out again.
+        let mut restart = false;
+        for (track, is_inflexible) in tracks.iter().zip(inflexible.iter_mut()) {
+            if let Some(factor) = track.flex_factor() {
+                if !*is_inflexible && fr_size.scale_by(factor) < track.base_size {
+                    *is_inflexible = true;
+                    restart = true;
+                }
+            }
+        }
+        if !restart {
+            return fr_size;
+        }
+    }
+}
+
+/// Sets the position of each of `tracks`, distributing `free_space` according to
+/// `distribution`.
+///
+/// <https://drafts.csswg.org/css-align/#content-distribution>
+fn position_tracks(
+    tracks: &mut [GridTrack],
+    free_space: Au,
+    gap: Au,
+    distribution: ContentDistribution,
+) {
+    let track_count = tracks.len() as i32;
+    let (mut position, extra_gap) = match distribution {
+        ContentDistribution::Start | ContentDistribution::Stretch => (Au(0), Au(0)),
+        ContentDistribution::End => (free_space, Au(0)),
+        ContentDistribution::Center => (free_space / 2, Au(0)),
+        ContentDistribution::SpaceBetween if free_space > Au(0) && track_count > 1 => {
+            (Au(0), free_space / (track_count - 1))
+        },
+        ContentDistribution::SpaceBetween => (Au(0), Au(0)),
+        ContentDistribution::SpaceAround if free_space > Au(0) && track_count > 0 => {
+            (free_space / track_count / 2, free_space / track_count)
+        },
+        ContentDistribution::SpaceAround => (free_space / 2, Au(0)),
+    };
+    for track in tracks.iter_mut() {
+        track.position = position;
+        position += track.base_size + gap + extra_gap;
+    }
+}
+
+#[allow(unsafe_code)]
+unsafe impl ::flow::HasBaseFlow for GridFlow {}
+
+/// A block with the CSS `display` property equal to `grid`.
+#[derive(Debug, Serialize)]
+#[repr(C)]
+pub struct GridFlow {
+    /// Data common to all block flows.
+    block_flow: BlockFlow,
+    /// The in-flow children of this grid container and the grid areas they occupy, in
+    /// order-modified document order.
+    items: Vec<GridItem>,
+    /// The rows of the grid, including implicit ones.
+    rows: Vec<GridTrack>,
+    /// The columns of the grid, including implicit ones.
+    columns: Vec<GridTrack>,
+}
+
+impl GridFlow {
+    pub fn from_fragment(fragment: Fragment, flotation: Option<FloatKind>) -> GridFlow {
+        GridFlow {
+            block_flow: BlockFlow::from_fragment_and_float_kind(fragment, flotation),
+            items: Vec::new(),
+            rows: Vec::new(),
+            columns: Vec::new(),
+        }
+    }
+
+    /// The block size of the content box of the grid container, if it's definite.
+    fn explicit_content_block_size(&self, layout_context: &LayoutContext) -> Option<Au> {
+        let box_border = self
+            .block_flow
+            .fragment
+            .box_sizing_boundary(Direction::Block);
+        let parent_container_size = self
+            .block_flow
+            .explicit_block_containing_size(layout_context.shared_context());
+        // https://drafts.csswg.org/css-ui-3/#box-sizing
+        self.block_flow
+            .explicit_block_size(parent_container_size)
+            .map(|size| max(size - box_border, Au(0)))
+    }
+
+    /// Places the in-flow children into the grid, and builds the implicit grid around them.
+    ///
+    /// <https://drafts.csswg.org/css-grid/#auto-placement-algo>
+    fn place_items(&self, inline_size: Option<Au>, block_size: Option<Au>) -> GridPlacement {
+        let style = self.block_flow.fragment.style();
+        let position = style.get_position();
+        let column_gap = resolve_gap(&position.column_gap, inline_size);
+        let row_gap = resolve_gap(&position.row_gap, block_size);
+        let (explicit_rows, row_names) = explicit_grid(style, GridAxis::Row, block_size, row_gap);
+        let (explicit_columns, column_names) =
+            explicit_grid(style, GridAxis::Column, inline_size, column_gap);
+
+        // Resolve the definite positions of each item, in order-modified document order.
+        let mut placements: Vec<(i32, usize, LinePlacement, LinePlacement)> = self
+            .block_flow
+            .base
+            .children
+            .iter()
+            .enumerate()
+            .filter(|&(_, flow)| {
+                !flow
+                    .as_block()
+                    .base
+                    .flags
+                    .contains(FlowFlags::IS_ABSOLUTELY_POSITIONED)
+            }).map(|(index, flow)| {
+                let position = flow.as_block().fragment.style().get_position();
+                let row = LinePlacement::new(
+                    &position.grid_row_start,
+                    &position.grid_row_end,
+                    &row_names,
+                );
+                let column = LinePlacement::new(
+                    &position.grid_column_start,
+                    &position.grid_column_end,
+                    &column_names,
+                );
+                (position.order, index, row, column)
+            }).collect();
+        placements.sort_by_key(|placement| placement.0);
+
+        // Implicit tracks are added before the explicit grid for items placed before its start.
+        let leading_rows = placements
+            .iter()
+            .filter_map(|placement| placement.2.start())
+            .fold(0, |leading, start| max(leading, -start));
+        let leading_columns = placements
+            .iter()
+            .filter_map(|placement| placement.3.start())
+            .fold(0, |leading, start| max(leading, -start));
+
+        // The auto-placement cursor moves along the tracks of one axis, and across to the next
+        // track of the other axis when it reaches the end.
+        let auto_flow = position.grid_auto_flow;
+        let row_flow = auto_flow.autoflow == AutoFlow::Row;
+        let (explicit_along_count, leading_along) = if row_flow {
+            (explicit_columns.len(), leading_columns as usize)
+        } else {
+            (explicit_rows.len(), leading_rows as usize)
+        };
+        let items: Vec<(usize, TrackPlacement, TrackPlacement)> = placements
+            .into_iter()
+            .map(|(_, index, row, column)| {
+                let row = row.to_tracks(leading_rows);
+                let column = column.to_tracks(leading_columns);
+                if row_flow {
+                    (index, row, column)
+                } else {
+                    (index, column, row)
+                }
+            }).collect();
+
+        let mut grid = OccupancyGrid { cells: vec![] };
+        let mut areas: Vec<Option<(Range<usize>, Range<usize>)>> = vec![None; items.len()];
+
+        // Items with a definite position in both axes are placed first.
+        for (area, &(_, ref across, ref along)) in areas.iter_mut().zip(items.iter()) {
+            if let (&TrackPlacement::Definite(ref across), &TrackPlacement::Definite(ref along)) =
+                (across, along)
+            {
+                grid.occupy(across, along);
+                *area = Some((across.clone(), along.clone()));
+            }
+        }
+
+        // Then items locked to a given track of the axis the cursor moves across.
+        let mut locked_cursors: Vec<usize> = vec![];
+        for (area, &(_, ref across, ref along)) in areas.iter_mut().zip(items.iter()) {
+            if let (&TrackPlacement::Definite(ref across), &TrackPlacement::Auto(span)) =
+                (across, along)
+            {
+                while locked_cursors.len() <= across.start {
+                    locked_cursors.push(0);
+                }
+                let mut start = if auto_flow.dense {
+                    0
+                } else {
+                    locked_cursors[across.start]
+                };
+                while !grid.is_free(across, &(start..start + span)) {
+                    start += 1;
+                }
+                locked_cursors[across.start] = start + span;
+                grid.occupy(across, &(start..start + span));
+                *area = Some((across.clone(), start..start + span));
+            }
+        }
+
+        // The implicit grid has as many tracks along the cursor as needed to fit all items.
+        let along_count = items
+            .iter()
+            .map(|&(_, _, ref along)| along.span())
+            .chain(areas.iter().filter_map(|area| area.as_ref().map(|area| area.1.end)))
+            .fold(explicit_along_count + leading_along, max);
+
+        // Finally, everything else is auto-placed.
+        let mut cursor = (0, 0);
+        for (area, &(_, ref across, ref along)) in areas.iter_mut().zip(items.iter()) {
+            if area.is_some() {
+                continue;
+            }
+            if auto_flow.dense {
+                cursor = (0, 0);
+            }
+            let across_span = across.span();
+            match *along {
+                TrackPlacement::Definite(ref along) => {
+                    if along.start < cursor.1 {
+                        cursor.0 += 1;
+                    }
+                    cursor.1 = along.start;
+                    while !grid.is_free(&(cursor.0..cursor.0 + across_span), along) {
+                        cursor.0 += 1;
+                    }
+                    *area = Some((cursor.0..cursor.0 + across_span, along.clone()));
+                },
+                TrackPlacement::Auto(along_span) => {
+                    loop {
+                        if cursor.1 + along_span > along_count {
+                            cursor = (cursor.0 + 1, 0);
+                        } else if grid.is_free(
+                            &(cursor.0..cursor.0 + across_span),
+                            &(cursor.1..cursor.1 + along_span),
+                        ) {
+                            break;
+                        } else {
+                            cursor.1 += 1;
+                        }
+                    }
+                    *area = Some((
+                        cursor.0..cursor.0 + across_span,
+                        cursor.1..cursor.1 + along_span,
+                    ));
+                },
+            }
+            let &(ref across, ref along) = area.as_ref().unwrap();
+            grid.occupy(across, along);
+        }
+
+        let mut row_count = explicit_rows.len() + leading_rows as usize;
+        let mut column_count = explicit_columns.len() + leading_columns as usize;
+        let items: Vec<GridItem> = items
+            .iter()
+            .zip(areas.into_iter())
+            .map(|(&(index, _, _), area)| {
+                let (across, along) = area.unwrap();
+                let (row, column) = if row_flow {
+                    (across, along)
+                } else {
+                    (along, across)
+                };
+                row_count = max(row_count, row.end);
+                column_count = max(column_count, column.end);
+                GridItem {
+                    index: index,
+                    row: row,
+                    column: column,
+                }
+            }).collect();
+
+        // Tracks outside the explicit grid are sized by `grid-auto-rows` and
+        // `grid-auto-columns`.
+        //
+        // https://drafts.csswg.org/css-grid/#implicit-grids
+        let implicit_tracks = |explicit: Vec<GridTrack>,
+                               auto_size: &TrackSize,
+                               containing_size: Option<Au>,
+                               leading: usize,
+                               count: usize| {
+            let mut tracks: Vec<GridTrack> = (0..leading)
+                .map(|_| GridTrack::new(auto_size, containing_size))
+                .collect();
+            tracks.extend(explicit);
+            while tracks.len() < count {
+                tracks.push(GridTrack::new(auto_size, containing_size));
+            }
+            tracks
+        };
+
+        GridPlacement {
+            items: items,
+            rows: implicit_tracks(
+                explicit_rows,
+                &position.grid_auto_rows,
+                block_size,
+                leading_rows as usize,
+                row_count,
+            ),
+            columns: implicit_tracks(
+                explicit_columns,
+                &position.grid_auto_columns,
+                inline_size,
+                leading_columns as usize,
+                column_count,
+            ),
+        }
+    }
+
+    /// The contributions of the grid items to the sizes of the columns they span.
+    fn column_contributions(&self, items: &[GridItem]) -> Vec<TrackContribution> {
+        let intrinsic_inline_sizes: Vec<_> = self
+            .block_flow
+            .base
+            .children
+            .iter()
+            .map(|kid| kid.base().intrinsic_inline_sizes)
+            .collect();
+        items
+            .iter()
+            .map(|item| TrackContribution {
+                span: item.column.clone(),
+                min_content: intrinsic_inline_sizes[item.index].minimum_inline_size,
+                max_content: intrinsic_inline_sizes[item.index].preferred_inline_size,
+            }).collect()
+    }
+
+    /// The contributions of the grid items to the sizes of the rows they span, once the
+    /// items have been laid out.
+    fn row_contributions(&self) -> Vec<TrackContribution> {
+        let outer_block_sizes: Vec<Au> = self
+            .block_flow
+            .base
+            .children
+            .iter()
+            .map(|kid| {
+                let fragment = &kid.as_block().fragment;
+                fragment.border_box.size.block + fragment.margin.block_start_end()
+            }).collect();
+        self.items
+            .iter()
+            .map(|item| TrackContribution {
+                span: item.row.clone(),
+                min_content: outer_block_sizes[item.index],
+                max_content: outer_block_sizes[item.index],
+            }).collect()
+    }
+}
+
+impl Flow for GridFlow {
+    fn class(&self) -> FlowClass {
+        FlowClass::Grid
+    }
+
+    fn as_mut_grid(&mut self) -> &mut GridFlow {
+        self
+    }
+
+    fn as_grid(&self) -> &GridFlow {
+        self
+    }
+
+    fn as_block(&self) -> &BlockFlow {
+        &self.block_flow
+    }
+
+    fn as_mut_block(&mut self) -> &mut BlockFlow {
+        &mut self.block_flow
+    }
+
+    fn mark_as_root(&mut self) {
+        self.block_flow.mark_as_root();
+    }
+
+    fn bubble_inline_sizes(&mut self) {
+        let _scope = layout_debug_scope!(
+            "grid::bubble_inline_sizes {:x}",
+            self.block_flow.base.debug_id()
+        );
+
+        let fixed_width = match self.block_flow.fragment.style().get_position().width {
+            LengthOrPercentageOrAuto::Length(_) => true,
+            _ => false,
+        };
+
+        let mut computation = self.block_flow.fragment.compute_intrinsic_inline_sizes();
+        if !fixed_width {
+            // The intrinsic sizes of the grid container are the sizes of its columns when sized
+            // under a min-content or max-content constraint.
+            //
+            // https://drafts.csswg.org/css-grid/#intrinsic-sizes
+            let placement = self.place_items(None, None);
+            let contributions = self.column_contributions(&placement.items);
+            let column_gap =
+                resolve_gap(&self.block_flow.fragment.style().get_position().column_gap, None);
+            let mut columns = placement.columns;
+
+            size_tracks(
+                &mut columns,
+                &contributions,
+                AvailableSpace::MinContent,
+                column_gap,
+                false,
+            );
+            computation.content_intrinsic_sizes.minimum_inline_size =
+                used_size(&columns, column_gap);
+
+            size_tracks(
+                &mut columns,
+                &contributions,
+                AvailableSpace::MaxContent,
+                column_gap,
+                false,
+            );
+            computation.content_intrinsic_sizes.preferred_inline_size =
+                used_size(&columns, column_gap);
+        }
+        self.block_flow.base.intrinsic_inline_sizes = computation.finish();
+    }
+
+    fn assign_inline_sizes(&mut self, layout_context: &LayoutContext) {
+        let _scope = layout_debug_scope!(
+            "grid::assign_inline_sizes {:x}",
+            self.block_flow.base.debug_id()
+        );
+        debug!("assign_inline_sizes");
+
+        if !self
+            .block_flow
+            .base
+            .restyle_damage
+            .intersects(ServoRestyleDamage::REFLOW_OUT_OF_FLOW | ServoRestyleDamage::REFLOW)
+        {
+            return;
+        }
+
+        self.block_flow
+            .initialize_container_size_for_root(layout_context.shared_context());
+
+        // Our inline-size was set to the inline-size of the containing block by the flow's parent.
+        // Now compute the real value.
+        let containing_block_inline_size = self.block_flow.base.block_container_inline_size;
+        self.block_flow.compute_used_inline_size(
+            layout_context.shared_context(),
+            containing_block_inline_size,
+        );
+        if self.block_flow.base.flags.is_float() {
+            self.block_flow
+                .float
+                .as_mut()
+                .unwrap()
+                .containing_inline_size = containing_block_inline_size
+        }
+
+        // Move in from the inline-start border edge.
+        let inline_start_content_edge = self.block_flow.fragment.border_box.start.i +
+            self.block_flow.fragment.border_padding.inline_start;
+        let content_inline_size = self.block_flow.fragment.border_box.size.inline -
+            self.block_flow.fragment.border_padding.inline_start_end();
+        let explicit_content_block_size = self.explicit_content_block_size(layout_context);
+
+        let placement = self.place_items(Some(content_inline_size), explicit_content_block_size);
+        let contributions = self.column_contributions(&placement.items);
+        self.items = placement.items;
+        self.rows = placement.rows;
+        self.columns = placement.columns;
+
+        let (column_gap, row_gap, justify_content, containing_block_text_align) = {
+            let style = self.block_flow.fragment.style();
+            (
+                resolve_gap(&style.get_position().column_gap, Some(content_inline_size)),
+                resolve_gap(&style.get_position().row_gap, explicit_content_block_size),
+                ContentDistribution::from_justify_content(style.get_position().justify_content),
+                style.get_inherited_text().text_align,
+            )
+        };
+        size_tracks(
+            &mut self.columns,
+            &contributions,
+            AvailableSpace::Definite(content_inline_size),
+            column_gap,
+            justify_content == ContentDistribution::Stretch,
+        );
+        let free_space = content_inline_size - used_size(&self.columns, column_gap);
+        position_tracks(&mut self.columns, free_space, column_gap, justify_content);
+
+        let containing_block_mode = self.block_flow.base.writing_mode;
+        {
+            let mut children = self.block_flow.base.children.random_access_mut();
+            for item in &self.items {
+                let kid_base = children.get(item.index).mut_base();
+                kid_base.block_container_inline_size = area_size(&self.columns, &item.column);
+                kid_base.block_container_writing_mode = containing_block_mode;
+                // Items get a definite block size to resolve percentages against when the rows
+                // they span don't depend on their contents.
+                kid_base.block_container_explicit_block_size =
+                    definite_area_size(&self.rows, &item.row, row_gap);
+                kid_base.position.start.i =
+                    inline_start_content_edge + self.columns[item.column.start].position;
+                // Per CSS 2.1 § 16.3.1, text alignment propagates to all children in flow.
+                //
+                // TODO(#2265, pcwalton): Do this in the cascade instead.
+                kid_base.flags.set_text_align(containing_block_text_align);
+            }
+        }
+
+        for kid in self.block_flow.base.children.iter_mut() {
+            let kid_base = kid.mut_base();
+            if kid_base.flags.contains(FlowFlags::IS_ABSOLUTELY_POSITIONED) {
+                kid_base.block_container_inline_size = content_inline_size;
+                kid_base.block_container_writing_mode = containing_block_mode;
+                if kid_base.flags.contains(FlowFlags::INLINE_POSITION_IS_STATIC) {
+                    kid_base.position.start.i = inline_start_content_edge;
+                }
+            }
+        }
+    }
+
+    fn assign_block_size(&mut self, layout_context: &LayoutContext) {
+        let _scope = layout_debug_scope!(
+            "grid::assign_block_size {:x}",
+            self.block_flow.base.debug_id()
+        );
+
+        let explicit_content_block_size = self.explicit_content_block_size(layout_context);
+        let (row_gap, align_content, align_items) = {
+            let position = self.block_flow.fragment.style().get_position();
+            (
+                resolve_gap(&position.row_gap, explicit_content_block_size),
+                ContentDistribution::from_align_content(position.align_content),
+                position.align_items,
+            )
+        };
+        let stretch_auto_rows = align_content == ContentDistribution::Stretch;
+        let contributions = self.row_contributions();
+
+        let content_block_size = match explicit_content_block_size {
+            Some(size) => {
+                size_tracks(
+                    &mut self.rows,
+                    &contributions,
+                    AvailableSpace::Definite(size),
+                    row_gap,
+                    stretch_auto_rows,
+                );
+                size
+            },
+            None => {
+                size_tracks(
+                    &mut self.rows,
+                    &contributions,
+                    AvailableSpace::MaxContent,
+                    row_gap,
+                    stretch_auto_rows,
+                );
+                let used_block_size = used_size(&self.rows, row_gap);
+                let box_border = self
+                    .block_flow
+                    .fragment
+                    .box_sizing_boundary(Direction::Block);
+                let parent_container_size = self
+                    .block_flow
+                    .explicit_block_containing_size(layout_context.shared_context());
+                let style = self.block_flow.fragment.style();
+                let size_constraint = SizeConstraint::new(
+                    parent_container_size,
+                    style.min_block_size(),
+                    style.max_block_size(),
+                    Some(box_border),
+                );
+                let content_block_size = size_constraint.clamp(used_block_size);
+                // A `min-block-size` or `max-block-size` gives the rows a definite size to fill.
+                if content_block_size != used_block_size {
+                    size_tracks(
+                        &mut self.rows,
+                        &contributions,
+                        AvailableSpace::Definite(content_block_size),
+                        row_gap,
+                        stretch_auto_rows,
+                    );
+                }
+                content_block_size
+            },
+        };
+        let free_space = content_block_size - used_size(&self.rows, row_gap);
+        position_tracks(&mut self.rows, free_space, row_gap, align_content);
+
+        let block_start_content_edge = self.block_flow.fragment.border_padding.block_start;
+        {
+            let mut children = self.block_flow.base.children.random_access_mut();
+            for item in &self.items {
+                let block = children.get(item.index).as_mut_block();
+                let area_block_size = area_size(&self.rows, &item.row);
+                let area_block_start = self.rows[item.row.start].position;
+
+                let margin = block.fragment.style().logical_margin();
+                let auto_margin_count = [margin.block_start, margin.block_end]
+                    .iter()
+                    .filter(|margin| **margin == LengthOrPercentageOrAuto::Auto)
+                    .count() as i32;
+                let self_align = match block.fragment.style().get_position().align_self {
+                    AlignSelf::Auto => match align_items {
+                        AlignItems::Stretch => AlignSelf::Stretch,
+                        AlignItems::FlexStart => AlignSelf::FlexStart,
+                        AlignItems::FlexEnd => AlignSelf::FlexEnd,
+                        AlignItems::Center => AlignSelf::Center,
+                        AlignItems::Baseline => AlignSelf::Baseline,
+                    },
+                    align_self => align_self,
+                };
+                let auto_block_size = block.fragment.style().content_block_size() ==
+                    LengthOrPercentageOrAuto::Auto;
+
+                let mut free_space = area_block_size - block.fragment.border_box.size.block -
+                    block.fragment.margin.block_start_end();
+                let mut offset = Au(0);
+                if auto_margin_count > 0 {
+                    // https://drafts.csswg.org/css-grid/#auto-margins
+                    let auto_margin = max(free_space, Au(0)) / auto_margin_count;
+                    if margin.block_start == LengthOrPercentageOrAuto::Auto {
+                        block.fragment.margin.block_start = auto_margin;
+                    }
+                    if margin.block_end == LengthOrPercentageOrAuto::Auto {
+                        block.fragment.margin.block_end = auto_margin;
+                    }
+                } else if self_align == AlignSelf::Stretch && auto_block_size {
+                    free_space = Au(0);
+                    block.fragment.border_box.size.block =
+                        area_block_size - block.fragment.margin.block_start_end();
+                    // FIXME: Items stretched this way should act as if they had a definite block
+                    // size, and lay out their contents against it.
+                }
+                // TODO: support baseline alignment.
+                if free_space != Au(0) {
+                    offset = match self_align {
+                        AlignSelf::FlexEnd => free_space,
+                        AlignSelf::Center => free_space / 2,
+                        _ => Au(0),
+                    };
+                }
+
+                block.base.position.start.b = block_start_content_edge +
+                    area_block_start +
+                    block.fragment.margin.block_start +
+                    offset;
+                block.base.position.size.block = block.fragment.border_box.size.block;
+            }
+        }
+
+        let total_block_size =
+            content_block_size + self.block_flow.fragment.border_padding.block_start_end();
+        self.block_flow.fragment.border_box.size.block = total_block_size;
+        self.block_flow.base.position.size.block = total_block_size;
+
+        let block_start =
+            AdjoiningMargins::from_margin(self.block_flow.fragment.margin.block_start);
+        let block_end =
+            AdjoiningMargins::from_margin(self.block_flow.fragment.margin.block_end);
+        self.block_flow.base.collapsible_margins =
+            CollapsibleMargins::Collapse(block_start, block_end);
+
+        // TODO: assign proper static position for absolute descendants.
+        if (&*self as &Flow).contains_roots_of_absolute_flow_tree() {
+            // Assign block-sizes for all flows in this absolute flow tree.
+            // This is preorder because the block-size of an absolute flow may depend on
+            // the block-size of its containing block, which may also be an absolute flow.
+            let assign_abs_b_sizes = AbsoluteAssignBSizesTraversal(layout_context.shared_context());
+            assign_abs_b_sizes.traverse_absolute_flows(&mut *self);
+        }
+    }
+
+    fn compute_stacking_relative_position(&mut self, layout_context: &LayoutContext) {
+        self.block_flow
+            .compute_stacking_relative_position(layout_context)
+    }
+
+    fn place_float_if_applicable<'a>(&mut self) {
+        self.block_flow.place_float_if_applicable()
+    }
+
+    fn update_late_computed_inline_position_if_necessary(&mut self, inline_position: Au) {
+        self.block_flow
+            .update_late_computed_inline_position_if_necessary(inline_position)
+    }
+
+    fn update_late_computed_block_position_if_necessary(&mut self, block_position: Au) {
+        self.block_flow
+            .update_late_computed_block_position_if_necessary(block_position)
+    }
+
+    fn build_display_list(&mut self, state: &mut DisplayListBuildState) {
+        self.build_display_list_for_grid(state);
+    }
+
+    fn collect_stacking_contexts(&mut self, state: &mut StackingContextCollectionState) {
+        self.block_flow.collect_stacking_contexts(state);
+    }
+
+    fn repair_style(&mut self, new_style: &::ServoArc<ComputedValues>) {
+        self.block_flow.repair_style(new_style)
+    }
+
+    fn compute_overflow(&self) -> Overflow {
+        self.block_flow.compute_overflow()
+    }
+
+    fn contains_roots_of_absolute_flow_tree(&self) -> bool {
+        self.block_flow.contains_roots_of_absolute_flow_tree()
+    }
+
+    fn is_absolute_containing_block(&self) -> bool {
+        self.block_flow.is_absolute_containing_block()
+    }
+
+    fn generated_containing_block_size(&self, flow: OpaqueFlow) -> LogicalSize<Au> {
+        self.block_flow.generated_containing_block_size(flow)
+    }
+
+    fn iterate_through_fragment_border_boxes(
+        &self,
+        iterator: &mut FragmentBorderBoxIterator,
+        level: i32,
+        stacking_context_position: &Point2D<Au>,
+    ) {
+        self.block_flow.iterate_through_fragment_border_boxes(
+            iterator,
+            level,
+            stacking_context_position,
+        );
+    }
+
+    fn mutate_fragments(&mut self, mutator: &mut FnMut(&mut Fragment)) {
+        self.block_flow.mutate_fragments(mutator);
+    }
+}
@@ -947,6 +947,16 @@ impl InlineFragments {
 #[allow(unsafe_code)]
 unsafe impl ::flow::HasBaseFlow for InlineFlow {}
 
+/// The `::first-line` style of the block an inline flow starts.
+#[derive(Clone)]
+pub struct FirstLineStyle {
+    /// The style of the `::first-line` pseudo-element of the block.
+    pub style: ServoArc<ComputedValues>,
+    /// The style of the `::first-letter` fragment directly inside the block, if any, which keeps
+    /// its own style.
+    pub first_letter_style: Option<ServoArc<ComputedValues>>,
+}
+
 /// Flows for inline layout.
 #[derive(Serialize)]
 #[repr(C)]
@@ -969,6 +979,10 @@ pub struct InlineFlow {
     /// (because percentages are relative to the containing block, and we aren't in a position to
     /// compute things relative to our parent's containing block).
     pub first_line_indentation: Au,
+
+    /// The `::first-line` style of the block, if this is its first inline flow and it has one.
+    #[serde(skip_serializing)]
+    pub first_line_style: Option<FirstLineStyle>,
 }
 
 impl InlineFlow {
@@ -979,6 +993,7 @@ impl InlineFlow {
             lines: Vec::new(),
             minimum_line_metrics: LineMetrics::new(Au(0), Au(0)),
             first_line_indentation: Au(0),
+            first_line_style: None,
         };
 
         if flow
@@ -1324,6 +1339,7 @@ impl InlineFlow {
                 (Display::Inline, GenericVerticalAlign::Top) |
                 (Display::Block, GenericVerticalAlign::Top) |
                 (Display::InlineFlex, GenericVerticalAlign::Top) |
+                (Display::InlineGrid, GenericVerticalAlign::Top) |
                 (Display::InlineBlock, GenericVerticalAlign::Top)
                     if inline_metrics.space_above_baseline >= Au(0) =>
                 {
@@ -1335,6 +1351,7 @@ impl InlineFlow {
                 (Display::Inline, GenericVerticalAlign::Bottom) |
                 (Display::Block, GenericVerticalAlign::Bottom) |
                 (Display::InlineFlex, GenericVerticalAlign::Bottom) |
+                (Display::InlineGrid, GenericVerticalAlign::Bottom) |
                 (Display::InlineBlock, GenericVerticalAlign::Bottom)
                     if inline_metrics.space_below_baseline >= Au(0) =>
                 {
@@ -1427,6 +1444,33 @@ impl InlineFlow {
         }
         None
     }
+
+    /// Returns the `::first-line` style to paint the fragment at `index` with, if it is text
+    /// directly inside the block that's laid out on the first line.
+    ///
+    /// Text runs are shaped before lines are known, so only the properties that apply at paint
+    /// time, like `color` or `text-decoration`, are honored; font properties, `letter-spacing` and
+    /// the like keep the values of the block. Text inside inline descendants of the block keeps
+    /// the style of its element.
+    pub fn first_line_style_for_fragment(&self, index: usize) -> Option<ServoArc<ComputedValues>> {
+        let first_line_style = self.first_line_style.as_ref()?;
+        if !self.lines.first()?.range.contains(FragmentIndex(index as isize)) {
+            return None;
+        }
+        let fragment = &self.fragments.fragments[index];
+        if fragment.pseudo != PseudoElementType::Normal || fragment.inline_context.is_some() {
+            return None;
+        }
+        if let Some(ref first_letter_style) = first_line_style.first_letter_style {
+            if ServoArc::ptr_eq(&fragment.style, first_letter_style) {
+                return None;
+            }
+        }
+        match fragment.specific {
+            SpecificFragmentInfo::ScannedText(_) => Some(first_line_style.style.clone()),
+            _ => None,
+        }
+    }
 }
 
 impl Flow for InlineFlow {
@@ -31,6 +31,7 @@ extern crate range;
 extern crate rayon;
 extern crate script_layout_interface;
 extern crate script_traits;
+extern crate selectors;
 #[macro_use]
 extern crate serde;
 extern crate serde_json;
@@ -39,6 +40,7 @@ extern crate servo_atoms;
 extern crate servo_channel;
 extern crate servo_config;
 extern crate servo_geometry;
+extern crate servo_svg;
 extern crate servo_url;
 extern crate smallvec;
 extern crate style;
@@ -64,6 +66,7 @@ mod flow_list;
 pub mod flow_ref;
 mod fragment;
 mod generated_content;
+mod grid;
 pub mod incremental;
 mod inline;
 mod linked_list;
@@ -71,10 +74,12 @@ mod list_item;
 mod model;
 mod multicol;
 pub mod opaque_node;
+pub mod pagination;
 pub mod parallel;
 mod persistent_list;
 pub mod query;
 pub mod sequential;
+pub mod svg;
 mod table;
 mod table_caption;
 mod table_cell;
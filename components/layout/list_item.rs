@@ -112,11 +112,16 @@ impl ListItemFlow {
 
     fn assign_marker_block_sizes(&mut self, layout_context: &LayoutContext) {
         // FIXME(pcwalton): Do this during flow construction, like `InlineFlow` does?
+        // The markers have the `::marker` style of the list item, if it has one.
+        let marker_style = self
+            .marker_fragments
+            .first()
+            .map_or(&*self.block_flow.fragment.style, |marker| &*marker.style);
         let marker_line_metrics = with_thread_local_font_context(layout_context, |font_context| {
             InlineFlow::minimum_line_metrics_for_fragments(
                 &self.marker_fragments,
                 font_context,
-                &*self.block_flow.fragment.style,
+                marker_style,
             )
         });
 
@@ -0,0 +1,316 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Fragments a laid out flow tree into pages of a fixed block size, for printing.
+//!
+//! Pages are stacked one after the other in the block direction, the way WebKit paginates a
+//! document: once the flow tree has been laid out, its contents are moved down so that nothing
+//! that can't be broken straddles the end of a page, and the boxes that contain them grow to
+//! fit. Page `n` then shows the block offsets `[n * page_block_size, (n + 1) * page_block_size)`
+//! of the document. Specifically:
+//!
+//! * Lines, replaced content, table rows and boxes with `break-inside: avoid` that would
+//!   straddle the end of a page are moved to the start of the next page, unless they are taller
+//!   than a page.
+//! * `break-before` and `break-after` with a page value move the following box to the start of
+//!   the next page. Those of the first and last boxes in a box apply to the box itself, which
+//!   breaks before or after it instead.
+//! * Boxes that are broken across pages are split where the page ends, as with
+//!   `box-decoration-break: slice`. Boxes with a definite block size do not grow.
+//! * Boxes laid out side by side, such as table cells, flex items and columns, are broken
+//!   independently of each other.
+//!
+//! Absolutely positioned boxes are not moved, and the `avoid` values of `break-before` and
+//! `break-after`, `orphans` and `widows` are ignored.
+
+use app_units::Au;
+use block::BlockFlow;
+use flow::{Flow, FlowClass, FlowFlags, GetBaseFlow};
+use inline::InlineFlow;
+use range::RangeIndex;
+use std::cmp::{Reverse, max};
+use std::collections::BinaryHeap;
+use style::computed_values::break_after::T as BreakAfter;
+use style::computed_values::break_before::T as BreakBefore;
+use style::computed_values::break_inside::T as BreakInside;
+use style::properties::ComputedValues;
+use style::values::computed::LengthOrPercentageOrAuto;
+
+/// Fragments `root` into pages of `page_block_size`, moving its contents as needed.
+///
+/// This must run after block sizes are assigned, and before overflow is stored.
+pub fn paginate(root: &mut Flow, page_block_size: Au) {
+    if page_block_size > Au(0) {
+        paginate_flow(root, Au(0), page_block_size);
+    }
+}
+
+/// Returns the block offsets, relative to the root flow, at which the pages of a document of
+/// `content_block_size` start.
+///
+/// There is always at least one page, which starts at zero.
+pub fn page_starts(content_block_size: Au, page_block_size: Au) -> Vec<Au> {
+    let mut page_starts = vec![Au(0)];
+    if page_block_size <= Au(0) {
+        return page_starts;
+    }
+
+    let mut page_start = page_block_size;
+    while page_start < content_block_size {
+        page_starts.push(page_start);
+        page_start += page_block_size;
+    }
+    page_starts
+}
+
+/// Returns how far something that can't be broken, that starts at `offset` and is `block_size`
+/// tall, must move down to not straddle the end of a page.
+///
+/// Things taller than a page are broken anyway, and so never move.
+pub fn unbreakable_strut(offset: Au, block_size: Au, page_block_size: Au) -> Au {
+    if page_block_size <= Au(0) || block_size > page_block_size {
+        return Au(0);
+    }
+
+    let page_end = page_end(offset, page_block_size);
+    if offset + block_size <= page_end {
+        Au(0)
+    } else {
+        page_end - offset
+    }
+}
+
+/// Returns how far a box that starts at `offset`, with a forced page break before it, must move
+/// down to start a page.
+pub fn forced_break_strut(offset: Au, page_block_size: Au) -> Au {
+    if page_block_size <= Au(0) || offset.0 % page_block_size.0 == 0 {
+        return Au(0);
+    }
+    page_end(offset, page_block_size) - offset
+}
+
+/// Returns the end of the page that `offset` is on.
+fn page_end(offset: Au, page_block_size: Au) -> Au {
+    let page_index = if offset >= Au(0) {
+        offset.0 / page_block_size.0
+    } else {
+        (offset.0 + 1) / page_block_size.0 - 1
+    };
+    Au((page_index + 1) * page_block_size.0)
+}
+
+/// Fragments `flow`, whose border box starts at `offset` relative to the root flow.
+///
+/// Returns how much its block size grew.
+fn paginate_flow(flow: &mut Flow, offset: Au, page_block_size: Au) -> Au {
+    let growth = match flow.class() {
+        FlowClass::Inline => paginate_lines(flow.as_mut_inline(), offset, page_block_size),
+        FlowClass::TableColGroup => Au(0),
+        _ => paginate_children(flow, offset, page_block_size),
+    };
+    if growth == Au(0) {
+        return Au(0);
+    }
+
+    if let Some(block) = block_flow_mut(flow) {
+        match block.fragment.style().content_block_size() {
+            LengthOrPercentageOrAuto::Auto => {},
+            _ => return Au(0),
+        }
+        block.fragment.border_box.size.block += growth;
+    }
+    flow.mut_base().position.size.block += growth;
+    growth
+}
+
+/// Moves the lines of `flow` that straddle the end of a page to the next one.
+fn paginate_lines(flow: &mut InlineFlow, offset: Au, page_block_size: Au) -> Au {
+    let mut displacement = Au(0);
+    for line in &mut flow.lines {
+        line.bounds.start.b += displacement;
+        let strut = unbreakable_strut(
+            offset + line.bounds.start.b,
+            line.bounds.size.block,
+            page_block_size,
+        );
+        line.bounds.start.b += strut;
+        displacement += strut;
+
+        for fragment_index in line.range.each_index() {
+            let fragment = flow.fragments.get_mut(fragment_index.to_usize());
+            fragment.border_box.start.b += displacement;
+        }
+    }
+    displacement
+}
+
+/// Fragments the children of `flow`, and moves each of them down by as much as the ones that
+/// end before it starts moved.
+fn paginate_children(flow: &mut Flow, offset: Au, page_block_size: Au) -> Au {
+    let mut kids = vec![];
+    // The break before the first kid in flow is the one of `flow`, which its parent honours.
+    let mut previous_forced_break_after = None;
+    for kid in flow.mut_base().child_iter_mut() {
+        let kid_flags = kid.base().flags;
+        if kid_flags.contains(FlowFlags::IS_ABSOLUTELY_POSITIONED) {
+            continue;
+        }
+
+        let mut forced_break = false;
+        if !kid_flags.is_float() {
+            let (forced_break_before, forced_break_after) = forced_breaks(kid);
+            forced_break = previous_forced_break_after.map_or(false, |previous_forced_break_after| {
+                previous_forced_break_after || forced_break_before
+            });
+            previous_forced_break_after = Some(forced_break_after);
+        }
+        kids.push((kid, forced_break));
+    }
+    kids.sort_by_key(|&(ref kid, _)| kid.base().position.start.b);
+
+    // The kids already fragmented, with the first to end on top, and how far their ends moved.
+    let mut fragmented = BinaryHeap::new();
+    // How far the ends of the kids that end before the current one starts moved, at most.
+    let mut displacement = Au(0);
+    let mut growth = Au(0);
+    for (kid, forced_break) in kids {
+        let start = kid.base().position.start.b;
+        let block_size = kid.base().position.size.block;
+        while fragmented
+            .peek()
+            .map_or(false, |&(Reverse(end), _)| end <= start)
+        {
+            let (_, moved) = fragmented.pop().unwrap();
+            displacement = max(displacement, moved);
+        }
+
+        let mut kid_offset = offset + start + displacement;
+        if forced_break {
+            kid_offset += forced_break_strut(kid_offset, page_block_size);
+        }
+        let monolithic = is_monolithic(kid);
+        if monolithic || avoids_break_inside(kid) {
+            kid_offset += unbreakable_strut(kid_offset, block_size, page_block_size);
+        }
+        kid.mut_base().position.start.b = kid_offset - offset;
+
+        let kid_growth = if monolithic {
+            Au(0)
+        } else {
+            paginate_flow(kid, kid_offset, page_block_size)
+        };
+        let moved = kid_offset - offset - start + kid_growth;
+        fragmented.push((Reverse(start + block_size), moved));
+        growth = max(growth, moved);
+    }
+    growth
+}
+
+fn block_flow_mut(flow: &mut Flow) -> Option<&mut BlockFlow> {
+    match flow.class() {
+        FlowClass::Inline | FlowClass::TableColGroup => None,
+        _ => Some(flow.as_mut_block()),
+    }
+}
+
+/// Returns the style that the break properties of `flow` are read from, if any.
+///
+/// Tables and columns share the style of the table wrapper and multi-column container that
+/// they are in, which are the ones that break.
+fn block_style(flow: &Flow) -> Option<&ComputedValues> {
+    match flow.class() {
+        FlowClass::Inline |
+        FlowClass::Table |
+        FlowClass::TableColGroup |
+        FlowClass::MulticolColumn => None,
+        _ => Some(flow.as_block().fragment.style()),
+    }
+}
+
+/// Whether `flow` is replaced content, which is never broken.
+fn is_monolithic(flow: &Flow) -> bool {
+    match flow.class() {
+        FlowClass::Inline | FlowClass::TableColGroup => false,
+        _ => flow.as_block().fragment.is_replaced(),
+    }
+}
+
+/// Whether `flow` should rather move to the next page than be broken.
+fn avoids_break_inside(flow: &Flow) -> bool {
+    if flow.class() == FlowClass::TableRow {
+        return true;
+    }
+    block_style(flow).map_or(false, |style| {
+        style.get_box().break_inside != BreakInside::Auto
+    })
+}
+
+/// Returns whether there is a forced page break before and after `flow`.
+///
+/// The breaks before the first kid in flow of a box and after its last one are propagated to
+/// the box.
+///
+/// <https://drafts.csswg.org/css-break-3/#break-propagation>
+fn forced_breaks(flow: &Flow) -> (bool, bool) {
+    let (mut forced_break_before, mut forced_break_after) = match block_style(flow) {
+        Some(style) => (
+            forces_page_break_before(style.get_box().break_before),
+            forces_page_break_after(style.get_box().break_after),
+        ),
+        None => (false, false),
+    };
+    if !stacks_children(flow) {
+        return (forced_break_before, forced_break_after);
+    }
+
+    let mut kids = flow.base().child_iter().filter(|kid| {
+        let kid_flags = kid.base().flags;
+        !kid_flags.contains(FlowFlags::IS_ABSOLUTELY_POSITIONED) && !kid_flags.is_float()
+    });
+    if let Some(first_kid) = kids.next() {
+        let (first_kid_break_before, mut last_kid_break_after) = forced_breaks(first_kid);
+        if let Some(last_kid) = kids.last() {
+            last_kid_break_after = forced_breaks(last_kid).1;
+        }
+        forced_break_before |= first_kid_break_before;
+        forced_break_after |= last_kid_break_after;
+    }
+    (forced_break_before, forced_break_after)
+}
+
+/// Whether the kids of `flow` are laid out one after the other in the block direction, so that
+/// breaks between them are breaks of `flow` too.
+fn stacks_children(flow: &Flow) -> bool {
+    match flow.class() {
+        FlowClass::Inline |
+        FlowClass::TableColGroup |
+        FlowClass::TableRow |
+        FlowClass::Multicol |
+        FlowClass::Flex |
+        FlowClass::Grid => false,
+        _ => true,
+    }
+}
+
+fn forces_page_break_before(break_before: BreakBefore) -> bool {
+    match break_before {
+        BreakBefore::Page |
+        BreakBefore::Left |
+        BreakBefore::Right |
+        BreakBefore::Recto |
+        BreakBefore::Verso => true,
+        _ => false,
+    }
+}
+
+fn forces_page_break_after(break_after: BreakAfter) -> bool {
+    match break_after {
+        BreakAfter::Page |
+        BreakAfter::Left |
+        BreakAfter::Right |
+        BreakAfter::Recto |
+        BreakAfter::Verso => true,
+        _ => false,
+    }
+}
@@ -12,13 +12,15 @@ use display_list::items::{DisplayList, OpaqueNode, ScrollOffsetMap};
 use euclid::{Point2D, Vector2D, Rect, Size2D};
 use flow::{Flow, GetBaseFlow};
 use fragment::{Fragment, FragmentBorderBoxIterator, SpecificFragmentInfo};
+use fxhash::FxHashMap;
 use inline::InlineFragmentNodeFlags;
 use ipc_channel::ipc::IpcSender;
 use msg::constellation_msg::PipelineId;
 use opaque_node::OpaqueNodeMethods;
 use script_layout_interface::{LayoutElementType, LayoutNodeType};
 use script_layout_interface::StyleData;
-use script_layout_interface::rpc::{ContentBoxResponse, ContentBoxesResponse, LayoutRPC};
+use script_layout_interface::rpc::{ContentBoxResponse, ContentBoxesResponse, CssAnimationResponse};
+use script_layout_interface::rpc::LayoutRPC;
 use script_layout_interface::rpc::{NodeGeometryResponse, NodeScrollIdResponse};
 use script_layout_interface::rpc::{OffsetParentResponse, ResolvedStyleResponse, StyleResponse};
 use script_layout_interface::rpc::TextIndexResponse;
@@ -29,6 +31,7 @@ use sequential;
 use std::cmp::{min, max};
 use std::ops::Deref;
 use std::sync::{Arc, Mutex};
+use style::animation::Animation;
 use style::computed_values::display::T as Display;
 use style::computed_values::position::T as Position;
 use style::computed_values::visibility::T as Visibility;
@@ -37,6 +40,7 @@ use style::dom::TElement;
 use style::logical_geometry::{WritingMode, BlockFlowDirection, InlineBaseDirection};
 use style::properties::{style_structs, PropertyId, PropertyDeclarationId, LonghandId};
 use style::selector_parser::PseudoElement;
+use style::timer::Timer;
 use style_traits::ToCss;
 use webrender_api::ExternalScrollId;
 use wrapper::LayoutNodeLayoutData;
@@ -88,6 +92,9 @@ pub struct LayoutThreadData {
 
     /// A queued response for the inner text of a given element.
     pub element_inner_text_response: String,
+
+    /// A queued response for the CSS animations running on a node.
+    pub css_animations_response: Vec<CssAnimationResponse>,
 }
 
 pub struct LayoutRPCImpl(pub Arc<Mutex<LayoutThreadData>>);
@@ -191,6 +198,12 @@ impl LayoutRPC for LayoutRPCImpl {
         let rw_data = rw_data.lock().unwrap();
         rw_data.element_inner_text_response.clone()
     }
+
+    fn css_animations(&self) -> Vec<CssAnimationResponse> {
+        let &LayoutRPCImpl(ref rw_data) = self;
+        let rw_data = rw_data.lock().unwrap();
+        rw_data.css_animations_response.clone()
+    }
 }
 
 struct UnioningFragmentBorderBoxIterator {
@@ -795,6 +808,26 @@ where
     N: LayoutNode,
 {
     let layout_el = requested_node.to_threadsafe().as_element().unwrap();
+
+    // ::first-letter, ::first-line and ::marker don't get boxes of their own,
+    // so there's no used value to look for.
+    if let Some(ref pseudo) = *pseudo {
+        if pseudo.is_first_letter() || pseudo.is_first_line() || pseudo.is_marker() {
+            let style = match layout_el.eager_pseudo_style(pseudo) {
+                Some(style) => style,
+                None => return String::new(),
+            };
+            let id = match *property {
+                PropertyId::LonghandAlias(id, _) | PropertyId::Longhand(id) => {
+                    PropertyDeclarationId::Longhand(id)
+                },
+                PropertyId::Custom(ref name) => PropertyDeclarationId::Custom(name),
+                PropertyId::ShorthandAlias(..) | PropertyId::Shorthand(_) => return String::new(),
+            };
+            return style.computed_value_to_string(id);
+        }
+    }
+
     let layout_el = match *pseudo {
         Some(PseudoElement::Before) => layout_el.get_before_pseudo(),
         Some(PseudoElement::After) => layout_el.get_after_pseudo(),
@@ -1087,7 +1120,11 @@ fn inner_text_collection_steps<N: LayoutNode>(
                     "\u{000A}", /* line feed */
                 )));
             },
-            Display::Block | Display::Flex | Display::TableCaption | Display::Table => {
+            Display::Block |
+            Display::Flex |
+            Display::Grid |
+            Display::TableCaption |
+            Display::Table => {
                 // Step 9.
                 items.insert(0, InnerTextItem::RequiredLineBreakCount(1));
                 items.push(InnerTextItem::RequiredLineBreakCount(1));
@@ -1108,3 +1145,28 @@ fn is_last_table_row() -> bool {
     // FIXME(ferjm) Implement this.
     false
 }
+
+/// Returns the CSS animations running on the given node, or on all the nodes.
+pub fn process_css_animations_query(
+    node: Option<OpaqueNode>,
+    running_animations: &FxHashMap<OpaqueNode, Vec<Animation>>,
+    timer: &Timer,
+) -> Vec<CssAnimationResponse> {
+    let now = timer.seconds();
+    running_animations
+        .iter()
+        .filter(|&(animated_node, _)| node.map_or(true, |node| node == *animated_node))
+        .flat_map(|(_, animations)| animations.iter())
+        .filter_map(|animation| match *animation {
+            Animation::Keyframes(ref node, _, ref name, ref state) if !state.expired => {
+                Some(CssAnimationResponse {
+                    node_address: node.to_untrusted_node_address(),
+                    name: name.clone(),
+                    timing: state.effect_timing(),
+                    current_time: state.current_time(now),
+                    paused: animation.is_paused(),
+                })
+            },
+            _ => None,
+        }).collect()
+}
@@ -0,0 +1,455 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Inline `<svg>` elements.
+//!
+//! The content of an `<svg>` element is built into a scene from the layout tree
+//! when its fragment is constructed, through the same code as SVG images. The
+//! shapes of the scene are rasterized into an image when the fragment is painted,
+//! and its text is painted with text display items over it.
+
+use app_units::Au;
+use context::{LayoutContext, with_thread_local_font_context};
+use display_list::items::OpaqueNode;
+use euclid::{Point2D, Size2D, Transform2D, Vector2D};
+use fnv::FnvHashMap;
+use gfx::font::{ShapingFlags, ShapingOptions};
+use gfx::text::glyph::ByteIndex;
+use gfx::text::text_run::TextRun;
+use html5ever::LocalName;
+use net_traits::image_cache::ImageCache;
+use ordered_float::NotNan;
+use range::Range;
+use script_layout_interface::wrapper_traits::{PseudoElementType, ThreadSafeLayoutElement};
+use script_layout_interface::wrapper_traits::ThreadSafeLayoutNode;
+use selectors::Element;
+use servo_arc::Arc as ServoArc;
+use servo_svg::rasterizer;
+use servo_svg::scene::{self, FillRule, Length, LineCap, LineJoin, Scene, SvgElement};
+use servo_svg::scene::{SvgPaint, SvgStyle, TextAnchor, ViewBox};
+use std::sync::Arc;
+use style::computed_values::fill_rule::T as ComputedFillRule;
+use style::computed_values::stroke_linecap::T as ComputedLineCap;
+use style::computed_values::stroke_linejoin::T as ComputedLineJoin;
+use style::computed_values::text_anchor::T as ComputedTextAnchor;
+use style::computed_values::visibility::T as Visibility;
+use style::dom::{NodeInfo, TElement};
+use style::properties::ComputedValues;
+use style::properties::style_structs::Font;
+use style::values::{Either, RGBA};
+use style::values::computed::LengthOrPercentage;
+use style::values::computed::svg::SVGPaint;
+use style::values::generics::svg::{SVGLength, SVGOpacity, SVGPaintKind, SVGStrokeDashArray};
+use style::values::generics::svg::SvgLengthOrPercentageOrNumber;
+use unicode_bidi as bidi;
+use unicode_script::Script;
+use webrender_api::ImageKey;
+
+/// The largest width or height, in device pixels, of the images inline SVG is
+/// rasterized into.
+const MAX_IMAGE_SIZE: f32 = 4096.;
+
+/// The content of an inline `<svg>` element.
+pub struct SvgContent {
+    /// The shapes of the element, in its user space.
+    pub scene: Scene,
+    pub texts: Vec<SvgTextItem>,
+    pub view_box: Option<ViewBox>,
+}
+
+/// A `<text>` element of an inline `<svg>` element.
+pub struct SvgTextItem {
+    /// The text content of the element, with its whitespace collapsed.
+    pub text: String,
+    /// The start of the baseline of the text, in the user space of the element.
+    pub position: Point2D<f32>,
+    pub anchor: TextAnchor,
+    pub color: RGBA,
+    /// The transform from the user space of the element to the one of the
+    /// `<svg>` element.
+    pub transform: Transform2D<f32>,
+    pub font: ServoArc<Font>,
+}
+
+impl SvgContent {
+    /// Builds the content of the given `<svg>` element, whose percentages
+    /// refer to a viewport of the given size.
+    pub fn new<N: ThreadSafeLayoutNode>(node: &N, viewport: Size2D<f32>) -> SvgContent {
+        let element = node.as_element().unwrap();
+        let mut ids = FnvHashMap::default();
+        collect_ids(element, &mut ids);
+        let root = LayoutSvgElement {
+            element,
+            ids: &ids,
+        };
+
+        let view_box = root
+            .attribute("viewBox")
+            .and_then(|view_box| ViewBox::parse(view_box, root.attribute("preserveAspectRatio")));
+        let style = root.style(&SvgStyle::default()).unwrap_or_default();
+        // Percentages refer to the view box when there is one.
+        let viewport = view_box.map_or(viewport, |view_box| view_box.rect.size);
+        let (scene, texts) = scene::build_scene(&root, &style, viewport);
+        let texts = texts
+            .into_iter()
+            .filter_map(|text| {
+                let font = computed_style(&text.element.element)?.clone_font();
+                Some(SvgTextItem {
+                    text: text.text,
+                    position: text.position,
+                    anchor: text.anchor,
+                    color: text.color,
+                    transform: text.transform,
+                    font,
+                })
+            }).collect();
+
+        SvgContent {
+            scene,
+            texts,
+            view_box,
+        }
+    }
+
+    /// The transform from the user space of the element to its content box,
+    /// when it has the given size.
+    pub fn transform(&self, size: Size2D<f32>) -> Transform2D<f32> {
+        match self.view_box {
+            Some(ref view_box) => view_box.transform(size),
+            None => Transform2D::identity(),
+        }
+    }
+}
+
+impl SvgTextItem {
+    /// Shapes the text at the size it's painted at by the given transform from
+    /// the user space of the `<svg>` element to CSS pixels, and returns the run
+    /// with the start of its baseline.
+    ///
+    /// The text is scaled along with the transform, but not rotated or skewed.
+    pub fn shape(
+        &self,
+        layout_context: &LayoutContext,
+        transform: &Transform2D<f32>,
+    ) -> Option<(TextRun, Point2D<Au>)> {
+        let transform = self.transform.post_mul(transform);
+        let scale = (transform.m11 * transform.m22 - transform.m12 * transform.m21)
+            .abs()
+            .sqrt();
+        let mut font = (*self.font).clone();
+        font.font_size.size = self.font.font_size.size.scale_by(scale);
+        font.font_size.keyword_info = None;
+
+        let options = ShapingOptions {
+            letter_spacing: None,
+            word_spacing: (Au(0), NotNan::new(0.0).unwrap()),
+            script: Script::Common,
+            flags: ShapingFlags::empty(),
+        };
+        let run = with_thread_local_font_context(layout_context, |font_context| {
+            let font_group = font_context.font_group(ServoArc::new(font));
+            let font = font_group.borrow_mut().first(font_context)?;
+            let (run, _) = TextRun::new(
+                &mut *font.borrow_mut(),
+                self.text.clone(),
+                &options,
+                bidi::Level::ltr(),
+                &mut None,
+            );
+            Some(run)
+        })?;
+
+        let range = Range::new(ByteIndex(0), ByteIndex(self.text.len() as isize));
+        let advance = run.advance_for_range(&range).to_f32_px();
+        let offset = match self.anchor {
+            TextAnchor::Start => 0.,
+            TextAnchor::Middle => advance / 2.,
+            TextAnchor::End => advance,
+        };
+        let origin = transform.transform_point(&self.position) - Vector2D::new(offset, 0.);
+        Some((run, Point2D::new(Au::from_f32_px(origin.x), Au::from_f32_px(origin.y))))
+    }
+}
+
+/// The images inline `<svg>` elements were rasterized into when they were last
+/// painted, which are reused as long as their content and size don't change.
+#[derive(Default)]
+pub struct SvgImages {
+    images: FnvHashMap<OpaqueNode, SvgImage>,
+    /// The keys of the images which aren't used by the last display list that
+    /// was built, and are released once it's sent to WebRender.
+    unused_keys: Vec<ImageKey>,
+}
+
+struct SvgImage {
+    content: Arc<SvgContent>,
+    size: Size2D<u32>,
+    key: ImageKey,
+    painted: bool,
+}
+
+impl SvgImages {
+    /// Returns the key of the image the content of the given `<svg>` element
+    /// is painted with, when its content box has the given size.
+    pub fn image_key(
+        &mut self,
+        node: OpaqueNode,
+        content: &Arc<SvgContent>,
+        size: Size2D<f32>,
+        device_pixel_ratio: f32,
+        image_cache: &ImageCache,
+    ) -> Option<ImageKey> {
+        let scale = device_pixel_ratio.min(MAX_IMAGE_SIZE / size.width.max(size.height));
+        let device_size = Size2D::new(
+            (size.width * scale).ceil() as u32,
+            (size.height * scale).ceil() as u32,
+        );
+        if device_size.width == 0 || device_size.height == 0 {
+            return None;
+        }
+
+        if let Some(image) = self.images.get_mut(&node) {
+            if Arc::ptr_eq(&image.content, content) && image.size == device_size {
+                image.painted = true;
+                return Some(image.key);
+            }
+        }
+
+        let transform = content
+            .transform(size)
+            .post_mul(&Transform2D::create_scale(scale, scale));
+        let mut image = rasterizer::rasterize(
+            &content.scene,
+            &transform,
+            device_size.width,
+            device_size.height,
+        );
+        image_cache.set_webrender_image_key(&mut image);
+        let key = image.id?;
+        let image = SvgImage {
+            content: content.clone(),
+            size: device_size,
+            key,
+            painted: true,
+        };
+        if let Some(replaced) = self.images.insert(node, image) {
+            self.unused_keys.push(replaced.key);
+        }
+        Some(key)
+    }
+
+    /// Forgets the images which weren't painted since the last call, once a
+    /// new display list has been built.
+    pub fn finish_display_list(&mut self) {
+        let unused_keys = &mut self.unused_keys;
+        self.images.retain(|_, image| {
+            if !image.painted {
+                unused_keys.push(image.key);
+                return false;
+            }
+            image.painted = false;
+            true
+        });
+    }
+
+    /// Releases the images which aren't used anymore, once the display list
+    /// which doesn't use them has been sent to WebRender.
+    pub fn release_unused(&mut self, image_cache: &ImageCache) {
+        if !self.unused_keys.is_empty() {
+            let keys = ::std::mem::replace(&mut self.unused_keys, vec![]);
+            image_cache.delete_webrender_image_keys(keys);
+        }
+    }
+}
+
+/// An element of the content of an inline `<svg>` element.
+#[derive(Clone)]
+struct LayoutSvgElement<'a, E: 'a> {
+    element: E,
+    /// The elements with an `id` in the `<svg>` element.
+    ids: &'a FnvHashMap<String, E>,
+}
+
+impl<'a, E: ThreadSafeLayoutElement> SvgElement for LayoutSvgElement<'a, E> {
+    fn local_name(&self) -> &str {
+        &**self.element.local_name()
+    }
+
+    fn attribute(&self, name: &str) -> Option<&str> {
+        let value = self.element.get_attr(&ns!(), &LocalName::from(name));
+        if value.is_none() && name == "href" {
+            return self.element.get_attr(&ns!(xlink), &local_name!("href"));
+        }
+        value
+    }
+
+    fn children(&self) -> Vec<Self> {
+        element_children(self.element)
+            .into_iter()
+            .map(|element| LayoutSvgElement {
+                element,
+                ids: self.ids,
+            }).collect()
+    }
+
+    fn text_content(&self) -> String {
+        let mut text = String::new();
+        append_text_content(self.element.as_node(), &mut text);
+        text
+    }
+
+    /// The style system already inherits styles from the parent elements in
+    /// the tree, which differ from the given ones only for the content of
+    /// `<use>` elements.
+    fn style(&self, _parent: &SvgStyle) -> Option<SvgStyle> {
+        computed_style(&self.element).and_then(|style| svg_style(&*style))
+    }
+
+    fn element_by_id(&self, id: &str) -> Option<Self> {
+        self.ids.get(id).map(|element| LayoutSvgElement {
+            element: *element,
+            ids: self.ids,
+        })
+    }
+}
+
+fn element_children<E: ThreadSafeLayoutElement>(element: E) -> Vec<E> {
+    element
+        .as_node()
+        .children()
+        .filter_map(|child| child.as_element())
+        .filter(|child| child.get_pseudo_element_type() == PseudoElementType::Normal)
+        .collect()
+}
+
+fn collect_ids<E: ThreadSafeLayoutElement>(element: E, ids: &mut FnvHashMap<String, E>) {
+    for child in element_children(element) {
+        if let Some(id) = child.get_attr(&ns!(), &local_name!("id")) {
+            ids.entry(id.to_owned()).or_insert(child);
+        }
+        collect_ids(child, ids);
+    }
+}
+
+fn append_text_content<N: ThreadSafeLayoutNode>(node: N, text: &mut String) {
+    for child in node.children() {
+        if child.is_text_node() {
+            text.push_str(&child.node_text_content());
+        } else if child.get_pseudo_element_type() == PseudoElementType::Normal {
+            append_text_content(child, text);
+        }
+    }
+}
+
+/// The computed style of an element, which it may not have when it's in a
+/// `display: none` subtree.
+#[allow(unsafe_code)]
+fn computed_style<E: ThreadSafeLayoutElement>(element: &E) -> Option<ServoArc<ComputedValues>> {
+    let element = unsafe { element.unsafe_get() };
+    let data = element.borrow_data()?;
+    let style = data.styles.get_primary().cloned();
+    style
+}
+
+fn svg_style(style: &ComputedValues) -> Option<SvgStyle> {
+    if style.get_box().display.is_none() {
+        return None;
+    }
+
+    let color = style.get_color().color;
+    let svg = style.get_inherited_svg();
+    Some(SvgStyle {
+        color,
+        fill: paint(&svg.fill, color),
+        fill_opacity: opacity(&svg.fill_opacity),
+        fill_rule: match svg.fill_rule {
+            ComputedFillRule::Nonzero => FillRule::NonZero,
+            ComputedFillRule::Evenodd => FillRule::EvenOdd,
+        },
+        stroke: paint(&svg.stroke, color),
+        stroke_opacity: opacity(&svg.stroke_opacity),
+        stroke_width: match svg.stroke_width {
+            SVGLength::Length(ref width) => length(width),
+            SVGLength::ContextValue => Length::Absolute(1.),
+        },
+        stroke_linecap: match svg.stroke_linecap {
+            ComputedLineCap::Butt => LineCap::Butt,
+            ComputedLineCap::Round => LineCap::Round,
+            ComputedLineCap::Square => LineCap::Square,
+        },
+        stroke_linejoin: match svg.stroke_linejoin {
+            ComputedLineJoin::Miter => LineJoin::Miter,
+            ComputedLineJoin::Round => LineJoin::Round,
+            ComputedLineJoin::Bevel => LineJoin::Bevel,
+        },
+        stroke_miterlimit: svg.stroke_miterlimit.0,
+        stroke_dasharray: match svg.stroke_dasharray {
+            SVGStrokeDashArray::Values(ref values) => {
+                values.iter().map(length).collect()
+            },
+            SVGStrokeDashArray::ContextValue => vec![],
+        },
+        stroke_dashoffset: match svg.stroke_dashoffset {
+            SVGLength::Length(ref offset) => length(offset),
+            SVGLength::ContextValue => Length::Absolute(0.),
+        },
+        text_anchor: match svg.text_anchor {
+            ComputedTextAnchor::Start => TextAnchor::Start,
+            ComputedTextAnchor::Middle => TextAnchor::Middle,
+            ComputedTextAnchor::End => TextAnchor::End,
+        },
+        visible: style.get_inherited_box().visibility == Visibility::Visible,
+        opacity: style.get_effects().opacity,
+        stop_color: style.get_svg().stop_color.to_rgba(color),
+        stop_opacity: style.get_svg().stop_opacity,
+    })
+}
+
+fn paint(paint: &SVGPaint, color: RGBA) -> SvgPaint {
+    let fallback = match paint.fallback {
+        Some(Either::First(ref fallback)) => Some(fallback.to_rgba(color)),
+        _ => None,
+    };
+    match paint.kind {
+        SVGPaintKind::None => SvgPaint::None,
+        SVGPaintKind::Color(ref paint_color) => SvgPaint::Color(paint_color.to_rgba(color)),
+        // Only references to elements of the document are supported.
+        SVGPaintKind::PaintServer(ref url) => match url.url().and_then(|url| url.fragment()) {
+            Some(id) => SvgPaint::Server(id.to_owned(), fallback),
+            None => fallback.map_or(SvgPaint::None, SvgPaint::Color),
+        },
+        // These only apply to the content of markers and `<use>` elements in
+        // SVG 2, which we don't support.
+        SVGPaintKind::ContextFill | SVGPaintKind::ContextStroke => SvgPaint::None,
+    }
+}
+
+fn opacity(opacity: &SVGOpacity<f32>) -> f32 {
+    match *opacity {
+        SVGOpacity::Opacity(opacity) => opacity,
+        SVGOpacity::ContextFillOpacity | SVGOpacity::ContextStrokeOpacity => 1.,
+    }
+}
+
+fn length_or_percentage(length: LengthOrPercentage) -> Length {
+    match length {
+        LengthOrPercentage::Length(length) => Length::Absolute(length.px()),
+        LengthOrPercentage::Percentage(percentage) => Length::Percentage(percentage.0 * 100.),
+        // FIXME: The percentage of `calc()` lengths is ignored.
+        LengthOrPercentage::Calc(calc) => Length::Absolute(calc.length().px()),
+    }
+}
+
+fn length<L, N>(length: &SvgLengthOrPercentageOrNumber<L, N>) -> Length
+where
+    L: Copy + Into<LengthOrPercentage>,
+    N: Copy + Into<f32>,
+{
+    match *length {
+        SvgLengthOrPercentageOrNumber::LengthOrPercentage(length) => {
+            length_or_percentage(length.into())
+        },
+        SvgLengthOrPercentageOrNumber::Number(number) => Length::Absolute(number.into()),
+    }
+}
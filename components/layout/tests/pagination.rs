@@ -0,0 +1,66 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+extern crate app_units;
+extern crate layout;
+
+use app_units::Au;
+use layout::pagination::{forced_break_strut, page_starts, unbreakable_strut};
+
+const PAGE: Au = Au(1000);
+
+#[test]
+fn test_forced_break_moves_to_next_page() {
+    assert_eq!(forced_break_strut(Au(250), PAGE), Au(750));
+    assert_eq!(forced_break_strut(Au(1999), PAGE), Au(1));
+}
+
+#[test]
+fn test_forced_break_at_page_start_does_not_move() {
+    assert_eq!(forced_break_strut(Au(0), PAGE), Au(0));
+    assert_eq!(forced_break_strut(Au(3000), PAGE), Au(0));
+}
+
+#[test]
+fn test_avoid_break_inside_moves_to_next_page() {
+    assert_eq!(unbreakable_strut(Au(900), Au(200), PAGE), Au(100));
+    assert_eq!(unbreakable_strut(Au(1800), Au(1000), PAGE), Au(200));
+}
+
+#[test]
+fn test_avoid_break_inside_that_fits_does_not_move() {
+    assert_eq!(unbreakable_strut(Au(800), Au(200), PAGE), Au(0));
+    assert_eq!(unbreakable_strut(Au(1000), Au(1000), PAGE), Au(0));
+}
+
+#[test]
+fn test_taller_than_page_is_not_moved() {
+    assert_eq!(unbreakable_strut(Au(500), Au(1001), PAGE), Au(0));
+    assert_eq!(
+        page_starts(Au(2500), PAGE),
+        vec![Au(0), Au(1000), Au(2000)]
+    );
+}
+
+#[test]
+fn test_negative_offset_is_on_previous_page() {
+    assert_eq!(unbreakable_strut(Au(-100), Au(200), PAGE), Au(100));
+    assert_eq!(forced_break_strut(Au(-100), PAGE), Au(100));
+}
+
+#[test]
+fn test_page_starts() {
+    assert_eq!(page_starts(Au(0), PAGE), vec![Au(0)]);
+    assert_eq!(page_starts(Au(1000), PAGE), vec![Au(0)]);
+    assert_eq!(page_starts(Au(1001), PAGE), vec![Au(0), Au(1000)]);
+}
+
+#[test]
+fn test_empty_page_does_not_break() {
+    for &page in &[Au(0), Au(-1000)] {
+        assert_eq!(page_starts(Au(5000), page), vec![Au(0)]);
+        assert_eq!(unbreakable_strut(Au(900), Au(200), page), Au(0));
+        assert_eq!(forced_break_strut(Au(250), page), Au(0));
+    }
+}
@@ -38,9 +38,10 @@ use layout::wrapper::GetRawData;
 use msg::constellation_msg::{BrowsingContextId, PipelineId};
 use range::Range;
 use script::layout_exports::{CharacterDataTypeId, ElementTypeId, HTMLElementTypeId, NodeTypeId};
-use script::layout_exports::{Document, Element, Node, Text};
+use script::layout_exports::{Document, Element, HTMLSlotElement, Node, ShadowRoot, Text};
 use script::layout_exports::{LayoutCharacterDataHelpers, LayoutDocumentHelpers};
 use script::layout_exports::{LayoutElementHelpers, LayoutNodeHelpers, LayoutDom, RawLayoutElementHelpers};
+use script::layout_exports::{LayoutHTMLSlotElementHelpers, LayoutShadowRootHelpers};
 use script::layout_exports::NodeFlags;
 use script::layout_exports::PendingRestyle;
 use script_layout_interface::{HTMLCanvasData, LayoutNodeType, SVGSVGData, TrustedNodeAddress};
@@ -63,16 +64,19 @@ use std::sync::atomic::Ordering;
 use style::CaseSensitivityExt;
 use style::applicable_declarations::ApplicableDeclarationBlock;
 use style::attr::AttrValue;
+use style::computed_values::display::T as Display;
 use style::context::SharedStyleContext;
 use style::data::ElementData;
 use style::dom::{DomChildren, LayoutIterator, NodeInfo, OpaqueNode};
 use style::dom::{TDocument, TElement, TNode, TShadowRoot};
 use style::element_state::*;
 use style::font_metrics::ServoMetricsProvider;
+use style::media_queries::Device;
 use style::properties::{ComputedValues, PropertyDeclarationBlock};
 use style::selector_parser::{AttrValue as SelectorAttrValue, NonTSPseudoClass, Lang};
 use style::selector_parser::{PseudoElement, SelectorImpl, extended_filtering};
 use style::shared_lock::{SharedRwLock as StyleSharedRwLock, Locked as StyleLocked};
+use style::shared_lock::SharedRwLockReadGuard;
 use style::str::is_whitespace;
 use style::stylist::CascadeData;
 
@@ -148,34 +152,62 @@ impl<'ln> NodeInfo for ServoLayoutNode<'ln> {
 }
 
 #[derive(Clone, Copy, PartialEq)]
-enum Impossible {}
+pub struct ServoShadowRoot<'lr> {
+    /// The wrapped shadow root.
+    shadow_root: LayoutDom<ShadowRoot>,
 
-#[derive(Clone, Copy, PartialEq)]
-pub struct ShadowRoot<'lr>(Impossible, PhantomData<&'lr ()>);
+    /// Being chained to a PhantomData prevents `ShadowRoot`s from escaping.
+    chain: PhantomData<&'lr ()>,
+}
+
+impl<'lr> Debug for ServoShadowRoot<'lr> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.as_node().fmt(f)
+    }
+}
 
-impl<'lr> TShadowRoot for ShadowRoot<'lr> {
+impl<'lr> TShadowRoot for ServoShadowRoot<'lr> {
     type ConcreteNode = ServoLayoutNode<'lr>;
 
     fn as_node(&self) -> Self::ConcreteNode {
-        match self.0 {}
+        ServoLayoutNode::from_layout_js(self.shadow_root.upcast())
     }
 
     fn host(&self) -> ServoLayoutElement<'lr> {
-        match self.0 {}
+        ServoLayoutElement::from_layout_js(unsafe { self.shadow_root.get_host_for_layout() })
     }
 
     fn style_data<'a>(&self) -> Option<&'a CascadeData>
     where
         Self: 'a,
     {
-        match self.0 {}
+        Some(unsafe { self.shadow_root.get_style_data_for_layout() })
+    }
+}
+
+impl<'lr> ServoShadowRoot<'lr> {
+    fn from_layout_js(shadow_root: LayoutDom<ShadowRoot>) -> ServoShadowRoot<'lr> {
+        ServoShadowRoot {
+            shadow_root,
+            chain: PhantomData,
+        }
+    }
+
+    pub unsafe fn flush_stylesheets(
+        &self,
+        device: &Device,
+        quirks_mode: QuirksMode,
+        guard: &SharedRwLockReadGuard,
+    ) {
+        self.shadow_root
+            .flush_stylesheets::<ServoLayoutElement>(device, quirks_mode, guard)
     }
 }
 
 impl<'ln> TNode for ServoLayoutNode<'ln> {
     type ConcreteDocument = ServoLayoutDocument<'ln>;
     type ConcreteElement = ServoLayoutElement<'ln>;
-    type ConcreteShadowRoot = ShadowRoot<'ln>;
+    type ConcreteShadowRoot = ServoShadowRoot<'ln>;
 
     fn parent_node(&self) -> Option<Self> {
         unsafe {
@@ -222,7 +254,16 @@ impl<'ln> TNode for ServoLayoutNode<'ln> {
     }
 
     fn traversal_parent(&self) -> Option<ServoLayoutElement<'ln>> {
-        self.parent_element()
+        // Slotted nodes are rendered as children of their slot, and the
+        // children of a shadow root as children of its host.
+        if let Some(slot) = unsafe { self.node.assigned_slot_ref() } {
+            return Some(ServoLayoutElement::from_layout_js(slot.upcast()));
+        }
+        let parent = self.parent_node()?;
+        if let Some(shadow_root) = parent.as_shadow_root() {
+            return Some(shadow_root.host());
+        }
+        parent.as_element()
     }
 
     fn opaque(&self) -> OpaqueNode {
@@ -243,8 +284,8 @@ impl<'ln> TNode for ServoLayoutNode<'ln> {
             .map(ServoLayoutDocument::from_layout_js)
     }
 
-    fn as_shadow_root(&self) -> Option<ShadowRoot<'ln>> {
-        None
+    fn as_shadow_root(&self) -> Option<ServoShadowRoot<'ln>> {
+        self.node.downcast().map(ServoShadowRoot::from_layout_js)
     }
 
     fn is_in_document(&self) -> bool {
@@ -365,6 +406,33 @@ impl<'ld> ServoLayoutDocument<'ld> {
         unsafe { self.document.style_shared_lock() }
     }
 
+    pub fn shadow_roots(&self) -> Vec<ServoShadowRoot<'ld>> {
+        unsafe {
+            self.document
+                .shadow_roots()
+                .into_iter()
+                .map(ServoShadowRoot::from_layout_js)
+                .collect()
+        }
+    }
+
+    pub fn flush_shadow_roots_stylesheets(
+        &self,
+        device: &Device,
+        quirks_mode: QuirksMode,
+        guard: &SharedRwLockReadGuard,
+    ) {
+        unsafe {
+            if !self.document.shadow_roots_styles_changed() {
+                return;
+            }
+            self.document.flush_shadow_roots_stylesheets();
+            for shadow_root in self.shadow_roots() {
+                shadow_root.flush_stylesheets(device, quirks_mode, guard);
+            }
+        }
+    }
+
     pub fn from_layout_js(doc: LayoutDom<Document>) -> ServoLayoutDocument<'ld> {
         ServoLayoutDocument {
             document: doc,
@@ -392,7 +460,7 @@ impl<'le> fmt::Debug for ServoLayoutElement<'le> {
 
 impl<'le> TElement for ServoLayoutElement<'le> {
     type ConcreteNode = ServoLayoutNode<'le>;
-    type TraversalChildrenIterator = DomChildren<Self::ConcreteNode>;
+    type TraversalChildrenIterator = ServoChildrenIterator<'le>;
 
     type FontMetricsProvider = ServoMetricsProvider;
 
@@ -401,7 +469,19 @@ impl<'le> TElement for ServoLayoutElement<'le> {
     }
 
     fn traversal_children(&self) -> LayoutIterator<Self::TraversalChildrenIterator> {
-        LayoutIterator(self.as_node().dom_children())
+        if let Some(shadow_root) = self.shadow_root() {
+            return LayoutIterator(ServoChildrenIterator::Children(
+                shadow_root.as_node().dom_children(),
+            ));
+        }
+        if let Some(assigned_nodes) = self.slot_assigned_nodes() {
+            return LayoutIterator(ServoChildrenIterator::Slotted(assigned_nodes.into_iter()));
+        }
+        LayoutIterator(ServoChildrenIterator::Children(self.as_node().dom_children()))
+    }
+
+    fn inheritance_parent(&self) -> Option<Self> {
+        self.traversal_parent()
     }
 
     fn is_html_element(&self) -> bool {
@@ -526,6 +606,24 @@ impl<'le> TElement for ServoLayoutElement<'le> {
         false
     }
 
+    fn may_generate_pseudo(&self, pseudo: &PseudoElement, primary_style: &ComputedValues) -> bool {
+        // Layout only builds `::first-letter` and `::first-line` for block containers, and
+        // `::marker` for list items, so don't cascade them for anything else.
+        let display = primary_style.get_box().display;
+        match *pseudo {
+            PseudoElement::FirstLetter | PseudoElement::FirstLine => match display {
+                Display::Block |
+                Display::InlineBlock |
+                Display::ListItem |
+                Display::TableCell |
+                Display::TableCaption => true,
+                _ => false,
+            },
+            PseudoElement::Marker => display == Display::ListItem,
+            _ => true,
+        }
+    }
+
     unsafe fn set_selector_flags(&self, flags: ElementSelectorFlags) {
         self.element.insert_selector_flags(flags);
     }
@@ -603,12 +701,41 @@ impl<'le> TElement for ServoLayoutElement<'le> {
         }
     }
 
-    fn shadow_root(&self) -> Option<ShadowRoot<'le>> {
-        None
+    fn shadow_root(&self) -> Option<ServoShadowRoot<'le>> {
+        unsafe {
+            self.element
+                .get_shadow_root_for_layout()
+                .map(ServoShadowRoot::from_layout_js)
+        }
     }
 
-    fn containing_shadow(&self) -> Option<ShadowRoot<'le>> {
-        None
+    fn containing_shadow(&self) -> Option<ServoShadowRoot<'le>> {
+        if !unsafe { self.as_node().node.get_flag(NodeFlags::IS_IN_SHADOW_TREE) } {
+            return None;
+        }
+        let mut current = self.as_node();
+        while let Some(parent) = current.parent_node() {
+            current = parent;
+        }
+        current.as_shadow_root()
+    }
+}
+
+/// The children of an element in the flat tree: its DOM children, the children
+/// of its shadow root, or the nodes assigned to it if it is a slot.
+pub enum ServoChildrenIterator<'a> {
+    Children(DomChildren<ServoLayoutNode<'a>>),
+    Slotted(::std::vec::IntoIter<ServoLayoutNode<'a>>),
+}
+
+impl<'a> Iterator for ServoChildrenIterator<'a> {
+    type Item = ServoLayoutNode<'a>;
+
+    fn next(&mut self) -> Option<ServoLayoutNode<'a>> {
+        match *self {
+            ServoChildrenIterator::Children(ref mut children) => children.next(),
+            ServoChildrenIterator::Slotted(ref mut nodes) => nodes.next(),
+        }
     }
 }
 
@@ -662,8 +789,6 @@ impl<'le> ServoLayoutElement<'le> {
     }
 
     pub unsafe fn note_dirty_descendant(&self) {
-        use ::selectors::Element;
-
         let mut current = Some(*self);
         while let Some(el) = current {
             // FIXME(bholley): Ideally we'd have the invariant that any element
@@ -672,8 +797,24 @@ impl<'le> ServoLayoutElement<'le> {
             // we get that wrong.  I have in-flight patches to fix all this
             // stuff up, so we just always propagate this bit for now.
             el.set_dirty_descendants();
-            current = el.parent_element();
+            current = el.traversal_parent();
+        }
+    }
+
+    /// Returns the nodes assigned to this element if it is a slot with
+    /// assigned nodes, which are then rendered instead of its children.
+    fn slot_assigned_nodes(&self) -> Option<Vec<ServoLayoutNode<'le>>> {
+        let slot = self.element.downcast::<HTMLSlotElement>()?;
+        let assigned_nodes = unsafe { slot.assigned_nodes_for_layout() };
+        if assigned_nodes.is_empty() {
+            return None;
         }
+        Some(
+            assigned_nodes
+                .into_iter()
+                .map(ServoLayoutNode::from_layout_js)
+                .collect(),
+        )
     }
 }
 
@@ -693,11 +834,22 @@ impl<'le> ::selectors::Element for ServoLayoutElement<'le> {
     }
 
     fn parent_node_is_shadow_root(&self) -> bool {
-        false
+        self.as_node()
+            .parent_node()
+            .map_or(false, |parent| parent.as_shadow_root().is_some())
     }
 
     fn containing_shadow_host(&self) -> Option<Self> {
-        None
+        self.containing_shadow().map(|shadow_root| shadow_root.host())
+    }
+
+    fn assigned_slot(&self) -> Option<Self> {
+        unsafe {
+            self.as_node()
+                .node
+                .assigned_slot_ref()
+                .map(|slot| ServoLayoutElement::from_layout_js(slot.upcast()))
+        }
     }
 
     fn prev_sibling_element(&self) -> Option<ServoLayoutElement<'le>> {
@@ -722,6 +874,17 @@ impl<'le> ::selectors::Element for ServoLayoutElement<'le> {
         None
     }
 
+    fn first_element_child(&self) -> Option<ServoLayoutElement<'le>> {
+        let mut child = self.as_node().first_child();
+        while let Some(node) = child {
+            if let Some(element) = node.as_element() {
+                return Some(element);
+            }
+            child = node.next_sibling();
+        }
+        None
+    }
+
     fn attr_matches(
         &self,
         ns: &NamespaceConstraint<&Namespace>,
@@ -822,7 +985,16 @@ impl<'le> ::selectors::Element for ServoLayoutElement<'le> {
             NonTSPseudoClass::Indeterminate |
             NonTSPseudoClass::ReadWrite |
             NonTSPseudoClass::PlaceholderShown |
-            NonTSPseudoClass::Target => self
+            NonTSPseudoClass::Target |
+            NonTSPseudoClass::Valid |
+            NonTSPseudoClass::Invalid |
+            NonTSPseudoClass::Required |
+            NonTSPseudoClass::Optional |
+            NonTSPseudoClass::InRange |
+            NonTSPseudoClass::OutOfRange |
+            NonTSPseudoClass::Default |
+            NonTSPseudoClass::FocusWithin |
+            NonTSPseudoClass::FocusVisible => self
                 .element
                 .get_state_for_layout()
                 .contains(pseudo_class.state_flag()),
@@ -897,12 +1069,35 @@ impl<'a> PartialEq for ServoThreadSafeLayoutNode<'a> {
 }
 
 impl<'ln> DangerousThreadSafeLayoutNode for ServoThreadSafeLayoutNode<'ln> {
+    // These follow the flat tree, like `TElement::traversal_children`.
     unsafe fn dangerous_first_child(&self) -> Option<Self> {
+        if let Some(element) = self.node.as_element() {
+            if let Some(shadow_root) = element.shadow_root() {
+                return shadow_root
+                    .as_node()
+                    .first_child()
+                    .map(|node| ServoThreadSafeLayoutNode::new(&node));
+            }
+            if let Some(assigned_nodes) = element.slot_assigned_nodes() {
+                return assigned_nodes
+                    .first()
+                    .map(|node| ServoThreadSafeLayoutNode::new(node));
+            }
+        }
         self.get_jsmanaged()
             .first_child_ref()
             .map(|node| self.new_with_this_lifetime(&node))
     }
     unsafe fn dangerous_next_sibling(&self) -> Option<Self> {
+        if let Some(slot) = self.get_jsmanaged().assigned_slot_ref() {
+            let assigned_nodes = slot.assigned_nodes_for_layout();
+            let index = assigned_nodes
+                .iter()
+                .position(|node| node == self.get_jsmanaged())?;
+            return assigned_nodes
+                .get(index + 1)
+                .map(|node| self.new_with_this_lifetime(node));
+        }
         self.get_jsmanaged()
             .next_sibling_ref()
             .map(|node| self.new_with_this_lifetime(&node))
@@ -965,7 +1160,7 @@ impl<'ln> ThreadSafeLayoutNode for ServoThreadSafeLayoutNode<'ln> {
     }
 
     fn parent_style(&self) -> Arc<ComputedValues> {
-        let parent = self.node.parent_node().unwrap().as_element().unwrap();
+        let parent = self.node.traversal_parent().unwrap();
         let parent_data = parent.get_data().unwrap().borrow();
         parent_data.styles.primary().clone()
     }
@@ -1286,6 +1481,12 @@ impl<'le> ::selectors::Element for ServoThreadSafeLayoutElement<'le> {
         None
     }
 
+    // Skips non-element nodes
+    fn first_element_child(&self) -> Option<Self> {
+        warn!("ServoThreadSafeLayoutElement::first_element_child called");
+        None
+    }
+
     fn is_html_slot_element(&self) -> bool {
         self.element.is_html_slot_element()
     }
@@ -58,15 +58,17 @@ use app_units::Au;
 use dom_wrapper::{ServoLayoutElement, ServoLayoutDocument, ServoLayoutNode};
 use dom_wrapper::drop_style_and_layout_data;
 use embedder_traits::resources::{self, Resource};
-use euclid::{Point2D, Rect, Size2D, TypedScale, TypedSize2D};
+use euclid::{Point2D, Rect, SideOffsets2D, Size2D, TypedPoint2D, TypedRect, TypedScale};
+use euclid::TypedSize2D;
 use fnv::FnvHashMap;
 use fxhash::FxHashMap;
 use gfx::font;
-use gfx::font_cache_thread::FontCacheThread;
+use gfx::font_cache_thread::{FontCacheThread, ScriptFontFace};
 use gfx::font_context;
 use gfx_traits::{Epoch, node_id_from_scroll_id};
 use histogram::Histogram;
 use ipc_channel::ipc::{self, IpcReceiver, IpcSender};
+use ipc_channel::router::ROUTER;
 use layout::animation;
 use layout::construct::ConstructionResult;
 use layout::context::LayoutContext;
@@ -79,12 +81,14 @@ use layout::flow::{Flow, GetBaseFlow, ImmutableFlowUtils, MutableOwnedFlowUtils}
 use layout::flow_ref::FlowRef;
 use layout::incremental::{LayoutDamageComputation, RelayoutMode, SpecialRestyleDamage};
 use layout::layout_debug;
+use layout::pagination;
 use layout::parallel;
 use layout::query::{LayoutRPCImpl, LayoutThreadData, process_content_box_request, process_content_boxes_request};
-use layout::query::{process_element_inner_text_query, process_node_geometry_request};
+use layout::query::{process_css_animations_query, process_element_inner_text_query, process_node_geometry_request};
 use layout::query::{process_node_scroll_area_request, process_node_scroll_id_request};
 use layout::query::{process_offset_parent_query, process_resolved_style_request, process_style_query};
 use layout::sequential;
+use layout::svg::SvgImages;
 use layout::traversal::{ComputeStackingRelativePositions, PreorderFlowTraversal, RecalcStyleAndConstructFlows};
 use layout::wrapper::LayoutNodeLayoutData;
 use layout_traits::LayoutThreadFactory;
@@ -93,6 +97,7 @@ use malloc_size_of::{MallocSizeOf, MallocSizeOfOps};
 use metrics::{PaintTimeMetrics, ProfilerMetadataFactory, ProgressiveWebMetric};
 use msg::constellation_msg::PipelineId;
 use msg::constellation_msg::TopLevelBrowsingContextId;
+use net_traits::csp::CspList;
 use net_traits::image_cache::{ImageCache, UsePlaceholder};
 use parking_lot::RwLock;
 use profile_traits::mem::{self, Report, ReportKind, ReportsChan};
@@ -100,11 +105,12 @@ use profile_traits::time::{self, TimerMetadata, profile};
 use profile_traits::time::{TimerMetadataFrameType, TimerMetadataReflowType};
 use script_layout_interface::message::{Msg, NewLayoutThreadInfo, NodesFromPointQueryType, Reflow};
 use script_layout_interface::message::{ReflowComplete, QueryMsg, ReflowGoal, ScriptReflow};
+use script_layout_interface::message::WebFontSource;
 use script_layout_interface::rpc::{LayoutRPC, StyleResponse, OffsetParentResponse};
 use script_layout_interface::rpc::TextIndexResponse;
 use script_layout_interface::wrapper_traits::LayoutNode;
-use script_traits::{ConstellationControlMsg, LayoutControlMsg, LayoutMsg as ConstellationMsg};
-use script_traits::{DrawAPaintImageResult, PaintWorkletError};
+use script_traits::{AnimationState, ConstellationControlMsg, LayoutControlMsg, LayoutMsg as ConstellationMsg};
+use script_traits::{DrawAPaintImageResult, PaintWorkletError, PrintLayout};
 use script_traits::{ScrollState, UntrustedNodeAddress};
 use script_traits::Painter;
 use selectors::Element;
@@ -124,12 +130,13 @@ use std::process;
 use std::sync::{Arc, Mutex, MutexGuard};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::thread;
-use style::animation::Animation;
+use style::animation::{Animation, KeyframesRunningState, ScriptAnimationState};
 use style::context::{QuirksMode, RegisteredSpeculativePainter, RegisteredSpeculativePainters};
 use style::context::{SharedStyleContext, StyleSystemOptions, ThreadLocalStyleContextCreationInfo};
 use style::dom::{ShowSubtree, ShowSubtreeDataAndPrimaryValues, TElement, TNode};
 use style::driver;
 use style::error_reporting::RustLogReporter;
+use style::invalidation::element::relative_selector::invalidate_relative_selector_anchors;
 use style::invalidation::element::restyle_hints::RestyleHint;
 use style::logical_geometry::LogicalPoint;
 use style::media_queries::{Device, MediaList, MediaType};
@@ -137,12 +144,15 @@ use style::properties::PropertyId;
 use style::selector_parser::SnapshotMap;
 use style::servo::restyle_damage::ServoRestyleDamage;
 use style::shared_lock::{SharedRwLock, SharedRwLockReadGuard, StylesheetGuards};
-use style::stylesheets::{Origin, Stylesheet, DocumentStyleSheet, StylesheetInDocument, UserAgentStylesheets};
+use style::stylesheets::{DocumentStyleSheet, Origin, PageBox, Stylesheet, StylesheetInDocument};
+use style::stylesheets::UserAgentStylesheets;
+use style::stylesheets::keyframes_rule::KeyframesAnimation;
 use style::stylist::Stylist;
 use style::thread_state::{self, ThreadState};
 use style::timer::Timer;
 use style::traversal::DomTraversal;
 use style::traversal_flags::TraversalFlags;
+use style::values::computed::font::FamilyName;
 use style_traits::CSSPixel;
 use style_traits::DevicePixel;
 use style_traits::SpeculativePainter;
@@ -171,10 +181,10 @@ pub struct LayoutThread {
     pipeline_port: Receiver<LayoutControlMsg>,
 
     /// The port on which we receive messages from the font cache thread.
-    font_cache_receiver: Receiver<()>,
+    font_cache_receiver: Receiver<bool>,
 
     /// The channel on which the font cache can send messages to us.
-    font_cache_sender: IpcSender<()>,
+    font_cache_sender: IpcSender<bool>,
 
     /// The channel on which messages can be sent to the constellation.
     constellation_chan: IpcSender<ConstellationMsg>,
@@ -236,6 +246,15 @@ pub struct LayoutThread {
     /// constraints.
     viewport_size: Size2D<Au>,
 
+    /// The size of the contents of the root flow, as last reported to the constellation.
+    content_size: Cell<Size2D<Au>>,
+
+    /// The page box the document is laid out in, if it is being printed.
+    page_box: Option<PageBox>,
+
+    /// The Content Security Policy of the document, which Web fonts are loaded with.
+    csp_list: Option<CspList>,
+
     /// A mutex to allow for fast, read-only RPC of layout's internal data
     /// structures, while still letting the LayoutThread modify them.
     ///
@@ -244,6 +263,18 @@ pub struct LayoutThread {
 
     webrender_image_cache: Arc<RwLock<FnvHashMap<(ServoUrl, UsePlaceholder), WebRenderImageInfo>>>,
 
+    /// The times at which the animated images painted by this layout thread
+    /// started animating, keyed by the key of their first frame.
+    animated_images: Arc<RwLock<FnvHashMap<webrender_api::ImageKey, f64>>>,
+
+    /// The earliest time at which one of the animated images in the current
+    /// display list shows its next frame, if there are any.
+    next_image_frame_at: Cell<Option<f64>>,
+
+    /// The images the inline `<svg>` elements in the current display list were
+    /// rasterized into.
+    svg_images: Arc<Mutex<SvgImages>>,
+
     /// The executors for paint worklets.
     registered_painters: RegisteredPaintersImpl,
 
@@ -429,8 +460,9 @@ fn add_font_face_rules(
     guard: &SharedRwLockReadGuard,
     device: &Device,
     font_cache_thread: &FontCacheThread,
-    font_cache_sender: &IpcSender<()>,
+    font_cache_sender: &IpcSender<bool>,
     outstanding_web_fonts_counter: &Arc<AtomicUsize>,
+    csp_list: &Option<CspList>,
 ) {
     if opts::get().load_webfonts_synchronously {
         let (sender, receiver) = ipc::channel().unwrap();
@@ -440,6 +472,7 @@ fn add_font_face_rules(
                 font_cache_thread.add_web_font(
                     font_face.family().clone(),
                     effective_sources,
+                    csp_list.clone(),
                     sender.clone(),
                 );
                 receiver.recv().unwrap();
@@ -453,6 +486,7 @@ fn add_font_face_rules(
                 font_cache_thread.add_web_font(
                     font_face.family().clone(),
                     effective_sources,
+                    csp_list.clone(),
                     (*font_cache_sender).clone(),
                 );
             }
@@ -523,7 +557,7 @@ impl LayoutThread {
             mem_profiler_chan: mem_profiler_chan,
             registered_painters: RegisteredPaintersImpl(Default::default()),
             image_cache: image_cache.clone(),
-            font_cache_thread: font_cache_thread,
+            font_cache_thread: font_cache_thread.for_document(id),
             first_reflow: Cell::new(true),
             font_cache_receiver: font_cache_receiver,
             font_cache_sender: ipc_font_cache_sender,
@@ -539,6 +573,9 @@ impl LayoutThread {
             expired_animations: ServoArc::new(RwLock::new(Default::default())),
             epoch: Cell::new(Epoch(0)),
             viewport_size: Size2D::new(Au(0), Au(0)),
+            content_size: Cell::new(Size2D::new(Au(0), Au(0))),
+            page_box: None,
+            csp_list: None,
             webrender_api: webrender_api_sender.create_api(),
             webrender_document,
             stylist: Stylist::new(device, QuirksMode::NoQuirks),
@@ -558,8 +595,12 @@ impl LayoutThread {
                 text_index_response: TextIndexResponse(None),
                 nodes_from_point_response: vec![],
                 element_inner_text_response: String::new(),
+                css_animations_response: vec![],
             })),
             webrender_image_cache: Arc::new(RwLock::new(FnvHashMap::default())),
+            animated_images: Arc::new(RwLock::new(FnvHashMap::default())),
+            next_image_frame_at: Cell::new(None),
+            svg_images: Arc::new(Mutex::new(SvgImages::default())),
             timer: if PREFS
                 .get("layout.animations.test.enabled")
                 .as_boolean()
@@ -616,6 +657,9 @@ impl LayoutThread {
             image_cache: self.image_cache.clone(),
             font_cache_thread: Mutex::new(self.font_cache_thread.clone()),
             webrender_image_cache: self.webrender_image_cache.clone(),
+            animated_images: self.animated_images.clone(),
+            next_image_frame_at: Mutex::new(None),
+            svg_images: self.svg_images.clone(),
             pending_images: if script_initiated_layout {
                 Some(Mutex::new(vec![]))
             } else {
@@ -790,6 +834,34 @@ impl LayoutThread {
             Msg::SetNavigationStart(time) => {
                 self.paint_time_metrics.set_navigation_start(time);
             },
+            Msg::UpdateScriptAnimation(node, keyframes, state) => {
+                self.handle_update_script_animation(node, keyframes, state);
+            },
+            Msg::RemoveScriptAnimation(node, id) => {
+                self.handle_remove_script_animation(node, id);
+            },
+            Msg::SetCssAnimationPaused(node, name, paused) => {
+                self.handle_set_css_animation_paused(node, name, paused);
+            },
+            Msg::LoadWebFont(id, family, source, sender) => {
+                self.handle_load_web_font(id, family, source, sender);
+            },
+            Msg::AddWebFontToSet(id, family) => {
+                let font_face = ScriptFontFace { document: self.id, id };
+                self.font_cache_thread.add_script_font_face_to_set(font_face, family);
+                self.handle_web_font_set_changed();
+            },
+            Msg::RemoveWebFontFromSet(id) => {
+                let font_face = ScriptFontFace { document: self.id, id };
+                self.font_cache_thread.remove_script_font_face_from_set(font_face);
+                self.handle_web_font_set_changed();
+            },
+            Msg::HasFontFamily(family, sender) => {
+                let _ = sender.send(self.font_cache_thread.has_family(&family));
+            },
+            Msg::SetCspList(csp_list) => {
+                self.csp_list = csp_list;
+            },
         }
 
         true
@@ -891,6 +963,7 @@ impl LayoutThread {
         );
 
         self.root_flow.borrow_mut().take();
+        self.font_cache_thread.remove_document_fonts(self.id);
         // Drop the rayon threadpool if present.
         let _ = self.parallel_traversal.take();
     }
@@ -906,6 +979,7 @@ impl LayoutThread {
                 &self.font_cache_thread,
                 &self.font_cache_sender,
                 &self.outstanding_web_fonts,
+                &self.csp_list,
             );
         }
     }
@@ -923,6 +997,122 @@ impl LayoutThread {
         }
     }
 
+    /// Starts running an animation created from script, or updates it. The
+    /// style it was computed for is kept until the node is restyled.
+    fn handle_update_script_animation(
+        &self,
+        node: OpaqueNode,
+        keyframes: KeyframesAnimation,
+        mut state: ScriptAnimationState,
+    ) {
+        let mut running_animations = self.running_animations.write();
+        let animations = running_animations.entry(node).or_insert_with(Vec::new);
+        let position = animations.iter().position(|animation| match *animation {
+            Animation::Script(_, _, ref other_state) => other_state.id == state.id,
+            _ => false,
+        });
+        match position {
+            Some(index) => {
+                if let Animation::Script(_, _, ref old_state) = animations[index] {
+                    if state.cascade_style.is_none() {
+                        state.cascade_style = old_state.cascade_style.clone();
+                    }
+                }
+                animations[index] = Animation::Script(node, keyframes, state);
+            },
+            None => animations.push(Animation::Script(node, keyframes, state)),
+        }
+    }
+
+    /// Stops running an animation created from script.
+    fn handle_remove_script_animation(&self, node: OpaqueNode, id: usize) {
+        let mut running_animations = self.running_animations.write();
+        let node_has_animations = match running_animations.get_mut(&node) {
+            Some(animations) => {
+                animations.retain(|animation| match *animation {
+                    Animation::Script(_, _, ref state) => state.id != id,
+                    _ => true,
+                });
+                !animations.is_empty()
+            },
+            None => return,
+        };
+        if node_has_animations {
+            return;
+        }
+
+        running_animations.remove(&node);
+        if running_animations.is_empty() && self.next_image_frame_at.get().is_none() {
+            self.constellation_chan
+                .send(ConstellationMsg::ChangeRunningAnimationsState(
+                    self.id,
+                    AnimationState::NoAnimationsPresent,
+                )).unwrap();
+        }
+    }
+
+    /// Pauses or resumes a CSS animation from script. This lasts until the
+    /// node is restyled with a different `animation-play-state`.
+    fn handle_set_css_animation_paused(&self, node: OpaqueNode, name: Atom, paused: bool) {
+        let mut running_animations = self.running_animations.write();
+        let animations = match running_animations.get_mut(&node) {
+            Some(animations) => animations,
+            None => return,
+        };
+        for animation in animations.iter_mut() {
+            if let Animation::Keyframes(_, _, ref animation_name, ref mut state) = *animation {
+                if *animation_name != name {
+                    continue;
+                }
+                let mut new_state = state.clone();
+                new_state.running_state = if paused {
+                    KeyframesRunningState::Paused(0.)
+                } else {
+                    KeyframesRunningState::Running
+                };
+                state.update_from_other(&new_state, &self.timer);
+            }
+        }
+    }
+
+    /// Loads a font face created by script, telling script whether it loaded
+    /// once the font cache is done with it.
+    fn handle_load_web_font(&self, id: u64, family: FamilyName, source: WebFontSource, sender: IpcSender<bool>) {
+        let font_face = ScriptFontFace { document: self.id, id };
+        self.outstanding_web_fonts.fetch_add(1, Ordering::SeqCst);
+        let (font_sender, font_receiver) = ipc::channel().unwrap();
+        let font_cache_sender = self.font_cache_sender.clone();
+        ROUTER.add_route(
+            font_receiver.to_opaque(),
+            Box::new(move |message| {
+                let loaded = message.to().unwrap();
+                // Script may query layout as soon as it knows the font face
+                // loaded, so the font caches can't wait for the layout thread.
+                font_context::invalidate_font_caches();
+                let _ = font_cache_sender.send(loaded);
+                let _ = sender.send(loaded);
+            }),
+        );
+        match source {
+            WebFontSource::Sources(sources) => {
+                let csp_list = self.csp_list.clone();
+                self.font_cache_thread.add_script_font_face(font_face, family, sources, csp_list, font_sender)
+            },
+            WebFontSource::Data(bytes) => {
+                self.font_cache_thread.add_script_font_face_data(font_face, family, bytes, font_sender)
+            },
+        }
+    }
+
+    /// Lays the document out again with the font faces of its `FontFaceSet`,
+    /// after one of them was added or removed.
+    fn handle_web_font_set_changed(&self) {
+        font_context::invalidate_font_caches();
+        self.script_chan
+            .send(ConstellationControlMsg::WebFontLoaded(self.id))
+            .unwrap();
+    }
+
     /// Sets quirks mode for the document, causing the quirks mode stylesheet to be used.
     fn handle_set_quirks_mode<'a, 'b>(&mut self, quirks_mode: QuirksMode) {
         self.stylist.set_quirks_mode(quirks_mode);
@@ -1039,6 +1229,51 @@ impl LayoutThread {
                         build_state.root_stacking_context.bounds = origin;
                         build_state.root_stacking_context.overflow = origin;
 
+                        // The compositor needs the size of the page to capture all of it.
+                        if !self.is_iframe && self.content_size.get() != root_size {
+                            self.content_size.set(root_size);
+                            let size = TypedSize2D::new(
+                                root_size.width.to_f32_px(),
+                                root_size.height.to_f32_px(),
+                            );
+                            let msg = ConstellationMsg::ContentSizeChanged(self.id, size);
+                            if let Err(e) = self.constellation_chan.send(msg) {
+                                warn!("Sending content size to constellation failed ({}).", e);
+                            }
+                        }
+
+                        if let (Some(page_box), false) = (self.page_box, self.is_iframe) {
+                            let content_size = page_box.content_size();
+                            let page_starts = pagination::page_starts(
+                                layout_root.base().position.size.block,
+                                content_size.height,
+                            );
+                            let print_layout = PrintLayout {
+                                page_size: TypedSize2D::new(
+                                    page_box.size.width.to_f32_px(),
+                                    page_box.size.height.to_f32_px(),
+                                ),
+                                content_rect: TypedRect::new(
+                                    TypedPoint2D::new(
+                                        page_box.margins.left.to_f32_px(),
+                                        page_box.margins.top.to_f32_px(),
+                                    ),
+                                    TypedSize2D::new(
+                                        content_size.width.to_f32_px(),
+                                        content_size.height.to_f32_px(),
+                                    ),
+                                ),
+                                page_starts: page_starts
+                                    .iter()
+                                    .map(|start| start.to_f32_px())
+                                    .collect(),
+                            };
+                            let msg = ConstellationMsg::PrintLayout(self.id, print_layout);
+                            if let Err(e) = self.constellation_chan.send(msg) {
+                                warn!("Sending print layout to constellation failed ({}).", e);
+                            }
+                        }
+
                         if !build_state.iframe_sizes.is_empty() {
                             // build_state.iframe_sizes is only used here, so its okay to replace
                             // it with an empty vector
@@ -1055,6 +1290,11 @@ impl LayoutThread {
                             IndexableText::default(),
                         );
                         rw_data.display_list = Some(Arc::new(build_state.to_display_list()));
+
+                        let next_image_frame_at =
+                            layout_context.next_image_frame_at.lock().unwrap().take();
+                        self.set_next_image_frame_at(next_image_frame_at);
+                        self.svg_images.lock().unwrap().finish_display_list();
                     }
                 }
 
@@ -1117,6 +1357,10 @@ impl LayoutThread {
                 txn.generate_frame();
                 self.webrender_api
                     .send_transaction(self.webrender_document, txn);
+                self.svg_images
+                    .lock()
+                    .unwrap()
+                    .release_unused(&*self.image_cache);
             },
         );
     }
@@ -1186,6 +1430,9 @@ impl LayoutThread {
                         &QueryMsg::ElementInnerTextQuery(_) => {
                             rw_data.element_inner_text_response = String::new();
                         },
+                        &QueryMsg::CssAnimationsQuery(_) => {
+                            rw_data.css_animations_response = vec![];
+                        },
                     },
                     ReflowGoal::Full | ReflowGoal::TickAnimations => {},
                 }
@@ -1200,13 +1447,9 @@ impl LayoutThread {
         );
         trace!("{:?}", ShowSubtree(element.as_node()));
 
-        let initial_viewport = data.window_size.initial_viewport;
+        let mut initial_viewport = data.window_size.initial_viewport;
         let device_pixel_ratio = data.window_size.device_pixel_ratio;
         let old_viewport_size = self.viewport_size;
-        let current_screen_size = Size2D::new(
-            Au::from_f32_px(initial_viewport.width),
-            Au::from_f32_px(initial_viewport.height),
-        );
 
         // Calculate the actual viewport as per DEVICE-ADAPT § 6
         // If the entire flow tree is invalid, then it will be reflowed anyhow.
@@ -1222,7 +1465,28 @@ impl LayoutThread {
         };
 
         let had_used_viewport_units = self.stylist.device().used_viewport_units();
-        let device = Device::new(MediaType::screen(), initial_viewport, device_pixel_ratio);
+        let was_printing = self.page_box.take().is_some();
+        let media_type = if data.printing {
+            // The @page rules are matched against the window, and the document is then laid
+            // out in the area of the page inside its margins.
+            let device = Device::new(MediaType::print(), initial_viewport, device_pixel_ratio);
+            self.stylist.set_device(device, &guards);
+            let page_box = self.stylist.page_box(&guards, default_page_box());
+            let content_size = page_box.content_size();
+            initial_viewport = TypedSize2D::new(
+                content_size.width.to_f32_px(),
+                content_size.height.to_f32_px(),
+            );
+            self.page_box = Some(page_box);
+            MediaType::print()
+        } else {
+            MediaType::screen()
+        };
+        let current_screen_size = Size2D::new(
+            Au::from_f32_px(initial_viewport.width),
+            Au::from_f32_px(initial_viewport.height),
+        );
+        let device = Device::new(media_type, initial_viewport, device_pixel_ratio);
         let sheet_origins_affected_by_device_change = self.stylist.set_device(device, &guards);
 
         self.stylist
@@ -1286,7 +1550,7 @@ impl LayoutThread {
             }
         }
 
-        if viewport_size_changed {
+        if viewport_size_changed || was_printing != data.printing {
             if let Some(mut flow) = self.try_get_layout_root(element.as_node()) {
                 LayoutThread::reflow_all_nodes(FlowRef::deref_mut(&mut flow));
             }
@@ -1306,7 +1570,7 @@ impl LayoutThread {
             // Propagate the descendant bit up the ancestors. Do this before
             // the restyle calculation so that we can also do it for new
             // unstyled nodes, which the descendants bit helps us find.
-            if let Some(parent) = el.parent_element() {
+            if let Some(parent) = el.traversal_parent() {
                 unsafe { parent.note_dirty_descendant() };
             }
 
@@ -1335,6 +1599,23 @@ impl LayoutThread {
 
         self.stylist.flush(&guards, Some(element), Some(&map));
 
+        // The stylesheets of shadow trees are not part of the stylist, and are
+        // flushed separately.
+        document.flush_shadow_roots_stylesheets(
+            self.stylist.device(),
+            self.stylist.quirks_mode(),
+            guards.author,
+        );
+
+        // Changes to an element may affect the :has() selectors of its
+        // ancestors and their siblings, which the traversal can't reach from
+        // the element, so invalidate them now.
+        for el in &elements_with_snapshot {
+            if el.has_snapshot() {
+                invalidate_relative_selector_anchors(*el, &self.stylist, &map);
+            }
+        }
+
         // Create a layout context for use throughout the following passes.
         let mut layout_context = self.build_layout_context(guards.clone(), true, &map);
 
@@ -1532,6 +1813,14 @@ impl LayoutThread {
                     rw_data.element_inner_text_response =
                         process_element_inner_text_query(node, &rw_data.indexable_text);
                 },
+                &QueryMsg::CssAnimationsQuery(node) => {
+                    let node = node.map(|node| unsafe { ServoLayoutNode::new(&node) }.opaque());
+                    rw_data.css_animations_response = process_css_animations_query(
+                        node,
+                        &*self.running_animations.read(),
+                        &self.timer,
+                    );
+                },
             },
             ReflowGoal::Full | ReflowGoal::TickAnimations => {},
         }
@@ -1564,6 +1853,30 @@ impl LayoutThread {
         rw_data.scroll_offsets = layout_scroll_states
     }
 
+    /// Keeps track of when the animated images of the display list change
+    /// frames, letting the compositor know whether it needs to keep ticking
+    /// this layout thread for them.
+    fn set_next_image_frame_at(&self, next_image_frame_at: Option<f64>) {
+        let was_animating_images = self.next_image_frame_at.get().is_some();
+        self.next_image_frame_at.set(next_image_frame_at);
+        if was_animating_images == next_image_frame_at.is_some() ||
+            !self.running_animations.read().is_empty()
+        {
+            return;
+        }
+
+        let animation_state = if next_image_frame_at.is_some() {
+            AnimationState::AnimationsPresent
+        } else {
+            AnimationState::NoAnimationsPresent
+        };
+        self.constellation_chan
+            .send(ConstellationMsg::ChangeRunningAnimationsState(
+                self.id,
+                animation_state,
+            )).unwrap();
+    }
+
     fn tick_all_animations<'a, 'b>(&mut self, possibly_locked_rw_data: &mut RwData<'a, 'b>) {
         let mut rw_data = possibly_locked_rw_data.lock();
         self.tick_animations(&mut rw_data);
@@ -1582,6 +1895,19 @@ impl LayoutThread {
                 page_clip_rect: Rect::max_rect(),
             };
 
+            // The display list needs to be rebuilt when one of its animated
+            // images has to show its next frame.
+            if self
+                .next_image_frame_at
+                .get()
+                .map_or(false, |time| self.timer.seconds() >= time)
+            {
+                FlowRef::deref_mut(&mut root_flow)
+                    .mut_base()
+                    .restyle_damage
+                    .insert(ServoRestyleDamage::REPAINT);
+            }
+
             // Unwrap here should not panic since self.root_flow is only ever set to Some(_)
             // in handle_reflow() where self.document_shared_lock is as well.
             let author_shared_lock = self.document_shared_lock.clone().unwrap();
@@ -1649,6 +1975,7 @@ impl LayoutThread {
                 &self.new_animations_receiver,
                 self.id,
                 &self.timer,
+                self.next_image_frame_at.get().is_some(),
             );
         }
 
@@ -1669,6 +1996,12 @@ impl LayoutThread {
             },
         );
 
+        // Pagination moves boxes after they are laid out, so a document that is being printed
+        // is laid out again from scratch every time.
+        if self.page_box.is_some() && !self.is_iframe {
+            LayoutThread::reflow_all_nodes(FlowRef::deref_mut(root_flow));
+        }
+
         if opts::get().trace_layout {
             layout_debug::begin_trace(root_flow.clone());
         }
@@ -1718,6 +2051,13 @@ impl LayoutThread {
                         //Sequential mode
                         LayoutThread::solve_constraints(FlowRef::deref_mut(root_flow), &context)
                     }
+
+                    if let (Some(page_box), false) = (self.page_box, self.is_iframe) {
+                        pagination::paginate(
+                            FlowRef::deref_mut(root_flow),
+                            page_box.content_size().height,
+                        );
+                    }
                 },
             );
         }
@@ -1818,6 +2158,16 @@ impl ProfilerMetadataFactory for LayoutThread {
 // clearing the frame buffer to white. This ensures that setting a background
 // color on an iframe element, while the iframe content itself has a default
 // transparent background color is handled correctly.
+/// The page box used when printing a document that doesn't size its pages with `@page`:
+/// an A4 page with one centimeter margins.
+fn default_page_box() -> PageBox {
+    let mm = |mm: f32| Au::from_f32_px(mm * 96. / 25.4);
+    PageBox {
+        size: Size2D::new(mm(210.), mm(297.)),
+        margins: SideOffsets2D::new_all_same(mm(10.)),
+    }
+}
+
 fn get_root_flow_background_color(flow: &mut Flow) -> webrender_api::ColorF {
     let transparent = webrender_api::ColorF {
         r: 0.0,
@@ -794,8 +794,13 @@ where
         match self {
             Component::AttributeOther(ref attr_selector) => attr_selector.size_of(ops),
             Component::Negation(ref components) => components.size_of(ops),
+            Component::Is(ref selectors) |
+            Component::Where(ref selectors) |
+            Component::Has(ref selectors) => selectors.size_of(ops),
             Component::NonTSPseudoClass(ref pseudo) => (*pseudo).size_of(ops),
-            Component::Slotted(ref selector) | Component::Host(Some(ref selector)) => {
+            Component::Slotted(ref selector) |
+            Component::Host(Some(ref selector)) |
+            Component::HostContext(ref selector) => {
                 selector.size_of(ops)
             },
             Component::PseudoElement(ref pseudo) => (*pseudo).size_of(ops),
@@ -823,6 +828,7 @@ where
             Component::FirstOfType |
             Component::LastOfType |
             Component::OnlyOfType |
+            Component::RelativeSelectorAnchor |
             Component::Host(None) => 0,
         }
     }
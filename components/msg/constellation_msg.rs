@@ -8,6 +8,7 @@
 use std::cell::Cell;
 use std::fmt;
 use std::num::NonZeroU32;
+use uuid::Uuid;
 use webrender_api;
 
 #[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
@@ -397,6 +398,43 @@ impl fmt::Display for HistoryStateId {
     }
 }
 
+/// The id of a MessagePort. Ports keep their id when they are transferred to
+/// another global, which may live in any thread or process, so these are not
+/// allocated from a pipeline namespace.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct MessagePortId(Uuid);
+malloc_size_of_is_0!(MessagePortId);
+
+impl MessagePortId {
+    pub fn new() -> MessagePortId {
+        MessagePortId(Uuid::new_v4())
+    }
+}
+
+impl fmt::Display for MessagePortId {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}", self.0)
+    }
+}
+
+/// The id of the router through which the constellation delivers broadcasts
+/// to the BroadcastChannels of a global, which may be a worker.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct BroadcastChannelRouterId(Uuid);
+malloc_size_of_is_0!(BroadcastChannelRouterId);
+
+impl BroadcastChannelRouterId {
+    pub fn new() -> BroadcastChannelRouterId {
+        BroadcastChannelRouterId(Uuid::new_v4())
+    }
+}
+
+impl fmt::Display for BroadcastChannelRouterId {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}", self.0)
+    }
+}
+
 // We provide ids just for unit testing.
 pub const TEST_NAMESPACE: PipelineNamespaceId = PipelineNamespaceId(1234);
 #[allow(unsafe_code)]
@@ -12,6 +12,7 @@ extern crate malloc_size_of;
 extern crate malloc_size_of_derive;
 #[macro_use]
 extern crate serde;
+extern crate uuid;
 extern crate webrender_api;
 
 pub mod constellation_msg;
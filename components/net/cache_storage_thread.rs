@@ -0,0 +1,453 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! The storage of the caches of the Cache API.
+//!
+//! The caches of each origin are listed in their own index file, in the `cache_storage`
+//! directory of the config directory, and the body of each cached response is saved in a file
+//! of its own, in a directory named after the origin. Files are written to a temporary file
+//! which then replaces the previous one, so that an interrupted write doesn't lose the caches.
+
+use ipc_channel::ipc::{self, IpcReceiver, IpcSender};
+use net_traits::cache_storage_thread::{CacheEntry, CacheQueryOptions, CacheStorageError};
+use net_traits::cache_storage_thread::{CacheStorageThreadMsg, CachedRequest, CachedResponse};
+use resource_thread;
+use serde_json;
+use servo_url::ServoUrl;
+use std::borrow::ToOwned;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io;
+use std::io::prelude::*;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::thread;
+use url::Position;
+use uuid::Uuid;
+
+pub trait CacheStorageThreadFactory {
+    fn new(config_dir: Option<PathBuf>) -> Self;
+}
+
+impl CacheStorageThreadFactory for IpcSender<CacheStorageThreadMsg> {
+    /// Create a cache storage thread
+    fn new(config_dir: Option<PathBuf>) -> IpcSender<CacheStorageThreadMsg> {
+        let (chan, port) = ipc::channel().unwrap();
+        thread::Builder::new().name("CacheStorageManager".to_owned()).spawn(move || {
+            CacheStorageManager::new(port, config_dir).start();
+        }).expect("Thread spawning failed");
+        chan
+    }
+}
+
+/// A request-response pair of a cache, whose response body is saved separately.
+#[derive(Clone, Deserialize, Serialize)]
+struct StoredEntry {
+    request: CachedRequest,
+    /// The response, without its body.
+    response: CachedResponse,
+    /// The id of the body of the response.
+    body: String,
+}
+
+/// <https://w3c.github.io/ServiceWorker/#cache-objects>
+#[derive(Clone, Deserialize, Serialize)]
+struct Cache {
+    name: String,
+    entries: Vec<StoredEntry>,
+}
+
+/// The caches of an origin, in creation order.
+///
+/// <https://w3c.github.io/ServiceWorker/#name-to-cache-map>
+type Caches = Vec<Cache>;
+
+/// <https://w3c.github.io/ServiceWorker/#request-matches-cached-item-algorithm>
+fn request_matches_cached_item(query: &CachedRequest,
+                               entry: &StoredEntry,
+                               options: &CacheQueryOptions)
+                               -> bool {
+    // Step 1.
+    if !options.ignore_method && query.method != "GET" {
+        return false;
+    }
+
+    // Steps 2-5.
+    let end = if options.ignore_search { Position::AfterPath } else { Position::AfterQuery };
+    if query.url[..end] != entry.request.url[..end] {
+        return false;
+    }
+
+    // Step 6.
+    if options.ignore_vary {
+        return true;
+    }
+    let vary = entry.response.headers.iter()
+        .filter(|&&(ref name, _)| name.eq_ignore_ascii_case("vary"))
+        .flat_map(|&(_, ref value)| value.split(','))
+        .map(|field| field.trim());
+
+    // Steps 7-8.
+    for field in vary {
+        if field == "*" || query.header(field) != entry.request.header(field) {
+            return false;
+        }
+    }
+    true
+}
+
+/// <https://w3c.github.io/ServiceWorker/#query-cache>
+fn query_cache<'a>(cache: &'a Cache,
+                   query: Option<&CachedRequest>,
+                   options: &CacheQueryOptions)
+                   -> Vec<&'a StoredEntry> {
+    cache.entries.iter().filter(|entry| {
+        query.map_or(true, |query| request_matches_cached_item(query, entry, options))
+    }).collect()
+}
+
+/// The origin, escaping the characters which aren't allowed in file names everywhere, which
+/// names the index file and the directory of the bodies of its caches.
+fn escape_origin(origin: &str) -> String {
+    let mut name = String::new();
+    for byte in origin.bytes() {
+        if byte.is_ascii_alphanumeric() || byte == b'.' || byte == b'-' {
+            name.push(byte as char);
+        } else {
+            name.push_str(&format!("_{:02x}", byte));
+        }
+    }
+    name
+}
+
+fn storage_error(error: io::Error) -> CacheStorageError {
+    warn!("Couldn't save the caches: {}", error);
+    CacheStorageError::Storage(error.to_string())
+}
+
+fn read_caches(path: &Path) -> Result<Caches, String> {
+    let file = File::open(path).map_err(|error| error.to_string())?;
+    serde_json::from_reader(file).map_err(|error| error.to_string())
+}
+
+struct CacheStorageManager {
+    port: IpcReceiver<CacheStorageThreadMsg>,
+    /// The caches of the origins that were accessed so far, by origin.
+    caches: HashMap<String, Caches>,
+    /// The directory the caches are saved in.
+    directory: Option<PathBuf>,
+    /// The response bodies by id, when the caches aren't saved.
+    bodies: HashMap<String, Vec<u8>>,
+}
+
+impl CacheStorageManager {
+    fn new(port: IpcReceiver<CacheStorageThreadMsg>,
+           config_dir: Option<PathBuf>)
+           -> CacheStorageManager {
+        CacheStorageManager {
+            port: port,
+            caches: HashMap::new(),
+            directory: config_dir.map(|config_dir| config_dir.join("cache_storage")),
+            bodies: HashMap::new(),
+        }
+    }
+}
+
+impl CacheStorageManager {
+    fn start(&mut self) {
+        loop {
+            match self.port.recv().unwrap() {
+                CacheStorageThreadMsg::OpenCache(sender, url, name) => {
+                    self.open_cache(sender, url, name)
+                }
+                CacheStorageThreadMsg::HasCache(sender, url, name) => {
+                    self.has_cache(sender, url, name)
+                }
+                CacheStorageThreadMsg::DeleteCache(sender, url, name) => {
+                    self.delete_cache(sender, url, name)
+                }
+                CacheStorageThreadMsg::CacheNames(sender, url) => {
+                    self.cache_names(sender, url)
+                }
+                CacheStorageThreadMsg::Match(sender, url, name, request, options) => {
+                    self.match_entries(sender, url, name, request, options)
+                }
+                CacheStorageThreadMsg::Put(sender, url, name, entries) => {
+                    self.put(sender, url, name, entries)
+                }
+                CacheStorageThreadMsg::Delete(sender, url, name, request, options) => {
+                    self.delete(sender, url, name, request, options)
+                }
+                CacheStorageThreadMsg::Exit(sender) => {
+                    let _ = sender.send(());
+                    break;
+                }
+            }
+        }
+    }
+
+    /// The caches of an origin, read from disk on first access.
+    fn caches(&mut self, origin: &str) -> &mut Caches {
+        if !self.caches.contains_key(origin) {
+            let mut caches = vec![];
+            if let Some(ref directory) = self.directory {
+                let path = directory.join(format!("{}.json", escape_origin(origin)));
+                if path.exists() {
+                    match read_caches(&path) {
+                        Ok(saved) => caches = saved,
+                        Err(error) => warn!("Couldn't read the caches from {}: {}", path.display(), error),
+                    }
+                }
+            }
+            self.caches.insert(origin.to_owned(), caches);
+        }
+        self.caches.get_mut(origin).unwrap()
+    }
+
+    /// Saves the list of the caches of an origin.
+    fn save_index(&self, origin: &str) -> Result<(), CacheStorageError> {
+        let directory = match self.directory {
+            Some(ref directory) => directory,
+            None => return Ok(()),
+        };
+        let caches = match self.caches.get(origin) {
+            Some(caches) => caches,
+            None => return Ok(()),
+        };
+        let file_name = format!("{}.json", escape_origin(origin));
+        fs::create_dir_all(directory).and_then(|_| {
+            resource_thread::write_json_to_file_atomically(caches, directory, &file_name)
+        }).map_err(storage_error)
+    }
+
+    fn body_path(&self, origin: &str, id: &str) -> Option<PathBuf> {
+        self.directory.as_ref().map(|directory| directory.join(escape_origin(origin)).join(id))
+    }
+
+    fn write_body(&mut self, origin: &str, id: &str, body: Vec<u8>) -> Result<(), CacheStorageError> {
+        match self.body_path(origin, id) {
+            Some(path) => {
+                let directory = path.parent().unwrap().to_owned();
+                fs::create_dir_all(&directory).and_then(|_| {
+                    resource_thread::write_file_atomically(&path, &body)
+                }).map_err(storage_error)
+            },
+            None => {
+                self.bodies.insert(id.to_owned(), body);
+                Ok(())
+            },
+        }
+    }
+
+    fn read_body(&self, origin: &str, id: &str) -> Result<Vec<u8>, CacheStorageError> {
+        match self.body_path(origin, id) {
+            Some(path) => {
+                let mut body = vec![];
+                File::open(&path).and_then(|mut file| file.read_to_end(&mut body)).map_err(|error| {
+                    warn!("Couldn't read the cached response body {}: {}", path.display(), error);
+                    CacheStorageError::Storage(error.to_string())
+                })?;
+                Ok(body)
+            },
+            None => Ok(self.bodies.get(id).cloned().unwrap_or_default()),
+        }
+    }
+
+    fn remove_bodies<'a, I>(&mut self, origin: &str, entries: I)
+        where I: IntoIterator<Item = &'a StoredEntry>
+    {
+        for entry in entries {
+            match self.body_path(origin, &entry.body) {
+                Some(path) => {
+                    if let Err(error) = fs::remove_file(&path) {
+                        warn!("Couldn't remove the cached response body {}: {}", path.display(), error);
+                    }
+                },
+                None => {
+                    self.bodies.remove(&entry.body);
+                },
+            }
+        }
+    }
+
+    /// <https://w3c.github.io/ServiceWorker/#cache-storage-open>
+    fn open_cache(&mut self, sender: IpcSender<Result<(), CacheStorageError>>, url: ServoUrl, name: String) {
+        let origin = url.origin().ascii_serialization();
+        let created = {
+            let caches = self.caches(&origin);
+            if caches.iter().any(|cache| cache.name == name) {
+                false
+            } else {
+                caches.push(Cache { name: name, entries: vec![] });
+                true
+            }
+        };
+        let mut result = Ok(());
+        if created {
+            result = self.save_index(&origin);
+            if result.is_err() {
+                self.caches(&origin).pop();
+            }
+        }
+        sender.send(result).unwrap();
+    }
+
+    /// <https://w3c.github.io/ServiceWorker/#cache-storage-has>
+    fn has_cache(&mut self, sender: IpcSender<bool>, url: ServoUrl, name: String) {
+        let origin = url.origin().ascii_serialization();
+        let has_cache = self.caches(&origin).iter().any(|cache| cache.name == name);
+        sender.send(has_cache).unwrap();
+    }
+
+    /// <https://w3c.github.io/ServiceWorker/#cache-storage-delete>
+    fn delete_cache(&mut self, sender: IpcSender<Result<bool, CacheStorageError>>, url: ServoUrl, name: String) {
+        let origin = url.origin().ascii_serialization();
+        let deleted = {
+            let caches = self.caches(&origin);
+            let index = caches.iter().position(|cache| cache.name == name);
+            index.map(|index| (index, caches.remove(index)))
+        };
+        let result = match deleted {
+            Some((index, cache)) => {
+                match self.save_index(&origin) {
+                    Ok(()) => {
+                        self.remove_bodies(&origin, &cache.entries);
+                        Ok(true)
+                    },
+                    Err(error) => {
+                        self.caches(&origin).insert(index, cache);
+                        Err(error)
+                    },
+                }
+            },
+            None => Ok(false),
+        };
+        sender.send(result).unwrap();
+    }
+
+    /// <https://w3c.github.io/ServiceWorker/#cache-storage-keys>
+    fn cache_names(&mut self, sender: IpcSender<Vec<String>>, url: ServoUrl) {
+        let origin = url.origin().ascii_serialization();
+        let names = self.caches(&origin).iter().map(|cache| cache.name.clone()).collect();
+        sender.send(names).unwrap();
+    }
+
+    fn match_entries(&mut self,
+                     sender: IpcSender<Result<Vec<CacheEntry>, CacheStorageError>>,
+                     url: ServoUrl,
+                     name: Option<String>,
+                     request: Option<CachedRequest>,
+                     options: CacheQueryOptions) {
+        let origin = url.origin().ascii_serialization();
+        let matched: Vec<StoredEntry> = self.caches(&origin).iter()
+            .filter(|cache| name.as_ref().map_or(true, |name| cache.name == *name))
+            .flat_map(|cache| query_cache(cache, request.as_ref(), &options))
+            .cloned()
+            .collect();
+        let result = matched.into_iter().map(|entry| {
+            let mut response = entry.response;
+            response.body = self.read_body(&origin, &entry.body)?;
+            Ok(CacheEntry { request: entry.request, response: response })
+        }).collect();
+        sender.send(result).unwrap();
+    }
+
+    /// The put operations of <https://w3c.github.io/ServiceWorker/#batch-cache-operations>
+    fn put(&mut self,
+           sender: IpcSender<Result<(), CacheStorageError>>,
+           url: ServoUrl,
+           name: String,
+           entries: Vec<CacheEntry>) {
+        let origin = url.origin().ascii_serialization();
+        let result = self.put_entries(&origin, &name, entries);
+        sender.send(result).unwrap();
+    }
+
+    fn put_entries(&mut self, origin: &str, name: &str, entries: Vec<CacheEntry>) -> Result<(), CacheStorageError> {
+        if !self.caches(origin).iter().any(|cache| cache.name == name) {
+            return Err(CacheStorageError::NotFound);
+        }
+
+        // Save the bodies first, so that the index never refers to missing ones.
+        let mut stored_entries = vec![];
+        for entry in entries {
+            let mut response = entry.response;
+            let body = mem::replace(&mut response.body, vec![]);
+            let id = Uuid::new_v4().to_string();
+            if let Err(error) = self.write_body(origin, &id, body) {
+                self.remove_bodies(origin, &stored_entries);
+                return Err(error);
+            }
+            stored_entries.push(StoredEntry { request: entry.request, response: response, body: id });
+        }
+
+        let previous_entries = {
+            let cache = self.caches(origin).iter_mut().find(|cache| cache.name == name).unwrap();
+            let previous_entries = cache.entries.clone();
+            let options = CacheQueryOptions::default();
+            for entry in stored_entries.iter().cloned() {
+                cache.entries.retain(|cached| !request_matches_cached_item(&entry.request, cached, &options));
+                cache.entries.push(entry);
+            }
+            previous_entries
+        };
+        self.commit_entries(origin, name, previous_entries, &stored_entries)
+    }
+
+    /// Saves the index after the entries of the named cache changed from `previous_entries`,
+    /// removing the bodies which are no longer used, or restores them if saving failed, in which
+    /// case the bodies of `new_entries` are removed.
+    fn commit_entries(&mut self,
+                      origin: &str,
+                      name: &str,
+                      mut previous_entries: Vec<StoredEntry>,
+                      new_entries: &[StoredEntry])
+                      -> Result<(), CacheStorageError> {
+        match self.save_index(origin) {
+            Ok(()) => {
+                let current_bodies: Vec<String> = self.caches(origin).iter()
+                    .filter(|cache| cache.name == name)
+                    .flat_map(|cache| cache.entries.iter().map(|entry| entry.body.clone()))
+                    .collect();
+                previous_entries.retain(|entry| !current_bodies.contains(&entry.body));
+                self.remove_bodies(origin, &previous_entries);
+                Ok(())
+            },
+            Err(error) => {
+                if let Some(cache) = self.caches(origin).iter_mut().find(|cache| cache.name == name) {
+                    cache.entries = previous_entries;
+                }
+                self.remove_bodies(origin, new_entries);
+                Err(error)
+            },
+        }
+    }
+
+    /// The delete operations of <https://w3c.github.io/ServiceWorker/#batch-cache-operations>
+    fn delete(&mut self,
+              sender: IpcSender<Result<bool, CacheStorageError>>,
+              url: ServoUrl,
+              name: String,
+              request: CachedRequest,
+              options: CacheQueryOptions) {
+        let origin = url.origin().ascii_serialization();
+        let previous_entries = match self.caches(&origin).iter_mut().find(|cache| cache.name == name) {
+            Some(cache) => {
+                let previous_entries = cache.entries.clone();
+                cache.entries.retain(|cached| !request_matches_cached_item(&request, cached, &options));
+                if cache.entries.len() != previous_entries.len() {
+                    Some(previous_entries)
+                } else {
+                    None
+                }
+            },
+            None => None,
+        };
+        let result = match previous_entries {
+            Some(previous_entries) => self.commit_entries(&origin, &name, previous_entries, &[]).map(|_| true),
+            None => Ok(false),
+        };
+        sender.send(result).unwrap();
+    }
+}
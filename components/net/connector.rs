@@ -2,7 +2,6 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use hosts::replace_host;
 use hyper::client::Pool;
 use hyper::error::{Result as HyperResult, Error as HyperError};
 use hyper::net::{NetworkConnector, HttpsStream, HttpStream, SslClient};
@@ -10,17 +9,19 @@ use hyper_openssl::OpensslClient;
 use openssl::ssl::{SSL_OP_NO_COMPRESSION, SSL_OP_NO_SSLV2, SSL_OP_NO_SSLV3};
 use openssl::ssl::{SslConnector, SslConnectorBuilder, SslMethod};
 use openssl::x509;
+use proxy::{self, ProxyConfig};
 use std::io;
-use std::net::TcpStream;
 
 pub struct HttpsConnector {
     ssl: OpensslClient,
+    proxy: Option<ProxyConfig>,
 }
 
 impl HttpsConnector {
-    fn new(ssl: OpensslClient) -> HttpsConnector {
+    fn new(ssl: OpensslClient, proxy: Option<ProxyConfig>) -> HttpsConnector {
         HttpsConnector {
             ssl: ssl,
+            proxy: proxy,
         }
     }
 }
@@ -34,9 +35,9 @@ impl NetworkConnector for HttpsConnector {
                                                      "Invalid scheme for Http")));
         }
 
-        // Perform host replacement when making the actual TCP connection.
-        let addr = &(&*replace_host(host), port);
-        let stream = HttpStream(TcpStream::connect(addr)?);
+        // Perform host replacement when making the actual TCP connection, which goes through
+        // the configured proxy, if any.
+        let stream = HttpStream(proxy::connect(self.proxy.as_ref(), host, port, scheme)?);
 
         if scheme == "http" {
             Ok(HttpsStream::Http(stream))
@@ -87,8 +88,8 @@ pub fn create_ssl_client(certs: &str) -> OpensslClient {
     OpensslClient::from(ssl_connector)
 }
 
-pub fn create_http_connector(ssl_client: OpensslClient) -> Pool<Connector> {
-    let https_connector = HttpsConnector::new(ssl_client);
+pub fn create_http_connector(ssl_client: OpensslClient, proxy: Option<ProxyConfig>) -> Pool<Connector> {
+    let https_connector = HttpsConnector::new(ssl_client, proxy);
     Pool::with_connector(Default::default(), https_connector)
 }
 
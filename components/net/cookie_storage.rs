@@ -172,6 +172,16 @@ impl CookieStorage {
         }
     }
 
+    /// Removes the cookies that would be sent along with a request to the given url,
+    /// or only the one of them with the given name.
+    pub fn delete_cookies_for_url(&mut self, url: &ServoUrl, name: Option<&str>) {
+        let domain = reg_host(url.host_str().unwrap_or(""));
+        let cookies = self.cookies_map.entry(domain).or_insert(vec![]);
+        cookies.retain(|c| {
+            !c.appropriate_for_url(url, CookieSource::HTTP) || name.map_or(false, |name| c.cookie.name() != name)
+        });
+    }
+
     pub fn cookies_data_for_url<'a>(&'a mut self,
                                     url: &'a ServoUrl,
                                     source: CookieSource)
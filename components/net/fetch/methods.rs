@@ -15,9 +15,10 @@ use hyper::header::{Header, HeaderFormat, HeaderView, Headers, Referer as Refere
 use hyper::method::Method;
 use hyper::mime::{Mime, SubLevel, TopLevel};
 use hyper::status::StatusCode;
-use ipc_channel::ipc::IpcReceiver;
+use ipc_channel::ipc::{IpcReceiver, IpcSender};
 use mime_guess::guess_mime_type;
-use net_traits::{FetchTaskTarget, NetworkError, ReferrerPolicy};
+use net_traits::{CustomResponseMediator, FetchTaskTarget, NetworkError, ReferrerPolicy};
+use net_traits::csp::CheckResult;
 use net_traits::request::{CredentialsMode, Destination, Referrer, Request, RequestMode};
 use net_traits::request::{ResponseTainting, Origin, Window};
 use net_traits::response::{Response, ResponseBody, ResponseType};
@@ -50,6 +51,8 @@ pub struct FetchContext {
     pub devtools_chan: Option<Sender<DevtoolsControlMsg>>,
     pub filemanager: FileManager,
     pub cancellation_listener: Arc<Mutex<CancellationListener>>,
+    /// The channel to the service worker manager, to let service workers respond to requests.
+    pub swmanager_chan: Option<IpcSender<CustomResponseMediator>>,
 }
 
 pub struct CancellationListener {
@@ -66,18 +69,17 @@ impl CancellationListener {
     }
 
     pub fn cancelled(&mut self) -> bool {
-        if let Some(ref cancel_chan) = self.cancel_chan {
-            if self.cancelled {
-                true
-            } else if cancel_chan.try_recv().is_ok() {
-                self.cancelled = true;
-                true
-            } else {
-                false
+        if !self.cancelled {
+            if let Some(ref cancel_chan) = self.cancel_chan {
+                self.cancelled = cancel_chan.try_recv().is_ok();
             }
-        } else {
-            false
         }
+        self.cancelled
+    }
+
+    /// Cancels the fetch, as for `CoreResourceMsg::Cancel`.
+    pub fn cancel(&mut self) {
+        self.cancelled = true;
     }
 }
 pub type DoneChannel = Option<(Sender<Data>, Receiver<Data>)>;
@@ -147,7 +149,8 @@ pub fn main_fetch(request: &mut Request,
     }
 
     // Step 3.
-    // TODO: handle content security policy violations.
+    // Violations of report-only policies are reported in Step 5, together with the
+    // violations of enforced policies.
 
     // Step 4.
     // TODO: handle upgrade to a potentially secure URL.
@@ -157,7 +160,16 @@ pub fn main_fetch(request: &mut Request,
         response = Some(Response::network_error(NetworkError::Internal("Request attempted on bad port".into())));
     }
     // TODO: handle blocking as mixed content.
-    // TODO: handle blocking by content security policy.
+    if let Some(ref csp_list) = request.csp_list {
+        let (result, violations) = csp_list.should_request_be_blocked(request);
+        if !violations.is_empty() {
+            target.process_csp_violations(request, violations);
+        }
+        if result == CheckResult::Blocked {
+            response = Some(Response::network_error(
+                NetworkError::Internal("Blocked by Content Security Policy".into())));
+        }
+    }
 
     // Step 6
     // TODO: handle request's client's referrer policy.
@@ -414,6 +426,7 @@ pub fn main_fetch(request: &mut Request,
     if !response.is_network_error() {
         if let Ok(mut http_cache) = context.state.http_cache.write() {
             http_cache.update_awaiting_consumers(&request, &response);
+            http_cache.write_to_disk(&request);
         }
     }
 
@@ -5,9 +5,10 @@
 #![deny(missing_docs)]
 
 //! A memory cache implementing the logic specified in <http://tools.ietf.org/html/rfc7234>
-//! and <http://tools.ietf.org/html/rfc7232>.
+//! and <http://tools.ietf.org/html/rfc7232>, optionally backed by a disk cache.
 
 use fetch::methods::{Data, DoneChannel};
+use http_disk_cache::{DiskCache, DiskCacheEntry};
 use hyper::header;
 use hyper::header::ContentType;
 use hyper::header::Headers;
@@ -24,6 +25,7 @@ use servo_channel::{Sender, channel};
 use servo_config::prefs::PREFS;
 use servo_url::ServoUrl;
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::str;
 use std::sync::Mutex;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -76,6 +78,8 @@ struct MeasurableCachedResource {
     url_list: Vec<ServoUrl>,
     expires: Duration,
     last_validated: Tm,
+    /// The name of the file holding the body in the disk cache, if it was stored there.
+    disk_file: Option<String>,
 }
 
 impl MallocSizeOf for CachedResource {
@@ -130,6 +134,9 @@ pub struct CachedResponse {
 pub struct HttpCache {
     /// cached responses.
     entries: HashMap<CacheKey, Vec<CachedResource>>,
+    /// The disk cache backing this cache, if any.
+    #[ignore_malloc_size_of = "Stored on disk"]
+    disk_cache: Option<DiskCache>,
 }
 
 
@@ -358,10 +365,68 @@ fn create_resource_with_bytes_from_resource(bytes: &[u8], resource: &CachedResou
             url_list: resource.data.url_list.clone(),
             expires: resource.data.expires.clone(),
             last_validated: resource.data.last_validated.clone(),
+            disk_file: None,
         })
     }
 }
 
+/// Describe a resource, so that it can be stored in the disk cache.
+fn disk_cache_entry(key: &CacheKey, resource: &CachedResource) -> DiskCacheEntry {
+    let metadata = &resource.data.metadata;
+    DiskCacheEntry {
+        url: key.url(),
+        request_headers: Serde(resource.request_headers.lock().unwrap().clone()),
+        headers: Serde(metadata.headers.lock().unwrap().clone()),
+        final_url: metadata.data.final_url.clone(),
+        content_type: metadata.data.content_type.clone(),
+        charset: metadata.data.charset.clone(),
+        status: metadata.data.status.clone(),
+        location_url: resource.data.location_url.clone(),
+        https_state: resource.data.https_state,
+        raw_status: resource.data.raw_status.clone(),
+        url_list: resource.data.url_list.clone(),
+        expires: resource.data.expires.num_seconds(),
+        last_validated: resource.data.last_validated.to_timespec().sec,
+    }
+}
+
+/// Update the description of a resource in the disk cache, if it is stored there.
+fn update_on_disk(disk_cache: &Option<DiskCache>, key: &CacheKey, resource: &CachedResource) {
+    if let (&Some(ref disk_cache), &Some(ref file_name)) = (disk_cache, &resource.data.disk_file) {
+        disk_cache.update(file_name, disk_cache_entry(key, resource));
+    }
+}
+
+/// Create a resource from its description in the disk cache. Its body is
+/// only read from disk when a response is constructed from it.
+fn resource_from_disk_cache_entry(file_name: String, entry: DiskCacheEntry) -> CachedResource {
+    CachedResource {
+        request_headers: Arc::new(Mutex::new(entry.request_headers.into_inner())),
+        body: Arc::new(Mutex::new(ResponseBody::Empty)),
+        aborted: Arc::new(AtomicBool::new(false)),
+        awaiting_body: Arc::new(Mutex::new(vec![])),
+        data: Measurable(MeasurableCachedResource {
+            metadata: CachedMetadata {
+                headers: Arc::new(Mutex::new(entry.headers.into_inner())),
+                data: Measurable(MeasurableCachedMetadata {
+                    final_url: entry.final_url,
+                    content_type: entry.content_type,
+                    charset: entry.charset,
+                    status: entry.status,
+                }),
+            },
+            location_url: entry.location_url,
+            https_state: entry.https_state,
+            status: entry.raw_status.as_ref().map(|&(code, _)| StatusCode::from_u16(code)),
+            raw_status: entry.raw_status,
+            url_list: entry.url_list,
+            expires: Duration::seconds(entry.expires),
+            last_validated: time::at(time::Timespec::new(entry.last_validated, 0)),
+            disk_file: Some(file_name),
+        }),
+    }
+}
+
 /// Support for range requests <https://tools.ietf.org/html/rfc7233>.
 fn handle_range_request(request: &Request,
     candidates: Vec<&CachedResource>,
@@ -519,10 +584,44 @@ impl HttpCache {
     /// Create a new memory cache instance.
     pub fn new() -> HttpCache {
         HttpCache {
-            entries: HashMap::new()
+            entries: HashMap::new(),
+            disk_cache: None,
         }
     }
 
+    /// Create a new cache instance, backed by the disk cache stored in the given directory,
+    /// whose bodies can take up to `capacity` bytes.
+    pub fn new_with_disk_cache(directory: PathBuf, capacity: u64) -> HttpCache {
+        let disk_cache = DiskCache::new(directory, capacity);
+        let mut entries = HashMap::new();
+        for (file_name, entry) in disk_cache.entries() {
+            let key = CacheKey::from_servo_url(&entry.url);
+            let resource = resource_from_disk_cache_entry(file_name, entry);
+            entries.entry(key).or_insert(vec![]).push(resource);
+        }
+        HttpCache {
+            entries: entries,
+            disk_cache: Some(disk_cache),
+        }
+    }
+
+    /// Read the body of a resource from the disk cache, if it is stored there and hasn't
+    /// been read yet. Returns false if the body is missing.
+    fn load_body_from_disk(&self, resource: &CachedResource) -> bool {
+        let (disk_cache, file_name) = match (&self.disk_cache, &resource.data.disk_file) {
+            (&Some(ref disk_cache), &Some(ref file_name)) => (disk_cache, file_name),
+            _ => return true,
+        };
+        let mut body = resource.body.lock().unwrap();
+        if let ResponseBody::Empty = *body {
+            match disk_cache.read_body(file_name) {
+                Some(bytes) => *body = ResponseBody::Done(bytes),
+                None => return false,
+            }
+        }
+        true
+    }
+
     /// Constructing Responses from Caches.
     /// <https://tools.ietf.org/html/rfc7234#section-4>
     pub fn construct_response(&self, request: &Request, done_chan: &mut DoneChannel) -> Option<CachedResponse> {
@@ -532,7 +631,9 @@ impl HttpCache {
             return None;
         }
         let entry_key = CacheKey::new(request.clone());
-        let resources = self.entries.get(&entry_key)?.into_iter().filter(|r| { !r.aborted.load(Ordering::Relaxed) });
+        let resources = self.entries.get(&entry_key)?.into_iter().filter(|r| {
+            !r.aborted.load(Ordering::Relaxed) && self.load_body_from_disk(r)
+        });
         let mut candidates = vec![];
         for cached_resource in resources {
             let mut can_be_constructed = true;
@@ -617,6 +718,75 @@ impl HttpCache {
         }
     }
 
+    /// Store the completed bodies of the resources cached for a request in the disk cache,
+    /// if this cache is backed by one. The files are written by the thread of the disk cache.
+    pub fn write_to_disk(&mut self, request: &Request) {
+        let disk_cache = match self.disk_cache {
+            Some(ref disk_cache) => disk_cache,
+            None => return,
+        };
+        let entry_key = CacheKey::new(request.clone());
+        let mut evicted = vec![];
+        if let Some(cached_resources) = self.entries.get_mut(&entry_key) {
+            for cached_resource in cached_resources.iter_mut() {
+                let aborted = cached_resource.aborted.load(Ordering::Relaxed);
+                if aborted || cached_resource.data.disk_file.is_some() {
+                    continue;
+                }
+                let stored = match *cached_resource.body.lock().unwrap() {
+                    ResponseBody::Done(ref body) => {
+                        disk_cache.store(disk_cache_entry(&entry_key, cached_resource), body)
+                    },
+                    _ => continue,
+                };
+                if let Some((file_name, evicted_files)) = stored {
+                    cached_resource.data.disk_file = Some(file_name);
+                    evicted.extend(evicted_files);
+                }
+            }
+        }
+        if evicted.is_empty() {
+            return;
+        }
+        // The evicted resources whose body wasn't read yet can't be used anymore.
+        for cached_resources in self.entries.values_mut() {
+            cached_resources.retain(|resource| {
+                match resource.data.disk_file {
+                    Some(ref file_name) if evicted.contains(file_name) => {
+                        match *resource.body.lock().unwrap() {
+                            ResponseBody::Empty => false,
+                            _ => true,
+                        }
+                    },
+                    _ => true,
+                }
+            });
+            for resource in cached_resources.iter_mut() {
+                let was_evicted = resource.data.disk_file.as_ref().map_or(false, |file_name| {
+                    evicted.contains(file_name)
+                });
+                if was_evicted {
+                    resource.data.disk_file = None;
+                }
+            }
+        }
+    }
+
+    /// Remove all the cached responses, including the ones stored on disk.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        if let Some(ref disk_cache) = self.disk_cache {
+            disk_cache.clear();
+        }
+    }
+
+    /// Write the state of the disk cache, if any, so that it is used after a restart.
+    pub fn flush(&self) {
+        if let Some(ref disk_cache) = self.disk_cache {
+            disk_cache.flush();
+        }
+    }
+
     /// Freshening Stored Responses upon Validation.
     /// <https://tools.ietf.org/html/rfc7234#section-4.3.4>
     pub fn refresh(&mut self, request: &Request, response: Response, done_chan: &mut DoneChannel) -> Option<Response> {
@@ -653,9 +823,12 @@ impl HttpCache {
                 constructed_response.raw_status = cached_resource.data.raw_status.clone();
                 constructed_response.url_list = cached_resource.data.url_list.clone();
                 cached_resource.data.expires = get_response_expiry(&constructed_response);
-                let mut stored_headers = cached_resource.data.metadata.headers.lock().unwrap();
-                stored_headers.extend(response.headers.iter());
-                constructed_response.headers = stored_headers.clone();
+                {
+                    let mut stored_headers = cached_resource.data.metadata.headers.lock().unwrap();
+                    stored_headers.extend(response.headers.iter());
+                    constructed_response.headers = stored_headers.clone();
+                }
+                update_on_disk(&self.disk_cache, &entry_key, cached_resource);
                 return Some(constructed_response);
             }
         }
@@ -667,6 +840,7 @@ impl HttpCache {
         if let Some(cached_resources) = self.entries.get_mut(&entry_key) {
             for cached_resource in cached_resources.iter_mut() {
                 cached_resource.data.expires = Duration::seconds(0i64);
+                update_on_disk(&self.disk_cache, &entry_key, cached_resource);
             }
         }
     }
@@ -734,7 +908,8 @@ impl HttpCache {
                 raw_status: response.raw_status.clone(),
                 url_list: response.url_list.clone(),
                 expires: expiry,
-                last_validated: time::now()
+                last_validated: time::now(),
+                disk_file: None,
             })
         };
         let entry = self.entries.entry(entry_key).or_insert(vec![]);
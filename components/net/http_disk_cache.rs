@@ -0,0 +1,381 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#![deny(missing_docs)]
+
+//! The on-disk storage of the HTTP cache, which lets cached responses survive restarts.
+//!
+//! Bodies are stored in their own files, next to an index describing the stored responses.
+//! The total size of the bodies is kept under a capacity, by evicting the least recently
+//! used responses.
+//!
+//! Files are written by a thread of the disk cache, so that fetches don't wait on them. The
+//! index is replaced atomically, only once the bodies it lists have been written, and at most
+//! once for all the changes that were made while it was being written.
+
+use hyper::header::{ContentType, Headers};
+use hyper_serde::Serde;
+use net_traits::response::HttpsState;
+use resource_thread::write_file_atomically;
+use serde_json;
+use servo_channel::{Receiver, Sender, channel};
+use servo_url::ServoUrl;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use uuid::Uuid;
+
+/// The name of the file holding the index of the disk cache.
+const INDEX_FILE_NAME: &'static str = "index.json";
+
+/// A response stored in the disk cache, as described by its index.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct DiskCacheEntry {
+    /// The URL of the request the response was stored for.
+    pub url: ServoUrl,
+    /// The headers of the request the response was stored for, used to match Vary headers.
+    pub request_headers: Serde<Headers>,
+    /// The headers of the response.
+    pub headers: Serde<Headers>,
+    /// Final URL after redirects.
+    pub final_url: ServoUrl,
+    /// MIME type / subtype.
+    pub content_type: Option<Serde<ContentType>>,
+    /// Character set.
+    pub charset: Option<String>,
+    /// The status of the response metadata.
+    pub status: Option<(u16, Vec<u8>)>,
+    /// The location URL of the response, if any.
+    pub location_url: Option<Result<ServoUrl, String>>,
+    /// The HTTPS state of the response.
+    pub https_state: HttpsState,
+    /// The raw status of the response.
+    pub raw_status: Option<(u16, Vec<u8>)>,
+    /// The URL list of the response.
+    pub url_list: Vec<ServoUrl>,
+    /// The freshness lifetime of the response, in seconds.
+    pub expires: i64,
+    /// When the response was last validated, in seconds since the epoch.
+    pub last_validated: i64,
+}
+
+/// An entry of the index, along with what is needed to evict it.
+#[derive(Deserialize, Serialize)]
+struct IndexEntry {
+    entry: DiskCacheEntry,
+    /// The size of the stored body, in bytes.
+    size: u64,
+    /// The value of the index clock when the response was last used.
+    last_used: u64,
+    /// Whether the body hasn't been written yet, in which case the entry is left out of the
+    /// index on disk.
+    #[serde(skip)]
+    pending: bool,
+}
+
+/// The index of the disk cache, by body file name.
+#[derive(Default, Deserialize, Serialize)]
+struct Index {
+    entries: HashMap<String, IndexEntry>,
+    /// Incremented whenever a response is used, to order responses by recency.
+    clock: u64,
+}
+
+/// The index as it is written to disk, without the entries whose body isn't written yet.
+#[derive(Serialize)]
+struct WrittenIndex<'a> {
+    entries: HashMap<&'a String, &'a IndexEntry>,
+    clock: u64,
+}
+
+impl Index {
+    fn total_size(&self) -> u64 {
+        self.entries.values().map(|entry| entry.size).sum()
+    }
+
+    fn written(&self) -> WrittenIndex {
+        WrittenIndex {
+            entries: self.entries.iter().filter(|&(_, entry)| !entry.pending).collect(),
+            clock: self.clock,
+        }
+    }
+}
+
+/// A message to the thread writing the files of the disk cache.
+enum WriterMsg {
+    /// Write a body to the file with the given name.
+    WriteBody(String, Vec<u8>),
+    /// Remove the body file with the given name.
+    RemoveFile(String),
+    /// Write the index, once the messages sent before this one have been handled.
+    WriteIndex,
+    /// Write the index, then reply once it is written.
+    Flush(Sender<()>),
+    /// Write the index, then stop.
+    Exit,
+}
+
+/// The thread writing the files of the disk cache.
+struct Writer {
+    /// The directory holding the index and the bodies.
+    directory: PathBuf,
+    index: Arc<Mutex<Index>>,
+    receiver: Receiver<WriterMsg>,
+}
+
+impl Writer {
+    fn run(&self) {
+        while let Some(msg) = self.receiver.recv() {
+            // Handle all the messages that are already waiting before writing the index,
+            // so that it is written once for all of them.
+            let mut write_index = false;
+            let mut flushes = vec![];
+            let mut exit = false;
+            let mut next = Some(msg);
+            while let Some(msg) = next {
+                match msg {
+                    WriterMsg::WriteBody(file_name, body) => self.write_body(&file_name, &body),
+                    WriterMsg::RemoveFile(file_name) => remove_file(&self.directory, &file_name),
+                    WriterMsg::WriteIndex => write_index = true,
+                    WriterMsg::Flush(sender) => {
+                        write_index = true;
+                        flushes.push(sender);
+                    },
+                    WriterMsg::Exit => {
+                        write_index = true;
+                        exit = true;
+                    },
+                }
+                next = if exit { None } else { self.receiver.try_recv() };
+            }
+            if write_index {
+                self.write_index();
+            }
+            for sender in flushes {
+                let _ = sender.send(());
+            }
+            if exit {
+                return;
+            }
+        }
+    }
+
+    /// Write a body, then list it in the index. A body that couldn't be written is dropped
+    /// from the index.
+    fn write_body(&self, file_name: &str, body: &[u8]) {
+        let path = self.directory.join(file_name);
+        let result = File::create(&path).and_then(|mut file| file.write_all(body));
+        let mut index = self.index.lock().unwrap();
+        if let Err(error) = result {
+            warn!("Couldn't write cached body {}: {}", file_name, error);
+            index.entries.remove(file_name);
+            drop(index);
+            return remove_file(&self.directory, file_name);
+        }
+        if let Some(entry) = index.entries.get_mut(file_name) {
+            entry.pending = false;
+        }
+    }
+
+    fn write_index(&self) {
+        let json = {
+            let index = self.index.lock().unwrap();
+            match serde_json::to_vec(&index.written()) {
+                Ok(json) => json,
+                Err(_) => return,
+            }
+        };
+        let path = self.directory.join(INDEX_FILE_NAME);
+        if let Err(error) = write_file_atomically(&path, &json) {
+            warn!("Couldn't write the HTTP cache index {}: {}", path.display(), error);
+        }
+    }
+}
+
+fn remove_file(directory: &PathBuf, file_name: &str) {
+    if let Err(error) = fs::remove_file(directory.join(file_name)) {
+        warn!("Couldn't remove cached body {}: {}", file_name, error);
+    }
+}
+
+/// The on-disk storage of an HTTP cache.
+pub struct DiskCache {
+    /// The directory holding the index and the bodies.
+    directory: PathBuf,
+    /// The maximum total size of the stored bodies, in bytes.
+    capacity: u64,
+    index: Arc<Mutex<Index>>,
+    /// The channel to the thread writing the files of the cache.
+    writer_chan: Sender<WriterMsg>,
+    writer_thread: Option<JoinHandle<()>>,
+}
+
+impl DiskCache {
+    /// Open the disk cache stored in the given directory, creating it if needed.
+    pub fn new(directory: PathBuf, capacity: u64) -> DiskCache {
+        if let Err(error) = fs::create_dir_all(&directory) {
+            warn!("Couldn't create the HTTP cache directory {}: {}", directory.display(), error);
+        }
+        let index = match File::open(directory.join(INDEX_FILE_NAME)) {
+            Ok(mut file) => {
+                let mut buffer = String::new();
+                match file.read_to_string(&mut buffer).ok().and_then(|_| serde_json::from_str(&buffer).ok()) {
+                    Some(index) => index,
+                    None => {
+                        warn!("Couldn't read the HTTP cache index, clearing the cache");
+                        Index::default()
+                    },
+                }
+            },
+            Err(_) => Index::default(),
+        };
+        remove_unindexed_files(&directory, &index);
+
+        let index = Arc::new(Mutex::new(index));
+        let (writer_chan, receiver) = channel();
+        let writer = Writer {
+            directory: directory.clone(),
+            index: index.clone(),
+            receiver: receiver,
+        };
+        let writer_thread = thread::Builder::new()
+            .name("HttpDiskCacheWriter".to_owned())
+            .spawn(move || writer.run())
+            .expect("Thread spawning failed");
+        DiskCache {
+            directory: directory,
+            capacity: capacity,
+            index: index,
+            writer_chan: writer_chan,
+            writer_thread: Some(writer_thread),
+        }
+    }
+
+    /// The responses stored in the cache, by body file name.
+    pub fn entries(&self) -> Vec<(String, DiskCacheEntry)> {
+        let index = self.index.lock().unwrap();
+        index.entries.iter().map(|(file_name, entry)| (file_name.clone(), entry.entry.clone())).collect()
+    }
+
+    /// Read the body stored in the given file, marking its response as used.
+    /// Returns `None` if the response was evicted, or its body couldn't be read.
+    pub fn read_body(&self, file_name: &str) -> Option<Vec<u8>> {
+        {
+            let mut index = self.index.lock().unwrap();
+            index.clock += 1;
+            let clock = index.clock;
+            index.entries.get_mut(file_name)?.last_used = clock;
+        }
+        let mut body = vec![];
+        match File::open(self.directory.join(file_name)).and_then(|mut file| file.read_to_end(&mut body)) {
+            Ok(_) => Some(body),
+            Err(error) => {
+                warn!("Couldn't read cached body {}: {}", file_name, error);
+                None
+            },
+        }
+    }
+
+    /// Store a response and its body. Returns the name of the file of the body, along with
+    /// the ones of the responses that were evicted to make room for it, or `None` if the
+    /// response is too large to be stored. The body is written to the file later on.
+    pub fn store(&self, entry: DiskCacheEntry, body: &[u8]) -> Option<(String, Vec<String>)> {
+        let size = body.len() as u64;
+        if size > self.capacity {
+            return None;
+        }
+        let file_name = Uuid::new_v4().simple().to_string();
+
+        let mut index = self.index.lock().unwrap();
+        let mut evicted = vec![];
+        let mut total_size = index.total_size();
+        while total_size + size > self.capacity {
+            let least_recently_used = index.entries
+                .iter()
+                .min_by_key(|&(_, entry)| entry.last_used)
+                .map(|(file_name, _)| file_name.clone());
+            let file_name = match least_recently_used {
+                Some(file_name) => file_name,
+                None => break,
+            };
+            if let Some(entry) = index.entries.remove(&file_name) {
+                total_size -= entry.size;
+            }
+            self.send(WriterMsg::RemoveFile(file_name.clone()));
+            evicted.push(file_name);
+        }
+
+        index.clock += 1;
+        let clock = index.clock;
+        index.entries.insert(file_name.clone(), IndexEntry {
+            entry: entry,
+            size: size,
+            last_used: clock,
+            pending: true,
+        });
+        self.send(WriterMsg::WriteBody(file_name.clone(), body.to_vec()));
+        self.send(WriterMsg::WriteIndex);
+        Some((file_name, evicted))
+    }
+
+    /// Replace the description of a stored response, after it was refreshed or invalidated.
+    pub fn update(&self, file_name: &str, entry: DiskCacheEntry) {
+        let mut index = self.index.lock().unwrap();
+        if let Some(index_entry) = index.entries.get_mut(file_name) {
+            index_entry.entry = entry;
+            self.send(WriterMsg::WriteIndex);
+        }
+    }
+
+    /// Remove all the stored responses.
+    pub fn clear(&self) {
+        let mut index = self.index.lock().unwrap();
+        for file_name in index.entries.keys() {
+            self.send(WriterMsg::RemoveFile(file_name.clone()));
+        }
+        *index = Index::default();
+        self.send(WriterMsg::WriteIndex);
+    }
+
+    /// Write the index to disk, to keep the recency of the responses across restarts, and
+    /// wait for all the changes made so far to be written.
+    pub fn flush(&self) {
+        let (sender, receiver) = channel();
+        self.send(WriterMsg::Flush(sender));
+        let _ = receiver.recv();
+    }
+
+    fn send(&self, msg: WriterMsg) {
+        if self.writer_chan.send(msg).is_err() {
+            warn!("The HTTP disk cache writer thread is gone");
+        }
+    }
+}
+
+impl Drop for DiskCache {
+    fn drop(&mut self) {
+        self.send(WriterMsg::Exit);
+        if let Some(writer_thread) = self.writer_thread.take() {
+            let _ = writer_thread.join();
+        }
+    }
+}
+
+/// Remove the bodies that aren't described by the index, such as the ones
+/// written before a crash, or all of them if the index was lost.
+fn remove_unindexed_files(directory: &PathBuf, index: &Index) {
+    let files = match fs::read_dir(directory) {
+        Ok(files) => files,
+        Err(_) => return,
+    };
+    for file in files.filter_map(Result::ok) {
+        let file_name = file.file_name().to_string_lossy().into_owned();
+        if file_name != INDEX_FILE_NAME && !index.entries.contains_key(&file_name) {
+            remove_file(directory, &file_name);
+        }
+    }
+}
@@ -16,6 +16,7 @@ use hsts::HstsList;
 use http_cache::HttpCache;
 use hyper::Error as HttpError;
 use hyper::LanguageTag;
+use hyper::Result as HyperResult;
 use hyper::client::{Pool, Request as HyperRequest, Response as HyperResponse};
 use hyper::header::{Accept, AccessControlAllowCredentials, AccessControlAllowHeaders};
 use hyper::header::{AccessControlAllowMethods, AccessControlAllowOrigin};
@@ -27,19 +28,26 @@ use hyper::header::{Host, HttpDate, Origin as HyperOrigin, IfMatch, IfRange};
 use hyper::header::{IfUnmodifiedSince, IfModifiedSince, IfNoneMatch, Location};
 use hyper::header::{Pragma, Quality, QualityItem, Referer, SetCookie};
 use hyper::header::{UserAgent, q, qitem};
+use hyper::http::RawStatus;
+use hyper::http::h1::Http11Message;
+use hyper::http::message::HttpMessage;
 use hyper::method::Method;
+use hyper::net::{Fresh, NetworkConnector};
 use hyper::status::StatusCode;
 use hyper_openssl::OpensslClient;
 use hyper_serde::Serde;
+use ipc_channel::ipc;
 use log;
 use msg::constellation_msg::{HistoryStateId, PipelineId};
-use net_traits::{CookieSource, FetchMetadata, NetworkError, ReferrerPolicy};
-use net_traits::request::{CacheMode, CredentialsMode, Destination, Origin};
-use net_traits::request::{RedirectMode, Referrer, Request, RequestMode};
+use net_traits::{CookieSource, CustomResponseMediator, FetchMetadata, NetworkError, ReferrerPolicy};
+use net_traits::request::{BodyChunk, BodySource, CacheMode, CredentialsMode, Destination, Origin};
+use net_traits::request::{RedirectMode, Referrer, Request, RequestInit, RequestMode};
 use net_traits::request::{ResponseTainting, ServiceWorkersMode};
 use net_traits::response::{HttpsState, Response, ResponseBody, ResponseType};
+use proxy::{self, ProxyConfig};
 use resource_thread::AuthCache;
 use servo_channel::{channel, Sender};
+use servo_config::prefs::PREFS;
 use servo_url::{ImmutableOrigin, ServoUrl};
 use std::collections::{HashMap, HashSet};
 use std::error::Error;
@@ -75,11 +83,13 @@ pub struct HttpState {
     pub auth_cache: RwLock<AuthCache>,
     pub history_states: RwLock<HashMap<HistoryStateId, Vec<u8>>>,
     pub ssl_client: OpensslClient,
+    /// The proxy that connections go through, if any.
+    pub proxy: Option<ProxyConfig>,
     pub connector: Pool<Connector>,
 }
 
 impl HttpState {
-    pub fn new(ssl_client: OpensslClient) -> HttpState {
+    pub fn new(ssl_client: OpensslClient, proxy: Option<ProxyConfig>) -> HttpState {
         HttpState {
             hsts_list: RwLock::new(HstsList::new()),
             cookie_jar: RwLock::new(CookieStorage::new(150)),
@@ -87,7 +97,8 @@ impl HttpState {
             history_states: RwLock::new(HashMap::new()),
             http_cache: RwLock::new(HttpCache::new()),
             ssl_client: ssl_client.clone(),
-            connector: create_http_connector(ssl_client),
+            connector: create_http_connector(ssl_client, proxy.clone()),
+            proxy: proxy,
         }
     }
 }
@@ -371,11 +382,29 @@ fn auth_from_cache(auth_cache: &RwLock<AuthCache>, origin: &ImmutableOrigin) ->
     }
 }
 
+/// Start a request that is written in absolute form to the HTTP proxy which forwards it.
+///
+/// <https://tools.ietf.org/html/rfc7230#section-5.3.2>
+fn forwarded_request(method: &Method,
+                     url: &ServoUrl,
+                     connector: &Pool<Connector>)
+                     -> HyperResult<HyperRequest<Fresh>> {
+    let url = url.clone().into_url();
+    let stream = connector.connect(url.host_str().unwrap_or(""),
+                                   url.port_or_known_default().unwrap_or(80),
+                                   url.scheme())?;
+    let mut message = Http11Message::with_stream(Box::new(stream));
+    message.set_proxied(true);
+    HyperRequest::with_message(method.clone(), url, Box::new(message))
+}
+
 fn obtain_response(connector: &Pool<Connector>,
+                   proxy: Option<&ProxyConfig>,
                    url: &ServoUrl,
                    method: &Method,
                    request_headers: &Headers,
                    data: &Option<Vec<u8>>,
+                   body_source: &Option<BodySource>,
                    load_data_method: &Method,
                    pipeline_id: &Option<PipelineId>,
                    iters: u32,
@@ -399,11 +428,17 @@ fn obtain_response(connector: &Pool<Connector>,
         // https://tools.ietf.org/html/rfc7231#section-6.4
         let is_redirected_request = iters != 1;
         let request_body;
-        match data {
-            &Some(ref d) if !is_redirected_request => {
+        let mut streamed_body = None;
+        match (data, body_source) {
+            (&Some(ref d), _) if !is_redirected_request => {
                 headers.set(ContentLength(d.len() as u64));
                 request_body = data;
             }
+            // Without a Content-Length, hyper sends the body with chunked encoding.
+            (&None, &Some(ref source)) if !is_redirected_request => {
+                request_body = &null_data;
+                streamed_body = Some(source);
+            }
             _ => {
                 if *load_data_method != Method::Get && *load_data_method != Method::Head {
                     headers.set(ContentLength(0))
@@ -422,14 +457,20 @@ fn obtain_response(connector: &Pool<Connector>,
 
         let connect_start = precise_time_ms();
 
-        let request = HyperRequest::with_connector(method.clone(),
-                                                   url.clone().into_url(),
-                                                   &*connector);
+        let forwarding_proxy = proxy::forwarding_proxy(proxy, url.as_url());
+        let request = if forwarding_proxy.is_some() {
+            forwarded_request(method, url, connector)
+        } else {
+            HyperRequest::with_connector(method.clone(), url.clone().into_url(), &*connector)
+        };
         let mut request = match request {
             Ok(request) => request,
             Err(e) => return Err(NetworkError::from_hyper_error(&url, e)),
         };
         *request.headers_mut() = headers.clone();
+        if let Some(authorization) = forwarding_proxy.and_then(ProxyConfig::authorization) {
+            request.headers_mut().set_raw("Proxy-Authorization", vec![authorization.into_bytes()]);
+        }
 
         let connect_end = precise_time_ms();
 
@@ -446,11 +487,29 @@ fn obtain_response(connector: &Pool<Connector>,
             }
         }
 
+        if let Some(source) = streamed_body {
+            loop {
+                match source.next_chunk() {
+                    BodyChunk::Bytes(bytes) => {
+                        if let Err(e) = request_writer.write_all(&bytes) {
+                            return Err(NetworkError::Internal(e.description().to_owned()))
+                        }
+                    },
+                    BodyChunk::Done => break,
+                    BodyChunk::Error => {
+                        return Err(NetworkError::Internal("Failed to read the request body".to_owned()))
+                    },
+                }
+            }
+        }
+
         let response = match request_writer.send() {
             Ok(w) => w,
+            // A body given as a stream has been read, so it can't be sent again.
             Err(HttpError::Io(ref io_error))
-                if io_error.kind() == io::ErrorKind::ConnectionAborted ||
-                   io_error.kind() == io::ErrorKind::ConnectionReset => {
+                if streamed_body.is_none() &&
+                   (io_error.kind() == io::ErrorKind::ConnectionAborted ||
+                    io_error.kind() == io::ErrorKind::ConnectionReset) => {
                 debug!("connection aborted ({:?}), possibly stale, trying new connection", io_error.description());
                 continue;
             },
@@ -500,7 +559,7 @@ pub fn http_fetch(request: &mut Request,
     if request.service_workers_mode != ServiceWorkersMode::None {
         // Substep 1
         if request.service_workers_mode == ServiceWorkersMode::All {
-            // TODO (handle fetch unimplemented)
+            response = handle_fetch(request, context);
         }
 
         // Substep 2
@@ -613,6 +672,76 @@ pub fn http_fetch(request: &mut Request,
     response
 }
 
+/// Lets the service worker controlling the client of the request, if any, respond to it
+/// instead of the network.
+///
+/// <https://w3c.github.io/ServiceWorker/#handle-fetch>
+fn handle_fetch(request: &Request, context: &FetchContext) -> Option<Response> {
+    let swmanager_chan = match context.swmanager_chan {
+        Some(ref swmanager_chan) => swmanager_chan,
+        None => return None,
+    };
+    if !PREFS.get("dom.serviceworker.enabled").as_boolean().unwrap_or(false) {
+        return None;
+    }
+
+    let client_url = if request.is_navigation_request() {
+        request.current_url()
+    } else if request.is_subresource_request() {
+        match request.client_url {
+            Some(ref url) => url.clone(),
+            None => return None,
+        }
+    } else {
+        return None;
+    };
+
+    let (response_chan, response_port) = ipc::channel().unwrap();
+    let mediator = CustomResponseMediator {
+        response_chan: response_chan,
+        client_url: client_url,
+        request: RequestInit {
+            id: request.id,
+            method: request.method.clone(),
+            url: request.current_url(),
+            headers: request.headers.clone(),
+            body: request.body.clone(),
+            destination: request.destination,
+            mode: request.mode.clone(),
+            cache_mode: request.cache_mode,
+            credentials_mode: request.credentials_mode,
+            origin: match request.origin {
+                Origin::Origin(ref origin) => origin.clone(),
+                Origin::Client => ImmutableOrigin::new_opaque(),
+            },
+            referrer_url: request.referrer.to_url().cloned(),
+            referrer_policy: request.referrer_policy,
+            pipeline_id: request.pipeline_id,
+            redirect_mode: request.redirect_mode,
+            integrity_metadata: request.integrity_metadata.clone(),
+            client_url: request.client_url.clone(),
+            ..RequestInit::default()
+        },
+    };
+    if swmanager_chan.send(mediator).is_err() {
+        return None;
+    }
+
+    match response_port.recv() {
+        Ok(Some(Ok(custom_response))) => {
+            let mut response = Response::new(request.current_url());
+            let RawStatus(code, reason) = custom_response.raw_status;
+            response.status = Some(StatusCode::from_u16(code));
+            response.raw_status = Some((code, reason.as_bytes().to_vec()));
+            response.headers = custom_response.headers;
+            *response.body.lock().unwrap() = ResponseBody::Done(custom_response.body);
+            Some(response)
+        },
+        Ok(Some(Err(error))) => Some(Response::network_error(error)),
+        Ok(None) | Err(_) => None,
+    }
+}
+
 /// [HTTP redirect fetch](https://fetch.spec.whatwg.org#http-redirect-fetch)
 pub fn http_redirect_fetch(request: &mut Request,
                            cache: &mut CorsCache,
@@ -665,7 +794,7 @@ pub fn http_redirect_fetch(request: &mut Request,
 
     // Step 9
     if response.actual_response().status.map_or(true, |s| s != StatusCode::SeeOther) &&
-       request.body.as_ref().map_or(false, |b| b.is_empty()) {
+       (request.body.as_ref().map_or(false, |b| b.is_empty()) || request.body_source.is_some()) {
         return Response::network_error(NetworkError::Internal("Request body is not done".into()));
     }
 
@@ -680,6 +809,7 @@ pub fn http_redirect_fetch(request: &mut Request,
         (code == StatusCode::SeeOther && request.method != Method::Head)) {
         request.method = Method::Get;
         request.body = None;
+        request.body_source = None;
     }
 
     // Step 12
@@ -739,6 +869,8 @@ fn http_network_or_cache_fetch(request: &mut Request,
     };
 
     let content_length_value = match http_request.body {
+        // The length of a body given as a stream isn't known until it has been sent.
+        None if http_request.body_source.is_some() => None,
         None =>
             match http_request.method {
                 // Step 6
@@ -1054,6 +1186,11 @@ fn http_network_fetch(request: &Request,
     // Step 5
     let url = request.current_url();
 
+    // Don't open a connection for a fetch that was cancelled before it got this far.
+    if context.cancellation_listener.lock().unwrap().cancelled() {
+        return Response::network_error(NetworkError::Internal("Fetch aborted".into()))
+    }
+
     let request_id = context.devtools_chan.as_ref().map(|_| {
         uuid::Uuid::new_v4().simple().to_string()
     });
@@ -1063,10 +1200,11 @@ fn http_network_fetch(request: &Request,
     // since things like image fetches are classified differently by devtools
     let is_xhr = request.destination == Destination::None;
     let wrapped_response = obtain_response(&context.state.connector,
+                                           context.state.proxy.as_ref(),
                                            &url,
                                            &request.method,
                                            &request.headers,
-                                           &request.body, &request.method,
+                                           &request.body, &request.body_source, &request.method,
                                            &request.pipeline_id, request.redirect_count + 1,
                                            request_id.as_ref().map(Deref::deref), is_xhr);
 
@@ -1107,6 +1245,12 @@ fn http_network_fetch(request: &Request,
     if cancellation_listener.lock().unwrap().cancelled() {
         return Response::network_error(NetworkError::Internal("Fetch aborted".into()))
     }
+    // Only the body that is handed over to the consumer waits for it to ask for more.
+    let body_demand = if is_redirect_status(res.status) || res.status == StatusCode::NotModified {
+        None
+    } else {
+        request.body_demand.clone()
+    };
     thread::Builder::new().name(format!("fetch worker thread")).spawn(move || {
         match StreamedResponse::from_http_response(res) {
             Ok(mut res) => {
@@ -1129,6 +1273,12 @@ fn http_network_fetch(request: &Request,
                 }
 
                 loop {
+                    if let Some(ref body_demand) = body_demand {
+                        if !body_demand.wait() {
+                            // Nobody is left to read the rest of the body.
+                            cancellation_listener.lock().unwrap().cancel();
+                        }
+                    }
                     if cancellation_listener.lock().unwrap().cancelled() {
                         *res_body.lock().unwrap() = ResponseBody::Done(vec![]);
                         let _ = done_sender.send(Data::Cancelled);
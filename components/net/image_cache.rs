@@ -9,6 +9,7 @@ use net_traits::image::base::{Image, ImageMetadata, PixelFormat, load_from_memor
 use net_traits::image_cache::{CanRequestImages, ImageCache, ImageResponder};
 use net_traits::image_cache::{ImageOrMetadataAvailable, ImageResponse, ImageState};
 use net_traits::image_cache::{PendingImageId, UsePlaceholder};
+use servo_svg::document::{is_svg, load_svg};
 use servo_url::ServoUrl;
 use std::collections::HashMap;
 use std::collections::hash_map::Entry::{Occupied, Vacant};
@@ -33,7 +34,11 @@ use webrender_api;
 // ======================================================================
 
 fn decode_bytes_sync(key: LoadKey, bytes: &[u8]) -> DecoderMsg {
-    let image = load_from_memory(bytes);
+    let image = if is_svg(bytes) {
+        load_svg(bytes)
+    } else {
+        load_from_memory(bytes)
+    };
     DecoderMsg {
         key: key,
         image: image
@@ -48,14 +53,36 @@ fn get_placeholder_image(webrender_api: &webrender_api::RenderApi, data: &[u8])
 
 fn set_webrender_image_key(webrender_api: &webrender_api::RenderApi, image: &mut Image) {
     if image.id.is_some() { return; }
+    let mut txn = webrender_api::Transaction::new();
+    if image.is_animated() {
+        // Each frame of an animated image gets its own key, so that layout
+        // can animate it by switching keys.
+        for index in 0..image.frames.len() {
+            let frame_key = add_webrender_image(webrender_api, &mut txn, image, image.frame_bytes(index));
+            image.frames[index].id = Some(frame_key);
+        }
+        image.id = image.frames[0].id;
+    } else {
+        let image_key = add_webrender_image(webrender_api, &mut txn, image, &*image.bytes);
+        image.id = Some(image_key);
+    }
+    webrender_api.update_resources(txn.resource_updates);
+}
+
+fn add_webrender_image(
+    webrender_api: &webrender_api::RenderApi,
+    txn: &mut webrender_api::Transaction,
+    image: &Image,
+    pixels: &[u8],
+) -> webrender_api::ImageKey {
     let mut bytes = Vec::new();
     let is_opaque = match image.format {
         PixelFormat::BGRA8 => {
-            bytes.extend_from_slice(&*image.bytes);
+            bytes.extend_from_slice(pixels);
             premultiply(bytes.as_mut_slice())
         }
         PixelFormat::RGB8 => {
-            for bgr in image.bytes.chunks(3) {
+            for bgr in pixels.chunks(3) {
                 bytes.extend_from_slice(&[
                     bgr[2],
                     bgr[1],
@@ -80,10 +107,8 @@ fn set_webrender_image_key(webrender_api: &webrender_api::RenderApi, image: &mut
     };
     let data = webrender_api::ImageData::new(bytes);
     let image_key = webrender_api.generate_image_key();
-    let mut txn = webrender_api::Transaction::new();
     txn.add_image(image_key, descriptor, data, None);
-    webrender_api.update_resources(txn.resource_updates);
-    image.id = Some(image_key);
+    image_key
 }
 
 // Returns true if the image was found to be
@@ -486,7 +511,8 @@ impl ImageCache for ImageCacheImpl {
     fn notify_pending_response(&self, id: PendingImageId, action: FetchResponseMsg) {
         match (action, id) {
             (FetchResponseMsg::ProcessRequestBody, _) |
-            (FetchResponseMsg::ProcessRequestEOF, _) => return,
+            (FetchResponseMsg::ProcessRequestEOF, _) |
+            (FetchResponseMsg::ProcessCspViolations(_), _) => return,
             (FetchResponseMsg::ProcessResponse(response), _) => {
                 let mut store = self.store.lock().unwrap();
                 let pending_load = store.pending_loads.get_by_key_mut(&id).unwrap();
@@ -560,4 +586,15 @@ impl ImageCache for ImageCacheImpl {
     fn set_webrender_image_key(&self, image: &mut Image) {
         set_webrender_image_key(&self.store.lock().unwrap().webrender_api, image);
     }
+
+    fn delete_webrender_image_keys(&self, keys: Vec<webrender_api::ImageKey>) {
+        if keys.is_empty() {
+            return;
+        }
+        let mut txn = webrender_api::Transaction::new();
+        for key in keys {
+            txn.delete_image(key);
+        }
+        self.store.lock().unwrap().webrender_api.update_resources(txn.resource_updates);
+    }
 }
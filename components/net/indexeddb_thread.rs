@@ -0,0 +1,910 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use ipc_channel::ipc::{self, IpcReceiver, IpcSender};
+use net_traits::indexeddb_thread::{IndexInfo, IndexedDBBlockingStatus, IndexedDBConnectionEvent};
+use net_traits::indexeddb_thread::{IndexedDBDeletionStatus, IndexedDBError, IndexedDBKey};
+use net_traits::indexeddb_thread::{IndexedDBKeyPath, IndexedDBKeyRange};
+use net_traits::indexeddb_thread::{IndexedDBOperation, IndexedDBQuery, IndexedDBRecord};
+use net_traits::indexeddb_thread::{IndexedDBThreadMsg, ObjectStoreInfo};
+use resource_thread;
+use serde_json;
+use servo_url::ServoUrl;
+use std::borrow::ToOwned;
+use std::cmp;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+
+/// <https://w3c.github.io/IndexedDB/#key-generator-construct>
+const MAX_GENERATED_KEY: u64 = 1 << 53;
+
+/// The directory of the config dir where the databases of each origin are saved, in a file
+/// of their own.
+const DATA_DIR: &'static str = "indexeddb";
+
+pub trait IndexedDBThreadFactory {
+    fn new(config_dir: Option<PathBuf>) -> Self;
+}
+
+impl IndexedDBThreadFactory for IpcSender<IndexedDBThreadMsg> {
+    /// Create an IndexedDB thread
+    fn new(config_dir: Option<PathBuf>) -> IpcSender<IndexedDBThreadMsg> {
+        let (chan, port) = ipc::channel().unwrap();
+        thread::Builder::new().name("IndexedDBManager".to_owned()).spawn(move || {
+            IndexedDBManager::new(port, config_dir).start();
+        }).expect("Thread spawning failed");
+        chan
+    }
+}
+
+/// JSON objects can only have string keys, so the records of an object store are
+/// saved as a list of pairs.
+mod records {
+    use net_traits::indexeddb_thread::IndexedDBKey;
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::collections::BTreeMap;
+
+    pub fn serialize<S>(records: &BTreeMap<IndexedDBKey, Vec<u8>>, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        serializer.collect_seq(records.iter())
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<BTreeMap<IndexedDBKey, Vec<u8>>, D::Error>
+        where D: Deserializer<'de>
+    {
+        let pairs: Vec<(IndexedDBKey, Vec<u8>)> = Deserialize::deserialize(deserializer)?;
+        Ok(pairs.into_iter().collect())
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+struct Index {
+    info: IndexInfo,
+    /// The (index key, primary key) pairs of the index, in index order.
+    entries: BTreeSet<(IndexedDBKey, IndexedDBKey)>,
+}
+
+/// The value of a record along with its entries in each index, as copied from the database
+/// of a transaction to the committed database.
+struct StoredRecord {
+    value: Vec<u8>,
+    index_entries: Vec<(String, IndexedDBKey)>,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+struct ObjectStore {
+    key_path: Option<IndexedDBKeyPath>,
+    auto_increment: bool,
+    /// <https://w3c.github.io/IndexedDB/#key-generator-current-number>
+    current_number: u64,
+    #[serde(with = "records")]
+    records: BTreeMap<IndexedDBKey, Vec<u8>>,
+    indexes: BTreeMap<String, Index>,
+}
+
+impl ObjectStore {
+    /// <https://w3c.github.io/IndexedDB/#generate-a-key>
+    fn generate_key(&mut self) -> Result<IndexedDBKey, IndexedDBError> {
+        if self.current_number > MAX_GENERATED_KEY {
+            return Err(IndexedDBError::Constraint);
+        }
+        let key = IndexedDBKey::Number(self.current_number as f64);
+        self.current_number += 1;
+        Ok(key)
+    }
+
+    /// <https://w3c.github.io/IndexedDB/#possibly-update-the-key-generator>
+    fn possibly_update_key_generator(&mut self, key: &IndexedDBKey) {
+        if let IndexedDBKey::Number(value) = *key {
+            let value = value.min(MAX_GENERATED_KEY as f64).floor();
+            if value >= self.current_number as f64 {
+                self.current_number = value as u64 + 1;
+            }
+        }
+    }
+
+    /// Removes the record with the given key, along with its index entries.
+    fn take_record(&mut self, primary_key: &IndexedDBKey) -> Option<StoredRecord> {
+        let value = self.records.remove(primary_key)?;
+        let mut index_entries = vec![];
+        for (name, index) in &mut self.indexes {
+            let entries: Vec<(IndexedDBKey, IndexedDBKey)> = index.entries.iter()
+                .filter(|&&(_, ref key)| key == primary_key)
+                .cloned()
+                .collect();
+            for entry in entries {
+                index.entries.remove(&entry);
+                index_entries.push((name.clone(), entry.0));
+            }
+        }
+        Some(StoredRecord {
+            value: value,
+            index_entries: index_entries,
+        })
+    }
+
+    /// The record with the given key, along with its index entries.
+    fn record(&self, primary_key: &IndexedDBKey) -> Option<StoredRecord> {
+        let value = self.records.get(primary_key)?.clone();
+        let mut index_entries = vec![];
+        for (name, index) in &self.indexes {
+            index_entries.extend(index.entries.iter()
+                .filter(|&&(_, ref key)| key == primary_key)
+                .map(|&(ref index_key, _)| (name.clone(), index_key.clone())));
+        }
+        Some(StoredRecord {
+            value: value,
+            index_entries: index_entries,
+        })
+    }
+
+    /// Replaces the record with the given key, or deletes it if `record` is `None`.
+    fn set_record(&mut self, primary_key: IndexedDBKey, record: Option<StoredRecord>) {
+        self.take_record(&primary_key);
+        if let Some(record) = record {
+            for (name, index_key) in record.index_entries {
+                if let Some(index) = self.indexes.get_mut(&name) {
+                    index.entries.insert((index_key, primary_key.clone()));
+                }
+            }
+            self.records.insert(primary_key, record.value);
+        }
+    }
+
+    /// <https://w3c.github.io/IndexedDB/#store-a-record-into-an-object-store>
+    ///
+    /// Returns the key of the record.
+    fn put(&mut self,
+           key: Option<IndexedDBKey>,
+           value: Vec<u8>,
+           index_keys: Vec<(String, Vec<IndexedDBKey>)>,
+           overwrite: bool)
+           -> Result<IndexedDBKey, IndexedDBError> {
+        // Steps 1-2.
+        let key = match key {
+            Some(key) => {
+                if self.auto_increment {
+                    self.possibly_update_key_generator(&key);
+                }
+                key
+            },
+            None if self.auto_increment => self.generate_key()?,
+            None => return Err(IndexedDBError::Data),
+        };
+
+        // Step 3.
+        if !overwrite && self.records.contains_key(&key) {
+            return Err(IndexedDBError::Constraint);
+        }
+
+        // Step 5.
+        for &(ref name, ref index_keys) in &index_keys {
+            let index = match self.indexes.get(name) {
+                Some(index) => index,
+                None => continue,
+            };
+            if !index.info.unique {
+                continue;
+            }
+            let violates_uniqueness = index.entries.iter().any(|&(ref index_key, ref primary_key)| {
+                *primary_key != key && index_keys.contains(index_key)
+            });
+            if violates_uniqueness {
+                return Err(IndexedDBError::Constraint);
+            }
+        }
+
+        // Steps 4 and 6.
+        self.take_record(&key);
+        self.records.insert(key.clone(), value);
+        for (name, index_keys) in index_keys {
+            if let Some(index) = self.indexes.get_mut(&name) {
+                index.entries.extend(index_keys.into_iter().map(|index_key| (index_key, key.clone())));
+            }
+        }
+
+        Ok(key)
+    }
+
+    /// The (key, primary key) pairs of an object store or index, in ascending order.
+    fn entries(&self, index: Option<&str>) -> Result<Vec<(&IndexedDBKey, &IndexedDBKey)>, IndexedDBError> {
+        Ok(match index {
+            Some(name) => {
+                let index = self.indexes.get(name).ok_or(IndexedDBError::NotFound)?;
+                index.entries.iter().map(|&(ref key, ref primary_key)| (key, primary_key)).collect()
+            },
+            None => self.records.keys().map(|key| (key, key)).collect(),
+        })
+    }
+
+    /// <https://w3c.github.io/IndexedDB/#iterate-a-cursor>
+    fn get(&self, query: &IndexedDBQuery) -> Result<Vec<IndexedDBRecord>, IndexedDBError> {
+        let mut entries = self.entries(query.index.as_ref().map(|name| &**name))?;
+        entries.retain(|&(key, _)| query.range.contains(key));
+
+        // For unique directions, only the entry with the lowest primary key of each key
+        // is visited, whichever the direction.
+        if query.direction.is_unique() {
+            entries.dedup_by(|&mut (key, _), &mut (previous_key, _)| key == previous_key);
+        }
+        if query.direction.is_reverse() {
+            entries.reverse();
+        }
+
+        if let Some((ref position_key, ref position_primary_key)) = query.position {
+            let reverse = query.direction.is_reverse();
+            let unique = query.direction.is_unique();
+            entries.retain(|&(key, primary_key)| {
+                match (reverse, unique) {
+                    (false, false) => (key, primary_key) > (position_key, position_primary_key),
+                    (false, true) => key > position_key,
+                    (true, false) => (key, primary_key) < (position_key, position_primary_key),
+                    (true, true) => key < position_key,
+                }
+            });
+        }
+
+        let count = query.count.map_or(entries.len(), |count| count as usize);
+        Ok(entries.into_iter().take(count).map(|(key, primary_key)| {
+            IndexedDBRecord {
+                key: key.clone(),
+                primary_key: primary_key.clone(),
+                value: self.records[primary_key].clone(),
+            }
+        }).collect())
+    }
+
+    fn count(&self, index: Option<&str>, range: &IndexedDBKeyRange) -> Result<u64, IndexedDBError> {
+        let entries = self.entries(index)?;
+        Ok(entries.into_iter().filter(|&(key, _)| range.contains(key)).count() as u64)
+    }
+
+    /// <https://w3c.github.io/IndexedDB/#delete-records-from-an-object-store>
+    ///
+    /// Returns the keys of the deleted records.
+    fn delete(&mut self, range: &IndexedDBKeyRange) -> Vec<IndexedDBKey> {
+        let keys: Vec<IndexedDBKey> = self.records.keys().filter(|key| range.contains(key)).cloned().collect();
+        for key in &keys {
+            self.take_record(key);
+        }
+        keys
+    }
+
+    /// <https://w3c.github.io/IndexedDB/#clear-an-object-store>
+    ///
+    /// Returns the keys of the deleted records.
+    fn clear(&mut self) -> Vec<IndexedDBKey> {
+        self.delete(&IndexedDBKeyRange::default())
+    }
+
+    fn info(&self, name: &str) -> ObjectStoreInfo {
+        ObjectStoreInfo {
+            name: name.to_owned(),
+            key_path: self.key_path.clone(),
+            auto_increment: self.auto_increment,
+            indexes: self.indexes.values().map(|index| index.info.clone()).collect(),
+        }
+    }
+}
+
+#[derive(Clone, Default, Deserialize, Serialize)]
+struct Database {
+    version: u64,
+    object_stores: BTreeMap<String, ObjectStore>,
+}
+
+impl Database {
+    fn object_store(&self, name: &str) -> Result<&ObjectStore, IndexedDBError> {
+        self.object_stores.get(name).ok_or(IndexedDBError::NotFound)
+    }
+
+    fn object_store_mut(&mut self, name: &str) -> Result<&mut ObjectStore, IndexedDBError> {
+        self.object_stores.get_mut(name).ok_or(IndexedDBError::NotFound)
+    }
+
+    fn create_object_store(&mut self,
+                           name: String,
+                           key_path: Option<IndexedDBKeyPath>,
+                           auto_increment: bool)
+                           -> Result<(), IndexedDBError> {
+        if self.object_stores.contains_key(&name) {
+            return Err(IndexedDBError::Constraint);
+        }
+        self.object_stores.insert(name, ObjectStore {
+            key_path: key_path,
+            auto_increment: auto_increment,
+            current_number: 1,
+            records: BTreeMap::new(),
+            indexes: BTreeMap::new(),
+        });
+        Ok(())
+    }
+
+    fn delete_object_store(&mut self, name: &str) -> Result<ObjectStore, IndexedDBError> {
+        self.object_stores.remove(name).ok_or(IndexedDBError::NotFound)
+    }
+
+    fn create_index(&mut self,
+                    store: &str,
+                    info: IndexInfo,
+                    entries: Vec<(IndexedDBKey, Vec<IndexedDBKey>)>)
+                    -> Result<(), IndexedDBError> {
+        let store = self.object_store_mut(store)?;
+        if store.indexes.contains_key(&info.name) {
+            return Err(IndexedDBError::Constraint);
+        }
+        let mut index_entries = BTreeSet::new();
+        for (primary_key, index_keys) in entries {
+            for index_key in index_keys {
+                if info.unique && index_entries.iter().any(|&(ref key, _)| *key == index_key) {
+                    return Err(IndexedDBError::Constraint);
+                }
+                index_entries.insert((index_key, primary_key.clone()));
+            }
+        }
+        store.indexes.insert(info.name.clone(), Index {
+            info: info,
+            entries: index_entries,
+        });
+        Ok(())
+    }
+
+    fn delete_index(&mut self, store: &str, name: &str) -> Result<Index, IndexedDBError> {
+        let store = self.object_store_mut(store)?;
+        store.indexes.remove(name).ok_or(IndexedDBError::NotFound)
+    }
+}
+
+/// A change made by a transaction to its copy of a database.
+enum Change {
+    /// The version, object stores or indexes were changed, which only upgrade transactions do.
+    Schema,
+    /// The key generator of the named object store was updated.
+    KeyGenerator(String),
+    /// The record with the given key of the named object store was stored or deleted.
+    Record(String, IndexedDBKey),
+}
+
+/// An active transaction that changed its database.
+///
+/// The changes are made to a copy of the database, so that other transactions don't see them
+/// until the transaction is committed, and aborting the transaction only has to drop it.
+struct Transaction {
+    origin: String,
+    name: String,
+    /// The committed database at the time of the first change, along with the changes.
+    database: Database,
+    changes: Vec<Change>,
+}
+
+impl Transaction {
+    /// Applies the changes of the transaction to the committed database.
+    fn commit(self, committed: &mut Database) {
+        // Upgrade transactions run while no other connection to the database is open.
+        if self.changes.iter().any(|change| match *change { Change::Schema => true, _ => false }) {
+            *committed = self.database;
+            return;
+        }
+        for change in self.changes {
+            match change {
+                Change::Schema => {},
+                Change::KeyGenerator(store) => {
+                    if let (Ok(changed), Ok(store)) = (self.database.object_store(&store),
+                                                       committed.object_store_mut(&store)) {
+                        store.current_number = cmp::max(store.current_number, changed.current_number);
+                    }
+                },
+                Change::Record(store, key) => {
+                    if let (Ok(changed), Ok(store)) = (self.database.object_store(&store),
+                                                       committed.object_store_mut(&store)) {
+                        let record = changed.record(&key);
+                        store.set_record(key, record);
+                    }
+                },
+            }
+        }
+    }
+}
+
+/// The databases of an origin, as saved to disk.
+#[derive(Deserialize, Serialize)]
+struct SavedOrigin {
+    origin: String,
+    databases: HashMap<String, Database>,
+}
+
+/// The name of the file the databases of an origin are saved to, with the characters of its
+/// serialization that can't appear in file names escaped.
+fn file_name(origin: &str) -> String {
+    let mut name = String::new();
+    for byte in origin.bytes() {
+        match byte {
+            b'a'...b'z' | b'A'...b'Z' | b'0'...b'9' | b'.' | b'-' => name.push(byte as char),
+            _ => name.push_str(&format!("_{:02x}", byte)),
+        }
+    }
+    name + ".json"
+}
+
+fn read_saved_origin(path: &Path) -> Result<SavedOrigin, String> {
+    let file = File::open(path).map_err(|error| error.to_string())?;
+    serde_json::from_reader(file).map_err(|error| error.to_string())
+}
+
+/// Reads the databases of each origin from the config dir.
+fn load_databases(config_dir: &Path) -> HashMap<String, HashMap<String, Database>> {
+    let mut databases = HashMap::new();
+    let entries = match fs::read_dir(config_dir.join(DATA_DIR)) {
+        Ok(entries) => entries,
+        Err(_) => return databases,
+    };
+    for entry in entries.filter_map(Result::ok) {
+        let path = entry.path();
+        // Skip the temporary files of interrupted writes.
+        if path.extension().and_then(|extension| extension.to_str()) != Some("json") {
+            continue;
+        }
+        match read_saved_origin(&path) {
+            Ok(saved) => {
+                databases.insert(saved.origin, saved.databases);
+            },
+            Err(error) => warn!("Couldn't read IndexedDB databases from {}: {}", path.display(), error),
+        }
+    }
+    databases
+}
+
+/// <https://w3c.github.io/IndexedDB/#connection>
+struct Connection {
+    origin: String,
+    name: String,
+    events: IpcSender<IndexedDBConnectionEvent>,
+}
+
+/// What waits for the connections to a database to close.
+enum Waiter {
+    /// A connection that changes the version of the database.
+    Upgrade(u64, IpcSender<IndexedDBBlockingStatus>),
+    /// A request that deletes the database with the given origin and name.
+    Deletion(String, String, IpcSender<IndexedDBDeletionStatus>),
+}
+
+/// A version change of a database, which waits for the other connections to the database to
+/// close.
+struct VersionChange {
+    waiter: Waiter,
+    /// The other connections that the `versionchange` event wasn't fired at yet.
+    unfired: HashSet<u64>,
+    /// The other connections that are still open.
+    open: HashSet<u64>,
+    blocked: bool,
+}
+
+impl VersionChange {
+    /// The connection that changes the version of the database, unless it is deleted.
+    fn connection(&self) -> Option<u64> {
+        match self.waiter {
+            Waiter::Upgrade(connection, _) => Some(connection),
+            Waiter::Deletion(..) => None,
+        }
+    }
+}
+
+struct IndexedDBManager {
+    port: IpcReceiver<IndexedDBThreadMsg>,
+    /// The databases of each origin, by name.
+    databases: HashMap<String, HashMap<String, Database>>,
+    /// The active transactions that changed their database.
+    transactions: HashMap<u64, Transaction>,
+    next_transaction_id: u64,
+    /// The open connections to the databases, of every origin.
+    connections: HashMap<u64, Connection>,
+    next_connection_id: u64,
+    /// The connections waiting for the other connections to their database to close.
+    version_changes: Vec<VersionChange>,
+    config_dir: Option<PathBuf>,
+}
+
+impl IndexedDBManager {
+    fn new(port: IpcReceiver<IndexedDBThreadMsg>,
+           config_dir: Option<PathBuf>)
+           -> IndexedDBManager {
+        let databases = match config_dir {
+            Some(ref config_dir) => load_databases(config_dir),
+            None => HashMap::new(),
+        };
+        IndexedDBManager {
+            port: port,
+            databases: databases,
+            transactions: HashMap::new(),
+            next_transaction_id: 0,
+            connections: HashMap::new(),
+            next_connection_id: 0,
+            version_changes: vec![],
+            config_dir: config_dir,
+        }
+    }
+}
+
+impl IndexedDBManager {
+    fn start(&mut self) {
+        loop {
+            match self.port.recv().unwrap() {
+                IndexedDBThreadMsg::OpenDatabase(sender, url, name) => {
+                    self.open_database(sender, url, name)
+                }
+                IndexedDBThreadMsg::DeleteDatabase(sender, url, name) => {
+                    self.delete_database(sender, url, name)
+                }
+                IndexedDBThreadMsg::OpenConnection(sender, url, name, events) => {
+                    self.open_connection(sender, url, name, events)
+                }
+                IndexedDBThreadMsg::CloseConnection(connection) => {
+                    self.close_connection(connection)
+                }
+                IndexedDBThreadMsg::WaitForOtherConnections(sender, connection, version) => {
+                    self.wait_for_other_connections(sender, connection, version)
+                }
+                IndexedDBThreadMsg::VersionChangeFired(connection) => {
+                    self.version_change_fired(connection)
+                }
+                IndexedDBThreadMsg::ObjectStores(sender, url, name) => {
+                    self.object_stores(sender, url, name)
+                }
+                IndexedDBThreadMsg::NewTransaction(sender) => {
+                    self.next_transaction_id += 1;
+                    let _ = sender.send(self.next_transaction_id);
+                }
+                IndexedDBThreadMsg::Operation(url, name, transaction, operation) => {
+                    self.operation(url, name, transaction, operation)
+                }
+                IndexedDBThreadMsg::Commit(sender, url, name, transaction) => {
+                    self.commit(sender, url, name, transaction)
+                }
+                IndexedDBThreadMsg::Abort(sender, url, name, transaction) => {
+                    self.abort(sender, url, name, transaction)
+                }
+                IndexedDBThreadMsg::Exit(sender) => {
+                    // Nothing to do since committed transactions are saved eagerly.
+                    let _ = sender.send(());
+                    break
+                }
+            }
+        }
+    }
+
+    /// Saves the committed databases of an origin.
+    fn save_origin(&self, origin: &str) {
+        let config_dir = match self.config_dir {
+            Some(ref config_dir) => config_dir,
+            None => return,
+        };
+        // All opaque origins serialize to "null", and their databases don't outlive them anyway.
+        if origin == "null" {
+            return;
+        }
+
+        let databases = self.databases.get(origin).cloned().unwrap_or_default();
+
+        let dir = config_dir.join(DATA_DIR);
+        let file_name = file_name(origin);
+        let result = if databases.is_empty() {
+            match fs::remove_file(dir.join(&file_name)) {
+                Err(ref error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+                result => result,
+            }
+        } else {
+            let saved = SavedOrigin {
+                origin: origin.to_owned(),
+                databases: databases,
+            };
+            fs::create_dir_all(&dir).and_then(|_| {
+                resource_thread::write_json_to_file_atomically(&saved, &dir, &file_name)
+            })
+        };
+        if let Err(error) = result {
+            warn!("Couldn't save the IndexedDB databases of {}: {}", origin, error);
+        }
+    }
+
+    fn database_mut(&mut self, url: ServoUrl, name: String) -> &mut Database {
+        let origin = self.origin_as_string(url);
+        self.databases.entry(origin).or_insert_with(HashMap::new)
+                      .entry(name).or_insert_with(Database::default)
+    }
+
+    fn open_database(&mut self, sender: IpcSender<u64>, url: ServoUrl, name: String) {
+        let version = self.database_mut(url, name).version;
+        let _ = sender.send(version);
+    }
+
+    fn version(&self, origin: &str, name: &str) -> Option<u64> {
+        self.databases.get(origin)
+                      .and_then(|databases| databases.get(name))
+                      .map(|database| database.version)
+    }
+
+    /// <https://w3c.github.io/IndexedDB/#delete-a-database> Steps 3-11.
+    fn delete_database(&mut self, sender: IpcSender<IndexedDBDeletionStatus>, url: ServoUrl, name: String) {
+        let origin = self.origin_as_string(url);
+        let version = match self.version(&origin, &name) {
+            Some(version) => version,
+            None => {
+                let _ = sender.send(IndexedDBDeletionStatus::Deleted(None));
+                return;
+            },
+        };
+        let others = self.fire_version_change(&origin, &name, None, version, None);
+        self.version_changes.push(VersionChange {
+            waiter: Waiter::Deletion(origin, name, sender),
+            unfired: others.clone(),
+            open: others,
+            blocked: false,
+        });
+        self.update_version_changes();
+    }
+
+    fn open_connection(&mut self,
+                       sender: IpcSender<u64>,
+                       url: ServoUrl,
+                       name: String,
+                       events: IpcSender<IndexedDBConnectionEvent>) {
+        self.next_connection_id += 1;
+        let connection = Connection {
+            origin: self.origin_as_string(url),
+            name: name,
+            events: events,
+        };
+        self.connections.insert(self.next_connection_id, connection);
+        let _ = sender.send(self.next_connection_id);
+    }
+
+    fn close_connection(&mut self, connection: u64) {
+        self.connections.remove(&connection);
+        self.version_changes.retain(|version_change| version_change.connection() != Some(connection));
+        for version_change in &mut self.version_changes {
+            version_change.unfired.remove(&connection);
+            version_change.open.remove(&connection);
+        }
+        self.update_version_changes();
+    }
+
+    /// <https://w3c.github.io/IndexedDB/#open-a-database> Steps 10.1-10.5.
+    fn wait_for_other_connections(&mut self,
+                                  sender: IpcSender<IndexedDBBlockingStatus>,
+                                  connection: u64,
+                                  version: u64) {
+        let (origin, name) = match self.connections.get(&connection) {
+            Some(connection) => (connection.origin.clone(), connection.name.clone()),
+            None => return,
+        };
+        let old_version = self.version(&origin, &name).unwrap_or(0);
+        let others = self.fire_version_change(&origin, &name, Some(connection), old_version, Some(version));
+        self.version_changes.push(VersionChange {
+            waiter: Waiter::Upgrade(connection, sender),
+            unfired: others.clone(),
+            open: others,
+            blocked: false,
+        });
+        self.update_version_changes();
+    }
+
+    /// Has the `versionchange` event fired at the open connections to a database, except
+    /// for the one that changes its version, and returns them.
+    fn fire_version_change(&mut self,
+                           origin: &str,
+                           name: &str,
+                           except: Option<u64>,
+                           old_version: u64,
+                           new_version: Option<u64>)
+                           -> HashSet<u64> {
+        let mut others = HashSet::new();
+        let mut gone = vec![];
+        for (&id, other) in &self.connections {
+            if Some(id) == except || other.origin != origin || other.name != name {
+                continue;
+            }
+            match other.events.send(IndexedDBConnectionEvent::VersionChange(old_version, new_version)) {
+                Ok(()) => {
+                    others.insert(id);
+                },
+                // The global of the connection went away.
+                Err(_) => gone.push(id),
+            }
+        }
+        for id in gone {
+            self.connections.remove(&id);
+        }
+        others
+    }
+
+    fn version_change_fired(&mut self, connection: u64) {
+        for version_change in &mut self.version_changes {
+            version_change.unfired.remove(&connection);
+        }
+        self.update_version_changes();
+    }
+
+    /// Lets the version changes waiting for connections to close know whether they still are
+    /// blocked, and carries out the ones that no longer wait.
+    fn update_version_changes(&mut self) {
+        let (unblocked, waiting): (Vec<_>, Vec<_>) =
+            self.version_changes.drain(..).partition(|version_change| version_change.open.is_empty());
+        self.version_changes = waiting;
+        for version_change in unblocked {
+            match version_change.waiter {
+                Waiter::Upgrade(_, sender) => {
+                    let _ = sender.send(IndexedDBBlockingStatus::Unblocked);
+                },
+                Waiter::Deletion(origin, name, sender) => {
+                    let version = self.databases.get_mut(&origin)
+                                                .and_then(|databases| databases.remove(&name))
+                                                .map(|database| database.version);
+                    if version.is_some() {
+                        self.save_origin(&origin);
+                    }
+                    let _ = sender.send(IndexedDBDeletionStatus::Deleted(version));
+                },
+            }
+        }
+
+        for version_change in &mut self.version_changes {
+            if !version_change.unfired.is_empty() || version_change.blocked {
+                continue;
+            }
+            version_change.blocked = true;
+            match version_change.waiter {
+                Waiter::Upgrade(_, ref sender) => {
+                    let _ = sender.send(IndexedDBBlockingStatus::Blocked);
+                },
+                Waiter::Deletion(ref origin, ref name, ref sender) => {
+                    let version = self.databases.get(origin)
+                                                .and_then(|databases| databases.get(name))
+                                                .map_or(0, |database| database.version);
+                    let _ = sender.send(IndexedDBDeletionStatus::Blocked(version));
+                },
+            }
+        }
+    }
+
+    fn object_stores(&mut self, sender: IpcSender<Vec<ObjectStoreInfo>>, url: ServoUrl, name: String) {
+        let database = self.database_mut(url, name);
+        let stores = database.object_stores.iter().map(|(name, store)| store.info(name)).collect();
+        let _ = sender.send(stores);
+    }
+
+    fn operation(&mut self, url: ServoUrl, name: String, transaction: u64, operation: IndexedDBOperation) {
+        let origin = self.origin_as_string(url);
+        let reads_only = match operation {
+            IndexedDBOperation::Get(..) | IndexedDBOperation::Count(..) => true,
+            _ => false,
+        };
+        if !reads_only && !self.transactions.contains_key(&transaction) {
+            let database = self.databases.entry(origin.clone()).or_insert_with(HashMap::new)
+                                         .entry(name.clone()).or_insert_with(Database::default)
+                                         .clone();
+            self.transactions.insert(transaction, Transaction {
+                origin: origin.clone(),
+                name: name.clone(),
+                database: database,
+                changes: vec![],
+            });
+        }
+
+        let mut changes = vec![];
+        {
+            // Transactions which didn't change anything yet read the committed database.
+            let database = match self.transactions.get_mut(&transaction) {
+                Some(transaction) => &mut transaction.database,
+                None => self.databases.entry(origin).or_insert_with(HashMap::new)
+                                      .entry(name).or_insert_with(Database::default),
+            };
+
+            match operation {
+                IndexedDBOperation::SetVersion(sender, version) => {
+                    changes.push(Change::Schema);
+                    database.version = version;
+                    let _ = sender.send(Ok(()));
+                }
+                IndexedDBOperation::CreateObjectStore(sender, store, key_path, auto_increment) => {
+                    let result = database.create_object_store(store.clone(), key_path, auto_increment);
+                    if result.is_ok() {
+                        changes.push(Change::Schema);
+                    }
+                    let _ = sender.send(result);
+                }
+                IndexedDBOperation::DeleteObjectStore(sender, store) => {
+                    let result = database.delete_object_store(&store).map(|_| {
+                        changes.push(Change::Schema);
+                    });
+                    let _ = sender.send(result);
+                }
+                IndexedDBOperation::CreateIndex(sender, store, info, entries) => {
+                    let result = database.create_index(&store, info, entries);
+                    if result.is_ok() {
+                        changes.push(Change::Schema);
+                    }
+                    let _ = sender.send(result);
+                }
+                IndexedDBOperation::DeleteIndex(sender, store, index) => {
+                    let result = database.delete_index(&store, &index).map(|_| {
+                        changes.push(Change::Schema);
+                    });
+                    let _ = sender.send(result);
+                }
+                IndexedDBOperation::Put(sender, store_name, key, value, index_keys, overwrite) => {
+                    let result = database.object_store_mut(&store_name).and_then(|store| {
+                        let current_number = store.current_number;
+                        let result = store.put(key, value, index_keys, overwrite);
+                        if store.current_number != current_number {
+                            changes.push(Change::KeyGenerator(store_name.clone()));
+                        }
+                        let key = result?;
+                        changes.push(Change::Record(store_name.clone(), key.clone()));
+                        Ok(key)
+                    });
+                    let _ = sender.send(result);
+                }
+                IndexedDBOperation::Get(sender, store, query) => {
+                    let _ = sender.send(database.object_store(&store).and_then(|store| store.get(&query)));
+                }
+                IndexedDBOperation::Count(sender, store, index, range) => {
+                    let result = database.object_store(&store).and_then(|store| {
+                        store.count(index.as_ref().map(|name| &**name), &range)
+                    });
+                    let _ = sender.send(result);
+                }
+                IndexedDBOperation::Delete(sender, store_name, range) => {
+                    let result = database.object_store_mut(&store_name).map(|store| {
+                        for key in store.delete(&range) {
+                            changes.push(Change::Record(store_name.clone(), key));
+                        }
+                    });
+                    let _ = sender.send(result);
+                }
+                IndexedDBOperation::Clear(sender, store_name) => {
+                    let result = database.object_store_mut(&store_name).map(|store| {
+                        for key in store.clear() {
+                            changes.push(Change::Record(store_name.clone(), key));
+                        }
+                    });
+                    let _ = sender.send(result);
+                }
+            }
+        }
+
+        if let Some(transaction) = self.transactions.get_mut(&transaction) {
+            transaction.changes.extend(changes);
+        }
+    }
+
+    /// Applies the changes of the transaction, if it made any, and saves them.
+    fn commit(&mut self, sender: IpcSender<()>, _url: ServoUrl, _name: String, transaction: u64) {
+        if let Some(transaction) = self.transactions.remove(&transaction) {
+            let origin = transaction.origin.clone();
+            let database = self.databases.get_mut(&origin)
+                                         .and_then(|databases| databases.get_mut(&transaction.name));
+            // The database may have been deleted in the meantime.
+            if let Some(database) = database {
+                transaction.commit(database);
+            }
+            self.save_origin(&origin);
+        }
+        let _ = sender.send(());
+    }
+
+    /// Drops the changes of the transaction, which no other transaction saw.
+    fn abort(&mut self, sender: IpcSender<()>, _url: ServoUrl, _name: String, transaction: u64) {
+        self.transactions.remove(&transaction);
+        let _ = sender.send(());
+    }
+
+    fn origin_as_string(&self, url: ServoUrl) -> String {
+        url.origin().ascii_serialization()
+    }
+}
@@ -35,15 +35,17 @@ extern crate servo_allocator;
 extern crate servo_arc;
 extern crate servo_channel;
 extern crate servo_config;
+extern crate servo_svg;
 extern crate servo_url;
 extern crate time;
+extern crate tungstenite;
 extern crate unicase;
 extern crate url;
 extern crate uuid;
 extern crate webrender_api;
-extern crate ws;
 
 mod blob_loader;
+mod cache_storage_thread;
 pub mod connector;
 pub mod cookie;
 pub mod cookie_storage;
@@ -52,9 +54,12 @@ pub mod filemanager_thread;
 mod hosts;
 pub mod hsts;
 pub mod http_cache;
+mod http_disk_cache;
 pub mod http_loader;
 pub mod image_cache;
+mod indexeddb_thread;
 pub mod mime_classifier;
+mod proxy;
 pub mod resource_thread;
 mod storage_thread;
 pub mod subresource_integrity;
@@ -69,4 +74,5 @@ pub mod fetch {
 pub mod test {
     pub use http_loader::HttpState;
     pub use hosts::{replace_host_table, parse_hostsfile};
+    pub use proxy::{ProxyConfig, ProxyScheme};
 }
@@ -3,6 +3,7 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 //! A thread that takes a URL and streams back the binary data.
+use cache_storage_thread::CacheStorageThreadFactory;
 use connector::{create_http_connector, create_ssl_client};
 use cookie;
 use cookie_rs;
@@ -17,28 +18,34 @@ use hsts::HstsList;
 use http_cache::HttpCache;
 use http_loader::{HttpState, http_redirect_fetch};
 use hyper_serde::Serde;
+use indexeddb_thread::IndexedDBThreadFactory;
 use ipc_channel::ipc::{self, IpcReceiver, IpcReceiverSet, IpcSender};
 use malloc_size_of::{MallocSizeOf, MallocSizeOfOps};
 use net_traits::{CookieSource, CoreResourceThread};
 use net_traits::{CoreResourceMsg, CustomResponseMediator, FetchChannels};
 use net_traits::{FetchResponseMsg, ResourceThreads, WebSocketDomAction};
 use net_traits::WebSocketNetworkEvent;
-use net_traits::request::{Request, RequestInit};
+use net_traits::cache_storage_thread::CacheStorageThreadMsg;
+use net_traits::indexeddb_thread::IndexedDBThreadMsg;
+use net_traits::request::{BodyDemand, Request, RequestId, RequestInit};
 use net_traits::response::{Response, ResponseInit};
 use net_traits::storage_thread::StorageThreadMsg;
 use profile_traits::mem::{Report, ReportsChan, ReportKind};
 use profile_traits::mem::ProfilerChan as MemProfilerChan;
 use profile_traits::time::ProfilerChan;
+use proxy::ProxyConfig;
 use serde::{Deserialize, Serialize};
 use serde_json;
 use servo_allocator;
 use servo_channel::Sender;
 use servo_config::opts;
+use servo_config::prefs::PREFS;
 use servo_url::ServoUrl;
 use std::borrow::{Cow, ToOwned};
 use std::collections::HashMap;
 use std::error::Error;
 use std::fs::{self, File};
+use std::io;
 use std::io::prelude::*;
 use std::ops::Deref;
 use std::path::{Path, PathBuf};
@@ -62,9 +69,11 @@ pub fn new_resource_threads(user_agent: Cow<'static, str>,
         mem_profiler_chan,
         embedder_proxy,
         config_dir.clone());
-    let storage: IpcSender<StorageThreadMsg> = StorageThreadFactory::new(config_dir);
-    (ResourceThreads::new(public_core, storage.clone()),
-     ResourceThreads::new(private_core, storage))
+    let storage: IpcSender<StorageThreadMsg> = StorageThreadFactory::new(config_dir.clone());
+    let indexeddb: IpcSender<IndexedDBThreadMsg> = IndexedDBThreadFactory::new(config_dir.clone());
+    let cache_storage: IpcSender<CacheStorageThreadMsg> = CacheStorageThreadFactory::new(config_dir);
+    (ResourceThreads::new(public_core, storage.clone(), indexeddb.clone(), cache_storage.clone()),
+     ResourceThreads::new(private_core, storage, indexeddb, cache_storage))
 }
 
 
@@ -112,12 +121,16 @@ struct ResourceChannelManager {
 fn create_http_states(config_dir: Option<&Path>) -> (Arc<HttpState>, Arc<HttpState>) {
     let mut hsts_list = HstsList::from_servo_preload();
     let mut auth_cache = AuthCache::new();
-    let http_cache = HttpCache::new();
+    let mut http_cache = HttpCache::new();
     let mut cookie_jar = CookieStorage::new(150);
     if let Some(config_dir) = config_dir {
         read_json_from_file(&mut auth_cache, config_dir, "auth_cache.json");
         read_json_from_file(&mut hsts_list, config_dir, "hsts_list.json");
         read_json_from_file(&mut cookie_jar, config_dir, "cookie_jar.json");
+        let capacity = PREFS.get("network.http-cache.disk-capacity").as_u64().unwrap_or(0);
+        if capacity > 0 {
+            http_cache = HttpCache::new_with_disk_cache(config_dir.join("http_cache"), capacity);
+        }
     }
 
     let certs = match opts::get().certificate_path {
@@ -129,6 +142,9 @@ fn create_http_states(config_dir: Option<&Path>) -> (Arc<HttpState>, Arc<HttpSta
         },
     };
 
+    // The proxy is only configured at startup.
+    let proxy = ProxyConfig::from_opts_and_prefs();
+
     let ssl_client = create_ssl_client(&certs);
     let http_state = HttpState {
         cookie_jar: RwLock::new(cookie_jar),
@@ -137,11 +153,12 @@ fn create_http_states(config_dir: Option<&Path>) -> (Arc<HttpState>, Arc<HttpSta
         hsts_list: RwLock::new(hsts_list),
         history_states: RwLock::new(HashMap::new()),
         ssl_client: ssl_client.clone(),
-        connector: create_http_connector(ssl_client),
+        connector: create_http_connector(ssl_client, proxy.clone()),
+        proxy: proxy.clone(),
     };
 
     let private_ssl_client = create_ssl_client(&certs);
-    let private_http_state = HttpState::new(private_ssl_client);
+    let private_http_state = HttpState::new(private_ssl_client, proxy);
 
     (Arc::new(http_state), Arc::new(private_http_state))
 }
@@ -221,14 +238,15 @@ impl ResourceChannelManager {
         match msg {
             CoreResourceMsg::Fetch(req_init, channels) => {
                 match channels {
-                    FetchChannels::ResponseMsg(sender, cancel_chan) =>
-                        self.resource_manager.fetch(req_init, None, sender, http_state, cancel_chan),
+                    FetchChannels::ResponseMsg(sender, cancel_chan, demand_chan) =>
+                        self.resource_manager.fetch(req_init, None, sender, http_state, cancel_chan, demand_chan),
                     FetchChannels::WebSocket { event_sender, action_receiver } =>
                         self.resource_manager.websocket_connect(req_init, event_sender, action_receiver, http_state),
                 }
             }
             CoreResourceMsg::FetchRedirect(req_init, res_init, sender, cancel_chan) =>
-                self.resource_manager.fetch(req_init, Some(res_init), sender, http_state, cancel_chan),
+                self.resource_manager.fetch(req_init, Some(res_init), sender, http_state, cancel_chan, None),
+            CoreResourceMsg::Cancel(ids) => self.resource_manager.cancel(ids),
             CoreResourceMsg::SetCookieForUrl(request, cookie, source) =>
                 self.resource_manager.set_cookie_for_url(&request, cookie.into_inner(), source, http_state),
             CoreResourceMsg::SetCookiesForUrl(request, cookies, source) => {
@@ -236,6 +254,10 @@ impl ResourceChannelManager {
                     self.resource_manager.set_cookie_for_url(&request, cookie.into_inner(), source, http_state);
                 }
             }
+            CoreResourceMsg::DeleteCookiesForUrl(url, name) => {
+                let mut cookie_jar = http_state.cookie_jar.write().unwrap();
+                cookie_jar.delete_cookies_for_url(&url, name.as_ref().map(|name| &**name));
+            }
             CoreResourceMsg::GetCookiesForUrl(url, consumer, source) => {
                 let mut cookie_jar = http_state.cookie_jar.write().unwrap();
                 consumer.send(cookie_jar.cookies_for_url(&url, source)).unwrap();
@@ -262,6 +284,11 @@ impl ResourceChannelManager {
                     history_states.remove(&history_state);
                 }
             }
+            CoreResourceMsg::ClearCache => {
+                if let Ok(mut http_cache) = http_state.http_cache.write() {
+                    http_cache.clear();
+                }
+            }
             CoreResourceMsg::Synchronize(sender) => {
                 let _ = sender.send(());
             }
@@ -280,6 +307,10 @@ impl ResourceChannelManager {
                         Ok(hsts) => write_json_to_file(&*hsts, config_dir, "hsts_list.json"),
                         Err(_) => warn!("Error writing hsts list to disk"),
                     }
+                    match http_state.http_cache.read() {
+                        Ok(http_cache) => http_cache.flush(),
+                        Err(_) => warn!("Error writing http cache to disk"),
+                    }
                 }
                 let _ = sender.send(());
                 return false;
@@ -345,6 +376,30 @@ pub fn write_json_to_file<T>(data: &T, config_dir: &Path, filename: &str)
     }
 }
 
+/// Writes `data` as JSON to `filename` in `config_dir` with `write_file_atomically`, so that
+/// a crash or an IO error never leaves a truncated file behind.
+pub fn write_json_to_file_atomically<T>(data: &T, config_dir: &Path, filename: &str) -> io::Result<()>
+    where T: Serialize
+{
+    let json_encoded = serde_json::to_vec(data)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    write_file_atomically(&config_dir.join(filename), &json_encoded)
+}
+
+/// Writes `bytes` to a temporary file next to `path`, which is then renamed over `path`
+/// once it has been written in full.
+pub fn write_file_atomically(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+    let temp_path = PathBuf::from(temp_path);
+    {
+        let mut file = File::create(&temp_path)?;
+        file.write_all(bytes)?;
+        file.sync_all()?;
+    }
+    fs::rename(&temp_path, path)
+}
+
 #[derive(Clone, Deserialize, Serialize)]
 pub struct AuthCacheEntry {
     pub user_name: String,
@@ -371,6 +426,9 @@ pub struct CoreResourceManager {
     devtools_chan: Option<Sender<DevtoolsControlMsg>>,
     swmanager_chan: Option<IpcSender<CustomResponseMediator>>,
     filemanager: FileManager,
+    /// The cancellation listeners of the ongoing fetches, by the id of their request. Fetches
+    /// that follow a redirect share the id of the request they started with.
+    cancellation_listeners: Arc<Mutex<HashMap<RequestId, Vec<Arc<Mutex<CancellationListener>>>>>>,
 }
 
 impl CoreResourceManager {
@@ -383,6 +441,7 @@ impl CoreResourceManager {
             devtools_chan: devtools_channel,
             swmanager_chan: None,
             filemanager: FileManager::new(embedder_proxy),
+            cancellation_listeners: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
@@ -401,24 +460,38 @@ impl CoreResourceManager {
              res_init_: Option<ResponseInit>,
              mut sender: IpcSender<FetchResponseMsg>,
              http_state: &Arc<HttpState>,
-             cancel_chan: Option<IpcReceiver<()>>) {
+             cancel_chan: Option<IpcReceiver<()>>,
+             demand_chan: Option<IpcReceiver<()>>) {
         let http_state = http_state.clone();
         let ua = self.user_agent.clone();
         let dc = self.devtools_chan.clone();
         let filemanager = self.filemanager.clone();
+        let swmanager_chan = self.swmanager_chan.clone();
+        let request_id = req_init.id;
+        let cancellation_listener = Arc::new(Mutex::new(CancellationListener::new(cancel_chan)));
+        let cancellation_listeners = self.cancellation_listeners.clone();
+        cancellation_listeners.lock().unwrap()
+            .entry(request_id)
+            .or_insert_with(Vec::new)
+            .push(cancellation_listener.clone());
 
         thread::Builder::new().name(format!("fetch thread for {}", req_init.url)).spawn(move || {
             let mut request = Request::from_init(req_init);
+            // Integrity checks need the whole body before the response is handed over,
+            // so such bodies can't wait for their consumer.
+            if request.integrity_metadata.is_empty() {
+                request.body_demand = demand_chan.map(BodyDemand::new);
+            }
             // XXXManishearth: Check origin against pipeline id (also ensure that the mode is allowed)
             // todo load context / mimesniff in fetch
             // todo referrer policy?
-            // todo service worker stuff
             let context = FetchContext {
                 state: http_state,
                 user_agent: ua,
                 devtools_chan: dc,
                 filemanager: filemanager,
-                cancellation_listener: Arc::new(Mutex::new(CancellationListener::new(cancel_chan))),
+                cancellation_listener: cancellation_listener.clone(),
+                swmanager_chan: swmanager_chan,
             };
 
             match res_init_ {
@@ -434,9 +507,32 @@ impl CoreResourceManager {
                 },
                 None => fetch(&mut request, &mut sender, &context),
             };
+
+            // Other fetches with the same id may still be going on.
+            let mut cancellation_listeners = cancellation_listeners.lock().unwrap();
+            let is_empty = match cancellation_listeners.get_mut(&request_id) {
+                Some(listeners) => {
+                    listeners.retain(|listener| !Arc::ptr_eq(listener, &cancellation_listener));
+                    listeners.is_empty()
+                },
+                None => false,
+            };
+            if is_empty {
+                cancellation_listeners.remove(&request_id);
+            }
         }).expect("Thread spawning failed");
     }
 
+    /// Cancels the fetches with the given ids that are still ongoing.
+    fn cancel(&self, ids: Vec<RequestId>) {
+        let cancellation_listeners = self.cancellation_listeners.lock().unwrap();
+        for id in ids {
+            for listener in cancellation_listeners.get(&id).into_iter().flatten() {
+                listener.lock().unwrap().cancel();
+            }
+        }
+    }
+
     fn websocket_connect(
         &self,
         request: RequestInit,
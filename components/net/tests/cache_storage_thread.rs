@@ -0,0 +1,169 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use create_embedder_proxy;
+use ipc_channel::ipc;
+use net::resource_thread::new_resource_threads;
+use net_traits::{IpcSend, ResourceThreads};
+use net_traits::cache_storage_thread::{CacheEntry, CacheQueryOptions, CacheStorageError, CacheStorageThreadMsg};
+use net_traits::cache_storage_thread::{CachedRequest, CachedResponse};
+use profile_traits::mem::ProfilerChan as MemProfilerChan;
+use profile_traits::time::ProfilerChan;
+use servo_url::ServoUrl;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use time;
+
+fn new_threads(config_dir: Option<PathBuf>) -> ResourceThreads {
+    let (tx, _rx) = ipc::channel().unwrap();
+    let (mtx, _mrx) = ipc::channel().unwrap();
+    let (resource_threads, _private_resource_threads) = new_resource_threads(
+        "".into(), None, ProfilerChan(tx), MemProfilerChan(mtx), create_embedder_proxy(), config_dir);
+    resource_threads
+}
+
+fn url() -> ServoUrl {
+    ServoUrl::parse("https://example.com/").unwrap()
+}
+
+fn request(url: &str, headers: Vec<(&str, &str)>) -> CachedRequest {
+    CachedRequest {
+        url: ServoUrl::parse(url).unwrap(),
+        method: "GET".to_owned(),
+        headers: headers.into_iter().map(|(name, value)| (name.to_owned(), value.to_owned())).collect(),
+    }
+}
+
+fn entry(request: CachedRequest, headers: Vec<(&str, &str)>, body: &[u8]) -> CacheEntry {
+    CacheEntry {
+        response: CachedResponse {
+            url: Some(request.url.clone()),
+            status: 200,
+            status_text: b"OK".to_vec(),
+            headers: headers.into_iter().map(|(name, value)| (name.to_owned(), value.to_owned())).collect(),
+            body: body.to_vec(),
+        },
+        request: request,
+    }
+}
+
+fn open(threads: &ResourceThreads, name: &str) {
+    let (sender, receiver) = ipc::channel().unwrap();
+    threads.send(CacheStorageThreadMsg::OpenCache(sender, url(), name.to_owned())).unwrap();
+    assert_eq!(receiver.recv().unwrap(), Ok(()));
+}
+
+fn put(threads: &ResourceThreads, name: &str, entries: Vec<CacheEntry>) -> Result<(), CacheStorageError> {
+    let (sender, receiver) = ipc::channel().unwrap();
+    threads.send(CacheStorageThreadMsg::Put(sender, url(), name.to_owned(), entries)).unwrap();
+    receiver.recv().unwrap()
+}
+
+fn match_bodies(threads: &ResourceThreads,
+                name: Option<&str>,
+                request: Option<CachedRequest>,
+                options: CacheQueryOptions)
+                -> Vec<Vec<u8>> {
+    let (sender, receiver) = ipc::channel().unwrap();
+    let name = name.map(ToOwned::to_owned);
+    threads.send(CacheStorageThreadMsg::Match(sender, url(), name, request, options)).unwrap();
+    receiver.recv().unwrap().unwrap().into_iter().map(|entry| entry.response.body).collect()
+}
+
+fn exit(threads: ResourceThreads) {
+    let (sender, receiver) = ipc::channel().unwrap();
+    threads.send(CacheStorageThreadMsg::Exit(sender)).unwrap();
+    receiver.recv().unwrap();
+}
+
+#[test]
+fn test_put_replaces_matching_entries() {
+    let threads = new_threads(None);
+    assert_eq!(put(&threads, "v1", vec![entry(request("https://example.com/a", vec![]), vec![], b"a")]),
+               Err(CacheStorageError::NotFound));
+
+    open(&threads, "v1");
+    assert!(put(&threads, "v1", vec![
+        entry(request("https://example.com/a", vec![]), vec![], b"a"),
+        entry(request("https://example.com/b", vec![]), vec![], b"b"),
+    ]).is_ok());
+    assert!(put(&threads, "v1", vec![entry(request("https://example.com/a#top", vec![]), vec![], b"c")]).is_ok());
+
+    assert_eq!(match_bodies(&threads, Some("v1"), None, CacheQueryOptions::default()),
+               vec![b"b".to_vec(), b"c".to_vec()]);
+    exit(threads);
+}
+
+#[test]
+fn test_match_options() {
+    let threads = new_threads(None);
+    open(&threads, "v1");
+    let cached = request("https://example.com/a?x=1", vec![("Accept-Language", "en")]);
+    assert!(put(&threads, "v1", vec![entry(cached, vec![("Vary", "Accept-Language")], b"a")]).is_ok());
+
+    let options = CacheQueryOptions::default();
+    let query = request("https://example.com/a?x=1", vec![("accept-language", "en")]);
+    assert_eq!(match_bodies(&threads, None, Some(query), options), vec![b"a".to_vec()]);
+
+    let query = request("https://example.com/a?x=1", vec![("Accept-Language", "fr")]);
+    assert!(match_bodies(&threads, None, Some(query.clone()), options).is_empty());
+    let ignore_vary = CacheQueryOptions { ignore_vary: true, .. options };
+    assert_eq!(match_bodies(&threads, None, Some(query), ignore_vary), vec![b"a".to_vec()]);
+
+    let query = request("https://example.com/a", vec![("Accept-Language", "en")]);
+    assert!(match_bodies(&threads, None, Some(query.clone()), options).is_empty());
+    let ignore_search = CacheQueryOptions { ignore_search: true, .. options };
+    assert_eq!(match_bodies(&threads, None, Some(query.clone()), ignore_search), vec![b"a".to_vec()]);
+
+    let post = CachedRequest { method: "POST".to_owned(), .. query };
+    assert!(match_bodies(&threads, None, Some(post.clone()), ignore_search).is_empty());
+    let ignore_method = CacheQueryOptions { ignore_method: true, .. ignore_search };
+    assert_eq!(match_bodies(&threads, None, Some(post), ignore_method), vec![b"a".to_vec()]);
+    exit(threads);
+}
+
+#[test]
+fn test_caches_persist() {
+    let config_dir = env::temp_dir().join(format!("servo-cache-storage-{}", time::precise_time_ns()));
+    let _ = fs::remove_dir_all(&config_dir);
+
+    let threads = new_threads(Some(config_dir.clone()));
+    open(&threads, "v1");
+    open(&threads, "v2");
+    assert!(put(&threads, "v2", vec![entry(request("https://example.com/a", vec![]), vec![], b"a")]).is_ok());
+    let (sender, receiver) = ipc::channel().unwrap();
+    threads.send(CacheStorageThreadMsg::DeleteCache(sender, url(), "v1".to_owned())).unwrap();
+    assert_eq!(receiver.recv().unwrap(), Ok(true));
+    exit(threads);
+
+    let threads = new_threads(Some(config_dir.clone()));
+    let (sender, receiver) = ipc::channel().unwrap();
+    threads.send(CacheStorageThreadMsg::CacheNames(sender, url())).unwrap();
+    assert_eq!(receiver.recv().unwrap(), vec!["v2".to_owned()]);
+    assert_eq!(match_bodies(&threads, None, None, CacheQueryOptions::default()), vec![b"a".to_vec()]);
+    exit(threads);
+
+    let _ = fs::remove_dir_all(&config_dir);
+}
+
+#[test]
+fn test_replaced_bodies_are_removed() {
+    let config_dir = env::temp_dir().join(format!("servo-cache-storage-{}", time::precise_time_ns()));
+    let _ = fs::remove_dir_all(&config_dir);
+
+    let threads = new_threads(Some(config_dir.clone()));
+    open(&threads, "v1");
+    assert!(put(&threads, "v1", vec![entry(request("https://example.com/a", vec![]), vec![], b"a")]).is_ok());
+    assert!(put(&threads, "v1", vec![entry(request("https://example.com/a", vec![]), vec![], b"b")]).is_ok());
+    exit(threads);
+
+    let bodies = fs::read_dir(config_dir.join("cache_storage").join("https_3a_2f_2fexample.com")).unwrap();
+    assert_eq!(bodies.count(), 1);
+    let threads = new_threads(Some(config_dir.clone()));
+    assert_eq!(match_bodies(&threads, Some("v1"), None, CacheQueryOptions::default()), vec![b"b".to_vec()]);
+    exit(threads);
+
+    let _ = fs::remove_dir_all(&config_dir);
+}
@@ -314,6 +314,29 @@ fn test_secure_cookies_eviction_non_http_source() {
 }
 
 
+#[test]
+fn test_delete_cookies_for_url() {
+    let mut storage = CookieStorage::new(5);
+    let url = ServoUrl::parse("https://home.example.org:8888/foo/cookie-parser?0001").unwrap();
+    let other_url = ServoUrl::parse("https://other.example.org:8888/cookie-parser?0001").unwrap();
+
+    add_cookie_to_storage(&mut storage, &url, "foo=bar");
+    add_cookie_to_storage(&mut storage, &url, "foo2=bar; HttpOnly");
+    add_cookie_to_storage(&mut storage, &url, "foo3=bar; Path=/foo/bar");
+    add_cookie_to_storage(&mut storage, &other_url, "foo=bar");
+
+    storage.delete_cookies_for_url(&url, Some("foo"));
+    assert_eq!(storage.cookies_for_url(&url, CookieSource::HTTP).unwrap(), "foo2=bar");
+    assert_eq!(storage.cookies_for_url(&other_url, CookieSource::HTTP).unwrap(), "foo=bar");
+
+    storage.delete_cookies_for_url(&url, None);
+    assert_eq!(storage.cookies_for_url(&url, CookieSource::HTTP), None);
+
+    // Cookies which wouldn't be sent to the url are kept.
+    let url = ServoUrl::parse("https://home.example.org:8888/foo/bar/cookie-parser?0001").unwrap();
+    assert_eq!(storage.cookies_for_url(&url, CookieSource::HTTP).unwrap(), "foo3=bar");
+}
+
 fn add_retrieve_cookies(set_location: &str,
                         set_cookies: &[String],
                         final_location: &str)
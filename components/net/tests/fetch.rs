@@ -31,6 +31,7 @@ use net::test::HttpState;
 use net_traits::IncludeSubdomains;
 use net_traits::NetworkError;
 use net_traits::ReferrerPolicy;
+use net_traits::csp::{CspList, PolicyDisposition, PolicySource};
 use net_traits::request::{Destination, Origin, RedirectMode, Referrer, Request, RequestMode};
 use net_traits::response::{CacheState, Response, ResponseBody, ResponseType};
 use servo_channel::{channel, Sender};
@@ -76,6 +77,44 @@ fn test_fetch_on_bad_port_is_network_error() {
     assert_eq!(fetch_error, &NetworkError::Internal("Request attempted on bad port".into()))
 }
 
+#[test]
+fn test_fetch_blocked_by_csp_is_network_error() {
+    let url = ServoUrl::parse("http://www.example.org/image.png").unwrap();
+    let origin = Origin::Origin(ServoUrl::parse("http://www.example.com").unwrap().origin());
+    let mut request = Request::new(url, Some(origin), None);
+    request.referrer = Referrer::NoReferrer;
+    request.destination = Destination::Image;
+    request.csp_list = Some(CspList::parse("default-src 'none'; img-src 'self'",
+                                           PolicySource::Header,
+                                           PolicyDisposition::Enforce));
+    let fetch_response = fetch(&mut request, None);
+    assert!(fetch_response.is_network_error());
+    let fetch_error = fetch_response.get_network_error().unwrap();
+    assert_eq!(fetch_error, &NetworkError::Internal("Blocked by Content Security Policy".into()))
+}
+
+#[test]
+fn test_fetch_allowed_by_csp() {
+    static MESSAGE: &'static [u8] = b"Hello World!";
+    let handler = move |_: HyperRequest, response: HyperResponse| {
+        response.send(MESSAGE).unwrap();
+    };
+    let (mut server, url) = make_server(handler);
+
+    let origin = Origin::Origin(url.origin());
+    let mut request = Request::new(url, Some(origin), None);
+    request.referrer = Referrer::NoReferrer;
+    request.destination = Destination::Script;
+    // The enforced policy allows the request, and the report-only one must not block it.
+    let mut csp_list = CspList::parse("script-src 'self'", PolicySource::Header, PolicyDisposition::Enforce);
+    csp_list.append(CspList::parse("default-src 'none'", PolicySource::Header, PolicyDisposition::Report));
+    request.csp_list = Some(csp_list);
+    let fetch_response = fetch(&mut request, None);
+    let _ = server.close();
+
+    assert!(!fetch_response.is_network_error());
+}
+
 #[test]
 fn test_fetch_response_body_matches_const_message() {
     static MESSAGE: &'static [u8] = b"Hello World!";
@@ -507,6 +546,29 @@ fn test_fetch_with_local_urls_only() {
     assert!(server_response.is_network_error());
 }
 
+#[test]
+fn test_fetch_cancelled_before_connecting() {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let counter = requests.clone();
+    let handler = move |_: HyperRequest, response: HyperResponse| {
+        counter.fetch_add(1, Ordering::SeqCst);
+        response.send(b"").unwrap();
+    };
+    let (mut server, url) = make_server(handler);
+
+    let origin = Origin::Origin(url.origin());
+    let mut request = Request::new(url, Some(origin), None);
+    request.referrer = Referrer::NoReferrer;
+
+    let context = new_fetch_context(None, None);
+    context.cancellation_listener.lock().unwrap().cancel();
+    let fetch_response = fetch_with_context(&mut request, &context);
+    let _ = server.close();
+
+    assert!(fetch_response.is_network_error());
+    assert_eq!(requests.load(Ordering::SeqCst), 0);
+}
+
 // NOTE(emilio): If this test starts failing:
 //
 // openssl req -x509 -nodes -days 3650 -newkey rsa:2048 \
@@ -535,11 +597,12 @@ fn test_fetch_with_hsts() {
     let ssl_client = create_ssl_client(&ca_content);
 
     let context = FetchContext {
-        state: Arc::new(HttpState::new(ssl_client)),
+        state: Arc::new(HttpState::new(ssl_client, None)),
         user_agent: DEFAULT_USER_AGENT.into(),
         devtools_chan: None,
         filemanager: FileManager::new(create_embedder_proxy()),
         cancellation_listener: Arc::new(Mutex::new(CancellationListener::new(None))),
+        swmanager_chan: None,
     };
 
     {
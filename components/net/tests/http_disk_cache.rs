@@ -0,0 +1,113 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use hyper::header::{CacheControl, CacheDirective};
+use hyper::method::Method;
+use msg::constellation_msg::TEST_PIPELINE_ID;
+use net::http_cache::HttpCache;
+use net_traits::request::{Destination, Request, RequestInit};
+use net_traits::response::{Response, ResponseBody};
+use servo_url::ServoUrl;
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::PathBuf;
+use time;
+
+fn cache_directory(name: &str) -> PathBuf {
+    let directory = env::temp_dir().join(format!("servo-{}-{}", name, time::precise_time_ns()));
+    let _ = fs::remove_dir_all(&directory);
+    directory
+}
+
+fn request(url: &ServoUrl) -> Request {
+    Request::from_init(RequestInit {
+        url: url.clone(),
+        method: Method::Get,
+        destination: Destination::Document,
+        origin: url.clone().origin(),
+        pipeline_id: Some(TEST_PIPELINE_ID),
+        .. RequestInit::default()
+    })
+}
+
+fn store_response(cache: &mut HttpCache, request: &Request, body: &[u8]) {
+    let mut response = Response::new(request.current_url());
+    response.headers.set(CacheControl(vec![CacheDirective::MaxAge(3600)]));
+    *response.body.lock().unwrap() = ResponseBody::Done(body.to_vec());
+    cache.store(request, &response);
+    cache.write_to_disk(request);
+}
+
+fn cached_body(cache: &HttpCache, request: &Request) -> Option<Vec<u8>> {
+    let cached = cache.construct_response(request, &mut None)?;
+    let body = cached.response.body.lock().unwrap();
+    match *body {
+        ResponseBody::Done(ref bytes) => Some(bytes.clone()),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_disk_cache_survives_restart() {
+    let directory = cache_directory("http-disk-cache-restart");
+    let request = request(&ServoUrl::parse("https://servo.org/image.png").unwrap());
+    {
+        let mut cache = HttpCache::new_with_disk_cache(directory.clone(), 1024);
+        store_response(&mut cache, &request, b"servo");
+        cache.flush();
+    }
+
+    let mut cache = HttpCache::new_with_disk_cache(directory.clone(), 1024);
+    assert_eq!(cached_body(&cache, &request), Some(b"servo".to_vec()));
+
+    cache.clear();
+    assert_eq!(cached_body(&cache, &request), None);
+    cache.flush();
+    let cache = HttpCache::new_with_disk_cache(directory.clone(), 1024);
+    assert_eq!(cached_body(&cache, &request), None);
+    let _ = fs::remove_dir_all(&directory);
+}
+
+#[test]
+fn test_disk_cache_evicts_least_recently_used() {
+    let directory = cache_directory("http-disk-cache-eviction");
+    let first = request(&ServoUrl::parse("https://servo.org/first").unwrap());
+    let second = request(&ServoUrl::parse("https://servo.org/second").unwrap());
+    let third = request(&ServoUrl::parse("https://servo.org/third").unwrap());
+    {
+        let mut cache = HttpCache::new_with_disk_cache(directory.clone(), 10);
+        store_response(&mut cache, &first, b"1111");
+        store_response(&mut cache, &second, b"2222");
+        cache.flush();
+    }
+
+    let mut cache = HttpCache::new_with_disk_cache(directory.clone(), 10);
+    // Reading the first body makes the second one the least recently used.
+    assert_eq!(cached_body(&cache, &first), Some(b"1111".to_vec()));
+    store_response(&mut cache, &third, b"3333");
+    cache.flush();
+
+    let cache = HttpCache::new_with_disk_cache(directory.clone(), 10);
+    assert_eq!(cached_body(&cache, &first), Some(b"1111".to_vec()));
+    assert_eq!(cached_body(&cache, &second), None);
+    assert_eq!(cached_body(&cache, &third), Some(b"3333".to_vec()));
+    let _ = fs::remove_dir_all(&directory);
+}
+
+#[test]
+fn test_disk_cache_ignores_interrupted_index_writes() {
+    let directory = cache_directory("http-disk-cache-interrupted");
+    let request = request(&ServoUrl::parse("https://servo.org/image.png").unwrap());
+    {
+        let mut cache = HttpCache::new_with_disk_cache(directory.clone(), 1024);
+        store_response(&mut cache, &request, b"servo");
+    }
+    // A crash while the index is written leaves a partial copy of it behind.
+    File::create(directory.join("index.json.tmp")).unwrap().write_all(b"{\"entries\": {").unwrap();
+
+    let cache = HttpCache::new_with_disk_cache(directory.clone(), 1024);
+    assert_eq!(cached_body(&cache, &request), Some(b"servo".to_vec()));
+    let _ = fs::remove_dir_all(&directory);
+}
@@ -20,6 +20,7 @@ use hyper::mime::{Mime, SubLevel, TopLevel};
 use hyper::server::{Request as HyperRequest, Response as HyperResponse};
 use hyper::status::StatusCode;
 use hyper::uri::RequestUri;
+use ipc_channel::ipc::{self, IpcSender};
 use make_server;
 use msg::constellation_msg::TEST_PIPELINE_ID;
 use net::cookie::Cookie;
@@ -27,7 +28,7 @@ use net::cookie_storage::CookieStorage;
 use net::resource_thread::AuthCacheEntry;
 use net::test::replace_host_table;
 use net_traits::{CookieSource, NetworkError};
-use net_traits::request::{Request, RequestInit, RequestMode, CredentialsMode, Destination};
+use net_traits::request::{BodyChunk, BodySource, Request, RequestInit, RequestMode, CredentialsMode, Destination};
 use net_traits::response::ResponseBody;
 use new_fetch_context;
 use servo_channel::{channel, Receiver};
@@ -37,6 +38,7 @@ use std::io::{Read, Write};
 use std::str::FromStr;
 use std::sync::{Arc, Mutex, RwLock};
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
 
 fn mock_origin() -> ImmutableOrigin {
     ServoUrl::parse("http://servo.org").unwrap().origin()
@@ -54,6 +56,18 @@ fn read_response(reader: &mut Read) -> String {
     }
 }
 
+fn body_source(chunks: Vec<&'static [u8]>) -> BodySource {
+    let (chunk_requester, chunk_requests) = ipc::channel::<IpcSender<BodyChunk>>().unwrap();
+    thread::spawn(move || {
+        let mut chunks = chunks.into_iter();
+        while let Ok(sender) = chunk_requests.recv() {
+            let chunk = chunks.next().map_or(BodyChunk::Done, |chunk| BodyChunk::Bytes(chunk.to_vec()));
+            let _ = sender.send(chunk);
+        }
+    });
+    BodySource::new(chunk_requester)
+}
+
 fn assert_cookie_for_domain(cookie_jar: &RwLock<CookieStorage>, domain: &str, cookie: Option<&str>) {
     let mut cookie_jar = cookie_jar.write().unwrap();
     let url = ServoUrl::parse(&*domain).unwrap();
@@ -712,6 +726,90 @@ fn test_load_sets_content_length_to_length_of_request_body() {
     assert!(response.internal_response.unwrap().status.unwrap().is_success());
 }
 
+#[test]
+fn test_load_sends_streamed_request_body_in_chunks() {
+    let handler = move |mut request: HyperRequest, response: HyperResponse| {
+        assert_eq!(request.headers.get::<ContentLength>(), None);
+        let mut body = String::new();
+        request.read_to_string(&mut body).unwrap();
+        assert_eq!(body, "This is a request body");
+        response.send(b"Yay!").unwrap();
+    };
+    let (mut server, url) = make_server(handler);
+
+    let mut request = Request::from_init(RequestInit {
+        url: url.clone(),
+        method: Method::Post,
+        body_source: Some(body_source(vec![b"This is ", b"a request ", b"body"])),
+        destination: Destination::Document,
+        origin: mock_origin(),
+        pipeline_id: Some(TEST_PIPELINE_ID),
+        .. RequestInit::default()
+    });
+    let response = fetch(&mut request, None);
+
+    let _ = server.close();
+
+    assert!(response.internal_response.unwrap().status.unwrap().is_success());
+}
+
+#[test]
+fn test_load_fails_when_streamed_request_body_errors() {
+    let (chunk_requester, chunk_requests) = ipc::channel::<IpcSender<BodyChunk>>().unwrap();
+    thread::spawn(move || {
+        while let Ok(sender) = chunk_requests.recv() {
+            let _ = sender.send(BodyChunk::Error);
+        }
+    });
+    let handler = move |_: HyperRequest, response: HyperResponse| {
+        response.send(b"Yay!").unwrap();
+    };
+    let (mut server, url) = make_server(handler);
+
+    let mut request = Request::from_init(RequestInit {
+        url: url.clone(),
+        method: Method::Post,
+        body_source: Some(BodySource::new(chunk_requester)),
+        destination: Destination::Document,
+        origin: mock_origin(),
+        pipeline_id: Some(TEST_PIPELINE_ID),
+        .. RequestInit::default()
+    });
+    let response = fetch(&mut request, None);
+
+    let _ = server.close();
+
+    assert!(response.is_network_error());
+}
+
+#[test]
+fn test_load_fails_when_redirecting_a_streamed_request_body() {
+    let redirect_url = ServoUrl::parse("http://servo.org/").unwrap();
+    let handler = move |mut request: HyperRequest, mut response: HyperResponse| {
+        let mut body = String::new();
+        request.read_to_string(&mut body).unwrap();
+        response.headers_mut().set(Location(redirect_url.to_string()));
+        *response.status_mut() = StatusCode::TemporaryRedirect;
+        response.send(b"").unwrap();
+    };
+    let (mut server, url) = make_server(handler);
+
+    let mut request = Request::from_init(RequestInit {
+        url: url.clone(),
+        method: Method::Post,
+        body_source: Some(body_source(vec![b"Body on POST!"])),
+        destination: Destination::Document,
+        origin: mock_origin(),
+        pipeline_id: Some(TEST_PIPELINE_ID),
+        .. RequestInit::default()
+    });
+    let response = fetch(&mut request, None);
+
+    let _ = server.close();
+
+    assert!(response.is_network_error());
+}
+
 #[test]
 fn test_load_uses_explicit_accept_from_headers_in_load_data() {
     let accept = Accept(vec![qitem(Mime(TopLevel::Text, SubLevel::Html, vec![]))]);
@@ -0,0 +1,318 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use create_embedder_proxy;
+use ipc_channel::ipc::{self, IpcReceiver};
+use net::resource_thread::new_resource_threads;
+use net_traits::{IpcSend, ResourceThreads};
+use net_traits::indexeddb_thread::{IndexInfo, IndexedDBBlockingStatus, IndexedDBConnectionEvent};
+use net_traits::indexeddb_thread::{IndexedDBCursorDirection, IndexedDBDeletionStatus, IndexedDBError, IndexedDBKey};
+use net_traits::indexeddb_thread::{IndexedDBKeyPath, IndexedDBKeyRange, IndexedDBOperation};
+use net_traits::indexeddb_thread::{IndexedDBQuery, IndexedDBThreadMsg};
+use profile_traits::mem::ProfilerChan as MemProfilerChan;
+use profile_traits::time::ProfilerChan;
+use servo_url::ServoUrl;
+use std::env;
+use std::f64;
+use std::fs;
+use std::path::PathBuf;
+use time;
+
+fn new_threads() -> ResourceThreads {
+    new_threads_with_config_dir(None)
+}
+
+fn new_threads_with_config_dir(config_dir: Option<PathBuf>) -> ResourceThreads {
+    let (tx, _rx) = ipc::channel().unwrap();
+    let (mtx, _mrx) = ipc::channel().unwrap();
+    let (resource_threads, _private_resource_threads) = new_resource_threads(
+        "".into(), None, ProfilerChan(tx), MemProfilerChan(mtx), create_embedder_proxy(), config_dir);
+    resource_threads
+}
+
+fn url() -> ServoUrl {
+    ServoUrl::parse("https://example.com/").unwrap()
+}
+
+fn transaction(threads: &ResourceThreads) -> u64 {
+    let (sender, receiver) = ipc::channel().unwrap();
+    threads.send(IndexedDBThreadMsg::NewTransaction(sender)).unwrap();
+    receiver.recv().unwrap()
+}
+
+fn create_store(threads: &ResourceThreads, transaction: u64, auto_increment: bool) {
+    let (sender, receiver) = ipc::channel().unwrap();
+    let operation = IndexedDBOperation::CreateObjectStore(sender, "store".to_owned(), None, auto_increment);
+    threads.send(IndexedDBThreadMsg::Operation(url(), "db".to_owned(), transaction, operation)).unwrap();
+    assert_eq!(receiver.recv().unwrap(), Ok(()));
+}
+
+fn put(threads: &ResourceThreads,
+       transaction: u64,
+       key: Option<IndexedDBKey>,
+       index_keys: Vec<(String, Vec<IndexedDBKey>)>,
+       overwrite: bool)
+       -> Result<IndexedDBKey, IndexedDBError> {
+    let (sender, receiver) = ipc::channel().unwrap();
+    let operation = IndexedDBOperation::Put(sender, "store".to_owned(), key, vec![1, 2, 3], index_keys, overwrite);
+    threads.send(IndexedDBThreadMsg::Operation(url(), "db".to_owned(), transaction, operation)).unwrap();
+    receiver.recv().unwrap()
+}
+
+fn keys(threads: &ResourceThreads, transaction: u64, direction: IndexedDBCursorDirection) -> Vec<IndexedDBKey> {
+    let (sender, receiver) = ipc::channel().unwrap();
+    let query = IndexedDBQuery {
+        index: None,
+        range: IndexedDBKeyRange::default(),
+        direction: direction,
+        position: None,
+        count: None,
+    };
+    let operation = IndexedDBOperation::Get(sender, "store".to_owned(), query);
+    threads.send(IndexedDBThreadMsg::Operation(url(), "db".to_owned(), transaction, operation)).unwrap();
+    receiver.recv().unwrap().unwrap().into_iter().map(|record| record.key).collect()
+}
+
+fn commit(threads: &ResourceThreads, transaction: u64) {
+    let (sender, receiver) = ipc::channel().unwrap();
+    threads.send(IndexedDBThreadMsg::Commit(sender, url(), "db".to_owned(), transaction)).unwrap();
+    receiver.recv().unwrap();
+}
+
+fn abort(threads: &ResourceThreads, transaction: u64) {
+    let (sender, receiver) = ipc::channel().unwrap();
+    threads.send(IndexedDBThreadMsg::Abort(sender, url(), "db".to_owned(), transaction)).unwrap();
+    receiver.recv().unwrap();
+}
+
+fn open_connection(threads: &ResourceThreads, name: &str) -> (u64, IpcReceiver<IndexedDBConnectionEvent>) {
+    let (events_sender, events_receiver) = ipc::channel().unwrap();
+    let (sender, receiver) = ipc::channel().unwrap();
+    threads.send(IndexedDBThreadMsg::OpenConnection(sender, url(), name.to_owned(), events_sender)).unwrap();
+    (receiver.recv().unwrap(), events_receiver)
+}
+
+fn exit(threads: ResourceThreads) {
+    let (sender, receiver) = ipc::channel().unwrap();
+    threads.send(IndexedDBThreadMsg::Exit(sender)).unwrap();
+    receiver.recv().unwrap();
+}
+
+#[test]
+fn test_key_ordering() {
+    let mut keys = vec![
+        IndexedDBKey::Array(vec![]),
+        IndexedDBKey::Binary(vec![1]),
+        IndexedDBKey::String("b".to_owned()),
+        IndexedDBKey::String("a".to_owned()),
+        IndexedDBKey::Date(0.),
+        IndexedDBKey::Number(2.),
+        IndexedDBKey::Number(-1.),
+    ];
+    keys.sort();
+    assert_eq!(keys, vec![
+        IndexedDBKey::Number(-1.),
+        IndexedDBKey::Number(2.),
+        IndexedDBKey::Date(0.),
+        IndexedDBKey::String("a".to_owned()),
+        IndexedDBKey::String("b".to_owned()),
+        IndexedDBKey::Binary(vec![1]),
+        IndexedDBKey::Array(vec![]),
+    ]);
+}
+
+#[test]
+fn test_key_generator_and_order() {
+    let threads = new_threads();
+    let transaction = transaction(&threads);
+    create_store(&threads, transaction, true);
+
+    assert_eq!(put(&threads, transaction, None, vec![], false), Ok(IndexedDBKey::Number(1.)));
+    assert_eq!(put(&threads, transaction, Some(IndexedDBKey::Number(10.5)), vec![], false),
+               Ok(IndexedDBKey::Number(10.5)));
+    assert_eq!(put(&threads, transaction, None, vec![], false), Ok(IndexedDBKey::Number(11.)));
+    assert_eq!(put(&threads, transaction, Some(IndexedDBKey::Number(1.)), vec![], false),
+               Err(IndexedDBError::Constraint));
+    assert_eq!(put(&threads, transaction, Some(IndexedDBKey::Number(1.)), vec![], true),
+               Ok(IndexedDBKey::Number(1.)));
+
+    assert_eq!(keys(&threads, transaction, IndexedDBCursorDirection::Prev),
+               vec![IndexedDBKey::Number(11.), IndexedDBKey::Number(10.5), IndexedDBKey::Number(1.)]);
+    exit(threads);
+}
+
+#[test]
+fn test_abort_reverts_changes() {
+    let threads = new_threads();
+    let upgrade = transaction(&threads);
+    create_store(&threads, upgrade, false);
+    commit(&threads, upgrade);
+
+    let transaction = transaction(&threads);
+    assert_eq!(put(&threads, transaction, None, vec![], false), Err(IndexedDBError::Data));
+    assert!(put(&threads, transaction, Some(IndexedDBKey::String("a".to_owned())), vec![], false).is_ok());
+    abort(&threads, transaction);
+
+    assert!(keys(&threads, transaction, IndexedDBCursorDirection::Next).is_empty());
+    exit(threads);
+}
+
+#[test]
+fn test_abort_keeps_changes_of_other_transactions() {
+    let threads = new_threads();
+    let upgrade = transaction(&threads);
+    create_store(&threads, upgrade, false);
+    commit(&threads, upgrade);
+
+    let aborted = transaction(&threads);
+    let other = transaction(&threads);
+    assert!(put(&threads, aborted, Some(IndexedDBKey::String("a".to_owned())), vec![], false).is_ok());
+    assert!(put(&threads, other, Some(IndexedDBKey::String("b".to_owned())), vec![], false).is_ok());
+    abort(&threads, aborted);
+    commit(&threads, other);
+
+    assert_eq!(keys(&threads, other, IndexedDBCursorDirection::Next), vec![IndexedDBKey::String("b".to_owned())]);
+    exit(threads);
+}
+
+#[test]
+fn test_abort_keeps_changes_of_other_transactions_to_the_same_record() {
+    let threads = new_threads();
+    let upgrade = transaction(&threads);
+    create_store(&threads, upgrade, false);
+    commit(&threads, upgrade);
+
+    let aborted = transaction(&threads);
+    let other = transaction(&threads);
+    assert!(put(&threads, aborted, Some(IndexedDBKey::String("a".to_owned())), vec![], true).is_ok());
+    assert!(put(&threads, other, Some(IndexedDBKey::String("a".to_owned())), vec![], true).is_ok());
+    commit(&threads, other);
+    abort(&threads, aborted);
+
+    assert_eq!(keys(&threads, other, IndexedDBCursorDirection::Next), vec![IndexedDBKey::String("a".to_owned())]);
+    exit(threads);
+}
+
+#[test]
+fn test_uncommitted_changes_are_only_seen_by_their_transaction() {
+    let threads = new_threads();
+    let upgrade = transaction(&threads);
+    create_store(&threads, upgrade, false);
+    commit(&threads, upgrade);
+
+    let writer = transaction(&threads);
+    let reader = transaction(&threads);
+    assert!(put(&threads, writer, Some(IndexedDBKey::String("a".to_owned())), vec![], false).is_ok());
+    assert_eq!(keys(&threads, writer, IndexedDBCursorDirection::Next), vec![IndexedDBKey::String("a".to_owned())]);
+    assert!(keys(&threads, reader, IndexedDBCursorDirection::Next).is_empty());
+
+    commit(&threads, writer);
+    assert_eq!(keys(&threads, reader, IndexedDBCursorDirection::Next), vec![IndexedDBKey::String("a".to_owned())]);
+    exit(threads);
+}
+
+#[test]
+fn test_committed_changes_persist() {
+    let config_dir = env::temp_dir().join(format!("servo-indexeddb-{}", time::precise_time_ns()));
+    let _ = fs::remove_dir_all(&config_dir);
+
+    let threads = new_threads_with_config_dir(Some(config_dir.clone()));
+    let upgrade = transaction(&threads);
+    create_store(&threads, upgrade, false);
+    commit(&threads, upgrade);
+    let committed = transaction(&threads);
+    assert!(put(&threads, committed, Some(IndexedDBKey::Number(f64::INFINITY)), vec![], false).is_ok());
+    let pending = transaction(&threads);
+    assert!(put(&threads, pending, Some(IndexedDBKey::Number(f64::NEG_INFINITY)), vec![], false).is_ok());
+    commit(&threads, committed);
+    exit(threads);
+
+    let threads = new_threads_with_config_dir(Some(config_dir.clone()));
+    let transaction = transaction(&threads);
+    assert_eq!(keys(&threads, transaction, IndexedDBCursorDirection::Next),
+               vec![IndexedDBKey::Number(f64::INFINITY)]);
+    exit(threads);
+
+    let _ = fs::remove_dir_all(&config_dir);
+}
+
+#[test]
+fn test_unique_index() {
+    let threads = new_threads();
+    let transaction = transaction(&threads);
+    create_store(&threads, transaction, true);
+
+    let (sender, receiver) = ipc::channel().unwrap();
+    let info = IndexInfo {
+        name: "by_name".to_owned(),
+        key_path: IndexedDBKeyPath::String("name".to_owned()),
+        unique: true,
+        multi_entry: false,
+    };
+    let operation = IndexedDBOperation::CreateIndex(sender, "store".to_owned(), info, vec![]);
+    threads.send(IndexedDBThreadMsg::Operation(url(), "db".to_owned(), transaction, operation)).unwrap();
+    assert_eq!(receiver.recv().unwrap(), Ok(()));
+
+    let index_keys = || vec![("by_name".to_owned(), vec![IndexedDBKey::String("servo".to_owned())])];
+    assert!(put(&threads, transaction, None, index_keys(), false).is_ok());
+    assert_eq!(put(&threads, transaction, None, index_keys(), false), Err(IndexedDBError::Constraint));
+    assert_eq!(keys(&threads, transaction, IndexedDBCursorDirection::Next), vec![IndexedDBKey::Number(1.)]);
+    exit(threads);
+}
+
+#[test]
+fn test_version_change_is_blocked_until_other_connections_close() {
+    let threads = new_threads();
+    let (upgrading, _) = open_connection(&threads, "db");
+    let (other, other_events) = open_connection(&threads, "db");
+    let (_, unrelated_events) = open_connection(&threads, "unrelated");
+
+    let (sender, receiver) = ipc::channel().unwrap();
+    threads.send(IndexedDBThreadMsg::WaitForOtherConnections(sender, upgrading, 1)).unwrap();
+    assert_eq!(other_events.recv().unwrap(), IndexedDBConnectionEvent::VersionChange(0, Some(1)));
+
+    threads.send(IndexedDBThreadMsg::VersionChangeFired(other)).unwrap();
+    assert_eq!(receiver.recv().unwrap(), IndexedDBBlockingStatus::Blocked);
+
+    threads.send(IndexedDBThreadMsg::CloseConnection(other)).unwrap();
+    assert_eq!(receiver.recv().unwrap(), IndexedDBBlockingStatus::Unblocked);
+    assert!(unrelated_events.try_recv().is_err());
+    exit(threads);
+}
+
+#[test]
+fn test_version_change_is_not_blocked_by_connections_closed_on_versionchange() {
+    let threads = new_threads();
+    let (upgrading, _) = open_connection(&threads, "db");
+    let (other, other_events) = open_connection(&threads, "db");
+
+    let (sender, receiver) = ipc::channel().unwrap();
+    threads.send(IndexedDBThreadMsg::WaitForOtherConnections(sender, upgrading, 2)).unwrap();
+    assert_eq!(other_events.recv().unwrap(), IndexedDBConnectionEvent::VersionChange(0, Some(2)));
+
+    threads.send(IndexedDBThreadMsg::CloseConnection(other)).unwrap();
+    threads.send(IndexedDBThreadMsg::VersionChangeFired(other)).unwrap();
+    assert_eq!(receiver.recv().unwrap(), IndexedDBBlockingStatus::Unblocked);
+    exit(threads);
+}
+
+#[test]
+fn test_deletion_waits_for_connections_to_close() {
+    let threads = new_threads();
+    let (sender, receiver) = ipc::channel().unwrap();
+    threads.send(IndexedDBThreadMsg::OpenDatabase(sender, url(), "db".to_owned())).unwrap();
+    assert_eq!(receiver.recv().unwrap(), 0);
+    let (connection, events) = open_connection(&threads, "db");
+
+    let (sender, receiver) = ipc::channel().unwrap();
+    threads.send(IndexedDBThreadMsg::DeleteDatabase(sender, url(), "db".to_owned())).unwrap();
+    assert_eq!(events.recv().unwrap(), IndexedDBConnectionEvent::VersionChange(0, None));
+
+    threads.send(IndexedDBThreadMsg::VersionChangeFired(connection)).unwrap();
+    assert_eq!(receiver.recv().unwrap(), IndexedDBDeletionStatus::Blocked(0));
+
+    threads.send(IndexedDBThreadMsg::CloseConnection(connection)).unwrap();
+    assert_eq!(receiver.recv().unwrap(), IndexedDBDeletionStatus::Deleted(Some(0)));
+    exit(threads);
+}
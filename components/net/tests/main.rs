@@ -23,6 +23,7 @@ extern crate time;
 extern crate unicase;
 extern crate url;
 
+mod cache_storage_thread;
 mod cookie;
 mod cookie_http_state;
 mod data_loader;
@@ -30,8 +31,11 @@ mod fetch;
 mod file_loader;
 mod filemanager_thread;
 mod hsts;
+mod http_disk_cache;
 mod http_loader;
+mod indexeddb_thread;
 mod mime_classifier;
+mod proxy;
 mod resource_thread;
 mod subresource_integrity;
 
@@ -45,6 +49,7 @@ use net::fetch::methods::{self, CancellationListener, FetchContext};
 use net::filemanager_thread::FileManager;
 use net::test::HttpState;
 use net_traits::FetchTaskTarget;
+use net_traits::csp::Violation;
 use net_traits::request::Request;
 use net_traits::response::Response;
 use servo_channel::{channel, Sender};
@@ -87,11 +92,12 @@ fn new_fetch_context(dc: Option<Sender<DevtoolsControlMsg>>, fc: Option<Embedder
     let ssl_client = create_ssl_client(&resources::read_string(Resource::SSLCertificates));
     let sender = fc.unwrap_or_else(|| create_embedder_proxy());
     FetchContext {
-        state: Arc::new(HttpState::new(ssl_client)),
+        state: Arc::new(HttpState::new(ssl_client, None)),
         user_agent: DEFAULT_USER_AGENT.into(),
         devtools_chan: dc,
         filemanager: FileManager::new(sender),
         cancellation_listener: Arc::new(Mutex::new(CancellationListener::new(None))),
+        swmanager_chan: None,
     }
 }
 impl FetchTaskTarget for FetchResponseCollector {
@@ -103,6 +109,7 @@ impl FetchTaskTarget for FetchResponseCollector {
     fn process_response_eof(&mut self, response: &Response) {
         let _ = self.sender.send(response.clone());
     }
+    fn process_csp_violations(&mut self, _: &Request, _: Vec<Violation>) {}
 }
 
 fn fetch(request: &mut Request, dc: Option<Sender<DevtoolsControlMsg>>) -> Response {
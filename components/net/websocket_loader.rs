@@ -6,148 +6,190 @@ use connector::create_ssl_connector;
 use cookie::Cookie;
 use embedder_traits::resources::{self, Resource};
 use fetch::methods::should_be_blocked_due_to_bad_port;
-use hosts::replace_host;
 use http_loader::HttpState;
-use hyper::header::{Headers, Host, SetCookie};
 use ipc_channel::ipc::{IpcReceiver, IpcSender};
 use net_traits::{CookieSource, MessageData};
 use net_traits::{WebSocketDomAction, WebSocketNetworkEvent};
-use net_traits::request::{RequestInit, RequestMode};
+use net_traits::csp::CheckResult;
+use net_traits::request::{Origin, Request, RequestInit, RequestMode};
 use openssl::ssl::SslStream;
+use proxy;
+use servo_channel::{Receiver, route_ipc_receiver_to_new_servo_receiver};
 use servo_config::opts;
 use servo_url::ServoUrl;
+use std::borrow::Cow;
 use std::fs;
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::str;
 use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
-use url::Url;
-use ws::{CloseCode, Factory, Handler, Handshake, Message, Request, Response as WsResponse, Sender, WebSocket};
-use ws::{Error as WebSocketError, ErrorKind as WebSocketErrorKind, Result as WebSocketResult};
-use ws::util::TcpStream;
-
-/// A client for connecting to a websocket server
-#[derive(Clone)]
-struct Client<'a> {
-    origin: &'a str,
-    host: &'a Host,
-    protocols: &'a [String],
-    http_state: &'a Arc<HttpState>,
-    resource_url: &'a ServoUrl,
-    event_sender: &'a IpcSender<WebSocketNetworkEvent>,
-    protocol_in_use: Option<String>,
+use std::time::Duration;
+use tungstenite::{Error as WebSocketError, Message, WebSocket, client};
+use tungstenite::handshake::client::Request as HandshakeRequest;
+use tungstenite::protocol::CloseFrame;
+use tungstenite::protocol::frame::coding::CloseCode;
+
+/// How long a connection waits for incoming data before it checks whether
+/// script asked it to send something.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A connection to a WebSocket server, over a stream that is connected by
+/// the loader itself, through the proxy if there is one.
+struct Connection {
+    url: ServoUrl,
+    protocols: Vec<String>,
+    http_state: Arc<HttpState>,
+    event_sender: IpcSender<WebSocketNetworkEvent>,
 }
 
-impl<'a> Factory for Client<'a> {
-    type Handler = Self;
-
-    fn connection_made(&mut self, _: Sender) -> Self::Handler {
-        self.clone()
-    }
-
-    fn connection_lost(&mut self, _: Self::Handler) {
+impl Connection {
+    fn fail(&self) {
         let _ = self.event_sender.send(WebSocketNetworkEvent::Fail);
     }
-}
-
-
-impl<'a> Handler for Client<'a> {
-    fn build_request(&mut self, url: &Url) -> WebSocketResult<Request> {
-        let mut req = Request::from_url(url)?;
-        req.headers_mut().push(("Origin".to_string(), self.origin.as_bytes().to_owned()));
-        req.headers_mut().push(("Host".to_string(), format!("{}", self.host).as_bytes().to_owned()));
-
-        for protocol in self.protocols {
-            req.add_protocol(protocol);
-        };
 
+    fn handshake_request(&self, origin: &str) -> HandshakeRequest<'static> {
+        let mut request = HandshakeRequest::from(self.url.clone().into_url());
+        request.add_header(Cow::from("Origin"), Cow::from(origin.to_owned()));
+        for protocol in &self.protocols {
+            request.add_protocol(Cow::from(protocol.clone()));
+        }
         let mut cookie_jar = self.http_state.cookie_jar.write().unwrap();
-        if let Some(cookie_list) = cookie_jar.cookies_for_url(self.resource_url, CookieSource::HTTP) {
-            req.headers_mut().push(("Cookie".into(), cookie_list.as_bytes().to_owned()))
+        if let Some(cookie_list) = cookie_jar.cookies_for_url(&self.url, CookieSource::HTTP) {
+            request.add_header(Cow::from("Cookie"), Cow::from(cookie_list));
         }
+        request
+    }
 
-        Ok(req)
+    fn upgrade_tls(&self, stream: TcpStream) -> io::Result<SslStream<TcpStream>> {
+        let certs = match opts::get().certificate_path {
+            Some(ref path) => {
+                fs::read_to_string(path).expect("Couldn't not find certificate file")
+            }
+            None => {
+                resources::read_string(Resource::SSLCertificates)
+            },
+        };
+
+        let domain = self.url.as_url().domain().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::Other, format!("Unable to parse domain from {}. Needed for SSL.", self.url))
+        })?;
+        let connector = create_ssl_connector(&certs);
+        connector.connect(domain, stream).map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))
     }
 
-    fn on_open(&mut self, shake: Handshake) -> WebSocketResult<()> {
-        let mut headers = Headers::new();
-        for &(ref name, ref value) in shake.response.headers().iter() {
-            headers.set_raw(name.clone(), vec![value.clone()]);
+    /// Performs the opening handshake over `stream`, and then relays messages
+    /// between the server and script until the connection is closed. `socket`
+    /// is the TCP connection that `stream` runs over.
+    fn run<S: Read + Write>(
+        &self,
+        request: HandshakeRequest,
+        stream: S,
+        socket: &TcpStream,
+        dom_actions: Receiver<WebSocketDomAction>,
+    ) {
+        let (mut websocket, response) = match client(request, stream) {
+            Ok(handshake) => handshake,
+            Err(_) => {
+                debug!("Failed to establish a WebSocket connection: opening handshake failed");
+                return self.fail();
+            },
+        };
+
+        let protocol_in_use = response
+            .headers
+            .find_first("Sec-WebSocket-Protocol")
+            .and_then(|protocol| str::from_utf8(protocol).ok())
+            .map(str::to_owned);
+        if let Some(ref protocol_name) = protocol_in_use {
+            if !self.protocols.is_empty() && !self.protocols.iter().any(|p| protocol_name == p) {
+                debug!("Protocol in Use not in client-supplied protocol list");
+                return self.fail();
+            }
         }
 
-         if let Some(cookies) = headers.get::<SetCookie>() {
+        {
             let mut jar = self.http_state.cookie_jar.write().unwrap();
-            for cookie in &**cookies {
-                if let Some(cookie) =
-                    Cookie::from_cookie_string(cookie.clone(), self.resource_url, CookieSource::HTTP)
-                {
-                    jar.push(cookie, self.resource_url, CookieSource::HTTP);
+            for cookie in response.headers.find("Set-Cookie") {
+                let cookie = String::from_utf8_lossy(cookie).into_owned();
+                if let Some(cookie) = Cookie::from_cookie_string(cookie, &self.url, CookieSource::HTTP) {
+                    jar.push(cookie, &self.url, CookieSource::HTTP);
                 }
             }
         }
 
         let _ = self.event_sender.send(
-            WebSocketNetworkEvent::ConnectionEstablished { protocol_in_use: self.protocol_in_use.clone() });
-        Ok(())
-    }
-
-     fn on_message(&mut self, message: Message) -> WebSocketResult<()> {
-        let message = match message {
-            Message::Text(message) => MessageData::Text(message),
-            Message::Binary(message) => MessageData::Binary(message),
-        };
-        let _ = self.event_sender.send(WebSocketNetworkEvent::MessageReceived(message));
-
-        Ok(())
-    }
-
+            WebSocketNetworkEvent::ConnectionEstablished { protocol_in_use: protocol_in_use });
 
-    fn on_error(&mut self, err: WebSocketError) {
-        debug!("Error in WebSocket communication: {:?}", err);
-        let _ = self.event_sender.send(WebSocketNetworkEvent::Fail);
-    }
-
-
-    fn on_response(&mut self, res: &WsResponse) -> WebSocketResult<()> {
-        let protocol_in_use = res.protocol()?;
-
-        if let Some(protocol_name) = protocol_in_use {
-            if !self.protocols.is_empty() && !self.protocols.iter().any(|p| protocol_name == (*p)) {
-                let error = WebSocketError::new(WebSocketErrorKind::Protocol,
-                                                "Protocol in Use not in client-supplied protocol list");
-                return Err(error);
-            }
-            self.protocol_in_use = Some(protocol_name.into());
+        if let Err(e) = socket.set_read_timeout(Some(POLL_INTERVAL)) {
+            debug!("Failed to set the read timeout of a WebSocket connection: {}", e);
+            return self.fail();
         }
-        Ok(())
-    }
-
-    fn on_close(&mut self, code: CloseCode, reason: &str) {
-        debug!("Connection closing due to ({:?}) {}", code, reason);
-        let _ = self.event_sender.send(WebSocketNetworkEvent::Close(Some(code.into()), reason.to_owned()));
-    }
 
-    fn upgrade_ssl_client(
-        &mut self,
-        stream: TcpStream,
-        url: &Url,
-    ) -> WebSocketResult<SslStream<TcpStream>> {
-        let certs = match opts::get().certificate_path {
-            Some(ref path) => {
-                fs::read_to_string(path).expect("Couldn't not find certificate file")
+        // The close frame of the server, once it has sent one.
+        let mut close_frame: Option<Option<CloseFrame>> = None;
+        loop {
+            while let Some(dom_action) = dom_actions.try_recv() {
+                let result = match dom_action {
+                    WebSocketDomAction::SendMessage(MessageData::Text(data)) => {
+                        websocket.write_message(Message::Text(data))
+                    },
+                    WebSocketDomAction::SendMessage(MessageData::Binary(data)) => {
+                        websocket.write_message(Message::Binary(data))
+                    },
+                    WebSocketDomAction::Close(code, reason) => {
+                        websocket.close(code.map(|code| CloseFrame {
+                            code: CloseCode::from(code),
+                            reason: Cow::from(reason.unwrap_or_default()),
+                        }))
+                    },
+                };
+                if let Err(e) = result {
+                    debug!("Error in WebSocket communication: {}", e);
+                }
             }
-            None => {
-                resources::read_string(Resource::SSLCertificates)
-            },
-        };
 
-        let domain = self.resource_url.as_url().domain().ok_or(WebSocketError::new(
-            WebSocketErrorKind::Protocol,
-            format!("Unable to parse domain from {}. Needed for SSL.", url),
-        ))?;
-        let connector = create_ssl_connector(&certs);
-        connector.connect(domain, stream).map_err(WebSocketError::from)
+            match websocket.read_message() {
+                Ok(Message::Text(message)) => {
+                    let _ = self.event_sender.send(
+                        WebSocketNetworkEvent::MessageReceived(MessageData::Text(message)));
+                },
+                Ok(Message::Binary(message)) => {
+                    let _ = self.event_sender.send(
+                        WebSocketNetworkEvent::MessageReceived(MessageData::Binary(message)));
+                },
+                Ok(Message::Close(frame)) => close_frame = Some(frame),
+                // Pings are answered by the client itself.
+                Ok(_) => {},
+                Err(WebSocketError::Io(ref e)) if is_timeout(e) => {
+                    if let Err(e) = websocket.write_pending() {
+                        debug!("Error in WebSocket communication: {}", e);
+                    }
+                },
+                Err(e) => {
+                    match close_frame {
+                        Some(frame) => {
+                            let (code, reason) = match frame {
+                                Some(frame) => (Some(frame.code.into()), frame.reason.into_owned()),
+                                None => (None, String::new()),
+                            };
+                            debug!("Connection closing due to ({:?}) {}", code, reason);
+                            let _ = self.event_sender.send(WebSocketNetworkEvent::Close(code, reason));
+                        },
+                        None => {
+                            debug!("Error in WebSocket communication: {}", e);
+                            self.fail();
+                        },
+                    }
+                    return;
+                },
+            }
+        }
     }
+}
 
+fn is_timeout(error: &io::Error) -> bool {
+    error.kind() == io::ErrorKind::WouldBlock || error.kind() == io::ErrorKind::TimedOut
 }
 
 pub fn init(
@@ -176,61 +218,57 @@ pub fn init(
             return;
         }
 
-        let host = replace_host(req_init.url.host_str().unwrap());
-        let mut net_url = req_init.url.clone().into_url();
-        net_url.set_host(Some(&host)).unwrap();
+        if let Some(ref csp_list) = req_init.csp_list {
+            // TODO: Report the violations to the WebSocket's global.
+            let request = Request::new(req_init.url.clone(),
+                                       Some(Origin::Origin(req_init.origin.clone())),
+                                       req_init.pipeline_id);
+            if csp_list.should_request_be_blocked(&request).0 == CheckResult::Blocked {
+                debug!("Failed to establish a WebSocket connection: blocked by Content Security Policy");
+                let _ = resource_event_sender.send(WebSocketNetworkEvent::Fail);
+                return;
+            }
+        }
 
-        let host = Host {
-            hostname: req_init.url.host_str().unwrap().to_owned(),
-            port: req_init.url.port_or_known_default(),
+        let connection = Connection {
+            url: req_init.url.clone(),
+            protocols: protocols,
+            http_state: http_state,
+            event_sender: resource_event_sender,
         };
 
-        let client = Client {
-            origin: &req_init.origin.ascii_serialization(),
-            host: &host,
-            protocols: &protocols,
-            http_state: &http_state,
-            resource_url: &req_init.url,
-            event_sender: &resource_event_sender,
-            protocol_in_use: None,
+        // The connection is made here rather than by the WebSocket client, so
+        // that it goes through the proxy when there is one. The original host
+        // is still used for the handshake and TLS.
+        let hostname = req_init.url.host_str().unwrap();
+        let port = req_init.url.port_or_known_default().unwrap();
+        let stream = match proxy::connect(connection.http_state.proxy.as_ref(), hostname, port, scheme) {
+            Ok(stream) => stream,
+            Err(e) => {
+                debug!("Failed to establish a WebSocket connection: {}", e);
+                return connection.fail();
+            },
         };
-        let mut ws = WebSocket::new(client).unwrap();
-
-        if let Err(e) = ws.connect(net_url) {
-            debug!("Failed to establish a WebSocket connection: {:?}", e);
-            return;
+        let socket = match stream.try_clone() {
+            Ok(socket) => socket,
+            Err(e) => {
+                debug!("Failed to establish a WebSocket connection: {}", e);
+                return connection.fail();
+            },
         };
 
-        let ws_sender = ws.broadcaster();
-        let initiated_close = Arc::new(AtomicBool::new(false));
-
-        thread::spawn(move || {
-            while let Ok(dom_action) = dom_action_receiver.recv() {
-                match dom_action {
-                    WebSocketDomAction::SendMessage(MessageData::Text(data)) => {
-                        ws_sender.send(Message::text(data)).unwrap();
-                    },
-                    WebSocketDomAction::SendMessage(MessageData::Binary(data)) => {
-                        ws_sender.send(Message::binary(data)).unwrap();
-                    },
-                    WebSocketDomAction::Close(code, reason) => {
-                        if !initiated_close.fetch_or(true, Ordering::SeqCst) {
-                            match code {
-                                Some(code) => {
-                                    ws_sender.close_with_reason(code.into(), reason.unwrap_or("".to_owned())).unwrap()
-                                },
-                                None => ws_sender.close(CloseCode::Status).unwrap(),
-                            };
-                        }
-                    },
-                }
+        let request = connection.handshake_request(&req_init.origin.ascii_serialization());
+        let dom_actions = route_ipc_receiver_to_new_servo_receiver(dom_action_receiver);
+        if scheme == "wss" {
+            match connection.upgrade_tls(stream) {
+                Ok(stream) => connection.run(request, stream, &socket, dom_actions),
+                Err(e) => {
+                    debug!("Failed to establish a WebSocket connection: {}", e);
+                    connection.fail();
+                },
             }
-        });
-
-        if let Err(e) = ws.run() {
-            debug!("Failed to run WebSocket: {:?}", e);
-            let _ = resource_event_sender.send(WebSocketNetworkEvent::Fail);
-        };
+        } else {
+            connection.run(request, stream, &socket, dom_actions);
+        }
     }).expect("Thread spawning failed");
 }
-
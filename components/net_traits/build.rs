@@ -0,0 +1,25 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+extern crate cc;
+extern crate pkg_config;
+
+fn main() {
+    // WebP and AVIF images are decoded by the system libwebp and libavif.
+    pkg_config::Config::new()
+        .atleast_version("0.5")
+        .probe("libwebpdemux")
+        .expect("libwebpdemux is needed to decode WebP images");
+    let avif = pkg_config::Config::new()
+        .atleast_version("1.0")
+        .probe("libavif")
+        .expect("libavif is needed to decode AVIF images");
+
+    let mut build = cc::Build::new();
+    for path in &avif.include_paths {
+        build.include(path);
+    }
+    build.file("image/avif.c").compile("servo_avif");
+    println!("cargo:rerun-if-changed=image/avif.c");
+}
@@ -0,0 +1,93 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use ipc_channel::ipc::IpcSender;
+use servo_url::ServoUrl;
+
+/// The request of a cached request-response pair.
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub struct CachedRequest {
+    pub url: ServoUrl,
+    pub method: String,
+    /// The header list, in order.
+    pub headers: Vec<(String, String)>,
+}
+
+impl CachedRequest {
+    /// The value of the named header, if any.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .find(|&&(ref header_name, _)| header_name.eq_ignore_ascii_case(name))
+            .map(|&(_, ref value)| &**value)
+    }
+}
+
+/// The response of a cached request-response pair.
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub struct CachedResponse {
+    pub url: Option<ServoUrl>,
+    pub status: u16,
+    pub status_text: Vec<u8>,
+    /// The header list, in order.
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+/// <https://w3c.github.io/ServiceWorker/#request-response-list>
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub struct CacheEntry {
+    pub request: CachedRequest,
+    pub response: CachedResponse,
+}
+
+/// <https://w3c.github.io/ServiceWorker/#dictdef-cachequeryoptions>
+#[derive(Clone, Copy, Debug, Default, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub struct CacheQueryOptions {
+    pub ignore_search: bool,
+    pub ignore_method: bool,
+    pub ignore_vary: bool,
+}
+
+/// Why an operation on the caches failed.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum CacheStorageError {
+    /// The named cache doesn't exist.
+    NotFound,
+    /// The caches couldn't be read from or saved to disk.
+    Storage(String),
+}
+
+/// Request operations on the caches associated with the origin of a url
+#[derive(Deserialize, Serialize)]
+pub enum CacheStorageThreadMsg {
+    /// opens the named cache, creating it if it doesn't exist
+    OpenCache(IpcSender<Result<(), CacheStorageError>>, ServoUrl, String),
+
+    /// checks whether the named cache exists
+    HasCache(IpcSender<bool>, ServoUrl, String),
+
+    /// deletes the named cache, replying whether it existed
+    DeleteCache(IpcSender<Result<bool, CacheStorageError>>, ServoUrl, String),
+
+    /// gets the names of the caches, in creation order
+    CacheNames(IpcSender<Vec<String>>, ServoUrl),
+
+    /// gets the entries of the named cache, or of every cache in creation order, whose
+    /// request matches the given one, or all of them if no request is given
+    Match(IpcSender<Result<Vec<CacheEntry>, CacheStorageError>>,
+          ServoUrl,
+          Option<String>,
+          Option<CachedRequest>,
+          CacheQueryOptions),
+
+    /// stores entries in the named cache, replacing the entries for the same requests
+    Put(IpcSender<Result<(), CacheStorageError>>, ServoUrl, String, Vec<CacheEntry>),
+
+    /// deletes the entries of the named cache whose request matches the given one,
+    /// replying whether there were any
+    Delete(IpcSender<Result<bool, CacheStorageError>>, ServoUrl, String, CachedRequest, CacheQueryOptions),
+
+    /// send a reply when done cleaning up thread resources and then shut it down
+    Exit(IpcSender<()>),
+}
@@ -0,0 +1,532 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! [Content Security Policy](https://w3c.github.io/webappsec-csp/) parsing and checks.
+
+use base64;
+use hyper::header::Headers;
+use openssl::hash::{MessageDigest, hash2};
+use request::{Destination, Origin, Request};
+use servo_url::{ImmutableOrigin, ServoUrl};
+
+/// The maximum length of the sample of a violating inline resource or eval string.
+const SAMPLE_LENGTH: usize = 40;
+
+static ASCII_WHITESPACE: &'static [char] = &['\u{0009}', '\u{000a}', '\u{000c}', '\u{000d}', '\u{0020}'];
+
+/// A policy [disposition](https://w3c.github.io/webappsec-csp/#policy-disposition)
+#[derive(Clone, Copy, Debug, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub enum PolicyDisposition {
+    Enforce,
+    Report,
+}
+
+/// A policy [source](https://w3c.github.io/webappsec-csp/#policy-source)
+#[derive(Clone, Copy, Debug, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub enum PolicySource {
+    Header,
+    Meta,
+}
+
+/// A [directive](https://w3c.github.io/webappsec-csp/#directives)
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub struct Directive {
+    /// The directive name, lowercased.
+    pub name: String,
+    /// The source expressions of the directive.
+    pub value: Vec<String>,
+}
+
+/// A [policy](https://w3c.github.io/webappsec-csp/#content-security-policy-object)
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub struct Policy {
+    pub directives: Vec<Directive>,
+    pub disposition: PolicyDisposition,
+    pub source: PolicySource,
+    /// The serialized policy, as reported in violations.
+    pub serialized: String,
+}
+
+/// A [CSP list](https://w3c.github.io/webappsec-csp/#csp-list)
+#[derive(Clone, Debug, Default, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub struct CspList(pub Vec<Policy>);
+
+/// The result of checking something against a `CspList`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CheckResult {
+    Allowed,
+    Blocked,
+}
+
+/// The kinds of inline behavior checked by
+/// <https://w3c.github.io/webappsec-csp/#should-block-inline>
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InlineCheckType {
+    Script,
+    ScriptAttribute,
+    Style,
+    StyleAttribute,
+}
+
+impl InlineCheckType {
+    /// <https://w3c.github.io/webappsec-csp/#directive-fallback-list>
+    fn fallback_list(&self) -> &'static [&'static str] {
+        match *self {
+            InlineCheckType::Script => &["script-src-elem", "script-src", "default-src"],
+            InlineCheckType::ScriptAttribute => &["script-src-attr", "script-src", "default-src"],
+            InlineCheckType::Style => &["style-src-elem", "style-src", "default-src"],
+            InlineCheckType::StyleAttribute => &["style-src-attr", "style-src", "default-src"],
+        }
+    }
+
+    fn is_attribute(&self) -> bool {
+        match *self {
+            InlineCheckType::ScriptAttribute | InlineCheckType::StyleAttribute => true,
+            InlineCheckType::Script | InlineCheckType::Style => false,
+        }
+    }
+}
+
+/// A violation's [resource](https://w3c.github.io/webappsec-csp/#violation-resource)
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub enum ViolationResource {
+    Inline,
+    Eval,
+    Url(ServoUrl),
+}
+
+/// A [violation](https://w3c.github.io/webappsec-csp/#violation)
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub struct Violation {
+    pub resource: ViolationResource,
+    /// The effective directive that was violated.
+    pub directive: String,
+    /// The serialized policy that was violated.
+    pub policy: String,
+    pub disposition: PolicyDisposition,
+    /// <https://w3c.github.io/webappsec-csp/#violation-sample>
+    pub sample: String,
+}
+
+impl CspList {
+    /// <https://w3c.github.io/webappsec-csp/#parse-serialized-policy-list>
+    pub fn parse(list: &str, source: PolicySource, disposition: PolicyDisposition) -> CspList {
+        CspList(list.split(',')
+                    .map(|serialized| Policy::parse(serialized, source, disposition))
+                    .filter(|policy| !policy.directives.is_empty())
+                    .collect())
+    }
+
+    /// <https://w3c.github.io/webappsec-csp/#parse-response-csp>
+    pub fn from_headers(headers: &Headers) -> CspList {
+        let mut csp_list = CspList::default();
+        let header_dispositions = [
+            ("Content-Security-Policy", PolicyDisposition::Enforce),
+            ("Content-Security-Policy-Report-Only", PolicyDisposition::Report),
+        ];
+        for &(name, disposition) in header_dispositions.iter() {
+            for value in headers.get_raw(name).unwrap_or(&[]) {
+                let value = String::from_utf8_lossy(value);
+                csp_list.append(CspList::parse(&value, PolicySource::Header, disposition));
+            }
+        }
+        csp_list
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn append(&mut self, mut other: CspList) {
+        self.0.append(&mut other.0)
+    }
+
+    /// Checks `request` against every policy of the list, returning whether it must be blocked
+    /// and the violations of both enforced and report-only policies.
+    ///
+    /// <https://w3c.github.io/webappsec-csp/#report-for-request>
+    /// <https://w3c.github.io/webappsec-csp/#should-block-request>
+    pub fn should_request_be_blocked(&self, request: &Request) -> (CheckResult, Vec<Violation>) {
+        self.check(|policy| policy.request_violation(request))
+    }
+
+    /// Checks the navigation of a nested browsing context to `url` against the `frame-src`
+    /// directives of the list.
+    ///
+    /// <https://w3c.github.io/webappsec-csp/#should-block-navigation-request>
+    pub fn should_nested_navigation_be_blocked(&self,
+                                               url: &ServoUrl,
+                                               origin: &ImmutableOrigin)
+                                               -> (CheckResult, Vec<Violation>) {
+        self.check(|policy| policy.nested_navigation_violation(url, origin))
+    }
+
+    /// <https://w3c.github.io/webappsec-csp/#should-block-inline>
+    pub fn should_elements_inline_type_behavior_be_blocked(&self,
+                                                           nonce: Option<&str>,
+                                                           type_: InlineCheckType,
+                                                           source: &str)
+                                                           -> (CheckResult, Vec<Violation>) {
+        self.check(|policy| policy.inline_violation(nonce, type_, source))
+    }
+
+    /// <https://w3c.github.io/webappsec-csp/#can-compile-strings>
+    pub fn is_js_evaluation_allowed(&self, source: &str) -> (CheckResult, Vec<Violation>) {
+        self.check(|policy| policy.eval_violation(source))
+    }
+
+    fn check<F>(&self, violation_for_policy: F) -> (CheckResult, Vec<Violation>)
+        where F: Fn(&Policy) -> Option<Violation>
+    {
+        let violations: Vec<Violation> = self.0.iter().filter_map(violation_for_policy).collect();
+        let result = if violations.iter().any(|v| v.disposition == PolicyDisposition::Enforce) {
+            CheckResult::Blocked
+        } else {
+            CheckResult::Allowed
+        };
+        (result, violations)
+    }
+}
+
+impl Policy {
+    /// <https://w3c.github.io/webappsec-csp/#parse-serialized-policy>
+    pub fn parse(serialized: &str, source: PolicySource, disposition: PolicyDisposition) -> Policy {
+        let serialized = serialized.trim_matches(ASCII_WHITESPACE);
+        let mut directives: Vec<Directive> = vec![];
+        for token in serialized.split(';') {
+            let mut parts = token.split(ASCII_WHITESPACE).filter(|part| !part.is_empty());
+            let name = match parts.next() {
+                Some(name) => name.to_ascii_lowercase(),
+                None => continue,
+            };
+            // Only the first occurrence of a directive is taken into account.
+            if directives.iter().any(|directive| directive.name == name) {
+                continue;
+            }
+            // https://w3c.github.io/webappsec-csp/#meta-element
+            if source == PolicySource::Meta &&
+               (name == "frame-ancestors" || name == "report-uri" || name == "sandbox") {
+                continue;
+            }
+            directives.push(Directive {
+                name: name,
+                value: parts.map(|part| part.to_owned()).collect(),
+            });
+        }
+        Policy {
+            directives: directives,
+            disposition: disposition,
+            source: source,
+            serialized: serialized.to_owned(),
+        }
+    }
+
+    /// The first directive of `fallback_list` present in this policy, which is the one
+    /// <https://w3c.github.io/webappsec-csp/#should-directive-execute> selects.
+    fn effective_directive(&self, fallback_list: &[&str]) -> Option<&Directive> {
+        fallback_list.iter().filter_map(|name| {
+            self.directives.iter().find(|directive| directive.name == *name)
+        }).next()
+    }
+
+    fn violation(&self, resource: ViolationResource, directive: &str, sample: String) -> Violation {
+        Violation {
+            resource: resource,
+            directive: directive.to_owned(),
+            policy: self.serialized.clone(),
+            disposition: self.disposition,
+            sample: sample,
+        }
+    }
+
+    /// <https://w3c.github.io/webappsec-csp/#directive-pre-request-check>
+    fn request_violation(&self, request: &Request) -> Option<Violation> {
+        let fallback_list = request_fallback_list(request.destination)?;
+        let directive = self.effective_directive(fallback_list)?;
+
+        let nonceable = request.destination.is_script_like() || request.destination == Destination::Style;
+        if nonceable && !request.cryptographic_nonce_metadata.is_empty() &&
+           directive.allows_nonce(&request.cryptographic_nonce_metadata) {
+            return None;
+        }
+
+        let url = request.current_url();
+        let origin = match request.origin {
+            Origin::Origin(ref origin) => Some(origin),
+            Origin::Client => None,
+        };
+        if directive.matches_url(&url, origin, request.redirect_count) {
+            return None;
+        }
+        Some(self.violation(ViolationResource::Url(url), fallback_list[0], String::new()))
+    }
+
+    /// <https://w3c.github.io/webappsec-csp/#frame-src-pre-request>
+    fn nested_navigation_violation(&self, url: &ServoUrl, origin: &ImmutableOrigin) -> Option<Violation> {
+        let fallback_list = &["frame-src", "child-src", "default-src"];
+        let directive = self.effective_directive(fallback_list)?;
+        if directive.matches_url(url, Some(origin), 0) {
+            return None;
+        }
+        Some(self.violation(ViolationResource::Url(url.clone()), fallback_list[0], String::new()))
+    }
+
+    /// <https://w3c.github.io/webappsec-csp/#directive-inline-check>
+    fn inline_violation(&self, nonce: Option<&str>, type_: InlineCheckType, source: &str) -> Option<Violation> {
+        let fallback_list = type_.fallback_list();
+        let directive = self.effective_directive(fallback_list)?;
+        if directive.allows_inline(nonce, type_, source) {
+            return None;
+        }
+        Some(self.violation(ViolationResource::Inline, fallback_list[0], directive.sample(source)))
+    }
+
+    /// <https://w3c.github.io/webappsec-csp/#can-compile-strings>
+    fn eval_violation(&self, source: &str) -> Option<Violation> {
+        let directive = self.effective_directive(&["script-src", "default-src"])?;
+        if directive.has_keyword("'unsafe-eval'") {
+            return None;
+        }
+        Some(self.violation(ViolationResource::Eval, "script-src", directive.sample(source)))
+    }
+}
+
+/// <https://w3c.github.io/webappsec-csp/#effective-directive-for-a-request>, followed by its
+/// <https://w3c.github.io/webappsec-csp/#directive-fallback-list>
+fn request_fallback_list(destination: Destination) -> Option<&'static [&'static str]> {
+    match destination {
+        Destination::None => Some(&["connect-src", "default-src"]),
+        Destination::Audio | Destination::Track | Destination::Video => Some(&["media-src", "default-src"]),
+        Destination::Embed | Destination::Object => Some(&["object-src", "default-src"]),
+        Destination::Font => Some(&["font-src", "default-src"]),
+        Destination::Image => Some(&["img-src", "default-src"]),
+        Destination::Manifest => Some(&["manifest-src", "default-src"]),
+        Destination::Script | Destination::Xslt => Some(&["script-src-elem", "script-src", "default-src"]),
+        Destination::Style => Some(&["style-src-elem", "style-src", "default-src"]),
+        Destination::ServiceWorker | Destination::SharedWorker | Destination::Worker => {
+            Some(&["worker-src", "child-src", "script-src", "default-src"])
+        },
+        // Navigations are checked by the script thread before they are started.
+        Destination::Document | Destination::Report => None,
+    }
+}
+
+impl Directive {
+    fn has_keyword(&self, keyword: &str) -> bool {
+        self.value.iter().any(|expression| expression.eq_ignore_ascii_case(keyword))
+    }
+
+    /// <https://w3c.github.io/webappsec-csp/#obtain-violation-sample>
+    fn sample(&self, source: &str) -> String {
+        if self.has_keyword("'report-sample'") {
+            source.chars().take(SAMPLE_LENGTH).collect()
+        } else {
+            String::new()
+        }
+    }
+
+    fn allows_nonce(&self, nonce: &str) -> bool {
+        self.value.iter().any(|expression| nonce_source_value(expression) == Some(nonce))
+    }
+
+    /// <https://w3c.github.io/webappsec-csp/#allow-all-inline>
+    fn allows_all_inline(&self) -> bool {
+        let has_nonce_or_hash = self.value.iter().any(|expression| {
+            nonce_source_value(expression).is_some() || hash_source_value(expression).is_some()
+        });
+        !has_nonce_or_hash && !self.has_keyword("'strict-dynamic'") && self.has_keyword("'unsafe-inline'")
+    }
+
+    /// <https://w3c.github.io/webappsec-csp/#match-element-to-source-list>
+    fn allows_inline(&self, nonce: Option<&str>, type_: InlineCheckType, source: &str) -> bool {
+        if self.allows_all_inline() {
+            return true;
+        }
+        if let Some(nonce) = nonce {
+            if !type_.is_attribute() && !nonce.is_empty() && self.allows_nonce(nonce) {
+                return true;
+            }
+        }
+        if type_.is_attribute() && !self.has_keyword("'unsafe-hashes'") {
+            return false;
+        }
+        self.value.iter().any(|expression| hash_source_matches(expression, source))
+    }
+
+    /// <https://w3c.github.io/webappsec-csp/#match-url-to-source-list>
+    fn matches_url(&self, url: &ServoUrl, origin: Option<&ImmutableOrigin>, redirect_count: u32) -> bool {
+        self.value.iter().any(|expression| source_expression_matches(expression, url, origin, redirect_count))
+    }
+}
+
+/// The base64 value of a `'nonce-...'` source expression.
+fn nonce_source_value(expression: &str) -> Option<&str> {
+    let prefix = "'nonce-";
+    let has_prefix = expression.get(..prefix.len()).map_or(false, |p| p.eq_ignore_ascii_case(prefix));
+    if has_prefix && expression.len() > prefix.len() + 1 && expression.ends_with('\'') {
+        Some(&expression[prefix.len()..expression.len() - 1])
+    } else {
+        None
+    }
+}
+
+/// The digest algorithm and base64 value of a `'sha256-...'`, `'sha384-...'` or
+/// `'sha512-...'` source expression.
+fn hash_source_value(expression: &str) -> Option<(MessageDigest, &str)> {
+    if expression.len() < 2 || !expression.starts_with('\'') || !expression.ends_with('\'') {
+        return None;
+    }
+    let inner = &expression[1..expression.len() - 1];
+    let dash = inner.find('-')?;
+    let digest = match &*inner[..dash].to_ascii_lowercase() {
+        "sha256" => MessageDigest::sha256(),
+        "sha384" => MessageDigest::sha384(),
+        "sha512" => MessageDigest::sha512(),
+        _ => return None,
+    };
+    Some((digest, &inner[dash + 1..]))
+}
+
+fn hash_source_matches(expression: &str, source: &str) -> bool {
+    let (digest, expected) = match hash_source_value(expression) {
+        Some(hash) => hash,
+        None => return false,
+    };
+    let actual = match hash2(digest, source.as_bytes()) {
+        Ok(actual) => base64::encode(&actual),
+        Err(_) => return false,
+    };
+    // Both base64 and base64url encodings are accepted.
+    actual == expected.replace('-', "+").replace('_', "/")
+}
+
+fn is_network_scheme(scheme: &str) -> bool {
+    scheme == "http" || scheme == "https" || scheme == "ws" || scheme == "wss" || scheme == "ftp"
+}
+
+/// <https://w3c.github.io/webappsec-csp/#scheme-part-match>
+fn scheme_part_matches(expression_scheme: &str, url_scheme: &str) -> bool {
+    match (&*expression_scheme.to_ascii_lowercase(), url_scheme) {
+        (a, b) if a == b => true,
+        ("http", "https") |
+        ("ws", "wss") | ("ws", "http") | ("ws", "https") |
+        ("wss", "https") => true,
+        _ => false,
+    }
+}
+
+/// <https://w3c.github.io/webappsec-csp/#match-url-to-source-expression>
+fn source_expression_matches(expression: &str,
+                             url: &ServoUrl,
+                             origin: Option<&ImmutableOrigin>,
+                             redirect_count: u32)
+                             -> bool {
+    let origin_scheme = match origin {
+        Some(&ImmutableOrigin::Tuple(ref scheme, _, _)) => Some(&**scheme),
+        _ => None,
+    };
+
+    // Step 1.
+    if expression == "*" {
+        return is_network_scheme(url.scheme()) || origin_scheme == Some(url.scheme());
+    }
+
+    // Step 4.
+    if expression.eq_ignore_ascii_case("'self'") {
+        return origin.map_or(false, |origin| origin_matches_self(origin, url));
+    }
+
+    // Other keywords, nonces and hashes never match a URL.
+    if expression.starts_with('\'') {
+        return false;
+    }
+
+    // Step 2.
+    if expression.ends_with(':') && !expression.contains('/') {
+        return scheme_part_matches(&expression[..expression.len() - 1], url.scheme());
+    }
+
+    // Step 3.
+    let (scheme, rest) = match expression.find("://") {
+        Some(index) => (Some(&expression[..index]), &expression[index + 3..]),
+        None => (None, expression),
+    };
+    let scheme_matches = match scheme.or(origin_scheme) {
+        Some(scheme) => scheme_part_matches(scheme, url.scheme()),
+        None => false,
+    };
+    if !scheme_matches {
+        return false;
+    }
+
+    let (host_and_port, path) = match rest.find('/') {
+        Some(index) => (&rest[..index], &rest[index..]),
+        None => (rest, ""),
+    };
+    let (host, port) = match host_and_port.rfind(':') {
+        Some(index) => (&host_and_port[..index], Some(&host_and_port[index + 1..])),
+        None => (host_and_port, None),
+    };
+
+    // https://w3c.github.io/webappsec-csp/#match-hosts
+    let url_host = match url.host_str() {
+        Some(url_host) => url_host.to_ascii_lowercase(),
+        None => return false,
+    };
+    let host = host.to_ascii_lowercase();
+    let host_matches = if host == "*" {
+        true
+    } else if host.starts_with("*.") {
+        url_host.ends_with(&host[1..])
+    } else {
+        url_host == host
+    };
+    if !host_matches {
+        return false;
+    }
+
+    // https://w3c.github.io/webappsec-csp/#match-ports
+    let url_port = url.port_or_known_default();
+    let port_matches = match port {
+        None => url.port().is_none(),
+        Some("*") => true,
+        Some(port) => match port.parse::<u16>() {
+            Ok(port) => url_port == Some(port) || (port == 80 && url_port == Some(443)),
+            Err(_) => false,
+        },
+    };
+    if !port_matches {
+        return false;
+    }
+
+    // https://w3c.github.io/webappsec-csp/#match-paths
+    // Paths are ignored after a redirect, to avoid leaking the path of the redirect target.
+    if redirect_count > 0 || path.is_empty() || path == "/" {
+        return true;
+    }
+    if path.ends_with('/') {
+        url.path().starts_with(path)
+    } else {
+        url.path() == path
+    }
+}
+
+/// The `'self'` keyword matches URLs of the same origin, and secure upgrades of it.
+fn origin_matches_self(origin: &ImmutableOrigin, url: &ServoUrl) -> bool {
+    match (origin, url.origin()) {
+        (&ImmutableOrigin::Tuple(ref scheme, ref host, port),
+         ImmutableOrigin::Tuple(ref url_scheme, ref url_host, url_port)) => {
+            if host != url_host {
+                return false;
+            }
+            if scheme == url_scheme && port == url_port {
+                return true;
+            }
+            let upgraded = (scheme == "http" && (url_scheme == "https" || url_scheme == "ws")) ||
+                           (scheme == "https" && url_scheme == "wss");
+            upgraded && (port == url_port || (port == 80 && url_port == 443))
+        },
+        (origin, url_origin) => *origin == url_origin,
+    }
+}
@@ -0,0 +1,63 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+// The layout of the libavif structs changes between releases, so they are
+// only ever looked into from C, against the headers of the installed libavif.
+
+#include <avif/avif.h>
+
+avifDecoder* servo_avif_decoder_new(const uint8_t* data, size_t size) {
+    avifDecoder* decoder = avifDecoderCreate();
+    if (!decoder) {
+        return NULL;
+    }
+    // The decoder reads the data in place, so it must outlive the decoder.
+    if (avifDecoderSetIOMemory(decoder, data, size) != AVIF_RESULT_OK ||
+        avifDecoderParse(decoder) != AVIF_RESULT_OK) {
+        avifDecoderDestroy(decoder);
+        return NULL;
+    }
+    return decoder;
+}
+
+void servo_avif_decoder_info(const avifDecoder* decoder,
+                             uint32_t* width,
+                             uint32_t* height,
+                             int* image_count,
+                             int* repetition_count) {
+    *width = decoder->image->width;
+    *height = decoder->image->height;
+    *image_count = decoder->imageCount;
+    *repetition_count = decoder->repetitionCount;
+}
+
+int servo_avif_decoder_next_rgba(avifDecoder* decoder,
+                                 uint8_t* pixels,
+                                 uint32_t width,
+                                 uint32_t height,
+                                 double* duration) {
+    if (avifDecoderNextImage(decoder) != AVIF_RESULT_OK) {
+        return 0;
+    }
+    avifImage* image = decoder->image;
+    if (image->width != width || image->height != height) {
+        return 0;
+    }
+
+    avifRGBImage rgb;
+    avifRGBImageSetDefaults(&rgb, image);
+    rgb.format = AVIF_RGB_FORMAT_RGBA;
+    rgb.depth = 8;
+    rgb.pixels = pixels;
+    rgb.rowBytes = width * 4;
+    if (avifImageYUVToRGB(image, &rgb) != AVIF_RESULT_OK) {
+        return 0;
+    }
+    *duration = decoder->imageTiming.duration;
+    return 1;
+}
+
+void servo_avif_decoder_delete(avifDecoder* decoder) {
+    avifDecoderDestroy(decoder);
+}
@@ -3,8 +3,11 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use ipc_channel::ipc::IpcSharedMemory;
-use piston_image::{self, DynamicImage, ImageFormat};
+use piston_image::{self, DynamicImage, ImageDecoder, ImageFormat, Rgba, RgbaImage};
+use piston_image::gif::Decoder as GifDecoder;
+use std::{cmp, ptr, slice};
 use std::fmt;
+use std::io::Cursor;
 use webrender_api;
 
 #[derive(Clone, Copy, Debug, Deserialize, Eq, MallocSizeOf, PartialEq, Serialize)]
@@ -19,24 +22,94 @@ pub enum PixelFormat {
     BGRA8,
 }
 
+impl PixelFormat {
+    pub fn bytes_per_pixel(&self) -> usize {
+        match *self {
+            PixelFormat::K8 => 1,
+            PixelFormat::KA8 => 2,
+            PixelFormat::RGB8 => 3,
+            PixelFormat::BGRA8 => 4,
+        }
+    }
+}
+
 #[derive(Clone, Deserialize, MallocSizeOf, Serialize)]
 pub struct Image {
     pub width: u32,
     pub height: u32,
     pub format: PixelFormat,
+    /// The pixels of the first frame of the image, followed by the ones of
+    /// its other frames if it is animated.
     #[ignore_malloc_size_of = "Defined in ipc-channel"]
     pub bytes: IpcSharedMemory,
+    /// The key of the first frame of the image.
     #[ignore_malloc_size_of = "Defined in webrender_api"]
     pub id: Option<webrender_api::ImageKey>,
+    /// The frames of the image if it is animated, empty otherwise.
+    pub frames: Vec<ImageFrame>,
+    /// How many times the animation of the image plays, or `None` if it
+    /// loops forever.
+    pub play_count: Option<u32>,
+}
+
+impl Image {
+    /// The pixels of the first frame of the image, which is what is shown
+    /// wherever images aren't animated.
+    pub fn first_frame(&self) -> &[u8] {
+        self.frame_bytes(0)
+    }
+
+    /// The pixels of the frame at the given index.
+    pub fn frame_bytes(&self, index: usize) -> &[u8] {
+        let frame_len = self.width as usize * self.height as usize * self.format.bytes_per_pixel();
+        &self.bytes[index * frame_len..(index + 1) * frame_len]
+    }
+
+    pub fn is_animated(&self) -> bool {
+        self.frames.len() > 1
+    }
+
+    /// Returns the index of the frame shown once the image has been animating
+    /// for the given number of milliseconds, along with the number of
+    /// milliseconds left before the next frame is shown, if the animation
+    /// isn't over. Animations which are over stay on their last frame.
+    pub fn frame_at(&self, elapsed: u64) -> (usize, Option<u64>) {
+        let duration = self.frames.iter().map(|frame| frame.delay as u64).sum::<u64>();
+        if duration == 0 {
+            return (0, None);
+        }
+        if let Some(play_count) = self.play_count {
+            if elapsed >= duration.saturating_mul(play_count as u64) {
+                return (self.frames.len() - 1, None);
+            }
+        }
+        let mut time = elapsed % duration;
+        for (index, frame) in self.frames.iter().enumerate() {
+            if time < frame.delay as u64 {
+                return (index, Some(frame.delay as u64 - time));
+            }
+            time -= frame.delay as u64;
+        }
+        unreachable!()
+    }
 }
 
 impl fmt::Debug for Image {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Image {{ width: {}, height: {}, format: {:?}, ..., id: {:?} }}",
-               self.width, self.height, self.format, self.id)
+        write!(f, "Image {{ width: {}, height: {}, format: {:?}, ..., id: {:?}, frames: {} }}",
+               self.width, self.height, self.format, self.id, self.frames.len())
     }
 }
 
+/// A frame of an animated image.
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, Serialize)]
+pub struct ImageFrame {
+    /// How long the frame is shown, in milliseconds.
+    pub delay: u32,
+    #[ignore_malloc_size_of = "Defined in webrender_api"]
+    pub id: Option<webrender_api::ImageKey>,
+}
+
 #[derive(Clone, Debug, Deserialize, Eq, MallocSizeOf, PartialEq, Serialize)]
 pub struct ImageMetadata {
     pub width: u32,
@@ -69,34 +142,675 @@ pub fn load_from_memory(buffer: &[u8]) -> Option<Image> {
         return None;
     }
 
+    // The image crate doesn't know about AVIF, which is decoded by libavif.
+    if is_avif(buffer) {
+        return match load_avif_frames(buffer) {
+            Some((ref frames, play_count)) if frames.len() > 1 => Some(image_from_frames(frames, play_count)),
+            Some((mut frames, _)) => frames.pop().map(|(rgba, _)| image_from_rgba(rgba)),
+            None => {
+                debug!("Image decoding error: invalid AVIF");
+                None
+            },
+        };
+    }
+
     let image_fmt_result = detect_image_format(buffer);
     match image_fmt_result {
         Err(msg) => {
             debug!("{}", msg);
             None
         },
-        Ok(_) => {
-            match piston_image::load_from_memory(buffer) {
-                Ok(image) => {
-                    let mut rgba = match image {
-                        DynamicImage::ImageRgba8(rgba) => rgba,
-                        image => image.to_rgba(),
-                    };
-                    byte_swap_and_premultiply(&mut *rgba);
-                    Some(Image {
-                        width: rgba.width(),
-                        height: rgba.height(),
-                        format: PixelFormat::BGRA8,
-                        bytes: IpcSharedMemory::from_bytes(&*rgba),
-                        id: None,
-                    })
-                },
+        Ok(ImageFormat::GIF) => {
+            match load_gif_frames(buffer) {
+                Ok(ref frames) if frames.len() > 1 => Some(image_from_frames(frames, gif_play_count(buffer))),
+                // Still GIFs are decoded like any other image.
+                Ok(_) => load_still_image(buffer, ImageFormat::GIF),
                 Err(e) => {
                     debug!("Image decoding error: {:?}", e);
                     None
                 },
             }
         },
+        Ok(ImageFormat::PNG) => match load_apng_frames(buffer) {
+            Some((ref frames, play_count)) if frames.len() > 1 => Some(image_from_frames(frames, play_count)),
+            // Broken animations show the default image of the PNG instead.
+            _ => load_still_image(buffer, ImageFormat::PNG),
+        },
+        // The WebP decoder of the image crate only decodes the luma of lossy images.
+        Ok(ImageFormat::WEBP) => match load_webp_frames(buffer) {
+            Some((ref frames, play_count)) if frames.len() > 1 => Some(image_from_frames(frames, play_count)),
+            Some((mut frames, _)) => frames.pop().map(|(rgba, _)| image_from_rgba(rgba)),
+            None => {
+                debug!("Image decoding error: invalid WebP");
+                None
+            },
+        },
+        Ok(format) => load_still_image(buffer, format),
+    }
+}
+
+fn load_still_image(buffer: &[u8], format: ImageFormat) -> Option<Image> {
+    match piston_image::load_from_memory_with_format(buffer, format) {
+        Ok(image) => {
+            let rgba = match image {
+                DynamicImage::ImageRgba8(rgba) => rgba,
+                image => image.to_rgba(),
+            };
+            Some(image_from_rgba(rgba))
+        },
+        Err(e) => {
+            debug!("Image decoding error: {:?}", e);
+            None
+        },
+    }
+}
+
+fn image_from_rgba(mut rgba: RgbaImage) -> Image {
+    byte_swap_and_premultiply(&mut *rgba);
+    Image {
+        width: rgba.width(),
+        height: rgba.height(),
+        format: PixelFormat::BGRA8,
+        bytes: IpcSharedMemory::from_bytes(&*rgba),
+        id: None,
+        frames: vec![],
+        play_count: None,
+    }
+}
+
+/// The most memory the decoded frames of an animated image may take. Only the
+/// first frame of animations which need more is decoded, and shown as a still
+/// image.
+const MAX_ANIMATION_BYTES: usize = 256 * 1024 * 1024;
+
+/// Whether the given number of decoded frames of an animated image fit in
+/// `MAX_ANIMATION_BYTES`.
+fn animation_fits(width: u32, height: u32, frame_count: usize) -> bool {
+    (width as usize)
+        .saturating_mul(height as usize)
+        .saturating_mul(4)
+        .saturating_mul(frame_count) <= MAX_ANIMATION_BYTES
+}
+
+/// Decodes all the frames of a GIF, along with their delays in milliseconds.
+///
+/// The frames the decoder gives us may only cover part of the image, in which
+/// case they are drawn over the previous one to get the whole image.
+fn load_gif_frames(buffer: &[u8]) -> piston_image::ImageResult<Vec<(RgbaImage, u32)>> {
+    let mut decoder = GifDecoder::new(Cursor::new(buffer));
+    let (width, height) = decoder.dimensions()?;
+    let mut canvas = RgbaImage::new(width, height);
+    let mut frames = vec![];
+    for frame in decoder.into_frames()? {
+        if !frames.is_empty() && !animation_fits(width, height, frames.len() + 1) {
+            debug!("Animated GIF too large, only showing its first frame");
+            frames.truncate(1);
+            break;
+        }
+        let (left, top) = (frame.left(), frame.top());
+        if (left, top) == (0, 0) && frame.buffer().dimensions() == (width, height) {
+            canvas = frame.buffer().clone();
+        } else {
+            for (x, y, pixel) in frame.buffer().enumerate_pixels() {
+                if left + x < width && top + y < height && pixel[3] != 0 {
+                    canvas.put_pixel(left + x, top + y, *pixel);
+                }
+            }
+        }
+        let delay = frame.delay();
+        let delay = (*delay.numer() as u32 * 1000) / cmp::max(*delay.denom() as u32, 1);
+        frames.push((canvas.clone(), clamp_frame_delay(delay)));
+    }
+    Ok(frames)
+}
+
+/// Returns how many times the animation of a GIF plays, or `None` if it loops
+/// forever.
+///
+/// The loop count of the NETSCAPE2.0 application extension is how many times
+/// the animation plays again after the first time, and animations without it
+/// only play once.
+fn gif_play_count(buffer: &[u8]) -> Option<u32> {
+    match gif_loop_count(buffer) {
+        None => Some(1),
+        Some(0) => None,
+        Some(loop_count) => Some(loop_count as u32 + 1),
+    }
+}
+
+/// Finds the loop count of the NETSCAPE2.0 application extension of a GIF, or
+/// of the ANIMEXTS1.0 one which is the same thing, if there is one.
+fn gif_loop_count(buffer: &[u8]) -> Option<u16> {
+    // Skip the header, the logical screen descriptor and the global color table.
+    let flags = *buffer.get(10)?;
+    let mut position = 13;
+    if flags & 0x80 != 0 {
+        position += 3 << ((flags & 0x07) + 1);
+    }
+
+    loop {
+        match *buffer.get(position)? {
+            // An extension, made of its label and its data sub-blocks.
+            0x21 => {
+                let label = *buffer.get(position + 1)?;
+                position += 2;
+                if label == 0xff && *buffer.get(position)? == 11 {
+                    let identifier = buffer.get(position + 1..position + 12)?;
+                    if identifier == b"NETSCAPE2.0" || identifier == b"ANIMEXTS1.0" {
+                        let data = buffer.get(position + 12..position + 16)?;
+                        if data[0] == 3 && data[1] == 1 {
+                            return Some(data[2] as u16 | (data[3] as u16) << 8);
+                        }
+                    }
+                }
+                position = skip_gif_sub_blocks(buffer, position)?;
+            },
+            // An image, made of its descriptor, its local color table and its
+            // data sub-blocks.
+            0x2c => {
+                let flags = *buffer.get(position + 9)?;
+                position += 10;
+                if flags & 0x80 != 0 {
+                    position += 3 << ((flags & 0x07) + 1);
+                }
+                // Skip the minimum code size of the image data.
+                position = skip_gif_sub_blocks(buffer, position + 1)?;
+            },
+            // The trailer, or something which isn't a GIF block.
+            _ => return None,
+        }
+    }
+}
+
+/// Returns the position following the data sub-blocks of a GIF block.
+fn skip_gif_sub_blocks(buffer: &[u8], mut position: usize) -> Option<usize> {
+    loop {
+        let len = *buffer.get(position)? as usize;
+        position += 1 + len;
+        if len == 0 {
+            return Some(position);
+        }
+    }
+}
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A];
+
+/// A chunk of a PNG.
+struct PngChunk<'a> {
+    kind: &'a [u8],
+    data: &'a [u8],
+    /// The whole chunk, including its length, kind and CRC.
+    bytes: &'a [u8],
+}
+
+/// Splits a PNG into its chunks, returning `None` if one of them is cut off.
+fn png_chunks(buffer: &[u8]) -> Option<Vec<PngChunk>> {
+    let mut chunks = vec![];
+    let mut rest = buffer.get(PNG_SIGNATURE.len()..)?;
+    while rest.len() >= 12 {
+        let len = read_u32(&rest[0..4]) as usize;
+        if len > rest.len() - 12 {
+            return None;
+        }
+        let chunk = PngChunk {
+            kind: &rest[4..8],
+            data: &rest[8..8 + len],
+            bytes: &rest[..12 + len],
+        };
+        let is_end = chunk.kind == b"IEND";
+        chunks.push(chunk);
+        if is_end {
+            break;
+        }
+        rest = &rest[12 + len..];
+    }
+    Some(chunks)
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    (bytes[0] as u32) << 24 | (bytes[1] as u32) << 16 | (bytes[2] as u32) << 8 | bytes[3] as u32
+}
+
+fn read_u16(bytes: &[u8]) -> u16 {
+    (bytes[0] as u16) << 8 | bytes[1] as u16
+}
+
+fn u32_bytes(value: u32) -> [u8; 4] {
+    [(value >> 24) as u8, (value >> 16) as u8, (value >> 8) as u8, value as u8]
+}
+
+fn write_png_chunk(png: &mut Vec<u8>, kind: &[u8], data: &[u8]) {
+    png.extend_from_slice(&u32_bytes(data.len() as u32));
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&u32_bytes(crc));
+}
+
+/// The CRC of PNG chunks.
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { 0xedb88320 ^ (crc >> 1) } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+const APNG_DISPOSE_OP_BACKGROUND: u8 = 1;
+const APNG_DISPOSE_OP_PREVIOUS: u8 = 2;
+const APNG_BLEND_OP_OVER: u8 = 1;
+
+/// The `fcTL` chunk of a frame of an animated PNG.
+struct ApngFrameControl {
+    width: u32,
+    height: u32,
+    x_offset: u32,
+    y_offset: u32,
+    /// How long the frame is shown, in milliseconds.
+    delay: u32,
+    dispose_op: u8,
+    blend_op: u8,
+}
+
+impl ApngFrameControl {
+    fn parse(data: &[u8], width: u32, height: u32) -> Option<ApngFrameControl> {
+        if data.len() != 26 {
+            return None;
+        }
+        let frame_control = ApngFrameControl {
+            width: read_u32(&data[4..8]),
+            height: read_u32(&data[8..12]),
+            x_offset: read_u32(&data[12..16]),
+            y_offset: read_u32(&data[16..20]),
+            delay: {
+                let (numerator, denominator) = (read_u16(&data[20..22]), read_u16(&data[22..24]));
+                let denominator = if denominator == 0 { 100 } else { denominator };
+                numerator as u32 * 1000 / denominator as u32
+            },
+            dispose_op: data[24],
+            blend_op: data[25],
+        };
+        let fits = frame_control.x_offset.checked_add(frame_control.width).map_or(false, |x| x <= width) &&
+            frame_control.y_offset.checked_add(frame_control.height).map_or(false, |y| y <= height);
+        if frame_control.width == 0 || frame_control.height == 0 || !fits {
+            return None;
+        }
+        Some(frame_control)
+    }
+}
+
+/// Decodes all the frames of an animated PNG, along with their delays in
+/// milliseconds and how many times the animation plays, or returns `None` if
+/// the PNG isn't animated or its animation is broken.
+///
+/// Each frame is turned into a PNG of its own, made of the chunks of the
+/// animated PNG which apply to all of its frames, like the palette, and of the
+/// frame data, so that it can be decoded like any other PNG.
+///
+/// <https://wiki.mozilla.org/APNG_Specification>
+fn load_apng_frames(buffer: &[u8]) -> Option<(Vec<(RgbaImage, u32)>, Option<u32>)> {
+    let chunks = png_chunks(buffer)?;
+    let header = chunks.first().filter(|chunk| chunk.kind == b"IHDR" && chunk.data.len() == 13)?;
+    let (width, height) = (read_u32(&header.data[0..4]), read_u32(&header.data[4..8]));
+    let animation_control = chunks
+        .iter()
+        .take_while(|chunk| chunk.kind != b"IDAT")
+        .find(|chunk| chunk.kind == b"acTL")?;
+    if animation_control.data.len() != 8 {
+        return None;
+    }
+    let play_count = match read_u32(&animation_control.data[4..8]) {
+        0 => None,
+        play_count => Some(play_count),
+    };
+    let shared_chunks: Vec<_> = chunks[1..]
+        .iter()
+        .take_while(|chunk| chunk.kind != b"IDAT")
+        .filter(|chunk| chunk.kind != b"acTL" && chunk.kind != b"fcTL")
+        .collect();
+
+    // The image data which follows each fcTL chunk, leaving out the default
+    // image if it isn't part of the animation.
+    let mut frame_data: Vec<(ApngFrameControl, Vec<u8>)> = vec![];
+    for chunk in &chunks {
+        if chunk.kind == b"fcTL" {
+            frame_data.push((ApngFrameControl::parse(chunk.data, width, height)?, vec![]));
+        } else if chunk.kind == b"IDAT" {
+            if let Some(&mut (_, ref mut frame)) = frame_data.last_mut() {
+                frame.extend_from_slice(chunk.data);
+            }
+        } else if chunk.kind == b"fdAT" {
+            // Leave out the sequence number.
+            let data = chunk.data.get(4..)?;
+            if let Some(&mut (_, ref mut frame)) = frame_data.last_mut() {
+                frame.extend_from_slice(data);
+            }
+        }
+    }
+
+    if !animation_fits(width, height, frame_data.len()) {
+        debug!("Animated PNG too large, only showing its default image");
+        return None;
+    }
+
+    let mut canvas = RgbaImage::new(width, height);
+    let mut frames = Vec::with_capacity(frame_data.len());
+    for &(ref frame_control, ref data) in &frame_data {
+        let mut png = PNG_SIGNATURE.to_vec();
+        let mut frame_header = header.data.to_vec();
+        frame_header[0..4].copy_from_slice(&u32_bytes(frame_control.width));
+        frame_header[4..8].copy_from_slice(&u32_bytes(frame_control.height));
+        write_png_chunk(&mut png, b"IHDR", &frame_header);
+        for chunk in &shared_chunks {
+            png.extend_from_slice(chunk.bytes);
+        }
+        write_png_chunk(&mut png, b"IDAT", data);
+        write_png_chunk(&mut png, b"IEND", &[]);
+        let frame = piston_image::load_from_memory_with_format(&png, ImageFormat::PNG).ok()?.to_rgba();
+        if frame.dimensions() != (frame_control.width, frame_control.height) {
+            return None;
+        }
+
+        let previous_canvas = if frame_control.dispose_op == APNG_DISPOSE_OP_PREVIOUS {
+            Some(canvas.clone())
+        } else {
+            None
+        };
+        for (x, y, pixel) in frame.enumerate_pixels() {
+            let canvas_pixel = canvas.get_pixel_mut(frame_control.x_offset + x, frame_control.y_offset + y);
+            if frame_control.blend_op == APNG_BLEND_OP_OVER {
+                blend_over(canvas_pixel, pixel);
+            } else {
+                *canvas_pixel = *pixel;
+            }
+        }
+        frames.push((canvas.clone(), clamp_frame_delay(frame_control.delay)));
+
+        if let Some(previous_canvas) = previous_canvas {
+            canvas = previous_canvas;
+        } else if frame_control.dispose_op == APNG_DISPOSE_OP_BACKGROUND {
+            for y in 0..frame_control.height {
+                for x in 0..frame_control.width {
+                    canvas.put_pixel(frame_control.x_offset + x, frame_control.y_offset + y, Rgba { data: [0; 4] });
+                }
+            }
+        }
+    }
+    Some((frames, play_count))
+}
+
+/// The parts of the libwebp demux API that are used to decode WebPs.
+///
+/// <https://developers.google.com/speed/webp/docs/container-api#webpanimdecoder_api>
+#[allow(non_camel_case_types, non_snake_case)]
+mod webp {
+    use libc::{c_int, c_void, size_t};
+
+    /// `WEBP_DEMUX_ABI_VERSION` of libwebp 0.5 and later.
+    pub const WEBP_DEMUX_ABI_VERSION: c_int = 0x0107;
+    /// `MODE_RGBA` of `WEBP_CSP_MODE`.
+    pub const MODE_RGBA: c_int = 1;
+
+    pub type WebPAnimDecoder = c_void;
+
+    #[repr(C)]
+    pub struct WebPData {
+        pub bytes: *const u8,
+        pub size: size_t,
+    }
+
+    #[repr(C)]
+    pub struct WebPAnimDecoderOptions {
+        pub color_mode: c_int,
+        pub use_threads: c_int,
+        pub padding: [u32; 7],
+    }
+
+    #[repr(C)]
+    pub struct WebPAnimInfo {
+        pub canvas_width: u32,
+        pub canvas_height: u32,
+        pub loop_count: u32,
+        pub bgcolor: u32,
+        pub frame_count: u32,
+        pub pad: [u32; 4],
+    }
+
+    extern "C" {
+        pub fn WebPAnimDecoderOptionsInitInternal(options: *mut WebPAnimDecoderOptions, abi_version: c_int) -> c_int;
+        pub fn WebPAnimDecoderNewInternal(
+            data: *const WebPData,
+            options: *const WebPAnimDecoderOptions,
+            abi_version: c_int,
+        ) -> *mut WebPAnimDecoder;
+        pub fn WebPAnimDecoderGetInfo(decoder: *const WebPAnimDecoder, info: *mut WebPAnimInfo) -> c_int;
+        pub fn WebPAnimDecoderHasMoreFrames(decoder: *const WebPAnimDecoder) -> c_int;
+        pub fn WebPAnimDecoderGetNext(decoder: *mut WebPAnimDecoder, pixels: *mut *mut u8, timestamp: *mut c_int) -> c_int;
+        pub fn WebPAnimDecoderDelete(decoder: *mut WebPAnimDecoder);
+    }
+}
+
+/// A libwebp animation decoder, which is deleted when dropped.
+struct WebPAnimation(*mut webp::WebPAnimDecoder);
+
+impl Drop for WebPAnimation {
+    #[allow(unsafe_code)]
+    fn drop(&mut self) {
+        unsafe { webp::WebPAnimDecoderDelete(self.0) }
+    }
+}
+
+/// Decodes all the frames of a WebP, along with their delays in milliseconds
+/// and how many times the animation plays, or returns `None` if the WebP is
+/// broken. Still WebPs have a single frame.
+///
+/// libwebp draws the frames of animations over each other to get whole images.
+///
+/// <https://developers.google.com/speed/webp/docs/riff_container>
+#[allow(unsafe_code)]
+fn load_webp_frames(buffer: &[u8]) -> Option<(Vec<(RgbaImage, u32)>, Option<u32>)> {
+    let data = webp::WebPData {
+        bytes: buffer.as_ptr(),
+        size: buffer.len(),
+    };
+    let mut options = webp::WebPAnimDecoderOptions {
+        color_mode: 0,
+        use_threads: 0,
+        padding: [0; 7],
+    };
+    if unsafe { webp::WebPAnimDecoderOptionsInitInternal(&mut options, webp::WEBP_DEMUX_ABI_VERSION) } == 0 {
+        return None;
+    }
+    options.color_mode = webp::MODE_RGBA;
+    let decoder = WebPAnimation(unsafe {
+        webp::WebPAnimDecoderNewInternal(&data, &options, webp::WEBP_DEMUX_ABI_VERSION)
+    });
+    if decoder.0.is_null() {
+        return None;
+    }
+
+    let mut info = webp::WebPAnimInfo {
+        canvas_width: 0,
+        canvas_height: 0,
+        loop_count: 0,
+        bgcolor: 0,
+        frame_count: 0,
+        pad: [0; 4],
+    };
+    if unsafe { webp::WebPAnimDecoderGetInfo(decoder.0, &mut info) } == 0 {
+        return None;
+    }
+    let (width, height) = (info.canvas_width, info.canvas_height);
+    let play_count = match info.loop_count {
+        0 => None,
+        loop_count => Some(loop_count),
+    };
+    let frame_count = if animation_fits(width, height, info.frame_count as usize) {
+        info.frame_count as usize
+    } else {
+        debug!("Animated WebP too large, only showing its first frame");
+        1
+    };
+
+    let mut frames = vec![];
+    let mut previous_timestamp = 0;
+    while frames.len() < frame_count && unsafe { webp::WebPAnimDecoderHasMoreFrames(decoder.0) } != 0 {
+        let mut pixels: *mut u8 = ptr::null_mut();
+        let mut timestamp = 0;
+        if unsafe { webp::WebPAnimDecoderGetNext(decoder.0, &mut pixels, &mut timestamp) } == 0 {
+            return None;
+        }
+        // The canvas belongs to the decoder, and is only valid until the next frame.
+        let canvas = unsafe { slice::from_raw_parts(pixels, width as usize * height as usize * 4) };
+        let frame = RgbaImage::from_raw(width, height, canvas.to_vec())?;
+        let delay = cmp::max(timestamp - previous_timestamp, 0) as u32;
+        previous_timestamp = timestamp;
+        frames.push((frame, clamp_frame_delay(delay)));
+    }
+    if frames.is_empty() {
+        return None;
+    }
+    Some((frames, play_count))
+}
+
+/// The libavif structs change between releases, so AVIFs are decoded through
+/// the small C wrapper in `avif.c`, which is built against the installed
+/// libavif.
+mod avif {
+    use libc::{c_double, c_int, c_void, size_t};
+
+    pub type AvifDecoder = c_void;
+
+    extern "C" {
+        pub fn servo_avif_decoder_new(data: *const u8, size: size_t) -> *mut AvifDecoder;
+        pub fn servo_avif_decoder_info(
+            decoder: *const AvifDecoder,
+            width: *mut u32,
+            height: *mut u32,
+            image_count: *mut c_int,
+            repetition_count: *mut c_int,
+        );
+        pub fn servo_avif_decoder_next_rgba(
+            decoder: *mut AvifDecoder,
+            pixels: *mut u8,
+            width: u32,
+            height: u32,
+            duration: *mut c_double,
+        ) -> c_int;
+        pub fn servo_avif_decoder_delete(decoder: *mut AvifDecoder);
+    }
+}
+
+/// A libavif decoder, which is destroyed when dropped.
+struct AvifDecoder(*mut avif::AvifDecoder);
+
+impl Drop for AvifDecoder {
+    #[allow(unsafe_code)]
+    fn drop(&mut self) {
+        unsafe { avif::servo_avif_decoder_delete(self.0) }
+    }
+}
+
+/// Decodes all the frames of an AVIF, along with their delays in milliseconds
+/// and how many times the animation plays, or returns `None` if the AVIF is
+/// broken. Still AVIFs have a single frame, and image sequences one for each of
+/// their images.
+///
+/// <https://aomediacodec.github.io/av1-avif/>
+#[allow(unsafe_code)]
+fn load_avif_frames(buffer: &[u8]) -> Option<(Vec<(RgbaImage, u32)>, Option<u32>)> {
+    // The decoder reads the buffer in place, and it outlives the decoder.
+    let decoder = AvifDecoder(unsafe { avif::servo_avif_decoder_new(buffer.as_ptr(), buffer.len()) });
+    if decoder.0.is_null() {
+        return None;
+    }
+
+    let (mut width, mut height, mut image_count, mut repetition_count) = (0, 0, 0, 0);
+    unsafe {
+        avif::servo_avif_decoder_info(decoder.0, &mut width, &mut height, &mut image_count, &mut repetition_count)
+    };
+    // The repetition count is how many times the animation plays again after
+    // the first time, and is negative for animations which loop forever, or
+    // don't say.
+    let play_count = if repetition_count >= 0 {
+        Some(repetition_count as u32 + 1)
+    } else {
+        None
+    };
+    let frame_count = cmp::max(image_count, 1) as usize;
+    let frame_count = if animation_fits(width, height, frame_count) {
+        frame_count
+    } else {
+        debug!("AVIF image sequence too large, only showing its first image");
+        1
+    };
+
+    let mut frames = Vec::with_capacity(frame_count);
+    while frames.len() < frame_count {
+        // Convert the image to RGBA, along with its alpha if it has any.
+        let mut pixels = vec![0; width as usize * height as usize * 4];
+        let mut duration = 0.;
+        let decoded = unsafe {
+            avif::servo_avif_decoder_next_rgba(decoder.0, pixels.as_mut_ptr(), width, height, &mut duration)
+        };
+        if decoded == 0 {
+            return None;
+        }
+        let delay = duration * 1000.;
+        frames.push((RgbaImage::from_raw(width, height, pixels)?, clamp_frame_delay(delay as u32)));
+    }
+    Some((frames, play_count))
+}
+
+/// Draws a pixel over another one, neither of them being premultiplied.
+fn blend_over(destination: &mut Rgba<u8>, source: &Rgba<u8>) {
+    let source_alpha = source[3] as u32;
+    if source_alpha == 255 {
+        *destination = *source;
+        return;
+    }
+    if source_alpha == 0 {
+        return;
+    }
+    let destination_alpha = destination[3] as u32 * (255 - source_alpha) / 255;
+    let alpha = source_alpha + destination_alpha;
+    for channel in 0..3 {
+        destination[channel] =
+            ((source[channel] as u32 * source_alpha + destination[channel] as u32 * destination_alpha) / alpha) as u8;
+    }
+    destination[3] = alpha as u8;
+}
+
+/// Browsers show the frames with very short delays for 100ms instead, as
+/// they were likely meant to be shown as fast as possible by images which
+/// would otherwise spin the CPU.
+fn clamp_frame_delay(delay: u32) -> u32 {
+    if delay <= 10 {
+        100
+    } else {
+        delay
+    }
+}
+
+fn image_from_frames(frames: &[(RgbaImage, u32)], play_count: Option<u32>) -> Image {
+    let (width, height) = frames[0].0.dimensions();
+    let mut bytes = Vec::with_capacity(frames.len() * width as usize * height as usize * 4);
+    for &(ref rgba, _) in frames {
+        bytes.extend_from_slice(&**rgba);
+    }
+    byte_swap_and_premultiply(&mut bytes);
+    Image {
+        width: width,
+        height: height,
+        format: PixelFormat::BGRA8,
+        bytes: IpcSharedMemory::from_bytes(&bytes),
+        id: None,
+        frames: frames.iter().map(|&(_, delay)| ImageFrame { delay: delay, id: None }).collect(),
+        play_count: play_count,
     }
 }
 
@@ -113,6 +827,8 @@ pub fn detect_image_format(buffer: &[u8]) -> Result<ImageFormat, &str> {
         Ok(ImageFormat::BMP)
     } else if is_ico(buffer) {
         Ok(ImageFormat::ICO)
+    } else if is_webp(buffer) {
+        Ok(ImageFormat::WEBP)
     } else {
         Err("Image Format Not Supported")
     }
@@ -127,7 +843,7 @@ fn is_jpeg(buffer: &[u8]) -> bool {
 }
 
 fn is_png(buffer: &[u8]) -> bool {
-    buffer.starts_with(&[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A])
+    buffer.starts_with(&PNG_SIGNATURE)
 }
 
 fn is_bmp(buffer: &[u8]) -> bool {
@@ -137,3 +853,13 @@ fn is_bmp(buffer: &[u8]) -> bool {
 fn is_ico(buffer: &[u8]) -> bool {
     buffer.starts_with(&[0x00, 0x00, 0x01, 0x00])
 }
+
+fn is_webp(buffer: &[u8]) -> bool {
+    buffer.starts_with(b"RIFF") && buffer.len() >= 12 && &buffer[8..12] == b"WEBP"
+}
+
+/// Whether the image is an AVIF, which `detect_image_format` doesn't detect as
+/// it isn't one of the formats of the image crate.
+pub fn is_avif(buffer: &[u8]) -> bool {
+    buffer.len() >= 12 && &buffer[4..8] == b"ftyp" && (&buffer[8..12] == b"avif" || &buffer[8..12] == b"avis")
+}
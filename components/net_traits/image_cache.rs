@@ -121,4 +121,8 @@ pub trait ImageCache: Sync + Send {
 
     /// Ensure an image has a webrender key.
     fn set_webrender_image_key(&self, image: &mut Image);
+
+    /// Release the webrender keys of images which aren't displayed anymore,
+    /// like those layout rasterizes for inline SVG.
+    fn delete_webrender_image_keys(&self, keys: Vec<webrender_api::ImageKey>);
 }
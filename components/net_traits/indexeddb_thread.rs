@@ -0,0 +1,307 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use ipc_channel::ipc::IpcSender;
+use servo_url::ServoUrl;
+use std::cmp::Ordering;
+
+/// <https://w3c.github.io/IndexedDB/#key-construct>
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub enum IndexedDBKey {
+    Number(#[serde(with = "key_number")] f64),
+    /// The time value of a `Date`, in milliseconds since the epoch.
+    Date(#[serde(with = "key_number")] f64),
+    String(String),
+    Binary(Vec<u8>),
+    Array(Vec<IndexedDBKey>),
+}
+
+/// Infinite numbers are valid keys but JSON can't represent them, so the numbers of keys
+/// are serialized as the bits of their representation instead.
+mod key_number {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(number: &f64, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        serializer.serialize_u64(number.to_bits())
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<f64, D::Error>
+        where D: Deserializer<'de>
+    {
+        u64::deserialize(deserializer).map(f64::from_bits)
+    }
+}
+
+impl IndexedDBKey {
+    fn type_order(&self) -> u8 {
+        match *self {
+            IndexedDBKey::Number(_) => 0,
+            IndexedDBKey::Date(_) => 1,
+            IndexedDBKey::String(_) => 2,
+            IndexedDBKey::Binary(_) => 3,
+            IndexedDBKey::Array(_) => 4,
+        }
+    }
+}
+
+impl Eq for IndexedDBKey {}
+
+impl PartialOrd for IndexedDBKey {
+    fn partial_cmp(&self, other: &IndexedDBKey) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// <https://w3c.github.io/IndexedDB/#compare-two-keys>
+impl Ord for IndexedDBKey {
+    fn cmp(&self, other: &IndexedDBKey) -> Ordering {
+        match (self, other) {
+            (&IndexedDBKey::Number(a), &IndexedDBKey::Number(b)) |
+            (&IndexedDBKey::Date(a), &IndexedDBKey::Date(b)) => {
+                // Keys are never NaN, the script thread rejects such values.
+                a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+            },
+            (&IndexedDBKey::String(ref a), &IndexedDBKey::String(ref b)) => {
+                a.encode_utf16().cmp(b.encode_utf16())
+            },
+            (&IndexedDBKey::Binary(ref a), &IndexedDBKey::Binary(ref b)) => a.cmp(b),
+            (&IndexedDBKey::Array(ref a), &IndexedDBKey::Array(ref b)) => a.cmp(b),
+            _ => self.type_order().cmp(&other.type_order()),
+        }
+    }
+}
+
+/// <https://w3c.github.io/IndexedDB/#range-construct>
+#[derive(Clone, Debug, Default, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub struct IndexedDBKeyRange {
+    pub lower: Option<IndexedDBKey>,
+    pub upper: Option<IndexedDBKey>,
+    pub lower_open: bool,
+    pub upper_open: bool,
+}
+
+impl IndexedDBKeyRange {
+    /// A range containing only `key`.
+    pub fn only(key: IndexedDBKey) -> IndexedDBKeyRange {
+        IndexedDBKeyRange {
+            lower: Some(key.clone()),
+            upper: Some(key),
+            lower_open: false,
+            upper_open: false,
+        }
+    }
+
+    /// <https://w3c.github.io/IndexedDB/#in>
+    pub fn contains(&self, key: &IndexedDBKey) -> bool {
+        let above_lower = match self.lower {
+            Some(ref lower) if self.lower_open => key > lower,
+            Some(ref lower) => key >= lower,
+            None => true,
+        };
+        let below_upper = match self.upper {
+            Some(ref upper) if self.upper_open => key < upper,
+            Some(ref upper) => key <= upper,
+            None => true,
+        };
+        above_lower && below_upper
+    }
+}
+
+/// <https://w3c.github.io/IndexedDB/#key-path-construct>
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub enum IndexedDBKeyPath {
+    String(String),
+    Sequence(Vec<String>),
+}
+
+/// <https://w3c.github.io/IndexedDB/#cursor-direction>
+#[derive(Clone, Copy, Debug, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub enum IndexedDBCursorDirection {
+    Next,
+    NextUnique,
+    Prev,
+    PrevUnique,
+}
+
+impl IndexedDBCursorDirection {
+    pub fn is_reverse(&self) -> bool {
+        *self == IndexedDBCursorDirection::Prev || *self == IndexedDBCursorDirection::PrevUnique
+    }
+
+    pub fn is_unique(&self) -> bool {
+        *self == IndexedDBCursorDirection::NextUnique || *self == IndexedDBCursorDirection::PrevUnique
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub struct IndexInfo {
+    pub name: String,
+    pub key_path: IndexedDBKeyPath,
+    pub unique: bool,
+    pub multi_entry: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub struct ObjectStoreInfo {
+    pub name: String,
+    pub key_path: Option<IndexedDBKeyPath>,
+    pub auto_increment: bool,
+    pub indexes: Vec<IndexInfo>,
+}
+
+/// A record of an object store or an index, in the order of the source it was read from.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct IndexedDBRecord {
+    /// The key of the record in its source, which is the index key for an index.
+    pub key: IndexedDBKey,
+    /// The key of the record in the object store.
+    pub primary_key: IndexedDBKey,
+    /// The structured clone of the record's value.
+    pub value: Vec<u8>,
+}
+
+/// Describes which records of an object store or of one of its indexes are retrieved.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct IndexedDBQuery {
+    /// The index to read from, or the object store itself if `None`.
+    pub index: Option<String>,
+    pub range: IndexedDBKeyRange,
+    pub direction: IndexedDBCursorDirection,
+    /// Only records strictly after this (key, primary key) position, in the direction
+    /// of iteration, are returned. Used to advance cursors.
+    pub position: Option<(IndexedDBKey, IndexedDBKey)>,
+    pub count: Option<u32>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum IndexedDBError {
+    /// A uniqueness constraint of the object store or of an index would be violated.
+    Constraint,
+    /// The object store or index doesn't exist.
+    NotFound,
+    /// No key was given for an object store without a key generator.
+    Data,
+    /// The IndexedDB thread couldn't be reached.
+    Unknown,
+}
+
+/// An operation run as part of a transaction.
+#[derive(Deserialize, Serialize)]
+pub enum IndexedDBOperation {
+    /// Sets the version of the database, during an upgrade transaction
+    SetVersion(IpcSender<Result<(), IndexedDBError>>, u64),
+
+    /// Creates an object store with the given name, key path and key generator flag
+    CreateObjectStore(IpcSender<Result<(), IndexedDBError>>, String, Option<IndexedDBKeyPath>, bool),
+
+    /// Deletes the named object store
+    DeleteObjectStore(IpcSender<Result<(), IndexedDBError>>, String),
+
+    /// Creates an index on an object store, along with the index keys of each existing record
+    CreateIndex(IpcSender<Result<(), IndexedDBError>>,
+                String,
+                IndexInfo,
+                Vec<(IndexedDBKey, Vec<IndexedDBKey>)>),
+
+    /// Deletes the named index of an object store
+    DeleteIndex(IpcSender<Result<(), IndexedDBError>>, String, String),
+
+    /// Stores a value in an object store, along with its keys in each index, replying with its
+    /// key. The key is generated if none is given. Existing records are only overwritten if the
+    /// last argument is true.
+    Put(IpcSender<Result<IndexedDBKey, IndexedDBError>>,
+        String,
+        Option<IndexedDBKey>,
+        Vec<u8>,
+        Vec<(String, Vec<IndexedDBKey>)>,
+        bool),
+
+    /// Retrieves the records of an object store or index matching a query
+    Get(IpcSender<Result<Vec<IndexedDBRecord>, IndexedDBError>>, String, IndexedDBQuery),
+
+    /// Counts the records of an object store or index within a key range
+    Count(IpcSender<Result<u64, IndexedDBError>>, String, Option<String>, IndexedDBKeyRange),
+
+    /// Deletes the records of an object store within a key range
+    Delete(IpcSender<Result<(), IndexedDBError>>, String, IndexedDBKeyRange),
+
+    /// Deletes all the records of an object store
+    Clear(IpcSender<Result<(), IndexedDBError>>, String),
+}
+
+/// An event that the IndexedDB thread sends to an open connection.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum IndexedDBConnectionEvent {
+    /// Another connection is about to change the version of the database from the first
+    /// version to the second one. The connection replies with `VersionChangeFired` once the
+    /// `versionchange` event was fired at it.
+    VersionChange(u64, Option<u64>),
+}
+
+/// Whether the other connections to a database whose version is about to change are closed.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum IndexedDBBlockingStatus {
+    /// Some of the connections are still open after the `versionchange` event was fired at
+    /// each of them.
+    Blocked,
+    /// All the connections are closed.
+    Unblocked,
+}
+
+/// How the deletion of a database is going.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum IndexedDBDeletionStatus {
+    /// Some of the connections to the database, whose version is given, are still open after
+    /// the `versionchange` event was fired at each of them.
+    Blocked(u64),
+    /// The database was deleted once all the connections to it were closed, replying with
+    /// its version if it existed.
+    Deleted(Option<u64>),
+}
+
+/// Request operations on the databases associated with the origin of a url
+#[derive(Deserialize, Serialize)]
+pub enum IndexedDBThreadMsg {
+    /// gets the version of the named database, creating it with version 0 if it doesn't exist
+    OpenDatabase(IpcSender<u64>, ServoUrl, String),
+
+    /// fires `versionchange` events at the open connections to the named database, and deletes
+    /// it once all of them are closed
+    DeleteDatabase(IpcSender<IndexedDBDeletionStatus>, ServoUrl, String),
+
+    /// registers a connection to the named database, replying with its identifier, and sends
+    /// the events of the connection to the given sender
+    OpenConnection(IpcSender<u64>, ServoUrl, String, IpcSender<IndexedDBConnectionEvent>),
+
+    /// unregisters a connection, once it is closed
+    CloseConnection(u64),
+
+    /// fires `versionchange` events at the other open connections to the database of a
+    /// connection, and replies with whether they are closed: `Blocked` if some of them are
+    /// still open once the events were fired, and `Unblocked` once all of them are closed
+    WaitForOtherConnections(IpcSender<IndexedDBBlockingStatus>, u64, u64),
+
+    /// notes that the `versionchange` event was fired at a connection
+    VersionChangeFired(u64),
+
+    /// gets the object stores of the named database
+    ObjectStores(IpcSender<Vec<ObjectStoreInfo>>, ServoUrl, String),
+
+    /// allocates the identifier of a new transaction
+    NewTransaction(IpcSender<u64>),
+
+    /// runs an operation in a transaction of the named database
+    Operation(ServoUrl, String, u64, IndexedDBOperation),
+
+    /// makes the changes of a transaction durable
+    Commit(IpcSender<()>, ServoUrl, String, u64),
+
+    /// reverts the changes of a transaction
+    Abort(IpcSender<()>, ServoUrl, String, u64),
+
+    /// send a reply when done cleaning up thread resources and then shut it down
+    Exit(IpcSender<()>),
+}
@@ -5,6 +5,7 @@
 
 #![deny(unsafe_code)]
 
+extern crate base64;
 extern crate cookie as cookie_rs;
 extern crate embedder_traits;
 extern crate hyper;
@@ -12,11 +13,13 @@ extern crate hyper_serde;
 extern crate image as piston_image;
 extern crate ipc_channel;
 #[macro_use] extern crate lazy_static;
+extern crate libc;
 #[macro_use] extern crate log;
 #[macro_use] extern crate malloc_size_of;
 #[macro_use] extern crate malloc_size_of_derive;
 extern crate msg;
 extern crate num_traits;
+extern crate openssl;
 #[macro_use] extern crate serde;
 extern crate servo_arc;
 extern crate servo_url;
@@ -24,26 +27,32 @@ extern crate url;
 extern crate uuid;
 extern crate webrender_api;
 
+use cache_storage_thread::CacheStorageThreadMsg;
 use cookie_rs::Cookie;
+use csp::Violation;
 use filemanager_thread::FileManagerThreadMsg;
 use hyper::Error as HyperError;
 use hyper::header::{ContentType, Headers, ReferrerPolicy as ReferrerPolicyHeader};
 use hyper::http::RawStatus;
 use hyper::mime::{Attr, Mime};
 use hyper_serde::Serde;
+use indexeddb_thread::IndexedDBThreadMsg;
 use ipc_channel::Error as IpcError;
 use ipc_channel::ipc::{self, IpcReceiver, IpcSender};
 use ipc_channel::router::ROUTER;
 use msg::constellation_msg::HistoryStateId;
-use request::{Request, RequestInit};
+use request::{Request, RequestId, RequestInit};
 use response::{HttpsState, Response, ResponseInit};
 use servo_url::ServoUrl;
 use std::error::Error;
 use storage_thread::StorageThreadMsg;
 
 pub mod blob_url_store;
+pub mod cache_storage_thread;
+pub mod csp;
 pub mod filemanager_thread;
 pub mod image_cache;
+pub mod indexeddb_thread;
 pub mod net_error_list;
 pub mod pub_domains;
 pub mod request;
@@ -97,10 +106,16 @@ impl CustomResponse {
     }
 }
 
+/// A request for a service worker to respond to a fetch, by dispatching a `FetchEvent`.
 #[derive(Clone, Deserialize, Serialize)]
 pub struct CustomResponseMediator {
-    pub response_chan: IpcSender<Option<CustomResponse>>,
-    pub load_url: ServoUrl,
+    /// Receives the response of the worker, or `None` if the request should go to the network.
+    pub response_chan: IpcSender<Option<Result<CustomResponse, NetworkError>>>,
+    /// The URL of the client the request comes from, which decides the controlling worker.
+    /// This is the URL of the request itself for navigations.
+    pub client_url: ServoUrl,
+    /// The request to respond to.
+    pub request: RequestInit,
 }
 
 /// [Policies](https://w3c.github.io/webappsec-referrer-policy/#referrer-policy-states)
@@ -157,6 +172,7 @@ pub enum FetchResponseMsg {
     ProcessResponse(Result<FetchMetadata, NetworkError>),
     ProcessResponseChunk(Vec<u8>),
     ProcessResponseEOF(Result<(), NetworkError>),
+    ProcessCspViolations(Vec<Violation>),
 }
 
 pub trait FetchTaskTarget {
@@ -182,6 +198,11 @@ pub trait FetchTaskTarget {
     ///
     /// Fired when the response is fully fetched
     fn process_response_eof(&mut self, response: &Response);
+
+    /// <https://w3c.github.io/webappsec-csp/#report-violation>
+    ///
+    /// Fired when the request violates a content security policy of its client
+    fn process_csp_violations(&mut self, request: &Request, violations: Vec<Violation>);
 }
 
 #[derive(Clone, Deserialize, Serialize)]
@@ -207,6 +228,7 @@ pub trait FetchResponseListener {
     fn process_response(&mut self, metadata: Result<FetchMetadata, NetworkError>);
     fn process_response_chunk(&mut self, chunk: Vec<u8>);
     fn process_response_eof(&mut self, response: Result<(), NetworkError>);
+    fn process_csp_violations(&mut self, violations: Vec<Violation>);
 }
 
 impl FetchTaskTarget for IpcSender<FetchResponseMsg> {
@@ -233,6 +255,10 @@ impl FetchTaskTarget for IpcSender<FetchResponseMsg> {
             let _ = self.send(FetchResponseMsg::ProcessResponseEOF(Ok(())));
         }
     }
+
+    fn process_csp_violations(&mut self, _: &Request, violations: Vec<Violation>) {
+        let _ = self.send(FetchResponseMsg::ProcessCspViolations(violations));
+    }
 }
 
 
@@ -249,6 +275,7 @@ impl<T: FetchResponseListener> Action<T> for FetchResponseMsg {
             FetchResponseMsg::ProcessResponse(meta) => listener.process_response(meta),
             FetchResponseMsg::ProcessResponseChunk(data) => listener.process_response_chunk(data),
             FetchResponseMsg::ProcessResponseEOF(data) => listener.process_response_eof(data),
+            FetchResponseMsg::ProcessCspViolations(violations) => listener.process_csp_violations(violations),
         }
     }
 }
@@ -279,13 +306,21 @@ pub trait IpcSend<T>
 pub struct ResourceThreads {
     core_thread: CoreResourceThread,
     storage_thread: IpcSender<StorageThreadMsg>,
+    indexeddb_thread: IpcSender<IndexedDBThreadMsg>,
+    cache_storage_thread: IpcSender<CacheStorageThreadMsg>,
 }
 
 impl ResourceThreads {
-    pub fn new(c: CoreResourceThread, s: IpcSender<StorageThreadMsg>) -> ResourceThreads {
+    pub fn new(c: CoreResourceThread,
+               s: IpcSender<StorageThreadMsg>,
+               i: IpcSender<IndexedDBThreadMsg>,
+               cs: IpcSender<CacheStorageThreadMsg>)
+               -> ResourceThreads {
         ResourceThreads {
             core_thread: c,
             storage_thread: s,
+            indexeddb_thread: i,
+            cache_storage_thread: cs,
         }
     }
 }
@@ -310,6 +345,26 @@ impl IpcSend<StorageThreadMsg> for ResourceThreads {
     }
 }
 
+impl IpcSend<IndexedDBThreadMsg> for ResourceThreads {
+    fn send(&self, msg: IndexedDBThreadMsg) -> IpcSendResult {
+        self.indexeddb_thread.send(msg)
+    }
+
+    fn sender(&self) -> IpcSender<IndexedDBThreadMsg> {
+        self.indexeddb_thread.clone()
+    }
+}
+
+impl IpcSend<CacheStorageThreadMsg> for ResourceThreads {
+    fn send(&self, msg: CacheStorageThreadMsg) -> IpcSendResult {
+        self.cache_storage_thread.send(msg)
+    }
+
+    fn sender(&self) -> IpcSender<CacheStorageThreadMsg> {
+        self.cache_storage_thread.clone()
+    }
+}
+
 // Ignore the sub-fields
 malloc_size_of_is_0!(ResourceThreads);
 
@@ -344,7 +399,9 @@ pub enum WebSocketNetworkEvent {
 #[derive(Deserialize, Serialize)]
 /// IPC channels to communicate with the script thread about network or DOM events.
 pub enum FetchChannels {
-    ResponseMsg(IpcSender<FetchResponseMsg>, /* cancel_chan */ Option<IpcReceiver<()>>),
+    ResponseMsg(IpcSender<FetchResponseMsg>,
+                /* cancel_chan */ Option<IpcReceiver<()>>,
+                /* demand_chan */ Option<IpcReceiver<()>>),
     WebSocket {
         event_sender: IpcSender<WebSocketNetworkEvent>,
         action_receiver: IpcReceiver<WebSocketDomAction>,
@@ -356,6 +413,8 @@ pub enum CoreResourceMsg {
     Fetch(RequestInit, FetchChannels),
     /// Initiate a fetch in response to processing a redirection
     FetchRedirect(RequestInit, ResponseInit, IpcSender<FetchResponseMsg>, /* cancel_chan */ Option<IpcReceiver<()>>),
+    /// Cancel the fetches with the given ids, if they are still ongoing
+    Cancel(Vec<RequestId>),
     /// Store a cookie for a given originating URL
     SetCookieForUrl(ServoUrl, Serde<Cookie<'static>>, CookieSource),
     /// Store a set of cookies for a given originating URL
@@ -364,12 +423,16 @@ pub enum CoreResourceMsg {
     GetCookiesForUrl(ServoUrl, IpcSender<Option<String>>, CookieSource),
     /// Get a cookie by name for a given originating URL
     GetCookiesDataForUrl(ServoUrl, IpcSender<Vec<Serde<Cookie<'static>>>>, CookieSource),
+    /// Remove the cookies for a given URL, or only the one with the given name
+    DeleteCookiesForUrl(ServoUrl, Option<String>),
     /// Get a history state by a given history state id
     GetHistoryState(HistoryStateId, IpcSender<Option<Vec<u8>>>),
     /// Set a history state for a given history state id
     SetHistoryState(HistoryStateId, Vec<u8>),
     /// Removes history states for the given ids
     RemoveHistoryStates(Vec<HistoryStateId>),
+    /// Remove all the responses stored in the HTTP cache, including the ones on disk
+    ClearCache,
     /// Synchronization message solely for knowing the state of the ResourceChannelManager loop
     Synchronize(IpcSender<()>),
     /// Send the network sender in constellation to CoreResourceThread
@@ -389,7 +452,7 @@ pub fn fetch_async<F>(request: RequestInit, core_resource_thread: &CoreResourceT
     ROUTER.add_route(action_receiver.to_opaque(),
                      Box::new(move |message| f(message.to().unwrap())));
     core_resource_thread.send(
-        CoreResourceMsg::Fetch(request, FetchChannels::ResponseMsg(action_sender, None))).unwrap();
+        CoreResourceMsg::Fetch(request, FetchChannels::ResponseMsg(action_sender, None, None))).unwrap();
 }
 
 #[derive(Clone, Deserialize, MallocSizeOf, Serialize)]
@@ -484,14 +547,15 @@ pub fn load_whole_resource(request: RequestInit,
                            -> Result<(Metadata, Vec<u8>), NetworkError> {
     let (action_sender, action_receiver) = ipc::channel().unwrap();
     core_resource_thread.send(
-        CoreResourceMsg::Fetch(request, FetchChannels::ResponseMsg(action_sender, None))).unwrap();
+        CoreResourceMsg::Fetch(request, FetchChannels::ResponseMsg(action_sender, None, None))).unwrap();
 
     let mut buf = vec![];
     let mut metadata = None;
     loop {
         match action_receiver.recv().unwrap() {
             FetchResponseMsg::ProcessRequestBody |
-            FetchResponseMsg::ProcessRequestEOF => (),
+            FetchResponseMsg::ProcessRequestEOF |
+            FetchResponseMsg::ProcessCspViolations(_) => (),
             FetchResponseMsg::ProcessResponse(Ok(m)) => {
                 metadata = Some(match m {
                     FetchMetadata::Unfiltered(m) => m,
@@ -3,11 +3,15 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use ReferrerPolicy;
+use csp::CspList;
 use hyper::header::Headers;
 use hyper::method::Method;
+use ipc_channel::ipc::{self, IpcReceiver, IpcSender};
 use msg::constellation_msg::PipelineId;
 use servo_url::{ImmutableOrigin, ServoUrl};
 use std::default::Default;
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
 
 /// An [initiator](https://fetch.spec.whatwg.org/#concept-request-initiator)
 #[derive(Clone, Copy, MallocSizeOf, PartialEq)]
@@ -135,8 +139,77 @@ pub enum CorsSettings {
     UseCredentials,
 }
 
+/// Identifies a fetch, so that it can be cancelled with `CoreResourceMsg::Cancel`.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct RequestId(pub Uuid);
+
+malloc_size_of_is_0!(RequestId);
+
+impl RequestId {
+    pub fn new() -> RequestId {
+        RequestId(Uuid::new_v4())
+    }
+}
+
+/// A chunk of a request body given as a stream, read in the content process.
+#[derive(Debug, Deserialize, Serialize)]
+pub enum BodyChunk {
+    /// The next bytes of the body.
+    Bytes(Vec<u8>),
+    /// The stream is closed, and the whole body has been read.
+    Done,
+    /// The stream errored, or one of its chunks wasn't bytes.
+    Error,
+}
+
+/// Where to read a request body given as a stream from. Its chunks are read one at a time,
+/// as they are written to the network.
+#[derive(Clone, Deserialize, MallocSizeOf, Serialize)]
+pub struct BodySource {
+    /// Each message asks for the next chunk, which is sent back on the channel it carries.
+    #[ignore_malloc_size_of = "Channels are hard"]
+    chunk_requester: IpcSender<IpcSender<BodyChunk>>,
+}
+
+impl BodySource {
+    pub fn new(chunk_requester: IpcSender<IpcSender<BodyChunk>>) -> BodySource {
+        BodySource {
+            chunk_requester: chunk_requester,
+        }
+    }
+
+    /// Reads the next chunk of the body, blocking until the content process has read it.
+    pub fn next_chunk(&self) -> BodyChunk {
+        let (sender, receiver) = match ipc::channel() {
+            Ok(channel) => channel,
+            Err(_) => return BodyChunk::Error,
+        };
+        if self.chunk_requester.send(sender).is_err() {
+            return BodyChunk::Error;
+        }
+        receiver.recv().unwrap_or(BodyChunk::Error)
+    }
+}
+
+/// Lets the consumer of a response body ask for its chunks when it is ready for them, so that
+/// the body isn't read from the network faster than it is consumed.
+#[derive(Clone)]
+pub struct BodyDemand(Arc<Mutex<IpcReceiver<()>>>);
+
+impl BodyDemand {
+    pub fn new(receiver: IpcReceiver<()>) -> BodyDemand {
+        BodyDemand(Arc::new(Mutex::new(receiver)))
+    }
+
+    /// Blocks until the consumer asks for another chunk. Returns false if it went away.
+    pub fn wait(&self) -> bool {
+        self.0.lock().unwrap().recv().is_ok()
+    }
+}
+
 #[derive(Clone, Deserialize, MallocSizeOf, Serialize)]
 pub struct RequestInit {
+    pub id: RequestId,
     #[serde(deserialize_with = "::hyper_serde::deserialize",
             serialize_with = "::hyper_serde::serialize")]
     #[ignore_malloc_size_of = "Defined in hyper"]
@@ -148,6 +221,8 @@ pub struct RequestInit {
     pub headers: Headers,
     pub unsafe_request: bool,
     pub body: Option<Vec<u8>>,
+    /// The source of a body given as a stream, which is sent in chunks as it is read.
+    pub body_source: Option<BodySource>,
     pub service_workers_mode: ServiceWorkersMode,
     // TODO: client object
     pub destination: Destination,
@@ -164,18 +239,25 @@ pub struct RequestInit {
     pub pipeline_id: Option<PipelineId>,
     pub redirect_mode: RedirectMode,
     pub integrity_metadata: String,
+    pub cryptographic_nonce_metadata: String,
     // to keep track of redirects
     pub url_list: Vec<ServoUrl>,
+    // XXXManishearth this should be part of the client object
+    pub csp_list: Option<CspList>,
+    /// The URL of the request's client, used to find the service worker controlling it.
+    pub client_url: Option<ServoUrl>,
 }
 
 impl Default for RequestInit {
     fn default() -> RequestInit {
         RequestInit {
+            id: RequestId::new(),
             method: Method::Get,
             url: ServoUrl::parse("about:blank").unwrap(),
             headers: Headers::new(),
             unsafe_request: false,
             body: None,
+            body_source: None,
             service_workers_mode: ServiceWorkersMode::All,
             destination: Destination::None,
             synchronous: false,
@@ -190,7 +272,10 @@ impl Default for RequestInit {
             pipeline_id: None,
             redirect_mode: RedirectMode::Follow,
             integrity_metadata: "".to_owned(),
+            cryptographic_nonce_metadata: "".to_owned(),
             url_list: vec![],
+            csp_list: None,
+            client_url: None,
         }
     }
 }
@@ -199,6 +284,8 @@ impl Default for RequestInit {
 /// the Fetch spec.
 #[derive(Clone, MallocSizeOf)]
 pub struct Request {
+    /// The id of the fetch of this request, which is kept by the requests made from it.
+    pub id: RequestId,
     /// <https://fetch.spec.whatwg.org/#concept-request-method>
     #[ignore_malloc_size_of = "Defined in hyper"]
     pub method: Method,
@@ -213,6 +300,11 @@ pub struct Request {
     pub unsafe_request: bool,
     /// <https://fetch.spec.whatwg.org/#concept-request-body>
     pub body: Option<Vec<u8>>,
+    /// The source of a body given as a stream, which can't be sent again on redirects.
+    pub body_source: Option<BodySource>,
+    /// Paces the reading of the response body to its consumer, if it asked for that.
+    #[ignore_malloc_size_of = "Channels are hard"]
+    pub body_demand: Option<BodyDemand>,
     // TODO: client object
     pub window: Window,
     // TODO: target browsing context
@@ -248,6 +340,8 @@ pub struct Request {
     pub redirect_mode: RedirectMode,
     /// <https://fetch.spec.whatwg.org/#concept-request-integrity-metadata>
     pub integrity_metadata: String,
+    /// <https://fetch.spec.whatwg.org/#concept-request-nonce-metadata>
+    pub cryptographic_nonce_metadata: String,
     // Use the last method on url_list to act as spec current url field, and
     // first method to act as spec url field
     /// <https://fetch.spec.whatwg.org/#concept-request-url-list>
@@ -256,6 +350,10 @@ pub struct Request {
     pub redirect_count: u32,
     /// <https://fetch.spec.whatwg.org/#concept-request-response-tainting>
     pub response_tainting: ResponseTainting,
+    /// The [CSP list](https://w3c.github.io/webappsec-csp/#csp-list) of the request's client.
+    pub csp_list: Option<CspList>,
+    /// The URL of the request's [client](https://fetch.spec.whatwg.org/#concept-request-client).
+    pub client_url: Option<ServoUrl>,
 }
 
 impl Request {
@@ -264,12 +362,15 @@ impl Request {
                pipeline_id: Option<PipelineId>)
                -> Request {
         Request {
+            id: RequestId::new(),
             method: Method::Get,
             local_urls_only: false,
             sandboxed_storage_area_urls: false,
             headers: Headers::new(),
             unsafe_request: false,
             body: None,
+            body_source: None,
+            body_demand: None,
             window: Window::Client,
             keep_alive: false,
             service_workers_mode: ServiceWorkersMode::All,
@@ -287,9 +388,12 @@ impl Request {
             cache_mode: CacheMode::Default,
             redirect_mode: RedirectMode::Follow,
             integrity_metadata: String::new(),
+            cryptographic_nonce_metadata: String::new(),
             url_list: vec![url],
             redirect_count: 0,
             response_tainting: ResponseTainting::Basic,
+            csp_list: None,
+            client_url: None,
         }
     }
 
@@ -297,10 +401,12 @@ impl Request {
         let mut req = Request::new(init.url.clone(),
                                    Some(Origin::Origin(init.origin)),
                                    init.pipeline_id);
+        req.id = init.id;
         req.method = init.method;
         req.headers = init.headers;
         req.unsafe_request = init.unsafe_request;
         req.body = init.body;
+        req.body_source = init.body_source;
         req.service_workers_mode = init.service_workers_mode;
         req.destination = init.destination;
         req.synchronous = init.synchronous;
@@ -324,6 +430,9 @@ impl Request {
         req.redirect_count = url_list.len() as u32 - 1;
         req.url_list = url_list;
         req.integrity_metadata = init.integrity_metadata;
+        req.cryptographic_nonce_metadata = init.cryptographic_nonce_metadata;
+        req.csp_list = init.csp_list;
+        req.client_url = init.client_url;
         req
     }
 
@@ -2,9 +2,11 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+extern crate ipc_channel;
 extern crate net_traits;
 
-use net_traits::image::base::detect_image_format;
+use ipc_channel::ipc::IpcSharedMemory;
+use net_traits::image::base::{Image, ImageFrame, PixelFormat, detect_image_format, is_avif, load_from_memory};
 
 #[test]
 fn test_supported_images() {
@@ -14,6 +16,9 @@ fn test_supported_images() {
     let png = [0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A];
     let bmp = [0x42, 0x4D];
     let ico = [0x00, 0x00, 0x01, 0x00];
+    let webp = [b'R', b'I', b'F', b'F', 0x1a, 0x00, 0x00, 0x00, b'W', b'E', b'B', b'P'];
+    let riff = [b'R', b'I', b'F', b'F', 0x1a, 0x00, 0x00, 0x00, b'W', b'A', b'V', b'E'];
+    let avif = [0x00, 0x00, 0x00, 0x1c, b'f', b't', b'y', b'p', b'a', b'v', b'i', b'f'];
     let junk_format = [0x01, 0x02, 0x03, 0x04, 0x05];
 
     assert!(detect_image_format(&gif1).is_ok());
@@ -22,5 +27,119 @@ fn test_supported_images() {
     assert!(detect_image_format(&png).is_ok());
     assert!(detect_image_format(&bmp).is_ok());
     assert!(detect_image_format(&ico).is_ok());
+    assert!(detect_image_format(&webp).is_ok());
+    assert!(detect_image_format(&riff).is_err());
+    assert!(detect_image_format(&avif).is_err());
     assert!(detect_image_format(&junk_format).is_err());
+
+    assert!(is_avif(&avif));
+    assert!(!is_avif(&png));
+    assert!(!is_avif(&webp));
+}
+
+#[test]
+fn test_animated_image_frames() {
+    let image = Image {
+        width: 1,
+        height: 1,
+        format: PixelFormat::BGRA8,
+        bytes: IpcSharedMemory::from_bytes(&[0, 0, 0, 255, 255, 255, 255, 255, 0, 0, 255, 255]),
+        id: None,
+        frames: vec![
+            ImageFrame { delay: 100, id: None },
+            ImageFrame { delay: 50, id: None },
+            ImageFrame { delay: 200, id: None },
+        ],
+        play_count: None,
+    };
+
+    assert!(image.is_animated());
+    assert_eq!(image.first_frame(), &[0, 0, 0, 255]);
+    assert_eq!(image.frame_bytes(2), &[0, 0, 255, 255]);
+    assert_eq!(image.frame_at(0), (0, Some(100)));
+    assert_eq!(image.frame_at(120), (1, Some(30)));
+    assert_eq!(image.frame_at(150), (2, Some(200)));
+    assert_eq!(image.frame_at(360), (0, Some(90)));
+
+    let image = Image { play_count: Some(2), ..image };
+    assert_eq!(image.frame_at(360), (0, Some(90)));
+    assert_eq!(image.frame_at(690), (2, Some(10)));
+    assert_eq!(image.frame_at(700), (2, None));
+    assert_eq!(image.frame_at(1000), (2, None));
+}
+
+#[test]
+fn test_gif_loop_count() {
+    let frame: &[u8] = b"\x21\xf9\x04\x00\x0a\x00\x00\x00\x2c\x00\x00\x00\x00\x01\x00\x01\x00\x00\x02\x01\x44\x00";
+    let gif = |extension: &[u8]| {
+        let mut gif = b"GIF89a\x01\x00\x01\x00\x80\x00\x00\x00\x00\x00\xff\xff\xff".to_vec();
+        gif.extend_from_slice(extension);
+        gif.extend_from_slice(frame);
+        gif.extend_from_slice(frame);
+        gif.push(0x3b);
+        gif
+    };
+
+    let image = load_from_memory(&gif(b"")).unwrap();
+    assert_eq!(image.frames.len(), 2);
+    assert_eq!(image.play_count, Some(1));
+    let image = load_from_memory(&gif(b"\x21\xff\x0bNETSCAPE2.0\x03\x01\x02\x00\x00")).unwrap();
+    assert_eq!(image.play_count, Some(3));
+    let image = load_from_memory(&gif(b"\x21\xff\x0bNETSCAPE2.0\x03\x01\x00\x00\x00")).unwrap();
+    assert_eq!(image.play_count, None);
+}
+
+#[test]
+fn test_apng_frames() {
+    // A red frame shown for 100ms, then a blue one shown for 200ms, twice.
+    let apng = b"\
+        \x89\x50\x4e\x47\x0d\x0a\x1a\x0a\x00\x00\x00\x0d\x49\x48\x44\x52\x00\x00\x00\x01\x00\x00\x00\x01\
+        \x08\x06\x00\x00\x00\x1f\x15\xc4\x89\x00\x00\x00\x08\x61\x63\x54\x4c\x00\x00\x00\x02\x00\x00\x00\
+        \x02\x1d\x83\xf2\x5c\x00\x00\x00\x1a\x66\x63\x54\x4c\x00\x00\x00\x00\x00\x00\x00\x01\x00\x00\x00\
+        \x01\x00\x00\x00\x00\x00\x00\x00\x00\x00\x01\x00\x0a\x00\x00\x5a\x7f\x30\xd0\x00\x00\x00\x0d\x49\
+        \x44\x41\x54\x78\x9c\x63\xf8\xcf\xc0\xf0\x1f\x00\x05\x00\x01\xff\x89\x99\x3d\x1d\x00\x00\x00\x1a\
+        \x66\x63\x54\x4c\x00\x00\x00\x01\x00\x00\x00\x01\x00\x00\x00\x01\x00\x00\x00\x00\x00\x00\x00\x00\
+        \x00\x01\x00\x05\x00\x00\xca\x50\x9d\x39\x00\x00\x00\x11\x66\x64\x41\x54\x00\x00\x00\x02\x78\x9c\
+        \x63\x60\x60\xf8\xff\x1f\x00\x03\x02\x01\xff\xf5\x7b\xa5\xd7\x00\x00\x00\x00\x49\x45\x4e\x44\xae\
+        \x42\x60\x82";
+
+    let image = load_from_memory(apng).unwrap();
+    assert_eq!((image.width, image.height), (1, 1));
+    assert_eq!(image.frames.iter().map(|frame| frame.delay).collect::<Vec<_>>(), vec![100, 200]);
+    assert_eq!(image.play_count, Some(2));
+    assert_eq!(image.frame_bytes(0), &[0, 0, 255, 255]);
+    assert_eq!(image.frame_bytes(1), &[255, 0, 0, 255]);
+}
+
+#[test]
+fn test_webp_colours_and_alpha() {
+    // A lossless 2x2 image with a red, a green, a blue and a transparent pixel.
+    let webp = b"\
+        \x52\x49\x46\x46\x1a\x00\x00\x00\x57\x45\x42\x50\x56\x50\x38\x4c\x0e\x00\x00\x00\x2f\x01\x40\x00\
+        \x10\x98\xff\xf9\x9f\xff\xf9\x0f\x4d\x06";
+
+    let image = load_from_memory(webp).unwrap();
+    assert_eq!((image.width, image.height), (2, 2));
+    assert_eq!(image.format, PixelFormat::BGRA8);
+    assert!(!image.is_animated());
+    assert_eq!(image.first_frame(), &[0, 0, 255, 255, 0, 255, 0, 255, 255, 0, 0, 255, 0, 0, 0, 0][..]);
+}
+
+#[test]
+fn test_animated_webp_frames() {
+    // A red frame shown for 100ms, then a blue one shown for 200ms, twice.
+    let webp = b"\
+        \x52\x49\x46\x46\x78\x00\x00\x00\x57\x45\x42\x50\x56\x50\x38\x58\x0a\x00\x00\x00\x12\x00\x00\x00\
+        \x00\x00\x00\x00\x00\x00\x41\x4e\x49\x4d\x06\x00\x00\x00\x00\x00\x00\x00\x02\x00\x41\x4e\x4d\x46\
+        \x22\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x64\x00\x00\x02\x56\x50\x38\x4c\
+        \x0a\x00\x00\x00\x2f\x00\x00\x00\x10\x88\xfe\x47\xff\x03\x41\x4e\x4d\x46\x22\x00\x00\x00\x00\x00\
+        \x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\xc8\x00\x00\x02\x56\x50\x38\x4c\x0a\x00\x00\x00\x2f\x00\
+        \x00\x00\x10\x88\xe8\x7f\xff\x03";
+
+    let image = load_from_memory(webp).unwrap();
+    assert_eq!((image.width, image.height), (1, 1));
+    assert_eq!(image.frames.iter().map(|frame| frame.delay).collect::<Vec<_>>(), vec![100, 200]);
+    assert_eq!(image.play_count, Some(2));
+    assert_eq!(image.frame_bytes(0), &[0, 0, 255, 255]);
+    assert_eq!(image.frame_bytes(1), &[255, 0, 0, 255]);
 }
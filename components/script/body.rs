@@ -3,15 +3,19 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use dom::bindings::codegen::Bindings::FormDataBinding::FormDataMethods;
+use dom::bindings::codegen::Bindings::ResponseBinding::BodyInit;
 use dom::bindings::error::{Error, Fallible};
 use dom::bindings::reflector::DomObject;
 use dom::bindings::root::DomRoot;
-use dom::bindings::str::USVString;
+use dom::bindings::str::{DOMString, USVString};
 use dom::bindings::trace::RootedTraceableBox;
 use dom::blob::{Blob, BlobImpl};
 use dom::formdata::FormData;
 use dom::globalscope::GlobalScope;
 use dom::promise::Promise;
+use dom::readablestream::{ReadAllBytesSteps, ReadableStream, read_all_bytes};
+use dom::readablestreamdefaultreader::ReadableStreamDefaultReader;
+use dom::xmlhttprequest::Extractable;
 use js::jsapi::Heap;
 use js::jsapi::JSContext;
 use js::jsapi::JSObject;
@@ -19,6 +23,7 @@ use js::jsapi::JS_ClearPendingException;
 use js::jsapi::Value as JSValue;
 use js::jsval::JSVal;
 use js::jsval::UndefinedValue;
+use js::rust::HandleValue;
 use js::rust::wrappers::JS_GetPendingException;
 use js::rust::wrappers::JS_ParseJSON;
 use js::typedarray::{ArrayBuffer, CreateWith};
@@ -47,10 +52,48 @@ pub enum FetchedData {
     JSException(RootedTraceableBox<Heap<JSVal>>)
 }
 
+/// A body extracted from a `BodyInit`.
+///
+/// <https://fetch.spec.whatwg.org/#concept-body>
+pub struct ExtractedBody {
+    pub stream: DomRoot<ReadableStream>,
+    /// The bytes of the body, unless it is a stream given by script.
+    pub source: Option<Vec<u8>>,
+    pub content_type: Option<DOMString>,
+}
+
+// https://fetch.spec.whatwg.org/#concept-bodyinit-extract
+pub fn extract_body(global: &GlobalScope, init: &BodyInit) -> Fallible<ExtractedBody> {
+    let (bytes, content_type) = match *init {
+        BodyInit::ReadableStream(ref stream) => {
+            if stream.is_disturbed() || stream.is_locked() {
+                return Err(Error::Type("The body's stream is disturbed or locked".to_string()));
+            }
+            return Ok(ExtractedBody {
+                stream: stream.clone(),
+                source: None,
+                content_type: None,
+            });
+        },
+        BodyInit::String(ref s) => s.extract(),
+        BodyInit::URLSearchParams(ref usp) => usp.extract(),
+        BodyInit::Blob(ref b) => b.extract(),
+        BodyInit::FormData(ref formdata) => formdata.extract(),
+        BodyInit::ArrayBuffer(ref typedarray) => (typedarray.to_vec(), None),
+        BodyInit::ArrayBufferView(ref typedarray) => (typedarray.to_vec(), None),
+    };
+    Ok(ExtractedBody {
+        stream: ReadableStream::new_from_bytes(global, bytes.clone()),
+        source: Some(bytes),
+        content_type: content_type,
+    })
+}
+
 // https://fetch.spec.whatwg.org/#concept-body-consume-body
 #[allow(unrooted_must_root)]
 pub fn consume_body<T: BodyOperations + DomObject>(object: &T, body_type: BodyType) -> Rc<Promise> {
-    let promise = Promise::new(&object.global());
+    let global = object.global();
+    let promise = Promise::new(&global);
 
     // Step 1
     if object.get_body_used() || object.is_locked() {
@@ -60,33 +103,63 @@ pub fn consume_body<T: BodyOperations + DomObject>(object: &T, body_type: BodyTy
         return promise;
     }
 
-    object.set_body_promise(&promise, body_type);
+    let mime_type = object.get_mime_type().clone();
+
+    // Step 2
+    let stream = match object.get_stream() {
+        Some(stream) => stream,
+        None => {
+            resolve_with_package_data(&global, &promise, vec![], body_type, &mime_type);
+            return promise;
+        },
+    };
 
-    // Steps 2-4
-    // TODO: Body does not yet have a stream.
+    // Step 3
+    let reader = match ReadableStreamDefaultReader::new(&global, &stream) {
+        Ok(reader) => reader,
+        Err(error) => {
+            promise.reject_error(error);
+            return promise;
+        },
+    };
 
-    consume_body_with_promise(object, body_type, &promise);
+    // Steps 4-5
+    read_all_bytes(&reader, Box::new(ConsumeBody {
+        promise: promise.clone(),
+        body_type: body_type,
+        mime_type: mime_type,
+    }));
 
     promise
 }
 
-// https://fetch.spec.whatwg.org/#concept-body-consume-body
-#[allow(unrooted_must_root)]
-pub fn consume_body_with_promise<T: BodyOperations + DomObject>(object: &T,
-                                                                body_type: BodyType,
-                                                                promise: &Promise) {
-    // Step 5
-    let body = match object.take_body() {
-        Some(body) => body,
-        None => return,
-    };
+/// Packages the bytes of a body once its stream has been read to the end.
+#[derive(JSTraceable, MallocSizeOf)]
+struct ConsumeBody {
+    #[ignore_malloc_size_of = "Rc"]
+    promise: Rc<Promise>,
+    body_type: BodyType,
+    mime_type: Vec<u8>,
+}
 
-    let pkg_data_results = run_package_data_algorithm(object,
-                                                      body,
-                                                      body_type,
-                                                      object.get_mime_type());
+impl ReadAllBytesSteps for ConsumeBody {
+    fn success_steps(self: Box<Self>, bytes: Vec<u8>) {
+        let global = self.promise.global();
+        resolve_with_package_data(&global, &self.promise, bytes, self.body_type, &self.mime_type);
+    }
 
-    match pkg_data_results {
+    #[allow(unsafe_code)]
+    fn failure_steps(self: Box<Self>, cx: *mut JSContext, error: HandleValue) {
+        unsafe { self.promise.reject(cx, error) };
+    }
+}
+
+fn resolve_with_package_data(global: &GlobalScope,
+                             promise: &Promise,
+                             bytes: Vec<u8>,
+                             body_type: BodyType,
+                             mime_type: &[u8]) {
+    match run_package_data_algorithm(global, bytes, body_type, mime_type) {
         Ok(results) => {
             match results {
                 FetchedData::Text(s) => promise.resolve_native(&USVString(s)),
@@ -103,19 +176,17 @@ pub fn consume_body_with_promise<T: BodyOperations + DomObject>(object: &T,
 
 // https://fetch.spec.whatwg.org/#concept-body-package-data
 #[allow(unsafe_code)]
-fn run_package_data_algorithm<T: BodyOperations + DomObject>(object: &T,
-                                                             bytes: Vec<u8>,
-                                                             body_type: BodyType,
-                                                             mime_type: Ref<Vec<u8>>)
-                                                             -> Fallible<FetchedData> {
-    let global = object.global();
+fn run_package_data_algorithm(global: &GlobalScope,
+                              bytes: Vec<u8>,
+                              body_type: BodyType,
+                              mime: &[u8])
+                              -> Fallible<FetchedData> {
     let cx = global.get_cx();
-    let mime = &*mime_type;
     match body_type {
         BodyType::Text => run_text_data_algorithm(bytes),
         BodyType::Json => run_json_data_algorithm(cx, bytes),
-        BodyType::Blob => run_blob_data_algorithm(&global, bytes, mime),
-        BodyType::FormData => run_form_data_algorithm(&global, bytes, mime),
+        BodyType::Blob => run_blob_data_algorithm(global, bytes, mime),
+        BodyType::FormData => run_form_data_algorithm(global, bytes, mime),
         BodyType::ArrayBuffer => unsafe {
             run_array_buffer_data_algorithm(cx, bytes)
         }
@@ -196,10 +267,8 @@ unsafe fn run_array_buffer_data_algorithm(cx: *mut JSContext, bytes: Vec<u8>) ->
 
 pub trait BodyOperations {
     fn get_body_used(&self) -> bool;
-    fn set_body_promise(&self, p: &Rc<Promise>, body_type: BodyType);
-    /// Returns `Some(_)` if the body is complete, `None` if there is more to
-    /// come.
-    fn take_body(&self) -> Option<Vec<u8>>;
     fn is_locked(&self) -> bool;
+    /// Returns the stream of the body, or `None` if the body is null.
+    fn get_stream(&self) -> Option<DomRoot<ReadableStream>>;
     fn get_mime_type(&self) -> Ref<Vec<u8>>;
 }
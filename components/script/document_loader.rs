@@ -139,7 +139,8 @@ impl DocumentLoader {
         let cancel_receiver = canceller.initialize();
         self.cancellers.push(canceller);
         self.resource_threads.sender().send(
-            CoreResourceMsg::Fetch(request, FetchChannels::ResponseMsg(fetch_target, Some(cancel_receiver)))).unwrap();
+            CoreResourceMsg::Fetch(request,
+                                   FetchChannels::ResponseMsg(fetch_target, Some(cancel_receiver), None))).unwrap();
     }
 
     /// Mark an in-progress network request complete.
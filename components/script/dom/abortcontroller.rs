@@ -0,0 +1,51 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::abortsignal::AbortSignal;
+use dom::bindings::codegen::Bindings::AbortControllerBinding::{self, AbortControllerMethods};
+use dom::bindings::error::Fallible;
+use dom::bindings::reflector::{Reflector, reflect_dom_object};
+use dom::bindings::root::{Dom, DomRoot};
+use dom::globalscope::GlobalScope;
+use dom_struct::dom_struct;
+
+/// <https://dom.spec.whatwg.org/#abortcontroller>
+#[dom_struct]
+pub struct AbortController {
+    reflector_: Reflector,
+    signal: Dom<AbortSignal>,
+}
+
+impl AbortController {
+    fn new_inherited(signal: &AbortSignal) -> AbortController {
+        AbortController {
+            reflector_: Reflector::new(),
+            signal: Dom::from_ref(signal),
+        }
+    }
+
+    pub fn new(global: &GlobalScope) -> DomRoot<AbortController> {
+        let signal = AbortSignal::new(global);
+        reflect_dom_object(Box::new(AbortController::new_inherited(&signal)),
+                           global,
+                           AbortControllerBinding::Wrap)
+    }
+
+    // https://dom.spec.whatwg.org/#dom-abortcontroller-abortcontroller
+    pub fn Constructor(global: &GlobalScope) -> Fallible<DomRoot<AbortController>> {
+        Ok(AbortController::new(global))
+    }
+}
+
+impl AbortControllerMethods for AbortController {
+    // https://dom.spec.whatwg.org/#dom-abortcontroller-signal
+    fn Signal(&self) -> DomRoot<AbortSignal> {
+        DomRoot::from_ref(&*self.signal)
+    }
+
+    // https://dom.spec.whatwg.org/#dom-abortcontroller-abort
+    fn Abort(&self) {
+        self.signal.signal_abort();
+    }
+}
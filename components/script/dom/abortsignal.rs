@@ -0,0 +1,117 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::cell::DomRefCell;
+use dom::bindings::codegen::Bindings::AbortSignalBinding::{self, AbortSignalMethods};
+use dom::bindings::inheritance::Castable;
+use dom::bindings::reflector::reflect_dom_object;
+use dom::bindings::root::{Dom, DomRoot};
+use dom::bindings::trace::JSTraceable;
+use dom::eventtarget::EventTarget;
+use dom::globalscope::GlobalScope;
+use dom_struct::dom_struct;
+use malloc_size_of::MallocSizeOf;
+use std::cell::Cell;
+use std::mem;
+
+/// Steps that run when a signal is aborted, such as cancelling the fetch it was given to.
+///
+/// <https://dom.spec.whatwg.org/#abortsignal-abort-algorithms>
+pub trait AbortAlgorithm: JSTraceable + MallocSizeOf {
+    fn abort(self: Box<Self>);
+}
+
+/// <https://dom.spec.whatwg.org/#abortsignal>
+#[dom_struct]
+pub struct AbortSignal {
+    eventtarget: EventTarget,
+    /// <https://dom.spec.whatwg.org/#abortsignal-aborted-flag>
+    aborted: Cell<bool>,
+    /// <https://dom.spec.whatwg.org/#abortsignal-abort-algorithms>
+    algorithms: DomRefCell<Vec<Box<AbortAlgorithm>>>,
+}
+
+impl AbortSignal {
+    fn new_inherited() -> AbortSignal {
+        AbortSignal {
+            eventtarget: EventTarget::new_inherited(),
+            aborted: Cell::new(false),
+            algorithms: DomRefCell::new(vec![]),
+        }
+    }
+
+    pub fn new(global: &GlobalScope) -> DomRoot<AbortSignal> {
+        reflect_dom_object(Box::new(AbortSignal::new_inherited()), global, AbortSignalBinding::Wrap)
+    }
+
+    /// Runs `algorithm` when the signal is aborted, unless it already is.
+    ///
+    /// <https://dom.spec.whatwg.org/#abortsignal-add>
+    pub fn add_algorithm(&self, algorithm: Box<AbortAlgorithm>) {
+        if self.aborted.get() {
+            return;
+        }
+        self.algorithms.borrow_mut().push(algorithm);
+    }
+
+    /// <https://dom.spec.whatwg.org/#abortsignal-signal-abort>
+    pub fn signal_abort(&self) {
+        // Step 1
+        if self.aborted.get() {
+            return;
+        }
+
+        // Step 2
+        self.aborted.set(true);
+
+        // Step 3-4
+        let algorithms = mem::replace(&mut *self.algorithms.borrow_mut(), vec![]);
+        for algorithm in algorithms {
+            algorithm.abort();
+        }
+
+        // Step 5
+        self.upcast::<EventTarget>().fire_event(atom!("abort"));
+    }
+
+    /// Makes this signal abort when `parent` does.
+    ///
+    /// <https://dom.spec.whatwg.org/#abortsignal-follow>
+    #[allow(unrooted_must_root)]
+    pub fn follow(&self, parent: &AbortSignal) {
+        // Step 1
+        if self.aborted.get() {
+            return;
+        }
+
+        // Step 2
+        if parent.aborted.get() {
+            return self.signal_abort();
+        }
+
+        // Step 3
+        parent.add_algorithm(Box::new(FollowingSignal(Dom::from_ref(self))));
+    }
+}
+
+impl AbortSignalMethods for AbortSignal {
+    // https://dom.spec.whatwg.org/#dom-abortsignal-aborted
+    fn Aborted(&self) -> bool {
+        self.aborted.get()
+    }
+
+    // https://dom.spec.whatwg.org/#dom-abortsignal-onabort
+    event_handler!(abort, GetOnabort, SetOnabort);
+}
+
+/// Aborts a signal that follows another one.
+#[derive(JSTraceable, MallocSizeOf)]
+#[must_root]
+struct FollowingSignal(Dom<AbortSignal>);
+
+impl AbortAlgorithm for FollowingSignal {
+    fn abort(self: Box<Self>) {
+        self.0.signal_abort();
+    }
+}
@@ -6,13 +6,14 @@ use dom::bindings::refcounted::Trusted;
 use dom::bindings::reflector::DomObject;
 use dom::bindings::structuredclone::StructuredCloneData;
 use script_runtime::CommonScriptMsg;
+use script_traits::TransferredPort;
 
 /// Messages used to control the worker event loops
 pub enum WorkerScriptMsg {
     /// Common variants associated with the script messages
     Common(CommonScriptMsg),
-    /// Message sent through Worker.postMessage
-    DOMMessage(StructuredCloneData)
+    /// Message sent through Worker.postMessage, along with the ports transferred with it
+    DOMMessage(StructuredCloneData, Vec<TransferredPort>)
 }
 
 pub struct SimpleWorkerErrorHandler<T: DomObject> {
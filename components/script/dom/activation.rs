@@ -96,6 +96,7 @@ pub fn synthetic_click_activation(element: &Element,
                                 None,
                                 None);
     let event = mouse.upcast::<Event>();
+    event.set_composed(true);
     if source == ActivationSource::FromClick {
         event.set_trusted(false);
     }
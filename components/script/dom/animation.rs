@@ -0,0 +1,745 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::animationeffect::AnimationEffect;
+use dom::animationplaybackevent::AnimationPlaybackEvent;
+use dom::animationtimeline::AnimationTimeline;
+use dom::bindings::cell::DomRefCell;
+use dom::bindings::codegen::Bindings::AnimationBinding::{self, AnimationMethods, AnimationPlayState};
+use dom::bindings::codegen::Bindings::AnimationPlaybackEventBinding::AnimationPlaybackEventInit;
+use dom::bindings::codegen::Bindings::DocumentBinding::DocumentMethods;
+use dom::bindings::codegen::Bindings::KeyframeEffectBinding::KeyframeEffectMethods;
+use dom::bindings::codegen::Bindings::WindowBinding::WindowBinding::WindowMethods;
+use dom::bindings::error::{Error, ErrorResult, Fallible};
+use dom::bindings::inheritance::Castable;
+use dom::bindings::num::Finite;
+use dom::bindings::refcounted::Trusted;
+use dom::bindings::reflector::{DomObject, reflect_dom_object};
+use dom::bindings::root::{DomRoot, MutNullableDom};
+use dom::bindings::str::DOMString;
+use dom::cssanimation::CSSAnimation;
+use dom::element::Element;
+use dom::event::Event;
+use dom::eventtarget::EventTarget;
+use dom::globalscope::GlobalScope;
+use dom::keyframeeffect::KeyframeEffect;
+use dom::node::{Node, NodeDamage};
+use dom::promise::Promise;
+use dom::window::Window;
+use dom_struct::dom_struct;
+use script_layout_interface::message::Msg;
+use servo_atoms::Atom;
+use std::cell::Cell;
+use std::f64;
+use std::rc::Rc;
+use style::animation::ScriptAnimationState;
+use task_source::TaskSource;
+
+/// <https://drafts.csswg.org/web-animations/#the-animation-interface>
+///
+/// Animations are played as soon as they are asked to, so they never have a
+/// pending play or pause task, and their ready promise is always resolved.
+#[dom_struct]
+pub struct Animation {
+    eventtarget: EventTarget,
+    /// Identifies this animation to layout, unique in its document.
+    animation_id: usize,
+    /// <https://drafts.csswg.org/web-animations/#dom-animation-id>
+    id: DomRefCell<DOMString>,
+    /// <https://drafts.csswg.org/web-animations/#animation-associated-effect>
+    effect: MutNullableDom<AnimationEffect>,
+    /// <https://drafts.csswg.org/web-animations/#animation-timeline>
+    timeline: MutNullableDom<AnimationTimeline>,
+    /// <https://drafts.csswg.org/web-animations/#animation-start-time>
+    start_time: Cell<Option<f64>>,
+    /// <https://drafts.csswg.org/web-animations/#animation-hold-time>
+    hold_time: Cell<Option<f64>>,
+    /// <https://drafts.csswg.org/web-animations/#previous-current-time>
+    previous_current_time: Cell<Option<f64>>,
+    /// <https://drafts.csswg.org/web-animations/#playback-rate>
+    playback_rate: Cell<f64>,
+    /// <https://drafts.csswg.org/web-animations/#current-ready-promise>
+    #[ignore_malloc_size_of = "promises are hard"]
+    ready_promise: Rc<Promise>,
+    /// <https://drafts.csswg.org/web-animations/#current-finished-promise>
+    #[ignore_malloc_size_of = "promises are hard"]
+    finished_promise: DomRefCell<Rc<Promise>>,
+    /// The element layout runs this animation on, if any.
+    layout_target: MutNullableDom<Element>,
+}
+
+impl Animation {
+    #[allow(unrooted_must_root)]
+    pub fn new_inherited(global: &GlobalScope, timeline: Option<&AnimationTimeline>) -> Animation {
+        Animation {
+            eventtarget: EventTarget::new_inherited(),
+            animation_id: global.as_window().Document().next_animation_id(),
+            id: DomRefCell::new(DOMString::new()),
+            effect: Default::default(),
+            timeline: MutNullableDom::new(timeline),
+            start_time: Cell::new(None),
+            hold_time: Cell::new(None),
+            previous_current_time: Cell::new(None),
+            playback_rate: Cell::new(1.),
+            ready_promise: Promise::new(global),
+            finished_promise: DomRefCell::new(Promise::new(global)),
+            layout_target: Default::default(),
+        }
+    }
+
+    pub fn new(
+        window: &Window,
+        effect: Option<&AnimationEffect>,
+        timeline: Option<&AnimationTimeline>,
+    ) -> DomRoot<Animation> {
+        let animation = reflect_dom_object(
+            Box::new(Animation::new_inherited(window.upcast(), timeline)),
+            window,
+            AnimationBinding::Wrap,
+        );
+        animation.init(effect);
+        animation
+    }
+
+    /// Sets up an animation once it has been reflected.
+    pub fn init(&self, effect: Option<&AnimationEffect>) {
+        self.ready_promise.resolve_native(&DomRoot::from_ref(self));
+        self.set_effect(effect);
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-animation-animation
+    pub fn Constructor(
+        window: &Window,
+        effect: Option<&AnimationEffect>,
+        timeline: Option<Option<&AnimationTimeline>>,
+    ) -> Fallible<DomRoot<Animation>> {
+        let document_timeline = window.Document().Timeline();
+        let timeline = timeline.unwrap_or(Some(document_timeline.upcast()));
+        Ok(Animation::new(window, effect, timeline))
+    }
+
+    /// <https://drafts.csswg.org/web-animations/#setting-the-associated-effect>
+    fn set_effect(&self, effect: Option<&AnimationEffect>) {
+        if let Some(effect) = effect {
+            if let Some(animation) = effect.animation() {
+                animation.set_effect(None);
+            }
+            effect.set_animation(Some(self));
+        }
+        if let Some(old_effect) = self.effect.get() {
+            old_effect.set_animation(None);
+        }
+        self.effect.set(effect);
+        self.update_finished_state(false);
+        self.state_changed();
+    }
+
+    pub fn effect(&self) -> Option<DomRoot<AnimationEffect>> {
+        self.effect.get()
+    }
+
+    pub fn playback_rate(&self) -> f64 {
+        self.playback_rate.get()
+    }
+
+    /// Called when the timing or the keyframes of the effect of this animation
+    /// change.
+    pub fn effect_changed(&self) {
+        self.update_finished_state(false);
+        self.state_changed();
+    }
+
+    /// Whether this animation is relevant, i.e. whether `getAnimations()`
+    /// returns it.
+    ///
+    /// <https://drafts.csswg.org/web-animations/#relevant-animation>
+    pub fn is_relevant(&self) -> bool {
+        self.effect.get().map_or(false, |effect| effect.is_relevant())
+    }
+
+    /// Returns the target of the keyframe effect of this animation, if any.
+    pub fn target(&self) -> Option<DomRoot<Element>> {
+        self.effect
+            .get()
+            .and_then(DomRoot::downcast::<KeyframeEffect>)
+            .and_then(|effect| effect.GetTarget())
+    }
+
+    fn end_time(&self) -> f64 {
+        self.effect.get().map_or(0., |effect| effect.timing().end_time())
+    }
+
+    /// <https://drafts.csswg.org/web-animations/#the-current-time-of-an-animation>
+    pub fn current_time(&self) -> Option<f64> {
+        self.hold_time.get().or_else(|| self.current_time_ignoring_hold_time())
+    }
+
+    fn current_time_ignoring_hold_time(&self) -> Option<f64> {
+        let timeline = self.timeline.get()?;
+        let start_time = self.start_time.get()?;
+        Some((timeline.current_time() - start_time) * self.playback_rate.get())
+    }
+
+    /// <https://drafts.csswg.org/web-animations/#play-states>
+    pub fn play_state(&self) -> AnimationPlayState {
+        // Step 1.
+        let current_time = self.current_time();
+        let start_time = self.start_time.get();
+        if current_time.is_none() && start_time.is_none() {
+            return AnimationPlayState::Idle;
+        }
+
+        // Step 2.
+        if start_time.is_none() {
+            return AnimationPlayState::Paused;
+        }
+
+        // Step 3.
+        let playback_rate = self.playback_rate.get();
+        if let Some(current_time) = current_time {
+            if (playback_rate > 0. && current_time >= self.end_time()) ||
+                (playback_rate < 0. && current_time <= 0.)
+            {
+                return AnimationPlayState::Finished;
+            }
+        }
+
+        // Step 4.
+        AnimationPlayState::Running
+    }
+
+    /// Sets the times of this animation from the ones layout runs it with,
+    /// for CSS animations.
+    pub fn set_times_from_layout(&self, current_time: f64, paused: bool) {
+        let timeline_time = self.timeline.get().map(|timeline| timeline.current_time());
+        match timeline_time {
+            Some(timeline_time) if !paused => {
+                self.start_time.set(Some(timeline_time - current_time / self.playback_rate.get()));
+                self.hold_time.set(None);
+            },
+            _ => {
+                self.start_time.set(None);
+                self.hold_time.set(Some(current_time));
+            },
+        }
+        self.previous_current_time.set(Some(current_time));
+    }
+
+    /// <https://drafts.csswg.org/web-animations/#silently-set-the-current-time>
+    fn silently_set_current_time(&self, seek_time: Option<f64>) -> ErrorResult {
+        // Step 1.
+        let seek_time = match seek_time {
+            Some(seek_time) => seek_time,
+            None if self.current_time().is_some() => {
+                return Err(Error::Type("The current time can't be unresolved".to_owned()));
+            },
+            None => return Ok(()),
+        };
+
+        // Step 2.
+        let timeline_time = self.timeline.get().map(|timeline| timeline.current_time());
+        let playback_rate = self.playback_rate.get();
+        match (self.hold_time.get(), self.start_time.get(), timeline_time) {
+            (None, Some(_), Some(timeline_time)) if playback_rate != 0. => {
+                self.start_time.set(Some(timeline_time - seek_time / playback_rate));
+            },
+            _ => self.hold_time.set(Some(seek_time)),
+        }
+
+        // Step 3.
+        if timeline_time.is_none() {
+            self.start_time.set(None);
+        }
+
+        // Step 4.
+        self.previous_current_time.set(None);
+        Ok(())
+    }
+
+    /// <https://drafts.csswg.org/web-animations/#setting-the-current-time-of-an-animation>
+    fn set_current_time(&self, seek_time: Option<f64>) -> ErrorResult {
+        // Step 1.
+        self.silently_set_current_time(seek_time)?;
+
+        // Step 2 doesn't apply, as there are no pending pause tasks.
+
+        // Step 3.
+        self.update_finished_state(true);
+        self.state_changed();
+        Ok(())
+    }
+
+    /// <https://drafts.csswg.org/web-animations/#set-the-playback-rate>
+    fn set_playback_rate(&self, playback_rate: f64) {
+        // Step 1.
+        let previous_time = self.current_time();
+
+        // Step 2.
+        self.playback_rate.set(playback_rate);
+
+        // Step 3.
+        if previous_time.is_some() {
+            let _ = self.set_current_time(previous_time);
+        } else {
+            self.state_changed();
+        }
+    }
+
+    /// <https://drafts.csswg.org/web-animations/#update-an-animations-finished-state>
+    fn update_finished_state(&self, did_seek: bool) {
+        // Step 1.
+        let unconstrained_current_time = if did_seek {
+            self.current_time()
+        } else {
+            self.current_time_ignoring_hold_time()
+        };
+
+        // Step 2.
+        if let (Some(current_time), Some(_)) = (unconstrained_current_time, self.start_time.get()) {
+            let playback_rate = self.playback_rate.get();
+            let end_time = self.end_time();
+            let previous_current_time = self.previous_current_time.get();
+            if playback_rate > 0. && current_time >= end_time {
+                self.hold_time.set(Some(if did_seek {
+                    current_time
+                } else {
+                    previous_current_time.map_or(end_time, |previous| previous.max(end_time))
+                }));
+            } else if playback_rate < 0. && current_time <= 0. {
+                self.hold_time.set(Some(if did_seek {
+                    current_time
+                } else {
+                    previous_current_time.map_or(0., |previous| previous.min(0.))
+                }));
+            } else if let Some(timeline) = self.timeline.get() {
+                if playback_rate != 0. {
+                    if let (true, Some(hold_time)) = (did_seek, self.hold_time.get()) {
+                        self.start_time.set(Some(timeline.current_time() - hold_time / playback_rate));
+                    }
+                    self.hold_time.set(None);
+                }
+            }
+        }
+
+        // Step 3.
+        self.previous_current_time.set(self.current_time());
+
+        // Step 4.
+        let current_finished_state = self.play_state() == AnimationPlayState::Finished;
+
+        // Step 5. The finish notification steps always run synchronously,
+        // rather than in a microtask, as the finish event is queued anyway.
+        if current_finished_state && !self.finished_promise.borrow().is_fulfilled() {
+            self.finish_notification_steps();
+        }
+
+        // Step 6.
+        if !current_finished_state && self.finished_promise.borrow().is_fulfilled() {
+            *self.finished_promise.borrow_mut() = Promise::new(&self.global());
+        }
+    }
+
+    /// <https://drafts.csswg.org/web-animations/#finish-notification-steps>
+    fn finish_notification_steps(&self) {
+        // Step 1.
+        if self.play_state() != AnimationPlayState::Finished {
+            return;
+        }
+
+        // Step 2.
+        self.finished_promise.borrow().resolve_native(&DomRoot::from_ref(self));
+
+        // Step 3.
+        self.queue_playback_event(atom!("finish"), self.current_time());
+    }
+
+    /// Queues a task to fire an `AnimationPlaybackEvent` at this animation.
+    fn queue_playback_event(&self, type_: Atom, current_time: Option<f64>) {
+        let global = self.global();
+        let window = global.as_window();
+        let timeline_time = self.timeline.get().map(|timeline| timeline.current_time());
+        let this = Trusted::new(self);
+        let _ = window.dom_manipulation_task_source().queue(
+            task!(fire_animation_playback_event: move || {
+                let this = this.root();
+                let global = this.global();
+                let mut init = AnimationPlaybackEventInit::empty();
+                init.currentTime = current_time.and_then(Finite::new);
+                init.timelineTime = timeline_time.and_then(Finite::new);
+                let event = AnimationPlaybackEvent::new(global.as_window(), type_, &init);
+                event.upcast::<Event>().fire(this.upcast());
+            }),
+            window.upcast(),
+        );
+    }
+
+    /// <https://drafts.csswg.org/web-animations/#playing-an-animation-section>
+    fn play(&self, auto_rewind: bool) -> ErrorResult {
+        // Steps 1-3, as there are no pending tasks.
+        let playback_rate = self.playback_rate.get();
+        let end_time = self.end_time();
+        let current_time = self.current_time();
+
+        // Step 4.
+        let seek_time = if playback_rate > 0. &&
+            auto_rewind &&
+            current_time.map_or(true, |time| time < 0. || time >= end_time)
+        {
+            Some(0.)
+        } else if playback_rate < 0. &&
+            auto_rewind &&
+            current_time.map_or(true, |time| time <= 0. || time > end_time)
+        {
+            if end_time == f64::INFINITY {
+                return Err(Error::InvalidState);
+            }
+            Some(end_time)
+        } else if playback_rate == 0. && current_time.is_none() {
+            Some(0.)
+        } else {
+            None
+        };
+
+        // Step 5.
+        if seek_time.is_some() {
+            self.hold_time.set(seek_time);
+        }
+
+        // Step 6.
+        if self.hold_time.get().is_some() {
+            self.start_time.set(None);
+        }
+
+        // Steps 7-11. Instead of scheduling a pending play task, the
+        // animation starts playing right away.
+        if let (Some(hold_time), Some(timeline)) = (self.hold_time.get(), self.timeline.get()) {
+            let ready_time = timeline.current_time();
+            if playback_rate == 0. {
+                self.start_time.set(Some(ready_time));
+            } else {
+                self.start_time.set(Some(ready_time - hold_time / playback_rate));
+                self.hold_time.set(None);
+            }
+        }
+
+        // Step 12.
+        self.update_finished_state(false);
+        self.state_changed();
+        Ok(())
+    }
+
+    /// Lets layout and the document know that the state of this animation
+    /// changed.
+    fn state_changed(&self) {
+        if let Some(css_animation) = self.downcast::<CSSAnimation>() {
+            return css_animation.set_paused_in_layout(self.start_time.get().is_none());
+        }
+
+        self.update_layout();
+        if self.play_state() != AnimationPlayState::Idle {
+            self.global().as_window().Document().register_animation(self);
+        }
+    }
+
+    /// Updates the finished state of this animation as the time of its
+    /// timeline advances, returning whether it's still running.
+    pub fn tick(&self) -> bool {
+        if self.play_state() != AnimationPlayState::Running {
+            return false;
+        }
+        self.update_finished_state(false);
+        if self.play_state() == AnimationPlayState::Finished {
+            self.update_layout();
+            return false;
+        }
+        true
+    }
+
+    /// Starts running this animation in layout, updates it, or stops running
+    /// it if it doesn't have any effect anymore.
+    fn update_layout(&self) {
+        let global = self.global();
+        let window = global.as_window();
+        let effect = self.effect.get().and_then(DomRoot::downcast::<KeyframeEffect>);
+        let target = self.target();
+        let previous_target = self.layout_target.take();
+        if let Some(ref previous_target) = previous_target {
+            if target.as_ref() != Some(previous_target) {
+                self.remove_from_layout(window, previous_target);
+            }
+        }
+
+        let (effect, target) = match (effect, target) {
+            (Some(effect), Some(target)) => (effect, target),
+            _ => return,
+        };
+
+        // Finished animations which don't fill forwards don't have any effect
+        // anymore.
+        let keyframes = effect.keyframes_animation();
+        let in_effect = match self.play_state() {
+            AnimationPlayState::Idle => false,
+            AnimationPlayState::Finished => {
+                effect.upcast::<AnimationEffect>().computed_timing().active_time.is_some()
+            },
+            AnimationPlayState::Running | AnimationPlayState::Paused => true,
+        };
+        if !in_effect || keyframes.steps.is_empty() {
+            if previous_target.as_ref() == Some(&target) {
+                self.remove_from_layout(window, &target);
+            }
+            return;
+        }
+
+        let timeline = self.timeline.get();
+        let state = ScriptAnimationState {
+            id: self.animation_id,
+            timing: effect.upcast::<AnimationEffect>().timing(),
+            start_time: self.start_time.get().and_then(|start_time| {
+                timeline.map(|timeline| timeline.to_layout_time(start_time))
+            }),
+            hold_time: self.hold_time.get(),
+            playback_rate: self.playback_rate.get(),
+            expired: false,
+            cascade_style: None,
+        };
+        let node = target.upcast::<Node>();
+        window
+            .layout_chan()
+            .send(Msg::UpdateScriptAnimation(node.to_opaque(), keyframes, state))
+            .unwrap();
+        node.dirty(NodeDamage::OtherNodeDamage);
+        self.layout_target.set(Some(&target));
+    }
+
+    fn remove_from_layout(&self, window: &Window, target: &Element) {
+        let node = target.upcast::<Node>();
+        window
+            .layout_chan()
+            .send(Msg::RemoveScriptAnimation(node.to_opaque(), self.animation_id))
+            .unwrap();
+        node.dirty(NodeDamage::OtherNodeDamage);
+    }
+}
+
+impl AnimationMethods for Animation {
+    // https://drafts.csswg.org/web-animations/#dom-animation-id
+    fn Id(&self) -> DOMString {
+        self.id.borrow().clone()
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-animation-id
+    fn SetId(&self, id: DOMString) {
+        *self.id.borrow_mut() = id;
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-animation-effect
+    fn GetEffect(&self) -> Option<DomRoot<AnimationEffect>> {
+        self.effect.get()
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-animation-timeline
+    fn GetTimeline(&self) -> Option<DomRoot<AnimationTimeline>> {
+        self.timeline.get()
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-animation-starttime
+    fn GetStartTime(&self) -> Option<Finite<f64>> {
+        self.start_time.get().and_then(Finite::new)
+    }
+
+    // https://drafts.csswg.org/web-animations/#set-the-start-time
+    fn SetStartTime(&self, start_time: Option<Finite<f64>>) {
+        let new_start_time = start_time.map(|start_time| *start_time);
+
+        // Step 1.
+        let timeline_time = self.timeline.get().map(|timeline| timeline.current_time());
+
+        // Step 2.
+        if timeline_time.is_none() && new_start_time.is_some() {
+            self.hold_time.set(None);
+        }
+
+        // Step 3.
+        let previous_current_time = self.current_time();
+
+        // Step 4 doesn't apply, as there are no pending tasks.
+
+        // Step 5.
+        self.start_time.set(new_start_time);
+
+        // Step 6.
+        if new_start_time.is_some() {
+            if self.playback_rate.get() != 0. {
+                self.hold_time.set(None);
+            }
+        } else {
+            self.hold_time.set(previous_current_time);
+        }
+
+        // Steps 7-8.
+        self.update_finished_state(true);
+        self.state_changed();
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-animation-currenttime
+    fn GetCurrentTime(&self) -> Option<Finite<f64>> {
+        self.current_time().and_then(Finite::new)
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-animation-currenttime
+    fn SetCurrentTime(&self, current_time: Option<Finite<f64>>) -> ErrorResult {
+        self.set_current_time(current_time.map(|current_time| *current_time))
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-animation-playbackrate
+    fn PlaybackRate(&self) -> Finite<f64> {
+        Finite::wrap(self.playback_rate.get())
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-animation-playbackrate
+    fn SetPlaybackRate(&self, playback_rate: Finite<f64>) {
+        self.set_playback_rate(*playback_rate);
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-animation-playstate
+    fn PlayState(&self) -> AnimationPlayState {
+        self.play_state()
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-animation-pending
+    fn Pending(&self) -> bool {
+        false
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-animation-ready
+    fn Ready(&self) -> Rc<Promise> {
+        self.ready_promise.clone()
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-animation-finished
+    fn Finished(&self) -> Rc<Promise> {
+        self.finished_promise.borrow().clone()
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-animation-onfinish
+    event_handler!(finish, GetOnfinish, SetOnfinish);
+
+    // https://drafts.csswg.org/web-animations/#dom-animation-oncancel
+    event_handler!(cancel, GetOncancel, SetOncancel);
+
+    // https://drafts.csswg.org/web-animations/#cancel-an-animation
+    fn Cancel(&self) {
+        if self.play_state() != AnimationPlayState::Idle {
+            // Step 1 doesn't apply, as there are no pending tasks.
+
+            // Step 2.
+            if !self.finished_promise.borrow().is_fulfilled() {
+                self.finished_promise.borrow().reject_error(Error::Abort);
+            }
+
+            // Step 3.
+            *self.finished_promise.borrow_mut() = Promise::new(&self.global());
+
+            // Step 4.
+            self.queue_playback_event(atom!("cancel"), None);
+        }
+
+        // Steps 5-6.
+        self.hold_time.set(None);
+        self.start_time.set(None);
+        self.state_changed();
+    }
+
+    // https://drafts.csswg.org/web-animations/#finish-an-animation
+    fn Finish(&self) -> ErrorResult {
+        // Step 1.
+        let playback_rate = self.playback_rate.get();
+        let end_time = self.end_time();
+        if playback_rate == 0. || (playback_rate > 0. && end_time == f64::INFINITY) {
+            return Err(Error::InvalidState);
+        }
+
+        // Steps 2-3.
+        let limit = if playback_rate > 0. { end_time } else { 0. };
+        self.silently_set_current_time(Some(limit))?;
+
+        // Step 4.
+        if self.start_time.get().is_none() {
+            if let Some(timeline) = self.timeline.get() {
+                self.start_time.set(Some(timeline.current_time() - limit / playback_rate));
+            }
+        }
+
+        // Steps 5-6 don't apply, as there are no pending tasks.
+
+        // Step 7.
+        self.update_finished_state(true);
+        self.state_changed();
+        Ok(())
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-animation-play
+    fn Play(&self) -> ErrorResult {
+        self.play(true)
+    }
+
+    // https://drafts.csswg.org/web-animations/#pause-an-animation
+    fn Pause(&self) -> ErrorResult {
+        // Steps 1-3.
+        let current_time = self.current_time();
+        let seek_time = if current_time.is_some() {
+            None
+        } else if self.playback_rate.get() >= 0. {
+            Some(0.)
+        } else {
+            let end_time = self.end_time();
+            if end_time == f64::INFINITY {
+                return Err(Error::InvalidState);
+            }
+            Some(end_time)
+        };
+
+        // Step 4.
+        if seek_time.is_some() {
+            self.hold_time.set(seek_time);
+        }
+
+        // Steps 5-10. Instead of scheduling a pending pause task, the
+        // animation is paused right away.
+        if self.hold_time.get().is_none() {
+            self.hold_time.set(current_time);
+        }
+        self.start_time.set(None);
+        self.update_finished_state(false);
+        self.state_changed();
+        Ok(())
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-animation-updateplaybackrate
+    fn UpdatePlaybackRate(&self, playback_rate: Finite<f64>) {
+        // There are no pending tasks, so the playback rate is updated right
+        // away.
+        self.set_playback_rate(*playback_rate);
+    }
+
+    // https://drafts.csswg.org/web-animations/#reverse-an-animation
+    fn Reverse(&self) -> ErrorResult {
+        // Step 1.
+        if self.timeline.get().is_none() {
+            return Err(Error::InvalidState);
+        }
+
+        // Steps 2-3.
+        let original_playback_rate = self.playback_rate.get();
+        self.set_playback_rate(-original_playback_rate);
+
+        // Steps 4-5.
+        let result = self.play(true);
+        if result.is_err() {
+            self.set_playback_rate(original_playback_rate);
+        }
+        result
+    }
+}
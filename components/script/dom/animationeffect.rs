@@ -0,0 +1,311 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use cssparser::{Parser, ParserInput};
+use dom::animation::Animation;
+use dom::bindings::cell::DomRefCell;
+use dom::bindings::codegen::Bindings::AnimationEffectBinding::{AnimationEffectMethods, ComputedEffectTiming};
+use dom::bindings::codegen::Bindings::AnimationEffectBinding::{FillMode, OptionalEffectTiming, PlaybackDirection};
+use dom::bindings::codegen::Bindings::AnimationEffectBinding::EffectTiming as EffectTimingDictionary;
+use dom::bindings::codegen::Bindings::WindowBinding::WindowBinding::WindowMethods;
+use dom::bindings::codegen::UnionTypes::UnrestrictedDoubleOrString;
+use dom::bindings::error::{Error, ErrorResult, Fallible};
+use dom::bindings::num::Finite;
+use dom::bindings::reflector::{DomObject, Reflector};
+use dom::bindings::root::{DomRoot, MutNullableDom};
+use dom::bindings::str::DOMString;
+use dom::window::Window;
+use dom_struct::dom_struct;
+use euclid::{TypedScale, TypedSize2D};
+use std::cell::Cell;
+use style::animation::{self, ComputedTiming, EffectTiming};
+use style::context::QuirksMode;
+use style::media_queries::{Device, MediaType};
+use style::parser::{Parse, ParserContext};
+use style::stylesheets::CssRuleType;
+use style::values::computed::{Context, ToComputedValue};
+use style::values::computed::transform::TimingFunction;
+use style::values::specified::transform::TimingFunction as SpecifiedTimingFunction;
+use style_traits::{ParsingMode, ToCss};
+
+/// <https://drafts.csswg.org/web-animations/#the-animationeffect-interface>
+#[dom_struct]
+pub struct AnimationEffect {
+    reflector_: Reflector,
+    /// <https://drafts.csswg.org/web-animations/#the-effecttiming-dictionaries>
+    #[ignore_malloc_size_of = "Defined in style"]
+    timing: DomRefCell<EffectTiming>,
+    /// Whether the iteration duration is `auto`, which is zero for keyframe
+    /// effects.
+    auto_duration: Cell<bool>,
+    /// The serialization of the timing function of the effect.
+    easing: DomRefCell<DOMString>,
+    /// <https://drafts.csswg.org/web-animations/#associated-animation-of-an-animation-effect>
+    animation: MutNullableDom<Animation>,
+}
+
+impl AnimationEffect {
+    pub fn new_inherited() -> AnimationEffect {
+        AnimationEffect {
+            reflector_: Reflector::new(),
+            timing: DomRefCell::new(EffectTiming::default()),
+            auto_duration: Cell::new(true),
+            easing: DomRefCell::new(DOMString::from("linear")),
+            animation: Default::default(),
+        }
+    }
+
+    pub fn timing(&self) -> EffectTiming {
+        self.timing.borrow().clone()
+    }
+
+    /// Replaces the timing of this effect, as when a CSS animation changes,
+    /// without notifying its animation.
+    pub fn set_timing(&self, timing: EffectTiming) {
+        self.auto_duration.set(false);
+        *self.easing.borrow_mut() = DOMString::from(timing.easing.to_css_string());
+        *self.timing.borrow_mut() = timing;
+    }
+
+    pub fn animation(&self) -> Option<DomRoot<Animation>> {
+        self.animation.get()
+    }
+
+    pub fn set_animation(&self, animation: Option<&Animation>) {
+        self.animation.set(animation);
+    }
+
+    /// <https://drafts.csswg.org/web-animations/#local-time>
+    pub fn local_time(&self) -> Option<f64> {
+        self.animation.get().and_then(|animation| animation.current_time())
+    }
+
+    pub fn computed_timing(&self) -> ComputedTiming {
+        let playback_rate = self.animation.get().map_or(1., |animation| animation.playback_rate());
+        self.timing.borrow().computed_timing(self.local_time(), playback_rate)
+    }
+
+    /// Whether this effect is current or in effect, which makes the animation
+    /// it is associated with relevant.
+    ///
+    /// <https://drafts.csswg.org/web-animations/#current>
+    pub fn is_relevant(&self) -> bool {
+        let playback_rate = self.animation.get().map_or(1., |animation| animation.playback_rate());
+        let timing = self.computed_timing();
+        match timing.phase {
+            animation::AnimationPhase::Idle => false,
+            animation::AnimationPhase::Active => true,
+            animation::AnimationPhase::Before => playback_rate > 0. || timing.active_time.is_some(),
+            animation::AnimationPhase::After => playback_rate < 0. || timing.active_time.is_some(),
+        }
+    }
+
+    /// <https://drafts.csswg.org/web-animations/#update-the-timing-properties-of-an-animation-effect>
+    pub fn update_timing(&self, input: &OptionalEffectTiming) -> ErrorResult {
+        // Step 1.
+        if input.iterationStart.map_or(false, |iteration_start| *iteration_start < 0.) {
+            return Err(Error::Type("iterationStart must not be negative".to_owned()));
+        }
+        if input.iterations.map_or(false, |iterations| iterations < 0. || iterations.is_nan()) {
+            return Err(Error::Type("iterations must not be negative".to_owned()));
+        }
+        let duration = match input.duration {
+            Some(UnrestrictedDoubleOrString::UnrestrictedDouble(duration)) => {
+                if duration < 0. || duration.is_nan() {
+                    return Err(Error::Type("duration must not be negative".to_owned()));
+                }
+                Some(Some(duration))
+            },
+            Some(UnrestrictedDoubleOrString::String(ref duration)) if &**duration == "auto" => Some(None),
+            Some(UnrestrictedDoubleOrString::String(_)) => {
+                return Err(Error::Type("duration must be a number or 'auto'".to_owned()));
+            },
+            None => None,
+        };
+
+        // Step 2.
+        let easing = match input.easing {
+            Some(ref easing) => Some(parse_easing(&self.global().as_window(), easing)?),
+            None => None,
+        };
+
+        // Step 3.
+        {
+            let mut timing = self.timing.borrow_mut();
+            if let Some(delay) = input.delay {
+                timing.delay = *delay;
+            }
+            if let Some(end_delay) = input.endDelay {
+                timing.end_delay = *end_delay;
+            }
+            if let Some(fill) = input.fill {
+                timing.fill = fill_mode_to_style(fill);
+            }
+            if let Some(iteration_start) = input.iterationStart {
+                timing.iteration_start = *iteration_start;
+            }
+            if let Some(iterations) = input.iterations {
+                timing.iterations = iterations;
+            }
+            if let Some(duration) = duration {
+                self.auto_duration.set(duration.is_none());
+                timing.duration = duration.unwrap_or(0.);
+            }
+            if let Some(direction) = input.direction {
+                timing.direction = direction_to_style(direction);
+            }
+            if let Some((timing_function, serialization)) = easing {
+                timing.easing = timing_function;
+                *self.easing.borrow_mut() = serialization;
+            }
+        }
+
+        if let Some(animation) = self.animation.get() {
+            animation.effect_changed();
+        }
+        Ok(())
+    }
+}
+
+impl AnimationEffectMethods for AnimationEffect {
+    // https://drafts.csswg.org/web-animations/#dom-animationeffect-gettiming
+    fn GetTiming(&self) -> EffectTimingDictionary {
+        let timing = self.timing.borrow();
+        let duration = if self.auto_duration.get() {
+            UnrestrictedDoubleOrString::String(DOMString::from("auto"))
+        } else {
+            UnrestrictedDoubleOrString::UnrestrictedDouble(timing.duration)
+        };
+        EffectTimingDictionary {
+            delay: Finite::wrap(timing.delay),
+            endDelay: Finite::wrap(timing.end_delay),
+            fill: fill_mode_from_style(timing.fill),
+            iterationStart: Finite::wrap(timing.iteration_start),
+            iterations: timing.iterations,
+            duration: Some(duration),
+            direction: direction_from_style(timing.direction),
+            easing: self.easing.borrow().clone(),
+        }
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-animationeffect-getcomputedtiming
+    fn GetComputedTiming(&self) -> ComputedEffectTiming {
+        let mut parent = self.GetTiming();
+        let timing = self.timing.borrow();
+        // The computed duration and fill mode of keyframe effects.
+        parent.duration = Some(UnrestrictedDoubleOrString::UnrestrictedDouble(timing.duration));
+        if parent.fill == FillMode::Auto {
+            parent.fill = FillMode::None;
+        }
+        let computed_timing = self.computed_timing();
+        ComputedEffectTiming {
+            parent: parent,
+            endTime: Some(timing.end_time()),
+            activeDuration: Some(timing.active_duration()),
+            localTime: Some(self.local_time().map(Finite::wrap)),
+            progress: Some(computed_timing.progress.map(Finite::wrap)),
+            currentIteration: Some(computed_timing.current_iteration),
+        }
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-animationeffect-updatetiming
+    fn UpdateTiming(&self, timing: &OptionalEffectTiming) -> ErrorResult {
+        self.update_timing(timing)
+    }
+}
+
+/// Converts the timing properties passed when creating an effect to an update
+/// of its timing.
+pub fn optional_effect_timing(timing: &EffectTimingDictionary) -> OptionalEffectTiming {
+    let duration = match timing.duration {
+        Some(UnrestrictedDoubleOrString::UnrestrictedDouble(duration)) => {
+            UnrestrictedDoubleOrString::UnrestrictedDouble(duration)
+        },
+        Some(UnrestrictedDoubleOrString::String(ref duration)) => {
+            UnrestrictedDoubleOrString::String(duration.clone())
+        },
+        None => UnrestrictedDoubleOrString::String(DOMString::from("auto")),
+    };
+    OptionalEffectTiming {
+        delay: Some(timing.delay),
+        endDelay: Some(timing.endDelay),
+        fill: Some(timing.fill),
+        iterationStart: Some(timing.iterationStart),
+        iterations: Some(timing.iterations),
+        duration: Some(duration),
+        direction: Some(timing.direction),
+        easing: Some(timing.easing.clone()),
+    }
+}
+
+/// Parses a timing function as given to the Web Animations API, returning its
+/// computed value and its serialization.
+///
+/// <https://drafts.csswg.org/web-animations/#dom-effecttiming-easing>
+pub fn parse_easing(window: &Window, easing: &str) -> Fallible<(TimingFunction, DOMString)> {
+    let document = window.Document();
+    let url = document.url();
+    let context = ParserContext::new_for_cssom(
+        &url,
+        Some(CssRuleType::Style),
+        ParsingMode::DEFAULT,
+        QuirksMode::NoQuirks,
+        None,
+        None,
+    );
+    let mut input = ParserInput::new(easing);
+    let mut parser = Parser::new(&mut input);
+    let timing_function = match parser.parse_entirely(|input| SpecifiedTimingFunction::parse(&context, input)) {
+        Ok(timing_function) => timing_function,
+        Err(_) => return Err(Error::Type(format!("'{}' is not a valid easing", easing))),
+    };
+
+    // The computed value of a timing function doesn't depend on the viewport,
+    // so any device will do if the window has no size yet.
+    let device = document.device().unwrap_or_else(|| {
+        Device::new(MediaType::screen(), TypedSize2D::zero(), TypedScale::new(1.0))
+    });
+    let computed = Context::for_media_query_evaluation(&device, document.quirks_mode(), |context| {
+        timing_function.to_computed_value(context)
+    });
+    Ok((computed, DOMString::from(timing_function.to_css_string())))
+}
+
+fn fill_mode_to_style(fill: FillMode) -> animation::FillMode {
+    match fill {
+        FillMode::None => animation::FillMode::None,
+        FillMode::Forwards => animation::FillMode::Forwards,
+        FillMode::Backwards => animation::FillMode::Backwards,
+        FillMode::Both => animation::FillMode::Both,
+        FillMode::Auto => animation::FillMode::Auto,
+    }
+}
+
+fn fill_mode_from_style(fill: animation::FillMode) -> FillMode {
+    match fill {
+        animation::FillMode::None => FillMode::None,
+        animation::FillMode::Forwards => FillMode::Forwards,
+        animation::FillMode::Backwards => FillMode::Backwards,
+        animation::FillMode::Both => FillMode::Both,
+        animation::FillMode::Auto => FillMode::Auto,
+    }
+}
+
+fn direction_to_style(direction: PlaybackDirection) -> animation::PlaybackDirection {
+    match direction {
+        PlaybackDirection::Normal => animation::PlaybackDirection::Normal,
+        PlaybackDirection::Reverse => animation::PlaybackDirection::Reverse,
+        PlaybackDirection::Alternate => animation::PlaybackDirection::Alternate,
+        PlaybackDirection::Alternate_reverse => animation::PlaybackDirection::AlternateReverse,
+    }
+}
+
+fn direction_from_style(direction: animation::PlaybackDirection) -> PlaybackDirection {
+    match direction {
+        animation::PlaybackDirection::Normal => PlaybackDirection::Normal,
+        animation::PlaybackDirection::Reverse => PlaybackDirection::Reverse,
+        animation::PlaybackDirection::Alternate => PlaybackDirection::Alternate,
+        animation::PlaybackDirection::AlternateReverse => PlaybackDirection::Alternate_reverse,
+    }
+}
@@ -0,0 +1,49 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::AnimationTimelineBinding::AnimationTimelineMethods;
+use dom::bindings::codegen::Bindings::PerformanceBinding::PerformanceMethods;
+use dom::bindings::num::Finite;
+use dom::bindings::reflector::{DomObject, Reflector};
+use dom_struct::dom_struct;
+
+/// <https://drafts.csswg.org/web-animations/#the-animationtimeline-interface>
+#[dom_struct]
+pub struct AnimationTimeline {
+    reflector_: Reflector,
+    /// The time, relative to the time origin of the global, that this timeline
+    /// starts at, in milliseconds.
+    origin_time: f64,
+}
+
+impl AnimationTimeline {
+    pub fn new_inherited(origin_time: f64) -> AnimationTimeline {
+        AnimationTimeline {
+            reflector_: Reflector::new(),
+            origin_time: origin_time,
+        }
+    }
+
+    /// Returns the current time of this timeline in milliseconds. Document
+    /// timelines are always active, so this is always resolved.
+    ///
+    /// <https://drafts.csswg.org/web-animations/#timeline-current-time>
+    pub fn current_time(&self) -> f64 {
+        *self.global().performance().Now() - self.origin_time
+    }
+
+    /// Converts a time value of this timeline to the time of the timer layout
+    /// animates with, which is in seconds.
+    pub fn to_layout_time(&self, time: f64) -> f64 {
+        let navigation_start = self.global().as_window().get_navigation_start() as f64;
+        (navigation_start + (self.origin_time + time) * 1_000_000.) / 1_000_000_000.
+    }
+}
+
+impl AnimationTimelineMethods for AnimationTimeline {
+    // https://drafts.csswg.org/web-animations/#dom-animationtimeline-currenttime
+    fn GetCurrentTime(&self) -> Option<Finite<f64>> {
+        Finite::new(self.current_time())
+    }
+}
@@ -315,7 +315,8 @@ impl DOMString {
     /// https://html.spec.whatwg.org/multipage/#best-representation-of-the-number-as-a-floating-point-number
     pub fn set_best_representation_of_the_floating_point_number(&mut self) {
         if let Ok(val) = parse_floating_point_number(&self.0) {
-            self.0 = val.to_string();
+            // TODO(#19773): need consider `min`, `max`, `step`, when they are implemented
+            self.0 = val.round().to_string();
         }
     }
 
@@ -458,7 +459,7 @@ impl Extend<char> for DOMString {
 }
 
 /// https://html.spec.whatwg.org/multipage/#parse-a-month-string
-fn parse_month_string(value: &str) -> Result<(u32, u32), ()> {
+pub fn parse_month_string(value: &str) -> Result<(u32, u32), ()> {
     // Step 1, 2, 3
     let (year_int, month_int) = parse_month_component(value)?;
 
@@ -471,7 +472,7 @@ fn parse_month_string(value: &str) -> Result<(u32, u32), ()> {
 }
 
 /// https://html.spec.whatwg.org/multipage/#parse-a-date-string
-fn parse_date_string(value: &str) -> Result<(u32, u32, u32), ()> {
+pub fn parse_date_string(value: &str) -> Result<(u32, u32, u32), ()> {
     // Step 1, 2, 3
     let (year_int, month_int, day_int) = parse_date_component(value)?;
 
@@ -485,7 +486,7 @@ fn parse_date_string(value: &str) -> Result<(u32, u32, u32), ()> {
 }
 
 /// https://html.spec.whatwg.org/multipage/#parse-a-week-string
-fn parse_week_string(value: &str) -> Result<(u32, u32), ()> {
+pub fn parse_week_string(value: &str) -> Result<(u32, u32), ()> {
     // Step 1, 2, 3
     let mut iterator = value.split('-');
     let year = iterator.next().ok_or(())?;
@@ -623,8 +624,17 @@ fn parse_time_component(value: &str) -> Result<(u32, u32, f32), ()> {
     Ok((hour_int, minute_int, second_float))
 }
 
+/// https://html.spec.whatwg.org/multipage/#parse-a-time-string
+pub fn parse_time_string(value: &str) -> Result<(u32, u32, f32), ()> {
+    // Step 1, 2, 3, 4
+    if !DOMString::from(value).is_valid_time_string() {
+        return Err(());
+    }
+    parse_time_component(value)
+}
+
 /// https://html.spec.whatwg.org/multipage/#parse-a-local-date-and-time-string
-fn parse_local_date_and_time_string(value: &str) ->  Result<((u32, u32, u32), (u32, u32, f32)), ()> {
+pub fn parse_local_date_and_time_string(value: &str) ->  Result<((u32, u32, u32), (u32, u32, f32)), ()> {
     // Step 1, 2, 4
     let mut iterator = if value.contains('T') {
         value.split('T')
@@ -679,15 +689,14 @@ fn is_leap_year(year: u32) -> bool {
 }
 
 /// https://html.spec.whatwg.org/multipage/#rules-for-parsing-floating-point-number-values
-fn parse_floating_point_number(input: &str) -> Result<f64, ()> {
+pub fn parse_floating_point_number(input: &str) -> Result<f64, ()> {
     match input.trim().parse::<f64>() {
         Ok(val) if !(
             // A valid number is the same as what rust considers to be valid,
             // except for +1., NaN, and Infinity.
             val.is_infinite() || val.is_nan() || input.ends_with(".") || input.starts_with("+")
         ) => {
-            // TODO(#19773): need consider `min`, `max`, `step`, when they are implemented
-            Ok(val.round())
+            Ok(val)
         },
         _ => Err(())
     }
@@ -5,12 +5,13 @@
 //! This module implements structured cloning, as defined by [HTML]
 //! (https://html.spec.whatwg.org/multipage/#safe-passing-of-structured-data).
 
-use dom::bindings::conversions::root_from_handleobject;
+use dom::bindings::conversions::{ToJSValConvertible, root_from_handleobject};
 use dom::bindings::error::{Error, Fallible};
 use dom::bindings::reflector::DomObject;
 use dom::bindings::root::DomRoot;
 use dom::blob::{Blob, BlobImpl};
 use dom::globalscope::GlobalScope;
+use dom::messageport::MessagePort;
 use js::glue::CopyJSStructuredCloneData;
 use js::glue::DeleteJSAutoStructuredCloneBuffer;
 use js::glue::GetLengthOfJSStructuredCloneData;
@@ -27,9 +28,11 @@ use js::jsapi::JS_STRUCTURED_CLONE_VERSION;
 use js::jsapi::MutableHandleObject as RawMutableHandleObject;
 use js::jsapi::StructuredCloneScope;
 use js::jsapi::TransferableOwnership;
-use js::rust::{Handle, HandleValue, MutableHandleValue};
+use js::jsval::UndefinedValue;
+use js::rust::{Handle, HandleValue, MutableHandle, MutableHandleValue};
 use js::rust::wrappers::{JS_WriteStructuredClone, JS_ReadStructuredClone};
 use libc::size_t;
+use script_traits::TransferredPort;
 use std::os::raw;
 use std::ptr;
 use std::slice;
@@ -43,6 +46,7 @@ enum StructuredCloneTags {
     /// To support additional types, add new tags with values incremented from the last one before Max.
     Min = 0xFFFF8000,
     DomBlob = 0xFFFF8001,
+    DomMessagePort = 0xFFFF8002,
     Max = 0xFFFFFFFF,
 }
 
@@ -160,25 +164,49 @@ unsafe extern "C" fn write_callback(_cx: *mut JSContext,
     return false
 }
 
-unsafe extern "C" fn read_transfer_callback(_cx: *mut JSContext,
+unsafe extern "C" fn read_transfer_callback(cx: *mut JSContext,
                                             _r: *mut JSStructuredCloneReader,
-                                            _tag: u32,
+                                            tag: u32,
                                             _content: *mut raw::c_void,
-                                            _extra_data: u64,
-                                            _closure: *mut raw::c_void,
-                                            _return_object: RawMutableHandleObject)
+                                            extra_data: u64,
+                                            closure: *mut raw::c_void,
+                                            return_object: RawMutableHandleObject)
                                             -> bool {
+    if tag == StructuredCloneTags::DomMessagePort as u32 {
+        let sc_holder = &mut *(closure as *mut StructuredCloneHolder);
+        let transferred = match sc_holder.transferred_ports.get_mut(extra_data as usize).and_then(Option::take) {
+            Some(transferred) => transferred,
+            None => return false,
+        };
+        let target_global = GlobalScope::from_context(cx);
+        let port = MessagePort::new_transferred(&target_global, transferred);
+        MutableHandle::from_raw(return_object).set(port.reflector().get_jsobject().get());
+        sc_holder.ports.push(port);
+        return true
+    }
     false
 }
 
 unsafe extern "C" fn write_transfer_callback(_cx: *mut JSContext,
-                                             _obj: RawHandleObject,
-                                             _closure: *mut raw::c_void,
-                                             _tag: *mut u32,
-                                             _ownership: *mut TransferableOwnership,
-                                             _content:  *mut *mut raw::c_void,
-                                             _extra_data: *mut u64)
+                                             obj: RawHandleObject,
+                                             closure: *mut raw::c_void,
+                                             tag: *mut u32,
+                                             ownership: *mut TransferableOwnership,
+                                             content:  *mut *mut raw::c_void,
+                                             extra_data: *mut u64)
                                              -> bool {
+    if let Ok(port) = root_from_handleobject::<MessagePort>(Handle::from_raw(obj)) {
+        if port.detached() {
+            return false
+        }
+        let transfer_holder = &mut *(closure as *mut TransferHolder);
+        *tag = StructuredCloneTags::DomMessagePort as u32;
+        *ownership = TransferableOwnership::SCTAG_TMO_CUSTOM;
+        *content = ptr::null_mut();
+        *extra_data = transfer_holder.ports.len() as u64;
+        transfer_holder.ports.push(port);
+        return true
+    }
     false
 }
 
@@ -202,7 +230,16 @@ static STRUCTURED_CLONE_CALLBACKS: JSStructuredCloneCallbacks = JSStructuredClon
 };
 
 struct StructuredCloneHolder {
-    blob: Option<DomRoot<Blob>>
+    blob: Option<DomRoot<Blob>>,
+    /// The ports transferred along with the clone, taken out as they get revived.
+    transferred_ports: Vec<Option<TransferredPort>>,
+    /// The ports revived while reading the clone, in transfer order.
+    ports: Vec<DomRoot<MessagePort>>,
+}
+
+/// The objects transferred along with a structured clone being written.
+struct TransferHolder {
+    ports: Vec<DomRoot<MessagePort>>,
 }
 
 /// A buffer for a structured clone.
@@ -218,35 +255,67 @@ impl StructuredCloneData {
     /// Writes a structured clone. Returns a `DataClone` error if that fails.
     pub fn write(cx: *mut JSContext, message: HandleValue) -> Fallible<StructuredCloneData> {
         unsafe {
-            let scbuf = NewJSAutoStructuredCloneBuffer(StructuredCloneScope::DifferentProcess,
-                                                       &STRUCTURED_CLONE_CALLBACKS);
-            let scdata = &mut ((*scbuf).data_);
-            let policy = CloneDataPolicy {
-                // TODO: SAB?
-                sharedArrayBuffer_: false,
-            };
-            let result = JS_WriteStructuredClone(cx,
-                                                 message,
-                                                 scdata,
-                                                 StructuredCloneScope::DifferentProcess,
-                                                 policy,
-                                                 &STRUCTURED_CLONE_CALLBACKS,
-                                                 ptr::null_mut(),
-                                                 HandleValue::undefined());
-            if !result {
-                JS_ClearPendingException(cx);
-                return Err(Error::DataClone);
+            StructuredCloneData::write_clone(cx, message, HandleValue::undefined(), ptr::null_mut())
+        }
+    }
+
+    /// Writes a structured clone, transferring the given ports along with it.
+    /// The ports are detached once the clone succeeded.
+    /// Returns a `DataClone` error if that fails.
+    ///
+    /// <https://html.spec.whatwg.org/multipage/#structuredserializewithtransfer>
+    pub fn write_with_transfer(cx: *mut JSContext,
+                               message: HandleValue,
+                               transfer: Vec<DomRoot<MessagePort>>)
+                               -> Fallible<(StructuredCloneData, Vec<TransferredPort>)> {
+        let mut transfer_holder = TransferHolder { ports: vec![] };
+        let data = unsafe {
+            rooted!(in(cx) let mut transfer_list = UndefinedValue());
+            if !transfer.is_empty() {
+                transfer.to_jsval(cx, transfer_list.handle_mut());
             }
+            StructuredCloneData::write_clone(cx,
+                                             message,
+                                             transfer_list.handle(),
+                                             &mut transfer_holder as *mut _ as *mut raw::c_void)?
+        };
+        let ports = transfer_holder.ports.iter().map(|port| port.transfer()).collect();
+        Ok((data, ports))
+    }
+
+    unsafe fn write_clone(cx: *mut JSContext,
+                          message: HandleValue,
+                          transfer: HandleValue,
+                          closure: *mut raw::c_void)
+                          -> Fallible<StructuredCloneData> {
+        let scbuf = NewJSAutoStructuredCloneBuffer(StructuredCloneScope::DifferentProcess,
+                                                   &STRUCTURED_CLONE_CALLBACKS);
+        let scdata = &mut ((*scbuf).data_);
+        let policy = CloneDataPolicy {
+            // TODO: SAB?
+            sharedArrayBuffer_: false,
+        };
+        let result = JS_WriteStructuredClone(cx,
+                                             message,
+                                             scdata,
+                                             StructuredCloneScope::DifferentProcess,
+                                             policy,
+                                             &STRUCTURED_CLONE_CALLBACKS,
+                                             closure,
+                                             transfer);
+        if !result {
+            JS_ClearPendingException(cx);
+            return Err(Error::DataClone);
+        }
 
-            let nbytes = GetLengthOfJSStructuredCloneData(scdata);
-            let mut data = Vec::with_capacity(nbytes);
-            CopyJSStructuredCloneData(scdata, data.as_mut_ptr());
-            data.set_len(nbytes);
+        let nbytes = GetLengthOfJSStructuredCloneData(scdata);
+        let mut data = Vec::with_capacity(nbytes);
+        CopyJSStructuredCloneData(scdata, data.as_mut_ptr());
+        data.set_len(nbytes);
 
-            DeleteJSAutoStructuredCloneBuffer(scbuf);
+        DeleteJSAutoStructuredCloneBuffer(scbuf);
 
-            Ok(StructuredCloneData::Vector(data))
-        }
+        Ok(StructuredCloneData::Vector(data))
     }
 
     /// Converts a StructuredCloneData to Vec<u8> for inter-thread sharing
@@ -261,17 +330,23 @@ impl StructuredCloneData {
         }
     }
 
-    /// Reads a structured clone.
+    /// Reads a structured clone, returning the ports that were transferred with it.
     ///
     /// Panics if `JS_ReadStructuredClone` fails.
     fn read_clone(global: &GlobalScope,
                   data: *mut u64,
                   nbytes: size_t,
-                  rval: MutableHandleValue) {
+                  ports: Vec<TransferredPort>,
+                  rval: MutableHandleValue)
+                  -> Vec<DomRoot<MessagePort>> {
         let cx = global.get_cx();
         let globalhandle = global.reflector().get_jsobject();
         let _ac = JSAutoCompartment::new(cx, globalhandle.get());
-        let mut sc_holder = StructuredCloneHolder { blob: None };
+        let mut sc_holder = StructuredCloneHolder {
+            blob: None,
+            transferred_ports: ports.into_iter().map(Some).collect(),
+            ports: vec![],
+        };
         let sc_holder_ptr = &mut sc_holder as *mut _;
         unsafe {
             let scbuf = NewJSAutoStructuredCloneBuffer(StructuredCloneScope::DifferentProcess,
@@ -290,17 +365,31 @@ impl StructuredCloneData {
 
             DeleteJSAutoStructuredCloneBuffer(scbuf);
         }
+        sc_holder.ports
     }
 
     /// Thunk for the actual `read_clone` method. Resolves proper variant for read_clone.
     pub fn read(self, global: &GlobalScope, rval: MutableHandleValue) {
+        self.read_with_transfer(global, vec![], rval);
+    }
+
+    /// Reads a structured clone, reviving the ports transferred with it in the given global.
+    ///
+    /// <https://html.spec.whatwg.org/multipage/#structureddeserializewithtransfer>
+    pub fn read_with_transfer(self,
+                              global: &GlobalScope,
+                              ports: Vec<TransferredPort>,
+                              rval: MutableHandleValue)
+                              -> Vec<DomRoot<MessagePort>> {
         match self {
             StructuredCloneData::Vector(mut vec_msg) => {
                 let nbytes = vec_msg.len();
                 let data = vec_msg.as_mut_ptr() as *mut u64;
-                StructuredCloneData::read_clone(global, data, nbytes, rval);
+                StructuredCloneData::read_clone(global, data, nbytes, ports, rval)
+            }
+            StructuredCloneData::Struct(data, nbytes) => {
+                StructuredCloneData::read_clone(global, data, nbytes, ports, rval)
             }
-            StructuredCloneData::Struct(data, nbytes) => StructuredCloneData::read_clone(global, data, nbytes, rval)
         }
     }
 }
@@ -0,0 +1,519 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::cell::DomRefCell;
+use dom::bindings::codegen::Bindings::CacheBinding;
+use dom::bindings::codegen::Bindings::CacheBinding::{CacheMethods, CacheQueryOptions};
+use dom::bindings::codegen::Bindings::RequestBinding::{RequestInfo, RequestInit, RequestMethods};
+use dom::bindings::codegen::Bindings::ResponseBinding::{ResponseMethods, ResponseType};
+use dom::bindings::conversions::root_from_handlevalue;
+use dom::bindings::error::{Error, Fallible};
+use dom::bindings::reflector::{DomObject, Reflector, reflect_dom_object};
+use dom::bindings::root::{Dom, DomRoot};
+use dom::globalscope::GlobalScope;
+use dom::headers::Guard;
+use dom::promise::Promise;
+use dom::promisenativehandler::Callback;
+use dom::readablestream::{ReadAllBytesSteps, ReadableStream};
+use dom::request::Request;
+use dom::response::Response;
+use dom::streams::upon_settlement;
+use dom_struct::dom_struct;
+use fetch::Fetch;
+use hyper::header::Headers as HyperHeaders;
+use hyper::method::Method as HttpMethod;
+use hyper_serde::Serde;
+use ipc_channel::ipc;
+use js::jsapi::JSContext;
+use js::rust::HandleValue;
+use net_traits::IpcSend;
+use net_traits::cache_storage_thread::{CacheEntry, CacheStorageError, CacheStorageThreadMsg};
+use net_traits::cache_storage_thread::{CachedRequest, CachedResponse};
+use net_traits::cache_storage_thread::CacheQueryOptions as QueryOptions;
+use net_traits::request::Request as NetTraitsRequest;
+use servo_url::ServoUrl;
+use std::cell::Cell;
+use std::rc::Rc;
+use std::str::FromStr;
+
+// https://w3c.github.io/ServiceWorker/#cache-interface
+#[dom_struct]
+pub struct Cache {
+    reflector_: Reflector,
+    /// The name of the cache in the cache storage of the origin.
+    name: String,
+}
+
+impl Cache {
+    fn new_inherited(name: String) -> Cache {
+        Cache {
+            reflector_: Reflector::new(),
+            name: name,
+        }
+    }
+
+    pub fn new(global: &GlobalScope, name: String) -> DomRoot<Cache> {
+        reflect_dom_object(Box::new(Cache::new_inherited(name)), global, CacheBinding::Wrap)
+    }
+
+    /// Queries the entries of this cache whose request matches `request`.
+    fn query(&self, request: Option<RequestInfo>, options: &CacheQueryOptions) -> Fallible<Vec<CacheEntry>> {
+        match_entries(&self.global(), Some(self.name.clone()), request, options)
+    }
+
+    /// Fetches or reads the responses to `requests`, and stores them in this cache once they
+    /// all succeeded.
+    #[allow(unrooted_must_root)]
+    fn put_all(&self, requests: Vec<(CachedRequest, ResponseSource)>) -> Rc<Promise> {
+        let global = self.global();
+        if let Err(error) = origin_url(&global) {
+            return rejected(&global, error);
+        }
+        let promise = Promise::new(&global);
+        let mut cached_requests = vec![];
+        let mut sources = vec![];
+        for (request, source) in requests {
+            cached_requests.push(request);
+            sources.push(source);
+        }
+        let put = Rc::new(PendingPut {
+            cache: Dom::from_ref(self),
+            promise: promise.clone(),
+            responses: DomRefCell::new(vec![None; cached_requests.len()]),
+            requests: cached_requests,
+            remaining: Cell::new(sources.len()),
+            failed: Cell::new(false),
+        });
+        if sources.is_empty() {
+            put.store();
+        }
+        for (index, source) in sources.into_iter().enumerate() {
+            match source {
+                ResponseSource::Fetch(request) => {
+                    let init = RequestInit::empty();
+                    let response = Fetch(&global, RequestInfo::Request(request), init);
+                    upon_settlement(&response,
+                                    Box::new(FetchedResponse { put: put.clone(), index: index }),
+                                    Box::new(FetchFailed(put.clone())));
+                },
+                ResponseSource::Response(response) => put.read_response(index, &response),
+            }
+        }
+        promise
+    }
+}
+
+impl CacheMethods for Cache {
+    #[allow(unrooted_must_root)]
+    // https://w3c.github.io/ServiceWorker/#cache-match
+    fn Match(&self, request: RequestInfo, options: &CacheQueryOptions) -> Rc<Promise> {
+        let global = self.global();
+        let promise = Promise::new(&global);
+        match self.query(Some(request), options) {
+            Ok(entries) => match entries.into_iter().next() {
+                Some(entry) => promise.resolve_native(&response_from_cached(&global, entry.response)),
+                None => promise.resolve_native(&()),
+            },
+            Err(error) => promise.reject_error(error),
+        }
+        promise
+    }
+
+    #[allow(unrooted_must_root)]
+    // https://w3c.github.io/ServiceWorker/#cache-matchall
+    fn MatchAll(&self, request: Option<RequestInfo>, options: &CacheQueryOptions) -> Rc<Promise> {
+        let global = self.global();
+        let promise = Promise::new(&global);
+        match self.query(request, options) {
+            Ok(entries) => {
+                let responses: Vec<_> = entries.into_iter()
+                    .map(|entry| response_from_cached(&global, entry.response))
+                    .collect();
+                promise.resolve_native(&responses);
+            },
+            Err(error) => promise.reject_error(error),
+        }
+        promise
+    }
+
+    #[allow(unrooted_must_root)]
+    // https://w3c.github.io/ServiceWorker/#cache-add
+    fn Add(&self, request: RequestInfo) -> Rc<Promise> {
+        self.AddAll(vec![request])
+    }
+
+    #[allow(unrooted_must_root)]
+    // https://w3c.github.io/ServiceWorker/#cache-addAll
+    fn AddAll(&self, requests: Vec<RequestInfo>) -> Rc<Promise> {
+        let global = self.global();
+        let mut fetches = vec![];
+        for request in requests {
+            let request = match request_from_info(&global, request) {
+                Ok(request) => request,
+                Err(error) => return rejected(&global, error),
+            };
+            let cached = cached_request(&request);
+            if let Err(error) = check_request_to_store(&cached) {
+                return rejected(&global, error);
+            }
+            fetches.push((cached, ResponseSource::Fetch(request)));
+        }
+        self.put_all(fetches)
+    }
+
+    #[allow(unrooted_must_root)]
+    // https://w3c.github.io/ServiceWorker/#cache-put
+    fn Put(&self, request: RequestInfo, response: &Response) -> Rc<Promise> {
+        let global = self.global();
+        let request = match request_from_info(&global, request) {
+            Ok(request) => request,
+            Err(error) => return rejected(&global, error),
+        };
+        let cached = cached_request(&request);
+        if let Err(error) = check_request_to_store(&cached) {
+            return rejected(&global, error);
+        }
+        if let Err(error) = check_response_to_store(response) {
+            return rejected(&global, error);
+        }
+        if response.BodyUsed() || response.GetBody().map_or(false, |body| body.is_locked()) {
+            return rejected(&global, Error::Type("The body of the response was already used".to_owned()));
+        }
+        self.put_all(vec![(cached, ResponseSource::Response(DomRoot::from_ref(response)))])
+    }
+
+    #[allow(unrooted_must_root)]
+    // https://w3c.github.io/ServiceWorker/#cache-delete
+    fn Delete(&self, request: RequestInfo, options: &CacheQueryOptions) -> Rc<Promise> {
+        let global = self.global();
+        let promise = Promise::new(&global);
+        let result = origin_url(&global).and_then(|url| {
+            let query = match cached_request_for_query(&global, request, options.ignoreMethod)? {
+                Some(query) => query,
+                None => return Ok(false),
+            };
+            let (sender, receiver) = ipc::channel().unwrap();
+            let msg = CacheStorageThreadMsg::Delete(sender, url, self.name.clone(), query, query_options(options));
+            global.resource_threads().send(msg).unwrap();
+            receiver.recv().unwrap().map_err(cache_storage_error)
+        });
+        match result {
+            Ok(deleted) => promise.resolve_native(&deleted),
+            Err(error) => promise.reject_error(error),
+        }
+        promise
+    }
+
+    #[allow(unrooted_must_root)]
+    // https://w3c.github.io/ServiceWorker/#cache-keys
+    fn Keys(&self, request: Option<RequestInfo>, options: &CacheQueryOptions) -> Rc<Promise> {
+        let global = self.global();
+        let promise = Promise::new(&global);
+        match self.query(request, options) {
+            Ok(entries) => {
+                let requests: Vec<_> = entries.into_iter()
+                    .map(|entry| request_from_cached(&global, entry.request))
+                    .collect();
+                promise.resolve_native(&requests);
+            },
+            Err(error) => promise.reject_error(error),
+        }
+        promise
+    }
+}
+
+/// Queries the entries of the named cache, or of all the caches of the origin of `global`,
+/// whose request matches `request`.
+pub fn match_entries(global: &GlobalScope,
+                     name: Option<String>,
+                     request: Option<RequestInfo>,
+                     options: &CacheQueryOptions)
+                     -> Fallible<Vec<CacheEntry>> {
+    let url = origin_url(global)?;
+    let query = match request {
+        Some(request) => match cached_request_for_query(global, request, options.ignoreMethod)? {
+            Some(query) => Some(query),
+            None => return Ok(vec![]),
+        },
+        None => None,
+    };
+    let (sender, receiver) = ipc::channel().unwrap();
+    let msg = CacheStorageThreadMsg::Match(sender, url, name, query, query_options(options));
+    global.resource_threads().send(msg).unwrap();
+    receiver.recv().unwrap().map_err(cache_storage_error)
+}
+
+/// The URL to identify the origin of `global` with to the cache storage thread; the caches of
+/// opaque origins can't be accessed.
+pub fn origin_url(global: &GlobalScope) -> Fallible<ServoUrl> {
+    if !global.origin().is_tuple() {
+        return Err(Error::Security);
+    }
+    Ok(global.get_url())
+}
+
+/// The error to reject the promise of an operation on the caches with.
+pub fn cache_storage_error(error: CacheStorageError) -> Error {
+    match error {
+        CacheStorageError::NotFound => Error::NotFound,
+        CacheStorageError::Storage(_) => Error::Unknown,
+    }
+}
+
+#[allow(unrooted_must_root)]
+fn rejected(global: &GlobalScope, error: Error) -> Rc<Promise> {
+    let promise = Promise::new(global);
+    promise.reject_error(error);
+    promise
+}
+
+fn query_options(options: &CacheQueryOptions) -> QueryOptions {
+    QueryOptions {
+        ignore_search: options.ignoreSearch,
+        ignore_method: options.ignoreMethod,
+        ignore_vary: options.ignoreVary,
+    }
+}
+
+/// The request `info` stands for, as created by the `Request` constructor.
+fn request_from_info(global: &GlobalScope, info: RequestInfo) -> Fallible<DomRoot<Request>> {
+    match info {
+        RequestInfo::Request(request) => Ok(request),
+        info => Request::Constructor(global, info, RequestInit::empty()),
+    }
+}
+
+fn cached_request(request: &Request) -> CachedRequest {
+    let net_request = request.get_request();
+    CachedRequest {
+        url: net_request.url(),
+        method: net_request.method.as_ref().to_owned(),
+        headers: header_list(&request.Headers().get_headers_list()),
+    }
+}
+
+/// The request to query the caches with for `info`, or `None` if no request can match it.
+fn cached_request_for_query(global: &GlobalScope,
+                                info: RequestInfo,
+                                ignore_method: bool)
+                                -> Fallible<Option<CachedRequest>> {
+    let is_request = match info {
+        RequestInfo::Request(_) => true,
+        RequestInfo::USVString(_) => false,
+    };
+    let request = cached_request(&*request_from_info(global, info)?);
+    if is_request && request.method != "GET" && !ignore_method {
+        return Ok(None);
+    }
+    Ok(Some(request))
+}
+
+fn check_request_to_store(request: &CachedRequest) -> Fallible<()> {
+    match request.url.scheme() {
+        "http" | "https" => {},
+        _ => return Err(Error::Type("Only http and https requests can be cached".to_owned())),
+    }
+    if request.method != "GET" {
+        return Err(Error::Type("Only GET requests can be cached".to_owned()));
+    }
+    Ok(())
+}
+
+fn check_response_to_store(response: &Response) -> Fallible<()> {
+    if response.Status() == 206 {
+        return Err(Error::Type("Partial responses can't be cached".to_owned()));
+    }
+    let headers = header_list(&response.Headers().get_headers_list());
+    let varies_on_everything = headers.iter()
+        .filter(|&&(ref name, _)| name.eq_ignore_ascii_case("vary"))
+        .flat_map(|&(_, ref value)| value.split(','))
+        .any(|field| field.trim() == "*");
+    if varies_on_everything {
+        return Err(Error::Type("Responses which vary on `*` can't be cached".to_owned()));
+    }
+    Ok(())
+}
+
+fn header_list(headers: &HyperHeaders) -> Vec<(String, String)> {
+    headers.iter().map(|header| (header.name().to_owned(), header.value_string())).collect()
+}
+
+fn hyper_headers(list: Vec<(String, String)>) -> HyperHeaders {
+    let mut headers = HyperHeaders::new();
+    for (name, value) in list {
+        headers.set_raw(name, vec![value.into_bytes()]);
+    }
+    headers
+}
+
+/// A request object for a request stored in the caches.
+fn request_from_cached(global: &GlobalScope, cached: CachedRequest) -> DomRoot<Request> {
+    let mut request = NetTraitsRequest::new(cached.url, None, None);
+    request.method = HttpMethod::from_str(&cached.method).unwrap_or(HttpMethod::Get);
+    request.headers = hyper_headers(cached.headers);
+    Request::from_foreign_request(global, request)
+}
+
+/// A response object for a response stored in the caches.
+pub fn response_from_cached(global: &GlobalScope, cached: CachedResponse) -> DomRoot<Response> {
+    let response = Response::new(global);
+    response.set_headers(Some(Serde(hyper_headers(cached.headers))));
+    response.Headers().set_guard(Guard::Immutable);
+    response.set_raw_status(Some((cached.status, cached.status_text)));
+    if let Some(url) = cached.url {
+        response.set_final_url(url);
+    }
+    response.set_body(&ReadableStream::new_from_bytes(global, cached.body));
+    response
+}
+
+/// Where the response to a request to store comes from.
+enum ResponseSource {
+    Fetch(DomRoot<Request>),
+    Response(DomRoot<Response>),
+}
+
+/// The entries being stored by `addAll` or `put`, once the bodies of their responses have all
+/// been read.
+///
+/// <https://w3c.github.io/ServiceWorker/#cache-addAll> steps 7-8.
+#[derive(JSTraceable, MallocSizeOf)]
+#[must_root]
+struct PendingPut {
+    cache: Dom<Cache>,
+    #[ignore_malloc_size_of = "Rc"]
+    promise: Rc<Promise>,
+    requests: Vec<CachedRequest>,
+    responses: DomRefCell<Vec<Option<CachedResponse>>>,
+    /// The number of responses which weren't read yet.
+    remaining: Cell<usize>,
+    /// Whether reading one of the responses failed, in which case nothing is stored.
+    failed: Cell<bool>,
+}
+
+impl PendingPut {
+    #[allow(unrooted_must_root)]
+    fn read_response(self: &Rc<Self>, index: usize, response: &Response) {
+        let cached = CachedResponse {
+            url: ServoUrl::parse(&response.Url()).ok(),
+            status: response.Status(),
+            status_text: response.StatusText().into(),
+            headers: header_list(&response.Headers().get_headers_list()),
+            body: vec![],
+        };
+        let steps = Box::new(ResponseBodyRead { put: self.clone(), index: index, response: cached });
+        if let Err(error) = response.read_body(steps) {
+            self.fail(error);
+        }
+    }
+
+    fn response_read(&self, index: usize, response: CachedResponse) {
+        if self.failed.get() {
+            return;
+        }
+        self.responses.borrow_mut()[index] = Some(response);
+        self.remaining.set(self.remaining.get() - 1);
+        if self.remaining.get() == 0 {
+            self.store();
+        }
+    }
+
+    fn store(&self) {
+        let global = self.cache.global();
+        let url = match origin_url(&global) {
+            Ok(url) => url,
+            Err(error) => return self.promise.reject_error(error),
+        };
+        let responses = self.responses.borrow_mut().drain(..).map(Option::unwrap).collect::<Vec<_>>();
+        let entries = self.requests.iter().cloned().zip(responses).map(|(request, response)| {
+            CacheEntry { request: request, response: response }
+        }).collect();
+        let (sender, receiver) = ipc::channel().unwrap();
+        let msg = CacheStorageThreadMsg::Put(sender, url, self.cache.name.clone(), entries);
+        global.resource_threads().send(msg).unwrap();
+        match receiver.recv().unwrap() {
+            Ok(()) => self.promise.resolve_native(&()),
+            Err(error) => self.promise.reject_error(cache_storage_error(error)),
+        }
+    }
+
+    fn fail(&self, error: Error) {
+        if !self.failed.get() {
+            self.failed.set(true);
+            self.promise.reject_error(error);
+        }
+    }
+
+    #[allow(unsafe_code)]
+    fn fail_with_reason(&self, cx: *mut JSContext, reason: HandleValue) {
+        if !self.failed.get() {
+            self.failed.set(true);
+            unsafe { self.promise.reject(cx, reason) };
+        }
+    }
+}
+
+/// Checks the response fetched for one of the requests of `addAll`, and reads its body.
+#[derive(JSTraceable, MallocSizeOf)]
+#[must_root]
+struct FetchedResponse {
+    #[ignore_malloc_size_of = "Rc"]
+    put: Rc<PendingPut>,
+    index: usize,
+}
+
+impl Callback for FetchedResponse {
+    #[allow(unrooted_must_root)]
+    fn callback(&self, _cx: *mut JSContext, v: HandleValue) {
+        let response = match root_from_handlevalue::<Response>(v) {
+            Ok(response) => response,
+            Err(()) => return self.put.fail(Error::Type("Fetching the request failed".to_owned())),
+        };
+        match response.Type() {
+            ResponseType::Error | ResponseType::Opaque | ResponseType::Opaqueredirect => {
+                return self.put.fail(Error::Type("Opaque responses can't be added to caches".to_owned()));
+            },
+            _ => {},
+        }
+        if !response.Ok() {
+            return self.put.fail(Error::Type("Only ok responses can be added to caches".to_owned()));
+        }
+        if let Err(error) = check_response_to_store(&response) {
+            return self.put.fail(error);
+        }
+        self.put.read_response(self.index, &response);
+    }
+}
+
+#[derive(JSTraceable, MallocSizeOf)]
+#[must_root]
+struct FetchFailed(#[ignore_malloc_size_of = "Rc"] Rc<PendingPut>);
+
+impl Callback for FetchFailed {
+    fn callback(&self, cx: *mut JSContext, v: HandleValue) {
+        self.0.fail_with_reason(cx, v);
+    }
+}
+
+#[derive(JSTraceable, MallocSizeOf)]
+#[must_root]
+struct ResponseBodyRead {
+    #[ignore_malloc_size_of = "Rc"]
+    put: Rc<PendingPut>,
+    index: usize,
+    response: CachedResponse,
+}
+
+impl ReadAllBytesSteps for ResponseBodyRead {
+    #[allow(unrooted_must_root)]
+    fn success_steps(self: Box<Self>, bytes: Vec<u8>) {
+        let ResponseBodyRead { put, index, mut response } = *self;
+        response.body = bytes;
+        put.response_read(index, response);
+    }
+
+    fn failure_steps(self: Box<Self>, cx: *mut JSContext, error: HandleValue) {
+        self.put.fail_with_reason(cx, error);
+    }
+}
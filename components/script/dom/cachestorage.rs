@@ -0,0 +1,134 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::CacheStorageBinding;
+use dom::bindings::codegen::Bindings::CacheStorageBinding::{CacheStorageMethods, MultiCacheQueryOptions};
+use dom::bindings::codegen::Bindings::RequestBinding::RequestInfo;
+use dom::bindings::error::Fallible;
+use dom::bindings::reflector::{DomObject, Reflector, reflect_dom_object};
+use dom::bindings::root::DomRoot;
+use dom::bindings::str::DOMString;
+use dom::cache::{Cache, cache_storage_error, match_entries, origin_url, response_from_cached};
+use dom::globalscope::GlobalScope;
+use dom::promise::Promise;
+use dom_struct::dom_struct;
+use ipc_channel::ipc::{self, IpcSender};
+use net_traits::IpcSend;
+use net_traits::cache_storage_thread::CacheStorageThreadMsg;
+use serde::{Deserialize, Serialize};
+use servo_url::ServoUrl;
+use std::rc::Rc;
+
+// https://w3c.github.io/ServiceWorker/#cachestorage-interface
+#[dom_struct]
+pub struct CacheStorage {
+    reflector_: Reflector,
+}
+
+impl CacheStorage {
+    fn new_inherited() -> CacheStorage {
+        CacheStorage {
+            reflector_: Reflector::new(),
+        }
+    }
+
+    pub fn new(global: &GlobalScope) -> DomRoot<CacheStorage> {
+        reflect_dom_object(Box::new(CacheStorage::new_inherited()), global, CacheStorageBinding::Wrap)
+    }
+
+    /// Sends the message built by `msg` to the cache storage thread, and waits for its reply.
+    fn send<T, F>(&self, msg: F) -> Fallible<T>
+        where T: for<'de> Deserialize<'de> + Serialize,
+              F: FnOnce(IpcSender<T>, ServoUrl) -> CacheStorageThreadMsg
+    {
+        let global = self.global();
+        let url = origin_url(&global)?;
+        let (sender, receiver) = ipc::channel().unwrap();
+        global.resource_threads().send(msg(sender, url)).unwrap();
+        Ok(receiver.recv().unwrap())
+    }
+}
+
+impl CacheStorageMethods for CacheStorage {
+    #[allow(unrooted_must_root)]
+    // https://w3c.github.io/ServiceWorker/#cache-storage-match
+    fn Match(&self, request: RequestInfo, options: &MultiCacheQueryOptions) -> Rc<Promise> {
+        let global = self.global();
+        let promise = Promise::new(&global);
+        let name = options.cacheName.as_ref().map(|name| String::from(name.clone()));
+        let has_cache = match name {
+            Some(ref name) => self.send(|sender, url| CacheStorageThreadMsg::HasCache(sender, url, name.clone())),
+            None => Ok(true),
+        };
+        let result = has_cache.and_then(|has_cache| {
+            if !has_cache {
+                return Ok(vec![]);
+            }
+            match_entries(&global, name, Some(request), &options.parent)
+        });
+        match result {
+            Ok(entries) => match entries.into_iter().next() {
+                Some(entry) => promise.resolve_native(&response_from_cached(&global, entry.response)),
+                None => promise.resolve_native(&()),
+            },
+            Err(error) => promise.reject_error(error),
+        }
+        promise
+    }
+
+    #[allow(unrooted_must_root)]
+    // https://w3c.github.io/ServiceWorker/#cache-storage-has
+    fn Has(&self, cache_name: DOMString) -> Rc<Promise> {
+        let promise = Promise::new(&self.global());
+        let name = String::from(cache_name);
+        match self.send(|sender, url| CacheStorageThreadMsg::HasCache(sender, url, name)) {
+            Ok(has_cache) => promise.resolve_native(&has_cache),
+            Err(error) => promise.reject_error(error),
+        }
+        promise
+    }
+
+    #[allow(unrooted_must_root)]
+    // https://w3c.github.io/ServiceWorker/#cache-storage-open
+    fn Open(&self, cache_name: DOMString) -> Rc<Promise> {
+        let global = self.global();
+        let promise = Promise::new(&global);
+        let name = String::from(cache_name);
+        let opened = self.send(|sender, url| CacheStorageThreadMsg::OpenCache(sender, url, name.clone()))
+            .and_then(|result| result.map_err(cache_storage_error));
+        match opened {
+            Ok(()) => promise.resolve_native(&Cache::new(&global, name)),
+            Err(error) => promise.reject_error(error),
+        }
+        promise
+    }
+
+    #[allow(unrooted_must_root)]
+    // https://w3c.github.io/ServiceWorker/#cache-storage-delete
+    fn Delete(&self, cache_name: DOMString) -> Rc<Promise> {
+        let promise = Promise::new(&self.global());
+        let name = String::from(cache_name);
+        let deleted = self.send(|sender, url| CacheStorageThreadMsg::DeleteCache(sender, url, name))
+            .and_then(|result| result.map_err(cache_storage_error));
+        match deleted {
+            Ok(deleted) => promise.resolve_native(&deleted),
+            Err(error) => promise.reject_error(error),
+        }
+        promise
+    }
+
+    #[allow(unrooted_must_root)]
+    // https://w3c.github.io/ServiceWorker/#cache-storage-keys
+    fn Keys(&self) -> Rc<Promise> {
+        let promise = Promise::new(&self.global());
+        match self.send(CacheStorageThreadMsg::CacheNames) {
+            Ok(names) => {
+                let names: Vec<DOMString> = names.into_iter().map(DOMString::from).collect();
+                promise.resolve_native(&names);
+            },
+            Err(error) => promise.reject_error(error),
+        }
+        promise
+    }
+}
@@ -3,19 +3,25 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use canvas_traits::canvas::{Canvas2dMsg, CanvasMsg, CanvasId};
+use canvas_traits::canvas::{CanvasFontFamily, CanvasFontStyle, Direction, TextAlign, TextBaseline};
 use canvas_traits::canvas::{CompositionOrBlending, FillOrStrokeStyle, FillRule};
 use canvas_traits::canvas::{LineCapStyle, LineJoinStyle, LinearGradientStyle};
 use canvas_traits::canvas::{RadialGradientStyle, RepetitionStyle, byte_swap_and_premultiply};
-use cssparser::{Parser, ParserInput, RGBA};
+use canvas_traits::canvas::TextMetrics as TextMetricsData;
+use cssparser::{Parser, ParserInput, RGBA, serialize_identifier, serialize_string};
 use cssparser::Color as CSSColor;
 use dom::bindings::cell::DomRefCell;
 use dom::bindings::codegen::Bindings::CanvasRenderingContext2DBinding;
+use dom::bindings::codegen::Bindings::CanvasRenderingContext2DBinding::CanvasDirection;
 use dom::bindings::codegen::Bindings::CanvasRenderingContext2DBinding::CanvasFillRule;
 use dom::bindings::codegen::Bindings::CanvasRenderingContext2DBinding::CanvasImageSource;
 use dom::bindings::codegen::Bindings::CanvasRenderingContext2DBinding::CanvasLineCap;
 use dom::bindings::codegen::Bindings::CanvasRenderingContext2DBinding::CanvasLineJoin;
 use dom::bindings::codegen::Bindings::CanvasRenderingContext2DBinding::CanvasRenderingContext2DMethods;
+use dom::bindings::codegen::Bindings::CanvasRenderingContext2DBinding::CanvasTextAlign;
+use dom::bindings::codegen::Bindings::CanvasRenderingContext2DBinding::CanvasTextBaseline;
 use dom::bindings::codegen::Bindings::ImageDataBinding::ImageDataMethods;
+use dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
 use dom::bindings::codegen::UnionTypes::StringOrCanvasGradientOrCanvasPattern;
 use dom::bindings::error::{Error, ErrorResult, Fallible};
 use dom::bindings::inheritance::Castable;
@@ -30,6 +36,7 @@ use dom::globalscope::GlobalScope;
 use dom::htmlcanvaselement::HTMLCanvasElement;
 use dom::imagedata::ImageData;
 use dom::node::{Node, NodeDamage, window_from_node};
+use dom::textmetrics::TextMetrics;
 use dom_struct::dom_struct;
 use euclid::{Transform2D, Point2D, Vector2D, Rect, Size2D, vec2};
 use ipc_channel::ipc::{self, IpcSender};
@@ -48,6 +55,15 @@ use std::{cmp, fmt, mem};
 use std::cell::Cell;
 use std::str::FromStr;
 use std::sync::Arc;
+use style::computed_values::direction::T as ComputedDirection;
+use style::parser::ParserContext;
+use style::properties::longhands::font_variant_caps;
+use style::properties::shorthands::font;
+use style::stylesheets::CssRuleType;
+use style::values::computed::{Context, ToComputedValue};
+use style::values::computed::font::SingleFontFamily;
+use style::values::generics::font::FontStyle as GenericFontStyle;
+use style_traits::ParsingMode;
 use unpremultiplytable::UNPREMULTIPLY_TABLE;
 
 #[must_root]
@@ -98,6 +114,13 @@ struct CanvasContextState {
     shadow_offset_y: f64,
     shadow_blur: f64,
     shadow_color: RGBA,
+    font_style: CanvasFontStyle,
+    text_align: TextAlign,
+    text_baseline: TextBaseline,
+    direction: Direction,
+    /// The direction that the paint thread was last told to lay text out in, which it saves and
+    /// restores along with the rest of the state.
+    painted_direction: Direction,
 }
 
 impl CanvasContextState {
@@ -118,6 +141,11 @@ impl CanvasContextState {
             shadow_offset_y: 0.0,
             shadow_blur: 0.0,
             shadow_color: RGBA::transparent(),
+            font_style: CanvasFontStyle::default(),
+            text_align: TextAlign::default(),
+            text_baseline: TextBaseline::default(),
+            direction: Direction::default(),
+            painted_direction: Direction::Ltr,
         }
     }
 }
@@ -469,7 +497,7 @@ impl CanvasRenderingContext2D {
 
         let image_size = Size2D::new(img.width as i32, img.height as i32);
         let image_data = match img.format {
-            PixelFormat::BGRA8 => img.bytes.to_vec(),
+            PixelFormat::BGRA8 => img.first_frame().to_vec(),
             PixelFormat::K8 => panic!("K8 color type not supported"),
             PixelFormat::RGB8 => panic!("RGB8 color type not supported"),
             PixelFormat::KA8 => panic!("KA8 color type not supported"),
@@ -550,6 +578,88 @@ impl CanvasRenderingContext2D {
         }
     }
 
+    /// Parses a value of the `font` attribute, returning `None` if it could not be parsed
+    /// as a CSS `font` value.
+    ///
+    /// <https://html.spec.whatwg.org/multipage/#dom-context-2d-font>
+    fn parse_font(&self, value: &str) -> Option<CanvasFontStyle> {
+        // The font attribute is only exposed on contexts created by a canvas element.
+        let canvas = self.canvas.as_ref()?;
+        let document = window_from_node(&**canvas).Document();
+        let url = document.url();
+        let quirks_mode = document.quirks_mode();
+        let context = ParserContext::new_for_cssom(
+            &url,
+            Some(CssRuleType::Style),
+            ParsingMode::DEFAULT,
+            quirks_mode,
+            None,
+            None,
+        );
+        let mut input = ParserInput::new(value);
+        let mut parser = Parser::new(&mut input);
+        let longhands = parser.parse_entirely(|input| font::parse_value(&context, input)).ok()?;
+
+        // TODO: Relative keywords and lengths should be computed relative to the computed
+        // font of the canvas element, rather than to the initial font.
+        let device = document.device()?;
+        Some(Context::for_media_query_evaluation(&device, quirks_mode, |context| {
+            let families = longhands.font_family.to_computed_value(context).0.iter().map(|family| {
+                match *family {
+                    SingleFontFamily::FamilyName(ref name) => {
+                        CanvasFontFamily::Specific(String::from(&*name.name))
+                    },
+                    SingleFontFamily::Generic(ref name) => {
+                        CanvasFontFamily::Generic(String::from(&**name))
+                    },
+                }
+            }).collect();
+            let (oblique_angle, italic) = match longhands.font_style.to_computed_value(context) {
+                GenericFontStyle::Normal => (None, false),
+                GenericFontStyle::Italic => (Some(font_style_default_angle()), true),
+                GenericFontStyle::Oblique(angle) => (Some(angle.0.degrees()), false),
+            };
+            let stretch = longhands.font_stretch.to_computed_value(context);
+            CanvasFontStyle {
+                families: families,
+                size: longhands.font_size.to_computed_value(context).size().to_f64_px(),
+                weight: longhands.font_weight.to_computed_value(context).0,
+                stretch: ((stretch.0).0).0 * 100.,
+                oblique_angle: oblique_angle,
+                italic: italic,
+                small_caps: longhands.font_variant_caps.to_computed_value(context) ==
+                    font_variant_caps::T::SmallCaps,
+            }
+        }))
+    }
+
+    /// The direction text is laid out in, resolving `inherit` against the canvas element.
+    ///
+    /// <https://html.spec.whatwg.org/multipage/#dom-context-2d-direction>
+    fn resolved_direction(&self) -> Direction {
+        match self.state.borrow().direction {
+            Direction::Inherit => {},
+            direction => return direction,
+        }
+        let style = self.canvas.as_ref().and_then(|canvas| canvas.upcast::<Element>().style());
+        match style {
+            Some(ref style) if style.get_inherited_box().direction == ComputedDirection::Rtl => {
+                Direction::Rtl
+            },
+            _ => Direction::Ltr,
+        }
+    }
+
+    /// Tells the paint thread the direction to lay text out in, if it changed.
+    fn update_text_direction(&self) {
+        let direction = self.resolved_direction();
+        if self.state.borrow().painted_direction == direction {
+            return;
+        }
+        self.state.borrow_mut().painted_direction = direction;
+        self.send_canvas_2d_msg(Canvas2dMsg::SetTextDirection(direction));
+    }
+
     pub fn get_canvas_id(&self) -> CanvasId {
         self.canvas_id.clone()
     }
@@ -790,11 +900,44 @@ impl CanvasRenderingContext2DMethods for CanvasRenderingContext2D {
 
     // https://html.spec.whatwg.org/multipage/#dom-context-2d-filltext
     fn FillText(&self, text: DOMString, x: f64, y: f64, max_width: Option<f64>) {
+        if !(x.is_finite() && y.is_finite()) {
+            return;
+        }
+        if max_width.map_or(false, |max_width| !max_width.is_finite()) {
+            return;
+        }
+
         let parsed_text: String = text.into();
+        self.update_text_direction();
         self.send_canvas_2d_msg(Canvas2dMsg::FillText(parsed_text, x, y, max_width));
         self.mark_as_dirty();
     }
 
+    // https://html.spec.whatwg.org/multipage/#dom-context-2d-stroketext
+    fn StrokeText(&self, text: DOMString, x: f64, y: f64, max_width: Option<f64>) {
+        if !(x.is_finite() && y.is_finite()) {
+            return;
+        }
+        if max_width.map_or(false, |max_width| !max_width.is_finite()) {
+            return;
+        }
+
+        let parsed_text: String = text.into();
+        self.update_text_direction();
+        self.send_canvas_2d_msg(Canvas2dMsg::StrokeText(parsed_text, x, y, max_width));
+        self.mark_as_dirty();
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-context-2d-measuretext
+    fn MeasureText(&self, text: DOMString) -> DomRoot<TextMetrics> {
+        let (sender, receiver) =
+            profiled_ipc::channel::<TextMetricsData>(self.global().time_profiler_chan().clone()).unwrap();
+        self.update_text_direction();
+        self.send_canvas_2d_msg(Canvas2dMsg::MeasureText(text.into(), sender));
+        let metrics = receiver.recv().unwrap();
+        TextMetrics::new(&self.global(), metrics)
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-context-2d-drawimage
     fn DrawImage(&self,
                  image: CanvasImageSource,
@@ -1359,6 +1502,90 @@ impl CanvasRenderingContext2DMethods for CanvasRenderingContext2D {
             self.send_canvas_2d_msg(Canvas2dMsg::SetShadowColor(color))
         }
     }
+
+    // https://html.spec.whatwg.org/multipage/#dom-context-2d-font
+    fn Font(&self) -> DOMString {
+        let mut result = String::new();
+        serialize_font(&self.state.borrow().font_style, &mut result).unwrap();
+        DOMString::from(result)
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-context-2d-font
+    fn SetFont(&self, value: DOMString) {
+        if let Some(font_style) = self.parse_font(&value) {
+            self.state.borrow_mut().font_style = font_style.clone();
+            self.send_canvas_2d_msg(Canvas2dMsg::SetFont(font_style))
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-context-2d-textalign
+    fn TextAlign(&self) -> CanvasTextAlign {
+        match self.state.borrow().text_align {
+            TextAlign::Start => CanvasTextAlign::Start,
+            TextAlign::End => CanvasTextAlign::End,
+            TextAlign::Left => CanvasTextAlign::Left,
+            TextAlign::Right => CanvasTextAlign::Right,
+            TextAlign::Center => CanvasTextAlign::Center,
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-context-2d-textalign
+    fn SetTextAlign(&self, value: CanvasTextAlign) {
+        let text_align = match value {
+            CanvasTextAlign::Start => TextAlign::Start,
+            CanvasTextAlign::End => TextAlign::End,
+            CanvasTextAlign::Left => TextAlign::Left,
+            CanvasTextAlign::Right => TextAlign::Right,
+            CanvasTextAlign::Center => TextAlign::Center,
+        };
+        self.state.borrow_mut().text_align = text_align;
+        self.send_canvas_2d_msg(Canvas2dMsg::SetTextAlign(text_align))
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-context-2d-textbaseline
+    fn TextBaseline(&self) -> CanvasTextBaseline {
+        match self.state.borrow().text_baseline {
+            TextBaseline::Top => CanvasTextBaseline::Top,
+            TextBaseline::Hanging => CanvasTextBaseline::Hanging,
+            TextBaseline::Middle => CanvasTextBaseline::Middle,
+            TextBaseline::Alphabetic => CanvasTextBaseline::Alphabetic,
+            TextBaseline::Ideographic => CanvasTextBaseline::Ideographic,
+            TextBaseline::Bottom => CanvasTextBaseline::Bottom,
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-context-2d-textbaseline
+    fn SetTextBaseline(&self, value: CanvasTextBaseline) {
+        let text_baseline = match value {
+            CanvasTextBaseline::Top => TextBaseline::Top,
+            CanvasTextBaseline::Hanging => TextBaseline::Hanging,
+            CanvasTextBaseline::Middle => TextBaseline::Middle,
+            CanvasTextBaseline::Alphabetic => TextBaseline::Alphabetic,
+            CanvasTextBaseline::Ideographic => TextBaseline::Ideographic,
+            CanvasTextBaseline::Bottom => TextBaseline::Bottom,
+        };
+        self.state.borrow_mut().text_baseline = text_baseline;
+        self.send_canvas_2d_msg(Canvas2dMsg::SetTextBaseline(text_baseline))
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-context-2d-direction
+    fn Direction(&self) -> CanvasDirection {
+        match self.state.borrow().direction {
+            Direction::Ltr => CanvasDirection::Ltr,
+            Direction::Rtl => CanvasDirection::Rtl,
+            Direction::Inherit => CanvasDirection::Inherit,
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-context-2d-direction
+    fn SetDirection(&self, value: CanvasDirection) {
+        self.state.borrow_mut().direction = match value {
+            CanvasDirection::Ltr => Direction::Ltr,
+            CanvasDirection::Rtl => Direction::Rtl,
+            CanvasDirection::Inherit => Direction::Inherit,
+        };
+        self.update_text_direction();
+    }
 }
 
 impl Drop for CanvasRenderingContext2D {
@@ -1390,6 +1617,69 @@ fn is_rect_valid(rect: Rect<f64>) -> bool {
     rect.size.width > 0.0 && rect.size.height > 0.0
 }
 
+fn font_style_default_angle() -> f32 {
+    use style::values::computed::font::FontStyle;
+    FontStyle::default_angle().0.degrees()
+}
+
+// https://html.spec.whatwg.org/multipage/#dom-context-2d-font
+// The font is serialized like the CSS `font` shorthand, without a `line-height` component.
+fn serialize_font<W>(font_style: &CanvasFontStyle, dest: &mut W) -> fmt::Result
+    where W: fmt::Write
+{
+    if font_style.italic {
+        dest.write_str("italic ")?;
+    } else if let Some(angle) = font_style.oblique_angle {
+        if angle == font_style_default_angle() {
+            dest.write_str("oblique ")?;
+        } else {
+            write!(dest, "oblique {}deg ", angle)?;
+        }
+    }
+    if font_style.small_caps {
+        dest.write_str("small-caps ")?;
+    }
+    if font_style.weight == 700. {
+        dest.write_str("bold ")?;
+    } else if font_style.weight != 400. {
+        write!(dest, "{} ", font_style.weight)?;
+    }
+    let stretch = match font_style.stretch {
+        s if s == 50. => Some("ultra-condensed"),
+        s if s == 62.5 => Some("extra-condensed"),
+        s if s == 75. => Some("condensed"),
+        s if s == 87.5 => Some("semi-condensed"),
+        s if s == 112.5 => Some("semi-expanded"),
+        s if s == 125. => Some("expanded"),
+        s if s == 150. => Some("extra-expanded"),
+        s if s == 200. => Some("ultra-expanded"),
+        _ => None,
+    };
+    if let Some(stretch) = stretch {
+        write!(dest, "{} ", stretch)?;
+    }
+    write!(dest, "{}px", font_style.size)?;
+    for (i, family) in font_style.families.iter().enumerate() {
+        dest.write_str(if i == 0 { " " } else { ", " })?;
+        match *family {
+            CanvasFontFamily::Specific(ref name) => {
+                if name.split(' ').all(|ident| !ident.is_empty()) {
+                    let mut words = name.split(' ');
+                    serialize_identifier(words.next().unwrap(), dest)?;
+                    for word in words {
+                        dest.write_str(" ")?;
+                        serialize_identifier(word, dest)?;
+                    }
+                } else {
+                    serialize_string(name, dest)?;
+                }
+            },
+            CanvasFontFamily::Generic(ref name) => dest.write_str(name)?,
+        }
+    }
+    Ok(())
+}
+
 // https://html.spec.whatwg.org/multipage/#serialisation-of-a-colour
 fn serialize<W>(color: &RGBA, dest: &mut W) -> fmt::Result
     where W: fmt::Write
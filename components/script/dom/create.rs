@@ -60,6 +60,7 @@ use dom::htmlprogresselement::HTMLProgressElement;
 use dom::htmlquoteelement::HTMLQuoteElement;
 use dom::htmlscriptelement::HTMLScriptElement;
 use dom::htmlselectelement::HTMLSelectElement;
+use dom::htmlslotelement::HTMLSlotElement;
 use dom::htmlsourceelement::HTMLSourceElement;
 use dom::htmlspanelement::HTMLSpanElement;
 use dom::htmlstyleelement::HTMLStyleElement;
@@ -78,7 +79,22 @@ use dom::htmltrackelement::HTMLTrackElement;
 use dom::htmlulistelement::HTMLUListElement;
 use dom::htmlunknownelement::HTMLUnknownElement;
 use dom::htmlvideoelement::HTMLVideoElement;
+use dom::svgcircleelement::SVGCircleElement;
+use dom::svgdefselement::SVGDefsElement;
+use dom::svgellipseelement::SVGEllipseElement;
+use dom::svggelement::SVGGElement;
+use dom::svglineargradientelement::SVGLinearGradientElement;
+use dom::svglineelement::SVGLineElement;
+use dom::svgpathelement::SVGPathElement;
+use dom::svgpolygonelement::SVGPolygonElement;
+use dom::svgpolylineelement::SVGPolylineElement;
+use dom::svgradialgradientelement::SVGRadialGradientElement;
+use dom::svgrectelement::SVGRectElement;
+use dom::svgstopelement::SVGStopElement;
 use dom::svgsvgelement::SVGSVGElement;
+use dom::svgsymbolelement::SVGSymbolElement;
+use dom::svgtextelement::SVGTextElement;
+use dom::svguseelement::SVGUseElement;
 use html5ever::{LocalName, Prefix, QualName};
 use js::jsapi::JSAutoCompartment;
 use script_thread::ScriptThread;
@@ -106,8 +122,23 @@ fn create_svg_element(name: QualName,
     }
 
     match name.local {
-        local_name!("svg")        => make!(SVGSVGElement),
-        _                   => Element::new(name.local, name.ns, prefix, document),
+        local_name!("circle")         => make!(SVGCircleElement),
+        local_name!("defs")           => make!(SVGDefsElement),
+        local_name!("ellipse")        => make!(SVGEllipseElement),
+        local_name!("g")              => make!(SVGGElement),
+        local_name!("line")           => make!(SVGLineElement),
+        local_name!("linearGradient") => make!(SVGLinearGradientElement),
+        local_name!("path")           => make!(SVGPathElement),
+        local_name!("polygon")        => make!(SVGPolygonElement),
+        local_name!("polyline")       => make!(SVGPolylineElement),
+        local_name!("radialGradient") => make!(SVGRadialGradientElement),
+        local_name!("rect")           => make!(SVGRectElement),
+        local_name!("stop")           => make!(SVGStopElement),
+        local_name!("svg")            => make!(SVGSVGElement),
+        local_name!("symbol")         => make!(SVGSymbolElement),
+        local_name!("text")           => make!(SVGTextElement),
+        local_name!("use")            => make!(SVGUseElement),
+        _                             => Element::new(name.local, name.ns, prefix, document),
     }
 }
 
@@ -323,6 +354,7 @@ pub fn create_native_html_element(
         local_name!("script")     => make!(HTMLScriptElement, creator),
         local_name!("section")    => make!(HTMLElement),
         local_name!("select")     => make!(HTMLSelectElement),
+        local_name!("slot")       => make!(HTMLSlotElement),
         local_name!("small")      => make!(HTMLElement),
         local_name!("source")     => make!(HTMLSourceElement),
         // https://html.spec.whatwg.org/multipage/#other-elements,-attributes-and-apis:spacer
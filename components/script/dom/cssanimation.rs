@@ -0,0 +1,102 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::animation::Animation;
+use dom::bindings::codegen::Bindings::CSSAnimationBinding::{self, CSSAnimationMethods};
+use dom::bindings::codegen::Bindings::DocumentBinding::DocumentMethods;
+use dom::bindings::inheritance::Castable;
+use dom::bindings::reflector::reflect_dom_object;
+use dom::bindings::root::DomRoot;
+use dom::bindings::str::DOMString;
+use dom::element::Element;
+use dom::keyframeeffect::KeyframeEffect;
+use dom::node::{Node, NodeDamage, window_from_node};
+use dom::window::Window;
+use dom_struct::dom_struct;
+use script_layout_interface::message::Msg;
+use servo_atoms::Atom;
+use std::cell::Cell;
+use style::animation::EffectTiming;
+
+/// <https://drafts.csswg.org/css-animations-2/#the-CSSAnimation-interface>
+///
+/// The keyframes of CSS animations stay in layout, so their effect doesn't
+/// have any, and the only thing script controls is whether they are paused.
+#[dom_struct]
+pub struct CSSAnimation {
+    animation: Animation,
+    animation_name: Atom,
+    /// Whether layout runs this animation paused.
+    paused_in_layout: Cell<bool>,
+}
+
+impl CSSAnimation {
+    pub fn new(
+        window: &Window,
+        target: &Element,
+        animation_name: Atom,
+        timing: EffectTiming,
+        current_time: f64,
+        paused: bool,
+    ) -> DomRoot<CSSAnimation> {
+        let timeline = window.Document().Timeline();
+        let animation = reflect_dom_object(
+            Box::new(CSSAnimation {
+                animation: Animation::new_inherited(window.upcast(), Some(timeline.upcast())),
+                animation_name: animation_name,
+                paused_in_layout: Cell::new(paused),
+            }),
+            window,
+            CSSAnimationBinding::Wrap,
+        );
+        let effect = KeyframeEffect::new(window, Some(target));
+        animation.update_from_layout(timing, current_time, paused);
+        animation.upcast::<Animation>().init(Some(effect.upcast()));
+        animation
+    }
+
+    pub fn target(&self) -> Option<DomRoot<Element>> {
+        self.upcast::<Animation>().target()
+    }
+
+    pub fn animation_name(&self) -> &Atom {
+        &self.animation_name
+    }
+
+    /// Updates this animation with the state layout runs it with.
+    pub fn update_from_layout(&self, timing: EffectTiming, current_time: f64, paused: bool) {
+        let animation = self.upcast::<Animation>();
+        if let Some(effect) = animation.effect() {
+            effect.set_timing(timing);
+        }
+        animation.set_times_from_layout(current_time, paused);
+        self.paused_in_layout.set(paused);
+    }
+
+    /// Pauses or resumes this animation in layout.
+    pub fn set_paused_in_layout(&self, paused: bool) {
+        if self.paused_in_layout.get() == paused {
+            return;
+        }
+        let target = match self.target() {
+            Some(target) => target,
+            None => return,
+        };
+        self.paused_in_layout.set(paused);
+        let window = window_from_node(&*target);
+        let node = target.upcast::<Node>();
+        window
+            .layout_chan()
+            .send(Msg::SetCssAnimationPaused(node.to_opaque(), self.animation_name.clone(), paused))
+            .unwrap();
+        node.dirty(NodeDamage::OtherNodeDamage);
+    }
+}
+
+impl CSSAnimationMethods for CSSAnimation {
+    // https://drafts.csswg.org/css-animations-2/#dom-cssanimation-animationname
+    fn AnimationName(&self) -> DOMString {
+        DOMString::from(&*self.animation_name)
+    }
+}
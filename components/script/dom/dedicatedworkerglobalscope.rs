@@ -10,6 +10,7 @@ use dom::abstractworkerglobalscope::{WorkerEventLoopMethods, run_worker_event_lo
 use dom::bindings::cell::DomRefCell;
 use dom::bindings::codegen::Bindings::DedicatedWorkerGlobalScopeBinding;
 use dom::bindings::codegen::Bindings::DedicatedWorkerGlobalScopeBinding::DedicatedWorkerGlobalScopeMethods;
+use dom::bindings::codegen::Bindings::WorkerBinding::WorkerType;
 use dom::bindings::error::{ErrorInfo, ErrorResult};
 use dom::bindings::inheritance::Castable;
 use dom::bindings::reflector::DomObject;
@@ -21,6 +22,7 @@ use dom::event::{Event, EventBubbles, EventCancelable, EventStatus};
 use dom::eventtarget::EventTarget;
 use dom::globalscope::GlobalScope;
 use dom::messageevent::MessageEvent;
+use dom::messageport::MessagePort;
 use dom::worker::{TrustedWorkerAddress, Worker};
 use dom::workerglobalscope::WorkerGlobalScope;
 use dom_struct::dom_struct;
@@ -33,6 +35,7 @@ use js::rust::HandleValue;
 use msg::constellation_msg::TopLevelBrowsingContextId;
 use net_traits::{IpcSend, load_whole_resource};
 use net_traits::request::{CredentialsMode, Destination, RequestInit};
+use script_module::{fetch_module_worker_script_graph, run_module_script};
 use script_runtime::{CommonScriptMsg, ScriptChan, ScriptPort, new_rt_and_cx, Runtime};
 use script_runtime::ScriptThreadEventCategory::WorkerEvent;
 use script_traits::{TimerEvent, TimerSource, WorkerGlobalScopeInit, WorkerScriptLoadOrigin};
@@ -263,11 +266,16 @@ impl DedicatedWorkerGlobalScope {
                             own_sender: Sender<DedicatedWorkerScriptMsg>,
                             receiver: Receiver<DedicatedWorkerScriptMsg>,
                             worker_load_origin: WorkerScriptLoadOrigin,
-                            closing: Arc<AtomicBool>) {
+                            closing: Arc<AtomicBool>,
+                            worker_type: WorkerType,
+                            credentials_mode: CredentialsMode) {
         let serialized_worker_url = worker_url.to_string();
         let name = format!("WebWorker for {}", serialized_worker_url);
         let top_level_browsing_context_id = TopLevelBrowsingContextId::installed();
-        let origin = GlobalScope::current().expect("No current global object").origin().immutable().clone();
+        let current_global = GlobalScope::current().expect("No current global object");
+        let origin = current_global.origin().immutable().clone();
+        // The worker's script is fetched with the policies of its owner.
+        let owner_csp_list = current_global.get_csp_list();
 
         thread::Builder::new().name(name).spawn(move || {
             thread_state::initialize(ThreadState::SCRIPT | ThreadState::IN_WORKER);
@@ -290,25 +298,33 @@ impl DedicatedWorkerGlobalScope {
                 referrer_url: referrer_url,
                 referrer_policy: referrer_policy,
                 origin,
+                csp_list: owner_csp_list.clone(),
                 .. RequestInit::default()
             };
 
-            let (metadata, bytes) = match load_whole_resource(request,
-                                                              &init.resource_threads.sender()) {
-                Err(_) => {
-                    println!("error loading script {}", serialized_worker_url);
-                    parent_sender.send(CommonScriptMsg::Task(
-                        WorkerEvent,
-                        Box::new(SimpleWorkerErrorHandler::new(worker)),
-                        pipeline_id,
-                        TaskSourceName::DOMManipulation,
-                    )).unwrap();
-                    return;
-                }
-                Ok((metadata, bytes)) => (metadata, bytes)
+            let (url, source, metadata) = match worker_type {
+                WorkerType::Classic => {
+                    match load_whole_resource(request, &init.resource_threads.sender()) {
+                        Err(_) => {
+                            println!("error loading script {}", serialized_worker_url);
+                            parent_sender.send(CommonScriptMsg::Task(
+                                WorkerEvent,
+                                Box::new(SimpleWorkerErrorHandler::new(worker)),
+                                pipeline_id,
+                                TaskSourceName::DOMManipulation,
+                            )).unwrap();
+                            return;
+                        }
+                        Ok((metadata, bytes)) => {
+                            let source = String::from_utf8_lossy(&bytes).into_owned();
+                            (metadata.final_url.clone(), Some(source), Some(metadata))
+                        }
+                    }
+                },
+                // The module graph is kept in the module map of the worker's global, so it is
+                // only fetched once the global exists.
+                WorkerType::Module => (worker_url.clone(), None, None),
             };
-            let url = metadata.final_url;
-            let source = String::from_utf8_lossy(&bytes);
 
             let runtime = unsafe { new_rt_and_cx() };
 
@@ -330,6 +346,10 @@ impl DedicatedWorkerGlobalScope {
             // FIXME(njn): workers currently don't have a unique ID suitable for using in reporter
             // registration (#6631), so we instead use a random number and cross our fingers.
             let scope = global.upcast::<WorkerGlobalScope>();
+            scope.set_csp_list(owner_csp_list);
+            if let Some(ref metadata) = metadata {
+                scope.initialize_csp_list(metadata);
+            }
 
             unsafe {
                 // Handle interrupt requests
@@ -342,7 +362,27 @@ impl DedicatedWorkerGlobalScope {
 
             {
                 let _ar = AutoWorkerReset::new(&global, worker.clone());
-                scope.execute_script(DOMString::from(source));
+                match source {
+                    Some(source) => scope.execute_script(DOMString::from(source)),
+                    None => {
+                        let global_scope = scope.upcast::<GlobalScope>();
+                        match fetch_module_worker_script_graph(global_scope, worker_url, credentials_mode) {
+                            Ok(module) => run_module_script(global_scope, &module),
+                            Err(error) => {
+                                // The error is forwarded to the worker object, as nothing can
+                                // listen to it in the worker yet.
+                                let error_info = ErrorInfo {
+                                    message: format!("Failed to load module script: {:?}", error),
+                                    filename: serialized_worker_url,
+                                    lineno: 0,
+                                    column: 0,
+                                };
+                                global_scope.report_an_error(error_info, HandleValue::null());
+                                return;
+                            },
+                        }
+                    },
+                }
             }
 
             let reporter_name = format!("dedicated-worker-reporter-{}", random::<u64>());
@@ -375,14 +415,14 @@ impl DedicatedWorkerGlobalScope {
 
     fn handle_script_event(&self, msg: WorkerScriptMsg) {
         match msg {
-            WorkerScriptMsg::DOMMessage(data) => {
+            WorkerScriptMsg::DOMMessage(data, ports) => {
                 let scope = self.upcast::<WorkerGlobalScope>();
                 let target = self.upcast();
                 let _ac = JSAutoCompartment::new(scope.get_cx(),
                                                  scope.reflector().get_jsobject().get());
                 rooted!(in(scope.get_cx()) let mut message = UndefinedValue());
-                data.read(scope.upcast(), message.handle_mut());
-                MessageEvent::dispatch_jsval(target, scope.upcast(), message.handle(), None);
+                let ports = data.read_with_transfer(scope.upcast(), ports, message.handle_mut());
+                MessageEvent::dispatch_jsval(target, scope.upcast(), message.handle(), None, ports);
             },
             WorkerScriptMsg::Common(msg) => {
                 self.upcast::<WorkerGlobalScope>().process_event(msg);
@@ -475,12 +515,16 @@ unsafe extern "C" fn interrupt_callback(cx: *mut JSContext) -> bool {
 impl DedicatedWorkerGlobalScopeMethods for DedicatedWorkerGlobalScope {
     #[allow(unsafe_code)]
     // https://html.spec.whatwg.org/multipage/#dom-dedicatedworkerglobalscope-postmessage
-    unsafe fn PostMessage(&self, cx: *mut JSContext, message: HandleValue) -> ErrorResult {
-        let data = StructuredCloneData::write(cx, message)?;
+    unsafe fn PostMessage(&self,
+                          cx: *mut JSContext,
+                          message: HandleValue,
+                          transfer: Option<Vec<DomRoot<MessagePort>>>)
+                          -> ErrorResult {
+        let (data, ports) = StructuredCloneData::write_with_transfer(cx, message, transfer.unwrap_or(vec![]))?;
         let worker = self.worker.borrow().as_ref().unwrap().clone();
         let pipeline_id = self.upcast::<GlobalScope>().pipeline_id();
         let task = Box::new(task!(post_worker_message: move || {
-            Worker::handle_message(worker, data);
+            Worker::handle_message(worker, data, ports);
         }));
         // TODO: Change this task source to a new `unshipped-port-message-queue` task source
         self.parent_sender.send(CommonScriptMsg::Task(
@@ -6,6 +6,7 @@ use cookie_rs;
 use devtools_traits::ScriptToDevtoolsControlMsg;
 use document_loader::{DocumentLoader, LoadType};
 use dom::activation::{ActivationSource, synthetic_click_activation};
+use dom::animation::Animation;
 use dom::attr::Attr;
 use dom::beforeunloadevent::BeforeUnloadEvent;
 use dom::bindings::callback::ExceptionHandling;
@@ -14,7 +15,10 @@ use dom::bindings::codegen::Bindings::BeforeUnloadEventBinding::BeforeUnloadEven
 use dom::bindings::codegen::Bindings::DocumentBinding;
 use dom::bindings::codegen::Bindings::DocumentBinding::{DocumentMethods, DocumentReadyState, ElementCreationOptions};
 use dom::bindings::codegen::Bindings::EventBinding::EventBinding::EventMethods;
+use dom::bindings::codegen::Bindings::HTMLFormElementBinding::HTMLFormElementMethods;
 use dom::bindings::codegen::Bindings::HTMLIFrameElementBinding::HTMLIFrameElementBinding::HTMLIFrameElementMethods;
+use dom::bindings::codegen::Bindings::HTMLInputElementBinding::HTMLInputElementMethods;
+use dom::bindings::codegen::Bindings::HTMLTextAreaElementBinding::HTMLTextAreaElementMethods;
 use dom::bindings::codegen::Bindings::NodeBinding::NodeMethods;
 use dom::bindings::codegen::Bindings::NodeFilterBinding::NodeFilter;
 use dom::bindings::codegen::Bindings::PerformanceBinding::PerformanceMethods;
@@ -32,10 +36,12 @@ use dom::bindings::xmlname::{namespace_from_domstring, validate_and_extract, xml
 use dom::bindings::xmlname::XMLName::InvalidXMLName;
 use dom::closeevent::CloseEvent;
 use dom::comment::Comment;
+use dom::cssanimation::CSSAnimation;
 use dom::cssstylesheet::CSSStyleSheet;
 use dom::customelementregistry::CustomElementDefinition;
 use dom::customevent::CustomEvent;
 use dom::documentfragment::DocumentFragment;
+use dom::documenttimeline::DocumentTimeline;
 use dom::documenttype::DocumentType;
 use dom::domimplementation::DOMImplementation;
 use dom::element::{Element, ElementCreator, ElementPerformFullscreenEnter, ElementPerformFullscreenExit};
@@ -44,6 +50,7 @@ use dom::errorevent::ErrorEvent;
 use dom::event::{Event, EventBubbles, EventCancelable, EventDefault, EventStatus};
 use dom::eventtarget::EventTarget;
 use dom::focusevent::FocusEvent;
+use dom::fontfaceset::FontFaceSet;
 use dom::globalscope::GlobalScope;
 use dom::hashchangeevent::HashChangeEvent;
 use dom::htmlanchorelement::HTMLAnchorElement;
@@ -58,8 +65,10 @@ use dom::htmlheadelement::HTMLHeadElement;
 use dom::htmlhtmlelement::HTMLHtmlElement;
 use dom::htmliframeelement::HTMLIFrameElement;
 use dom::htmlimageelement::HTMLImageElement;
+use dom::htmlinputelement::{HTMLInputElement, InputType};
 use dom::htmlmetaelement::HTMLMetaElement;
-use dom::htmlscriptelement::{HTMLScriptElement, ScriptResult};
+use dom::htmlscriptelement::{ExternalScriptKind, HTMLScriptElement, ScriptOrigin, ScriptResult};
+use dom::htmltextareaelement::HTMLTextAreaElement;
 use dom::htmltitleelement::HTMLTitleElement;
 use dom::keyboardevent::KeyboardEvent;
 use dom::location::Location;
@@ -77,8 +86,9 @@ use dom::progressevent::ProgressEvent;
 use dom::promise::Promise;
 use dom::range::Range;
 use dom::servoparser::ServoParser;
+use dom::shadowroot::ShadowRoot;
 use dom::storageevent::StorageEvent;
-use dom::stylesheetlist::StyleSheetList;
+use dom::stylesheetlist::{StyleSheetList, StyleSheetListOwner};
 use dom::text::Text;
 use dom::touch::Touch;
 use dom::touchevent::TouchEvent;
@@ -92,7 +102,7 @@ use dom::windowproxy::WindowProxy;
 use dom_struct::dom_struct;
 use embedder_traits::EmbedderMsg;
 use encoding_rs::{Encoding, UTF_8};
-use euclid::Point2D;
+use euclid::{Point2D, Vector2D};
 use fetch::FetchCanceller;
 use html5ever::{LocalName, Namespace, QualName};
 use hyper::header::{Header, SetCookie};
@@ -106,6 +116,7 @@ use msg::constellation_msg::{BrowsingContextId, Key, KeyModifiers, KeyState};
 use net_traits::{FetchResponseMsg, IpcSend, ReferrerPolicy};
 use net_traits::CookieSource::NonHTTP;
 use net_traits::CoreResourceMsg::{GetCookiesForUrl, SetCookiesForUrl};
+use net_traits::csp::CspList;
 use net_traits::pub_domains::is_pub_domain;
 use net_traits::request::RequestInit;
 use net_traits::response::HttpsState;
@@ -114,9 +125,10 @@ use profile_traits::ipc as profile_ipc;
 use profile_traits::time::{TimerMetadata, TimerMetadataFrameType, TimerMetadataReflowType};
 use ref_slice::ref_slice;
 use script_layout_interface::message::{Msg, NodesFromPointQueryType, QueryMsg, ReflowGoal};
+use script_module::{link_module_graph, module_graph_fetched};
 use script_runtime::{CommonScriptMsg, ScriptThreadEventCategory};
 use script_thread::{MainThreadScriptMsg, ScriptThread};
-use script_traits::{AnimationState, DocumentActivity, MouseButton, MouseEventType};
+use script_traits::{AnimationState, DocumentActivity, DocumentSessionState, MouseButton, MouseEventType};
 use script_traits::{MsDuration, ScriptMsg, TouchEventType, TouchId, UntrustedNodeAddress};
 use servo_arc::Arc;
 use servo_atoms::Atom;
@@ -135,6 +147,7 @@ use std::time::{Duration, Instant};
 use style::attr::AttrValue;
 use style::context::QuirksMode;
 use style::invalidation::element::restyle_hints::RestyleHint;
+use style::invalidation::media_queries::{MediaListKey, ToMediaListKey};
 use style::media_queries::{Device, MediaList, MediaType};
 use style::selector_parser::{RestyleDamage, Snapshot};
 use style::shared_lock::{SharedRwLock as StyleSharedRwLock, SharedRwLockReadGuard};
@@ -156,6 +169,10 @@ const SPURIOUS_ANIMATION_FRAME_THRESHOLD: u8 = 5;
 /// The amount of time between fake `requestAnimationFrame()`s.
 const FAKE_REQUEST_ANIMATION_FRAME_DELAY: u64 = 16;
 
+/// The longest time, in milliseconds, by which a report of the scroll offset and form contents
+/// of a document to the constellation is delayed.
+const SESSION_STATE_REPORT_DELAY: u64 = 1000;
+
 pub enum TouchEventResult {
     Processed(bool),
     Forwarded,
@@ -208,10 +225,10 @@ impl PendingRestyle {
 
 #[derive(Clone, JSTraceable, MallocSizeOf)]
 #[must_root]
-struct StyleSheetInDocument {
+pub struct StyleSheetInDocument {
     #[ignore_malloc_size_of = "Arc"]
-    sheet: Arc<Stylesheet>,
-    owner: Dom<Element>,
+    pub sheet: Arc<Stylesheet>,
+    pub owner: Dom<Element>,
 }
 
 impl fmt::Debug for StyleSheetInDocument {
@@ -226,6 +243,12 @@ impl PartialEq for StyleSheetInDocument {
     }
 }
 
+impl ToMediaListKey for StyleSheetInDocument {
+    fn to_media_list_key(&self) -> MediaListKey {
+        self.sheet.to_media_list_key()
+    }
+}
+
 impl ::style::stylesheets::StylesheetInDocument for StyleSheetInDocument {
     fn origin(&self, guard: &SharedRwLockReadGuard) -> Origin {
         self.sheet.origin(guard)
@@ -282,11 +305,18 @@ pub struct Document {
     /// List of stylesheets associated with nodes in this document. |None| if the list needs to be refreshed.
     stylesheets: DomRefCell<DocumentStylesheetSet<StyleSheetInDocument>>,
     stylesheet_list: MutNullableDom<StyleSheetList>,
+    /// The shadow roots whose host is connected to this document, which the
+    /// layout thread needs to flush the stylesheets of.
+    shadow_roots: DomRefCell<Vec<Dom<ShadowRoot>>>,
+    /// Whether any of the shadow roots above had their stylesheets changed.
+    shadow_roots_styles_changed: Cell<bool>,
     ready_state: Cell<DocumentReadyState>,
     /// Whether the DOMContentLoaded event has already been dispatched.
     domcontentloaded_dispatched: Cell<bool>,
     /// The element that has most recently requested focus for itself.
     possibly_focused: MutNullableDom<Element>,
+    /// Whether the current focus transaction was started by a pointing device.
+    focus_from_pointer: Cell<bool>,
     /// The element that currently has the document focus context.
     focused: MutNullableDom<Element>,
     /// The script element that is currently executing.
@@ -301,6 +331,8 @@ pub struct Document {
     asap_in_order_scripts_list: PendingInOrderScriptVec,
     /// <https://html.spec.whatwg.org/multipage/#set-of-scripts-that-will-execute-as-soon-as-possible>
     asap_scripts_set: DomRefCell<Vec<Dom<HTMLScriptElement>>>,
+    /// The module scripts whose module graph is being fetched.
+    pending_module_graphs: DomRefCell<Vec<PendingModuleGraph>>,
     /// <https://html.spec.whatwg.org/multipage/#concept-n-noscript>
     /// True if scripting is enabled for all scripts in this document
     scripting_enabled: bool,
@@ -315,6 +347,17 @@ pub struct Document {
     /// Tracking this is not necessary for correctness. Instead, it is an optimization to avoid
     /// sending needless `ChangeRunningAnimationsState` messages to the compositor.
     running_animation_callbacks: Cell<bool>,
+    /// <https://drafts.csswg.org/web-animations/#the-documents-default-timeline>
+    timeline: MutNullableDom<DocumentTimeline>,
+    /// The animations created by script in this document which are either
+    /// running or still relevant.
+    animations: DomRefCell<Vec<Dom<Animation>>>,
+    /// The objects handed out to script for the CSS animations of this document.
+    css_animations: DomRefCell<Vec<Dom<CSSAnimation>>>,
+    /// The identifier of the last animation created in this document.
+    animation_id: Cell<usize>,
+    /// <https://drafts.csswg.org/css-font-loading/#font-face-source>
+    fonts: MutNullableDom<FontFaceSet>,
     /// Tracks all outstanding loads related to this document.
     loader: DomRefCell<DocumentLoader>,
     /// The current active HTML parser, to allow resuming after interruptions.
@@ -350,6 +393,8 @@ pub struct Document {
     origin: MutableOrigin,
     ///  https://w3c.github.io/webappsec-referrer-policy/#referrer-policy-states
     referrer_policy: Cell<Option<ReferrerPolicy>>,
+    /// <https://w3c.github.io/webappsec-csp/#concept-document-csp-list>
+    csp_list: DomRefCell<Option<CspList>>,
     /// <https://html.spec.whatwg.org/multipage/#dom-document-referrer>
     referrer: Option<String>,
     /// <https://html.spec.whatwg.org/multipage/#target-element>
@@ -394,6 +439,49 @@ pub struct Document {
     fired_unload: Cell<bool>,
     /// List of responsive images
     responsive_images: DomRefCell<Vec<Dom<HTMLImageElement>>>,
+    /// The persisted state to restore once the document has loaded, if it is the document of a
+    /// restored session history entry.
+    #[ignore_malloc_size_of = "Defined in script_traits"]
+    restored_session_state: DomRefCell<Option<DocumentSessionState>>,
+    /// Whether a report of the state of this document to the constellation is scheduled.
+    session_state_report_pending: Cell<bool>,
+}
+
+/// The key under which the value of a text control is persisted with the session history,
+/// which is its id or, failing that, its name. The values of hidden controls come from the
+/// server and the ones of controls with autofill turned off are sensitive, so neither are
+/// persisted.
+fn session_state_form_control_key(element: &Element) -> Option<String> {
+    let form_owner = match element.downcast::<HTMLInputElement>() {
+        Some(input) => {
+            let input_type = input.input_type();
+            if !input_type.is_textual() || input_type == InputType::Hidden {
+                return None;
+            }
+            input.form_owner()
+        },
+        None => element.downcast::<HTMLTextAreaElement>()?.form_owner(),
+    };
+    // https://html.spec.whatwg.org/multipage/#autofill-anchor-mantle
+    let autocomplete = element.get_string_attribute(&local_name!("autocomplete"));
+    let autofill_off = if autocomplete.trim().is_empty() {
+        form_owner.map_or(false, |form| form.Autocomplete() == "off")
+    } else {
+        autocomplete.trim().eq_ignore_ascii_case("off")
+    };
+    if autofill_off {
+        return None;
+    }
+    let id = element.get_string_attribute(&local_name!("id"));
+    let key = if id.is_empty() {
+        element.get_string_attribute(&local_name!("name"))
+    } else {
+        id
+    };
+    if key.is_empty() {
+        return None;
+    }
+    Some(String::from(key))
 }
 
 #[derive(JSTraceable, MallocSizeOf)]
@@ -581,6 +669,7 @@ impl Document {
         // not the document element. Needs some layout changes to make
         // that workable.
         self.stylesheets.borrow().has_changed() ||
+        self.shadow_roots_styles_changed.get() ||
         self.GetDocumentElement().map_or(false, |root| {
             root.upcast::<Node>().has_dirty_descendants() ||
             !self.pending_restyles.borrow().is_empty() ||
@@ -844,6 +933,7 @@ impl Document {
     /// `request_focus` before `commit_focus_transaction` is called will receive focus.
     pub fn begin_focus_transaction(&self) {
         self.possibly_focused.set(None);
+        self.focus_from_pointer.set(false);
     }
 
     /// Request that the given element receive focus once the current transaction is complete.
@@ -869,12 +959,21 @@ impl Document {
             if elem.input_method_type().is_some() {
                 self.send_to_embedder(EmbedderMsg::HideIME);
             }
+
+            // The contents of a text control are persisted once the user is done editing it.
+            if session_state_form_control_key(elem).is_some() {
+                self.schedule_session_state_report();
+            }
         }
 
         self.focused.set(self.possibly_focused.get().r());
 
         if let Some(ref elem) = self.focused.get() {
             elem.set_focus_state(true);
+            // Elements focused with a pointing device only match `:focus-visible` if they take
+            // keyboard input.
+            // https://drafts.csswg.org/selectors-4/#the-focus-visible-pseudo
+            elem.set_focus_visible_state(!self.focus_from_pointer.get() || elem.input_method_type().is_some());
             let node = elem.upcast::<Node>();
             // FIXME: pass appropriate relatedTarget
             self.fire_focus_event(FocusEventType::Focus, node, None);
@@ -956,6 +1055,7 @@ impl Document {
             }
 
             self.begin_focus_transaction();
+            self.focus_from_pointer.set(true);
         }
 
         // https://w3c.github.io/uievents/#event-type-click
@@ -982,6 +1082,7 @@ impl Document {
             point_in_node,
         );
         let event = event.upcast::<Event>();
+        event.set_composed(true);
 
         // https://w3c.github.io/uievents/#trusted-events
         event.set_trusted(true);
@@ -1056,7 +1157,9 @@ impl Document {
                     None,
                     None
                 );
-                event.upcast::<Event>().fire(target.upcast());
+                let event = event.upcast::<Event>();
+                event.set_composed(true);
+                event.fire(target.upcast());
 
                 // When a double click occurs, self.last_click_info is left as None so that a
                 // third sequential click will not cause another double click.
@@ -1092,6 +1195,7 @@ impl Document {
             None
         );
         let event = mouse_event.upcast::<Event>();
+        event.set_composed(true);
         event.fire(target);
     }
 
@@ -1281,6 +1385,7 @@ impl Document {
             false,
         );
         let event = event.upcast::<Event>();
+        event.set_composed(true);
         let result = event.fire(&target);
 
         window.reflow(ReflowGoal::Full, ReflowReason::MouseEvent);
@@ -1341,6 +1446,7 @@ impl Document {
                                           None,
                                           props.key_code);
         let event = keyevent.upcast::<Event>();
+        event.set_composed(true);
         event.fire(target);
         let mut cancel_state = event.get_cancel_state();
 
@@ -1367,6 +1473,7 @@ impl Document {
                                            props.char_code,
                                            0);
             let ev = event.upcast::<Event>();
+            ev.set_composed(true);
             ev.fire(target);
             cancel_state = ev.get_cancel_state();
         }
@@ -1489,6 +1596,13 @@ impl Document {
         self.animation_frame_ident.set(ident);
         self.animation_frame_list.borrow_mut().push((ident, Some(callback)));
 
+        self.schedule_animation_frame();
+
+        ident
+    }
+
+    /// Makes sure the animation frame callbacks run at the next frame.
+    fn schedule_animation_frame(&self) {
         // TODO: Should tick animation only when document is visible
 
         // If we are running 'fake' animation frames, we unconditionally
@@ -1510,8 +1624,6 @@ impl Document {
             let event = ScriptMsg::ChangeRunningAnimationsState(AnimationState::AnimationCallbacksPresent);
             self.window().send_to_constellation(event);
         }
-
-        ident
     }
 
     /// <https://html.spec.whatwg.org/multipage/#dom-window-cancelanimationframe>
@@ -1532,6 +1644,7 @@ impl Document {
         self.running_animation_callbacks.set(true);
         let was_faking_animation_frames = self.is_faking_animation_frames();
         let timing = self.global().performance().Now();
+        let animations_running = self.update_animations();
 
         for (_, callback) in animation_frame_list.drain(..) {
             if let Some(callback) = callback {
@@ -1566,8 +1679,13 @@ impl Document {
         // constellation to stop giving us video refresh callbacks, to save energy. (A spurious
         // animation frame is one in which the callback did not mutate the DOM—that is, an
         // animation frame that wasn't actually used for animation.)
+        //
+        // Running script animations need to be ticked at every frame, so they
+        // keep the callbacks present and don't make frames spurious.
         let is_empty = self.animation_frame_list.borrow().is_empty();
-        if is_empty || (!was_faking_animation_frames && self.is_faking_animation_frames()) {
+        if (is_empty && !animations_running) ||
+            (!was_faking_animation_frames && self.is_faking_animation_frames())
+        {
             if is_empty {
                 // If the current animation frame list in the DOM instance is empty,
                 // we can reuse the original `Vec<T>` that we put on the stack to
@@ -1583,7 +1701,7 @@ impl Document {
         }
 
         // Update the counter of spurious animation frames.
-        if spurious {
+        if spurious && !animations_running {
             if self.spurious_animation_frames.get() < SPURIOUS_ANIMATION_FRAME_THRESHOLD {
                 self.spurious_animation_frames.set(self.spurious_animation_frames.get() + 1)
             }
@@ -1592,6 +1710,98 @@ impl Document {
         }
     }
 
+    /// Returns a new identifier for an animation of this document.
+    pub fn next_animation_id(&self) -> usize {
+        let id = self.animation_id.get() + 1;
+        self.animation_id.set(id);
+        id
+    }
+
+    /// Keeps track of an animation which started playing, so that it is
+    /// ticked at every frame.
+    pub fn register_animation(&self, animation: &Animation) {
+        {
+            let mut animations = self.animations.borrow_mut();
+            if !animations.iter().any(|other| *other == animation) {
+                animations.push(Dom::from_ref(animation));
+            }
+        }
+        self.schedule_animation_frame();
+    }
+
+    /// <https://drafts.csswg.org/web-animations/#update-animations-and-send-events>
+    ///
+    /// Returns whether some animations are still running.
+    fn update_animations(&self) -> bool {
+        rooted_vec!(let animations <- self.animations.borrow().iter().map(|animation| {
+            DomRoot::from_ref(&**animation)
+        }));
+        let mut running = false;
+        for animation in animations.iter() {
+            running |= animation.tick();
+        }
+        self.animations.borrow_mut().retain(|animation| animation.is_relevant());
+        running
+    }
+
+    /// Returns the script animations of this document targeting the given
+    /// element, or all of them.
+    pub fn script_animations(&self, target: Option<&Element>) -> Vec<DomRoot<Animation>> {
+        self.animations
+            .borrow()
+            .iter()
+            .filter(|animation| animation.is_relevant())
+            .filter(|animation| {
+                target.map_or(true, |target| animation.target().map_or(false, |other| &*other == target))
+            })
+            .map(|animation| DomRoot::from_ref(&**animation))
+            .collect()
+    }
+
+    /// Returns the CSS animations of the given element, or of the whole
+    /// document, reusing the objects previously handed out for them.
+    pub fn css_animations(&self, target: Option<&Element>) -> Vec<DomRoot<CSSAnimation>> {
+        let address = target.map(|target| target.upcast::<Node>().to_trusted_node_address());
+        let responses = self.window.css_animations_query(address);
+
+        let animations = responses.into_iter().filter_map(|(node, response)| {
+            let element = DomRoot::downcast::<Element>(node)?;
+            let existing = self.css_animations.borrow().iter().find(|animation| {
+                animation.animation_name() == &response.name &&
+                    animation.target().map_or(false, |other| other == element)
+            }).map(|animation| DomRoot::from_ref(&**animation));
+            Some(match existing {
+                Some(animation) => {
+                    animation.update_from_layout(response.timing, response.current_time, response.paused);
+                    animation
+                },
+                None => CSSAnimation::new(
+                    &self.window,
+                    &element,
+                    response.name,
+                    response.timing,
+                    response.current_time,
+                    response.paused,
+                ),
+            })
+        }).collect::<Vec<_>>();
+
+        let mut css_animations = self.css_animations.borrow_mut();
+        css_animations.retain(|animation| {
+            target.map_or(false, |target| animation.target().map_or(false, |other| &*other != target))
+        });
+        css_animations.extend(animations.iter().map(|animation| Dom::from_ref(&**animation)));
+        animations
+    }
+
+    /// Lets the font face set of this document know that it may be done
+    /// loading, after the document or one of its web fonts finished loading.
+    pub fn update_font_load_status(&self) {
+        if let Some(fonts) = self.fonts.get() {
+            fonts.update_status();
+        }
+    }
+
     pub fn fetch_async(&self, load: LoadType,
                        request: RequestInit,
                        fetch_target: IpcSender<FetchResponseMsg>) {
@@ -1770,6 +1980,7 @@ impl Document {
             let global_scope = self.window.upcast::<GlobalScope>();
             // Step 1 of clean-up steps.
             global_scope.close_event_sources();
+            global_scope.cancel_ongoing_fetches();
             let msg = ScriptMsg::DiscardDocument;
             let _ = global_scope.script_to_constellation_chan().send(msg);
         }
@@ -1829,11 +2040,15 @@ impl Document {
 
                 window.reflow(ReflowGoal::Full, ReflowReason::DocumentLoaded);
 
+                document.update_font_load_status();
+
                 document.notify_constellation_load();
 
                 if let Some(fragment) = document.url().fragment() {
                     document.check_and_scroll_fragment(fragment);
                 }
+
+                document.restore_session_state();
             }),
             self.window.upcast(),
         ).unwrap();
@@ -1954,6 +2169,44 @@ impl Document {
         }
     }
 
+    /// Makes `element` wait for every module of the graph of `script` to be fetched, after which
+    /// the graph is linked and handed to the list of scripts given by `kind`.
+    pub fn add_pending_module_graph(&self,
+                                    element: &HTMLScriptElement,
+                                    script: ScriptOrigin,
+                                    kind: ExternalScriptKind) {
+        self.pending_module_graphs.borrow_mut().push(PendingModuleGraph {
+            element: Dom::from_ref(element),
+            script: script,
+            kind: kind,
+        });
+        self.process_pending_module_graphs();
+    }
+
+    /// Hands the module graphs which finished fetching to their script elements, which is
+    /// checked whenever a module of this document's module map is fetched.
+    ///
+    /// <https://html.spec.whatwg.org/multipage/#fetch-the-descendants-of-and-link-a-module-script>
+    #[allow(unrooted_must_root)]
+    pub fn process_pending_module_graphs(&self) {
+        let global = self.global();
+        loop {
+            let index = self.pending_module_graphs.borrow().iter().position(|graph| {
+                module_graph_fetched(&global, graph.script.module_tree().unwrap()).is_some()
+            });
+            let graph = match index {
+                Some(index) => self.pending_module_graphs.borrow_mut().remove(index),
+                None => return,
+            };
+            let PendingModuleGraph { element, script, kind } = graph;
+            let result = {
+                let root = script.module_tree().unwrap();
+                module_graph_fetched(&global, root).unwrap().map(|()| link_module_graph(&global, root))
+            };
+            element.script_loaded(kind, result.map(|()| script));
+        }
+    }
+
     // https://html.spec.whatwg.org/multipage/#list-of-scripts-that-will-execute-when-the-document-has-finished-parsing
     pub fn add_deferred_script(&self, script: &HTMLScriptElement) {
         self.deferred_scripts.push(script);
@@ -2059,6 +2312,91 @@ impl Document {
         self.window().send_to_constellation(ScriptMsg::LoadComplete);
     }
 
+    /// Report the state of this document to the constellation within
+    /// `SESSION_STATE_REPORT_DELAY` milliseconds, along with any other change to it in the
+    /// meantime, since collecting it walks the whole document.
+    pub fn schedule_session_state_report(&self) {
+        if !self.window.is_top_level() || self.session_state_report_pending.get() {
+            return;
+        }
+        self.session_state_report_pending.set(true);
+        let callback = SessionStateReportCallback {
+            document: Trusted::new(self),
+        };
+        self.global().schedule_callback(
+            OneshotTimerCallback::SessionStateReport(callback),
+            MsDuration::new(SESSION_STATE_REPORT_DELAY),
+        );
+    }
+
+    /// Report the state of this document to the constellation right away if a report of it is
+    /// pending, so that the report reaches the constellation before a navigation away from it.
+    pub fn flush_session_state_report(&self) {
+        if self.session_state_report_pending.get() {
+            self.notify_constellation_session_state();
+        }
+    }
+
+    /// Report the scroll offset and form contents of this document to the constellation, which
+    /// persists them with the session history. Only top-level documents are reported.
+    pub fn notify_constellation_session_state(&self) {
+        self.session_state_report_pending.set(false);
+        if !self.window.is_top_level() || self.browsing_context().is_none() {
+            return;
+        }
+        let form_values = self.upcast::<Node>()
+            .traverse_preorder()
+            .filter_map(DomRoot::downcast::<Element>)
+            .filter_map(|element| {
+                let key = session_state_form_control_key(&element)?;
+                let value = match element.downcast::<HTMLInputElement>() {
+                    Some(input) => input.Value(),
+                    None => element.downcast::<HTMLTextAreaElement>()?.Value(),
+                };
+                Some((key, String::from(value)))
+            })
+            .collect();
+        let origin = self.window.current_viewport().origin;
+        let session_state = DocumentSessionState {
+            scroll_offset: Vector2D::new(origin.x.to_f32_px(), origin.y.to_f32_px()),
+            form_values: form_values,
+        };
+        self.window().send_to_constellation(ScriptMsg::SetSessionState(session_state));
+    }
+
+    pub fn set_restored_session_state(&self, session_state: DocumentSessionState) {
+        *self.restored_session_state.borrow_mut() = Some(session_state);
+    }
+
+    /// Restore the form contents and scroll offset of a restored session history entry.
+    fn restore_session_state(&self) {
+        let session_state = match self.restored_session_state.borrow_mut().take() {
+            Some(session_state) => session_state,
+            None => return,
+        };
+        let mut form_values: HashMap<String, String> =
+            session_state.form_values.into_iter().collect();
+        let elements: Vec<DomRoot<Element>> = self.upcast::<Node>()
+            .traverse_preorder()
+            .filter_map(DomRoot::downcast::<Element>)
+            .collect();
+        for element in elements {
+            let value = match session_state_form_control_key(&element)
+                .and_then(|key| form_values.remove(&key))
+            {
+                Some(value) => DOMString::from(value),
+                None => continue,
+            };
+            if let Some(input) = element.downcast::<HTMLInputElement>() {
+                let _ = input.SetValue(value);
+            } else if let Some(textarea) = element.downcast::<HTMLTextAreaElement>() {
+                textarea.SetValue(value);
+            }
+        }
+        let offset = session_state.scroll_offset;
+        self.window.scroll(offset.x as f64, offset.y as f64, ScrollBehavior::Instant);
+    }
+
     pub fn set_current_parser(&self, script: Option<&ServoParser>) {
         self.current_parser.set(script);
     }
@@ -2167,6 +2505,7 @@ impl Document {
                                     0i32,
                                     related_target);
         let event = event.upcast::<Event>();
+        event.set_composed(true);
         event.set_trusted(true);
         let target = node.upcast();
         event.fire(target);
@@ -2256,6 +2595,9 @@ pub trait LayoutDocumentHelpers {
     unsafe fn will_paint(&self);
     unsafe fn quirks_mode(&self) -> QuirksMode;
     unsafe fn style_shared_lock(&self) -> &StyleSharedRwLock;
+    unsafe fn shadow_roots(&self) -> Vec<LayoutDom<ShadowRoot>>;
+    unsafe fn shadow_roots_styles_changed(&self) -> bool;
+    unsafe fn flush_shadow_roots_stylesheets(&self);
 }
 
 #[allow(unsafe_code)]
@@ -2297,6 +2639,21 @@ impl LayoutDocumentHelpers for LayoutDom<Document> {
     unsafe fn style_shared_lock(&self) -> &StyleSharedRwLock {
         (*self.unsafe_get()).style_shared_lock()
     }
+
+    #[inline]
+    unsafe fn shadow_roots(&self) -> Vec<LayoutDom<ShadowRoot>> {
+        (*self.unsafe_get()).shadow_roots.borrow_for_layout().iter().map(|root| root.to_layout()).collect()
+    }
+
+    #[inline]
+    unsafe fn shadow_roots_styles_changed(&self) -> bool {
+        (*self.unsafe_get()).shadow_roots_styles_changed.get()
+    }
+
+    #[inline]
+    unsafe fn flush_shadow_roots_stylesheets(&self) {
+        (*self.unsafe_get()).shadow_roots_styles_changed.set(false)
+    }
 }
 
 // https://html.spec.whatwg.org/multipage/#is-a-registrable-domain-suffix-of-or-is-equal-to
@@ -2436,9 +2793,12 @@ impl Document {
             },
             stylesheets: DomRefCell::new(DocumentStylesheetSet::new()),
             stylesheet_list: MutNullableDom::new(None),
+            shadow_roots: DomRefCell::new(vec![]),
+            shadow_roots_styles_changed: Cell::new(false),
             ready_state: Cell::new(ready_state),
             domcontentloaded_dispatched: Cell::new(domcontentloaded_dispatched),
             possibly_focused: Default::default(),
+            focus_from_pointer: Cell::new(false),
             focused: Default::default(),
             current_script: Default::default(),
             pending_parsing_blocking_script: Default::default(),
@@ -2446,10 +2806,16 @@ impl Document {
             deferred_scripts: Default::default(),
             asap_in_order_scripts_list: Default::default(),
             asap_scripts_set: Default::default(),
+            pending_module_graphs: Default::default(),
             scripting_enabled: has_browsing_context == HasBrowsingContext::Yes,
             animation_frame_ident: Cell::new(0),
             animation_frame_list: DomRefCell::new(vec![]),
             running_animation_callbacks: Cell::new(false),
+            timeline: Default::default(),
+            animations: DomRefCell::new(vec![]),
+            css_animations: DomRefCell::new(vec![]),
+            animation_id: Cell::new(0),
+            fonts: Default::default(),
             loader: DomRefCell::new(doc_loader),
             current_parser: Default::default(),
             reflow_timeout: Cell::new(None),
@@ -2470,6 +2836,7 @@ impl Document {
             origin: origin,
             referrer: referrer,
             referrer_policy: Cell::new(referrer_policy),
+            csp_list: DomRefCell::new(None),
             target_element: MutNullableDom::new(None),
             last_click_info: DomRefCell::new(None),
             ignore_destructive_writes_counter: Default::default(),
@@ -2485,7 +2852,9 @@ impl Document {
             page_showing: Cell::new(false),
             salvageable: Cell::new(true),
             fired_unload: Cell::new(false),
-            responsive_images: Default::default()
+            responsive_images: Default::default(),
+            restored_session_state: Default::default(),
+            session_state_report_pending: Cell::new(false),
         }
     }
 
@@ -2660,6 +3029,21 @@ impl Document {
         }
     }
 
+    pub fn register_shadow_root(&self, shadow_root: &ShadowRoot) {
+        self.shadow_roots.borrow_mut().push(Dom::from_ref(shadow_root));
+        self.invalidate_shadow_roots_stylesheets();
+    }
+
+    pub fn unregister_shadow_root(&self, shadow_root: &ShadowRoot) {
+        self.shadow_roots.borrow_mut().retain(|root| &**root != shadow_root);
+    }
+
+    /// Notes that the stylesheets of a shadow root changed, so that the next
+    /// reflow flushes them.
+    pub fn invalidate_shadow_roots_stylesheets(&self) {
+        self.shadow_roots_styles_changed.set(true);
+    }
+
     /// Returns the number of document stylesheets.
     pub fn stylesheet_count(&self) -> usize {
         self.stylesheets.borrow().len()
@@ -2768,6 +3152,22 @@ impl Document {
         return self.referrer_policy.get();
     }
 
+    /// Adds the policies of `csp_list` to the policies enforced or monitored by this document.
+    pub fn append_csp_list(&self, csp_list: CspList) {
+        if csp_list.is_empty() {
+            return;
+        }
+        self.csp_list.borrow_mut().get_or_insert_with(CspList::default).append(csp_list);
+        // Layout loads the Web fonts of the document.
+        if self.has_browsing_context {
+            self.window.layout_chan().send(Msg::SetCspList(self.get_csp_list())).unwrap();
+        }
+    }
+
+    pub fn get_csp_list(&self) -> Option<CspList> {
+        self.csp_list.borrow().clone()
+    }
+
     pub fn set_target_element(&self, node: Option<&Element>) {
         if let Some(ref element) = self.target_element.get() {
             element.set_target_state(false);
@@ -2975,7 +3375,9 @@ impl ProfilerMetadataFactory for Document {
 impl DocumentMethods for Document {
     // https://drafts.csswg.org/cssom/#dom-document-stylesheets
     fn StyleSheets(&self) -> DomRoot<StyleSheetList> {
-        self.stylesheet_list.or_init(|| StyleSheetList::new(&self.window, Dom::from_ref(&self)))
+        self.stylesheet_list.or_init(|| {
+            StyleSheetList::new(&self.window, StyleSheetListOwner::Document(Dom::from_ref(self)))
+        })
     }
 
     // https://dom.spec.whatwg.org/#dom-document-implementation
@@ -3270,7 +3672,7 @@ impl DocumentMethods for Document {
     // https://dom.spec.whatwg.org/#dom-document-importnode
     fn ImportNode(&self, node: &Node, deep: bool) -> Fallible<DomRoot<Node>> {
         // Step 1.
-        if node.is::<Document>() {
+        if node.is::<Document>() || node.is::<ShadowRoot>() {
             return Err(Error::NotSupported);
         }
 
@@ -3292,9 +3694,14 @@ impl DocumentMethods for Document {
         }
 
         // Step 2.
-        Node::adopt(node, self);
+        if node.is::<ShadowRoot>() {
+            return Err(Error::HierarchyRequest);
+        }
 
         // Step 3.
+        Node::adopt(node, self);
+
+        // Step 4.
         Ok(DomRoot::from_ref(node))
     }
 
@@ -4183,6 +4590,26 @@ impl DocumentMethods for Document {
     fn ExitFullscreen(&self) -> Rc<Promise> {
         self.exit_fullscreen()
     }
+
+    // https://drafts.csswg.org/web-animations/#dom-document-timeline
+    fn Timeline(&self) -> DomRoot<DocumentTimeline> {
+        self.timeline.or_init(|| DocumentTimeline::new(&self.window, 0.))
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-document-getanimations
+    fn GetAnimations(&self) -> Vec<DomRoot<Animation>> {
+        let css_animations = self.css_animations(None);
+        css_animations
+            .iter()
+            .map(|animation| DomRoot::from_ref(animation.upcast::<Animation>()))
+            .chain(self.script_animations(None))
+            .collect()
+    }
+
+    // https://drafts.csswg.org/css-font-loading/#dom-fontfacesource-fonts
+    fn Fonts(&self) -> DomRoot<FontFaceSet> {
+        self.fonts.or_init(|| FontFaceSet::new(&self.window))
+    }
 }
 
 fn update_with_current_time_ms(marker: &Cell<u64>) {
@@ -4242,6 +4669,21 @@ impl FakeRequestAnimationFrameCallback {
     }
 }
 
+/// Reports the state of a document to the constellation once the delay scheduled by
+/// `Document::schedule_session_state_report` has elapsed.
+#[derive(JSTraceable, MallocSizeOf)]
+pub struct SessionStateReportCallback {
+    /// The document.
+    #[ignore_malloc_size_of = "non-owning"]
+    document: Trusted<Document>,
+}
+
+impl SessionStateReportCallback {
+    pub fn invoke(self) {
+        self.document.root().flush_session_state_report();
+    }
+}
+
 #[derive(JSTraceable, MallocSizeOf)]
 pub enum AnimationFrameCallback {
     DevtoolsFramerateTick { actor_name: String },
@@ -4301,6 +4743,14 @@ impl PendingInOrderScriptVec {
     }
 }
 
+#[derive(JSTraceable, MallocSizeOf)]
+#[must_root]
+struct PendingModuleGraph {
+    element: Dom<HTMLScriptElement>,
+    script: ScriptOrigin,
+    kind: ExternalScriptKind,
+}
+
 #[derive(JSTraceable, MallocSizeOf)]
 #[must_root]
 struct PendingScript {
@@ -0,0 +1,39 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::animationtimeline::AnimationTimeline;
+use dom::bindings::codegen::Bindings::DocumentTimelineBinding;
+use dom::bindings::codegen::Bindings::DocumentTimelineBinding::DocumentTimelineOptions;
+use dom::bindings::error::Fallible;
+use dom::bindings::reflector::reflect_dom_object;
+use dom::bindings::root::DomRoot;
+use dom::window::Window;
+use dom_struct::dom_struct;
+
+/// <https://drafts.csswg.org/web-animations/#the-documenttimeline-interface>
+#[dom_struct]
+pub struct DocumentTimeline {
+    timeline: AnimationTimeline,
+}
+
+impl DocumentTimeline {
+    fn new_inherited(origin_time: f64) -> DocumentTimeline {
+        DocumentTimeline {
+            timeline: AnimationTimeline::new_inherited(origin_time),
+        }
+    }
+
+    pub fn new(window: &Window, origin_time: f64) -> DomRoot<DocumentTimeline> {
+        reflect_dom_object(
+            Box::new(DocumentTimeline::new_inherited(origin_time)),
+            window,
+            DocumentTimelineBinding::Wrap,
+        )
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-documenttimeline-documenttimeline
+    pub fn Constructor(window: &Window, options: &DocumentTimelineOptions) -> Fallible<DomRoot<DocumentTimeline>> {
+        Ok(DocumentTimeline::new(window, *options.originTime))
+    }
+}
@@ -36,6 +36,13 @@ pub enum DOMErrorName {
     InvalidNodeTypeError = DOMExceptionConstants::INVALID_NODE_TYPE_ERR,
     DataCloneError = DOMExceptionConstants::DATA_CLONE_ERR,
     NotReadableError = DOMExceptionConstants::NOT_READABLE_ERR,
+    // The following names have no legacy code.
+    UnknownError,
+    ConstraintError,
+    DataError,
+    TransactionInactiveError,
+    ReadOnlyError,
+    VersionError,
 }
 
 #[dom_struct]
@@ -62,7 +69,15 @@ impl DOMException {
 impl DOMExceptionMethods for DOMException {
     // https://heycam.github.io/webidl/#dfn-DOMException
     fn Code(&self) -> u16 {
-        self.code as u16
+        match self.code {
+            DOMErrorName::UnknownError |
+            DOMErrorName::ConstraintError |
+            DOMErrorName::DataError |
+            DOMErrorName::TransactionInactiveError |
+            DOMErrorName::ReadOnlyError |
+            DOMErrorName::VersionError => 0,
+            code => code as u16,
+        }
     }
 
     // https://heycam.github.io/webidl/#idl-DOMException-error-names
@@ -95,7 +110,16 @@ impl DOMExceptionMethods for DOMException {
             DOMErrorName::InvalidNodeTypeError =>
                 "The supplied node is incorrect or has an incorrect ancestor for this operation.",
             DOMErrorName::DataCloneError => "The object can not be cloned.",
-            DOMErrorName::NotReadableError => "The I/O read operation failed."
+            DOMErrorName::NotReadableError => "The I/O read operation failed.",
+            DOMErrorName::UnknownError => "The operation failed for an unknown transient reason.",
+            DOMErrorName::ConstraintError =>
+                "A mutation operation in a transaction failed because a constraint was not satisfied.",
+            DOMErrorName::DataError => "Provided data is inadequate.",
+            DOMErrorName::TransactionInactiveError =>
+                "A request was placed against a transaction which is currently not active, or which is finished.",
+            DOMErrorName::ReadOnlyError => "The mutating operation was attempted in a \"readonly\" transaction.",
+            DOMErrorName::VersionError =>
+                "An attempt was made to open a database using a lower version than the existing version.",
         };
 
         DOMString::from(message)
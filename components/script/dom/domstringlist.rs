@@ -0,0 +1,54 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::DOMStringListBinding;
+use dom::bindings::codegen::Bindings::DOMStringListBinding::DOMStringListMethods;
+use dom::bindings::reflector::{Reflector, reflect_dom_object};
+use dom::bindings::root::DomRoot;
+use dom::bindings::str::DOMString;
+use dom::globalscope::GlobalScope;
+use dom_struct::dom_struct;
+
+#[dom_struct]
+pub struct DOMStringList {
+    reflector_: Reflector,
+    strings: Vec<DOMString>,
+}
+
+impl DOMStringList {
+    fn new_inherited(strings: Vec<DOMString>) -> DOMStringList {
+        DOMStringList {
+            reflector_: Reflector::new(),
+            strings: strings,
+        }
+    }
+
+    pub fn new(global: &GlobalScope, strings: Vec<DOMString>) -> DomRoot<DOMStringList> {
+        reflect_dom_object(Box::new(DOMStringList::new_inherited(strings)),
+                           global,
+                           DOMStringListBinding::Wrap)
+    }
+}
+
+impl DOMStringListMethods for DOMStringList {
+    // https://html.spec.whatwg.org/multipage/#dom-domstringlist-length
+    fn Length(&self) -> u32 {
+        self.strings.len() as u32
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-domstringlist-item
+    fn Item(&self, index: u32) -> Option<DOMString> {
+        self.strings.get(index as usize).cloned()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-domstringlist-contains
+    fn Contains(&self, string: DOMString) -> bool {
+        self.strings.contains(&string)
+    }
+
+    // check-tidy: no specs after this line
+    fn IndexedGetter(&self, index: u32) -> Option<DOMString> {
+        self.Item(index)
+    }
+}
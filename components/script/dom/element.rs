@@ -6,8 +6,12 @@
 
 use devtools_traits::AttrInfo;
 use dom::activation::Activatable;
+use dom::animation::Animation;
+use dom::animationeffect::optional_effect_timing;
 use dom::attr::{Attr, AttrHelpersForLayout};
 use dom::bindings::cell::DomRefCell;
+use dom::bindings::codegen::Bindings::AnimationBinding::AnimationMethods;
+use dom::bindings::codegen::Bindings::AnimationEffectBinding::OptionalEffectTiming;
 use dom::bindings::codegen::Bindings::AttrBinding::AttrMethods;
 use dom::bindings::codegen::Bindings::DocumentBinding::DocumentMethods;
 use dom::bindings::codegen::Bindings::ElementBinding;
@@ -16,9 +20,11 @@ use dom::bindings::codegen::Bindings::EventBinding::EventMethods;
 use dom::bindings::codegen::Bindings::FunctionBinding::Function;
 use dom::bindings::codegen::Bindings::HTMLTemplateElementBinding::HTMLTemplateElementMethods;
 use dom::bindings::codegen::Bindings::NodeBinding::NodeMethods;
+use dom::bindings::codegen::Bindings::ShadowRootBinding::{ShadowRootInit, ShadowRootMode};
 use dom::bindings::codegen::Bindings::WindowBinding::{ScrollBehavior, ScrollToOptions};
 use dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
-use dom::bindings::codegen::UnionTypes::NodeOrString;
+use dom::bindings::codegen::UnionTypes::{NodeOrString, UnrestrictedDoubleOrKeyframeAnimationOptions};
+use dom::bindings::codegen::UnionTypes::UnrestrictedDoubleOrString;
 use dom::bindings::conversions::DerivedFrom;
 use dom::bindings::error::{Error, ErrorResult, Fallible};
 use dom::bindings::inheritance::{Castable, ElementTypeId, HTMLElementTypeId, NodeTypeId};
@@ -31,6 +37,7 @@ use dom::bindings::xmlname::XMLName::InvalidXMLName;
 use dom::characterdata::CharacterData;
 use dom::create::create_element;
 use dom::customelementregistry::{CallbackReaction, CustomElementDefinition, CustomElementReaction};
+use dom::customelementregistry::is_valid_custom_element_name;
 use dom::document::{Document, LayoutDocumentHelpers};
 use dom::documentfragment::DocumentFragment;
 use dom::domrect::DOMRect;
@@ -56,6 +63,7 @@ use dom::htmllinkelement::HTMLLinkElement;
 use dom::htmlobjectelement::HTMLObjectElement;
 use dom::htmloptgroupelement::HTMLOptGroupElement;
 use dom::htmlselectelement::HTMLSelectElement;
+use dom::htmlslotelement::HTMLSlotElement;
 use dom::htmlstyleelement::HTMLStyleElement;
 use dom::htmltablecellelement::{HTMLTableCellElement, HTMLTableCellElementLayoutHelpers};
 use dom::htmltableelement::{HTMLTableElement, HTMLTableElementLayoutHelpers};
@@ -63,6 +71,7 @@ use dom::htmltablerowelement::{HTMLTableRowElement, HTMLTableRowElementLayoutHel
 use dom::htmltablesectionelement::{HTMLTableSectionElement, HTMLTableSectionElementLayoutHelpers};
 use dom::htmltemplateelement::HTMLTemplateElement;
 use dom::htmltextareaelement::{HTMLTextAreaElement, LayoutHTMLTextAreaElementHelpers};
+use dom::keyframeeffect::KeyframeEffect;
 use dom::mutationobserver::{Mutation, MutationObserver};
 use dom::namednodemap::NamedNodeMap;
 use dom::node::{ChildrenMutation, LayoutNodeHelpers, Node};
@@ -71,6 +80,8 @@ use dom::node::{document_from_node, window_from_node};
 use dom::nodelist::NodeList;
 use dom::promise::Promise;
 use dom::servoparser::ServoParser;
+use dom::shadowroot::ShadowRoot;
+use dom::svgelement::SVGElement;
 use dom::text::Text;
 use dom::validation::Validatable;
 use dom::virtualmethods::{VirtualMethods, vtable_for};
@@ -81,9 +92,10 @@ use html5ever::serialize;
 use html5ever::serialize::SerializeOpts;
 use html5ever::serialize::TraversalScope;
 use html5ever::serialize::TraversalScope::{ChildrenOnly, IncludeNode};
-use js::jsapi::Heap;
+use js::jsapi::{Heap, JSContext, JSObject};
 use js::jsval::JSVal;
 use msg::constellation_msg::InputMethodType;
+use net_traits::csp::{CheckResult, InlineCheckType};
 use net_traits::request::CorsSettings;
 use ref_filter_map::ref_filter_map;
 use script_layout_interface::message::ReflowGoal;
@@ -159,6 +171,8 @@ pub struct Element {
     custom_element_definition: DomRefCell<Option<Rc<CustomElementDefinition>>>,
     /// <https://dom.spec.whatwg.org/#concept-element-custom-element-state>
     custom_element_state: Cell<CustomElementState>,
+    /// <https://dom.spec.whatwg.org/#concept-element-shadow-root>
+    shadow_root: MutNullableDom<ShadowRoot>,
 }
 
 impl fmt::Debug for Element {
@@ -274,6 +288,7 @@ impl Element {
             custom_element_reaction_queue: Default::default(),
             custom_element_definition: Default::default(),
             custom_element_state: Cell::new(CustomElementState::Uncustomized),
+            shadow_root: Default::default(),
         }
     }
 
@@ -300,6 +315,62 @@ impl Element {
         }
     }
 
+    /// Restyles this element and its whole flat tree subtree, and rebuilds their
+    /// boxes. Used when the shadow tree or the slot assignments below it change.
+    pub fn restyle_subtree(&self) {
+        let doc = self.node.owner_doc();
+        let mut restyle = doc.ensure_pending_restyle(self);
+        restyle.hint.insert(RestyleHint::restyle_subtree());
+        restyle.damage = RestyleDamage::rebuild_and_reflow();
+    }
+
+    /// Restyles the elements whose `:has()` selectors may stop or start matching
+    /// when the children of this element change.
+    ///
+    /// Those are the anchors whose `:has()` looked at this element or its
+    /// children, which we find following the selector flags set while matching,
+    /// so that mutations away from any `:has()` return right away.
+    pub fn invalidate_relative_selector_anchors(&self) {
+        let mut current = Some(DomRoot::from_ref(self));
+        while let Some(element) = current {
+            let flags = element.selector_flags.get();
+            if flags.intersects(ElementSelectorFlags::HAS_RELATIVE_SELECTOR_SIBLING_ANCHOR) {
+                for child in element.node.children().filter_map(DomRoot::downcast::<Element>) {
+                    child.invalidate_relative_selector_anchor();
+                }
+            }
+            element.invalidate_relative_selector_anchor();
+            if !flags.intersects(
+                ElementSelectorFlags::RELATIVE_SELECTOR_CANDIDATE |
+                ElementSelectorFlags::ANCHORS_RELATIVE_SELECTOR |
+                ElementSelectorFlags::ANCHORS_NON_SUBJECT_RELATIVE_SELECTOR
+            ) {
+                break;
+            }
+            current = element.node.GetParentElement();
+        }
+    }
+
+    /// Restyles this element if it anchors a `:has()` selector, along with its
+    /// descendants and later siblings if the `:has()` may affect their style.
+    fn invalidate_relative_selector_anchor(&self) {
+        let flags = self.selector_flags.get();
+        if flags.intersects(ElementSelectorFlags::ANCHORS_NON_SUBJECT_RELATIVE_SELECTOR) {
+            let doc = self.node.owner_doc();
+            doc.ensure_pending_restyle(self).hint.insert(RestyleHint::restyle_subtree());
+            for sibling in self.node.following_siblings().filter_map(DomRoot::downcast::<Element>) {
+                doc.ensure_pending_restyle(&sibling).hint.insert(RestyleHint::restyle_subtree());
+            }
+        } else if flags.intersects(ElementSelectorFlags::ANCHORS_RELATIVE_SELECTOR) {
+            self.restyle(NodeDamage::NodeStyleDamaged);
+        }
+    }
+
+    /// <https://dom.spec.whatwg.org/#concept-element-shadow-root>
+    pub fn shadow_root(&self) -> Option<DomRoot<ShadowRoot>> {
+        self.shadow_root.get()
+    }
+
     pub fn set_is(&self, is: LocalName) {
         *self.is.borrow_mut() = Some(is);
     }
@@ -485,6 +556,8 @@ pub trait LayoutElementHelpers {
     fn get_state_for_layout(&self) -> ElementState;
     fn insert_selector_flags(&self, flags: ElementSelectorFlags);
     fn has_selector_flags(&self, flags: ElementSelectorFlags) -> bool;
+    #[allow(unsafe_code)]
+    unsafe fn get_shadow_root_for_layout(&self) -> Option<LayoutDom<ShadowRoot>>;
 }
 
 impl LayoutElementHelpers for LayoutDom<Element> {
@@ -783,6 +856,23 @@ impl LayoutElementHelpers for LayoutDom<Element> {
                 shared_lock,
                 PropertyDeclaration::BorderRightWidth(width_value)));
         }
+
+        // SVG presentation attributes are parsed into declaration blocks when
+        // they're set, see `SVGElement::parse_plain_attribute`.
+        if self.downcast::<SVGElement>().is_some() {
+            let attrs = (*self.unsafe_get()).attrs.borrow_for_layout();
+            for attr in attrs.iter() {
+                let attr = attr.to_layout();
+                if attr.local_name_atom_forever() == local_name!("style") {
+                    continue;
+                }
+                if let AttrValue::Declaration(_, ref block) = *attr.value_forever() {
+                    hints.push(ApplicableDeclarationBlock::from_declarations(
+                        block.clone(),
+                        CascadeLevel::PresHints));
+                }
+            }
+        }
     }
 
     #[allow(unsafe_code)]
@@ -914,6 +1004,12 @@ impl LayoutElementHelpers for LayoutDom<Element> {
             (*self.unsafe_get()).selector_flags.get().contains(flags)
         }
     }
+
+    #[inline]
+    #[allow(unsafe_code)]
+    unsafe fn get_shadow_root_for_layout(&self) -> Option<LayoutDom<ShadowRoot>> {
+        (*self.unsafe_get()).shadow_root.get_inner_as_layout()
+    }
 }
 
 impl Element {
@@ -2213,7 +2309,7 @@ impl ElementMethods for Element {
             NodeTypeId::Document(_) => return Err(Error::NoModificationAllowed),
 
             // Step 4.
-            NodeTypeId::DocumentFragment => {
+            NodeTypeId::DocumentFragment(_) => {
                 let body_elem = Element::create(QualName::new(None, ns!(html), local_name!("body")),
                                                 None,
                                                 &context_document,
@@ -2390,6 +2486,56 @@ impl ElementMethods for Element {
         self.insert_adjacent(position, fragment.upcast()).map(|_| ())
     }
 
+    // https://dom.spec.whatwg.org/#dom-element-attachshadow
+    fn AttachShadow(&self, init: &ShadowRootInit) -> Fallible<DomRoot<ShadowRoot>> {
+        // Step 1.
+        if self.namespace != ns!(html) {
+            return Err(Error::NotSupported);
+        }
+        // Step 2.
+        match self.local_name() {
+            &local_name!("article") | &local_name!("aside") | &local_name!("blockquote") |
+            &local_name!("body") | &local_name!("div") | &local_name!("footer") |
+            &local_name!("h1") | &local_name!("h2") | &local_name!("h3") |
+            &local_name!("h4") | &local_name!("h5") | &local_name!("h6") |
+            &local_name!("header") | &local_name!("main") | &local_name!("nav") |
+            &local_name!("p") | &local_name!("section") | &local_name!("span") => {},
+            local_name if is_valid_custom_element_name(local_name) => {},
+            _ => return Err(Error::NotSupported),
+        }
+        // Step 3.
+        if self.shadow_root.get().is_some() {
+            return Err(Error::NotSupported);
+        }
+        // Steps 4-5.
+        let shadow_root = ShadowRoot::new(self, &document_from_node(self), init.mode);
+        self.shadow_root.set(Some(&shadow_root));
+        // The children of this element are no longer rendered until they are
+        // assigned to a slot.
+        if self.upcast::<Node>().is_in_doc() {
+            shadow_root.bind_to_tree();
+            self.restyle_subtree();
+        }
+        // Step 6.
+        Ok(shadow_root)
+    }
+
+    // https://dom.spec.whatwg.org/#dom-element-shadowroot
+    fn GetShadowRoot(&self) -> Option<DomRoot<ShadowRoot>> {
+        self.shadow_root.get().filter(|shadow_root| shadow_root.mode() == ShadowRootMode::Open)
+    }
+
+    // https://dom.spec.whatwg.org/#dom-element-slot
+    make_getter!(Slot, "slot");
+
+    // https://dom.spec.whatwg.org/#dom-element-slot
+    make_setter!(SetSlot, "slot");
+
+    // https://dom.spec.whatwg.org/#dom-slotable-assignedslot
+    fn GetAssignedSlot(&self) -> Option<DomRoot<HTMLSlotElement>> {
+        HTMLSlotElement::find_a_slot(self.upcast(), true)
+    }
+
     // check-tidy: no specs after this line
     fn EnterFormalActivationState(&self) -> ErrorResult {
         match self.as_maybe_activatable() {
@@ -2417,6 +2563,55 @@ impl ElementMethods for Element {
         let doc = document_from_node(self);
         doc.enter_fullscreen(self)
     }
+
+    // https://drafts.csswg.org/web-animations/#dom-animatable-animate
+    #[allow(unsafe_code)]
+    unsafe fn Animate(
+        &self,
+        cx: *mut JSContext,
+        keyframes: *mut JSObject,
+        options: Option<UnrestrictedDoubleOrKeyframeAnimationOptions>,
+    ) -> Fallible<DomRoot<Animation>> {
+        let window = window_from_node(self);
+        let (timing, id) = match options {
+            Some(UnrestrictedDoubleOrKeyframeAnimationOptions::UnrestrictedDouble(duration)) => {
+                let mut timing = OptionalEffectTiming::empty();
+                timing.duration = Some(UnrestrictedDoubleOrString::UnrestrictedDouble(duration));
+                (timing, DOMString::new())
+            },
+            Some(UnrestrictedDoubleOrKeyframeAnimationOptions::KeyframeAnimationOptions(ref options)) => {
+                (optional_effect_timing(&options.parent.parent), options.id.clone())
+            },
+            None => (OptionalEffectTiming::empty(), DOMString::new()),
+        };
+
+        // Steps 2-3.
+        let effect = KeyframeEffect::new_with_keyframes(cx, &window, Some(self), keyframes, &timing)?;
+
+        // Steps 4-5.
+        let timeline = window.Document().Timeline();
+        let animation = Animation::new(&window, Some(effect.upcast()), Some(timeline.upcast()));
+
+        // Step 6.
+        animation.SetId(id);
+
+        // Step 7.
+        animation.Play()?;
+
+        // Step 8.
+        Ok(animation)
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-animatable-getanimations
+    fn GetAnimations(&self) -> Vec<DomRoot<Animation>> {
+        let document = document_from_node(self);
+        let css_animations = document.css_animations(Some(self));
+        css_animations
+            .iter()
+            .map(|animation| DomRoot::from_ref(animation.upcast::<Animation>()))
+            .chain(document.script_animations(Some(self)))
+            .collect()
+    }
 }
 
 impl VirtualMethods for Element {
@@ -2453,7 +2648,7 @@ impl VirtualMethods for Element {
                             _ => false,
                         };
 
-                        let block = if is_declaration {
+                        if is_declaration {
                             let mut value = AttrValue::String(String::new());
                             attr.swap_value(&mut value);
                             let (serialization, block) = match value {
@@ -2462,17 +2657,18 @@ impl VirtualMethods for Element {
                             };
                             let mut value = AttrValue::String(serialization);
                             attr.swap_value(&mut value);
-                            block
+                            Some(block)
+                        } else if self.is_inline_behavior_blocked_by_csp(InlineCheckType::StyleAttribute,
+                                                                         &attr.value()) {
+                            None
                         } else {
                             let win = window_from_node(self);
-                            Arc::new(doc.style_shared_lock().wrap(parse_style_attribute(
+                            Some(Arc::new(doc.style_shared_lock().wrap(parse_style_attribute(
                                 &attr.value(),
                                 &doc.base_url(),
                                 win.css_error_reporter(),
-                                doc.quirks_mode())))
-                        };
-
-                        Some(block)
+                                doc.quirks_mode()))))
+                        }
                     }
                     AttributeMutation::Removed => {
                         None
@@ -2489,7 +2685,9 @@ impl VirtualMethods for Element {
                             None
                         }
                     });
-                if node.is_in_doc() {
+                // Elements in shadow trees are not found by the document's
+                // getElementById.
+                if node.is_in_doc() && !node.is_in_shadow_tree() {
                     let value = attr.value().as_atom().clone();
                     match mutation {
                         AttributeMutation::Set(old_value) => {
@@ -2509,6 +2707,13 @@ impl VirtualMethods for Element {
                     }
                 }
             },
+            &local_name!("slot") if attr.namespace() == &ns!() => {
+                // https://dom.spec.whatwg.org/#shadow-tree-slots
+                if let Some(slot) = node.assigned_slot() {
+                    slot.assign_slottables();
+                }
+                HTMLSlotElement::assign_a_slot(node);
+            },
             _ => {
                 // FIXME(emilio): This is pretty dubious, and should be done in
                 // the relevant super-classes.
@@ -2547,11 +2752,17 @@ impl VirtualMethods for Element {
         }
 
         let doc = document_from_node(self);
-        if let Some(ref value) = *self.id_attribute.borrow() {
-            doc.register_named_element(self, value.clone());
+        if !self.upcast::<Node>().is_in_shadow_tree() {
+            if let Some(ref value) = *self.id_attribute.borrow() {
+                doc.register_named_element(self, value.clone());
+            }
         }
         // This is used for layout optimization.
         doc.increment_dom_count();
+
+        if let Some(shadow_root) = self.shadow_root.get() {
+            shadow_root.bind_to_tree();
+        }
     }
 
     fn unbind_from_tree(&self, context: &UnbindContext) {
@@ -2570,11 +2781,18 @@ impl VirtualMethods for Element {
         if fullscreen.r() == Some(self) {
             doc.exit_fullscreen();
         }
-        if let Some(ref value) = *self.id_attribute.borrow() {
-            doc.unregister_named_element(self, value.clone());
+        // Only elements outside of shadow trees were registered when bound.
+        if !self.upcast::<Node>().is_in_shadow_tree() && !context.parent.is_in_shadow_tree() {
+            if let Some(ref value) = *self.id_attribute.borrow() {
+                doc.unregister_named_element(self, value.clone());
+            }
         }
         // This is used for layout optimization.
         doc.decrement_dom_count();
+
+        if let Some(shadow_root) = self.shadow_root.get() {
+            shadow_root.unbind_from_tree(context);
+        }
     }
 
     fn children_changed(&self, mutation: &ChildrenMutation) {
@@ -2602,6 +2820,10 @@ impl VirtualMethods for Element {
                 }
             }
         }
+
+        if self.upcast::<Node>().is_in_doc() {
+            self.invalidate_relative_selector_anchors();
+        }
     }
 
     fn adopting_steps(&self, old_doc: &Document) {
@@ -2610,6 +2832,10 @@ impl VirtualMethods for Element {
         if document_from_node(self).is_html_document() != old_doc.is_html_document() {
             self.tag_name.clear();
         }
+
+        if let Some(shadow_root) = self.shadow_root.get() {
+            shadow_root.adopt(old_doc);
+        }
     }
 }
 
@@ -2625,11 +2851,15 @@ impl<'a> SelectorsElement for DomRoot<Element> {
     }
 
     fn parent_node_is_shadow_root(&self) -> bool {
-        false
+        self.upcast::<Node>().GetParentNode().map_or(false, |parent| parent.is::<ShadowRoot>())
     }
 
     fn containing_shadow_host(&self) -> Option<Self> {
-        None
+        self.upcast::<Node>().containing_shadow_root().map(|shadow_root| shadow_root.host())
+    }
+
+    fn assigned_slot(&self) -> Option<Self> {
+        self.upcast::<Node>().assigned_slot().map(DomRoot::upcast)
     }
 
     fn match_pseudo_element(
@@ -2648,6 +2878,10 @@ impl<'a> SelectorsElement for DomRoot<Element> {
         self.node.following_siblings().filter_map(DomRoot::downcast).next()
     }
 
+    fn first_element_child(&self) -> Option<DomRoot<Element>> {
+        self.node.children().filter_map(DomRoot::downcast).next()
+    }
+
     fn attr_matches(&self,
                     ns: &NamespaceConstraint<&Namespace>,
                     local_name: &LocalName,
@@ -2740,7 +2974,16 @@ impl<'a> SelectorsElement for DomRoot<Element> {
             NonTSPseudoClass::Indeterminate |
             NonTSPseudoClass::ReadWrite |
             NonTSPseudoClass::PlaceholderShown |
-            NonTSPseudoClass::Target =>
+            NonTSPseudoClass::Target |
+            NonTSPseudoClass::Valid |
+            NonTSPseudoClass::Invalid |
+            NonTSPseudoClass::Required |
+            NonTSPseudoClass::Optional |
+            NonTSPseudoClass::InRange |
+            NonTSPseudoClass::OutOfRange |
+            NonTSPseudoClass::Default |
+            NonTSPseudoClass::FocusWithin |
+            NonTSPseudoClass::FocusVisible =>
                 Element::state(self).contains(pseudo_class.state_flag()),
         }
     }
@@ -2851,6 +3094,24 @@ impl Element {
         element
     }
 
+    /// Checks the inline behavior `source` of this element against the CSP list of its node
+    /// document, reporting the violations.
+    /// <https://w3c.github.io/webappsec-csp/#should-block-inline>
+    pub fn is_inline_behavior_blocked_by_csp(&self, type_: InlineCheckType, source: &str) -> bool {
+        let doc = document_from_node(self);
+        let csp_list = match doc.get_csp_list() {
+            Some(csp_list) => csp_list,
+            None => return false,
+        };
+        let nonce = self.get_string_attribute(&local_name!("nonce"));
+        let (result, violations) =
+            csp_list.should_elements_inline_type_behavior_be_blocked(Some(&nonce), type_, source);
+        if !violations.is_empty() {
+            doc.global().report_csp_violations(violations, Some(self));
+        }
+        result == CheckResult::Blocked
+    }
+
     pub fn click_in_progress(&self) -> bool {
         self.upcast::<Node>().get_flag(NodeFlags::CLICK_IN_PROGRESS)
     }
@@ -2974,9 +3235,23 @@ impl Element {
 
     pub fn set_focus_state(&self, value: bool) {
         self.set_state(ElementState::IN_FOCUS_STATE, value);
+        if !value {
+            self.set_focus_visible_state(false);
+        }
+        // https://drafts.csswg.org/selectors-4/#the-focus-within-pseudo
+        for ancestor in self.upcast::<Node>().shadow_including_inclusive_ancestors() {
+            if let Some(element) = ancestor.downcast::<Element>() {
+                element.set_state(ElementState::IN_FOCUS_WITHIN_STATE, value);
+            }
+        }
         self.upcast::<Node>().dirty(NodeDamage::OtherNodeDamage);
     }
 
+    /// <https://drafts.csswg.org/selectors-4/#the-focus-visible-pseudo>
+    pub fn set_focus_visible_state(&self, value: bool) {
+        self.set_state(ElementState::IN_FOCUSRING_STATE, value)
+    }
+
     pub fn hover_state(&self) -> bool {
         self.state.get().contains(ElementState::IN_HOVER_STATE)
     }
@@ -3038,9 +3313,7 @@ impl Element {
 
     /// <https://dom.spec.whatwg.org/#connected>
     pub fn is_connected(&self) -> bool {
-        let node = self.upcast::<Node>();
-        let root = node.GetRootNode();
-        root.is::<Document>()
+        self.upcast::<Node>().shadow_including_root().is::<Document>()
     }
 }
 
@@ -7,16 +7,20 @@ use dom::bindings::callback::ExceptionHandling;
 use dom::bindings::cell::DomRefCell;
 use dom::bindings::codegen::Bindings::EventBinding;
 use dom::bindings::codegen::Bindings::EventBinding::{EventConstants, EventMethods};
+use dom::bindings::codegen::Bindings::ShadowRootBinding::{ShadowRootMethods, ShadowRootMode};
 use dom::bindings::error::Fallible;
 use dom::bindings::inheritance::Castable;
 use dom::bindings::refcounted::Trusted;
 use dom::bindings::reflector::{DomObject, Reflector, reflect_dom_object};
-use dom::bindings::root::{DomRoot, MutNullableDom, RootedReference};
+use dom::bindings::root::{Dom, DomRoot, MutNullableDom, RootedReference};
 use dom::bindings::str::DOMString;
 use dom::document::Document;
 use dom::eventtarget::{CompiledEventListener, EventTarget, ListenerPhase};
 use dom::globalscope::GlobalScope;
+use dom::idbrequest::IDBRequest;
+use dom::idbtransaction::IDBTransaction;
 use dom::node::Node;
+use dom::shadowroot::ShadowRoot;
 use dom::virtualmethods::vtable_for;
 use dom::window::Window;
 use dom_struct::dom_struct;
@@ -38,10 +42,16 @@ pub struct Event {
     stop_immediate: Cell<bool>,
     cancelable: Cell<bool>,
     bubbles: Cell<bool>,
+    /// <https://dom.spec.whatwg.org/#composed-flag>
+    composed: Cell<bool>,
     trusted: Cell<bool>,
     dispatching: Cell<bool>,
     initialized: Cell<bool>,
     timestamp: u64,
+    /// The targets the event is being dispatched to, starting with its target.
+    ///
+    /// <https://dom.spec.whatwg.org/#event-path>
+    path: DomRefCell<Vec<Dom<EventTarget>>>,
 }
 
 impl Event {
@@ -57,10 +67,12 @@ impl Event {
             stop_immediate: Cell::new(false),
             cancelable: Cell::new(false),
             bubbles: Cell::new(false),
+            composed: Cell::new(false),
             trusted: Cell::new(false),
             dispatching: Cell::new(false),
             initialized: Cell::new(false),
             timestamp: time::get_time().sec as u64,
+            path: DomRefCell::new(vec![]),
         }
     }
 
@@ -84,7 +96,9 @@ impl Event {
                        init: &EventBinding::EventInit) -> Fallible<DomRoot<Event>> {
         let bubbles = EventBubbles::from(init.bubbles);
         let cancelable = EventCancelable::from(init.cancelable);
-        Ok(Event::new(global, Atom::from(type_), bubbles, cancelable))
+        let event = Event::new(global, Atom::from(type_), bubbles, cancelable);
+        event.set_composed(init.composed);
+        Ok(event)
     }
 
     pub fn init_event(&self, type_: Atom, bubbles: bool, cancelable: bool) {
@@ -119,8 +133,10 @@ impl Event {
         // The "invoke" algorithm is only used on `target` separately,
         // so we don't put it in the path.
         if let Some(target_node) = target.downcast::<Node>() {
-            for ancestor in target_node.ancestors() {
-                event_path.push(DomRoot::from_ref(ancestor.upcast::<EventTarget>()));
+            let mut current = self.get_the_parent(target_node, target_node);
+            while let Some(parent) = current {
+                event_path.push(DomRoot::from_ref(parent.upcast::<EventTarget>()));
+                current = self.get_the_parent(&parent, target_node);
             }
             let top_most_ancestor_or_target =
                 event_path.last().cloned().unwrap_or(DomRoot::from_ref(target));
@@ -129,10 +145,37 @@ impl Event {
                     event_path.push(DomRoot::from_ref(document.window().upcast()));
                 }
             }
+        } else if let Some(request) = target.downcast::<IDBRequest>() {
+            // https://w3c.github.io/IndexedDB/#ref-for-get-the-parent
+            if let Some(transaction) = request.transaction() {
+                event_path.push(DomRoot::from_ref(transaction.upcast()));
+                event_path.push(DomRoot::from_ref(transaction.db().upcast()));
+            }
+        } else if let Some(transaction) = target.downcast::<IDBTransaction>() {
+            // https://w3c.github.io/IndexedDB/#ref-for-get-the-parent%E2%91%A0
+            event_path.push(DomRoot::from_ref(transaction.db().upcast()));
         }
         event_path
     }
 
+    /// <https://dom.spec.whatwg.org/#get-the-parent> for nodes, which goes from
+    /// slotted nodes to their slot, and from shadow roots to their host.
+    fn get_the_parent(&self, node: &Node, target: &Node) -> Option<DomRoot<Node>> {
+        if let Some(shadow_root) = node.downcast::<ShadowRoot>() {
+            // A shadow root's parent is its host, unless the event isn't composed
+            // and the shadow root is the root of the event's target.
+            let is_target_root = target.inclusive_ancestors().last().map_or(false, |root| &*root == node);
+            if !self.composed.get() && is_target_root {
+                return None;
+            }
+            return Some(DomRoot::upcast(shadow_root.host()));
+        }
+        if let Some(slot) = node.assigned_slot() {
+            return Some(DomRoot::upcast(slot));
+        }
+        node.GetParentNode()
+    }
+
     // https://dom.spec.whatwg.org/#concept-event-dispatch
     pub fn dispatch(&self,
                     target: &EventTarget,
@@ -162,21 +205,35 @@ impl Event {
 
         // Step 3-4.
         let path = self.construct_event_path(&target);
+        {
+            let mut dispatch_path = self.path.borrow_mut();
+            dispatch_path.push(Dom::from_ref(target));
+            dispatch_path.extend(path.iter().map(|target| Dom::from_ref(&**target)));
+        }
         rooted_vec!(let event_path <- path.into_iter());
         // Steps 5-9. In a separate function to short-circuit various things easily.
         dispatch_to_listeners(self, target, event_path.r());
 
         // Default action.
-        if let Some(target) = self.GetTarget() {
-            if let Some(node) = target.downcast::<Node>() {
-                let vtable = vtable_for(&node);
-                vtable.handle_event(self);
-            }
+        let original_target = DomRoot::from_ref(target_override.unwrap_or(target));
+        if let Some(node) = original_target.downcast::<Node>() {
+            let vtable = vtable_for(&node);
+            vtable.handle_event(self);
         }
 
         // Step 10-12.
         self.clear_dispatching_flags();
 
+        // Step 13. The target isn't exposed after dispatch if it is in a shadow tree.
+        let target_in_shadow_tree = original_target.downcast::<Node>().map_or(false, |node| {
+            node.inclusive_ancestors().last().map_or(false, |root| root.is::<ShadowRoot>())
+        });
+        if target_in_shadow_tree {
+            self.target.set(None);
+        } else {
+            self.target.set(Some(&original_target));
+        }
+
         // Step 14.
         self.status()
     }
@@ -203,6 +260,7 @@ impl Event {
         self.stop_immediate.set(false);
         self.phase.set(EventPhase::None);
         self.current_target.set(None);
+        self.path.borrow_mut().clear();
     }
 
     #[inline]
@@ -215,6 +273,15 @@ impl Event {
         self.type_.borrow().clone()
     }
 
+    #[inline]
+    pub fn composed(&self) -> bool {
+        self.composed.get()
+    }
+
+    pub fn set_composed(&self, composed: bool) {
+        self.composed.set(composed);
+    }
+
     #[inline]
     pub fn mark_as_handled(&self) {
         self.canceled.set(EventDefault::Handled);
@@ -257,6 +324,19 @@ impl EventMethods for Event {
         self.current_target.get()
     }
 
+    // https://dom.spec.whatwg.org/#dom-event-composedpath
+    fn ComposedPath(&self) -> Vec<DomRoot<EventTarget>> {
+        let current_target = match self.current_target.get() {
+            Some(current_target) => current_target,
+            None => return vec![],
+        };
+        // Targets in closed shadow trees are hidden from the listeners outside of them.
+        self.path.borrow().iter()
+            .filter(|target| !is_hidden_from(target, &current_target))
+            .map(|target| DomRoot::from_ref(&**target))
+            .collect()
+    }
+
     // https://dom.spec.whatwg.org/#dom-event-defaultprevented
     fn DefaultPrevented(&self) -> bool {
         self.canceled.get() == EventDefault::Prevented
@@ -290,6 +370,11 @@ impl EventMethods for Event {
         self.cancelable.get()
     }
 
+    // https://dom.spec.whatwg.org/#dom-event-composed
+    fn Composed(&self) -> bool {
+        self.composed.get()
+    }
+
     // https://dom.spec.whatwg.org/#dom-event-timestamp
     fn TimeStamp(&self) -> u64 {
         self.timestamp
@@ -431,6 +516,9 @@ fn dispatch_to_listeners(event: &Event, target: &EventTarget, event_path: &[&Eve
     assert!(!event.stop_propagation.get());
     assert!(!event.stop_immediate.get());
 
+    // Listeners see the target retargeted against themselves, so keep the original around.
+    let original_target = event.target.get().expect("dispatching an event without a target");
+
     let window = match DomRoot::downcast::<Window>(target.global()) {
         Some(window) => {
             if window.need_emit_timeline_marker(TimelineMarkerType::DOMEvent) {
@@ -447,7 +535,7 @@ fn dispatch_to_listeners(event: &Event, target: &EventTarget, event_path: &[&Eve
 
     // Step 6.
     for object in event_path.iter().rev() {
-        invoke(window.r(), object, event, Some(ListenerPhase::Capturing));
+        invoke(window.r(), &original_target, object, event, Some(ListenerPhase::Capturing));
         if event.stop_propagation.get() {
             return;
         }
@@ -459,7 +547,7 @@ fn dispatch_to_listeners(event: &Event, target: &EventTarget, event_path: &[&Eve
     event.phase.set(EventPhase::AtTarget);
 
     // Step 8.
-    invoke(window.r(), target, event, None);
+    invoke(window.r(), &original_target, target, event, None);
     if event.stop_propagation.get() {
         return;
     }
@@ -475,7 +563,7 @@ fn dispatch_to_listeners(event: &Event, target: &EventTarget, event_path: &[&Eve
 
     // Step 9.2.
     for object in event_path {
-        invoke(window.r(), object, event, Some(ListenerPhase::Bubbling));
+        invoke(window.r(), &original_target, object, event, Some(ListenerPhase::Bubbling));
         if event.stop_propagation.get() {
             return;
         }
@@ -484,6 +572,7 @@ fn dispatch_to_listeners(event: &Event, target: &EventTarget, event_path: &[&Eve
 
 // https://dom.spec.whatwg.org/#concept-event-listener-invoke
 fn invoke(window: Option<&Window>,
+          target: &EventTarget,
           object: &EventTarget,
           event: &Event,
           specific_listener_phase: Option<ListenerPhase>) {
@@ -495,6 +584,7 @@ fn invoke(window: Option<&Window>,
 
     // Step 4.
     event.current_target.set(Some(object));
+    event.target.set(Some(&retarget(target, object)));
 
     // Step 5.
     inner_invoke(window, object, event, &listeners);
@@ -543,6 +633,55 @@ impl Default for EventBinding::EventInit {
         EventBinding::EventInit {
             bubbles: false,
             cancelable: false,
+            composed: false,
+        }
+    }
+}
+
+/// <https://dom.spec.whatwg.org/#retarget>
+fn retarget(a: &EventTarget, b: &EventTarget) -> DomRoot<EventTarget> {
+    let mut a = DomRoot::from_ref(a);
+    loop {
+        let shadow_root = a.downcast::<Node>()
+            .and_then(|node| node.inclusive_ancestors().last())
+            .and_then(DomRoot::downcast::<ShadowRoot>);
+        let shadow_root = match shadow_root {
+            Some(shadow_root) => shadow_root,
+            None => return a,
+        };
+        let is_ancestor_of_b = b.downcast::<Node>().map_or(false, |b| {
+            shadow_root.upcast::<Node>().is_shadow_including_inclusive_ancestor_of(b)
+        });
+        if is_ancestor_of_b {
+            return a;
+        }
+        a = DomRoot::upcast(shadow_root.host());
+    }
+}
+
+/// Whether `target` is inside a closed shadow tree that `current_target` is not part of,
+/// in which case `composedPath()` must not expose it.
+fn is_hidden_from(target: &EventTarget, current_target: &EventTarget) -> bool {
+    let mut node = match target.downcast::<Node>() {
+        Some(node) => DomRoot::from_ref(node),
+        None => return false,
+    };
+    let current_node = current_target.downcast::<Node>();
+    loop {
+        let shadow_root = node.inclusive_ancestors().last().and_then(DomRoot::downcast::<ShadowRoot>);
+        let shadow_root = match shadow_root {
+            Some(shadow_root) => shadow_root,
+            None => return false,
+        };
+        let contains_current = current_node.map_or(false, |current| {
+            shadow_root.upcast::<Node>().is_shadow_including_inclusive_ancestor_of(current)
+        });
+        if contains_current {
+            return false;
+        }
+        if shadow_root.Mode() == ShadowRootMode::Closed {
+            return true;
         }
+        node = DomRoot::upcast(shadow_root.host());
     }
 }
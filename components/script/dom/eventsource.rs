@@ -26,6 +26,7 @@ use js::jsval::UndefinedValue;
 use mime::{Mime, TopLevel, SubLevel};
 use net_traits::{CoreResourceMsg, FetchChannels, FetchMetadata};
 use net_traits::{FetchResponseMsg, FetchResponseListener, NetworkError};
+use net_traits::csp::Violation;
 use net_traits::request::{CacheMode, CorsSettings, CredentialsMode};
 use net_traits::request::{RequestInit, RequestMode};
 use network_listener::{NetworkListener, PreInvoke};
@@ -221,7 +222,8 @@ impl EventSourceContext {
             unsafe { self.data.to_jsval(event_source.global().get_cx(), data.handle_mut()) };
             MessageEvent::new(&*event_source.global(), type_, false, false, data.handle(),
                               DOMString::from(self.origin.clone()),
-                              event_source.last_event_id.borrow().clone())
+                              event_source.last_event_id.borrow().clone(),
+                              vec![])
         };
         // Step 7
         self.event_type.clear();
@@ -380,6 +382,11 @@ impl FetchResponseListener for EventSourceContext {
         }
         self.reestablish_the_connection();
     }
+
+    fn process_csp_violations(&mut self, violations: Vec<Violation>) {
+        let global = &self.event_source.root().global();
+        global.report_csp_violations(violations, None);
+    }
 }
 
 impl PreInvoke for EventSourceContext {
@@ -472,6 +479,8 @@ impl EventSource {
             } else {
                 CredentialsMode::Include
             },
+            csp_list: global.get_csp_list(),
+            client_url: Some(global.get_url()),
             ..RequestInit::default()
         };
         // Step 10
@@ -508,7 +517,8 @@ impl EventSource {
         }));
         let cancel_receiver = ev.canceller.borrow_mut().initialize();
         global.core_resource_thread().send(
-            CoreResourceMsg::Fetch(request, FetchChannels::ResponseMsg(action_sender, Some(cancel_receiver)))).unwrap();
+            CoreResourceMsg::Fetch(request,
+                                   FetchChannels::ResponseMsg(action_sender, Some(cancel_receiver), None))).unwrap();
         // Step 13
         Ok(ev)
     }
@@ -582,6 +592,6 @@ impl EventSourceTimeoutCallback {
         }
         // Step 5.4
         global.core_resource_thread().send(
-            CoreResourceMsg::Fetch(request, FetchChannels::ResponseMsg(self.action_sender, None))).unwrap();
+            CoreResourceMsg::Fetch(request, FetchChannels::ResponseMsg(self.action_sender, None, None))).unwrap();
     }
 }
@@ -2,32 +2,54 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use dom::bindings::cell::DomRefCell;
 use dom::bindings::codegen::Bindings::EventBinding::{self, EventMethods};
 use dom::bindings::codegen::Bindings::ExtendableEventBinding;
 use dom::bindings::error::{Error, ErrorResult, Fallible};
 use dom::bindings::inheritance::Castable;
 use dom::bindings::reflector::reflect_dom_object;
-use dom::bindings::root::DomRoot;
+use dom::bindings::root::{Dom, DomRoot};
 use dom::bindings::str::DOMString;
+use dom::bindings::trace::JSTraceable;
 use dom::event::Event;
+use dom::promise::Promise;
+use dom::promisenativehandler::Callback;
 use dom::serviceworkerglobalscope::ServiceWorkerGlobalScope;
+use dom::streams::upon_settlement;
 use dom_struct::dom_struct;
 use js::jsapi::JSContext;
 use js::rust::HandleValue;
+use malloc_size_of::MallocSizeOf;
 use servo_atoms::Atom;
+use std::cell::Cell;
+
+/// The steps to run once the extend lifetime promises of an event have all settled.
+pub trait ExtendLifetimeSteps: JSTraceable + MallocSizeOf {
+    /// `rejected` is whether any of the promises was rejected.
+    fn settled_steps(self: Box<Self>, rejected: bool);
+}
 
 // https://w3c.github.io/ServiceWorker/#extendable-event
 #[dom_struct]
 pub struct ExtendableEvent {
     event: Event,
-    extensions_allowed: bool
+    /// The number of extend lifetime promises which haven't settled yet.
+    ///
+    /// <https://w3c.github.io/ServiceWorker/#extendableevent-pending-promises-count>
+    pending_promises: Cell<usize>,
+    /// Whether any of the extend lifetime promises was rejected.
+    rejected: Cell<bool>,
+    /// The steps to run once the extend lifetime promises have settled.
+    settled_steps: DomRefCell<Option<Box<ExtendLifetimeSteps>>>,
 }
 
 impl ExtendableEvent {
     pub fn new_inherited() -> ExtendableEvent {
         ExtendableEvent {
             event: Event::new_inherited(),
-            extensions_allowed: true
+            pending_promises: Cell::new(0),
+            rejected: Cell::new(false),
+            settled_steps: DomRefCell::new(None),
         }
     }
     pub fn new(worker: &ServiceWorkerGlobalScope,
@@ -57,16 +79,61 @@ impl ExtendableEvent {
     }
 
     // https://w3c.github.io/ServiceWorker/#wait-until-method
-    pub fn WaitUntil(&self, _cx: *mut JSContext, _val: HandleValue) -> ErrorResult {
+    pub fn WaitUntil(&self, promise: &Promise) -> ErrorResult {
         // Step 1
-        if !self.extensions_allowed {
+        if !self.IsTrusted() {
             return Err(Error::InvalidState);
         }
         // Step 2
-        // TODO add a extended_promises array to enqueue the `val`
+        self.add_lifetime_promise(promise)
+    }
+
+    /// <https://w3c.github.io/ServiceWorker/#extendableevent-active>
+    fn is_active(&self) -> bool {
+        self.event.dispatching() || self.pending_promises.get() > 0
+    }
+
+    /// <https://w3c.github.io/ServiceWorker/#extendableevent-add-lifetime-promise>
+    #[allow(unrooted_must_root)]
+    pub fn add_lifetime_promise(&self, promise: &Promise) -> ErrorResult {
+        // Step 2.
+        if !self.is_active() {
+            return Err(Error::InvalidState);
+        }
+        // Steps 3-4.
+        self.pending_promises.set(self.pending_promises.get() + 1);
+        upon_settlement(promise,
+                        Box::new(LifetimePromiseSettled { event: Dom::from_ref(self), rejected: false }),
+                        Box::new(LifetimePromiseSettled { event: Dom::from_ref(self), rejected: true }));
         Ok(())
     }
 
+    fn lifetime_promise_settled(&self, rejected: bool) {
+        if rejected {
+            self.rejected.set(true);
+        }
+        self.pending_promises.set(self.pending_promises.get() - 1);
+        if !self.is_active() {
+            self.run_settled_steps();
+        }
+    }
+
+    /// Runs `steps` once the extend lifetime promises added during or after the dispatch of
+    /// this event have all settled, which must be called once it was dispatched.
+    pub fn upon_lifetime_promises_settled(&self, steps: Box<ExtendLifetimeSteps>) {
+        *self.settled_steps.borrow_mut() = Some(steps);
+        if !self.is_active() {
+            self.run_settled_steps();
+        }
+    }
+
+    fn run_settled_steps(&self) {
+        let steps = self.settled_steps.borrow_mut().take();
+        if let Some(steps) = steps {
+            steps.settled_steps(self.rejected.get());
+        }
+    }
+
     // https://dom.spec.whatwg.org/#dom-event-istrusted
     pub fn IsTrusted(&self) -> bool {
         self.event.IsTrusted()
@@ -80,3 +147,18 @@ impl Default for ExtendableEventBinding::ExtendableEventInit {
         }
     }
 }
+
+/// Updates the pending promises count of an event once one of its extend lifetime promises
+/// settles.
+#[derive(JSTraceable, MallocSizeOf)]
+#[must_root]
+struct LifetimePromiseSettled {
+    event: Dom<ExtendableEvent>,
+    rejected: bool,
+}
+
+impl Callback for LifetimePromiseSettled {
+    fn callback(&self, _cx: *mut JSContext, _v: HandleValue) {
+        self.event.lifetime_promise_settled(self.rejected);
+    }
+}
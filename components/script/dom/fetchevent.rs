@@ -0,0 +1,235 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::cell::DomRefCell;
+use dom::bindings::codegen::Bindings::EventBinding::EventMethods;
+use dom::bindings::codegen::Bindings::FetchEventBinding;
+use dom::bindings::codegen::Bindings::FetchEventBinding::FetchEventMethods;
+use dom::bindings::codegen::Bindings::HeadersBinding::HeadersMethods;
+use dom::bindings::codegen::Bindings::ResponseBinding::{ResponseMethods, ResponseType};
+use dom::bindings::conversions::root_from_handlevalue;
+use dom::bindings::error::{Error, ErrorResult, Fallible};
+use dom::bindings::inheritance::Castable;
+use dom::bindings::reflector::reflect_dom_object;
+use dom::bindings::root::{Dom, DomRoot};
+use dom::bindings::str::DOMString;
+use dom::event::{Event, EventStatus};
+use dom::extendableevent::ExtendableEvent;
+use dom::globalscope::GlobalScope;
+use dom::promise::Promise;
+use dom::promisenativehandler::Callback;
+use dom::readablestream::ReadAllBytesSteps;
+use dom::request::Request;
+use dom::response::Response;
+use dom::serviceworkerglobalscope::ServiceWorkerGlobalScope;
+use dom::streams::upon_settlement;
+use dom_struct::dom_struct;
+use hyper::header::Headers as HyperHeaders;
+use hyper::http::RawStatus;
+use ipc_channel::ipc::IpcSender;
+use js::jsapi::JSContext;
+use js::rust::HandleValue;
+use net_traits::{CustomResponse, NetworkError};
+use servo_atoms::Atom;
+use std::cell::Cell;
+
+/// The channel a service worker sends its response to a fetch on, or `None` to let the request
+/// go to the network.
+pub type ResponseChan = IpcSender<Option<Result<CustomResponse, NetworkError>>>;
+
+// https://w3c.github.io/ServiceWorker/#fetchevent-interface
+#[dom_struct]
+pub struct FetchEvent {
+    event: ExtendableEvent,
+    request: Dom<Request>,
+    client_id: DOMString,
+    is_reload: bool,
+    /// <https://w3c.github.io/ServiceWorker/#fetchevent-respond-with-entered-flag>
+    respond_with_entered: Cell<bool>,
+    /// Where to send the response, for the events dispatched by the user agent.
+    #[ignore_malloc_size_of = "Defined in ipc-channel"]
+    response_chan: DomRefCell<Option<ResponseChan>>,
+}
+
+impl FetchEvent {
+    fn new_inherited(request: &Request, client_id: DOMString, is_reload: bool) -> FetchEvent {
+        FetchEvent {
+            event: ExtendableEvent::new_inherited(),
+            request: Dom::from_ref(request),
+            client_id: client_id,
+            is_reload: is_reload,
+            respond_with_entered: Cell::new(false),
+            response_chan: DomRefCell::new(None),
+        }
+    }
+
+    pub fn new(global: &GlobalScope,
+               type_: Atom,
+               bubbles: bool,
+               cancelable: bool,
+               request: &Request,
+               client_id: DOMString,
+               is_reload: bool)
+               -> DomRoot<FetchEvent> {
+        let ev = reflect_dom_object(
+            Box::new(FetchEvent::new_inherited(request, client_id, is_reload)),
+            global,
+            FetchEventBinding::Wrap
+        );
+        ev.upcast::<Event>().init_event(type_, bubbles, cancelable);
+        ev
+    }
+
+    pub fn Constructor(worker: &ServiceWorkerGlobalScope,
+                       type_: DOMString,
+                       init: &FetchEventBinding::FetchEventInit) -> Fallible<DomRoot<FetchEvent>> {
+        Ok(FetchEvent::new(worker.upcast(),
+                           Atom::from(type_),
+                           init.parent.parent.bubbles,
+                           init.parent.parent.cancelable,
+                           &init.request,
+                           init.clientId.clone(),
+                           init.isReload))
+    }
+
+    /// Fires a fetch event for `request` at `worker`, and sends its response on `response_chan`
+    /// once there is one.
+    ///
+    /// <https://w3c.github.io/ServiceWorker/#handle-fetch> steps 20-23.
+    pub fn dispatch(worker: &ServiceWorkerGlobalScope, request: &Request, response_chan: ResponseChan) {
+        let event = FetchEvent::new(worker.upcast(), atom!("fetch"), false, true,
+                                    request, DOMString::new(), false);
+        *event.response_chan.borrow_mut() = Some(response_chan);
+        let status = event.upcast::<Event>().fire(worker.upcast());
+        if !event.respond_with_entered.get() {
+            if status == EventStatus::Canceled {
+                event.respond_with_network_error();
+            } else {
+                event.respond(None);
+            }
+        }
+    }
+
+    fn respond(&self, response: Option<Result<CustomResponse, NetworkError>>) {
+        if let Some(response_chan) = self.response_chan.borrow_mut().take() {
+            let _ = response_chan.send(response);
+        }
+    }
+
+    fn respond_with_network_error(&self) {
+        let error = NetworkError::Internal("The service worker didn't respond with a response".to_owned());
+        self.respond(Some(Err(error)));
+    }
+}
+
+impl FetchEventMethods for FetchEvent {
+    // https://w3c.github.io/ServiceWorker/#fetch-event-request
+    fn Request(&self) -> DomRoot<Request> {
+        DomRoot::from_ref(&*self.request)
+    }
+
+    // https://w3c.github.io/ServiceWorker/#fetch-event-clientid
+    fn ClientId(&self) -> DOMString {
+        self.client_id.clone()
+    }
+
+    // https://w3c.github.io/ServiceWorker/#fetch-event-isreload
+    fn IsReload(&self) -> bool {
+        self.is_reload
+    }
+
+    // https://w3c.github.io/ServiceWorker/#fetch-event-respondwith
+    #[allow(unrooted_must_root)]
+    fn RespondWith(&self, promise: &Promise) -> ErrorResult {
+        let event = self.upcast::<Event>();
+        // Step 1.
+        if !event.dispatching() || event.DefaultPrevented() {
+            return Err(Error::InvalidState);
+        }
+        // Step 2.
+        if self.respond_with_entered.get() {
+            return Err(Error::InvalidState);
+        }
+        // Step 3.
+        self.upcast::<ExtendableEvent>().add_lifetime_promise(promise)?;
+        // Step 4.
+        event.StopPropagation();
+        event.StopImmediatePropagation();
+        // Step 5.
+        self.respond_with_entered.set(true);
+        // Steps 7-8.
+        upon_settlement(promise,
+                        Box::new(RespondWithFulfilled(Dom::from_ref(self))),
+                        Box::new(RespondWithRejected(Dom::from_ref(self))));
+        Ok(())
+    }
+
+    // https://dom.spec.whatwg.org/#dom-event-istrusted
+    fn IsTrusted(&self) -> bool {
+        self.upcast::<Event>().IsTrusted()
+    }
+}
+
+/// <https://w3c.github.io/ServiceWorker/#fetch-event-respondwith> step 8.1.
+#[derive(JSTraceable, MallocSizeOf)]
+#[must_root]
+struct RespondWithFulfilled(Dom<FetchEvent>);
+
+impl Callback for RespondWithFulfilled {
+    #[allow(unrooted_must_root)]
+    fn callback(&self, _cx: *mut JSContext, v: HandleValue) {
+        let response = match root_from_handlevalue::<Response>(v) {
+            Ok(response) => response,
+            Err(()) => return self.0.respond_with_network_error(),
+        };
+        if response.Type() == ResponseType::Error || response.BodyUsed() {
+            return self.0.respond_with_network_error();
+        }
+        let steps = Box::new(SendResponse {
+            event: Dom::from_ref(&*self.0),
+            headers: response.Headers().get_headers_list(),
+            status: response.Status(),
+            status_text: response.StatusText().into(),
+        });
+        if response.read_body(steps).is_err() {
+            self.0.respond_with_network_error();
+        }
+    }
+}
+
+/// <https://w3c.github.io/ServiceWorker/#fetch-event-respondwith> step 7.1.
+#[derive(JSTraceable, MallocSizeOf)]
+#[must_root]
+struct RespondWithRejected(Dom<FetchEvent>);
+
+impl Callback for RespondWithRejected {
+    fn callback(&self, _cx: *mut JSContext, _v: HandleValue) {
+        self.0.respond_with_network_error();
+    }
+}
+
+/// Sends the response a service worker responded with, once its body has been read.
+#[derive(JSTraceable, MallocSizeOf)]
+#[must_root]
+struct SendResponse {
+    event: Dom<FetchEvent>,
+    #[ignore_malloc_size_of = "Defined in hyper"]
+    headers: HyperHeaders,
+    status: u16,
+    status_text: Vec<u8>,
+}
+
+impl ReadAllBytesSteps for SendResponse {
+    #[allow(unrooted_must_root)]
+    fn success_steps(self: Box<Self>, bytes: Vec<u8>) {
+        let SendResponse { event, headers, status, status_text } = *self;
+        let status_text = String::from_utf8_lossy(&status_text).into_owned();
+        let raw_status = RawStatus(status, status_text.into());
+        event.respond(Some(Ok(CustomResponse::new(headers, raw_status, bytes))));
+    }
+
+    fn failure_steps(self: Box<Self>, _cx: *mut JSContext, _error: HandleValue) {
+        self.event.respond_with_network_error();
+    }
+}
@@ -0,0 +1,346 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use cssparser::{Parser, ParserInput, UnicodeRange};
+use dom::bindings::cell::DomRefCell;
+use dom::bindings::codegen::Bindings::DocumentBinding::DocumentMethods;
+use dom::bindings::codegen::Bindings::FontFaceBinding::{self, FontFaceDescriptors, FontFaceLoadStatus};
+use dom::bindings::codegen::Bindings::FontFaceBinding::FontFaceMethods;
+use dom::bindings::codegen::Bindings::FontFaceSetBinding::FontFaceSetMethods;
+use dom::bindings::codegen::Bindings::WindowBinding::WindowBinding::WindowMethods;
+use dom::bindings::codegen::UnionTypes::StringOrArrayBufferOrArrayBufferView;
+use dom::bindings::error::{Error, ErrorResult, Fallible};
+use dom::bindings::inheritance::Castable;
+use dom::bindings::refcounted::Trusted;
+use dom::bindings::reflector::{DomObject, Reflector, reflect_dom_object};
+use dom::bindings::root::DomRoot;
+use dom::bindings::str::DOMString;
+use dom::promise::Promise;
+use dom::window::Window;
+use dom_struct::dom_struct;
+use ipc_channel::ipc;
+use ipc_channel::router::ROUTER;
+use script_layout_interface::message::{Msg, WebFontSource};
+use servo_atoms::Atom;
+use std::cell::Cell;
+use std::rc::Rc;
+use style::context::QuirksMode;
+use style::font_face::{EffectiveSources, FontDisplay, FontStretch, FontStyle, FontWeight, Source};
+use style::parser::{Parse, ParserContext};
+use style::stylesheets::CssRuleType;
+use style::values::computed::font::{FamilyName, FamilyNameSyntax};
+use style_traits::{ParsingMode, ToCss};
+use task_source::{TaskSource, TaskSourceName};
+
+/// <https://drafts.csswg.org/css-font-loading/#fontface-interface>
+///
+/// The font cache only picks faces of a family by looking at their data, so
+/// the descriptors other than the family are only validated and reflected.
+#[dom_struct]
+pub struct FontFace {
+    reflector_: Reflector,
+    /// The id of this font face within its document, which layout knows it by.
+    id: u64,
+    /// <https://drafts.csswg.org/css-font-loading/#dom-fontface-family>
+    family: DomRefCell<DOMString>,
+    /// The parsed name of the family of this font face.
+    family_name: DomRefCell<Atom>,
+    /// <https://drafts.csswg.org/css-font-loading/#dom-fontface-style>
+    style: DomRefCell<DOMString>,
+    /// <https://drafts.csswg.org/css-font-loading/#dom-fontface-weight>
+    weight: DomRefCell<DOMString>,
+    /// <https://drafts.csswg.org/css-font-loading/#dom-fontface-stretch>
+    stretch: DomRefCell<DOMString>,
+    /// <https://drafts.csswg.org/css-font-loading/#dom-fontface-unicoderange>
+    unicode_range: DomRefCell<DOMString>,
+    /// The inclusive ranges of code points `unicode_range` covers.
+    unicode_ranges: DomRefCell<Vec<(u32, u32)>>,
+    /// <https://drafts.csswg.org/css-font-loading/#dom-fontface-display>
+    display: DomRefCell<DOMString>,
+    /// <https://drafts.csswg.org/css-font-loading/#dom-fontface-status>
+    status: Cell<FontFaceLoadStatus>,
+    /// <https://drafts.csswg.org/css-font-loading/#dom-fontface-loaded>
+    #[ignore_malloc_size_of = "promises are hard"]
+    loaded_promise: Rc<Promise>,
+    /// Where to load this font face from, until it starts loading.
+    #[ignore_malloc_size_of = "Defined in style"]
+    source: DomRefCell<Option<WebFontSource>>,
+}
+
+impl FontFace {
+    #[allow(unrooted_must_root)]
+    fn new_inherited(window: &Window, family: DOMString) -> FontFace {
+        FontFace {
+            reflector_: Reflector::new(),
+            id: window.Document().Fonts().next_font_face_id(),
+            family: DomRefCell::new(family),
+            family_name: DomRefCell::new(atom!("")),
+            style: DomRefCell::new(DOMString::from("normal")),
+            weight: DomRefCell::new(DOMString::from("normal")),
+            stretch: DomRefCell::new(DOMString::from("normal")),
+            unicode_range: DomRefCell::new(DOMString::from("U+0-10FFFF")),
+            unicode_ranges: DomRefCell::new(vec![(0, 0x10FFFF)]),
+            display: DomRefCell::new(DOMString::from("auto")),
+            status: Cell::new(FontFaceLoadStatus::Unloaded),
+            loaded_promise: Promise::new(window.upcast()),
+            source: DomRefCell::new(None),
+        }
+    }
+
+    // https://drafts.csswg.org/css-font-loading/#dom-fontface-fontface
+    #[allow(unsafe_code)]
+    pub fn Constructor(
+        window: &Window,
+        family: DOMString,
+        mut source: StringOrArrayBufferOrArrayBufferView,
+        descriptors: &FontFaceDescriptors,
+    ) -> Fallible<DomRoot<FontFace>> {
+        // Step 1.
+        let font_face = reflect_dom_object(
+            Box::new(FontFace::new_inherited(window, family.clone())),
+            window,
+            FontFaceBinding::Wrap,
+        );
+
+        // Step 2. A font face whose family, descriptors or source doesn't
+        // parse is in the error state, rather than the constructor throwing.
+        let parsed = font_face.SetFamily(family)
+            .and_then(|()| font_face.SetStyle(descriptors.style.clone()))
+            .and_then(|()| font_face.SetWeight(descriptors.weight.clone()))
+            .and_then(|()| font_face.SetStretch(descriptors.stretch.clone()))
+            .and_then(|()| font_face.SetUnicodeRange(descriptors.unicodeRange.clone()))
+            .and_then(|()| font_face.SetDisplay(descriptors.display.clone()))
+            .and_then(|()| match source {
+                StringOrArrayBufferOrArrayBufferView::String(ref source) => {
+                    let sources = parse_descriptor::<Vec<Source>>(window, source)?;
+                    Ok(WebFontSource::Sources(EffectiveSources::new(&sources)))
+                },
+                StringOrArrayBufferOrArrayBufferView::ArrayBuffer(ref mut buffer) => {
+                    Ok(WebFontSource::Data(unsafe { buffer.as_slice().to_vec() }))
+                },
+                StringOrArrayBufferOrArrayBufferView::ArrayBufferView(ref mut view) => {
+                    Ok(WebFontSource::Data(unsafe { view.as_slice().to_vec() }))
+                },
+            });
+        let source = match parsed {
+            Ok(source) => source,
+            Err(error) => {
+                font_face.status.set(FontFaceLoadStatus::Error);
+                font_face.loaded_promise.reject_error(error);
+                return Ok(font_face);
+            },
+        };
+
+        // Step 3-4. Font faces created from data are loaded right away.
+        let is_data = match source {
+            WebFontSource::Data(_) => true,
+            WebFontSource::Sources(_) => false,
+        };
+        *font_face.source.borrow_mut() = Some(source);
+        if is_data {
+            font_face.load();
+        }
+
+        // Step 5.
+        Ok(font_face)
+    }
+
+    /// The name of the family of this font face, as the font cache knows it.
+    pub fn family_name(&self) -> Atom {
+        self.family_name.borrow().clone()
+    }
+
+    fn family_for_layout(&self) -> FamilyName {
+        FamilyName {
+            name: self.family_name(),
+            syntax: FamilyNameSyntax::Quoted,
+        }
+    }
+
+    /// Whether the unicode ranges of this font face cover some of the
+    /// characters of the text.
+    pub fn covers_text(&self, text: &str) -> bool {
+        let unicode_ranges = self.unicode_ranges.borrow();
+        text.chars().any(|c| {
+            unicode_ranges.iter().any(|&(start, end)| start <= c as u32 && c as u32 <= end)
+        })
+    }
+
+    /// Lets the document use this font face, as it was added to its
+    /// `FontFaceSet`.
+    pub fn add_to_font_face_set(&self) {
+        let msg = Msg::AddWebFontToSet(self.id, self.family_for_layout());
+        self.global().as_window().layout_chan().send(msg).unwrap();
+    }
+
+    /// Stops the document from using this font face, as it was removed from
+    /// its `FontFaceSet`.
+    pub fn remove_from_font_face_set(&self) {
+        let msg = Msg::RemoveWebFontFromSet(self.id);
+        self.global().as_window().layout_chan().send(msg).unwrap();
+    }
+
+    /// <https://drafts.csswg.org/css-font-loading/#font-face-load>
+    fn load(&self) {
+        // Step 1. The source is only there while the font face is unloaded.
+        let source = match self.source.borrow_mut().take() {
+            Some(source) => source,
+            None => return,
+        };
+
+        // Step 2.
+        self.status.set(FontFaceLoadStatus::Loading);
+        let global = self.global();
+        let window = global.as_window();
+        window.Document().Fonts().font_face_loading(self);
+
+        // Step 3. The font cache lets layout know about the new font face
+        // itself, so that it gets used by the next reflow.
+        let (sender, receiver) = ipc::channel().unwrap();
+        let this = Trusted::new(self);
+        let task_source = window.dom_manipulation_task_source();
+        let canceller = window.task_canceller(TaskSourceName::DOMManipulation);
+        ROUTER.add_route(receiver.to_opaque(), Box::new(move |message| {
+            let loaded = message.to().unwrap();
+            let this = this.clone();
+            let _ = task_source.queue_with_canceller(
+                task!(finish_loading_font_face: move || {
+                    this.root().finish_loading(loaded);
+                }),
+                &canceller,
+            );
+        }));
+        let msg = Msg::LoadWebFont(self.id, self.family_for_layout(), source, sender);
+        window.layout_chan().send(msg).unwrap();
+    }
+
+    /// Settles the loaded promise of this font face once the font cache is
+    /// done with it.
+    fn finish_loading(&self, loaded: bool) {
+        if loaded {
+            self.status.set(FontFaceLoadStatus::Loaded);
+            self.loaded_promise.resolve_native(&DomRoot::from_ref(self));
+        } else {
+            self.status.set(FontFaceLoadStatus::Error);
+            self.loaded_promise.reject_error(Error::Network);
+        }
+        self.global().as_window().Document().Fonts().font_face_loaded(self);
+    }
+}
+
+impl FontFaceMethods for FontFace {
+    // https://drafts.csswg.org/css-font-loading/#dom-fontface-family
+    fn Family(&self) -> DOMString {
+        self.family.borrow().clone()
+    }
+
+    // https://drafts.csswg.org/css-font-loading/#dom-fontface-family
+    fn SetFamily(&self, family: DOMString) -> ErrorResult {
+        let global = self.global();
+        let window = global.as_window();
+        let family_name = parse_descriptor::<FamilyName>(window, &family)?;
+        *self.family_name.borrow_mut() = family_name.name;
+        *self.family.borrow_mut() = family;
+
+        // Let the document use this font face under its new family.
+        if window.Document().Fonts().Has(self) {
+            self.add_to_font_face_set();
+        }
+        Ok(())
+    }
+
+    // https://drafts.csswg.org/css-font-loading/#dom-fontface-style
+    fn Style(&self) -> DOMString {
+        self.style.borrow().clone()
+    }
+
+    // https://drafts.csswg.org/css-font-loading/#dom-fontface-style
+    fn SetStyle(&self, style: DOMString) -> ErrorResult {
+        let style = parse_descriptor::<FontStyle>(self.global().as_window(), &style)?;
+        *self.style.borrow_mut() = DOMString::from(style.to_css_string());
+        Ok(())
+    }
+
+    // https://drafts.csswg.org/css-font-loading/#dom-fontface-weight
+    fn Weight(&self) -> DOMString {
+        self.weight.borrow().clone()
+    }
+
+    // https://drafts.csswg.org/css-font-loading/#dom-fontface-weight
+    fn SetWeight(&self, weight: DOMString) -> ErrorResult {
+        let weight = parse_descriptor::<FontWeight>(self.global().as_window(), &weight)?;
+        *self.weight.borrow_mut() = DOMString::from(weight.to_css_string());
+        Ok(())
+    }
+
+    // https://drafts.csswg.org/css-font-loading/#dom-fontface-stretch
+    fn Stretch(&self) -> DOMString {
+        self.stretch.borrow().clone()
+    }
+
+    // https://drafts.csswg.org/css-font-loading/#dom-fontface-stretch
+    fn SetStretch(&self, stretch: DOMString) -> ErrorResult {
+        let stretch = parse_descriptor::<FontStretch>(self.global().as_window(), &stretch)?;
+        *self.stretch.borrow_mut() = DOMString::from(stretch.to_css_string());
+        Ok(())
+    }
+
+    // https://drafts.csswg.org/css-font-loading/#dom-fontface-unicoderange
+    fn UnicodeRange(&self) -> DOMString {
+        self.unicode_range.borrow().clone()
+    }
+
+    // https://drafts.csswg.org/css-font-loading/#dom-fontface-unicoderange
+    fn SetUnicodeRange(&self, unicode_range: DOMString) -> ErrorResult {
+        let ranges = parse_descriptor::<Vec<UnicodeRange>>(self.global().as_window(), &unicode_range)?;
+        *self.unicode_ranges.borrow_mut() = ranges.iter().map(|range| (range.start, range.end)).collect();
+        *self.unicode_range.borrow_mut() = unicode_range;
+        Ok(())
+    }
+
+    // https://drafts.csswg.org/css-font-loading/#dom-fontface-display
+    fn Display(&self) -> DOMString {
+        self.display.borrow().clone()
+    }
+
+    // https://drafts.csswg.org/css-font-loading/#dom-fontface-display
+    fn SetDisplay(&self, display: DOMString) -> ErrorResult {
+        let display = parse_descriptor::<FontDisplay>(self.global().as_window(), &display)?;
+        *self.display.borrow_mut() = DOMString::from(display.to_css_string());
+        Ok(())
+    }
+
+    // https://drafts.csswg.org/css-font-loading/#dom-fontface-status
+    fn Status(&self) -> FontFaceLoadStatus {
+        self.status.get()
+    }
+
+    // https://drafts.csswg.org/css-font-loading/#dom-fontface-load
+    fn Load(&self) -> Rc<Promise> {
+        self.load();
+        self.loaded_promise.clone()
+    }
+
+    // https://drafts.csswg.org/css-font-loading/#dom-fontface-loaded
+    fn Loaded(&self) -> Rc<Promise> {
+        self.loaded_promise.clone()
+    }
+}
+
+/// Parses the value of a descriptor of a `@font-face` rule, throwing a
+/// `SyntaxError` if it's invalid.
+fn parse_descriptor<T: Parse>(window: &Window, value: &str) -> Fallible<T> {
+    let url = window.get_url();
+    let context = ParserContext::new_for_cssom(
+        &url,
+        Some(CssRuleType::FontFace),
+        ParsingMode::DEFAULT,
+        QuirksMode::NoQuirks,
+        None,
+        None,
+    );
+    let mut input = ParserInput::new(value);
+    let mut parser = Parser::new(&mut input);
+    parser.parse_entirely(|input| T::parse(&context, input)).map_err(|_| Error::Syntax)
+}
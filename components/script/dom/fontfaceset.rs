@@ -0,0 +1,437 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use cssparser::{Parser, ParserInput};
+use dom::bindings::cell::DomRefCell;
+use dom::bindings::codegen::Bindings::DocumentBinding::{DocumentMethods, DocumentReadyState};
+use dom::bindings::codegen::Bindings::FontFaceBinding::{FontFaceLoadStatus, FontFaceMethods};
+use dom::bindings::codegen::Bindings::FontFaceSetBinding::{self, FontFaceSetLoadStatus, FontFaceSetMethods};
+use dom::bindings::codegen::Bindings::WindowBinding::WindowBinding::WindowMethods;
+use dom::bindings::error::{Error, Fallible};
+use dom::bindings::inheritance::Castable;
+use dom::bindings::refcounted::Trusted;
+use dom::bindings::reflector::{DomObject, reflect_dom_object};
+use dom::bindings::root::{Dom, DomRoot};
+use dom::bindings::str::DOMString;
+use dom::event::Event;
+use dom::eventtarget::EventTarget;
+use dom::fontface::FontFace;
+use dom::fontfacesetloadevent::FontFaceSetLoadEvent;
+use dom::promise::Promise;
+use dom::window::Window;
+use dom_struct::dom_struct;
+use euclid::{TypedScale, TypedSize2D};
+use servo_atoms::Atom;
+use std::cell::Cell;
+use std::rc::Rc;
+use style::media_queries::{Device, MediaType};
+use style::parser::ParserContext;
+use style::properties::shorthands::font;
+use style::stylesheets::CssRuleType;
+use style::values::computed::{Context, ToComputedValue};
+use style::values::computed::font::SingleFontFamily;
+use style_traits::ParsingMode;
+use task_source::TaskSource;
+
+/// A call to `FontFaceSet.load()` waiting for its font faces to load.
+#[derive(JSTraceable, MallocSizeOf)]
+#[must_root]
+struct PendingFontFaceSetLoad {
+    #[ignore_malloc_size_of = "promises are hard"]
+    promise: Rc<Promise>,
+    font_faces: Vec<Dom<FontFace>>,
+}
+
+/// <https://drafts.csswg.org/css-font-loading/#FontFaceSet-interface>
+///
+/// Only the font faces added from script are in the set, the ones of
+/// `@font-face` rules are only taken into account to know when the set is
+/// ready.
+#[dom_struct]
+pub struct FontFaceSet {
+    eventtarget: EventTarget,
+    /// The font faces in this set, in insertion order.
+    font_faces: DomRefCell<Vec<Dom<FontFace>>>,
+    /// <https://drafts.csswg.org/css-font-loading/#dom-fontfaceset-loadingfonts-slot>
+    loading_fonts: DomRefCell<Vec<Dom<FontFace>>>,
+    /// <https://drafts.csswg.org/css-font-loading/#dom-fontfaceset-loadedfonts-slot>
+    loaded_fonts: DomRefCell<Vec<Dom<FontFace>>>,
+    /// <https://drafts.csswg.org/css-font-loading/#dom-fontfaceset-failedfonts-slot>
+    failed_fonts: DomRefCell<Vec<Dom<FontFace>>>,
+    /// <https://drafts.csswg.org/css-font-loading/#dom-fontfaceset-readypromise-slot>
+    #[ignore_malloc_size_of = "promises are hard"]
+    ready_promise: DomRefCell<Rc<Promise>>,
+    /// <https://drafts.csswg.org/css-font-loading/#dom-fontfaceset-status>
+    status: Cell<FontFaceSetLoadStatus>,
+    /// The calls to `load()` whose font faces are still loading.
+    pending_loads: DomRefCell<Vec<PendingFontFaceSetLoad>>,
+    /// The id of the last font face created for the document of this set.
+    font_face_id: Cell<u64>,
+}
+
+impl FontFaceSet {
+    #[allow(unrooted_must_root)]
+    fn new_inherited(window: &Window) -> FontFaceSet {
+        FontFaceSet {
+            eventtarget: EventTarget::new_inherited(),
+            font_faces: DomRefCell::new(vec![]),
+            loading_fonts: DomRefCell::new(vec![]),
+            loaded_fonts: DomRefCell::new(vec![]),
+            failed_fonts: DomRefCell::new(vec![]),
+            ready_promise: DomRefCell::new(Promise::new(window.upcast())),
+            status: Cell::new(FontFaceSetLoadStatus::Loading),
+            pending_loads: DomRefCell::new(vec![]),
+            font_face_id: Cell::new(0),
+        }
+    }
+
+    pub fn new(window: &Window) -> DomRoot<FontFaceSet> {
+        let font_face_set = reflect_dom_object(
+            Box::new(FontFaceSet::new_inherited(window)),
+            window,
+            FontFaceSetBinding::Wrap,
+        );
+        font_face_set.update_status();
+        font_face_set
+    }
+
+    /// Returns a new identifier for a font face created for the document of
+    /// this set.
+    pub fn next_font_face_id(&self) -> u64 {
+        let id = self.font_face_id.get() + 1;
+        self.font_face_id.set(id);
+        id
+    }
+
+    /// <https://drafts.csswg.org/css-font-loading/#fontfaceset-pending-on-the-environment>
+    ///
+    /// The document is pending until it has loaded, and so are the fonts of
+    /// its `@font-face` rules.
+    fn is_pending_on_the_environment(&self) -> bool {
+        let global = self.global();
+        let window = global.as_window();
+        window.Document().ReadyState() != DocumentReadyState::Complete ||
+            window.has_pending_web_fonts()
+    }
+
+    /// Called when a font face starts loading.
+    pub fn font_face_loading(&self, font_face: &FontFace) {
+        if !self.font_faces.borrow().iter().any(|other| *other == font_face) {
+            return;
+        }
+        self.add_loading_font(font_face);
+    }
+
+    /// <https://drafts.csswg.org/css-font-loading/#fontfaceset-loading>
+    fn add_loading_font(&self, font_face: &FontFace) {
+        let was_empty = {
+            let mut loading_fonts = self.loading_fonts.borrow_mut();
+            let was_empty = loading_fonts.is_empty();
+            loading_fonts.push(Dom::from_ref(font_face));
+            was_empty
+        };
+        if !was_empty {
+            return;
+        }
+
+        // Step 1.
+        self.status.set(FontFaceSetLoadStatus::Loading);
+
+        // Step 2.
+        if self.ready_promise.borrow().is_fulfilled() {
+            *self.ready_promise.borrow_mut() = Promise::new(&self.global());
+        }
+
+        // Step 3.
+        self.queue_load_event(atom!("loading"), vec![]);
+    }
+
+    /// Called when a font face finished loading, successfully or not.
+    pub fn font_face_loaded(&self, font_face: &FontFace) {
+        self.settle_pending_loads();
+
+        let was_loading = {
+            let mut loading_fonts = self.loading_fonts.borrow_mut();
+            let len = loading_fonts.len();
+            loading_fonts.retain(|other| *other != font_face);
+            loading_fonts.len() != len
+        };
+        if !was_loading {
+            return;
+        }
+        if font_face.Status() == FontFaceLoadStatus::Loaded {
+            self.loaded_fonts.borrow_mut().push(Dom::from_ref(font_face));
+        } else {
+            self.failed_fonts.borrow_mut().push(Dom::from_ref(font_face));
+        }
+        self.update_status();
+    }
+
+    /// Resolves or rejects the promises of the calls to `load()` whose font
+    /// faces are done loading.
+    #[allow(unrooted_must_root)]
+    fn settle_pending_loads(&self) {
+        let settled = {
+            let mut pending_loads = self.pending_loads.borrow_mut();
+            let (settled, pending): (Vec<_>, Vec<_>) = pending_loads.drain(..).partition(|load| {
+                load.font_faces.iter().all(|font_face| font_face.Status() == FontFaceLoadStatus::Loaded) ||
+                    load.font_faces.iter().any(|font_face| font_face.Status() == FontFaceLoadStatus::Error)
+            });
+            *pending_loads = pending;
+            settled
+        };
+        for load in settled {
+            if load.font_faces.iter().any(|font_face| font_face.Status() == FontFaceLoadStatus::Error) {
+                load.promise.reject_error(Error::Network);
+            } else {
+                let font_faces: Vec<_> = load.font_faces
+                    .iter()
+                    .map(|font_face| DomRoot::from_ref(&**font_face))
+                    .collect();
+                load.promise.resolve_native(&font_faces);
+            }
+        }
+    }
+
+    /// Switches this set to the loaded state if none of its font faces are
+    /// loading, and if it isn't pending on the environment anymore.
+    ///
+    /// <https://drafts.csswg.org/css-font-loading/#fontfaceset-loaded>
+    pub fn update_status(&self) {
+        if self.status.get() == FontFaceSetLoadStatus::Loaded ||
+            !self.loading_fonts.borrow().is_empty() ||
+            self.is_pending_on_the_environment()
+        {
+            return;
+        }
+
+        // Step 1.
+        self.status.set(FontFaceSetLoadStatus::Loaded);
+
+        // Step 2.
+        self.ready_promise.borrow().resolve_native(&DomRoot::from_ref(self));
+
+        // Steps 3-6.
+        let loaded_fonts = self.loaded_fonts
+            .borrow_mut()
+            .drain(..)
+            .map(|font_face| DomRoot::from_ref(&*font_face))
+            .collect();
+        let failed_fonts: Vec<_> = self.failed_fonts
+            .borrow_mut()
+            .drain(..)
+            .map(|font_face| DomRoot::from_ref(&*font_face))
+            .collect();
+        self.queue_load_event(atom!("loadingdone"), loaded_fonts);
+        if !failed_fonts.is_empty() {
+            self.queue_load_event(atom!("loadingerror"), failed_fonts);
+        }
+    }
+
+    /// Queues a task to fire a `FontFaceSetLoadEvent` at this set.
+    fn queue_load_event(&self, type_: Atom, font_faces: Vec<DomRoot<FontFace>>) {
+        let global = self.global();
+        let window = global.as_window();
+        let this = Trusted::new(self);
+        let font_faces: Vec<_> = font_faces.iter().map(|font_face| Trusted::new(&**font_face)).collect();
+        let _ = window.dom_manipulation_task_source().queue(
+            task!(fire_font_face_set_load_event: move || {
+                let this = this.root();
+                let global = this.global();
+                let font_faces = font_faces.iter().map(|font_face| font_face.root()).collect();
+                let event = FontFaceSetLoadEvent::new(global.as_window(), type_, false, false, font_faces);
+                event.upcast::<Event>().fire(this.upcast());
+            }),
+            window.upcast(),
+        );
+    }
+
+    /// <https://drafts.csswg.org/css-font-loading/#find-the-matching-font-faces>
+    ///
+    /// Also returns whether all the font families of the font are available,
+    /// that is whether some font faces of this set in the family cover the
+    /// text, or whether there are system fonts or `@font-face` rules for it.
+    fn find_matching_font_faces(&self, font: &str, text: &str) -> Fallible<(Vec<DomRoot<FontFace>>, bool)> {
+        // Step 1.
+        let families = self.parse_font_families(font).ok_or(Error::Syntax)?;
+
+        // Steps 2-7.
+        let global = self.global();
+        let window = global.as_window();
+        let font_faces = self.font_faces.borrow();
+        let mut matching_font_faces: Vec<DomRoot<FontFace>> = vec![];
+        let mut all_available = true;
+        for family in &families {
+            let mut found = false;
+            for font_face in font_faces.iter() {
+                if !family.eq_ignore_ascii_case(&font_face.family_name()) || !font_face.covers_text(text) {
+                    continue;
+                }
+                found = true;
+                if !matching_font_faces.iter().any(|other| *font_face == &**other) {
+                    matching_font_faces.push(DomRoot::from_ref(&**font_face));
+                }
+            }
+            if !found && !window.has_font_family(family) {
+                all_available = false;
+            }
+        }
+        Ok((matching_font_faces, all_available))
+    }
+
+    /// Parses a CSS `font` value, returning the names of the font families it
+    /// lists, leaving out the generic ones.
+    fn parse_font_families(&self, font: &str) -> Option<Vec<Atom>> {
+        let global = self.global();
+        let document = global.as_window().Document();
+        let url = document.url();
+        let quirks_mode = document.quirks_mode();
+        let context = ParserContext::new_for_cssom(
+            &url,
+            Some(CssRuleType::Style),
+            ParsingMode::DEFAULT,
+            quirks_mode,
+            None,
+            None,
+        );
+        let mut input = ParserInput::new(font);
+        let mut parser = Parser::new(&mut input);
+        let longhands = parser.parse_entirely(|input| font::parse_value(&context, input)).ok()?;
+
+        // The font families don't depend on the viewport, so any device will
+        // do if the window has no size yet.
+        let device = document.device().unwrap_or_else(|| {
+            Device::new(MediaType::screen(), TypedSize2D::zero(), TypedScale::new(1.0))
+        });
+        Some(Context::for_media_query_evaluation(&device, quirks_mode, |context| {
+            longhands.font_family.to_computed_value(context).0.iter().filter_map(|family| {
+                match *family {
+                    SingleFontFamily::FamilyName(ref name) => Some(name.name.clone()),
+                    SingleFontFamily::Generic(_) => None,
+                }
+            }).collect()
+        }))
+    }
+}
+
+impl FontFaceSetMethods for FontFaceSet {
+    // https://drafts.csswg.org/css-font-loading/#fontfaceset-interface
+    fn Size(&self) -> u32 {
+        self.font_faces.borrow().len() as u32
+    }
+
+    // https://drafts.csswg.org/css-font-loading/#fontfaceset-interface
+    fn Has(&self, font: &FontFace) -> bool {
+        self.font_faces.borrow().iter().any(|other| *other == font)
+    }
+
+    // https://drafts.csswg.org/css-font-loading/#dom-fontfaceset-add
+    fn Add(&self, font: &FontFace) -> DomRoot<FontFaceSet> {
+        // Step 1.
+        if !self.Has(font) {
+            // Step 3.
+            self.font_faces.borrow_mut().push(Dom::from_ref(font));
+            font.add_to_font_face_set();
+
+            // Step 4.
+            if font.Status() == FontFaceLoadStatus::Loading {
+                self.add_loading_font(font);
+            }
+        }
+
+        // Step 5.
+        DomRoot::from_ref(self)
+    }
+
+    // https://drafts.csswg.org/css-font-loading/#dom-fontfaceset-delete
+    fn Delete(&self, font: &FontFace) -> bool {
+        // Steps 2-3.
+        self.loading_fonts.borrow_mut().retain(|other| *other != font);
+        self.loaded_fonts.borrow_mut().retain(|other| *other != font);
+        self.failed_fonts.borrow_mut().retain(|other| *other != font);
+
+        // Step 4.
+        let deleted = {
+            let mut font_faces = self.font_faces.borrow_mut();
+            let len = font_faces.len();
+            font_faces.retain(|other| *other != font);
+            font_faces.len() != len
+        };
+        if deleted {
+            font.remove_from_font_face_set();
+        }
+        self.update_status();
+        deleted
+    }
+
+    // https://drafts.csswg.org/css-font-loading/#dom-fontfaceset-clear
+    fn Clear(&self) {
+        for font_face in self.font_faces.borrow_mut().drain(..) {
+            font_face.remove_from_font_face_set();
+        }
+        self.loading_fonts.borrow_mut().clear();
+        self.loaded_fonts.borrow_mut().clear();
+        self.failed_fonts.borrow_mut().clear();
+        self.update_status();
+    }
+
+    // https://drafts.csswg.org/css-font-loading/#dom-fontfaceset-onloading
+    event_handler!(loading, GetOnloading, SetOnloading);
+
+    // https://drafts.csswg.org/css-font-loading/#dom-fontfaceset-onloadingdone
+    event_handler!(loadingdone, GetOnloadingdone, SetOnloadingdone);
+
+    // https://drafts.csswg.org/css-font-loading/#dom-fontfaceset-onloadingerror
+    event_handler!(loadingerror, GetOnloadingerror, SetOnloadingerror);
+
+    // https://drafts.csswg.org/css-font-loading/#dom-fontfaceset-load
+    #[allow(unrooted_must_root)]
+    fn Load(&self, font: DOMString, text: DOMString) -> Rc<Promise> {
+        // Step 1.
+        let promise = Promise::new(&self.global());
+
+        // Step 2.
+        let font_faces = match self.find_matching_font_faces(&font, &text) {
+            Ok((font_faces, _)) => font_faces,
+            Err(error) => {
+                promise.reject_error(error);
+                return promise;
+            },
+        };
+
+        // Steps 3-4. The promise is settled once all the font faces are done
+        // loading, which may be right away if they already are.
+        for font_face in &font_faces {
+            font_face.Load();
+        }
+        self.pending_loads.borrow_mut().push(PendingFontFaceSetLoad {
+            promise: promise.clone(),
+            font_faces: font_faces.iter().map(|font_face| Dom::from_ref(&**font_face)).collect(),
+        });
+        self.settle_pending_loads();
+
+        // Step 5.
+        promise
+    }
+
+    // https://drafts.csswg.org/css-font-loading/#dom-fontfaceset-check
+    fn Check(&self, font: DOMString, text: DOMString) -> Fallible<bool> {
+        // Steps 1-4. A font family with neither font faces in this set for the
+        // text nor system fonts can't be used to render it.
+        let (font_faces, all_available) = self.find_matching_font_faces(&font, &text)?;
+
+        // Step 5.
+        Ok(all_available && font_faces.iter().all(|font_face| font_face.Status() == FontFaceLoadStatus::Loaded))
+    }
+
+    // https://drafts.csswg.org/css-font-loading/#dom-fontfaceset-ready
+    fn Ready(&self) -> Rc<Promise> {
+        self.ready_promise.borrow().clone()
+    }
+
+    // https://drafts.csswg.org/css-font-loading/#dom-fontfaceset-status
+    fn Status(&self) -> FontFaceSetLoadStatus {
+        self.status.get()
+    }
+}
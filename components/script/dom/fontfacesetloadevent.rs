@@ -0,0 +1,84 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::EventBinding::EventMethods;
+use dom::bindings::codegen::Bindings::FontFaceSetLoadEventBinding;
+use dom::bindings::codegen::Bindings::FontFaceSetLoadEventBinding::FontFaceSetLoadEventInit;
+use dom::bindings::codegen::Bindings::FontFaceSetLoadEventBinding::FontFaceSetLoadEventMethods;
+use dom::bindings::conversions::ToJSValConvertible;
+use dom::bindings::error::Fallible;
+use dom::bindings::inheritance::Castable;
+use dom::bindings::reflector::{DomObject, reflect_dom_object};
+use dom::bindings::root::DomRoot;
+use dom::bindings::str::DOMString;
+use dom::event::Event;
+use dom::fontface::FontFace;
+use dom::window::Window;
+use dom_struct::dom_struct;
+use js::jsapi::{Heap, JSContext};
+use js::jsval::{JSVal, UndefinedValue};
+use servo_atoms::Atom;
+
+/// <https://drafts.csswg.org/css-font-loading/#fontfacesetloadevent>
+#[dom_struct]
+pub struct FontFaceSetLoadEvent {
+    event: Event,
+    /// The array of font faces the event is about.
+    fontfaces: Heap<JSVal>,
+}
+
+impl FontFaceSetLoadEvent {
+    #[allow(unsafe_code)]
+    pub fn new(
+        window: &Window,
+        type_: Atom,
+        bubbles: bool,
+        cancelable: bool,
+        fontfaces: Vec<DomRoot<FontFace>>,
+    ) -> DomRoot<FontFaceSetLoadEvent> {
+        let event = reflect_dom_object(
+            Box::new(FontFaceSetLoadEvent {
+                event: Event::new_inherited(),
+                fontfaces: Heap::default(),
+            }),
+            window,
+            FontFaceSetLoadEventBinding::Wrap,
+        );
+        event.upcast::<Event>().init_event(type_, bubbles, cancelable);
+
+        let cx = event.global().get_cx();
+        rooted!(in(cx) let mut fontfaces_array = UndefinedValue());
+        unsafe { fontfaces.to_jsval(cx, fontfaces_array.handle_mut()) };
+        event.fontfaces.set(fontfaces_array.get());
+
+        event
+    }
+
+    pub fn Constructor(
+        window: &Window,
+        type_: DOMString,
+        init: &FontFaceSetLoadEventInit,
+    ) -> Fallible<DomRoot<FontFaceSetLoadEvent>> {
+        Ok(FontFaceSetLoadEvent::new(
+            window,
+            Atom::from(type_),
+            init.parent.bubbles,
+            init.parent.cancelable,
+            init.fontfaces.clone().unwrap_or(vec![]),
+        ))
+    }
+}
+
+impl FontFaceSetLoadEventMethods for FontFaceSetLoadEvent {
+    #[allow(unsafe_code)]
+    // https://drafts.csswg.org/css-font-loading/#dom-fontfacesetloadevent-fontfaces
+    unsafe fn Fontfaces(&self, _cx: *mut JSContext) -> JSVal {
+        self.fontfaces.get()
+    }
+
+    // https://dom.spec.whatwg.org/#dom-event-istrusted
+    fn IsTrusted(&self) -> bool {
+        self.upcast::<Event>().IsTrusted()
+    }
+}
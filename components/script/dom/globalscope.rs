@@ -4,29 +4,41 @@
 
 use devtools_traits::{ScriptToDevtoolsControlMsg, WorkerId};
 use dom::bindings::cell::DomRefCell;
+use dom::bindings::codegen::Bindings::EventBinding::EventInit;
 use dom::bindings::codegen::Bindings::EventSourceBinding::EventSourceBinding::EventSourceMethods;
+use dom::bindings::codegen::Bindings::SecurityPolicyViolationEventBinding::SecurityPolicyViolationEventDisposition;
+use dom::bindings::codegen::Bindings::SecurityPolicyViolationEventBinding::SecurityPolicyViolationEventInit;
 use dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
 use dom::bindings::codegen::Bindings::WorkerGlobalScopeBinding::WorkerGlobalScopeMethods;
 use dom::bindings::conversions::root_from_object;
 use dom::bindings::error::{ErrorInfo, report_pending_exception};
 use dom::bindings::inheritance::Castable;
+use dom::bindings::refcounted::Trusted;
 use dom::bindings::reflector::DomObject;
-use dom::bindings::root::{DomRoot, MutNullableDom};
+use dom::bindings::root::{Dom, DomRoot, MutNullableDom};
 use dom::bindings::settings_stack::{AutoEntryScript, entry_global, incumbent_global};
-use dom::bindings::str::DOMString;
+use dom::bindings::str::{DOMString, USVString};
 use dom::bindings::weakref::DOMTracker;
+use dom::broadcastchannel::BroadcastChannel;
+use dom::cachestorage::CacheStorage;
 use dom::crypto::Crypto;
 use dom::dedicatedworkerglobalscope::DedicatedWorkerGlobalScope;
+use dom::element::Element;
 use dom::errorevent::ErrorEvent;
 use dom::event::{Event, EventBubbles, EventCancelable, EventStatus};
 use dom::eventsource::EventSource;
 use dom::eventtarget::EventTarget;
+use dom::idbfactory::IDBFactory;
+use dom::messageport::MessagePort;
+use dom::node::Node;
 use dom::performance::Performance;
+use dom::securitypolicyviolationevent::SecurityPolicyViolationEvent;
 use dom::window::Window;
 use dom::workerglobalscope::WorkerGlobalScope;
 use dom::workletglobalscope::WorkletGlobalScope;
 use dom_struct::dom_struct;
-use ipc_channel::ipc::IpcSender;
+use ipc_channel::ipc::{self, IpcSender};
+use ipc_channel::router::ROUTER;
 use js::{JSCLASS_IS_DOMJSCLASS, JSCLASS_IS_GLOBAL};
 use js::glue::{IsWrapper, UnwrapObject};
 use js::jsapi::{CurrentGlobalOrNull, GetGlobalForObjectCrossCompartment};
@@ -38,26 +50,32 @@ use js::rust::{HandleValue, MutableHandleValue};
 use js::rust::wrappers::Evaluate2;
 use libc;
 use microtask::{Microtask, MicrotaskQueue};
-use msg::constellation_msg::PipelineId;
-use net_traits::{CoreResourceThread, ResourceThreads, IpcSend};
+use msg::constellation_msg::{BroadcastChannelRouterId, MessagePortId, PipelineId};
+use net_traits::{CoreResourceMsg, CoreResourceThread, ResourceThreads, IpcSend};
+use net_traits::csp::{CspList, PolicyDisposition, Violation, ViolationResource};
+use net_traits::request::RequestId;
 use profile_traits::{mem, time};
+use script_module::ModuleTree;
 use script_runtime::{CommonScriptMsg, ScriptChan, ScriptPort};
 use script_thread::{MainThreadScriptChan, ScriptThread};
-use script_traits::{MsDuration, ScriptToConstellationChan, TimerEvent};
+use script_traits::{BroadcastMsg, MessagePortMsg, MsDuration, PortMessageTask, ScriptMsg};
+use script_traits::{ScriptToConstellationChan, TimerEvent};
 use script_traits::{TimerEventId, TimerSchedulerMsg, TimerSource};
 use servo_url::{MutableOrigin, ServoUrl};
 use std::cell::Cell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::collections::hash_map::Entry;
 use std::ffi::CString;
 use std::rc::Rc;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use task::TaskCanceller;
-use task_source::TaskSourceName;
+use task_source::{TaskSource, TaskSourceName};
+use task_source::database_access::DatabaseAccessTaskSource;
 use task_source::file_reading::FileReadingTaskSource;
 use task_source::networking::NetworkingTaskSource;
 use task_source::performance_timeline::PerformanceTimelineTaskSource;
+use task_source::port_message::PortMessageQueueTaskSource;
 use task_source::remote_event::RemoteEventTaskSource;
 use task_source::websocket::WebsocketTaskSource;
 use time::{Timespec, get_time};
@@ -79,6 +97,8 @@ impl Drop for AutoCloseWorker {
 pub struct GlobalScope {
     eventtarget: EventTarget,
     crypto: MutNullableDom<Crypto>,
+    indexed_db: MutNullableDom<IDBFactory>,
+    caches: MutNullableDom<CacheStorage>,
     next_worker_id: Cell<WorkerId>,
 
     /// Pipeline id associated with this global.
@@ -137,6 +157,29 @@ pub struct GlobalScope {
 
     /// Vector storing references of all eventsources.
     event_source_tracker: DOMTracker<EventSource>,
+
+    /// The ids of the ongoing fetches started by `fetch()` and `XMLHttpRequest`.
+    ongoing_fetches: DomRefCell<HashSet<RequestId>>,
+
+    /// The MessagePorts owned by this global.
+    message_ports: DomRefCell<HashMap<MessagePortId, Dom<MessagePort>>>,
+
+    /// The channel through which the constellation routes the messages posted to
+    /// the ports of this global, created along with the first port.
+    #[ignore_malloc_size_of = "channels are hard"]
+    message_port_chan: DomRefCell<Option<IpcSender<MessagePortMsg>>>,
+
+    /// The BroadcastChannels of this global that weren't closed yet.
+    broadcast_channels: DomRefCell<Vec<Dom<BroadcastChannel>>>,
+
+    /// The router through which the constellation delivers broadcasts to this global,
+    /// registered along with the first channel.
+    #[ignore_malloc_size_of = "channels are hard"]
+    broadcast_router: DomRefCell<Option<(BroadcastChannelRouterId, IpcSender<BroadcastMsg>)>>,
+
+    /// <https://html.spec.whatwg.org/multipage/#concept-settings-object-module-map>
+    #[ignore_malloc_size_of = "Rc<T> is hard"]
+    module_map: DomRefCell<HashMap<ServoUrl, Rc<ModuleTree>>>,
 }
 
 impl GlobalScope {
@@ -155,6 +198,8 @@ impl GlobalScope {
         Self {
             eventtarget: EventTarget::new_inherited(),
             crypto: Default::default(),
+            indexed_db: Default::default(),
+            caches: Default::default(),
             next_worker_id: Cell::new(WorkerId(0)),
             pipeline_id,
             devtools_wants_updates: Default::default(),
@@ -171,6 +216,12 @@ impl GlobalScope {
             microtask_queue,
             list_auto_close_worker: Default::default(),
             event_source_tracker: DOMTracker::new(),
+            ongoing_fetches: DomRefCell::new(HashSet::new()),
+            message_ports: DomRefCell::new(HashMap::new()),
+            message_port_chan: DomRefCell::new(None),
+            broadcast_channels: DomRefCell::new(Vec::new()),
+            broadcast_router: DomRefCell::new(None),
+            module_map: DomRefCell::new(HashMap::new()),
         }
     }
 
@@ -196,6 +247,190 @@ impl GlobalScope {
         canceled_any_fetch
     }
 
+    pub fn track_fetch(&self, id: RequestId) {
+        self.ongoing_fetches.borrow_mut().insert(id);
+    }
+
+    pub fn untrack_fetch(&self, id: RequestId) {
+        self.ongoing_fetches.borrow_mut().remove(&id);
+    }
+
+    /// Asks the network layer to stop the given fetches.
+    pub fn cancel_fetches(&self, ids: Vec<RequestId>) {
+        {
+            let mut ongoing_fetches = self.ongoing_fetches.borrow_mut();
+            for id in &ids {
+                ongoing_fetches.remove(id);
+            }
+        }
+        let _ = self.core_resource_thread().send(CoreResourceMsg::Cancel(ids));
+    }
+
+    /// Stops all the ongoing fetches of this global, as when its document is unloaded.
+    pub fn cancel_ongoing_fetches(&self) {
+        let ids: Vec<RequestId> = self.ongoing_fetches.borrow_mut().drain().collect();
+        if !ids.is_empty() {
+            let _ = self.core_resource_thread().send(CoreResourceMsg::Cancel(ids));
+        }
+    }
+
+    /// Makes the constellation route the messages posted to a port to this global,
+    /// once the port was created or transferred here.
+    pub fn track_message_port(&self, port: &MessagePort) {
+        let chan = self.message_port_chan();
+        self.message_ports.borrow_mut().insert(port.id(), Dom::from_ref(port));
+        let _ = self.script_to_constellation_chan().send(ScriptMsg::NewMessagePort(port.id(), chan));
+    }
+
+    /// Forgets about a port that was transferred away from this global, or closed.
+    pub fn untrack_message_port(&self, port: &MessagePort) {
+        self.message_ports.borrow_mut().remove(&port.id());
+    }
+
+    fn message_port_chan(&self) -> IpcSender<MessagePortMsg> {
+        if let Some(ref chan) = *self.message_port_chan.borrow() {
+            return chan.clone();
+        }
+        let (chan, receiver) = ipc::channel().unwrap();
+        let this = Trusted::new(self);
+        let task_source = self.port_message_queue();
+        let canceller = self.task_canceller(TaskSourceName::PortMessage);
+        ROUTER.add_route(receiver.to_opaque(), Box::new(move |message| {
+            let this = this.clone();
+            let _ = match message.to().unwrap() {
+                MessagePortMsg::Message(port_id, task) => task_source.queue_with_canceller(
+                    task!(route_port_message: move || {
+                        this.root().route_port_message(port_id, task);
+                    }),
+                    &canceller,
+                ),
+                MessagePortMsg::Disentangle(port_id) => task_source.queue_with_canceller(
+                    task!(disentangle_port: move || {
+                        this.root().disentangle_port(port_id);
+                    }),
+                    &canceller,
+                ),
+            };
+        }));
+        *self.message_port_chan.borrow_mut() = Some(chan.clone());
+        chan
+    }
+
+    /// Hands a message posted to one of the ports of this global to that port.
+    fn route_port_message(&self, port_id: MessagePortId, task: PortMessageTask) {
+        let port = self.message_ports.borrow().get(&port_id).map(|port| DomRoot::from_ref(&**port));
+        match port {
+            Some(port) => port.handle_message(task),
+            None => {
+                // The port was transferred away while the message was in flight,
+                // let the constellation route it to its new owner.
+                let msg = ScriptMsg::PostMessageToPort(port_id, task);
+                let _ = self.script_to_constellation_chan().send(msg);
+            },
+        }
+    }
+
+    /// Lets one of the ports of this global know that the port it was entangled with went away.
+    fn disentangle_port(&self, port_id: MessagePortId) {
+        // If the port was transferred away meanwhile, messages it posts are dropped
+        // by the constellation anyway.
+        let port = self.message_ports.borrow().get(&port_id).map(|port| DomRoot::from_ref(&**port));
+        if let Some(port) = port {
+            port.disentangle();
+        }
+    }
+
+    /// Makes the broadcasts of same-origin globals reach a newly created channel.
+    pub fn track_broadcast_channel(&self, channel: &BroadcastChannel) {
+        if self.broadcast_router.borrow().is_none() {
+            self.register_broadcast_router();
+        }
+        self.broadcast_channels.borrow_mut().push(Dom::from_ref(channel));
+    }
+
+    /// Forgets about a closed channel, and stops receiving broadcasts once the
+    /// last channel of this global was closed.
+    pub fn untrack_broadcast_channel(&self, channel: &BroadcastChannel) {
+        let mut channels = self.broadcast_channels.borrow_mut();
+        channels.retain(|other| &**other as *const BroadcastChannel != channel as *const BroadcastChannel);
+        if !channels.is_empty() {
+            return;
+        }
+        if let Some((router_id, _)) = self.broadcast_router.borrow_mut().take() {
+            let _ = self.script_to_constellation_chan().send(ScriptMsg::RemoveBroadcastChannelRouter(router_id));
+        }
+    }
+
+    fn register_broadcast_router(&self) {
+        let router_id = BroadcastChannelRouterId::new();
+        let (chan, receiver) = ipc::channel().unwrap();
+        let this = Trusted::new(self);
+        // FIXME: The spec queues broadcasts on the DOM manipulation task source,
+        // which workers don't have.
+        let task_source = self.port_message_queue();
+        let canceller = self.task_canceller(TaskSourceName::PortMessage);
+        ROUTER.add_route(receiver.to_opaque(), Box::new(move |message| {
+            let this = this.clone();
+            let msg: BroadcastMsg = message.to().unwrap();
+            let _ = task_source.queue_with_canceller(
+                task!(broadcast_message_event: move || {
+                    this.root().broadcast_message_event(msg, None);
+                }),
+                &canceller,
+            );
+        }));
+        let origin = self.origin().immutable().clone();
+        let msg = ScriptMsg::NewBroadcastChannelRouter(router_id, chan.clone(), origin);
+        let _ = self.script_to_constellation_chan().send(msg);
+        *self.broadcast_router.borrow_mut() = Some((router_id, chan));
+    }
+
+    /// Delivers a message posted to the given channel to the other channels with
+    /// the same name, in this global and in every same-origin global.
+    ///
+    /// <https://html.spec.whatwg.org/multipage/#dom-broadcastchannel-postmessage>
+    pub fn schedule_broadcast(&self, msg: BroadcastMsg, source: &BroadcastChannel) {
+        let this = Trusted::new(self);
+        let source = Trusted::new(source);
+        let local_msg = msg.clone();
+        let _ = self.port_message_queue().queue(
+            task!(broadcast_message_event: move || {
+                this.root().broadcast_message_event(local_msg, Some(&source.root()));
+            }),
+            self,
+        );
+
+        let router_id = self.broadcast_router.borrow().as_ref().map(|&(router_id, _)| router_id);
+        if let Some(router_id) = router_id {
+            let _ = self.script_to_constellation_chan().send(ScriptMsg::ScheduleBroadcast(router_id, msg));
+        }
+    }
+
+    /// Fires a message event at the open channels of this global that the given
+    /// broadcast was posted to, except the one it was posted on.
+    ///
+    /// <https://html.spec.whatwg.org/multipage/#dom-broadcastchannel-postmessage> steps 6-10.
+    fn broadcast_message_event(&self, msg: BroadcastMsg, source: Option<&BroadcastChannel>) {
+        if let Some(window) = self.downcast::<Window>() {
+            if !window.Document().is_fully_active() {
+                return;
+            }
+        }
+        let channels: Vec<_> = self.broadcast_channels.borrow().iter()
+            .map(|channel| DomRoot::from_ref(&**channel))
+            .collect();
+        for channel in channels {
+            let is_source = source.map_or(false, |source| {
+                &*channel as *const BroadcastChannel == source as *const BroadcastChannel
+            });
+            // A channel can get closed by the listeners of the previous ones.
+            if is_source || channel.closed() || channel.name() != msg.channel_name {
+                continue;
+            }
+            channel.dispatch_message(&msg);
+        }
+    }
+
     /// Returns the global scope of the realm that the given DOM object's reflector
     /// was created in.
     #[allow(unsafe_code)]
@@ -237,6 +472,22 @@ impl GlobalScope {
         self.crypto.or_init(|| Crypto::new(self))
     }
 
+    pub fn indexed_db(&self) -> DomRoot<IDBFactory> {
+        self.indexed_db.or_init(|| IDBFactory::new(self))
+    }
+
+    pub fn caches(&self) -> DomRoot<CacheStorage> {
+        self.caches.or_init(|| CacheStorage::new(self))
+    }
+
+    pub fn module_map_entry(&self, url: &ServoUrl) -> Option<Rc<ModuleTree>> {
+        self.module_map.borrow().get(url).cloned()
+    }
+
+    pub fn set_module_map_entry(&self, url: ServoUrl, module: Rc<ModuleTree>) {
+        self.module_map.borrow_mut().insert(url, module);
+    }
+
     /// Get next worker id.
     pub fn get_next_worker_id(&self) -> WorkerId {
         let worker_id = self.next_worker_id.get();
@@ -341,6 +592,87 @@ impl GlobalScope {
         unreachable!();
     }
 
+    /// Get the [CSP list](https://w3c.github.io/webappsec-csp/#global-object-csp-list)
+    /// for this global scope.
+    pub fn get_csp_list(&self) -> Option<CspList> {
+        if let Some(window) = self.downcast::<Window>() {
+            return window.Document().get_csp_list();
+        }
+        if let Some(worker) = self.downcast::<WorkerGlobalScope>() {
+            return worker.get_csp_list();
+        }
+        // TODO: Worklet global scopes have their own CSP list.
+        None
+    }
+
+    /// Queues the firing of a `securitypolicyviolation` event for each of `violations`, at
+    /// `element` if it is connected and at the document otherwise.
+    /// <https://w3c.github.io/webappsec-csp/#report-violation>
+    pub fn report_csp_violations(&self, violations: Vec<Violation>, element: Option<&Element>) {
+        for violation in &violations {
+            warn!("Content Security Policy violation of {}: {:?}", violation.directive, violation.resource);
+        }
+        let window = match self.downcast::<Window>() {
+            Some(window) => window,
+            // TODO: Fire the events at worker and worklet global scopes too.
+            None => return,
+        };
+        let document_uri = self.get_url();
+        for violation in violations {
+            // Step 3.
+            let target = match element {
+                Some(element) if element.upcast::<Node>().is_in_doc() => {
+                    Trusted::new(element.upcast::<EventTarget>())
+                },
+                _ => Trusted::new(window.Document().upcast::<EventTarget>()),
+            };
+            let document_uri = document_uri.clone();
+            // Step 4.
+            let _ = window.dom_manipulation_task_source().queue(
+                task!(fire_security_policy_violation_event: move || {
+                    let target = target.root();
+                    let blocked_uri = match violation.resource {
+                        ViolationResource::Inline => "inline".to_owned(),
+                        ViolationResource::Eval => "eval".to_owned(),
+                        ViolationResource::Url(ref url) => strip_url_for_csp_report(url),
+                    };
+                    let disposition = match violation.disposition {
+                        PolicyDisposition::Enforce => SecurityPolicyViolationEventDisposition::Enforce,
+                        PolicyDisposition::Report => SecurityPolicyViolationEventDisposition::Report,
+                    };
+                    let init = SecurityPolicyViolationEventInit {
+                        parent: EventInit {
+                            bubbles: true,
+                            cancelable: false,
+                            composed: true,
+                        },
+                        documentURI: USVString(strip_url_for_csp_report(&document_uri)),
+                        referrer: USVString(String::new()),
+                        blockedURI: USVString(blocked_uri),
+                        violatedDirective: DOMString::from(violation.directive.clone()),
+                        effectiveDirective: DOMString::from(violation.directive),
+                        originalPolicy: DOMString::from(violation.policy),
+                        sourceFile: USVString(String::new()),
+                        sample: DOMString::from(violation.sample),
+                        disposition: disposition,
+                        statusCode: 0,
+                        lineNumber: 0,
+                        columnNumber: 0,
+                    };
+                    let event = SecurityPolicyViolationEvent::new(
+                        &target.global(),
+                        atom!("securitypolicyviolation"),
+                        EventBubbles::Bubbles,
+                        EventCancelable::NotCancelable,
+                        &init,
+                    );
+                    event.upcast::<Event>().fire(&target);
+                }),
+                self,
+            );
+        }
+    }
+
     /// Extract a `Window`, panic if the global object is not a `Window`.
     pub fn as_window(&self) -> &Window {
         self.downcast::<Window>().expect("expected a Window scope")
@@ -431,6 +763,30 @@ impl GlobalScope {
         unreachable!();
     }
 
+    /// `ScriptChan` to send messages to the database access task source of
+    /// this global scope.
+    pub fn database_access_task_source(&self) -> DatabaseAccessTaskSource {
+        if let Some(window) = self.downcast::<Window>() {
+            return window.database_access_task_source();
+        }
+        if let Some(worker) = self.downcast::<WorkerGlobalScope>() {
+            return worker.database_access_task_source();
+        }
+        unreachable!();
+    }
+
+    /// `ScriptChan` to send messages to the port message queue of
+    /// this global scope.
+    pub fn port_message_queue(&self) -> PortMessageQueueTaskSource {
+        if let Some(window) = self.downcast::<Window>() {
+            return window.port_message_queue();
+        }
+        if let Some(worker) = self.downcast::<WorkerGlobalScope>() {
+            return worker.port_message_queue();
+        }
+        unreachable!();
+    }
+
     /// `ScriptChan` to send messages to the websocket task source of
     /// this global scope.
     pub fn websocket_task_source(&self) -> WebsocketTaskSource {
@@ -671,6 +1027,20 @@ impl GlobalScope {
 
 }
 
+/// <https://w3c.github.io/webappsec-csp/#strip-url-for-use-in-reports>
+fn strip_url_for_csp_report(url: &ServoUrl) -> String {
+    // Step 1.
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return url.scheme().to_owned();
+    }
+    // Steps 2-4.
+    let mut url = url.as_url().clone();
+    url.set_fragment(None);
+    let _ = url.set_username("");
+    let _ = url.set_password(None);
+    url.into_string()
+}
+
 fn timestamp_in_ms(time: Timespec) -> u64 {
     (time.sec * 1000 + (time.nsec / 1000000) as i64) as u64
 }
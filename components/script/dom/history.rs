@@ -68,6 +68,7 @@ impl History {
         if !self.window.Document().is_fully_active() {
             return Err(Error::Security);
         }
+        self.window.Document().flush_session_state_report();
         let msg = ScriptMsg::TraverseHistory(direction);
         let _ = self.window.upcast::<GlobalScope>().script_to_constellation_chan().send(msg);
         Ok(())
@@ -93,6 +94,39 @@ impl History {
         // Step 11
         let state_changed = state_id != self.state_id.get();
         self.state_id.set(state_id);
+        self.load_state(state_id);
+
+        // TODO: Queue events on DOM Manipulation task source if non-blocking flag is set.
+        // Step 16.1
+        if state_changed {
+            PopStateEvent::dispatch_jsval(
+                self.window.upcast::<EventTarget>(),
+                &*self.window,
+                unsafe { HandleValue::from_raw(self.state.handle()) }
+            );
+        }
+
+        // Step 16.3
+        if hash_changed {
+            let event = HashChangeEvent::new(
+                &self.window,
+                atom!("hashchange"),
+                false,
+                false,
+                old_url.into_string(),
+                url.into_string());
+            event.upcast::<Event>().fire(self.window.upcast::<EventTarget>());
+        }
+    }
+
+    /// Activate the history state of a restored session history entry when its document is
+    /// created, without firing any events.
+    pub fn restore_state(&self, state_id: HistoryStateId) {
+        self.state_id.set(Some(state_id));
+        self.load_state(Some(state_id));
+    }
+
+    fn load_state(&self, state_id: Option<HistoryStateId>) {
         let serialized_data = match state_id {
             Some(state_id) => {
                 let (tx, rx) = ipc::channel(self.global().time_profiler_chan().clone()).unwrap();
@@ -116,28 +150,6 @@ impl History {
                 self.state.set(NullValue());
             }
         }
-
-        // TODO: Queue events on DOM Manipulation task source if non-blocking flag is set.
-        // Step 16.1
-        if state_changed {
-            PopStateEvent::dispatch_jsval(
-                self.window.upcast::<EventTarget>(),
-                &*self.window,
-                unsafe { HandleValue::from_raw(self.state.handle()) }
-            );
-        }
-
-        // Step 16.3
-        if hash_changed {
-            let event = HashChangeEvent::new(
-                &self.window,
-                atom!("hashchange"),
-                false,
-                false,
-                old_url.into_string(),
-                url.into_string());
-            event.upcast::<Event>().fire(self.window.upcast::<EventTarget>());
-        }
     }
 
     pub fn remove_states(&self, states: Vec<HistoryStateId>) {
@@ -20,8 +20,8 @@ use dom::htmlformelement::{FormSubmitter, ResetFrom, SubmittedFrom};
 use dom::htmlformelement::HTMLFormElement;
 use dom::node::{Node, UnbindContext, document_from_node, window_from_node};
 use dom::nodelist::NodeList;
-use dom::validation::Validatable;
-use dom::validitystate::{ValidityState, ValidationFlags};
+use dom::validation::{Validatable, is_barred_by_datalist_ancestor};
+use dom::validitystate::ValidityState;
 use dom::virtualmethods::VirtualMethods;
 use dom_struct::dom_struct;
 use html5ever::{LocalName, Prefix};
@@ -43,6 +43,7 @@ pub struct HTMLButtonElement {
     htmlelement: HTMLElement,
     button_type: Cell<ButtonType>,
     form_owner: MutNullableDom<HTMLFormElement>,
+    validity_state: MutNullableDom<ValidityState>,
 }
 
 impl HTMLButtonElement {
@@ -55,6 +56,7 @@ impl HTMLButtonElement {
                                                       local_name, prefix, document),
             button_type: Cell::new(ButtonType::Submit),
             form_owner: Default::default(),
+            validity_state: Default::default(),
         }
     }
 
@@ -69,10 +71,34 @@ impl HTMLButtonElement {
 }
 
 impl HTMLButtonElementMethods for HTMLButtonElement {
+    // https://html.spec.whatwg.org/multipage/#dom-cva-willvalidate
+    fn WillValidate(&self) -> bool {
+        self.is_instance_validatable()
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-cva-validity
     fn Validity(&self) -> DomRoot<ValidityState> {
-        let window = window_from_node(self);
-        ValidityState::new(&window, self.upcast())
+        self.validity_state()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-cva-validationmessage
+    fn ValidationMessage(&self) -> DOMString {
+        self.validation_message()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-cva-checkvalidity
+    fn CheckValidity(&self) -> bool {
+        self.check_validity()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-cva-reportvalidity
+    fn ReportValidity(&self) -> bool {
+        self.report_validity()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-cva-setcustomvalidity
+    fn SetCustomValidity(&self, error: DOMString) {
+        self.set_custom_validity(error);
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-fe-disabled
@@ -144,6 +170,11 @@ impl HTMLButtonElementMethods for HTMLButtonElement {
 }
 
 impl HTMLButtonElement {
+    /// <https://html.spec.whatwg.org/multipage/#concept-submit-button>
+    pub fn is_submit_button(&self) -> bool {
+        self.button_type.get() == ButtonType::Submit
+    }
+
     /// <https://html.spec.whatwg.org/multipage/#constructing-the-form-data-set>
     /// Steps range from 3.1 to 3.7 (specific to HTMLButtonElement)
     pub fn form_datum(&self, submitter: Option<FormSubmitter>) -> Option<FormDatum> {
@@ -214,12 +245,24 @@ impl VirtualMethods for HTMLButtonElement {
                         self.button_type.set(ButtonType::Submit);
                     }
                 }
+                // Only submit buttons can be the default button of their form.
+                self.upcast::<Element>().set_state(ElementState::IN_DEFAULT_STATE, false);
+                if let Some(form) = self.form_owner() {
+                    form.update_default_button_state();
+                }
             },
             &local_name!("form") => {
                 self.form_attribute_mutated(mutation);
             }
             _ => {},
         }
+
+        match attr.local_name() {
+            &local_name!("disabled") | &local_name!("type") | &local_name!("form") => {
+                self.update_validity_state();
+            },
+            _ => {},
+        }
     }
 
     fn bind_to_tree(&self, tree_in_doc: bool) {
@@ -228,6 +271,7 @@ impl VirtualMethods for HTMLButtonElement {
         }
 
         self.upcast::<Element>().check_ancestors_disabled_state_for_form_control();
+        self.update_validity_state();
     }
 
     fn unbind_from_tree(&self, context: &UnbindContext) {
@@ -240,6 +284,7 @@ impl VirtualMethods for HTMLButtonElement {
         } else {
             el.check_disabled_attribute();
         }
+        self.update_validity_state();
     }
 }
 
@@ -258,13 +303,22 @@ impl FormControl for HTMLButtonElement {
 }
 
 impl Validatable for HTMLButtonElement {
-    fn is_instance_validatable(&self) -> bool {
-        true
+    fn as_element(&self) -> &Element {
+        self.upcast()
+    }
+
+    fn validity_state(&self) -> DomRoot<ValidityState> {
+        let window = window_from_node(self);
+        self.validity_state.or_init(|| ValidityState::new(&window, self.upcast()))
     }
-    fn validate(&self, validate_flags: ValidationFlags) -> bool {
-        if validate_flags.is_empty() {}
-        // Need more flag check for different validation types later
-        true
+
+    // https://html.spec.whatwg.org/multipage/#candidate-for-constraint-validation
+    fn is_instance_validatable(&self) -> bool {
+        // https://html.spec.whatwg.org/multipage/#the-button-element:barred-from-constraint-validation
+        // https://html.spec.whatwg.org/multipage/#enabling-and-disabling-form-controls:-the-disabled-attribute:barred-from-constraint-validation
+        let element = self.upcast::<Element>();
+        self.button_type.get() == ButtonType::Submit &&
+        !element.disabled_state() && !is_barred_by_datalist_ancestor(element)
     }
 }
 
@@ -321,7 +375,7 @@ impl Activatable for HTMLButtonElement {
         node.query_selector_iter(DOMString::from("button[type=submit]")).unwrap()
             .filter_map(DomRoot::downcast::<HTMLButtonElement>)
             .find(|r| r.form_owner() == owner)
-            .map(|s| synthetic_click_activation(s.as_element(),
+            .map(|s| synthetic_click_activation(s.upcast(),
                                                 ctrl_key,
                                                 shift_key,
                                                 alt_key,
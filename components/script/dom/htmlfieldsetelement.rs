@@ -141,12 +141,18 @@ impl VirtualMethods for HTMLFieldSetElement {
                         let el = field.downcast::<Element>().unwrap();
                         el.set_disabled_state(true);
                         el.set_enabled_state(false);
+                        if let Some(validatable) = el.as_maybe_validatable() {
+                            validatable.update_validity_state();
+                        }
                     }
                 } else {
                     for field in fields {
                         let el = field.downcast::<Element>().unwrap();
                         el.check_disabled_attribute();
                         el.check_ancestors_disabled_state_for_form_control();
+                        if let Some(validatable) = el.as_maybe_validatable() {
+                            validatable.update_validity_state();
+                        }
                     }
                 }
             },
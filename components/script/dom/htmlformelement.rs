@@ -39,7 +39,7 @@ use dom::htmlselectelement::HTMLSelectElement;
 use dom::htmltextareaelement::HTMLTextAreaElement;
 use dom::node::{Node, NodeFlags, UnbindContext, VecPreOrderInsertionHelper};
 use dom::node::{document_from_node, window_from_node};
-use dom::validitystate::ValidationFlags;
+use dom::validation::report_problem;
 use dom::virtualmethods::VirtualMethods;
 use dom::window::Window;
 use dom_struct::dom_struct;
@@ -53,6 +53,7 @@ use servo_rand::random;
 use std::borrow::ToOwned;
 use std::cell::Cell;
 use style::attr::AttrValue;
+use style::element_state::ElementState;
 use style::str::split_html_space_chars;
 use task_source::TaskSource;
 use url::UrlQuery;
@@ -165,6 +166,16 @@ impl HTMLFormElementMethods for HTMLFormElement {
         self.reset(ResetFrom::FromForm);
     }
 
+    // https://html.spec.whatwg.org/multipage/#dom-form-checkvalidity
+    fn CheckValidity(&self) -> bool {
+        self.static_validation().is_ok()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-form-reportvalidity
+    fn ReportValidity(&self) -> bool {
+        self.interactive_validation().is_ok()
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-form-elements
     fn Elements(&self) -> DomRoot<HTMLFormControlsCollection> {
         #[derive(JSTraceable, MallocSizeOf)]
@@ -303,8 +314,6 @@ impl HTMLFormElement {
            !submitter.no_validate(self)
         {
             if self.interactive_validation().is_err() {
-                // TODO: Implement event handlers on all form control elements
-                self.upcast::<EventTarget>().fire_event(atom!("invalid"));
                 return;
             }
         }
@@ -479,13 +488,20 @@ impl HTMLFormElement {
     /// Interactively validate the constraints of form elements
     /// <https://html.spec.whatwg.org/multipage/#interactively-validate-the-constraints>
     fn interactive_validation(&self) -> Result<(), ()> {
-        // Step 1-3
-        let _unhandled_invalid_controls = match self.static_validation() {
+        // Step 1-2
+        let unhandled_invalid_controls = match self.static_validation() {
             Ok(()) => return Ok(()),
             Err(err) => err
         };
-        // TODO: Report the problems with the constraints of at least one of
-        //       the elements given in unhandled invalid controls to the user
+        // Step 3
+        // The `invalid` events were all fired by static validation, so the problems are
+        // reported once every handler ran. Only the first control is focused.
+        let validatables = unhandled_invalid_controls.iter().filter_map(|control| {
+            control.as_element().as_maybe_validatable()
+        });
+        for (index, validatable) in validatables.enumerate() {
+            report_problem(validatable, index == 0);
+        }
         // Step 4
         Err(())
     }
@@ -493,29 +509,16 @@ impl HTMLFormElement {
     /// Statitically validate the constraints of form elements
     /// <https://html.spec.whatwg.org/multipage/#statically-validate-the-constraints>
     fn static_validation(&self) -> Result<(), Vec<FormSubmittableElement>> {
-        let node = self.upcast::<Node>();
-        // FIXME(#3553): This is an incorrect way of getting controls owned by the
-        //               form, refactor this when html5ever's form owner PR lands
         // Step 1-3
-        let invalid_controls = node.traverse_preorder().filter_map(|field| {
-            if let Some(el) = field.downcast::<Element>() {
-                if el.disabled_state() {
-                    None
-                } else {
-                    let validatable = match el.as_maybe_validatable() {
-                        Some(v) => v,
-                        None => return None
-                    };
-                    if !validatable.is_instance_validatable() {
-                        None
-                    } else if validatable.validate(ValidationFlags::empty()) {
-                        None
-                    } else {
-                        Some(FormSubmittableElement::from_element(&el))
-                    }
-                }
-            } else {
+        let invalid_controls = self.controls.borrow().iter().filter_map(|field| {
+            let validatable = match field.as_maybe_validatable() {
+                Some(v) => v,
+                None => return None
+            };
+            if !validatable.is_instance_validatable() || validatable.satisfies_constraints() {
                 None
+            } else {
+                Some(FormSubmittableElement::from_element(&field))
             }
         }).collect::<Vec<FormSubmittableElement>>();
         // Step 4
@@ -687,18 +690,52 @@ impl HTMLFormElement {
         let root = self.upcast::<Element>().root_element();
         let root = root.r().upcast::<Node>();
 
-        let mut controls = self.controls.borrow_mut();
-        controls.insert_pre_order(control.to_element(), root);
+        self.controls.borrow_mut().insert_pre_order(control.to_element(), root);
+        self.update_default_button_state();
     }
 
     fn remove_control<T: ?Sized + FormControl>(&self, control: &T) {
         let control = control.to_element();
-        let mut controls = self.controls.borrow_mut();
-        controls.iter().position(|c| c.r() == control)
-                       .map(|idx| controls.remove(idx));
+        {
+            let mut controls = self.controls.borrow_mut();
+            controls.iter().position(|c| c.r() == control)
+                           .map(|idx| controls.remove(idx));
+        }
+        if is_submit_button(control) {
+            control.set_state(ElementState::IN_DEFAULT_STATE, false);
+        }
+        self.update_default_button_state();
+    }
+
+    /// The radio buttons that this form owns, in tree order.
+    pub fn radio_buttons(&self) -> Vec<DomRoot<HTMLInputElement>> {
+        self.controls.borrow().iter()
+            .filter_map(|control| control.downcast::<HTMLInputElement>())
+            .filter(|input| input.input_type() == InputType::Radio)
+            .map(DomRoot::from_ref)
+            .collect()
+    }
+
+    /// Makes the default button of this form match `:default`, and its other submit buttons not.
+    /// <https://html.spec.whatwg.org/multipage/#default-button>
+    pub fn update_default_button_state(&self) {
+        let controls = self.controls.borrow();
+        let mut found_default_button = false;
+        for control in controls.iter().filter(|control| is_submit_button(control)) {
+            control.set_state(ElementState::IN_DEFAULT_STATE, !found_default_button);
+            found_default_button = true;
+        }
     }
 }
 
+/// <https://html.spec.whatwg.org/multipage/#concept-submit-button>
+fn is_submit_button(element: &Element) -> bool {
+    if let Some(input) = element.downcast::<HTMLInputElement>() {
+        return input.is_submit_button();
+    }
+    element.downcast::<HTMLButtonElement>().map_or(false, |button| button.is_submit_button())
+}
+
 #[derive(Clone, JSTraceable, MallocSizeOf)]
 pub enum FormDatumValue {
     #[allow(dead_code)]
@@ -753,6 +790,16 @@ pub enum FormSubmittableElement {
 }
 
 impl FormSubmittableElement {
+    fn as_element(&self) -> &Element {
+        match *self {
+            FormSubmittableElement::ButtonElement(ref button) => button.upcast(),
+            FormSubmittableElement::InputElement(ref input) => input.upcast(),
+            FormSubmittableElement::ObjectElement(ref object) => object.upcast(),
+            FormSubmittableElement::SelectElement(ref select) => select.upcast(),
+            FormSubmittableElement::TextAreaElement(ref textarea) => textarea.upcast()
+        }
+    }
+
     fn as_event_target(&self) -> &EventTarget {
         match *self {
             FormSubmittableElement::ButtonElement(ref button) => button.upcast(),
@@ -1050,10 +1097,6 @@ pub trait FormControl: DomObject {
             self.form_owner().map_or(false, |t| owner(&t))
         }
     }
-
-    // XXXKiChjang: Implement these on inheritors
-    // fn candidate_for_validation(&self) -> bool;
-    // fn satisfies_constraints(&self) -> bool;
 }
 
 impl VirtualMethods for HTMLFormElement {
@@ -27,6 +27,7 @@ use dom_struct::dom_struct;
 use html5ever::{LocalName, Prefix};
 use ipc_channel::ipc;
 use msg::constellation_msg::{BrowsingContextId, PipelineId, TopLevelBrowsingContextId};
+use net_traits::csp::CheckResult;
 use profile_traits::ipc as ProfiledIpc;
 use script_layout_interface::message::ReflowGoal;
 use script_thread::ScriptThread;
@@ -238,13 +239,27 @@ impl HTMLIFrameElement {
 
         // TODO: check ancestor browsing contexts for same URL
 
+        // https://w3c.github.io/webappsec-csp/#should-block-navigation-request
+        let document = document_from_node(self);
+        if url.as_str() != "about:blank" {
+            if let Some(csp_list) = document.get_csp_list() {
+                let origin = document.origin().immutable();
+                let (result, violations) = csp_list.should_nested_navigation_be_blocked(&url, origin);
+                if !violations.is_empty() {
+                    window.upcast::<GlobalScope>().report_csp_violations(violations, Some(self.upcast()));
+                }
+                if result == CheckResult::Blocked {
+                    return;
+                }
+            }
+        }
+
         let creator_pipeline_id = if url.as_str() == "about:blank" {
             Some(window.upcast::<GlobalScope>().pipeline_id())
         } else {
             None
         };
 
-        let document = document_from_node(self);
         let load_data = LoadData::new(url, creator_pipeline_id, document.get_referrer_policy(), Some(document.url()));
 
         let pipeline_id = self.pipeline_id();
@@ -46,11 +46,12 @@ use ipc_channel::router::ROUTER;
 use microtask::{Microtask, MicrotaskRunnable};
 use mime::{Mime, TopLevel, SubLevel};
 use net_traits::{FetchResponseListener, FetchMetadata, NetworkError, FetchResponseMsg};
+use net_traits::csp::Violation;
 use net_traits::image::base::{Image, ImageMetadata};
 use net_traits::image_cache::{CanRequestImages, ImageCache, ImageOrMetadataAvailable};
 use net_traits::image_cache::{ImageResponder, ImageResponse, ImageState, PendingImageId};
 use net_traits::image_cache::UsePlaceholder;
-use net_traits::request::RequestInit;
+use net_traits::request::{Destination, RequestInit};
 use network_listener::{NetworkListener, PreInvoke};
 use num_traits::ToPrimitive;
 use script_thread::ScriptThread;
@@ -163,6 +164,8 @@ struct ImageContext {
     id: PendingImageId,
     /// Used to mark abort
     aborted: Cell<bool>,
+    /// The document associated with this request
+    doc: Trusted<Document>,
 }
 
 impl FetchResponseListener for ImageContext {
@@ -219,6 +222,11 @@ impl FetchResponseListener for ImageContext {
             self.id,
             FetchResponseMsg::ProcessResponseEOF(response));
     }
+
+    fn process_csp_violations(&mut self, violations: Vec<Violation>) {
+        let global = &self.doc.root().global();
+        global.report_csp_violations(violations, None);
+    }
 }
 
 impl PreInvoke for ImageContext {
@@ -301,6 +309,7 @@ impl HTMLImageElement {
             status: Ok(()),
             id: id,
             aborted: Cell::new(false),
+            doc: Trusted::new(&document),
         }));
 
         let (action_sender, action_receiver) = ipc::channel().unwrap();
@@ -316,7 +325,10 @@ impl HTMLImageElement {
         let request = RequestInit {
             url: img_url.clone(),
             origin: document.origin().immutable().clone(),
+            destination: Destination::Image,
             pipeline_id: Some(document.global().pipeline_id()),
+            csp_list: document.get_csp_list(),
+            client_url: Some(document.url()),
             .. RequestInit::default()
         };
 
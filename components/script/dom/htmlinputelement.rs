@@ -3,6 +3,7 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use caseless::compatibility_caseless_match_str;
+use chrono::{NaiveDate, Weekday};
 use dom::activation::{Activatable, ActivationSource, synthetic_click_activation};
 use dom::attr::Attr;
 use dom::bindings::cell::DomRefCell;
@@ -16,7 +17,9 @@ use dom::bindings::error::{Error, ErrorResult};
 use dom::bindings::inheritance::Castable;
 use dom::bindings::reflector::DomObject;
 use dom::bindings::root::{Dom, DomRoot, LayoutDom, MutNullableDom, RootedReference};
-use dom::bindings::str::DOMString;
+use dom::bindings::str::{DOMString, parse_date_string, parse_floating_point_number};
+use dom::bindings::str::{parse_local_date_and_time_string, parse_month_string};
+use dom::bindings::str::{parse_time_string, parse_week_string};
 use dom::document::Document;
 use dom::element::{AttributeMutation, Element, LayoutElementHelpers, RawLayoutElementHelpers};
 use dom::event::{Event, EventBubbles, EventCancelable};
@@ -34,8 +37,8 @@ use dom::node::{Node, NodeDamage, UnbindContext};
 use dom::node::{document_from_node, window_from_node};
 use dom::nodelist::NodeList;
 use dom::textcontrol::{TextControlElement, TextControlSelection};
-use dom::validation::Validatable;
-use dom::validitystate::ValidationFlags;
+use dom::validation::{Validatable, is_barred_by_datalist_ancestor};
+use dom::validitystate::{ValidationFlags, ValidityState};
 use dom::virtualmethods::VirtualMethods;
 use dom_struct::dom_struct;
 use embedder_traits::FilterPattern;
@@ -46,9 +49,11 @@ use net_traits::{CoreResourceMsg, IpcSend};
 use net_traits::blob_url_store::get_blob_origin;
 use net_traits::filemanager_thread::FileManagerThreadMsg;
 use profile_traits::ipc;
+use regex::Regex;
 use script_layout_interface::rpc::TextIndexResponse;
 use script_traits::ScriptToConstellationChan;
 use servo_atoms::Atom;
+use servo_url::ServoUrl;
 use std::borrow::ToOwned;
 use std::cell::Cell;
 use std::ops::Range;
@@ -95,7 +100,7 @@ impl InputType {
     // Note that Password is not included here since it is handled
     // slightly differently, with placeholder characters shown rather
     // than the underlying value.
-    fn is_textual(&self) -> bool {
+    pub fn is_textual(&self) -> bool {
         match *self {
             InputType::Color | InputType::Date | InputType::DatetimeLocal
             | InputType::Email | InputType::Hidden | InputType::Month
@@ -113,6 +118,42 @@ impl InputType {
         self.is_textual() || *self == InputType::Password
     }
 
+    // https://html.spec.whatwg.org/multipage/#do-not-apply
+    fn has_text_constraints(&self) -> bool {
+        match *self {
+            InputType::Text | InputType::Search | InputType::Url
+            | InputType::Tel | InputType::Email | InputType::Password => true,
+            _ => false,
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/#the-readonly-attribute
+    fn applies_readonly(&self) -> bool {
+        match *self {
+            InputType::Date | InputType::DatetimeLocal | InputType::Month
+            | InputType::Number | InputType::Time | InputType::Week => true,
+            _ => self.has_text_constraints(),
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/#the-required-attribute
+    fn applies_required(&self) -> bool {
+        match *self {
+            InputType::Checkbox | InputType::Radio | InputType::File => true,
+            _ => self.applies_readonly(),
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/#the-min-and-max-attributes
+    fn applies_range(&self) -> bool {
+        match *self {
+            InputType::Date | InputType::DatetimeLocal | InputType::Month
+            | InputType::Number | InputType::Range | InputType::Time
+            | InputType::Week => true,
+            _ => false,
+        }
+    }
+
     fn to_str(&self) -> &str {
         match *self {
             InputType::Button => "button",
@@ -219,8 +260,16 @@ pub struct HTMLInputElement {
     // https://html.spec.whatwg.org/multipage/#concept-input-value-dirty-flag
     value_dirty: Cell<bool>,
 
+    // https://html.spec.whatwg.org/multipage/#setting-minimum-input-length-requirements:last-changed-by-a-user-edit
+    value_changed_by_user: Cell<bool>,
+
     filelist: MutNullableDom<FileList>,
     form_owner: MutNullableDom<HTMLFormElement>,
+    validity_state: MutNullableDom<ValidityState>,
+    /// The compiled `pattern` attribute, which is only compiled again when the attribute
+    /// changes.
+    #[ignore_malloc_size_of = "Defined in regex"]
+    compiled_pattern: DomRefCell<Option<Regex>>,
 }
 
 #[derive(JSTraceable)]
@@ -260,7 +309,8 @@ impl HTMLInputElement {
         HTMLInputElement {
             htmlelement:
                 HTMLElement::new_inherited_with_state(ElementState::IN_ENABLED_STATE |
-                                                      ElementState::IN_READ_WRITE_STATE,
+                                                      ElementState::IN_READ_WRITE_STATE |
+                                                      ElementState::IN_OPTIONAL_STATE,
                                                       local_name, prefix, document),
             input_type: Cell::new(Default::default()),
             placeholder: DomRefCell::new(DOMString::new()),
@@ -276,8 +326,11 @@ impl HTMLInputElement {
                                                       SelectionDirection::None)),
             activation_state: DomRefCell::new(InputActivationState::new()),
             value_dirty: Cell::new(false),
+            value_changed_by_user: Cell::new(false),
             filelist: MutNullableDom::new(None),
             form_owner: Default::default(),
+            validity_state: Default::default(),
+            compiled_pattern: DomRefCell::new(None),
         }
     }
 
@@ -319,6 +372,29 @@ impl HTMLInputElement {
     pub fn input_type(&self) -> InputType {
         self.input_type.get()
     }
+
+    /// <https://html.spec.whatwg.org/multipage/#concept-submit-button>
+    pub fn is_submit_button(&self) -> bool {
+        match self.input_type() {
+            InputType::Submit | InputType::Image => true,
+            _ => false,
+        }
+    }
+
+    /// Makes this element match `:default` if it is checked by default, or the default
+    /// button of its form.
+    /// <https://html.spec.whatwg.org/multipage/#selector-default>
+    fn update_default_state(&self) {
+        let el = self.upcast::<Element>();
+        let checked_by_default = match self.input_type() {
+            InputType::Checkbox | InputType::Radio => el.has_attribute(&local_name!("checked")),
+            _ => false,
+        };
+        el.set_state(ElementState::IN_DEFAULT_STATE, checked_by_default);
+        if let Some(form) = self.form_owner() {
+            form.update_default_button_state();
+        }
+    }
 }
 
 pub trait LayoutHTMLInputElementHelpers {
@@ -577,6 +653,7 @@ impl HTMLInputElementMethods for HTMLInputElement {
             ValueMode::Value => {
                 // Step 3.
                 self.value_dirty.set(true);
+                self.value_changed_by_user.set(false);
 
                 // Step 4.
                  self.sanitize_value(&mut value);
@@ -607,6 +684,7 @@ impl HTMLInputElementMethods for HTMLInputElement {
             }
         }
 
+        self.update_validity_state();
         self.upcast::<Node>().dirty(NodeDamage::OtherNodeDamage);
         Ok(())
     }
@@ -787,6 +865,36 @@ impl HTMLInputElementMethods for HTMLInputElement {
         self.selection().set_dom_range_text(replacement, Some(start), Some(end), selection_mode)
     }
 
+    // https://html.spec.whatwg.org/multipage/#dom-cva-willvalidate
+    fn WillValidate(&self) -> bool {
+        self.is_instance_validatable()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-cva-validity
+    fn Validity(&self) -> DomRoot<ValidityState> {
+        self.validity_state()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-cva-validationmessage
+    fn ValidationMessage(&self) -> DOMString {
+        self.validation_message()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-cva-checkvalidity
+    fn CheckValidity(&self) -> bool {
+        self.check_validity()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-cva-reportvalidity
+    fn ReportValidity(&self) -> bool {
+        self.report_validity()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-cva-setcustomvalidity
+    fn SetCustomValidity(&self, error: DOMString) {
+        self.set_custom_validity(error);
+    }
+
     // Select the files based on filepaths passed in,
     // enabled by dom.htmlinputelement.select_files.enabled,
     // used for test purpose.
@@ -830,6 +938,14 @@ fn broadcast_radio_checked(broadcaster: &HTMLInputElement, group: Option<&Atom>)
     do_broadcast(doc.upcast(), broadcaster, owner.r(), group)
 }
 
+/// Compiles the value of a `pattern` attribute, anchored to match the whole value.
+/// <https://html.spec.whatwg.org/multipage/#compiled-pattern-regular-expression>
+fn compile_pattern(pattern: &str) -> Option<Regex> {
+    // FIXME: The pattern should be compiled with the JavaScript regular expression
+    // syntax, with the `u` flag set.
+    Regex::new(&format!("^(?:{})$", pattern)).ok()
+}
+
 // https://html.spec.whatwg.org/multipage/#radio-button-group
 fn in_same_group(other: &HTMLInputElement, owner: Option<&HTMLFormElement>,
                  group: Option<&Atom>) -> bool {
@@ -942,6 +1058,7 @@ impl HTMLInputElement {
                                     self.radio_group_name().as_ref());
         }
 
+        self.constraints_changed();
         self.upcast::<Node>().dirty(NodeDamage::OtherNodeDamage);
         //TODO: dispatch change event
     }
@@ -965,6 +1082,8 @@ impl HTMLInputElement {
         }
         self.textinput.borrow_mut().set_content(self.DefaultValue());
         self.value_dirty.set(false);
+        self.value_changed_by_user.set(false);
+        self.update_validity_state();
         self.upcast::<Node>().dirty(NodeDamage::OtherNodeDamage);
     }
 
@@ -1039,6 +1158,7 @@ impl HTMLInputElement {
         } else {
             let filelist = FileList::new(&window, files);
             self.filelist.set(Some(&filelist));
+            self.update_validity_state();
 
             target.fire_bubbling_event(atom!("input"));
             target.fire_bubbling_event(atom!("change"));
@@ -1109,6 +1229,183 @@ impl HTMLInputElement {
         }
     }
 
+    /// Updates the validity of this element, and of the rest of its radio button group
+    /// if it is a radio button, since they share their `required` constraint.
+    fn constraints_changed(&self) {
+        if self.input_type() == InputType::Radio {
+            for radio in self.radio_group_members() {
+                radio.update_validity_state();
+            }
+        } else {
+            self.update_validity_state();
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/#radio-button-group
+    fn radio_group_members(&self) -> Vec<DomRoot<HTMLInputElement>> {
+        let group = self.radio_group_name();
+        let mut members = match group {
+            None | Some(atom!("")) => vec![],
+            _ => {
+                let owner = self.form_owner();
+                // The members of the group of a radio button with a form owner are among the
+                // controls of the form, which saves looking through the whole document.
+                let candidates = match owner {
+                    Some(ref form) => form.radio_buttons(),
+                    None => {
+                        document_from_node(self).upcast::<Node>()
+                            .query_selector_iter(DOMString::from("input[type=radio]")).unwrap()
+                            .filter_map(DomRoot::downcast::<HTMLInputElement>)
+                            .collect()
+                    },
+                };
+                candidates.into_iter()
+                    .filter(|r| in_same_group(&r, owner.r(), group.as_ref()) && self != &**r)
+                    .collect()
+            },
+        };
+        members.push(DomRoot::from_ref(self));
+        members
+    }
+
+    // https://html.spec.whatwg.org/multipage/#suffering-from-being-missing
+    fn suffers_from_being_missing(&self) -> bool {
+        let ty = self.input_type();
+        if !ty.applies_required() || !self.is_mutable() {
+            return false;
+        }
+        match ty {
+            // https://html.spec.whatwg.org/multipage/#checkbox-state-(type=checkbox):suffering-from-being-missing
+            InputType::Checkbox => self.Required() && !self.Checked(),
+            // https://html.spec.whatwg.org/multipage/#radio-button-state-(type=radio):suffering-from-being-missing
+            InputType::Radio => {
+                let members = self.radio_group_members();
+                members.iter().any(|radio| radio.Required()) && !members.iter().any(|radio| radio.Checked())
+            },
+            // https://html.spec.whatwg.org/multipage/#file-upload-state-(type=file):suffering-from-being-missing
+            InputType::File => {
+                self.Required() && self.filelist.get().map_or(true, |files| files.Length() == 0)
+            },
+            // https://html.spec.whatwg.org/multipage/#the-required-attribute:suffering-from-being-missing
+            _ => self.Required() && self.Value().is_empty(),
+        }
+    }
+
+    fn compiled_pattern(&self) -> Option<Regex> {
+        self.compiled_pattern.borrow().clone()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#concept-input-value-string-number
+    fn convert_string_to_number(&self, value: &str) -> Option<f64> {
+        fn milliseconds_since_epoch(date: NaiveDate) -> f64 {
+            date.signed_duration_since(NaiveDate::from_ymd(1970, 1, 1)).num_milliseconds() as f64
+        }
+        fn milliseconds_since_midnight((hour, minute, second): (u32, u32, f32)) -> f64 {
+            (hour * 3600000 + minute * 60000) as f64 + (second * 1000.) as f64
+        }
+
+        match self.input_type() {
+            // https://html.spec.whatwg.org/multipage/#date-state-(type=date):concept-input-value-string-number
+            InputType::Date => {
+                let (year, month, day) = parse_date_string(value).ok()?;
+                Some(milliseconds_since_epoch(NaiveDate::from_ymd_opt(year as i32, month, day)?))
+            },
+            // https://html.spec.whatwg.org/multipage/#month-state-(type=month):concept-input-value-string-number
+            InputType::Month => {
+                let (year, month) = parse_month_string(value).ok()?;
+                Some((year as f64 - 1970.) * 12. + (month as f64 - 1.))
+            },
+            // https://html.spec.whatwg.org/multipage/#week-state-(type=week):concept-input-value-string-number
+            InputType::Week => {
+                let (year, week) = parse_week_string(value).ok()?;
+                Some(milliseconds_since_epoch(NaiveDate::from_isoywd_opt(year as i32, week, Weekday::Mon)?))
+            },
+            // https://html.spec.whatwg.org/multipage/#time-state-(type=time):concept-input-value-string-number
+            InputType::Time => {
+                parse_time_string(value).ok().map(milliseconds_since_midnight)
+            },
+            // https://html.spec.whatwg.org/multipage/#local-date-and-time-state-(type=datetime-local):concept-input-value-string-number
+            InputType::DatetimeLocal => {
+                let ((year, month, day), time) = parse_local_date_and_time_string(value).ok()?;
+                let date = NaiveDate::from_ymd_opt(year as i32, month, day)?;
+                Some(milliseconds_since_epoch(date) + milliseconds_since_midnight(time))
+            },
+            // https://html.spec.whatwg.org/multipage/#number-state-(type=number):concept-input-value-string-number
+            // https://html.spec.whatwg.org/multipage/#range-state-(type=range):concept-input-value-string-number
+            InputType::Number | InputType::Range => parse_floating_point_number(value).ok(),
+            _ => None,
+        }
+    }
+
+    fn attribute_as_number(&self, name: &LocalName) -> Option<f64> {
+        let attr = self.upcast::<Element>().get_attribute(&ns!(), name)?;
+        let value = attr.value();
+        self.convert_string_to_number(&value)
+    }
+
+    // https://html.spec.whatwg.org/multipage/#concept-input-min
+    fn minimum(&self) -> Option<f64> {
+        self.attribute_as_number(&local_name!("min")).or_else(|| {
+            // https://html.spec.whatwg.org/multipage/#range-state-(type=range):concept-input-min-default
+            if self.input_type() == InputType::Range { Some(0.) } else { None }
+        })
+    }
+
+    // https://html.spec.whatwg.org/multipage/#concept-input-max
+    fn maximum(&self) -> Option<f64> {
+        self.attribute_as_number(&local_name!("max")).or_else(|| {
+            // https://html.spec.whatwg.org/multipage/#range-state-(type=range):concept-input-max-default
+            if self.input_type() == InputType::Range { Some(100.) } else { None }
+        })
+    }
+
+    /// Returns the default step and the step scale factor of the input type.
+    // https://html.spec.whatwg.org/multipage/#concept-input-step-default
+    // https://html.spec.whatwg.org/multipage/#concept-input-step-scale
+    fn step_default_and_scale(&self) -> (f64, f64) {
+        match self.input_type() {
+            InputType::Date => (1., 86400000.),
+            InputType::Week => (1., 604800000.),
+            InputType::Time | InputType::DatetimeLocal => (60., 1000.),
+            _ => (1., 1.),
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/#concept-input-step
+    fn allowed_value_step(&self) -> Option<f64> {
+        let (default_step, scale) = self.step_default_and_scale();
+        let step = match self.upcast::<Element>().get_attribute(&ns!(), &local_name!("step")) {
+            // Step 1.
+            None => default_step,
+            Some(attr) => {
+                let value = attr.value();
+                // Step 2.
+                if value.eq_ignore_ascii_case("any") {
+                    return None;
+                }
+                // Steps 3-4.
+                match parse_floating_point_number(&value) {
+                    Ok(step) if step > 0. => match self.input_type() {
+                        InputType::Date | InputType::Month | InputType::Week => step.round().max(1.),
+                        _ => step,
+                    },
+                    _ => default_step,
+                }
+            },
+        };
+        Some(step * scale)
+    }
+
+    // https://html.spec.whatwg.org/multipage/#concept-input-min-zero
+    fn step_base(&self) -> f64 {
+        self.attribute_as_number(&local_name!("min"))
+            .or_else(|| self.attribute_as_number(&local_name!("value")))
+            .unwrap_or_else(|| {
+                // https://html.spec.whatwg.org/multipage/#week-state-(type=week):concept-input-step-default-base
+                if self.input_type() == InputType::Week { -259200000. } else { 0. }
+            })
+    }
+
     #[allow(unrooted_must_root)]
     fn selection(&self) -> TextControlSelection<Self> {
         TextControlSelection::new(&self, &self.textinput)
@@ -1310,6 +1607,33 @@ impl VirtualMethods for HTMLInputElement {
             &local_name!("form") => {
                 self.form_attribute_mutated(mutation);
             },
+            &local_name!("pattern") => {
+                *self.compiled_pattern.borrow_mut() = match mutation {
+                    AttributeMutation::Set(_) => compile_pattern(&**attr.value()),
+                    AttributeMutation::Removed => None,
+                };
+            },
+            _ => {},
+        }
+
+        // Only the attributes that radio buttons share their constraints through update the
+        // rest of the radio button group.
+        match attr.local_name() {
+            &local_name!("checked") | &local_name!("type") | &local_name!("name") |
+            &local_name!("form") | &local_name!("required") => {
+                self.constraints_changed();
+            },
+            &local_name!("disabled") | &local_name!("value") | &local_name!("maxlength") |
+            &local_name!("minlength") | &local_name!("readonly") | &local_name!("pattern") |
+            &local_name!("min") | &local_name!("max") | &local_name!("step") |
+            &local_name!("multiple") => {
+                self.update_validity_state();
+            },
+            _ => {},
+        }
+
+        match attr.local_name() {
+            &local_name!("checked") | &local_name!("type") => self.update_default_state(),
             _ => {},
         }
     }
@@ -1331,6 +1655,7 @@ impl VirtualMethods for HTMLInputElement {
             s.bind_to_tree(tree_in_doc);
         }
         self.upcast::<Element>().check_ancestors_disabled_state_for_form_control();
+        self.constraints_changed();
     }
 
     fn unbind_from_tree(&self, context: &UnbindContext) {
@@ -1343,6 +1668,7 @@ impl VirtualMethods for HTMLInputElement {
         } else {
             el.check_disabled_attribute();
         }
+        self.constraints_changed();
     }
 
     fn handle_event(&self, event: &Event) {
@@ -1394,7 +1720,9 @@ impl VirtualMethods for HTMLInputElement {
                         },
                         DispatchInput => {
                             self.value_dirty.set(true);
+                            self.value_changed_by_user.set(true);
                             self.update_placeholder_shown_state();
+                            self.update_validity_state();
                             self.upcast::<Node>().dirty(NodeDamage::OtherNodeDamage);
                             event.mark_as_handled();
                         }
@@ -1435,13 +1763,135 @@ impl FormControl for HTMLInputElement {
 }
 
 impl Validatable for HTMLInputElement {
+    fn as_element(&self) -> &Element {
+        self.upcast()
+    }
+
+    fn validity_state(&self) -> DomRoot<ValidityState> {
+        let window = window_from_node(self);
+        self.validity_state.or_init(|| ValidityState::new(&window, self.upcast()))
+    }
+
+    // https://html.spec.whatwg.org/multipage/#candidate-for-constraint-validation
     fn is_instance_validatable(&self) -> bool {
-        // https://html.spec.whatwg.org/multipage/#candidate-for-constraint-validation
-        true
+        match self.input_type() {
+            // https://html.spec.whatwg.org/multipage/#hidden-state-(type=hidden):barred-from-constraint-validation
+            // https://html.spec.whatwg.org/multipage/#reset-button-state-(type=reset):barred-from-constraint-validation
+            // https://html.spec.whatwg.org/multipage/#button-state-(type=button):barred-from-constraint-validation
+            InputType::Hidden | InputType::Reset | InputType::Button => return false,
+            // https://html.spec.whatwg.org/multipage/#the-readonly-attribute:barred-from-constraint-validation
+            ty if ty.applies_readonly() && self.ReadOnly() => return false,
+            _ => {},
+        }
+        // https://html.spec.whatwg.org/multipage/#enabling-and-disabling-form-controls:-the-disabled-attribute:barred-from-constraint-validation
+        let element = self.upcast::<Element>();
+        !element.disabled_state() && !is_barred_by_datalist_ancestor(element)
     }
-    fn validate(&self, _validate_flags: ValidationFlags) -> bool {
-        // call stub methods defined in validityState.rs file here according to the flags set in validate_flags
-        true
+
+    fn required_state(&self) -> Option<bool> {
+        if self.input_type().applies_required() { Some(self.Required()) } else { None }
+    }
+
+    // https://html.spec.whatwg.org/multipage/#have-range-limitations
+    fn has_range_limitations(&self) -> bool {
+        self.input_type().applies_range() && (self.minimum().is_some() || self.maximum().is_some())
+    }
+
+    fn perform_validation(&self, validate_flags: ValidationFlags) -> ValidationFlags {
+        let mut failed_flags = ValidationFlags::empty();
+        let ty = self.input_type();
+        let value = self.Value();
+
+        // https://html.spec.whatwg.org/multipage/#suffering-from-being-missing
+        if validate_flags.contains(ValidationFlags::VALUE_MISSING) && self.suffers_from_being_missing() {
+            failed_flags.insert(ValidationFlags::VALUE_MISSING);
+        }
+
+        // https://html.spec.whatwg.org/multipage/#suffering-from-a-type-mismatch
+        if validate_flags.contains(ValidationFlags::TYPE_MISMATCH) && !value.is_empty() {
+            let type_mismatch = match ty {
+                // https://html.spec.whatwg.org/multipage/#e-mail-state-(type=email):suffering-from-a-type-mismatch
+                InputType::Email if self.Multiple() => {
+                    !value.split(',').all(|address| is_valid_email_address(address.trim()))
+                },
+                InputType::Email => !is_valid_email_address(&value),
+                // https://html.spec.whatwg.org/multipage/#url-state-(type=url):suffering-from-a-type-mismatch
+                InputType::Url => ServoUrl::parse(&value).is_err(),
+                _ => false,
+            };
+            if type_mismatch {
+                failed_flags.insert(ValidationFlags::TYPE_MISMATCH);
+            }
+        }
+
+        // https://html.spec.whatwg.org/multipage/#suffering-from-a-pattern-mismatch
+        if validate_flags.contains(ValidationFlags::PATTERN_MISMATCH) &&
+           ty.has_text_constraints() && !value.is_empty()
+        {
+            if let Some(pattern) = self.compiled_pattern() {
+                let pattern_mismatch = if ty == InputType::Email && self.Multiple() {
+                    !value.split(',').all(|address| pattern.is_match(address.trim()))
+                } else {
+                    !pattern.is_match(&value)
+                };
+                if pattern_mismatch {
+                    failed_flags.insert(ValidationFlags::PATTERN_MISMATCH);
+                }
+            }
+        }
+
+        // https://html.spec.whatwg.org/multipage/#setting-minimum-input-length-requirements:-the-minlength-attribute
+        // https://html.spec.whatwg.org/multipage/#limiting-user-input-length:-the-maxlength-attribute
+        if validate_flags.intersects(ValidationFlags::TOO_LONG | ValidationFlags::TOO_SHORT) &&
+           ty.has_text_constraints() && self.value_dirty.get() && self.value_changed_by_user.get()
+        {
+            let length = value.encode_utf16().count() as i32;
+            let max_length = self.maxlength.get();
+            let min_length = self.minlength.get();
+            if validate_flags.contains(ValidationFlags::TOO_LONG) &&
+               max_length != DEFAULT_MAX_LENGTH && length > max_length
+            {
+                failed_flags.insert(ValidationFlags::TOO_LONG);
+            }
+            if validate_flags.contains(ValidationFlags::TOO_SHORT) &&
+               min_length != DEFAULT_MIN_LENGTH && length > 0 && length < min_length
+            {
+                failed_flags.insert(ValidationFlags::TOO_SHORT);
+            }
+        }
+
+        if validate_flags.intersects(ValidationFlags::RANGE_UNDERFLOW | ValidationFlags::RANGE_OVERFLOW |
+                                     ValidationFlags::STEP_MISMATCH) &&
+           ty.applies_range()
+        {
+            if let Some(number) = self.convert_string_to_number(&value) {
+                // https://html.spec.whatwg.org/multipage/#the-min-and-max-attributes:suffering-from-an-underflow
+                if validate_flags.contains(ValidationFlags::RANGE_UNDERFLOW) &&
+                   self.minimum().map_or(false, |min| number < min)
+                {
+                    failed_flags.insert(ValidationFlags::RANGE_UNDERFLOW);
+                }
+
+                // https://html.spec.whatwg.org/multipage/#the-min-and-max-attributes:suffering-from-an-overflow
+                if validate_flags.contains(ValidationFlags::RANGE_OVERFLOW) &&
+                   self.maximum().map_or(false, |max| number > max)
+                {
+                    failed_flags.insert(ValidationFlags::RANGE_OVERFLOW);
+                }
+
+                // https://html.spec.whatwg.org/multipage/#the-step-attribute:suffering-from-a-step-mismatch
+                if validate_flags.contains(ValidationFlags::STEP_MISMATCH) {
+                    if let Some(step) = self.allowed_value_step() {
+                        let steps = (number - self.step_base()) / step;
+                        if (steps - steps.round()).abs() > 1e-9 {
+                            failed_flags.insert(ValidationFlags::STEP_MISMATCH);
+                        }
+                    }
+                }
+            }
+        }
+
+        failed_flags
     }
 }
 
@@ -1618,7 +2068,7 @@ impl Activatable for HTMLInputElement {
         match submit_button {
             Some(ref button) => {
                 if button.is_instance_activatable() {
-                    synthetic_click_activation(button.as_element(),
+                    synthetic_click_activation(button.upcast(),
                                                ctrl_key,
                                                shift_key,
                                                alt_key,
@@ -1652,6 +2102,24 @@ impl Activatable for HTMLInputElement {
 }
 
 // https://html.spec.whatwg.org/multipage/#attr-input-accept
+// https://html.spec.whatwg.org/multipage/#valid-e-mail-address
+fn is_valid_email_address(value: &str) -> bool {
+    fn is_valid_label(label: &str) -> bool {
+        !label.is_empty() && label.len() <= 63 &&
+        !label.starts_with('-') && !label.ends_with('-') &&
+        label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    }
+
+    let mut parts = value.splitn(2, '@');
+    let (local, domain) = match (parts.next(), parts.next()) {
+        (Some(local), Some(domain)) => (local, domain),
+        _ => return false,
+    };
+    !local.is_empty() &&
+    local.chars().all(|c| c.is_ascii_alphanumeric() || ".!#$%&'*+/=?^_`{|}~-".contains(c)) &&
+    domain.split('.').all(is_valid_label)
+}
+
 fn filter_from_accept(s: &DOMString) -> Vec<FilterPattern> {
     let mut filter = vec![];
     for p in split_commas(s) {
@@ -19,6 +19,7 @@ use dom::node::{Node, UnbindContext, document_from_node, window_from_node};
 use dom::virtualmethods::VirtualMethods;
 use dom_struct::dom_struct;
 use html5ever::{LocalName, Prefix};
+use net_traits::csp::{CspList, Policy, PolicyDisposition, PolicySource};
 use parking_lot::RwLock;
 use servo_arc::Arc;
 use servo_config::prefs::PREFS;
@@ -87,6 +88,15 @@ impl HTMLMetaElement {
                 self.apply_referrer();
             }
         }
+
+        if let Some(http_equiv) = element.get_attribute(&ns!(), &local_name!("http-equiv")).r() {
+            let http_equiv = http_equiv.value().to_ascii_lowercase();
+            let http_equiv = http_equiv.trim_matches(HTML_SPACE_CHARACTERS);
+
+            if http_equiv == "content-security-policy" {
+                self.apply_csp_list();
+            }
+        }
     }
 
     fn apply_viewport(&self) {
@@ -134,6 +144,27 @@ impl HTMLMetaElement {
         }
     }
 
+    /// <https://html.spec.whatwg.org/multipage/#attr-meta-http-equiv-content-security-policy>
+    fn apply_csp_list(&self) {
+        // Step 1.
+        let in_head = self.upcast::<Node>().GetParentElement().map_or(false, |parent| {
+            parent.is::<HTMLHeadElement>()
+        });
+        if !in_head {
+            return;
+        }
+
+        // Step 2.
+        let content = self.upcast::<Element>().get_string_attribute(&local_name!("content"));
+        if content.is_empty() {
+            return;
+        }
+
+        // Steps 3-5.
+        let policy = Policy::parse(&content, PolicySource::Meta, PolicyDisposition::Enforce);
+        document_from_node(self).append_csp_list(CspList(vec![policy]));
+    }
+
     /// <https://html.spec.whatwg.org/multipage/#meta-referrer>
     fn apply_referrer(&self) {
         if let Some(parent) = self.upcast::<Node>().GetParentElement() {
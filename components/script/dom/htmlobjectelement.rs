@@ -15,7 +15,7 @@ use dom::htmlelement::HTMLElement;
 use dom::htmlformelement::{FormControl, HTMLFormElement};
 use dom::node::{Node, window_from_node};
 use dom::validation::Validatable;
-use dom::validitystate::{ValidityState, ValidationFlags};
+use dom::validitystate::ValidityState;
 use dom::virtualmethods::VirtualMethods;
 use dom_struct::dom_struct;
 use html5ever::{LocalName, Prefix};
@@ -29,6 +29,7 @@ pub struct HTMLObjectElement {
     #[ignore_malloc_size_of = "Arc"]
     image: DomRefCell<Option<Arc<Image>>>,
     form_owner: MutNullableDom<HTMLFormElement>,
+    validity_state: MutNullableDom<ValidityState>,
 }
 
 impl HTMLObjectElement {
@@ -40,6 +41,7 @@ impl HTMLObjectElement {
                 HTMLElement::new_inherited(local_name, prefix, document),
             image: DomRefCell::new(None),
             form_owner: Default::default(),
+            validity_state: Default::default(),
         }
     }
 
@@ -77,8 +79,7 @@ impl<'a> ProcessDataURL for &'a HTMLObjectElement {
 impl HTMLObjectElementMethods for HTMLObjectElement {
     // https://html.spec.whatwg.org/multipage/#dom-cva-validity
     fn Validity(&self) -> DomRoot<ValidityState> {
-        let window = window_from_node(self);
-        ValidityState::new(&window, self.upcast())
+        self.validity_state()
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-object-type
@@ -94,13 +95,18 @@ impl HTMLObjectElementMethods for HTMLObjectElement {
 }
 
 impl Validatable for HTMLObjectElement {
-    fn is_instance_validatable(&self) -> bool {
-        true
+    fn as_element(&self) -> &Element {
+        self.upcast()
     }
-    fn validate(&self, validate_flags: ValidationFlags) -> bool {
-        if validate_flags.is_empty() {}
-        // Need more flag check for different validation types later
-        true
+
+    fn validity_state(&self) -> DomRoot<ValidityState> {
+        let window = window_from_node(self);
+        self.validity_state.or_init(|| ValidityState::new(&window, self.upcast()))
+    }
+
+    // https://html.spec.whatwg.org/multipage/#the-object-element:barred-from-constraint-validation
+    fn is_instance_validatable(&self) -> bool {
+        false
     }
 }
 
@@ -215,6 +215,8 @@ impl VirtualMethods for HTMLOptionElement {
                         }
                     },
                 }
+                // https://html.spec.whatwg.org/multipage/#selector-default
+                self.upcast::<Element>().set_state(ElementState::IN_DEFAULT_STATE, !mutation.is_removal());
             },
             _ => {},
         }
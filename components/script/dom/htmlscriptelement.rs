@@ -29,8 +29,10 @@ use ipc_channel::ipc;
 use ipc_channel::router::ROUTER;
 use js::jsval::UndefinedValue;
 use net_traits::{FetchMetadata, FetchResponseListener, Metadata, NetworkError};
+use net_traits::csp::{InlineCheckType, Violation};
 use net_traits::request::{CorsSettings, CredentialsMode, Destination, RequestInit, RequestMode};
 use network_listener::{NetworkListener, PreInvoke};
+use script_module::{self, ModuleFetchOptions, ModuleTree};
 use servo_atoms::Atom;
 use servo_config::opts;
 use servo_url::ServoUrl;
@@ -39,6 +41,7 @@ use std::fs::File;
 use std::io::{Read, Write};
 use std::path::PathBuf;
 use std::process::{Command, Stdio};
+use std::rc::Rc;
 use std::sync::{Arc, Mutex};
 use style::str::{HTML_SPACE_CHARACTERS, StaticStringVec};
 use task_source::TaskSourceName;
@@ -92,7 +95,7 @@ impl HTMLScriptElement {
 
 /// Supported script types as defined by
 /// <https://html.spec.whatwg.org/multipage/#javascript-mime-type>.
-static SCRIPT_JS_MIMES: StaticStringVec = &[
+pub static SCRIPT_JS_MIMES: StaticStringVec = &[
     "application/ecmascript",
     "application/javascript",
     "application/x-ecmascript",
@@ -111,32 +114,58 @@ static SCRIPT_JS_MIMES: StaticStringVec = &[
     "text/x-javascript",
 ];
 
+/// <https://html.spec.whatwg.org/multipage/#concept-script-type>
+#[derive(Clone, Copy, Debug, JSTraceable, MallocSizeOf, PartialEq)]
+pub enum ScriptType {
+    Classic,
+    Module,
+}
+
 #[derive(JSTraceable, MallocSizeOf)]
-pub struct ClassicScript {
+pub struct ScriptOrigin {
     text: DOMString,
     url: ServoUrl,
     external: bool,
+    /// The root of the module graph, for module scripts.
+    #[ignore_malloc_size_of = "Rc<T> is hard"]
+    module: Option<Rc<ModuleTree>>,
 }
 
-impl ClassicScript {
-    fn internal(text: DOMString, url: ServoUrl) -> ClassicScript {
-        ClassicScript {
+impl ScriptOrigin {
+    fn internal(text: DOMString, url: ServoUrl) -> ScriptOrigin {
+        ScriptOrigin {
             text: text,
             url: url,
             external: false,
+            module: None,
         }
     }
 
-    fn external(text: DOMString, url: ServoUrl) -> ClassicScript {
-        ClassicScript {
+    fn external(text: DOMString, url: ServoUrl) -> ScriptOrigin {
+        ScriptOrigin {
             text: text,
             url: url,
             external: true,
+            module: None,
+        }
+    }
+
+    pub fn module(module: Rc<ModuleTree>, external: bool) -> ScriptOrigin {
+        ScriptOrigin {
+            text: DOMString::new(),
+            url: module.url().clone(),
+            external: external,
+            module: Some(module),
         }
     }
+
+    /// The root of the module graph, for module scripts.
+    pub fn module_tree(&self) -> Option<&Rc<ModuleTree>> {
+        self.module.as_ref()
+    }
 }
 
-pub type ScriptResult = Result<ClassicScript, NetworkError>;
+pub type ScriptResult = Result<ScriptOrigin, NetworkError>;
 
 /// The context required for asynchronously loading an external script source.
 struct ScriptContext {
@@ -203,24 +232,21 @@ impl FetchResponseListener for ScriptContext {
 
             // Step 7.
             let (source_text, _, _) = encoding.decode(&self.data);
-            ClassicScript::external(DOMString::from(source_text), metadata.final_url)
+            ScriptOrigin::external(DOMString::from(source_text), metadata.final_url)
         });
 
         // Step 9.
-        // https://html.spec.whatwg.org/multipage/#prepare-a-script
-        // Step 18.6 (When the chosen algorithm asynchronously completes).
         let elem = self.elem.root();
         let document = document_from_node(&*elem);
-
-        match self.kind {
-            ExternalScriptKind::Asap => document.asap_script_loaded(&elem, load),
-            ExternalScriptKind::AsapInOrder => document.asap_in_order_script_loaded(&elem, load),
-            ExternalScriptKind::Deferred => document.deferred_script_loaded(&elem, load),
-            ExternalScriptKind::ParsingBlocking => document.pending_parsing_blocking_script_loaded(&elem, load),
-        }
+        elem.script_loaded(self.kind, load);
 
         document.finish_load(LoadType::Script(self.url.clone()));
     }
+
+    fn process_csp_violations(&mut self, violations: Vec<Violation>) {
+        let global = &self.elem.root().global();
+        global.report_csp_violations(violations, None);
+    }
 }
 
 impl PreInvoke for ScriptContext {}
@@ -231,6 +257,7 @@ fn fetch_a_classic_script(script: &HTMLScriptElement,
                           url: ServoUrl,
                           cors_setting: Option<CorsSettings>,
                           integrity_metadata: String,
+                          cryptographic_nonce: String,
                           character_encoding: &'static Encoding) {
     let doc = document_from_node(script);
 
@@ -255,6 +282,9 @@ fn fetch_a_classic_script(script: &HTMLScriptElement,
         referrer_url: Some(doc.url()),
         referrer_policy: doc.get_referrer_policy(),
         integrity_metadata: integrity_metadata,
+        cryptographic_nonce_metadata: cryptographic_nonce,
+        csp_list: doc.get_csp_list(),
+        client_url: Some(doc.url()),
         .. RequestInit::default()
     };
 
@@ -315,9 +345,10 @@ impl HTMLScriptElement {
         }
 
         // Step 6.
-        if !self.is_javascript() {
-            return;
-        }
+        let script_type = match self.get_script_type() {
+            Some(script_type) => script_type,
+            None => return,
+        };
 
         // Step 7.
         if was_parser_inserted {
@@ -339,9 +370,16 @@ impl HTMLScriptElement {
             return;
         }
 
-        // TODO: Step 11: nomodule content attribute
+        // Step 11.
+        if script_type == ScriptType::Classic && element.has_attribute(&local_name!("nomodule")) {
+            return;
+        }
 
-        // TODO(#4577): Step 12: CSP.
+        // Step 12.
+        if !element.has_attribute(&local_name!("src")) &&
+           element.is_inline_behavior_blocked_by_csp(InlineCheckType::Script, &text) {
+            return;
+        }
 
         // Step 13.
         let for_attribute = element.get_attribute(&ns!(), &local_name!("for"));
@@ -371,9 +409,14 @@ impl HTMLScriptElement {
         // Step 15.
         let cors_setting = cors_setting_for_element(element);
 
-        // TODO: Step 16: Module script credentials mode.
+        // Step 16.
+        let module_credentials_mode = match cors_setting {
+            Some(CorsSettings::UseCredentials) => CredentialsMode::Include,
+            _ => CredentialsMode::CredentialsSameOrigin,
+        };
 
-        // TODO: Step 17: Nonce.
+        // Step 17.
+        let cryptographic_nonce = String::from(element.get_string_attribute(&local_name!("nonce")));
 
         // Step 18: Integrity metadata.
         let im_attribute = element.get_attribute(&ns!(), &local_name!("integrity"));
@@ -387,6 +430,12 @@ impl HTMLScriptElement {
 
         // TODO: Step 20: environment settings object.
 
+        let module_options = ModuleFetchOptions {
+            credentials_mode: module_credentials_mode,
+            cryptographic_nonce: cryptographic_nonce.clone(),
+            integrity_metadata: integrity_metadata.to_owned(),
+        };
+
         let base_url = doc.base_url();
         if let Some(src) = element.get_attribute(&ns!(), &local_name!("src")) {
             // Step 21.
@@ -400,7 +449,7 @@ impl HTMLScriptElement {
                 return;
             }
 
-            // Step 21.3: The "from an external file"" flag is stored in ClassicScript.
+            // Step 21.3: The "from an external file"" flag is stored in ScriptOrigin.
 
             // Step 21.4-21.5.
             let url = match base_url.join(&src) {
@@ -413,34 +462,66 @@ impl HTMLScriptElement {
             };
 
             // Preparation for step 23.
-            let kind = if element.has_attribute(&local_name!("defer")) && was_parser_inserted && !async {
-                // Step 23.a: classic, has src, has defer, was parser-inserted, is not async.
+            let kind = if (script_type == ScriptType::Module || element.has_attribute(&local_name!("defer"))) &&
+                          was_parser_inserted && !async {
+                // Step 23.a: classic, has src, has defer, was parser-inserted, is not async;
+                // or module, was parser-inserted, is not async.
                 ExternalScriptKind::Deferred
             } else if was_parser_inserted && !async {
                 // Step 23.c: classic, has src, was parser-inserted, is not async.
                 ExternalScriptKind::ParsingBlocking
             } else if !async && !self.non_blocking.get() {
-                // Step 23.d: classic, has src, is not async, is not non-blocking.
+                // Step 23.d: classic with src or module, is not async, is not non-blocking.
                 ExternalScriptKind::AsapInOrder
             } else {
-                // Step 23.f: classic, has src.
+                // Step 23.f: classic with src or module.
                 ExternalScriptKind::Asap
             };
 
             // Step 21.6.
-            fetch_a_classic_script(self, kind, url, cors_setting, integrity_metadata.to_owned(), encoding);
+            let module = match script_type {
+                ScriptType::Classic => {
+                    fetch_a_classic_script(self,
+                                           kind,
+                                           url,
+                                           cors_setting,
+                                           integrity_metadata.to_owned(),
+                                           cryptographic_nonce,
+                                           encoding);
+                    None
+                },
+                ScriptType::Module => {
+                    Some(script_module::fetch_external_module_script(&doc, url, module_options))
+                },
+            };
 
             // Step 23.
-            match kind {
-                ExternalScriptKind::Deferred => doc.add_deferred_script(self),
-                ExternalScriptKind::ParsingBlocking => doc.set_pending_parsing_blocking_script(self, None),
-                ExternalScriptKind::AsapInOrder => doc.push_asap_in_order_script(self),
-                ExternalScriptKind::Asap => doc.add_asap_script(self),
+            self.add_pending_script(&doc, kind);
+            if let Some(module) = module {
+                doc.add_pending_module_graph(self, ScriptOrigin::module(module, true), kind);
             }
+        } else if script_type == ScriptType::Module {
+            // Step 22.
+            assert!(!text.is_empty());
+            let module = script_module::fetch_inline_module_script(&doc, &text, base_url, module_options);
+
+            // Step 23.
+            let kind = if was_parser_inserted && !async {
+                // Step 23.a: module, was parser-inserted, is not async.
+                ExternalScriptKind::Deferred
+            } else if !async && !self.non_blocking.get() {
+                // Step 23.d: module, is not async, is not non-blocking.
+                ExternalScriptKind::AsapInOrder
+            } else {
+                // Step 23.f: module.
+                ExternalScriptKind::Asap
+            };
+            self.add_pending_script(&doc, kind);
+            doc.add_pending_module_graph(self, ScriptOrigin::module(module, false), kind);
         } else {
             // Step 22.
             assert!(!text.is_empty());
-            let result = Ok(ClassicScript::internal(text, base_url));
+            let result = Ok(ScriptOrigin::internal(text, base_url));
 
             // Step 23.
             if was_parser_inserted &&
@@ -455,8 +536,34 @@ impl HTMLScriptElement {
         }
     }
 
-    fn unminify_js(&self, script: &mut ClassicScript) {
-        if !opts::get().unminify_js {
+    /// Adds this script to the list of scripts of `doc` that `kind` of scripts wait in.
+    ///
+    /// <https://html.spec.whatwg.org/multipage/#prepare-a-script> step 23.
+    fn add_pending_script(&self, doc: &Document, kind: ExternalScriptKind) {
+        match kind {
+            ExternalScriptKind::Deferred => doc.add_deferred_script(self),
+            ExternalScriptKind::ParsingBlocking => doc.set_pending_parsing_blocking_script(self, None),
+            ExternalScriptKind::AsapInOrder => doc.push_asap_in_order_script(self),
+            ExternalScriptKind::Asap => doc.add_asap_script(self),
+        }
+    }
+
+    /// Hands the fetched script to the list of scripts it waits in.
+    ///
+    /// <https://html.spec.whatwg.org/multipage/#prepare-a-script>
+    /// Step 23 (When the chosen algorithm asynchronously completes).
+    pub fn script_loaded(&self, kind: ExternalScriptKind, load: ScriptResult) {
+        let document = document_from_node(self);
+        match kind {
+            ExternalScriptKind::Asap => document.asap_script_loaded(self, load),
+            ExternalScriptKind::AsapInOrder => document.asap_in_order_script_loaded(self, load),
+            ExternalScriptKind::Deferred => document.deferred_script_loaded(self, load),
+            ExternalScriptKind::ParsingBlocking => document.pending_parsing_blocking_script_loaded(self, load),
+        }
+    }
+
+    fn unminify_js(&self, script: &mut ScriptOrigin) {
+        if !opts::get().unminify_js || script.module.is_some() {
             return;
         }
 
@@ -499,7 +606,7 @@ impl HTMLScriptElement {
     }
 
     /// <https://html.spec.whatwg.org/multipage/#execute-the-script-block>
-    pub fn execute(&self, result: Result<ClassicScript, NetworkError>) {
+    pub fn execute(&self, result: ScriptResult) {
         // Step 1.
         let doc = document_from_node(self);
         if self.parser_inserted.get() && &*doc != &*self.parser_document {
@@ -520,7 +627,7 @@ impl HTMLScriptElement {
         self.unminify_js(&mut script);
 
         // Step 3.
-        let neutralized_doc = if script.external {
+        let neutralized_doc = if script.external || script.module.is_some() {
             debug!("loading external script, url = {}", script.url);
             let doc = document_from_node(self);
             doc.incr_ignore_destructive_writes_counter();
@@ -529,18 +636,26 @@ impl HTMLScriptElement {
             None
         };
 
-        // Step 4.
-        let document = document_from_node(self);
-        let old_script = document.GetCurrentScript();
+        match script.module {
+            None => {
+                // Step 4.
+                let document = document_from_node(self);
+                let old_script = document.GetCurrentScript();
 
-        // Step 5.a.1.
-        document.set_current_script(Some(self));
+                // Step 5.a.1.
+                document.set_current_script(Some(self));
 
-        // Step 5.a.2.
-        self.run_a_classic_script(&script);
+                // Step 5.a.2.
+                self.run_a_classic_script(&script);
 
-        // Step 6.
-        document.set_current_script(old_script.r());
+                // Step 6.
+                document.set_current_script(old_script.r());
+            },
+            Some(ref module) => {
+                // Step 5.b: currentScript stays null while module scripts run.
+                self.run_a_module_script(module);
+            },
+        }
 
         // Step 7.
         if let Some(doc) = neutralized_doc {
@@ -554,7 +669,7 @@ impl HTMLScriptElement {
     }
 
     // https://html.spec.whatwg.org/multipage/#run-a-classic-script
-    pub fn run_a_classic_script(&self, script: &ClassicScript) {
+    pub fn run_a_classic_script(&self, script: &ScriptOrigin) {
         // TODO use a settings object rather than this element's document/window
         // Step 2
         let document = document_from_node(self);
@@ -571,6 +686,20 @@ impl HTMLScriptElement {
             &script.text, script.url.as_str(), rval.handle_mut(), line_number);
     }
 
+    /// <https://html.spec.whatwg.org/multipage/#run-a-module-script>
+    pub fn run_a_module_script(&self, module: &ModuleTree) {
+        // TODO use a settings object rather than this element's document/window
+        // Step 2
+        let document = document_from_node(self);
+        if !document.is_fully_active() || !document.is_scripting_enabled() {
+            return;
+        }
+
+        // Steps 3-7
+        let window = window_from_node(self);
+        script_module::run_module_script(window.upcast(), module);
+    }
+
     pub fn queue_error_event(&self) {
         let window = window_from_node(self);
         window.dom_manipulation_task_source().queue_simple_event(self.upcast(), atom!("error"), &window);
@@ -588,7 +717,8 @@ impl HTMLScriptElement {
                             EventCancelable::NotCancelable);
     }
 
-    pub fn is_javascript(&self) -> bool {
+    /// <https://html.spec.whatwg.org/multipage/#prepare-a-script> step 6.
+    pub fn get_script_type(&self) -> Option<ScriptType> {
         let element = self.upcast::<Element>();
         let type_attr = element.get_attribute(&ns!(), &local_name!("type"));
         let is_js = match type_attr.as_ref().map(|s| s.value()) {
@@ -599,7 +729,12 @@ impl HTMLScriptElement {
             },
             Some(s) => {
                 debug!("script type={}", &**s);
-                SCRIPT_JS_MIMES.contains(&s.to_ascii_lowercase().trim_matches(HTML_SPACE_CHARACTERS))
+                let type_ = s.to_ascii_lowercase();
+                let type_ = type_.trim_matches(HTML_SPACE_CHARACTERS);
+                if type_ == "module" {
+                    return Some(ScriptType::Module);
+                }
+                SCRIPT_JS_MIMES.contains(&type_)
             },
             None => {
                 debug!("no script type");
@@ -625,7 +760,7 @@ impl HTMLScriptElement {
             }
         };
         // https://github.com/rust-lang/rust/issues/21114
-        is_js
+        if is_js { Some(ScriptType::Classic) } else { None }
     }
 
     pub fn set_parser_inserted(&self, parser_inserted: bool) {
@@ -730,6 +865,11 @@ impl HTMLScriptElementMethods for HTMLScriptElement {
     // https://html.spec.whatwg.org/multipage/#dom-script-defer
     make_bool_setter!(SetDefer, "defer");
 
+    // https://html.spec.whatwg.org/multipage/#dom-script-nomodule
+    make_bool_getter!(NoModule, "nomodule");
+    // https://html.spec.whatwg.org/multipage/#dom-script-nomodule
+    make_bool_setter!(SetNoModule, "nomodule");
+
     // https://html.spec.whatwg.org/multipage/#dom-script-integrity
     make_getter!(Integrity, "integrity");
     // https://html.spec.whatwg.org/multipage/#dom-script-integrity
@@ -766,8 +906,8 @@ impl HTMLScriptElementMethods for HTMLScriptElement {
     }
 }
 
-#[derive(Clone, Copy)]
-enum ExternalScriptKind {
+#[derive(Clone, Copy, JSTraceable, MallocSizeOf)]
+pub enum ExternalScriptKind {
     Deferred,
     ParsingBlocking,
     AsapInOrder,
@@ -27,7 +27,7 @@ use dom::htmloptionelement::HTMLOptionElement;
 use dom::htmloptionscollection::HTMLOptionsCollection;
 use dom::node::{Node, UnbindContext, window_from_node};
 use dom::nodelist::NodeList;
-use dom::validation::Validatable;
+use dom::validation::{Validatable, is_barred_by_datalist_ancestor};
 use dom::validitystate::{ValidityState, ValidationFlags};
 use dom::virtualmethods::VirtualMethods;
 use dom_struct::dom_struct;
@@ -63,6 +63,7 @@ pub struct HTMLSelectElement {
     htmlelement: HTMLElement,
     options: MutNullableDom<HTMLOptionsCollection>,
     form_owner: MutNullableDom<HTMLFormElement>,
+    validity_state: MutNullableDom<ValidityState>,
 }
 
 static DEFAULT_SELECT_SIZE: u32 = 0;
@@ -73,10 +74,12 @@ impl HTMLSelectElement {
                      document: &Document) -> HTMLSelectElement {
         HTMLSelectElement {
             htmlelement:
-                HTMLElement::new_inherited_with_state(ElementState::IN_ENABLED_STATE,
+                HTMLElement::new_inherited_with_state(ElementState::IN_ENABLED_STATE |
+                                                      ElementState::IN_OPTIONAL_STATE,
                                                       local_name, prefix, document),
                 options: Default::default(),
                 form_owner: Default::default(),
+                validity_state: Default::default(),
         }
     }
 
@@ -117,6 +120,7 @@ impl HTMLSelectElement {
     // https://html.spec.whatwg.org/multipage/#ask-for-a-reset
     pub fn ask_for_reset(&self) {
         if self.Multiple() {
+            self.update_validity_state();
             return;
         }
 
@@ -143,6 +147,8 @@ impl HTMLSelectElement {
                 }
             }
         }
+
+        self.update_validity_state();
     }
 
     pub fn push_form_data(&self, data_set: &mut Vec<FormDatum>) {
@@ -173,6 +179,18 @@ impl HTMLSelectElement {
         }
     }
 
+    // https://html.spec.whatwg.org/multipage/#placeholder-label-option
+    fn placeholder_label_option(&self) -> Option<DomRoot<HTMLOptionElement>> {
+        if self.Required() && !self.Multiple() && self.display_size() == 1 {
+            self.list_of_options().next().filter(|option| {
+                option.Value().is_empty() &&
+                option.upcast::<Node>().GetParentNode().r() == Some(self.upcast::<Node>())
+            })
+        } else {
+            None
+        }
+    }
+
     // https://html.spec.whatwg.org/multipage/#concept-select-size
     fn display_size(&self) -> u32 {
          if self.Size() == 0 {
@@ -188,10 +206,34 @@ impl HTMLSelectElement {
 }
 
 impl HTMLSelectElementMethods for HTMLSelectElement {
+    // https://html.spec.whatwg.org/multipage/#dom-cva-willvalidate
+    fn WillValidate(&self) -> bool {
+        self.is_instance_validatable()
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-cva-validity
     fn Validity(&self) -> DomRoot<ValidityState> {
-        let window = window_from_node(self);
-        ValidityState::new(&window, self.upcast())
+        self.validity_state()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-cva-validationmessage
+    fn ValidationMessage(&self) -> DOMString {
+        self.validation_message()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-cva-checkvalidity
+    fn CheckValidity(&self) -> bool {
+        self.check_validity()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-cva-reportvalidity
+    fn ReportValidity(&self) -> bool {
+        self.report_validity()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-cva-setcustomvalidity
+    fn SetCustomValidity(&self, error: DOMString) {
+        self.set_custom_validity(error);
     }
 
     // Note: this function currently only exists for union.html.
@@ -222,6 +264,12 @@ impl HTMLSelectElementMethods for HTMLSelectElement {
     // https://html.spec.whatwg.org/multipage/#dom-fe-name
     make_setter!(SetName, "name");
 
+    // https://html.spec.whatwg.org/multipage/#dom-select-required
+    make_bool_getter!(Required, "required");
+
+    // https://html.spec.whatwg.org/multipage/#dom-select-required
+    make_bool_setter!(SetRequired, "required");
+
     // https://html.spec.whatwg.org/multipage/#dom-select-size
     make_uint_getter!(Size, "size", DEFAULT_SELECT_SIZE);
 
@@ -311,6 +359,7 @@ impl HTMLSelectElementMethods for HTMLSelectElement {
         for opt in opt_iter {
             opt.set_selectedness(false);
         }
+        self.update_validity_state();
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-select-selectedindex
@@ -337,6 +386,7 @@ impl HTMLSelectElementMethods for HTMLSelectElement {
                 opt.set_selectedness(false);
             }
         }
+        self.update_validity_state();
     }
 }
 
@@ -367,6 +417,14 @@ impl VirtualMethods for HTMLSelectElement {
             },
             _ => {},
         }
+
+        match attr.local_name() {
+            &local_name!("disabled") | &local_name!("form") | &local_name!("multiple") |
+            &local_name!("required") | &local_name!("size") => {
+                self.update_validity_state();
+            },
+            _ => {},
+        }
     }
 
     fn bind_to_tree(&self, tree_in_doc: bool) {
@@ -375,6 +433,7 @@ impl VirtualMethods for HTMLSelectElement {
         }
 
         self.upcast::<Element>().check_ancestors_disabled_state_for_form_control();
+        self.update_validity_state();
     }
 
     fn unbind_from_tree(&self, context: &UnbindContext) {
@@ -387,6 +446,7 @@ impl VirtualMethods for HTMLSelectElement {
         } else {
             el.check_disabled_attribute();
         }
+        self.update_validity_state();
     }
 
     fn parse_plain_attribute(&self, local_name: &LocalName, value: DOMString) -> AttrValue {
@@ -412,13 +472,45 @@ impl FormControl for HTMLSelectElement {
 }
 
 impl Validatable for HTMLSelectElement {
-    fn is_instance_validatable(&self) -> bool {
-        true
+    fn as_element(&self) -> &Element {
+        self.upcast()
     }
-    fn validate(&self, validate_flags: ValidationFlags) -> bool {
-        if validate_flags.is_empty() {}
-        // Need more flag check for different validation types later
-        true
+
+    fn validity_state(&self) -> DomRoot<ValidityState> {
+        let window = window_from_node(self);
+        self.validity_state.or_init(|| ValidityState::new(&window, self.upcast()))
+    }
+
+    // https://html.spec.whatwg.org/multipage/#candidate-for-constraint-validation
+    fn is_instance_validatable(&self) -> bool {
+        // https://html.spec.whatwg.org/multipage/#enabling-and-disabling-form-controls:-the-disabled-attribute:barred-from-constraint-validation
+        let element = self.upcast::<Element>();
+        !element.disabled_state() && !is_barred_by_datalist_ancestor(element)
+    }
+
+    fn required_state(&self) -> Option<bool> {
+        Some(self.Required())
+    }
+
+    fn perform_validation(&self, validate_flags: ValidationFlags) -> ValidationFlags {
+        let mut failed_flags = ValidationFlags::empty();
+
+        // https://html.spec.whatwg.org/multipage/#the-select-element:suffering-from-being-missing
+        if validate_flags.contains(ValidationFlags::VALUE_MISSING) && self.Required() {
+            let placeholder = self.placeholder_label_option();
+            let mut selected_options = self.list_of_options().filter(|option| option.Selected());
+            let value_missing = match selected_options.next() {
+                None => true,
+                Some(option) => {
+                    selected_options.next().is_none() && placeholder.map_or(false, |p| p == option)
+                },
+            };
+            if value_missing {
+                failed_flags.insert(ValidationFlags::VALUE_MISSING);
+            }
+        }
+
+        failed_flags
     }
 }
 
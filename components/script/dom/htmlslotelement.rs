@@ -0,0 +1,230 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::attr::Attr;
+use dom::bindings::cell::DomRefCell;
+use dom::bindings::codegen::Bindings::HTMLSlotElementBinding;
+use dom::bindings::codegen::Bindings::HTMLSlotElementBinding::{AssignedNodesOptions, HTMLSlotElementMethods};
+use dom::bindings::codegen::Bindings::NodeBinding::NodeMethods;
+use dom::bindings::codegen::Bindings::ShadowRootBinding::ShadowRootMode;
+use dom::bindings::inheritance::Castable;
+use dom::bindings::root::{Dom, DomRoot, LayoutDom};
+use dom::bindings::str::DOMString;
+use dom::document::Document;
+use dom::element::{AttributeMutation, Element};
+use dom::htmlelement::HTMLElement;
+use dom::mutationobserver::MutationObserver;
+use dom::node::{Node, NodeDamage};
+use dom::text::Text;
+use dom::virtualmethods::VirtualMethods;
+use dom_struct::dom_struct;
+use html5ever::{LocalName, Prefix};
+use script_thread::ScriptThread;
+
+#[dom_struct]
+pub struct HTMLSlotElement {
+    htmlelement: HTMLElement,
+    /// <https://dom.spec.whatwg.org/#slot-assigned-nodes>
+    assigned_nodes: DomRefCell<Vec<Dom<Node>>>,
+}
+
+impl HTMLSlotElement {
+    fn new_inherited(local_name: LocalName, prefix: Option<Prefix>, document: &Document) -> HTMLSlotElement {
+        HTMLSlotElement {
+            htmlelement: HTMLElement::new_inherited(local_name, prefix, document),
+            assigned_nodes: DomRefCell::new(vec![]),
+        }
+    }
+
+    #[allow(unrooted_must_root)]
+    pub fn new(local_name: LocalName,
+               prefix: Option<Prefix>,
+               document: &Document) -> DomRoot<HTMLSlotElement> {
+        Node::reflect_node(Box::new(HTMLSlotElement::new_inherited(local_name, prefix, document)),
+                           document,
+                           HTMLSlotElementBinding::Wrap)
+    }
+
+    /// <https://dom.spec.whatwg.org/#slot-name>
+    fn name(&self) -> DOMString {
+        self.upcast::<Element>().get_string_attribute(&local_name!("name"))
+    }
+
+    /// Whether this slot is in a shadow tree, and thus can get nodes assigned.
+    fn is_in_shadow_root(&self) -> bool {
+        self.upcast::<Node>().containing_shadow_root().is_some()
+    }
+
+    pub fn has_assigned_nodes(&self) -> bool {
+        !self.assigned_nodes.borrow().is_empty()
+    }
+
+    /// <https://dom.spec.whatwg.org/#find-a-slot>
+    pub fn find_a_slot(slottable: &Node, open: bool) -> Option<DomRoot<HTMLSlotElement>> {
+        // Steps 1-2.
+        let shadow_root = slottable.GetParentElement()?.shadow_root()?;
+        // Step 3.
+        if open && shadow_root.mode() != ShadowRootMode::Open {
+            return None;
+        }
+        // Step 4.
+        let name = slottable_name(slottable);
+        shadow_root.upcast::<Node>()
+                   .traverse_preorder()
+                   .filter_map(DomRoot::downcast::<HTMLSlotElement>)
+                   .find(|slot| slot.name() == name)
+    }
+
+    /// <https://dom.spec.whatwg.org/#find-slotables>
+    fn find_slottables(&self) -> Vec<DomRoot<Node>> {
+        // Steps 1-3.
+        let host = match self.upcast::<Node>().containing_shadow_root() {
+            Some(shadow_root) => shadow_root.host(),
+            None => return vec![],
+        };
+        // Step 4.
+        host.upcast::<Node>().children().filter(|child| {
+            is_slottable(child) &&
+            HTMLSlotElement::find_a_slot(child, false).map_or(false, |slot| &*slot == self)
+        }).collect()
+    }
+
+    /// <https://dom.spec.whatwg.org/#find-flattened-slotables>
+    fn find_flattened_slottables(&self) -> Vec<DomRoot<Node>> {
+        // Steps 1-2.
+        if !self.is_in_shadow_root() {
+            return vec![];
+        }
+        // Steps 3-4.
+        let mut slottables = self.find_slottables();
+        if slottables.is_empty() {
+            slottables = self.upcast::<Node>().children().filter(|child| is_slottable(child)).collect();
+        }
+        // Step 5.
+        let mut result = vec![];
+        for node in slottables {
+            match node.downcast::<HTMLSlotElement>() {
+                Some(slot) if slot.is_in_shadow_root() => result.extend(slot.find_flattened_slottables()),
+                _ => result.push(node.clone()),
+            }
+        }
+        result
+    }
+
+    /// <https://dom.spec.whatwg.org/#assign-slotables>
+    pub fn assign_slottables(&self) {
+        // Step 1.
+        let slottables = self.find_slottables();
+        // Step 2.
+        let unchanged = {
+            let assigned_nodes = self.assigned_nodes.borrow();
+            assigned_nodes.len() == slottables.len() &&
+            assigned_nodes.iter().zip(slottables.iter()).all(|(old, new)| &**old == &**new)
+        };
+        if unchanged {
+            return;
+        }
+        self.signal_a_slot_change();
+        // Nodes that are no longer assigned to this slot lose their assigned slot.
+        for old in self.assigned_nodes.borrow().iter() {
+            if old.assigned_slot().map_or(false, |slot| &*slot == self) {
+                old.set_assigned_slot(None);
+                old.dirty(NodeDamage::OtherNodeDamage);
+            }
+        }
+        // Steps 3-4.
+        for slottable in &slottables {
+            slottable.set_assigned_slot(Some(self));
+            slottable.dirty(NodeDamage::OtherNodeDamage);
+        }
+        *self.assigned_nodes.borrow_mut() = slottables.iter().map(|node| Dom::from_ref(&**node)).collect();
+        // The flat tree changed, so the slot and its new children need new boxes.
+        if self.upcast::<Node>().is_in_doc() {
+            self.upcast::<Element>().restyle_subtree();
+        }
+    }
+
+    /// <https://dom.spec.whatwg.org/#assign-slotables-for-a-tree>
+    pub fn assign_slottables_for_a_tree(root: &Node) {
+        for slot in root.traverse_preorder().filter_map(DomRoot::downcast::<HTMLSlotElement>) {
+            slot.assign_slottables();
+        }
+    }
+
+    /// <https://dom.spec.whatwg.org/#assign-a-slot>
+    pub fn assign_a_slot(slottable: &Node) {
+        if let Some(slot) = HTMLSlotElement::find_a_slot(slottable, false) {
+            slot.assign_slottables();
+        }
+    }
+
+    /// <https://dom.spec.whatwg.org/#signal-a-slot-change>
+    pub fn signal_a_slot_change(&self) {
+        ScriptThread::add_signal_slot(self);
+        MutationObserver::queue_mutation_observer_compound_microtask();
+    }
+}
+
+/// <https://dom.spec.whatwg.org/#concept-slotable>
+pub fn is_slottable(node: &Node) -> bool {
+    node.is::<Element>() || node.is::<Text>()
+}
+
+/// <https://dom.spec.whatwg.org/#slotable-name>
+fn slottable_name(slottable: &Node) -> DOMString {
+    slottable.downcast::<Element>()
+             .map_or(DOMString::new(), |element| element.get_string_attribute(&local_name!("slot")))
+}
+
+impl HTMLSlotElementMethods for HTMLSlotElement {
+    // https://html.spec.whatwg.org/multipage/#dom-slot-name
+    make_getter!(Name, "name");
+
+    // https://html.spec.whatwg.org/multipage/#dom-slot-name
+    make_setter!(SetName, "name");
+
+    // https://html.spec.whatwg.org/multipage/#dom-slot-assignednodes
+    fn AssignedNodes(&self, options: &AssignedNodesOptions) -> Vec<DomRoot<Node>> {
+        if options.flatten {
+            self.find_flattened_slottables()
+        } else {
+            self.assigned_nodes.borrow().iter().map(|node| DomRoot::from_ref(&**node)).collect()
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-slot-assignedelements
+    fn AssignedElements(&self, options: &AssignedNodesOptions) -> Vec<DomRoot<Element>> {
+        self.AssignedNodes(options).into_iter().filter_map(DomRoot::downcast::<Element>).collect()
+    }
+}
+
+impl VirtualMethods for HTMLSlotElement {
+    fn super_type(&self) -> Option<&VirtualMethods> {
+        Some(self.upcast::<HTMLElement>() as &VirtualMethods)
+    }
+
+    fn attribute_mutated(&self, attr: &Attr, mutation: AttributeMutation) {
+        self.super_type().unwrap().attribute_mutated(attr, mutation);
+
+        // https://dom.spec.whatwg.org/#shadow-tree-slots
+        if attr.local_name() == &local_name!("name") && attr.namespace() == &ns!() {
+            if let Some(shadow_root) = self.upcast::<Node>().containing_shadow_root() {
+                HTMLSlotElement::assign_slottables_for_a_tree(shadow_root.upcast());
+            }
+        }
+    }
+}
+
+pub trait LayoutHTMLSlotElementHelpers {
+    #[allow(unsafe_code)]
+    unsafe fn assigned_nodes_for_layout(&self) -> Vec<LayoutDom<Node>>;
+}
+
+impl LayoutHTMLSlotElementHelpers for LayoutDom<HTMLSlotElement> {
+    #[allow(unsafe_code)]
+    unsafe fn assigned_nodes_for_layout(&self) -> Vec<LayoutDom<Node>> {
+        (*self.unsafe_get()).assigned_nodes.borrow_for_layout().iter().map(|node| node.to_layout()).collect()
+    }
+}
+
@@ -26,7 +26,8 @@ use dom::node::{CloneChildrenFlag, ChildrenMutation, Node, NodeDamage, UnbindCon
 use dom::node::{document_from_node, window_from_node};
 use dom::nodelist::NodeList;
 use dom::textcontrol::{TextControlElement, TextControlSelection};
-use dom::validation::Validatable;
+use dom::validation::{Validatable, is_barred_by_datalist_ancestor};
+use dom::validitystate::{ValidationFlags, ValidityState};
 use dom::virtualmethods::VirtualMethods;
 use dom_struct::dom_struct;
 use html5ever::{LocalName, Prefix};
@@ -46,7 +47,10 @@ pub struct HTMLTextAreaElement {
     placeholder: DomRefCell<DOMString>,
     // https://html.spec.whatwg.org/multipage/#concept-textarea-dirty
     value_dirty: Cell<bool>,
+    // https://html.spec.whatwg.org/multipage/#setting-minimum-input-length-requirements:last-changed-by-a-user-edit
+    value_changed_by_user: Cell<bool>,
     form_owner: MutNullableDom<HTMLFormElement>,
+    validity_state: MutNullableDom<ValidityState>,
 }
 
 pub trait LayoutHTMLTextAreaElementHelpers {
@@ -121,13 +125,16 @@ impl HTMLTextAreaElement {
         HTMLTextAreaElement {
             htmlelement:
                 HTMLElement::new_inherited_with_state(ElementState::IN_ENABLED_STATE |
-                                                      ElementState::IN_READ_WRITE_STATE,
+                                                      ElementState::IN_READ_WRITE_STATE |
+                                                      ElementState::IN_OPTIONAL_STATE,
                                                       local_name, prefix, document),
             placeholder: DomRefCell::new(DOMString::new()),
             textinput: DomRefCell::new(TextInput::new(
                     Lines::Multiple, DOMString::new(), chan, None, None, SelectionDirection::None)),
             value_dirty: Cell::new(false),
+            value_changed_by_user: Cell::new(false),
             form_owner: Default::default(),
+            validity_state: Default::default(),
         }
     }
 
@@ -269,12 +276,15 @@ impl HTMLTextAreaElementMethods for HTMLTextAreaElement {
 
         // Step 3
         self.value_dirty.set(true);
+        self.value_changed_by_user.set(false);
 
         if old_value != textinput.get_content() {
             // Step 4
             textinput.clear_selection_to_limit(Direction::Forward);
         }
 
+        drop(textinput);
+        self.update_validity_state();
         self.upcast::<Node>().dirty(NodeDamage::OtherNodeDamage);
     }
 
@@ -338,15 +348,46 @@ impl HTMLTextAreaElementMethods for HTMLTextAreaElement {
                      selection_mode: SelectionMode) -> ErrorResult {
         self.selection().set_dom_range_text(replacement, Some(start), Some(end), selection_mode)
     }
+
+    // https://html.spec.whatwg.org/multipage/#dom-cva-willvalidate
+    fn WillValidate(&self) -> bool {
+        self.is_instance_validatable()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-cva-validity
+    fn Validity(&self) -> DomRoot<ValidityState> {
+        self.validity_state()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-cva-validationmessage
+    fn ValidationMessage(&self) -> DOMString {
+        self.validation_message()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-cva-checkvalidity
+    fn CheckValidity(&self) -> bool {
+        self.check_validity()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-cva-reportvalidity
+    fn ReportValidity(&self) -> bool {
+        self.report_validity()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-cva-setcustomvalidity
+    fn SetCustomValidity(&self, error: DOMString) {
+        self.set_custom_validity(error);
+    }
 }
 
 
 impl HTMLTextAreaElement {
     pub fn reset(&self) {
         // https://html.spec.whatwg.org/multipage/#the-textarea-element:concept-form-reset-control
-        let mut textinput = self.textinput.borrow_mut();
-        textinput.set_content(self.DefaultValue());
+        self.textinput.borrow_mut().set_content(self.DefaultValue());
         self.value_dirty.set(false);
+        self.value_changed_by_user.set(false);
+        self.update_validity_state();
     }
 
     #[allow(unrooted_must_root)]
@@ -438,6 +479,14 @@ impl VirtualMethods for HTMLTextAreaElement {
             },
             _ => {},
         }
+
+        match *attr.local_name() {
+            local_name!("disabled") | local_name!("maxlength") | local_name!("minlength") |
+            local_name!("readonly") | local_name!("required") | local_name!("form") => {
+                self.update_validity_state();
+            },
+            _ => {},
+        }
     }
 
     fn bind_to_tree(&self, tree_in_doc: bool) {
@@ -446,6 +495,7 @@ impl VirtualMethods for HTMLTextAreaElement {
         }
 
         self.upcast::<Element>().check_ancestors_disabled_state_for_form_control();
+        self.update_validity_state();
     }
 
     fn parse_plain_attribute(&self, name: &LocalName, value: DOMString) -> AttrValue {
@@ -468,6 +518,7 @@ impl VirtualMethods for HTMLTextAreaElement {
         } else {
             el.check_disabled_attribute();
         }
+        self.update_validity_state();
     }
 
     // The cloning steps for textarea elements must propagate the raw value
@@ -511,7 +562,9 @@ impl VirtualMethods for HTMLTextAreaElement {
                     KeyReaction::TriggerDefaultAction => (),
                     KeyReaction::DispatchInput => {
                         self.value_dirty.set(true);
+                        self.value_changed_by_user.set(true);
                         self.update_placeholder_shown_state();
+                        self.update_validity_state();
                         self.upcast::<Node>().dirty(NodeDamage::OtherNodeDamage);
                         event.mark_as_handled();
                     }
@@ -557,5 +610,56 @@ impl FormControl for HTMLTextAreaElement {
     }
 }
 
+impl Validatable for HTMLTextAreaElement {
+    fn as_element(&self) -> &Element {
+        self.upcast()
+    }
+
+    fn validity_state(&self) -> DomRoot<ValidityState> {
+        let window = window_from_node(self);
+        self.validity_state.or_init(|| ValidityState::new(&window, self.upcast()))
+    }
+
+    // https://html.spec.whatwg.org/multipage/#candidate-for-constraint-validation
+    fn is_instance_validatable(&self) -> bool {
+        // https://html.spec.whatwg.org/multipage/#the-textarea-element:barred-from-constraint-validation
+        // https://html.spec.whatwg.org/multipage/#enabling-and-disabling-form-controls:-the-disabled-attribute:barred-from-constraint-validation
+        let element = self.upcast::<Element>();
+        !self.ReadOnly() && !element.disabled_state() && !is_barred_by_datalist_ancestor(element)
+    }
+
+    fn required_state(&self) -> Option<bool> {
+        Some(self.Required())
+    }
+
+    fn perform_validation(&self, validate_flags: ValidationFlags) -> ValidationFlags {
+        let mut failed_flags = ValidationFlags::empty();
+        let length = self.TextLength() as i32;
 
-impl Validatable for HTMLTextAreaElement {}
+        // https://html.spec.whatwg.org/multipage/#the-textarea-element:suffering-from-being-missing
+        if validate_flags.contains(ValidationFlags::VALUE_MISSING) &&
+           self.Required() && !self.upcast::<Element>().disabled_state() && !self.ReadOnly() && length == 0
+        {
+            failed_flags.insert(ValidationFlags::VALUE_MISSING);
+        }
+
+        // https://html.spec.whatwg.org/multipage/#limiting-user-input-length:-the-maxlength-attribute
+        // https://html.spec.whatwg.org/multipage/#setting-minimum-input-length-requirements:-the-minlength-attribute
+        if self.value_dirty.get() && self.value_changed_by_user.get() {
+            let max_length = self.MaxLength();
+            let min_length = self.MinLength();
+            if validate_flags.contains(ValidationFlags::TOO_LONG) &&
+               max_length != DEFAULT_MAX_LENGTH && length > max_length
+            {
+                failed_flags.insert(ValidationFlags::TOO_LONG);
+            }
+            if validate_flags.contains(ValidationFlags::TOO_SHORT) &&
+               min_length != DEFAULT_MIN_LENGTH && length > 0 && length < min_length
+            {
+                failed_flags.insert(ValidationFlags::TOO_SHORT);
+            }
+        }
+
+        failed_flags
+    }
+}
@@ -0,0 +1,290 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::cell::DomRefCell;
+use dom::bindings::codegen::Bindings::IDBCursorBinding;
+use dom::bindings::codegen::Bindings::IDBCursorBinding::{IDBCursorDirection, IDBCursorMethods};
+use dom::bindings::codegen::Bindings::IDBDatabaseBinding::IDBTransactionMode;
+use dom::bindings::codegen::UnionTypes::{IDBObjectStoreOrIDBIndex, IDBObjectStoreOrIDBIndexOrIDBCursor};
+use dom::bindings::conversions::ToJSValConvertible;
+use dom::bindings::error::{Error, ErrorResult, Fallible};
+use dom::bindings::inheritance::Castable;
+use dom::bindings::reflector::{DomObject, Reflector, reflect_dom_object};
+use dom::bindings::root::{Dom, DomRoot};
+use dom::globalscope::GlobalScope;
+use dom::idbcursorwithvalue::IDBCursorWithValue;
+use dom::idbindex::IDBIndex;
+use dom::idbobjectstore::IDBObjectStore;
+use dom::idbrequest::{IDBRequest, IDBRequestResult};
+use dom_struct::dom_struct;
+use indexed_db::{RecordValue, convert_value_to_key, error_name, key_to_jsval};
+use js::jsapi::JSContext;
+use js::jsval::{JSVal, UndefinedValue};
+use js::rust::HandleValue;
+use net_traits::indexeddb_thread::{IndexedDBCursorDirection, IndexedDBKey, IndexedDBKeyRange};
+use net_traits::indexeddb_thread::{IndexedDBOperation, IndexedDBQuery, IndexedDBRecord, ObjectStoreInfo};
+use std::cell::Cell;
+
+pub fn direction_from_idl(direction: IDBCursorDirection) -> IndexedDBCursorDirection {
+    match direction {
+        IDBCursorDirection::Next => IndexedDBCursorDirection::Next,
+        IDBCursorDirection::Nextunique => IndexedDBCursorDirection::NextUnique,
+        IDBCursorDirection::Prev => IndexedDBCursorDirection::Prev,
+        IDBCursorDirection::Prevunique => IndexedDBCursorDirection::PrevUnique,
+    }
+}
+
+#[derive(JSTraceable, MallocSizeOf)]
+#[must_root]
+pub enum IDBCursorSource {
+    ObjectStore(Dom<IDBObjectStore>),
+    Index(Dom<IDBIndex>),
+}
+
+/// <https://w3c.github.io/IndexedDB/#cursor-construct>
+#[dom_struct]
+pub struct IDBCursor {
+    reflector_: Reflector,
+    source: IDBCursorSource,
+    direction: IDBCursorDirection,
+    range: IndexedDBKeyRange,
+    /// The key of the current record in the source.
+    key: DomRefCell<Option<IndexedDBKey>>,
+    /// The key of the current record in the object store.
+    primary_key: DomRefCell<Option<IndexedDBKey>>,
+    request: Dom<IDBRequest>,
+    got_value: Cell<bool>,
+}
+
+impl IDBCursor {
+    pub fn new_inherited(source: IDBObjectStoreOrIDBIndex,
+                         direction: IDBCursorDirection,
+                         range: IndexedDBKeyRange,
+                         request: &IDBRequest)
+                         -> IDBCursor {
+        IDBCursor {
+            reflector_: Reflector::new(),
+            source: match source {
+                IDBObjectStoreOrIDBIndex::IDBObjectStore(store) => IDBCursorSource::ObjectStore(Dom::from_ref(&*store)),
+                IDBObjectStoreOrIDBIndex::IDBIndex(index) => IDBCursorSource::Index(Dom::from_ref(&*index)),
+            },
+            direction: direction,
+            range: range,
+            key: DomRefCell::new(None),
+            primary_key: DomRefCell::new(None),
+            request: Dom::from_ref(request),
+            got_value: Cell::new(false),
+        }
+    }
+
+    pub fn new(global: &GlobalScope,
+               source: IDBObjectStoreOrIDBIndex,
+               direction: IDBCursorDirection,
+               range: IndexedDBKeyRange,
+               request: &IDBRequest)
+               -> DomRoot<IDBCursor> {
+        reflect_dom_object(Box::new(IDBCursor::new_inherited(source, direction, range, request)),
+                           global,
+                           IDBCursorBinding::Wrap)
+    }
+
+    /// The object store this cursor iterates over, directly or through an index.
+    pub fn object_store(&self) -> DomRoot<IDBObjectStore> {
+        match self.source {
+            IDBCursorSource::ObjectStore(ref store) => DomRoot::from_ref(&**store),
+            IDBCursorSource::Index(ref index) => index.object_store(),
+        }
+    }
+
+    fn index_name(&self) -> Option<String> {
+        match self.source {
+            IDBCursorSource::ObjectStore(_) => None,
+            IDBCursorSource::Index(ref index) => Some(index.name()),
+        }
+    }
+
+    /// Moves this cursor to a record, setting its value if it has one.
+    #[allow(unsafe_code)]
+    pub unsafe fn set_record(&self, cx: *mut JSContext, record: IndexedDBRecord, store: Option<&ObjectStoreInfo>) {
+        if let Some(cursor) = self.downcast::<IDBCursorWithValue>() {
+            rooted!(in(cx) let mut value = UndefinedValue());
+            RecordValue::new(&self.global(), &record, store).to_jsval(cx, value.handle_mut());
+            cursor.set_value(value.handle());
+        }
+        *self.key.borrow_mut() = Some(record.key);
+        *self.primary_key.borrow_mut() = Some(record.primary_key);
+        self.got_value.set(true);
+    }
+
+    /// Checks that the cursor can be moved or used to change its current record.
+    fn check_iterable(&self) -> ErrorResult {
+        let store = self.object_store();
+        if !store.transaction().is_active() {
+            return Err(Error::TransactionInactive);
+        }
+        let source_deleted = match self.source {
+            IDBCursorSource::ObjectStore(ref store) => store.info().is_none(),
+            IDBCursorSource::Index(ref index) => index.info().is_none(),
+        };
+        if source_deleted || !self.got_value.get() {
+            return Err(Error::InvalidState);
+        }
+        Ok(())
+    }
+
+    /// <https://w3c.github.io/IndexedDB/#iterate-a-cursor>
+    fn iterate(&self, range: IndexedDBKeyRange, count: u32) {
+        self.got_value.set(false);
+        let key = self.key.borrow().clone();
+        let primary_key = self.primary_key.borrow().clone();
+        let position = match (key, primary_key) {
+            (Some(key), Some(primary_key)) => Some((key, primary_key)),
+            _ => None,
+        };
+        let records = self.object_store().get_records(IndexedDBQuery {
+            index: self.index_name(),
+            range: range,
+            direction: direction_from_idl(self.direction),
+            position: position,
+            count: Some(count),
+        });
+        let result = records.map(|records| {
+            let found = records.len() == count as usize;
+            IDBRequestResult::Cursor(if found { records.into_iter().last() } else { None })
+        });
+        self.request.queue_result(result.map_err(error_name));
+    }
+
+    /// Checks that the record of the cursor can be changed.
+    fn check_writable(&self) -> Fallible<ObjectStoreInfo> {
+        let store = self.object_store();
+        let transaction = store.transaction();
+        if !transaction.is_active() {
+            return Err(Error::TransactionInactive);
+        }
+        if transaction.mode() == IDBTransactionMode::Readonly {
+            return Err(Error::ReadOnly);
+        }
+        self.check_iterable()?;
+        if !self.is::<IDBCursorWithValue>() {
+            return Err(Error::InvalidState);
+        }
+        store.info().ok_or(Error::InvalidState)
+    }
+}
+
+impl IDBCursorMethods for IDBCursor {
+    // https://w3c.github.io/IndexedDB/#dom-idbcursor-source
+    fn Source(&self) -> IDBObjectStoreOrIDBIndex {
+        match self.source {
+            IDBCursorSource::ObjectStore(ref store) => {
+                IDBObjectStoreOrIDBIndex::IDBObjectStore(DomRoot::from_ref(&**store))
+            },
+            IDBCursorSource::Index(ref index) => IDBObjectStoreOrIDBIndex::IDBIndex(DomRoot::from_ref(&**index)),
+        }
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbcursor-direction
+    fn Direction(&self) -> IDBCursorDirection {
+        self.direction
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbcursor-key
+    unsafe fn Key(&self, cx: *mut JSContext) -> JSVal {
+        rooted!(in(cx) let mut key = UndefinedValue());
+        if let Some(ref current) = *self.key.borrow() {
+            key_to_jsval(cx, current, key.handle_mut());
+        }
+        key.get()
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbcursor-primarykey
+    unsafe fn PrimaryKey(&self, cx: *mut JSContext) -> JSVal {
+        rooted!(in(cx) let mut key = UndefinedValue());
+        if let Some(ref current) = *self.primary_key.borrow() {
+            key_to_jsval(cx, current, key.handle_mut());
+        }
+        key.get()
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbcursor-request
+    fn Request(&self) -> DomRoot<IDBRequest> {
+        DomRoot::from_ref(&*self.request)
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbcursor-advance
+    fn Advance(&self, count: u32) -> ErrorResult {
+        // Step 1.
+        if count == 0 {
+            return Err(Error::Type("The count of records to advance by can't be 0".to_owned()));
+        }
+
+        // Steps 2-4.
+        self.check_iterable()?;
+
+        // Steps 5-8.
+        self.iterate(self.range.clone(), count);
+        Ok(())
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbcursor-continue
+    unsafe fn Continue(&self, cx: *mut JSContext, key: HandleValue) -> ErrorResult {
+        // Steps 1-3.
+        self.check_iterable()?;
+
+        // Step 4.
+        let mut range = self.range.clone();
+        if !key.is_undefined() {
+            let key = convert_value_to_key(cx, key, &mut vec![])?;
+            let current = self.key.borrow().clone();
+            let reverse = direction_from_idl(self.direction).is_reverse();
+            if current.map_or(false, |current| if reverse { key >= current } else { key <= current }) {
+                return Err(Error::Data);
+            }
+            if reverse {
+                range.upper = Some(key);
+                range.upper_open = false;
+            } else {
+                range.lower = Some(key);
+                range.lower_open = false;
+            }
+        }
+
+        // Steps 5-8.
+        self.iterate(range, 1);
+        Ok(())
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbcursor-update
+    unsafe fn Update(&self, cx: *mut JSContext, value: HandleValue) -> Fallible<DomRoot<IDBRequest>> {
+        // Steps 1-7.
+        let info = self.check_writable()?;
+
+        // Steps 8-10.
+        let primary_key = self.primary_key.borrow().clone();
+        let source = IDBObjectStoreOrIDBIndexOrIDBCursor::IDBCursor(DomRoot::from_ref(self));
+        self.object_store().store_value(cx, source, &info, value, primary_key, true)
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbcursor-delete
+    fn Delete(&self) -> Fallible<DomRoot<IDBRequest>> {
+        // Steps 1-6.
+        self.check_writable()?;
+
+        // Steps 7-8.
+        let store = self.object_store();
+        let name = store.name();
+        let range = IndexedDBKeyRange::only(self.primary_key.borrow().clone().unwrap());
+        let transaction = store.transaction();
+        let result = transaction.operation(|sender| IndexedDBOperation::Delete(sender, name, range));
+        let source = IDBObjectStoreOrIDBIndexOrIDBCursor::IDBCursor(DomRoot::from_ref(self));
+        Ok(IDBRequest::execute(source,
+                               &transaction,
+                               result.map(|()| IDBRequestResult::Undefined).map_err(error_name)))
+    }
+}
@@ -0,0 +1,61 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::IDBCursorBinding::IDBCursorDirection;
+use dom::bindings::codegen::Bindings::IDBCursorWithValueBinding;
+use dom::bindings::codegen::Bindings::IDBCursorWithValueBinding::IDBCursorWithValueMethods;
+use dom::bindings::codegen::UnionTypes::IDBObjectStoreOrIDBIndex;
+use dom::bindings::reflector::reflect_dom_object;
+use dom::bindings::root::DomRoot;
+use dom::globalscope::GlobalScope;
+use dom::idbcursor::IDBCursor;
+use dom::idbrequest::IDBRequest;
+use dom_struct::dom_struct;
+use js::jsapi::{Heap, JSContext};
+use js::jsval::JSVal;
+use js::rust::HandleValue;
+use net_traits::indexeddb_thread::IndexedDBKeyRange;
+
+#[dom_struct]
+pub struct IDBCursorWithValue {
+    idbcursor: IDBCursor,
+    #[ignore_malloc_size_of = "Defined in rust-mozjs"]
+    value: Heap<JSVal>,
+}
+
+impl IDBCursorWithValue {
+    fn new_inherited(source: IDBObjectStoreOrIDBIndex,
+                     direction: IDBCursorDirection,
+                     range: IndexedDBKeyRange,
+                     request: &IDBRequest)
+                     -> IDBCursorWithValue {
+        IDBCursorWithValue {
+            idbcursor: IDBCursor::new_inherited(source, direction, range, request),
+            value: Heap::default(),
+        }
+    }
+
+    pub fn new(global: &GlobalScope,
+               source: IDBObjectStoreOrIDBIndex,
+               direction: IDBCursorDirection,
+               range: IndexedDBKeyRange,
+               request: &IDBRequest)
+               -> DomRoot<IDBCursorWithValue> {
+        reflect_dom_object(Box::new(IDBCursorWithValue::new_inherited(source, direction, range, request)),
+                           global,
+                           IDBCursorWithValueBinding::Wrap)
+    }
+
+    pub fn set_value(&self, value: HandleValue) {
+        self.value.set(value.get());
+    }
+}
+
+impl IDBCursorWithValueMethods for IDBCursorWithValue {
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbcursorwithvalue-value
+    unsafe fn Value(&self, _cx: *mut JSContext) -> JSVal {
+        self.value.get()
+    }
+}
@@ -0,0 +1,331 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::cell::DomRefCell;
+use dom::bindings::codegen::Bindings::IDBDatabaseBinding;
+use dom::bindings::codegen::Bindings::IDBDatabaseBinding::{IDBDatabaseMethods, IDBObjectStoreParameters};
+use dom::bindings::codegen::Bindings::IDBDatabaseBinding::IDBTransactionMode;
+use dom::bindings::codegen::UnionTypes::StringOrStringSequence;
+use dom::bindings::error::{Error, ErrorResult, Fallible};
+use dom::bindings::inheritance::Castable;
+use dom::bindings::refcounted::Trusted;
+use dom::bindings::reflector::{DomObject, reflect_dom_object};
+use dom::bindings::root::{DomRoot, MutNullableDom};
+use dom::bindings::str::DOMString;
+use dom::domstringlist::DOMStringList;
+use dom::event::{Event, EventBubbles, EventCancelable};
+use dom::eventtarget::EventTarget;
+use dom::globalscope::GlobalScope;
+use dom::idbobjectstore::IDBObjectStore;
+use dom::idbtransaction::IDBTransaction;
+use dom::idbversionchangeevent::IDBVersionChangeEvent;
+use dom_struct::dom_struct;
+use indexed_db::{key_path_from_idl, to_error};
+use ipc_channel::ipc;
+use ipc_channel::router::ROUTER;
+use net_traits::IpcSend;
+use net_traits::indexeddb_thread::{IndexedDBConnectionEvent, IndexedDBKeyPath, IndexedDBOperation};
+use net_traits::indexeddb_thread::{IndexedDBThreadMsg, ObjectStoreInfo};
+use profile_traits::ipc as profile_ipc;
+use std::cell::Cell;
+use task_source::{TaskSource, TaskSourceName};
+
+/// <https://w3c.github.io/IndexedDB/#connection>
+#[dom_struct]
+pub struct IDBDatabase {
+    eventtarget: EventTarget,
+    /// The identifier of the connection in the IndexedDB thread.
+    id: u64,
+    name: DOMString,
+    version: Cell<u64>,
+    /// The object stores of the database, as of the last change made through this
+    /// connection.
+    object_stores: DomRefCell<Vec<ObjectStoreInfo>>,
+    closed: Cell<bool>,
+    upgrade_transaction: MutNullableDom<IDBTransaction>,
+}
+
+impl IDBDatabase {
+    fn new_inherited(id: u64, name: DOMString, version: u64) -> IDBDatabase {
+        IDBDatabase {
+            eventtarget: EventTarget::new_inherited(),
+            id: id,
+            name: name,
+            version: Cell::new(version),
+            object_stores: DomRefCell::new(vec![]),
+            closed: Cell::new(false),
+            upgrade_transaction: Default::default(),
+        }
+    }
+
+    /// Opens a connection to the named database, which the IndexedDB thread keeps track of
+    /// until it is closed.
+    pub fn new(global: &GlobalScope, name: DOMString, version: u64) -> Fallible<DomRoot<IDBDatabase>> {
+        let (events_sender, events_receiver) = ipc::channel().unwrap();
+        let (sender, receiver) = profile_ipc::channel(global.time_profiler_chan().clone()).unwrap();
+        let msg = IndexedDBThreadMsg::OpenConnection(sender,
+                                                     global.get_url(),
+                                                     String::from(name.clone()),
+                                                     events_sender);
+        global.resource_threads().send(msg).map_err(|_| Error::Unknown)?;
+        let id = receiver.recv().map_err(|_| Error::Unknown)?;
+
+        let database = reflect_dom_object(Box::new(IDBDatabase::new_inherited(id, name, version)),
+                                          global,
+                                          IDBDatabaseBinding::Wrap);
+        if let Err(error) = database.reload_object_stores() {
+            database.close();
+            return Err(error);
+        }
+
+        let trusted_database = Trusted::new(&*database);
+        let task_source = global.database_access_task_source();
+        let canceller = global.task_canceller(TaskSourceName::DatabaseAccess);
+        let resource_threads = global.resource_threads().clone();
+        ROUTER.add_route(events_receiver.to_opaque(), Box::new(move |message| {
+            let IndexedDBConnectionEvent::VersionChange(old_version, new_version) = message.to().unwrap();
+            let database = trusted_database.clone();
+            let result = task_source.queue_with_canceller(
+                task!(fire_versionchange: move || {
+                    database.root().fire_version_change(old_version, new_version);
+                }),
+                &canceller,
+            );
+            // The global of the connection went away, which closes it.
+            if result.is_err() {
+                let _ = resource_threads.send(IndexedDBThreadMsg::CloseConnection(id));
+            }
+        }));
+        Ok(database)
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn name(&self) -> String {
+        String::from(self.name.clone())
+    }
+
+    pub fn set_version(&self, version: u64) {
+        self.version.set(version);
+    }
+
+    pub fn set_upgrade_transaction(&self, transaction: Option<&IDBTransaction>) {
+        self.upgrade_transaction.set(transaction);
+    }
+
+    /// Reads the object stores of the database again, after they were created or
+    /// deleted by a transaction that aborted.
+    pub fn reload_object_stores(&self) -> ErrorResult {
+        let global = self.global();
+        let (sender, receiver) = profile_ipc::channel(global.time_profiler_chan().clone()).unwrap();
+        let msg = IndexedDBThreadMsg::ObjectStores(sender, global.get_url(), self.name());
+        global.resource_threads().send(msg).map_err(|_| Error::Unknown)?;
+        *self.object_stores.borrow_mut() = receiver.recv().map_err(|_| Error::Unknown)?;
+        Ok(())
+    }
+
+    pub fn object_store_info(&self, name: &str) -> Option<ObjectStoreInfo> {
+        self.object_stores.borrow().iter().find(|store| store.name == name).cloned()
+    }
+
+    pub fn update_object_store_info(&self, info: ObjectStoreInfo) {
+        let mut object_stores = self.object_stores.borrow_mut();
+        if let Some(store) = object_stores.iter_mut().find(|store| store.name == info.name) {
+            *store = info;
+        }
+    }
+
+    /// The names of the object stores, sorted.
+    pub fn object_store_names(&self) -> Vec<DOMString> {
+        let mut names: Vec<DOMString> = self.object_stores.borrow().iter()
+                                            .map(|store| DOMString::from(store.name.clone()))
+                                            .collect();
+        names.sort();
+        names
+    }
+
+    /// <https://w3c.github.io/IndexedDB/#close-a-database-connection>
+    pub fn close(&self) {
+        if self.closed.get() {
+            return;
+        }
+        self.closed.set(true);
+        let msg = IndexedDBThreadMsg::CloseConnection(self.id);
+        let _ = self.global().resource_threads().send(msg);
+    }
+
+    /// Fires a `versionchange` event at this connection, unless it is closing, before
+    /// another connection changes the version of the database.
+    ///
+    /// <https://w3c.github.io/IndexedDB/#open-a-database> Step 10.2.
+    fn fire_version_change(&self, old_version: u64, new_version: Option<u64>) {
+        let global = self.global();
+        if !self.closed.get() {
+            let event = IDBVersionChangeEvent::new(&global,
+                                                   atom!("versionchange"),
+                                                   EventBubbles::DoesNotBubble,
+                                                   EventCancelable::NotCancelable,
+                                                   old_version,
+                                                   new_version);
+            event.upcast::<Event>().fire(self.upcast());
+        }
+        let msg = IndexedDBThreadMsg::VersionChangeFired(self.id);
+        let _ = global.resource_threads().send(msg);
+    }
+
+    /// The upgrade transaction of this connection, if it is active.
+    fn active_upgrade_transaction(&self) -> Fallible<DomRoot<IDBTransaction>> {
+        let transaction = self.upgrade_transaction.get().ok_or(Error::InvalidState)?;
+        if !transaction.is_active() {
+            return Err(Error::TransactionInactive);
+        }
+        Ok(transaction)
+    }
+}
+
+impl IDBDatabaseMethods for IDBDatabase {
+    // https://w3c.github.io/IndexedDB/#dom-idbdatabase-name
+    fn Name(&self) -> DOMString {
+        self.name.clone()
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbdatabase-version
+    fn Version(&self) -> u64 {
+        self.version.get()
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbdatabase-objectstorenames
+    fn ObjectStoreNames(&self) -> DomRoot<DOMStringList> {
+        DOMStringList::new(&self.global(), self.object_store_names())
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbdatabase-transaction
+    fn Transaction(&self,
+                   store_names: StringOrStringSequence,
+                   mode: IDBTransactionMode)
+                   -> Fallible<DomRoot<IDBTransaction>> {
+        // Step 1.
+        if self.upgrade_transaction.get().is_some() {
+            return Err(Error::InvalidState);
+        }
+
+        // Step 2.
+        if self.closed.get() {
+            return Err(Error::InvalidState);
+        }
+
+        // Step 3.
+        let mut scope = match store_names {
+            StringOrStringSequence::String(name) => vec![name],
+            StringOrStringSequence::StringSequence(names) => names,
+        };
+        scope.sort();
+        scope.dedup();
+
+        // Step 4.
+        if scope.iter().any(|name| self.object_store_info(name).is_none()) {
+            return Err(Error::NotFound);
+        }
+
+        // Step 5.
+        if scope.is_empty() {
+            return Err(Error::InvalidAccess);
+        }
+
+        // Step 6.
+        if mode == IDBTransactionMode::Versionchange {
+            return Err(Error::Type("Version change transactions can't be created explicitly".to_owned()));
+        }
+
+        // Steps 7-9.
+        IDBTransaction::new(&self.global(), self, mode, scope)
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbdatabase-close
+    fn Close(&self) {
+        self.close();
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbdatabase-createobjectstore
+    fn CreateObjectStore(&self,
+                         name: DOMString,
+                         options: &IDBObjectStoreParameters)
+                         -> Fallible<DomRoot<IDBObjectStore>> {
+        // Steps 1-4.
+        let transaction = self.active_upgrade_transaction()?;
+
+        // Steps 5-6.
+        let key_path = match options.keyPath {
+            Some(ref key_path) => Some(key_path_from_idl(key_path)?),
+            None => None,
+        };
+
+        // Step 7.
+        if self.object_store_info(&name).is_some() {
+            return Err(Error::Constraint);
+        }
+
+        // Steps 8-9.
+        let auto_increment = options.autoIncrement;
+        let has_empty_or_sequence_key_path = match key_path {
+            Some(IndexedDBKeyPath::String(ref path)) => path.is_empty(),
+            Some(IndexedDBKeyPath::Sequence(_)) => true,
+            None => false,
+        };
+        if auto_increment && has_empty_or_sequence_key_path {
+            return Err(Error::InvalidAccess);
+        }
+
+        // Step 10.
+        let store_name = String::from(name.clone());
+        let result = transaction.operation(|sender| {
+            IndexedDBOperation::CreateObjectStore(sender, store_name.clone(), key_path.clone(), auto_increment)
+        });
+        result.map_err(to_error)?;
+        self.object_stores.borrow_mut().push(ObjectStoreInfo {
+            name: store_name,
+            key_path: key_path,
+            auto_increment: auto_increment,
+            indexes: vec![],
+        });
+        transaction.add_to_scope(name.clone());
+
+        // Step 11.
+        Ok(IDBObjectStore::new(&self.global(), name, &transaction))
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbdatabase-deleteobjectstore
+    fn DeleteObjectStore(&self, name: DOMString) -> ErrorResult {
+        // Steps 1-4.
+        let transaction = self.active_upgrade_transaction()?;
+
+        // Step 5.
+        if self.object_store_info(&name).is_none() {
+            return Err(Error::NotFound);
+        }
+
+        // Step 6.
+        let store_name = String::from(name.clone());
+        let result = transaction.operation(|sender| {
+            IndexedDBOperation::DeleteObjectStore(sender, store_name.clone())
+        });
+        result.map_err(to_error)?;
+        self.object_stores.borrow_mut().retain(|store| store.name != store_name);
+        Ok(())
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbdatabase-onabort
+    event_handler!(abort, GetOnabort, SetOnabort);
+
+    // https://w3c.github.io/IndexedDB/#dom-idbdatabase-onclose
+    event_handler!(close, GetOnclose, SetOnclose);
+
+    // https://w3c.github.io/IndexedDB/#dom-idbdatabase-onerror
+    event_handler!(error, GetOnerror, SetOnerror);
+
+    // https://w3c.github.io/IndexedDB/#dom-idbdatabase-onversionchange
+    event_handler!(versionchange, GetOnversionchange, SetOnversionchange);
+}
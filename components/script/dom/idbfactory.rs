@@ -0,0 +1,106 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::IDBFactoryBinding;
+use dom::bindings::codegen::Bindings::IDBFactoryBinding::IDBFactoryMethods;
+use dom::bindings::error::{Error, Fallible};
+use dom::bindings::refcounted::Trusted;
+use dom::bindings::reflector::{DomObject, Reflector, reflect_dom_object};
+use dom::bindings::root::DomRoot;
+use dom::bindings::str::DOMString;
+use dom::globalscope::GlobalScope;
+use dom::idbopendbrequest::IDBOpenDBRequest;
+use dom_struct::dom_struct;
+use indexed_db::convert_value_to_key;
+use js::jsapi::JSContext;
+use js::rust::HandleValue;
+use std::cmp::Ordering;
+use task_source::TaskSource;
+
+#[dom_struct]
+pub struct IDBFactory {
+    reflector_: Reflector,
+}
+
+impl IDBFactory {
+    fn new_inherited() -> IDBFactory {
+        IDBFactory {
+            reflector_: Reflector::new(),
+        }
+    }
+
+    pub fn new(global: &GlobalScope) -> DomRoot<IDBFactory> {
+        reflect_dom_object(Box::new(IDBFactory::new_inherited()), global, IDBFactoryBinding::Wrap)
+    }
+}
+
+impl IDBFactoryMethods for IDBFactory {
+    // https://w3c.github.io/IndexedDB/#dom-idbfactory-open
+    fn Open(&self, name: DOMString, version: Option<u64>) -> Fallible<DomRoot<IDBOpenDBRequest>> {
+        // Step 1.
+        if version == Some(0) {
+            return Err(Error::Type("The version of a database can't be 0".to_owned()));
+        }
+
+        // Steps 2-3.
+        let global = self.global();
+        if !global.origin().is_tuple() {
+            return Err(Error::Security);
+        }
+
+        // Step 4.
+        let request = IDBOpenDBRequest::new(&global);
+
+        // Step 5.
+        let trusted_request = Trusted::new(&*request);
+        let _ = global.database_access_task_source().queue(
+            task!(open_indexeddb_database: move || {
+                trusted_request.root().open_database(name, version);
+            }),
+            &global,
+        );
+
+        // Step 6.
+        Ok(request)
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbfactory-deletedatabase
+    fn DeleteDatabase(&self, name: DOMString) -> Fallible<DomRoot<IDBOpenDBRequest>> {
+        // Steps 1-2.
+        let global = self.global();
+        if !global.origin().is_tuple() {
+            return Err(Error::Security);
+        }
+
+        // Step 3.
+        let request = IDBOpenDBRequest::new(&global);
+
+        // Step 4.
+        let trusted_request = Trusted::new(&*request);
+        let _ = global.database_access_task_source().queue(
+            task!(delete_indexeddb_database: move || {
+                trusted_request.root().delete_database(name);
+            }),
+            &global,
+        );
+
+        // Step 5.
+        Ok(request)
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbfactory-cmp
+    unsafe fn Cmp(&self, cx: *mut JSContext, first: HandleValue, second: HandleValue) -> Fallible<i16> {
+        // Steps 1-4.
+        let first = convert_value_to_key(cx, first, &mut vec![])?;
+        let second = convert_value_to_key(cx, second, &mut vec![])?;
+
+        // Step 5.
+        Ok(match first.cmp(&second) {
+            Ordering::Less => -1,
+            Ordering::Equal => 0,
+            Ordering::Greater => 1,
+        })
+    }
+}
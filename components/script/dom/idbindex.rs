@@ -0,0 +1,185 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::IDBCursorBinding::IDBCursorDirection;
+use dom::bindings::codegen::Bindings::IDBIndexBinding;
+use dom::bindings::codegen::Bindings::IDBIndexBinding::IDBIndexMethods;
+use dom::bindings::codegen::UnionTypes::{IDBObjectStoreOrIDBIndex, IDBObjectStoreOrIDBIndexOrIDBCursor};
+use dom::bindings::error::{Error, Fallible};
+use dom::bindings::reflector::{Reflector, reflect_dom_object};
+use dom::bindings::root::{Dom, DomRoot};
+use dom::bindings::str::DOMString;
+use dom::globalscope::GlobalScope;
+use dom::idbobjectstore::IDBObjectStore;
+use dom::idbrequest::{IDBRequest, IDBRequestResult};
+use dom_struct::dom_struct;
+use indexed_db::{convert_value_to_key_range, key_path_to_jsval};
+use js::jsapi::JSContext;
+use js::jsval::{JSVal, NullValue};
+use js::rust::HandleValue;
+use net_traits::indexeddb_thread::IndexInfo;
+
+#[dom_struct]
+pub struct IDBIndex {
+    reflector_: Reflector,
+    object_store: Dom<IDBObjectStore>,
+    name: DOMString,
+}
+
+impl IDBIndex {
+    fn new_inherited(object_store: &IDBObjectStore, name: DOMString) -> IDBIndex {
+        IDBIndex {
+            reflector_: Reflector::new(),
+            object_store: Dom::from_ref(object_store),
+            name: name,
+        }
+    }
+
+    pub fn new(global: &GlobalScope, object_store: &IDBObjectStore, name: DOMString) -> DomRoot<IDBIndex> {
+        reflect_dom_object(Box::new(IDBIndex::new_inherited(object_store, name)),
+                           global,
+                           IDBIndexBinding::Wrap)
+    }
+
+    pub fn name(&self) -> String {
+        String::from(self.name.clone())
+    }
+
+    pub fn object_store(&self) -> DomRoot<IDBObjectStore> {
+        DomRoot::from_ref(&*self.object_store)
+    }
+
+    /// The description of this index, or `None` if it or its object store was deleted.
+    pub fn info(&self) -> Option<IndexInfo> {
+        self.object_store.info().and_then(|store| {
+            store.indexes.into_iter().find(|index| index.name == *self.name)
+        })
+    }
+
+    /// Checks that requests can be placed against this index.
+    fn check_active(&self) -> Fallible<()> {
+        if self.info().is_none() {
+            return Err(Error::InvalidState);
+        }
+        self.object_store.check_active().map(|_| ())
+    }
+
+    fn source(&self) -> IDBObjectStoreOrIDBIndexOrIDBCursor {
+        IDBObjectStoreOrIDBIndexOrIDBCursor::IDBIndex(DomRoot::from_ref(self))
+    }
+}
+
+impl IDBIndexMethods for IDBIndex {
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-name
+    fn Name(&self) -> DOMString {
+        self.name.clone()
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-objectstore
+    fn ObjectStore(&self) -> DomRoot<IDBObjectStore> {
+        self.object_store()
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-keypath
+    unsafe fn KeyPath(&self, cx: *mut JSContext) -> JSVal {
+        rooted!(in(cx) let mut key_path = NullValue());
+        if let Some(info) = self.info() {
+            key_path_to_jsval(cx, &info.key_path, key_path.handle_mut());
+        }
+        key_path.get()
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-multientry
+    fn MultiEntry(&self) -> bool {
+        self.info().map_or(false, |info| info.multi_entry)
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-unique
+    fn Unique(&self) -> bool {
+        self.info().map_or(false, |info| info.unique)
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-get
+    unsafe fn Get(&self, cx: *mut JSContext, query: HandleValue) -> Fallible<DomRoot<IDBRequest>> {
+        self.check_active()?;
+        let range = convert_value_to_key_range(cx, query, true)?;
+        Ok(self.object_store.query(self.source(), Some(self.name()), range, Some(1), |records| {
+            IDBRequestResult::Value(records.into_iter().next())
+        }))
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-getkey
+    unsafe fn GetKey(&self, cx: *mut JSContext, query: HandleValue) -> Fallible<DomRoot<IDBRequest>> {
+        self.check_active()?;
+        let range = convert_value_to_key_range(cx, query, true)?;
+        Ok(self.object_store.query(self.source(), Some(self.name()), range, Some(1), |records| {
+            IDBRequestResult::Key(records.into_iter().next().map(|record| record.primary_key))
+        }))
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-getall
+    unsafe fn GetAll(&self,
+                     cx: *mut JSContext,
+                     query: HandleValue,
+                     count: Option<u32>)
+                     -> Fallible<DomRoot<IDBRequest>> {
+        self.check_active()?;
+        let range = convert_value_to_key_range(cx, query, false)?;
+        let count = count.and_then(|count| if count == 0 { None } else { Some(count) });
+        Ok(self.object_store.query(self.source(), Some(self.name()), range, count, IDBRequestResult::Values))
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-getallkeys
+    unsafe fn GetAllKeys(&self,
+                         cx: *mut JSContext,
+                         query: HandleValue,
+                         count: Option<u32>)
+                         -> Fallible<DomRoot<IDBRequest>> {
+        self.check_active()?;
+        let range = convert_value_to_key_range(cx, query, false)?;
+        let count = count.and_then(|count| if count == 0 { None } else { Some(count) });
+        Ok(self.object_store.query(self.source(), Some(self.name()), range, count, |records| {
+            IDBRequestResult::Keys(records.into_iter().map(|record| record.primary_key).collect())
+        }))
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-count
+    unsafe fn Count(&self, cx: *mut JSContext, query: HandleValue) -> Fallible<DomRoot<IDBRequest>> {
+        self.check_active()?;
+        let range = convert_value_to_key_range(cx, query, false)?;
+        Ok(self.object_store.count(self.source(), Some(self.name()), range))
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-opencursor
+    unsafe fn OpenCursor(&self,
+                         cx: *mut JSContext,
+                         query: HandleValue,
+                         direction: IDBCursorDirection)
+                         -> Fallible<DomRoot<IDBRequest>> {
+        self.check_active()?;
+        let range = convert_value_to_key_range(cx, query, false)?;
+        let source = IDBObjectStoreOrIDBIndex::IDBIndex(DomRoot::from_ref(self));
+        Ok(self.object_store.open_cursor(source, Some(self.name()), range, direction, false))
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-openkeycursor
+    unsafe fn OpenKeyCursor(&self,
+                            cx: *mut JSContext,
+                            query: HandleValue,
+                            direction: IDBCursorDirection)
+                            -> Fallible<DomRoot<IDBRequest>> {
+        self.check_active()?;
+        let range = convert_value_to_key_range(cx, query, false)?;
+        let source = IDBObjectStoreOrIDBIndex::IDBIndex(DomRoot::from_ref(self));
+        Ok(self.object_store.open_cursor(source, Some(self.name()), range, direction, true))
+    }
+}
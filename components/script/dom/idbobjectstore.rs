@@ -0,0 +1,545 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::IDBCursorBinding::IDBCursorDirection;
+use dom::bindings::codegen::Bindings::IDBDatabaseBinding::IDBTransactionMode;
+use dom::bindings::codegen::Bindings::IDBObjectStoreBinding;
+use dom::bindings::codegen::Bindings::IDBObjectStoreBinding::{IDBIndexParameters, IDBObjectStoreMethods};
+use dom::bindings::codegen::UnionTypes::{IDBObjectStoreOrIDBIndex, IDBObjectStoreOrIDBIndexOrIDBCursor};
+use dom::bindings::codegen::UnionTypes::StringOrStringSequence;
+use dom::bindings::error::{Error, ErrorResult, Fallible};
+use dom::bindings::reflector::{DomObject, Reflector, reflect_dom_object};
+use dom::bindings::root::{Dom, DomRoot};
+use dom::bindings::str::DOMString;
+use dom::bindings::structuredclone::StructuredCloneData;
+use dom::domstringlist::DOMStringList;
+use dom::globalscope::GlobalScope;
+use dom::idbcursor::{IDBCursor, direction_from_idl};
+use dom::idbcursorwithvalue::IDBCursorWithValue;
+use dom::idbindex::IDBIndex;
+use dom::idbrequest::{IDBRequest, IDBRequestResult};
+use dom::idbtransaction::IDBTransaction;
+use dom_struct::dom_struct;
+use indexed_db::{can_inject_key, convert_value_to_key, convert_value_to_key_range, error_name};
+use indexed_db::{extract_key, index_keys, key_path_from_idl, key_path_to_jsval, to_error};
+use js::jsapi::JSContext;
+use js::jsval::{JSVal, NullValue, UndefinedValue};
+use js::rust::HandleValue;
+use net_traits::indexeddb_thread::{IndexInfo, IndexedDBCursorDirection, IndexedDBError, IndexedDBKey};
+use net_traits::indexeddb_thread::{IndexedDBKeyPath, IndexedDBKeyRange, IndexedDBOperation};
+use net_traits::indexeddb_thread::{IndexedDBQuery, IndexedDBRecord, ObjectStoreInfo};
+
+#[dom_struct]
+pub struct IDBObjectStore {
+    reflector_: Reflector,
+    name: DOMString,
+    transaction: Dom<IDBTransaction>,
+}
+
+impl IDBObjectStore {
+    fn new_inherited(name: DOMString, transaction: &IDBTransaction) -> IDBObjectStore {
+        IDBObjectStore {
+            reflector_: Reflector::new(),
+            name: name,
+            transaction: Dom::from_ref(transaction),
+        }
+    }
+
+    pub fn new(global: &GlobalScope, name: DOMString, transaction: &IDBTransaction) -> DomRoot<IDBObjectStore> {
+        reflect_dom_object(Box::new(IDBObjectStore::new_inherited(name, transaction)),
+                           global,
+                           IDBObjectStoreBinding::Wrap)
+    }
+
+    pub fn name(&self) -> String {
+        String::from(self.name.clone())
+    }
+
+    pub fn transaction(&self) -> DomRoot<IDBTransaction> {
+        DomRoot::from_ref(&*self.transaction)
+    }
+
+    /// The description of this object store, or `None` if it was deleted.
+    pub fn info(&self) -> Option<ObjectStoreInfo> {
+        self.transaction.db().object_store_info(&self.name)
+    }
+
+    /// Checks that requests can be placed against this object store.
+    pub fn check_active(&self) -> Fallible<ObjectStoreInfo> {
+        let info = self.info().ok_or(Error::InvalidState)?;
+        if !self.transaction.is_active() {
+            return Err(Error::TransactionInactive);
+        }
+        Ok(info)
+    }
+
+    /// Checks that the records of this object store can be modified.
+    pub fn check_writable(&self) -> Fallible<ObjectStoreInfo> {
+        let info = self.check_active()?;
+        if self.transaction.mode() == IDBTransactionMode::Readonly {
+            return Err(Error::ReadOnly);
+        }
+        Ok(info)
+    }
+
+    /// Retrieves the records of this object store, or of one of its indexes.
+    pub fn get_records(&self, query: IndexedDBQuery) -> Result<Vec<IndexedDBRecord>, IndexedDBError> {
+        let name = self.name();
+        self.transaction.operation(|sender| IndexedDBOperation::Get(sender, name, query))
+    }
+
+    /// Runs a query and makes a request whose result is derived from the records found.
+    pub fn query(&self,
+                 source: IDBObjectStoreOrIDBIndexOrIDBCursor,
+                 index: Option<String>,
+                 range: IndexedDBKeyRange,
+                 count: Option<u32>,
+                 result: fn(Vec<IndexedDBRecord>) -> IDBRequestResult)
+                 -> DomRoot<IDBRequest> {
+        let records = self.get_records(IndexedDBQuery {
+            index: index,
+            range: range,
+            direction: IndexedDBCursorDirection::Next,
+            position: None,
+            count: count,
+        });
+        IDBRequest::execute(source, &self.transaction, records.map(result).map_err(error_name))
+    }
+
+    /// Makes a request counting the records of this object store or of one of its indexes.
+    pub fn count(&self,
+                 source: IDBObjectStoreOrIDBIndexOrIDBCursor,
+                 index: Option<String>,
+                 range: IndexedDBKeyRange)
+                 -> DomRoot<IDBRequest> {
+        let name = self.name();
+        let count = self.transaction.operation(|sender| IndexedDBOperation::Count(sender, name, index, range));
+        IDBRequest::execute(source, &self.transaction, count.map(IDBRequestResult::Count).map_err(error_name))
+    }
+
+    /// Makes a request iterating over the records of this object store or of one of its
+    /// indexes with a new cursor.
+    ///
+    /// <https://w3c.github.io/IndexedDB/#dom-idbobjectstore-opencursor> Steps 5-10.
+    pub fn open_cursor(&self,
+                       source: IDBObjectStoreOrIDBIndex,
+                       index: Option<String>,
+                       range: IndexedDBKeyRange,
+                       direction: IDBCursorDirection,
+                       key_only: bool)
+                       -> DomRoot<IDBRequest> {
+        let global = self.global();
+        let request_source = match source {
+            IDBObjectStoreOrIDBIndex::IDBObjectStore(ref store) => {
+                IDBObjectStoreOrIDBIndexOrIDBCursor::IDBObjectStore(store.clone())
+            },
+            IDBObjectStoreOrIDBIndex::IDBIndex(ref index) => {
+                IDBObjectStoreOrIDBIndexOrIDBCursor::IDBIndex(index.clone())
+            },
+        };
+        let request = IDBRequest::new(&global, Some(request_source), Some(&self.transaction));
+        let cursor = if key_only {
+            IDBCursor::new(&global, source, direction, range.clone(), &request)
+        } else {
+            DomRoot::upcast(IDBCursorWithValue::new(&global, source, direction, range.clone(), &request))
+        };
+        request.set_cursor(&cursor);
+
+        let records = self.get_records(IndexedDBQuery {
+            index: index,
+            range: range,
+            direction: direction_from_idl(direction),
+            position: None,
+            count: Some(1),
+        });
+        request.queue_result(records.map(|records| IDBRequestResult::Cursor(records.into_iter().next()))
+                                    .map_err(error_name));
+        request
+    }
+
+    /// Stores a value in this object store, under `key` if the object store uses
+    /// out-of-line keys. For object stores with in-line keys, `key` is only given when
+    /// updating a record through a cursor, and it must match the key of the value.
+    ///
+    /// <https://w3c.github.io/IndexedDB/#add-or-put> Steps 9-13.
+    #[allow(unsafe_code)]
+    pub unsafe fn store_value(&self,
+                              cx: *mut JSContext,
+                              source: IDBObjectStoreOrIDBIndexOrIDBCursor,
+                              info: &ObjectStoreInfo,
+                              value: HandleValue,
+                              key: Option<IndexedDBKey>,
+                              overwrite: bool)
+                              -> Fallible<DomRoot<IDBRequest>> {
+        // Steps 9-10.
+        let data = StructuredCloneData::write(cx, value)?.move_to_arraybuffer();
+        rooted!(in(cx) let mut clone = UndefinedValue());
+        StructuredCloneData::Vector(data.clone()).read(&self.global(), clone.handle_mut());
+
+        // Step 11.
+        let key = match info.key_path {
+            Some(ref key_path) => {
+                match extract_key(cx, clone.handle(), key_path, false)? {
+                    Some(extracted) => {
+                        if key.map_or(false, |key| key != extracted) {
+                            return Err(Error::Data);
+                        }
+                        Some(extracted)
+                    },
+                    None => {
+                        if !info.auto_increment {
+                            return Err(Error::Data);
+                        }
+                        match *key_path {
+                            IndexedDBKeyPath::String(ref path) => {
+                                if !can_inject_key(cx, clone.handle(), path)? {
+                                    return Err(Error::Data);
+                                }
+                            },
+                            IndexedDBKeyPath::Sequence(_) => return Err(Error::Data),
+                        }
+                        None
+                    },
+                }
+            },
+            None => key,
+        };
+
+        let mut keys = vec![];
+        for index in &info.indexes {
+            keys.push((index.name.clone(), index_keys(cx, clone.handle(), index)?));
+        }
+
+        // Steps 12-13.
+        let name = self.name();
+        let result = self.transaction.operation(|sender| {
+            IndexedDBOperation::Put(sender, name, key, data, keys, overwrite)
+        });
+        Ok(IDBRequest::execute(source,
+                               &self.transaction,
+                               result.map(|key| IDBRequestResult::Key(Some(key))).map_err(error_name)))
+    }
+
+    /// <https://w3c.github.io/IndexedDB/#add-or-put>
+    #[allow(unsafe_code)]
+    unsafe fn add_or_put(&self,
+                         cx: *mut JSContext,
+                         value: HandleValue,
+                         key: HandleValue,
+                         overwrite: bool)
+                         -> Fallible<DomRoot<IDBRequest>> {
+        // Steps 1-5.
+        let info = self.check_writable()?;
+
+        // Step 6.
+        if info.key_path.is_some() && !key.is_undefined() {
+            return Err(Error::Data);
+        }
+
+        // Step 7.
+        if info.key_path.is_none() && !info.auto_increment && key.is_undefined() {
+            return Err(Error::Data);
+        }
+
+        // Step 8.
+        let key = if key.is_undefined() {
+            None
+        } else {
+            Some(convert_value_to_key(cx, key, &mut vec![])?)
+        };
+
+        // Steps 9-13.
+        self.store_value(cx, self.source(), &info, value, key, overwrite)
+    }
+
+    fn source(&self) -> IDBObjectStoreOrIDBIndexOrIDBCursor {
+        IDBObjectStoreOrIDBIndexOrIDBCursor::IDBObjectStore(DomRoot::from_ref(self))
+    }
+}
+
+impl IDBObjectStoreMethods for IDBObjectStore {
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-name
+    fn Name(&self) -> DOMString {
+        self.name.clone()
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-keypath
+    unsafe fn KeyPath(&self, cx: *mut JSContext) -> JSVal {
+        rooted!(in(cx) let mut key_path = NullValue());
+        if let Some(ObjectStoreInfo { key_path: Some(ref path), .. }) = self.info() {
+            key_path_to_jsval(cx, path, key_path.handle_mut());
+        }
+        key_path.get()
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-indexnames
+    fn IndexNames(&self) -> DomRoot<DOMStringList> {
+        let mut names: Vec<DOMString> = self.info().map_or(vec![], |info| {
+            info.indexes.into_iter().map(|index| DOMString::from(index.name)).collect()
+        });
+        names.sort();
+        DOMStringList::new(&self.global(), names)
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-transaction
+    fn Transaction(&self) -> DomRoot<IDBTransaction> {
+        self.transaction()
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-autoincrement
+    fn AutoIncrement(&self) -> bool {
+        self.info().map_or(false, |info| info.auto_increment)
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-put
+    unsafe fn Put(&self, cx: *mut JSContext, value: HandleValue, key: HandleValue) -> Fallible<DomRoot<IDBRequest>> {
+        self.add_or_put(cx, value, key, true)
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-add
+    unsafe fn Add(&self, cx: *mut JSContext, value: HandleValue, key: HandleValue) -> Fallible<DomRoot<IDBRequest>> {
+        self.add_or_put(cx, value, key, false)
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-delete
+    unsafe fn Delete(&self, cx: *mut JSContext, query: HandleValue) -> Fallible<DomRoot<IDBRequest>> {
+        // Steps 1-6.
+        self.check_writable()?;
+
+        // Step 7.
+        let range = convert_value_to_key_range(cx, query, true)?;
+
+        // Step 8.
+        let name = self.name();
+        let result = self.transaction.operation(|sender| IndexedDBOperation::Delete(sender, name, range));
+        Ok(IDBRequest::execute(self.source(),
+                               &self.transaction,
+                               result.map(|()| IDBRequestResult::Undefined).map_err(error_name)))
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-clear
+    fn Clear(&self) -> Fallible<DomRoot<IDBRequest>> {
+        // Steps 1-6.
+        self.check_writable()?;
+
+        // Step 7.
+        let name = self.name();
+        let result = self.transaction.operation(|sender| IndexedDBOperation::Clear(sender, name));
+        Ok(IDBRequest::execute(self.source(),
+                               &self.transaction,
+                               result.map(|()| IDBRequestResult::Undefined).map_err(error_name)))
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-get
+    unsafe fn Get(&self, cx: *mut JSContext, query: HandleValue) -> Fallible<DomRoot<IDBRequest>> {
+        self.check_active()?;
+        let range = convert_value_to_key_range(cx, query, true)?;
+        Ok(self.query(self.source(), None, range, Some(1), |records| {
+            IDBRequestResult::Value(records.into_iter().next())
+        }))
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-getkey
+    unsafe fn GetKey(&self, cx: *mut JSContext, query: HandleValue) -> Fallible<DomRoot<IDBRequest>> {
+        self.check_active()?;
+        let range = convert_value_to_key_range(cx, query, true)?;
+        Ok(self.query(self.source(), None, range, Some(1), |records| {
+            IDBRequestResult::Key(records.into_iter().next().map(|record| record.primary_key))
+        }))
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-getall
+    unsafe fn GetAll(&self,
+                     cx: *mut JSContext,
+                     query: HandleValue,
+                     count: Option<u32>)
+                     -> Fallible<DomRoot<IDBRequest>> {
+        self.check_active()?;
+        let range = convert_value_to_key_range(cx, query, false)?;
+        let count = count.and_then(|count| if count == 0 { None } else { Some(count) });
+        Ok(self.query(self.source(), None, range, count, IDBRequestResult::Values))
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-getallkeys
+    unsafe fn GetAllKeys(&self,
+                         cx: *mut JSContext,
+                         query: HandleValue,
+                         count: Option<u32>)
+                         -> Fallible<DomRoot<IDBRequest>> {
+        self.check_active()?;
+        let range = convert_value_to_key_range(cx, query, false)?;
+        let count = count.and_then(|count| if count == 0 { None } else { Some(count) });
+        Ok(self.query(self.source(), None, range, count, |records| {
+            IDBRequestResult::Keys(records.into_iter().map(|record| record.primary_key).collect())
+        }))
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-count
+    unsafe fn Count(&self, cx: *mut JSContext, query: HandleValue) -> Fallible<DomRoot<IDBRequest>> {
+        self.check_active()?;
+        let range = convert_value_to_key_range(cx, query, false)?;
+        Ok(self.count(self.source(), None, range))
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-opencursor
+    unsafe fn OpenCursor(&self,
+                         cx: *mut JSContext,
+                         query: HandleValue,
+                         direction: IDBCursorDirection)
+                         -> Fallible<DomRoot<IDBRequest>> {
+        self.check_active()?;
+        let range = convert_value_to_key_range(cx, query, false)?;
+        let source = IDBObjectStoreOrIDBIndex::IDBObjectStore(DomRoot::from_ref(self));
+        Ok(self.open_cursor(source, None, range, direction, false))
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-openkeycursor
+    unsafe fn OpenKeyCursor(&self,
+                            cx: *mut JSContext,
+                            query: HandleValue,
+                            direction: IDBCursorDirection)
+                            -> Fallible<DomRoot<IDBRequest>> {
+        self.check_active()?;
+        let range = convert_value_to_key_range(cx, query, false)?;
+        let source = IDBObjectStoreOrIDBIndex::IDBObjectStore(DomRoot::from_ref(self));
+        Ok(self.open_cursor(source, None, range, direction, true))
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-index
+    fn Index(&self, name: DOMString) -> Fallible<DomRoot<IDBIndex>> {
+        // Steps 1-4.
+        let info = self.info().ok_or(Error::InvalidState)?;
+        if self.transaction.is_finished() {
+            return Err(Error::InvalidState);
+        }
+
+        // Step 5.
+        if !info.indexes.iter().any(|index| index.name == *name) {
+            return Err(Error::NotFound);
+        }
+
+        // Step 6.
+        Ok(IDBIndex::new(&self.global(), self, name))
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-createindex
+    fn CreateIndex(&self,
+                   name: DOMString,
+                   key_path: StringOrStringSequence,
+                   options: &IDBIndexParameters)
+                   -> Fallible<DomRoot<IDBIndex>> {
+        // Steps 1-2.
+        if self.transaction.mode() != IDBTransactionMode::Versionchange {
+            return Err(Error::InvalidState);
+        }
+
+        // Steps 3-4.
+        let mut info = self.info().ok_or(Error::InvalidState)?;
+
+        // Step 5.
+        if !self.transaction.is_active() {
+            return Err(Error::TransactionInactive);
+        }
+
+        // Step 6.
+        if info.indexes.iter().any(|index| index.name == *name) {
+            return Err(Error::Constraint);
+        }
+
+        // Step 7.
+        let key_path = key_path_from_idl(&key_path)?;
+
+        // Steps 8-9.
+        let is_sequence = match key_path {
+            IndexedDBKeyPath::Sequence(_) => true,
+            IndexedDBKeyPath::String(_) => false,
+        };
+        if is_sequence && options.multiEntry {
+            return Err(Error::InvalidAccess);
+        }
+
+        // Step 10.
+        let index = IndexInfo {
+            name: String::from(name.clone()),
+            key_path: key_path,
+            unique: options.unique,
+            multi_entry: options.multiEntry,
+        };
+        let records = self.get_records(IndexedDBQuery {
+            index: None,
+            range: IndexedDBKeyRange::default(),
+            direction: IndexedDBCursorDirection::Next,
+            position: None,
+            count: None,
+        }).map_err(to_error)?;
+        let global = self.global();
+        let cx = global.get_cx();
+        let mut keys = vec![];
+        for record in records {
+            rooted!(in(cx) let mut value = UndefinedValue());
+            StructuredCloneData::Vector(record.value).read(&global, value.handle_mut());
+            let index_keys = unsafe { index_keys(cx, value.handle(), &index)? };
+            keys.push((record.primary_key, index_keys));
+        }
+
+        // The index is created asynchronously, a failure aborts the upgrade transaction.
+        let store_name = self.name();
+        let index_info = index.clone();
+        let result = self.transaction.operation(|sender| {
+            IndexedDBOperation::CreateIndex(sender, store_name, index_info, keys)
+        });
+        if let Err(error) = result {
+            self.transaction.abort(Some(error_name(error)));
+        }
+        info.indexes.push(index);
+        self.transaction.db().update_object_store_info(info);
+
+        // Step 11.
+        Ok(IDBIndex::new(&global, self, name))
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-deleteindex
+    fn DeleteIndex(&self, name: DOMString) -> ErrorResult {
+        // Steps 1-2.
+        if self.transaction.mode() != IDBTransactionMode::Versionchange {
+            return Err(Error::InvalidState);
+        }
+
+        // Steps 3-4.
+        let mut info = self.info().ok_or(Error::InvalidState)?;
+
+        // Step 5.
+        if !self.transaction.is_active() {
+            return Err(Error::TransactionInactive);
+        }
+
+        // Step 6.
+        if !info.indexes.iter().any(|index| index.name == *name) {
+            return Err(Error::NotFound);
+        }
+
+        // Steps 7-8.
+        let store_name = self.name();
+        let index_name = String::from(name);
+        let result = self.transaction.operation(|sender| {
+            IndexedDBOperation::DeleteIndex(sender, store_name, index_name.clone())
+        });
+        result.map_err(to_error)?;
+        info.indexes.retain(|index| index.name != index_name);
+        self.transaction.db().update_object_store_info(info);
+        Ok(())
+    }
+}
@@ -0,0 +1,261 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::IDBDatabaseBinding::IDBTransactionMode;
+use dom::bindings::codegen::Bindings::IDBOpenDBRequestBinding;
+use dom::bindings::codegen::Bindings::IDBOpenDBRequestBinding::IDBOpenDBRequestMethods;
+use dom::bindings::inheritance::Castable;
+use dom::bindings::refcounted::Trusted;
+use dom::bindings::reflector::{DomObject, reflect_dom_object};
+use dom::bindings::root::DomRoot;
+use dom::bindings::str::DOMString;
+use dom::domexception::DOMErrorName;
+use dom::event::{Event, EventBubbles, EventCancelable};
+use dom::globalscope::GlobalScope;
+use dom::idbdatabase::IDBDatabase;
+use dom::idbrequest::IDBRequest;
+use dom::idbtransaction::IDBTransaction;
+use dom::idbversionchangeevent::IDBVersionChangeEvent;
+use dom_struct::dom_struct;
+use ipc_channel::ipc;
+use ipc_channel::router::ROUTER;
+use js::jsval::ObjectValue;
+use net_traits::IpcSend;
+use net_traits::indexeddb_thread::{IndexedDBBlockingStatus, IndexedDBDeletionStatus, IndexedDBThreadMsg};
+use profile_traits::ipc as profile_ipc;
+use std::cmp;
+use task_source::{TaskSource, TaskSourceName};
+
+#[dom_struct]
+pub struct IDBOpenDBRequest {
+    idbrequest: IDBRequest,
+}
+
+impl IDBOpenDBRequest {
+    fn new_inherited() -> IDBOpenDBRequest {
+        IDBOpenDBRequest {
+            idbrequest: IDBRequest::new_inherited(None, None),
+        }
+    }
+
+    pub fn new(global: &GlobalScope) -> DomRoot<IDBOpenDBRequest> {
+        reflect_dom_object(Box::new(IDBOpenDBRequest::new_inherited()),
+                           global,
+                           IDBOpenDBRequestBinding::Wrap)
+    }
+
+    fn set_connection(&self, connection: &IDBDatabase) {
+        let cx = self.global().get_cx();
+        rooted!(in(cx) let result = ObjectValue(connection.reflector().get_jsobject().get()));
+        self.upcast::<IDBRequest>().set_result(result.handle());
+    }
+
+    /// <https://w3c.github.io/IndexedDB/#open-a-database>
+    pub fn open_database(&self, name: DOMString, version: Option<u64>) {
+        let global = self.global();
+        let (sender, receiver) = profile_ipc::channel(global.time_profiler_chan().clone()).unwrap();
+        let msg = IndexedDBThreadMsg::OpenDatabase(sender, global.get_url(), String::from(name.clone()));
+        if global.resource_threads().send(msg).is_err() {
+            return self.upcast::<IDBRequest>().fire_error(DOMErrorName::UnknownError);
+        }
+        let old_version = match receiver.recv() {
+            Ok(old_version) => old_version,
+            Err(_) => return self.upcast::<IDBRequest>().fire_error(DOMErrorName::UnknownError),
+        };
+
+        // Step 4.
+        let version = version.unwrap_or(cmp::max(old_version, 1));
+
+        // Step 5.
+        if version < old_version {
+            return self.upcast::<IDBRequest>().fire_error(DOMErrorName::VersionError);
+        }
+
+        // Step 6.
+        let connection = match IDBDatabase::new(&global, name, old_version) {
+            Ok(connection) => connection,
+            Err(_) => return self.upcast::<IDBRequest>().fire_error(DOMErrorName::UnknownError),
+        };
+
+        // Step 10.
+        if version > old_version {
+            return self.wait_for_other_connections(&connection, old_version, version);
+        }
+
+        // Step 11.
+        self.set_connection(&connection);
+        let event = Event::new(&global,
+                               atom!("success"),
+                               EventBubbles::DoesNotBubble,
+                               EventCancelable::NotCancelable);
+        self.upcast::<IDBRequest>().fire_success(&event);
+    }
+
+    /// Has the IndexedDB thread fire `versionchange` events at the other open connections to
+    /// the database, and runs the upgrade transaction once all of them are closed.
+    ///
+    /// <https://w3c.github.io/IndexedDB/#open-a-database> Steps 10.1-10.5.
+    fn wait_for_other_connections(&self, connection: &IDBDatabase, old_version: u64, version: u64) {
+        let global = self.global();
+        let (sender, receiver) = ipc::channel().unwrap();
+        let trusted_request = Trusted::new(self);
+        let trusted_connection = Trusted::new(connection);
+        let task_source = global.database_access_task_source();
+        let canceller = global.task_canceller(TaskSourceName::DatabaseAccess);
+        ROUTER.add_route(receiver.to_opaque(), Box::new(move |message| {
+            let status: IndexedDBBlockingStatus = message.to().unwrap();
+            let request = trusted_request.clone();
+            let connection = trusted_connection.clone();
+            let _ = task_source.queue_with_canceller(
+                task!(indexeddb_blocking_status_changed: move || {
+                    request.root().blocking_status_changed(&connection.root(), status, old_version, version);
+                }),
+                &canceller,
+            );
+        }));
+        let msg = IndexedDBThreadMsg::WaitForOtherConnections(sender, connection.id(), version);
+        if global.resource_threads().send(msg).is_err() {
+            connection.close();
+            self.upcast::<IDBRequest>().fire_error(DOMErrorName::UnknownError);
+        }
+    }
+
+    fn blocking_status_changed(&self,
+                               connection: &IDBDatabase,
+                               status: IndexedDBBlockingStatus,
+                               old_version: u64,
+                               version: u64) {
+        match status {
+            // Step 10.4.
+            IndexedDBBlockingStatus::Blocked => {
+                let event = IDBVersionChangeEvent::new(&self.global(),
+                                                       atom!("blocked"),
+                                                       EventBubbles::DoesNotBubble,
+                                                       EventCancelable::NotCancelable,
+                                                       old_version,
+                                                       Some(version));
+                event.upcast::<Event>().fire(self.upcast());
+            },
+            // Step 10.6.
+            IndexedDBBlockingStatus::Unblocked => {
+                self.run_upgrade_transaction(connection, old_version, version)
+            },
+        }
+    }
+
+    /// <https://w3c.github.io/IndexedDB/#run-an-upgrade-transaction>
+    fn run_upgrade_transaction(&self, connection: &IDBDatabase, old_version: u64, version: u64) {
+        let global = self.global();
+
+        // Steps 2-4.
+        let transaction = match IDBTransaction::new(&global,
+                                                    connection,
+                                                    IDBTransactionMode::Versionchange,
+                                                    connection.object_store_names()) {
+            Ok(transaction) => transaction,
+            Err(_) => {
+                connection.close();
+                return self.upcast::<IDBRequest>().fire_error(DOMErrorName::UnknownError);
+            },
+        };
+        transaction.set_open_request(self, old_version);
+        connection.set_upgrade_transaction(Some(&transaction));
+
+        // Steps 5-6.
+        if transaction.set_version(version).is_err() {
+            return transaction.abort(Some(DOMErrorName::UnknownError));
+        }
+
+        // Steps 7-9.
+        let request = self.upcast::<IDBRequest>();
+        self.set_connection(connection);
+        request.set_transaction(Some(&transaction));
+
+        // Step 10.
+        let event = IDBVersionChangeEvent::new(&global,
+                                               atom!("upgradeneeded"),
+                                               EventBubbles::DoesNotBubble,
+                                               EventCancelable::NotCancelable,
+                                               old_version,
+                                               Some(version));
+        request.fire_success(event.upcast());
+    }
+
+    /// Fires the last event of this request once its upgrade transaction finished.
+    ///
+    /// <https://w3c.github.io/IndexedDB/#open-a-database> Step 10.
+    pub fn upgrade_finished(&self, connection: &IDBDatabase, committed: bool) {
+        let request = self.upcast::<IDBRequest>();
+        request.set_transaction(None);
+        if !committed {
+            connection.close();
+            return request.fire_error(DOMErrorName::AbortError);
+        }
+        let event = Event::new(&self.global(),
+                               atom!("success"),
+                               EventBubbles::DoesNotBubble,
+                               EventCancelable::NotCancelable);
+        request.fire_success(&event);
+    }
+
+    /// Has the IndexedDB thread fire `versionchange` events at the open connections to the
+    /// database, and delete it once all of them are closed.
+    ///
+    /// <https://w3c.github.io/IndexedDB/#delete-a-database>
+    pub fn delete_database(&self, name: DOMString) {
+        let global = self.global();
+        let (sender, receiver) = ipc::channel().unwrap();
+        let trusted_request = Trusted::new(self);
+        let task_source = global.database_access_task_source();
+        let canceller = global.task_canceller(TaskSourceName::DatabaseAccess);
+        ROUTER.add_route(receiver.to_opaque(), Box::new(move |message| {
+            let status: IndexedDBDeletionStatus = message.to().unwrap();
+            let request = trusted_request.clone();
+            let _ = task_source.queue_with_canceller(
+                task!(indexeddb_deletion_status_changed: move || {
+                    request.root().deletion_status_changed(status);
+                }),
+                &canceller,
+            );
+        }));
+        let msg = IndexedDBThreadMsg::DeleteDatabase(sender, global.get_url(), String::from(name));
+        if global.resource_threads().send(msg).is_err() {
+            self.upcast::<IDBRequest>().fire_error(DOMErrorName::UnknownError);
+        }
+    }
+
+    fn deletion_status_changed(&self, status: IndexedDBDeletionStatus) {
+        let global = self.global();
+        match status {
+            // Step 7.
+            IndexedDBDeletionStatus::Blocked(version) => {
+                let event = IDBVersionChangeEvent::new(&global,
+                                                       atom!("blocked"),
+                                                       EventBubbles::DoesNotBubble,
+                                                       EventCancelable::NotCancelable,
+                                                       version,
+                                                       None);
+                event.upcast::<Event>().fire(self.upcast());
+            },
+            // Steps 8-11.
+            IndexedDBDeletionStatus::Deleted(version) => {
+                let event = IDBVersionChangeEvent::new(&global,
+                                                       atom!("success"),
+                                                       EventBubbles::DoesNotBubble,
+                                                       EventCancelable::NotCancelable,
+                                                       version.unwrap_or(0),
+                                                       None);
+                self.upcast::<IDBRequest>().fire_success(event.upcast());
+            },
+        }
+    }
+}
+
+impl IDBOpenDBRequestMethods for IDBOpenDBRequest {
+    // https://w3c.github.io/IndexedDB/#dom-idbopendbrequest-onblocked
+    event_handler!(blocked, GetOnblocked, SetOnblocked);
+
+    // https://w3c.github.io/IndexedDB/#dom-idbopendbrequest-onupgradeneeded
+    event_handler!(upgradeneeded, GetOnupgradeneeded, SetOnupgradeneeded);
+}
@@ -0,0 +1,322 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::cell::DomRefCell;
+use dom::bindings::codegen::Bindings::IDBRequestBinding;
+use dom::bindings::codegen::Bindings::IDBRequestBinding::{IDBRequestMethods, IDBRequestReadyState};
+use dom::bindings::codegen::UnionTypes::IDBObjectStoreOrIDBIndexOrIDBCursor;
+use dom::bindings::conversions::ToJSValConvertible;
+use dom::bindings::error::{Error, Fallible};
+use dom::bindings::inheritance::Castable;
+use dom::bindings::refcounted::Trusted;
+use dom::bindings::reflector::{DomObject, reflect_dom_object};
+use dom::bindings::root::{Dom, DomRoot, MutNullableDom};
+use dom::domexception::{DOMErrorName, DOMException};
+use dom::event::{Event, EventBubbles, EventCancelable, EventStatus};
+use dom::eventtarget::EventTarget;
+use dom::globalscope::GlobalScope;
+use dom::idbcursor::IDBCursor;
+use dom::idbindex::IDBIndex;
+use dom::idbobjectstore::IDBObjectStore;
+use dom::idbtransaction::IDBTransaction;
+use dom_struct::dom_struct;
+use indexed_db::{RecordValue, key_to_jsval};
+use js::jsapi::{Heap, JSAutoCompartment, JSContext};
+use js::jsval::{JSVal, NullValue, UndefinedValue};
+use js::rust::{HandleValue, MutableHandleValue};
+use net_traits::indexeddb_thread::{IndexedDBKey, IndexedDBRecord};
+use std::cell::Cell;
+use task_source::TaskSource;
+
+#[derive(JSTraceable, MallocSizeOf)]
+#[must_root]
+pub enum IDBRequestSource {
+    ObjectStore(Dom<IDBObjectStore>),
+    Index(Dom<IDBIndex>),
+    Cursor(Dom<IDBCursor>),
+}
+
+/// The outcome of an operation, converted to the result of its request when the
+/// success event is fired.
+pub enum IDBRequestResult {
+    Undefined,
+    Key(Option<IndexedDBKey>),
+    Keys(Vec<IndexedDBKey>),
+    Count(u64),
+    /// The value of the first record found, if any.
+    Value(Option<IndexedDBRecord>),
+    Values(Vec<IndexedDBRecord>),
+    /// The record the cursor of the request moved to, if any.
+    Cursor(Option<IndexedDBRecord>),
+}
+
+#[dom_struct]
+pub struct IDBRequest {
+    eventtarget: EventTarget,
+    #[ignore_malloc_size_of = "Defined in rust-mozjs"]
+    result: Heap<JSVal>,
+    error: MutNullableDom<DOMException>,
+    source: DomRefCell<Option<IDBRequestSource>>,
+    transaction: MutNullableDom<IDBTransaction>,
+    ready_state: Cell<IDBRequestReadyState>,
+    /// The cursor iterated by this request, if it was made to open one.
+    cursor: MutNullableDom<IDBCursor>,
+}
+
+impl IDBRequest {
+    pub fn new_inherited(source: Option<IDBObjectStoreOrIDBIndexOrIDBCursor>,
+                         transaction: Option<&IDBTransaction>)
+                         -> IDBRequest {
+        let source = source.map(|source| match source {
+            IDBObjectStoreOrIDBIndexOrIDBCursor::IDBObjectStore(store) => {
+                IDBRequestSource::ObjectStore(Dom::from_ref(&*store))
+            },
+            IDBObjectStoreOrIDBIndexOrIDBCursor::IDBIndex(index) => {
+                IDBRequestSource::Index(Dom::from_ref(&*index))
+            },
+            IDBObjectStoreOrIDBIndexOrIDBCursor::IDBCursor(cursor) => {
+                IDBRequestSource::Cursor(Dom::from_ref(&*cursor))
+            },
+        });
+        IDBRequest {
+            eventtarget: EventTarget::new_inherited(),
+            result: Heap::default(),
+            error: Default::default(),
+            source: DomRefCell::new(source),
+            transaction: MutNullableDom::new(transaction),
+            ready_state: Cell::new(IDBRequestReadyState::Pending),
+            cursor: Default::default(),
+        }
+    }
+
+    pub fn new(global: &GlobalScope,
+               source: Option<IDBObjectStoreOrIDBIndexOrIDBCursor>,
+               transaction: Option<&IDBTransaction>)
+               -> DomRoot<IDBRequest> {
+        reflect_dom_object(Box::new(IDBRequest::new_inherited(source, transaction)),
+                           global,
+                           IDBRequestBinding::Wrap)
+    }
+
+    /// <https://w3c.github.io/IndexedDB/#asynchronously-execute-a-request>
+    pub fn execute(source: IDBObjectStoreOrIDBIndexOrIDBCursor,
+                   transaction: &IDBTransaction,
+                   result: Result<IDBRequestResult, DOMErrorName>)
+                   -> DomRoot<IDBRequest> {
+        let request = IDBRequest::new(&transaction.global(), Some(source), Some(transaction));
+        request.queue_result(result);
+        request
+    }
+
+    pub fn transaction(&self) -> Option<DomRoot<IDBTransaction>> {
+        self.transaction.get()
+    }
+
+    pub fn set_transaction(&self, transaction: Option<&IDBTransaction>) {
+        self.transaction.set(transaction);
+    }
+
+    pub fn set_cursor(&self, cursor: &IDBCursor) {
+        self.cursor.set(Some(cursor));
+    }
+
+    pub fn set_result(&self, result: HandleValue) {
+        self.result.set(result.get());
+    }
+
+    pub fn ready_state(&self) -> IDBRequestReadyState {
+        self.ready_state.get()
+    }
+
+    /// The object store whose records this request reads.
+    fn object_store(&self) -> Option<DomRoot<IDBObjectStore>> {
+        match *self.source.borrow() {
+            Some(IDBRequestSource::ObjectStore(ref store)) => Some(DomRoot::from_ref(&**store)),
+            Some(IDBRequestSource::Index(ref index)) => Some(index.object_store()),
+            Some(IDBRequestSource::Cursor(ref cursor)) => Some(cursor.object_store()),
+            None => None,
+        }
+    }
+
+    /// Queues a task setting the result of this request once its operation ran.
+    pub fn queue_result(&self, result: Result<IDBRequestResult, DOMErrorName>) {
+        self.ready_state.set(IDBRequestReadyState::Pending);
+        if let Some(transaction) = self.transaction.get() {
+            transaction.add_request(self);
+        }
+        let request = Trusted::new(self);
+        let global = self.global();
+        let _ = global.database_access_task_source().queue(
+            task!(indexeddb_request_done: move || {
+                request.root().handle_result(result);
+            }),
+            &global,
+        );
+    }
+
+    #[allow(unsafe_code)]
+    fn handle_result(&self, result: Result<IDBRequestResult, DOMErrorName>) {
+        // The transaction of the request was aborted in the meantime.
+        if self.ready_state.get() == IDBRequestReadyState::Done {
+            return;
+        }
+
+        let result = match result {
+            Ok(result) => result,
+            Err(error) => return self.fire_error(error),
+        };
+
+        let global = self.global();
+        let cx = global.get_cx();
+        let _ac = JSAutoCompartment::new(cx, self.reflector().get_jsobject().get());
+        rooted!(in(cx) let mut value = UndefinedValue());
+        unsafe {
+            self.result_to_jsval(cx, result, value.handle_mut());
+        }
+        self.set_result(value.handle());
+        let event = Event::new(&global,
+                               atom!("success"),
+                               EventBubbles::DoesNotBubble,
+                               EventCancelable::NotCancelable);
+        self.fire_success(&event);
+    }
+
+    #[allow(unsafe_code)]
+    unsafe fn result_to_jsval(&self, cx: *mut JSContext, result: IDBRequestResult, mut rval: MutableHandleValue) {
+        let global = self.global();
+        let store = self.object_store().and_then(|store| store.info());
+        match result {
+            IDBRequestResult::Undefined |
+            IDBRequestResult::Key(None) |
+            IDBRequestResult::Value(None) => rval.set(UndefinedValue()),
+            IDBRequestResult::Key(Some(key)) => key_to_jsval(cx, &key, rval),
+            IDBRequestResult::Keys(keys) => key_to_jsval(cx, &IndexedDBKey::Array(keys), rval),
+            IDBRequestResult::Count(count) => (count as f64).to_jsval(cx, rval),
+            IDBRequestResult::Value(Some(record)) => {
+                RecordValue::new(&global, &record, store.as_ref()).to_jsval(cx, rval)
+            },
+            IDBRequestResult::Values(records) => {
+                records.iter()
+                       .map(|record| RecordValue::new(&global, record, store.as_ref()))
+                       .collect::<Vec<_>>()
+                       .to_jsval(cx, rval)
+            },
+            IDBRequestResult::Cursor(record) => {
+                let cursor = self.cursor.get().expect("Cursor result without a cursor");
+                match record {
+                    Some(record) => {
+                        cursor.set_record(cx, record, store.as_ref());
+                        cursor.to_jsval(cx, rval);
+                    },
+                    None => rval.set(NullValue()),
+                }
+            },
+        }
+    }
+
+    /// <https://w3c.github.io/IndexedDB/#fire-a-success-event>
+    pub fn fire_success(&self, event: &Event) {
+        self.ready_state.set(IDBRequestReadyState::Done);
+        let transaction = self.transaction.get();
+        if let Some(ref transaction) = transaction {
+            transaction.set_active(true);
+        }
+        // TODO: Abort the transaction if a listener threw an exception.
+        event.fire(self.upcast());
+        if let Some(transaction) = transaction {
+            transaction.set_active(false);
+            transaction.request_finished(self);
+        }
+    }
+
+    /// <https://w3c.github.io/IndexedDB/#fire-an-error-event>
+    pub fn fire_error(&self, error: DOMErrorName) {
+        let global = self.global();
+        self.ready_state.set(IDBRequestReadyState::Done);
+        self.result.set(UndefinedValue());
+        self.error.set(Some(&DOMException::new(&global, error)));
+
+        let transaction = self.transaction.get();
+        if let Some(ref transaction) = transaction {
+            transaction.set_active(true);
+        }
+        let event = Event::new(&global, atom!("error"), EventBubbles::Bubbles, EventCancelable::Cancelable);
+        let status = event.fire(self.upcast());
+        if let Some(transaction) = transaction {
+            transaction.set_active(false);
+            if status == EventStatus::NotCanceled {
+                transaction.abort(Some(error));
+            }
+            transaction.request_finished(self);
+        }
+    }
+
+    /// Fails this request because its transaction is being aborted.
+    ///
+    /// <https://w3c.github.io/IndexedDB/#abort-a-transaction>
+    pub fn abort(&self) {
+        let global = self.global();
+        self.ready_state.set(IDBRequestReadyState::Done);
+        self.result.set(UndefinedValue());
+        self.error.set(Some(&DOMException::new(&global, DOMErrorName::AbortError)));
+        let request = Trusted::new(self);
+        let _ = global.database_access_task_source().queue(
+            task!(indexeddb_request_aborted: move || {
+                let request = request.root();
+                request.upcast::<EventTarget>().fire_bubbling_cancelable_event(atom!("error"));
+            }),
+            &global,
+        );
+    }
+}
+
+impl IDBRequestMethods for IDBRequest {
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbrequest-result
+    unsafe fn Result(&self, _cx: *mut JSContext) -> Fallible<JSVal> {
+        if self.ready_state.get() == IDBRequestReadyState::Pending {
+            return Err(Error::InvalidState);
+        }
+        Ok(self.result.get())
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbrequest-error
+    fn GetError(&self) -> Fallible<Option<DomRoot<DOMException>>> {
+        if self.ready_state.get() == IDBRequestReadyState::Pending {
+            return Err(Error::InvalidState);
+        }
+        Ok(self.error.get())
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbrequest-source
+    fn GetSource(&self) -> Option<IDBObjectStoreOrIDBIndexOrIDBCursor> {
+        self.source.borrow().as_ref().map(|source| match *source {
+            IDBRequestSource::ObjectStore(ref store) => {
+                IDBObjectStoreOrIDBIndexOrIDBCursor::IDBObjectStore(DomRoot::from_ref(&**store))
+            },
+            IDBRequestSource::Index(ref index) => {
+                IDBObjectStoreOrIDBIndexOrIDBCursor::IDBIndex(DomRoot::from_ref(&**index))
+            },
+            IDBRequestSource::Cursor(ref cursor) => {
+                IDBObjectStoreOrIDBIndexOrIDBCursor::IDBCursor(DomRoot::from_ref(&**cursor))
+            },
+        })
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbrequest-transaction
+    fn GetTransaction(&self) -> Option<DomRoot<IDBTransaction>> {
+        self.transaction.get()
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbrequest-readystate
+    fn ReadyState(&self) -> IDBRequestReadyState {
+        self.ready_state.get()
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbrequest-onsuccess
+    event_handler!(success, GetOnsuccess, SetOnsuccess);
+
+    // https://w3c.github.io/IndexedDB/#dom-idbrequest-onerror
+    event_handler!(error, GetOnerror, SetOnerror);
+}
@@ -0,0 +1,315 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::cell::DomRefCell;
+use dom::bindings::codegen::Bindings::IDBDatabaseBinding::IDBTransactionMode;
+use dom::bindings::codegen::Bindings::IDBTransactionBinding;
+use dom::bindings::codegen::Bindings::IDBTransactionBinding::IDBTransactionMethods;
+use dom::bindings::error::{Error, ErrorResult, Fallible};
+use dom::bindings::inheritance::Castable;
+use dom::bindings::refcounted::Trusted;
+use dom::bindings::reflector::{DomObject, reflect_dom_object};
+use dom::bindings::root::{Dom, DomRoot, MutNullableDom};
+use dom::bindings::str::DOMString;
+use dom::domexception::{DOMErrorName, DOMException};
+use dom::domstringlist::DOMStringList;
+use dom::eventtarget::EventTarget;
+use dom::globalscope::GlobalScope;
+use dom::idbdatabase::IDBDatabase;
+use dom::idbobjectstore::IDBObjectStore;
+use dom::idbopendbrequest::IDBOpenDBRequest;
+use dom::idbrequest::IDBRequest;
+use dom_struct::dom_struct;
+use ipc_channel::ipc::IpcSender;
+use net_traits::IpcSend;
+use net_traits::indexeddb_thread::{IndexedDBError, IndexedDBOperation, IndexedDBThreadMsg};
+use profile_traits::ipc;
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use task_source::TaskSource;
+
+/// <https://w3c.github.io/IndexedDB/#transaction-concept>
+#[dom_struct]
+pub struct IDBTransaction {
+    eventtarget: EventTarget,
+    /// The identifier of this transaction in the IndexedDB thread.
+    id: u64,
+    db: Dom<IDBDatabase>,
+    mode: IDBTransactionMode,
+    scope: DomRefCell<Vec<DOMString>>,
+    active: Cell<bool>,
+    finished: Cell<bool>,
+    error: MutNullableDom<DOMException>,
+    /// The requests of this transaction whose events weren't fired yet.
+    requests: DomRefCell<Vec<Dom<IDBRequest>>>,
+    /// The request that opened the connection, for upgrade transactions.
+    open_request: MutNullableDom<IDBOpenDBRequest>,
+    /// The version of the database before an upgrade transaction.
+    old_version: Cell<u64>,
+}
+
+impl IDBTransaction {
+    fn new_inherited(id: u64,
+                     db: &IDBDatabase,
+                     mode: IDBTransactionMode,
+                     scope: Vec<DOMString>)
+                     -> IDBTransaction {
+        IDBTransaction {
+            eventtarget: EventTarget::new_inherited(),
+            id: id,
+            db: Dom::from_ref(db),
+            mode: mode,
+            scope: DomRefCell::new(scope),
+            active: Cell::new(true),
+            finished: Cell::new(false),
+            error: Default::default(),
+            requests: DomRefCell::new(vec![]),
+            open_request: Default::default(),
+            old_version: Cell::new(0),
+        }
+    }
+
+    pub fn new(global: &GlobalScope,
+               db: &IDBDatabase,
+               mode: IDBTransactionMode,
+               scope: Vec<DOMString>)
+               -> Fallible<DomRoot<IDBTransaction>> {
+        let (sender, receiver) = ipc::channel(global.time_profiler_chan().clone()).unwrap();
+        global.resource_threads().send(IndexedDBThreadMsg::NewTransaction(sender)).map_err(|_| Error::Unknown)?;
+        let id = receiver.recv().map_err(|_| Error::Unknown)?;
+
+        let transaction = reflect_dom_object(Box::new(IDBTransaction::new_inherited(id, db, mode, scope)),
+                                             global,
+                                             IDBTransactionBinding::Wrap);
+
+        // https://w3c.github.io/IndexedDB/#transaction-lifetime-concept
+        // The transaction stays active until control returns to the event loop.
+        let trusted_transaction = Trusted::new(&*transaction);
+        let _ = global.database_access_task_source().queue(
+            task!(deactivate_indexeddb_transaction: move || {
+                let transaction = trusted_transaction.root();
+                transaction.set_active(false);
+                transaction.maybe_commit();
+            }),
+            global,
+        );
+
+        Ok(transaction)
+    }
+
+    pub fn db(&self) -> DomRoot<IDBDatabase> {
+        DomRoot::from_ref(&*self.db)
+    }
+
+    pub fn mode(&self) -> IDBTransactionMode {
+        self.mode
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active.get()
+    }
+
+    pub fn set_active(&self, active: bool) {
+        self.active.set(active && !self.finished.get());
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished.get()
+    }
+
+    pub fn set_open_request(&self, request: &IDBOpenDBRequest, old_version: u64) {
+        self.open_request.set(Some(request));
+        self.old_version.set(old_version);
+    }
+
+    pub fn add_to_scope(&self, name: DOMString) {
+        let mut scope = self.scope.borrow_mut();
+        if !scope.contains(&name) {
+            scope.push(name);
+            scope.sort();
+        }
+    }
+
+    /// Sends an operation to the IndexedDB thread and waits for its outcome.
+    pub fn operation<T, F>(&self, operation: F) -> Result<T, IndexedDBError>
+        where T: for<'de> Deserialize<'de> + Serialize,
+              F: FnOnce(IpcSender<Result<T, IndexedDBError>>) -> IndexedDBOperation,
+    {
+        let global = self.global();
+        let (sender, receiver) = ipc::channel(global.time_profiler_chan().clone()).unwrap();
+        let msg = IndexedDBThreadMsg::Operation(global.get_url(), self.db.name(), self.id, operation(sender));
+        global.resource_threads().send(msg).map_err(|_| IndexedDBError::Unknown)?;
+        receiver.recv().unwrap_or(Err(IndexedDBError::Unknown))
+    }
+
+    /// Sets the version of the database, for upgrade transactions.
+    pub fn set_version(&self, version: u64) -> Result<(), IndexedDBError> {
+        self.operation(|sender| IndexedDBOperation::SetVersion(sender, version))?;
+        self.db.set_version(version);
+        Ok(())
+    }
+
+    pub fn add_request(&self, request: &IDBRequest) {
+        self.requests.borrow_mut().push(Dom::from_ref(request));
+    }
+
+    pub fn request_finished(&self, request: &IDBRequest) {
+        self.requests.borrow_mut().retain(|pending| &**pending as *const IDBRequest != request as *const IDBRequest);
+        self.maybe_commit();
+    }
+
+    /// Commits the transaction once it is inactive and all its requests are done.
+    ///
+    /// <https://w3c.github.io/IndexedDB/#commit-a-transaction>
+    fn maybe_commit(&self) {
+        if self.finished.get() || self.active.get() || !self.requests.borrow().is_empty() {
+            return;
+        }
+
+        // Steps 1-4.
+        let global = self.global();
+        let (sender, receiver) = ipc::channel(global.time_profiler_chan().clone()).unwrap();
+        let msg = IndexedDBThreadMsg::Commit(sender, global.get_url(), self.db.name(), self.id);
+        let committed = global.resource_threads().send(msg).is_ok() && receiver.recv().is_ok();
+        if !committed {
+            return self.abort(Some(DOMErrorName::UnknownError));
+        }
+        self.finished.set(true);
+
+        // Step 5.
+        let transaction = Trusted::new(self);
+        let _ = global.database_access_task_source().queue(
+            task!(indexeddb_transaction_complete: move || {
+                let transaction = transaction.root();
+                if transaction.mode == IDBTransactionMode::Versionchange {
+                    transaction.db.set_upgrade_transaction(None);
+                }
+                transaction.upcast::<EventTarget>().fire_event(atom!("complete"));
+                if let Some(request) = transaction.open_request.get() {
+                    request.upgrade_finished(&transaction.db, true);
+                }
+            }),
+            &global,
+        );
+    }
+
+    /// <https://w3c.github.io/IndexedDB/#abort-a-transaction>
+    pub fn abort(&self, error: Option<DOMErrorName>) {
+        if self.finished.get() {
+            return;
+        }
+        self.finished.set(true);
+        self.active.set(false);
+
+        // Step 1.
+        let global = self.global();
+        let (sender, receiver) = ipc::channel(global.time_profiler_chan().clone()).unwrap();
+        let msg = IndexedDBThreadMsg::Abort(sender, global.get_url(), self.db.name(), self.id);
+        // The changes are gone anyway if the IndexedDB thread is.
+        if global.resource_threads().send(msg).is_ok() {
+            let _ = receiver.recv();
+        }
+
+        // Step 2.
+        if self.mode == IDBTransactionMode::Versionchange {
+            self.db.set_version(self.old_version.get());
+            let _ = self.db.reload_object_stores();
+        }
+
+        // Step 4.
+        if let Some(error) = error {
+            self.error.set(Some(&DOMException::new(&global, error)));
+        }
+
+        // Step 5.
+        let requests: Vec<DomRoot<IDBRequest>> = self.requests.borrow_mut()
+                                                     .drain(..)
+                                                     .map(|request| DomRoot::from_ref(&*request))
+                                                     .collect();
+        for request in requests {
+            request.abort();
+        }
+
+        // Step 6.
+        let transaction = Trusted::new(self);
+        let _ = global.database_access_task_source().queue(
+            task!(indexeddb_transaction_abort: move || {
+                let transaction = transaction.root();
+                if transaction.mode == IDBTransactionMode::Versionchange {
+                    transaction.db.set_upgrade_transaction(None);
+                }
+                transaction.upcast::<EventTarget>().fire_bubbling_event(atom!("abort"));
+                if let Some(request) = transaction.open_request.get() {
+                    request.upgrade_finished(&transaction.db, false);
+                }
+            }),
+            &global,
+        );
+    }
+}
+
+impl IDBTransactionMethods for IDBTransaction {
+    // https://w3c.github.io/IndexedDB/#dom-idbtransaction-objectstorenames
+    fn ObjectStoreNames(&self) -> DomRoot<DOMStringList> {
+        let names = if self.mode == IDBTransactionMode::Versionchange {
+            self.db.object_store_names()
+        } else {
+            self.scope.borrow().clone()
+        };
+        DOMStringList::new(&self.global(), names)
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbtransaction-mode
+    fn Mode(&self) -> IDBTransactionMode {
+        self.mode
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbtransaction-db
+    fn Db(&self) -> DomRoot<IDBDatabase> {
+        self.db()
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbtransaction-error
+    fn GetError(&self) -> Option<DomRoot<DOMException>> {
+        self.error.get()
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbtransaction-objectstore
+    fn ObjectStore(&self, name: DOMString) -> Fallible<DomRoot<IDBObjectStore>> {
+        // Step 1.
+        if self.finished.get() {
+            return Err(Error::InvalidState);
+        }
+
+        // Step 2.
+        let in_scope = self.mode == IDBTransactionMode::Versionchange || self.scope.borrow().contains(&name);
+        if !in_scope || self.db.object_store_info(&name).is_none() {
+            return Err(Error::NotFound);
+        }
+
+        // Step 3.
+        Ok(IDBObjectStore::new(&self.global(), name, self))
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbtransaction-abort
+    fn Abort(&self) -> ErrorResult {
+        // Step 1.
+        if self.finished.get() {
+            return Err(Error::InvalidState);
+        }
+
+        // Step 2.
+        self.abort(None);
+        Ok(())
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbtransaction-onabort
+    event_handler!(abort, GetOnabort, SetOnabort);
+
+    // https://w3c.github.io/IndexedDB/#dom-idbtransaction-oncomplete
+    event_handler!(complete, GetOncomplete, SetOncomplete);
+
+    // https://w3c.github.io/IndexedDB/#dom-idbtransaction-onerror
+    event_handler!(error, GetOnerror, SetOnerror);
+}
@@ -0,0 +1,415 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use cssparser::SourceLocation;
+use dom::animationeffect::{AnimationEffect, optional_effect_timing, parse_easing};
+use dom::bindings::cell::DomRefCell;
+use dom::bindings::codegen::Bindings::AnimationEffectBinding::OptionalEffectTiming;
+use dom::bindings::codegen::Bindings::KeyframeEffectBinding::{self, KeyframeEffectMethods};
+use dom::bindings::codegen::Bindings::WindowBinding::WindowBinding::WindowMethods;
+use dom::bindings::codegen::UnionTypes::{StringOrStringSequence, UnrestrictedDoubleOrKeyframeEffectOptions};
+use dom::bindings::codegen::UnionTypes::UnrestrictedDoubleOrString;
+use dom::bindings::conversions::{ConversionResult, FromJSValConvertible, StringificationBehavior};
+use dom::bindings::conversions::is_array_like;
+use dom::bindings::error::{Error, ErrorResult, Fallible};
+use dom::bindings::inheritance::Castable;
+use dom::bindings::mozmap::MozMap;
+use dom::bindings::reflector::{DomObject, reflect_dom_object};
+use dom::bindings::root::{DomRoot, MutNullableDom};
+use dom::bindings::str::DOMString;
+use dom::bindings::utils::set_dictionary_property;
+use dom::element::Element;
+use dom::window::Window;
+use dom_struct::dom_struct;
+use js::conversions::ToJSValConvertible;
+use js::jsapi::{HandleValueArray, JSContext, JSObject, JS_NewArrayObject, JS_NewPlainObject};
+use js::jsval::{JSVal, ObjectValue, UndefinedValue};
+use servo_arc::Arc;
+use std::f64;
+use style::properties::{Importance, LonghandId, PropertyDeclarationBlock, PropertyId};
+use style::properties::{SourcePropertyDeclaration, parse_one_declaration_into};
+use style::shared_lock::Locked;
+use style::stylesheets::keyframes_rule::{Keyframe, KeyframePercentage, KeyframeSelector, KeyframesAnimation};
+use style_traits::ParsingMode;
+
+/// <https://drafts.csswg.org/web-animations/#the-keyframeeffect-interface>
+#[dom_struct]
+pub struct KeyframeEffect {
+    effect: AnimationEffect,
+    /// <https://drafts.csswg.org/web-animations/#effect-target>
+    target: MutNullableDom<Element>,
+    /// <https://drafts.csswg.org/web-animations/#keyframe>
+    keyframes: DomRefCell<Vec<ComputedKeyframe>>,
+}
+
+/// A keyframe given to the Web Animations API, once processed.
+#[derive(JSTraceable, MallocSizeOf)]
+struct ComputedKeyframe {
+    /// The offset of the keyframe, if it was specified.
+    offset: Option<f64>,
+    /// <https://drafts.csswg.org/web-animations/#computed-keyframe-offset>
+    computed_offset: f64,
+    /// The serialization of the timing function of the keyframe.
+    easing: DOMString,
+    /// The property values of the keyframe as given, keyed by the IDL name of
+    /// their property.
+    values: Vec<(DOMString, DOMString)>,
+    /// The parsed property values of the keyframe, along with its easing.
+    #[ignore_malloc_size_of = "Arc"]
+    block: Arc<Locked<PropertyDeclarationBlock>>,
+}
+
+impl KeyframeEffect {
+    fn new_inherited(target: Option<&Element>) -> KeyframeEffect {
+        KeyframeEffect {
+            effect: AnimationEffect::new_inherited(),
+            target: MutNullableDom::new(target),
+            keyframes: DomRefCell::new(vec![]),
+        }
+    }
+
+    pub fn new(window: &Window, target: Option<&Element>) -> DomRoot<KeyframeEffect> {
+        reflect_dom_object(
+            Box::new(KeyframeEffect::new_inherited(target)),
+            window,
+            KeyframeEffectBinding::Wrap,
+        )
+    }
+
+    /// Creates a keyframe effect from the arguments given to its constructor
+    /// or to `Element.animate()`.
+    ///
+    /// <https://drafts.csswg.org/web-animations/#dom-keyframeeffect-keyframeeffect>
+    #[allow(unsafe_code)]
+    pub unsafe fn new_with_keyframes(
+        cx: *mut JSContext,
+        window: &Window,
+        target: Option<&Element>,
+        keyframes: *mut JSObject,
+        timing: &OptionalEffectTiming,
+    ) -> Fallible<DomRoot<KeyframeEffect>> {
+        let effect = KeyframeEffect::new(window, target);
+        effect.upcast::<AnimationEffect>().update_timing(timing)?;
+        effect.SetKeyframes(cx, keyframes)?;
+        Ok(effect)
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-keyframeeffect-keyframeeffect
+    #[allow(unsafe_code)]
+    pub unsafe fn Constructor(
+        cx: *mut JSContext,
+        window: &Window,
+        target: Option<&Element>,
+        keyframes: *mut JSObject,
+        options: Option<UnrestrictedDoubleOrKeyframeEffectOptions>,
+    ) -> Fallible<DomRoot<KeyframeEffect>> {
+        let timing = match options {
+            Some(UnrestrictedDoubleOrKeyframeEffectOptions::UnrestrictedDouble(duration)) => {
+                let mut timing = OptionalEffectTiming::empty();
+                timing.duration = Some(UnrestrictedDoubleOrString::UnrestrictedDouble(duration));
+                timing
+            },
+            Some(UnrestrictedDoubleOrKeyframeEffectOptions::KeyframeEffectOptions(ref options)) => {
+                optional_effect_timing(&options.parent)
+            },
+            None => OptionalEffectTiming::empty(),
+        };
+        KeyframeEffect::new_with_keyframes(cx, window, target, keyframes, &timing)
+    }
+
+    /// Returns the keyframes of this effect, as layout animates them.
+    pub fn keyframes_animation(&self) -> KeyframesAnimation {
+        let document = self.global().as_window().Document();
+        let lock = document.style_shared_lock();
+        let keyframes = self
+            .keyframes
+            .borrow()
+            .iter()
+            .map(|keyframe| {
+                Arc::new(lock.wrap(Keyframe {
+                    selector: KeyframeSelector::from_percentages(vec![
+                        KeyframePercentage::new(keyframe.computed_offset as f32),
+                    ]),
+                    block: keyframe.block.clone(),
+                    source_location: SourceLocation { line: 0, column: 0 },
+                }))
+            }).collect::<Vec<_>>();
+        let guard = lock.read();
+        KeyframesAnimation::from_keyframes(&keyframes, None, &guard)
+    }
+
+    fn notify_animation(&self) {
+        if let Some(animation) = self.upcast::<AnimationEffect>().animation() {
+            animation.effect_changed();
+        }
+    }
+}
+
+impl KeyframeEffectMethods for KeyframeEffect {
+    // https://drafts.csswg.org/web-animations/#dom-keyframeeffect-target
+    fn GetTarget(&self) -> Option<DomRoot<Element>> {
+        self.target.get()
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-keyframeeffect-target
+    fn SetTarget(&self, target: Option<&Element>) {
+        self.target.set(target);
+        self.notify_animation();
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-keyframeeffect-getkeyframes
+    #[allow(unsafe_code)]
+    unsafe fn GetKeyframes(&self, cx: *mut JSContext) -> Fallible<JSVal> {
+        rooted!(in(cx) let keyframes = JS_NewArrayObject(cx, &HandleValueArray::from_rooted_slice(&[])));
+        rooted!(in(cx) let mut value = UndefinedValue());
+        for (index, keyframe) in self.keyframes.borrow().iter().enumerate() {
+            rooted!(in(cx) let object = JS_NewPlainObject(cx));
+            keyframe.offset.to_jsval(cx, value.handle_mut());
+            set_dictionary_property(cx, object.handle(), "offset", value.handle()).map_err(|_| Error::JSFailed)?;
+            keyframe.computed_offset.to_jsval(cx, value.handle_mut());
+            set_dictionary_property(cx, object.handle(), "computedOffset", value.handle())
+                .map_err(|_| Error::JSFailed)?;
+            keyframe.easing.to_jsval(cx, value.handle_mut());
+            set_dictionary_property(cx, object.handle(), "easing", value.handle()).map_err(|_| Error::JSFailed)?;
+            DOMString::from("auto").to_jsval(cx, value.handle_mut());
+            set_dictionary_property(cx, object.handle(), "composite", value.handle())
+                .map_err(|_| Error::JSFailed)?;
+            for &(ref name, ref property_value) in &keyframe.values {
+                property_value.to_jsval(cx, value.handle_mut());
+                set_dictionary_property(cx, object.handle(), name, value.handle()).map_err(|_| Error::JSFailed)?;
+            }
+
+            value.set(ObjectValue(object.get()));
+            set_dictionary_property(cx, keyframes.handle(), &index.to_string(), value.handle())
+                .map_err(|_| Error::JSFailed)?;
+        }
+        Ok(ObjectValue(keyframes.get()))
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-keyframeeffect-setkeyframes
+    #[allow(unsafe_code)]
+    unsafe fn SetKeyframes(&self, cx: *mut JSContext, keyframes: *mut JSObject) -> ErrorResult {
+        let keyframes = process_keyframes(cx, &self.global().as_window(), keyframes)?;
+        *self.keyframes.borrow_mut() = keyframes;
+        self.notify_animation();
+        Ok(())
+    }
+}
+
+/// Keyframe properties which aren't animated properties.
+const KEYFRAME_MEMBERS: [&'static str; 3] = ["offset", "easing", "composite"];
+
+/// <https://drafts.csswg.org/web-animations/#processing-a-keyframes-argument>
+#[allow(unsafe_code)]
+unsafe fn process_keyframes(
+    cx: *mut JSContext,
+    window: &Window,
+    object: *mut JSObject,
+) -> Fallible<Vec<ComputedKeyframe>> {
+    // Step 1.
+    if object.is_null() {
+        return Ok(vec![]);
+    }
+
+    rooted!(in(cx) let object = ObjectValue(object));
+    let mut keyframes = if is_array_like(cx, object.handle()) {
+        // Step 4.
+        let maps = match Vec::<MozMap<DOMString>>::from_jsval(cx, object.handle(), StringificationBehavior::Default) {
+            Ok(ConversionResult::Success(maps)) => maps,
+            Ok(ConversionResult::Failure(error)) => return Err(Error::Type(error.into_owned())),
+            Err(()) => return Err(Error::JSFailed),
+        };
+        let mut keyframes = vec![];
+        for map in maps {
+            let offset = match map.get("offset") {
+                Some(offset) => parse_offset(offset)?,
+                None => None,
+            };
+            let easing = map.get("easing").cloned().unwrap_or_else(|| DOMString::from("linear"));
+            let values = map
+                .iter()
+                .filter(|&(name, _)| !KEYFRAME_MEMBERS.contains(&&**name))
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect();
+            keyframes.push((offset, offset, easing, values));
+        }
+
+        // Step 5.
+        let mut previous_offset = f64::NEG_INFINITY;
+        for &(offset, _, _, _) in &keyframes {
+            if let Some(offset) = offset {
+                if offset < previous_offset {
+                    return Err(Error::Type("Keyframe offsets must be sorted".to_owned()));
+                }
+                previous_offset = offset;
+            }
+        }
+        keyframes
+    } else {
+        // Step 5, for property-indexed keyframes.
+        let map = match MozMap::<StringOrStringSequence>::from_jsval(cx, object.handle(), ()) {
+            Ok(ConversionResult::Success(map)) => map,
+            Ok(ConversionResult::Failure(error)) => return Err(Error::Type(error.into_owned())),
+            Err(()) => return Err(Error::JSFailed),
+        };
+        let list = |name: &str| match map.get(name) {
+            Some(&StringOrStringSequence::String(ref value)) => vec![value.clone()],
+            Some(&StringOrStringSequence::StringSequence(ref values)) => values.clone(),
+            None => vec![],
+        };
+
+        // The values of each property are evenly spaced, and the keyframes with
+        // the same offset are merged, keeping them sorted.
+        let mut properties: Vec<(f64, Vec<(DOMString, DOMString)>)> = vec![];
+        for name in map.keys().filter(|name| !KEYFRAME_MEMBERS.contains(&&***name)) {
+            let values = list(name);
+            let count = values.len();
+            for (index, value) in values.into_iter().enumerate() {
+                let offset = if count == 1 {
+                    1.
+                } else {
+                    index as f64 / (count - 1) as f64
+                };
+                let position = properties.iter().position(|&(other, _)| other >= offset);
+                match position {
+                    Some(position) if properties[position].0 == offset => {
+                        properties[position].1.push((name.clone(), value));
+                    },
+                    Some(position) => properties.insert(position, (offset, vec![(name.clone(), value)])),
+                    None => properties.push((offset, vec![(name.clone(), value)])),
+                }
+            }
+        }
+
+        let offsets = list("offset");
+        let easings = list("easing");
+        let mut keyframes = vec![];
+        for (index, (computed_offset, values)) in properties.into_iter().enumerate() {
+            let offset = match offsets.get(index) {
+                Some(offset) => parse_offset(offset)?,
+                None => None,
+            };
+            let easing = if easings.is_empty() {
+                DOMString::from("linear")
+            } else {
+                easings[index % easings.len()].clone()
+            };
+            keyframes.push((offset, offset.or(Some(computed_offset)), easing, values));
+        }
+        keyframes
+    };
+
+    // https://drafts.csswg.org/web-animations/#compute-missing-keyframe-offsets
+    let offsets = keyframes.iter().map(|keyframe| keyframe.1).collect::<Vec<_>>();
+    let computed_offsets = compute_missing_offsets(&offsets);
+
+    // Step 6.
+    let mut computed_keyframes = vec![];
+    for ((offset, _, easing, values), computed_offset) in keyframes.drain(..).zip(computed_offsets) {
+        let (_, easing) = parse_easing(window, &easing)?;
+        let block = declaration_block(window, &values, &easing);
+        computed_keyframes.push(ComputedKeyframe {
+            offset: offset,
+            computed_offset: computed_offset,
+            easing: easing,
+            values: values,
+            block: block,
+        });
+    }
+    Ok(computed_keyframes)
+}
+
+/// Parses the offset of a keyframe, which was converted to a string.
+fn parse_offset(offset: &DOMString) -> Fallible<Option<f64>> {
+    if &**offset == "null" || &**offset == "undefined" {
+        return Ok(None);
+    }
+    match offset.parse::<f64>() {
+        Ok(offset) if offset >= 0. && offset <= 1. => Ok(Some(offset)),
+        _ => Err(Error::Type(format!("'{}' is not a valid keyframe offset", offset))),
+    }
+}
+
+/// <https://drafts.csswg.org/web-animations/#compute-missing-keyframe-offsets>
+fn compute_missing_offsets(offsets: &[Option<f64>]) -> Vec<f64> {
+    // Step 1-2.
+    let mut computed = offsets.to_vec();
+    let count = computed.len();
+    if count > 1 && computed[0].is_none() {
+        computed[0] = Some(0.);
+    }
+
+    // Step 3.
+    if count > 0 && computed[count - 1].is_none() {
+        computed[count - 1] = Some(1.);
+    }
+
+    // Step 4.
+    let mut previous = 0;
+    for index in 1..count {
+        let end = match computed[index] {
+            Some(end) => end,
+            None => continue,
+        };
+        let start = computed[previous].unwrap_or(0.);
+        let steps = (index - previous) as f64;
+        for between in (previous + 1)..index {
+            computed[between] = Some(start + (end - start) * (between - previous) as f64 / steps);
+        }
+        previous = index;
+    }
+    computed.into_iter().map(|offset| offset.unwrap_or(1.)).collect()
+}
+
+/// Parses the property values of a keyframe, ignoring the ones that aren't
+/// valid, and adds its easing to them as the timing function layout applies.
+fn declaration_block(
+    window: &Window,
+    values: &[(DOMString, DOMString)],
+    easing: &DOMString,
+) -> Arc<Locked<PropertyDeclarationBlock>> {
+    let document = window.Document();
+    let url = document.url();
+    let mut block = PropertyDeclarationBlock::new();
+    let properties = values
+        .iter()
+        .filter_map(|&(ref name, ref value)| property_id_from_idl_name(name).map(|id| (id, value)));
+    let timing_function = PropertyId::Longhand(LonghandId::AnimationTimingFunction);
+    for (id, value) in properties.chain(Some((timing_function, easing))) {
+        let mut declarations = SourcePropertyDeclaration::new();
+        let result = parse_one_declaration_into(
+            &mut declarations,
+            id,
+            value,
+            &url,
+            window.css_error_reporter(),
+            ParsingMode::DEFAULT,
+            document.quirks_mode(),
+        );
+        if result.is_ok() {
+            block.extend(declarations.drain(), Importance::Normal);
+        }
+    }
+    Arc::new(document.style_shared_lock().wrap(block))
+}
+
+/// <https://drafts.csswg.org/web-animations/#idl-attribute-name-to-animation-property-name>
+fn property_id_from_idl_name(name: &str) -> Option<PropertyId> {
+    if name == "cssFloat" {
+        return Some(PropertyId::Longhand(LonghandId::Float));
+    }
+    if name == "float" || name.contains('-') {
+        return None;
+    }
+    let mut property = String::with_capacity(name.len());
+    for c in name.chars() {
+        if c.is_ascii_uppercase() {
+            property.push('-');
+            property.push(c.to_ascii_lowercase());
+        } else {
+            property.push(c);
+        }
+    }
+    PropertyId::parse_enabled_for_all_content(&property).ok()
+}
@@ -5,6 +5,7 @@
 use dom::bindings::codegen::Bindings::EventBinding::EventMethods;
 use dom::bindings::codegen::Bindings::MessageEventBinding;
 use dom::bindings::codegen::Bindings::MessageEventBinding::MessageEventMethods;
+use dom::bindings::conversions::ToJSValConvertible;
 use dom::bindings::error::Fallible;
 use dom::bindings::inheritance::Castable;
 use dom::bindings::reflector::reflect_dom_object;
@@ -14,9 +15,10 @@ use dom::bindings::trace::RootedTraceableBox;
 use dom::event::Event;
 use dom::eventtarget::EventTarget;
 use dom::globalscope::GlobalScope;
+use dom::messageport::MessagePort;
 use dom_struct::dom_struct;
 use js::jsapi::{Heap, JSContext};
-use js::jsval::JSVal;
+use js::jsval::{JSVal, UndefinedValue};
 use js::rust::HandleValue;
 use servo_atoms::Atom;
 
@@ -26,6 +28,8 @@ pub struct MessageEvent {
     data: Heap<JSVal>,
     origin: DOMString,
     lastEventId: DOMString,
+    /// The array of ports transferred with the message.
+    ports: Heap<JSVal>,
 }
 
 impl MessageEvent {
@@ -33,30 +37,40 @@ impl MessageEvent {
         MessageEvent::new_initialized(global,
                                       HandleValue::undefined(),
                                       DOMString::new(),
-                                      DOMString::new())
+                                      DOMString::new(),
+                                      vec![])
     }
 
+    #[allow(unsafe_code)]
     pub fn new_initialized(global: &GlobalScope,
                            data: HandleValue,
                            origin: DOMString,
-                           lastEventId: DOMString) -> DomRoot<MessageEvent> {
+                           lastEventId: DOMString,
+                           ports: Vec<DomRoot<MessagePort>>) -> DomRoot<MessageEvent> {
         let ev = Box::new(MessageEvent {
             event: Event::new_inherited(),
             data: Heap::default(),
             origin: origin,
             lastEventId: lastEventId,
+            ports: Heap::default(),
         });
         let ev = reflect_dom_object(ev, global, MessageEventBinding::Wrap);
         ev.data.set(data.get());
 
+        let cx = global.get_cx();
+        rooted!(in(cx) let mut ports_array = UndefinedValue());
+        unsafe { ports.to_jsval(cx, ports_array.handle_mut()) };
+        ev.ports.set(ports_array.get());
+
         ev
     }
 
     pub fn new(global: &GlobalScope, type_: Atom,
                bubbles: bool, cancelable: bool,
-               data: HandleValue, origin: DOMString, lastEventId: DOMString)
+               data: HandleValue, origin: DOMString, lastEventId: DOMString,
+               ports: Vec<DomRoot<MessagePort>>)
                -> DomRoot<MessageEvent> {
-        let ev = MessageEvent::new_initialized(global, data, origin, lastEventId);
+        let ev = MessageEvent::new_initialized(global, data, origin, lastEventId, ports);
         {
             let event = ev.upcast::<Event>();
             event.init_event(type_, bubbles, cancelable);
@@ -74,7 +88,8 @@ impl MessageEvent {
                                    init.parent.cancelable,
                                    init.data.handle(),
                                    init.origin.clone(),
-                                   init.lastEventId.clone());
+                                   init.lastEventId.clone(),
+                                   init.ports.clone().unwrap_or(vec![]));
         Ok(ev)
     }
 }
@@ -83,7 +98,8 @@ impl MessageEvent {
     pub fn dispatch_jsval(target: &EventTarget,
                           scope: &GlobalScope,
                           message: HandleValue,
-                          origin: Option<&str>) {
+                          origin: Option<&str>,
+                          ports: Vec<DomRoot<MessagePort>>) {
         let messageevent = MessageEvent::new(
             scope,
             atom!("message"),
@@ -91,7 +107,8 @@ impl MessageEvent {
             false,
             message,
             DOMString::from(origin.unwrap_or("")),
-            DOMString::new());
+            DOMString::new(),
+            ports);
         messageevent.upcast::<Event>().fire(target);
     }
 }
@@ -113,6 +130,12 @@ impl MessageEventMethods for MessageEvent {
         self.lastEventId.clone()
     }
 
+    #[allow(unsafe_code)]
+    // https://html.spec.whatwg.org/multipage/#dom-messageevent-ports
+    unsafe fn Ports(&self, _cx: *mut JSContext) -> JSVal {
+        self.ports.get()
+    }
+
     // https://dom.spec.whatwg.org/#dom-event-istrusted
     fn IsTrusted(&self) -> bool {
         self.event.IsTrusted()
@@ -0,0 +1,240 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::cell::DomRefCell;
+use dom::bindings::codegen::Bindings::EventHandlerBinding::EventHandlerNonNull;
+use dom::bindings::codegen::Bindings::MessagePortBinding;
+use dom::bindings::codegen::Bindings::MessagePortBinding::MessagePortMethods;
+use dom::bindings::error::{Error, ErrorResult};
+use dom::bindings::inheritance::Castable;
+use dom::bindings::refcounted::Trusted;
+use dom::bindings::reflector::{DomObject, reflect_dom_object};
+use dom::bindings::root::DomRoot;
+use dom::bindings::structuredclone::StructuredCloneData;
+use dom::eventtarget::EventTarget;
+use dom::globalscope::GlobalScope;
+use dom::messageevent::MessageEvent;
+use dom_struct::dom_struct;
+use js::jsapi::{JSAutoCompartment, JSContext};
+use js::jsval::UndefinedValue;
+use js::rust::HandleValue;
+use msg::constellation_msg::MessagePortId;
+use script_traits::{PortMessageTask, ScriptMsg, TransferredPort};
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+use task_source::TaskSource;
+
+/// <https://html.spec.whatwg.org/multipage/#messageport>
+#[dom_struct]
+pub struct MessagePort {
+    eventtarget: EventTarget,
+    /// The id of this port, which stays the same when it gets transferred.
+    id: MessagePortId,
+    /// The id of the port this port is entangled with, if any.
+    entangled_port: Cell<Option<MessagePortId>>,
+    /// <https://html.spec.whatwg.org/multipage/#detached>
+    detached: Cell<bool>,
+    /// Whether the port message queue is enabled.
+    ///
+    /// <https://html.spec.whatwg.org/multipage/#port-message-queue>
+    enabled: Cell<bool>,
+    /// The messages received while the port message queue was disabled.
+    message_buffer: DomRefCell<VecDeque<PortMessageTask>>,
+}
+
+impl MessagePort {
+    fn new_inherited(id: MessagePortId) -> MessagePort {
+        MessagePort {
+            eventtarget: EventTarget::new_inherited(),
+            id: id,
+            entangled_port: Cell::new(None),
+            detached: Cell::new(false),
+            enabled: Cell::new(false),
+            message_buffer: DomRefCell::new(VecDeque::new()),
+        }
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#create-a-new-messageport-object>
+    pub fn new(owner: &GlobalScope) -> DomRoot<MessagePort> {
+        let port = reflect_dom_object(Box::new(MessagePort::new_inherited(MessagePortId::new())),
+                                      owner,
+                                      MessagePortBinding::Wrap);
+        owner.track_message_port(&port);
+        port
+    }
+
+    /// Revives a port that was transferred to the given global.
+    ///
+    /// <https://html.spec.whatwg.org/multipage/#message-ports:transfer-receiving-steps>
+    pub fn new_transferred(owner: &GlobalScope, transferred: TransferredPort) -> DomRoot<MessagePort> {
+        let port = reflect_dom_object(Box::new(MessagePort::new_inherited(transferred.id)),
+                                      owner,
+                                      MessagePortBinding::Wrap);
+        port.entangled_port.set(transferred.entangled_with);
+        port.message_buffer.borrow_mut().extend(transferred.queued);
+        owner.track_message_port(&port);
+        port
+    }
+
+    pub fn id(&self) -> MessagePortId {
+        self.id
+    }
+
+    pub fn detached(&self) -> bool {
+        self.detached.get()
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#entangle>
+    pub fn entangle(&self, other: &MessagePort) {
+        self.entangled_port.set(Some(other.id));
+        other.entangled_port.set(Some(self.id));
+        let msg = ScriptMsg::EntangleMessagePorts(self.id, other.id);
+        let _ = self.global().script_to_constellation_chan().send(msg);
+    }
+
+    /// Forgets about the port this port was entangled with, after it was closed or its global
+    /// went away. Messages posted to this port are dropped from then on.
+    ///
+    /// <https://html.spec.whatwg.org/multipage/#disentangle>
+    pub fn disentangle(&self) {
+        self.entangled_port.set(None);
+    }
+
+    /// Detaches this port so that it can be revived in another global.
+    ///
+    /// <https://html.spec.whatwg.org/multipage/#message-ports:transfer-steps>
+    pub fn transfer(&self) -> TransferredPort {
+        self.detached.set(true);
+        let global = self.global();
+        global.untrack_message_port(self);
+        let _ = global.script_to_constellation_chan().send(ScriptMsg::MessagePortShipped(self.id));
+        TransferredPort {
+            id: self.id,
+            entangled_with: self.entangled_port.get(),
+            queued: self.message_buffer.borrow_mut().drain(..).collect(),
+        }
+    }
+
+    /// Handles a message posted to the port this port is entangled with.
+    pub fn handle_message(&self, task: PortMessageTask) {
+        if self.enabled.get() {
+            self.dispatch_message(task);
+        } else {
+            self.message_buffer.borrow_mut().push_back(task);
+        }
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#dom-messageport-postmessage> step 7.
+    fn dispatch_message(&self, task: PortMessageTask) {
+        let global = self.global();
+        let cx = global.get_cx();
+        let _ac = JSAutoCompartment::new(cx, self.reflector().get_jsobject().get());
+        rooted!(in(cx) let mut message_clone = UndefinedValue());
+        let data = StructuredCloneData::Vector(task.data);
+        let new_ports = data.read_with_transfer(&global, task.ports, message_clone.handle_mut());
+        MessageEvent::dispatch_jsval(self.upcast(), &global, message_clone.handle(), None, new_ports);
+    }
+}
+
+impl MessagePortMethods for MessagePort {
+    #[allow(unsafe_code)]
+    // https://html.spec.whatwg.org/multipage/#dom-messageport-postmessage
+    unsafe fn PostMessage(&self,
+                          cx: *mut JSContext,
+                          message: HandleValue,
+                          transfer: Option<Vec<DomRoot<MessagePort>>>)
+                          -> ErrorResult {
+        if self.detached.get() {
+            return Ok(());
+        }
+        let transfer = transfer.unwrap_or(vec![]);
+
+        // Step 1.
+        let target_port = self.entangled_port.get();
+
+        // Step 2.
+        if transfer.iter().any(|port| port.id == self.id) {
+            return Err(Error::DataClone);
+        }
+
+        // Step 3.
+        let doomed = target_port.map_or(false, |target| transfer.iter().any(|port| port.id == target));
+
+        // Step 4.
+        let (data, ports) = StructuredCloneData::write_with_transfer(cx, message, transfer)?;
+
+        // Step 5.
+        let target_port = match target_port {
+            Some(target_port) if !doomed => target_port,
+            _ => return Ok(()),
+        };
+
+        // Step 6.
+        let task = PortMessageTask {
+            data: data.move_to_arraybuffer(),
+            ports: ports,
+        };
+        let _ = self.global().script_to_constellation_chan().send(ScriptMsg::PostMessageToPort(target_port, task));
+        Ok(())
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-messageport-start
+    fn Start(&self) {
+        if self.enabled.get() || self.detached.get() {
+            return;
+        }
+        self.enabled.set(true);
+
+        // Dispatch the messages that were received while the queue was disabled,
+        // unless the port gets transferred away in the meantime.
+        let this = Trusted::new(self);
+        let global = self.global();
+        let _ = global.port_message_queue().queue(
+            task!(process_port_message_queue: move || {
+                let port = this.root();
+                while !port.detached.get() {
+                    let task = port.message_buffer.borrow_mut().pop_front();
+                    match task {
+                        Some(task) => port.dispatch_message(task),
+                        None => break,
+                    }
+                }
+            }),
+            &global,
+        );
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-messageport-close
+    fn Close(&self) {
+        if self.detached.get() {
+            return;
+        }
+
+        // Step 1.
+        self.detached.set(true);
+
+        // Step 2.
+        self.entangled_port.set(None);
+        self.message_buffer.borrow_mut().clear();
+        let global = self.global();
+        global.untrack_message_port(self);
+        let _ = global.script_to_constellation_chan().send(ScriptMsg::RemoveMessagePort(self.id));
+    }
+
+    // https://html.spec.whatwg.org/multipage/#handler-messageport-onmessage
+    fn GetOnmessage(&self) -> Option<Rc<EventHandlerNonNull>> {
+        self.upcast::<EventTarget>().get_event_handler_common("message")
+    }
+
+    // https://html.spec.whatwg.org/multipage/#handler-messageport-onmessage
+    fn SetOnmessage(&self, listener: Option<Rc<EventHandlerNonNull>>) {
+        self.upcast::<EventTarget>().set_event_handler_common("message", listener);
+        // Setting the handler implicitly enables the port message queue.
+        self.Start();
+    }
+
+    // https://html.spec.whatwg.org/multipage/#handler-messageport-onmessageerror
+    event_handler!(messageerror, GetOnmessageerror, SetOnmessageerror);
+}
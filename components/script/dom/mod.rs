@@ -212,9 +212,15 @@ pub mod types {
     include!(concat!(env!("OUT_DIR"), "/build/InterfaceTypes.rs"));
 }
 
+pub mod abortcontroller;
+pub mod abortsignal;
 pub mod abstractworker;
 pub mod abstractworkerglobalscope;
 pub mod activation;
+pub mod animation;
+pub mod animationeffect;
+pub mod animationplaybackevent;
+pub mod animationtimeline;
 pub mod attr;
 pub mod audiobuffer;
 pub mod audiobuffersourcenode;
@@ -228,6 +234,7 @@ pub mod baseaudiocontext;
 pub mod beforeunloadevent;
 pub mod bindings;
 pub mod blob;
+pub mod broadcastchannel;
 pub mod bluetooth;
 pub mod bluetoothadvertisingevent;
 pub mod bluetoothcharacteristicproperties;
@@ -238,6 +245,8 @@ pub mod bluetoothremotegattdescriptor;
 pub mod bluetoothremotegattserver;
 pub mod bluetoothremotegattservice;
 pub mod bluetoothuuid;
+pub mod cache;
+pub mod cachestorage;
 pub mod canvasgradient;
 pub mod canvaspattern;
 pub mod canvasrenderingcontext2d;
@@ -251,6 +260,7 @@ pub mod console;
 mod create;
 pub mod crypto;
 pub mod css;
+pub mod cssanimation;
 pub mod cssconditionrule;
 pub mod cssfontfacerule;
 pub mod cssgroupingrule;
@@ -274,6 +284,7 @@ pub mod dissimilaroriginlocation;
 pub mod dissimilaroriginwindow;
 pub mod document;
 pub mod documentfragment;
+pub mod documenttimeline;
 pub mod documenttype;
 pub mod domexception;
 pub mod domimplementation;
@@ -285,6 +296,7 @@ pub mod dompointreadonly;
 pub mod domquad;
 pub mod domrect;
 pub mod domrectreadonly;
+pub mod domstringlist;
 pub mod domstringmap;
 pub mod domtokenlist;
 pub mod element;
@@ -294,11 +306,15 @@ pub mod eventsource;
 pub mod eventtarget;
 pub mod extendableevent;
 pub mod extendablemessageevent;
+pub mod fetchevent;
 pub mod file;
 pub mod filelist;
 pub mod filereader;
 pub mod filereadersync;
 pub mod focusevent;
+pub mod fontface;
+pub mod fontfaceset;
+pub mod fontfacesetloadevent;
 pub mod formdata;
 pub mod gainnode;
 pub mod gamepad;
@@ -364,6 +380,7 @@ pub mod htmlprogresselement;
 pub mod htmlquoteelement;
 pub mod htmlscriptelement;
 pub mod htmlselectelement;
+pub mod htmlslotelement;
 pub mod htmlsourceelement;
 pub mod htmlspanelement;
 pub mod htmlstyleelement;
@@ -383,15 +400,29 @@ pub mod htmltrackelement;
 pub mod htmlulistelement;
 pub mod htmlunknownelement;
 pub mod htmlvideoelement;
+pub mod idbcursor;
+pub mod idbcursorwithvalue;
+pub mod idbdatabase;
+pub mod idbfactory;
+pub mod idbindex;
+pub mod idbkeyrange;
+pub mod idbobjectstore;
+pub mod idbopendbrequest;
+pub mod idbrequest;
+pub mod idbtransaction;
+pub mod idbversionchangeevent;
 pub mod imagedata;
 pub mod inputevent;
 pub mod keyboardevent;
+pub mod keyframeeffect;
 pub mod location;
 pub mod mediaerror;
 pub mod medialist;
 pub mod mediaquerylist;
 pub mod mediaquerylistevent;
+pub mod messagechannel;
 pub mod messageevent;
+pub mod messageport;
 pub mod mimetype;
 pub mod mimetypearray;
 pub mod mouseevent;
@@ -430,22 +461,49 @@ pub mod promise;
 pub mod promisenativehandler;
 pub mod radionodelist;
 pub mod range;
+pub mod readablebytestreamcontroller;
+pub mod readablestream;
+pub mod readablestreambyobreader;
+pub mod readablestreamdefaultcontroller;
+pub mod readablestreamdefaultreader;
 pub mod request;
 pub mod response;
 pub mod screen;
+pub mod securitypolicyviolationevent;
 pub mod serviceworker;
 pub mod serviceworkercontainer;
 pub mod serviceworkerglobalscope;
 pub mod serviceworkerregistration;
 pub mod servoparser;
+pub mod shadowroot;
 pub mod storage;
 pub mod storageevent;
+pub mod streams;
 pub mod stylepropertymapreadonly;
 pub mod stylesheet;
 pub mod stylesheetlist;
+pub mod svgcircleelement;
+pub mod svgdefselement;
 pub mod svgelement;
+pub mod svgellipseelement;
+pub mod svggelement;
+pub mod svggeometryelement;
+pub mod svggradientelement;
 pub mod svggraphicselement;
+pub mod svglineargradientelement;
+pub mod svglineelement;
+pub mod svgpathelement;
+pub mod svgpolygonelement;
+pub mod svgpolylineelement;
+pub mod svgradialgradientelement;
+pub mod svgrectelement;
+pub mod svgstopelement;
 pub mod svgsvgelement;
+pub mod svgsymbolelement;
+pub mod svgtextcontentelement;
+pub mod svgtextelement;
+pub mod svgtextpositioningelement;
+pub mod svguseelement;
 pub mod testbinding;
 pub mod testbindingiterable;
 pub mod testbindingpairiterable;
@@ -457,9 +515,12 @@ pub mod text;
 pub mod textcontrol;
 pub mod textdecoder;
 pub mod textencoder;
+pub mod textmetrics;
 pub mod touch;
 pub mod touchevent;
 pub mod touchlist;
+pub mod transformstream;
+pub mod transformstreamdefaultcontroller;
 pub mod transitionevent;
 pub mod treewalker;
 pub mod uievent;
@@ -506,6 +567,9 @@ pub mod workerlocation;
 pub mod workernavigator;
 pub mod worklet;
 pub mod workletglobalscope;
+pub mod writablestream;
+pub mod writablestreamdefaultcontroller;
+pub mod writablestreamdefaultwriter;
 pub mod xmldocument;
 pub mod xmlhttprequest;
 pub mod xmlhttprequesteventtarget;
@@ -12,15 +12,15 @@ use dom::bindings::codegen::Bindings::CharacterDataBinding::CharacterDataMethods
 use dom::bindings::codegen::Bindings::DocumentBinding::DocumentMethods;
 use dom::bindings::codegen::Bindings::ElementBinding::ElementMethods;
 use dom::bindings::codegen::Bindings::HTMLCollectionBinding::HTMLCollectionMethods;
-use dom::bindings::codegen::Bindings::NodeBinding::{NodeConstants, NodeMethods};
+use dom::bindings::codegen::Bindings::NodeBinding::{GetRootNodeOptions, NodeConstants, NodeMethods};
 use dom::bindings::codegen::Bindings::NodeListBinding::NodeListMethods;
 use dom::bindings::codegen::Bindings::ProcessingInstructionBinding::ProcessingInstructionMethods;
 use dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
 use dom::bindings::codegen::UnionTypes::NodeOrString;
 use dom::bindings::conversions::{self, DerivedFrom};
 use dom::bindings::error::{Error, ErrorResult, Fallible};
-use dom::bindings::inheritance::{Castable, CharacterDataTypeId, ElementTypeId};
-use dom::bindings::inheritance::{EventTargetTypeId, HTMLElementTypeId, NodeTypeId};
+use dom::bindings::inheritance::{Castable, CharacterDataTypeId, DocumentFragmentTypeId};
+use dom::bindings::inheritance::{ElementTypeId, EventTargetTypeId, HTMLElementTypeId, NodeTypeId};
 use dom::bindings::inheritance::{SVGElementTypeId, SVGGraphicsElementTypeId};
 use dom::bindings::reflector::{DomObject, reflect_dom_object};
 use dom::bindings::root::{Dom, DomRoot, LayoutDom, MutNullableDom, RootedReference};
@@ -44,12 +44,15 @@ use dom::htmlimageelement::{HTMLImageElement, LayoutHTMLImageElementHelpers};
 use dom::htmlinputelement::{HTMLInputElement, LayoutHTMLInputElementHelpers};
 use dom::htmllinkelement::HTMLLinkElement;
 use dom::htmlmetaelement::HTMLMetaElement;
+use dom::htmlslotelement::{HTMLSlotElement, is_slottable};
 use dom::htmlstyleelement::HTMLStyleElement;
 use dom::htmltextareaelement::{HTMLTextAreaElement, LayoutHTMLTextAreaElementHelpers};
 use dom::mutationobserver::{Mutation, MutationObserver, RegisteredObserver};
 use dom::nodelist::NodeList;
 use dom::processinginstruction::ProcessingInstruction;
 use dom::range::WeakRangeVec;
+use dom::shadowroot::ShadowRoot;
+use dom::stylesheetlist::StyleSheetListOwner;
 use dom::svgsvgelement::{SVGSVGElement, LayoutSVGSVGElementHelpers};
 use dom::text::Text;
 use dom::virtualmethods::{VirtualMethods, vtable_for};
@@ -142,6 +145,11 @@ pub struct Node {
     /// Registered observers for this node.
     mutation_observers: DomRefCell<Vec<RegisteredObserver>>,
 
+    /// The slot this node is assigned to, if it is a slottable child of a shadow host.
+    ///
+    /// <https://dom.spec.whatwg.org/#slotable-assigned-slot>
+    assigned_slot: MutNullableDom<HTMLSlotElement>,
+
     unique_id: UniqueId,
 }
 
@@ -164,7 +172,10 @@ bitflags! {
                  to be reachable with using sequential focus navigation."]
         const SEQUENTIALLY_FOCUSABLE = 1 << 3;
 
-        // There are two free bits here.
+        #[doc = "Specifies whether this node is a shadow root or one of its descendants."]
+        const IS_IN_SHADOW_TREE = 1 << 4;
+
+        // There is one free bit here.
 
         #[doc = "Specifies whether the parser has set an associated form owner for \
                  this element. Only applicable for form-associatable elements."]
@@ -214,6 +225,13 @@ impl Node {
         }
     }
 
+    /// Disposes of the style and layout data of this node, if any.
+    pub fn dispose_style_and_layout_data(&self) {
+        if let Some(data) = self.style_and_layout_data.get() {
+            self.dispose(data);
+        }
+    }
+
     /// Adds a new child to the end of this node's list of children.
     ///
     /// Fails unless `new_child` is disconnected from the tree.
@@ -257,8 +275,10 @@ impl Node {
         self.children_count.set(self.children_count.get() + 1);
 
         let parent_in_doc = self.is_in_doc();
+        let parent_in_shadow_tree = self.is_in_shadow_tree();
         for node in new_child.traverse_preorder() {
             node.set_flag(NodeFlags::IS_IN_DOC, parent_in_doc);
+            node.set_flag(NodeFlags::IS_IN_SHADOW_TREE, parent_in_shadow_tree);
             // Out-of-document elements never have the descendants flag set.
             debug_assert!(!node.get_flag(NodeFlags::HAS_DIRTY_DESCENDANTS));
             vtable_for(&&*node).bind_to_tree(parent_in_doc);
@@ -298,8 +318,9 @@ impl Node {
 
         for node in child.traverse_preorder() {
             // Out-of-document elements never have the descendants flag set.
-            node.set_flag(NodeFlags::IS_IN_DOC | NodeFlags::HAS_DIRTY_DESCENDANTS |
-                          NodeFlags::HAS_SNAPSHOT | NodeFlags::HANDLED_SNAPSHOT,
+            node.set_flag(NodeFlags::IS_IN_DOC | NodeFlags::IS_IN_SHADOW_TREE |
+                          NodeFlags::HAS_DIRTY_DESCENDANTS | NodeFlags::HAS_SNAPSHOT |
+                          NodeFlags::HANDLED_SNAPSHOT,
                           false);
         }
         for node in child.traverse_preorder() {
@@ -319,6 +340,10 @@ impl Node {
         UntrustedNodeAddress(self.reflector().get_jsobject().get() as *const c_void)
     }
 
+    pub fn to_opaque(&self) -> OpaqueNode {
+        OpaqueNode(self.reflector().get_jsobject().get() as usize)
+    }
+
     pub fn as_custom_element(&self) -> Option<DomRoot<Element>> {
         self.downcast::<Element>()
             .and_then(|element| if element.get_custom_element_definition().is_some() {
@@ -429,6 +454,75 @@ impl Node {
         self.flags.get().contains(NodeFlags::IS_IN_DOC)
     }
 
+    pub fn is_in_shadow_tree(&self) -> bool {
+        self.flags.get().contains(NodeFlags::IS_IN_SHADOW_TREE)
+    }
+
+    /// Returns the shadow root of the shadow tree this node is in, if any.
+    pub fn containing_shadow_root(&self) -> Option<DomRoot<ShadowRoot>> {
+        if !self.is_in_shadow_tree() {
+            return None;
+        }
+        self.inclusive_ancestors().last().and_then(DomRoot::downcast::<ShadowRoot>)
+    }
+
+    /// Returns the node whose stylesheet list the `<style>` and `<link>` elements
+    /// in this node's tree contribute to.
+    #[allow(unrooted_must_root)]
+    pub fn stylesheet_list_owner(&self) -> StyleSheetListOwner {
+        match self.containing_shadow_root() {
+            Some(shadow_root) => StyleSheetListOwner::ShadowRoot(Dom::from_ref(&*shadow_root)),
+            None => StyleSheetListOwner::Document(Dom::from_ref(&*self.owner_doc())),
+        }
+    }
+
+    /// <https://dom.spec.whatwg.org/#concept-shadow-including-root>
+    pub fn shadow_including_root(&self) -> DomRoot<Node> {
+        let root = self.inclusive_ancestors().last().unwrap();
+        let host = root.downcast::<ShadowRoot>().map(|shadow_root| shadow_root.host());
+        match host {
+            Some(host) => host.upcast::<Node>().shadow_including_root(),
+            None => root,
+        }
+    }
+
+    /// <https://dom.spec.whatwg.org/#concept-shadow-including-inclusive-ancestor>
+    pub fn is_shadow_including_inclusive_ancestor_of(&self, node: &Node) -> bool {
+        let mut current = DomRoot::from_ref(node);
+        loop {
+            if self.is_inclusive_ancestor_of(&current) {
+                return true;
+            }
+            let host = current.containing_shadow_root().map(|shadow_root| shadow_root.host());
+            match host {
+                Some(host) => current = DomRoot::upcast(host),
+                None => return false,
+            }
+        }
+    }
+
+    /// <https://dom.spec.whatwg.org/#slotable-assigned-slot>
+    pub fn assigned_slot(&self) -> Option<DomRoot<HTMLSlotElement>> {
+        self.assigned_slot.get()
+    }
+
+    pub fn set_assigned_slot(&self, slot: Option<&HTMLSlotElement>) {
+        self.assigned_slot.set(slot);
+    }
+
+    /// Returns the parent element of this node in the flat tree, that is, the slot
+    /// it is assigned to, or the host if its parent is a shadow root.
+    pub fn traversal_parent(&self) -> Option<DomRoot<Element>> {
+        if let Some(slot) = self.assigned_slot() {
+            return Some(DomRoot::upcast(slot));
+        }
+        let parent = self.GetParentNode()?;
+        if let Some(shadow_root) = parent.downcast::<ShadowRoot>() {
+            return Some(shadow_root.host());
+        }
+        DomRoot::downcast(parent)
+    }
+
     /// Returns the type ID of this node.
     pub fn type_id(&self) -> NodeTypeId {
         match *self.eventtarget.type_id() {
@@ -491,12 +585,19 @@ impl Node {
     pub fn note_dirty_descendants(&self) {
         debug_assert!(self.is_in_doc());
 
-        for ancestor in self.inclusive_ancestors() {
+        // Walk up the flat tree, so that the style traversal reaches shadow trees
+        // and slotted nodes.
+        let mut current = Some(DomRoot::from_ref(self));
+        while let Some(ancestor) = current {
             if ancestor.get_flag(NodeFlags::HAS_DIRTY_DESCENDANTS) {
                 return;
             }
 
             ancestor.set_flag(NodeFlags::HAS_DIRTY_DESCENDANTS, true);
+            current = match ancestor.downcast::<ShadowRoot>() {
+                Some(shadow_root) => Some(DomRoot::upcast(shadow_root.host())),
+                None => ancestor.traversal_parent().map(DomRoot::upcast).or_else(|| ancestor.GetParentNode()),
+            };
         }
     }
 
@@ -524,8 +625,13 @@ impl Node {
         }
 
         match self.type_id() {
-            NodeTypeId::CharacterData(CharacterDataTypeId::Text) =>
-                self.parent_node.get().unwrap().downcast::<Element>().unwrap().restyle(damage),
+            NodeTypeId::CharacterData(CharacterDataTypeId::Text) => {
+                if let Some(parent) = self.traversal_parent() {
+                    parent.restyle(damage);
+                }
+            },
+            NodeTypeId::DocumentFragment(DocumentFragmentTypeId::ShadowRoot) =>
+                self.downcast::<ShadowRoot>().unwrap().host().restyle(damage),
             NodeTypeId::Element(_) =>
                 self.downcast::<Element>().unwrap().restyle(damage),
             _ => {},
@@ -816,6 +922,17 @@ impl Node {
         }
     }
 
+    /// <https://dom.spec.whatwg.org/#concept-shadow-including-inclusive-ancestor>
+    pub fn shadow_including_inclusive_ancestors(&self) -> impl Iterator<Item=DomRoot<Node>> {
+        SimpleNodeIterator {
+            current: Some(DomRoot::from_ref(self)),
+            next_node: |n| match n.downcast::<ShadowRoot>() {
+                Some(shadow_root) => Some(DomRoot::upcast(shadow_root.host())),
+                None => n.GetParentNode(),
+            },
+        }
+    }
+
     pub fn owner_doc(&self) -> DomRoot<Document> {
         self.owner_doc.get().unwrap()
     }
@@ -1014,6 +1131,7 @@ pub trait LayoutNodeHelpers {
     unsafe fn last_child_ref(&self) -> Option<LayoutDom<Node>>;
     unsafe fn prev_sibling_ref(&self) -> Option<LayoutDom<Node>>;
     unsafe fn next_sibling_ref(&self) -> Option<LayoutDom<Node>>;
+    unsafe fn assigned_slot_ref(&self) -> Option<LayoutDom<HTMLSlotElement>>;
 
     unsafe fn owner_doc_for_layout(&self) -> LayoutDom<Document>;
 
@@ -1081,6 +1199,12 @@ impl LayoutNodeHelpers for LayoutDom<Node> {
         (*self.unsafe_get()).next_sibling.get_inner_as_layout()
     }
 
+    #[inline]
+    #[allow(unsafe_code)]
+    unsafe fn assigned_slot_ref(&self) -> Option<LayoutDom<HTMLSlotElement>> {
+        (*self.unsafe_get()).assigned_slot.get_inner_as_layout()
+    }
+
     #[inline]
     #[allow(unsafe_code)]
     unsafe fn owner_doc_for_layout(&self) -> LayoutDom<Document> {
@@ -1422,6 +1546,8 @@ impl Node {
 
             mutation_observers: Default::default(),
 
+            assigned_slot: Default::default(),
+
             unique_id: UniqueId::new(),
         }
     }
@@ -1457,7 +1583,7 @@ impl Node {
         // Step 1.
         match parent.type_id() {
             NodeTypeId::Document(_) |
-            NodeTypeId::DocumentFragment |
+            NodeTypeId::DocumentFragment(_) |
             NodeTypeId::Element(..) => (),
             _ => return Err(Error::HierarchyRequest)
         }
@@ -1486,7 +1612,7 @@ impl Node {
                     return Err(Error::HierarchyRequest);
                 }
             },
-            NodeTypeId::DocumentFragment |
+            NodeTypeId::DocumentFragment(_) |
             NodeTypeId::Element(_) |
             NodeTypeId::CharacterData(CharacterDataTypeId::ProcessingInstruction) |
             NodeTypeId::CharacterData(CharacterDataTypeId::Comment) => (),
@@ -1497,7 +1623,7 @@ impl Node {
         if parent.is::<Document>() {
             match node.type_id() {
                 // Step 6.1
-                NodeTypeId::DocumentFragment => {
+                NodeTypeId::DocumentFragment(_) => {
                     // Step 6.1.1(b)
                     if node.children()
                            .any(|c| c.is::<Text>())
@@ -1614,7 +1740,7 @@ impl Node {
             }
         }
         rooted_vec!(let mut new_nodes);
-        let new_nodes = if let NodeTypeId::DocumentFragment = node.type_id() {
+        let new_nodes = if let NodeTypeId::DocumentFragment(_) = node.type_id() {
             // Step 3.
             new_nodes.extend(node.children().map(|kid| Dom::from_ref(&*kid)));
             // Step 4.
@@ -1651,6 +1777,24 @@ impl Node {
         for kid in new_nodes {
             // Step 7.1.
             parent.add_child(*kid, child);
+            // Step 7.4.
+            let parent_is_shadow_host = parent.downcast::<Element>()
+                .map_or(false, |parent| parent.shadow_root().is_some());
+            if parent_is_shadow_host && is_slottable(kid) {
+                HTMLSlotElement::assign_a_slot(kid);
+            }
+            // Step 7.5.
+            if parent.is_in_shadow_tree() {
+                if let Some(slot) = parent.downcast::<HTMLSlotElement>() {
+                    if !slot.has_assigned_nodes() {
+                        slot.signal_a_slot_change();
+                    }
+                }
+            }
+            // Step 7.6.
+            if let Some(shadow_root) = kid.containing_shadow_root() {
+                HTMLSlotElement::assign_slottables_for_a_tree(shadow_root.upcast());
+            }
             // Step 7.7.
             for descendant in kid.traverse_preorder().filter_map(DomRoot::downcast::<Element>) {
                 // Step 7.7.2.
@@ -1690,7 +1834,7 @@ impl Node {
         // Step 3.
         rooted_vec!(let mut added_nodes);
         let added_nodes = if let Some(node) = node.as_ref() {
-            if let NodeTypeId::DocumentFragment = node.type_id() {
+            if let NodeTypeId::DocumentFragment(_) = node.type_id() {
                 added_nodes.extend(node.children().map(|child| Dom::from_ref(&*child)));
                 added_nodes.r()
             } else {
@@ -1763,6 +1907,24 @@ impl Node {
         let old_next_sibling = node.GetNextSibling();
         // Steps 9-10 are handled in unbind_from_tree.
         parent.remove_child(node, cached_index);
+        // https://dom.spec.whatwg.org/#concept-node-remove steps 10-12, which
+        // keep the slot assignments up to date.
+        if let Some(slot) = node.assigned_slot() {
+            slot.assign_slottables();
+        }
+        if parent.is_in_shadow_tree() {
+            if let Some(slot) = parent.downcast::<HTMLSlotElement>() {
+                if !slot.has_assigned_nodes() {
+                    slot.signal_a_slot_change();
+                }
+            }
+            if node.traverse_preorder().any(|descendant| descendant.is::<HTMLSlotElement>()) {
+                if let Some(shadow_root) = parent.containing_shadow_root() {
+                    HTMLSlotElement::assign_slottables_for_a_tree(shadow_root.upcast());
+                }
+                HTMLSlotElement::assign_slottables_for_a_tree(node);
+            }
+        }
         // Step 11. transient registered observers
         // Step 12.
         if let SuppressObserver::Unsuppressed = suppress_observers {
@@ -1802,7 +1964,7 @@ impl Node {
                                                 &document);
                 DomRoot::upcast::<Node>(doctype)
             },
-            NodeTypeId::DocumentFragment => {
+            NodeTypeId::DocumentFragment(_) => {
                 let doc_fragment = DocumentFragment::new(&document);
                 DomRoot::upcast::<Node>(doc_fragment)
             },
@@ -1925,7 +2087,7 @@ impl Node {
                     .GetDocumentElement().as_ref()
                     .map_or(ns!(), |elem| elem.locate_namespace(prefix))
             },
-            NodeTypeId::DocumentType | NodeTypeId::DocumentFragment => ns!(),
+            NodeTypeId::DocumentType | NodeTypeId::DocumentFragment(_) => ns!(),
             _ => {
                 node.GetParentElement().as_ref()
                     .map_or(ns!(), |elem| elem.locate_namespace(prefix))
@@ -1948,7 +2110,7 @@ impl NodeMethods for Node {
                 NodeConstants::DOCUMENT_NODE,
             NodeTypeId::DocumentType =>
                 NodeConstants::DOCUMENT_TYPE_NODE,
-            NodeTypeId::DocumentFragment =>
+            NodeTypeId::DocumentFragment(_) =>
                 NodeConstants::DOCUMENT_FRAGMENT_NODE,
             NodeTypeId::Element(_) =>
                 NodeConstants::ELEMENT_NODE,
@@ -1969,7 +2131,7 @@ impl NodeMethods for Node {
             NodeTypeId::DocumentType => {
                 self.downcast::<DocumentType>().unwrap().name().clone()
             },
-            NodeTypeId::DocumentFragment => DOMString::from("#document-fragment"),
+            NodeTypeId::DocumentFragment(_) => DOMString::from("#document-fragment"),
             NodeTypeId::Document(_) => DOMString::from("#document")
         }
     }
@@ -1985,14 +2147,18 @@ impl NodeMethods for Node {
             NodeTypeId::CharacterData(..) |
             NodeTypeId::Element(..) |
             NodeTypeId::DocumentType |
-            NodeTypeId::DocumentFragment => Some(self.owner_doc()),
+            NodeTypeId::DocumentFragment(_) => Some(self.owner_doc()),
             NodeTypeId::Document(_) => None
         }
     }
 
     // https://dom.spec.whatwg.org/#dom-node-getrootnode
-    fn GetRootNode(&self) -> DomRoot<Node> {
-        self.inclusive_ancestors().last().unwrap()
+    fn GetRootNode(&self, options: &GetRootNodeOptions) -> DomRoot<Node> {
+        if options.composed {
+            self.shadow_including_root()
+        } else {
+            self.inclusive_ancestors().last().unwrap()
+        }
     }
 
     // https://dom.spec.whatwg.org/#dom-node-parentnode
@@ -2054,7 +2220,7 @@ impl NodeMethods for Node {
     // https://dom.spec.whatwg.org/#dom-node-textcontent
     fn GetTextContent(&self) -> Option<DOMString> {
         match self.type_id() {
-            NodeTypeId::DocumentFragment |
+            NodeTypeId::DocumentFragment(_) |
             NodeTypeId::Element(..) => {
                 let content = Node::collect_text_contents(self.traverse_preorder());
                 Some(content)
@@ -2074,7 +2240,7 @@ impl NodeMethods for Node {
     fn SetTextContent(&self, value: Option<DOMString>) {
         let value = value.unwrap_or_default();
         match self.type_id() {
-            NodeTypeId::DocumentFragment |
+            NodeTypeId::DocumentFragment(_) |
             NodeTypeId::Element(..) => {
                 // Step 1-2.
                 let node = if value.is_empty() {
@@ -2110,7 +2276,7 @@ impl NodeMethods for Node {
         // Step 1.
         match self.type_id() {
             NodeTypeId::Document(_) |
-            NodeTypeId::DocumentFragment |
+            NodeTypeId::DocumentFragment(_) |
             NodeTypeId::Element(..) => (),
             _ => return Err(Error::HierarchyRequest)
         }
@@ -2138,7 +2304,7 @@ impl NodeMethods for Node {
         if self.is::<Document>() {
             match node.type_id() {
                 // Step 6.1
-                NodeTypeId::DocumentFragment => {
+                NodeTypeId::DocumentFragment(_) => {
                     // Step 6.1.1(b)
                     if node.children()
                            .any(|c| c.is::<Text>())
@@ -2219,7 +2385,7 @@ impl NodeMethods for Node {
 
         // Step 12.
         rooted_vec!(let mut nodes);
-        let nodes = if node.type_id() == NodeTypeId::DocumentFragment {
+        let nodes = if node.is::<DocumentFragment>() {
             nodes.extend(node.children().map(|node| Dom::from_ref(&*node)));
             nodes.r()
         } else {
@@ -2280,12 +2446,18 @@ impl NodeMethods for Node {
     }
 
     // https://dom.spec.whatwg.org/#dom-node-clonenode
-    fn CloneNode(&self, deep: bool) -> DomRoot<Node> {
-        Node::clone(self, None, if deep {
+    fn CloneNode(&self, deep: bool) -> Fallible<DomRoot<Node>> {
+        // Step 1.
+        if self.is::<ShadowRoot>() {
+            return Err(Error::NotSupported);
+        }
+
+        // Step 2.
+        Ok(Node::clone(self, None, if deep {
             CloneChildrenFlag::CloneChildren
         } else {
             CloneChildrenFlag::DoNotCloneChildren
-        })
+        }))
     }
 
     // https://dom.spec.whatwg.org/#dom-node-isequalnode
@@ -2473,7 +2645,7 @@ impl NodeMethods for Node {
                     element.lookup_prefix(namespace)
                 })
             },
-            NodeTypeId::DocumentType | NodeTypeId::DocumentFragment => None,
+            NodeTypeId::DocumentType | NodeTypeId::DocumentFragment(_) => None,
             _ => {
                 self.GetParentElement().and_then(|element| {
                     element.lookup_prefix(namespace)
@@ -2700,6 +2872,19 @@ impl<'a> UnbindContext<'a> {
         self.index.set(Some(index));
         index
     }
+
+    /// The owner of the stylesheet list that `node`, an inclusive descendant of
+    /// the removed node, contributed to while it was in the tree.
+    #[allow(unrooted_must_root)]
+    pub fn stylesheet_list_owner(&self, node: &Node) -> StyleSheetListOwner {
+        // Nodes of a shadow tree that is disconnected along with its host stay
+        // in that shadow tree.
+        if node.is_in_shadow_tree() {
+            node.stylesheet_list_owner()
+        } else {
+            self.parent.stylesheet_list_owner()
+        }
+    }
 }
 
 /// A node's unique ID, for devtools.
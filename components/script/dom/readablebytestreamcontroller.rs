@@ -0,0 +1,412 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::cell::DomRefCell;
+use dom::bindings::codegen::Bindings::ReadableByteStreamControllerBinding;
+use dom::bindings::codegen::Bindings::ReadableByteStreamControllerBinding::ReadableByteStreamControllerMethods;
+use dom::bindings::error::{Error, Fallible};
+use dom::bindings::reflector::{DomObject, Reflector, reflect_dom_object};
+use dom::bindings::root::{Dom, DomRoot};
+use dom::globalscope::GlobalScope;
+use dom::promise::Promise;
+use dom::promisenativehandler::Callback;
+use dom::readablestream::{ReadRequest, ReadableStream, ReadableStreamState, UnderlyingSource};
+use dom::streams::{create_read_result, create_uint8_array, error_to_jsval, invoke_or_noop, object_value};
+use dom::streams::{resolved_promise, upon_settlement};
+use dom_struct::dom_struct;
+use js::jsapi::{Heap, JSContext, JSObject, Type};
+use js::jsval::{Int32Value, UndefinedValue};
+use js::rust::{CustomAutoRooterGuard, HandleObject, HandleValue};
+use js::typedarray::ArrayBufferView;
+use std::cell::Cell;
+use std::cmp;
+use std::collections::VecDeque;
+use std::mem;
+use std::rc::Rc;
+
+/// A pending read into the view given to `ReadableStreamBYOBReader.read()`.
+///
+/// <https://streams.spec.whatwg.org/#pull-into-descriptor>
+#[derive(JSTraceable)]
+#[must_root]
+struct PullIntoDescriptor {
+    view: Box<Heap<*mut JSObject>>,
+    element_size: usize,
+    promise: Rc<Promise>,
+}
+
+/// <https://streams.spec.whatwg.org/#rbs-controller-class>
+#[dom_struct]
+pub struct ReadableByteStreamController {
+    reflector_: Reflector,
+    stream: Dom<ReadableStream>,
+    /// The underlying source, until the stream is closed, errored or cancelled.
+    #[ignore_malloc_size_of = "Rc"]
+    source: DomRefCell<Option<Rc<UnderlyingSource>>>,
+    queue: DomRefCell<VecDeque<Vec<u8>>>,
+    queue_total_size: Cell<usize>,
+    #[ignore_malloc_size_of = "Defined in rust-mozjs"]
+    pending_pull_intos: DomRefCell<VecDeque<PullIntoDescriptor>>,
+    started: Cell<bool>,
+    close_requested: Cell<bool>,
+    pulling: Cell<bool>,
+    pull_again: Cell<bool>,
+    high_water_mark: f64,
+}
+
+impl ReadableByteStreamController {
+    #[allow(unrooted_must_root)]
+    fn new(global: &GlobalScope,
+           stream: &ReadableStream,
+           source: UnderlyingSource,
+           high_water_mark: f64)
+           -> DomRoot<ReadableByteStreamController> {
+        reflect_dom_object(Box::new(ReadableByteStreamController {
+            reflector_: Reflector::new(),
+            stream: Dom::from_ref(stream),
+            source: DomRefCell::new(Some(Rc::new(source))),
+            queue: Default::default(),
+            queue_total_size: Cell::new(0),
+            pending_pull_intos: Default::default(),
+            started: Cell::new(false),
+            close_requested: Cell::new(false),
+            pulling: Cell::new(false),
+            pull_again: Cell::new(false),
+            high_water_mark: high_water_mark,
+        }), global, ReadableByteStreamControllerBinding::Wrap)
+    }
+
+    /// <https://streams.spec.whatwg.org/#set-up-readable-byte-stream-controller>
+    #[allow(unrooted_must_root)]
+    pub fn set_up(stream: &ReadableStream, source: UnderlyingSource, high_water_mark: f64) -> Fallible<()> {
+        let global = stream.global();
+        let controller = ReadableByteStreamController::new(&global, stream, source, high_water_mark);
+        stream.set_byte_controller(&controller);
+        let source = controller.source().expect("A new controller without a source");
+        let start_promise = source.start(&global, object_value(&*controller))?;
+        upon_settlement(&start_promise,
+                        ControllerReaction::new(&controller, ControllerStep::StartFulfilled),
+                        ControllerReaction::new(&controller, ControllerStep::StartRejected));
+        Ok(())
+    }
+
+    #[allow(unrooted_must_root)]
+    fn source(&self) -> Option<Rc<UnderlyingSource>> {
+        self.source.borrow().clone()
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-clear-algorithms>
+    fn clear_algorithms(&self) {
+        let source = self.source.borrow_mut().take();
+        drop(source);
+    }
+
+    pub fn num_pull_intos(&self) -> usize {
+        self.pending_pull_intos.borrow().len()
+    }
+
+    fn can_close_or_enqueue(&self) -> bool {
+        !self.close_requested.get() && self.stream.state() == ReadableStreamState::Readable
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-should-call-pull>
+    fn should_call_pull(&self) -> bool {
+        if !self.can_close_or_enqueue() || !self.started.get() {
+            return false;
+        }
+        if self.stream.num_read_requests() > 0 || self.num_pull_intos() > 0 {
+            return true;
+        }
+        self.desired_size().map_or(false, |size| size > 0.)
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-call-pull-if-needed>
+    #[allow(unrooted_must_root)]
+    fn call_pull_if_needed(&self) {
+        if !self.should_call_pull() {
+            return;
+        }
+        if self.pulling.get() {
+            self.pull_again.set(true);
+            return;
+        }
+        let source = match self.source() {
+            Some(source) => source,
+            None => return,
+        };
+        self.pulling.set(true);
+        let pull_promise = source.pull(&self.global(), object_value(self));
+        upon_settlement(&pull_promise,
+                        ControllerReaction::new(self, ControllerStep::PullFulfilled),
+                        ControllerReaction::new(self, ControllerStep::PullRejected));
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-get-desired-size>
+    fn desired_size(&self) -> Option<f64> {
+        match self.stream.state() {
+            ReadableStreamState::Errored => None,
+            ReadableStreamState::Closed => Some(0.),
+            ReadableStreamState::Readable => Some(self.high_water_mark - self.queue_total_size.get() as f64),
+        }
+    }
+
+    /// Takes up to `length` bytes from the front of the queue.
+    fn dequeue_bytes(&self, length: usize) -> Vec<u8> {
+        let mut queue = self.queue.borrow_mut();
+        let mut bytes = Vec::with_capacity(length);
+        while bytes.len() < length {
+            let mut chunk = match queue.pop_front() {
+                Some(chunk) => chunk,
+                None => break,
+            };
+            let wanted = length - bytes.len();
+            if chunk.len() > wanted {
+                let rest = chunk.split_off(wanted);
+                queue.push_front(rest);
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+        self.queue_total_size.set(self.queue_total_size.get() - bytes.len());
+        bytes
+    }
+
+    /// Closes the stream if it was requested and the queue has been drained.
+    fn close_if_drained(&self) -> bool {
+        if self.close_requested.get() && self.queue_total_size.get() == 0 &&
+           self.stream.state() == ReadableStreamState::Readable {
+            self.clear_algorithms();
+            self.stream.close();
+            return true;
+        }
+        false
+    }
+
+    /// Fills the pending views from the queue, a whole number of elements at a time.
+    ///
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-process-pull-into-descriptors-using-queue>
+    #[allow(unsafe_code)]
+    fn process_pull_intos(&self, cx: *mut JSContext) {
+        loop {
+            let (view, element_size, promise) = {
+                let pull_intos = self.pending_pull_intos.borrow();
+                match pull_intos.front() {
+                    Some(descriptor) if self.queue_total_size.get() >= descriptor.element_size => {
+                        (descriptor.view.get(), descriptor.element_size, descriptor.promise.clone())
+                    },
+                    _ => break,
+                }
+            };
+            self.pending_pull_intos.borrow_mut().pop_front();
+            rooted!(in(cx) let view = view);
+            let filled = unsafe {
+                typedarray!(in(cx) let array: ArrayBufferView = view.get());
+                let mut array = array.expect("A pull-into descriptor without a view");
+                let data = array.as_mut_slice();
+                let length = cmp::min(self.queue_total_size.get(), data.len());
+                let bytes = self.dequeue_bytes(length - length % element_size);
+                data[..bytes.len()].copy_from_slice(&bytes);
+                bytes.len()
+            };
+            unsafe { resolve_with_subarray(cx, view.handle(), (filled / element_size) as i32, false, &promise) };
+        }
+        self.close_if_drained();
+    }
+
+    /// Performs a read into `view` for a BYOB reader.
+    ///
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-pull-into>
+    #[allow(unrooted_must_root, unsafe_code)]
+    pub fn pull_into(&self, cx: *mut JSContext, view: HandleObject, element_size: usize, promise: Rc<Promise>) {
+        if self.stream.state() == ReadableStreamState::Closed {
+            unsafe { resolve_with_subarray(cx, view, 0, true, &promise) };
+            return;
+        }
+        self.pending_pull_intos.borrow_mut().push_back(PullIntoDescriptor {
+            view: Heap::boxed(view.get()),
+            element_size: element_size,
+            promise: promise,
+        });
+        self.process_pull_intos(cx);
+        self.call_pull_if_needed();
+    }
+
+    /// Resolves the pending reads into views once the stream is closed.
+    #[allow(unsafe_code)]
+    pub fn close_pull_intos(&self, cx: *mut JSContext) {
+        let pull_intos = mem::replace(&mut *self.pending_pull_intos.borrow_mut(), VecDeque::new());
+        for descriptor in pull_intos {
+            rooted!(in(cx) let view = descriptor.view.get());
+            unsafe { resolve_with_subarray(cx, view.handle(), 0, true, &descriptor.promise) };
+        }
+    }
+
+    /// Rejects the pending reads into views with `error`.
+    #[allow(unsafe_code)]
+    pub fn error_pull_intos(&self, cx: *mut JSContext, error: HandleValue) {
+        let pull_intos = mem::replace(&mut *self.pending_pull_intos.borrow_mut(), VecDeque::new());
+        for descriptor in pull_intos {
+            unsafe { descriptor.promise.reject(cx, error) };
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-close>
+    pub fn close(&self) -> Fallible<()> {
+        if !self.can_close_or_enqueue() {
+            return Err(Error::Type("The stream can't be closed".to_owned()));
+        }
+        self.close_requested.set(true);
+        self.close_if_drained();
+        Ok(())
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-error>
+    pub fn error(&self, cx: *mut JSContext, error: HandleValue) {
+        if self.stream.state() != ReadableStreamState::Readable {
+            return;
+        }
+        self.queue.borrow_mut().clear();
+        self.queue_total_size.set(0);
+        self.clear_algorithms();
+        self.stream.error(cx, error);
+    }
+
+    /// <https://streams.spec.whatwg.org/#rbs-controller-private-pull>
+    #[allow(unrooted_must_root, unsafe_code)]
+    pub fn pull_steps(&self, cx: *mut JSContext, request: ReadRequest) {
+        if self.queue_total_size.get() == 0 {
+            self.stream.add_read_request(request);
+            self.call_pull_if_needed();
+            return;
+        }
+        let length = self.queue.borrow().front().map_or(0, |chunk| chunk.len());
+        let bytes = self.dequeue_bytes(length);
+        rooted!(in(cx) let mut chunk = UndefinedValue());
+        unsafe { create_uint8_array(cx, &bytes, chunk.handle_mut()) };
+        if !self.close_if_drained() {
+            self.call_pull_if_needed();
+        }
+        request.chunk_steps(cx, chunk.handle());
+    }
+
+    /// <https://streams.spec.whatwg.org/#rbs-controller-private-cancel>
+    #[allow(unrooted_must_root)]
+    pub fn cancel_steps(&self, cx: *mut JSContext, reason: HandleValue) -> Rc<Promise> {
+        self.queue.borrow_mut().clear();
+        self.queue_total_size.set(0);
+        let global = self.global();
+        let result = match self.source() {
+            Some(source) => source.cancel(&global, cx, reason),
+            None => resolved_promise(&global),
+        };
+        self.clear_algorithms();
+        result
+    }
+}
+
+impl ReadableByteStreamControllerMethods for ReadableByteStreamController {
+    /// <https://streams.spec.whatwg.org/#rbs-controller-desired-size>
+    fn GetDesiredSize(&self) -> Option<f64> {
+        self.desired_size()
+    }
+
+    /// <https://streams.spec.whatwg.org/#rbs-controller-close>
+    fn Close(&self) -> Fallible<()> {
+        self.close()
+    }
+
+    /// <https://streams.spec.whatwg.org/#rbs-controller-enqueue>
+    #[allow(unsafe_code)]
+    fn Enqueue(&self, chunk: CustomAutoRooterGuard<ArrayBufferView>) -> Fallible<()> {
+        let bytes = chunk.to_vec();
+        if bytes.is_empty() {
+            return Err(Error::Type("Can't enqueue an empty chunk".to_owned()));
+        }
+        if !self.can_close_or_enqueue() {
+            return Err(Error::Type("The stream can't be enqueued into".to_owned()));
+        }
+        let cx = self.global().get_cx();
+        if self.stream.num_read_requests() > 0 {
+            rooted!(in(cx) let mut chunk = UndefinedValue());
+            unsafe { create_uint8_array(cx, &bytes, chunk.handle_mut()) };
+            self.stream.fulfill_read_request(cx, chunk.handle());
+        } else {
+            self.queue_total_size.set(self.queue_total_size.get() + bytes.len());
+            self.queue.borrow_mut().push_back(bytes);
+            self.process_pull_intos(cx);
+        }
+        self.call_pull_if_needed();
+        Ok(())
+    }
+
+    /// <https://streams.spec.whatwg.org/#rbs-controller-error>
+    #[allow(unsafe_code)]
+    unsafe fn Error(&self, cx: *mut JSContext, error: HandleValue) {
+        self.error(cx, error)
+    }
+}
+
+/// The size in bytes of the elements of a typed array.
+pub fn element_size(array_type: Type) -> usize {
+    match array_type {
+        Type::Int16 | Type::Uint16 => 2,
+        Type::Int32 | Type::Uint32 | Type::Float32 => 4,
+        Type::Float64 => 8,
+        _ => 1,
+    }
+}
+
+/// Resolves `promise` with a read result holding the first `length` elements of `view`.
+#[allow(unsafe_code)]
+unsafe fn resolve_with_subarray(cx: *mut JSContext, view: HandleObject, length: i32, done: bool, promise: &Promise) {
+    rooted!(in(cx) let mut subarray = UndefinedValue());
+    let args = [Int32Value(0), Int32Value(length)];
+    if let Err(error) = invoke_or_noop(cx, view, "subarray", &args, subarray.handle_mut()) {
+        error_to_jsval(cx, &promise.global(), error, subarray.handle_mut());
+        return promise.reject(cx, subarray.handle());
+    }
+    rooted!(in(cx) let mut result = UndefinedValue());
+    create_read_result(cx, subarray.handle(), done, result.handle_mut());
+    promise.resolve(cx, result.handle());
+}
+
+#[derive(JSTraceable, MallocSizeOf)]
+enum ControllerStep {
+    StartFulfilled,
+    StartRejected,
+    PullFulfilled,
+    PullRejected,
+}
+
+/// Reacts to the promises returned by the `start` and `pull` methods of the underlying source.
+#[derive(JSTraceable, MallocSizeOf)]
+#[must_root]
+struct ControllerReaction {
+    controller: Dom<ReadableByteStreamController>,
+    step: ControllerStep,
+}
+
+impl ControllerReaction {
+    fn new(controller: &ReadableByteStreamController, step: ControllerStep) -> Box<Callback> {
+        Box::new(ControllerReaction { controller: Dom::from_ref(controller), step: step })
+    }
+}
+
+impl Callback for ControllerReaction {
+    fn callback(&self, cx: *mut JSContext, v: HandleValue) {
+        let controller = &self.controller;
+        match self.step {
+            ControllerStep::StartFulfilled => {
+                controller.started.set(true);
+                controller.call_pull_if_needed();
+            },
+            ControllerStep::PullFulfilled => {
+                controller.pulling.set(false);
+                if controller.pull_again.replace(false) {
+                    controller.call_pull_if_needed();
+                }
+            },
+            ControllerStep::StartRejected | ControllerStep::PullRejected => controller.error(cx, v),
+        }
+    }
+}
@@ -0,0 +1,978 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::cell::DomRefCell;
+use dom::bindings::codegen::Bindings::QueuingStrategyBinding::QueuingStrategy;
+use dom::bindings::codegen::Bindings::ReadableStreamBinding;
+use dom::bindings::codegen::Bindings::ReadableStreamBinding::{ReadableStreamGetReaderOptions, ReadableStreamMethods};
+use dom::bindings::codegen::Bindings::ReadableStreamBinding::{ReadableStreamReader, ReadableStreamReaderMode};
+use dom::bindings::codegen::Bindings::ReadableStreamBinding::{ReadableWritablePair, StreamPipeOptions};
+use dom::bindings::conversions::{StringificationBehavior, get_property};
+use dom::bindings::error::{Error, Fallible};
+use dom::bindings::reflector::{DomObject, Reflector, reflect_dom_object};
+use dom::bindings::root::{Dom, DomRoot, MutNullableDom};
+use dom::bindings::str::DOMString;
+use dom::bindings::trace::JSTraceable;
+use dom::globalscope::GlobalScope;
+use dom::promise::Promise;
+use dom::promisenativehandler::Callback;
+use dom::readablebytestreamcontroller::ReadableByteStreamController;
+use dom::readablestreambyobreader::ReadableStreamBYOBReader;
+use dom::readablestreamdefaultcontroller::ReadableStreamDefaultController;
+use dom::readablestreamdefaultreader::ReadableStreamDefaultReader;
+use dom::streams::{create_read_result, create_uint8_array, error_to_jsval, invoke_or_noop, object_value};
+use dom::streams::{promise_invoke_or_noop, rejected_promise, resolved_promise};
+use dom::streams::{transform_to_undefined, upon_fulfillment, upon_rejection, upon_settlement};
+use dom::streams::validate_high_water_mark;
+use dom::transformstream::TransformStream;
+use dom::writablestream::WritableStream;
+use dom::writablestreamdefaultwriter::WritableStreamDefaultWriter;
+use dom_struct::dom_struct;
+use fetch::FetchCanceller;
+use ipc_channel::ipc::IpcSender;
+use js::jsapi::{HandleValueArray, Heap, JSAutoCompartment, JSContext, JSObject, JS_NewArrayObject};
+use js::jsval::{JSVal, ObjectValue, UndefinedValue};
+use js::rust::{HandleValue, MutableHandleValue};
+use js::typedarray::Uint8Array;
+use malloc_size_of::MallocSizeOf;
+use std::cell::Cell;
+use std::ptr;
+use std::rc::Rc;
+
+/// <https://streams.spec.whatwg.org/#readablestream-state>
+#[derive(Clone, Copy, Debug, JSTraceable, MallocSizeOf, PartialEq)]
+pub enum ReadableStreamState {
+    Readable,
+    Closed,
+    Errored,
+}
+
+/// <https://streams.spec.whatwg.org/#rs-class>
+#[dom_struct]
+pub struct ReadableStream {
+    reflector_: Reflector,
+    state: Cell<ReadableStreamState>,
+    /// <https://streams.spec.whatwg.org/#readablestream-disturbed>
+    disturbed: Cell<bool>,
+    /// <https://streams.spec.whatwg.org/#readablestream-storederror>
+    #[ignore_malloc_size_of = "Defined in rust-mozjs"]
+    stored_error: Heap<JSVal>,
+    /// The controller of a stream of arbitrary chunks.
+    default_controller: MutNullableDom<ReadableStreamDefaultController>,
+    /// The controller of a byte stream, created with `type: "bytes"`.
+    byte_controller: MutNullableDom<ReadableByteStreamController>,
+    /// The reader that locks this stream, if it is a default reader.
+    default_reader: MutNullableDom<ReadableStreamDefaultReader>,
+    /// The reader that locks this stream, if it is a BYOB reader.
+    byob_reader: MutNullableDom<ReadableStreamBYOBReader>,
+}
+
+impl ReadableStream {
+    fn new_inherited() -> ReadableStream {
+        ReadableStream {
+            reflector_: Reflector::new(),
+            state: Cell::new(ReadableStreamState::Readable),
+            disturbed: Cell::new(false),
+            stored_error: Heap::default(),
+            default_controller: Default::default(),
+            byte_controller: Default::default(),
+            default_reader: Default::default(),
+            byob_reader: Default::default(),
+        }
+    }
+
+    fn new(global: &GlobalScope) -> DomRoot<ReadableStream> {
+        reflect_dom_object(Box::new(ReadableStream::new_inherited()),
+                           global,
+                           ReadableStreamBinding::Wrap)
+    }
+
+    /// <https://streams.spec.whatwg.org/#rs-constructor>
+    #[allow(unsafe_code)]
+    pub unsafe fn Constructor(cx: *mut JSContext,
+                              global: &GlobalScope,
+                              underlying_source: Option<*mut JSObject>,
+                              strategy: &QueuingStrategy)
+                              -> Fallible<DomRoot<ReadableStream>> {
+        rooted!(in(cx) let source = underlying_source.unwrap_or(ptr::null_mut()));
+        let source_type = if source.is_null() {
+            None
+        } else {
+            get_property::<DOMString>(cx, source.handle(), "type", StringificationBehavior::Default)?
+        };
+
+        let stream = ReadableStream::new(global);
+        match source_type {
+            None => {
+                let high_water_mark = validate_high_water_mark(strategy.highWaterMark, 1.)?;
+                ReadableStreamDefaultController::set_up(&stream,
+                                                        UnderlyingSource::Js(Heap::boxed(source.get())),
+                                                        high_water_mark,
+                                                        strategy.size.clone())?;
+            },
+            Some(ref source_type) if &**source_type == "bytes" => {
+                if strategy.size.is_some() {
+                    return Err(Error::Range("A byte stream can't have a size function".to_owned()));
+                }
+                let high_water_mark = validate_high_water_mark(strategy.highWaterMark, 0.)?;
+                ReadableByteStreamController::set_up(&stream,
+                                                     UnderlyingSource::Js(Heap::boxed(source.get())),
+                                                     high_water_mark)?;
+            },
+            Some(_) => return Err(Error::Range("Unknown underlying source type".to_owned())),
+        }
+        Ok(stream)
+    }
+
+    /// Creates a stream whose chunks come from Rust rather than an underlying source object.
+    ///
+    /// <https://streams.spec.whatwg.org/#create-readable-stream>
+    pub fn new_with_source(global: &GlobalScope,
+                           source: UnderlyingSource,
+                           high_water_mark: f64)
+                           -> DomRoot<ReadableStream> {
+        let stream = ReadableStream::new(global);
+        ReadableStreamDefaultController::set_up(&stream, source, high_water_mark, None)
+            .expect("Native underlying sources can't throw while starting");
+        stream
+    }
+
+    /// Creates a stream that the bytes of a fetch are enqueued into as they are received.
+    /// Cancelling the stream cancels the fetch. If there is a `demand_chan`, a message is sent on
+    /// it whenever the stream wants another chunk, so that the fetch can wait for that.
+    pub fn new_for_fetch(global: &GlobalScope,
+                         canceller: FetchCanceller,
+                         demand_chan: Option<IpcSender<()>>)
+                         -> DomRoot<ReadableStream> {
+        ReadableStream::new_with_source(global,
+                                        UnderlyingSource::Native(DomRefCell::new(canceller), demand_chan),
+                                        1.)
+    }
+
+    /// Creates a closed stream with `bytes` as its only chunk, as for the bodies of requests and
+    /// responses constructed with a buffer.
+    pub fn new_from_bytes(global: &GlobalScope, bytes: Vec<u8>) -> DomRoot<ReadableStream> {
+        let stream = ReadableStream::new_for_fetch(global, FetchCanceller::new(), None);
+        if !bytes.is_empty() {
+            stream.enqueue_native(bytes);
+        }
+        stream.close_native();
+        stream
+    }
+
+    /// Enqueues a chunk of bytes, as a `Uint8Array`.
+    #[allow(unsafe_code)]
+    pub fn enqueue_native(&self, bytes: Vec<u8>) {
+        let controller = match self.default_controller.get() {
+            Some(controller) => controller,
+            None => return,
+        };
+        if !controller.can_close_or_enqueue() {
+            return;
+        }
+        let cx = self.global().get_cx();
+        let _ac = JSAutoCompartment::new(cx, self.reflector().get_jsobject().get());
+        rooted!(in(cx) let mut chunk = UndefinedValue());
+        unsafe { create_uint8_array(cx, &bytes, chunk.handle_mut()) };
+        let _ = controller.enqueue(cx, chunk.handle());
+    }
+
+    /// Closes the stream once the chunks that are already enqueued have been read.
+    pub fn close_native(&self) {
+        if let Some(controller) = self.default_controller.get() {
+            if controller.can_close_or_enqueue() {
+                let cx = self.global().get_cx();
+                let _ac = JSAutoCompartment::new(cx, self.reflector().get_jsobject().get());
+                let _ = controller.close();
+            }
+        }
+    }
+
+    /// Errors the stream with `error`.
+    #[allow(unsafe_code)]
+    pub fn error_native(&self, error: Error) {
+        let controller = match self.default_controller.get() {
+            Some(controller) => controller,
+            None => return,
+        };
+        let global = self.global();
+        let cx = global.get_cx();
+        let _ac = JSAutoCompartment::new(cx, self.reflector().get_jsobject().get());
+        rooted!(in(cx) let mut error_value = UndefinedValue());
+        unsafe { error.to_jsval(cx, &global, error_value.handle_mut()) };
+        controller.error(cx, error_value.handle());
+    }
+
+    pub fn state(&self) -> ReadableStreamState {
+        self.state.get()
+    }
+
+    pub fn stored_error(&self, mut rval: MutableHandleValue) {
+        rval.set(self.stored_error.get());
+    }
+
+    /// <https://streams.spec.whatwg.org/#is-readable-stream-locked>
+    pub fn is_locked(&self) -> bool {
+        self.default_reader.get().is_some() || self.byob_reader.get().is_some()
+    }
+
+    /// <https://streams.spec.whatwg.org/#is-readable-stream-disturbed>
+    pub fn is_disturbed(&self) -> bool {
+        self.disturbed.get()
+    }
+
+    pub fn default_controller(&self) -> Option<DomRoot<ReadableStreamDefaultController>> {
+        self.default_controller.get()
+    }
+
+    pub fn set_default_controller(&self, controller: &ReadableStreamDefaultController) {
+        self.default_controller.set(Some(controller));
+    }
+
+    pub fn byte_controller(&self) -> Option<DomRoot<ReadableByteStreamController>> {
+        self.byte_controller.get()
+    }
+
+    pub fn set_byte_controller(&self, controller: &ReadableByteStreamController) {
+        self.byte_controller.set(Some(controller));
+    }
+
+    pub fn default_reader(&self) -> Option<DomRoot<ReadableStreamDefaultReader>> {
+        self.default_reader.get()
+    }
+
+    pub fn set_default_reader(&self, reader: Option<&ReadableStreamDefaultReader>) {
+        self.default_reader.set(reader);
+    }
+
+    pub fn byob_reader(&self) -> Option<DomRoot<ReadableStreamBYOBReader>> {
+        self.byob_reader.get()
+    }
+
+    pub fn set_byob_reader(&self, reader: Option<&ReadableStreamBYOBReader>) {
+        self.byob_reader.set(reader);
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-get-num-read-requests>
+    pub fn num_read_requests(&self) -> usize {
+        self.default_reader.get().map_or(0, |reader| reader.num_read_requests())
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-get-num-read-into-requests>
+    pub fn num_read_into_requests(&self) -> usize {
+        self.byte_controller.get().map_or(0, |controller| controller.num_pull_intos())
+    }
+
+    pub fn set_disturbed(&self) {
+        self.disturbed.set(true);
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-add-read-request>
+    #[allow(unrooted_must_root)]
+    pub fn add_read_request(&self, request: ReadRequest) {
+        self.default_reader.get().expect("Read request without a default reader").add_read_request(request);
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-fulfill-read-request>
+    pub fn fulfill_read_request(&self, cx: *mut JSContext, chunk: HandleValue) {
+        let reader = self.default_reader.get().expect("Fulfilled a read request without a reader");
+        if let Some(request) = reader.take_read_request() {
+            request.chunk_steps(cx, chunk);
+        }
+    }
+
+    /// Marks the stream as disturbed and performs a read with `request`.
+    ///
+    /// <https://streams.spec.whatwg.org/#readable-stream-default-reader-read>
+    #[allow(unrooted_must_root)]
+    pub fn read(&self, cx: *mut JSContext, request: ReadRequest) {
+        self.disturbed.set(true);
+        match self.state.get() {
+            ReadableStreamState::Closed => request.close_steps(cx),
+            ReadableStreamState::Errored => {
+                rooted!(in(cx) let mut error = UndefinedValue());
+                self.stored_error(error.handle_mut());
+                request.error_steps(cx, error.handle());
+            },
+            ReadableStreamState::Readable => {
+                if let Some(controller) = self.default_controller.get() {
+                    controller.pull_steps(cx, request);
+                } else if let Some(controller) = self.byte_controller.get() {
+                    controller.pull_steps(cx, request);
+                }
+            },
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-close>
+    pub fn close(&self) {
+        assert_eq!(self.state.get(), ReadableStreamState::Readable);
+        self.state.set(ReadableStreamState::Closed);
+        let cx = self.global().get_cx();
+        if let Some(reader) = self.default_reader.get() {
+            reader.close(cx);
+        }
+        if let Some(reader) = self.byob_reader.get() {
+            reader.close(cx);
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-error>
+    pub fn error(&self, cx: *mut JSContext, error: HandleValue) {
+        assert_eq!(self.state.get(), ReadableStreamState::Readable);
+        self.state.set(ReadableStreamState::Errored);
+        self.stored_error.set(error.get());
+        if let Some(reader) = self.default_reader.get() {
+            reader.error(cx, error);
+        }
+        if let Some(reader) = self.byob_reader.get() {
+            reader.error(cx, error);
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-cancel>
+    #[allow(unrooted_must_root)]
+    pub fn cancel(&self, cx: *mut JSContext, reason: HandleValue) -> Rc<Promise> {
+        let global = self.global();
+        self.disturbed.set(true);
+        match self.state.get() {
+            ReadableStreamState::Closed => return resolved_promise(&global),
+            ReadableStreamState::Errored => {
+                rooted!(in(cx) let mut error = UndefinedValue());
+                self.stored_error(error.handle_mut());
+                return rejected_promise(&global, cx, error.handle());
+            },
+            ReadableStreamState::Readable => {},
+        }
+        self.close();
+        let source_cancel_promise = if let Some(controller) = self.default_controller.get() {
+            controller.cancel_steps(cx, reason)
+        } else {
+            self.byte_controller.get().expect("A stream without a controller").cancel_steps(cx, reason)
+        };
+        transform_to_undefined(&global, &source_cancel_promise)
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-tee>
+    #[allow(unrooted_must_root)]
+    pub fn tee(&self) -> Fallible<(DomRoot<ReadableStream>, DomRoot<ReadableStream>)> {
+        let global = self.global();
+        let reader = ReadableStreamDefaultReader::new(&global, self)?;
+        let tee = Rc::new(TeeState {
+            stream: Dom::from_ref(self),
+            reader: Dom::from_ref(&*reader),
+            reading: Cell::new(false),
+            read_again: Cell::new(false),
+            canceled1: Cell::new(false),
+            canceled2: Cell::new(false),
+            reason1: Heap::default(),
+            reason2: Heap::default(),
+            branch1: Default::default(),
+            branch2: Default::default(),
+            cancel_promise: Promise::new(&global),
+        });
+        let branch1 = ReadableStream::new_with_source(&global,
+                                                      UnderlyingSource::Tee(tee.clone(), TeeBranch::First),
+                                                      1.);
+        let branch2 = ReadableStream::new_with_source(&global,
+                                                      UnderlyingSource::Tee(tee.clone(), TeeBranch::Second),
+                                                      1.);
+        tee.branch1.set(Some(&branch1));
+        tee.branch2.set(Some(&branch2));
+        upon_rejection(&reader.closed_promise(), Box::new(TeeSourceErrored(tee)));
+        Ok((branch1, branch2))
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-pipe-to>
+    #[allow(unrooted_must_root)]
+    pub fn pipe_to(&self, destination: &WritableStream, options: &StreamPipeOptions) -> Rc<Promise> {
+        let global = self.global();
+        let promise = Promise::new(&global);
+        if self.is_locked() {
+            promise.reject_error(Error::Type("The source stream is locked".to_owned()));
+            return promise;
+        }
+        if destination.is_locked() {
+            promise.reject_error(Error::Type("The destination stream is locked".to_owned()));
+            return promise;
+        }
+        let reader = ReadableStreamDefaultReader::new(&global, self).expect("Locked an unlocked stream");
+        let writer = WritableStreamDefaultWriter::new(&global, destination).expect("Locked an unlocked stream");
+        self.disturbed.set(true);
+
+        let pipe = Rc::new(PipeState {
+            source: Dom::from_ref(self),
+            destination: Dom::from_ref(destination),
+            reader: Dom::from_ref(&*reader),
+            writer: Dom::from_ref(&*writer),
+            prevent_close: options.preventClose,
+            prevent_abort: options.preventAbort,
+            prevent_cancel: options.preventCancel,
+            shutting_down: Cell::new(false),
+            promise: promise.clone(),
+        });
+        upon_rejection(&reader.closed_promise(), PipeReaction::new(&pipe, PipeStep::SourceErrored));
+        upon_settlement(&writer.closed_promise(),
+                        PipeReaction::new(&pipe, PipeStep::DestinationClosed),
+                        PipeReaction::new(&pipe, PipeStep::DestinationErrored));
+        PipeState::pump(&pipe, global.get_cx());
+        promise
+    }
+}
+
+impl ReadableStreamMethods for ReadableStream {
+    /// <https://streams.spec.whatwg.org/#rs-locked>
+    fn Locked(&self) -> bool {
+        self.is_locked()
+    }
+
+    /// <https://streams.spec.whatwg.org/#rs-cancel>
+    #[allow(unrooted_must_root, unsafe_code)]
+    unsafe fn Cancel(&self, cx: *mut JSContext, reason: HandleValue) -> Rc<Promise> {
+        if self.is_locked() {
+            let promise = Promise::new(&self.global());
+            promise.reject_error(Error::Type("The stream is locked".to_owned()));
+            return promise;
+        }
+        self.cancel(cx, reason)
+    }
+
+    /// <https://streams.spec.whatwg.org/#rs-get-reader>
+    fn GetReader(&self, options: &ReadableStreamGetReaderOptions) -> Fallible<ReadableStreamReader> {
+        let global = self.global();
+        match options.mode {
+            None => Ok(ReadableStreamReader::ReadableStreamDefaultReader(
+                ReadableStreamDefaultReader::new(&global, self)?)),
+            Some(ReadableStreamReaderMode::Byob) => Ok(ReadableStreamReader::ReadableStreamBYOBReader(
+                ReadableStreamBYOBReader::new(&global, self)?)),
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#rs-pipe-through>
+    fn PipeThrough(&self,
+                   transform: &ReadableWritablePair,
+                   options: &StreamPipeOptions)
+                   -> Fallible<DomRoot<ReadableStream>> {
+        if self.is_locked() {
+            return Err(Error::Type("The source stream is locked".to_owned()));
+        }
+        if transform.writable.is_locked() {
+            return Err(Error::Type("The destination stream is locked".to_owned()));
+        }
+        self.pipe_to(&transform.writable, options);
+        Ok(DomRoot::from_ref(&*transform.readable))
+    }
+
+    /// <https://streams.spec.whatwg.org/#rs-pipe-to>
+    #[allow(unrooted_must_root)]
+    fn PipeTo(&self, destination: &WritableStream, options: &StreamPipeOptions) -> Rc<Promise> {
+        self.pipe_to(destination, options)
+    }
+
+    /// <https://streams.spec.whatwg.org/#rs-tee>
+    fn Tee(&self) -> Fallible<Vec<DomRoot<ReadableStream>>> {
+        let (branch1, branch2) = self.tee()?;
+        Ok(vec![branch1, branch2])
+    }
+}
+
+/// Where the chunks of a readable stream come from.
+#[derive(JSTraceable)]
+#[must_root]
+pub enum UnderlyingSource {
+    /// An underlying source object given by script.
+    Js(Box<Heap<*mut JSObject>>),
+    /// Chunks that are enqueued from Rust, like the bytes of a fetch, and the channel that
+    /// asks for more of them.
+    Native(DomRefCell<FetchCanceller>, Option<IpcSender<()>>),
+    /// One of the branches of a teed stream.
+    Tee(Rc<TeeState>, TeeBranch),
+    /// The readable side of a transform stream.
+    Transform(Dom<TransformStream>),
+}
+
+impl UnderlyingSource {
+    /// Runs the start algorithm of the source, whose result is wrapped in a promise. Only the
+    /// `start` method of a source object can throw.
+    #[allow(unrooted_must_root, unsafe_code)]
+    pub fn start(&self, global: &GlobalScope, controller: JSVal) -> Fallible<Rc<Promise>> {
+        match *self {
+            UnderlyingSource::Js(ref source) => unsafe {
+                let cx = global.get_cx();
+                rooted!(in(cx) let source = source.get());
+                rooted!(in(cx) let controller = controller);
+                rooted!(in(cx) let mut result = UndefinedValue());
+                invoke_or_noop(cx, source.handle(), "start", &[controller.get()], result.handle_mut())?;
+                Promise::new_resolved(global, cx, result.handle())
+            },
+            UnderlyingSource::Transform(ref stream) => Ok(stream.start_promise()),
+            _ => Ok(resolved_promise(global)),
+        }
+    }
+
+    #[allow(unrooted_must_root, unsafe_code)]
+    pub fn pull(&self, global: &GlobalScope, controller: JSVal) -> Rc<Promise> {
+        match *self {
+            UnderlyingSource::Js(ref source) => unsafe {
+                let cx = global.get_cx();
+                rooted!(in(cx) let source = source.get());
+                rooted!(in(cx) let controller = controller);
+                promise_invoke_or_noop(global, cx, source.handle(), "pull", &[controller.get()])
+            },
+            UnderlyingSource::Native(_, ref demand_chan) => {
+                // The fetch may have completed already.
+                if let Some(ref demand_chan) = *demand_chan {
+                    let _ = demand_chan.send(());
+                }
+                resolved_promise(global)
+            },
+            UnderlyingSource::Tee(ref tee, _) => TeeState::pull(tee, global.get_cx()),
+            UnderlyingSource::Transform(ref stream) => stream.readable_pull(),
+        }
+    }
+
+    #[allow(unrooted_must_root, unsafe_code)]
+    pub fn cancel(&self, global: &GlobalScope, cx: *mut JSContext, reason: HandleValue) -> Rc<Promise> {
+        match *self {
+            UnderlyingSource::Js(ref source) => unsafe {
+                rooted!(in(cx) let source = source.get());
+                promise_invoke_or_noop(global, cx, source.handle(), "cancel", &[reason.get()])
+            },
+            UnderlyingSource::Native(ref canceller, _) => {
+                canceller.borrow_mut().cancel();
+                resolved_promise(global)
+            },
+            UnderlyingSource::Tee(ref tee, branch) => TeeState::cancel(tee, cx, branch, reason),
+            UnderlyingSource::Transform(ref stream) => stream.readable_cancel(cx, reason),
+        }
+    }
+}
+
+/// The steps to run when a read request made by another algorithm is fulfilled.
+///
+/// <https://streams.spec.whatwg.org/#read-request>
+pub trait ReadRequestSteps: JSTraceable + MallocSizeOf {
+    fn chunk_steps(self: Box<Self>, cx: *mut JSContext, chunk: HandleValue);
+    fn close_steps(self: Box<Self>, cx: *mut JSContext);
+    fn error_steps(self: Box<Self>, cx: *mut JSContext, error: HandleValue);
+}
+
+/// <https://streams.spec.whatwg.org/#read-request>
+#[derive(JSTraceable)]
+#[must_root]
+pub enum ReadRequest {
+    /// A `read()` call from script, whose promise is resolved with a read result.
+    Promise(Rc<Promise>),
+    /// A read made by another algorithm, like piping or reading a whole body.
+    Native(Box<ReadRequestSteps>),
+}
+
+impl ReadRequest {
+    #[allow(unsafe_code)]
+    pub fn chunk_steps(self, cx: *mut JSContext, chunk: HandleValue) {
+        match self {
+            ReadRequest::Promise(promise) => unsafe {
+                rooted!(in(cx) let mut result = UndefinedValue());
+                create_read_result(cx, chunk, false, result.handle_mut());
+                promise.resolve(cx, result.handle());
+            },
+            ReadRequest::Native(steps) => steps.chunk_steps(cx, chunk),
+        }
+    }
+
+    #[allow(unsafe_code)]
+    pub fn close_steps(self, cx: *mut JSContext) {
+        match self {
+            ReadRequest::Promise(promise) => unsafe {
+                rooted!(in(cx) let mut result = UndefinedValue());
+                create_read_result(cx, HandleValue::undefined(), true, result.handle_mut());
+                promise.resolve(cx, result.handle());
+            },
+            ReadRequest::Native(steps) => steps.close_steps(cx),
+        }
+    }
+
+    #[allow(unsafe_code)]
+    pub fn error_steps(self, cx: *mut JSContext, error: HandleValue) {
+        match self {
+            ReadRequest::Promise(promise) => unsafe { promise.reject(cx, error) },
+            ReadRequest::Native(steps) => steps.error_steps(cx, error),
+        }
+    }
+}
+
+/// The steps to run once all the bytes of a stream have been read.
+pub trait ReadAllBytesSteps: JSTraceable + MallocSizeOf {
+    fn success_steps(self: Box<Self>, bytes: Vec<u8>);
+    fn failure_steps(self: Box<Self>, cx: *mut JSContext, error: HandleValue);
+}
+
+/// Reads all the chunks of the stream locked by `reader`, which must be `Uint8Array`s, and
+/// runs `steps` with their concatenation.
+///
+/// <https://streams.spec.whatwg.org/#readablestreamdefaultreader-read-all-bytes>
+#[allow(unrooted_must_root)]
+pub fn read_all_bytes(reader: &ReadableStreamDefaultReader, steps: Box<ReadAllBytesSteps>) {
+    let request = ReadAllBytes {
+        reader: Dom::from_ref(reader),
+        bytes: vec![],
+        steps: steps,
+    };
+    reader.read(reader.global().get_cx(), ReadRequest::Native(Box::new(request)));
+}
+
+#[derive(JSTraceable, MallocSizeOf)]
+#[must_root]
+struct ReadAllBytes {
+    reader: Dom<ReadableStreamDefaultReader>,
+    bytes: Vec<u8>,
+    steps: Box<ReadAllBytesSteps>,
+}
+
+impl ReadRequestSteps for ReadAllBytes {
+    #[allow(unrooted_must_root, unsafe_code)]
+    fn chunk_steps(self: Box<Self>, cx: *mut JSContext, chunk: HandleValue) {
+        let mut this = self;
+        let is_bytes = chunk.is_object() && unsafe {
+            typedarray!(in(cx) let array: Uint8Array = chunk.to_object());
+            match array {
+                Ok(array) => {
+                    this.bytes.extend_from_slice(array.as_slice());
+                    true
+                },
+                Err(()) => false,
+            }
+        };
+        if !is_bytes {
+            let global = this.reader.global();
+            rooted!(in(cx) let mut error = UndefinedValue());
+            unsafe {
+                Error::Type("The chunks of a body must be Uint8Arrays".to_owned())
+                    .to_jsval(cx, &global, error.handle_mut());
+            }
+            return this.steps.failure_steps(cx, error.handle());
+        }
+        let reader = DomRoot::from_ref(&*this.reader);
+        reader.read(cx, ReadRequest::Native(this));
+    }
+
+    fn close_steps(self: Box<Self>, _cx: *mut JSContext) {
+        let this = *self;
+        this.steps.success_steps(this.bytes);
+    }
+
+    fn error_steps(self: Box<Self>, cx: *mut JSContext, error: HandleValue) {
+        let this = *self;
+        this.steps.failure_steps(cx, error);
+    }
+}
+
+#[derive(Clone, Copy, JSTraceable, MallocSizeOf, PartialEq)]
+pub enum TeeBranch {
+    First,
+    Second,
+}
+
+/// The state shared by the two branches of a teed stream.
+///
+/// <https://streams.spec.whatwg.org/#abstract-opdef-readablestreamdefaulttee>
+#[derive(JSTraceable)]
+#[must_root]
+pub struct TeeState {
+    stream: Dom<ReadableStream>,
+    reader: Dom<ReadableStreamDefaultReader>,
+    reading: Cell<bool>,
+    read_again: Cell<bool>,
+    canceled1: Cell<bool>,
+    canceled2: Cell<bool>,
+    reason1: Heap<JSVal>,
+    reason2: Heap<JSVal>,
+    branch1: MutNullableDom<ReadableStream>,
+    branch2: MutNullableDom<ReadableStream>,
+    cancel_promise: Rc<Promise>,
+}
+
+impl TeeState {
+    #[allow(unrooted_must_root)]
+    fn pull(tee: &Rc<TeeState>, cx: *mut JSContext) -> Rc<Promise> {
+        let global = tee.stream.global();
+        if tee.reading.get() {
+            tee.read_again.set(true);
+            return resolved_promise(&global);
+        }
+        tee.reading.set(true);
+        tee.reader.read(cx, ReadRequest::Native(Box::new(TeeReadRequest(tee.clone()))));
+        resolved_promise(&global)
+    }
+
+    #[allow(unrooted_must_root, unsafe_code)]
+    fn cancel(tee: &Rc<TeeState>, cx: *mut JSContext, branch: TeeBranch, reason: HandleValue) -> Rc<Promise> {
+        match branch {
+            TeeBranch::First => {
+                tee.canceled1.set(true);
+                tee.reason1.set(reason.get());
+            },
+            TeeBranch::Second => {
+                tee.canceled2.set(true);
+                tee.reason2.set(reason.get());
+            },
+        }
+        if tee.canceled1.get() && tee.canceled2.get() {
+            let reasons = [tee.reason1.get(), tee.reason2.get()];
+            unsafe {
+                let reasons = HandleValueArray::from_rooted_slice(&reasons);
+                rooted!(in(cx) let composite_reason = JS_NewArrayObject(cx, &reasons));
+                rooted!(in(cx) let composite_reason = ObjectValue(composite_reason.get()));
+                let cancel_result = tee.stream.cancel(cx, composite_reason.handle());
+                rooted!(in(cx) let cancel_result = object_value(&*cancel_result));
+                tee.cancel_promise.resolve(cx, cancel_result.handle());
+            }
+        }
+        tee.cancel_promise.clone()
+    }
+
+    fn branches(&self) -> Vec<DomRoot<ReadableStream>> {
+        let mut branches = vec![];
+        if !self.canceled1.get() {
+            branches.extend(self.branch1.get());
+        }
+        if !self.canceled2.get() {
+            branches.extend(self.branch2.get());
+        }
+        branches
+    }
+}
+
+#[derive(JSTraceable, MallocSizeOf)]
+#[must_root]
+struct TeeReadRequest(#[ignore_malloc_size_of = "Rc"] Rc<TeeState>);
+
+impl ReadRequestSteps for TeeReadRequest {
+    fn chunk_steps(self: Box<Self>, cx: *mut JSContext, chunk: HandleValue) {
+        let tee = &self.0;
+        tee.read_again.set(false);
+        // Both branches get the same chunk, since we never need to clone it for the second one.
+        for branch in tee.branches() {
+            if let Some(controller) = branch.default_controller() {
+                let _ = controller.enqueue(cx, chunk);
+            }
+        }
+        tee.reading.set(false);
+        if tee.read_again.get() {
+            TeeState::pull(tee, cx);
+        }
+    }
+
+    fn close_steps(self: Box<Self>, _cx: *mut JSContext) {
+        let tee = &self.0;
+        tee.reading.set(false);
+        for branch in tee.branches() {
+            if let Some(controller) = branch.default_controller() {
+                let _ = controller.close();
+            }
+        }
+        if !tee.canceled1.get() || !tee.canceled2.get() {
+            tee.cancel_promise.resolve_native(&());
+        }
+    }
+
+    fn error_steps(self: Box<Self>, _cx: *mut JSContext, _error: HandleValue) {
+        self.0.reading.set(false);
+    }
+}
+
+/// Errors both branches when the teed stream errors.
+#[derive(JSTraceable, MallocSizeOf)]
+#[must_root]
+struct TeeSourceErrored(#[ignore_malloc_size_of = "Rc"] Rc<TeeState>);
+
+impl Callback for TeeSourceErrored {
+    fn callback(&self, cx: *mut JSContext, error: HandleValue) {
+        let tee = &self.0;
+        for branch in vec![tee.branch1.get(), tee.branch2.get()].into_iter().filter_map(|branch| branch) {
+            if let Some(controller) = branch.default_controller() {
+                controller.error(cx, error);
+            }
+        }
+        if !tee.canceled1.get() || !tee.canceled2.get() {
+            tee.cancel_promise.resolve_native(&());
+        }
+    }
+}
+
+/// The state of a pipe from a readable stream to a writable stream.
+///
+/// <https://streams.spec.whatwg.org/#readable-stream-pipe-to>
+#[derive(JSTraceable)]
+#[must_root]
+struct PipeState {
+    source: Dom<ReadableStream>,
+    destination: Dom<WritableStream>,
+    reader: Dom<ReadableStreamDefaultReader>,
+    writer: Dom<WritableStreamDefaultWriter>,
+    prevent_close: bool,
+    prevent_abort: bool,
+    prevent_cancel: bool,
+    shutting_down: Cell<bool>,
+    promise: Rc<Promise>,
+}
+
+impl PipeState {
+    /// Reads the next chunk once the destination is ready for it.
+    #[allow(unrooted_must_root)]
+    fn pump(pipe: &Rc<PipeState>, cx: *mut JSContext) {
+        if pipe.shutting_down.get() {
+            return;
+        }
+        match pipe.writer.desired_size() {
+            Ok(Some(size)) if size <= 0. => {
+                upon_fulfillment(&pipe.writer.ready_promise(), PipeReaction::new(pipe, PipeStep::Pump));
+            },
+            // An errored destination is taken care of by the reaction to its closed promise.
+            Ok(None) | Err(_) => {},
+            Ok(Some(_)) => {
+                pipe.reader.read(cx, ReadRequest::Native(Box::new(PipeReadRequest(pipe.clone()))));
+            },
+        }
+    }
+
+    /// Shuts the pipe down after performing `action`, if any, and settles the pipe's promise.
+    ///
+    /// <https://streams.spec.whatwg.org/#rs-pipeTo-shutdown-with-action>
+    #[allow(unrooted_must_root, unsafe_code)]
+    fn shutdown(pipe: &Rc<PipeState>, cx: *mut JSContext, action: Option<Rc<Promise>>, error: Option<HandleValue>) {
+        if pipe.shutting_down.replace(true) {
+            return;
+        }
+        let step = match error {
+            Some(error) => PipeStep::Finalize(Some(Heap::boxed(error.get()))),
+            None => PipeStep::Finalize(None),
+        };
+        match action {
+            Some(action) => upon_settlement(&action,
+                                            PipeReaction::new(pipe, step),
+                                            PipeReaction::new(pipe, PipeStep::FinalizeWithActionError)),
+            None => pipe.finalize(cx, error),
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#rs-pipeTo-finalize>
+    #[allow(unsafe_code)]
+    fn finalize(&self, cx: *mut JSContext, error: Option<HandleValue>) {
+        self.writer.release();
+        self.reader.release();
+        match error {
+            Some(error) => unsafe { self.promise.reject(cx, error) },
+            None => self.promise.resolve_native(&()),
+        }
+    }
+}
+
+#[derive(JSTraceable, MallocSizeOf)]
+#[must_root]
+struct PipeReadRequest(#[ignore_malloc_size_of = "Rc"] Rc<PipeState>);
+
+impl ReadRequestSteps for PipeReadRequest {
+    fn chunk_steps(self: Box<Self>, cx: *mut JSContext, chunk: HandleValue) {
+        let pipe = &self.0;
+        if pipe.shutting_down.get() {
+            return;
+        }
+        // Errors while writing show up as a rejection of the destination's closed promise.
+        pipe.writer.write(cx, chunk);
+        PipeState::pump(pipe, cx);
+    }
+
+    #[allow(unrooted_must_root)]
+    fn close_steps(self: Box<Self>, cx: *mut JSContext) {
+        let pipe = &self.0;
+        if pipe.prevent_close {
+            PipeState::shutdown(pipe, cx, None, None);
+        } else {
+            // Closing waits for the chunks that are still being written.
+            let action = pipe.writer.close_with_error_propagation(cx);
+            PipeState::shutdown(pipe, cx, Some(action), None);
+        }
+    }
+
+    fn error_steps(self: Box<Self>, cx: *mut JSContext, error: HandleValue) {
+        source_errored(&self.0, cx, error);
+    }
+}
+
+#[allow(unrooted_must_root)]
+fn source_errored(pipe: &Rc<PipeState>, cx: *mut JSContext, error: HandleValue) {
+    if pipe.prevent_abort {
+        PipeState::shutdown(pipe, cx, None, Some(error));
+    } else {
+        let action = pipe.destination.abort(cx, error);
+        PipeState::shutdown(pipe, cx, Some(action), Some(error));
+    }
+}
+
+#[allow(unrooted_must_root)]
+fn destination_errored(pipe: &Rc<PipeState>, cx: *mut JSContext, error: HandleValue) {
+    if pipe.prevent_cancel {
+        PipeState::shutdown(pipe, cx, None, Some(error));
+    } else {
+        let action = pipe.source.cancel(cx, error);
+        PipeState::shutdown(pipe, cx, Some(action), Some(error));
+    }
+}
+
+#[derive(JSTraceable, MallocSizeOf)]
+enum PipeStep {
+    Pump,
+    SourceErrored,
+    DestinationClosed,
+    DestinationErrored,
+    Finalize(#[ignore_malloc_size_of = "Defined in rust-mozjs"] Option<Box<Heap<JSVal>>>),
+    FinalizeWithActionError,
+}
+
+#[derive(JSTraceable, MallocSizeOf)]
+#[must_root]
+struct PipeReaction {
+    #[ignore_malloc_size_of = "Rc"]
+    pipe: Rc<PipeState>,
+    step: PipeStep,
+}
+
+impl PipeReaction {
+    #[allow(unrooted_must_root)]
+    fn new(pipe: &Rc<PipeState>, step: PipeStep) -> Box<Callback> {
+        Box::new(PipeReaction { pipe: pipe.clone(), step: step })
+    }
+}
+
+impl Callback for PipeReaction {
+    #[allow(unsafe_code)]
+    fn callback(&self, cx: *mut JSContext, v: HandleValue) {
+        let pipe = &self.pipe;
+        match self.step {
+            PipeStep::Pump => PipeState::pump(pipe, cx),
+            PipeStep::SourceErrored => source_errored(pipe, cx, v),
+            PipeStep::DestinationErrored => destination_errored(pipe, cx, v),
+            PipeStep::DestinationClosed => {
+                // The destination was closed by something else than this pipe.
+                let global = pipe.source.global();
+                rooted!(in(cx) let mut error = UndefinedValue());
+                unsafe {
+                    error_to_jsval(cx,
+                                   &global,
+                                   Error::Type("The destination stream was closed".to_owned()),
+                                   error.handle_mut());
+                }
+                destination_errored(pipe, cx, error.handle());
+            },
+            PipeStep::Finalize(ref error) => {
+                match *error {
+                    Some(ref error) => pipe.finalize(cx, Some(error.handle())),
+                    None => pipe.finalize(cx, None),
+                }
+            },
+            PipeStep::FinalizeWithActionError => pipe.finalize(cx, Some(v)),
+        }
+    }
+}
@@ -0,0 +1,160 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::cell::DomRefCell;
+use dom::bindings::codegen::Bindings::ReadableStreamBYOBReaderBinding;
+use dom::bindings::codegen::Bindings::ReadableStreamBYOBReaderBinding::ReadableStreamBYOBReaderMethods;
+use dom::bindings::error::{Error, Fallible};
+use dom::bindings::reflector::{DomObject, Reflector, reflect_dom_object};
+use dom::bindings::root::{DomRoot, MutNullableDom};
+use dom::globalscope::GlobalScope;
+use dom::promise::Promise;
+use dom::readablebytestreamcontroller::element_size;
+use dom::readablestream::{ReadableStream, ReadableStreamState};
+use dom::streams::{rejected_promise, resolved_promise};
+use dom_struct::dom_struct;
+use js::jsapi::JSContext;
+use js::jsval::UndefinedValue;
+use js::rust::{CustomAutoRooterGuard, HandleValue};
+use js::typedarray::ArrayBufferView;
+use std::rc::Rc;
+
+/// <https://streams.spec.whatwg.org/#byob-reader-class>
+#[dom_struct]
+pub struct ReadableStreamBYOBReader {
+    reflector_: Reflector,
+    /// The stream this reader locks, until the lock is released.
+    stream: MutNullableDom<ReadableStream>,
+    #[ignore_malloc_size_of = "Rc"]
+    closed_promise: DomRefCell<Rc<Promise>>,
+}
+
+impl ReadableStreamBYOBReader {
+    /// Acquires a BYOB reader for `stream`, which must be an unlocked byte stream.
+    ///
+    /// <https://streams.spec.whatwg.org/#set-up-readable-stream-byob-reader>
+    #[allow(unrooted_must_root, unsafe_code)]
+    pub fn new(global: &GlobalScope, stream: &ReadableStream) -> Fallible<DomRoot<ReadableStreamBYOBReader>> {
+        if stream.is_locked() {
+            return Err(Error::Type("The stream is locked".to_owned()));
+        }
+        if stream.byte_controller().is_none() {
+            return Err(Error::Type("The stream is not a byte stream".to_owned()));
+        }
+        let closed_promise = match stream.state() {
+            ReadableStreamState::Readable => Promise::new(global),
+            ReadableStreamState::Closed => resolved_promise(global),
+            ReadableStreamState::Errored => {
+                let cx = global.get_cx();
+                rooted!(in(cx) let mut error = UndefinedValue());
+                stream.stored_error(error.handle_mut());
+                rejected_promise(global, cx, error.handle())
+            },
+        };
+        let reader = reflect_dom_object(Box::new(ReadableStreamBYOBReader {
+            reflector_: Reflector::new(),
+            stream: MutNullableDom::new(Some(stream)),
+            closed_promise: DomRefCell::new(closed_promise),
+        }), global, ReadableStreamBYOBReaderBinding::Wrap);
+        stream.set_byob_reader(Some(&reader));
+        Ok(reader)
+    }
+
+    /// <https://streams.spec.whatwg.org/#byob-reader-constructor>
+    pub fn Constructor(global: &GlobalScope, stream: &ReadableStream) -> Fallible<DomRoot<ReadableStreamBYOBReader>> {
+        ReadableStreamBYOBReader::new(global, stream)
+    }
+
+    /// Resolves the closed promise and the pending reads, when the stream closes.
+    pub fn close(&self, cx: *mut JSContext) {
+        self.closed_promise.borrow().resolve_native(&());
+        if let Some(controller) = self.stream.get().and_then(|stream| stream.byte_controller()) {
+            controller.close_pull_intos(cx);
+        }
+    }
+
+    /// Rejects the closed promise and the pending reads, when the stream errors.
+    #[allow(unsafe_code)]
+    pub fn error(&self, cx: *mut JSContext, error: HandleValue) {
+        unsafe { self.closed_promise.borrow().reject(cx, error) };
+        if let Some(controller) = self.stream.get().and_then(|stream| stream.byte_controller()) {
+            controller.error_pull_intos(cx, error);
+        }
+    }
+}
+
+impl ReadableStreamBYOBReaderMethods for ReadableStreamBYOBReader {
+    /// <https://streams.spec.whatwg.org/#generic-reader-closed>
+    #[allow(unrooted_must_root)]
+    fn Closed(&self) -> Rc<Promise> {
+        self.closed_promise.borrow().clone()
+    }
+
+    /// <https://streams.spec.whatwg.org/#generic-reader-cancel>
+    #[allow(unrooted_must_root, unsafe_code)]
+    unsafe fn Cancel(&self, cx: *mut JSContext, reason: HandleValue) -> Rc<Promise> {
+        match self.stream.get() {
+            Some(stream) => stream.cancel(cx, reason),
+            None => {
+                let promise = Promise::new(&self.global());
+                promise.reject_error(Error::Type("The reader has released its lock".to_owned()));
+                promise
+            },
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#byob-reader-read>
+    #[allow(unrooted_must_root, unsafe_code)]
+    fn Read(&self, view: CustomAutoRooterGuard<ArrayBufferView>) -> Rc<Promise> {
+        let global = self.global();
+        let promise = Promise::new(&global);
+        if unsafe { view.as_slice().is_empty() } {
+            promise.reject_error(Error::Type("Can't read into an empty view".to_owned()));
+            return promise;
+        }
+        let stream = match self.stream.get() {
+            Some(stream) => stream,
+            None => {
+                promise.reject_error(Error::Type("The reader has released its lock".to_owned()));
+                return promise;
+            },
+        };
+        stream.set_disturbed();
+        let cx = global.get_cx();
+        if stream.state() == ReadableStreamState::Errored {
+            rooted!(in(cx) let mut error = UndefinedValue());
+            stream.stored_error(error.handle_mut());
+            return rejected_promise(&global, cx, error.handle());
+        }
+        let controller = stream.byte_controller().expect("A BYOB reader for a stream without bytes");
+        rooted!(in(cx) let view_object = *view.underlying_object());
+        controller.pull_into(cx, view_object.handle(), element_size(view.get_array_type()), promise.clone());
+        promise
+    }
+
+    /// <https://streams.spec.whatwg.org/#byob-reader-release-lock>
+    #[allow(unsafe_code)]
+    fn ReleaseLock(&self) {
+        let stream = match self.stream.get() {
+            Some(stream) => stream,
+            None => return,
+        };
+        let global = self.global();
+        let cx = global.get_cx();
+        rooted!(in(cx) let mut error = UndefinedValue());
+        unsafe {
+            Error::Type("The reader has released its lock".to_owned()).to_jsval(cx, &global, error.handle_mut());
+        }
+        if stream.state() == ReadableStreamState::Readable {
+            unsafe { self.closed_promise.borrow().reject(cx, error.handle()) };
+        } else {
+            *self.closed_promise.borrow_mut() = rejected_promise(&global, cx, error.handle());
+        }
+        if let Some(controller) = stream.byte_controller() {
+            controller.error_pull_intos(cx, error.handle());
+        }
+        stream.set_byob_reader(None);
+        self.stream.set(None);
+    }
+}
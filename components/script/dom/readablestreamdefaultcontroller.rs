@@ -0,0 +1,295 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::cell::DomRefCell;
+use dom::bindings::codegen::Bindings::QueuingStrategyBinding::QueuingStrategySize;
+use dom::bindings::codegen::Bindings::ReadableStreamDefaultControllerBinding;
+use dom::bindings::codegen::Bindings::ReadableStreamDefaultControllerBinding::ReadableStreamDefaultControllerMethods;
+use dom::bindings::error::{Error, Fallible};
+use dom::bindings::reflector::{DomObject, Reflector, reflect_dom_object};
+use dom::bindings::root::{Dom, DomRoot};
+use dom::globalscope::GlobalScope;
+use dom::promise::Promise;
+use dom::promisenativehandler::Callback;
+use dom::readablestream::{ReadRequest, ReadableStream, ReadableStreamState, UnderlyingSource};
+use dom::streams::{QueueWithSizes, chunk_size, error_to_jsval, object_value, resolved_promise, upon_settlement};
+use dom_struct::dom_struct;
+use js::jsapi::JSContext;
+use js::jsval::UndefinedValue;
+use js::rust::HandleValue;
+use js::rust::wrappers::JS_SetPendingException;
+use std::cell::Cell;
+use std::rc::Rc;
+
+/// <https://streams.spec.whatwg.org/#rs-default-controller-class>
+#[dom_struct]
+pub struct ReadableStreamDefaultController {
+    reflector_: Reflector,
+    stream: Dom<ReadableStream>,
+    /// The underlying source, until the stream is closed, errored or cancelled.
+    #[ignore_malloc_size_of = "Rc"]
+    source: DomRefCell<Option<Rc<UnderlyingSource>>>,
+    #[ignore_malloc_size_of = "Defined in rust-mozjs"]
+    queue: DomRefCell<QueueWithSizes>,
+    started: Cell<bool>,
+    close_requested: Cell<bool>,
+    pulling: Cell<bool>,
+    pull_again: Cell<bool>,
+    high_water_mark: f64,
+    #[ignore_malloc_size_of = "Rc"]
+    size: DomRefCell<Option<Rc<QueuingStrategySize>>>,
+}
+
+impl ReadableStreamDefaultController {
+    #[allow(unrooted_must_root)]
+    fn new(global: &GlobalScope,
+           stream: &ReadableStream,
+           source: UnderlyingSource,
+           high_water_mark: f64,
+           size: Option<Rc<QueuingStrategySize>>)
+           -> DomRoot<ReadableStreamDefaultController> {
+        reflect_dom_object(Box::new(ReadableStreamDefaultController {
+            reflector_: Reflector::new(),
+            stream: Dom::from_ref(stream),
+            source: DomRefCell::new(Some(Rc::new(source))),
+            queue: Default::default(),
+            started: Cell::new(false),
+            close_requested: Cell::new(false),
+            pulling: Cell::new(false),
+            pull_again: Cell::new(false),
+            high_water_mark: high_water_mark,
+            size: DomRefCell::new(size),
+        }), global, ReadableStreamDefaultControllerBinding::Wrap)
+    }
+
+    /// <https://streams.spec.whatwg.org/#set-up-readable-stream-default-controller>
+    #[allow(unrooted_must_root)]
+    pub fn set_up(stream: &ReadableStream,
+                  source: UnderlyingSource,
+                  high_water_mark: f64,
+                  size: Option<Rc<QueuingStrategySize>>)
+                  -> Fallible<()> {
+        let global = stream.global();
+        let controller = ReadableStreamDefaultController::new(&global, stream, source, high_water_mark, size);
+        stream.set_default_controller(&controller);
+        let source = controller.source().expect("A new controller without a source");
+        let start_promise = source.start(&global, object_value(&*controller))?;
+        upon_settlement(&start_promise,
+                        ControllerReaction::new(&controller, ControllerStep::StartFulfilled),
+                        ControllerReaction::new(&controller, ControllerStep::StartRejected));
+        Ok(())
+    }
+
+    #[allow(unrooted_must_root)]
+    fn source(&self) -> Option<Rc<UnderlyingSource>> {
+        self.source.borrow().clone()
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-default-controller-clear-algorithms>
+    fn clear_algorithms(&self) {
+        let source = self.source.borrow_mut().take();
+        *self.size.borrow_mut() = None;
+        drop(source);
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-default-controller-should-call-pull>
+    fn should_call_pull(&self) -> bool {
+        if !self.can_close_or_enqueue() || !self.started.get() {
+            return false;
+        }
+        if self.stream.is_locked() && self.stream.num_read_requests() > 0 {
+            return true;
+        }
+        self.desired_size().map_or(false, |size| size > 0.)
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-default-controller-call-pull-if-needed>
+    #[allow(unrooted_must_root)]
+    fn call_pull_if_needed(&self) {
+        if !self.should_call_pull() {
+            return;
+        }
+        if self.pulling.get() {
+            self.pull_again.set(true);
+            return;
+        }
+        let source = match self.source() {
+            Some(source) => source,
+            None => return,
+        };
+        self.pulling.set(true);
+        let pull_promise = source.pull(&self.global(), object_value(self));
+        upon_settlement(&pull_promise,
+                        ControllerReaction::new(self, ControllerStep::PullFulfilled),
+                        ControllerReaction::new(self, ControllerStep::PullRejected));
+    }
+
+    /// Whether the stream pulls more chunks from its source, which transform streams use to
+    /// propagate backpressure.
+    ///
+    /// <https://streams.spec.whatwg.org/#rs-default-controller-has-backpressure>
+    pub fn has_backpressure(&self) -> bool {
+        !self.should_call_pull()
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-default-controller-can-close-or-enqueue>
+    pub fn can_close_or_enqueue(&self) -> bool {
+        !self.close_requested.get() && self.stream.state() == ReadableStreamState::Readable
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-default-controller-get-desired-size>
+    pub fn desired_size(&self) -> Option<f64> {
+        match self.stream.state() {
+            ReadableStreamState::Errored => None,
+            ReadableStreamState::Closed => Some(0.),
+            ReadableStreamState::Readable => Some(self.high_water_mark - self.queue.borrow().total_size()),
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-default-controller-enqueue>
+    #[allow(unsafe_code)]
+    pub fn enqueue(&self, cx: *mut JSContext, chunk: HandleValue) -> Fallible<()> {
+        if !self.can_close_or_enqueue() {
+            return Err(Error::Type("The stream can't be enqueued into".to_owned()));
+        }
+        if self.stream.is_locked() && self.stream.num_read_requests() > 0 {
+            self.stream.fulfill_read_request(cx, chunk);
+        } else {
+            let size = self.size.borrow().clone();
+            let result = chunk_size(size.as_ref(), chunk)
+                .and_then(|size| self.queue.borrow_mut().enqueue(chunk, size));
+            if let Err(error) = result {
+                // The stream is errored with the exception, which is then rethrown.
+                let global = self.global();
+                rooted!(in(cx) let mut error_value = UndefinedValue());
+                unsafe { error_to_jsval(cx, &global, error, error_value.handle_mut()) };
+                self.error(cx, error_value.handle());
+                unsafe { JS_SetPendingException(cx, error_value.handle()) };
+                return Err(Error::JSFailed);
+            }
+        }
+        self.call_pull_if_needed();
+        Ok(())
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-default-controller-close>
+    pub fn close(&self) -> Fallible<()> {
+        if !self.can_close_or_enqueue() {
+            return Err(Error::Type("The stream can't be closed".to_owned()));
+        }
+        self.close_requested.set(true);
+        if self.queue.borrow().is_empty() {
+            self.clear_algorithms();
+            self.stream.close();
+        }
+        Ok(())
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-default-controller-error>
+    pub fn error(&self, cx: *mut JSContext, error: HandleValue) {
+        if self.stream.state() != ReadableStreamState::Readable {
+            return;
+        }
+        self.queue.borrow_mut().reset();
+        self.clear_algorithms();
+        self.stream.error(cx, error);
+    }
+
+    /// <https://streams.spec.whatwg.org/#rs-default-controller-private-pull>
+    #[allow(unrooted_must_root)]
+    pub fn pull_steps(&self, cx: *mut JSContext, request: ReadRequest) {
+        if self.queue.borrow().is_empty() {
+            self.stream.add_read_request(request);
+            self.call_pull_if_needed();
+            return;
+        }
+        rooted!(in(cx) let mut chunk = UndefinedValue());
+        self.queue.borrow_mut().dequeue(chunk.handle_mut());
+        if self.close_requested.get() && self.queue.borrow().is_empty() {
+            self.clear_algorithms();
+            self.stream.close();
+        } else {
+            self.call_pull_if_needed();
+        }
+        request.chunk_steps(cx, chunk.handle());
+    }
+
+    /// <https://streams.spec.whatwg.org/#rs-default-controller-private-cancel>
+    #[allow(unrooted_must_root)]
+    pub fn cancel_steps(&self, cx: *mut JSContext, reason: HandleValue) -> Rc<Promise> {
+        self.queue.borrow_mut().reset();
+        let global = self.global();
+        let result = match self.source() {
+            Some(source) => source.cancel(&global, cx, reason),
+            None => resolved_promise(&global),
+        };
+        self.clear_algorithms();
+        result
+    }
+}
+
+impl ReadableStreamDefaultControllerMethods for ReadableStreamDefaultController {
+    /// <https://streams.spec.whatwg.org/#rs-default-controller-desired-size>
+    fn GetDesiredSize(&self) -> Option<f64> {
+        self.desired_size()
+    }
+
+    /// <https://streams.spec.whatwg.org/#rs-default-controller-close>
+    fn Close(&self) -> Fallible<()> {
+        self.close()
+    }
+
+    /// <https://streams.spec.whatwg.org/#rs-default-controller-enqueue>
+    #[allow(unsafe_code)]
+    unsafe fn Enqueue(&self, cx: *mut JSContext, chunk: HandleValue) -> Fallible<()> {
+        self.enqueue(cx, chunk)
+    }
+
+    /// <https://streams.spec.whatwg.org/#rs-default-controller-error>
+    #[allow(unsafe_code)]
+    unsafe fn Error(&self, cx: *mut JSContext, error: HandleValue) {
+        self.error(cx, error)
+    }
+}
+
+#[derive(JSTraceable, MallocSizeOf)]
+enum ControllerStep {
+    StartFulfilled,
+    StartRejected,
+    PullFulfilled,
+    PullRejected,
+}
+
+/// Reacts to the promises returned by the `start` and `pull` methods of the underlying source.
+#[derive(JSTraceable, MallocSizeOf)]
+#[must_root]
+struct ControllerReaction {
+    controller: Dom<ReadableStreamDefaultController>,
+    step: ControllerStep,
+}
+
+impl ControllerReaction {
+    fn new(controller: &ReadableStreamDefaultController, step: ControllerStep) -> Box<Callback> {
+        Box::new(ControllerReaction { controller: Dom::from_ref(controller), step: step })
+    }
+}
+
+impl Callback for ControllerReaction {
+    fn callback(&self, cx: *mut JSContext, v: HandleValue) {
+        let controller = &self.controller;
+        match self.step {
+            ControllerStep::StartFulfilled => {
+                controller.started.set(true);
+                controller.call_pull_if_needed();
+            },
+            ControllerStep::PullFulfilled => {
+                controller.pulling.set(false);
+                if controller.pull_again.replace(false) {
+                    controller.call_pull_if_needed();
+                }
+            },
+            ControllerStep::StartRejected | ControllerStep::PullRejected => controller.error(cx, v),
+        }
+    }
+}
@@ -0,0 +1,186 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::cell::DomRefCell;
+use dom::bindings::codegen::Bindings::ReadableStreamDefaultReaderBinding;
+use dom::bindings::codegen::Bindings::ReadableStreamDefaultReaderBinding::ReadableStreamDefaultReaderMethods;
+use dom::bindings::error::{Error, Fallible};
+use dom::bindings::reflector::{DomObject, Reflector, reflect_dom_object};
+use dom::bindings::root::{DomRoot, MutNullableDom};
+use dom::globalscope::GlobalScope;
+use dom::promise::Promise;
+use dom::readablestream::{ReadRequest, ReadableStream, ReadableStreamState};
+use dom::streams::{rejected_promise, resolved_promise};
+use dom_struct::dom_struct;
+use js::jsapi::JSContext;
+use js::jsval::UndefinedValue;
+use js::rust::HandleValue;
+use std::collections::VecDeque;
+use std::mem;
+use std::rc::Rc;
+
+/// <https://streams.spec.whatwg.org/#default-reader-class>
+#[dom_struct]
+pub struct ReadableStreamDefaultReader {
+    reflector_: Reflector,
+    /// The stream this reader locks, until the lock is released.
+    stream: MutNullableDom<ReadableStream>,
+    #[ignore_malloc_size_of = "Rc"]
+    closed_promise: DomRefCell<Rc<Promise>>,
+    #[ignore_malloc_size_of = "Defined in rust-mozjs"]
+    read_requests: DomRefCell<VecDeque<ReadRequest>>,
+}
+
+impl ReadableStreamDefaultReader {
+    /// Acquires a reader for `stream`, which must not be locked.
+    ///
+    /// <https://streams.spec.whatwg.org/#set-up-readable-stream-default-reader>
+    #[allow(unrooted_must_root, unsafe_code)]
+    pub fn new(global: &GlobalScope, stream: &ReadableStream) -> Fallible<DomRoot<ReadableStreamDefaultReader>> {
+        if stream.is_locked() {
+            return Err(Error::Type("The stream is locked".to_owned()));
+        }
+        let closed_promise = match stream.state() {
+            ReadableStreamState::Readable => Promise::new(global),
+            ReadableStreamState::Closed => resolved_promise(global),
+            ReadableStreamState::Errored => {
+                let cx = global.get_cx();
+                rooted!(in(cx) let mut error = UndefinedValue());
+                stream.stored_error(error.handle_mut());
+                rejected_promise(global, cx, error.handle())
+            },
+        };
+        let reader = reflect_dom_object(Box::new(ReadableStreamDefaultReader {
+            reflector_: Reflector::new(),
+            stream: MutNullableDom::new(Some(stream)),
+            closed_promise: DomRefCell::new(closed_promise),
+            read_requests: Default::default(),
+        }), global, ReadableStreamDefaultReaderBinding::Wrap);
+        stream.set_default_reader(Some(&reader));
+        Ok(reader)
+    }
+
+    /// <https://streams.spec.whatwg.org/#default-reader-constructor>
+    pub fn Constructor(global: &GlobalScope,
+                       stream: &ReadableStream)
+                       -> Fallible<DomRoot<ReadableStreamDefaultReader>> {
+        ReadableStreamDefaultReader::new(global, stream)
+    }
+
+    #[allow(unrooted_must_root)]
+    pub fn closed_promise(&self) -> Rc<Promise> {
+        self.closed_promise.borrow().clone()
+    }
+
+    pub fn num_read_requests(&self) -> usize {
+        self.read_requests.borrow().len()
+    }
+
+    #[allow(unrooted_must_root)]
+    pub fn add_read_request(&self, request: ReadRequest) {
+        self.read_requests.borrow_mut().push_back(request);
+    }
+
+    #[allow(unrooted_must_root)]
+    pub fn take_read_request(&self) -> Option<ReadRequest> {
+        self.read_requests.borrow_mut().pop_front()
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-default-reader-read>
+    #[allow(unrooted_must_root, unsafe_code)]
+    pub fn read(&self, cx: *mut JSContext, request: ReadRequest) {
+        match self.stream.get() {
+            Some(stream) => stream.read(cx, request),
+            None => {
+                rooted!(in(cx) let mut error = UndefinedValue());
+                unsafe {
+                    Error::Type("The reader has released its lock".to_owned())
+                        .to_jsval(cx, &self.global(), error.handle_mut());
+                }
+                request.error_steps(cx, error.handle());
+            },
+        }
+    }
+
+    /// Resolves the closed promise and the pending read requests, when the stream closes.
+    #[allow(unrooted_must_root)]
+    pub fn close(&self, cx: *mut JSContext) {
+        self.closed_promise.borrow().resolve_native(&());
+        let requests = mem::replace(&mut *self.read_requests.borrow_mut(), VecDeque::new());
+        for request in requests {
+            request.close_steps(cx);
+        }
+    }
+
+    /// Rejects the closed promise and the pending read requests, when the stream errors.
+    #[allow(unrooted_must_root, unsafe_code)]
+    pub fn error(&self, cx: *mut JSContext, error: HandleValue) {
+        unsafe { self.closed_promise.borrow().reject(cx, error) };
+        let requests = mem::replace(&mut *self.read_requests.borrow_mut(), VecDeque::new());
+        for request in requests {
+            request.error_steps(cx, error);
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-default-reader-release>
+    #[allow(unrooted_must_root, unsafe_code)]
+    pub fn release(&self) {
+        let stream = match self.stream.get() {
+            Some(stream) => stream,
+            None => return,
+        };
+        let global = self.global();
+        let cx = global.get_cx();
+        rooted!(in(cx) let mut error = UndefinedValue());
+        unsafe {
+            Error::Type("The reader has released its lock".to_owned()).to_jsval(cx, &global, error.handle_mut());
+        }
+        if stream.state() == ReadableStreamState::Readable {
+            unsafe { self.closed_promise.borrow().reject(cx, error.handle()) };
+        } else {
+            *self.closed_promise.borrow_mut() = rejected_promise(&global, cx, error.handle());
+        }
+        stream.set_default_reader(None);
+        self.stream.set(None);
+        let requests = mem::replace(&mut *self.read_requests.borrow_mut(), VecDeque::new());
+        for request in requests {
+            request.error_steps(cx, error.handle());
+        }
+    }
+}
+
+impl ReadableStreamDefaultReaderMethods for ReadableStreamDefaultReader {
+    /// <https://streams.spec.whatwg.org/#generic-reader-closed>
+    #[allow(unrooted_must_root)]
+    fn Closed(&self) -> Rc<Promise> {
+        self.closed_promise()
+    }
+
+    /// <https://streams.spec.whatwg.org/#generic-reader-cancel>
+    #[allow(unrooted_must_root, unsafe_code)]
+    unsafe fn Cancel(&self, cx: *mut JSContext, reason: HandleValue) -> Rc<Promise> {
+        match self.stream.get() {
+            Some(stream) => stream.cancel(cx, reason),
+            None => {
+                let promise = Promise::new(&self.global());
+                promise.reject_error(Error::Type("The reader has released its lock".to_owned()));
+                promise
+            },
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#default-reader-read>
+    #[allow(unrooted_must_root)]
+    fn Read(&self) -> Rc<Promise> {
+        let global = self.global();
+        let promise = Promise::new(&global);
+        self.read(global.get_cx(), ReadRequest::Promise(promise.clone()));
+        promise
+    }
+
+    /// <https://streams.spec.whatwg.org/#default-reader-release-lock>
+    fn ReleaseLock(&self) {
+        self.release()
+    }
+}
@@ -2,7 +2,8 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use body::{BodyOperations, BodyType, consume_body};
+use body::{BodyOperations, BodyType, consume_body, extract_body};
+use dom::abortsignal::AbortSignal;
 use dom::bindings::cell::DomRefCell;
 use dom::bindings::codegen::Bindings::HeadersBinding::{HeadersInit, HeadersMethods};
 use dom::bindings::codegen::Bindings::RequestBinding;
@@ -23,7 +24,7 @@ use dom::bindings::trace::RootedTraceableBox;
 use dom::globalscope::GlobalScope;
 use dom::headers::{Guard, Headers};
 use dom::promise::Promise;
-use dom::xmlhttprequest::Extractable;
+use dom::readablestream::ReadableStream;
 use dom_struct::dom_struct;
 use hyper::method::Method as HttpMethod;
 use net_traits::ReferrerPolicy as MsgReferrerPolicy;
@@ -36,18 +37,18 @@ use net_traits::request::Referrer as NetTraitsRequestReferrer;
 use net_traits::request::Request as NetTraitsRequest;
 use net_traits::request::RequestMode as NetTraitsRequestMode;
 use servo_url::ServoUrl;
-use std::cell::{Cell, Ref};
+use std::cell::Ref;
 use std::rc::Rc;
 
 #[dom_struct]
 pub struct Request {
     reflector_: Reflector,
     request: DomRefCell<NetTraitsRequest>,
-    body_used: Cell<bool>,
+    /// The stream of the body, or `None` if the body is null.
+    body: MutNullableDom<ReadableStream>,
     headers: MutNullableDom<Headers>,
     mime_type: DomRefCell<Vec<u8>>,
-    #[ignore_malloc_size_of = "Rc"]
-    body_promise: DomRefCell<Option<(Rc<Promise>, BodyType)>>,
+    signal: MutNullableDom<AbortSignal>,
 }
 
 impl Request {
@@ -57,10 +58,10 @@ impl Request {
             reflector_: Reflector::new(),
             request: DomRefCell::new(
                 net_request_from_global(global, url)),
-            body_used: Cell::new(false),
+            body: Default::default(),
             headers: Default::default(),
             mime_type: DomRefCell::new("".to_string().into_bytes()),
-            body_promise: DomRefCell::new(None),
+            signal: Default::default(),
         }
     }
 
@@ -283,6 +284,18 @@ impl Request {
         let r = Request::from_net_request(global, request);
         r.headers.or_init(|| Headers::for_request(&r.global()));
 
+        // The signal of the request follows the one given in init, or the one of input.
+        let parent_signal = match init.signal {
+            Some(ref signal) => signal.clone(),
+            None => match input {
+                RequestInfo::Request(ref input_request) => Some(input_request.Signal()),
+                RequestInfo::USVString(_) => None,
+            },
+        };
+        if let Some(parent_signal) = parent_signal {
+            r.Signal().follow(&parent_signal);
+        }
+
         // Step 27
         let mut headers_copy = r.Headers();
 
@@ -343,16 +356,16 @@ impl Request {
         r.request.borrow_mut().headers = r.Headers().get_headers_list();
 
         // Step 32
-        let mut input_body = if let RequestInfo::Request(ref input_request) = input {
+        let (mut input_body, mut input_stream) = if let RequestInfo::Request(ref input_request) = input {
             let input_request_request = input_request.request.borrow();
-            input_request_request.body.clone()
+            (input_request_request.body.clone(), input_request.body.get())
         } else {
-            None
+            (None, None)
         };
 
         // Step 33
         if let Some(init_body_option) = init.body.as_ref() {
-            if init_body_option.is_some() || input_body.is_some() {
+            if init_body_option.is_some() || input_stream.is_some() {
                 let req = r.request.borrow();
                 let req_method = &req.method;
                 match *req_method {
@@ -368,12 +381,12 @@ impl Request {
         // Step 34
         if let Some(Some(ref init_body)) = init.body {
             // Step 34.2
-            let extracted_body_tmp = init_body.extract();
-            input_body = Some(extracted_body_tmp.0);
-            let content_type = extracted_body_tmp.1;
+            let extracted_body = extract_body(global, init_body)?;
+            input_body = extracted_body.source;
+            input_stream = Some(extracted_body.stream);
 
             // Step 34.3
-            if let Some(contents) = content_type {
+            if let Some(contents) = extracted_body.content_type {
                 if !r.Headers().Has(ByteString::new(b"Content-Type".to_vec())).unwrap() {
                     r.Headers().Append(ByteString::new(b"Content-Type".to_vec()),
                                             ByteString::new(contents.as_bytes().to_vec()))?;
@@ -382,24 +395,22 @@ impl Request {
         }
 
         // Step 35
+        // The bytes of a body that is a stream given by script are only known once it has been
+        // read, which fetch() does before sending the request.
         r.request.borrow_mut().body = input_body;
+        r.body.set(input_stream.as_ref().map(|stream| &**stream));
 
         // Step 36
         let extracted_mime_type = r.Headers().extract_mime_type();
         *r.mime_type.borrow_mut() = extracted_mime_type;
 
-        // Step 37
-        // TODO: `ReadableStream` object is not implemented in Servo yet.
-
         // Step 38
         Ok(r)
     }
 
     // https://fetch.spec.whatwg.org/#concept-body-locked
     fn locked(&self) -> bool {
-        // TODO: ReadableStream is unimplemented. Just return false
-        // for now.
-        false
+        self.body.get().map_or(false, |stream| stream.is_locked())
     }
 }
 
@@ -412,10 +423,24 @@ impl Request {
         r
     }
 
+    /// Creates a request object for a request that wasn't made by this global, like the ones
+    /// service workers respond to, whose headers can't be modified.
+    pub fn from_foreign_request(global: &GlobalScope,
+                                net_request: NetTraitsRequest) -> DomRoot<Request> {
+        let headers = net_request.headers.clone();
+        let body = net_request.body.clone();
+        let r = Request::from_net_request(global, net_request);
+        r.Headers().set_headers(headers);
+        r.Headers().set_guard(Guard::Immutable);
+        if let Some(body) = body {
+            r.body.set(Some(&ReadableStream::new_from_bytes(global, body)));
+        }
+        r
+    }
+
     fn clone_from(r: &Request) -> Fallible<DomRoot<Request>> {
         let req = r.request.borrow();
         let url = req.url();
-        let body_used = r.body_used.get();
         let mime_type = r.mime_type.borrow().clone();
         let headers_guard = r.Headers().get_guard();
         let r_clone = Request::new(&r.global(), url);
@@ -425,16 +450,31 @@ impl Request {
             borrowed_r_request.origin = req.origin.clone();
         }
         *r_clone.request.borrow_mut() = req.clone();
-        r_clone.body_used.set(body_used);
+        // https://fetch.spec.whatwg.org/#concept-body-clone
+        if let Some(stream) = r.body.get() {
+            let (first, second) = stream.tee()?;
+            r.body.set(Some(&first));
+            r_clone.body.set(Some(&second));
+        }
         *r_clone.mime_type.borrow_mut() = mime_type;
         r_clone.Headers().fill(Some(HeadersInit::Headers(r.Headers())))?;
         r_clone.Headers().set_guard(headers_guard);
+        r_clone.Signal().follow(&r.Signal());
         Ok(r_clone)
     }
 
     pub fn get_request(&self) -> NetTraitsRequest {
         self.request.borrow().clone()
     }
+
+    /// Returns the stream of the body if it was given by script, in which case the bytes of the
+    /// body aren't known until the stream has been read.
+    pub fn get_streaming_body(&self) -> Option<DomRoot<ReadableStream>> {
+        if self.request.borrow().body.is_some() {
+            return None;
+        }
+        self.body.get()
+    }
 }
 
 fn net_request_from_global(global: &GlobalScope,
@@ -487,16 +527,14 @@ fn includes_credentials(input: &ServoUrl) -> bool {
     !input.username().is_empty() || input.password().is_some()
 }
 
-// TODO: `Readable Stream` object is not implemented in Servo yet.
 // https://fetch.spec.whatwg.org/#concept-body-disturbed
-fn request_is_disturbed(_input: &Request) -> bool {
-    false
+fn request_is_disturbed(input: &Request) -> bool {
+    input.body.get().map_or(false, |stream| stream.is_disturbed())
 }
 
-// TODO: `Readable Stream` object is not implemented in Servo yet.
 // https://fetch.spec.whatwg.org/#concept-body-locked
-fn request_is_locked(_input: &Request) -> bool {
-    false
+fn request_is_locked(input: &Request) -> bool {
+    input.locked()
 }
 
 impl RequestMethods for Request {
@@ -569,9 +607,19 @@ impl RequestMethods for Request {
         DOMString::from_string(r.integrity_metadata.clone())
     }
 
+    // https://fetch.spec.whatwg.org/#dom-request-signal
+    fn Signal(&self) -> DomRoot<AbortSignal> {
+        self.signal.or_init(|| AbortSignal::new(&self.global()))
+    }
+
+    // https://fetch.spec.whatwg.org/#dom-body-body
+    fn GetBody(&self) -> Option<DomRoot<ReadableStream>> {
+        self.body.get()
+    }
+
     // https://fetch.spec.whatwg.org/#dom-body-bodyused
     fn BodyUsed(&self) -> bool {
-        self.body_used.get()
+        request_is_disturbed(self)
     }
 
     // https://fetch.spec.whatwg.org/#dom-request-clone
@@ -624,20 +672,12 @@ impl BodyOperations for Request {
         self.BodyUsed()
     }
 
-    fn set_body_promise(&self, p: &Rc<Promise>, body_type: BodyType) {
-        assert!(self.body_promise.borrow().is_none());
-        self.body_used.set(true);
-        *self.body_promise.borrow_mut() = Some((p.clone(), body_type));
-    }
-
     fn is_locked(&self) -> bool {
         self.locked()
     }
 
-    fn take_body(&self) -> Option<Vec<u8>> {
-        let mut request = self.request.borrow_mut();
-        let body = request.body.take();
-        Some(body.unwrap_or(vec![]))
+    fn get_stream(&self) -> Option<DomRoot<ReadableStream>> {
+        self.body.get()
     }
 
     fn get_mime_type(&self) -> Ref<Vec<u8>> {
@@ -2,13 +2,12 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use body::{BodyOperations, BodyType, consume_body, consume_body_with_promise};
+use body::{BodyOperations, BodyType, consume_body, extract_body};
 use dom::bindings::cell::DomRefCell;
 use dom::bindings::codegen::Bindings::HeadersBinding::{HeadersInit, HeadersMethods};
 use dom::bindings::codegen::Bindings::ResponseBinding;
-use dom::bindings::codegen::Bindings::ResponseBinding::{ResponseMethods, ResponseType as DOMResponseType};
-use dom::bindings::codegen::Bindings::XMLHttpRequestBinding::BodyInit;
-use dom::bindings::error::{Error, Fallible};
+use dom::bindings::codegen::Bindings::ResponseBinding::{BodyInit, ResponseMethods, ResponseType as DOMResponseType};
+use dom::bindings::error::{Error, ErrorResult, Fallible};
 use dom::bindings::reflector::{DomObject, Reflector, reflect_dom_object};
 use dom::bindings::root::{DomRoot, MutNullableDom};
 use dom::bindings::str::{ByteString, USVString};
@@ -16,15 +15,14 @@ use dom::globalscope::GlobalScope;
 use dom::headers::{Headers, Guard};
 use dom::headers::{is_vchar, is_obs_text};
 use dom::promise::Promise;
-use dom::xmlhttprequest::Extractable;
+use dom::readablestream::{ReadAllBytesSteps, ReadableStream, read_all_bytes};
+use dom::readablestreamdefaultreader::ReadableStreamDefaultReader;
 use dom_struct::dom_struct;
 use hyper::header::Headers as HyperHeaders;
 use hyper::status::StatusCode;
 use hyper_serde::Serde;
-use net_traits::response::{ResponseBody as NetTraitsResponseBody};
 use servo_url::ServoUrl;
-use std::cell::{Cell, Ref};
-use std::mem;
+use std::cell::Ref;
 use std::rc::Rc;
 use std::str::FromStr;
 use url::Position;
@@ -34,7 +32,6 @@ pub struct Response {
     reflector_: Reflector,
     headers_reflector: MutNullableDom<Headers>,
     mime_type: DomRefCell<Vec<u8>>,
-    body_used: Cell<bool>,
     /// `None` can be considered a StatusCode of `0`.
     #[ignore_malloc_size_of = "Defined in hyper"]
     status: DomRefCell<Option<StatusCode>>,
@@ -42,10 +39,8 @@ pub struct Response {
     response_type: DomRefCell<DOMResponseType>,
     url: DomRefCell<Option<ServoUrl>>,
     url_list: DomRefCell<Vec<ServoUrl>>,
-    // For now use the existing NetTraitsResponseBody enum
-    body: DomRefCell<NetTraitsResponseBody>,
-    #[ignore_malloc_size_of = "Rc"]
-    body_promise: DomRefCell<Option<(Rc<Promise>, BodyType)>>,
+    /// The stream of the body, or `None` if the body is null.
+    body: MutNullableDom<ReadableStream>,
 }
 
 impl Response {
@@ -54,14 +49,12 @@ impl Response {
             reflector_: Reflector::new(),
             headers_reflector: Default::default(),
             mime_type: DomRefCell::new("".to_string().into_bytes()),
-            body_used: Cell::new(false),
             status: DomRefCell::new(Some(StatusCode::Ok)),
             raw_status: DomRefCell::new(Some((200, b"OK".to_vec()))),
             response_type: DomRefCell::new(DOMResponseType::Default),
             url: DomRefCell::new(None),
             url_list: DomRefCell::new(vec![]),
-            body: DomRefCell::new(NetTraitsResponseBody::Empty),
-            body_promise: DomRefCell::new(None),
+            body: Default::default(),
         }
     }
 
@@ -112,11 +105,11 @@ impl Response {
             };
 
             // Step 7.3
-            let (extracted_body, content_type) = body.extract();
-            *r.body.borrow_mut() = NetTraitsResponseBody::Done(extracted_body);
+            let extracted_body = extract_body(global, body)?;
+            r.body.set(Some(&extracted_body.stream));
 
             // Step 7.4
-            if let Some(content_type_contents) = content_type {
+            if let Some(content_type_contents) = extracted_body.content_type {
                 if !r.Headers().Has(ByteString::new(b"Content-Type".to_vec())).unwrap() {
                     r.Headers().Append(ByteString::new(b"Content-Type".to_vec()),
                                             ByteString::new(content_type_contents.as_bytes().to_vec()))?;
@@ -185,9 +178,7 @@ impl Response {
 
     // https://fetch.spec.whatwg.org/#concept-body-locked
     fn locked(&self) -> bool {
-        // TODO: ReadableStream is unimplemented. Just return false
-        // for now.
-        false
+        self.body.get().map_or(false, |stream| stream.is_locked())
     }
 }
 
@@ -196,27 +187,12 @@ impl BodyOperations for Response {
         self.BodyUsed()
     }
 
-    fn set_body_promise(&self, p: &Rc<Promise>, body_type: BodyType) {
-        assert!(self.body_promise.borrow().is_none());
-        self.body_used.set(true);
-        *self.body_promise.borrow_mut() = Some((p.clone(), body_type));
-    }
-
     fn is_locked(&self) -> bool {
         self.locked()
     }
 
-    fn take_body(&self) -> Option<Vec<u8>> {
-        let body = mem::replace(&mut *self.body.borrow_mut(), NetTraitsResponseBody::Empty);
-        match body {
-            NetTraitsResponseBody::Done(bytes) => {
-                Some(bytes)
-            },
-            body => {
-                mem::replace(&mut *self.body.borrow_mut(), body);
-                None
-            },
-        }
+    fn get_stream(&self) -> Option<DomRoot<ReadableStream>> {
+        self.body.get()
     }
 
     fn get_mime_type(&self) -> Ref<Vec<u8>> {
@@ -297,7 +273,7 @@ impl ResponseMethods for Response {
     // https://fetch.spec.whatwg.org/#dom-response-clone
     fn Clone(&self) -> Fallible<DomRoot<Response>> {
         // Step 1
-        if self.is_locked() || self.body_used.get() {
+        if self.is_locked() || self.BodyUsed() {
             return Err(Error::Type("cannot clone a disturbed response".to_string()));
         }
 
@@ -315,20 +291,25 @@ impl ResponseMethods for Response {
         *new_response.url.borrow_mut() = self.url.borrow().clone();
         *new_response.url_list.borrow_mut() = self.url_list.borrow().clone();
 
-        if *self.body.borrow() != NetTraitsResponseBody::Empty {
-            *new_response.body.borrow_mut() = self.body.borrow().clone();
+        // https://fetch.spec.whatwg.org/#concept-body-clone
+        if let Some(stream) = self.body.get() {
+            let (first, second) = stream.tee()?;
+            self.body.set(Some(&first));
+            new_response.body.set(Some(&second));
         }
 
-        // Step 3
-        // TODO: This step relies on promises, which are still unimplemented.
-
         // Step 4
         Ok(new_response)
     }
 
+    // https://fetch.spec.whatwg.org/#dom-body-body
+    fn GetBody(&self) -> Option<DomRoot<ReadableStream>> {
+        self.body.get()
+    }
+
     // https://fetch.spec.whatwg.org/#dom-body-bodyused
     fn BodyUsed(&self) -> bool {
-        self.body_used.get()
+        self.body.get().map_or(false, |stream| stream.is_disturbed())
     }
 
     #[allow(unrooted_must_root)]
@@ -386,11 +367,19 @@ impl Response {
         *self.url.borrow_mut() = Some(final_url);
     }
 
-    #[allow(unrooted_must_root)]
-    pub fn finish(&self, body: Vec<u8>) {
-        *self.body.borrow_mut() = NetTraitsResponseBody::Done(body);
-        if let Some((p, body_type)) = self.body_promise.borrow_mut().take() {
-            consume_body_with_promise(self, body_type, &p);
-        }
+    pub fn set_body(&self, stream: &ReadableStream) {
+        self.body.set(Some(stream));
+    }
+
+    /// Reads the whole body, which is consumed, and runs `steps` with its bytes, a null body
+    /// having none.
+    pub fn read_body(&self, steps: Box<ReadAllBytesSteps>) -> ErrorResult {
+        let stream = match self.body.get() {
+            Some(stream) => stream,
+            None => return Ok(steps.success_steps(vec![])),
+        };
+        let reader = ReadableStreamDefaultReader::new(&self.global(), &stream)?;
+        read_all_bytes(&reader, steps);
+        Ok(())
     }
 }
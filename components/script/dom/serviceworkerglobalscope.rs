@@ -10,14 +10,15 @@ use dom::bindings::codegen::Bindings::ServiceWorkerGlobalScopeBinding;
 use dom::bindings::codegen::Bindings::ServiceWorkerGlobalScopeBinding::ServiceWorkerGlobalScopeMethods;
 use dom::bindings::inheritance::Castable;
 use dom::bindings::reflector::DomObject;
-use dom::bindings::root::{DomRoot, RootCollection, ThreadLocalStackRoots};
+use dom::bindings::root::{Dom, DomRoot, RootCollection, ThreadLocalStackRoots};
 use dom::bindings::str::DOMString;
 use dom::dedicatedworkerglobalscope::AutoWorkerReset;
 use dom::event::Event;
-use dom::eventtarget::EventTarget;
-use dom::extendableevent::ExtendableEvent;
+use dom::extendableevent::{ExtendLifetimeSteps, ExtendableEvent};
 use dom::extendablemessageevent::ExtendableMessageEvent;
+use dom::fetchevent::FetchEvent;
 use dom::globalscope::GlobalScope;
+use dom::request::Request;
 use dom::worker::TrustedWorkerAddress;
 use dom::workerglobalscope::WorkerGlobalScope;
 use dom_struct::dom_struct;
@@ -25,7 +26,8 @@ use ipc_channel::ipc::{self, IpcSender, IpcReceiver};
 use js::jsapi::{JSAutoCompartment, JSContext, JS_AddInterruptCallback};
 use js::jsval::UndefinedValue;
 use net_traits::{load_whole_resource, IpcSend, CustomResponseMediator};
-use net_traits::request::{CredentialsMode, Destination, RequestInit};
+use net_traits::request::{CredentialsMode, Destination, Request as NetTraitsRequest, RequestInit};
+use net_traits::request::ServiceWorkersMode;
 use script_runtime::{CommonScriptMsg, ScriptChan, new_rt_and_cx, Runtime};
 use script_traits::{TimerEvent, WorkerGlobalScopeInit, ScopeThings, ServiceWorkerMsg, WorkerScriptLoadOrigin};
 use servo_channel::{channel, route_ipc_receiver_to_new_servo_sender, Receiver, Sender};
@@ -230,14 +232,15 @@ impl ServiceWorkerGlobalScope {
                             receiver: Receiver<ServiceWorkerScriptMsg>,
                             devtools_receiver: IpcReceiver<DevtoolScriptControlMsg>,
                             swmanager_sender: IpcSender<ServiceWorkerMsg>,
-                            scope_url: ServoUrl) {
+                            scope_url: ServoUrl,
+                            needs_install: bool) {
         let ScopeThings { script_url,
                           init,
                           worker_load_origin,
                           .. } = scope_things;
 
         let serialized_worker_url = script_url.to_string();
-        let origin = GlobalScope::current().expect("No current global object").origin().immutable().clone();
+        let origin = script_url.origin();
         thread::Builder::new().name(format!("ServiceWorker for {}", serialized_worker_url)).spawn(move || {
             thread_state::initialize(ThreadState::SCRIPT | ThreadState::IN_WORKER);
             let roots = RootCollection::new();
@@ -254,17 +257,18 @@ impl ServiceWorkerGlobalScope {
                 referrer_url: referrer_url,
                 referrer_policy: referrer_policy,
                 origin,
+                service_workers_mode: ServiceWorkersMode::None,
                 .. RequestInit::default()
             };
 
-            let (url, source) = match load_whole_resource(request,
-                                                          &init.resource_threads.sender()) {
+            let (url, source, metadata) = match load_whole_resource(request,
+                                                                    &init.resource_threads.sender()) {
                 Err(_) => {
                     println!("error loading script {}", serialized_worker_url);
                     return;
                 }
                 Ok((metadata, bytes)) => {
-                    (metadata.final_url, String::from_utf8(bytes).unwrap())
+                    (metadata.final_url.clone(), String::from_utf8(bytes).unwrap(), metadata)
                 }
             };
 
@@ -280,6 +284,7 @@ impl ServiceWorkerGlobalScope {
                 own_sender, receiver,
                 timer_ipc_chan, timer_port, swmanager_sender, scope_url);
             let scope = global.upcast::<WorkerGlobalScope>();
+            scope.initialize_csp_list(&metadata);
 
             unsafe {
                 // Handle interrupt requests
@@ -294,7 +299,9 @@ impl ServiceWorkerGlobalScope {
                 let _ = timer_chan.send(());
             }).expect("Thread spawning failed");
 
-            global.dispatch_activate();
+            if needs_install {
+                global.dispatch_install();
+            }
             let reporter_name = format!("service-worker-reporter-{}", random::<u64>());
             scope.upcast::<GlobalScope>().mem_profiler_chan().run_with_memory_reporting(|| {
                 // Step 29, Run the responsible event loop specified by inside settings until it is destroyed.
@@ -336,7 +343,7 @@ impl ServiceWorkerGlobalScope {
         use self::ServiceWorkerScriptMsg::*;
 
         match msg {
-            CommonWorker(WorkerScriptMsg::DOMMessage(data)) => {
+            CommonWorker(WorkerScriptMsg::DOMMessage(data, _)) => {
                 let scope = self.upcast::<WorkerGlobalScope>();
                 let target = self.upcast();
                 let _ac = JSAutoCompartment::new(scope.get_cx(), scope.reflector().get_jsobject().get());
@@ -348,11 +355,9 @@ impl ServiceWorkerGlobalScope {
                 self.upcast::<WorkerGlobalScope>().process_event(msg);
             },
             Response(mediator) => {
-                // TODO XXXcreativcoder This will eventually use a FetchEvent interface to fire event
-                // when we have the Request and Response dom api's implemented
-                // https://slightlyoff.github.io/ServiceWorker/spec/service_worker_1/index.html#fetch-event-section
-                self.upcast::<EventTarget>().fire_event(atom!("fetch"));
-                let _ = mediator.response_chan.send(None);
+                let CustomResponseMediator { response_chan, request, .. } = mediator;
+                let request = Request::from_foreign_request(self.upcast(), NetTraitsRequest::from_init(request));
+                FetchEvent::dispatch(self, &request, response_chan);
             },
             WakeUp => {},
         }
@@ -364,10 +369,50 @@ impl ServiceWorkerGlobalScope {
         })
     }
 
+    /// Installs the worker, and activates it if that succeeds.
+    ///
+    /// <https://w3c.github.io/ServiceWorker/#installation-algorithm>
+    #[allow(unrooted_must_root)]
+    fn dispatch_install(&self) {
+        let event = ExtendableEvent::new(self, atom!("install"), false, false);
+        event.upcast::<Event>().fire(self.upcast());
+        event.upon_lifetime_promises_settled(Box::new(InstallSettled(Dom::from_ref(self))));
+    }
+
+    /// <https://w3c.github.io/ServiceWorker/#activation-algorithm>
+    #[allow(unrooted_must_root)]
     fn dispatch_activate(&self) {
         let event = ExtendableEvent::new(self, atom!("activate"), false, false);
-        let event = (&*event).upcast::<Event>();
-        self.upcast::<EventTarget>().dispatch_event(event);
+        event.upcast::<Event>().fire(self.upcast());
+        event.upon_lifetime_promises_settled(Box::new(ActivateSettled(Dom::from_ref(self))));
+    }
+}
+
+/// Activates the worker once the promises of its install event have settled, or unregisters
+/// it if one was rejected.
+#[derive(JSTraceable, MallocSizeOf)]
+#[must_root]
+struct InstallSettled(Dom<ServiceWorkerGlobalScope>);
+
+impl ExtendLifetimeSteps for InstallSettled {
+    fn settled_steps(self: Box<Self>, rejected: bool) {
+        if rejected {
+            let _ = self.0.swmanager_sender.send(ServiceWorkerMsg::InstallFailed(self.0.scope_url.clone()));
+        } else {
+            self.0.dispatch_activate();
+        }
+    }
+}
+
+/// Lets the worker handle the fetches of its clients once the promises of its activate event
+/// have settled.
+#[derive(JSTraceable, MallocSizeOf)]
+#[must_root]
+struct ActivateSettled(Dom<ServiceWorkerGlobalScope>);
+
+impl ExtendLifetimeSteps for ActivateSettled {
+    fn settled_steps(self: Box<Self>, _rejected: bool) {
+        let _ = self.0.swmanager_sender.send(ServiceWorkerMsg::Activated(self.0.scope_url.clone()));
     }
 }
 
@@ -383,6 +428,15 @@ unsafe extern "C" fn interrupt_callback(cx: *mut JSContext) -> bool {
 }
 
 impl ServiceWorkerGlobalScopeMethods for ServiceWorkerGlobalScope {
+    // https://w3c.github.io/ServiceWorker/#service-worker-global-scope-oninstall-attribute
+    event_handler!(install, GetOninstall, SetOninstall);
+
+    // https://w3c.github.io/ServiceWorker/#service-worker-global-scope-onactivate-attribute
+    event_handler!(activate, GetOnactivate, SetOnactivate);
+
+    // https://w3c.github.io/ServiceWorker/#service-worker-global-scope-onfetch-attribute
+    event_handler!(fetch, GetOnfetch, SetOnfetch);
+
     // https://w3c.github.io/ServiceWorker/#service-worker-global-scope-onmessage-attribute
     event_handler!(message, GetOnmessage, SetOnmessage);
 }
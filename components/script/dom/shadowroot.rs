@@ -0,0 +1,246 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::cell::DomRefCell;
+use dom::bindings::codegen::Bindings::ShadowRootBinding;
+use dom::bindings::codegen::Bindings::ShadowRootBinding::{ShadowRootMethods, ShadowRootMode};
+use dom::bindings::inheritance::Castable;
+use dom::bindings::root::{Dom, DomRoot, LayoutDom, MutNullableDom};
+use dom::cssstylesheet::CSSStyleSheet;
+use dom::customelementregistry::{CallbackReaction, try_upgrade_element};
+use dom::document::{Document, StyleSheetInDocument};
+use dom::documentfragment::DocumentFragment;
+use dom::element::Element;
+use dom::node::{Node, NodeDamage, NodeFlags, UnbindContext, window_from_node};
+use dom::stylesheetlist::{StyleSheetList, StyleSheetListOwner};
+use dom::virtualmethods::vtable_for;
+use dom_struct::dom_struct;
+use script_thread::ScriptThread;
+use servo_arc::Arc;
+use style::author_styles::AuthorStyles;
+use style::context::QuirksMode;
+use style::dom::TElement;
+use style::media_queries::Device;
+use style::shared_lock::SharedRwLockReadGuard;
+use style::stylesheets::Stylesheet;
+use style::stylist::CascadeData;
+
+/// <https://dom.spec.whatwg.org/#interface-shadowroot>
+#[dom_struct]
+pub struct ShadowRoot {
+    document_fragment: DocumentFragment,
+    host: Dom<Element>,
+    mode: ShadowRootMode,
+    /// The stylesheets of the `<style>` and `<link>` elements in this shadow tree.
+    #[ignore_malloc_size_of = "Stylesheets are measured by the layout thread"]
+    author_styles: DomRefCell<AuthorStyles<StyleSheetInDocument>>,
+    stylesheet_list: MutNullableDom<StyleSheetList>,
+}
+
+impl ShadowRoot {
+    #[allow(unrooted_must_root)]
+    fn new_inherited(host: &Element, document: &Document, mode: ShadowRootMode) -> ShadowRoot {
+        let document_fragment = DocumentFragment::new_inherited(document);
+        document_fragment.upcast::<Node>().set_flag(NodeFlags::IS_IN_SHADOW_TREE, true);
+        ShadowRoot {
+            document_fragment,
+            host: Dom::from_ref(host),
+            mode,
+            author_styles: DomRefCell::new(AuthorStyles::new()),
+            stylesheet_list: MutNullableDom::new(None),
+        }
+    }
+
+    pub fn new(host: &Element, document: &Document, mode: ShadowRootMode) -> DomRoot<ShadowRoot> {
+        Node::reflect_node(Box::new(ShadowRoot::new_inherited(host, document, mode)),
+                           document,
+                           ShadowRootBinding::Wrap)
+    }
+
+    pub fn host(&self) -> DomRoot<Element> {
+        DomRoot::from_ref(&self.host)
+    }
+
+    pub fn mode(&self) -> ShadowRootMode {
+        self.mode
+    }
+
+    /// Connects this shadow tree to the document, once its host got connected.
+    pub fn bind_to_tree(&self) {
+        self.host.upcast::<Node>().owner_doc().register_shadow_root(self);
+        for node in self.upcast::<Node>().traverse_preorder() {
+            node.set_flag(NodeFlags::IS_IN_DOC, true);
+            vtable_for(&node).bind_to_tree(true);
+        }
+        // The shadow-including descendants of a connected node are connected too.
+        // https://dom.spec.whatwg.org/#concept-node-insert step 7.7
+        for descendant in self.upcast::<Node>().traverse_preorder().filter_map(DomRoot::downcast::<Element>) {
+            if descendant.get_custom_element_definition().is_some() {
+                ScriptThread::enqueue_callback_reaction(&*descendant, CallbackReaction::Connected, None);
+            } else {
+                try_upgrade_element(&*descendant);
+            }
+        }
+    }
+
+    /// Disconnects this shadow tree from the document, once its host got disconnected.
+    pub fn unbind_from_tree(&self, context: &UnbindContext) {
+        if !context.tree_in_doc {
+            return;
+        }
+        self.host.upcast::<Node>().owner_doc().unregister_shadow_root(self);
+        for node in self.upcast::<Node>().traverse_preorder() {
+            node.set_flag(NodeFlags::IS_IN_DOC | NodeFlags::HAS_DIRTY_DESCENDANTS |
+                          NodeFlags::HAS_SNAPSHOT | NodeFlags::HANDLED_SNAPSHOT,
+                          false);
+        }
+        for node in self.upcast::<Node>().traverse_preorder() {
+            vtable_for(&node).unbind_from_tree(context);
+            node.dispose_style_and_layout_data();
+            // https://dom.spec.whatwg.org/#concept-node-remove step 14
+            if let Some(element) = node.as_custom_element() {
+                ScriptThread::enqueue_callback_reaction(&*element, CallbackReaction::Disconnected, None);
+            }
+        }
+    }
+
+    /// Moves this shadow tree to the document its host was adopted into.
+    pub fn adopt(&self, old_doc: &Document) {
+        let document = self.host.upcast::<Node>().owner_doc();
+        for descendant in self.upcast::<Node>().traverse_preorder() {
+            descendant.set_owner_doc(&document);
+        }
+        for descendant in self.upcast::<Node>().traverse_preorder().filter_map(|d| d.as_custom_element()) {
+            ScriptThread::enqueue_callback_reaction(&*descendant,
+                CallbackReaction::Adopted(DomRoot::from_ref(old_doc), document.clone()), None);
+        }
+        for descendant in self.upcast::<Node>().traverse_preorder() {
+            vtable_for(&descendant).adopting_steps(old_doc);
+        }
+    }
+
+    /// Returns the number of stylesheets in this shadow tree.
+    pub fn stylesheet_count(&self) -> usize {
+        self.author_styles.borrow().stylesheets.iter().count()
+    }
+
+    pub fn stylesheet_at(&self, index: usize) -> Option<DomRoot<CSSStyleSheet>> {
+        let author_styles = self.author_styles.borrow();
+        author_styles.stylesheets.iter().nth(index).and_then(|s| {
+            s.owner.upcast::<Node>().get_cssom_stylesheet()
+        })
+    }
+
+    /// Add a stylesheet owned by `owner` to the list of shadow root sheets, in the
+    /// correct tree position.
+    #[allow(unrooted_must_root)] // Owner needs to be rooted already necessarily.
+    pub fn add_stylesheet(&self, owner: &Element, sheet: Arc<Stylesheet>) {
+        let mut author_styles = self.author_styles.borrow_mut();
+        let insertion_point =
+            author_styles.stylesheets
+                .iter()
+                .find(|sheet_in_shadow| {
+                    owner.upcast::<Node>().is_before(sheet_in_shadow.owner.upcast())
+                }).cloned();
+
+        let sheet = StyleSheetInDocument {
+            sheet,
+            owner: Dom::from_ref(owner),
+        };
+
+        let document = self.host.upcast::<Node>().owner_doc();
+        let lock = document.style_shared_lock();
+        let guard = lock.read();
+
+        match insertion_point {
+            Some(ip) => {
+                author_styles.stylesheets.insert_stylesheet_before(None, sheet, ip, &guard);
+            }
+            None => {
+                author_styles.stylesheets.append_stylesheet(None, sheet, &guard);
+            }
+        }
+        drop(author_styles);
+        self.invalidate_stylesheets();
+    }
+
+    /// Remove a stylesheet owned by `owner` from the list of shadow root sheets.
+    #[allow(unrooted_must_root)] // Owner needs to be rooted already necessarily.
+    pub fn remove_stylesheet(&self, owner: &Element, s: &Arc<Stylesheet>) {
+        let guard = s.shared_lock.read();
+        self.author_styles.borrow_mut().stylesheets.remove_stylesheet(
+            None,
+            StyleSheetInDocument {
+                sheet: s.clone(),
+                owner: Dom::from_ref(owner),
+            },
+            &guard,
+        );
+        self.invalidate_stylesheets();
+    }
+
+    /// Marks the stylesheets of this shadow tree dirty, and restyles its host so
+    /// that the next reflow picks up the changes.
+    pub fn invalidate_stylesheets(&self) {
+        self.author_styles.borrow_mut().stylesheets.force_dirty();
+        self.host.upcast::<Node>().owner_doc().invalidate_shadow_roots_stylesheets();
+        if self.host.upcast::<Node>().is_in_doc() {
+            self.host.restyle_subtree();
+            self.host.upcast::<Node>().dirty(NodeDamage::NodeStyleDamaged);
+        }
+    }
+}
+
+impl ShadowRootMethods for ShadowRoot {
+    // https://dom.spec.whatwg.org/#dom-shadowroot-mode
+    fn Mode(&self) -> ShadowRootMode {
+        self.mode
+    }
+
+    // https://dom.spec.whatwg.org/#dom-shadowroot-host
+    fn Host(&self) -> DomRoot<Element> {
+        self.host()
+    }
+
+    // https://drafts.csswg.org/cssom/#dom-documentorshadowroot-stylesheets
+    fn StyleSheets(&self) -> DomRoot<StyleSheetList> {
+        self.stylesheet_list.or_init(|| {
+            StyleSheetList::new(&window_from_node(self), StyleSheetListOwner::ShadowRoot(Dom::from_ref(self)))
+        })
+    }
+}
+
+#[allow(unsafe_code)]
+pub trait LayoutShadowRootHelpers {
+    unsafe fn get_host_for_layout(&self) -> LayoutDom<Element>;
+    unsafe fn get_style_data_for_layout<'a>(&self) -> &'a CascadeData;
+    unsafe fn flush_stylesheets<E: TElement>(&self,
+                                             device: &Device,
+                                             quirks_mode: QuirksMode,
+                                             guard: &SharedRwLockReadGuard);
+}
+
+#[allow(unsafe_code)]
+impl LayoutShadowRootHelpers for LayoutDom<ShadowRoot> {
+    #[inline]
+    unsafe fn get_host_for_layout(&self) -> LayoutDom<Element> {
+        (*self.unsafe_get()).host.to_layout()
+    }
+
+    #[inline]
+    unsafe fn get_style_data_for_layout<'a>(&self) -> &'a CascadeData {
+        &(*self.unsafe_get()).author_styles.borrow_for_layout().data
+    }
+
+    #[inline]
+    unsafe fn flush_stylesheets<E: TElement>(&self,
+                                             device: &Device,
+                                             quirks_mode: QuirksMode,
+                                             guard: &SharedRwLockReadGuard) {
+        let mut author_styles = (*self.unsafe_get()).author_styles.borrow_mut_for_layout();
+        if author_styles.stylesheets.dirty() {
+            author_styles.flush::<E>(device, quirks_mode, guard);
+        }
+    }
+}
@@ -0,0 +1,266 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Abstract operations shared by readable, writable and transform streams.
+//!
+//! <https://streams.spec.whatwg.org/>
+
+use dom::bindings::callback::ExceptionHandling;
+use dom::bindings::codegen::Bindings::QueuingStrategyBinding::QueuingStrategySize;
+use dom::bindings::conversions::get_property_jsval;
+use dom::bindings::error::{Error, Fallible};
+use dom::bindings::reflector::DomObject;
+use dom::bindings::utils::set_dictionary_property;
+use dom::globalscope::GlobalScope;
+use dom::promise::Promise;
+use dom::promisenativehandler::{Callback, PromiseNativeHandler};
+use js::jsapi::{HandleValueArray, Heap, IsCallable, JSContext, JSObject};
+use js::jsapi::{JS_ClearPendingException, JS_IsExceptionPending, JS_NewPlainObject};
+use js::jsval::{BooleanValue, JSVal, ObjectValue, UndefinedValue};
+use js::rust::{HandleObject, HandleValue, MutableHandleValue};
+use js::rust::wrappers::{Call, JS_GetPendingException};
+use js::typedarray::{CreateWith, Uint8Array};
+use std::collections::VecDeque;
+use std::ptr;
+use std::rc::Rc;
+
+/// A queue of chunks, each with the size the queuing strategy gave it.
+///
+/// <https://streams.spec.whatwg.org/#queue-with-sizes>
+#[derive(Default, JSTraceable)]
+pub struct QueueWithSizes {
+    queue: VecDeque<(Box<Heap<JSVal>>, f64)>,
+    total_size: f64,
+}
+
+impl QueueWithSizes {
+    /// <https://streams.spec.whatwg.org/#enqueue-value-with-size>
+    pub fn enqueue(&mut self, value: HandleValue, size: f64) -> Fallible<()> {
+        if !size.is_finite() || size < 0. {
+            return Err(Error::Range("The size of a chunk must be a finite, non-negative number".to_owned()));
+        }
+        self.queue.push_back((Heap::boxed(value.get()), size));
+        self.total_size += size;
+        Ok(())
+    }
+
+    /// <https://streams.spec.whatwg.org/#dequeue-value>
+    pub fn dequeue(&mut self, mut rval: MutableHandleValue) {
+        let (value, size) = self.queue.pop_front().expect("Dequeued from an empty queue");
+        // Rounding errors could make the total size negative.
+        self.total_size = (self.total_size - size).max(0.);
+        rval.set(value.get());
+    }
+
+    /// <https://streams.spec.whatwg.org/#peek-queue-value>
+    pub fn peek(&self, mut rval: MutableHandleValue) {
+        let &(ref value, _) = self.queue.front().expect("Peeked at an empty queue");
+        rval.set(value.get());
+    }
+
+    /// <https://streams.spec.whatwg.org/#reset-queue>
+    pub fn reset(&mut self) {
+        self.queue.clear();
+        self.total_size = 0.;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    pub fn total_size(&self) -> f64 {
+        self.total_size
+    }
+}
+
+/// <https://streams.spec.whatwg.org/#validate-and-normalize-high-water-mark>
+pub fn validate_high_water_mark(high_water_mark: Option<f64>, default: f64) -> Fallible<f64> {
+    let high_water_mark = high_water_mark.unwrap_or(default);
+    if high_water_mark.is_nan() || high_water_mark < 0. {
+        return Err(Error::Range("The high water mark must be a non-negative number".to_owned()));
+    }
+    Ok(high_water_mark)
+}
+
+/// Runs the size algorithm of a queuing strategy on `chunk`. Chunks have a size of 1 when the
+/// strategy has no size function.
+///
+/// <https://streams.spec.whatwg.org/#make-size-algorithm-from-size-function>
+pub fn chunk_size(size: Option<&Rc<QueuingStrategySize>>, chunk: HandleValue) -> Fallible<f64> {
+    match size {
+        Some(size) => size.Call__(chunk, ExceptionHandling::Rethrow),
+        None => Ok(1.),
+    }
+}
+
+/// Calls the method `name` of an underlying source, sink or transformer, with the object as
+/// `this`. A missing method does nothing and returns `undefined`.
+///
+/// <https://streams.spec.whatwg.org/#invoke-or-noop>
+#[allow(unsafe_code)]
+pub unsafe fn invoke_or_noop(cx: *mut JSContext,
+                             object: HandleObject,
+                             name: &str,
+                             args: &[JSVal],
+                             rval: MutableHandleValue)
+                             -> Fallible<()> {
+    rooted!(in(cx) let mut method = UndefinedValue());
+    if !object.get().is_null() {
+        get_property_jsval(cx, object, name, method.handle_mut())?;
+    }
+    if method.is_undefined() {
+        return Ok(());
+    }
+    if !method.is_object() || !IsCallable(method.to_object()) {
+        return Err(Error::Type(format!("{} is not a function", name)));
+    }
+    rooted!(in(cx) let this = ObjectValue(object.get()));
+    let args = HandleValueArray::from_rooted_slice(args);
+    if !Call(cx, this.handle(), method.handle(), &args, rval) {
+        return Err(Error::JSFailed);
+    }
+    Ok(())
+}
+
+/// Like `invoke_or_noop`, but turns the result into a promise, and exceptions into rejections.
+///
+/// <https://streams.spec.whatwg.org/#promise-invoke-or-noop>
+#[allow(unrooted_must_root, unsafe_code)]
+pub unsafe fn promise_invoke_or_noop(global: &GlobalScope,
+                                     cx: *mut JSContext,
+                                     object: HandleObject,
+                                     name: &str,
+                                     args: &[JSVal])
+                                     -> Rc<Promise> {
+    rooted!(in(cx) let mut result = UndefinedValue());
+    match invoke_or_noop(cx, object, name, args, result.handle_mut()) {
+        Ok(()) => Promise::new_resolved(global, cx, result.handle()).unwrap(),
+        Err(error) => {
+            error_to_jsval(cx, global, error, result.handle_mut());
+            Promise::new_rejected(global, cx, result.handle()).unwrap()
+        },
+    }
+}
+
+/// Converts `error` to a JS value. `Error::JSFailed` takes the pending exception instead.
+#[allow(unsafe_code)]
+pub unsafe fn error_to_jsval(cx: *mut JSContext,
+                             global: &GlobalScope,
+                             error: Error,
+                             mut rval: MutableHandleValue) {
+    match error {
+        Error::JSFailed => {
+            rooted!(in(cx) let mut exception = UndefinedValue());
+            if JS_IsExceptionPending(cx) && JS_GetPendingException(cx, exception.handle_mut()) {
+                JS_ClearPendingException(cx);
+            }
+            rval.set(exception.get());
+        },
+        error => error.to_jsval(cx, global, rval),
+    }
+}
+
+/// <https://streams.spec.whatwg.org/#create-iter-result-object>
+#[allow(unsafe_code)]
+pub unsafe fn create_read_result(cx: *mut JSContext,
+                                 value: HandleValue,
+                                 done: bool,
+                                 mut rval: MutableHandleValue) {
+    rooted!(in(cx) let result = JS_NewPlainObject(cx));
+    rooted!(in(cx) let done = BooleanValue(done));
+    let _ = set_dictionary_property(cx, result.handle(), "value", value);
+    let _ = set_dictionary_property(cx, result.handle(), "done", done.handle());
+    rval.set(ObjectValue(result.get()));
+}
+
+/// Creates a `Uint8Array` holding a copy of `bytes`.
+#[allow(unsafe_code)]
+pub unsafe fn create_uint8_array(cx: *mut JSContext, bytes: &[u8], mut rval: MutableHandleValue) {
+    rooted!(in(cx) let mut array = ptr::null_mut::<JSObject>());
+    assert!(Uint8Array::create(cx, CreateWith::Slice(bytes), array.handle_mut()).is_ok());
+    rval.set(ObjectValue(array.get()));
+}
+
+/// A promise that is already resolved with `undefined`.
+#[allow(unrooted_must_root)]
+pub fn resolved_promise(global: &GlobalScope) -> Rc<Promise> {
+    let promise = Promise::new(global);
+    promise.resolve_native(&());
+    promise
+}
+
+/// A promise that is already rejected with `reason`.
+#[allow(unrooted_must_root, unsafe_code)]
+pub fn rejected_promise(global: &GlobalScope, cx: *mut JSContext, reason: HandleValue) -> Rc<Promise> {
+    let promise = Promise::new(global);
+    unsafe { promise.reject(cx, reason) };
+    promise
+}
+
+/// Runs `on_fulfilled` or `on_rejected` once `promise` settles.
+///
+/// <https://streams.spec.whatwg.org/#upon-fulfillment>
+pub fn upon_settlement(promise: &Promise, on_fulfilled: Box<Callback>, on_rejected: Box<Callback>) {
+    let handler = PromiseNativeHandler::new(&promise.global(), Some(on_fulfilled), Some(on_rejected));
+    promise.append_native_handler(&handler);
+}
+
+/// Runs `on_fulfilled` once `promise` is fulfilled.
+pub fn upon_fulfillment(promise: &Promise, on_fulfilled: Box<Callback>) {
+    let handler = PromiseNativeHandler::new(&promise.global(), Some(on_fulfilled), None);
+    promise.append_native_handler(&handler);
+}
+
+/// Runs `on_rejected` once `promise` is rejected.
+///
+/// <https://streams.spec.whatwg.org/#upon-rejection>
+pub fn upon_rejection(promise: &Promise, on_rejected: Box<Callback>) {
+    let handler = PromiseNativeHandler::new(&promise.global(), None, Some(on_rejected));
+    promise.append_native_handler(&handler);
+}
+
+/// A promise that settles like `promise`, but is fulfilled with `undefined`.
+#[allow(unrooted_must_root)]
+pub fn transform_to_undefined(global: &GlobalScope, promise: &Promise) -> Rc<Promise> {
+    let result = Promise::new(global);
+    upon_settlement(promise,
+                    Box::new(ResolveWithUndefined(result.clone())),
+                    Box::new(RejectWithReason(result.clone())));
+    result
+}
+
+/// A reaction that resolves a promise with `undefined`.
+#[derive(JSTraceable, MallocSizeOf)]
+pub struct ResolveWithUndefined(#[ignore_malloc_size_of = "Rc"] pub Rc<Promise>);
+
+impl Callback for ResolveWithUndefined {
+    fn callback(&self, _cx: *mut JSContext, _v: HandleValue) {
+        self.0.resolve_native(&());
+    }
+}
+
+/// A reaction that rejects a promise with the reason it was called with.
+#[derive(JSTraceable, MallocSizeOf)]
+pub struct RejectWithReason(#[ignore_malloc_size_of = "Rc"] pub Rc<Promise>);
+
+impl Callback for RejectWithReason {
+    #[allow(unsafe_code)]
+    fn callback(&self, cx: *mut JSContext, v: HandleValue) {
+        unsafe { self.0.reject(cx, v) };
+    }
+}
+
+/// The JS value of a DOM object, to pass it to script.
+pub fn object_value<T: DomObject>(object: &T) -> JSVal {
+    ObjectValue(object.reflector().get_jsobject().get())
+}
+
+/// Stores an underlying source, sink or transformer object given by script, if any.
+pub fn heap_object(object: Option<*mut JSObject>) -> Box<Heap<*mut JSObject>> {
+    let heap = Box::new(Heap::default());
+    if let Some(object) = object {
+        heap.set(object);
+    }
+    heap
+}
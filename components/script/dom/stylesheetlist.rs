@@ -6,29 +6,80 @@ use dom::bindings::codegen::Bindings::StyleSheetListBinding;
 use dom::bindings::codegen::Bindings::StyleSheetListBinding::StyleSheetListMethods;
 use dom::bindings::reflector::{Reflector, reflect_dom_object};
 use dom::bindings::root::{Dom, DomRoot};
+use dom::cssstylesheet::CSSStyleSheet;
 use dom::document::Document;
+use dom::element::Element;
+use dom::shadowroot::ShadowRoot;
 use dom::stylesheet::StyleSheet;
 use dom::window::Window;
 use dom_struct::dom_struct;
+use servo_arc::Arc;
+use style::stylesheets::Stylesheet;
+
+/// The node whose list of stylesheets a `<style>` or `<link>` element
+/// contributes to: its document, or the shadow root it is in.
+#[derive(Clone, JSTraceable, MallocSizeOf)]
+#[must_root]
+pub enum StyleSheetListOwner {
+    Document(Dom<Document>),
+    ShadowRoot(Dom<ShadowRoot>),
+}
+
+impl StyleSheetListOwner {
+    pub fn stylesheet_count(&self) -> usize {
+        match *self {
+            StyleSheetListOwner::Document(ref doc) => doc.stylesheet_count(),
+            StyleSheetListOwner::ShadowRoot(ref shadow_root) => shadow_root.stylesheet_count(),
+        }
+    }
+
+    pub fn stylesheet_at(&self, index: usize) -> Option<DomRoot<CSSStyleSheet>> {
+        match *self {
+            StyleSheetListOwner::Document(ref doc) => doc.stylesheet_at(index),
+            StyleSheetListOwner::ShadowRoot(ref shadow_root) => shadow_root.stylesheet_at(index),
+        }
+    }
+
+    pub fn add_stylesheet(&self, owner: &Element, sheet: Arc<Stylesheet>) {
+        match *self {
+            StyleSheetListOwner::Document(ref doc) => doc.add_stylesheet(owner, sheet),
+            StyleSheetListOwner::ShadowRoot(ref shadow_root) => shadow_root.add_stylesheet(owner, sheet),
+        }
+    }
+
+    pub fn remove_stylesheet(&self, owner: &Element, s: &Arc<Stylesheet>) {
+        match *self {
+            StyleSheetListOwner::Document(ref doc) => doc.remove_stylesheet(owner, s),
+            StyleSheetListOwner::ShadowRoot(ref shadow_root) => shadow_root.remove_stylesheet(owner, s),
+        }
+    }
+
+    pub fn invalidate_stylesheets(&self) {
+        match *self {
+            StyleSheetListOwner::Document(ref doc) => doc.invalidate_stylesheets(),
+            StyleSheetListOwner::ShadowRoot(ref shadow_root) => shadow_root.invalidate_stylesheets(),
+        }
+    }
+}
 
 #[dom_struct]
 pub struct StyleSheetList {
     reflector_: Reflector,
-    document: Dom<Document>,
+    owner: StyleSheetListOwner,
 }
 
 impl StyleSheetList {
     #[allow(unrooted_must_root)]
-    fn new_inherited(doc: Dom<Document>) -> StyleSheetList {
+    fn new_inherited(owner: StyleSheetListOwner) -> StyleSheetList {
         StyleSheetList {
             reflector_: Reflector::new(),
-            document: doc
+            owner: owner,
         }
     }
 
     #[allow(unrooted_must_root)]
-    pub fn new(window: &Window, document: Dom<Document>) -> DomRoot<StyleSheetList> {
-        reflect_dom_object(Box::new(StyleSheetList::new_inherited(document)),
+    pub fn new(window: &Window, owner: StyleSheetListOwner) -> DomRoot<StyleSheetList> {
+        reflect_dom_object(Box::new(StyleSheetList::new_inherited(owner)),
                            window, StyleSheetListBinding::Wrap)
     }
 }
@@ -36,14 +87,14 @@ impl StyleSheetList {
 impl StyleSheetListMethods for StyleSheetList {
     // https://drafts.csswg.org/cssom/#dom-stylesheetlist-length
     fn Length(&self) -> u32 {
-       self.document.stylesheet_count() as u32
+       self.owner.stylesheet_count() as u32
     }
 
     // https://drafts.csswg.org/cssom/#dom-stylesheetlist-item
     fn Item(&self, index: u32) -> Option<DomRoot<StyleSheet>> {
         // XXXManishearth this  doesn't handle the origin clean flag and is a
         // cors vulnerability
-        self.document.stylesheet_at(index as usize).map(DomRoot::upcast)
+        self.owner.stylesheet_at(index as usize).map(DomRoot::upcast)
     }
 
     // check-tidy: no specs after this line
@@ -2,13 +2,51 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use dom::attr::Attr;
+use dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
 use dom::bindings::inheritance::Castable;
+use dom::bindings::str::DOMString;
 use dom::document::Document;
-use dom::element::Element;
+use dom::element::{AttributeMutation, Element};
+use dom::node::{Node, NodeDamage, window_from_node};
 use dom::virtualmethods::VirtualMethods;
 use dom_struct::dom_struct;
 use html5ever::{LocalName, Prefix};
+use servo_arc::Arc;
+use style::attr::AttrValue;
 use style::element_state::ElementState;
+use style::properties::{Importance, PropertyDeclarationBlock, PropertyId};
+use style::properties::{SourcePropertyDeclaration, parse_one_declaration_into};
+use style_traits::ParsingMode;
+
+/// The presentation attributes of the properties we support, which map to
+/// the property of the same name.
+///
+/// <https://svgwg.org/svg2-draft/styling.html#PresentationAttributes>
+const PRESENTATION_ATTRIBUTES: &'static [&'static str] = &[
+    "color",
+    "display",
+    "fill",
+    "fill-opacity",
+    "fill-rule",
+    "font-family",
+    "font-size",
+    "font-style",
+    "font-weight",
+    "opacity",
+    "stop-color",
+    "stop-opacity",
+    "stroke",
+    "stroke-dasharray",
+    "stroke-dashoffset",
+    "stroke-linecap",
+    "stroke-linejoin",
+    "stroke-miterlimit",
+    "stroke-opacity",
+    "stroke-width",
+    "text-anchor",
+    "visibility",
+];
 
 #[dom_struct]
 pub struct SVGElement {
@@ -16,6 +54,11 @@ pub struct SVGElement {
 }
 
 impl SVGElement {
+    pub fn new_inherited(tag_name: LocalName, prefix: Option<Prefix>,
+                         document: &Document) -> SVGElement {
+        SVGElement::new_inherited_with_state(ElementState::empty(), tag_name, prefix, document)
+    }
+
     pub fn new_inherited_with_state(state: ElementState, tag_name: LocalName,
                                     prefix: Option<Prefix>, document: &Document)
                                     -> SVGElement {
@@ -24,10 +67,66 @@ impl SVGElement {
                 Element::new_inherited_with_state(state, tag_name, ns!(svg), prefix, document),
         }
     }
+
+    /// Parses the value of a presentation attribute into the declaration
+    /// block layout uses as a presentational hint, in which lengths may be
+    /// unitless.
+    fn parse_presentation_attribute(&self, name: &LocalName, value: DOMString) -> AttrValue {
+        let id = match PropertyId::parse_enabled_for_all_content(name) {
+            Ok(id) => id,
+            Err(()) => return AttrValue::String(value.into()),
+        };
+        let window = window_from_node(self);
+        let document = window.Document();
+        let mut declarations = SourcePropertyDeclaration::new();
+        let result = parse_one_declaration_into(
+            &mut declarations,
+            id,
+            &value,
+            &document.base_url(),
+            window.css_error_reporter(),
+            ParsingMode::ALLOW_UNITLESS_LENGTH,
+            document.quirks_mode(),
+        );
+        if result.is_err() {
+            return AttrValue::String(value.into());
+        }
+        let mut block = PropertyDeclarationBlock::new();
+        block.extend(declarations.drain(), Importance::Normal);
+        AttrValue::Declaration(value.into(), Arc::new(document.style_shared_lock().wrap(block)))
+    }
+}
+
+fn is_presentation_attribute(attr: &Attr) -> bool {
+    attr.namespace() == &ns!() && PRESENTATION_ATTRIBUTES.contains(&&**attr.local_name())
 }
 
 impl VirtualMethods for SVGElement {
     fn super_type(&self) -> Option<&VirtualMethods> {
         Some(self.upcast::<Element>() as &VirtualMethods)
     }
+
+    fn attribute_affects_presentational_hints(&self, attr: &Attr) -> bool {
+        if is_presentation_attribute(attr) {
+            return true;
+        }
+
+        self.super_type().unwrap().attribute_affects_presentational_hints(attr)
+    }
+
+    fn attribute_mutated(&self, attr: &Attr, mutation: AttributeMutation) {
+        self.super_type().unwrap().attribute_mutated(attr, mutation);
+        // The geometry of SVG content comes from its attributes, which layout
+        // reads when it builds the scene of the outermost `<svg>` element.
+        if !is_presentation_attribute(attr) {
+            self.upcast::<Node>().dirty(NodeDamage::OtherNodeDamage);
+        }
+    }
+
+    fn parse_plain_attribute(&self, name: &LocalName, value: DOMString) -> AttrValue {
+        if PRESENTATION_ATTRIBUTES.contains(&&**name) {
+            return self.parse_presentation_attribute(name, value);
+        }
+        self.super_type().unwrap().parse_plain_attribute(name, value)
+    }
 }
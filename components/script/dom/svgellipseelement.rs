@@ -0,0 +1,35 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::SVGEllipseElementBinding;
+use dom::bindings::root::DomRoot;
+use dom::document::Document;
+use dom::node::Node;
+use dom::svggeometryelement::SVGGeometryElement;
+use dom_struct::dom_struct;
+use html5ever::{LocalName, Prefix};
+
+#[dom_struct]
+pub struct SVGEllipseElement {
+    svggeometryelement: SVGGeometryElement,
+}
+
+impl SVGEllipseElement {
+    fn new_inherited(local_name: LocalName,
+                     prefix: Option<Prefix>,
+                     document: &Document) -> SVGEllipseElement {
+        SVGEllipseElement {
+            svggeometryelement: SVGGeometryElement::new_inherited(local_name, prefix, document),
+        }
+    }
+
+    #[allow(unrooted_must_root)]
+    pub fn new(local_name: LocalName,
+               prefix: Option<Prefix>,
+               document: &Document) -> DomRoot<SVGEllipseElement> {
+        Node::reflect_node(Box::new(SVGEllipseElement::new_inherited(local_name, prefix, document)),
+                           document,
+                           SVGEllipseElementBinding::Wrap)
+    }
+}
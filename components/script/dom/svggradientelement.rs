@@ -0,0 +1,22 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::document::Document;
+use dom::svgelement::SVGElement;
+use dom_struct::dom_struct;
+use html5ever::{LocalName, Prefix};
+
+#[dom_struct]
+pub struct SVGGradientElement {
+    svgelement: SVGElement,
+}
+
+impl SVGGradientElement {
+    pub fn new_inherited(tag_name: LocalName, prefix: Option<Prefix>,
+                         document: &Document) -> SVGGradientElement {
+        SVGGradientElement {
+            svgelement: SVGElement::new_inherited(tag_name, prefix, document),
+        }
+    }
+}
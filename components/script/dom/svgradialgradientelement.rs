@@ -0,0 +1,35 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::SVGRadialGradientElementBinding;
+use dom::bindings::root::DomRoot;
+use dom::document::Document;
+use dom::node::Node;
+use dom::svggradientelement::SVGGradientElement;
+use dom_struct::dom_struct;
+use html5ever::{LocalName, Prefix};
+
+#[dom_struct]
+pub struct SVGRadialGradientElement {
+    svggradientelement: SVGGradientElement,
+}
+
+impl SVGRadialGradientElement {
+    fn new_inherited(local_name: LocalName,
+                     prefix: Option<Prefix>,
+                     document: &Document) -> SVGRadialGradientElement {
+        SVGRadialGradientElement {
+            svggradientelement: SVGGradientElement::new_inherited(local_name, prefix, document),
+        }
+    }
+
+    #[allow(unrooted_must_root)]
+    pub fn new(local_name: LocalName,
+               prefix: Option<Prefix>,
+               document: &Document) -> DomRoot<SVGRadialGradientElement> {
+        Node::reflect_node(Box::new(SVGRadialGradientElement::new_inherited(local_name, prefix, document)),
+                           document,
+                           SVGRadialGradientElementBinding::Wrap)
+    }
+}
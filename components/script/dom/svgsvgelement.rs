@@ -6,7 +6,6 @@ use dom::attr::Attr;
 use dom::bindings::codegen::Bindings::SVGSVGElementBinding;
 use dom::bindings::inheritance::Castable;
 use dom::bindings::root::{DomRoot, LayoutDom};
-use dom::bindings::str::DOMString;
 use dom::document::Document;
 use dom::element::{AttributeMutation, Element, RawLayoutElementHelpers};
 use dom::node::Node;
@@ -14,8 +13,8 @@ use dom::svggraphicselement::SVGGraphicsElement;
 use dom::virtualmethods::VirtualMethods;
 use dom_struct::dom_struct;
 use html5ever::{LocalName, Prefix};
+use net_traits::image::svg::Length;
 use script_layout_interface::SVGSVGData;
-use style::attr::AttrValue;
 
 const DEFAULT_WIDTH: u32 = 300;
 const DEFAULT_HEIGHT: u32 = 150;
@@ -45,6 +44,15 @@ impl SVGSVGElement {
     }
 }
 
+/// The size in pixels given by a `width` or `height` attribute. Percentages
+/// aren't resolved, since layout sizes the element as a replaced box.
+fn parse_size(value: &str, default: u32) -> u32 {
+    match Length::parse(value) {
+        Some(Length::Absolute(length)) if length >= 0. => length.round() as u32,
+        _ => default,
+    }
+}
+
 pub trait LayoutSVGSVGElementHelpers {
     fn data(&self) -> SVGSVGData;
 }
@@ -55,11 +63,12 @@ impl LayoutSVGSVGElementHelpers for LayoutDom<SVGSVGElement> {
         unsafe {
             let SVG = &*self.unsafe_get();
 
-            let width_attr = SVG.upcast::<Element>().get_attr_for_layout(&ns!(), &local_name!("width"));
-            let height_attr = SVG.upcast::<Element>().get_attr_for_layout(&ns!(), &local_name!("height"));
+            let element = SVG.upcast::<Element>();
+            let width_attr = element.get_attr_val_for_layout(&ns!(), &local_name!("width"));
+            let height_attr = element.get_attr_val_for_layout(&ns!(), &local_name!("height"));
             SVGSVGData {
-                width: width_attr.map_or(DEFAULT_WIDTH, |val| val.as_uint()),
-                height: height_attr.map_or(DEFAULT_HEIGHT, |val| val.as_uint()),
+                width: width_attr.map_or(DEFAULT_WIDTH, |val| parse_size(val, DEFAULT_WIDTH)),
+                height: height_attr.map_or(DEFAULT_HEIGHT, |val| parse_size(val, DEFAULT_HEIGHT)),
             }
         }
     }
@@ -73,12 +82,4 @@ impl VirtualMethods for SVGSVGElement {
     fn attribute_mutated(&self, attr: &Attr, mutation: AttributeMutation) {
         self.super_type().unwrap().attribute_mutated(attr, mutation);
     }
-
-    fn parse_plain_attribute(&self, name: &LocalName, value: DOMString) -> AttrValue {
-        match name {
-            &local_name!("width") => AttrValue::from_u32(value.into(), DEFAULT_WIDTH),
-            &local_name!("height") => AttrValue::from_u32(value.into(), DEFAULT_HEIGHT),
-            _ => self.super_type().unwrap().parse_plain_attribute(name, value),
-        }
-    }
 }
@@ -13,6 +13,7 @@ use dom::bindings::root::{DomRoot, RootedReference};
 use dom::bindings::str::DOMString;
 use dom::characterdata::CharacterData;
 use dom::document::Document;
+use dom::htmlslotelement::HTMLSlotElement;
 use dom::node::Node;
 use dom::window::Window;
 use dom_struct::dom_struct;
@@ -90,4 +91,9 @@ impl TextMethods for Text {
         }
         DOMString::from(text)
     }
+
+    // https://dom.spec.whatwg.org/#dom-slotable-assignedslot
+    fn GetAssignedSlot(&self) -> Option<DomRoot<HTMLSlotElement>> {
+        HTMLSlotElement::find_a_slot(self.upcast(), true)
+    }
 }
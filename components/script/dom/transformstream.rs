@@ -0,0 +1,281 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::cell::DomRefCell;
+use dom::bindings::codegen::Bindings::QueuingStrategyBinding::QueuingStrategy;
+use dom::bindings::codegen::Bindings::TransformStreamBinding;
+use dom::bindings::codegen::Bindings::TransformStreamBinding::TransformStreamMethods;
+use dom::bindings::conversions::get_property_jsval;
+use dom::bindings::error::{Error, Fallible};
+use dom::bindings::reflector::{DomObject, Reflector, reflect_dom_object};
+use dom::bindings::root::{Dom, DomRoot, MutNullableDom};
+use dom::globalscope::GlobalScope;
+use dom::promise::Promise;
+use dom::promisenativehandler::Callback;
+use dom::readablestream::{ReadableStream, ReadableStreamState, UnderlyingSource};
+use dom::readablestreamdefaultcontroller::ReadableStreamDefaultController;
+use dom::streams::{RejectWithReason, ResolveWithUndefined, invoke_or_noop, object_value, resolved_promise};
+use dom::streams::{upon_settlement, validate_high_water_mark};
+use dom::transformstreamdefaultcontroller::TransformStreamDefaultController;
+use dom::writablestream::{WritableStream, WritableStreamState};
+use dom::writablestreamdefaultcontroller::UnderlyingSink;
+use dom_struct::dom_struct;
+use js::jsapi::{Heap, JSContext, JSObject};
+use js::jsval::{JSVal, UndefinedValue};
+use js::rust::HandleValue;
+use std::cell::Cell;
+use std::ptr;
+use std::rc::Rc;
+
+/// <https://streams.spec.whatwg.org/#ts-class>
+#[dom_struct]
+pub struct TransformStream {
+    reflector_: Reflector,
+    readable: MutNullableDom<ReadableStream>,
+    writable: MutNullableDom<WritableStream>,
+    controller: MutNullableDom<TransformStreamDefaultController>,
+    /// <https://streams.spec.whatwg.org/#transformstream-backpressure>
+    backpressure: Cell<bool>,
+    /// <https://streams.spec.whatwg.org/#transformstream-backpressurechangepromise>
+    #[ignore_malloc_size_of = "Rc"]
+    backpressure_change_promise: DomRefCell<Option<Rc<Promise>>>,
+    /// Resolved once the `start` method of the transformer has run.
+    #[ignore_malloc_size_of = "Rc"]
+    start_promise: Rc<Promise>,
+}
+
+impl TransformStream {
+    #[allow(unrooted_must_root)]
+    fn new(global: &GlobalScope) -> DomRoot<TransformStream> {
+        reflect_dom_object(Box::new(TransformStream {
+            reflector_: Reflector::new(),
+            readable: Default::default(),
+            writable: Default::default(),
+            controller: Default::default(),
+            backpressure: Cell::new(false),
+            backpressure_change_promise: Default::default(),
+            start_promise: Promise::new(global),
+        }), global, TransformStreamBinding::Wrap)
+    }
+
+    /// <https://streams.spec.whatwg.org/#ts-constructor>
+    #[allow(unsafe_code)]
+    pub unsafe fn Constructor(cx: *mut JSContext,
+                              global: &GlobalScope,
+                              transformer: Option<*mut JSObject>,
+                              writable_strategy: &QueuingStrategy,
+                              readable_strategy: &QueuingStrategy)
+                              -> Fallible<DomRoot<TransformStream>> {
+        rooted!(in(cx) let transformer = transformer.unwrap_or(ptr::null_mut()));
+        if !transformer.is_null() {
+            for name in &["readableType", "writableType"] {
+                rooted!(in(cx) let mut stream_type = UndefinedValue());
+                get_property_jsval(cx, transformer.handle(), name, stream_type.handle_mut())?;
+                if !stream_type.is_undefined() {
+                    return Err(Error::Range(format!("Unknown {}", name)));
+                }
+            }
+        }
+        let readable_high_water_mark = validate_high_water_mark(readable_strategy.highWaterMark, 0.)?;
+        let writable_high_water_mark = validate_high_water_mark(writable_strategy.highWaterMark, 1.)?;
+
+        // https://streams.spec.whatwg.org/#initialize-transform-stream
+        let stream = TransformStream::new(global);
+        let writable = WritableStream::new_with_sink(global,
+                                                     UnderlyingSink::Transform(Dom::from_ref(&*stream)),
+                                                     writable_high_water_mark,
+                                                     writable_strategy.size.clone())?;
+        stream.writable.set(Some(&writable));
+        let readable = ReadableStream::new_with_source(global,
+                                                       UnderlyingSource::Transform(Dom::from_ref(&*stream)),
+                                                       readable_high_water_mark);
+        stream.readable.set(Some(&readable));
+        stream.set_backpressure(true);
+
+        let controller = TransformStreamDefaultController::new(global, &stream, transformer.get());
+        stream.controller.set(Some(&controller));
+        rooted!(in(cx) let controller_value = object_value(&*controller));
+        rooted!(in(cx) let mut start_result = UndefinedValue());
+        invoke_or_noop(cx, transformer.handle(), "start", &[controller_value.get()], start_result.handle_mut())?;
+        stream.start_promise.resolve(cx, start_result.handle());
+        Ok(stream)
+    }
+
+    #[allow(unrooted_must_root)]
+    pub fn start_promise(&self) -> Rc<Promise> {
+        self.start_promise.clone()
+    }
+
+    pub fn readable(&self) -> DomRoot<ReadableStream> {
+        self.readable.get().expect("A transform stream without a readable side")
+    }
+
+    pub fn writable(&self) -> DomRoot<WritableStream> {
+        self.writable.get().expect("A transform stream without a writable side")
+    }
+
+    pub fn readable_controller(&self) -> DomRoot<ReadableStreamDefaultController> {
+        self.readable().default_controller().expect("A readable side without a controller")
+    }
+
+    fn controller(&self) -> DomRoot<TransformStreamDefaultController> {
+        self.controller.get().expect("A transform stream without a controller")
+    }
+
+    pub fn backpressure(&self) -> bool {
+        self.backpressure.get()
+    }
+
+    /// <https://streams.spec.whatwg.org/#transform-stream-set-backpressure>
+    pub fn set_backpressure(&self, backpressure: bool) {
+        if let Some(promise) = self.backpressure_change_promise.borrow_mut().take() {
+            promise.resolve_native(&());
+        }
+        *self.backpressure_change_promise.borrow_mut() = Some(Promise::new(&self.global()));
+        self.backpressure.set(backpressure);
+    }
+
+    /// <https://streams.spec.whatwg.org/#transform-stream-error>
+    pub fn error(&self, cx: *mut JSContext, error: HandleValue) {
+        self.readable_controller().error(cx, error);
+        self.error_writable_and_unblock_write(cx, error);
+    }
+
+    /// <https://streams.spec.whatwg.org/#transform-stream-error-writable-and-unblock-write>
+    pub fn error_writable_and_unblock_write(&self, cx: *mut JSContext, error: HandleValue) {
+        self.controller().clear_algorithms();
+        self.writable().controller().error_if_needed(cx, error);
+        if self.backpressure.get() {
+            self.set_backpressure(false);
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#transform-stream-default-sink-write-algorithm>
+    #[allow(unrooted_must_root)]
+    pub fn writable_write(&self, cx: *mut JSContext, chunk: HandleValue) -> Rc<Promise> {
+        if !self.backpressure.get() {
+            return self.controller().perform_transform(cx, chunk);
+        }
+        let promise = Promise::new(&self.global());
+        let backpressure_change_promise = self.backpressure_change_promise.borrow().clone()
+            .expect("Backpressure without a promise");
+        upon_settlement(&backpressure_change_promise,
+                        Box::new(TransformAfterBackpressure {
+                            stream: Dom::from_ref(self),
+                            chunk: Heap::boxed(chunk.get()),
+                            promise: promise.clone(),
+                        }),
+                        Box::new(RejectWithReason(promise.clone())));
+        promise
+    }
+
+    /// <https://streams.spec.whatwg.org/#transform-stream-default-sink-abort-algorithm>
+    #[allow(unrooted_must_root)]
+    pub fn writable_abort(&self, cx: *mut JSContext, reason: HandleValue) -> Rc<Promise> {
+        self.error(cx, reason);
+        resolved_promise(&self.global())
+    }
+
+    /// <https://streams.spec.whatwg.org/#transform-stream-default-sink-close-algorithm>
+    #[allow(unrooted_must_root)]
+    pub fn writable_close(&self) -> Rc<Promise> {
+        let controller = self.controller();
+        let flush_promise = controller.flush();
+        controller.clear_algorithms();
+        let promise = Promise::new(&self.global());
+        upon_settlement(&flush_promise,
+                        FlushReaction::new(self, &promise, true),
+                        FlushReaction::new(self, &promise, false));
+        promise
+    }
+
+    /// <https://streams.spec.whatwg.org/#transform-stream-default-source-pull>
+    #[allow(unrooted_must_root)]
+    pub fn readable_pull(&self) -> Rc<Promise> {
+        self.set_backpressure(false);
+        self.backpressure_change_promise.borrow().clone().expect("Backpressure without a promise")
+    }
+
+    /// <https://streams.spec.whatwg.org/#transform-stream-default-source-cancel>
+    #[allow(unrooted_must_root)]
+    pub fn readable_cancel(&self, cx: *mut JSContext, reason: HandleValue) -> Rc<Promise> {
+        self.error_writable_and_unblock_write(cx, reason);
+        resolved_promise(&self.global())
+    }
+}
+
+impl TransformStreamMethods for TransformStream {
+    /// <https://streams.spec.whatwg.org/#ts-readable>
+    fn Readable(&self) -> DomRoot<ReadableStream> {
+        self.readable()
+    }
+
+    /// <https://streams.spec.whatwg.org/#ts-writable>
+    fn Writable(&self) -> DomRoot<WritableStream> {
+        self.writable()
+    }
+}
+
+/// Transforms a chunk once the backpressure that held its write back is relieved.
+#[derive(JSTraceable, MallocSizeOf)]
+#[must_root]
+struct TransformAfterBackpressure {
+    stream: Dom<TransformStream>,
+    #[ignore_malloc_size_of = "Defined in rust-mozjs"]
+    chunk: Box<Heap<JSVal>>,
+    #[ignore_malloc_size_of = "Rc"]
+    promise: Rc<Promise>,
+}
+
+impl Callback for TransformAfterBackpressure {
+    #[allow(unrooted_must_root, unsafe_code)]
+    fn callback(&self, cx: *mut JSContext, _v: HandleValue) {
+        let writable = self.stream.writable();
+        if writable.state() == WritableStreamState::Erroring {
+            rooted!(in(cx) let mut error = UndefinedValue());
+            writable.stored_error(error.handle_mut());
+            return unsafe { self.promise.reject(cx, error.handle()) };
+        }
+        let transform_promise = self.stream.controller().perform_transform(cx, self.chunk.handle());
+        upon_settlement(&transform_promise,
+                        Box::new(ResolveWithUndefined(self.promise.clone())),
+                        Box::new(RejectWithReason(self.promise.clone())));
+    }
+}
+
+/// Closes or errors the readable side once the transformer has been flushed.
+#[derive(JSTraceable, MallocSizeOf)]
+#[must_root]
+struct FlushReaction {
+    stream: Dom<TransformStream>,
+    #[ignore_malloc_size_of = "Rc"]
+    promise: Rc<Promise>,
+    fulfilled: bool,
+}
+
+impl FlushReaction {
+    #[allow(unrooted_must_root)]
+    fn new(stream: &TransformStream, promise: &Rc<Promise>, fulfilled: bool) -> Box<Callback> {
+        Box::new(FlushReaction { stream: Dom::from_ref(stream), promise: promise.clone(), fulfilled: fulfilled })
+    }
+}
+
+impl Callback for FlushReaction {
+    #[allow(unsafe_code)]
+    fn callback(&self, cx: *mut JSContext, v: HandleValue) {
+        let readable = self.stream.readable();
+        if !self.fulfilled {
+            self.stream.error(cx, v);
+        } else if readable.state() != ReadableStreamState::Errored {
+            let controller = self.stream.readable_controller();
+            if controller.can_close_or_enqueue() {
+                let _ = controller.close();
+            }
+            return self.promise.resolve_native(&());
+        }
+        rooted!(in(cx) let mut error = UndefinedValue());
+        readable.stored_error(error.handle_mut());
+        unsafe { self.promise.reject(cx, error.handle()) };
+    }
+}
@@ -0,0 +1,186 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::cell::DomRefCell;
+use dom::bindings::codegen::Bindings::TransformStreamDefaultControllerBinding;
+use dom::bindings::codegen::Bindings::TransformStreamDefaultControllerBinding::TransformStreamDefaultControllerMethods;
+use dom::bindings::conversions::get_property_jsval;
+use dom::bindings::error::{Error, Fallible};
+use dom::bindings::reflector::{DomObject, Reflector, reflect_dom_object};
+use dom::bindings::root::{Dom, DomRoot};
+use dom::globalscope::GlobalScope;
+use dom::promise::Promise;
+use dom::promisenativehandler::Callback;
+use dom::readablestream::ReadableStream;
+use dom::streams::{ResolveWithUndefined, error_to_jsval, object_value, promise_invoke_or_noop};
+use dom::streams::{rejected_promise, resolved_promise, upon_settlement};
+use dom::transformstream::TransformStream;
+use dom_struct::dom_struct;
+use js::jsapi::{Heap, JSContext, JSObject};
+use js::jsval::UndefinedValue;
+use js::rust::HandleValue;
+use js::rust::wrappers::JS_SetPendingException;
+use std::ptr;
+use std::rc::Rc;
+
+/// <https://streams.spec.whatwg.org/#ts-default-controller-class>
+#[dom_struct]
+pub struct TransformStreamDefaultController {
+    reflector_: Reflector,
+    stream: Dom<TransformStream>,
+    /// The transformer object given by script, until its algorithms are cleared.
+    #[ignore_malloc_size_of = "Defined in rust-mozjs"]
+    transformer: DomRefCell<Option<Box<Heap<*mut JSObject>>>>,
+}
+
+impl TransformStreamDefaultController {
+    pub fn new(global: &GlobalScope,
+               stream: &TransformStream,
+               transformer: *mut JSObject)
+               -> DomRoot<TransformStreamDefaultController> {
+        reflect_dom_object(Box::new(TransformStreamDefaultController {
+            reflector_: Reflector::new(),
+            stream: Dom::from_ref(stream),
+            transformer: DomRefCell::new(Some(Heap::boxed(transformer))),
+        }), global, TransformStreamDefaultControllerBinding::Wrap)
+    }
+
+    /// <https://streams.spec.whatwg.org/#transform-stream-default-controller-clear-algorithms>
+    pub fn clear_algorithms(&self) {
+        *self.transformer.borrow_mut() = None;
+    }
+
+    fn transformer(&self) -> Option<*mut JSObject> {
+        self.transformer.borrow().as_ref().map(|transformer| transformer.get())
+    }
+
+    /// Runs the `transform` method of the transformer on `chunk`, or enqueues the chunk as is
+    /// when there is no such method.
+    ///
+    /// <https://streams.spec.whatwg.org/#transform-stream-default-controller-perform-transform>
+    #[allow(unrooted_must_root, unsafe_code)]
+    pub fn perform_transform(&self, cx: *mut JSContext, chunk: HandleValue) -> Rc<Promise> {
+        let global = self.global();
+        rooted!(in(cx) let transformer = self.transformer().unwrap_or(ptr::null_mut()));
+        rooted!(in(cx) let mut transform = UndefinedValue());
+        if !transformer.is_null() {
+            let result = unsafe { get_property_jsval(cx, transformer.handle(), "transform", transform.handle_mut()) };
+            if let Err(error) = result {
+                unsafe { error_to_jsval(cx, &global, error, transform.handle_mut()) };
+                return rejected_promise(&global, cx, transform.handle());
+            }
+        }
+        if transform.is_undefined() {
+            return match self.enqueue(cx, chunk) {
+                Ok(()) => resolved_promise(&global),
+                Err(error) => {
+                    rooted!(in(cx) let mut error_value = UndefinedValue());
+                    unsafe { error_to_jsval(cx, &global, error, error_value.handle_mut()) };
+                    rejected_promise(&global, cx, error_value.handle())
+                },
+            };
+        }
+        let args = [chunk.get(), object_value(self)];
+        let transform_promise = unsafe {
+            promise_invoke_or_noop(&global, cx, transformer.handle(), "transform", &args)
+        };
+        let promise = Promise::new(&global);
+        let on_rejected = TransformRejected { stream: Dom::from_ref(&*self.stream), promise: promise.clone() };
+        upon_settlement(&transform_promise,
+                        Box::new(ResolveWithUndefined(promise.clone())),
+                        Box::new(on_rejected));
+        promise
+    }
+
+    /// Runs the `flush` method of the transformer.
+    #[allow(unrooted_must_root, unsafe_code)]
+    pub fn flush(&self) -> Rc<Promise> {
+        let global = self.global();
+        let transformer = match self.transformer() {
+            Some(transformer) => transformer,
+            None => return resolved_promise(&global),
+        };
+        let cx = global.get_cx();
+        rooted!(in(cx) let transformer = transformer);
+        unsafe { promise_invoke_or_noop(&global, cx, transformer.handle(), "flush", &[object_value(self)]) }
+    }
+
+    /// <https://streams.spec.whatwg.org/#transform-stream-default-controller-enqueue>
+    #[allow(unsafe_code)]
+    fn enqueue(&self, cx: *mut JSContext, chunk: HandleValue) -> Fallible<()> {
+        let controller = self.stream.readable_controller();
+        if !controller.can_close_or_enqueue() {
+            return Err(Error::Type("The readable side can't be enqueued into".to_owned()));
+        }
+        if let Err(error) = controller.enqueue(cx, chunk) {
+            let readable = self.readable();
+            rooted!(in(cx) let mut error_value = UndefinedValue());
+            unsafe { error_to_jsval(cx, &self.global(), error, error_value.handle_mut()) };
+            self.stream.error_writable_and_unblock_write(cx, error_value.handle());
+            readable.stored_error(error_value.handle_mut());
+            unsafe { JS_SetPendingException(cx, error_value.handle()) };
+            return Err(Error::JSFailed);
+        }
+        if controller.has_backpressure() && !self.stream.backpressure() {
+            self.stream.set_backpressure(true);
+        }
+        Ok(())
+    }
+
+    fn readable(&self) -> DomRoot<ReadableStream> {
+        self.stream.readable()
+    }
+}
+
+impl TransformStreamDefaultControllerMethods for TransformStreamDefaultController {
+    /// <https://streams.spec.whatwg.org/#ts-default-controller-desired-size>
+    fn GetDesiredSize(&self) -> Option<f64> {
+        self.stream.readable_controller().desired_size()
+    }
+
+    /// <https://streams.spec.whatwg.org/#ts-default-controller-enqueue>
+    #[allow(unsafe_code)]
+    unsafe fn Enqueue(&self, cx: *mut JSContext, chunk: HandleValue) -> Fallible<()> {
+        self.enqueue(cx, chunk)
+    }
+
+    /// <https://streams.spec.whatwg.org/#ts-default-controller-error>
+    #[allow(unsafe_code)]
+    unsafe fn Error(&self, cx: *mut JSContext, reason: HandleValue) {
+        self.stream.error(cx, reason)
+    }
+
+    /// <https://streams.spec.whatwg.org/#ts-default-controller-terminate>
+    #[allow(unsafe_code)]
+    fn Terminate(&self) {
+        let controller = self.stream.readable_controller();
+        if controller.can_close_or_enqueue() {
+            let _ = controller.close();
+        }
+        let global = self.global();
+        let cx = global.get_cx();
+        rooted!(in(cx) let mut error = UndefinedValue());
+        unsafe {
+            Error::Type("The transform stream was terminated".to_owned()).to_jsval(cx, &global, error.handle_mut());
+        }
+        self.stream.error_writable_and_unblock_write(cx, error.handle());
+    }
+}
+
+/// Errors the transform stream when the `transform` method of the transformer fails.
+#[derive(JSTraceable, MallocSizeOf)]
+#[must_root]
+struct TransformRejected {
+    stream: Dom<TransformStream>,
+    #[ignore_malloc_size_of = "Rc"]
+    promise: Rc<Promise>,
+}
+
+impl Callback for TransformRejected {
+    #[allow(unsafe_code)]
+    fn callback(&self, cx: *mut JSContext, v: HandleValue) {
+        self.stream.error(cx, v);
+        unsafe { self.promise.reject(cx, v) };
+    }
+}
@@ -1,9 +1,190 @@
 /* This Source Code Form is subject to the terms of the Mozilla Public
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
-use dom::validitystate::ValidationFlags;
+use dom::bindings::codegen::Bindings::EventBinding::EventMethods;
+use dom::bindings::codegen::Bindings::HTMLElementBinding::HTMLElementMethods;
+use dom::bindings::inheritance::Castable;
+use dom::bindings::reflector::DomObject;
+use dom::bindings::root::DomRoot;
+use dom::bindings::str::DOMString;
+use dom::console::Console;
+use dom::element::Element;
+use dom::eventtarget::EventTarget;
+use dom::htmldatalistelement::HTMLDataListElement;
+use dom::htmlelement::HTMLElement;
+use dom::node::Node;
+use dom::validitystate::{ValidationFlags, ValidityState};
+use style::element_state::ElementState;
 
+/// Trait for elements that take part in
+/// <https://html.spec.whatwg.org/multipage/#constraint-validation>
 pub trait Validatable {
-    fn is_instance_validatable(&self) -> bool { true }
-    fn validate(&self, _validate_flags: ValidationFlags) -> bool { true }
+    fn as_element(&self) -> &Element;
+
+    /// The `ValidityState` of this element, which also holds its custom validity error message.
+    fn validity_state(&self) -> DomRoot<ValidityState>;
+
+    /// <https://html.spec.whatwg.org/multipage/#candidate-for-constraint-validation>
+    fn is_instance_validatable(&self) -> bool;
+
+    /// Whether the element is required, or `None` if it can't be required at all.
+    /// <https://html.spec.whatwg.org/multipage/#selector-required>
+    fn required_state(&self) -> Option<bool> {
+        None
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#have-range-limitations>
+    fn has_range_limitations(&self) -> bool {
+        false
+    }
+
+    /// Checks the element specific constraints given in `validate_flags`, and returns
+    /// the ones the element is suffering from.
+    fn perform_validation(&self, _validate_flags: ValidationFlags) -> ValidationFlags {
+        ValidationFlags::empty()
+    }
+
+    /// Returns the constraints given in `validate_flags` the element is suffering from,
+    /// including a custom error.
+    fn validate(&self, validate_flags: ValidationFlags) -> ValidationFlags {
+        let mut failed_flags = self.perform_validation(validate_flags);
+        if validate_flags.contains(ValidationFlags::CUSTOM_ERROR) &&
+           !self.validity_state().custom_error_message().is_empty()
+        {
+            failed_flags.insert(ValidationFlags::CUSTOM_ERROR);
+        }
+        failed_flags
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#concept-fv-valid>
+    fn satisfies_constraints(&self) -> bool {
+        self.validate(ValidationFlags::all()).is_empty()
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#check-validity-steps>
+    fn check_validity(&self) -> bool {
+        if self.is_instance_validatable() && !self.satisfies_constraints() {
+            self.as_element().upcast::<EventTarget>().fire_cancelable_event(atom!("invalid"));
+            false
+        } else {
+            true
+        }
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#report-validity-steps>
+    fn report_validity(&self) -> bool {
+        // Step 1.
+        if !self.is_instance_validatable() || self.satisfies_constraints() {
+            return true;
+        }
+
+        // Step 1.1.
+        let event = self.as_element().upcast::<EventTarget>().fire_cancelable_event(atom!("invalid"));
+
+        // Step 1.2.
+        if !event.DefaultPrevented() {
+            report_problem(self, true);
+        }
+
+        // Step 1.3.
+        false
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#dom-cva-validationmessage>
+    fn validation_message(&self) -> DOMString {
+        if self.is_instance_validatable() {
+            validation_message_for_flags(&self.validity_state(), self.validate(ValidationFlags::all()))
+        } else {
+            DOMString::new()
+        }
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#dom-cva-setcustomvalidity>
+    fn set_custom_validity(&self, error: DOMString) {
+        self.validity_state().set_custom_error_message(error);
+        self.update_validity_state();
+    }
+
+    /// Makes the element match `:valid` or `:invalid`, `:in-range` or `:out-of-range`, and
+    /// `:required` or `:optional` according to its current state.
+    /// Needs to be called whenever something a constraint depends on changes.
+    fn update_validity_state(&self) {
+        let element = self.as_element();
+        let (valid, invalid) = if self.is_instance_validatable() {
+            let satisfies_constraints = self.satisfies_constraints();
+            (satisfies_constraints, !satisfies_constraints)
+        } else {
+            (false, false)
+        };
+        element.set_state(ElementState::IN_VALID_STATE, valid);
+        element.set_state(ElementState::IN_INVALID_STATE, invalid);
+
+        // https://html.spec.whatwg.org/multipage/#selector-in-range
+        // https://html.spec.whatwg.org/multipage/#selector-out-of-range
+        let (in_range, out_of_range) = if self.is_instance_validatable() && self.has_range_limitations() {
+            let range_flags = ValidationFlags::RANGE_UNDERFLOW | ValidationFlags::RANGE_OVERFLOW;
+            let out_of_range = !self.validate(range_flags).is_empty();
+            (!out_of_range, out_of_range)
+        } else {
+            (false, false)
+        };
+        element.set_state(ElementState::IN_INRANGE_STATE, in_range);
+        element.set_state(ElementState::IN_OUTOFRANGE_STATE, out_of_range);
+
+        let required = self.required_state();
+        element.set_state(ElementState::IN_REQUIRED_STATE, required == Some(true));
+        element.set_state(ElementState::IN_OPTIONAL_STATE, required == Some(false));
+    }
+}
+
+/// <https://html.spec.whatwg.org/multipage/#the-datalist-element:barred-from-constraint-validation>
+pub fn is_barred_by_datalist_ancestor(element: &Element) -> bool {
+    element.upcast::<Node>().ancestors().any(|ancestor| ancestor.is::<HTMLDataListElement>())
+}
+
+/// Reports the problems with the constraints of `validatable` to the user, and focuses it
+/// if `focus` is true.
+/// <https://html.spec.whatwg.org/multipage/#report-validity-steps>
+pub fn report_problem<V: Validatable + ?Sized>(validatable: &V, focus: bool) {
+    // TODO: Show the message in the embedder instead of the console.
+    let message = validation_message_for_flags(&validatable.validity_state(),
+                                               validatable.validate(ValidationFlags::all()));
+    let element = validatable.as_element();
+    Console::Warn(&element.global(), vec![message]);
+    if !focus {
+        return;
+    }
+    if let Some(html_element) = element.downcast::<HTMLElement>() {
+        html_element.Focus();
+    }
+}
+
+/// A message describing the first problem among `failed_flags`.
+pub fn validation_message_for_flags(state: &ValidityState, failed_flags: ValidationFlags) -> DOMString {
+    if failed_flags.contains(ValidationFlags::CUSTOM_ERROR) {
+        return state.custom_error_message();
+    }
+
+    let message = if failed_flags.contains(ValidationFlags::VALUE_MISSING) {
+        "Please fill out this field."
+    } else if failed_flags.contains(ValidationFlags::TYPE_MISMATCH) {
+        "Please enter a value of the requested type."
+    } else if failed_flags.contains(ValidationFlags::PATTERN_MISMATCH) {
+        "Please match the requested format."
+    } else if failed_flags.contains(ValidationFlags::TOO_LONG) {
+        "Please shorten this text."
+    } else if failed_flags.contains(ValidationFlags::TOO_SHORT) {
+        "Please lengthen this text."
+    } else if failed_flags.contains(ValidationFlags::RANGE_UNDERFLOW) {
+        "Please select a value that is no less than the minimum."
+    } else if failed_flags.contains(ValidationFlags::RANGE_OVERFLOW) {
+        "Please select a value that is no more than the maximum."
+    } else if failed_flags.contains(ValidationFlags::STEP_MISMATCH) {
+        "Please select a valid value."
+    } else if failed_flags.contains(ValidationFlags::BAD_INPUT) {
+        "Please enter a valid value."
+    } else {
+        ""
+    };
+    DOMString::from(message)
 }
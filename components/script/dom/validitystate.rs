@@ -2,31 +2,17 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use dom::bindings::cell::DomRefCell;
 use dom::bindings::codegen::Bindings::ValidityStateBinding;
 use dom::bindings::codegen::Bindings::ValidityStateBinding::ValidityStateMethods;
 use dom::bindings::reflector::{Reflector, reflect_dom_object};
 use dom::bindings::root::{Dom, DomRoot};
+use dom::bindings::str::DOMString;
 use dom::element::Element;
 use dom::window::Window;
 use dom_struct::dom_struct;
 
 // https://html.spec.whatwg.org/multipage/#validity-states
-#[derive(JSTraceable, MallocSizeOf)]
-#[allow(dead_code)]
-pub enum ValidityStatus {
-    ValueMissing,
-    TypeMismatch,
-    PatternMismatch,
-    TooLong,
-    TooShort,
-    RangeUnderflow,
-    RangeOverflow,
-    StepMismatch,
-    BadInput,
-    CustomError,
-    Valid
-}
-
 bitflags!{
     pub struct ValidationFlags: u32 {
         const VALUE_MISSING    = 0b0000000001;
@@ -47,7 +33,8 @@ bitflags!{
 pub struct ValidityState {
     reflector_: Reflector,
     element: Dom<Element>,
-    state: ValidityStatus
+    // https://html.spec.whatwg.org/multipage/#custom-validity-error-message
+    custom_error_message: DomRefCell<DOMString>,
 }
 
 
@@ -56,7 +43,7 @@ impl ValidityState {
         ValidityState {
             reflector_: Reflector::new(),
             element: Dom::from_ref(element),
-            state: ValidityStatus::Valid
+            custom_error_message: DomRefCell::new(DOMString::new()),
         }
     }
 
@@ -65,61 +52,76 @@ impl ValidityState {
                            window,
                            ValidityStateBinding::Wrap)
     }
+
+    pub fn custom_error_message(&self) -> DOMString {
+        self.custom_error_message.borrow().clone()
+    }
+
+    pub fn set_custom_error_message(&self, error: DOMString) {
+        *self.custom_error_message.borrow_mut() = error;
+    }
+
+    fn invalid_flags(&self) -> ValidationFlags {
+        match self.element.as_maybe_validatable() {
+            Some(validatable) => validatable.validate(ValidationFlags::all()),
+            None => ValidationFlags::empty(),
+        }
+    }
 }
 
 impl ValidityStateMethods for ValidityState {
     // https://html.spec.whatwg.org/multipage/#dom-validitystate-valuemissing
     fn ValueMissing(&self) -> bool {
-        false
+        self.invalid_flags().contains(ValidationFlags::VALUE_MISSING)
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-validitystate-typemismatch
     fn TypeMismatch(&self) -> bool {
-        false
+        self.invalid_flags().contains(ValidationFlags::TYPE_MISMATCH)
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-validitystate-patternmismatch
     fn PatternMismatch(&self) -> bool {
-        false
+        self.invalid_flags().contains(ValidationFlags::PATTERN_MISMATCH)
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-validitystate-toolong
     fn TooLong(&self) -> bool {
-        false
+        self.invalid_flags().contains(ValidationFlags::TOO_LONG)
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-validitystate-tooshort
     fn TooShort(&self) -> bool {
-        false
+        self.invalid_flags().contains(ValidationFlags::TOO_SHORT)
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-validitystate-rangeunderflow
     fn RangeUnderflow(&self) -> bool {
-        false
+        self.invalid_flags().contains(ValidationFlags::RANGE_UNDERFLOW)
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-validitystate-rangeoverflow
     fn RangeOverflow(&self) -> bool {
-        false
+        self.invalid_flags().contains(ValidationFlags::RANGE_OVERFLOW)
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-validitystate-stepmismatch
     fn StepMismatch(&self) -> bool {
-        false
+        self.invalid_flags().contains(ValidationFlags::STEP_MISMATCH)
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-validitystate-badinput
     fn BadInput(&self) -> bool {
-        false
+        self.invalid_flags().contains(ValidationFlags::BAD_INPUT)
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-validitystate-customerror
     fn CustomError(&self) -> bool {
-        false
+        self.invalid_flags().contains(ValidationFlags::CUSTOM_ERROR)
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-validitystate-valid
     fn Valid(&self) -> bool {
-        false
+        self.invalid_flags().is_empty()
     }
 }
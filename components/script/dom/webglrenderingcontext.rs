@@ -520,7 +520,7 @@ impl WebGLRenderingContext {
 
                 // For now Servo's images are all stored as BGRA8 internally.
                 let mut data = match img.format {
-                    PixelFormat::BGRA8 => img.bytes.to_vec(),
+                    PixelFormat::BGRA8 => img.first_frame().to_vec(),
                     _ => unimplemented!(),
                 };
 
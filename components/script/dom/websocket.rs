@@ -191,6 +191,7 @@ impl WebSocket {
             url: url_record,
             origin: global.origin().immutable().clone(),
             mode: RequestMode::WebSocket { protocols },
+            csp_list: global.get_csp_list(),
             ..RequestInit::default()
         };
         let channels = FetchChannels::WebSocket {
@@ -560,7 +561,8 @@ impl TaskOnce for MessageReceivedTask {
                 ws.upcast(),
                 &global,
                 message.handle(),
-                Some(&ws.origin().ascii_serialization())
+                Some(&ws.origin().ascii_serialization()),
+                vec![]
             );
         }
     }
@@ -30,6 +30,7 @@ use dom::bindings::trace::RootedTraceableBox;
 use dom::bindings::utils::{GlobalStaticData, WindowProxyHandler};
 use dom::bindings::weakref::DOMTracker;
 use dom::bluetooth::BluetoothExtraPermissionData;
+use dom::cachestorage::CacheStorage;
 use dom::crypto::Crypto;
 use dom::cssstyledeclaration::{CSSModificationAccess, CSSStyleDeclaration, CSSStyleOwner};
 use dom::customelementregistry::CustomElementRegistry;
@@ -40,10 +41,12 @@ use dom::eventtarget::EventTarget;
 use dom::globalscope::GlobalScope;
 use dom::hashchangeevent::HashChangeEvent;
 use dom::history::History;
+use dom::idbfactory::IDBFactory;
 use dom::location::Location;
 use dom::mediaquerylist::{MediaQueryList, MediaQueryListMatchState};
 use dom::mediaquerylistevent::MediaQueryListEvent;
 use dom::messageevent::MessageEvent;
+use dom::messageport::MessagePort;
 use dom::navigator::Navigator;
 use dom::node::{Node, NodeDamage, document_from_node, from_untrusted_node_address};
 use dom::performance::Performance;
@@ -58,7 +61,7 @@ use dom_struct::dom_struct;
 use embedder_traits::EmbedderMsg;
 use euclid::{Point2D, Vector2D, Rect, Size2D, TypedPoint2D, TypedScale, TypedSize2D};
 use fetch;
-use ipc_channel::ipc::IpcSender;
+use ipc_channel::ipc::{self, IpcSender};
 use ipc_channel::router::ROUTER;
 use js::jsapi::JSAutoCompartment;
 use js::jsapi::JSContext;
@@ -83,17 +86,18 @@ use profile_traits::time::ProfilerChan as TimeProfilerChan;
 use script_layout_interface::{TrustedNodeAddress, PendingImageState};
 use script_layout_interface::message::{Msg, Reflow, QueryMsg, ReflowGoal, ScriptReflow};
 use script_layout_interface::reporter::CSSErrorReporter;
-use script_layout_interface::rpc::{ContentBoxResponse, ContentBoxesResponse, LayoutRPC};
+use script_layout_interface::rpc::{ContentBoxResponse, ContentBoxesResponse, CssAnimationResponse, LayoutRPC};
 use script_layout_interface::rpc::{NodeScrollIdResponse, ResolvedStyleResponse, TextIndexResponse};
 use script_runtime::{CommonScriptMsg, ScriptChan, ScriptPort, ScriptThreadEventCategory, Runtime};
 use script_thread::{ImageCacheMsg, MainThreadScriptChan, MainThreadScriptMsg};
 use script_thread::{ScriptThread, SendableMainThreadScriptChan};
 use script_traits::{ConstellationControlMsg, DocumentState, LoadData};
 use script_traits::{ScriptToConstellationChan, ScriptMsg, ScrollState, TimerEvent, TimerEventId};
-use script_traits::{TimerSchedulerMsg, UntrustedNodeAddress, WindowSizeData, WindowSizeType};
+use script_traits::{TimerSchedulerMsg, TransferredPort, UntrustedNodeAddress, WindowSizeData, WindowSizeType};
 use script_traits::webdriver_msg::{WebDriverJSError, WebDriverJSResult};
 use selectors::attr::CaseSensitivity;
 use servo_arc;
+use servo_atoms::Atom;
 use servo_channel::{channel, Sender};
 use servo_config::opts;
 use servo_geometry::{f32_rect_to_au_rect, MaxRect};
@@ -120,11 +124,13 @@ use style::stylesheets::CssRuleType;
 use style_traits::{CSSPixel, DevicePixel, ParsingMode};
 use task::TaskCanceller;
 use task_source::TaskSourceName;
+use task_source::database_access::DatabaseAccessTaskSource;
 use task_source::dom_manipulation::DOMManipulationTaskSource;
 use task_source::file_reading::FileReadingTaskSource;
 use task_source::history_traversal::HistoryTraversalTaskSource;
 use task_source::networking::NetworkingTaskSource;
 use task_source::performance_timeline::PerformanceTimelineTaskSource;
+use task_source::port_message::PortMessageQueueTaskSource;
 use task_source::remote_event::RemoteEventTaskSource;
 use task_source::user_interaction::UserInteractionTaskSource;
 use task_source::websocket::WebsocketTaskSource;
@@ -165,6 +171,7 @@ pub enum ReflowReason {
     IFrameLoadEvent,
     MissingExplicitReflow,
     ElementStateChanged,
+    Printing,
 }
 
 #[dom_struct]
@@ -188,6 +195,10 @@ pub struct Window {
     remote_event_task_source: RemoteEventTaskSource,
     #[ignore_malloc_size_of = "task sources are hard"]
     websocket_task_source: WebsocketTaskSource,
+    #[ignore_malloc_size_of = "task sources are hard"]
+    database_access_task_source: DatabaseAccessTaskSource,
+    #[ignore_malloc_size_of = "task sources are hard"]
+    port_message_queue: PortMessageQueueTaskSource,
     navigator: MutNullableDom<Navigator>,
     #[ignore_malloc_size_of = "Arc"]
     image_cache: Arc<ImageCache>,
@@ -246,6 +257,9 @@ pub struct Window {
     /// to prevent creating display list items for content that is far away from the viewport.
     page_clip_rect: Cell<Rect<Au>>,
 
+    /// Whether the document is laid out to be printed.
+    printing: Cell<bool>,
+
     /// Flag to suppress reflows. The first reflow will come either with
     /// RefreshTick or with FirstLoad. Until those first reflows, we want to
     /// suppress others like MissingExplicitReflow.
@@ -382,6 +396,14 @@ impl Window {
         self.websocket_task_source.clone()
     }
 
+    pub fn database_access_task_source(&self) -> DatabaseAccessTaskSource {
+        self.database_access_task_source.clone()
+    }
+
+    pub fn port_message_queue(&self) -> PortMessageQueueTaskSource {
+        self.port_message_queue.clone()
+    }
+
     pub fn main_thread_script_chan(&self) -> &Sender<MainThreadScriptMsg> {
         &self.script_chan.0
     }
@@ -569,6 +591,22 @@ impl WindowMethods for Window {
         receiver.recv().unwrap();
     }
 
+    // https://html.spec.whatwg.org/multipage/#dom-confirm
+    fn Confirm(&self, s: DOMString) -> bool {
+        let (sender, receiver) = ProfiledIpc::channel(self.global().time_profiler_chan().clone()).unwrap();
+        let msg = EmbedderMsg::Confirm(s.to_string(), sender);
+        self.send_to_embedder(msg);
+        receiver.recv().unwrap_or(false)
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-prompt
+    fn Prompt(&self, message: DOMString, default: DOMString) -> Option<DOMString> {
+        let (sender, receiver) = ProfiledIpc::channel(self.global().time_profiler_chan().clone()).unwrap();
+        let msg = EmbedderMsg::Prompt(message.to_string(), default.to_string(), sender);
+        self.send_to_embedder(msg);
+        receiver.recv().ok().and_then(|input| input).map(DOMString::from)
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-window-stop
     fn Stop(&self) {
         // TODO: Cancel ongoing navigation.
@@ -674,6 +712,16 @@ impl WindowMethods for Window {
         self.upcast::<GlobalScope>().crypto()
     }
 
+    // https://w3c.github.io/IndexedDB/#dom-windoworworkerglobalscope-indexeddb
+    fn IndexedDB(&self) -> DomRoot<IDBFactory> {
+        self.upcast::<GlobalScope>().indexed_db()
+    }
+
+    // https://w3c.github.io/ServiceWorker/#global-caches-attribute
+    fn Caches(&self) -> DomRoot<CacheStorage> {
+        self.upcast::<GlobalScope>().caches()
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-frameelement
     fn GetFrameElement(&self) -> Option<DomRoot<Element>> {
         // Steps 1-3.
@@ -842,7 +890,8 @@ impl WindowMethods for Window {
     unsafe fn PostMessage(&self,
                    cx: *mut JSContext,
                    message: HandleValue,
-                   origin: DOMString)
+                   origin: DOMString,
+                   transfer: Option<Vec<DomRoot<MessagePort>>>)
                    -> ErrorResult {
         // Step 3-5.
         let origin = match &origin[..] {
@@ -859,11 +908,10 @@ impl WindowMethods for Window {
         };
 
         // Step 1-2, 6-8.
-        // TODO(#12717): Should implement the `transfer` argument.
-        let data = StructuredCloneData::write(cx, message)?;
+        let (data, ports) = StructuredCloneData::write_with_transfer(cx, message, transfer.unwrap_or(vec![]))?;
 
         // Step 9.
-        self.post_message(origin, data);
+        self.post_message(origin, data, ports);
         Ok(())
     }
 
@@ -921,6 +969,12 @@ impl WindowMethods for Window {
                 Some(PseudoElement::Before),
             Some(ref pseudo) if pseudo == ":after" || pseudo == "::after" =>
                 Some(PseudoElement::After),
+            Some(ref pseudo) if pseudo == ":first-letter" || pseudo == "::first-letter" =>
+                Some(PseudoElement::FirstLetter),
+            Some(ref pseudo) if pseudo == ":first-line" || pseudo == "::first-line" =>
+                Some(PseudoElement::FirstLine),
+            Some(ref pseudo) if pseudo == "::marker" =>
+                Some(PseudoElement::Marker),
             _ => None
         };
 
@@ -1361,11 +1415,17 @@ impl Window {
         let needs_display = reflow_goal.needs_display();
         let reflow = ScriptReflow {
             reflow_info: Reflow {
-                page_clip_rect: self.page_clip_rect.get(),
+                // A printed document is shown whole, page after page.
+                page_clip_rect: if self.printing.get() {
+                    MaxRect::max_rect()
+                } else {
+                    self.page_clip_rect.get()
+                },
             },
             document: self.Document().upcast::<Node>().to_trusted_node_address(),
             stylesheets_changed,
             window_size,
+            printing: self.printing.get(),
             reflow_goal,
             script_join_chan: join_chan,
             dom_count: self.Document().dom_count(),
@@ -1603,6 +1663,24 @@ impl Window {
         self.layout_rpc.style().0
     }
 
+    /// Returns the CSS animations running on a node, or on the whole document,
+    /// along with the nodes they run on.
+    #[allow(unsafe_code)]
+    pub fn css_animations_query(
+        &self,
+        node: Option<TrustedNodeAddress>,
+    ) -> Vec<(DomRoot<Node>, CssAnimationResponse)> {
+        if !self.layout_reflow(QueryMsg::CssAnimationsQuery(node)) {
+            return vec![];
+        }
+        let js_runtime = self.js_runtime.borrow();
+        let js_runtime = js_runtime.as_ref().unwrap();
+        self.layout_rpc.css_animations().into_iter().map(|response| {
+            let node = unsafe { from_untrusted_node_address(js_runtime.rt(), response.node_address) };
+            (node, response)
+        }).collect()
+    }
+
     pub fn text_index_query(
         &self,
         node: TrustedNodeAddress,
@@ -1716,6 +1794,10 @@ impl Window {
         self.window_size.get()
     }
 
+    pub fn set_printing(&self, printing: bool) {
+        self.printing.set(printing);
+    }
+
     pub fn get_url(&self) -> ServoUrl {
         self.Document().url()
     }
@@ -1724,6 +1806,21 @@ impl Window {
         &self.layout_chan
     }
 
+    /// Whether layout is still loading some web fonts for this window.
+    pub fn has_pending_web_fonts(&self) -> bool {
+        let (sender, receiver) = ipc::channel().unwrap();
+        self.layout_chan.send(Msg::GetWebFontLoadState(sender)).unwrap();
+        receiver.recv().unwrap_or(false)
+    }
+
+    /// Whether there are system fonts or fonts of `@font-face` rules in the
+    /// given family.
+    pub fn has_font_family(&self, family: &Atom) -> bool {
+        let (sender, receiver) = ipc::channel().unwrap();
+        self.layout_chan.send(Msg::HasFontFamily(family.clone(), sender)).unwrap();
+        receiver.recv().unwrap_or(false)
+    }
+
     pub fn windowproxy_handler(&self) -> WindowProxyHandler {
         WindowProxyHandler(self.dom_static.windowproxy_handler.0)
     }
@@ -1913,6 +2010,8 @@ impl Window {
         performance_timeline_task_source: PerformanceTimelineTaskSource,
         remote_event_task_source: RemoteEventTaskSource,
         websocket_task_source: WebsocketTaskSource,
+        database_access_task_source: DatabaseAccessTaskSource,
+        port_message_queue: PortMessageQueueTaskSource,
         image_cache_chan: Sender<ImageCacheMsg>,
         image_cache: Arc<ImageCache>,
         resource_threads: ResourceThreads,
@@ -1967,6 +2066,8 @@ impl Window {
             performance_timeline_task_source,
             remote_event_task_source,
             websocket_task_source,
+            database_access_task_source,
+            port_message_queue,
             image_cache_chan,
             image_cache,
             navigator: Default::default(),
@@ -1993,6 +2094,7 @@ impl Window {
             layout_rpc,
             window_size: Cell::new(window_size),
             current_viewport: Cell::new(Rect::zero()),
+            printing: Cell::new(false),
             suppress_reflow: Cell::new(true),
             pending_reflow_count: Default::default(),
             current_state: Cell::new(WindowState::Alive),
@@ -2060,6 +2162,7 @@ fn debug_reflow_events(id: PipelineId, reflow_goal: &ReflowGoal, reason: &Reflow
             &QueryMsg::StyleQuery(_n) => "\tStyleQuery",
             &QueryMsg::TextIndexQuery(..) => "\tTextIndexQuery",
             &QueryMsg::ElementInnerTextQuery(_) => "\tElementInnerTextQuery",
+            &QueryMsg::CssAnimationsQuery(_) => "\tCssAnimationsQuery",
         },
     });
 
@@ -2084,6 +2187,7 @@ fn debug_reflow_events(id: PipelineId, reflow_goal: &ReflowGoal, reason: &Reflow
         ReflowReason::IFrameLoadEvent => "\tIFrameLoadEvent",
         ReflowReason::MissingExplicitReflow => "\tMissingExplicitReflow",
         ReflowReason::ElementStateChanged => "\tElementStateChanged",
+        ReflowReason::Printing => "\tPrinting",
     });
 
     println!("{}", debug_msg);
@@ -2095,6 +2199,7 @@ impl Window {
         &self,
         target_origin: Option<ImmutableOrigin>,
         serialize_with_transfer_result: StructuredCloneData,
+        transferred_ports: Vec<TransferredPort>,
     ) {
         let this = Trusted::new(self);
         let task = task!(post_serialised_message: move || {
@@ -2112,21 +2217,20 @@ impl Window {
             let obj = this.reflector().get_jsobject();
             let _ac = JSAutoCompartment::new(cx, obj.get());
             rooted!(in(cx) let mut message_clone = UndefinedValue());
-            serialize_with_transfer_result.read(
+            let new_ports = serialize_with_transfer_result.read_with_transfer(
                 this.upcast(),
+                transferred_ports,
                 message_clone.handle_mut(),
             );
 
-            // Step 7.6.
-            // TODO: MessagePort array.
-
-            // Step 7.7.
+            // Steps 7.6.-7.7.
             // TODO(#12719): Set the other attributes.
             MessageEvent::dispatch_jsval(
                 this.upcast(),
                 this.upcast(),
                 message_clone.handle(),
-                None
+                None,
+                new_ports,
             );
         });
         // FIXME(nox): Why are errors silenced here?
@@ -6,7 +6,7 @@ use devtools_traits::{DevtoolsPageInfo, ScriptToDevtoolsControlMsg};
 use dom::abstractworker::SimpleWorkerErrorHandler;
 use dom::abstractworker::WorkerScriptMsg;
 use dom::bindings::codegen::Bindings::WorkerBinding;
-use dom::bindings::codegen::Bindings::WorkerBinding::WorkerMethods;
+use dom::bindings::codegen::Bindings::WorkerBinding::{WorkerMethods, WorkerOptions};
 use dom::bindings::error::{Error, ErrorResult, Fallible};
 use dom::bindings::inheritance::Castable;
 use dom::bindings::refcounted::Trusted;
@@ -18,13 +18,14 @@ use dom::dedicatedworkerglobalscope::{DedicatedWorkerGlobalScope, DedicatedWorke
 use dom::eventtarget::EventTarget;
 use dom::globalscope::GlobalScope;
 use dom::messageevent::MessageEvent;
+use dom::messageport::MessagePort;
 use dom::workerglobalscope::prepare_workerscope_init;
 use dom_struct::dom_struct;
 use ipc_channel::ipc;
 use js::jsapi::{JSAutoCompartment, JSContext, JS_RequestInterruptCallback};
 use js::jsval::UndefinedValue;
 use js::rust::HandleValue;
-use script_traits::WorkerScriptLoadOrigin;
+use script_traits::{TransferredPort, WorkerScriptLoadOrigin};
 use servo_channel::{channel, Sender};
 use std::cell::Cell;
 use std::sync::Arc;
@@ -67,7 +68,10 @@ impl Worker {
 
     // https://html.spec.whatwg.org/multipage/#dom-worker
     #[allow(unsafe_code)]
-    pub fn Constructor(global: &GlobalScope, script_url: DOMString) -> Fallible<DomRoot<Worker>> {
+    pub fn Constructor(global: &GlobalScope,
+                       script_url: DOMString,
+                       worker_options: &WorkerOptions)
+                       -> Fallible<DomRoot<Worker>> {
         // Step 2-4.
         let worker_url = match global.api_base_url().join(&script_url) {
             Ok(url) => url,
@@ -104,7 +108,8 @@ impl Worker {
 
         DedicatedWorkerGlobalScope::run_worker_scope(
             init, worker_url, devtools_receiver, worker_ref,
-            global.script_chan(), sender, receiver, worker_load_origin, closing);
+            global.script_chan(), sender, receiver, worker_load_origin, closing,
+            worker_options.type_, worker_options.credentials.into());
 
         Ok(worker)
     }
@@ -118,7 +123,8 @@ impl Worker {
     }
 
     pub fn handle_message(address: TrustedWorkerAddress,
-                          data: StructuredCloneData) {
+                          data: StructuredCloneData,
+                          ports: Vec<TransferredPort>) {
         let worker = address.root();
 
         if worker.is_terminated() {
@@ -129,8 +135,8 @@ impl Worker {
         let target = worker.upcast();
         let _ac = JSAutoCompartment::new(global.get_cx(), target.reflector().get_jsobject().get());
         rooted!(in(global.get_cx()) let mut message = UndefinedValue());
-        data.read(&global, message.handle_mut());
-        MessageEvent::dispatch_jsval(target, &global, message.handle(), None);
+        let ports = data.read_with_transfer(&global, ports, message.handle_mut());
+        MessageEvent::dispatch_jsval(target, &global, message.handle(), None, ports);
     }
 
     pub fn dispatch_simple_error(address: TrustedWorkerAddress) {
@@ -142,13 +148,18 @@ impl Worker {
 impl WorkerMethods for Worker {
     #[allow(unsafe_code)]
     // https://html.spec.whatwg.org/multipage/#dom-worker-postmessage
-    unsafe fn PostMessage(&self, cx: *mut JSContext, message: HandleValue) -> ErrorResult {
-        let data = StructuredCloneData::write(cx, message)?;
+    unsafe fn PostMessage(&self,
+                          cx: *mut JSContext,
+                          message: HandleValue,
+                          transfer: Option<Vec<DomRoot<MessagePort>>>)
+                          -> ErrorResult {
+        let (data, ports) = StructuredCloneData::write_with_transfer(cx, message, transfer.unwrap_or(vec![]))?;
         let address = Trusted::new(self);
 
         // NOTE: step 9 of https://html.spec.whatwg.org/multipage/#dom-messageport-postmessage
         // indicates that a nonexistent communication channel should result in a silent error.
-        let _ = self.sender.send(DedicatedWorkerScriptMsg::CommonWorker(address, WorkerScriptMsg::DOMMessage(data)));
+        let msg = WorkerScriptMsg::DOMMessage(data, ports);
+        let _ = self.sender.send(DedicatedWorkerScriptMsg::CommonWorker(address, msg));
         Ok(())
     }
 
@@ -3,6 +3,7 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use devtools_traits::{DevtoolScriptControlMsg, WorkerId};
+use dom::bindings::cell::DomRefCell;
 use dom::bindings::codegen::Bindings::FunctionBinding::Function;
 use dom::bindings::codegen::Bindings::RequestBinding::RequestInit;
 use dom::bindings::codegen::Bindings::WorkerGlobalScopeBinding::WorkerGlobalScopeMethods;
@@ -14,9 +15,11 @@ use dom::bindings::root::{DomRoot, MutNullableDom};
 use dom::bindings::settings_stack::AutoEntryScript;
 use dom::bindings::str::{DOMString, USVString};
 use dom::bindings::trace::RootedTraceableBox;
+use dom::cachestorage::CacheStorage;
 use dom::crypto::Crypto;
 use dom::dedicatedworkerglobalscope::DedicatedWorkerGlobalScope;
 use dom::globalscope::GlobalScope;
+use dom::idbfactory::IDBFactory;
 use dom::performance::Performance;
 use dom::promise::Promise;
 use dom::serviceworkerglobalscope::ServiceWorkerGlobalScope;
@@ -31,7 +34,8 @@ use js::jsval::UndefinedValue;
 use js::panic::maybe_resume_unwind;
 use js::rust::HandleValue;
 use msg::constellation_msg::PipelineId;
-use net_traits::{IpcSend, load_whole_resource};
+use net_traits::{IpcSend, Metadata, load_whole_resource};
+use net_traits::csp::CspList;
 use net_traits::request::{CredentialsMode, Destination, RequestInit as NetRequestInit};
 use script_runtime::{CommonScriptMsg, ScriptChan, ScriptPort, get_reports, Runtime};
 use script_traits::{TimerEvent, TimerEventId};
@@ -43,9 +47,11 @@ use std::rc::Rc;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use task::TaskCanceller;
+use task_source::database_access::DatabaseAccessTaskSource;
 use task_source::file_reading::FileReadingTaskSource;
 use task_source::networking::NetworkingTaskSource;
 use task_source::performance_timeline::PerformanceTimelineTaskSource;
+use task_source::port_message::PortMessageQueueTaskSource;
 use task_source::remote_event::RemoteEventTaskSource;
 use task_source::websocket::WebsocketTaskSource;
 use time::precise_time_ns;
@@ -95,6 +101,9 @@ pub struct WorkerGlobalScope {
 
     navigation_start_precise: u64,
     performance: MutNullableDom<Performance>,
+
+    /// <https://w3c.github.io/webappsec-csp/#global-object-csp-list>
+    csp_list: DomRefCell<Option<CspList>>,
 }
 
 impl WorkerGlobalScope {
@@ -129,6 +138,7 @@ impl WorkerGlobalScope {
             from_devtools_receiver,
             navigation_start_precise: precise_time_ns(),
             performance: Default::default(),
+            csp_list: DomRefCell::new(None),
         }
     }
 
@@ -169,6 +179,32 @@ impl WorkerGlobalScope {
     pub fn pipeline_id(&self) -> PipelineId {
         self.globalscope.pipeline_id()
     }
+
+    pub fn get_csp_list(&self) -> Option<CspList> {
+        self.csp_list.borrow().clone()
+    }
+
+    /// Gives this worker the policies of the document or worker that created it, which its
+    /// script is fetched with.
+    pub fn set_csp_list(&self, csp_list: Option<CspList>) {
+        *self.csp_list.borrow_mut() = csp_list;
+    }
+
+    /// Replaces the policies of this worker with those delivered with its script, unless the
+    /// script has a local URL, in which case the worker keeps the policies of its creator.
+    ///
+    /// <https://w3c.github.io/webappsec-csp/#initialize-global-object-csp>
+    pub fn initialize_csp_list(&self, metadata: &Metadata) {
+        match metadata.final_url.scheme() {
+            "about" | "blob" | "data" => return,
+            _ => {},
+        }
+        let csp_list = match metadata.headers {
+            Some(ref headers) => CspList::from_headers(headers),
+            None => CspList::default(),
+        };
+        *self.csp_list.borrow_mut() = if csp_list.is_empty() { None } else { Some(csp_list) };
+    }
 }
 
 impl WorkerGlobalScopeMethods for WorkerGlobalScope {
@@ -210,6 +246,7 @@ impl WorkerGlobalScopeMethods for WorkerGlobalScope {
                 pipeline_id: Some(self.upcast::<GlobalScope>().pipeline_id()),
                 referrer_url: None,
                 referrer_policy: None,
+                csp_list: self.get_csp_list(),
                 .. NetRequestInit::default()
             };
             let (url, source) = match load_whole_resource(request,
@@ -247,6 +284,16 @@ impl WorkerGlobalScopeMethods for WorkerGlobalScope {
         self.upcast::<GlobalScope>().crypto()
     }
 
+    // https://w3c.github.io/IndexedDB/#dom-windoworworkerglobalscope-indexeddb
+    fn IndexedDB(&self) -> DomRoot<IDBFactory> {
+        self.upcast::<GlobalScope>().indexed_db()
+    }
+
+    // https://w3c.github.io/ServiceWorker/#global-caches-attribute
+    fn Caches(&self) -> DomRoot<CacheStorage> {
+        self.upcast::<GlobalScope>().caches()
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-windowbase64-btoa
     fn Btoa(&self, btoa: DOMString) -> Fallible<DOMString> {
         base64_btoa(btoa)
@@ -391,6 +438,14 @@ impl WorkerGlobalScope {
         WebsocketTaskSource(self.script_chan(), self.pipeline_id())
     }
 
+    pub fn database_access_task_source(&self) -> DatabaseAccessTaskSource {
+        DatabaseAccessTaskSource(self.script_chan(), self.pipeline_id())
+    }
+
+    pub fn port_message_queue(&self) -> PortMessageQueueTaskSource {
+        PortMessageQueueTaskSource(self.script_chan(), self.pipeline_id())
+    }
+
     pub fn new_script_pair(&self) -> (Box<ScriptChan + Send>, Box<ScriptPort + Send>) {
         let dedicated = self.downcast::<DedicatedWorkerGlobalScope>();
         if let Some(dedicated) = dedicated {
@@ -0,0 +1,434 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::cell::DomRefCell;
+use dom::bindings::codegen::Bindings::QueuingStrategyBinding::{QueuingStrategy, QueuingStrategySize};
+use dom::bindings::codegen::Bindings::WritableStreamBinding;
+use dom::bindings::codegen::Bindings::WritableStreamBinding::WritableStreamMethods;
+use dom::bindings::conversions::get_property_jsval;
+use dom::bindings::error::{Error, Fallible};
+use dom::bindings::reflector::{DomObject, Reflector, reflect_dom_object};
+use dom::bindings::root::{Dom, DomRoot, MutNullableDom};
+use dom::globalscope::GlobalScope;
+use dom::promise::Promise;
+use dom::promisenativehandler::Callback;
+use dom::streams::{rejected_promise, resolved_promise, upon_settlement, validate_high_water_mark};
+use dom::writablestreamdefaultcontroller::{UnderlyingSink, WritableStreamDefaultController};
+use dom::writablestreamdefaultwriter::WritableStreamDefaultWriter;
+use dom_struct::dom_struct;
+use js::jsapi::{Heap, JSContext, JSObject};
+use js::jsval::{JSVal, UndefinedValue};
+use js::rust::{HandleValue, MutableHandleValue};
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::mem;
+use std::ptr;
+use std::rc::Rc;
+
+/// <https://streams.spec.whatwg.org/#writablestream-state>
+#[derive(Clone, Copy, Debug, JSTraceable, MallocSizeOf, PartialEq)]
+pub enum WritableStreamState {
+    Writable,
+    Closed,
+    Erroring,
+    Errored,
+}
+
+/// <https://streams.spec.whatwg.org/#pending-abort-request>
+#[derive(JSTraceable)]
+#[must_root]
+struct PendingAbortRequest {
+    promise: Rc<Promise>,
+    reason: Box<Heap<JSVal>>,
+    was_already_erroring: bool,
+}
+
+/// <https://streams.spec.whatwg.org/#ws-class>
+#[dom_struct]
+pub struct WritableStream {
+    reflector_: Reflector,
+    state: Cell<WritableStreamState>,
+    #[ignore_malloc_size_of = "Defined in rust-mozjs"]
+    stored_error: Heap<JSVal>,
+    controller: MutNullableDom<WritableStreamDefaultController>,
+    /// The writer that locks this stream, if any.
+    writer: MutNullableDom<WritableStreamDefaultWriter>,
+    /// <https://streams.spec.whatwg.org/#writablestream-backpressure>
+    backpressure: Cell<bool>,
+    #[ignore_malloc_size_of = "Rc"]
+    write_requests: DomRefCell<VecDeque<Rc<Promise>>>,
+    #[ignore_malloc_size_of = "Rc"]
+    in_flight_write_request: DomRefCell<Option<Rc<Promise>>>,
+    #[ignore_malloc_size_of = "Rc"]
+    close_request: DomRefCell<Option<Rc<Promise>>>,
+    #[ignore_malloc_size_of = "Rc"]
+    in_flight_close_request: DomRefCell<Option<Rc<Promise>>>,
+    #[ignore_malloc_size_of = "Defined in rust-mozjs"]
+    pending_abort_request: DomRefCell<Option<PendingAbortRequest>>,
+}
+
+impl WritableStream {
+    fn new_inherited() -> WritableStream {
+        WritableStream {
+            reflector_: Reflector::new(),
+            state: Cell::new(WritableStreamState::Writable),
+            stored_error: Heap::default(),
+            controller: Default::default(),
+            writer: Default::default(),
+            backpressure: Cell::new(false),
+            write_requests: Default::default(),
+            in_flight_write_request: Default::default(),
+            close_request: Default::default(),
+            in_flight_close_request: Default::default(),
+            pending_abort_request: Default::default(),
+        }
+    }
+
+    fn new(global: &GlobalScope) -> DomRoot<WritableStream> {
+        reflect_dom_object(Box::new(WritableStream::new_inherited()),
+                           global,
+                           WritableStreamBinding::Wrap)
+    }
+
+    /// <https://streams.spec.whatwg.org/#ws-constructor>
+    #[allow(unsafe_code)]
+    pub unsafe fn Constructor(cx: *mut JSContext,
+                              global: &GlobalScope,
+                              underlying_sink: Option<*mut JSObject>,
+                              strategy: &QueuingStrategy)
+                              -> Fallible<DomRoot<WritableStream>> {
+        rooted!(in(cx) let sink = underlying_sink.unwrap_or(ptr::null_mut()));
+        if !sink.is_null() {
+            rooted!(in(cx) let mut sink_type = UndefinedValue());
+            get_property_jsval(cx, sink.handle(), "type", sink_type.handle_mut())?;
+            if !sink_type.is_undefined() {
+                return Err(Error::Range("Unknown underlying sink type".to_owned()));
+            }
+        }
+        let high_water_mark = validate_high_water_mark(strategy.highWaterMark, 1.)?;
+        let stream = WritableStream::new(global);
+        WritableStreamDefaultController::set_up(&stream,
+                                                UnderlyingSink::Js(Heap::boxed(sink.get())),
+                                                high_water_mark,
+                                                strategy.size.clone())?;
+        Ok(stream)
+    }
+
+    /// Creates a stream that writes to `sink`, as for the writable side of a transform stream.
+    pub fn new_with_sink(global: &GlobalScope,
+                         sink: UnderlyingSink,
+                         high_water_mark: f64,
+                         size: Option<Rc<QueuingStrategySize>>)
+                         -> Fallible<DomRoot<WritableStream>> {
+        let stream = WritableStream::new(global);
+        WritableStreamDefaultController::set_up(&stream, sink, high_water_mark, size)?;
+        Ok(stream)
+    }
+
+    pub fn state(&self) -> WritableStreamState {
+        self.state.get()
+    }
+
+    pub fn stored_error(&self, mut rval: MutableHandleValue) {
+        rval.set(self.stored_error.get());
+    }
+
+    pub fn backpressure(&self) -> bool {
+        self.backpressure.get()
+    }
+
+    pub fn controller(&self) -> DomRoot<WritableStreamDefaultController> {
+        self.controller.get().expect("A writable stream without a controller")
+    }
+
+    pub fn set_controller(&self, controller: &WritableStreamDefaultController) {
+        self.controller.set(Some(controller));
+    }
+
+    pub fn writer(&self) -> Option<DomRoot<WritableStreamDefaultWriter>> {
+        self.writer.get()
+    }
+
+    pub fn set_writer(&self, writer: Option<&WritableStreamDefaultWriter>) {
+        self.writer.set(writer);
+    }
+
+    /// <https://streams.spec.whatwg.org/#is-writable-stream-locked>
+    pub fn is_locked(&self) -> bool {
+        self.writer.get().is_some()
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-close-queued-or-in-flight>
+    pub fn close_queued_or_in_flight(&self) -> bool {
+        self.close_request.borrow().is_some() || self.in_flight_close_request.borrow().is_some()
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-has-operation-marked-in-flight>
+    pub fn has_operation_marked_in_flight(&self) -> bool {
+        self.in_flight_write_request.borrow().is_some() || self.in_flight_close_request.borrow().is_some()
+    }
+
+    pub fn has_in_flight_write_request(&self) -> bool {
+        self.in_flight_write_request.borrow().is_some()
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-abort>
+    #[allow(unrooted_must_root)]
+    pub fn abort(&self, cx: *mut JSContext, reason: HandleValue) -> Rc<Promise> {
+        let global = self.global();
+        match self.state.get() {
+            WritableStreamState::Closed | WritableStreamState::Errored => return resolved_promise(&global),
+            _ => {},
+        }
+        if let Some(ref request) = *self.pending_abort_request.borrow() {
+            return request.promise.clone();
+        }
+        let was_already_erroring = self.state.get() == WritableStreamState::Erroring;
+        rooted!(in(cx) let mut reason_value = reason.get());
+        if was_already_erroring {
+            reason_value.set(UndefinedValue());
+        }
+        let promise = Promise::new(&global);
+        *self.pending_abort_request.borrow_mut() = Some(PendingAbortRequest {
+            promise: promise.clone(),
+            reason: Heap::boxed(reason_value.get()),
+            was_already_erroring: was_already_erroring,
+        });
+        if !was_already_erroring {
+            self.start_erroring(cx, reason_value.handle());
+        }
+        promise
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-close>
+    #[allow(unrooted_must_root)]
+    pub fn close(&self) -> Rc<Promise> {
+        let global = self.global();
+        match self.state.get() {
+            WritableStreamState::Closed | WritableStreamState::Errored => {
+                let promise = Promise::new(&global);
+                promise.reject_error(Error::Type("The stream is closed or errored".to_owned()));
+                return promise;
+            },
+            _ => {},
+        }
+        assert!(!self.close_queued_or_in_flight());
+        let promise = Promise::new(&global);
+        *self.close_request.borrow_mut() = Some(promise.clone());
+        if let Some(writer) = self.writer.get() {
+            if self.backpressure.get() && self.state.get() == WritableStreamState::Writable {
+                writer.resolve_ready_promise();
+            }
+        }
+        self.controller().close();
+        promise
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-add-write-request>
+    #[allow(unrooted_must_root)]
+    pub fn add_write_request(&self) -> Rc<Promise> {
+        let promise = Promise::new(&self.global());
+        self.write_requests.borrow_mut().push_back(promise.clone());
+        promise
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-deal-with-rejection>
+    pub fn deal_with_rejection(&self, cx: *mut JSContext, error: HandleValue) {
+        if self.state.get() == WritableStreamState::Writable {
+            return self.start_erroring(cx, error);
+        }
+        self.finish_erroring(cx);
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-start-erroring>
+    pub fn start_erroring(&self, cx: *mut JSContext, reason: HandleValue) {
+        assert_eq!(self.state.get(), WritableStreamState::Writable);
+        self.state.set(WritableStreamState::Erroring);
+        self.stored_error.set(reason.get());
+        if let Some(writer) = self.writer.get() {
+            writer.ensure_ready_promise_rejected(cx, reason);
+        }
+        if !self.has_operation_marked_in_flight() && self.controller().started() {
+            self.finish_erroring(cx);
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-finish-erroring>
+    #[allow(unrooted_must_root, unsafe_code)]
+    pub fn finish_erroring(&self, cx: *mut JSContext) {
+        assert_eq!(self.state.get(), WritableStreamState::Erroring);
+        assert!(!self.has_operation_marked_in_flight());
+        self.state.set(WritableStreamState::Errored);
+        self.controller().error_steps();
+        rooted!(in(cx) let mut stored_error = UndefinedValue());
+        self.stored_error(stored_error.handle_mut());
+        let write_requests = mem::replace(&mut *self.write_requests.borrow_mut(), VecDeque::new());
+        for request in write_requests {
+            unsafe { request.reject(cx, stored_error.handle()) };
+        }
+        let abort_request = match self.pending_abort_request.borrow_mut().take() {
+            Some(request) => request,
+            None => return self.reject_close_and_closed_promise_if_needed(cx),
+        };
+        if abort_request.was_already_erroring {
+            unsafe { abort_request.promise.reject(cx, stored_error.handle()) };
+            return self.reject_close_and_closed_promise_if_needed(cx);
+        }
+        let promise = self.controller().abort_steps(cx, abort_request.reason.handle());
+        upon_settlement(&promise,
+                        AbortReaction::new(self, &abort_request.promise, true),
+                        AbortReaction::new(self, &abort_request.promise, false));
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-finish-in-flight-write>
+    pub fn finish_in_flight_write(&self) {
+        let request = self.in_flight_write_request.borrow_mut().take();
+        request.expect("No write in flight").resolve_native(&());
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-finish-in-flight-write-with-error>
+    #[allow(unsafe_code)]
+    pub fn finish_in_flight_write_with_error(&self, cx: *mut JSContext, error: HandleValue) {
+        let request = self.in_flight_write_request.borrow_mut().take();
+        unsafe { request.expect("No write in flight").reject(cx, error) };
+        self.deal_with_rejection(cx, error);
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-finish-in-flight-close>
+    pub fn finish_in_flight_close(&self) {
+        let request = self.in_flight_close_request.borrow_mut().take();
+        request.expect("No close in flight").resolve_native(&());
+        if self.state.get() == WritableStreamState::Erroring {
+            self.stored_error.set(UndefinedValue());
+            if let Some(request) = self.pending_abort_request.borrow_mut().take() {
+                request.promise.resolve_native(&());
+            }
+        }
+        self.state.set(WritableStreamState::Closed);
+        if let Some(writer) = self.writer.get() {
+            writer.resolve_closed_promise();
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-finish-in-flight-close-with-error>
+    #[allow(unsafe_code)]
+    pub fn finish_in_flight_close_with_error(&self, cx: *mut JSContext, error: HandleValue) {
+        let request = self.in_flight_close_request.borrow_mut().take();
+        unsafe { request.expect("No close in flight").reject(cx, error) };
+        if let Some(request) = self.pending_abort_request.borrow_mut().take() {
+            unsafe { request.promise.reject(cx, error) };
+        }
+        self.deal_with_rejection(cx, error);
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-mark-close-request-in-flight>
+    pub fn mark_close_request_in_flight(&self) {
+        let request = self.close_request.borrow_mut().take();
+        *self.in_flight_close_request.borrow_mut() = request;
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-mark-first-write-request-in-flight>
+    pub fn mark_first_write_request_in_flight(&self) {
+        let request = self.write_requests.borrow_mut().pop_front();
+        *self.in_flight_write_request.borrow_mut() = request;
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-reject-close-and-closed-promise-if-needed>
+    #[allow(unsafe_code)]
+    fn reject_close_and_closed_promise_if_needed(&self, cx: *mut JSContext) {
+        rooted!(in(cx) let mut stored_error = UndefinedValue());
+        self.stored_error(stored_error.handle_mut());
+        if let Some(request) = self.close_request.borrow_mut().take() {
+            unsafe { request.reject(cx, stored_error.handle()) };
+        }
+        if let Some(writer) = self.writer.get() {
+            writer.reject_closed_promise(cx, stored_error.handle());
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-update-backpressure>
+    pub fn update_backpressure(&self, backpressure: bool) {
+        if let Some(writer) = self.writer.get() {
+            if backpressure != self.backpressure.get() {
+                if backpressure {
+                    writer.reset_ready_promise();
+                } else {
+                    writer.resolve_ready_promise();
+                }
+            }
+        }
+        self.backpressure.set(backpressure);
+    }
+}
+
+impl WritableStreamMethods for WritableStream {
+    /// <https://streams.spec.whatwg.org/#ws-locked>
+    fn Locked(&self) -> bool {
+        self.is_locked()
+    }
+
+    /// <https://streams.spec.whatwg.org/#ws-abort>
+    #[allow(unrooted_must_root, unsafe_code)]
+    unsafe fn Abort(&self, cx: *mut JSContext, reason: HandleValue) -> Rc<Promise> {
+        if self.is_locked() {
+            let promise = Promise::new(&self.global());
+            promise.reject_error(Error::Type("The stream is locked".to_owned()));
+            return promise;
+        }
+        self.abort(cx, reason)
+    }
+
+    /// <https://streams.spec.whatwg.org/#ws-close>
+    #[allow(unrooted_must_root)]
+    fn Close(&self) -> Rc<Promise> {
+        if self.is_locked() || self.close_queued_or_in_flight() {
+            let promise = Promise::new(&self.global());
+            promise.reject_error(Error::Type("The stream is locked or already closing".to_owned()));
+            return promise;
+        }
+        self.close()
+    }
+
+    /// <https://streams.spec.whatwg.org/#ws-get-writer>
+    fn GetWriter(&self) -> Fallible<DomRoot<WritableStreamDefaultWriter>> {
+        WritableStreamDefaultWriter::new(&self.global(), self)
+    }
+}
+
+/// Settles the promise of an abort request once the underlying sink has been aborted.
+#[derive(JSTraceable, MallocSizeOf)]
+#[must_root]
+struct AbortReaction {
+    stream: Dom<WritableStream>,
+    #[ignore_malloc_size_of = "Rc"]
+    promise: Rc<Promise>,
+    fulfilled: bool,
+}
+
+impl AbortReaction {
+    #[allow(unrooted_must_root)]
+    fn new(stream: &WritableStream, promise: &Rc<Promise>, fulfilled: bool) -> Box<Callback> {
+        Box::new(AbortReaction { stream: Dom::from_ref(stream), promise: promise.clone(), fulfilled: fulfilled })
+    }
+}
+
+impl Callback for AbortReaction {
+    #[allow(unsafe_code)]
+    fn callback(&self, cx: *mut JSContext, v: HandleValue) {
+        if self.fulfilled {
+            self.promise.resolve_native(&());
+        } else {
+            unsafe { self.promise.reject(cx, v) };
+        }
+        self.stream.reject_close_and_closed_promise_if_needed(cx);
+    }
+}
+
+/// A promise rejected with the stored error of `stream`.
+#[allow(unrooted_must_root)]
+pub fn rejected_with_stored_error(stream: &WritableStream, cx: *mut JSContext) -> Rc<Promise> {
+    rooted!(in(cx) let mut error = UndefinedValue());
+    stream.stored_error(error.handle_mut());
+    rejected_promise(&stream.global(), cx, error.handle())
+}
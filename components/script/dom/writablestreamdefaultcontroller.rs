@@ -0,0 +1,343 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::cell::DomRefCell;
+use dom::bindings::codegen::Bindings::QueuingStrategyBinding::QueuingStrategySize;
+use dom::bindings::codegen::Bindings::WritableStreamDefaultControllerBinding;
+use dom::bindings::codegen::Bindings::WritableStreamDefaultControllerBinding::WritableStreamDefaultControllerMethods;
+use dom::bindings::error::Fallible;
+use dom::bindings::reflector::{DomObject, Reflector, reflect_dom_object};
+use dom::bindings::root::{Dom, DomRoot};
+use dom::globalscope::GlobalScope;
+use dom::promise::Promise;
+use dom::promisenativehandler::Callback;
+use dom::streams::{QueueWithSizes, chunk_size, error_to_jsval, invoke_or_noop, object_value};
+use dom::streams::{promise_invoke_or_noop, resolved_promise, upon_settlement};
+use dom::transformstream::TransformStream;
+use dom::writablestream::{WritableStream, WritableStreamState};
+use dom_struct::dom_struct;
+use js::jsapi::{Heap, JSContext, JSObject};
+use js::jsval::{JSVal, UndefinedValue};
+use js::rust::HandleValue;
+use std::cell::Cell;
+use std::rc::Rc;
+
+/// Where the chunks written to a writable stream go.
+#[derive(JSTraceable)]
+#[must_root]
+pub enum UnderlyingSink {
+    /// An underlying sink object given by script.
+    Js(Box<Heap<*mut JSObject>>),
+    /// The writable side of a transform stream.
+    Transform(Dom<TransformStream>),
+}
+
+impl UnderlyingSink {
+    /// Runs the start algorithm of the sink. Only the `start` method of a sink object can throw.
+    #[allow(unrooted_must_root, unsafe_code)]
+    fn start(&self, global: &GlobalScope, controller: JSVal) -> Fallible<Rc<Promise>> {
+        match *self {
+            UnderlyingSink::Js(ref sink) => unsafe {
+                let cx = global.get_cx();
+                rooted!(in(cx) let sink = sink.get());
+                rooted!(in(cx) let controller = controller);
+                rooted!(in(cx) let mut result = UndefinedValue());
+                invoke_or_noop(cx, sink.handle(), "start", &[controller.get()], result.handle_mut())?;
+                Promise::new_resolved(global, cx, result.handle())
+            },
+            UnderlyingSink::Transform(ref stream) => Ok(stream.start_promise()),
+        }
+    }
+
+    #[allow(unrooted_must_root, unsafe_code)]
+    fn write(&self, global: &GlobalScope, cx: *mut JSContext, chunk: HandleValue, controller: JSVal) -> Rc<Promise> {
+        match *self {
+            UnderlyingSink::Js(ref sink) => unsafe {
+                rooted!(in(cx) let sink = sink.get());
+                rooted!(in(cx) let controller = controller);
+                promise_invoke_or_noop(global, cx, sink.handle(), "write", &[chunk.get(), controller.get()])
+            },
+            UnderlyingSink::Transform(ref stream) => stream.writable_write(cx, chunk),
+        }
+    }
+
+    #[allow(unrooted_must_root, unsafe_code)]
+    fn close(&self, global: &GlobalScope) -> Rc<Promise> {
+        match *self {
+            UnderlyingSink::Js(ref sink) => unsafe {
+                let cx = global.get_cx();
+                rooted!(in(cx) let sink = sink.get());
+                promise_invoke_or_noop(global, cx, sink.handle(), "close", &[])
+            },
+            UnderlyingSink::Transform(ref stream) => stream.writable_close(),
+        }
+    }
+
+    #[allow(unrooted_must_root, unsafe_code)]
+    fn abort(&self, global: &GlobalScope, cx: *mut JSContext, reason: HandleValue) -> Rc<Promise> {
+        match *self {
+            UnderlyingSink::Js(ref sink) => unsafe {
+                rooted!(in(cx) let sink = sink.get());
+                promise_invoke_or_noop(global, cx, sink.handle(), "abort", &[reason.get()])
+            },
+            UnderlyingSink::Transform(ref stream) => stream.writable_abort(cx, reason),
+        }
+    }
+}
+
+/// <https://streams.spec.whatwg.org/#ws-default-controller-class>
+#[dom_struct]
+pub struct WritableStreamDefaultController {
+    reflector_: Reflector,
+    stream: Dom<WritableStream>,
+    /// The underlying sink, until the stream is closed or errored.
+    #[ignore_malloc_size_of = "Rc"]
+    sink: DomRefCell<Option<Rc<UnderlyingSink>>>,
+    #[ignore_malloc_size_of = "Defined in rust-mozjs"]
+    queue: DomRefCell<QueueWithSizes>,
+    /// Whether a close was requested. It is processed once the queued writes are done, like the
+    /// close sentinel of the specification.
+    close_queued: Cell<bool>,
+    started: Cell<bool>,
+    high_water_mark: f64,
+    #[ignore_malloc_size_of = "Rc"]
+    size: DomRefCell<Option<Rc<QueuingStrategySize>>>,
+}
+
+impl WritableStreamDefaultController {
+    #[allow(unrooted_must_root)]
+    fn new(global: &GlobalScope,
+           stream: &WritableStream,
+           sink: UnderlyingSink,
+           high_water_mark: f64,
+           size: Option<Rc<QueuingStrategySize>>)
+           -> DomRoot<WritableStreamDefaultController> {
+        reflect_dom_object(Box::new(WritableStreamDefaultController {
+            reflector_: Reflector::new(),
+            stream: Dom::from_ref(stream),
+            sink: DomRefCell::new(Some(Rc::new(sink))),
+            queue: Default::default(),
+            close_queued: Cell::new(false),
+            started: Cell::new(false),
+            high_water_mark: high_water_mark,
+            size: DomRefCell::new(size),
+        }), global, WritableStreamDefaultControllerBinding::Wrap)
+    }
+
+    /// <https://streams.spec.whatwg.org/#set-up-writable-stream-default-controller>
+    #[allow(unrooted_must_root)]
+    pub fn set_up(stream: &WritableStream,
+                  sink: UnderlyingSink,
+                  high_water_mark: f64,
+                  size: Option<Rc<QueuingStrategySize>>)
+                  -> Fallible<()> {
+        let global = stream.global();
+        let controller = WritableStreamDefaultController::new(&global, stream, sink, high_water_mark, size);
+        stream.set_controller(&controller);
+        stream.update_backpressure(controller.backpressure());
+        let sink = controller.sink().expect("A new controller without a sink");
+        let start_promise = sink.start(&global, object_value(&*controller))?;
+        upon_settlement(&start_promise,
+                        ControllerReaction::new(&controller, ControllerStep::StartFulfilled),
+                        ControllerReaction::new(&controller, ControllerStep::StartRejected));
+        Ok(())
+    }
+
+    #[allow(unrooted_must_root)]
+    fn sink(&self) -> Option<Rc<UnderlyingSink>> {
+        self.sink.borrow().clone()
+    }
+
+    pub fn started(&self) -> bool {
+        self.started.get()
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-controller-clear-algorithms>
+    fn clear_algorithms(&self) {
+        let sink = self.sink.borrow_mut().take();
+        *self.size.borrow_mut() = None;
+        drop(sink);
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-controller-get-desired-size>
+    pub fn desired_size(&self) -> f64 {
+        self.high_water_mark - self.queue.borrow().total_size()
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-controller-get-backpressure>
+    fn backpressure(&self) -> bool {
+        self.desired_size() <= 0.
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-controller-write>
+    #[allow(unsafe_code)]
+    pub fn write(&self, cx: *mut JSContext, chunk: HandleValue) {
+        let size = self.size.borrow().clone();
+        let result = chunk_size(size.as_ref(), chunk)
+            .and_then(|size| self.queue.borrow_mut().enqueue(chunk, size));
+        if let Err(error) = result {
+            rooted!(in(cx) let mut error_value = UndefinedValue());
+            unsafe { error_to_jsval(cx, &self.global(), error, error_value.handle_mut()) };
+            return self.error_if_needed(cx, error_value.handle());
+        }
+        if !self.stream.close_queued_or_in_flight() && self.stream.state() == WritableStreamState::Writable {
+            self.stream.update_backpressure(self.backpressure());
+        }
+        self.advance_queue_if_needed(cx);
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-controller-close>
+    pub fn close(&self) {
+        self.close_queued.set(true);
+        self.advance_queue_if_needed(self.global().get_cx());
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-controller-advance-queue-if-needed>
+    fn advance_queue_if_needed(&self, cx: *mut JSContext) {
+        if !self.started.get() || self.stream.has_in_flight_write_request() {
+            return;
+        }
+        match self.stream.state() {
+            WritableStreamState::Erroring => return self.stream.finish_erroring(cx),
+            WritableStreamState::Writable => {},
+            WritableStreamState::Closed | WritableStreamState::Errored => return,
+        }
+        if !self.queue.borrow().is_empty() {
+            rooted!(in(cx) let mut chunk = UndefinedValue());
+            self.queue.borrow().peek(chunk.handle_mut());
+            self.process_write(cx, chunk.handle());
+        } else if self.close_queued.get() {
+            self.process_close();
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-controller-process-close>
+    #[allow(unrooted_must_root)]
+    fn process_close(&self) {
+        self.stream.mark_close_request_in_flight();
+        self.close_queued.set(false);
+        let global = self.global();
+        let promise = match self.sink() {
+            Some(sink) => sink.close(&global),
+            None => resolved_promise(&global),
+        };
+        self.clear_algorithms();
+        upon_settlement(&promise,
+                        ControllerReaction::new(self, ControllerStep::CloseFulfilled),
+                        ControllerReaction::new(self, ControllerStep::CloseRejected));
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-controller-process-write>
+    #[allow(unrooted_must_root)]
+    fn process_write(&self, cx: *mut JSContext, chunk: HandleValue) {
+        self.stream.mark_first_write_request_in_flight();
+        let global = self.global();
+        let promise = match self.sink() {
+            Some(sink) => sink.write(&global, cx, chunk, object_value(self)),
+            None => resolved_promise(&global),
+        };
+        upon_settlement(&promise,
+                        ControllerReaction::new(self, ControllerStep::WriteFulfilled),
+                        ControllerReaction::new(self, ControllerStep::WriteRejected));
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-controller-error-if-needed>
+    pub fn error_if_needed(&self, cx: *mut JSContext, error: HandleValue) {
+        if self.stream.state() == WritableStreamState::Writable {
+            self.error(cx, error);
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-controller-error>
+    fn error(&self, cx: *mut JSContext, error: HandleValue) {
+        self.clear_algorithms();
+        self.stream.start_erroring(cx, error);
+    }
+
+    /// <https://streams.spec.whatwg.org/#ws-default-controller-private-error>
+    pub fn error_steps(&self) {
+        self.queue.borrow_mut().reset();
+        self.close_queued.set(false);
+    }
+
+    /// <https://streams.spec.whatwg.org/#ws-default-controller-private-abort>
+    #[allow(unrooted_must_root)]
+    pub fn abort_steps(&self, cx: *mut JSContext, reason: HandleValue) -> Rc<Promise> {
+        let global = self.global();
+        let result = match self.sink() {
+            Some(sink) => sink.abort(&global, cx, reason),
+            None => resolved_promise(&global),
+        };
+        self.clear_algorithms();
+        result
+    }
+}
+
+impl WritableStreamDefaultControllerMethods for WritableStreamDefaultController {
+    /// <https://streams.spec.whatwg.org/#ws-default-controller-error>
+    #[allow(unsafe_code)]
+    unsafe fn Error(&self, cx: *mut JSContext, error: HandleValue) {
+        if self.stream.state() == WritableStreamState::Writable {
+            self.error(cx, error);
+        }
+    }
+}
+
+#[derive(JSTraceable, MallocSizeOf)]
+enum ControllerStep {
+    StartFulfilled,
+    StartRejected,
+    WriteFulfilled,
+    WriteRejected,
+    CloseFulfilled,
+    CloseRejected,
+}
+
+/// Reacts to the promises returned by the methods of the underlying sink.
+#[derive(JSTraceable, MallocSizeOf)]
+#[must_root]
+struct ControllerReaction {
+    controller: Dom<WritableStreamDefaultController>,
+    step: ControllerStep,
+}
+
+impl ControllerReaction {
+    fn new(controller: &WritableStreamDefaultController, step: ControllerStep) -> Box<Callback> {
+        Box::new(ControllerReaction { controller: Dom::from_ref(controller), step: step })
+    }
+}
+
+impl Callback for ControllerReaction {
+    fn callback(&self, cx: *mut JSContext, v: HandleValue) {
+        let controller = &self.controller;
+        let stream = &controller.stream;
+        match self.step {
+            ControllerStep::StartFulfilled => {
+                controller.started.set(true);
+                controller.advance_queue_if_needed(cx);
+            },
+            ControllerStep::StartRejected => {
+                controller.started.set(true);
+                stream.deal_with_rejection(cx, v);
+            },
+            ControllerStep::WriteFulfilled => {
+                stream.finish_in_flight_write();
+                rooted!(in(cx) let mut chunk = UndefinedValue());
+                controller.queue.borrow_mut().dequeue(chunk.handle_mut());
+                if !stream.close_queued_or_in_flight() && stream.state() == WritableStreamState::Writable {
+                    stream.update_backpressure(controller.backpressure());
+                }
+                controller.advance_queue_if_needed(cx);
+            },
+            ControllerStep::WriteRejected => {
+                if stream.state() == WritableStreamState::Writable {
+                    controller.clear_algorithms();
+                }
+                stream.finish_in_flight_write_with_error(cx, v);
+            },
+            ControllerStep::CloseFulfilled => stream.finish_in_flight_close(),
+            ControllerStep::CloseRejected => stream.finish_in_flight_close_with_error(cx, v),
+        }
+    }
+}
@@ -0,0 +1,259 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::cell::DomRefCell;
+use dom::bindings::codegen::Bindings::WritableStreamDefaultWriterBinding;
+use dom::bindings::codegen::Bindings::WritableStreamDefaultWriterBinding::WritableStreamDefaultWriterMethods;
+use dom::bindings::error::{Error, Fallible};
+use dom::bindings::reflector::{DomObject, Reflector, reflect_dom_object};
+use dom::bindings::root::{DomRoot, MutNullableDom};
+use dom::globalscope::GlobalScope;
+use dom::promise::Promise;
+use dom::streams::{rejected_promise, resolved_promise};
+use dom::writablestream::{WritableStream, WritableStreamState, rejected_with_stored_error};
+use dom_struct::dom_struct;
+use js::jsapi::JSContext;
+use js::jsval::UndefinedValue;
+use js::rust::HandleValue;
+use std::rc::Rc;
+
+/// <https://streams.spec.whatwg.org/#default-writer-class>
+#[dom_struct]
+pub struct WritableStreamDefaultWriter {
+    reflector_: Reflector,
+    /// The stream this writer locks, until the lock is released.
+    stream: MutNullableDom<WritableStream>,
+    #[ignore_malloc_size_of = "Rc"]
+    ready_promise: DomRefCell<Rc<Promise>>,
+    #[ignore_malloc_size_of = "Rc"]
+    closed_promise: DomRefCell<Rc<Promise>>,
+}
+
+impl WritableStreamDefaultWriter {
+    /// Acquires a writer for `stream`, which must not be locked.
+    ///
+    /// <https://streams.spec.whatwg.org/#set-up-writable-stream-default-writer>
+    #[allow(unrooted_must_root)]
+    pub fn new(global: &GlobalScope, stream: &WritableStream) -> Fallible<DomRoot<WritableStreamDefaultWriter>> {
+        if stream.is_locked() {
+            return Err(Error::Type("The stream is locked".to_owned()));
+        }
+        let cx = global.get_cx();
+        let (ready_promise, closed_promise) = match stream.state() {
+            WritableStreamState::Writable => {
+                let ready_promise = if !stream.close_queued_or_in_flight() && stream.backpressure() {
+                    Promise::new(global)
+                } else {
+                    resolved_promise(global)
+                };
+                (ready_promise, Promise::new(global))
+            },
+            WritableStreamState::Erroring => (rejected_with_stored_error(stream, cx), Promise::new(global)),
+            WritableStreamState::Closed => (resolved_promise(global), resolved_promise(global)),
+            WritableStreamState::Errored => {
+                (rejected_with_stored_error(stream, cx), rejected_with_stored_error(stream, cx))
+            },
+        };
+        let writer = reflect_dom_object(Box::new(WritableStreamDefaultWriter {
+            reflector_: Reflector::new(),
+            stream: MutNullableDom::new(Some(stream)),
+            ready_promise: DomRefCell::new(ready_promise),
+            closed_promise: DomRefCell::new(closed_promise),
+        }), global, WritableStreamDefaultWriterBinding::Wrap);
+        stream.set_writer(Some(&writer));
+        Ok(writer)
+    }
+
+    /// <https://streams.spec.whatwg.org/#default-writer-constructor>
+    pub fn Constructor(global: &GlobalScope,
+                       stream: &WritableStream)
+                       -> Fallible<DomRoot<WritableStreamDefaultWriter>> {
+        WritableStreamDefaultWriter::new(global, stream)
+    }
+
+    #[allow(unrooted_must_root)]
+    pub fn ready_promise(&self) -> Rc<Promise> {
+        self.ready_promise.borrow().clone()
+    }
+
+    #[allow(unrooted_must_root)]
+    pub fn closed_promise(&self) -> Rc<Promise> {
+        self.closed_promise.borrow().clone()
+    }
+
+    pub fn resolve_ready_promise(&self) {
+        self.ready_promise.borrow().resolve_native(&());
+    }
+
+    /// Replaces the ready promise with a pending one, when backpressure is applied.
+    pub fn reset_ready_promise(&self) {
+        *self.ready_promise.borrow_mut() = Promise::new(&self.global());
+    }
+
+    pub fn resolve_closed_promise(&self) {
+        self.closed_promise.borrow().resolve_native(&());
+    }
+
+    #[allow(unsafe_code)]
+    pub fn reject_closed_promise(&self, cx: *mut JSContext, error: HandleValue) {
+        unsafe { self.closed_promise.borrow().reject(cx, error) };
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-writer-ensure-ready-promise-rejected>
+    #[allow(unsafe_code)]
+    pub fn ensure_ready_promise_rejected(&self, cx: *mut JSContext, error: HandleValue) {
+        if self.ready_promise.borrow().is_fulfilled() {
+            *self.ready_promise.borrow_mut() = rejected_promise(&self.global(), cx, error);
+        } else {
+            unsafe { self.ready_promise.borrow().reject(cx, error) };
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-writer-ensure-closed-promise-rejected>
+    #[allow(unsafe_code)]
+    fn ensure_closed_promise_rejected(&self, cx: *mut JSContext, error: HandleValue) {
+        if self.closed_promise.borrow().is_fulfilled() {
+            *self.closed_promise.borrow_mut() = rejected_promise(&self.global(), cx, error);
+        } else {
+            unsafe { self.closed_promise.borrow().reject(cx, error) };
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-writer-get-desired-size>
+    pub fn desired_size(&self) -> Fallible<Option<f64>> {
+        let stream = match self.stream.get() {
+            Some(stream) => stream,
+            None => return Err(Error::Type("The writer has released its lock".to_owned())),
+        };
+        Ok(match stream.state() {
+            WritableStreamState::Errored | WritableStreamState::Erroring => None,
+            WritableStreamState::Closed => Some(0.),
+            WritableStreamState::Writable => Some(stream.controller().desired_size()),
+        })
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-writer-write>
+    #[allow(unrooted_must_root)]
+    pub fn write(&self, cx: *mut JSContext, chunk: HandleValue) -> Rc<Promise> {
+        let stream = match self.stream.get() {
+            Some(stream) => stream,
+            None => return self.released_promise(),
+        };
+        match stream.state() {
+            WritableStreamState::Errored | WritableStreamState::Erroring => {
+                return rejected_with_stored_error(&stream, cx);
+            },
+            WritableStreamState::Closed => {
+                let promise = Promise::new(&self.global());
+                promise.reject_error(Error::Type("The stream is closed".to_owned()));
+                return promise;
+            },
+            WritableStreamState::Writable => {},
+        }
+        if stream.close_queued_or_in_flight() {
+            let promise = Promise::new(&self.global());
+            promise.reject_error(Error::Type("The stream is closing".to_owned()));
+            return promise;
+        }
+        let promise = stream.add_write_request();
+        stream.controller().write(cx, chunk);
+        promise
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-writer-close-with-error-propagation>
+    #[allow(unrooted_must_root)]
+    pub fn close_with_error_propagation(&self, cx: *mut JSContext) -> Rc<Promise> {
+        let stream = match self.stream.get() {
+            Some(stream) => stream,
+            None => return self.released_promise(),
+        };
+        if stream.close_queued_or_in_flight() || stream.state() == WritableStreamState::Closed {
+            return resolved_promise(&self.global());
+        }
+        if stream.state() == WritableStreamState::Errored {
+            return rejected_with_stored_error(&stream, cx);
+        }
+        stream.close()
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-writer-release>
+    #[allow(unsafe_code)]
+    pub fn release(&self) {
+        let stream = match self.stream.get() {
+            Some(stream) => stream,
+            None => return,
+        };
+        let global = self.global();
+        let cx = global.get_cx();
+        rooted!(in(cx) let mut error = UndefinedValue());
+        unsafe {
+            Error::Type("The writer has released its lock".to_owned()).to_jsval(cx, &global, error.handle_mut());
+        }
+        self.ensure_ready_promise_rejected(cx, error.handle());
+        self.ensure_closed_promise_rejected(cx, error.handle());
+        stream.set_writer(None);
+        self.stream.set(None);
+    }
+
+    #[allow(unrooted_must_root)]
+    fn released_promise(&self) -> Rc<Promise> {
+        let promise = Promise::new(&self.global());
+        promise.reject_error(Error::Type("The writer has released its lock".to_owned()));
+        promise
+    }
+}
+
+impl WritableStreamDefaultWriterMethods for WritableStreamDefaultWriter {
+    /// <https://streams.spec.whatwg.org/#default-writer-closed>
+    #[allow(unrooted_must_root)]
+    fn Closed(&self) -> Rc<Promise> {
+        self.closed_promise()
+    }
+
+    /// <https://streams.spec.whatwg.org/#default-writer-desired-size>
+    fn GetDesiredSize(&self) -> Fallible<Option<f64>> {
+        self.desired_size()
+    }
+
+    /// <https://streams.spec.whatwg.org/#default-writer-ready>
+    #[allow(unrooted_must_root)]
+    fn Ready(&self) -> Rc<Promise> {
+        self.ready_promise()
+    }
+
+    /// <https://streams.spec.whatwg.org/#default-writer-abort>
+    #[allow(unrooted_must_root, unsafe_code)]
+    unsafe fn Abort(&self, cx: *mut JSContext, reason: HandleValue) -> Rc<Promise> {
+        match self.stream.get() {
+            Some(stream) => stream.abort(cx, reason),
+            None => self.released_promise(),
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#default-writer-close>
+    #[allow(unrooted_must_root)]
+    fn Close(&self) -> Rc<Promise> {
+        let stream = match self.stream.get() {
+            Some(stream) => stream,
+            None => return self.released_promise(),
+        };
+        if stream.close_queued_or_in_flight() {
+            let promise = Promise::new(&self.global());
+            promise.reject_error(Error::Type("The stream is already closing".to_owned()));
+            return promise;
+        }
+        stream.close()
+    }
+
+    /// <https://streams.spec.whatwg.org/#default-writer-release-lock>
+    fn ReleaseLock(&self) {
+        self.release()
+    }
+
+    /// <https://streams.spec.whatwg.org/#default-writer-write>
+    #[allow(unrooted_must_root, unsafe_code)]
+    unsafe fn Write(&self, cx: *mut JSContext, chunk: HandleValue) -> Rc<Promise> {
+        self.write(cx, chunk)
+    }
+}
@@ -7,10 +7,10 @@ use dom::bindings::cell::DomRefCell;
 use dom::bindings::codegen::Bindings::BlobBinding::BlobBinding::BlobMethods;
 use dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
 use dom::bindings::codegen::Bindings::XMLHttpRequestBinding;
-use dom::bindings::codegen::Bindings::XMLHttpRequestBinding::BodyInit;
+use dom::bindings::codegen::Bindings::XMLHttpRequestBinding::XMLHttpRequestBodyInit;
 use dom::bindings::codegen::Bindings::XMLHttpRequestBinding::XMLHttpRequestMethods;
 use dom::bindings::codegen::Bindings::XMLHttpRequestBinding::XMLHttpRequestResponseType;
-use dom::bindings::codegen::UnionTypes::DocumentOrBodyInit;
+use dom::bindings::codegen::UnionTypes::DocumentOrXMLHttpRequestBodyInit;
 use dom::bindings::conversions::ToJSValConvertible;
 use dom::bindings::error::{Error, ErrorResult, Fallible};
 use dom::bindings::inheritance::Castable;
@@ -38,7 +38,6 @@ use dom::xmlhttprequestupload::XMLHttpRequestUpload;
 use dom_struct::dom_struct;
 use encoding_rs::{Encoding, UTF_8};
 use euclid::Length;
-use fetch::FetchCanceller;
 use html5ever::serialize;
 use html5ever::serialize::SerializeOpts;
 use hyper::header::{ContentLength, ContentType, ContentEncoding};
@@ -56,7 +55,8 @@ use js::typedarray::{ArrayBuffer, CreateWith};
 use net_traits::{FetchChannels, FetchMetadata, FilteredMetadata};
 use net_traits::{FetchResponseListener, NetworkError, ReferrerPolicy};
 use net_traits::CoreResourceMsg::Fetch;
-use net_traits::request::{CredentialsMode, Destination, RequestInit, RequestMode};
+use net_traits::csp::Violation;
+use net_traits::request::{CredentialsMode, Destination, RequestId, RequestInit, RequestMode};
 use net_traits::trim_http_whitespace;
 use network_listener::{NetworkListener, PreInvoke};
 use script_traits::DocumentActivity;
@@ -160,7 +160,8 @@ pub struct XMLHttpRequest {
     response_status: Cell<Result<(), ()>>,
     referrer_url: Option<ServoUrl>,
     referrer_policy: Option<ReferrerPolicy>,
-    canceller: DomRefCell<FetchCanceller>,
+    /// The id of the ongoing fetch, through which it is cancelled.
+    request_id: Cell<Option<RequestId>>,
 }
 
 impl XMLHttpRequest {
@@ -206,7 +207,7 @@ impl XMLHttpRequest {
             response_status: Cell::new(Ok(())),
             referrer_url: referrer_url,
             referrer_policy: referrer_policy,
-            canceller: DomRefCell::new(Default::default()),
+            request_id: Cell::new(None),
         }
     }
     pub fn new(global: &GlobalScope) -> DomRoot<XMLHttpRequest> {
@@ -227,8 +228,7 @@ impl XMLHttpRequest {
     fn initiate_async_xhr(context: Arc<Mutex<XHRContext>>,
                           task_source: NetworkingTaskSource,
                           global: &GlobalScope,
-                          init: RequestInit,
-                          cancellation_chan: ipc::IpcReceiver<()>) {
+                          init: RequestInit) {
         impl FetchResponseListener for XHRContext {
             fn process_request_body(&mut self) {
                 // todo
@@ -256,6 +256,11 @@ impl XMLHttpRequest {
                 let rv = self.xhr.root().process_response_complete(self.gen_id, response);
                 *self.sync_status.borrow_mut() = Some(rv);
             }
+
+            fn process_csp_violations(&mut self, violations: Vec<Violation>) {
+                let global = &self.xhr.root().global();
+                global.report_csp_violations(violations, None);
+            }
         }
 
         impl PreInvoke for XHRContext {
@@ -275,7 +280,7 @@ impl XMLHttpRequest {
             listener.notify_fetch(message.to().unwrap());
         }));
         global.core_resource_thread().send(
-            Fetch(init, FetchChannels::ResponseMsg(action_sender, Some(cancellation_chan)))).unwrap();
+            Fetch(init, FetchChannels::ResponseMsg(action_sender, None, None))).unwrap();
     }
 }
 
@@ -495,7 +500,7 @@ impl XMLHttpRequestMethods for XMLHttpRequest {
     }
 
     // https://xhr.spec.whatwg.org/#the-send()-method
-    fn Send(&self, data: Option<DocumentOrBodyInit>) -> ErrorResult {
+    fn Send(&self, data: Option<DocumentOrXMLHttpRequestBodyInit>) -> ErrorResult {
         // Step 1, 2
         if self.ready_state.get() != XMLHttpRequestState::Opened || self.send_flag.get() {
             return Err(Error::InvalidState);
@@ -508,7 +513,7 @@ impl XMLHttpRequestMethods for XMLHttpRequest {
         };
         // Step 4 (first half)
         let extracted_or_serialized = match data {
-            Some(DocumentOrBodyInit::Document(ref doc)) => {
+            Some(DocumentOrXMLHttpRequestBodyInit::Document(ref doc)) => {
                 let data = Vec::from(serialize_document(&doc)?.as_ref());
                 let content_type = if doc.is_html_document() {
                     "text/html;charset=UTF-8"
@@ -517,12 +522,14 @@ impl XMLHttpRequestMethods for XMLHttpRequest {
                 };
                 Some((data, Some(DOMString::from(content_type))))
             },
-            Some(DocumentOrBodyInit::Blob(ref b)) => Some(b.extract()),
-            Some(DocumentOrBodyInit::FormData(ref formdata)) => Some(formdata.extract()),
-            Some(DocumentOrBodyInit::String(ref str)) => Some(str.extract()),
-            Some(DocumentOrBodyInit::URLSearchParams(ref urlsp)) => Some(urlsp.extract()),
-            Some(DocumentOrBodyInit::ArrayBuffer(ref typedarray)) => Some((typedarray.to_vec(), None)),
-            Some(DocumentOrBodyInit::ArrayBufferView(ref typedarray)) => Some((typedarray.to_vec(), None)),
+            Some(DocumentOrXMLHttpRequestBodyInit::Blob(ref b)) => Some(b.extract()),
+            Some(DocumentOrXMLHttpRequestBodyInit::FormData(ref formdata)) => Some(formdata.extract()),
+            Some(DocumentOrXMLHttpRequestBodyInit::String(ref str)) => Some(str.extract()),
+            Some(DocumentOrXMLHttpRequestBodyInit::URLSearchParams(ref urlsp)) => Some(urlsp.extract()),
+            Some(DocumentOrXMLHttpRequestBodyInit::ArrayBuffer(ref typedarray)) => Some((typedarray.to_vec(), None)),
+            Some(DocumentOrXMLHttpRequestBodyInit::ArrayBufferView(ref typedarray)) => {
+                Some((typedarray.to_vec(), None))
+            },
             None => None,
         };
 
@@ -594,6 +601,8 @@ impl XMLHttpRequestMethods for XMLHttpRequest {
             referrer_url: self.referrer_url.clone(),
             referrer_policy: self.referrer_policy.clone(),
             pipeline_id: Some(self.global().pipeline_id()),
+            csp_list: self.global().get_csp_list(),
+            client_url: Some(self.global().get_url()),
             .. RequestInit::default()
         };
 
@@ -601,7 +610,8 @@ impl XMLHttpRequestMethods for XMLHttpRequest {
         match extracted_or_serialized {
             Some((_, ref content_type)) => {
                 let encoding = match data {
-                    Some(DocumentOrBodyInit::String(_)) | Some(DocumentOrBodyInit::Document(_)) =>
+                    Some(DocumentOrXMLHttpRequestBodyInit::String(_)) |
+                    Some(DocumentOrXMLHttpRequestBodyInit::Document(_)) =>
                     // XHR spec differs from http, and says UTF-8 should be in capitals,
                     // instead of "utf-8", which is what Hyper defaults to. So not
                     // using content types provided by Hyper.
@@ -970,7 +980,7 @@ impl XMLHttpRequest {
                         self.sync.get());
 
                 self.cancel_timeout();
-                self.canceller.borrow_mut().ignore();
+                self.fetch_finished();
 
                 // Part of step 11, send() (processing response end of file)
                 // XXXManishearth handle errors, if any (substep 2)
@@ -987,7 +997,7 @@ impl XMLHttpRequest {
             },
             XHRProgress::Errored(_, e) => {
                 self.cancel_timeout();
-                self.canceller.borrow_mut().ignore();
+                self.fetch_finished();
 
                 self.discard_subsequent_responses();
                 self.send_flag.set(false);
@@ -1016,8 +1026,16 @@ impl XMLHttpRequest {
         }
     }
 
+    fn fetch_finished(&self) {
+        if let Some(id) = self.request_id.take() {
+            self.global().untrack_fetch(id);
+        }
+    }
+
     fn terminate_ongoing_fetch(&self) {
-        self.canceller.borrow_mut().cancel();
+        if let Some(id) = self.request_id.take() {
+            self.global().cancel_fetches(vec![id]);
+        }
         let GenerationId(prev_id) = self.generation_id.get();
         self.generation_id.set(GenerationId(prev_id + 1));
         self.response_status.set(Ok(()));
@@ -1327,10 +1345,13 @@ impl XMLHttpRequest {
             (global.networking_task_source(), None)
         };
 
-        let cancel_receiver = self.canceller.borrow_mut().initialize();
+        // Cancel the previous fetch, if any.
+        if let Some(previous_id) = self.request_id.replace(Some(init.id)) {
+            global.cancel_fetches(vec![previous_id]);
+        }
+        global.track_fetch(init.id);
 
-        XMLHttpRequest::initiate_async_xhr(context.clone(), task_source,
-                                           global, init, cancel_receiver);
+        XMLHttpRequest::initiate_async_xhr(context.clone(), task_source, global, init);
 
         if let Some(script_port) = script_port {
             loop {
@@ -1436,16 +1457,16 @@ fn serialize_document(doc: &Document) -> Fallible<DOMString> {
     }
 }
 
-impl Extractable for BodyInit {
+impl Extractable for XMLHttpRequestBodyInit {
     // https://fetch.spec.whatwg.org/#concept-bodyinit-extract
     fn extract(&self) -> (Vec<u8>, Option<DOMString>) {
         match *self {
-            BodyInit::String(ref s) => s.extract(),
-            BodyInit::URLSearchParams(ref usp) => usp.extract(),
-            BodyInit::Blob(ref b) => b.extract(),
-            BodyInit::FormData(ref formdata) => formdata.extract(),
-            BodyInit::ArrayBuffer(ref typedarray) => ((typedarray.to_vec(), None)),
-            BodyInit::ArrayBufferView(ref typedarray) => ((typedarray.to_vec(), None)),
+            XMLHttpRequestBodyInit::String(ref s) => s.extract(),
+            XMLHttpRequestBodyInit::URLSearchParams(ref usp) => usp.extract(),
+            XMLHttpRequestBodyInit::Blob(ref b) => b.extract(),
+            XMLHttpRequestBodyInit::FormData(ref formdata) => formdata.extract(),
+            XMLHttpRequestBodyInit::ArrayBuffer(ref typedarray) => ((typedarray.to_vec(), None)),
+            XMLHttpRequestBodyInit::ArrayBufferView(ref typedarray) => ((typedarray.to_vec(), None)),
         }
     }
 }
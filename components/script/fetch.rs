@@ -2,41 +2,50 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use dom::abortsignal::{AbortAlgorithm, AbortSignal};
+use dom::bindings::codegen::Bindings::AbortSignalBinding::AbortSignalMethods;
 use dom::bindings::codegen::Bindings::RequestBinding::RequestInfo;
 use dom::bindings::codegen::Bindings::RequestBinding::RequestInit;
+use dom::bindings::codegen::Bindings::RequestBinding::RequestMethods;
 use dom::bindings::codegen::Bindings::ResponseBinding::ResponseBinding::ResponseMethods;
 use dom::bindings::codegen::Bindings::ResponseBinding::ResponseType as DOMResponseType;
 use dom::bindings::error::Error;
 use dom::bindings::inheritance::Castable;
 use dom::bindings::refcounted::{Trusted, TrustedPromise};
 use dom::bindings::reflector::DomObject;
-use dom::bindings::root::DomRoot;
+use dom::bindings::root::{Dom, DomRoot};
 use dom::bindings::trace::RootedTraceableBox;
 use dom::globalscope::GlobalScope;
 use dom::headers::Guard;
 use dom::promise::Promise;
+use dom::readablestream::{ReadRequest, ReadRequestSteps, ReadableStream};
+use dom::readablestreamdefaultreader::ReadableStreamDefaultReader;
 use dom::request::Request;
 use dom::response::Response;
 use dom::serviceworkerglobalscope::ServiceWorkerGlobalScope;
-use ipc_channel::ipc;
+use ipc_channel::ipc::{self, IpcSender};
 use ipc_channel::router::ROUTER;
-use js::jsapi::JSAutoCompartment;
+use js::jsapi::{JSAutoCompartment, JSContext};
+use js::rust::HandleValue;
+use js::typedarray::Uint8Array;
 use net_traits::{FetchChannels, FetchResponseListener, NetworkError};
 use net_traits::{FilteredMetadata, FetchMetadata, Metadata};
 use net_traits::CoreResourceMsg::Fetch as NetTraitsFetch;
-use net_traits::request::{Request as NetTraitsRequest, ServiceWorkersMode};
+use net_traits::csp::Violation;
+use net_traits::request::{BodyChunk, BodySource, Request as NetTraitsRequest, RequestId, ServiceWorkersMode};
 use net_traits::request::RequestInit as NetTraitsRequestInit;
 use network_listener::{NetworkListener, PreInvoke};
 use servo_url::ServoUrl;
-use std::mem;
 use std::rc::Rc;
 use std::sync::{Arc, Mutex};
-use task_source::TaskSourceName;
+use task_source::{TaskSource, TaskSourceName};
 
 struct FetchContext {
     fetch_promise: Option<TrustedPromise>,
     response_object: Trusted<Response>,
-    body: Vec<u8>,
+    /// The stream that the body of the response is enqueued into as it is received.
+    body_stream: Trusted<ReadableStream>,
+    request_id: RequestId,
 }
 
 /// RAII fetch canceller object. By default initialized to not having a canceller
@@ -95,7 +104,7 @@ fn from_referrer_to_referrer_url(request: &NetTraitsRequest) -> Option<ServoUrl>
     request.referrer.to_url().map(|url| url.clone())
 }
 
-fn request_init_from_request(request: NetTraitsRequest) -> NetTraitsRequestInit {
+fn request_init_from_request(global: &GlobalScope, request: NetTraitsRequest) -> NetTraitsRequestInit {
     NetTraitsRequestInit {
         method: request.method.clone(),
         url: request.url(),
@@ -108,7 +117,7 @@ fn request_init_from_request(request: NetTraitsRequest) -> NetTraitsRequestInit
         use_cors_preflight: request.use_cors_preflight,
         credentials_mode: request.credentials_mode,
         use_url_credentials: request.use_url_credentials,
-        origin: GlobalScope::current().expect("No current global object").origin().immutable().clone(),
+        origin: global.origin().immutable().clone(),
         referrer_url: from_referrer_to_referrer_url(&request),
         referrer_policy: request.referrer_policy,
         pipeline_id: request.pipeline_id,
@@ -121,11 +130,8 @@ fn request_init_from_request(request: NetTraitsRequest) -> NetTraitsRequestInit
 // https://fetch.spec.whatwg.org/#fetch-method
 #[allow(unrooted_must_root)]
 pub fn Fetch(global: &GlobalScope, input: RequestInfo, init: RootedTraceableBox<RequestInit>) -> Rc<Promise> {
-    let core_resource_thread = global.core_resource_thread();
-
     // Step 1
     let promise = Promise::new(global);
-    let response = Response::new(global);
 
     // Step 2
     let request = match Request::Constructor(global, input, init) {
@@ -133,9 +139,41 @@ pub fn Fetch(global: &GlobalScope, input: RequestInfo, init: RootedTraceableBox<
             promise.reject_error(e);
             return promise;
         },
-        Ok(r) => r.get_request(),
+        Ok(r) => r,
     };
-    let mut request_init = request_init_from_request(request);
+    let mut request_init = request_init_from_request(global, request.get_request());
+
+    // Step 3
+    let signal = request.Signal();
+    if signal.Aborted() {
+        promise.reject_error(Error::Abort);
+        return promise;
+    }
+
+    // A body given as a stream is read a chunk at a time, as it is sent.
+    if let Some(stream) = request.get_streaming_body() {
+        match ReadableStreamDefaultReader::new(global, &stream) {
+            Ok(reader) => request_init.body_source = Some(body_source_from_reader(global, &reader)),
+            Err(e) => {
+                promise.reject_error(e);
+                return promise;
+            },
+        }
+    }
+
+    send_request(global, request_init, &signal, &promise);
+    promise
+}
+
+#[allow(unrooted_must_root)]
+fn send_request(global: &GlobalScope,
+                mut request_init: NetTraitsRequestInit,
+                signal: &AbortSignal,
+                promise: &Rc<Promise>) {
+    let core_resource_thread = global.core_resource_thread();
+    let response = Response::new(global);
+    request_init.csp_list = global.get_csp_list();
+    request_init.client_url = Some(global.get_url());
 
     // Step 3
     if global.downcast::<ServiceWorkerGlobalScope>().is_some() {
@@ -145,12 +183,29 @@ pub fn Fetch(global: &GlobalScope, input: RequestInfo, init: RootedTraceableBox<
     // Step 4
     response.Headers().set_guard(Guard::Immutable);
 
+    // Cancelling the body of the response cancels the fetch.
+    let mut canceller = FetchCanceller::new();
+    let cancel_receiver = canceller.initialize();
+    // The body is only read from the network as fast as the stream is read.
+    let (demand_sender, demand_receiver) = ipc::channel().unwrap();
+    let body_stream = ReadableStream::new_for_fetch(global, canceller, Some(demand_sender));
+    response.set_body(&body_stream);
+
+    let request_id = request_init.id;
+    global.track_fetch(request_id);
+    signal.add_algorithm(Box::new(AbortFetch {
+        promise: promise.clone(),
+        response: Dom::from_ref(&*response),
+        request_id: request_id,
+    }));
+
     // Step 5
     let (action_sender, action_receiver) = ipc::channel().unwrap();
     let fetch_context = Arc::new(Mutex::new(FetchContext {
         fetch_promise: Some(TrustedPromise::new(promise.clone())),
         response_object: Trusted::new(&*response),
-        body: vec![],
+        body_stream: Trusted::new(&*body_stream),
+        request_id: request_id,
     }));
     let listener = NetworkListener {
         context: fetch_context,
@@ -162,9 +217,92 @@ pub fn Fetch(global: &GlobalScope, input: RequestInfo, init: RootedTraceableBox<
         listener.notify_fetch(message.to().unwrap());
     }));
     core_resource_thread.send(
-        NetTraitsFetch(request_init, FetchChannels::ResponseMsg(action_sender, None))).unwrap();
+        NetTraitsFetch(request_init,
+                       FetchChannels::ResponseMsg(action_sender, Some(cancel_receiver), Some(demand_receiver))))
+        .unwrap();
+}
 
-    promise
+/// Lets net read the chunks of a request body given as a stream from `reader`, one at a time
+/// as it sends them.
+fn body_source_from_reader(global: &GlobalScope, reader: &ReadableStreamDefaultReader) -> BodySource {
+    let (chunk_requester, chunk_requests) = ipc::channel().unwrap();
+    let reader = Trusted::new(reader);
+    let task_source = global.networking_task_source();
+    let canceller = global.task_canceller(TaskSourceName::Networking);
+    ROUTER.add_route(chunk_requests.to_opaque(), Box::new(move |message| {
+        let sender: IpcSender<BodyChunk> = message.to().unwrap();
+        let reader = reader.clone();
+        let _ = task_source.queue_with_canceller(
+            task!(read_request_body_chunk: move || {
+                let reader = reader.root();
+                let cx = reader.global().get_cx();
+                let _ac = JSAutoCompartment::new(cx, reader.reflector().get_jsobject().get());
+                reader.read(cx, ReadRequest::Native(Box::new(SendBodyChunk { sender: sender })));
+            }),
+            &canceller,
+        );
+    }));
+    BodySource::new(chunk_requester)
+}
+
+/// Sends the chunk read from a request body given as a stream to net.
+#[derive(JSTraceable, MallocSizeOf)]
+struct SendBodyChunk {
+    #[ignore_malloc_size_of = "Channels are hard"]
+    sender: IpcSender<BodyChunk>,
+}
+
+impl ReadRequestSteps for SendBodyChunk {
+    #[allow(unsafe_code)]
+    fn chunk_steps(self: Box<Self>, cx: *mut JSContext, chunk: HandleValue) {
+        let bytes = if chunk.is_object() {
+            unsafe {
+                typedarray!(in(cx) let array: Uint8Array = chunk.to_object());
+                array.ok().map(|array| array.as_slice().to_vec())
+            }
+        } else {
+            None
+        };
+        // Chunks that aren't bytes make the fetch fail.
+        let _ = self.sender.send(bytes.map_or(BodyChunk::Error, BodyChunk::Bytes));
+    }
+
+    fn close_steps(self: Box<Self>, _cx: *mut JSContext) {
+        let _ = self.sender.send(BodyChunk::Done);
+    }
+
+    fn error_steps(self: Box<Self>, _cx: *mut JSContext, _error: HandleValue) {
+        let _ = self.sender.send(BodyChunk::Error);
+    }
+}
+
+/// Rejects the promise of a fetch and errors the body of its response when its signal is
+/// aborted, and stops the transfer.
+///
+/// <https://fetch.spec.whatwg.org/#abort-fetch>
+#[derive(JSTraceable, MallocSizeOf)]
+#[must_root]
+struct AbortFetch {
+    #[ignore_malloc_size_of = "Rc"]
+    promise: Rc<Promise>,
+    response: Dom<Response>,
+    request_id: RequestId,
+}
+
+impl AbortAlgorithm for AbortFetch {
+    fn abort(self: Box<Self>) {
+        // Step 1
+        if !self.promise.is_fulfilled() {
+            self.promise.reject_error(Error::Abort);
+        }
+
+        // Step 3
+        if let Some(body) = self.response.GetBody() {
+            body.error_native(Error::Abort);
+        }
+
+        self.response.global().cancel_fetches(vec![self.request_id]);
+    }
 }
 
 impl PreInvoke for FetchContext {}
@@ -182,6 +320,12 @@ impl FetchResponseListener for FetchContext {
     fn process_response(&mut self, fetch_metadata: Result<FetchMetadata, NetworkError>) {
         let promise = self.fetch_promise.take().expect("fetch promise is missing").root();
 
+        // The fetch has been aborted, and its promise already rejected.
+        if promise.is_fulfilled() {
+            self.fetch_promise = Some(TrustedPromise::new(promise));
+            return;
+        }
+
         // JSAutoCompartment needs to be manually made.
         // Otherwise, Servo will crash.
         let promise_cx = promise.global().get_cx();
@@ -192,6 +336,7 @@ impl FetchResponseListener for FetchContext {
                 promise.reject_error(Error::Type("Network error occurred".to_string()));
                 self.fetch_promise = Some(TrustedPromise::new(promise));
                 self.response_object.root().set_type(DOMResponseType::Error);
+                self.body_stream.root().error_native(Error::Type("Network error occurred".to_string()));
                 return;
             },
             // Step 4.2
@@ -223,19 +368,25 @@ impl FetchResponseListener for FetchContext {
         self.fetch_promise = Some(TrustedPromise::new(promise));
     }
 
-    fn process_response_chunk(&mut self, mut chunk: Vec<u8>) {
-        self.body.append(&mut chunk);
+    fn process_response_chunk(&mut self, chunk: Vec<u8>) {
+        self.body_stream.root().enqueue_native(chunk);
     }
 
-    fn process_response_eof(&mut self, _response: Result<(), NetworkError>) {
-        let response = self.response_object.root();
-        let global = response.global();
-        let cx = global.get_cx();
-        let _ac = JSAutoCompartment::new(cx, global.reflector().get_jsobject().get());
-        response.finish(mem::replace(&mut self.body, vec![]));
+    fn process_response_eof(&mut self, response: Result<(), NetworkError>) {
+        let body_stream = self.body_stream.root();
+        body_stream.global().untrack_fetch(self.request_id);
+        match response {
+            Ok(()) => body_stream.close_native(),
+            Err(_) => body_stream.error_native(Error::Type("Network error occurred".to_string())),
+        }
         // TODO
         // ... trailerObject is not supported in Servo yet.
     }
+
+    fn process_csp_violations(&mut self, violations: Vec<Violation>) {
+        let global = &self.response_object.root().global();
+        global.report_csp_violations(violations, None);
+    }
 }
 
 fn fill_headers_with_metadata(r: DomRoot<Response>, m: Metadata) {
@@ -0,0 +1,401 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Conversions between script values and the keys of
+//! [IndexedDB](https://w3c.github.io/IndexedDB/).
+
+use dom::bindings::conversions::{ConversionBehavior, ToJSValConvertible};
+use dom::bindings::conversions::{get_property, get_property_jsval, is_array_like, jsstring_to_str};
+use dom::bindings::codegen::UnionTypes::StringOrStringSequence;
+use dom::bindings::conversions::root_from_handlevalue;
+use dom::bindings::error::{Error, Fallible};
+use dom::bindings::str::DOMString;
+use dom::bindings::structuredclone::StructuredCloneData;
+use dom::bindings::utils::{get_dictionary_property, set_dictionary_property};
+use dom::domexception::DOMErrorName;
+use dom::globalscope::GlobalScope;
+use dom::idbkeyrange::IDBKeyRange;
+use js::jsapi::{ClippedTime, JSContext, JSObject, JS_GetStringLength, JS_NewPlainObject, NewDateObject};
+use js::jsval::{DoubleValue, ObjectValue, UndefinedValue};
+use js::rust::{HandleValue, MutableHandleValue};
+use js::rust::wrappers::{DateGetMsecSinceEpoch, ObjectIsDate};
+use js::typedarray::{ArrayBuffer, ArrayBufferView, CreateWith};
+use net_traits::indexeddb_thread::{IndexInfo, IndexedDBError, IndexedDBKey, IndexedDBKeyPath};
+use net_traits::indexeddb_thread::{IndexedDBKeyRange, IndexedDBRecord, ObjectStoreInfo};
+use std::ptr;
+
+struct KeyValue<'a>(&'a IndexedDBKey);
+
+/// <https://w3c.github.io/IndexedDB/#convert-a-key-to-a-value>
+impl<'a> ToJSValConvertible for KeyValue<'a> {
+    #[allow(unsafe_code)]
+    unsafe fn to_jsval(&self, cx: *mut JSContext, mut rval: MutableHandleValue) {
+        match *self.0 {
+            IndexedDBKey::Number(number) => number.to_jsval(cx, rval),
+            IndexedDBKey::Date(time) => {
+                rooted!(in(cx) let date = NewDateObject(cx, ClippedTime { t: time }));
+                rval.set(ObjectValue(date.get()));
+            },
+            IndexedDBKey::String(ref string) => string.to_jsval(cx, rval),
+            IndexedDBKey::Binary(ref bytes) => {
+                rooted!(in(cx) let mut buffer = ptr::null_mut::<JSObject>());
+                assert!(ArrayBuffer::create(cx, CreateWith::Slice(bytes), buffer.handle_mut()).is_ok());
+                rval.set(ObjectValue(buffer.get()));
+            },
+            IndexedDBKey::Array(ref keys) => {
+                keys.iter().map(KeyValue).collect::<Vec<_>>().to_jsval(cx, rval)
+            },
+        }
+    }
+}
+
+/// The value of a record, with its primary key injected if the object store generates
+/// its keys and has a key path.
+pub struct RecordValue<'a> {
+    global: &'a GlobalScope,
+    record: &'a IndexedDBRecord,
+    key_path: Option<&'a str>,
+}
+
+impl<'a> RecordValue<'a> {
+    pub fn new(global: &'a GlobalScope,
+               record: &'a IndexedDBRecord,
+               store: Option<&'a ObjectStoreInfo>)
+               -> RecordValue<'a> {
+        // Generated keys aren't stored in the values, they are injected when reading them.
+        let key_path = match store {
+            Some(&ObjectStoreInfo { key_path: Some(IndexedDBKeyPath::String(ref path)), auto_increment: true, .. }) => {
+                Some(&**path)
+            },
+            _ => None,
+        };
+        RecordValue {
+            global: global,
+            record: record,
+            key_path: key_path,
+        }
+    }
+}
+
+impl<'a> ToJSValConvertible for RecordValue<'a> {
+    #[allow(unsafe_code)]
+    unsafe fn to_jsval(&self, cx: *mut JSContext, mut rval: MutableHandleValue) {
+        rooted!(in(cx) let mut value = UndefinedValue());
+        StructuredCloneData::Vector(self.record.value.clone()).read(self.global, value.handle_mut());
+        if let Some(key_path) = self.key_path {
+            inject_key(cx, value.handle(), &self.record.primary_key, key_path);
+        }
+        rval.set(value.get());
+    }
+}
+
+pub fn to_error(error: IndexedDBError) -> Error {
+    match error {
+        IndexedDBError::Constraint => Error::Constraint,
+        IndexedDBError::NotFound => Error::NotFound,
+        IndexedDBError::Data => Error::Data,
+        IndexedDBError::Unknown => Error::Unknown,
+    }
+}
+
+pub fn error_name(error: IndexedDBError) -> DOMErrorName {
+    match error {
+        IndexedDBError::Constraint => DOMErrorName::ConstraintError,
+        IndexedDBError::NotFound => DOMErrorName::NotFoundError,
+        IndexedDBError::Data => DOMErrorName::DataError,
+        IndexedDBError::Unknown => DOMErrorName::UnknownError,
+    }
+}
+
+/// <https://w3c.github.io/IndexedDB/#valid-key-path>
+pub fn key_path_from_idl(key_path: &StringOrStringSequence) -> Fallible<IndexedDBKeyPath> {
+    fn is_valid(path: &str) -> bool {
+        // TODO: Identifiers should be checked against the IdentifierName production of
+        // ECMAScript rather than only ASCII names.
+        path.is_empty() || path.split('.').all(|identifier| {
+            let mut chars = identifier.chars();
+            match chars.next() {
+                Some(c) if c.is_alphabetic() || c == '_' || c == '$' => {
+                    chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+                },
+                _ => false,
+            }
+        })
+    }
+
+    match *key_path {
+        StringOrStringSequence::String(ref path) => {
+            if !is_valid(path) {
+                return Err(Error::Syntax);
+            }
+            Ok(IndexedDBKeyPath::String(String::from(path.clone())))
+        },
+        StringOrStringSequence::StringSequence(ref paths) => {
+            if paths.is_empty() || !paths.iter().all(|path| !path.is_empty() && is_valid(path)) {
+                return Err(Error::Syntax);
+            }
+            Ok(IndexedDBKeyPath::Sequence(paths.iter().cloned().map(String::from).collect()))
+        },
+    }
+}
+
+/// <https://w3c.github.io/IndexedDB/#convert-a-key-path-to-a-value>
+#[allow(unsafe_code)]
+pub unsafe fn key_path_to_jsval(cx: *mut JSContext, key_path: &IndexedDBKeyPath, rval: MutableHandleValue) {
+    match *key_path {
+        IndexedDBKeyPath::String(ref path) => DOMString::from(path.clone()).to_jsval(cx, rval),
+        IndexedDBKeyPath::Sequence(ref paths) => {
+            paths.iter().cloned().map(DOMString::from).collect::<Vec<_>>().to_jsval(cx, rval)
+        },
+    }
+}
+
+#[allow(unsafe_code)]
+pub unsafe fn key_to_jsval(cx: *mut JSContext, key: &IndexedDBKey, rval: MutableHandleValue) {
+    KeyValue(key).to_jsval(cx, rval)
+}
+
+/// <https://w3c.github.io/IndexedDB/#convert-a-value-to-a-key>
+#[allow(unsafe_code)]
+pub unsafe fn convert_value_to_key(cx: *mut JSContext,
+                                   input: HandleValue,
+                                   seen: &mut Vec<*mut JSObject>)
+                                   -> Fallible<IndexedDBKey> {
+    if input.is_number() {
+        let number = input.to_number();
+        if number.is_nan() {
+            return Err(Error::Data);
+        }
+        return Ok(IndexedDBKey::Number(number));
+    }
+
+    if input.is_string() {
+        return Ok(IndexedDBKey::String(String::from(jsstring_to_str(cx, input.to_string()))));
+    }
+
+    if !input.is_object() {
+        return Err(Error::Data);
+    }
+
+    rooted!(in(cx) let object = input.to_object());
+    if seen.contains(&object.get()) {
+        return Err(Error::Data);
+    }
+
+    let mut is_date = false;
+    if !ObjectIsDate(cx, object.handle(), &mut is_date) {
+        return Err(Error::JSFailed);
+    }
+    if is_date {
+        let mut time = 0.;
+        if !DateGetMsecSinceEpoch(cx, object.handle(), &mut time) {
+            return Err(Error::JSFailed);
+        }
+        if time.is_nan() {
+            return Err(Error::Data);
+        }
+        return Ok(IndexedDBKey::Date(time));
+    }
+
+    typedarray!(in(cx) let buffer: ArrayBuffer = object.get());
+    if let Ok(buffer) = buffer {
+        return Ok(IndexedDBKey::Binary(buffer.as_slice().to_vec()));
+    }
+    typedarray!(in(cx) let view: ArrayBufferView = object.get());
+    if let Ok(view) = view {
+        return Ok(IndexedDBKey::Binary(view.as_slice().to_vec()));
+    }
+
+    if is_array_like(cx, input) {
+        let length = get_property::<u32>(cx, object.handle(), "length", ConversionBehavior::Default)?;
+        seen.push(object.get());
+        let mut keys = vec![];
+        for index in 0..length.unwrap_or(0) {
+            rooted!(in(cx) let mut entry = UndefinedValue());
+            get_property_jsval(cx, object.handle(), &index.to_string(), entry.handle_mut())?;
+            keys.push(convert_value_to_key(cx, entry.handle(), seen)?);
+        }
+        seen.pop();
+        return Ok(IndexedDBKey::Array(keys));
+    }
+
+    Err(Error::Data)
+}
+
+/// <https://w3c.github.io/IndexedDB/#convert-a-value-to-a-key-range>
+#[allow(unsafe_code)]
+pub unsafe fn convert_value_to_key_range(cx: *mut JSContext,
+                                         input: HandleValue,
+                                         null_disallowed: bool)
+                                         -> Fallible<IndexedDBKeyRange> {
+    // Step 1.
+    if let Ok(range) = root_from_handlevalue::<IDBKeyRange>(input) {
+        return Ok(range.inner().clone());
+    }
+
+    // Step 2.
+    if input.is_undefined() || input.is_null() {
+        if null_disallowed {
+            return Err(Error::Data);
+        }
+        return Ok(IndexedDBKeyRange::default());
+    }
+
+    // Steps 3-5.
+    convert_value_to_key(cx, input, &mut vec![]).map(IndexedDBKeyRange::only)
+}
+
+/// <https://w3c.github.io/IndexedDB/#evaluate-a-key-path-on-a-value>, for a string key path.
+///
+/// Returns `false` if a property of the path doesn't exist.
+#[allow(unsafe_code)]
+unsafe fn evaluate_key_path(cx: *mut JSContext,
+                            value: HandleValue,
+                            path: &str,
+                            mut rval: MutableHandleValue)
+                            -> Fallible<bool> {
+    rval.set(value.get());
+    if path.is_empty() {
+        return Ok(true);
+    }
+    for identifier in path.split('.') {
+        if rval.is_string() && identifier == "length" {
+            let length = JS_GetStringLength(rval.to_string());
+            rval.set(DoubleValue(length as f64));
+            continue;
+        }
+        if !rval.is_object() {
+            return Ok(false);
+        }
+        rooted!(in(cx) let object = rval.to_object());
+        rooted!(in(cx) let mut property = UndefinedValue());
+        match get_dictionary_property(cx, object.handle(), identifier, property.handle_mut()) {
+            Ok(true) => rval.set(property.get()),
+            Ok(false) => return Ok(false),
+            Err(()) => return Err(Error::JSFailed),
+        }
+    }
+    Ok(true)
+}
+
+/// <https://w3c.github.io/IndexedDB/#extract-a-key-from-a-value-using-a-key-path>
+///
+/// Returns `None` on failure, and a `DataError` if the key is invalid.
+#[allow(unsafe_code)]
+pub unsafe fn extract_key(cx: *mut JSContext,
+                          value: HandleValue,
+                          key_path: &IndexedDBKeyPath,
+                          multi_entry: bool)
+                          -> Fallible<Option<IndexedDBKey>> {
+    let paths = match *key_path {
+        IndexedDBKeyPath::String(ref path) => vec![path],
+        IndexedDBKeyPath::Sequence(ref paths) => paths.iter().collect(),
+    };
+
+    let mut keys = vec![];
+    for path in paths {
+        rooted!(in(cx) let mut result = UndefinedValue());
+        if !evaluate_key_path(cx, value, path, result.handle_mut())? {
+            return Ok(None);
+        }
+
+        if !multi_entry || !is_array_like(cx, result.handle()) {
+            keys.push(convert_value_to_key(cx, result.handle(), &mut vec![])?);
+            continue;
+        }
+
+        // https://w3c.github.io/IndexedDB/#convert-a-value-to-a-multientry-key
+        rooted!(in(cx) let array = result.to_object());
+        let length = get_property::<u32>(cx, array.handle(), "length", ConversionBehavior::Default)?;
+        let mut seen = vec![array.get()];
+        let mut subkeys = vec![];
+        for index in 0..length.unwrap_or(0) {
+            rooted!(in(cx) let mut entry = UndefinedValue());
+            get_property_jsval(cx, array.handle(), &index.to_string(), entry.handle_mut())?;
+            match convert_value_to_key(cx, entry.handle(), &mut seen) {
+                Ok(key) => {
+                    if !subkeys.contains(&key) {
+                        subkeys.push(key);
+                    }
+                },
+                Err(Error::JSFailed) => return Err(Error::JSFailed),
+                Err(_) => {},
+            }
+        }
+        keys.push(IndexedDBKey::Array(subkeys));
+    }
+
+    Ok(Some(match *key_path {
+        IndexedDBKeyPath::String(_) => keys.pop().unwrap(),
+        IndexedDBKeyPath::Sequence(_) => IndexedDBKey::Array(keys),
+    }))
+}
+
+/// The keys of `value` in an index, which are the entries of the extracted key for
+/// multiEntry indexes. Values without a valid key aren't part of the index.
+#[allow(unsafe_code)]
+pub unsafe fn index_keys(cx: *mut JSContext, value: HandleValue, index: &IndexInfo) -> Fallible<Vec<IndexedDBKey>> {
+    match extract_key(cx, value, &index.key_path, index.multi_entry) {
+        Ok(Some(IndexedDBKey::Array(keys))) if index.multi_entry => Ok(keys),
+        Ok(Some(key)) => Ok(vec![key]),
+        Ok(None) => Ok(vec![]),
+        Err(Error::JSFailed) => Err(Error::JSFailed),
+        Err(_) => Ok(vec![]),
+    }
+}
+
+/// <https://w3c.github.io/IndexedDB/#check-that-a-key-could-be-injected-into-a-value>
+#[allow(unsafe_code)]
+pub unsafe fn can_inject_key(cx: *mut JSContext, value: HandleValue, key_path: &str) -> Fallible<bool> {
+    let mut identifiers: Vec<&str> = key_path.split('.').collect();
+    identifiers.pop();
+    rooted!(in(cx) let mut current = value.get());
+    for identifier in identifiers {
+        if !current.is_object() {
+            return Ok(false);
+        }
+        rooted!(in(cx) let object = current.to_object());
+        rooted!(in(cx) let mut property = UndefinedValue());
+        match get_dictionary_property(cx, object.handle(), identifier, property.handle_mut()) {
+            Ok(true) => current.set(property.get()),
+            Ok(false) => return Ok(true),
+            Err(()) => return Err(Error::JSFailed),
+        }
+    }
+    Ok(current.is_object())
+}
+
+/// <https://w3c.github.io/IndexedDB/#inject-a-key-into-a-value-using-a-key-path>
+#[allow(unsafe_code)]
+pub unsafe fn inject_key(cx: *mut JSContext, value: HandleValue, key: &IndexedDBKey, key_path: &str) {
+    let mut identifiers: Vec<&str> = key_path.split('.').collect();
+    let last = identifiers.pop().unwrap();
+    rooted!(in(cx) let mut current = value.get());
+    for identifier in identifiers {
+        if !current.is_object() {
+            return;
+        }
+        rooted!(in(cx) let object = current.to_object());
+        rooted!(in(cx) let mut property = UndefinedValue());
+        match get_dictionary_property(cx, object.handle(), identifier, property.handle_mut()) {
+            Ok(true) => {},
+            Ok(false) => {
+                rooted!(in(cx) let new_object = JS_NewPlainObject(cx));
+                property.set(ObjectValue(new_object.get()));
+                if set_dictionary_property(cx, object.handle(), identifier, property.handle()).is_err() {
+                    return;
+                }
+            },
+            Err(()) => return,
+        }
+        current.set(property.get());
+    }
+    if !current.is_object() {
+        return;
+    }
+    rooted!(in(cx) let object = current.to_object());
+    rooted!(in(cx) let mut key_value = UndefinedValue());
+    key_to_jsval(cx, key, key_value.handle_mut());
+    let _ = set_dictionary_property(cx, object.handle(), last, key_value.handle());
+}
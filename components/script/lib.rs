@@ -78,8 +78,9 @@ extern crate regex;
 extern crate script_layout_interface;
 extern crate script_traits;
 extern crate selectors;
-extern crate serde;
+#[macro_use] extern crate serde;
 extern crate serde_bytes;
+extern crate serde_json;
 extern crate servo_allocator;
 extern crate servo_arc;
 #[macro_use] extern crate servo_atoms;
@@ -114,10 +115,12 @@ pub mod document_loader;
 #[macro_use]
 mod dom;
 pub mod fetch;
+mod indexed_db;
 mod layout_image;
 mod mem;
 mod microtask;
 mod network_listener;
+mod script_module;
 pub mod script_runtime;
 #[allow(unsafe_code)]
 pub mod script_thread;
@@ -144,8 +147,10 @@ pub mod layout_exports {
     pub use dom::characterdata::LayoutCharacterDataHelpers;
     pub use dom::document::{Document, LayoutDocumentHelpers, PendingRestyle};
     pub use dom::element::{Element, LayoutElementHelpers, RawLayoutElementHelpers};
+    pub use dom::htmlslotelement::{HTMLSlotElement, LayoutHTMLSlotElementHelpers};
     pub use dom::node::NodeFlags;
     pub use dom::node::{LayoutNodeHelpers, Node};
+    pub use dom::shadowroot::{LayoutShadowRootHelpers, ShadowRoot};
     pub use dom::text::Text;
 }
 
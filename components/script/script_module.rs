@@ -0,0 +1,569 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Module scripts: the entries of the module map, and the fetching, linking
+//! and evaluation of module graphs.
+//!
+//! <https://html.spec.whatwg.org/multipage/#module-script>
+//!
+//! Dynamic `import()` and `import.meta` are not supported. The SpiderMonkey that mozjs
+//! 0.9 builds doesn't support them, and has none of the hooks they need from the host
+//! (`SetModuleDynamicImportHook`, `FinishDynamicModuleImport` and `SetModuleMetadataHook`
+//! come with later versions), so modules and classic scripts that use them fail to compile
+//! with a `SyntaxError`, which is reported like any other. They can be added on top of the
+//! module map here once mozjs is upgraded past SpiderMonkey 68.
+
+use document_loader::LoadType;
+use dom::bindings::cell::DomRefCell;
+use dom::bindings::conversions::{ToJSValConvertible, jsstring_to_str};
+use dom::bindings::error::{Error, report_pending_exception};
+use dom::bindings::inheritance::Castable;
+use dom::bindings::refcounted::Trusted;
+use dom::bindings::reflector::DomObject;
+use dom::bindings::settings_stack::AutoEntryScript;
+use dom::document::Document;
+use dom::globalscope::GlobalScope;
+use dom::htmlscriptelement::SCRIPT_JS_MIMES;
+use dom::workerglobalscope::WorkerGlobalScope;
+use hyper::header::ContentType;
+use hyper::mime::Mime;
+use hyper_serde::Serde;
+use ipc_channel::ipc;
+use ipc_channel::router::ROUTER;
+use js::error::throw_type_error;
+use js::jsapi::{CompileModule, GetModuleHostDefinedField, GetRequestedModuleSpecifier, GetRequestedModules};
+use js::jsapi::{Heap, JSAutoCompartment, JSContext, JSObject, JS_GetArrayLength, JS_GetElement};
+use js::jsapi::{HandleObject, HandleString, ModuleEvaluate, ModuleInstantiate, SetModuleHostDefinedField};
+use js::jsapi::{JS_ClearPendingException, SourceBufferHolder};
+use js::jsval::{JSVal, UndefinedValue};
+use js::panic::{maybe_resume_unwind, wrap_panic};
+use js::rust::CompileOptionsWrapper;
+use js::rust::wrappers::{JS_GetPendingException, JS_SetPendingException};
+use net_traits::{FetchMetadata, FetchResponseListener, Metadata, NetworkError, load_whole_resource};
+use net_traits::csp::Violation;
+use net_traits::request::{CredentialsMode, Destination, RequestInit, RequestMode};
+use network_listener::{NetworkListener, PreInvoke};
+use servo_url::ServoUrl;
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::ffi::CString;
+use std::panic::AssertUnwindSafe;
+use std::ptr;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use task_source::TaskSourceName;
+
+/// Where a module of the module map is in its fetch.
+#[derive(Clone, Copy, Debug, JSTraceable, MallocSizeOf, PartialEq)]
+pub enum ModuleStatus {
+    /// The module is being fetched.
+    Fetching,
+    /// The module couldn't be fetched, which fails every graph it is part of.
+    FetchFailed,
+    /// The module was fetched, and parsed unless it has an error to rethrow.
+    Fetched,
+}
+
+/// A module script, and the modules it imports.
+///
+/// The external modules are shared through the module map of their global; inline module
+/// scripts are only owned by their script element.
+#[derive(JSTraceable, MallocSizeOf)]
+pub struct ModuleTree {
+    /// The URL the module was requested with, which keys it in the module map.
+    url: ServoUrl,
+    status: Cell<ModuleStatus>,
+    /// The module record, once the source was parsed.
+    #[ignore_malloc_size_of = "Defined in mozjs"]
+    record: Box<Heap<*mut JSObject>>,
+    /// The URLs of the modules imported by this one, in source order.
+    descendants: DomRefCell<Vec<ServoUrl>>,
+    /// <https://html.spec.whatwg.org/multipage/#concept-script-error-to-rethrow>
+    #[ignore_malloc_size_of = "Defined in mozjs"]
+    error: Box<Heap<JSVal>>,
+    has_error: Cell<bool>,
+}
+
+impl ModuleTree {
+    fn new(url: ServoUrl, status: ModuleStatus) -> ModuleTree {
+        ModuleTree {
+            url: url,
+            status: Cell::new(status),
+            record: Heap::boxed(ptr::null_mut()),
+            descendants: DomRefCell::new(vec![]),
+            error: Heap::boxed(UndefinedValue()),
+            has_error: Cell::new(false),
+        }
+    }
+
+    pub fn url(&self) -> &ServoUrl {
+        &self.url
+    }
+
+    /// Parses `source` into the record of this module, resolving the specifiers of its imports
+    /// against `base_url`. Failures are kept as the error to rethrow of the module.
+    ///
+    /// <https://html.spec.whatwg.org/multipage/#creating-a-module-script>
+    #[allow(unsafe_code)]
+    fn compile(&self, global: &GlobalScope, source: &str, base_url: &ServoUrl) {
+        let cx = global.get_cx();
+        let _ac = JSAutoCompartment::new(cx, global.reflector().get_jsobject().get());
+        let source: Vec<u16> = source.encode_utf16().collect();
+        let filename = CString::new(base_url.as_str()).unwrap();
+        let options = CompileOptionsWrapper::new(cx, filename.as_ptr(), 1);
+        let mut source_buffer = SourceBufferHolder {
+            data_: source.as_ptr(),
+            length_: source.len(),
+            ownsChars_: false,
+        };
+
+        // Steps 7-8.
+        rooted!(in(cx) let mut record = ptr::null_mut::<JSObject>());
+        if !unsafe { CompileModule(cx, options.ptr, &mut source_buffer, record.handle_mut().into()) } {
+            unsafe { self.take_pending_exception(cx) };
+            return;
+        }
+
+        // The resolve hook only gets the record of the importing module, so it is told
+        // the URL the specifiers are relative to through the host-defined field.
+        rooted!(in(cx) let mut url_value = UndefinedValue());
+        unsafe {
+            base_url.as_str().to_jsval(cx, url_value.handle_mut());
+            SetModuleHostDefinedField(record.get(), &*url_value);
+        }
+        self.record.set(record.get());
+
+        // Step 9.
+        let mut descendants = vec![];
+        rooted!(in(cx) let requested = unsafe { GetRequestedModules(cx, record.handle().into()) });
+        let mut length = 0;
+        unsafe { assert!(JS_GetArrayLength(cx, requested.handle().into(), &mut length)) };
+        for index in 0..length {
+            rooted!(in(cx) let mut request = UndefinedValue());
+            let specifier = unsafe {
+                assert!(JS_GetElement(cx, requested.handle().into(), index, request.handle_mut().into()));
+                jsstring_to_str(cx, GetRequestedModuleSpecifier(cx, request.handle().into()))
+            };
+            match resolve_module_specifier(base_url, &specifier) {
+                Some(url) => descendants.push(url),
+                None => {
+                    rooted!(in(cx) let mut error = UndefinedValue());
+                    let message = format!("Invalid module specifier \"{}\" in {}", specifier, base_url);
+                    unsafe { Error::Type(message).to_jsval(cx, global, error.handle_mut()) };
+                    self.set_error(error.get());
+                    return;
+                },
+            }
+        }
+        *self.descendants.borrow_mut() = descendants;
+    }
+
+    #[allow(unsafe_code)]
+    unsafe fn take_pending_exception(&self, cx: *mut JSContext) {
+        rooted!(in(cx) let mut error = UndefinedValue());
+        if JS_GetPendingException(cx, error.handle_mut()) {
+            JS_ClearPendingException(cx);
+        }
+        self.set_error(error.get());
+    }
+
+    fn set_error(&self, error: JSVal) {
+        self.error.set(error);
+        self.has_error.set(true);
+    }
+}
+
+/// The options the modules of a graph are fetched with.
+///
+/// <https://html.spec.whatwg.org/multipage/#script-fetch-options>
+#[derive(Clone)]
+pub struct ModuleFetchOptions {
+    pub credentials_mode: CredentialsMode,
+    pub cryptographic_nonce: String,
+    /// Only checked for the root of the graph.
+    pub integrity_metadata: String,
+}
+
+impl ModuleFetchOptions {
+    /// <https://html.spec.whatwg.org/multipage/#descendant-script-fetch-options>
+    fn descendant(&self) -> ModuleFetchOptions {
+        ModuleFetchOptions {
+            integrity_metadata: String::new(),
+            .. self.clone()
+        }
+    }
+}
+
+/// <https://html.spec.whatwg.org/multipage/#resolve-a-module-specifier>
+pub fn resolve_module_specifier(base_url: &ServoUrl, specifier: &str) -> Option<ServoUrl> {
+    // Step 1.
+    if let Ok(url) = ServoUrl::parse(specifier) {
+        return Some(url);
+    }
+
+    // Step 2.
+    if !specifier.starts_with("/") && !specifier.starts_with("./") && !specifier.starts_with("../") {
+        return None;
+    }
+
+    // Step 3.
+    base_url.join(specifier).ok()
+}
+
+fn is_javascript(metadata: &Metadata) -> bool {
+    metadata.content_type.as_ref().map_or(false, |&Serde(ContentType(Mime(ref top, ref sub, _)))| {
+        let mime = format!("{}/{}", top, sub).to_ascii_lowercase();
+        SCRIPT_JS_MIMES.contains(&&*mime)
+    })
+}
+
+/// SM callback for the imports of modules, which are looked up in the module map of the
+/// current global, where the graph fetch put them.
+///
+/// <https://html.spec.whatwg.org/multipage/#hostresolveimportedmodule(referencingscriptormodule,-specifier)>
+#[allow(unsafe_code)]
+pub unsafe extern "C" fn host_resolve_imported_module(cx: *mut JSContext,
+                                                      module: HandleObject,
+                                                      specifier: HandleString)
+                                                      -> *mut JSObject {
+    wrap_panic(AssertUnwindSafe(|| {
+        let global = GlobalScope::from_context(cx);
+
+        // Step 1.
+        rooted!(in(cx) let base_url = GetModuleHostDefinedField(module.get()));
+        let base_url = ServoUrl::parse(&jsstring_to_str(cx, base_url.to_string())).unwrap();
+
+        // Steps 2-5.
+        let specifier = jsstring_to_str(cx, specifier.get());
+        let record = resolve_module_specifier(&base_url, &specifier)
+            .and_then(|url| global.module_map_entry(&url))
+            .map_or(ptr::null_mut(), |tree| tree.record.get());
+        if record.is_null() {
+            throw_type_error(cx, &format!("Module \"{}\" wasn't fetched", specifier));
+        }
+        record
+    }), ptr::null_mut())
+}
+
+/// The modules of the graph rooted at `root`, in depth-first pre-order.
+fn module_graph(global: &GlobalScope, root: &Rc<ModuleTree>) -> Vec<Rc<ModuleTree>> {
+    let mut graph = vec![];
+    let mut visited = HashSet::new();
+    let mut stack = vec![root.clone()];
+    while let Some(tree) = stack.pop() {
+        if !visited.insert(tree.url.clone()) {
+            continue;
+        }
+        let descendants = tree.descendants.borrow().iter().rev()
+            .filter_map(|url| global.module_map_entry(url))
+            .collect::<Vec<_>>();
+        stack.extend(descendants);
+        graph.push(tree);
+    }
+    graph
+}
+
+/// Whether the graph rooted at `root` finished fetching, and if so, whether every module of it
+/// could be fetched.
+pub fn module_graph_fetched(global: &GlobalScope, root: &Rc<ModuleTree>) -> Option<Result<(), NetworkError>> {
+    let mut fetching = false;
+    for tree in module_graph(global, root) {
+        match tree.status.get() {
+            ModuleStatus::Fetching => fetching = true,
+            ModuleStatus::FetchFailed => {
+                return Some(Err(NetworkError::Internal(format!("Failed to fetch module {}", tree.url))));
+            },
+            ModuleStatus::Fetched => {},
+        }
+    }
+    if fetching { None } else { Some(Ok(())) }
+}
+
+/// Links the fetched graph rooted at `root`, keeping the first error of its modules, or the
+/// error of the instantiation, as the error to rethrow of `root`.
+///
+/// <https://html.spec.whatwg.org/multipage/#fetch-the-descendants-of-and-link-a-module-script>
+/// steps 5-7.
+#[allow(unsafe_code)]
+pub fn link_module_graph(global: &GlobalScope, root: &Rc<ModuleTree>) {
+    if root.has_error.get() {
+        return;
+    }
+
+    // Step 5.
+    if let Some(errored) = module_graph(global, root).into_iter().find(|tree| tree.has_error.get()) {
+        root.set_error(errored.error.get());
+        return;
+    }
+
+    // Step 6.
+    let cx = global.get_cx();
+    let _ac = JSAutoCompartment::new(cx, global.reflector().get_jsobject().get());
+    rooted!(in(cx) let record = root.record.get());
+    if !unsafe { ModuleInstantiate(cx, record.handle().into()) } {
+        unsafe { root.take_pending_exception(cx) };
+    }
+    maybe_resume_unwind();
+}
+
+/// <https://html.spec.whatwg.org/multipage/#run-a-module-script>
+#[allow(unsafe_code)]
+pub fn run_module_script(global: &GlobalScope, root: &ModuleTree) {
+    let cx = global.get_cx();
+    let _ac = JSAutoCompartment::new(cx, global.reflector().get_jsobject().get());
+    let _aes = AutoEntryScript::new(global);
+
+    // Step 5.
+    if root.has_error.get() {
+        rooted!(in(cx) let error = root.error.get());
+        unsafe {
+            JS_SetPendingException(cx, error.handle());
+            report_pending_exception(cx, true);
+        }
+        return;
+    }
+
+    // Step 6.
+    rooted!(in(cx) let record = root.record.get());
+    if !unsafe { ModuleEvaluate(cx, record.handle().into()) } {
+        unsafe { report_pending_exception(cx, true) };
+    }
+    maybe_resume_unwind();
+}
+
+/// The context of the fetch of a module of the module map of a document.
+struct ModuleContext {
+    document: Trusted<Document>,
+    url: ServoUrl,
+    options: ModuleFetchOptions,
+    data: Vec<u8>,
+    metadata: Option<Metadata>,
+    status: Result<(), NetworkError>,
+}
+
+impl FetchResponseListener for ModuleContext {
+    fn process_request_body(&mut self) {}
+
+    fn process_request_eof(&mut self) {}
+
+    fn process_response(&mut self, metadata: Result<FetchMetadata, NetworkError>) {
+        self.metadata = metadata.ok().map(|meta| match meta {
+            FetchMetadata::Unfiltered(m) => m,
+            FetchMetadata::Filtered { unsafe_, .. } => unsafe_
+        });
+
+        let status_code = self.metadata.as_ref().and_then(|m| m.status.as_ref().map(|&(code, _)| code));
+        self.status = match status_code {
+            Some(code) if code >= 200 && code < 300 => Ok(()),
+            Some(code) => Err(NetworkError::Internal(format!("HTTP error code {}", code))),
+            None => Err(NetworkError::Internal("No http status code received".to_owned())),
+        };
+    }
+
+    fn process_response_chunk(&mut self, mut chunk: Vec<u8>) {
+        if self.status.is_ok() {
+            self.data.append(&mut chunk);
+        }
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#fetch-a-single-module-script>
+    /// steps 9-14.
+    fn process_response_eof(&mut self, response: Result<(), NetworkError>) {
+        let document = self.document.root();
+        let global = document.global();
+        let tree = global.module_map_entry(&self.url).unwrap();
+
+        let metadata = self.metadata.take();
+        match response.and(self.status.clone()) {
+            // Step 9.
+            Ok(()) if metadata.as_ref().map_or(false, is_javascript) => {
+                // Steps 10-12.
+                let source = String::from_utf8_lossy(&self.data);
+                tree.compile(&global, &source, &metadata.unwrap().final_url);
+                tree.status.set(ModuleStatus::Fetched);
+                fetch_descendants(&document, &tree, &self.options);
+            },
+            result => {
+                warn!("error loading module {}: {:?}", self.url, result);
+                tree.status.set(ModuleStatus::FetchFailed);
+            },
+        }
+
+        document.finish_load(LoadType::Script(self.url.clone()));
+        document.process_pending_module_graphs();
+    }
+
+    fn process_csp_violations(&mut self, violations: Vec<Violation>) {
+        self.document.root().global().report_csp_violations(violations, None);
+    }
+}
+
+impl PreInvoke for ModuleContext {}
+
+/// Starts the fetch of the module at `url` into the module map of `document`, unless it is
+/// already there.
+///
+/// <https://html.spec.whatwg.org/multipage/#fetch-a-single-module-script>
+fn fetch_single_module_script(document: &Document,
+                              url: ServoUrl,
+                              referrer: ServoUrl,
+                              destination: Destination,
+                              options: ModuleFetchOptions)
+                              -> Rc<ModuleTree> {
+    let global = document.global();
+
+    // Steps 1-3.
+    if let Some(tree) = global.module_map_entry(&url) {
+        return tree;
+    }
+
+    // Step 4.
+    let tree = Rc::new(ModuleTree::new(url.clone(), ModuleStatus::Fetching));
+    global.set_module_map_entry(url.clone(), tree.clone());
+
+    // Steps 5-8.
+    let request = RequestInit {
+        url: url.clone(),
+        destination: destination,
+        mode: RequestMode::CorsMode,
+        credentials_mode: options.credentials_mode,
+        origin: document.origin().immutable().clone(),
+        pipeline_id: Some(global.pipeline_id()),
+        referrer_url: Some(referrer),
+        referrer_policy: document.get_referrer_policy(),
+        integrity_metadata: options.integrity_metadata.clone(),
+        cryptographic_nonce_metadata: options.cryptographic_nonce.clone(),
+        csp_list: document.get_csp_list(),
+        client_url: Some(document.url()),
+        .. RequestInit::default()
+    };
+
+    let context = Arc::new(Mutex::new(ModuleContext {
+        document: Trusted::new(document),
+        url: url.clone(),
+        options: options,
+        data: vec![],
+        metadata: None,
+        status: Ok(()),
+    }));
+
+    let (action_sender, action_receiver) = ipc::channel().unwrap();
+    let listener = NetworkListener {
+        context: context,
+        task_source: document.window().networking_task_source(),
+        canceller: Some(document.window().task_canceller(TaskSourceName::Networking)),
+    };
+    ROUTER.add_route(action_receiver.to_opaque(), Box::new(move |message| {
+        listener.notify_fetch(message.to().unwrap());
+    }));
+    document.fetch_async(LoadType::Script(url), request, action_sender);
+
+    tree
+}
+
+/// <https://html.spec.whatwg.org/multipage/#fetch-the-descendants-of-a-module-script>
+fn fetch_descendants(document: &Document, tree: &ModuleTree, options: &ModuleFetchOptions) {
+    let descendants = tree.descendants.borrow().clone();
+    for url in descendants {
+        fetch_single_module_script(document, url, tree.url.clone(), Destination::Script, options.descendant());
+    }
+}
+
+/// Starts fetching the graph of an external module script of `document`, whose completion is
+/// told to the document through `Document::process_pending_module_graphs`.
+///
+/// <https://html.spec.whatwg.org/multipage/#fetch-a-module-script-tree>
+pub fn fetch_external_module_script(document: &Document,
+                                    url: ServoUrl,
+                                    options: ModuleFetchOptions)
+                                    -> Rc<ModuleTree> {
+    fetch_single_module_script(document, url, document.url(), Destination::Script, options)
+}
+
+/// Parses an inline module script of `document`, and starts fetching the modules it imports.
+///
+/// <https://html.spec.whatwg.org/multipage/#fetch-an-inline-module-script-graph>
+pub fn fetch_inline_module_script(document: &Document,
+                                  source: &str,
+                                  base_url: ServoUrl,
+                                  options: ModuleFetchOptions)
+                                  -> Rc<ModuleTree> {
+    // Inline module scripts aren't part of the module map, so there is nothing that could
+    // import them and their URL is only used to tell their graph apart.
+    let tree = Rc::new(ModuleTree::new(base_url.clone(), ModuleStatus::Fetched));
+    tree.compile(&document.global(), source, &base_url);
+    fetch_descendants(document, &tree, &options);
+    tree
+}
+
+/// Fetches and links the module graph of a module worker, synchronously, since the worker has
+/// nothing else to do until it is done.
+///
+/// <https://html.spec.whatwg.org/multipage/#fetch-a-module-worker-script-tree>
+pub fn fetch_module_worker_script_graph(global: &GlobalScope,
+                                        url: ServoUrl,
+                                        credentials_mode: CredentialsMode)
+                                        -> Result<Rc<ModuleTree>, NetworkError> {
+    let root = fetch_module_synchronously(global, url, None, Destination::Worker, credentials_mode)?;
+    link_module_graph(global, &root);
+    Ok(root)
+}
+
+fn fetch_module_synchronously(global: &GlobalScope,
+                              url: ServoUrl,
+                              referrer: Option<ServoUrl>,
+                              destination: Destination,
+                              credentials_mode: CredentialsMode)
+                              -> Result<Rc<ModuleTree>, NetworkError> {
+    // Modules being fetched are already in the map, which is how cycles end.
+    if let Some(tree) = global.module_map_entry(&url) {
+        return match tree.status.get() {
+            ModuleStatus::FetchFailed => Err(NetworkError::Internal(format!("Failed to fetch module {}", url))),
+            _ => Ok(tree),
+        };
+    }
+
+    let tree = Rc::new(ModuleTree::new(url.clone(), ModuleStatus::Fetching));
+    global.set_module_map_entry(url.clone(), tree.clone());
+
+    let request = RequestInit {
+        url: url.clone(),
+        destination: destination,
+        mode: RequestMode::CorsMode,
+        credentials_mode: credentials_mode,
+        origin: global.origin().immutable().clone(),
+        pipeline_id: Some(global.pipeline_id()),
+        referrer_url: referrer,
+        csp_list: global.get_csp_list(),
+        .. RequestInit::default()
+    };
+    let (metadata, bytes) = match load_whole_resource(request, &global.core_resource_thread()) {
+        Ok((ref metadata, _)) if !is_javascript(metadata) => {
+            tree.status.set(ModuleStatus::FetchFailed);
+            return Err(NetworkError::Internal(format!("{} isn't a JavaScript module", url)));
+        },
+        Ok(response) => response,
+        Err(error) => {
+            tree.status.set(ModuleStatus::FetchFailed);
+            return Err(error);
+        },
+    };
+
+    // The policies of a module worker come with its top-level script, and apply to the
+    // modules it imports.
+    if destination == Destination::Worker {
+        if let Some(worker) = global.downcast::<WorkerGlobalScope>() {
+            worker.initialize_csp_list(&metadata);
+        }
+    }
+
+    tree.compile(global, &String::from_utf8_lossy(&bytes), &metadata.final_url);
+    tree.status.set(ModuleStatus::Fetched);
+
+    let descendants = tree.descendants.borrow().clone();
+    for descendant in descendants {
+        fetch_module_synchronously(global, descendant, Some(url.clone()), Destination::Script, credentials_mode)?;
+    }
+    Ok(tree)
+}
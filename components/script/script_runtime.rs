@@ -23,13 +23,16 @@ use js::jsapi::{JSGCMode, JSGCParamKey, JS_SetGCParameter, JS_SetGlobalJitCompil
 use js::jsapi::{JSJitCompilerOption, JS_SetOffthreadIonCompilationEnabled, JS_SetParallelParsingEnabled};
 use js::jsapi::{JSObject, SetPreserveWrapperCallback, SetEnqueuePromiseJobCallback};
 use js::jsapi::{SetBuildIdOp, BuildIdCharVector};
+use js::jsapi::{JSSecurityCallbacks, JS_GetRuntime, JS_SetSecurityCallbacks, SetModuleResolveHook};
 use js::jsapi::ContextOptionsRef;
 use js::panic::wrap_panic;
 use js::rust::Runtime as RustRuntime;
 use malloc_size_of::MallocSizeOfOps;
 use microtask::{EnqueuedPromiseCallback, Microtask};
 use msg::constellation_msg::PipelineId;
+use net_traits::csp::CheckResult;
 use profile_traits::mem::{Report, ReportKind, ReportsChan};
+use script_module::host_resolve_imported_module;
 use script_thread::trace_thread;
 use servo_config::opts;
 use servo_config::prefs::PREFS;
@@ -130,6 +133,31 @@ unsafe extern "C" fn enqueue_job(cx: *mut JSContext,
     }), false)
 }
 
+/// SM callback consulted before `eval()` and `new Function()` compile a string.
+/// <https://w3c.github.io/webappsec-csp/#can-compile-strings>
+#[allow(unsafe_code)]
+unsafe extern "C" fn content_security_policy_allows(cx: *mut JSContext) -> bool {
+    wrap_panic(AssertUnwindSafe(|| {
+        let global = GlobalScope::from_context(cx);
+        let csp_list = match global.get_csp_list() {
+            Some(csp_list) => csp_list,
+            None => return true,
+        };
+        // TODO: SpiderMonkey doesn't hand us the source being compiled, so
+        // violation reports are sent without a sample.
+        let (result, violations) = csp_list.is_js_evaluation_allowed("");
+        if !violations.is_empty() {
+            global.report_csp_violations(violations, None);
+        }
+        result == CheckResult::Allowed
+    }), false)
+}
+
+static SECURITY_CALLBACKS: JSSecurityCallbacks = JSSecurityCallbacks {
+    contentSecurityPolicyAllows: Some(content_security_policy_allows),
+    subsumes: None,
+};
+
 #[derive(JSTraceable)]
 pub struct Runtime(RustRuntime);
 
@@ -171,6 +199,10 @@ pub unsafe fn new_rt_and_cx() -> Runtime {
 
     SetEnqueuePromiseJobCallback(cx, Some(enqueue_job), ptr::null_mut());
 
+    JS_SetSecurityCallbacks(cx, &SECURITY_CALLBACKS);
+
+    SetModuleResolveHook(JS_GetRuntime(cx), Some(host_resolve_imported_module));
+
     set_gc_zeal_options(cx);
 
     // Enable or disable the JITs.
@@ -45,9 +45,11 @@ use dom::customelementregistry::{CallbackReaction, CustomElementDefinition, Cust
 use dom::document::{Document, DocumentSource, FocusType, HasBrowsingContext, IsHTMLDocument, TouchEventResult};
 use dom::element::Element;
 use dom::event::{Event, EventBubbles, EventCancelable};
+use dom::eventtarget::EventTarget;
 use dom::globalscope::GlobalScope;
 use dom::htmlanchorelement::HTMLAnchorElement;
 use dom::htmliframeelement::{HTMLIFrameElement, NavigationType};
+use dom::htmlslotelement::HTMLSlotElement;
 use dom::mutationobserver::MutationObserver;
 use dom::node::{Node, NodeDamage, window_from_node, from_untrusted_node_address};
 use dom::performanceentry::PerformanceEntry;
@@ -80,6 +82,7 @@ use msg::constellation_msg::{BrowsingContextId, HistoryStateId, PipelineId};
 use msg::constellation_msg::{PipelineNamespace, TopLevelBrowsingContextId};
 use net_traits::{FetchMetadata, FetchResponseListener, FetchResponseMsg};
 use net_traits::{Metadata, NetworkError, ReferrerPolicy, ResourceThreads};
+use net_traits::csp::CspList;
 use net_traits::image_cache::{ImageCache, PendingImageResponse};
 use net_traits::request::{CredentialsMode, Destination, RedirectMode, RequestInit};
 use net_traits::storage_thread::StorageType;
@@ -89,12 +92,12 @@ use script_layout_interface::message::{self, Msg, NewLayoutThreadInfo, ReflowGoa
 use script_runtime::{CommonScriptMsg, ScriptChan, ScriptThreadEventCategory};
 use script_runtime::{ScriptPort, get_reports, new_rt_and_cx, Runtime};
 use script_traits::{CompositorEvent, ConstellationControlMsg};
-use script_traits::{DiscardBrowsingContext, DocumentActivity, EventResult};
+use script_traits::{DiscardBrowsingContext, DocumentActivity, DocumentSessionState, EventResult};
 use script_traits::{InitialScriptState, JsEvalResult, LayoutMsg, LoadData};
 use script_traits::{MouseButton, MouseEventType, NewLayoutInfo};
 use script_traits::{ProgressiveWebMetricType, Painter, ScriptMsg, ScriptThreadFactory};
 use script_traits::{ScriptToConstellationChan, TimerEvent, TimerSchedulerMsg};
-use script_traits::{TimerSource, TouchEventType, TouchId, UntrustedNodeAddress};
+use script_traits::{TimerSource, TouchEventType, TouchId, TransferredPort, UntrustedNodeAddress};
 use script_traits::{UpdatePipelineIdReason, WindowSizeData, WindowSizeType};
 use script_traits::CompositorEvent::{KeyEvent, MouseButtonEvent, MouseMoveEvent, ResizeEvent, TouchEvent};
 use script_traits::webdriver_msg::WebDriverScriptCommand;
@@ -118,11 +121,13 @@ use std::thread;
 use style::thread_state::{self, ThreadState};
 use task_queue::{QueuedTask, QueuedTaskConversion, TaskQueue};
 use task_source::TaskSourceName;
+use task_source::database_access::DatabaseAccessTaskSource;
 use task_source::dom_manipulation::DOMManipulationTaskSource;
 use task_source::file_reading::FileReadingTaskSource;
 use task_source::history_traversal::HistoryTraversalTaskSource;
 use task_source::networking::NetworkingTaskSource;
 use task_source::performance_timeline::PerformanceTimelineTaskSource;
+use task_source::port_message::PortMessageQueueTaskSource;
 use task_source::remote_event::RemoteEventTaskSource;
 use task_source::user_interaction::UserInteractionTaskSource;
 use task_source::websocket::WebsocketTaskSource;
@@ -180,6 +185,10 @@ struct InProgressLoad {
     navigation_start_precise: u64,
     /// For cancelling the fetch
     canceller: FetchCanceller,
+    /// The history state to activate, if this load restores a session history entry.
+    history_state_id: Option<HistoryStateId>,
+    /// The document state to restore, if this load restores a session history entry.
+    session_state: Option<DocumentSessionState>,
 }
 
 impl InProgressLoad {
@@ -211,6 +220,8 @@ impl InProgressLoad {
             navigation_start: (current_time.sec * 1000 + current_time.nsec as i64 / 1000000) as u64,
             navigation_start_precise: navigation_start_precise,
             canceller: Default::default(),
+            history_state_id: None,
+            session_state: None,
         }
     }
 }
@@ -549,6 +560,9 @@ pub struct ScriptThread {
     /// The unit of related similar-origin browsing contexts' list of MutationObserver objects
     mutation_observers: DomRefCell<Vec<Dom<MutationObserver>>>,
 
+    /// <https://dom.spec.whatwg.org/#signal-slot-list>
+    signal_slots: DomRefCell<Vec<Dom<HTMLSlotElement>>>,
+
     /// A handle to the WebGL thread
     webgl_chan: Option<WebGLPipeline>,
 
@@ -698,6 +712,23 @@ impl ScriptThread {
         })
     }
 
+    pub fn add_signal_slot(slot: &HTMLSlotElement) {
+        SCRIPT_THREAD_ROOT.with(|root| {
+            let script_thread = unsafe { &*root.get().unwrap() };
+            let mut signal_slots = script_thread.signal_slots.borrow_mut();
+            if !signal_slots.iter().any(|s| &**s == slot) {
+                signal_slots.push(Dom::from_ref(slot));
+            }
+        })
+    }
+
+    pub fn take_signal_slots() -> Vec<DomRoot<HTMLSlotElement>> {
+        SCRIPT_THREAD_ROOT.with(|root| {
+            let script_thread = unsafe { &*root.get().unwrap() };
+            script_thread.signal_slots.borrow_mut().drain(..).map(|s| DomRoot::from_ref(&*s)).collect()
+        })
+    }
+
     pub fn mark_document_with_no_blocked_loads(doc: &Document) {
         SCRIPT_THREAD_ROOT.with(|root| {
             let script_thread = unsafe { &*root.get().unwrap() };
@@ -962,6 +993,8 @@ impl ScriptThread {
 
             mutation_observers: Default::default(),
 
+            signal_slots: Default::default(),
+
             layout_to_constellation_chan: state.layout_to_constellation_chan,
 
             webgl_chan: state.webgl_chan,
@@ -1253,6 +1286,7 @@ impl ScriptThread {
                     Reload(id, ..) => Some(id),
                     WebVREvents(id, ..) => Some(id),
                     PaintMetric(..) => None,
+                    SetPrinting(id, ..) => Some(id),
                 }
             },
             MixedMessage::FromDevtools(_) => None,
@@ -1359,8 +1393,8 @@ impl ScriptThread {
                 self.handle_visibility_change_msg(pipeline_id, visible),
             ConstellationControlMsg::NotifyVisibilityChange(parent_pipeline_id, browsing_context_id, visible) =>
                 self.handle_visibility_change_complete_msg(parent_pipeline_id, browsing_context_id, visible),
-            ConstellationControlMsg::PostMessage(pipeline_id, origin, data) =>
-                self.handle_post_message_msg(pipeline_id, origin, data),
+            ConstellationControlMsg::PostMessage(pipeline_id, origin, data, ports) =>
+                self.handle_post_message_msg(pipeline_id, origin, data, ports),
             ConstellationControlMsg::UpdatePipelineId(parent_pipeline_id,
                                                       browsing_context_id,
                                                       new_pipeline_id,
@@ -1398,6 +1432,8 @@ impl ScriptThread {
                 self.handle_webvr_events(pipeline_id, events),
             ConstellationControlMsg::PaintMetric(pipeline_id, metric_type, metric_value) =>
                 self.handle_paint_metric(pipeline_id, metric_type, metric_value),
+            ConstellationControlMsg::SetPrinting(pipeline_id, printing) =>
+                self.handle_set_printing(pipeline_id, printing),
             msg @ ConstellationControlMsg::AttachLayout(..) |
             msg @ ConstellationControlMsg::Viewport(..) |
             msg @ ConstellationControlMsg::SetScrollState(..) |
@@ -1508,6 +1544,12 @@ impl ScriptThread {
         match msg {
             WebDriverScriptCommand::AddCookie(params, reply) =>
                 webdriver_handlers::handle_add_cookie(&*documents, pipeline_id, params, reply),
+            WebDriverScriptCommand::DeleteCookies(name, reply) =>
+                webdriver_handlers::handle_delete_cookies(&*documents, pipeline_id, name, reply),
+            WebDriverScriptCommand::ElementClear(element_id, reply) =>
+                webdriver_handlers::handle_element_clear(&*documents, pipeline_id, element_id, reply),
+            WebDriverScriptCommand::ElementClick(element_id, reply) =>
+                webdriver_handlers::handle_element_click(&*documents, pipeline_id, element_id, reply),
             WebDriverScriptCommand::ExecuteScript(script, reply) =>
                 webdriver_handlers::handle_execute_script(&*documents, pipeline_id, script, reply),
             WebDriverScriptCommand::FindElementCSS(selector, reply) =>
@@ -1526,6 +1568,10 @@ impl ScriptThread {
                 webdriver_handlers::handle_get_name(&*documents, pipeline_id, node_id, reply),
             WebDriverScriptCommand::GetElementAttribute(node_id, name, reply) =>
                 webdriver_handlers::handle_get_attribute(&*documents, pipeline_id, node_id, name, reply),
+            WebDriverScriptCommand::GetElementInViewCenterPoint(node_id, reply) =>
+                webdriver_handlers::handle_get_element_in_view_center_point(&*documents, pipeline_id, node_id, reply),
+            WebDriverScriptCommand::GetElementFromPoint(x, y, reply) =>
+                webdriver_handlers::handle_get_element_from_point(&*documents, pipeline_id, x, y, reply),
             WebDriverScriptCommand::GetElementCSS(node_id, name, reply) =>
                 webdriver_handlers::handle_get_css(&*documents, pipeline_id, node_id, name, reply),
             WebDriverScriptCommand::GetElementRect(node_id, reply) =>
@@ -1534,6 +1580,8 @@ impl ScriptThread {
                 webdriver_handlers::handle_get_text(&*documents, pipeline_id, node_id, reply),
             WebDriverScriptCommand::GetBrowsingContextId(webdriver_frame_id, reply) =>
                 webdriver_handlers::handle_get_browsing_context_id(&*documents, pipeline_id, webdriver_frame_id, reply),
+            WebDriverScriptCommand::GetPageSource(reply) =>
+                webdriver_handlers::handle_get_page_source(&*documents, pipeline_id, reply),
             WebDriverScriptCommand::GetUrl(reply) =>
                 webdriver_handlers::handle_get_url(&*documents, pipeline_id, reply),
             WebDriverScriptCommand::IsEnabled(element_id, reply) =>
@@ -1588,6 +1636,7 @@ impl ScriptThread {
         for &(node_address, ref scroll_offset) in scroll_states {
             if node_address == UntrustedNodeAddress(ptr::null()) {
                 window.update_viewport_for_scroll(-scroll_offset.x, -scroll_offset.y);
+                window.Document().schedule_session_state_report();
             } else {
                 scroll_offsets.insert(node_address, -*scroll_offset);
             }
@@ -1733,16 +1782,21 @@ impl ScriptThread {
         }
     }
 
-    fn handle_post_message_msg(&self, pipeline_id: PipelineId, origin: Option<ImmutableOrigin>, data: Vec<u8>) {
+    fn handle_post_message_msg(&self,
+                               pipeline_id: PipelineId,
+                               origin: Option<ImmutableOrigin>,
+                               data: Vec<u8>,
+                               ports: Vec<TransferredPort>) {
         match { self.documents.borrow().find_window(pipeline_id) } {
             None => return warn!("postMessage after pipeline {} closed.", pipeline_id),
-            Some(window) => window.post_message(origin, StructuredCloneData::Vector(data)),
+            Some(window) => window.post_message(origin, StructuredCloneData::Vector(data), ports),
         }
     }
 
     fn handle_unload_document(&self, pipeline_id: PipelineId) {
         let document = self.documents.borrow().find_document(pipeline_id);
         if let Some(document) = document {
+            document.notify_constellation_session_state();
             document.unload(false, false);
         }
     }
@@ -1879,6 +1933,14 @@ impl ScriptThread {
         WebsocketTaskSource(self.remote_event_task_sender.clone(), pipeline_id)
     }
 
+    pub fn database_access_task_source(&self, pipeline_id: PipelineId) -> DatabaseAccessTaskSource {
+        DatabaseAccessTaskSource(self.remote_event_task_sender.clone(), pipeline_id)
+    }
+
+    pub fn port_message_queue(&self, pipeline_id: PipelineId) -> PortMessageQueueTaskSource {
+        PortMessageQueueTaskSource(self.remote_event_task_sender.clone(), pipeline_id)
+    }
+
     /// Handles a request for the window title.
     fn handle_get_title_msg(&self, pipeline_id: PipelineId) {
         let document = match { self.documents.borrow().find_document(pipeline_id) } {
@@ -2009,6 +2071,7 @@ impl ScriptThread {
             parent: EventInit {
                 bubbles: true,
                 cancelable: false,
+                composed: false,
             },
             propertyName: DOMString::from(name),
             elapsedTime: Finite::new(duration as f32).unwrap(),
@@ -2026,6 +2089,24 @@ impl ScriptThread {
         let document = self.documents.borrow().find_document(pipeline_id);
         if let Some(document) = document {
             self.rebuild_and_force_reflow(&document, ReflowReason::WebFontLoaded);
+            document.update_font_load_status();
+        }
+    }
+
+    /// Lays a document out for printing, after firing `beforeprint`, or lays it out for the
+    /// screen again, before firing `afterprint`.
+    fn handle_set_printing(&self, pipeline_id: PipelineId, printing: bool) {
+        let window = match self.documents.borrow().find_window(pipeline_id) {
+            Some(window) => window,
+            None => return warn!("Print request sent to nonexistent pipeline {}.", pipeline_id),
+        };
+        if printing {
+            window.upcast::<EventTarget>().fire_event(atom!("beforeprint"));
+        }
+        window.set_printing(printing);
+        self.rebuild_and_force_reflow(&window.Document(), ReflowReason::Printing);
+        if !printing {
+            window.upcast::<EventTarget>().fire_event(atom!("afterprint"));
         }
     }
 
@@ -2205,6 +2286,8 @@ impl ScriptThread {
             self.performance_timeline_task_source(incomplete.pipeline_id).clone(),
             self.remote_event_task_source(incomplete.pipeline_id),
             self.websocket_task_source(incomplete.pipeline_id),
+            self.database_access_task_source(incomplete.pipeline_id),
+            self.port_message_queue(incomplete.pipeline_id),
             self.image_cache_channel.clone(),
             self.image_cache.clone(),
             self.resource_threads.clone(),
@@ -2288,6 +2371,13 @@ impl ScriptThread {
 
         window.init_document(&document);
 
+        if let Some(history_state_id) = incomplete.history_state_id {
+            window.History().restore_state(history_state_id);
+        }
+        if let Some(session_state) = incomplete.session_state {
+            document.set_restored_session_state(session_state);
+        }
+
         self.script_sender
             .send((incomplete.pipeline_id, ScriptMsg::ActivateDocument))
             .unwrap();
@@ -2298,6 +2388,9 @@ impl ScriptThread {
         let parse_input = DOMString::new();
 
         document.set_https_state(metadata.https_state);
+        if let Some(ref headers) = metadata.headers {
+            document.append_csp_list(CspList::from_headers(headers));
+        }
         document.set_navigation_start(incomplete.navigation_start_precise);
 
         if is_html_document == IsHTMLDocument::NonHTMLDocument {
@@ -2518,6 +2611,11 @@ impl ScriptThread {
                 }
             }
             None => {
+                // The constellation has to know the state of the document before it is navigated away from.
+                let document = self.documents.borrow().find_document(parent_pipeline_id);
+                if let Some(document) = document {
+                    document.flush_session_state_report();
+                }
                 self.script_sender
                     .send((parent_pipeline_id, ScriptMsg::LoadUrl(load_data, replace)))
                     .unwrap();
@@ -2610,6 +2708,9 @@ impl ScriptThread {
         let context = ParserContext::new(id, load_data.url);
         self.incomplete_parser_contexts.borrow_mut().push((id, context));
 
+        incomplete.history_state_id = load_data.history_state_id;
+        incomplete.session_state = load_data.session_state;
+
         let cancel_chan = incomplete.canceller.initialize();
 
         self.script_sender.send((id, ScriptMsg::InitiateNavigateRequest(req_init, cancel_chan))).unwrap();
@@ -5,9 +5,11 @@
 //! The service worker manager persists the descriptor of any registered service workers.
 //! It also stores an active workers map, which holds descriptors of running service workers.
 //! If an active service worker timeouts, then it removes the descriptor entry from its
-//! active_workers map
+//! active_workers map. Once a registered worker has been installed and activated, it handles
+//! the fetches of the clients in its scope. Registrations are saved to the config dir, and
+//! restored when the manager starts.
 
-use devtools_traits::{DevtoolsPageInfo, ScriptToDevtoolsControlMsg};
+use devtools_traits::{DevtoolsPageInfo, ScriptToDevtoolsControlMsg, WorkerId};
 use dom::abstractworker::WorkerScriptMsg;
 use dom::bindings::structuredclone::StructuredCloneData;
 use dom::serviceworkerglobalscope::{ServiceWorkerGlobalScope, ServiceWorkerScriptMsg};
@@ -15,12 +17,59 @@ use dom::serviceworkerregistration::longest_prefix_match;
 use ipc_channel::ipc::{self, IpcSender};
 use net_traits::{CustomResponseMediator, CoreResourceMsg};
 use script_traits::{ServiceWorkerMsg, ScopeThings, SWManagerMsg, SWManagerSenders, DOMMessage};
+use script_traits::{WorkerGlobalScopeInit, WorkerScriptLoadOrigin};
+use serde_json;
 use servo_channel::{channel, route_ipc_receiver_to_new_servo_receiver, Sender, Receiver};
+use servo_config::opts;
 use servo_config::prefs::PREFS;
 use servo_url::ServoUrl;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::mem;
+use std::path::{Path, PathBuf};
 use std::thread;
 
+/// The file of the config dir where the registered service workers are saved.
+const REGISTRATIONS_FILE: &'static str = "service_worker_registrations.json";
+
+/// A registered service worker, as saved to disk.
+#[derive(Clone, Deserialize, Serialize)]
+struct SavedRegistration {
+    scope: ServoUrl,
+    script_url: ServoUrl,
+    activated: bool,
+}
+
+fn read_registrations(config_dir: &Path) -> Vec<SavedRegistration> {
+    let path = config_dir.join(REGISTRATIONS_FILE);
+    let file = match File::open(&path) {
+        Ok(file) => file,
+        Err(_) => return vec![],
+    };
+    match serde_json::from_reader(file) {
+        Ok(registrations) => registrations,
+        Err(error) => {
+            warn!("Couldn't read the service worker registrations from {}: {}", path.display(), error);
+            vec![]
+        },
+    }
+}
+
+/// Writes the registrations to a temporary file first, so that an interrupted write doesn't
+/// lose the ones saved previously.
+fn write_registrations(config_dir: &Path, registrations: &[SavedRegistration]) -> io::Result<()> {
+    let json = serde_json::to_vec(registrations).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+    let path = config_dir.join(REGISTRATIONS_FILE);
+    let temp_path = path.with_extension("json.tmp");
+    {
+        let mut file = File::create(&temp_path)?;
+        file.write_all(&json)?;
+        file.sync_all()?;
+    }
+    fs::rename(&temp_path, &path)
+}
+
 enum Message {
     FromResource(CustomResponseMediator),
     FromConstellation(ServiceWorkerMsg)
@@ -31,6 +80,13 @@ pub struct ServiceWorkerManager {
     registered_workers: HashMap<ServoUrl, ScopeThings>,
     // map of active service worker descriptors
     active_workers: HashMap<ServoUrl, Sender<ServiceWorkerScriptMsg>>,
+    // scopes of the registered service workers which were installed and activated
+    activated_scopes: HashSet<ServoUrl>,
+    // registrations restored from the config dir, until constellation provides the resources
+    // to run them
+    restored_registrations: Vec<SavedRegistration>,
+    // where registrations are saved
+    config_dir: Option<PathBuf>,
     // own sender to send messages here
     own_sender: IpcSender<ServiceWorkerMsg>,
     // receiver to receive messages from constellation
@@ -43,9 +99,18 @@ impl ServiceWorkerManager {
     fn new(own_sender: IpcSender<ServiceWorkerMsg>,
            from_constellation_receiver: Receiver<ServiceWorkerMsg>,
            resource_port: Receiver<CustomResponseMediator>) -> ServiceWorkerManager {
+        let config_dir = opts::get().config_dir.clone();
+        let restored_registrations = config_dir.as_ref().map_or(vec![], |dir| read_registrations(dir));
+        let activated_scopes = restored_registrations.iter()
+            .filter(|registration| registration.activated)
+            .map(|registration| registration.scope.clone())
+            .collect();
         ServiceWorkerManager {
             registered_workers: HashMap::new(),
             active_workers: HashMap::new(),
+            activated_scopes: activated_scopes,
+            restored_registrations: restored_registrations,
+            config_dir: config_dir,
             own_sender: own_sender,
             own_port: from_constellation_receiver,
             resource_receiver: resource_port
@@ -67,12 +132,10 @@ impl ServiceWorkerManager {
     }
 
     pub fn get_matching_scope(&self, load_url: &ServoUrl) -> Option<ServoUrl> {
-        for scope in self.registered_workers.keys() {
-            if longest_prefix_match(&scope, load_url) {
-                return Some(scope.clone());
-            }
-        }
-        None
+        self.registered_workers.keys()
+            .filter(|scope| longest_prefix_match(scope, load_url))
+            .max_by_key(|scope| scope.path().len())
+            .cloned()
     }
 
     pub fn wakeup_serviceworker(&mut self, scope_url: ServoUrl) -> Option<Sender<ServiceWorkerScriptMsg>> {
@@ -91,12 +154,14 @@ impl ServiceWorkerManager {
                                                                          devtools_sender,
                                                                          page_info));
             };
+            let needs_install = !self.activated_scopes.contains(&scope_url);
             ServiceWorkerGlobalScope::run_serviceworker_scope(scope_things.clone(),
                                                               sender.clone(),
                                                               receiver,
                                                               devtools_receiver,
                                                               self.own_sender.clone(),
-                                                              scope_url.clone());
+                                                              scope_url.clone(),
+                                                              needs_install);
             // We store the activated worker
             self.active_workers.insert(scope_url, sender.clone());
             return Some(sender);
@@ -106,6 +171,56 @@ impl ServiceWorkerManager {
         }
     }
 
+    /// Registers the service workers restored from the config dir, running the ones which
+    /// weren't activated yet so that they get installed.
+    fn register_restored_workers(&mut self, init: WorkerGlobalScopeInit) {
+        let restored_registrations = mem::replace(&mut self.restored_registrations, vec![]);
+        for (index, registration) in restored_registrations.into_iter().enumerate() {
+            if self.registered_workers.contains_key(&registration.scope) {
+                continue;
+            }
+            let mut init = init.clone();
+            init.worker_id = WorkerId(index as u32);
+            init.origin = registration.scope.origin();
+            let scope_things = ScopeThings {
+                script_url: registration.script_url,
+                worker_load_origin: WorkerScriptLoadOrigin {
+                    referrer_url: None,
+                    referrer_policy: None,
+                    pipeline_id: Some(init.pipeline_id),
+                },
+                devtools_chan: None,
+                worker_id: init.worker_id,
+                init: init,
+            };
+            self.registered_workers.insert(registration.scope.clone(), scope_things);
+            if !registration.activated {
+                self.wakeup_serviceworker(registration.scope);
+            }
+        }
+    }
+
+    fn save_registrations(&self) {
+        let config_dir = match self.config_dir {
+            Some(ref config_dir) => config_dir,
+            None => return,
+        };
+        let mut registrations: Vec<SavedRegistration> = self.registered_workers.iter().map(|(scope, scope_things)| {
+            SavedRegistration {
+                scope: scope.clone(),
+                script_url: scope_things.script_url.clone(),
+                activated: self.activated_scopes.contains(scope),
+            }
+        }).collect();
+        // Keep the restored registrations which can't run yet.
+        registrations.extend(self.restored_registrations.iter().filter(|registration| {
+            !self.registered_workers.contains_key(&registration.scope)
+        }).cloned());
+        if let Err(error) = write_registrations(config_dir, &registrations) {
+            warn!("Couldn't save the service worker registrations: {}", error);
+        }
+    }
+
     fn handle_message(&mut self) {
         while let Some(message) = self.receive_message() {
             let should_continue = match message {
@@ -125,7 +240,7 @@ impl ServiceWorkerManager {
     fn forward_message(&self, msg: DOMMessage, sender: &Sender<ServiceWorkerScriptMsg>) {
         let DOMMessage(data) = msg;
         let data = StructuredCloneData::Vector(data);
-        let _ = sender.send(ServiceWorkerScriptMsg::CommonWorker(WorkerScriptMsg::DOMMessage(data)));
+        let _ = sender.send(ServiceWorkerScriptMsg::CommonWorker(WorkerScriptMsg::DOMMessage(data, vec![])));
     }
 
     fn handle_message_from_constellation(&mut self, msg: ServiceWorkerMsg) -> bool {
@@ -134,7 +249,10 @@ impl ServiceWorkerManager {
                 if self.registered_workers.contains_key(&scope) {
                     warn!("ScopeThings for {:?} already stored in SW-Manager", scope);
                 } else {
-                    self.registered_workers.insert(scope, scope_things);
+                    self.registered_workers.insert(scope.clone(), scope_things);
+                    self.save_registrations();
+                    // Run the new worker right away, so that it gets installed.
+                    self.wakeup_serviceworker(scope);
                 }
                 true
             }
@@ -158,13 +276,31 @@ impl ServiceWorkerManager {
                 }
                 true
             }
+            ServiceWorkerMsg::Activated(scope) => {
+                self.activated_scopes.insert(scope);
+                self.save_registrations();
+                true
+            },
+            ServiceWorkerMsg::InstallFailed(scope) => {
+                warn!("ServiceWorker for {:?} failed to install", scope);
+                self.registered_workers.remove(&scope);
+                self.active_workers.remove(&scope);
+                self.save_registrations();
+                true
+            },
+            ServiceWorkerMsg::RestoredWorkersInit(init) => {
+                self.register_restored_workers(init);
+                true
+            },
             ServiceWorkerMsg::Exit => false
         }
     }
 
     fn handle_message_from_resource(&mut self, mediator: CustomResponseMediator) -> bool {
         if serviceworker_enabled() {
-            if let Some(scope) = self.get_matching_scope(&mediator.load_url) {
+            let scope = self.get_matching_scope(&mediator.client_url)
+                .filter(|scope| self.activated_scopes.contains(scope));
+            if let Some(scope) = scope {
                 if self.active_workers.contains_key(&scope) {
                     if let Some(sender) = self.active_workers.get(&scope) {
                         let _ = sender.send(ServiceWorkerScriptMsg::Response(mediator));
@@ -3,11 +3,13 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 
+pub mod database_access;
 pub mod dom_manipulation;
 pub mod file_reading;
 pub mod history_traversal;
 pub mod networking;
 pub mod performance_timeline;
+pub mod port_message;
 pub mod remote_event;
 pub mod user_interaction;
 pub mod websocket;
@@ -23,11 +25,13 @@ use task::{TaskCanceller, TaskOnce};
 // because it doesn't implement TaskSource.
 #[derive(Clone, Eq, Hash, IntoEnumIterator, JSTraceable, PartialEq)]
 pub enum TaskSourceName {
+    DatabaseAccess,
     DOMManipulation,
     FileReading,
     HistoryTraversal,
     Networking,
     PerformanceTimeline,
+    PortMessage,
     UserInteraction,
     RemoteEvent,
     Websocket,
@@ -4,23 +4,29 @@
 
 use cookie_rs::Cookie;
 use dom::bindings::codegen::Bindings::CSSStyleDeclarationBinding::CSSStyleDeclarationMethods;
+use dom::bindings::codegen::Bindings::DOMRectBinding::DOMRectMethods;
 use dom::bindings::codegen::Bindings::DocumentBinding::DocumentMethods;
 use dom::bindings::codegen::Bindings::ElementBinding::ElementMethods;
 use dom::bindings::codegen::Bindings::HTMLElementBinding::HTMLElementMethods;
 use dom::bindings::codegen::Bindings::HTMLInputElementBinding::HTMLInputElementMethods;
 use dom::bindings::codegen::Bindings::HTMLOptionElementBinding::HTMLOptionElementMethods;
+use dom::bindings::codegen::Bindings::HTMLTextAreaElementBinding::HTMLTextAreaElementMethods;
 use dom::bindings::codegen::Bindings::NodeBinding::NodeMethods;
-use dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
+use dom::bindings::codegen::Bindings::WindowBinding::{ScrollBehavior, WindowMethods};
 use dom::bindings::conversions::{ConversionResult, FromJSValConvertible, StringificationBehavior};
 use dom::bindings::inheritance::Castable;
+use dom::bindings::num::Finite;
 use dom::bindings::root::DomRoot;
 use dom::bindings::str::DOMString;
 use dom::element::Element;
+use dom::eventtarget::EventTarget;
 use dom::globalscope::GlobalScope;
 use dom::htmlelement::HTMLElement;
 use dom::htmliframeelement::HTMLIFrameElement;
-use dom::htmlinputelement::HTMLInputElement;
+use dom::htmlinputelement::{HTMLInputElement, InputType};
 use dom::htmloptionelement::HTMLOptionElement;
+use dom::htmlselectelement::HTMLSelectElement;
+use dom::htmltextareaelement::HTMLTextAreaElement;
 use dom::node::{Node, window_from_node};
 use euclid::{Point2D, Rect, Size2D};
 use hyper_serde::Serde;
@@ -31,11 +37,11 @@ use js::rust::HandleValue;
 use msg::constellation_msg::BrowsingContextId;
 use msg::constellation_msg::PipelineId;
 use net_traits::CookieSource::{HTTP, NonHTTP};
-use net_traits::CoreResourceMsg::{GetCookiesDataForUrl, SetCookieForUrl};
+use net_traits::CoreResourceMsg::{DeleteCookiesForUrl, GetCookiesDataForUrl, SetCookieForUrl};
 use net_traits::IpcSend;
 use script_thread::Documents;
 use script_traits::webdriver_msg::{WebDriverFrameId, WebDriverJSError, WebDriverJSResult, WebDriverJSValue};
-use script_traits::webdriver_msg::WebDriverCookieError;
+use script_traits::webdriver_msg::{WebDriverCookieError, WebDriverElementError};
 use servo_url::ServoUrl;
 
 fn find_node_by_unique_id(documents: &Documents,
@@ -47,6 +53,50 @@ fn find_node_by_unique_id(documents: &Documents,
     )
 }
 
+fn find_element_by_unique_id(documents: &Documents,
+                             pipeline: PipelineId,
+                             element_id: String)
+                             -> Result<DomRoot<Element>, WebDriverElementError> {
+    find_node_by_unique_id(documents, pipeline, element_id)
+        .and_then(DomRoot::downcast::<Element>)
+        .ok_or(WebDriverElementError::StaleElementReference)
+}
+
+/// <https://w3c.github.io/webdriver/#dfn-in-view-center-point>
+fn in_view_center_point(element: &Element) -> Option<(i64, i64)> {
+    let window = window_from_node(element);
+
+    // Step 1.
+    let rect = match element.GetClientRects().into_iter().next() {
+        Some(rect) => rect,
+        None => return None,
+    };
+
+    // Steps 2-5.
+    let left = rect.X().min(rect.X() + rect.Width()).max(0.);
+    let right = rect.X().max(rect.X() + rect.Width()).min(window.InnerWidth() as f64);
+    let top = rect.Y().min(rect.Y() + rect.Height()).max(0.);
+    let bottom = rect.Y().max(rect.Y() + rect.Height()).min(window.InnerHeight() as f64);
+    if left >= right || top >= bottom {
+        return None;
+    }
+
+    // Steps 6-8.
+    Some((((left + right) / 2.).floor() as i64, ((top + bottom) / 2.).floor() as i64))
+}
+
+/// <https://w3c.github.io/webdriver/#dfn-scrolls-into-view>
+fn scroll_into_view(element: &Element) {
+    if in_view_center_point(element).is_some() {
+        return;
+    }
+    let window = window_from_node(element);
+    let rect = element.GetBoundingClientRect();
+    window.scroll(rect.X() + window.ScrollX() as f64,
+                  rect.Y() + window.ScrollY() as f64,
+                  ScrollBehavior::Instant);
+}
+
 #[allow(unsafe_code)]
 pub unsafe fn jsval_to_webdriver(cx: *mut JSContext, val: HandleValue) -> WebDriverJSResult {
     if val.get().is_undefined() {
@@ -256,6 +306,22 @@ pub fn handle_add_cookie(documents: &Documents,
     }).unwrap();
 }
 
+// https://w3c.github.io/webdriver/#delete-cookie
+pub fn handle_delete_cookies(documents: &Documents,
+                             pipeline: PipelineId,
+                             name: Option<String>,
+                             reply: IpcSender<Result<(), ()>>) {
+    let document = match documents.find_document(pipeline) {
+        Some(document) => document,
+        None => return reply.send(Err(())).unwrap(),
+    };
+    let url = document.url();
+    let _ = document.window().upcast::<GlobalScope>().resource_threads().send(
+        DeleteCookiesForUrl(url, name)
+    );
+    reply.send(Ok(())).unwrap();
+}
+
 pub fn handle_get_title(documents: &Documents, pipeline: PipelineId, reply: IpcSender<String>) {
     // TODO: Return an error if the pipeline doesn't exist.
     let title = documents.find_document(pipeline)
@@ -402,3 +468,114 @@ pub fn handle_is_selected(documents: &Documents,
         None => Err(())
     }).unwrap();
 }
+
+// https://w3c.github.io/webdriver/#element-click
+pub fn handle_element_click(documents: &Documents,
+                            pipeline: PipelineId,
+                            element_id: String,
+                            reply: IpcSender<Result<Option<(i64, i64)>, WebDriverElementError>>) {
+    reply.send(find_element_by_unique_id(documents, pipeline, element_id).and_then(|element| {
+        // Step 4.
+        if let Some(input) = element.downcast::<HTMLInputElement>() {
+            if input.input_type() == InputType::File {
+                return Err(WebDriverElementError::InvalidArgument);
+            }
+        }
+
+        // Steps 5-6.
+        scroll_into_view(&element);
+        let center_point = match in_view_center_point(&element) {
+            Some(center_point) => center_point,
+            None => return Err(WebDriverElementError::ElementNotInteractable),
+        };
+
+        // Step 8, the option elements are clicked on without a pointer, as they may not be
+        // rendered where they are.
+        if let Some(option) = element.downcast::<HTMLOptionElement>() {
+            let select = element.upcast::<Node>()
+                .ancestors()
+                .filter_map(DomRoot::downcast::<HTMLSelectElement>)
+                .next();
+            if let Some(select) = select {
+                if !element.disabled_state() {
+                    option.SetSelected(true);
+                    let target = select.upcast::<EventTarget>();
+                    target.fire_bubbling_event(atom!("input"));
+                    target.fire_bubbling_event(atom!("change"));
+                }
+                option.upcast::<HTMLElement>().Click();
+                return Ok(None);
+            }
+        }
+
+        // The click itself is dispatched by the WebDriver server, as pointer actions
+        // at the in-view center point of the element.
+        Ok(Some(center_point))
+    })).unwrap();
+}
+
+// https://w3c.github.io/webdriver/#element-clear
+pub fn handle_element_clear(documents: &Documents,
+                            pipeline: PipelineId,
+                            element_id: String,
+                            reply: IpcSender<Result<(), WebDriverElementError>>) {
+    reply.send(find_element_by_unique_id(documents, pipeline, element_id).and_then(|element| {
+        // Step 5.
+        let read_only = match (element.downcast::<HTMLInputElement>(), element.downcast::<HTMLTextAreaElement>()) {
+            (Some(input), _) => input.ReadOnly(),
+            (_, Some(textarea)) => textarea.ReadOnly(),
+            _ => return Err(WebDriverElementError::InvalidElementState),
+        };
+        if read_only || element.disabled_state() {
+            return Err(WebDriverElementError::InvalidElementState);
+        }
+
+        // Steps 6-7.
+        scroll_into_view(&element);
+        if in_view_center_point(&element).is_none() {
+            return Err(WebDriverElementError::ElementNotInteractable);
+        }
+
+        // Step 8, clear a resettable element.
+        let html_element = element.downcast::<HTMLElement>().unwrap();
+        html_element.Focus();
+        if let Some(input) = element.downcast::<HTMLInputElement>() {
+            let _ = input.SetValue(DOMString::new());
+        } else if let Some(textarea) = element.downcast::<HTMLTextAreaElement>() {
+            textarea.SetValue(DOMString::new());
+        }
+        html_element.Blur();
+        Ok(())
+    })).unwrap();
+}
+
+// https://w3c.github.io/webdriver/#get-page-source
+pub fn handle_get_page_source(documents: &Documents,
+                              pipeline: PipelineId,
+                              reply: IpcSender<Result<String, ()>>) {
+    reply.send(documents.find_document(pipeline).ok_or(()).and_then(|document| {
+        match document.GetDocumentElement() {
+            Some(element) => element.GetOuterHTML().map(String::from).map_err(|_| ()),
+            None => Ok(String::new()),
+        }
+    })).unwrap();
+}
+
+pub fn handle_get_element_in_view_center_point(documents: &Documents,
+                                               pipeline: PipelineId,
+                                               element_id: String,
+                                               reply: IpcSender<Result<Option<(i64, i64)>, ()>>) {
+    reply.send(find_element_by_unique_id(documents, pipeline, element_id)
+               .map(|element| in_view_center_point(&element))
+               .map_err(|_| ())).unwrap();
+}
+
+pub fn handle_get_element_from_point(documents: &Documents,
+                                     pipeline: PipelineId,
+                                     x: i64,
+                                     y: i64,
+                                     reply: IpcSender<Option<String>>) {
+    reply.send(documents.find_document(pipeline)
+               .and_then(|doc| doc.ElementFromPoint(Finite::wrap(x as f64), Finite::wrap(y as f64)))
+               .map(|elem| elem.upcast::<Node>().unique_id())).unwrap();
+}
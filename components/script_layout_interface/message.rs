@@ -9,6 +9,7 @@ use gfx_traits::Epoch;
 use ipc_channel::ipc::{IpcReceiver, IpcSender};
 use metrics::PaintTimeMetrics;
 use msg::constellation_msg::PipelineId;
+use net_traits::csp::CspList;
 use net_traits::image_cache::ImageCache;
 use profile_traits::mem::ReportsChan;
 use rpc::LayoutRPC;
@@ -20,10 +21,15 @@ use servo_atoms::Atom;
 use servo_channel::{Receiver, Sender};
 use servo_url::ServoUrl;
 use std::sync::Arc;
+use style::animation::ScriptAnimationState;
 use style::context::QuirksMode;
+use style::dom::OpaqueNode;
+use style::font_face::EffectiveSources;
 use style::properties::PropertyId;
 use style::selector_parser::PseudoElement;
 use style::stylesheets::Stylesheet;
+use style::stylesheets::keyframes_rule::KeyframesAnimation;
+use style::values::computed::font::FamilyName;
 
 /// Asynchronous messages that script can send to layout.
 pub enum Msg {
@@ -98,6 +104,46 @@ pub enum Msg {
 
     /// Send to layout the precise time when the navigation started.
     SetNavigationStart(u64),
+
+    /// Starts running an animation created from script on a node, or updates
+    /// it if it's already running.
+    UpdateScriptAnimation(OpaqueNode, KeyframesAnimation, ScriptAnimationState),
+
+    /// Stops running the animation created from script with the given id on a
+    /// node.
+    RemoveScriptAnimation(OpaqueNode, usize),
+
+    /// Pauses or resumes the CSS animation with the given name on a node.
+    SetCssAnimationPaused(OpaqueNode, Atom, bool),
+
+    /// Loads the font face created by script with the given id, replying
+    /// whether it loaded. The document only uses it while it is in its
+    /// `FontFaceSet`.
+    LoadWebFont(u64, FamilyName, WebFontSource, IpcSender<bool>),
+
+    /// Lets the document use the font face created by script with the given
+    /// id, as it was added to its `FontFaceSet`.
+    AddWebFontToSet(u64, FamilyName),
+
+    /// Stops the document from using the font face created by script with the
+    /// given id, as it was removed from its `FontFaceSet`.
+    RemoveWebFontFromSet(u64),
+
+    /// Replies whether there are system fonts or fonts of `@font-face` rules
+    /// in the given family.
+    HasFontFamily(Atom, IpcSender<bool>),
+
+    /// Tells layout about the Content Security Policy of the document, which
+    /// its Web fonts are loaded with.
+    SetCspList(Option<CspList>),
+}
+
+/// Where a font face created by script is loaded from.
+pub enum WebFontSource {
+    /// The sources listed in the `src` descriptor of the font face.
+    Sources(EffectiveSources),
+    /// The font data the font face was created with.
+    Data(Vec<u8>),
 }
 
 #[derive(Debug, PartialEq)]
@@ -119,6 +165,7 @@ pub enum QueryMsg {
     TextIndexQuery(TrustedNodeAddress, Point2D<f32>),
     NodesFromPointQuery(Point2D<f32>, NodesFromPointQueryType),
     ElementInnerTextQuery(TrustedNodeAddress),
+    CssAnimationsQuery(Option<TrustedNodeAddress>),
 }
 
 /// Any query to perform with this reflow.
@@ -146,7 +193,8 @@ impl ReflowGoal {
                 &QueryMsg::NodeScrollIdQuery(_) |
                 &QueryMsg::ResolvedStyleQuery(..) |
                 &QueryMsg::OffsetParentQuery(_) |
-                &QueryMsg::StyleQuery(_) => false,
+                &QueryMsg::StyleQuery(_) |
+                &QueryMsg::CssAnimationsQuery(_) => false,
             },
         }
     }
@@ -167,7 +215,8 @@ impl ReflowGoal {
                 &QueryMsg::NodeScrollIdQuery(_) |
                 &QueryMsg::ResolvedStyleQuery(..) |
                 &QueryMsg::OffsetParentQuery(_) |
-                &QueryMsg::StyleQuery(_) => false,
+                &QueryMsg::StyleQuery(_) |
+                &QueryMsg::CssAnimationsQuery(_) => false,
             },
         }
     }
@@ -198,6 +247,8 @@ pub struct ScriptReflow {
     pub stylesheets_changed: bool,
     /// The current window size.
     pub window_size: WindowSizeData,
+    /// Whether the document is being laid out to be printed.
+    pub printing: bool,
     /// The channel that we send a notification to.
     pub script_join_chan: Sender<ReflowComplete>,
     /// The goal of this reflow.
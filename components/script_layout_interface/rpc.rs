@@ -6,6 +6,8 @@ use app_units::Au;
 use euclid::{Point2D, Rect};
 use script_traits::UntrustedNodeAddress;
 use servo_arc::Arc;
+use servo_atoms::Atom;
+use style::animation::EffectTiming;
 use style::properties::ComputedValues;
 use style::properties::longhands::overflow_x;
 use webrender_api::ExternalScrollId;
@@ -40,6 +42,8 @@ pub trait LayoutRPC {
     fn nodes_from_point_response(&self) -> Vec<UntrustedNodeAddress>;
     /// Query layout to get the inner text for a given element.
     fn element_inner_text(&self) -> String;
+    /// Requests the CSS animations running on a node, or on the whole document.
+    fn css_animations(&self) -> Vec<CssAnimationResponse>;
 }
 
 pub struct ContentBoxResponse(pub Option<Rect<Au>>);
@@ -76,3 +80,14 @@ pub struct StyleResponse(pub Option<Arc<ComputedValues>>);
 
 #[derive(Clone)]
 pub struct TextIndexResponse(pub Option<usize>);
+
+/// A CSS animation running on a node.
+#[derive(Clone)]
+pub struct CssAnimationResponse {
+    pub node_address: UntrustedNodeAddress,
+    pub name: Atom,
+    pub timing: EffectTiming,
+    /// The current time of the animation, in milliseconds.
+    pub current_time: f64,
+    pub paused: bool,
+}
@@ -458,6 +458,14 @@ pub trait ThreadSafeLayoutElement:
             .clone()
     }
 
+    /// Returns the style of the given eagerly-cascaded pseudo-element of this
+    /// element, like `::first-letter` or `::marker`, if it has one.
+    #[inline]
+    fn eager_pseudo_style(&self, pseudo: &PseudoElement) -> Option<Arc<ComputedValues>> {
+        debug_assert!(pseudo.is_eager());
+        self.style_data().styles.pseudos.get(pseudo).cloned()
+    }
+
     /// Returns the already resolved style of the node.
     ///
     /// This differs from `style(ctx)` in that if the pseudo-element has not yet
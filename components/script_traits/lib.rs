@@ -44,7 +44,7 @@ pub mod webdriver_msg;
 use bluetooth_traits::BluetoothRequest;
 use canvas_traits::webgl::WebGLPipeline;
 use devtools_traits::{DevtoolScriptControlMsg, ScriptToDevtoolsControlMsg, WorkerId};
-use euclid::{Length, Point2D, Vector2D, Rect, TypedSize2D, TypedScale};
+use euclid::{Length, Point2D, Vector2D, Rect, TypedRect, TypedSize2D, TypedScale};
 use gfx_traits::Epoch;
 use hyper::header::Headers;
 use hyper::method::Method;
@@ -77,6 +77,7 @@ use webvr_traits::{WebVREvent, WebVRMsg};
 
 pub use script_msg::{LayoutMsg, ScriptMsg, EventResult, LogEntry};
 pub use script_msg::{ServiceWorkerMsg, ScopeThings, SWManagerMsg, SWManagerSenders, DOMMessage};
+pub use script_msg::{BroadcastMsg, MessagePortMsg, PortMessageTask, TransferredPort};
 
 /// The address of a node. Layout sends these back. They must be validated via
 /// `from_untrusted_node_address` before they can be used, because we do not trust layout.
@@ -155,6 +156,43 @@ pub struct LoadData {
     pub referrer_policy: Option<ReferrerPolicy>,
     /// The referrer URL.
     pub referrer_url: Option<ServoUrl>,
+    /// The history state to activate when the document is created, if this load restores a
+    /// session history entry.
+    pub history_state_id: Option<HistoryStateId>,
+    /// The document state to restore once the document has loaded, if this load restores a
+    /// session history entry.
+    pub session_state: Option<DocumentSessionState>,
+}
+
+/// The state of a top-level document that is persisted along with its session history entry,
+/// so that it can be restored when the entry is loaded again.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DocumentSessionState {
+    /// The scroll offset of the document's viewport.
+    pub scroll_offset: Vector2D<f32>,
+    /// The values of the document's text controls, keyed by their id or name.
+    pub form_values: Vec<(String, String)>,
+}
+
+/// A snapshot of the session history of a top-level browsing context, which can be persisted
+/// and later used to restore that browsing context with `ConstellationMsg::RestoreBrowser`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SessionHistorySnapshot {
+    /// The entries of the session history, from oldest to newest.
+    pub entries: Vec<SessionHistoryEntrySnapshot>,
+    /// The index of the current entry in `entries`.
+    pub current_index: usize,
+}
+
+/// A single entry of a `SessionHistorySnapshot`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SessionHistoryEntrySnapshot {
+    /// The URL of the entry.
+    pub url: ServoUrl,
+    /// The structured clone serialization of the entry's `history.state`, if any.
+    pub history_state: Option<Vec<u8>>,
+    /// The state of the entry's document, if its document reported any.
+    pub document_state: Option<DocumentSessionState>,
 }
 
 /// The result of evaluating a javascript scheme url.
@@ -184,6 +222,8 @@ impl LoadData {
             js_eval_result: None,
             referrer_policy: referrer_policy,
             referrer_url: referrer_url,
+            history_state_id: None,
+            session_state: None,
         }
     }
 }
@@ -296,8 +336,8 @@ pub enum ConstellationControlMsg {
     /// Notifies script thread that a url should be loaded in this iframe.
     /// PipelineId is for the parent, BrowsingContextId is for the nested browsing context
     Navigate(PipelineId, BrowsingContextId, LoadData, bool),
-    /// Post a message to a given window.
-    PostMessage(PipelineId, Option<ImmutableOrigin>, Vec<u8>),
+    /// Post a message to a given window, along with the ports transferred with it.
+    PostMessage(PipelineId, Option<ImmutableOrigin>, Vec<u8>, Vec<TransferredPort>),
     /// Updates the current pipeline ID of a given iframe.
     /// First PipelineId is for the parent, second is the new PipelineId for the frame.
     UpdatePipelineId(
@@ -349,6 +389,8 @@ pub enum ConstellationControlMsg {
     WebVREvents(PipelineId, Vec<WebVREvent>),
     /// Notifies the script thread about a new recorded paint metric.
     PaintMetric(PipelineId, ProgressiveWebMetricType, u64),
+    /// Notifies the script thread that its document starts or stops being printed.
+    SetPrinting(PipelineId, bool),
 }
 
 impl fmt::Debug for ConstellationControlMsg {
@@ -385,6 +427,7 @@ impl fmt::Debug for ConstellationControlMsg {
             Reload(..) => "Reload",
             WebVREvents(..) => "WebVREvents",
             PaintMetric(..) => "PaintMetric",
+            SetPrinting(..) => "SetPrinting",
         };
         write!(formatter, "ConstellationControlMsg::{}", variant)
     }
@@ -684,6 +727,17 @@ pub struct WindowSizeData {
     pub device_pixel_ratio: TypedScale<f32, CSSPixel, DevicePixel>,
 }
 
+/// How a document is split into pages when it is printed.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct PrintLayout {
+    /// The size of a page.
+    pub page_size: TypedSize2D<f32, CSSPixel>,
+    /// The area of a page the document is drawn in, inside the page margins.
+    pub content_rect: TypedRect<f32, CSSPixel>,
+    /// The offsets into the document at which each page starts.
+    pub page_starts: Vec<f32>,
+}
+
 /// The type of window size change.
 #[derive(Clone, Copy, Deserialize, Eq, MallocSizeOf, PartialEq, Serialize)]
 pub enum WindowSizeType {
@@ -707,6 +761,19 @@ pub enum WebDriverCommandMsg {
     ScriptCommand(BrowsingContextId, WebDriverScriptCommand),
     /// Act as if keys were pressed in the browsing context with the given ID.
     SendKeys(BrowsingContextId, Vec<(Key, KeyModifiers, KeyState)>),
+    /// Act as if the mouse was moved to the given point of the viewport.
+    MouseMoveAction(f32, f32),
+    /// Act as if a mouse button was pressed or released at the given point of the viewport.
+    MouseButtonAction(MouseEventType, MouseButton, f32, f32),
+    /// Open a new top-level browsing context, and reply with its ID.
+    NewWindow(IpcSender<TopLevelBrowsingContextId>),
+    /// Close the top-level browsing context with the given ID.
+    CloseWindow(TopLevelBrowsingContextId, IpcSender<()>),
+    /// Get the message of the alert shown by the top-level browsing context with the given ID.
+    GetAlertText(TopLevelBrowsingContextId, IpcSender<Option<String>>),
+    /// Accept or dismiss the user prompt shown by the top-level browsing context with the
+    /// given ID, if any.
+    CloseAlert(TopLevelBrowsingContextId, bool, IpcSender<Result<(), ()>>),
     /// Set the window size.
     SetWindowSize(
         TopLevelBrowsingContextId,
@@ -757,6 +824,8 @@ pub enum ConstellationMsg {
     WebVREvents(Vec<PipelineId>, Vec<WebVREvent>),
     /// Create a new top level browsing context.
     NewBrowser(ServoUrl, TopLevelBrowsingContextId),
+    /// Create a new top level browsing context from a session history snapshot.
+    RestoreBrowser(SessionHistorySnapshot, TopLevelBrowsingContextId),
     /// Close a top level browsing context.
     CloseBrowser(TopLevelBrowsingContextId),
     /// Panic a top level browsing context.
@@ -767,6 +836,10 @@ pub enum ConstellationMsg {
     ForwardEvent(PipelineId, CompositorEvent),
     /// Requesting a change to the onscreen cursor.
     SetCursor(CursorKind),
+    /// Remove all the responses stored in the HTTP caches.
+    ClearCache,
+    /// Start or stop printing the document of a top level browsing context.
+    SetPrinting(TopLevelBrowsingContextId, bool),
 }
 
 impl fmt::Debug for ConstellationMsg {
@@ -788,11 +861,14 @@ impl fmt::Debug for ConstellationMsg {
             LogEntry(..) => "LogEntry",
             WebVREvents(..) => "WebVREvents",
             NewBrowser(..) => "NewBrowser",
+            RestoreBrowser(..) => "RestoreBrowser",
             CloseBrowser(..) => "CloseBrowser",
             SendError(..) => "SendError",
             SelectBrowser(..) => "SelectBrowser",
             ForwardEvent(..) => "ForwardEvent",
             SetCursor(..) => "SetCursor",
+            ClearCache => "ClearCache",
+            SetPrinting(..) => "SetPrinting",
         };
         write!(formatter, "ConstellationMsg::{}", variant)
     }
@@ -4,11 +4,13 @@
 
 use AnimationState;
 use AuxiliaryBrowsingContextLoadInfo;
+use DocumentSessionState;
 use DocumentState;
 use IFrameLoadInfo;
 use IFrameLoadInfoWithData;
 use LayoutControlMsg;
 use LoadData;
+use PrintLayout;
 use WorkerGlobalScopeInit;
 use WorkerScriptLoadOrigin;
 use canvas_traits::canvas::{CanvasMsg, CanvasId};
@@ -17,8 +19,8 @@ use embedder_traits::EmbedderMsg;
 use euclid::{Size2D, TypedSize2D};
 use gfx_traits::Epoch;
 use ipc_channel::ipc::{IpcReceiver, IpcSender};
-use msg::constellation_msg::{BrowsingContextId, PipelineId, TopLevelBrowsingContextId};
-use msg::constellation_msg::{HistoryStateId, TraversalDirection};
+use msg::constellation_msg::{BroadcastChannelRouterId, BrowsingContextId, PipelineId, TopLevelBrowsingContextId};
+use msg::constellation_msg::{HistoryStateId, MessagePortId, TraversalDirection};
 use net_traits::CoreResourceMsg;
 use net_traits::request::RequestInit;
 use net_traits::storage_thread::StorageType;
@@ -35,11 +37,15 @@ use webrender_api::{DeviceIntPoint, DeviceUintSize};
 pub enum LayoutMsg {
     /// Indicates whether this pipeline is currently running animations.
     ChangeRunningAnimationsState(PipelineId, AnimationState),
+    /// Inform the constellation of the size of the contents of a top-level document.
+    ContentSizeChanged(PipelineId, TypedSize2D<f32, CSSPixel>),
     /// Inform the constellation of the size of the iframe's viewport.
     IFrameSizes(Vec<(BrowsingContextId, TypedSize2D<f32, CSSPixel>)>),
     /// Requests that the constellation inform the compositor that it needs to record
     /// the time when the frame with the given ID (epoch) is painted.
     PendingPaintMetric(PipelineId, Epoch),
+    /// Inform the constellation of how a printed top-level document is split into pages.
+    PrintLayout(PipelineId, PrintLayout),
     /// Requests that the constellation inform the compositor of the a cursor change.
     SetCursor(CursorKind),
     /// Notifies the constellation that the viewport has been constrained in some manner
@@ -51,8 +57,10 @@ impl fmt::Debug for LayoutMsg {
         use self::LayoutMsg::*;
         let variant = match *self {
             ChangeRunningAnimationsState(..) => "ChangeRunningAnimationsState",
+            ContentSizeChanged(..) => "ContentSizeChanged",
             IFrameSizes(..) => "IFrameSizes",
             PendingPaintMetric(..) => "PendingPaintMetric",
+            PrintLayout(..) => "PrintLayout",
             SetCursor(..) => "SetCursor",
             ViewportConstrained(..) => "ViewportConstrained",
         };
@@ -99,6 +107,14 @@ pub enum ScriptMsg {
         Option<String>,
         Option<String>,
     ),
+    /// A global now has open BroadcastChannels, broadcasts from the given origin should be
+    /// delivered to it through the given router.
+    NewBroadcastChannelRouter(BroadcastChannelRouterId, IpcSender<BroadcastMsg>, ImmutableOrigin),
+    /// All the BroadcastChannels of a global were closed.
+    RemoveBroadcastChannelRouter(BroadcastChannelRouterId),
+    /// Deliver a broadcast to every same-origin global with open BroadcastChannels,
+    /// except the one it came from.
+    ScheduleBroadcast(BroadcastChannelRouterId, BroadcastMsg),
     /// Indicates whether this pipeline is currently running animations.
     ChangeRunningAnimationsState(AnimationState),
     /// Requests that a new 2D canvas thread be created. (This is done in the constellation because
@@ -133,8 +149,21 @@ pub enum ScriptMsg {
     LoadUrl(LoadData, bool),
     /// Abort loading after sending a LoadUrl message.
     AbortLoadUrl,
-    /// Post a message to the currently active window of a given browsing context.
-    PostMessage(BrowsingContextId, Option<ImmutableOrigin>, Vec<u8>),
+    /// Post a message to the currently active window of a given browsing context,
+    /// along with the ports transferred with it.
+    PostMessage(BrowsingContextId, Option<ImmutableOrigin>, Vec<u8>, Vec<TransferredPort>),
+    /// A MessagePort was created or transferred into this global,
+    /// messages posted to it should now be sent on the given channel.
+    NewMessagePort(MessagePortId, IpcSender<MessagePortMsg>),
+    /// A MessagePort was transferred out of this global, messages posted to it should be
+    /// buffered until the global it was transferred to claims it.
+    MessagePortShipped(MessagePortId),
+    /// Two MessagePorts were entangled, so that each one learns when the other one goes away.
+    EntangleMessagePorts(MessagePortId, MessagePortId),
+    /// Post a message to a MessagePort, wherever it lives.
+    PostMessageToPort(MessagePortId, PortMessageTask),
+    /// A MessagePort was closed or garbage collected, messages posted to it can be dropped.
+    RemoveMessagePort(MessagePortId),
     /// Inform the constellation that a fragment was navigated to and whether or not it was a replacement navigation.
     NavigatedToFragment(ServoUrl, bool),
     /// HTMLIFrameElement Forward or Back traversal.
@@ -145,6 +174,9 @@ pub enum ScriptMsg {
     ReplaceHistoryState(HistoryStateId, ServoUrl),
     /// Gets the length of the joint session history from the constellation.
     JointSessionHistoryLength(IpcSender<u32>),
+    /// Inform the constellation of the state of a top-level document, to be persisted along
+    /// with its session history entry.
+    SetSessionState(DocumentSessionState),
     /// Notification that this iframe should be removed.
     /// Returns a list of pipelines which were closed.
     RemoveIFrame(BrowsingContextId, IpcSender<Vec<PipelineId>>),
@@ -199,6 +231,9 @@ impl fmt::Debug for ScriptMsg {
             ForwardToEmbedder(..) => "ForwardToEmbedder",
             InitiateNavigateRequest(..) => "InitiateNavigateRequest",
             BroadcastStorageEvent(..) => "BroadcastStorageEvent",
+            NewBroadcastChannelRouter(..) => "NewBroadcastChannelRouter",
+            RemoveBroadcastChannelRouter(..) => "RemoveBroadcastChannelRouter",
+            ScheduleBroadcast(..) => "ScheduleBroadcast",
             ChangeRunningAnimationsState(..) => "ChangeRunningAnimationsState",
             CreateCanvasPaintThread(..) => "CreateCanvasPaintThread",
             Focus => "Focus",
@@ -210,11 +245,17 @@ impl fmt::Debug for ScriptMsg {
             LoadUrl(..) => "LoadUrl",
             AbortLoadUrl => "AbortLoadUrl",
             PostMessage(..) => "PostMessage",
+            NewMessagePort(..) => "NewMessagePort",
+            MessagePortShipped(..) => "MessagePortShipped",
+            EntangleMessagePorts(..) => "EntangleMessagePorts",
+            PostMessageToPort(..) => "PostMessageToPort",
+            RemoveMessagePort(..) => "RemoveMessagePort",
             NavigatedToFragment(..) => "NavigatedToFragment",
             TraverseHistory(..) => "TraverseHistory",
             PushHistoryState(..) => "PushHistoryState",
             ReplaceHistoryState(..) => "ReplaceHistoryState",
             JointSessionHistoryLength(..) => "JointSessionHistoryLength",
+            SetSessionState(..) => "SetSessionState",
             RemoveIFrame(..) => "RemoveIFrame",
             SetVisible(..) => "SetVisible",
             VisibilityChangeComplete(..) => "VisibilityChangeComplete",
@@ -259,6 +300,50 @@ pub struct ScopeThings {
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct DOMMessage(pub Vec<u8>);
 
+/// A message posted to a MessagePort.
+///
+/// <https://html.spec.whatwg.org/multipage/#port-message-queue>
+#[derive(Debug, Deserialize, MallocSizeOf, Serialize)]
+pub struct PortMessageTask {
+    /// The serialized message.
+    pub data: Vec<u8>,
+    /// The ports transferred with the message.
+    pub ports: Vec<TransferredPort>,
+}
+
+/// The state of a MessagePort while it is being transferred to another global.
+#[derive(Debug, Deserialize, MallocSizeOf, Serialize)]
+pub struct TransferredPort {
+    /// The id of the port.
+    pub id: MessagePortId,
+    /// The id of the port it is entangled with, if any.
+    pub entangled_with: Option<MessagePortId>,
+    /// The messages that were received by the port but not dispatched yet.
+    pub queued: Vec<PortMessageTask>,
+}
+
+/// Messages sent from the constellation to the global owning a MessagePort.
+#[derive(Debug, Deserialize, Serialize)]
+pub enum MessagePortMsg {
+    /// A message was posted to the given port.
+    Message(MessagePortId, PortMessageTask),
+    /// The port the given port was entangled with was closed, or its global went away.
+    Disentangle(MessagePortId),
+}
+
+/// A message posted to the BroadcastChannels of a given name.
+///
+/// <https://html.spec.whatwg.org/multipage/#dom-broadcastchannel-postmessage>
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BroadcastMsg {
+    /// The origin of the global that posted the message.
+    pub origin: ImmutableOrigin,
+    /// The name of the channels the message was posted to.
+    pub channel_name: String,
+    /// The serialized message.
+    pub data: Vec<u8>,
+}
+
 /// Channels to allow service worker manager to communicate with constellation and resource thread
 pub struct SWManagerSenders {
     /// sender for communicating with constellation
@@ -276,6 +361,14 @@ pub enum ServiceWorkerMsg {
     Timeout(ServoUrl),
     /// Message sent by constellation to forward to a running service worker
     ForwardDOMMessage(DOMMessage, ServoUrl),
+    /// Message sent by a service worker once it has been installed and activated,
+    /// after which it handles the fetches of the clients in its scope
+    Activated(ServoUrl),
+    /// Message sent by a service worker whose installation failed, to unregister it
+    InstallFailed(ServoUrl),
+    /// Message sent by constellation with the base resources of the service workers whose
+    /// registration was restored from a previous session, since no document registered them
+    RestoredWorkersInit(WorkerGlobalScopeInit),
     /// Exit the service worker manager
     Exit,
 }
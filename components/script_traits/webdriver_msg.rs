@@ -22,6 +22,9 @@ pub enum WebDriverScriptCommand {
         Cookie<'static>,
         IpcSender<Result<(), WebDriverCookieError>>,
     ),
+    DeleteCookies(Option<String>, IpcSender<Result<(), ()>>),
+    ElementClear(String, IpcSender<Result<(), WebDriverElementError>>),
+    ElementClick(String, IpcSender<Result<Option<(i64, i64)>, WebDriverElementError>>),
     ExecuteScript(String, IpcSender<WebDriverJSResult>),
     ExecuteAsyncScript(String, IpcSender<WebDriverJSResult>),
     FindElementCSS(String, IpcSender<Result<Option<String>, ()>>),
@@ -31,11 +34,14 @@ pub enum WebDriverScriptCommand {
     GetCookie(String, IpcSender<Vec<Serde<Cookie<'static>>>>),
     GetCookies(IpcSender<Vec<Serde<Cookie<'static>>>>),
     GetElementAttribute(String, String, IpcSender<Result<Option<String>, ()>>),
+    GetElementInViewCenterPoint(String, IpcSender<Result<Option<(i64, i64)>, ()>>),
+    GetElementFromPoint(i64, i64, IpcSender<Option<String>>),
     GetElementCSS(String, String, IpcSender<Result<String, ()>>),
     GetElementRect(String, IpcSender<Result<Rect<f64>, ()>>),
     GetElementTagName(String, IpcSender<Result<String, ()>>),
     GetElementText(String, IpcSender<Result<String, ()>>),
     GetBrowsingContextId(WebDriverFrameId, IpcSender<Result<BrowsingContextId, ()>>),
+    GetPageSource(IpcSender<Result<String, ()>>),
     GetUrl(IpcSender<ServoUrl>),
     IsEnabled(String, IpcSender<Result<bool, ()>>),
     IsSelected(String, IpcSender<Result<bool, ()>>),
@@ -48,6 +54,14 @@ pub enum WebDriverCookieError {
     UnableToSetCookie,
 }
 
+#[derive(Deserialize, Serialize)]
+pub enum WebDriverElementError {
+    StaleElementReference,
+    ElementNotInteractable,
+    InvalidElementState,
+    InvalidArgument,
+}
+
 #[derive(Deserialize, Serialize)]
 pub enum WebDriverJSValue {
     Undefined,
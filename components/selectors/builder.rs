@@ -329,6 +329,10 @@ where
                     *specificity += Specificity::from(selector.specificity());
                 }
             },
+            Component::HostContext(ref selector) => {
+                specificity.class_like_selectors += 1;
+                *specificity += Specificity::from(selector.specificity());
+            },
             Component::ID(..) => {
                 specificity.id_selectors += 1;
             },
@@ -364,6 +368,23 @@ where
                     simple_selector_specificity(builder, &ss, specificity);
                 }
             },
+            Component::Is(ref list) | Component::Has(ref list) => {
+                // https://drafts.csswg.org/selectors-4/#specificity-rules:
+                //
+                //   The specificity of an :is(), :not(), or :has()
+                //   pseudo-class is replaced by the specificity of the most
+                //   specific complex selector in its selector list argument.
+                let max = list
+                    .iter()
+                    .map(|selector| selector.specificity())
+                    .max()
+                    .unwrap_or(0);
+                *specificity += Specificity::from(max);
+            },
+            Component::Where(..) | Component::RelativeSelectorAnchor => {
+                // The specificity of a :where() pseudo-class is replaced by
+                // zero, and the anchor of a relative selector is implicit.
+            },
         }
     }
 
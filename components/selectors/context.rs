@@ -120,6 +120,10 @@ where
     /// The current shadow host we're collecting :host rules for.
     pub current_host: Option<OpaqueElement>,
 
+    /// The element a relative selector inside :has() is anchored at, while we
+    /// are matching it.
+    relative_selector_anchor: Option<OpaqueElement>,
+
     /// Controls how matching for links is handled.
     visited_handling: VisitedHandlingMode,
 
@@ -181,6 +185,7 @@ where
             classes_and_ids_case_sensitivity: quirks_mode.classes_and_ids_case_sensitivity(),
             scope_element: None,
             current_host: None,
+            relative_selector_anchor: None,
             nesting_level: 0,
             in_negation: false,
             pseudo_element_matching_fn: None,
@@ -297,4 +302,25 @@ where
     pub fn shadow_host(&self) -> Option<OpaqueElement> {
         self.current_host.clone()
     }
+
+    /// Runs F with a deeper nesting level, and with `anchor` as the element
+    /// the relative selectors of a :has() pseudo-class are anchored at.
+    #[inline]
+    pub fn nest_for_relative_selector<F, R>(&mut self, anchor: OpaqueElement, f: F) -> R
+    where
+        F: FnOnce(&mut Self) -> R,
+    {
+        let original_anchor = self.relative_selector_anchor.take();
+        self.relative_selector_anchor = Some(anchor);
+        let result = self.nest(f);
+        self.relative_selector_anchor = original_anchor;
+        result
+    }
+
+    /// Returns the element the relative selector we're matching is anchored
+    /// at, if any.
+    #[inline]
+    pub fn relative_selector_anchor(&self) -> Option<OpaqueElement> {
+        self.relative_selector_anchor.clone()
+    }
 }
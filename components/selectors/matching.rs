@@ -40,20 +40,45 @@ bitflags! {
         /// The element has an empty selector, so when a child is appended we
         /// might need to restyle the parent completely.
         const HAS_EMPTY_SELECTOR = 1 << 3;
+
+        /// The element has been matched against a :has() selector in the
+        /// subject compound selector, so changes to its descendants or later
+        /// siblings may change its style.
+        const ANCHORS_RELATIVE_SELECTOR = 1 << 4;
+
+        /// The element has been matched against a :has() selector in a
+        /// compound selector other than the subject one, so changes to its
+        /// descendants or later siblings may change the style of its
+        /// descendants and later siblings too.
+        const ANCHORS_NON_SUBJECT_RELATIVE_SELECTOR = 1 << 5;
+
+        /// The element has been looked at as a possible subject of the
+        /// relative selector of a :has(), so when a child is added or removed
+        /// the anchors of the :has() selectors around it may need restyling.
+        const RELATIVE_SELECTOR_CANDIDATE = 1 << 6;
+
+        /// A child of the element has been matched against a :has() selector
+        /// that looks at its later siblings, so when a child is added or
+        /// removed the children anchoring :has() selectors need restyling.
+        const HAS_RELATIVE_SELECTOR_SIBLING_ANCHOR = 1 << 7;
     }
 }
 
 impl ElementSelectorFlags {
     /// Returns the subset of flags that apply to the element.
     pub fn for_self(self) -> ElementSelectorFlags {
-        self & (ElementSelectorFlags::HAS_EMPTY_SELECTOR)
+        self & (ElementSelectorFlags::HAS_EMPTY_SELECTOR |
+            ElementSelectorFlags::ANCHORS_RELATIVE_SELECTOR |
+            ElementSelectorFlags::ANCHORS_NON_SUBJECT_RELATIVE_SELECTOR |
+            ElementSelectorFlags::RELATIVE_SELECTOR_CANDIDATE)
     }
 
     /// Returns the subset of flags that apply to the parent.
     pub fn for_parent(self) -> ElementSelectorFlags {
         self & (ElementSelectorFlags::HAS_SLOW_SELECTOR |
             ElementSelectorFlags::HAS_SLOW_SELECTOR_LATER_SIBLINGS |
-            ElementSelectorFlags::HAS_EDGE_CHILD_SELECTOR)
+            ElementSelectorFlags::HAS_EDGE_CHILD_SELECTOR |
+            ElementSelectorFlags::HAS_RELATIVE_SELECTOR_SIBLING_ANCHOR)
     }
 }
 
@@ -61,6 +86,7 @@ impl ElementSelectorFlags {
 struct LocalMatchingContext<'a, 'b: 'a, Impl: SelectorImpl> {
     shared: &'a mut MatchingContext<'b, Impl>,
     matches_hover_and_active_quirk: MatchesHoverAndActiveQuirk,
+    rightmost: Rightmost,
 }
 
 #[inline(always)]
@@ -240,6 +266,7 @@ where
     let mut local_context = LocalMatchingContext {
         shared: context,
         matches_hover_and_active_quirk: MatchesHoverAndActiveQuirk::No,
+        rightmost: Rightmost::No,
     };
 
     // Find the end of the selector or the next combinator, then match
@@ -377,6 +404,9 @@ fn matches_hover_and_active_quirk<Impl: SelectorImpl>(
         Component::Class(_) |
         Component::PseudoElement(_) |
         Component::Negation(_) |
+        Component::Is(_) |
+        Component::Where(_) |
+        Component::Has(_) |
         Component::FirstChild |
         Component::LastChild |
         Component::OnlyChild |
@@ -639,6 +669,7 @@ where
     let mut local_context = LocalMatchingContext {
         shared: context,
         matches_hover_and_active_quirk,
+        rightmost,
     };
     iter::once(selector)
         .chain(selector_iter)
@@ -780,6 +811,27 @@ where
                     })
                 })
         },
+        Component::HostContext(ref selector) => {
+            let is_host = context
+                .shared
+                .shadow_host()
+                .map_or(false, |host| host == element.opaque());
+            if !is_host {
+                return false;
+            }
+            context.shared.nest(|context| {
+                let mut current = Some(element.clone());
+                while let Some(ancestor) = current {
+                    if matches_complex_selector(selector.iter(), &ancestor, context, flags_setter) {
+                        return true;
+                    }
+                    current = ancestor
+                        .parent_element()
+                        .or_else(|| ancestor.containing_shadow_host());
+                }
+                false
+            })
+        },
         Component::Scope => match context.shared.scope_element {
             Some(ref scope_element) => element.opaque() == *scope_element,
             None => element.is_root(),
@@ -809,15 +861,155 @@ where
         Component::Negation(ref negated) => context.shared.nest_for_negation(|context| {
             let mut local_context = LocalMatchingContext {
                 matches_hover_and_active_quirk: MatchesHoverAndActiveQuirk::No,
+                rightmost: Rightmost::No,
                 shared: context,
             };
             !negated
                 .iter()
                 .all(|ss| matches_simple_selector(ss, element, &mut local_context, flags_setter))
         }),
+        Component::Is(ref list) | Component::Where(ref list) => context.shared.nest(|context| {
+            list.iter().any(|selector| {
+                matches_complex_selector(selector.iter(), element, context, flags_setter)
+            })
+        }),
+        Component::Has(ref relative_selectors) => {
+            // Only a :has() in the subject compound selector of a selector
+            // that isn't nested in another one affects just the anchor.
+            let is_subject = context.rightmost == Rightmost::Yes && !context.shared.is_nested();
+            let anchor_flag = if is_subject {
+                ElementSelectorFlags::ANCHORS_RELATIVE_SELECTOR
+            } else {
+                ElementSelectorFlags::ANCHORS_NON_SUBJECT_RELATIVE_SELECTOR
+            };
+            flags_setter(element, anchor_flag);
+            context
+                .shared
+                .nest_for_relative_selector(element.opaque(), |context| {
+                    relative_selectors.iter().any(|selector| {
+                        matches_relative_selector(selector, element, context, flags_setter)
+                    })
+                })
+        },
+        Component::RelativeSelectorAnchor => context
+            .shared
+            .relative_selector_anchor()
+            .map_or(false, |anchor| anchor == element.opaque()),
     }
 }
 
+/// Matches a relative selector of a :has() pseudo-class, anchored at
+/// `anchor`.
+///
+/// We look for the subject of the relative selector among the elements it
+/// could possibly match given its combinators, and match the whole selector
+/// against them, which will only succeed if the leftmost compound ends up
+/// being the anchor.
+fn matches_relative_selector<E, F>(
+    selector: &Selector<E::Impl>,
+    anchor: &E,
+    context: &mut MatchingContext<E::Impl>,
+    flags_setter: &mut F,
+) -> bool
+where
+    E: Element,
+    F: FnMut(&E, ElementSelectorFlags),
+{
+    // The anchor is the last component in matching order, so the leading
+    // combinator is right before it.
+    let leading_combinator = selector.combinator_at_match_order(selector.len() - 2);
+    let mut descendant_combinators = false;
+    let mut sibling_combinators = false;
+    for component in selector.iter_raw_match_order().take(selector.len() - 2) {
+        match *component {
+            Component::Combinator(c) if c.is_sibling() => sibling_combinators = true,
+            Component::Combinator(_) => descendant_combinators = true,
+            _ => {},
+        }
+    }
+
+    match leading_combinator {
+        Combinator::Child | Combinator::Descendant => {
+            let children_only = leading_combinator == Combinator::Child && !descendant_combinators;
+            let mut child = anchor.first_element_child();
+            while let Some(element) = child {
+                flags_setter(&element, ElementSelectorFlags::RELATIVE_SELECTOR_CANDIDATE);
+                if matches_complex_selector(selector.iter(), &element, context, flags_setter) {
+                    return true;
+                }
+                if !children_only &&
+                    matches_relative_selector_in_subtree(selector, &element, context, flags_setter)
+                {
+                    return true;
+                }
+                child = element.next_sibling_element();
+            }
+        },
+        Combinator::NextSibling | Combinator::LaterSibling => {
+            let next_sibling_only =
+                leading_combinator == Combinator::NextSibling && !sibling_combinators;
+            flags_setter(anchor, ElementSelectorFlags::HAS_RELATIVE_SELECTOR_SIBLING_ANCHOR);
+            let mut sibling = anchor.next_sibling_element();
+            while let Some(element) = sibling {
+                flags_setter(&element, ElementSelectorFlags::RELATIVE_SELECTOR_CANDIDATE);
+                if matches_complex_selector(selector.iter(), &element, context, flags_setter) {
+                    return true;
+                }
+                if descendant_combinators &&
+                    matches_relative_selector_in_subtree(selector, &element, context, flags_setter)
+                {
+                    return true;
+                }
+                if next_sibling_only {
+                    break;
+                }
+                sibling = element.next_sibling_element();
+            }
+        },
+        Combinator::PseudoElement | Combinator::SlotAssignment => {
+            unreachable!("Relative selectors can't start with {:?}", leading_combinator)
+        },
+    }
+
+    false
+}
+
+/// Matches a relative selector against the descendants of `root`, in tree
+/// order.
+fn matches_relative_selector_in_subtree<E, F>(
+    selector: &Selector<E::Impl>,
+    root: &E,
+    context: &mut MatchingContext<E::Impl>,
+    flags_setter: &mut F,
+) -> bool
+where
+    E: Element,
+    F: FnMut(&E, ElementSelectorFlags),
+{
+    let mut next = root.first_element_child();
+    while let Some(element) = next {
+        flags_setter(&element, ElementSelectorFlags::RELATIVE_SELECTOR_CANDIDATE);
+        if matches_complex_selector(selector.iter(), &element, context, flags_setter) {
+            return true;
+        }
+        next = element.first_element_child().or_else(|| {
+            // Find the next element in tree order that is not a descendant of
+            // this one, without leaving the subtree of the root.
+            let mut current = element;
+            loop {
+                if let Some(sibling) = current.next_sibling_element() {
+                    return Some(sibling);
+                }
+                current = current.parent_element()?;
+                if current.opaque() == root.opaque() {
+                    return None;
+                }
+            }
+        });
+    }
+    false
+}
+
 #[inline(always)]
 fn select_name<'a, T>(is_html: bool, local_name: &'a T, local_name_lower: &'a T) -> &'a T {
     if is_html {
@@ -406,7 +406,7 @@ where
                     return false;
                 }
             },
-            Host(Some(ref selector)) => {
+            Host(Some(ref selector)) | HostContext(ref selector) => {
                 if !selector.visit(visitor) {
                     return false;
                 }
@@ -418,6 +418,13 @@ where
                     }
                 }
             },
+            Is(ref list) | Where(ref list) | Has(ref list) => {
+                for selector in list.iter() {
+                    if !selector.visit(visitor) {
+                        return false;
+                    }
+                }
+            },
 
             AttributeInNoNamespaceExists {
                 ref local_name,
@@ -676,8 +683,9 @@ impl<'a, Impl: 'a + SelectorImpl> SelectorIter<'a, Impl> {
     /// combinators to the left.
     #[inline]
     pub(crate) fn is_featureless_host_selector(&mut self) -> bool {
-        self.all(|component| matches!(*component, Component::Host(..))) &&
-            self.next_sequence().is_none()
+        self.all(|component| {
+            matches!(*component, Component::Host(..) | Component::HostContext(..))
+        }) && self.next_sequence().is_none()
     }
 
     /// Returns remaining count of the simple selectors and combinators in the Selector.
@@ -890,6 +898,36 @@ pub enum Component<Impl: SelectorImpl> {
     ///
     /// See https://github.com/w3c/csswg-drafts/issues/2158
     Host(Option<Selector<Impl>>),
+    /// The `:host-context()` pseudo-class, which matches the shadow host if it,
+    /// or any of its shadow-including ancestors, matches the given compound
+    /// selector:
+    ///
+    /// https://drafts.csswg.org/css-scoping/#host-selector
+    HostContext(Selector<Impl>),
+    /// The `:is()` pseudo-class, which matches if any of the selectors in its
+    /// (forgiving) selector list match:
+    ///
+    /// https://drafts.csswg.org/selectors-4/#matches
+    ///
+    /// Its specificity is the one of its most specific argument.
+    Is(ThinBoxedSlice<Selector<Impl>>),
+    /// The `:where()` pseudo-class, which matches like `:is()`, but always has
+    /// zero specificity:
+    ///
+    /// https://drafts.csswg.org/selectors-4/#zero-matches
+    Where(ThinBoxedSlice<Selector<Impl>>),
+    /// The `:has()` relational pseudo-class, which matches if any of the
+    /// relative selectors in its argument match when anchored at the element:
+    ///
+    /// https://drafts.csswg.org/selectors-4/#relational
+    ///
+    /// Each selector starts with a `RelativeSelectorAnchor`, followed by the
+    /// combinator the relative selector was written with (or a descendant
+    /// combinator if there was none).
+    Has(ThinBoxedSlice<Selector<Impl>>),
+    /// The implicit leftmost compound of a relative selector inside `:has()`,
+    /// which only matches the element `:has()` is being matched against.
+    RelativeSelectorAnchor,
     PseudoElement(Impl::PseudoElement),
 }
 
@@ -1238,6 +1276,38 @@ impl<Impl: SelectorImpl> ToCss for Component<Impl> {
                 }
                 Ok(())
             },
+            HostContext(ref selector) => {
+                dest.write_str(":host-context(")?;
+                selector.to_css(dest)?;
+                dest.write_char(')')
+            },
+            Is(ref list) | Where(ref list) => {
+                match *self {
+                    Is(..) => dest.write_str(":is(")?,
+                    Where(..) => dest.write_str(":where(")?,
+                    _ => unreachable!(),
+                }
+                for (i, selector) in list.iter().enumerate() {
+                    if i != 0 {
+                        dest.write_str(", ")?;
+                    }
+                    selector.to_css(dest)?;
+                }
+                dest.write_char(')')
+            },
+            Has(ref list) => {
+                dest.write_str(":has(")?;
+                for (i, selector) in list.iter().enumerate() {
+                    if i != 0 {
+                        dest.write_str(", ")?;
+                    }
+                    // The anchor serializes to nothing, so we only need to get
+                    // rid of the whitespace before the leading combinator.
+                    dest.write_str(selector.to_css_string().trim_left())?;
+                }
+                dest.write_char(')')
+            },
+            RelativeSelectorAnchor => Ok(()),
             FirstOfType => dest.write_str(":first-of-type"),
             LastOfType => dest.write_str(":last-of-type"),
             OnlyOfType => dest.write_str(":only-of-type"),
@@ -1334,8 +1404,20 @@ where
     P: Parser<'i, Impl = Impl>,
     Impl: SelectorImpl,
 {
-    let mut builder = SelectorBuilder::default();
+    parse_selector_with_builder(parser, input, SelectorBuilder::default())
+}
 
+/// Parses the rest of a selector into a builder that may already contain the
+/// anchor of a relative selector.
+fn parse_selector_with_builder<'i, 't, P, Impl>(
+    parser: &P,
+    input: &mut CssParser<'i, 't>,
+    mut builder: SelectorBuilder<Impl>,
+) -> Result<Selector<Impl>, ParseError<'i, P::Error>>
+where
+    P: Parser<'i, Impl = Impl>,
+    Impl: SelectorImpl,
+{
     let mut has_pseudo_element;
     let mut slotted;
     'outer_loop: loop {
@@ -1810,6 +1892,94 @@ where
     ))
 }
 
+/// Parses the forgiving selector list of `:is()` and `:where()`, in which
+/// invalid selectors are dropped instead of invalidating the whole list.
+///
+/// https://drafts.csswg.org/selectors-4/#typedef-forgiving-selector-list
+fn parse_forgiving_selector_list<'i, 't, P, Impl>(
+    parser: &P,
+    input: &mut CssParser<'i, 't>,
+) -> Result<ThinBoxedSlice<Selector<Impl>>, ParseError<'i, P::Error>>
+where
+    P: Parser<'i, Impl = Impl>,
+    Impl: SelectorImpl,
+{
+    let mut values = vec![];
+    loop {
+        let selector =
+            input.parse_until_before(Delimiter::Comma, |input| Selector::parse(parser, input));
+        if let Ok(selector) = selector {
+            values.push(selector);
+        }
+        match input.next() {
+            Err(_) => break,
+            Ok(&Token::Comma) => continue,
+            Ok(_) => unreachable!(),
+        }
+    }
+    Ok(values.into_boxed_slice().into())
+}
+
+/// Parses the comma-separated list of relative selectors of `:has()`.
+fn parse_relative_selector_list<'i, 't, P, Impl>(
+    parser: &P,
+    input: &mut CssParser<'i, 't>,
+) -> Result<ThinBoxedSlice<Selector<Impl>>, ParseError<'i, P::Error>>
+where
+    P: Parser<'i, Impl = Impl>,
+    Impl: SelectorImpl,
+{
+    input
+        .parse_comma_separated(|input| parse_relative_selector(parser, input))
+        .map(|selectors| selectors.into_boxed_slice().into())
+}
+
+/// Parses a relative selector, that is, a complex selector that may start with
+/// a combinator, and which is anchored at the element `:has()` is matched
+/// against.
+///
+/// https://drafts.csswg.org/selectors-4/#relative
+fn parse_relative_selector<'i, 't, P, Impl>(
+    parser: &P,
+    input: &mut CssParser<'i, 't>,
+) -> Result<Selector<Impl>, ParseError<'i, P::Error>>
+where
+    P: Parser<'i, Impl = Impl>,
+    Impl: SelectorImpl,
+{
+    input.skip_whitespace();
+    let combinator = input
+        .try(|input| -> Result<_, BasicParseError<'i>> {
+            let location = input.current_source_location();
+            match *input.next()? {
+                Token::Delim('>') => Ok(Combinator::Child),
+                Token::Delim('+') => Ok(Combinator::NextSibling),
+                Token::Delim('~') => Ok(Combinator::LaterSibling),
+                ref t => Err(location.new_basic_unexpected_token_error(t.clone())),
+            }
+        }).unwrap_or(Combinator::Descendant);
+
+    let mut builder = SelectorBuilder::default();
+    builder.push_simple_selector(Component::RelativeSelectorAnchor);
+    builder.push_combinator(combinator);
+
+    let location = input.current_source_location();
+    let selector = parse_selector_with_builder(parser, input, builder)?;
+    if selector.has_pseudo_element() {
+        let e = SelectorParseErrorKind::PseudoElementInComplexSelector;
+        return Err(input.new_custom_error(e));
+    }
+    // :has() can't be nested.
+    let has_nested_has = selector
+        .iter_raw_match_order()
+        .any(|component| matches!(*component, Component::Has(..)));
+    if has_nested_has {
+        let e = SelectorParseErrorKind::UnexpectedIdent("has".into());
+        return Err(location.new_custom_error(e));
+    }
+    Ok(selector)
+}
+
 /// simple_selector_sequence
 /// : [ type_selector | universal ] [ HASH | class | attrib | pseudo | negation ]*
 /// | [ HASH | class | attrib | pseudo | negation ]+
@@ -1945,6 +2115,9 @@ where
         "nth-last-child" => return Ok(parse_nth_pseudo_class(input, Component::NthLastChild)?),
         "nth-last-of-type" => return Ok(parse_nth_pseudo_class(input, Component::NthLastOfType)?),
         "host" => return Ok(Component::Host(Some(parse_inner_compound_selector(parser, input)?))),
+        "host-context" if P::parse_host(parser) => {
+            return Ok(Component::HostContext(parse_inner_compound_selector(parser, input)?))
+        },
         "not" => {
             if inside_negation {
                 return Err(input.new_custom_error(
@@ -1953,6 +2126,13 @@ where
             }
             return parse_negation(parser, input)
         },
+        "is" | "where" | "has" if inside_negation => {
+            let e = SelectorParseErrorKind::UnexpectedIdent(name.clone());
+            return Err(input.new_custom_error(e))
+        },
+        "is" => return Ok(Component::Is(parse_forgiving_selector_list(parser, input)?)),
+        "where" => return Ok(Component::Where(parse_forgiving_selector_list(parser, input)?)),
+        "has" => return Ok(Component::Has(parse_relative_selector_list(parser, input)?)),
         _ => {}
     }
     P::parse_non_ts_functional_pseudo_class(parser, name, input).map(Component::NonTSPseudoClass)
@@ -2244,6 +2424,10 @@ pub mod tests {
             true
         }
 
+        fn parse_host(&self) -> bool {
+            true
+        }
+
         fn parse_non_ts_pseudo_class(
             &self,
             location: SourceLocation,
@@ -2820,6 +3004,51 @@ pub mod tests {
         // TODO
         assert!(parse("::slotted(div)::before").is_err());
         assert!(parse("slot::slotted(div,foo)").is_err());
+
+        assert!(parse(":host").is_ok());
+        assert!(parse(":host(.foo)").is_ok());
+        assert!(parse(":host-context(.foo)").is_ok());
+        assert!(parse(":host-context(body.dark) span").is_ok());
+        assert!(parse(":host-context()").is_err());
+        assert!(parse(":host-context(div span)").is_err());
+    }
+
+    #[test]
+    fn test_is_where_has() {
+        let selector = &parse(":is(.foo, #bar)").unwrap().0[0];
+        assert_eq!(selector.specificity(), specificity(1, 0, 0));
+        let selector = &parse(":where(.foo, #bar) span").unwrap().0[0];
+        assert_eq!(selector.specificity(), specificity(0, 0, 1));
+        let selector = &parse("div:is(p span, .foo)").unwrap().0[0];
+        assert_eq!(selector.specificity(), specificity(0, 1, 1));
+
+        // :is() and :where() take forgiving selector lists.
+        assert!(parse_expected(":is(.foo, !!, #bar)", Some(":is(.foo, #bar)")).is_ok());
+        assert!(parse_expected(":where(::before, .foo)", Some(":where(.foo)")).is_ok());
+        assert!(parse_expected(":is()", Some(":is()")).is_ok());
+        assert!(parse(":not(:is(.foo))").is_err());
+
+        let selector = &parse(":has(> img)").unwrap().0[0];
+        assert_eq!(selector.specificity(), specificity(0, 0, 1));
+        assert!(parse(":has(.foo, + #bar)").is_ok());
+        assert!(parse(":has(~ p span)").is_ok());
+        assert!(parse_expected(":has( > img)", Some(":has(> img)")).is_ok());
+        assert!(parse(":has()").is_err());
+        assert!(parse(":has(>)").is_err());
+        assert!(parse(":has(.foo, !!)").is_err());
+        assert!(parse(":has(::before)").is_err());
+        assert!(parse(":has(:has(.foo))").is_err());
+
+        let selector = &parse(":has(+ .foo)").unwrap().0[0];
+        match *selector.iter().next().unwrap() {
+            Component::Has(ref list) => {
+                let mut iter = list[0].iter();
+                assert!(matches!(iter.next(), Some(&Component::Class(..))));
+                assert_eq!(iter.next_sequence(), Some(Combinator::NextSibling));
+                assert_eq!(iter.next(), Some(&Component::RelativeSelectorAnchor));
+            },
+            ref other => panic!("Expected :has(), got {:?}", other),
+        }
     }
 
     #[test]
@@ -2886,5 +3115,10 @@ pub mod tests {
         let mut test_visitor = TestVisitor { seen: vec![] };
         parse("::before:hover").unwrap().0[0].visit(&mut test_visitor);
         assert!(test_visitor.seen.contains(&":hover".into()));
+
+        let mut test_visitor = TestVisitor { seen: vec![] };
+        parse(":is(p :hover) ~ :has(> .foo)").unwrap().0[0].visit(&mut test_visitor);
+        assert!(test_visitor.seen.contains(&":hover".into()));
+        assert!(test_visitor.seen.contains(&".foo".into()));
     }
 }
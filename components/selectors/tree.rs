@@ -51,6 +51,9 @@ pub trait Element: Sized + Clone + Debug {
     /// Skips non-element nodes
     fn next_sibling_element(&self) -> Option<Self>;
 
+    /// Skips non-element nodes
+    fn first_element_child(&self) -> Option<Self>;
+
     fn is_html_element_in_html_document(&self) -> bool;
 
     fn local_name(&self) -> &<Self::Impl as SelectorImpl>::BorrowedLocalName;
@@ -342,6 +342,14 @@ where
                 self.compositor.capture_webrender();
             },
 
+            WindowEvent::SaveScreenshot(path, area) => {
+                self.compositor.save_screenshot(path, area);
+            },
+
+            WindowEvent::PrintToPdf(path) => {
+                self.compositor.print_to_pdf(path);
+            },
+
             WindowEvent::NewBrowser(url, browser_id) => {
                 let msg = ConstellationMsg::NewBrowser(url, browser_id);
                 if let Err(e) = self.constellation_chan.send(msg) {
@@ -352,6 +360,16 @@ where
                 }
             },
 
+            WindowEvent::RestoreBrowser(snapshot, browser_id) => {
+                let msg = ConstellationMsg::RestoreBrowser(snapshot, browser_id);
+                if let Err(e) = self.constellation_chan.send(msg) {
+                    warn!(
+                        "Sending RestoreBrowser message to constellation failed ({:?}).",
+                        e
+                    );
+                }
+            },
+
             WindowEvent::SelectBrowser(ctx) => {
                 let msg = ConstellationMsg::SelectBrowser(ctx);
                 if let Err(e) = self.constellation_chan.send(msg) {
@@ -378,6 +396,13 @@ where
                     warn!("Sending SendError message to constellation failed ({:?}).", e);
                 }
             },
+
+            WindowEvent::ClearCache => {
+                let msg = ConstellationMsg::ClearCache;
+                if let Err(e) = self.constellation_chan.send(msg) {
+                    warn!("Sending ClearCache message to constellation failed ({:?}).", e);
+                }
+            },
         }
     }
 
@@ -31,6 +31,7 @@ use values::computed::box_::TransitionProperty;
 use values::computed::transform::TimingFunction;
 use values::generics::box_::AnimationIterationCount;
 use values::generics::transform::{StepPosition, TimingFunction as GenericTimingFunction};
+use values::generics::transform::TimingKeyword;
 
 
 /// This structure represents a keyframes animation current iteration state.
@@ -71,7 +72,7 @@ pub struct KeyframesAnimationState {
     pub delay: f64,
     /// The current iteration state for the animation.
     pub iteration_state: KeyframesIterationState,
-    /// Werther this animation is paused.
+    /// Whether this animation is paused.
     pub running_state: KeyframesRunningState,
     /// The declared animation direction of this animation.
     pub direction: AnimationDirection,
@@ -190,6 +191,326 @@ impl KeyframesAnimationState {
             KeyframesRunningState::Running => false,
         }
     }
+
+    /// Returns the timing of this animation, in the terms of the Web
+    /// Animations API.
+    pub fn effect_timing(&self) -> EffectTiming {
+        EffectTiming {
+            delay: self.delay * 1000.,
+            // TODO: support animation-fill-mode.
+            fill: FillMode::None,
+            iterations: match self.iteration_state {
+                KeyframesIterationState::Infinite => ::std::f64::INFINITY,
+                KeyframesIterationState::Finite(_, max) => max as f64,
+            },
+            duration: self.duration * 1000.,
+            direction: match self.direction {
+                AnimationDirection::Normal => PlaybackDirection::Normal,
+                AnimationDirection::Reverse => PlaybackDirection::Reverse,
+                AnimationDirection::Alternate => PlaybackDirection::Alternate,
+                AnimationDirection::AlternateReverse => PlaybackDirection::AlternateReverse,
+            },
+            ..EffectTiming::default()
+        }
+    }
+
+    /// Returns the time elapsed since this animation was started in
+    /// milliseconds, given the current time of the timer.
+    ///
+    /// The iterations of infinite animations aren't counted, so their current
+    /// time is relative to the start of their current iteration.
+    pub fn current_time(&self, now: f64) -> f64 {
+        let elapsed = match self.running_state {
+            KeyframesRunningState::Paused(progress) => self.duration * progress,
+            KeyframesRunningState::Running => now - self.started_at,
+        };
+        let iterations = match self.iteration_state {
+            KeyframesIterationState::Infinite => 0.,
+            KeyframesIterationState::Finite(current, _) => current as f64,
+        };
+        (self.delay + iterations * self.duration + elapsed) * 1000.
+    }
+}
+
+/// How an animation effect created from script applies outside of its active
+/// interval.
+///
+/// <https://drafts.csswg.org/web-animations/#fill-behavior>
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FillMode {
+    /// The effect doesn't apply outside of its active interval.
+    None,
+    /// The effect applies after its active interval.
+    Forwards,
+    /// The effect applies before its active interval.
+    Backwards,
+    /// The effect applies both before and after its active interval.
+    Both,
+    /// The default fill mode, which is `none` for keyframe effects.
+    Auto,
+}
+
+/// The direction the iterations of an animation effect created from script
+/// are played in.
+///
+/// <https://drafts.csswg.org/web-animations/#direction-behavior>
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PlaybackDirection {
+    /// All iterations are played forwards.
+    Normal,
+    /// All iterations are played backwards.
+    Reverse,
+    /// Even iterations are played forwards, odd ones backwards.
+    Alternate,
+    /// Even iterations are played backwards, odd ones forwards.
+    AlternateReverse,
+}
+
+/// The phase of an animation effect at a given local time.
+///
+/// <https://drafts.csswg.org/web-animations/#animation-effect-phases-and-states>
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AnimationPhase {
+    /// The local time is unresolved, i.e. the animation isn't playing.
+    Idle,
+    /// The local time is before the active interval.
+    Before,
+    /// The local time is in the active interval.
+    Active,
+    /// The local time is after the active interval.
+    After,
+}
+
+/// The timing properties of an animation effect created from script. All the
+/// times are in milliseconds, as in the Web Animations API.
+///
+/// <https://drafts.csswg.org/web-animations/#the-effecttiming-dictionaries>
+#[derive(Clone, Debug)]
+pub struct EffectTiming {
+    /// The delay before the start of the active interval.
+    pub delay: f64,
+    /// The delay after the end of the active interval.
+    pub end_delay: f64,
+    /// The fill mode of the effect.
+    pub fill: FillMode,
+    /// The iteration the effect starts at, which may be fractional.
+    pub iteration_start: f64,
+    /// The number of iterations, which may be infinite.
+    pub iterations: f64,
+    /// The duration of a single iteration.
+    pub duration: f64,
+    /// The direction the iterations are played in.
+    pub direction: PlaybackDirection,
+    /// The timing function applied to the progress of each iteration.
+    pub easing: TimingFunction,
+}
+
+impl Default for EffectTiming {
+    fn default() -> Self {
+        EffectTiming {
+            delay: 0.,
+            end_delay: 0.,
+            fill: FillMode::Auto,
+            iteration_start: 0.,
+            iterations: 1.,
+            duration: 0.,
+            direction: PlaybackDirection::Normal,
+            easing: GenericTimingFunction::Keyword(TimingKeyword::Linear),
+        }
+    }
+}
+
+/// The timing of an animation effect at a given local time.
+///
+/// <https://drafts.csswg.org/web-animations/#calculating-progress>
+#[derive(Clone, Copy, Debug)]
+pub struct ComputedTiming {
+    /// The phase the effect is in.
+    pub phase: AnimationPhase,
+    /// The time since the start of the active interval, if the effect is in
+    /// effect.
+    pub active_time: Option<f64>,
+    /// The transformed progress in the current iteration, if the effect is in
+    /// effect.
+    pub progress: Option<f64>,
+    /// The current iteration, if the effect is in effect.
+    pub current_iteration: Option<f64>,
+}
+
+impl EffectTiming {
+    /// <https://drafts.csswg.org/web-animations/#active-duration>
+    pub fn active_duration(&self) -> f64 {
+        if self.duration == 0. || self.iterations == 0. {
+            return 0.;
+        }
+        self.duration * self.iterations
+    }
+
+    /// <https://drafts.csswg.org/web-animations/#end-time>
+    pub fn end_time(&self) -> f64 {
+        (self.delay + self.active_duration() + self.end_delay).max(0.)
+    }
+
+    /// Computes the timing of the effect at the given local time, when played
+    /// at the given playback rate.
+    pub fn computed_timing(&self, local_time: Option<f64>, playback_rate: f64) -> ComputedTiming {
+        let mut timing = ComputedTiming {
+            phase: AnimationPhase::Idle,
+            active_time: None,
+            progress: None,
+            current_iteration: None,
+        };
+        let local_time = match local_time {
+            Some(local_time) => local_time,
+            None => return timing,
+        };
+
+        // https://drafts.csswg.org/web-animations/#animation-effect-phases-and-states
+        let active_duration = self.active_duration();
+        let end_time = self.end_time();
+        let before_active_boundary = self.delay.min(end_time).max(0.);
+        let active_after_boundary = (self.delay + active_duration).min(end_time).max(0.);
+        let backwards = playback_rate < 0.;
+        timing.phase = if local_time < before_active_boundary ||
+            (backwards && local_time == before_active_boundary)
+        {
+            AnimationPhase::Before
+        } else if local_time > active_after_boundary ||
+            (!backwards && local_time == active_after_boundary)
+        {
+            AnimationPhase::After
+        } else {
+            AnimationPhase::Active
+        };
+
+        // https://drafts.csswg.org/web-animations/#calculating-the-active-time
+        let fills_backwards = self.fill == FillMode::Backwards || self.fill == FillMode::Both;
+        let fills_forwards = self.fill == FillMode::Forwards || self.fill == FillMode::Both;
+        let active_time = match timing.phase {
+            AnimationPhase::Before if fills_backwards => (local_time - self.delay).max(0.),
+            AnimationPhase::Active => local_time - self.delay,
+            AnimationPhase::After if fills_forwards => {
+                (local_time - self.delay).min(active_duration).max(0.)
+            },
+            _ => return timing,
+        };
+        timing.active_time = Some(active_time);
+
+        // https://drafts.csswg.org/web-animations/#calculating-the-overall-progress
+        let mut overall_progress = if self.duration == 0. {
+            if timing.phase == AnimationPhase::Before {
+                0.
+            } else {
+                self.iterations
+            }
+        } else {
+            active_time / self.duration
+        };
+        overall_progress += self.iteration_start;
+
+        // https://drafts.csswg.org/web-animations/#calculating-the-simple-iteration-progress
+        let mut simple_iteration_progress = if overall_progress.is_infinite() {
+            self.iteration_start % 1.
+        } else {
+            overall_progress % 1.
+        };
+        if simple_iteration_progress == 0. &&
+            timing.phase != AnimationPhase::Before &&
+            active_time == active_duration &&
+            self.iterations != 0.
+        {
+            simple_iteration_progress = 1.;
+        }
+
+        // https://drafts.csswg.org/web-animations/#calculating-the-current-iteration
+        let current_iteration = if timing.phase == AnimationPhase::After &&
+            self.iterations.is_infinite()
+        {
+            ::std::f64::INFINITY
+        } else if simple_iteration_progress == 1. {
+            overall_progress.floor() - 1.
+        } else {
+            overall_progress.floor()
+        };
+        timing.current_iteration = Some(current_iteration);
+
+        // https://drafts.csswg.org/web-animations/#calculating-the-directed-progress
+        let forwards = match self.direction {
+            PlaybackDirection::Normal => true,
+            PlaybackDirection::Reverse => false,
+            PlaybackDirection::Alternate => current_iteration % 2. == 0.,
+            PlaybackDirection::AlternateReverse => current_iteration % 2. != 0.,
+        };
+        let directed_progress = if forwards {
+            simple_iteration_progress
+        } else {
+            1. - simple_iteration_progress
+        };
+
+        // https://drafts.csswg.org/web-animations/#calculating-the-transformed-progress
+        timing.progress = Some(apply_timing_function(
+            self.easing,
+            directed_progress,
+            self.duration / 1000.,
+        ));
+        timing
+    }
+}
+
+/// The state of an animation created from script through the Web Animations
+/// API, which the script thread keeps up to date.
+#[derive(Clone)]
+pub struct ScriptAnimationState {
+    /// An identifier for the animation, unique in its document.
+    pub id: usize,
+    /// The timing of the keyframe effect of the animation.
+    pub timing: EffectTiming,
+    /// The time the animation started at, as returned by the timer, if it's
+    /// playing.
+    pub start_time: Option<f64>,
+    /// The current time the animation is held at, in milliseconds, if it's
+    /// paused or finished.
+    pub hold_time: Option<f64>,
+    /// The playback rate of the animation.
+    pub playback_rate: f64,
+    /// Whether this animation was cancelled.
+    pub expired: bool,
+    /// The style of the element without its animations, needed to compute the
+    /// generated keyframes of the animation. This is only known once the
+    /// element has been restyled.
+    pub cascade_style: Option<Arc<ComputedValues>>,
+}
+
+impl ScriptAnimationState {
+    /// Returns the current time of the animation in milliseconds, given the
+    /// current time of the timer.
+    ///
+    /// <https://drafts.csswg.org/web-animations/#the-current-time-of-an-animation>
+    pub fn current_time(&self, now: f64) -> Option<f64> {
+        self.hold_time.or_else(|| {
+            self.start_time
+                .map(|start_time| (now - start_time) * 1000. * self.playback_rate)
+        })
+    }
+
+    #[inline]
+    fn is_paused(&self) -> bool {
+        self.start_time.is_none()
+    }
+}
+
+impl fmt::Debug for ScriptAnimationState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ScriptAnimationState")
+            .field("id", &self.id)
+            .field("timing", &self.timing)
+            .field("start_time", &self.start_time)
+            .field("hold_time", &self.hold_time)
+            .field("playback_rate", &self.playback_rate)
+            .field("expired", &self.expired)
+            .field("cascade_style", &())
+            .finish()
+    }
 }
 
 impl fmt::Debug for KeyframesAnimationState {
@@ -227,6 +548,9 @@ pub enum Animation {
         Atom,
         KeyframesAnimationState,
     ),
+    /// A keyframes animation created from script, identified by the id in its
+    /// state.
+    Script(OpaqueNode, KeyframesAnimation, ScriptAnimationState),
 }
 
 impl Animation {
@@ -237,6 +561,7 @@ impl Animation {
         match *self {
             Animation::Transition(_, _, _, ref mut expired) => *expired = true,
             Animation::Keyframes(_, _, _, ref mut state) => state.expired = true,
+            Animation::Script(_, _, ref mut state) => state.expired = true,
         }
     }
 
@@ -246,6 +571,7 @@ impl Animation {
         match *self {
             Animation::Transition(_, _, _, expired) => expired,
             Animation::Keyframes(_, _, _, ref state) => state.expired,
+            Animation::Script(_, _, ref state) => state.expired,
         }
     }
 
@@ -255,6 +581,7 @@ impl Animation {
         match *self {
             Animation::Transition(ref node, _, _, _) => node,
             Animation::Keyframes(ref node, _, _, _) => node,
+            Animation::Script(ref node, _, _) => node,
         }
     }
 
@@ -264,6 +591,7 @@ impl Animation {
         match *self {
             Animation::Transition(..) => false,
             Animation::Keyframes(_, _, _, ref state) => state.is_paused(),
+            Animation::Script(_, _, ref state) => state.is_paused(),
         }
     }
 
@@ -272,7 +600,7 @@ impl Animation {
     pub fn is_transition(&self) -> bool {
         match *self {
             Animation::Transition(..) => true,
-            Animation::Keyframes(..) => false,
+            Animation::Keyframes(..) | Animation::Script(..) => false,
         }
     }
 }
@@ -370,39 +698,8 @@ impl PropertyAnimation {
 
     /// Update the given animation at a given point of progress.
     pub fn update(&self, style: &mut ComputedValues, time: f64) {
-        let epsilon = 1. / (200. * (self.duration.seconds() as f64));
-        let progress = match self.timing_function {
-            GenericTimingFunction::CubicBezier { x1, y1, x2, y2 } => {
-                Bezier::new(x1, y1, x2, y2).solve(time, epsilon)
-            },
-            GenericTimingFunction::Steps(steps, StepPosition::Start) => {
-                (time * (steps as f64)).ceil() / (steps as f64)
-            },
-            GenericTimingFunction::Steps(steps, StepPosition::End) => {
-                (time * (steps as f64)).floor() / (steps as f64)
-            },
-            GenericTimingFunction::Frames(frames) => {
-                // https://drafts.csswg.org/css-timing/#frames-timing-functions
-                let mut out = (time * (frames as f64)).floor() / ((frames - 1) as f64);
-                if out > 1.0 {
-                    // FIXME: Basically, during the animation sampling process, the input progress
-                    // should be in the range of [0, 1]. However, |time| is not accurate enough
-                    // here, which means |time| could be larger than 1.0 in the last animation
-                    // frame. (It should be equal to 1.0 exactly.) This makes the output of frames
-                    // timing function jumps to the next frame/level.
-                    // However, this solution is still not correct because |time| is possible
-                    // outside the range of [0, 1] after introducing Web Animations. We should fix
-                    // this problem when implementing web animations.
-                    out = 1.0;
-                }
-                out
-            },
-            GenericTimingFunction::Keyword(keyword) => {
-                let (x1, x2, y1, y2) = keyword.to_bezier();
-                Bezier::new(x1, x2, y1, y2).solve(time, epsilon)
-            },
-        };
-
+        let progress =
+            apply_timing_function(self.timing_function, time, self.duration.seconds() as f64);
         self.property.update(style, progress);
     }
 
@@ -418,6 +715,44 @@ impl PropertyAnimation {
     }
 }
 
+/// Applies a timing function to the given progress of an animation. The
+/// duration of the animation, in seconds, determines the precision of the
+/// result.
+pub fn apply_timing_function(timing_function: TimingFunction, time: f64, duration: f64) -> f64 {
+    let epsilon = 1. / (200. * duration);
+    match timing_function {
+        GenericTimingFunction::CubicBezier { x1, y1, x2, y2 } => {
+            Bezier::new(x1, y1, x2, y2).solve(time, epsilon)
+        },
+        GenericTimingFunction::Steps(steps, StepPosition::Start) => {
+            (time * (steps as f64)).ceil() / (steps as f64)
+        },
+        GenericTimingFunction::Steps(steps, StepPosition::End) => {
+            (time * (steps as f64)).floor() / (steps as f64)
+        },
+        GenericTimingFunction::Frames(frames) => {
+            // https://drafts.csswg.org/css-timing/#frames-timing-functions
+            let mut out = (time * (frames as f64)).floor() / ((frames - 1) as f64);
+            if out > 1.0 {
+                // FIXME: Basically, during the animation sampling process, the input progress
+                // should be in the range of [0, 1]. However, |time| is not accurate enough
+                // here, which means |time| could be larger than 1.0 in the last animation
+                // frame. (It should be equal to 1.0 exactly.) This makes the output of frames
+                // timing function jumps to the next frame/level.
+                // However, this solution is still not correct because |time| is possible
+                // outside the range of [0, 1] after introducing Web Animations. We should fix
+                // this problem when implementing web animations.
+                out = 1.0;
+            }
+            out
+        },
+        GenericTimingFunction::Keyword(keyword) => {
+            let (x1, x2, y1, y2) = keyword.to_bezier();
+            Bezier::new(x1, x2, y1, y2).solve(time, epsilon)
+        },
+    }
+}
+
 /// Inserts transitions into the queue of running animations as applicable for
 /// the given style difference. This is called from the layout worker threads.
 /// Returns true if any animations were kicked off and false otherwise.
@@ -847,6 +1182,143 @@ pub fn update_style_for_animation<E>(
             );
             *style = new_style;
         },
+        Animation::Script(_, ref animation, ref state) => {
+            debug!("update_style_for_animation: script animation found: {:?}", state);
+            let cascade_style = match state.cascade_style {
+                Some(ref cascade_style) => cascade_style,
+                None => return,
+            };
+
+            let now = context.timer.seconds();
+            let timing = state
+                .timing
+                .computed_timing(state.current_time(now), state.playback_rate);
+            let progress = match timing.progress {
+                Some(progress) => progress,
+                None => return,
+            };
+
+            if animation.steps.is_empty() {
+                return;
+            }
+
+            update_style_for_keyframes_progress::<E>(
+                context,
+                animation,
+                progress,
+                state.timing.duration / 1000.,
+                cascade_style,
+                style,
+                font_metrics_provider,
+            );
+        },
+    }
+}
+
+/// Updates a style for a keyframes animation created from script, given the
+/// progress in the current iteration, which already takes in account the
+/// direction and the easing of the animation.
+///
+/// <https://drafts.csswg.org/web-animations/#the-effect-value-of-a-keyframe-animation-effect>
+fn update_style_for_keyframes_progress<E>(
+    context: &SharedStyleContext,
+    animation: &KeyframesAnimation,
+    progress: f64,
+    duration: f64,
+    cascade_style: &Arc<ComputedValues>,
+    style: &mut Arc<ComputedValues>,
+    font_metrics_provider: &FontMetricsProvider,
+) where
+    E: TElement,
+{
+    // There are always at least two steps, since the first and last ones are
+    // generated if needed. A progress out of the [0, 1] range, which easings
+    // can produce, extrapolates from the first or last two keyframes.
+    debug_assert!(animation.steps.len() >= 2);
+    let target_keyframe_position = match animation
+        .steps
+        .iter()
+        .position(|step| progress as f32 <= step.start_percentage.0)
+    {
+        Some(0) => 1,
+        Some(position) => position,
+        None => animation.steps.len() - 1,
+    };
+    let last_keyframe = &animation.steps[target_keyframe_position - 1];
+    let target_keyframe = &animation.steps[target_keyframe_position];
+
+    let from_style = compute_style_for_animation_step::<E>(
+        context,
+        last_keyframe,
+        cascade_style,
+        cascade_style,
+        font_metrics_provider,
+    );
+    let target_style = compute_style_for_animation_step::<E>(
+        context,
+        target_keyframe,
+        &from_style,
+        cascade_style,
+        font_metrics_provider,
+    );
+
+    let relative_timespan =
+        (target_keyframe.start_percentage.0 - last_keyframe.start_percentage.0) as f64;
+    let relative_progress = if relative_timespan == 0. {
+        1.
+    } else {
+        (progress - last_keyframe.start_percentage.0 as f64) / relative_timespan
+    };
+
+    // Unlike in CSS animations, keyframes are interpolated linearly unless
+    // they specify their own easing.
+    let timing_function = if last_keyframe.declared_timing_function {
+        from_style.get_box().animation_timing_function_at(0)
+    } else {
+        GenericTimingFunction::Keyword(TimingKeyword::Linear)
+    };
+    let relative_progress =
+        apply_timing_function(timing_function, relative_progress, relative_timespan * duration);
+
+    let mut new_style = (*style).clone();
+    for property in animation.properties_changed.iter() {
+        // NB: Unlike `PropertyAnimation`, this also applies properties whose
+        // value doesn't change between the two keyframes.
+        if let Some(animated) = AnimatedProperty::from_longhand(property, &from_style, &target_style)
+        {
+            animated.update(Arc::make_mut(&mut new_style), relative_progress);
+        }
+    }
+    *style = new_style;
+}
+
+/// Stores the style of a node without its animations in the script animations
+/// running on it, since they need it to compute their generated keyframes.
+#[cfg(feature = "servo")]
+pub fn update_script_animations_cascade_style(
+    context: &SharedStyleContext,
+    node: OpaqueNode,
+    style: &Arc<ComputedValues>,
+) {
+    let has_script_animations = context
+        .running_animations
+        .read()
+        .get(&node)
+        .map_or(false, |animations| {
+            animations.iter().any(|animation| match *animation {
+                Animation::Script(..) => true,
+                _ => false,
+            })
+        });
+    if !has_script_animations {
+        return;
+    }
+
+    let mut all_running_animations = context.running_animations.write();
+    for animation in all_running_animations.get_mut(&node).unwrap() {
+        if let Animation::Script(_, _, ref mut state) = *animation {
+            state.cascade_style = Some(style.clone());
+        }
     }
 }
 
@@ -529,7 +529,7 @@ pub trait TElement:
         // optimize out leaf elements.
 
         // ::first-letter and ::first-line are only supported for block-inside
-        // things, and only in Gecko, not Servo.  Unfortunately, Gecko has
+        // things, which Servo's layout elements check themselves.  Unfortunately, Gecko has
         // block-inside things that might have any computed display value due to
         // things like fieldsets, legends, etc.  Need to figure out how this
         // should work.
@@ -70,7 +70,9 @@ bitflags! {
         /// <https://html.spec.whatwg.org/multipage/#selector-optional>
         const IN_OPTIONAL_STATE = 1 << 22;
         /// <https://html.spec.whatwg.org/multipage/#selector-read-write>
-        const IN_READ_WRITE_STATE = 1 << 22;
+        ///
+        /// This is the same state as `IN_MOZ_READWRITE_STATE`.
+        const IN_READ_WRITE_STATE = 1 << 30;
         /// <https://html.spec.whatwg.org/multipage/#selector-defined>
         const IN_DEFINED_STATE = 1 << 23;
         /// <https://html.spec.whatwg.org/multipage/#selector-visited>
@@ -107,6 +109,8 @@ bitflags! {
         /// Non-standard & undocumented.
         const IN_INCREMENT_SCRIPT_LEVEL_STATE = 1 << 38;
         /// Non-standard: https://developer.mozilla.org/en-US/docs/Web/CSS/:-moz-focusring
+        ///
+        /// Servo uses it for <https://drafts.csswg.org/selectors-4/#the-focus-visible-pseudo>.
         const IN_FOCUSRING_STATE = 1 << 39;
         /// Non-standard & undocumented.
         const IN_HANDLER_CLICK_TO_PLAY_STATE = 1 << 40;
@@ -223,12 +223,20 @@ pub struct EffectiveSources(Vec<Source>);
 
 #[cfg(feature = "servo")]
 impl<'a> FontFace<'a> {
-    /// Returns the list of effective sources for that font-face, that is the
-    /// sources which don't list any format hint, or the ones which list at
-    /// least "truetype" or "opentype".
+    /// Returns the list of effective sources for that font-face.
     pub fn effective_sources(&self) -> EffectiveSources {
+        EffectiveSources::new(self.sources())
+    }
+}
+
+#[cfg(feature = "servo")]
+impl EffectiveSources {
+    /// Returns the effective sources among the given ones, that is the sources
+    /// which don't list any format hint, or the ones which list at least
+    /// "truetype" or "opentype".
+    pub fn new(sources: &[Source]) -> EffectiveSources {
         EffectiveSources(
-            self.sources()
+            sources
                 .iter()
                 .rev()
                 .filter(|source| {
@@ -9,7 +9,8 @@ use context::QuirksMode;
 use element_state::{DocumentState, ElementState};
 use fallible::FallibleVec;
 use hashglobe::FailedAllocationError;
-use selector_map::{MaybeCaseInsensitiveHashMap, SelectorMap, SelectorMapEntry};
+use selector_map::{MaybeCaseInsensitiveHashMap, PrecomputedHashSet, SelectorMap};
+use selector_map::SelectorMapEntry;
 use selector_parser::SelectorImpl;
 use selectors::attr::NamespaceConstraint;
 use selectors::parser::{Combinator, Component};
@@ -47,6 +48,13 @@ pub struct Dependency {
 
     /// The offset into the selector that we should match on.
     pub selector_offset: usize,
+
+    /// The dependency of the compound selector that contains the `:is()` or
+    /// `:where()` pseudo-class this selector is nested in, if any.
+    ///
+    /// When the nested selector matches completely, the invalidation carries on
+    /// from this dependency instead of invalidating the element that matched.
+    pub parent: Option<Box<Dependency>>,
 }
 
 /// The kind of elements down the tree this dependency may affect.
@@ -139,6 +147,70 @@ pub struct DocumentStateDependency {
     pub state: DocumentState,
 }
 
+/// The features that the relative selectors inside `:has()` pseudo-classes
+/// depend on.
+///
+/// When any of these change for an element, the `:has()` selectors anchored at
+/// its ancestors or at their earlier siblings may start or stop matching. See
+/// the `relative_selector` module.
+#[derive(Clone, Debug, Default, MallocSizeOf)]
+pub struct RelativeSelectorDependencies {
+    /// The classes the relative selectors are affected by.
+    pub classes: PrecomputedHashSet<Atom>,
+    /// The IDs the relative selectors are affected by.
+    pub ids: PrecomputedHashSet<Atom>,
+    /// The element states the relative selectors are affected by.
+    pub state: ElementState,
+    /// Whether the relative selectors are affected by other attributes,
+    /// including attribute selectors for `class` or `id`.
+    pub other_attributes: bool,
+}
+
+impl RelativeSelectorDependencies {
+    /// Returns whether no relative selector depends on anything that can
+    /// change for an element.
+    pub fn is_empty(&self) -> bool {
+        self.classes.is_empty() &&
+            self.ids.is_empty() &&
+            self.state.is_empty() &&
+            !self.other_attributes
+    }
+
+    fn clear(&mut self) {
+        self.classes.clear();
+        self.ids.clear();
+        self.state = ElementState::empty();
+        self.other_attributes = false;
+    }
+
+    fn note_selector(
+        &mut self,
+        selector: &Selector<SelectorImpl>,
+        document_state: &mut DocumentState,
+    ) -> Result<(), FailedAllocationError> {
+        let mut visitor = CompoundSelectorDependencyCollector {
+            classes: SmallVec::new(),
+            ids: SmallVec::new(),
+            state: ElementState::empty(),
+            document_state,
+            other_attributes: false,
+            has_id_attribute_selectors: false,
+            has_class_attribute_selectors: false,
+        };
+        selector.visit(&mut visitor);
+
+        for class in visitor.classes {
+            self.classes.try_insert(class)?;
+        }
+        for id in visitor.ids {
+            self.ids.try_insert(id)?;
+        }
+        self.state |= visitor.state;
+        self.other_attributes |= visitor.other_attributes;
+        Ok(())
+    }
+}
+
 /// A map where we store invalidations.
 ///
 /// This is slightly different to a SelectorMap, in the sense of that the same
@@ -171,6 +243,9 @@ pub struct InvalidationMap {
     /// `other_attribute_affecting_selectors` too even if only the `id` has
     /// changed.
     pub has_id_attribute_selectors: bool,
+    /// The features the relative selectors of `:has()` pseudo-classes depend
+    /// on.
+    pub relative_selector_dependencies: RelativeSelectorDependencies,
 }
 
 impl InvalidationMap {
@@ -184,6 +259,7 @@ impl InvalidationMap {
             other_attribute_affecting_selectors: SelectorMap::new(),
             has_class_attribute_selectors: false,
             has_id_attribute_selectors: false,
+            relative_selector_dependencies: RelativeSelectorDependencies::default(),
         }
     }
 
@@ -209,6 +285,7 @@ impl InvalidationMap {
         self.other_attribute_affecting_selectors.clear();
         self.has_id_attribute_selectors = false;
         self.has_class_attribute_selectors = false;
+        self.relative_selector_dependencies.clear();
     }
 
     /// Adds a selector to this `InvalidationMap`.  Returns Err(..) to
@@ -220,82 +297,130 @@ impl InvalidationMap {
     ) -> Result<(), FailedAllocationError> {
         debug!("InvalidationMap::note_selector({:?})", selector);
 
+        let mut document_state = DocumentState::empty();
+        self.note_selector_with_parent(selector, quirks_mode, None, &mut document_state)?;
+
+        if !document_state.is_empty() {
+            self.document_state_selectors
+                .try_push(DocumentStateDependency {
+                    state: document_state,
+                    selector: selector.clone(),
+                })?;
+        }
+
+        Ok(())
+    }
+
+    /// Adds the dependencies of `selector`, which is nested in the compound
+    /// selector `parent` points to, if any.
+    ///
+    /// Document state dependencies are collected in `document_state`, since
+    /// they always apply to the outermost selector.
+    fn note_selector_with_parent(
+        &mut self,
+        selector: &Selector<SelectorImpl>,
+        quirks_mode: QuirksMode,
+        parent: Option<&Dependency>,
+        document_state: &mut DocumentState,
+    ) -> Result<(), FailedAllocationError> {
         let mut iter = selector.iter();
         let mut combinator;
         let mut index = 0;
 
-        let mut document_state = DocumentState::empty();
+        let dependency = |selector_offset| Dependency {
+            selector: selector.clone(),
+            selector_offset,
+            parent: parent.map(|parent| Box::new(parent.clone())),
+        };
 
         loop {
             let sequence_start = index;
+            let mut nested_selectors = SmallVec::<[&Selector<SelectorImpl>; 2]>::new();
+
+            {
+                let mut compound_visitor = CompoundSelectorDependencyCollector {
+                    classes: SmallVec::new(),
+                    ids: SmallVec::new(),
+                    state: ElementState::empty(),
+                    document_state: &mut *document_state,
+                    other_attributes: false,
+                    has_id_attribute_selectors: false,
+                    has_class_attribute_selectors: false,
+                };
 
-            let mut compound_visitor = CompoundSelectorDependencyCollector {
-                classes: SmallVec::new(),
-                ids: SmallVec::new(),
-                state: ElementState::empty(),
-                document_state: &mut document_state,
-                other_attributes: false,
-                has_id_attribute_selectors: false,
-                has_class_attribute_selectors: false,
-            };
-
-            // Visit all the simple selectors in this sequence.
-            //
-            // Note that this works because we can't have combinators nested
-            // inside simple selectors (i.e. in :not() or :-moz-any()).
-            //
-            // If we ever support that we'll need to visit nested complex
-            // selectors as well, in order to mark them as affecting descendants
-            // at least.
-            for ss in &mut iter {
-                ss.visit(&mut compound_visitor);
-                index += 1; // Account for the simple selector.
-            }
-
-            self.has_id_attribute_selectors |= compound_visitor.has_id_attribute_selectors;
-            self.has_class_attribute_selectors |= compound_visitor.has_class_attribute_selectors;
-
-            for class in compound_visitor.classes {
-                self.class_to_selector
-                    .try_entry(class, quirks_mode)?
-                    .or_insert_with(SmallVec::new)
-                    .try_push(Dependency {
-                        selector: selector.clone(),
-                        selector_offset: sequence_start,
-                    })?;
-            }
-
-            for id in compound_visitor.ids {
-                self.id_to_selector
-                    .try_entry(id, quirks_mode)?
-                    .or_insert_with(SmallVec::new)
-                    .try_push(Dependency {
-                        selector: selector.clone(),
-                        selector_offset: sequence_start,
-                    })?;
-            }
-
-            if !compound_visitor.state.is_empty() {
-                self.state_affecting_selectors.insert(
-                    StateDependency {
-                        dep: Dependency {
-                            selector: selector.clone(),
-                            selector_offset: sequence_start,
+                // Visit all the simple selectors in this sequence.
+                //
+                // The selectors nested in :is() and :where() may contain
+                // combinators, so we note them separately below, pointing
+                // back to this compound selector.
+                //
+                // The relative selectors in :has() don't depend on this element
+                // but on its descendants and siblings, so they're tracked
+                // separately, and handled before the traversal.
+                for ss in &mut iter {
+                    match *ss {
+                        Component::Is(ref list) | Component::Where(ref list) => {
+                            nested_selectors.extend(list.iter());
                         },
-                        state: compound_visitor.state,
-                    },
-                    quirks_mode,
-                )?;
+                        Component::Has(ref list) => {
+                            for relative_selector in list.iter() {
+                                self.relative_selector_dependencies.note_selector(
+                                    relative_selector,
+                                    &mut *compound_visitor.document_state,
+                                )?;
+                            }
+                        },
+                        _ => {
+                            ss.visit(&mut compound_visitor);
+                        },
+                    }
+                    index += 1; // Account for the simple selector.
+                }
+
+                self.has_id_attribute_selectors |= compound_visitor.has_id_attribute_selectors;
+                self.has_class_attribute_selectors |=
+                    compound_visitor.has_class_attribute_selectors;
+
+                for class in compound_visitor.classes {
+                    self.class_to_selector
+                        .try_entry(class, quirks_mode)?
+                        .or_insert_with(SmallVec::new)
+                        .try_push(dependency(sequence_start))?;
+                }
+
+                for id in compound_visitor.ids {
+                    self.id_to_selector
+                        .try_entry(id, quirks_mode)?
+                        .or_insert_with(SmallVec::new)
+                        .try_push(dependency(sequence_start))?;
+                }
+
+                if !compound_visitor.state.is_empty() {
+                    self.state_affecting_selectors.insert(
+                        StateDependency {
+                            dep: dependency(sequence_start),
+                            state: compound_visitor.state,
+                        },
+                        quirks_mode,
+                    )?;
+                }
+
+                if compound_visitor.other_attributes {
+                    self.other_attribute_affecting_selectors
+                        .insert(dependency(sequence_start), quirks_mode)?;
+                }
             }
 
-            if compound_visitor.other_attributes {
-                self.other_attribute_affecting_selectors.insert(
-                    Dependency {
-                        selector: selector.clone(),
-                        selector_offset: sequence_start,
-                    },
-                    quirks_mode,
-                )?;
+            if !nested_selectors.is_empty() {
+                let parent = dependency(sequence_start);
+                for nested_selector in nested_selectors {
+                    self.note_selector_with_parent(
+                        nested_selector,
+                        quirks_mode,
+                        Some(&parent),
+                        document_state,
+                    )?;
+                }
             }
 
             combinator = iter.next_sequence();
@@ -306,14 +431,6 @@ impl InvalidationMap {
             index += 1; // Account for the combinator.
         }
 
-        if !document_state.is_empty() {
-            self.document_state_selectors
-                .try_push(DocumentStateDependency {
-                    state: document_state,
-                    selector: selector.clone(),
-                })?;
-        }
-
         Ok(())
     }
 }
@@ -7,6 +7,7 @@
 
 use context::StackLimitChecker;
 use dom::{TElement, TNode, TShadowRoot};
+use invalidation::element::invalidation_map::{Dependency, DependencyInvalidationKind};
 use selector_parser::SelectorImpl;
 use selectors::matching::{CompoundSelectorMatchingResult, MatchingContext};
 use selectors::matching::matches_compound_selector_from;
@@ -133,6 +134,12 @@ pub struct Invalidation<'a> {
     /// this one if the generated invalidation is effective for all the siblings
     /// or descendants after us.
     matched_by_any_previous: bool,
+    /// The dependency of the compound selector containing the `:is()` or
+    /// `:where()` this selector is nested in, if any.
+    ///
+    /// When the selector matches completely, we keep invalidating from this
+    /// dependency instead of invalidating the matched element.
+    parent: Option<&'a Dependency>,
 }
 
 impl<'a> Invalidation<'a> {
@@ -142,6 +149,21 @@ impl<'a> Invalidation<'a> {
             selector,
             offset,
             matched_by_any_previous: false,
+            parent: None,
+        }
+    }
+
+    /// Create a new invalidation for the compound selector to the right of the
+    /// combinator of the given dependency.
+    pub fn from_dependency(dependency: &'a Dependency) -> Self {
+        debug_assert_ne!(dependency.selector_offset, 0);
+        debug_assert_ne!(dependency.selector_offset, dependency.selector.len());
+
+        Self {
+            selector: &dependency.selector,
+            offset: dependency.selector.len() - dependency.selector_offset + 1,
+            matched_by_any_previous: false,
+            parent: dependency.parent.as_ref().map(|parent| &**parent),
         }
     }
 
@@ -693,7 +715,14 @@ where
             CompoundSelectorMatchingResult::FullyMatched => {
                 debug!(" > Invalidation matched completely");
                 matched = true;
-                invalidated_self = true;
+                invalidated_self = match invalidation.parent {
+                    Some(parent) => Self::note_parent_dependency(
+                        parent,
+                        descendant_invalidations,
+                        sibling_invalidations,
+                    ),
+                    None => true,
+                };
             },
             CompoundSelectorMatchingResult::Matched {
                 next_combinator_offset,
@@ -746,6 +775,7 @@ where
                     selector: invalidation.selector,
                     offset: next_combinator_offset + 1,
                     matched_by_any_previous: false,
+                    parent: invalidation.parent,
                 };
 
                 debug!(
@@ -849,4 +879,49 @@ where
             matched,
         }
     }
+
+    /// Keeps invalidating from the dependency of the compound selector that
+    /// contains the `:is()` or `:where()` selector that just matched the
+    /// current element.
+    ///
+    /// Returns whether the current element was invalidated.
+    fn note_parent_dependency(
+        dependency: &'b Dependency,
+        descendant_invalidations: &mut DescendantInvalidationLists<'b>,
+        sibling_invalidations: &mut InvalidationVector<'b>,
+    ) -> bool {
+        let invalidation_kind = dependency.invalidation_kind();
+        if matches!(invalidation_kind, DependencyInvalidationKind::Element) {
+            return match dependency.parent {
+                Some(ref parent) => Self::note_parent_dependency(
+                    parent,
+                    descendant_invalidations,
+                    sibling_invalidations,
+                ),
+                None => true,
+            };
+        }
+
+        let invalidation = Invalidation::from_dependency(dependency);
+        debug!(" > Invalidating from parent dependency: {:?}", invalidation);
+        match invalidation_kind {
+            DependencyInvalidationKind::Element => unreachable!(),
+            DependencyInvalidationKind::ElementAndDescendants => {
+                descendant_invalidations.dom_descendants.push(invalidation);
+                true
+            },
+            DependencyInvalidationKind::Descendants => {
+                descendant_invalidations.dom_descendants.push(invalidation);
+                false
+            },
+            DependencyInvalidationKind::Siblings => {
+                sibling_invalidations.push(invalidation);
+                false
+            },
+            DependencyInvalidationKind::SlottedElements => {
+                descendant_invalidations.slotted_descendants.push(invalidation);
+                false
+            },
+        }
+    }
 }
@@ -0,0 +1,143 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Invalidation of the elements that anchor `:has()` selectors.
+//!
+//! A `:has()` selector matches depending on the descendants and later siblings
+//! of the element it's anchored at, so a change to an element may affect the
+//! style of its ancestors and of their earlier siblings. The regular
+//! invalidation pass runs during the traversal and can only reach descendants
+//! and later siblings, so these elements are invalidated upfront instead, for
+//! every element with a snapshot.
+
+use {Atom, WeakAtom};
+use context::QuirksMode;
+use dom::TElement;
+use invalidation::element::element_wrapper::{ElementSnapshot, ElementWrapper};
+use invalidation::element::invalidation_map::RelativeSelectorDependencies;
+use invalidation::element::restyle_hints::RestyleHint;
+use selector_parser::SnapshotMap;
+use selectors::Element;
+use selectors::matching::ElementSelectorFlags;
+use smallvec::SmallVec;
+use stylist::Stylist;
+
+/// Invalidates the elements whose `:has()` selectors may be affected by the
+/// state and attribute changes of `element`.
+///
+/// Only elements that have matched a `:has()` selector before, and thus carry
+/// one of the `ANCHORS_*RELATIVE_SELECTOR` flags, are invalidated.
+pub fn invalidate_relative_selector_anchors<E>(
+    element: E,
+    stylist: &Stylist,
+    snapshots: &SnapshotMap,
+) where
+    E: TElement,
+{
+    let wrapper = ElementWrapper::new(element, snapshots);
+    let snapshot = match wrapper.snapshot() {
+        Some(snapshot) => snapshot,
+        None => return,
+    };
+
+    let state_changes = wrapper.state_changes();
+    if !snapshot.has_attrs() && state_changes.is_empty() {
+        return;
+    }
+
+    let mut classes = SmallVec::<[Atom; 8]>::new();
+    if snapshot.class_changed() {
+        snapshot.each_class(|c| classes.push(c.clone()));
+        element.each_class(|c| classes.push(c.clone()));
+    }
+
+    let mut ids = SmallVec::<[&WeakAtom; 2]>::new();
+    if snapshot.id_changed() {
+        ids.extend(snapshot.id_attr());
+        ids.extend(element.id());
+    }
+
+    let changed = |dependencies: &RelativeSelectorDependencies, quirks_mode: QuirksMode| {
+        if dependencies.is_empty() {
+            return false;
+        }
+        if dependencies.state.intersects(state_changes) {
+            return true;
+        }
+        if snapshot.other_attr_changed() && dependencies.other_attributes {
+            return true;
+        }
+        // Classes and IDs are compared case-insensitively in quirks mode, so
+        // don't bother looking them up in that case.
+        if quirks_mode == QuirksMode::Quirks {
+            return (!classes.is_empty() && !dependencies.classes.is_empty()) ||
+                (!ids.is_empty() && !dependencies.ids.is_empty());
+        }
+        classes.iter().any(|c| dependencies.classes.contains(c)) ||
+            ids.iter().any(|id| dependencies.ids.contains(*id))
+    };
+
+    let mut may_affect_anchors = false;
+    element.each_applicable_non_document_style_rule_data(|data, quirks_mode, _| {
+        may_affect_anchors |=
+            changed(&data.invalidation_map().relative_selector_dependencies, quirks_mode);
+    });
+
+    if !may_affect_anchors {
+        may_affect_anchors = stylist.iter_origins().any(|(data, _)| {
+            changed(
+                &data.invalidation_map().relative_selector_dependencies,
+                stylist.quirks_mode(),
+            )
+        });
+    }
+
+    if !may_affect_anchors {
+        return;
+    }
+
+    debug!("Invalidating :has() anchors for {:?}", element);
+
+    let mut current = Some(element);
+    while let Some(el) = current {
+        let mut sibling = el.prev_sibling_element();
+        while let Some(s) = sibling {
+            invalidate_anchor(s);
+            sibling = s.prev_sibling_element();
+        }
+
+        current = el.parent_element();
+        if let Some(parent) = current {
+            invalidate_anchor(parent);
+        }
+    }
+}
+
+fn invalidate_anchor<E>(element: E)
+where
+    E: TElement,
+{
+    if element.has_selector_flags(ElementSelectorFlags::ANCHORS_NON_SUBJECT_RELATIVE_SELECTOR) {
+        // The :has() may affect the style of the descendants and later
+        // siblings of the anchor too.
+        let mut current = Some(element);
+        while let Some(el) = current {
+            if let Some(mut data) = el.mutate_data() {
+                debug!(" > Invalidating :has() anchor or sibling {:?}", el);
+                data.hint.insert(RestyleHint::restyle_subtree());
+            }
+            current = el.next_sibling_element();
+        }
+        return;
+    }
+
+    if !element.has_selector_flags(ElementSelectorFlags::ANCHORS_RELATIVE_SELECTOR) {
+        return;
+    }
+
+    if let Some(mut data) = element.mutate_data() {
+        debug!(" > Invalidating :has() anchor {:?}", element);
+        data.hint.insert(RestyleHint::RESTYLE_SELF);
+    }
+}
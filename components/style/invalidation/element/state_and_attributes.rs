@@ -448,17 +448,19 @@ where
 
         let invalidation_kind = dependency.invalidation_kind();
         if matches!(invalidation_kind, DependencyInvalidationKind::Element) {
+            // A selector nested in :is() or :where() changed for this element,
+            // so the compound selector containing it may have changed too.
+            if let Some(ref parent) = dependency.parent {
+                if self.dependency_may_be_relevant(parent) {
+                    self.note_dependency(parent);
+                }
+                return;
+            }
             self.invalidates_self = true;
             return;
         }
 
-        debug_assert_ne!(dependency.selector_offset, 0);
-        debug_assert_ne!(dependency.selector_offset, dependency.selector.len());
-
-        let invalidation = Invalidation::new(
-            &dependency.selector,
-            dependency.selector.len() - dependency.selector_offset + 1,
-        );
+        let invalidation = Invalidation::from_dependency(dependency);
 
         match invalidation_kind {
             DependencyInvalidationKind::Element => unreachable!(),
@@ -433,8 +433,10 @@ trait PrivateMatchMethods: TElement {
             );
         }
 
-        let new_animations_sender = &context.thread_local.new_animations_sender;
         let this_opaque = self.as_node().opaque();
+        animation::update_script_animations_cascade_style(shared_context, this_opaque, new_values);
+
+        let new_animations_sender = &context.thread_local.new_animations_sender;
         // Trigger any present animations if necessary.
         animation::maybe_start_animations(
             *self,
@@ -478,6 +480,18 @@ trait PrivateMatchMethods: TElement {
 
         *damage |= difference.damage;
 
+        #[cfg(feature = "servo")]
+        {
+            // Layout doesn't repair the styles of the fragments of these
+            // pseudo-elements, so they need to be built again.
+            let is_fragment_pseudo = pseudo.map_or(false, |p| {
+                p.is_first_letter() || p.is_first_line() || p.is_marker()
+            });
+            if is_fragment_pseudo && !difference.damage.is_empty() {
+                *damage |= RestyleDamage::reconstruct();
+            }
+        }
+
         debug!(" > style difference: {:?}", difference);
 
         // We need to cascade the children in order to ensure the correct
@@ -461,6 +461,26 @@ ${helpers.single_keyword(
     animation_value_type="discrete",
 )}
 
+// CSS Fragmentation Module Level 3
+// https://drafts.csswg.org/css-break/
+% for side in ["before", "after"]:
+    ${helpers.single_keyword(
+        "break-%s" % side,
+        "auto avoid avoid-page page left right recto verso",
+        products="servo",
+        spec="https://drafts.csswg.org/css-break/#propdef-break-%s" % side,
+        animation_value_type="discrete",
+    )}
+% endfor
+
+${helpers.single_keyword(
+    "break-inside",
+    "auto avoid avoid-page",
+    products="servo",
+    spec="https://drafts.csswg.org/css-break/#propdef-break-inside",
+    animation_value_type="discrete",
+)}
+
 // CSS Basic User Interface Module Level 3
 // http://dev.w3.org/csswg/css-ui
 //
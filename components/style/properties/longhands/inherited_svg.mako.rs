@@ -13,7 +13,8 @@
 ${helpers.single_keyword(
     "text-anchor",
     "start middle end",
-    products="gecko",
+    products="gecko servo",
+    servo_restyle_damage="rebuild_and_reflow",
     animation_value_type="discrete",
     spec="https://www.w3.org/TR/SVG/text.html#TextAnchorProperty",
 )}
@@ -40,7 +41,8 @@ ${helpers.predefined_type(
     "fill",
     "SVGPaint",
     "::values::computed::SVGPaint::black()",
-    products="gecko",
+    products="gecko servo",
+    servo_restyle_damage="rebuild_and_reflow",
     animation_value_type="IntermediateSVGPaint",
     boxed=True,
     spec="https://www.w3.org/TR/SVG2/painting.html#SpecifyingFillPaint",
@@ -50,7 +52,8 @@ ${helpers.predefined_type(
     "fill-opacity",
     "SVGOpacity",
     "Default::default()",
-    products="gecko",
+    products="gecko servo",
+    servo_restyle_damage="rebuild_and_reflow",
     animation_value_type="ComputedValue",
     spec="https://www.w3.org/TR/SVG11/painting.html#FillOpacityProperty",
 )}
@@ -59,7 +62,8 @@ ${helpers.single_keyword(
     "fill-rule",
     "nonzero evenodd",
     gecko_enum_prefix="StyleFillRule",
-    products="gecko",
+    products="gecko servo",
+    servo_restyle_damage="rebuild_and_reflow",
     animation_value_type="discrete",
     spec="https://www.w3.org/TR/SVG11/painting.html#FillRuleProperty",
 )}
@@ -76,7 +80,8 @@ ${helpers.predefined_type(
     "stroke",
     "SVGPaint",
     "Default::default()",
-    products="gecko",
+    products="gecko servo",
+    servo_restyle_damage="rebuild_and_reflow",
     animation_value_type="IntermediateSVGPaint",
     boxed=True,
     spec="https://www.w3.org/TR/SVG2/painting.html#SpecifyingStrokePaint",
@@ -85,7 +90,8 @@ ${helpers.predefined_type(
 ${helpers.predefined_type(
     "stroke-width", "SVGWidth",
     "::values::computed::NonNegativeLength::new(1.).into()",
-    products="gecko",
+    products="gecko servo",
+    servo_restyle_damage="rebuild_and_reflow",
     animation_value_type="::values::computed::SVGWidth",
     spec="https://www.w3.org/TR/SVG2/painting.html#StrokeWidth",
 )}
@@ -93,7 +99,8 @@ ${helpers.predefined_type(
 ${helpers.single_keyword(
     "stroke-linecap",
     "butt round square",
-    products="gecko",
+    products="gecko servo",
+    servo_restyle_damage="rebuild_and_reflow",
     animation_value_type="discrete",
     spec="https://www.w3.org/TR/SVG11/painting.html#StrokeLinecapProperty",
 )}
@@ -101,7 +108,8 @@ ${helpers.single_keyword(
 ${helpers.single_keyword(
     "stroke-linejoin",
     "miter round bevel",
-    products="gecko",
+    products="gecko servo",
+    servo_restyle_damage="rebuild_and_reflow",
     animation_value_type="discrete",
     spec="https://www.w3.org/TR/SVG11/painting.html#StrokeLinejoinProperty",
 )}
@@ -110,7 +118,8 @@ ${helpers.predefined_type(
     "stroke-miterlimit",
     "GreaterThanOrEqualToOneNumber",
     "From::from(4.0)",
-    products="gecko",
+    products="gecko servo",
+    servo_restyle_damage="rebuild_and_reflow",
     animation_value_type="::values::computed::GreaterThanOrEqualToOneNumber",
     spec="https://www.w3.org/TR/SVG11/painting.html#StrokeMiterlimitProperty",
 )}
@@ -119,7 +128,8 @@ ${helpers.predefined_type(
     "stroke-opacity",
     "SVGOpacity",
     "Default::default()",
-    products="gecko",
+    products="gecko servo",
+    servo_restyle_damage="rebuild_and_reflow",
     animation_value_type="ComputedValue",
     spec="https://www.w3.org/TR/SVG11/painting.html#StrokeOpacityProperty",
 )}
@@ -128,7 +138,8 @@ ${helpers.predefined_type(
     "stroke-dasharray",
     "SVGStrokeDashArray",
     "Default::default()",
-    products="gecko",
+    products="gecko servo",
+    servo_restyle_damage="rebuild_and_reflow",
     animation_value_type="::values::computed::SVGStrokeDashArray",
     spec="https://www.w3.org/TR/SVG2/painting.html#StrokeDashing",
 )}
@@ -136,7 +147,8 @@ ${helpers.predefined_type(
 ${helpers.predefined_type(
     "stroke-dashoffset", "SVGLength",
     "Au(0).into()",
-    products="gecko",
+    products="gecko servo",
+    servo_restyle_damage="rebuild_and_reflow",
     animation_value_type="ComputedValue",
     spec="https://www.w3.org/TR/SVG2/painting.html#StrokeDashing",
 )}
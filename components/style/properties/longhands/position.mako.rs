@@ -369,7 +369,8 @@ ${helpers.predefined_type(
             "Default::default()",
             animation_value_type="discrete",
             spec="https://drafts.csswg.org/css-grid/#propdef-grid-%s-%s" % (kind, range),
-            products="gecko",
+            servo_pref="layout.grid.enabled",
+            servo_restyle_damage="reflow",
             boxed=True,
         )}
     % endfor
@@ -382,7 +383,8 @@ ${helpers.predefined_type(
         "Default::default()",
         animation_value_type="discrete",
         spec="https://drafts.csswg.org/css-grid/#propdef-grid-auto-%ss" % kind,
-        products="gecko",
+        servo_pref="layout.grid.enabled",
+        servo_restyle_damage="reflow",
         boxed=True,
     )}
 
@@ -390,7 +392,8 @@ ${helpers.predefined_type(
         "grid-template-%ss" % kind,
         "GridTemplateComponent",
         "specified::GenericGridTemplateComponent::None",
-        products="gecko",
+        servo_pref="layout.grid.enabled",
+        servo_restyle_damage="reflow",
         spec="https://drafts.csswg.org/css-grid/#propdef-grid-template-%ss" % kind,
         boxed=True,
         flags="GETCS_NEEDS_LAYOUT_FLUSH",
@@ -403,7 +406,8 @@ ${helpers.predefined_type(
     "grid-auto-flow",
     "GridAutoFlow",
     "computed::GridAutoFlow::row()",
-    products="gecko",
+    servo_pref="layout.grid.enabled",
+    servo_restyle_damage="reflow",
     animation_value_type="discrete",
     spec="https://drafts.csswg.org/css-grid/#propdef-grid-auto-flow",
 )}
@@ -412,7 +416,8 @@ ${helpers.predefined_type(
     "grid-template-areas",
     "GridTemplateAreas",
     "computed::GridTemplateAreas::none()",
-    products="gecko",
+    servo_pref="layout.grid.enabled",
+    servo_restyle_damage="reflow",
     animation_value_type="discrete",
     spec="https://drafts.csswg.org/css-grid/#propdef-grid-template-areas",
 )}
@@ -435,7 +440,7 @@ ${helpers.predefined_type(
     "length::NonNegativeLengthOrPercentageOrNormal",
     "Either::Second(Normal)",
     alias="grid-row-gap",
-    products="gecko",
+    servo_pref="layout.grid.enabled",
     spec="https://drafts.csswg.org/css-align-3/#propdef-row-gap",
     animation_value_type="NonNegativeLengthOrPercentageOrNormal",
     servo_restyle_damage="reflow",
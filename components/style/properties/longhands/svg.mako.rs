@@ -29,7 +29,8 @@ ${helpers.predefined_type(
     "stop-color",
     "Color",
     "RGBA::new(0, 0, 0, 255).into()",
-    products="gecko",
+    products="gecko servo",
+    servo_restyle_damage="rebuild_and_reflow",
     animation_value_type="AnimatedRGBA",
     spec="https://www.w3.org/TR/SVGTiny12/painting.html#StopColorProperty",
 )}
@@ -38,7 +39,8 @@ ${helpers.predefined_type(
     "stop-opacity",
     "Opacity",
     "1.0",
-    products="gecko",
+    products="gecko servo",
+    servo_restyle_damage="rebuild_and_reflow",
     animation_value_type="ComputedValue",
     spec="https://www.w3.org/TR/SVGTiny12/painting.html#propdef-stop-opacity",
 )}
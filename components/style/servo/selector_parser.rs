@@ -37,14 +37,14 @@ pub enum PseudoElement {
     After = 0,
     Before,
     Selection,
-    // If/when :first-letter is added, update is_first_letter accordingly.
-
-    // If/when :first-line is added, update is_first_line accordingly.
-
-    // If/when ::first-letter, ::first-line, or ::placeholder are added, adjust
-    // our property_restriction implementation to do property filtering for
-    // them.  Also, make sure the UA sheet has the !important rules some of the
-    // APPLIES_TO_PLACEHOLDER properties expect!
+    FirstLetter,
+    // Layout only honors the properties of ::first-line that apply at paint time.
+    FirstLine,
+    Marker,
+    // If/when ::placeholder is added, adjust our property_restriction
+    // implementation to do property filtering for it.  Also, make sure the UA
+    // sheet has the !important rules some of the APPLIES_TO_PLACEHOLDER
+    // properties expect!
 
     // Non-eager pseudos.
     DetailsSummary,
@@ -82,6 +82,9 @@ impl ToCss for PseudoElement {
             After => "::after",
             Before => "::before",
             Selection => "::selection",
+            FirstLetter => "::first-letter",
+            FirstLine => "::first-line",
+            Marker => "::marker",
             DetailsSummary => "::-servo-details-summary",
             DetailsContent => "::-servo-details-content",
             ServoText => "::-servo-text",
@@ -99,7 +102,11 @@ impl ToCss for PseudoElement {
 }
 
 /// The number of eager pseudo-elements. Keep this in sync with cascade_type.
-pub const EAGER_PSEUDO_COUNT: usize = 3;
+///
+/// Eager pseudo-elements are only cascaded for elements with rules matching them, and layout
+/// elements skip `::first-letter`, `::first-line` and `::marker` where they can't apply (see
+/// `TElement::may_generate_pseudo`), so the extra slots are usually empty.
+pub const EAGER_PSEUDO_COUNT: usize = 6;
 
 impl PseudoElement {
     /// Gets the canonical index of this eagerly-cascaded pseudo-element.
@@ -156,13 +163,19 @@ impl PseudoElement {
     /// Whether the current pseudo element is :first-letter
     #[inline]
     pub fn is_first_letter(&self) -> bool {
-        false
+        *self == PseudoElement::FirstLetter
     }
 
     /// Whether the current pseudo element is :first-line
     #[inline]
     pub fn is_first_line(&self) -> bool {
-        false
+        *self == PseudoElement::FirstLine
+    }
+
+    /// Whether this pseudo-element is the ::marker pseudo.
+    #[inline]
+    pub fn is_marker(&self) -> bool {
+        *self == PseudoElement::Marker
     }
 
     /// Whether this pseudo-element is eagerly-cascaded.
@@ -203,9 +216,12 @@ impl PseudoElement {
     #[inline]
     pub fn cascade_type(&self) -> PseudoElementCascadeType {
         match *self {
-            PseudoElement::After | PseudoElement::Before | PseudoElement::Selection => {
-                PseudoElementCascadeType::Eager
-            },
+            PseudoElement::After |
+            PseudoElement::Before |
+            PseudoElement::Selection |
+            PseudoElement::FirstLetter |
+            PseudoElement::FirstLine |
+            PseudoElement::Marker => PseudoElementCascadeType::Eager,
             PseudoElement::DetailsSummary => PseudoElementCascadeType::Lazy,
             PseudoElement::DetailsContent |
             PseudoElement::ServoText |
@@ -234,6 +250,9 @@ impl PseudoElement {
             PseudoElement::After |
             PseudoElement::Before |
             PseudoElement::Selection |
+            PseudoElement::FirstLetter |
+            PseudoElement::FirstLine |
+            PseudoElement::Marker |
             PseudoElement::DetailsContent |
             PseudoElement::DetailsSummary |
             // Anonymous table flows shouldn't inherit their parents properties in order
@@ -272,7 +291,11 @@ impl PseudoElement {
     /// Property flag that properties must have to apply to this pseudo-element.
     #[inline]
     pub fn property_restriction(&self) -> Option<PropertyFlags> {
-        None
+        match *self {
+            PseudoElement::FirstLetter => Some(PropertyFlags::APPLIES_TO_FIRST_LETTER),
+            PseudoElement::FirstLine => Some(PropertyFlags::APPLIES_TO_FIRST_LINE),
+            _ => None,
+        }
     }
 
     /// Whether this pseudo-element should actually exist if it has
@@ -302,20 +325,29 @@ pub enum NonTSPseudoClass {
     Active,
     AnyLink,
     Checked,
+    Default,
     Disabled,
     Enabled,
     Focus,
+    FocusVisible,
+    FocusWithin,
     Fullscreen,
     Hover,
+    InRange,
     Indeterminate,
+    Invalid,
     Lang(Lang),
     Link,
+    Optional,
+    OutOfRange,
     PlaceholderShown,
     ReadWrite,
     ReadOnly,
+    Required,
     ServoNonZeroBorder,
     ServoCaseSensitiveTypeAttr(Atom),
     Target,
+    Valid,
     Visited,
 }
 
@@ -352,18 +384,27 @@ impl ToCss for NonTSPseudoClass {
             Active => ":active",
             AnyLink => ":any-link",
             Checked => ":checked",
+            Default => ":default",
             Disabled => ":disabled",
             Enabled => ":enabled",
             Focus => ":focus",
+            FocusVisible => ":focus-visible",
+            FocusWithin => ":focus-within",
             Fullscreen => ":fullscreen",
             Hover => ":hover",
+            InRange => ":in-range",
             Indeterminate => ":indeterminate",
+            Invalid => ":invalid",
             Link => ":link",
+            Optional => ":optional",
+            OutOfRange => ":out-of-range",
             PlaceholderShown => ":placeholder-shown",
             ReadWrite => ":read-write",
             ReadOnly => ":read-only",
+            Required => ":required",
             ServoNonZeroBorder => ":-servo-nonzero-border",
             Target => ":target",
+            Valid => ":valid",
             Visited => ":visited",
             Lang(_) | ServoCaseSensitiveTypeAttr(_) => unreachable!(),
         })
@@ -399,6 +440,15 @@ impl NonTSPseudoClass {
             ReadOnly | ReadWrite => ElementState::IN_READ_WRITE_STATE,
             PlaceholderShown => ElementState::IN_PLACEHOLDER_SHOWN_STATE,
             Target => ElementState::IN_TARGET_STATE,
+            Valid => ElementState::IN_VALID_STATE,
+            Invalid => ElementState::IN_INVALID_STATE,
+            Required => ElementState::IN_REQUIRED_STATE,
+            Optional => ElementState::IN_OPTIONAL_STATE,
+            InRange => ElementState::IN_INRANGE_STATE,
+            OutOfRange => ElementState::IN_OUTOFRANGE_STATE,
+            Default => ElementState::IN_DEFAULT_STATE,
+            FocusWithin => ElementState::IN_FOCUS_WITHIN_STATE,
+            FocusVisible => ElementState::IN_FOCUSRING_STATE,
 
             AnyLink |
             Lang(_) |
@@ -451,6 +501,16 @@ impl<'a, 'i> ::selectors::Parser<'i> for SelectorParser<'a> {
     type Impl = SelectorImpl;
     type Error = StyleParseErrorKind<'i>;
 
+    #[inline]
+    fn parse_slotted(&self) -> bool {
+        true
+    }
+
+    #[inline]
+    fn parse_host(&self) -> bool {
+        true
+    }
+
     fn parse_non_ts_pseudo_class(
         &self,
         location: SourceLocation,
@@ -461,17 +521,26 @@ impl<'a, 'i> ::selectors::Parser<'i> for SelectorParser<'a> {
             "active" => Active,
             "any-link" => AnyLink,
             "checked" => Checked,
+            "default" => Default,
             "disabled" => Disabled,
             "enabled" => Enabled,
             "focus" => Focus,
+            "focus-visible" => FocusVisible,
+            "focus-within" => FocusWithin,
             "fullscreen" => Fullscreen,
             "hover" => Hover,
+            "in-range" => InRange,
             "indeterminate" => Indeterminate,
+            "invalid" => Invalid,
             "link" => Link,
+            "optional" => Optional,
+            "out-of-range" => OutOfRange,
             "placeholder-shown" => PlaceholderShown,
             "read-write" => ReadWrite,
             "read-only" => ReadOnly,
+            "required" => Required,
             "target" => Target,
+            "valid" => Valid,
             "visited" => Visited,
             "-servo-nonzero-border" => {
                 if !self.in_user_agent_stylesheet() {
@@ -519,6 +588,9 @@ impl<'a, 'i> ::selectors::Parser<'i> for SelectorParser<'a> {
             "before" => Before,
             "after" => After,
             "selection" => Selection,
+            "first-letter" => FirstLetter,
+            "first-line" => FirstLine,
+            "marker" => Marker,
             "-servo-details-summary" => {
                 if !self.in_user_agent_stylesheet() {
                     return Err(location.new_custom_error(SelectorParseErrorKind::UnexpectedIdent(name.clone())))
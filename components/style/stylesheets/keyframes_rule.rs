@@ -168,6 +168,12 @@ impl KeyframeSelector {
         KeyframeSelector(percentages)
     }
 
+    /// Creates a selector from a list of percentages, as for the keyframes of
+    /// an animation created from script.
+    pub fn from_percentages(percentages: Vec<KeyframePercentage>) -> KeyframeSelector {
+        KeyframeSelector(percentages)
+    }
+
     /// Parse a keyframe selector from CSS input.
     pub fn parse<'i, 't>(input: &mut Parser<'i, 't>) -> Result<Self, ParseError<'i>> {
         input
@@ -6,15 +6,25 @@
 //!
 //! [page]: https://drafts.csswg.org/css2/page.html#page-box
 
-use cssparser::SourceLocation;
+use app_units::Au;
+use cssparser::{parse_important, AtRuleParser, CowRcStr, DeclarationListParser};
+use cssparser::{DeclarationParser, Delimiter, Parser, SourceLocation, Token};
+use error_reporting::ContextualParseError;
+use euclid::{SideOffsets2D, Size2D};
 #[cfg(feature = "gecko")]
 use malloc_size_of::{MallocSizeOf, MallocSizeOfOps, MallocUnconditionalShallowSizeOf};
-use properties::PropertyDeclarationBlock;
+use parser::ParserContext;
+use properties::{Importance, PropertyDeclaration, PropertyDeclarationBlock, PropertyId};
+use properties::SourcePropertyDeclaration;
 use servo_arc::Arc;
 use shared_lock::{DeepCloneParams, DeepCloneWithLock, Locked};
 use shared_lock::{SharedRwLock, SharedRwLockReadGuard, ToCssWithGuard};
 use std::fmt::{self, Write};
 use str::CssStringWriter;
+use style_traits::{CssWriter, ParseError, StyleParseErrorKind, ToCss};
+use values::computed::font::FontSize;
+use values::specified::{AbsoluteLength, FontRelativeLength, Length, LengthOrPercentageOrAuto};
+use values::specified::NoCalcLength;
 
 /// A [`@page`][page] rule.
 ///
@@ -29,10 +39,289 @@ use str::CssStringWriter;
 pub struct PageRule {
     /// The declaration block this page rule contains.
     pub block: Arc<Locked<PropertyDeclarationBlock>>,
+    /// The `size` descriptor of this rule, if any.
+    pub size: Option<PageSize>,
     /// The source position this rule was found at.
     pub source_location: SourceLocation,
 }
 
+/// The orientation of a page.
+#[derive(Clone, Copy, Debug, MallocSizeOf, PartialEq, ToCss)]
+pub enum PageOrientation {
+    /// The longest side of the page is its height.
+    Portrait,
+    /// The longest side of the page is its width.
+    Landscape,
+}
+
+/// The value of the [`size`][size] descriptor.
+///
+/// Paper size keywords are turned into the lengths they stand for.
+///
+/// [size]: https://drafts.csswg.org/css-page-3/#page-size-prop
+#[derive(Clone, Debug, MallocSizeOf, PartialEq, ToCss)]
+pub enum PageSize {
+    /// `auto`
+    Auto,
+    /// `portrait` or `landscape`, which orient the default page size.
+    Orientation(PageOrientation),
+    /// A width and a height.
+    Size(Length, Length),
+}
+
+/// The width and height of the paper sizes that `size` accepts, in millimeters.
+fn paper_size(name: &str) -> Option<(f32, f32)> {
+    Some(match_ignore_ascii_case! { name,
+        "a5" => (148., 210.),
+        "a4" => (210., 297.),
+        "a3" => (297., 420.),
+        "b5" => (176., 250.),
+        "b4" => (250., 353.),
+        "jis-b5" => (182., 257.),
+        "jis-b4" => (257., 364.),
+        "letter" => (215.9, 279.4),
+        "legal" => (215.9, 355.6),
+        "ledger" => (279.4, 431.8),
+        _ => return None,
+    })
+}
+
+impl PageSize {
+    /// Parses a `size` descriptor.
+    pub fn parse<'i, 't>(
+        context: &ParserContext,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self, ParseError<'i>> {
+        if input.try(|i| i.expect_ident_matching("auto")).is_ok() {
+            return Ok(PageSize::Auto);
+        }
+
+        if let Ok(width) = input.try(|i| Length::parse_non_negative(context, i)) {
+            let height = input
+                .try(|i| Length::parse_non_negative(context, i))
+                .unwrap_or_else(|_| width.clone());
+            return Ok(PageSize::Size(width, height));
+        }
+
+        let mut orientation = None;
+        let mut paper = None;
+        for _ in 0..2 {
+            let location = input.current_source_location();
+            let ident = match input.try(|i| i.expect_ident_cloned()) {
+                Ok(ident) => ident,
+                Err(_) => break,
+            };
+            if orientation.is_none() {
+                if ident.eq_ignore_ascii_case("portrait") {
+                    orientation = Some(PageOrientation::Portrait);
+                    continue;
+                }
+                if ident.eq_ignore_ascii_case("landscape") {
+                    orientation = Some(PageOrientation::Landscape);
+                    continue;
+                }
+            }
+            match paper_size(&ident) {
+                Some(size) if paper.is_none() => paper = Some(size),
+                _ => return Err(location.new_unexpected_token_error(Token::Ident(ident.clone()))),
+            }
+        }
+
+        match (paper, orientation) {
+            (Some((width, height)), orientation) => {
+                let (width, height) = match orientation {
+                    Some(PageOrientation::Landscape) => (height, width),
+                    _ => (width, height),
+                };
+                Ok(PageSize::Size(
+                    Length::NoCalc(NoCalcLength::Absolute(AbsoluteLength::Mm(width))),
+                    Length::NoCalc(NoCalcLength::Absolute(AbsoluteLength::Mm(height))),
+                ))
+            },
+            (None, Some(orientation)) => Ok(PageSize::Orientation(orientation)),
+            (None, None) => Err(input.new_custom_error(StyleParseErrorKind::UnspecifiedError)),
+        }
+    }
+}
+
+/// The size of a page and of its margins, which surround the area the document is drawn in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PageBox {
+    /// The size of the page.
+    pub size: Size2D<Au>,
+    /// The margins of the page.
+    pub margins: SideOffsets2D<Au>,
+}
+
+impl PageBox {
+    /// The size of the area of the page the document is drawn in.
+    pub fn content_size(&self) -> Size2D<Au> {
+        Size2D::new(
+            self.size.width - self.margins.horizontal(),
+            self.size.height - self.margins.vertical(),
+        )
+    }
+}
+
+/// Resolves a length of an `@page` rule, which has no element to take a font size from:
+/// font-relative lengths use the initial font size, and viewport-relative ones are ignored.
+fn resolve_length(length: &NoCalcLength) -> Option<Au> {
+    let font_size = FontSize::medium().size().to_f32_px();
+    let px = match *length {
+        NoCalcLength::Absolute(ref length) => length.to_px(),
+        NoCalcLength::FontRelative(FontRelativeLength::Em(value)) |
+        NoCalcLength::FontRelative(FontRelativeLength::Rem(value)) => value * font_size,
+        NoCalcLength::FontRelative(FontRelativeLength::Ex(value)) |
+        NoCalcLength::FontRelative(FontRelativeLength::Ch(value)) => value * font_size / 2.,
+        _ => return None,
+    };
+    Some(Au::from_f32_px(px))
+}
+
+fn resolve_margin(margin: &LengthOrPercentageOrAuto, page_length: Au) -> Option<Au> {
+    match *margin {
+        LengthOrPercentageOrAuto::Length(ref length) => resolve_length(length),
+        LengthOrPercentageOrAuto::Percentage(percentage) => {
+            Some(page_length.scale_by(percentage.0))
+        },
+        LengthOrPercentageOrAuto::Auto => Some(Au(0)),
+        LengthOrPercentageOrAuto::Calc(..) => None,
+    }
+}
+
+impl PageRule {
+    /// Applies the size and margins this rule sets to the given page box.
+    ///
+    /// Margin percentages are relative to the width of the page for the left and right
+    /// margins, and to its height for the top and bottom ones.
+    pub fn apply_to(&self, guard: &SharedRwLockReadGuard, page_box: &mut PageBox) {
+        match self.size {
+            Some(PageSize::Size(Length::NoCalc(ref width), Length::NoCalc(ref height))) => {
+                let size = (resolve_length(width), resolve_length(height));
+                if let (Some(width), Some(height)) = size {
+                    page_box.size = Size2D::new(width, height);
+                }
+            },
+            Some(PageSize::Orientation(orientation)) => {
+                let landscape = page_box.size.width > page_box.size.height;
+                if landscape != (orientation == PageOrientation::Landscape) {
+                    page_box.size = Size2D::new(page_box.size.height, page_box.size.width);
+                }
+            },
+            _ => {},
+        }
+
+        let size = page_box.size;
+        for declaration in self.block.read_with(guard).declarations() {
+            let (margin, value) = match *declaration {
+                PropertyDeclaration::MarginTop(ref value) => {
+                    (&mut page_box.margins.top, resolve_margin(value, size.height))
+                },
+                PropertyDeclaration::MarginRight(ref value) => {
+                    (&mut page_box.margins.right, resolve_margin(value, size.width))
+                },
+                PropertyDeclaration::MarginBottom(ref value) => {
+                    (&mut page_box.margins.bottom, resolve_margin(value, size.height))
+                },
+                PropertyDeclaration::MarginLeft(ref value) => {
+                    (&mut page_box.margins.left, resolve_margin(value, size.width))
+                },
+                _ => continue,
+            };
+            if let Some(value) = value {
+                *margin = value;
+            }
+        }
+    }
+}
+
+/// Parses the block of an `@page` rule: property declarations, and the `size` descriptor.
+pub fn parse_page_rule_block(
+    context: &ParserContext,
+    input: &mut Parser,
+    source_location: SourceLocation,
+    lock: &SharedRwLock,
+) -> PageRule {
+    let mut declarations = SourcePropertyDeclaration::new();
+    let mut block = PropertyDeclarationBlock::new();
+    let mut size = None;
+    {
+        let parser = PageRuleParser {
+            context,
+            declarations: &mut declarations,
+            size: &mut size,
+        };
+        let mut iter = DeclarationListParser::new(input, parser);
+        while let Some(declaration) = iter.next() {
+            match declaration {
+                Ok(Some(importance)) => {
+                    block.extend(iter.parser.declarations.drain(), importance);
+                },
+                Ok(None) => {},
+                Err((error, slice)) => {
+                    iter.parser.declarations.clear();
+                    let location = error.location;
+                    let error = ContextualParseError::UnsupportedPropertyDeclaration(slice, error);
+                    context.log_css_error(location, error);
+                },
+            }
+        }
+    }
+    PageRule {
+        block: Arc::new(lock.wrap(block)),
+        size,
+        source_location,
+    }
+}
+
+/// Parses the declarations of an `@page` rule. Successfully parsed property declarations
+/// give their importance; the `size` descriptor gives `None`.
+struct PageRuleParser<'a, 'b: 'a> {
+    context: &'a ParserContext<'b>,
+    declarations: &'a mut SourcePropertyDeclaration,
+    size: &'a mut Option<PageSize>,
+}
+
+/// Default methods reject all at rules.
+impl<'a, 'b, 'i> AtRuleParser<'i> for PageRuleParser<'a, 'b> {
+    type PreludeNoBlock = ();
+    type PreludeBlock = ();
+    type AtRule = Option<Importance>;
+    type Error = StyleParseErrorKind<'i>;
+}
+
+impl<'a, 'b, 'i> DeclarationParser<'i> for PageRuleParser<'a, 'b> {
+    type Declaration = Option<Importance>;
+    type Error = StyleParseErrorKind<'i>;
+
+    fn parse_value<'t>(
+        &mut self,
+        name: CowRcStr<'i>,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Option<Importance>, ParseError<'i>> {
+        if name.eq_ignore_ascii_case("size") {
+            *self.size = Some(input.parse_entirely(|i| PageSize::parse(self.context, i))?);
+            return Ok(None);
+        }
+
+        let id = match PropertyId::parse(&name, self.context) {
+            Ok(id) => id,
+            Err(..) => {
+                return Err(input.new_custom_error(StyleParseErrorKind::UnknownProperty(name)))
+            },
+        };
+        input.parse_until_before(Delimiter::Bang, |input| {
+            PropertyDeclaration::parse_into(self.declarations, id, self.context, input)
+        })?;
+        let importance = match input.try(parse_important) {
+            Ok(()) => Importance::Important,
+            Err(_) => Importance::Normal,
+        };
+        input.expect_exhausted()?;
+        Ok(Some(importance))
+    }
+}
+
 impl PageRule {
     /// Measure heap usage.
     #[cfg(feature = "gecko")]
@@ -47,6 +336,11 @@ impl ToCssWithGuard for PageRule {
     /// StyleRule.
     fn to_css(&self, guard: &SharedRwLockReadGuard, dest: &mut CssStringWriter) -> fmt::Result {
         dest.write_str("@page { ")?;
+        if let Some(ref size) = self.size {
+            dest.write_str("size: ")?;
+            size.to_css(&mut CssWriter::new(dest))?;
+            dest.write_str("; ")?;
+        }
         let declaration_block = self.block.read_with(guard);
         declaration_block.to_css(dest)?;
         if !declaration_block.declarations().is_empty() {
@@ -65,6 +359,7 @@ impl DeepCloneWithLock for PageRule {
     ) -> Self {
         PageRule {
             block: Arc::new(lock.wrap(self.block.read_with(&guard).clone())),
+            size: self.size.clone(),
             source_location: self.source_location.clone(),
         }
     }
@@ -21,10 +21,11 @@ use str::starts_with_ignore_ascii_case;
 use style_traits::{ParseError, StyleParseErrorKind};
 use stylesheets::{CssRule, CssRuleType, CssRules, Origin, RulesMutateError, StylesheetLoader};
 use stylesheets::{DocumentRule, FontFeatureValuesRule, KeyframesRule, MediaRule};
-use stylesheets::{NamespaceRule, PageRule, StyleRule, SupportsRule, ViewportRule};
+use stylesheets::{NamespaceRule, StyleRule, SupportsRule, ViewportRule};
 use stylesheets::document_rule::DocumentCondition;
 use stylesheets::font_feature_values_rule::parse_family_name_list;
 use stylesheets::keyframes_rule::parse_keyframe_list;
+use stylesheets::page_rule::parse_page_rule_block;
 use stylesheets::stylesheet::Namespaces;
 use stylesheets::supports_rule::SupportsCondition;
 use stylesheets::viewport_rule;
@@ -427,11 +428,7 @@ impl<'a, 'b, 'i> AtRuleParser<'i> for NestedRuleParser<'a, 'b> {
                 Ok(AtRuleType::WithBlock(AtRuleBlockPrelude::Keyframes(name, prefix)))
             },
             "page" => {
-                if cfg!(feature = "gecko") {
-                    Ok(AtRuleType::WithBlock(AtRuleBlockPrelude::Page))
-                } else {
-                    Err(input.new_custom_error(StyleParseErrorKind::UnsupportedAtRule(name.clone())))
-                }
+                Ok(AtRuleType::WithBlock(AtRuleBlockPrelude::Page))
             },
             "-moz-document" => {
                 if !cfg!(feature = "gecko") {
@@ -545,11 +542,9 @@ impl<'a, 'b, 'i> AtRuleParser<'i> for NestedRuleParser<'a, 'b> {
                     self.namespaces,
                 );
 
-                let declarations = parse_property_declaration_list(&context, input);
-                Ok(CssRule::Page(Arc::new(self.shared_lock.wrap(PageRule {
-                    block: Arc::new(self.shared_lock.wrap(declarations)),
-                    source_location,
-                }))))
+                let rule =
+                    parse_page_rule_block(&context, input, source_location, self.shared_lock);
+                Ok(CssRule::Page(Arc::new(self.shared_lock.wrap(rule))))
             },
             AtRuleBlockPrelude::Document(condition) => {
                 if !cfg!(feature = "gecko") {
@@ -45,7 +45,7 @@ use stylesheet_set::{DataValidity, DocumentStylesheetSet, SheetRebuildKind};
 use stylesheet_set::{DocumentStylesheetFlusher, SheetCollectionFlusher};
 #[cfg(feature = "gecko")]
 use stylesheets::{CounterStyleRule, FontFaceRule, FontFeatureValuesRule, PageRule};
-use stylesheets::{CssRule, Origin, OriginSet, PerOrigin, PerOriginIter};
+use stylesheets::{CssRule, Origin, OriginSet, PageBox, PerOrigin, PerOriginIter};
 use stylesheets::StyleRule;
 use stylesheets::StylesheetInDocument;
 use stylesheets::keyframes_rule::KeyframesAnimation;
@@ -1041,6 +1041,19 @@ impl Stylist {
         self.media_features_change_changed_style(guards, &self.device)
     }
 
+    /// Computes the page box pages are printed on, applying the `@page` rules
+    /// that match the current device to `default`, in cascade order.
+    pub fn page_box(&self, guards: &StylesheetGuards, default: PageBox) -> PageBox {
+        let mut page_box = default;
+        for (stylesheet, origin) in self.stylesheets.iter() {
+            let guard = guards.for_origin(origin);
+            stylesheet.effective_page_rules(&self.device, guard, |rule| {
+                rule.apply_to(guard, &mut page_box);
+            });
+        }
+        page_box
+    }
+
     /// Returns whether, given a media feature change, any previously-applicable
     /// style has become non-applicable, or vice-versa for each origin, using
     /// `device`.
@@ -1807,6 +1820,9 @@ fn component_needs_revalidation(
         Component::FirstOfType |
         Component::LastOfType |
         Component::OnlyOfType => true,
+        // Whether :has() matches depends on the descendants and siblings of
+        // the element, which two elements sharing style may not have in common.
+        Component::Has(..) => true,
         Component::NonTSPseudoClass(ref p) => p.needs_cache_revalidation(),
         _ => false,
     }
@@ -1819,13 +1835,16 @@ impl<'a> SelectorVisitor for StylistSelectorVisitor<'a> {
         self.needs_revalidation =
             self.needs_revalidation || combinator.map_or(false, |c| c.is_sibling());
 
-        // NOTE(emilio): This works properly right now because we can't store
-        // complex selectors in nested selectors, otherwise we may need to
-        // rethink this.
-        //
-        // Also, note that this call happens before we visit any of the simple
+        // NOTE(emilio): This call happens before we visit any of the simple
         // selectors in the next ComplexSelector, so we can use this to skip
         // looking at them.
+        //
+        // Complex selectors nested in :is(), :where() and :has() get here too,
+        // so a combinator inside them makes us stop mapping ids earlier than
+        // we could. That only costs some style sharing, since unmapped ids
+        // make the selector a revalidation selector instead. Ids in a relative
+        // selector before its first combinator are mapped even though they
+        // belong to other elements, which is just as conservative.
         self.passed_rightmost_selector =
             self.passed_rightmost_selector ||
                 !matches!(combinator, None | Some(Combinator::PseudoElement));
@@ -1865,8 +1884,8 @@ impl<'a> SelectorVisitor for StylistSelectorVisitor<'a> {
                 // Importantly, this call happens before we visit any of the
                 // simple selectors in that ComplexSelector.
                 //
-                // NOTE(emilio): See the comment regarding on when this may
-                // break in visit_complex_selector.
+                // See the comment about nested selectors in
+                // visit_complex_selector.
                 self.mapped_ids.insert(id.clone());
             },
             _ => {},
@@ -24,6 +24,17 @@ fn in_ua_or_chrome_sheet(context: &ParserContext) -> bool {
     context.stylesheet_origin == Origin::UserAgent || context.chrome_rules_enabled()
 }
 
+#[cfg(feature = "gecko")]
+fn grid_enabled(_context: &ParserContext) -> bool {
+    true
+}
+
+#[cfg(feature = "servo")]
+fn grid_enabled(_context: &ParserContext) -> bool {
+    use servo_config::prefs::PREFS;
+    PREFS.get("layout.grid.enabled").as_boolean().unwrap_or(true)
+}
+
 #[cfg(feature = "gecko")]
 fn moz_display_values_enabled(context: &ParserContext) -> bool {
     use gecko_bindings::structs;
@@ -92,9 +103,9 @@ pub enum Display {
     Flex,
     #[parse(aliases = "-webkit-inline-flex")]
     InlineFlex,
-    #[cfg(feature = "gecko")]
+    #[parse(condition = "grid_enabled")]
     Grid,
-    #[cfg(feature = "gecko")]
+    #[parse(condition = "grid_enabled")]
     InlineGrid,
     #[cfg(feature = "gecko")]
     Ruby,
@@ -161,7 +172,6 @@ impl Display {
     pub fn is_item_container(&self) -> bool {
         match *self {
             Display::Flex | Display::InlineFlex => true,
-            #[cfg(feature = "gecko")]
             Display::Grid | Display::InlineGrid => true,
             _ => false,
         }
@@ -234,9 +244,8 @@ impl Display {
             // Values that have a corresponding block-outside version.
             Display::InlineTable => Display::Table,
             Display::InlineFlex => Display::Flex,
-
-            #[cfg(feature = "gecko")]
             Display::InlineGrid => Display::Grid,
+
             #[cfg(feature = "gecko")]
             Display::WebkitInlineBox => Display::WebkitBox,
 
@@ -249,12 +258,15 @@ impl Display {
             },
 
             // These are not changed by blockification.
-            Display::None | Display::Block | Display::Flex | Display::ListItem | Display::Table => {
-                *self
-            },
+            Display::None |
+            Display::Block |
+            Display::Flex |
+            Display::Grid |
+            Display::ListItem |
+            Display::Table => *self,
 
             #[cfg(feature = "gecko")]
-            Display::Contents | Display::FlowRoot | Display::Grid | Display::WebkitBox => *self,
+            Display::Contents | Display::FlowRoot | Display::WebkitBox => *self,
 
             // Everything else becomes block.
             _ => Display::Block,
@@ -41,7 +41,7 @@ impl SVGPathData {
 
     /// Create a normalized copy of this path by converting each relative command to an absolute
     /// command.
-    fn normalize(&self) -> Self {
+    pub fn normalize(&self) -> Self {
         let mut state = PathTraversalState {
             subpath_start: CoordPair::new(0.0, 0.0),
             pos: CoordPair::new(0.0, 0.0),
@@ -55,6 +55,27 @@ impl SVGPathData {
     }
 }
 
+impl SVGPathData {
+    /// Parse the path data of the `d` attribute of an SVG `<path>` element.
+    ///
+    /// Unlike the `d` property, an attribute with an error in its path data
+    /// still renders the commands before the error.
+    /// https://www.w3.org/TR/SVG11/implnote.html#PathElementImplementationNotes
+    pub fn parse_attribute(path_string: &str) -> Result<Self, ()> {
+        let mut path_parser = PathParser::new(path_string);
+        while skip_wsp(&mut path_parser.chars) {
+            if path_parser.parse_subpath().is_err() {
+                break;
+            }
+        }
+
+        if path_parser.path.is_empty() {
+            return Err(());
+        }
+        Ok(SVGPathData::new(path_parser.path.into_boxed_slice()))
+    }
+}
+
 impl ToCss for SVGPathData {
     #[inline]
     fn to_css<W>(&self, dest: &mut CssWriter<W>) -> fmt::Result
@@ -513,6 +534,18 @@ impl CoordPair {
     pub fn new(x: CSSFloat, y: CSSFloat) -> Self {
         CoordPair(x, y)
     }
+
+    /// The x coordinate.
+    #[inline]
+    pub fn x(&self) -> CSSFloat {
+        self.0
+    }
+
+    /// The y coordinate.
+    #[inline]
+    pub fn y(&self) -> CSSFloat {
+        self.1
+    }
 }
 
 impl AddAssign for CoordPair {
@@ -528,6 +561,14 @@ impl AddAssign for CoordPair {
 #[repr(C)]
 pub struct ArcFlag(bool);
 
+impl ArcFlag {
+    /// Whether the flag is set.
+    #[inline]
+    pub fn is_set(&self) -> bool {
+        self.0
+    }
+}
+
 impl ToCss for ArcFlag {
     #[inline]
     fn to_css<W>(&self, dest: &mut CssWriter<W>) -> fmt::Result
@@ -0,0 +1,673 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! SVG images, which are parsed and rasterized at their intrinsic size when
+//! they are decoded.
+//!
+//! Images are parsed as standalone documents with the XML parser. Their
+//! presentation attributes, `style` attributes and the simple selectors of
+//! their `<style>` elements are applied, and they are painted through the
+//! same scenes as inline `<svg>` elements.
+
+use cssparser::{Color, Parser, ParserInput, RGBA};
+use euclid::{Size2D, Transform2D};
+use net_traits::image::base::Image;
+use rasterizer::rasterize;
+use scene::{self, FillRule, Length, LineCap, LineJoin, SvgElement, SvgPaint, SvgStyle};
+use scene::{TextAnchor, ViewBox};
+use std::cmp;
+use std::collections::HashMap;
+use std::str;
+use xml5ever::driver::parse_document;
+use xml5ever::rcdom::{Handle, NodeData, RcDom};
+use xml5ever::tendril::TendrilSink;
+
+const SVG_NAMESPACE: &'static str = "http://www.w3.org/2000/svg";
+
+const XLINK_NAMESPACE: &'static str = "http://www.w3.org/1999/xlink";
+
+/// The depth beyond which elements are dropped while parsing, so that deeply
+/// nested documents can't exhaust the stack of the decoding thread.
+const MAX_NESTING: usize = 256;
+
+/// The size of SVG images which specify neither their size nor their view box.
+/// <https://www.w3.org/TR/CSS2/visudet.html#inline-replaced-width>
+const DEFAULT_SIZE: (f32, f32) = (300., 150.);
+
+/// The maximum width and height of a rasterized image, beyond which it is
+/// scaled down.
+const MAX_SIZE: f32 = 4096.;
+
+/// The properties which can be set by presentation attributes.
+/// <https://www.w3.org/TR/SVG11/styling.html#SVGStylingProperties>
+const PRESENTATION_ATTRIBUTES: &'static [&'static str] = &[
+    "color",
+    "display",
+    "fill",
+    "fill-opacity",
+    "fill-rule",
+    "opacity",
+    "stop-color",
+    "stop-opacity",
+    "stroke",
+    "stroke-dasharray",
+    "stroke-dashoffset",
+    "stroke-linecap",
+    "stroke-linejoin",
+    "stroke-miterlimit",
+    "stroke-opacity",
+    "stroke-width",
+    "text-anchor",
+    "visibility",
+];
+
+/// Whether a resource looks like an SVG document rather than a raster image.
+pub fn is_svg(buffer: &[u8]) -> bool {
+    let buffer = strip_bom(buffer);
+    let start = buffer.iter().position(|byte| !byte.is_ascii_whitespace());
+    match start {
+        Some(start) if buffer[start] == b'<' => {
+            let prefix = &buffer[start..cmp::min(buffer.len(), start + 4096)];
+            prefix.windows(4).any(|window| window == b"<svg" || window == b":svg")
+        },
+        _ => false,
+    }
+}
+
+fn strip_bom(buffer: &[u8]) -> &[u8] {
+    if buffer.starts_with(b"\xEF\xBB\xBF") {
+        &buffer[3..]
+    } else {
+        buffer
+    }
+}
+
+/// Parses an SVG document and rasterizes it at its intrinsic size.
+pub fn load_svg(buffer: &[u8]) -> Option<Image> {
+    let text = str::from_utf8(strip_bom(buffer)).ok()?;
+    let root = parse_xml(text)?;
+    if root.name != "svg" {
+        debug!("SVG image whose root element is a {}", root.name);
+        return None;
+    }
+    let document = Document::new(&root);
+    let root = DocumentElement {
+        element: &root,
+        document: &document,
+    };
+
+    let view_box = root
+        .attribute("viewBox")
+        .and_then(|view_box| ViewBox::parse(view_box, root.attribute("preserveAspectRatio")));
+    let size = intrinsic_size(&root, view_box.as_ref());
+    let scale = (MAX_SIZE / size.width.max(size.height)).min(1.);
+    let width = (size.width * scale).ceil().max(1.) as u32;
+    let height = (size.height * scale).ceil().max(1.) as u32;
+
+    let style = root.style(&SvgStyle::default()).unwrap_or_default();
+    // Percentages refer to the view box when there is one.
+    let viewport = view_box.map_or(size, |view_box| view_box.rect.size);
+    let (scene, _) = scene::build_scene(&root, &style, viewport);
+    let transform = match view_box {
+        Some(view_box) => view_box.transform(size),
+        None => Transform2D::identity(),
+    };
+    Some(rasterize(
+        &scene,
+        &transform.post_mul(&Transform2D::create_scale(scale, scale)),
+        width,
+        height,
+    ))
+}
+
+/// The size of an image, from the `width` and `height` attributes of its
+/// root element, or the size and aspect ratio of its view box.
+fn intrinsic_size(root: &DocumentElement, view_box: Option<&ViewBox>) -> Size2D<f32> {
+    let length = |name: &str| match root.attribute(name).and_then(Length::parse) {
+        Some(Length::Absolute(length)) if length > 0. && length.is_finite() => Some(length),
+        _ => None,
+    };
+    let (width, height) = match (length("width"), length("height"), view_box) {
+        (Some(width), Some(height), _) => (width, height),
+        (Some(width), None, Some(view_box)) => {
+            (width, width * view_box.rect.size.height / view_box.rect.size.width)
+        },
+        (None, Some(height), Some(view_box)) => {
+            (height * view_box.rect.size.width / view_box.rect.size.height, height)
+        },
+        (None, None, Some(view_box)) => (view_box.rect.size.width, view_box.rect.size.height),
+        (width, height, None) => (width.unwrap_or(DEFAULT_SIZE.0), height.unwrap_or(DEFAULT_SIZE.1)),
+    };
+    Size2D::new(width, height)
+}
+
+struct Element {
+    /// The local name of the element, without its prefix.
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Node>,
+}
+
+enum Node {
+    Element(Element),
+    Text(String),
+}
+
+impl Element {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        let find = |name: &str| {
+            self.attributes
+                .iter()
+                .find(|attribute| attribute.0 == name)
+                .map(|attribute| &*attribute.1)
+        };
+        match name {
+            "href" => find("href").or_else(|| find("xlink:href")),
+            _ => find(name),
+        }
+    }
+
+    fn append_text_content(&self, text: &mut String) {
+        for child in &self.children {
+            match *child {
+                Node::Element(ref element) => element.append_text_content(text),
+                Node::Text(ref data) => text.push_str(data),
+            }
+        }
+    }
+}
+
+/// Parses an XML document into its root element, or returns `None` if the
+/// document has no root element. Only elements in the SVG namespace are kept,
+/// along with the attributes in the null namespace and the XLink ones, whose
+/// names get an `xlink:` prefix.
+fn parse_xml(text: &str) -> Option<Element> {
+    let dom: RcDom = parse_document(RcDom::default(), Default::default()).one(text);
+    let root = dom
+        .document
+        .children
+        .borrow()
+        .iter()
+        .find(|node| match node.data {
+            NodeData::Element { .. } => true,
+            _ => false,
+        })
+        .cloned()?;
+    match convert_node(&root, 0)? {
+        Node::Element(element) => Some(element),
+        Node::Text(_) => None,
+    }
+}
+
+fn convert_node(node: &Handle, depth: usize) -> Option<Node> {
+    match node.data {
+        NodeData::Text { ref contents } => Some(Node::Text(contents.borrow().to_string())),
+        NodeData::Element { ref name, ref attrs, .. } => {
+            if &*name.ns != SVG_NAMESPACE || depth > MAX_NESTING {
+                return None;
+            }
+            let attributes = attrs
+                .borrow()
+                .iter()
+                .filter_map(|attribute| match &*attribute.name.ns {
+                    "" => Some((attribute.name.local.to_string(), attribute.value.to_string())),
+                    XLINK_NAMESPACE => {
+                        Some((format!("xlink:{}", &*attribute.name.local), attribute.value.to_string()))
+                    },
+                    _ => None,
+                })
+                .collect();
+            let children = node
+                .children
+                .borrow()
+                .iter()
+                .filter_map(|child| convert_node(child, depth + 1))
+                .collect();
+            Some(Node::Element(Element {
+                name: name.local.to_string(),
+                attributes,
+                children,
+            }))
+        },
+        _ => None,
+    }
+}
+
+/// A compound selector made of a type, an id and classes, which are the only
+/// selectors supported in the `<style>` elements of SVG images.
+struct Selector {
+    local_name: Option<String>,
+    id: Option<String>,
+    classes: Vec<String>,
+}
+
+fn is_name_character(character: char) -> bool {
+    character.is_alphanumeric() || character == '-' || character == '_'
+}
+
+impl Selector {
+    fn parse(text: &str) -> Option<Selector> {
+        let text = text.trim();
+        if text.is_empty() {
+            return None;
+        }
+        let name_end = text
+            .find(|c: char| !is_name_character(c) && c != '*')
+            .unwrap_or(text.len());
+        let mut selector = Selector {
+            local_name: match &text[..name_end] {
+                "" | "*" => None,
+                name => Some(name.to_owned()),
+            },
+            id: None,
+            classes: vec![],
+        };
+        let mut rest = &text[name_end..];
+        while let Some(kind) = rest.chars().next() {
+            if kind != '.' && kind != '#' {
+                return None;
+            }
+            let end = rest[1..]
+                .find(|c: char| !is_name_character(c))
+                .map_or(rest.len(), |end| end + 1);
+            let name = rest[1..end].to_owned();
+            if name.is_empty() {
+                return None;
+            }
+            if kind == '.' {
+                selector.classes.push(name);
+            } else {
+                selector.id = Some(name);
+            }
+            rest = &rest[end..];
+        }
+        Some(selector)
+    }
+
+    fn specificity(&self) -> u32 {
+        let ids = if self.id.is_some() { 1 } else { 0 };
+        let names = if self.local_name.is_some() { 1 } else { 0 };
+        ids << 20 | (self.classes.len() as u32) << 10 | names
+    }
+
+    fn matches(&self, element: &Element) -> bool {
+        if self.local_name.as_ref().map_or(false, |name| *name != element.name) {
+            return false;
+        }
+        if self.id.as_ref().map_or(false, |id| element.attribute("id") != Some(&**id)) {
+            return false;
+        }
+        let classes = element.attribute("class").unwrap_or("");
+        self.classes
+            .iter()
+            .all(|class| classes.split_whitespace().any(|c| c == class))
+    }
+}
+
+struct Rule {
+    selector: Selector,
+    declarations: Vec<(String, String)>,
+}
+
+/// Parses the declarations of a rule or of a `style` attribute.
+fn parse_declarations(text: &str) -> Vec<(String, String)> {
+    text.split(';')
+        .filter_map(|declaration| {
+            let colon = declaration.find(':')?;
+            let value = declaration[colon + 1..].trim();
+            let value = match value.find('!') {
+                Some(important) => value[..important].trim(),
+                None => value,
+            };
+            Some((declaration[..colon].trim().to_ascii_lowercase(), value.to_owned()))
+        })
+        .collect()
+}
+
+/// Appends the style rules of a style sheet whose selectors are supported.
+fn parse_style_sheet(text: &str, rules: &mut Vec<Rule>) {
+    let mut text = text.to_owned();
+    while let Some(start) = text.find("/*") {
+        let end = text[start..].find("*/").map_or(text.len(), |end| start + end + 2);
+        text.replace_range(start..end, " ");
+    }
+
+    let mut rest = &*text;
+    while let Some(open) = rest.find('{') {
+        let mut depth = 0;
+        let mut close = None;
+        for (index, character) in rest[open..].char_indices() {
+            match character {
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        close = Some(open + index);
+                        break;
+                    }
+                },
+                _ => {},
+            }
+        }
+        let close = match close {
+            Some(close) => close,
+            None => return,
+        };
+        // At-rules are skipped along with their block.
+        let prelude = rest[..open].rsplit(';').next().unwrap_or("").trim();
+        if !prelude.starts_with('@') {
+            let declarations = parse_declarations(&rest[open + 1..close]);
+            for selector in prelude.split(',').filter_map(Selector::parse) {
+                rules.push(Rule {
+                    selector,
+                    declarations: declarations.clone(),
+                });
+            }
+        }
+        rest = &rest[close + 1..];
+    }
+}
+
+/// The ids and style rules of an SVG image.
+struct Document<'a> {
+    ids: HashMap<&'a str, &'a Element>,
+    rules: Vec<Rule>,
+}
+
+impl<'a> Document<'a> {
+    fn new(root: &'a Element) -> Document<'a> {
+        let mut document = Document {
+            ids: HashMap::new(),
+            rules: vec![],
+        };
+        document.add_element(root);
+        document
+    }
+
+    fn add_element(&mut self, element: &'a Element) {
+        if let Some(id) = element.attribute("id") {
+            self.ids.entry(id).or_insert(element);
+        }
+        if element.name == "style" {
+            let mut text = String::new();
+            element.append_text_content(&mut text);
+            parse_style_sheet(&text, &mut self.rules);
+        }
+        for child in &element.children {
+            if let Node::Element(ref child) = *child {
+                self.add_element(child);
+            }
+        }
+    }
+
+    /// Cascades the declarations which apply to an element, in order of
+    /// precedence: presentation attributes, style rules by specificity, and
+    /// the `style` attribute.
+    fn style(&self, element: &Element, parent: &SvgStyle) -> Option<SvgStyle> {
+        let inline = element.attribute("style").map_or(vec![], parse_declarations);
+        let mut rules = self
+            .rules
+            .iter()
+            .filter(|rule| rule.selector.matches(element))
+            .collect::<Vec<_>>();
+        rules.sort_by_key(|rule| rule.selector.specificity());
+
+        let declarations = element
+            .attributes
+            .iter()
+            .filter(|attribute| PRESENTATION_ATTRIBUTES.iter().any(|name| *name == attribute.0))
+            .chain(rules.iter().flat_map(|rule| rule.declarations.iter()))
+            .chain(inline.iter())
+            .collect::<Vec<_>>();
+
+        let initial = SvgStyle::default();
+        let mut style = SvgStyle {
+            opacity: initial.opacity,
+            stop_color: initial.stop_color,
+            stop_opacity: initial.stop_opacity,
+            ..parent.clone()
+        };
+        let mut display_none = false;
+        // `currentColor` refers to the color of the element wherever it is set.
+        for &&(ref name, ref value) in declarations.iter().filter(|declaration| declaration.0 == "color") {
+            apply_declaration(&mut style, &mut display_none, parent, name, value);
+        }
+        for &&(ref name, ref value) in declarations.iter().filter(|declaration| declaration.0 != "color") {
+            apply_declaration(&mut style, &mut display_none, parent, name, value);
+        }
+        if display_none {
+            None
+        } else {
+            Some(style)
+        }
+    }
+}
+
+fn apply_declaration(
+    style: &mut SvgStyle,
+    display_none: &mut bool,
+    parent: &SvgStyle,
+    name: &str,
+    value: &str,
+) {
+    let value = value.trim();
+    if value == "inherit" {
+        return inherit_property(style, parent, name);
+    }
+    let current_color = style.color;
+    match name {
+        "color" => {
+            if let Some(color) = parse_color(value, parent.color) {
+                style.color = color;
+            }
+        },
+        "display" => *display_none = value == "none",
+        "fill" => {
+            if let Some(paint) = parse_paint(value, current_color) {
+                style.fill = paint;
+            }
+        },
+        "fill-opacity" => {
+            if let Some(opacity) = parse_opacity(value) {
+                style.fill_opacity = opacity;
+            }
+        },
+        "fill-rule" => match value {
+            "nonzero" => style.fill_rule = FillRule::NonZero,
+            "evenodd" => style.fill_rule = FillRule::EvenOdd,
+            _ => {},
+        },
+        "opacity" => {
+            if let Some(opacity) = parse_opacity(value) {
+                style.opacity = opacity;
+            }
+        },
+        "stop-color" => {
+            if let Some(color) = parse_color(value, current_color) {
+                style.stop_color = color;
+            }
+        },
+        "stop-opacity" => {
+            if let Some(opacity) = parse_opacity(value) {
+                style.stop_opacity = opacity;
+            }
+        },
+        "stroke" => {
+            if let Some(paint) = parse_paint(value, current_color) {
+                style.stroke = paint;
+            }
+        },
+        "stroke-dasharray" => {
+            if value == "none" {
+                style.stroke_dasharray = vec![];
+                return;
+            }
+            let dashes = value
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|dash| !dash.is_empty())
+                .map(Length::parse)
+                .collect::<Option<Vec<_>>>();
+            if let Some(dashes) = dashes {
+                style.stroke_dasharray = dashes;
+            }
+        },
+        "stroke-dashoffset" => {
+            if let Some(offset) = Length::parse(value) {
+                style.stroke_dashoffset = offset;
+            }
+        },
+        "stroke-linecap" => match value {
+            "butt" => style.stroke_linecap = LineCap::Butt,
+            "round" => style.stroke_linecap = LineCap::Round,
+            "square" => style.stroke_linecap = LineCap::Square,
+            _ => {},
+        },
+        "stroke-linejoin" => match value {
+            "miter" | "miter-clip" | "arcs" => style.stroke_linejoin = LineJoin::Miter,
+            "round" => style.stroke_linejoin = LineJoin::Round,
+            "bevel" => style.stroke_linejoin = LineJoin::Bevel,
+            _ => {},
+        },
+        "stroke-miterlimit" => match value.parse::<f32>() {
+            Ok(limit) if limit >= 1. => style.stroke_miterlimit = limit,
+            _ => {},
+        },
+        "stroke-opacity" => {
+            if let Some(opacity) = parse_opacity(value) {
+                style.stroke_opacity = opacity;
+            }
+        },
+        "stroke-width" => match Length::parse(value) {
+            Some(Length::Absolute(width)) if width < 0. => {},
+            Some(width) => style.stroke_width = width,
+            None => {},
+        },
+        "text-anchor" => match value {
+            "start" => style.text_anchor = TextAnchor::Start,
+            "middle" => style.text_anchor = TextAnchor::Middle,
+            "end" => style.text_anchor = TextAnchor::End,
+            _ => {},
+        },
+        "visibility" => match value {
+            "visible" => style.visible = true,
+            "hidden" | "collapse" => style.visible = false,
+            _ => {},
+        },
+        _ => {},
+    }
+}
+
+fn inherit_property(style: &mut SvgStyle, parent: &SvgStyle, name: &str) {
+    match name {
+        "color" => style.color = parent.color,
+        "fill" => style.fill = parent.fill.clone(),
+        "fill-opacity" => style.fill_opacity = parent.fill_opacity,
+        "fill-rule" => style.fill_rule = parent.fill_rule,
+        "opacity" => style.opacity = parent.opacity,
+        "stop-color" => style.stop_color = parent.stop_color,
+        "stop-opacity" => style.stop_opacity = parent.stop_opacity,
+        "stroke" => style.stroke = parent.stroke.clone(),
+        "stroke-dasharray" => style.stroke_dasharray = parent.stroke_dasharray.clone(),
+        "stroke-dashoffset" => style.stroke_dashoffset = parent.stroke_dashoffset,
+        "stroke-linecap" => style.stroke_linecap = parent.stroke_linecap,
+        "stroke-linejoin" => style.stroke_linejoin = parent.stroke_linejoin,
+        "stroke-miterlimit" => style.stroke_miterlimit = parent.stroke_miterlimit,
+        "stroke-opacity" => style.stroke_opacity = parent.stroke_opacity,
+        "stroke-width" => style.stroke_width = parent.stroke_width,
+        "text-anchor" => style.text_anchor = parent.text_anchor,
+        "visibility" => style.visible = parent.visible,
+        _ => {},
+    }
+}
+
+fn parse_color(value: &str, current_color: RGBA) -> Option<RGBA> {
+    let mut input = ParserInput::new(value);
+    let mut parser = Parser::new(&mut input);
+    let color = Color::parse(&mut parser).ok()?;
+    if !parser.is_exhausted() {
+        return None;
+    }
+    match color {
+        Color::RGBA(rgba) => Some(rgba),
+        Color::CurrentColor => Some(current_color),
+    }
+}
+
+/// <https://www.w3.org/TR/SVG11/painting.html#SpecifyingPaint>
+fn parse_paint(value: &str, current_color: RGBA) -> Option<SvgPaint> {
+    if value == "none" {
+        return Some(SvgPaint::None);
+    }
+    if !value.starts_with("url(") {
+        return parse_color(value, current_color).map(SvgPaint::Color);
+    }
+    let close = value.find(')')?;
+    let url = value[4..close].trim().trim_matches(|c| c == '"' || c == '\'');
+    let fallback = match value[close + 1..].trim() {
+        "" | "none" => None,
+        fallback => Some(parse_color(fallback, current_color)?),
+    };
+    if !url.starts_with('#') {
+        // Paint servers in other documents aren't loaded.
+        return Some(fallback.map_or(SvgPaint::None, SvgPaint::Color));
+    }
+    Some(SvgPaint::Server(url[1..].to_owned(), fallback))
+}
+
+fn parse_opacity(value: &str) -> Option<f32> {
+    let opacity = if value.ends_with('%') {
+        value[..value.len() - 1].parse::<f32>().ok()? / 100.
+    } else {
+        value.parse::<f32>().ok()?
+    };
+    Some(opacity.max(0.).min(1.))
+}
+
+/// An element of an SVG image, along with the document it belongs to.
+#[derive(Clone, Copy)]
+struct DocumentElement<'a> {
+    element: &'a Element,
+    document: &'a Document<'a>,
+}
+
+impl<'a> SvgElement for DocumentElement<'a> {
+    fn local_name(&self) -> &str {
+        &self.element.name
+    }
+
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.element.attribute(name)
+    }
+
+    fn children(&self) -> Vec<Self> {
+        self.element
+            .children
+            .iter()
+            .filter_map(|child| match *child {
+                Node::Element(ref element) => Some(DocumentElement {
+                    element,
+                    document: self.document,
+                }),
+                Node::Text(_) => None,
+            })
+            .collect()
+    }
+
+    fn text_content(&self) -> String {
+        let mut text = String::new();
+        self.element.append_text_content(&mut text);
+        text
+    }
+
+    fn style(&self, parent: &SvgStyle) -> Option<SvgStyle> {
+        self.document.style(self.element, parent)
+    }
+
+    fn element_by_id(&self, id: &str) -> Option<Self> {
+        self.document.ids.get(id).map(|element| DocumentElement {
+            element: *element,
+            document: self.document,
+        })
+    }
+}
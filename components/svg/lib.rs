@@ -0,0 +1,20 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! SVG content: the scenes painted for SVG images and inline `<svg>`
+//! elements, and the rasterizer they are painted with.
+
+#![deny(unsafe_code)]
+
+extern crate cssparser;
+extern crate euclid;
+extern crate ipc_channel;
+#[macro_use] extern crate log;
+extern crate net_traits;
+extern crate style;
+extern crate xml5ever;
+
+pub mod document;
+pub mod rasterizer;
+pub mod scene;
@@ -0,0 +1,702 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! A scanline rasterizer which paints the scenes of SVG content into images.
+//!
+//! Curves are flattened into polygons, strokes are converted into the
+//! polygons they cover, and polygons are filled with antialiasing by sampling
+//! several scanlines per row of pixels and computing the exact horizontal
+//! coverage of each of them.
+
+use cssparser::RGBA;
+use euclid::{Point2D, Transform2D, Vector2D};
+use ipc_channel::ipc::IpcSharedMemory;
+use net_traits::image::base::{Image, PixelFormat};
+use scene::{FillRule, GradientGeometry, GradientStop, GradientUnits, LineCap, LineJoin};
+use scene::{Paint, Path, PathSegment, Scene, Shape, SpreadMethod, StrokeStyle};
+use std::cmp::{self, Ordering};
+use std::f32;
+use std::f32::consts::PI;
+use std::mem;
+
+/// The number of scanlines sampled in each row of pixels.
+const SCANLINES_PER_PIXEL: usize = 8;
+
+/// The maximum distance between a curve and the polygon it is flattened
+/// into, in pixels.
+const TOLERANCE: f32 = 0.1;
+
+/// The number of colors computed for each gradient.
+const RAMP_SIZE: usize = 256;
+
+/// A color whose components are multiplied by its alpha, all between 0 and 1.
+type Premultiplied = [f32; 4];
+
+/// Paints a scene into a transparent image of the given size, with the given
+/// transform from the user space of the scene to the pixels of the image.
+pub fn rasterize(scene: &Scene, transform: &Transform2D<f32>, width: u32, height: u32) -> Image {
+    let mut canvas = Canvas {
+        width: width as usize,
+        height: height as usize,
+        pixels: vec![[0.; 4]; width as usize * height as usize],
+    };
+    for shape in &scene.shapes {
+        canvas.paint_shape(shape, &shape.transform.post_mul(transform));
+    }
+
+    let mut bytes = Vec::with_capacity(canvas.pixels.len() * 4);
+    for pixel in &canvas.pixels {
+        let alpha = pixel[3];
+        if alpha <= 0. {
+            bytes.extend_from_slice(&[0, 0, 0, 0]);
+            continue;
+        }
+        let to_byte = |value: f32| (value * 255.).round().max(0.).min(255.) as u8;
+        bytes.extend_from_slice(&[
+            to_byte(pixel[2] / alpha),
+            to_byte(pixel[1] / alpha),
+            to_byte(pixel[0] / alpha),
+            to_byte(alpha),
+        ]);
+    }
+    Image {
+        width,
+        height,
+        format: PixelFormat::BGRA8,
+        bytes: IpcSharedMemory::from_bytes(&bytes),
+        id: None,
+        frames: vec![],
+        play_count: None,
+    }
+}
+
+struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<Premultiplied>,
+}
+
+impl Canvas {
+    fn paint_shape(&mut self, shape: &Shape, transform: &Transform2D<f32>) {
+        if let Some(ref fill) = shape.fill {
+            if let Some(source) = Source::new(fill, &shape.path, transform) {
+                let polygons = flatten(&shape.path, transform, TOLERANCE)
+                    .into_iter()
+                    .map(|subpath| subpath.points)
+                    .collect::<Vec<_>>();
+                self.fill(&polygons, shape.fill_rule, &source);
+            }
+        }
+
+        if let Some(ref stroke) = shape.stroke {
+            if let Some(source) = Source::new(stroke, &shape.path, transform) {
+                // Strokes are outlined in the user space of the shape, where
+                // they have the same width everywhere.
+                let scale = (transform.m11 * transform.m22 - transform.m12 * transform.m21)
+                    .abs()
+                    .sqrt();
+                if scale <= 0. {
+                    return;
+                }
+                let tolerance = TOLERANCE / scale;
+                let subpaths = flatten(&shape.path, &Transform2D::identity(), tolerance);
+                let mut polygons = stroke_outline(&subpaths, &shape.stroke_style, tolerance);
+                for polygon in &mut polygons {
+                    for point in polygon.iter_mut() {
+                        *point = transform.transform_point(point);
+                    }
+                }
+                self.fill(&polygons, FillRule::NonZero, &source);
+            }
+        }
+    }
+
+    fn fill(&mut self, polygons: &[Vec<Point2D<f32>>], rule: FillRule, source: &Source) {
+        let mut edges = vec![];
+        for polygon in polygons {
+            for (index, start) in polygon.iter().enumerate() {
+                let end = polygon[(index + 1) % polygon.len()];
+                if let Some(edge) = Edge::new(*start, end) {
+                    edges.push(edge);
+                }
+            }
+        }
+        if edges.is_empty() {
+            return;
+        }
+        edges.sort_by(|a, b| a.top.partial_cmp(&b.top).unwrap_or(Ordering::Equal));
+
+        let (mut min_x, mut max_x, mut max_y) = (f32::INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY);
+        for edge in &edges {
+            let end_x = edge.x + (edge.bottom - edge.top) * edge.slope;
+            min_x = min_x.min(edge.x).min(end_x);
+            max_x = max_x.max(edge.x).max(end_x);
+            max_y = max_y.max(edge.bottom);
+        }
+        let first_column = cmp::min(cmp::max(min_x.floor() as i64, 0), self.width as i64) as usize;
+        let last_column = cmp::min(cmp::max(max_x.ceil() as i64, 0), self.width as i64) as usize;
+        let first_row = cmp::min(cmp::max(edges[0].top.floor() as i64, 0), self.height as i64) as usize;
+        let last_row = cmp::min(cmp::max(max_y.ceil() as i64, 0), self.height as i64) as usize;
+        if first_column >= last_column {
+            return;
+        }
+
+        // The coverage of the pixels of a row is the sum of their partial
+        // coverage and of the running sum of the deltas before them.
+        let columns = last_column - first_column;
+        let mut partial = vec![0.; columns + 1];
+        let mut deltas = vec![0.; columns + 1];
+        let mut active: Vec<usize> = vec![];
+        let mut next_edge = 0;
+        let mut crossings: Vec<(f32, i32)> = vec![];
+        let weight = 1. / SCANLINES_PER_PIXEL as f32;
+
+        for row in first_row..last_row {
+            for value in partial.iter_mut().chain(deltas.iter_mut()) {
+                *value = 0.;
+            }
+            for scanline in 0..SCANLINES_PER_PIXEL {
+                let y = row as f32 + (scanline as f32 + 0.5) * weight;
+                while next_edge < edges.len() && edges[next_edge].top <= y {
+                    active.push(next_edge);
+                    next_edge += 1;
+                }
+                active.retain(|&index| edges[index].bottom > y);
+
+                crossings.clear();
+                crossings.extend(active.iter().map(|&index| {
+                    let edge = &edges[index];
+                    (edge.x + (y - edge.top) * edge.slope, edge.winding)
+                }));
+                crossings.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+
+                let mut winding = 0;
+                for pair in crossings.windows(2) {
+                    winding += pair[0].1;
+                    let inside = match rule {
+                        FillRule::NonZero => winding != 0,
+                        FillRule::EvenOdd => winding % 2 != 0,
+                    };
+                    if !inside {
+                        continue;
+                    }
+                    let start = (pair[0].0 - first_column as f32).max(0.);
+                    let end = (pair[1].0 - first_column as f32).min(columns as f32);
+                    if end <= start {
+                        continue;
+                    }
+                    let (start_column, end_column) = (start as usize, end as usize);
+                    if start_column == end_column {
+                        partial[start_column] += (end - start) * weight;
+                        continue;
+                    }
+                    partial[start_column] += (start_column as f32 + 1. - start) * weight;
+                    deltas[start_column + 1] += weight;
+                    deltas[end_column] -= weight;
+                    partial[end_column] += (end - end_column as f32) * weight;
+                }
+            }
+
+            let mut running = 0.;
+            for column in 0..columns {
+                running += deltas[column];
+                let coverage = (partial[column] + running).min(1.);
+                if coverage <= 0.001 {
+                    continue;
+                }
+                let x = first_column + column;
+                let color = source.color_at(Point2D::new(x as f32 + 0.5, row as f32 + 0.5));
+                let pixel = &mut self.pixels[row * self.width + x];
+                let remaining = 1. - color[3] * coverage;
+                for channel in 0..4 {
+                    pixel[channel] = color[channel] * coverage + pixel[channel] * remaining;
+                }
+            }
+        }
+    }
+}
+
+/// A non-horizontal edge of a polygon, from its top to its bottom.
+struct Edge {
+    /// The horizontal position of the top of the edge.
+    x: f32,
+    top: f32,
+    bottom: f32,
+    /// The horizontal distance the edge moves by per pixel downwards.
+    slope: f32,
+    /// 1 if the edge goes downwards, -1 otherwise.
+    winding: i32,
+}
+
+impl Edge {
+    fn new(start: Point2D<f32>, end: Point2D<f32>) -> Option<Edge> {
+        if start.y == end.y || !(start.x + start.y + end.x + end.y).is_finite() {
+            return None;
+        }
+        let (top, bottom, winding) = if start.y < end.y {
+            (start, end, 1)
+        } else {
+            (end, start, -1)
+        };
+        Some(Edge {
+            x: top.x,
+            top: top.y,
+            bottom: bottom.y,
+            slope: (bottom.x - top.x) / (bottom.y - top.y),
+            winding,
+        })
+    }
+}
+
+/// How the pixels covered by a polygon are colored.
+enum Source {
+    Color(Premultiplied),
+    Gradient {
+        /// The transform from the pixels of the image to the coordinates of
+        /// the gradient.
+        inverse: Transform2D<f32>,
+        geometry: GradientGeometry,
+        spread: SpreadMethod,
+        ramp: Vec<Premultiplied>,
+    },
+}
+
+impl Source {
+    /// The source for a paint of the given path, or `None` if nothing is
+    /// painted.
+    fn new(paint: &Paint, path: &Path, transform: &Transform2D<f32>) -> Option<Source> {
+        let gradient = match *paint {
+            Paint::Color(color) => return Some(Source::Color(premultiply(color))),
+            Paint::Gradient(ref gradient) => gradient,
+        };
+        let mut gradient_transform = gradient.transform;
+        if gradient.units == GradientUnits::ObjectBoundingBox {
+            // https://www.w3.org/TR/SVG11/coords.html#ObjectBoundingBoxUnits
+            let bounds = path.bounds();
+            if bounds.size.width <= 0. || bounds.size.height <= 0. {
+                return None;
+            }
+            gradient_transform = gradient_transform.post_mul(&Transform2D::row_major(
+                bounds.size.width,
+                0.,
+                0.,
+                bounds.size.height,
+                bounds.origin.x,
+                bounds.origin.y,
+            ));
+        }
+
+        let geometry = match gradient.geometry {
+            GradientGeometry::Radial { center, radius, focus } => {
+                // A focus outside of the circle is moved onto it.
+                // https://www.w3.org/TR/SVG11/pservers.html#RadialGradientElementFXAttribute
+                let offset = focus - center;
+                let distance = offset.length();
+                let focus = if distance > radius * 0.99 && distance > 0. {
+                    center + offset * (radius * 0.99 / distance)
+                } else {
+                    focus
+                };
+                GradientGeometry::Radial { center, radius, focus }
+            },
+            geometry => geometry,
+        };
+        Some(Source::Gradient {
+            inverse: gradient_transform.post_mul(transform).inverse()?,
+            geometry,
+            spread: gradient.spread,
+            ramp: ramp(&gradient.stops),
+        })
+    }
+
+    fn color_at(&self, point: Point2D<f32>) -> Premultiplied {
+        let (inverse, geometry, spread, ramp) = match *self {
+            Source::Color(color) => return color,
+            Source::Gradient { ref inverse, geometry, spread, ref ramp } => (inverse, geometry, spread, ramp),
+        };
+        let point = inverse.transform_point(&point);
+        let offset = match geometry {
+            GradientGeometry::Linear { start, end } => {
+                let direction = end - start;
+                let length = direction.dot(direction);
+                if length == 0. {
+                    1.
+                } else {
+                    (point - start).dot(direction) / length
+                }
+            },
+            GradientGeometry::Radial { center, radius, focus } => {
+                // The offset of a point is the one of the circle going through
+                // it among those interpolated from the focus to the gradient
+                // circle.
+                let to_center = center - focus;
+                let to_point = point - focus;
+                let a = to_center.dot(to_center) - radius * radius;
+                let b = to_point.dot(to_center);
+                let c = to_point.dot(to_point);
+                if radius <= 0. {
+                    1.
+                } else if a.abs() < 1e-6 {
+                    if b <= 0. { 0. } else { c / (2. * b) }
+                } else {
+                    (b - (b * b - a * c).max(0.).sqrt()) / a
+                }
+            },
+        };
+        let offset = match spread {
+            SpreadMethod::Pad => offset.max(0.).min(1.),
+            SpreadMethod::Repeat => offset - offset.floor(),
+            SpreadMethod::Reflect => {
+                let offset = (offset % 2. + 2.) % 2.;
+                if offset > 1. { 2. - offset } else { offset }
+            },
+        };
+        ramp[(offset * (RAMP_SIZE - 1) as f32).round() as usize]
+    }
+}
+
+fn premultiply(color: RGBA) -> Premultiplied {
+    let alpha = color.alpha_f32();
+    [color.red_f32() * alpha, color.green_f32() * alpha, color.blue_f32() * alpha, alpha]
+}
+
+/// The colors of a gradient at evenly spaced offsets. Colors are interpolated
+/// before being premultiplied, as SVG requires.
+fn ramp(stops: &[GradientStop]) -> Vec<Premultiplied> {
+    let channels = |color: RGBA| [color.red_f32(), color.green_f32(), color.blue_f32(), color.alpha_f32()];
+    (0..RAMP_SIZE)
+        .map(|index| {
+            let offset = index as f32 / (RAMP_SIZE - 1) as f32;
+            let next = stops.iter().position(|stop| stop.offset > offset);
+            let color = match next {
+                None => channels(stops[stops.len() - 1].color),
+                Some(0) => channels(stops[0].color),
+                Some(next) => {
+                    let (start, end) = (&stops[next - 1], &stops[next]);
+                    let t = (offset - start.offset) / (end.offset - start.offset);
+                    let (start, end) = (channels(start.color), channels(end.color));
+                    [
+                        start[0] + (end[0] - start[0]) * t,
+                        start[1] + (end[1] - start[1]) * t,
+                        start[2] + (end[2] - start[2]) * t,
+                        start[3] + (end[3] - start[3]) * t,
+                    ]
+                },
+            };
+            [color[0] * color[3], color[1] * color[3], color[2] * color[3], color[3]]
+        })
+        .collect()
+}
+
+/// A polyline, which is closed if the subpath it was flattened from was.
+struct Subpath {
+    points: Vec<Point2D<f32>>,
+    closed: bool,
+}
+
+/// Flattens the curves of a path once transformed.
+fn flatten(path: &Path, transform: &Transform2D<f32>, tolerance: f32) -> Vec<Subpath> {
+    let mut subpaths = vec![];
+    let mut current: Option<Subpath> = None;
+    let mut last = Point2D::zero();
+    {
+        let mut finish = |subpath: Subpath| {
+            // A lone move command has no length, and isn't stroked.
+            if subpath.points.len() > 1 || subpath.closed {
+                subpaths.push(subpath);
+            }
+        };
+        for segment in path.segments() {
+            match *segment {
+                PathSegment::MoveTo(point) => {
+                    if let Some(subpath) = current.take() {
+                        finish(subpath);
+                    }
+                    last = transform.transform_point(&point);
+                    current = Some(Subpath { points: vec![last], closed: false });
+                },
+                PathSegment::LineTo(point) => {
+                    let start = last;
+                    last = transform.transform_point(&point);
+                    current
+                        .get_or_insert_with(|| Subpath { points: vec![start], closed: false })
+                        .points
+                        .push(last);
+                },
+                PathSegment::CubicTo(control1, control2, point) => {
+                    let start = last;
+                    last = transform.transform_point(&point);
+                    let points = &mut current
+                        .get_or_insert_with(|| Subpath { points: vec![start], closed: false })
+                        .points;
+                    flatten_cubic(
+                        start,
+                        transform.transform_point(&control1),
+                        transform.transform_point(&control2),
+                        last,
+                        tolerance,
+                        points,
+                    );
+                },
+                PathSegment::Close => {
+                    if let Some(mut subpath) = current.take() {
+                        subpath.closed = true;
+                        last = subpath.points[0];
+                        finish(subpath);
+                    }
+                },
+            }
+        }
+        if let Some(subpath) = current.take() {
+            finish(subpath);
+        }
+    }
+    subpaths
+}
+
+/// Appends the points of a cubic curve flattened into segments short enough
+/// for the curve to stay within the tolerance of them.
+fn flatten_cubic(
+    start: Point2D<f32>,
+    control1: Point2D<f32>,
+    control2: Point2D<f32>,
+    end: Point2D<f32>,
+    tolerance: f32,
+    points: &mut Vec<Point2D<f32>>,
+) {
+    let second_difference = |a: Point2D<f32>, b: Point2D<f32>, c: Point2D<f32>| {
+        Vector2D::new(a.x - 2. * b.x + c.x, a.y - 2. * b.y + c.y).length()
+    };
+    let curvature = second_difference(start, control1, control2).max(second_difference(control1, control2, end));
+    let count = ((0.75 * curvature / tolerance).sqrt().ceil() as usize).max(1).min(256);
+    for index in 1..count + 1 {
+        let t = index as f32 / count as f32;
+        let u = 1. - t;
+        let (a, b, c, d) = (u * u * u, 3. * u * u * t, 3. * u * t * t, t * t * t);
+        points.push(Point2D::new(
+            a * start.x + b * control1.x + c * control2.x + d * end.x,
+            a * start.y + b * control1.y + c * control2.y + d * end.y,
+        ));
+    }
+}
+
+/// The polygons covered by the stroke of flattened subpaths, in their space.
+/// <https://www.w3.org/TR/SVG11/painting.html#StrokeProperties>
+fn stroke_outline(subpaths: &[Subpath], style: &StrokeStyle, tolerance: f32) -> Vec<Vec<Point2D<f32>>> {
+    let dashed;
+    let subpaths = if style.dashes.is_empty() {
+        subpaths
+    } else {
+        dashed = dash(subpaths, &style.dashes, style.dash_offset);
+        &dashed
+    };
+
+    let half_width = style.width / 2.;
+    let mut polygons = vec![];
+    for subpath in subpaths {
+        let mut points: Vec<Point2D<f32>> = vec![];
+        for point in &subpath.points {
+            if points.last().map_or(true, |last| (*point - *last).length() > 1e-6) {
+                points.push(*point);
+            }
+        }
+        if subpath.closed && points.len() > 1 && (points[0] - points[points.len() - 1]).length() <= 1e-6 {
+            points.pop();
+        }
+
+        if points.len() == 1 {
+            // Zero-length subpaths are painted as dots by round and square caps.
+            let point = points[0];
+            match style.cap {
+                LineCap::Butt => {},
+                LineCap::Round => push_polygon(&mut polygons, circle(point, half_width, tolerance)),
+                LineCap::Square => push_polygon(
+                    &mut polygons,
+                    vec![
+                        Point2D::new(point.x - half_width, point.y - half_width),
+                        Point2D::new(point.x + half_width, point.y - half_width),
+                        Point2D::new(point.x + half_width, point.y + half_width),
+                        Point2D::new(point.x - half_width, point.y + half_width),
+                    ],
+                ),
+            }
+            continue;
+        }
+
+        let count = points.len();
+        let closed = subpath.closed && count > 2;
+        let segments = if closed { count } else { count - 1 };
+        for index in 0..segments {
+            let (start, end) = (points[index], points[(index + 1) % count]);
+            let normal = normal(end - start) * half_width;
+            push_polygon(&mut polygons, vec![start + normal, end + normal, end - normal, start - normal]);
+        }
+
+        let joins = if closed { 0..count } else { 1..count - 1 };
+        for index in joins {
+            let previous = points[(index + count - 1) % count];
+            let next = points[(index + 1) % count];
+            join(previous, points[index], next, style, half_width, tolerance, &mut polygons);
+        }
+
+        if !closed {
+            cap(points[0], points[1], style.cap, half_width, tolerance, &mut polygons);
+            cap(points[count - 1], points[count - 2], style.cap, half_width, tolerance, &mut polygons);
+        }
+    }
+    polygons
+}
+
+/// Adds the join of two consecutive segments meeting at a point, on the
+/// outer side of the turn.
+fn join(
+    previous: Point2D<f32>,
+    point: Point2D<f32>,
+    next: Point2D<f32>,
+    style: &StrokeStyle,
+    half_width: f32,
+    tolerance: f32,
+    polygons: &mut Vec<Vec<Point2D<f32>>>,
+) {
+    let incoming = (point - previous).normalize();
+    let outgoing = (next - point).normalize();
+    let cross = incoming.cross(outgoing);
+    if cross.abs() < 1e-6 && incoming.dot(outgoing) > 0. {
+        return;
+    }
+    if style.join == LineJoin::Round {
+        return push_polygon(polygons, circle(point, half_width, tolerance));
+    }
+
+    let outer = if cross > 0. { -1. } else { 1. };
+    let (normal_in, normal_out) = (normal(incoming) * outer, normal(outgoing) * outer);
+    let (start, end) = (point + normal_in * half_width, point + normal_out * half_width);
+    let bisector = normal_in + normal_out;
+    if style.join == LineJoin::Miter && bisector.length() > 1e-6 {
+        let bisector = bisector.normalize();
+        let ratio = 1. / bisector.dot(normal_in);
+        if ratio <= style.miter_limit {
+            let tip = point + bisector * (half_width * ratio);
+            return push_polygon(polygons, vec![point, start, tip, end]);
+        }
+    }
+    push_polygon(polygons, vec![point, start, end]);
+}
+
+/// Adds the cap at the end of an open subpath, whose last segment comes from
+/// the given point.
+fn cap(
+    end: Point2D<f32>,
+    from: Point2D<f32>,
+    cap: LineCap,
+    half_width: f32,
+    tolerance: f32,
+    polygons: &mut Vec<Vec<Point2D<f32>>>,
+) {
+    match cap {
+        LineCap::Butt => {},
+        LineCap::Round => push_polygon(polygons, circle(end, half_width, tolerance)),
+        LineCap::Square => {
+            let direction = (end - from).normalize() * half_width;
+            let normal = normal(end - from) * half_width;
+            push_polygon(
+                polygons,
+                vec![end + normal, end + normal + direction, end - normal + direction, end - normal],
+            );
+        },
+    }
+}
+
+/// The unit vector perpendicular to the given one.
+fn normal(vector: Vector2D<f32>) -> Vector2D<f32> {
+    let vector = vector.normalize();
+    Vector2D::new(-vector.y, vector.x)
+}
+
+fn circle(center: Point2D<f32>, radius: f32, tolerance: f32) -> Vec<Point2D<f32>> {
+    let count = if tolerance < radius {
+        (PI / (1. - tolerance / radius).acos()).ceil() as usize
+    } else {
+        0
+    };
+    let count = count.max(8).min(256);
+    (0..count)
+        .map(|index| {
+            let (sin, cos) = (index as f32 * 2. * PI / count as f32).sin_cos();
+            Point2D::new(center.x + radius * cos, center.y + radius * sin)
+        })
+        .collect()
+}
+
+/// Adds a polygon to the ones of a stroke, which are all oriented the same
+/// way so that overlapping ones don't cancel each other out.
+fn push_polygon(polygons: &mut Vec<Vec<Point2D<f32>>>, mut polygon: Vec<Point2D<f32>>) {
+    let area: f32 = (0..polygon.len())
+        .map(|index| {
+            let (a, b) = (polygon[index], polygon[(index + 1) % polygon.len()]);
+            a.x * b.y - b.x * a.y
+        })
+        .sum();
+    if area < 0. {
+        polygon.reverse();
+    }
+    polygons.push(polygon);
+}
+
+/// Splits subpaths into the dashes of a dash pattern.
+/// <https://www.w3.org/TR/SVG11/painting.html#StrokeDasharrayProperty>
+fn dash(subpaths: &[Subpath], dashes: &[f32], offset: f32) -> Vec<Subpath> {
+    let mut pattern = dashes.to_vec();
+    if pattern.len() % 2 == 1 {
+        pattern.extend_from_slice(dashes);
+    }
+    let total = pattern.iter().sum::<f32>();
+
+    let mut result = vec![];
+    for subpath in subpaths {
+        let mut points = subpath.points.clone();
+        if subpath.closed {
+            let first = points[0];
+            points.push(first);
+        }
+
+        let mut index = 0;
+        let mut phase = (offset % total + total) % total;
+        while phase >= pattern[index] {
+            phase -= pattern[index];
+            index = (index + 1) % pattern.len();
+        }
+        let mut remaining = pattern[index] - phase;
+        let mut current = if index % 2 == 0 { vec![points[0]] } else { vec![] };
+
+        for segment in points.windows(2) {
+            let (mut start, end) = (segment[0], segment[1]);
+            let mut length = (end - start).length();
+            while length > remaining {
+                let split = start + (end - start) * (remaining / length);
+                if index % 2 == 0 {
+                    current.push(split);
+                    result.push(Subpath {
+                        points: mem::replace(&mut current, vec![]),
+                        closed: false,
+                    });
+                } else {
+                    current = vec![split];
+                }
+                length -= remaining;
+                start = split;
+                index = (index + 1) % pattern.len();
+                remaining = pattern[index];
+            }
+            remaining -= length;
+            if index % 2 == 0 {
+                current.push(end);
+            }
+        }
+        if current.len() > 1 {
+            result.push(Subpath { points: current, closed: false });
+        }
+    }
+    result
+}
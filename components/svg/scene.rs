@@ -0,0 +1,1410 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! The scenes painted for SVG content, and the geometry they are built from.
+//!
+//! Scenes are built from an element tree through the `SvgElement` trait, which
+//! is implemented both for the documents of SVG images and for the inline
+//! `<svg>` elements laid out by layout, so that both render the same way.
+
+use cssparser::RGBA;
+use euclid::{Point2D, Rect, Size2D, Transform2D};
+use std::f32;
+use std::f32::consts::PI;
+use std::str;
+use style::values::specified::svg_path::{PathCommand, SVGPathData};
+
+/// The maximum depth of nested elements and references followed when
+/// building a scene.
+const MAX_DEPTH: usize = 32;
+
+/// The maximum number of elements expanded into a scene, counting every copy
+/// made by `<use>` elements, so that references which fan out can't make the
+/// scene grow exponentially with the size of the document.
+const MAX_ELEMENTS: usize = 100_000;
+
+/// The maximum number of shapes of a scene.
+const MAX_SHAPES: usize = 10_000;
+
+/// The distance between the end points and the control points of the cubic
+/// curves approximating a quarter of a circle of radius 1.
+const KAPPA: f32 = 0.552_284_8;
+
+/// A vector image, in the user space of its root `<svg>` element.
+#[derive(Clone, Debug, Default)]
+pub struct Scene {
+    /// The shapes of the image, in painting order.
+    pub shapes: Vec<Shape>,
+}
+
+/// A path which is filled and stroked.
+#[derive(Clone, Debug)]
+pub struct Shape {
+    /// The outline of the shape, in its own user space.
+    pub path: Path,
+    /// The transform from the user space of the shape to the one of the scene.
+    pub transform: Transform2D<f32>,
+    pub fill: Option<Paint>,
+    pub fill_rule: FillRule,
+    pub stroke: Option<Paint>,
+    pub stroke_style: StrokeStyle,
+}
+
+/// How a shape is filled or stroked.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Paint {
+    Color(RGBA),
+    Gradient(Gradient),
+}
+
+impl Paint {
+    /// Returns this paint with its alpha multiplied by the given opacity.
+    pub fn with_opacity(self, opacity: f32) -> Paint {
+        if opacity >= 1. {
+            return self;
+        }
+        match self {
+            Paint::Color(color) => Paint::Color(multiply_alpha(color, opacity)),
+            Paint::Gradient(mut gradient) => {
+                for stop in &mut gradient.stops {
+                    stop.color = multiply_alpha(stop.color, opacity);
+                }
+                Paint::Gradient(gradient)
+            },
+        }
+    }
+}
+
+fn multiply_alpha(color: RGBA, opacity: f32) -> RGBA {
+    let alpha = color.alpha as f32 * opacity.max(0.).min(1.);
+    RGBA::new(color.red, color.green, color.blue, alpha.round() as u8)
+}
+
+/// <https://www.w3.org/TR/SVG11/pservers.html#Gradients>
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gradient {
+    pub geometry: GradientGeometry,
+    /// The stops of the gradient, whose offsets are between 0 and 1 and never
+    /// decrease.
+    pub stops: Vec<GradientStop>,
+    pub units: GradientUnits,
+    /// The `gradientTransform` of the gradient.
+    pub transform: Transform2D<f32>,
+    pub spread: SpreadMethod,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GradientGeometry {
+    Linear {
+        start: Point2D<f32>,
+        end: Point2D<f32>,
+    },
+    Radial {
+        center: Point2D<f32>,
+        radius: f32,
+        focus: Point2D<f32>,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GradientStop {
+    pub offset: f32,
+    pub color: RGBA,
+}
+
+/// <https://www.w3.org/TR/SVG11/pservers.html#LinearGradientElementGradientUnitsAttribute>
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GradientUnits {
+    /// The geometry of the gradient is relative to the bounding box of the
+    /// painted shape.
+    ObjectBoundingBox,
+    /// The geometry of the gradient is in the user space of the painted shape.
+    UserSpaceOnUse,
+}
+
+/// <https://www.w3.org/TR/SVG11/pservers.html#LinearGradientElementSpreadMethodAttribute>
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SpreadMethod {
+    Pad,
+    Reflect,
+    Repeat,
+}
+
+/// <https://www.w3.org/TR/SVG11/painting.html#FillRuleProperty>
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FillRule {
+    NonZero,
+    EvenOdd,
+}
+
+/// <https://www.w3.org/TR/SVG11/painting.html#StrokeLinecapProperty>
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LineCap {
+    Butt,
+    Round,
+    Square,
+}
+
+/// <https://www.w3.org/TR/SVG11/painting.html#StrokeLinejoinProperty>
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LineJoin {
+    Miter,
+    Round,
+    Bevel,
+}
+
+/// The geometry of the stroke of a shape, in its user space.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StrokeStyle {
+    pub width: f32,
+    pub cap: LineCap,
+    pub join: LineJoin,
+    pub miter_limit: f32,
+    /// The lengths of the dashes and gaps of the stroke, empty if it is solid.
+    pub dashes: Vec<f32>,
+    pub dash_offset: f32,
+}
+
+impl Default for StrokeStyle {
+    fn default() -> StrokeStyle {
+        StrokeStyle {
+            width: 1.,
+            cap: LineCap::Butt,
+            join: LineJoin::Miter,
+            miter_limit: 4.,
+            dashes: vec![],
+            dash_offset: 0.,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PathSegment {
+    MoveTo(Point2D<f32>),
+    LineTo(Point2D<f32>),
+    CubicTo(Point2D<f32>, Point2D<f32>, Point2D<f32>),
+    Close,
+}
+
+/// An outline made of lines and cubic curves, which every SVG shape and path
+/// command is converted to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Path {
+    segments: Vec<PathSegment>,
+    current: Point2D<f32>,
+    subpath_start: Point2D<f32>,
+}
+
+impl Path {
+    pub fn new() -> Path {
+        Path {
+            segments: vec![],
+            current: Point2D::zero(),
+            subpath_start: Point2D::zero(),
+        }
+    }
+
+    pub fn segments(&self) -> &[PathSegment] {
+        &self.segments
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    pub fn move_to(&mut self, point: Point2D<f32>) {
+        self.segments.push(PathSegment::MoveTo(point));
+        self.current = point;
+        self.subpath_start = point;
+    }
+
+    pub fn line_to(&mut self, point: Point2D<f32>) {
+        self.segments.push(PathSegment::LineTo(point));
+        self.current = point;
+    }
+
+    pub fn cubic_to(&mut self, control1: Point2D<f32>, control2: Point2D<f32>, point: Point2D<f32>) {
+        self.segments.push(PathSegment::CubicTo(control1, control2, point));
+        self.current = point;
+    }
+
+    pub fn quad_to(&mut self, control: Point2D<f32>, point: Point2D<f32>) {
+        let from = self.current;
+        let control1 = from + (control - from) * (2. / 3.);
+        let control2 = point + (control - point) * (2. / 3.);
+        self.cubic_to(control1, control2, point);
+    }
+
+    /// Adds an elliptical arc from the current point, approximated by at most
+    /// four cubic curves.
+    /// <https://www.w3.org/TR/SVG11/implnote.html#ArcImplementationNotes>
+    pub fn arc_to(
+        &mut self,
+        rx: f32,
+        ry: f32,
+        x_axis_rotation: f32,
+        large_arc: bool,
+        sweep: bool,
+        point: Point2D<f32>,
+    ) {
+        let from = self.current;
+        if from == point {
+            return;
+        }
+        let (mut rx, mut ry) = (rx.abs(), ry.abs());
+        if rx == 0. || ry == 0. {
+            return self.line_to(point);
+        }
+
+        let (sin_phi, cos_phi) = x_axis_rotation.to_radians().sin_cos();
+        let dx = (from.x - point.x) / 2.;
+        let dy = (from.y - point.y) / 2.;
+        let x1 = cos_phi * dx + sin_phi * dy;
+        let y1 = -sin_phi * dx + cos_phi * dy;
+
+        // Scale the radii up if they are too small to join both points.
+        let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+        if lambda > 1. {
+            rx *= lambda.sqrt();
+            ry *= lambda.sqrt();
+        }
+
+        let numerator = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+        let denominator = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+        let mut coefficient = (numerator / denominator).max(0.).sqrt();
+        if large_arc == sweep {
+            coefficient = -coefficient;
+        }
+        let cx1 = coefficient * rx * y1 / ry;
+        let cy1 = -coefficient * ry * x1 / rx;
+        let cx = cos_phi * cx1 - sin_phi * cy1 + (from.x + point.x) / 2.;
+        let cy = sin_phi * cx1 + cos_phi * cy1 + (from.y + point.y) / 2.;
+
+        let start_angle = vector_angle(1., 0., (x1 - cx1) / rx, (y1 - cy1) / ry);
+        let mut sweep_angle = vector_angle(
+            (x1 - cx1) / rx,
+            (y1 - cy1) / ry,
+            (-x1 - cx1) / rx,
+            (-y1 - cy1) / ry,
+        );
+        if !sweep && sweep_angle > 0. {
+            sweep_angle -= 2. * PI;
+        } else if sweep && sweep_angle < 0. {
+            sweep_angle += 2. * PI;
+        }
+
+        let point_on_ellipse = |x: f32, y: f32| {
+            Point2D::new(
+                cx + rx * x * cos_phi - ry * y * sin_phi,
+                cy + rx * x * sin_phi + ry * y * cos_phi,
+            )
+        };
+        let count = (sweep_angle.abs() / (PI / 2.)).ceil().max(1.) as usize;
+        let step = sweep_angle / count as f32;
+        let k = 4. / 3. * (step / 4.).tan();
+        let mut angle = start_angle;
+        for i in 0..count {
+            let (sin1, cos1) = angle.sin_cos();
+            let (sin2, cos2) = (angle + step).sin_cos();
+            let end = if i + 1 == count {
+                point
+            } else {
+                point_on_ellipse(cos2, sin2)
+            };
+            self.cubic_to(
+                point_on_ellipse(cos1 - k * sin1, sin1 + k * cos1),
+                point_on_ellipse(cos2 + k * sin2, sin2 - k * cos2),
+                end,
+            );
+            angle += step;
+        }
+    }
+
+    pub fn close(&mut self) {
+        self.segments.push(PathSegment::Close);
+        self.current = self.subpath_start;
+    }
+
+    /// The outline described by the path data of a `<path>` element.
+    pub fn from_path_data(data: &SVGPathData) -> Path {
+        let mut path = Path::new();
+        let mut last_cubic_control = None;
+        let mut last_quad_control = None;
+        for command in data.normalize().commands() {
+            let current = path.current;
+            let reflect = |control: Option<Point2D<f32>>| {
+                control.map_or(current, |c| Point2D::new(2. * current.x - c.x, 2. * current.y - c.y))
+            };
+            let (cubic_control, quad_control) = match *command {
+                PathCommand::MoveTo { point, .. } => {
+                    path.move_to(Point2D::new(point.x(), point.y()));
+                    (None, None)
+                },
+                PathCommand::LineTo { point, .. } => {
+                    path.line_to(Point2D::new(point.x(), point.y()));
+                    (None, None)
+                },
+                PathCommand::HorizontalLineTo { x, .. } => {
+                    path.line_to(Point2D::new(x, current.y));
+                    (None, None)
+                },
+                PathCommand::VerticalLineTo { y, .. } => {
+                    path.line_to(Point2D::new(current.x, y));
+                    (None, None)
+                },
+                PathCommand::CurveTo { control1, control2, point, .. } => {
+                    let control2 = Point2D::new(control2.x(), control2.y());
+                    path.cubic_to(
+                        Point2D::new(control1.x(), control1.y()),
+                        control2,
+                        Point2D::new(point.x(), point.y()),
+                    );
+                    (Some(control2), None)
+                },
+                PathCommand::SmoothCurveTo { control2, point, .. } => {
+                    let control2 = Point2D::new(control2.x(), control2.y());
+                    path.cubic_to(
+                        reflect(last_cubic_control),
+                        control2,
+                        Point2D::new(point.x(), point.y()),
+                    );
+                    (Some(control2), None)
+                },
+                PathCommand::QuadBezierCurveTo { control1, point, .. } => {
+                    let control1 = Point2D::new(control1.x(), control1.y());
+                    path.quad_to(control1, Point2D::new(point.x(), point.y()));
+                    (None, Some(control1))
+                },
+                PathCommand::SmoothQuadBezierCurveTo { point, .. } => {
+                    let control1 = reflect(last_quad_control);
+                    path.quad_to(control1, Point2D::new(point.x(), point.y()));
+                    (None, Some(control1))
+                },
+                PathCommand::EllipticalArc {
+                    rx,
+                    ry,
+                    angle,
+                    large_arc_flag,
+                    sweep_flag,
+                    point,
+                    ..
+                } => {
+                    path.arc_to(
+                        rx,
+                        ry,
+                        angle,
+                        large_arc_flag.is_set(),
+                        sweep_flag.is_set(),
+                        Point2D::new(point.x(), point.y()),
+                    );
+                    (None, None)
+                },
+                PathCommand::ClosePath => {
+                    path.close();
+                    (None, None)
+                },
+                PathCommand::Unknown => (None, None),
+            };
+            last_cubic_control = cubic_control;
+            last_quad_control = quad_control;
+        }
+        path
+    }
+
+    /// The outline of a `<rect>` element, whose corners are rounded if both
+    /// radii are positive.
+    pub fn rect(rect: Rect<f32>, rx: f32, ry: f32) -> Path {
+        let mut path = Path::new();
+        let (x, y, width, height) = (rect.origin.x, rect.origin.y, rect.size.width, rect.size.height);
+        let rx = rx.min(width / 2.);
+        let ry = ry.min(height / 2.);
+        if rx <= 0. || ry <= 0. {
+            path.move_to(Point2D::new(x, y));
+            path.line_to(Point2D::new(x + width, y));
+            path.line_to(Point2D::new(x + width, y + height));
+            path.line_to(Point2D::new(x, y + height));
+            path.close();
+            return path;
+        }
+
+        let (kx, ky) = (rx * KAPPA, ry * KAPPA);
+        let (right, bottom) = (x + width, y + height);
+        path.move_to(Point2D::new(x + rx, y));
+        path.line_to(Point2D::new(right - rx, y));
+        path.cubic_to(
+            Point2D::new(right - rx + kx, y),
+            Point2D::new(right, y + ry - ky),
+            Point2D::new(right, y + ry),
+        );
+        path.line_to(Point2D::new(right, bottom - ry));
+        path.cubic_to(
+            Point2D::new(right, bottom - ry + ky),
+            Point2D::new(right - rx + kx, bottom),
+            Point2D::new(right - rx, bottom),
+        );
+        path.line_to(Point2D::new(x + rx, bottom));
+        path.cubic_to(
+            Point2D::new(x + rx - kx, bottom),
+            Point2D::new(x, bottom - ry + ky),
+            Point2D::new(x, bottom - ry),
+        );
+        path.line_to(Point2D::new(x, y + ry));
+        path.cubic_to(
+            Point2D::new(x, y + ry - ky),
+            Point2D::new(x + rx - kx, y),
+            Point2D::new(x + rx, y),
+        );
+        path.close();
+        path
+    }
+
+    /// The outline of an `<ellipse>` or `<circle>` element.
+    pub fn ellipse(center: Point2D<f32>, rx: f32, ry: f32) -> Path {
+        let mut path = Path::new();
+        let (cx, cy) = (center.x, center.y);
+        let (kx, ky) = (rx * KAPPA, ry * KAPPA);
+        path.move_to(Point2D::new(cx + rx, cy));
+        path.cubic_to(
+            Point2D::new(cx + rx, cy + ky),
+            Point2D::new(cx + kx, cy + ry),
+            Point2D::new(cx, cy + ry),
+        );
+        path.cubic_to(
+            Point2D::new(cx - kx, cy + ry),
+            Point2D::new(cx - rx, cy + ky),
+            Point2D::new(cx - rx, cy),
+        );
+        path.cubic_to(
+            Point2D::new(cx - rx, cy - ky),
+            Point2D::new(cx - kx, cy - ry),
+            Point2D::new(cx, cy - ry),
+        );
+        path.cubic_to(
+            Point2D::new(cx + kx, cy - ry),
+            Point2D::new(cx + rx, cy - ky),
+            Point2D::new(cx + rx, cy),
+        );
+        path.close();
+        path
+    }
+
+    /// The outline of a `<line>`, `<polyline>` or `<polygon>` element.
+    pub fn polyline(points: &[Point2D<f32>], closed: bool) -> Path {
+        let mut path = Path::new();
+        for (index, point) in points.iter().enumerate() {
+            if index == 0 {
+                path.move_to(*point);
+            } else {
+                path.line_to(*point);
+            }
+        }
+        if closed && !points.is_empty() {
+            path.close();
+        }
+        path
+    }
+
+    /// A rectangle containing the whole path, including the control points of
+    /// its curves.
+    pub fn bounds(&self) -> Rect<f32> {
+        let mut min = Point2D::new(f32::INFINITY, f32::INFINITY);
+        let mut max = Point2D::new(f32::NEG_INFINITY, f32::NEG_INFINITY);
+        {
+            let mut add = |point: &Point2D<f32>| {
+                min = Point2D::new(min.x.min(point.x), min.y.min(point.y));
+                max = Point2D::new(max.x.max(point.x), max.y.max(point.y));
+            };
+            for segment in &self.segments {
+                match *segment {
+                    PathSegment::MoveTo(ref point) | PathSegment::LineTo(ref point) => add(point),
+                    PathSegment::CubicTo(ref control1, ref control2, ref point) => {
+                        add(control1);
+                        add(control2);
+                        add(point);
+                    },
+                    PathSegment::Close => {},
+                }
+            }
+        }
+        if min.x > max.x {
+            return Rect::zero();
+        }
+        Rect::new(min, Size2D::new(max.x - min.x, max.y - min.y))
+    }
+}
+
+/// The signed angle from one vector to another.
+fn vector_angle(ux: f32, uy: f32, vx: f32, vy: f32) -> f32 {
+    (ux * vy - uy * vx).atan2(ux * vx + uy * vy)
+}
+
+/// A length in a geometry attribute of an SVG element.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Length {
+    /// A length in user units.
+    Absolute(f32),
+    /// A percentage of a dimension of the viewport.
+    Percentage(f32),
+}
+
+impl Length {
+    /// Parses a length, where units are converted to user units assuming a
+    /// font size of 16px.
+    pub fn parse(value: &str) -> Option<Length> {
+        let value = value.trim();
+        let mut position = 0;
+        let number = parse_number(value.as_bytes(), &mut position)?;
+        let factor = match &*value[position..].to_ascii_lowercase() {
+            "" | "px" => 1.,
+            "%" => return Some(Length::Percentage(number)),
+            "pt" => 4. / 3.,
+            "pc" => 16.,
+            "mm" => 96. / 25.4,
+            "cm" => 96. / 2.54,
+            "in" => 96.,
+            "em" => 16.,
+            "ex" => 8.,
+            _ => return None,
+        };
+        Some(Length::Absolute(number * factor))
+    }
+
+    /// Resolves this length against the dimension percentages refer to.
+    pub fn resolve(&self, reference: f32) -> f32 {
+        match *self {
+            Length::Absolute(length) => length,
+            Length::Percentage(percentage) => percentage / 100. * reference,
+        }
+    }
+}
+
+/// The dimension of a viewport that percentages of lengths which are neither
+/// horizontal nor vertical refer to.
+/// <https://www.w3.org/TR/SVG11/coords.html#Units>
+pub fn normalized_diagonal(size: Size2D<f32>) -> f32 {
+    ((size.width * size.width + size.height * size.height) / 2.).sqrt()
+}
+
+fn is_separator(byte: u8) -> bool {
+    byte == b',' || byte.is_ascii_whitespace()
+}
+
+fn skip_separators(bytes: &[u8], position: &mut usize) {
+    while *position < bytes.len() && is_separator(bytes[*position]) {
+        *position += 1;
+    }
+}
+
+/// Parses a number at the given position of an attribute, and moves the
+/// position past it.
+fn parse_number(bytes: &[u8], position: &mut usize) -> Option<f32> {
+    let start = *position;
+    let mut end = start;
+    let skip_digits = |end: &mut usize| {
+        let digits_start = *end;
+        while *end < bytes.len() && bytes[*end].is_ascii_digit() {
+            *end += 1;
+        }
+        *end > digits_start
+    };
+
+    if end < bytes.len() && (bytes[end] == b'+' || bytes[end] == b'-') {
+        end += 1;
+    }
+    let mut has_digits = skip_digits(&mut end);
+    if end < bytes.len() && bytes[end] == b'.' {
+        end += 1;
+        has_digits |= skip_digits(&mut end);
+    }
+    if !has_digits {
+        return None;
+    }
+    if end < bytes.len() && (bytes[end] == b'e' || bytes[end] == b'E') {
+        let mut exponent = end + 1;
+        if exponent < bytes.len() && (bytes[exponent] == b'+' || bytes[exponent] == b'-') {
+            exponent += 1;
+        }
+        if skip_digits(&mut exponent) {
+            end = exponent;
+        }
+    }
+
+    *position = end;
+    str::from_utf8(&bytes[start..end]).ok()?.parse().ok()
+}
+
+/// Parses a list of numbers separated by whitespace and commas, like the
+/// value of a `viewBox` attribute. The numbers before an error are kept.
+pub fn parse_number_list(value: &str) -> Vec<f32> {
+    let bytes = value.as_bytes();
+    let mut position = 0;
+    let mut numbers = vec![];
+    loop {
+        skip_separators(bytes, &mut position);
+        match parse_number(bytes, &mut position) {
+            Some(number) => numbers.push(number),
+            None => return numbers,
+        }
+    }
+}
+
+/// Parses the `points` attribute of a `<polyline>` or `<polygon>` element.
+/// <https://www.w3.org/TR/SVG11/shapes.html#PointsBNF>
+pub fn parse_points(value: &str) -> Vec<Point2D<f32>> {
+    parse_number_list(value)
+        .chunks(2)
+        .filter(|pair| pair.len() == 2)
+        .map(|pair| Point2D::new(pair[0], pair[1]))
+        .collect()
+}
+
+/// Parses a `transform` attribute into the transform from the user space it
+/// establishes to the one of the parent element.
+/// <https://www.w3.org/TR/SVG11/coords.html#TransformAttribute>
+pub fn parse_transform(value: &str) -> Option<Transform2D<f32>> {
+    let mut result = Transform2D::identity();
+    let mut rest = value.trim_left_matches(|c: char| c == ',' || c.is_whitespace());
+    while !rest.is_empty() {
+        let open = rest.find('(')?;
+        let close = rest.find(')')?;
+        if close < open {
+            return None;
+        }
+        let name = rest[..open].trim();
+        let arguments = parse_number_list(&rest[open + 1..close]);
+        let transform = match (name, arguments.len()) {
+            ("matrix", 6) => Transform2D::row_major(
+                arguments[0],
+                arguments[1],
+                arguments[2],
+                arguments[3],
+                arguments[4],
+                arguments[5],
+            ),
+            ("translate", 1) => Transform2D::create_translation(arguments[0], 0.),
+            ("translate", 2) => Transform2D::create_translation(arguments[0], arguments[1]),
+            ("scale", 1) => Transform2D::create_scale(arguments[0], arguments[0]),
+            ("scale", 2) => Transform2D::create_scale(arguments[0], arguments[1]),
+            ("rotate", 1) | ("rotate", 3) => {
+                let (sin, cos) = arguments[0].to_radians().sin_cos();
+                let rotation = Transform2D::row_major(cos, sin, -sin, cos, 0., 0.);
+                if arguments.len() == 3 {
+                    Transform2D::create_translation(-arguments[1], -arguments[2])
+                        .post_mul(&rotation)
+                        .post_mul(&Transform2D::create_translation(arguments[1], arguments[2]))
+                } else {
+                    rotation
+                }
+            },
+            ("skewX", 1) => {
+                Transform2D::row_major(1., 0., arguments[0].to_radians().tan(), 1., 0., 0.)
+            },
+            ("skewY", 1) => {
+                Transform2D::row_major(1., arguments[0].to_radians().tan(), 0., 1., 0., 0.)
+            },
+            _ => return None,
+        };
+        // Transforms later in the list apply first.
+        result = transform.post_mul(&result);
+        rest = rest[close + 1..].trim_left_matches(|c: char| c == ',' || c.is_whitespace());
+    }
+    Some(result)
+}
+
+/// <https://www.w3.org/TR/SVG11/coords.html#PreserveAspectRatioAttribute>
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PreserveAspectRatio {
+    /// The horizontal and vertical alignment of the view box, or `None` if it
+    /// is stretched to the viewport.
+    pub align: Option<(Align, Align)>,
+    /// Whether the view box covers the viewport rather than fitting in it.
+    pub slice: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Align {
+    Min,
+    Mid,
+    Max,
+}
+
+impl Default for PreserveAspectRatio {
+    fn default() -> PreserveAspectRatio {
+        PreserveAspectRatio {
+            align: Some((Align::Mid, Align::Mid)),
+            slice: false,
+        }
+    }
+}
+
+impl PreserveAspectRatio {
+    /// Parses a `preserveAspectRatio` attribute, falling back to the initial
+    /// value if it is invalid.
+    pub fn parse(value: &str) -> PreserveAspectRatio {
+        let mut words = value.split_whitespace();
+        let mut word = words.next();
+        if word == Some("defer") {
+            word = words.next();
+        }
+        let align = match word {
+            Some("none") => None,
+            Some(word) if word.len() == 8 && word.starts_with('x') && word[4..].starts_with('Y') => {
+                let parse_align = |align: &str| match align {
+                    "Min" => Some(Align::Min),
+                    "Mid" => Some(Align::Mid),
+                    "Max" => Some(Align::Max),
+                    _ => None,
+                };
+                match (parse_align(&word[1..4]), parse_align(&word[5..8])) {
+                    (Some(x), Some(y)) => Some((x, y)),
+                    _ => return PreserveAspectRatio::default(),
+                }
+            },
+            _ => return PreserveAspectRatio::default(),
+        };
+        let slice = match words.next() {
+            None | Some("meet") => false,
+            Some("slice") => true,
+            Some(_) => return PreserveAspectRatio::default(),
+        };
+        PreserveAspectRatio { align, slice }
+    }
+}
+
+/// The `viewBox` and `preserveAspectRatio` attributes of an element which
+/// establishes a new viewport.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ViewBox {
+    pub rect: Rect<f32>,
+    pub preserve_aspect_ratio: PreserveAspectRatio,
+}
+
+impl ViewBox {
+    /// Parses the given attributes, returning `None` if the view box is
+    /// invalid or empty, in which case it is ignored.
+    pub fn parse(view_box: &str, preserve_aspect_ratio: Option<&str>) -> Option<ViewBox> {
+        let numbers = parse_number_list(view_box);
+        if numbers.len() != 4 || numbers[2] <= 0. || numbers[3] <= 0. {
+            return None;
+        }
+        Some(ViewBox {
+            rect: Rect::new(
+                Point2D::new(numbers[0], numbers[1]),
+                Size2D::new(numbers[2], numbers[3]),
+            ),
+            preserve_aspect_ratio: preserve_aspect_ratio
+                .map_or(PreserveAspectRatio::default(), PreserveAspectRatio::parse),
+        })
+    }
+
+    /// The transform from the view box to a viewport of the given size.
+    pub fn transform(&self, viewport: Size2D<f32>) -> Transform2D<f32> {
+        let mut scale_x = viewport.width / self.rect.size.width;
+        let mut scale_y = viewport.height / self.rect.size.height;
+        let mut offset = (0., 0.);
+        if let Some((align_x, align_y)) = self.preserve_aspect_ratio.align {
+            let scale = if self.preserve_aspect_ratio.slice {
+                scale_x.max(scale_y)
+            } else {
+                scale_x.min(scale_y)
+            };
+            scale_x = scale;
+            scale_y = scale;
+            let align_offset = |align: Align, space: f32| match align {
+                Align::Min => 0.,
+                Align::Mid => space / 2.,
+                Align::Max => space,
+            };
+            offset = (
+                align_offset(align_x, viewport.width - self.rect.size.width * scale),
+                align_offset(align_y, viewport.height - self.rect.size.height * scale),
+            );
+        }
+        Transform2D::row_major(
+            scale_x,
+            0.,
+            0.,
+            scale_y,
+            offset.0 - self.rect.origin.x * scale_x,
+            offset.1 - self.rect.origin.y * scale_y,
+        )
+    }
+}
+
+/// The paint of the `fill` or `stroke` property, with `currentColor` resolved.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SvgPaint {
+    None,
+    Color(RGBA),
+    /// A reference to a gradient by its id, along with the color used if the
+    /// reference is invalid.
+    Server(String, Option<RGBA>),
+}
+
+/// <https://www.w3.org/TR/SVG11/text.html#TextAnchorProperty>
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextAnchor {
+    Start,
+    Middle,
+    End,
+}
+
+/// The computed values of the properties used to paint an SVG element.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SvgStyle {
+    pub color: RGBA,
+    pub fill: SvgPaint,
+    pub fill_opacity: f32,
+    pub fill_rule: FillRule,
+    pub stroke: SvgPaint,
+    pub stroke_opacity: f32,
+    pub stroke_width: Length,
+    pub stroke_linecap: LineCap,
+    pub stroke_linejoin: LineJoin,
+    pub stroke_miterlimit: f32,
+    pub stroke_dasharray: Vec<Length>,
+    pub stroke_dashoffset: Length,
+    pub text_anchor: TextAnchor,
+    /// Whether `visibility` is `visible`.
+    pub visible: bool,
+    pub opacity: f32,
+    pub stop_color: RGBA,
+    pub stop_opacity: f32,
+}
+
+impl Default for SvgStyle {
+    /// The initial values of the properties.
+    fn default() -> SvgStyle {
+        let black = RGBA::new(0, 0, 0, 255);
+        SvgStyle {
+            color: black,
+            fill: SvgPaint::Color(black),
+            fill_opacity: 1.,
+            fill_rule: FillRule::NonZero,
+            stroke: SvgPaint::None,
+            stroke_opacity: 1.,
+            stroke_width: Length::Absolute(1.),
+            stroke_linecap: LineCap::Butt,
+            stroke_linejoin: LineJoin::Miter,
+            stroke_miterlimit: 4.,
+            stroke_dasharray: vec![],
+            stroke_dashoffset: Length::Absolute(0.),
+            text_anchor: TextAnchor::Start,
+            visible: true,
+            opacity: 1.,
+            stop_color: black,
+            stop_opacity: 1.,
+        }
+    }
+}
+
+/// An element of an SVG document that scenes are built from.
+pub trait SvgElement: Clone {
+    fn local_name(&self) -> &str;
+
+    /// The value of the attribute with the given name in the null namespace.
+    /// `href` also finds `xlink:href` attributes.
+    fn attribute(&self, name: &str) -> Option<&str>;
+
+    /// The child elements of the element.
+    fn children(&self) -> Vec<Self>;
+
+    fn text_content(&self) -> String;
+
+    /// The style of the element, given the one of the element it inherits
+    /// from when it is rendered, or `None` if it isn't rendered at all.
+    fn style(&self, parent: &SvgStyle) -> Option<SvgStyle>;
+
+    /// Finds the element with the given id in the document of this element.
+    fn element_by_id(&self, id: &str) -> Option<Self>;
+}
+
+/// A `<text>` element of a scene, which is shaped and painted by the caller.
+#[derive(Clone, Debug)]
+pub struct SvgText<E> {
+    pub element: E,
+    /// The text content of the element, with its whitespace collapsed.
+    pub text: String,
+    /// The start of the baseline of the text, in the user space of the element.
+    pub position: Point2D<f32>,
+    pub anchor: TextAnchor,
+    pub color: RGBA,
+    /// The transform from the user space of the element to the one of the scene.
+    pub transform: Transform2D<f32>,
+}
+
+#[derive(Clone, Copy)]
+enum Axis {
+    Horizontal,
+    Vertical,
+    Other,
+}
+
+/// Builds the scene painted for the children of the given `<svg>` element,
+/// whose percentages refer to a viewport of the given size.
+pub fn build_scene<E: SvgElement>(
+    root: &E,
+    style: &SvgStyle,
+    viewport: Size2D<f32>,
+) -> (Scene, Vec<SvgText<E>>) {
+    let mut builder = SceneBuilder {
+        root: root.clone(),
+        viewport,
+        scene: Scene::default(),
+        texts: vec![],
+        expanded: 0,
+        references: vec![],
+    };
+    for child in root.children() {
+        builder.add_element(&child, style, &Transform2D::identity(), 1., 0);
+    }
+    (builder.scene, builder.texts)
+}
+
+struct SceneBuilder<E> {
+    root: E,
+    viewport: Size2D<f32>,
+    scene: Scene,
+    texts: Vec<SvgText<E>>,
+    /// The number of elements expanded so far.
+    expanded: usize,
+    /// The ids of the elements being expanded, which `<use>` elements can't
+    /// reference without creating a cycle.
+    references: Vec<String>,
+}
+
+impl<E: SvgElement> SceneBuilder<E> {
+    fn resolve(&self, length: Length, axis: Axis) -> f32 {
+        length.resolve(match axis {
+            Axis::Horizontal => self.viewport.width,
+            Axis::Vertical => self.viewport.height,
+            Axis::Other => normalized_diagonal(self.viewport),
+        })
+    }
+
+    fn length(&self, element: &E, name: &str, axis: Axis, default: Length) -> f32 {
+        let length = element.attribute(name).and_then(Length::parse).unwrap_or(default);
+        self.resolve(length, axis)
+    }
+
+    /// The id referenced by the `href` attribute of an element, if it refers
+    /// to an element of the same document.
+    fn href_id<'a>(&self, element: &'a E) -> Option<&'a str> {
+        let href = element.attribute("href")?.trim();
+        if !href.starts_with('#') {
+            return None;
+        }
+        Some(&href[1..])
+    }
+
+    fn element_by_href(&self, element: &E) -> Option<E> {
+        self.root.element_by_id(self.href_id(element)?)
+    }
+
+    fn add_element(
+        &mut self,
+        element: &E,
+        parent_style: &SvgStyle,
+        transform: &Transform2D<f32>,
+        opacity: f32,
+        depth: usize,
+    ) {
+        if depth > MAX_DEPTH {
+            return;
+        }
+        if self.expanded == MAX_ELEMENTS {
+            warn!("Too many elements in an SVG scene, dropping the rest of its content");
+        }
+        self.expanded += 1;
+        if self.expanded > MAX_ELEMENTS {
+            return;
+        }
+        match element.attribute("id") {
+            Some(id) => {
+                self.references.push(id.to_owned());
+                self.add_element_content(element, parent_style, transform, opacity, depth);
+                self.references.pop();
+            },
+            None => self.add_element_content(element, parent_style, transform, opacity, depth),
+        }
+    }
+
+    fn add_element_content(
+        &mut self,
+        element: &E,
+        parent_style: &SvgStyle,
+        transform: &Transform2D<f32>,
+        opacity: f32,
+        depth: usize,
+    ) {
+        let style = match element.style(parent_style) {
+            Some(style) => style,
+            None => return,
+        };
+        let mut transform = match element.attribute("transform").and_then(parse_transform) {
+            Some(element_transform) => element_transform.post_mul(transform),
+            None => *transform,
+        };
+        // Group opacity is approximated by applying it to every shape of the
+        // group.
+        let opacity = opacity * style.opacity;
+
+        match element.local_name() {
+            "g" | "a" | "switch" => {},
+            "svg" => {
+                let x = self.length(element, "x", Axis::Horizontal, Length::Absolute(0.));
+                let y = self.length(element, "y", Axis::Vertical, Length::Absolute(0.));
+                let width = self.length(element, "width", Axis::Horizontal, Length::Percentage(100.));
+                let height = self.length(element, "height", Axis::Vertical, Length::Percentage(100.));
+                transform = Transform2D::create_translation(x, y).post_mul(&transform);
+                let view_box = element
+                    .attribute("viewBox")
+                    .and_then(|view_box| ViewBox::parse(view_box, element.attribute("preserveAspectRatio")));
+                if let Some(view_box) = view_box {
+                    transform = view_box.transform(Size2D::new(width, height)).post_mul(&transform);
+                }
+            },
+            "use" => return self.add_use(element, &style, &transform, opacity, depth),
+            "text" => return self.add_text(element, &style, &transform, opacity),
+            name => {
+                if let Some(path) = self.shape_path(element, name) {
+                    self.add_shape(path, &style, transform, opacity);
+                }
+                return;
+            },
+        }
+
+        for child in element.children() {
+            self.add_element(&child, &style, &transform, opacity, depth + 1);
+        }
+    }
+
+    /// <https://www.w3.org/TR/SVG11/struct.html#UseElement>
+    fn add_use(
+        &mut self,
+        element: &E,
+        style: &SvgStyle,
+        transform: &Transform2D<f32>,
+        opacity: f32,
+        depth: usize,
+    ) {
+        let id = match self.href_id(element) {
+            Some(id) => id,
+            None => return,
+        };
+        // A reference to an element being expanded, or to one of its
+        // ancestors, is an error which disables the `<use>` element.
+        if self.references.iter().any(|reference| reference == id) {
+            debug!("Cyclic reference to #{} in an SVG scene", id);
+            return;
+        }
+        let target = match self.root.element_by_id(id) {
+            Some(target) => target,
+            None => return,
+        };
+        let x = self.length(element, "x", Axis::Horizontal, Length::Absolute(0.));
+        let y = self.length(element, "y", Axis::Vertical, Length::Absolute(0.));
+        let transform = Transform2D::create_translation(x, y).post_mul(transform);
+
+        if target.local_name() != "symbol" {
+            return self.add_element(&target, style, &transform, opacity, depth + 1);
+        }
+
+        let symbol_style = match target.style(style) {
+            Some(symbol_style) => symbol_style,
+            None => return,
+        };
+        let width = self.length(element, "width", Axis::Horizontal, Length::Percentage(100.));
+        let height = self.length(element, "height", Axis::Vertical, Length::Percentage(100.));
+        let view_box = target
+            .attribute("viewBox")
+            .and_then(|view_box| ViewBox::parse(view_box, target.attribute("preserveAspectRatio")));
+        let transform = match view_box {
+            Some(view_box) => view_box.transform(Size2D::new(width, height)).post_mul(&transform),
+            None => transform,
+        };
+        self.references.push(id.to_owned());
+        for child in target.children() {
+            self.add_element(&child, &symbol_style, &transform, opacity, depth + 1);
+        }
+        self.references.pop();
+    }
+
+    fn add_text(&mut self, element: &E, style: &SvgStyle, transform: &Transform2D<f32>, opacity: f32) {
+        let color = match style.fill {
+            SvgPaint::Color(color) | SvgPaint::Server(_, Some(color)) => color,
+            SvgPaint::None | SvgPaint::Server(_, None) => return,
+        };
+        let text = element.text_content().split_whitespace().collect::<Vec<_>>().join(" ");
+        if !style.visible || text.is_empty() {
+            return;
+        }
+        let position = {
+            // Only the first position of a list applies to the whole text.
+            let coordinate = |name: &str, axis: Axis| {
+                let length = element
+                    .attribute(name)
+                    .and_then(|value| {
+                        value
+                            .split(|c: char| c == ',' || c.is_whitespace())
+                            .find(|value| !value.is_empty())
+                    })
+                    .and_then(Length::parse)
+                    .unwrap_or(Length::Absolute(0.));
+                self.resolve(length, axis)
+            };
+            Point2D::new(coordinate("x", Axis::Horizontal), coordinate("y", Axis::Vertical))
+        };
+        self.texts.push(SvgText {
+            element: element.clone(),
+            text,
+            position,
+            anchor: style.text_anchor,
+            color: multiply_alpha(color, style.fill_opacity * opacity),
+            transform: *transform,
+        });
+    }
+
+    /// The outline of a basic shape or path element, or `None` if the element
+    /// isn't one or doesn't render.
+    /// <https://www.w3.org/TR/SVG11/shapes.html>
+    fn shape_path(&self, element: &E, name: &str) -> Option<Path> {
+        let zero = Length::Absolute(0.);
+        let path = match name {
+            "rect" => {
+                let width = self.length(element, "width", Axis::Horizontal, zero);
+                let height = self.length(element, "height", Axis::Vertical, zero);
+                if width <= 0. || height <= 0. {
+                    return None;
+                }
+                let rect = Rect::new(
+                    Point2D::new(
+                        self.length(element, "x", Axis::Horizontal, zero),
+                        self.length(element, "y", Axis::Vertical, zero),
+                    ),
+                    Size2D::new(width, height),
+                );
+                let radius = |name: &str, axis: Axis| {
+                    element
+                        .attribute(name)
+                        .and_then(Length::parse)
+                        .map(|length| self.resolve(length, axis))
+                };
+                let (rx, ry) = match (radius("rx", Axis::Horizontal), radius("ry", Axis::Vertical)) {
+                    (Some(rx), Some(ry)) => (rx, ry),
+                    (Some(radius), None) | (None, Some(radius)) => (radius, radius),
+                    (None, None) => (0., 0.),
+                };
+                Path::rect(rect, rx, ry)
+            },
+            "circle" | "ellipse" => {
+                let center = Point2D::new(
+                    self.length(element, "cx", Axis::Horizontal, zero),
+                    self.length(element, "cy", Axis::Vertical, zero),
+                );
+                let (rx, ry) = if name == "circle" {
+                    let r = self.length(element, "r", Axis::Other, zero);
+                    (r, r)
+                } else {
+                    (
+                        self.length(element, "rx", Axis::Horizontal, zero),
+                        self.length(element, "ry", Axis::Vertical, zero),
+                    )
+                };
+                if rx <= 0. || ry <= 0. {
+                    return None;
+                }
+                Path::ellipse(center, rx, ry)
+            },
+            "line" => Path::polyline(
+                &[
+                    Point2D::new(
+                        self.length(element, "x1", Axis::Horizontal, zero),
+                        self.length(element, "y1", Axis::Vertical, zero),
+                    ),
+                    Point2D::new(
+                        self.length(element, "x2", Axis::Horizontal, zero),
+                        self.length(element, "y2", Axis::Vertical, zero),
+                    ),
+                ],
+                false,
+            ),
+            "polyline" | "polygon" => {
+                let points = parse_points(element.attribute("points")?);
+                if points.len() < 2 {
+                    return None;
+                }
+                Path::polyline(&points, name == "polygon")
+            },
+            "path" => Path::from_path_data(&SVGPathData::parse_attribute(element.attribute("d")?).ok()?),
+            _ => return None,
+        };
+        Some(path)
+    }
+
+    fn add_shape(&mut self, path: Path, style: &SvgStyle, transform: Transform2D<f32>, opacity: f32) {
+        if !style.visible || self.scene.shapes.len() >= MAX_SHAPES {
+            return;
+        }
+        let fill = self.paint(&style.fill, style.fill_opacity * opacity);
+        let width = self.resolve(style.stroke_width, Axis::Other);
+        let stroke = if width > 0. {
+            self.paint(&style.stroke, style.stroke_opacity * opacity)
+        } else {
+            None
+        };
+        if fill.is_none() && stroke.is_none() {
+            return;
+        }
+
+        let mut dashes = style
+            .stroke_dasharray
+            .iter()
+            .map(|dash| self.resolve(*dash, Axis::Other))
+            .collect::<Vec<_>>();
+        if dashes.iter().any(|dash| *dash < 0.) || dashes.iter().sum::<f32>() <= 0. {
+            dashes.clear();
+        }
+        let dash_offset = self.resolve(style.stroke_dashoffset, Axis::Other);
+        self.scene.shapes.push(Shape {
+            path,
+            transform,
+            fill,
+            fill_rule: style.fill_rule,
+            stroke,
+            stroke_style: StrokeStyle {
+                width,
+                cap: style.stroke_linecap,
+                join: style.stroke_linejoin,
+                miter_limit: style.stroke_miterlimit,
+                dashes,
+                dash_offset,
+            },
+        });
+    }
+
+    fn paint(&self, paint: &SvgPaint, opacity: f32) -> Option<Paint> {
+        let paint = match *paint {
+            SvgPaint::None => None,
+            SvgPaint::Color(color) => Some(Paint::Color(color)),
+            SvgPaint::Server(ref id, fallback) => {
+                self.gradient(id).or(fallback.map(Paint::Color))
+            },
+        };
+        paint.map(|paint| paint.with_opacity(opacity))
+    }
+
+    /// Resolves the gradient with the given id, along with the attributes and
+    /// stops it inherits from the gradients it references.
+    /// <https://www.w3.org/TR/SVG11/pservers.html#LinearGradientElementHrefAttribute>
+    fn gradient(&self, id: &str) -> Option<Paint> {
+        let element = self.root.element_by_id(id)?;
+        let linear = match element.local_name() {
+            "linearGradient" => true,
+            "radialGradient" => false,
+            _ => return None,
+        };
+        let mut chain = vec![element];
+        while chain.len() < MAX_DEPTH {
+            let next = self.element_by_href(chain.last().unwrap());
+            match next {
+                Some(next) => {
+                    if next.local_name() != "linearGradient" && next.local_name() != "radialGradient" {
+                        break;
+                    }
+                    chain.push(next);
+                },
+                None => break,
+            }
+        }
+        let attribute = |name: &str| chain.iter().filter_map(|element| element.attribute(name)).next();
+
+        let mut stops: Vec<GradientStop> = vec![];
+        let stop_elements = chain
+            .iter()
+            .map(|element| {
+                element
+                    .children()
+                    .into_iter()
+                    .filter(|child| child.local_name() == "stop")
+                    .collect::<Vec<_>>()
+            })
+            .find(|stops| !stops.is_empty())
+            .unwrap_or(vec![]);
+        for stop in stop_elements {
+            let style = stop.style(&SvgStyle::default()).unwrap_or_default();
+            let offset = stop.attribute("offset").map_or(0., |offset| {
+                let offset = offset.trim();
+                if offset.ends_with('%') {
+                    parse_number_list(&offset[..offset.len() - 1]).first().map_or(0., |o| o / 100.)
+                } else {
+                    parse_number_list(offset).first().cloned().unwrap_or(0.)
+                }
+            });
+            let previous = stops.last().map_or(0., |stop| stop.offset);
+            stops.push(GradientStop {
+                offset: offset.max(previous).min(1.),
+                color: multiply_alpha(style.stop_color, style.stop_opacity),
+            });
+        }
+        match stops.len() {
+            0 => return Some(Paint::Color(RGBA::transparent())),
+            1 => return Some(Paint::Color(stops[0].color)),
+            _ => {},
+        }
+
+        let units = match attribute("gradientUnits") {
+            Some("userSpaceOnUse") => GradientUnits::UserSpaceOnUse,
+            _ => GradientUnits::ObjectBoundingBox,
+        };
+        let coordinate = |name: &str, default: Length, axis: Axis| {
+            let length = attribute(name).and_then(Length::parse).unwrap_or(default);
+            match (units, length) {
+                (GradientUnits::ObjectBoundingBox, Length::Absolute(fraction)) => fraction,
+                (GradientUnits::ObjectBoundingBox, Length::Percentage(percentage)) => percentage / 100.,
+                (GradientUnits::UserSpaceOnUse, length) => self.resolve(length, axis),
+            }
+        };
+        let geometry = if linear {
+            GradientGeometry::Linear {
+                start: Point2D::new(
+                    coordinate("x1", Length::Percentage(0.), Axis::Horizontal),
+                    coordinate("y1", Length::Percentage(0.), Axis::Vertical),
+                ),
+                end: Point2D::new(
+                    coordinate("x2", Length::Percentage(100.), Axis::Horizontal),
+                    coordinate("y2", Length::Percentage(0.), Axis::Vertical),
+                ),
+            }
+        } else {
+            let center = Point2D::new(
+                coordinate("cx", Length::Percentage(50.), Axis::Horizontal),
+                coordinate("cy", Length::Percentage(50.), Axis::Vertical),
+            );
+            let focus = Point2D::new(
+                attribute("fx").map_or(center.x, |_| coordinate("fx", Length::Percentage(50.), Axis::Horizontal)),
+                attribute("fy").map_or(center.y, |_| coordinate("fy", Length::Percentage(50.), Axis::Vertical)),
+            );
+            GradientGeometry::Radial {
+                center,
+                radius: coordinate("r", Length::Percentage(50.), Axis::Other),
+                focus,
+            }
+        };
+
+        Some(Paint::Gradient(Gradient {
+            geometry,
+            stops,
+            units,
+            transform: attribute("gradientTransform")
+                .and_then(parse_transform)
+                .unwrap_or(Transform2D::identity()),
+            spread: match attribute("spreadMethod") {
+                Some("reflect") => SpreadMethod::Reflect,
+                Some("repeat") => SpreadMethod::Repeat,
+                _ => SpreadMethod::Pad,
+            },
+        }))
+    }
+}
@@ -0,0 +1,82 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+extern crate net_traits;
+extern crate servo_svg;
+
+use net_traits::image::base::{Image, PixelFormat};
+use servo_svg::document::{is_svg, load_svg};
+
+fn pixel(image: &Image, x: usize, y: usize) -> &[u8] {
+    let offset = (y * image.width as usize + x) * 4;
+    &image.bytes[offset..offset + 4]
+}
+
+#[test]
+fn test_svg_detection() {
+    assert!(is_svg(b"<svg xmlns=\"http://www.w3.org/2000/svg\"/>"));
+    assert!(is_svg(b"\xEF\xBB\xBF<?xml version=\"1.0\"?>\n<!-- icon -->\n<svg></svg>"));
+    assert!(!is_svg(b"<html><body></body></html>"));
+    assert!(!is_svg(&[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A]));
+}
+
+#[test]
+fn test_svg_rasterization() {
+    let svg = br##"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="10" viewBox="0 0 4 2">
+        <style>.right { fill: #0000ff }</style>
+        <rect width="2" height="2" fill="red"/>
+        <rect x="2" width="2" height="2" class="right" fill-opacity="50%"/>
+    </svg>"##;
+    let image = load_svg(svg).unwrap();
+    assert_eq!((image.width, image.height), (20, 10));
+    assert_eq!(image.format, PixelFormat::BGRA8);
+    assert_eq!(pixel(&image, 2, 5), &[0, 0, 255, 255]);
+    assert_eq!(pixel(&image, 15, 5), &[255, 0, 0, 128]);
+}
+
+#[test]
+fn test_svg_namespaces() {
+    let svg = br##"<s:svg xmlns:s="http://www.w3.org/2000/svg" xmlns:l="http://www.w3.org/1999/xlink"
+                          xmlns:other="urn:other" width="2" height="1">
+        <s:defs><s:rect id="square" width="1" height="1" fill="lime"/></s:defs>
+        <s:use l:href="#square"/>
+        <other:rect x="1" width="1" height="1" fill="red"/>
+    </s:svg>"##;
+    let image = load_svg(svg).unwrap();
+    assert_eq!(pixel(&image, 0, 0), &[0, 255, 0, 255]);
+    assert_eq!(pixel(&image, 1, 0), &[0, 0, 0, 0]);
+
+    // Without the SVG namespace, the document isn't an SVG image.
+    assert!(load_svg(b"<svg width=\"2\" height=\"1\"><rect width=\"2\" height=\"1\"/></svg>").is_none());
+}
+
+#[test]
+fn test_svg_cyclic_use() {
+    let svg = br##"<svg xmlns="http://www.w3.org/2000/svg" width="2" height="1">
+        <g id="a"><rect width="1" height="1" fill="lime"/><use href="#a" x="1"/></g>
+    </svg>"##;
+    let image = load_svg(svg).unwrap();
+    assert_eq!(pixel(&image, 0, 0), &[0, 255, 0, 255]);
+    assert_eq!(pixel(&image, 1, 0), &[0, 0, 0, 0]);
+}
+
+#[test]
+fn test_svg_use_fan_out() {
+    // Each level references the previous one ten times, which would expand
+    // into ten billion rectangles without a budget.
+    let mut svg = String::from(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"1\" height=\"1\">\
+         <defs><rect id=\"l0\" width=\"1\" height=\"1\" fill=\"lime\"/>",
+    );
+    for level in 1..11 {
+        svg.push_str(&format!("<g id=\"l{}\">", level));
+        for _ in 0..10 {
+            svg.push_str(&format!("<use href=\"#l{}\"/>", level - 1));
+        }
+        svg.push_str("</g>");
+    }
+    svg.push_str("</defs><use href=\"#l10\"/></svg>");
+    let image = load_svg(svg.as_bytes()).unwrap();
+    assert_eq!(pixel(&image, 0, 0), &[0, 255, 0, 255]);
+}
@@ -0,0 +1,544 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use Handler;
+use ipc_channel::ipc;
+use keys::key_from_char;
+use msg::constellation_msg::{Key, KeyModifiers, KeyState};
+use script_traits::{ConstellationMsg, MouseButton, MouseEventType, WebDriverCommandMsg};
+use script_traits::webdriver_msg::WebDriverScriptCommand;
+use std::collections::{HashMap, HashSet};
+use std::mem;
+use std::thread;
+use std::time::Duration;
+use webdriver::actions::{ActionSequence, ActionsType, GeneralAction, KeyAction, KeyActionItem};
+use webdriver::actions::{NullActionItem, PointerAction, PointerActionItem, PointerOrigin};
+use webdriver::actions::PointerType;
+use webdriver::command::ActionsParameters;
+use webdriver::common::Nullable;
+use webdriver::error::{ErrorStatus, WebDriverError, WebDriverResult};
+use webdriver::response::WebDriverResponse;
+
+/// <https://w3c.github.io/webdriver/#dfn-input-source-state>
+pub enum InputSourceState {
+    Null,
+    Key(KeyInputState),
+    Pointer(PointerInputState),
+}
+
+/// <https://w3c.github.io/webdriver/#dfn-key-input-state>
+pub struct KeyInputState {
+    pressed: HashSet<char>,
+    modifiers: KeyModifiers,
+}
+
+impl KeyInputState {
+    fn new() -> KeyInputState {
+        KeyInputState {
+            pressed: HashSet::new(),
+            modifiers: KeyModifiers::empty(),
+        }
+    }
+}
+
+/// <https://w3c.github.io/webdriver/#dfn-pointer-input-state>
+pub struct PointerInputState {
+    pressed: HashSet<u64>,
+    /// The element under the pointer when each pressed button was pressed, used to
+    /// click only if the button is released over the same element.
+    press_targets: HashMap<u64, Option<String>>,
+    x: i64,
+    y: i64,
+}
+
+impl PointerInputState {
+    fn new() -> PointerInputState {
+        PointerInputState {
+            pressed: HashSet::new(),
+            press_targets: HashMap::new(),
+            x: 0,
+            y: 0,
+        }
+    }
+}
+
+/// An action undoing a key or button press, dispatched when the actions are released.
+///
+/// <https://w3c.github.io/webdriver/#dfn-input-cancel-list>
+pub enum CancelAction {
+    KeyUp(String, char),
+    PointerUp(String, u64),
+}
+
+/// An action of an input source, as extracted from the parameters of Perform Actions.
+enum Action {
+    Pause(u64),
+    KeyDown(char),
+    KeyUp(char),
+    PointerDown(u64),
+    PointerUp(u64),
+    PointerMove {
+        duration: Option<u64>,
+        origin: Origin,
+        x: i64,
+        y: i64,
+    },
+    PointerCancel,
+}
+
+impl Action {
+    fn duration(&self) -> u64 {
+        match *self {
+            Action::Pause(duration) => duration,
+            Action::PointerMove { duration, .. } => duration.unwrap_or(0),
+            _ => 0,
+        }
+    }
+}
+
+/// What the coordinates of a pointer move are relative to.
+enum Origin {
+    Viewport,
+    Pointer,
+    Element(String),
+}
+
+fn general_action(action: &GeneralAction) -> Action {
+    match *action {
+        GeneralAction::Pause(ref pause) => Action::Pause(pause.duration),
+    }
+}
+
+fn pointer_action(item: &PointerActionItem) -> Action {
+    match *item {
+        PointerActionItem::General(ref action) => general_action(action),
+        PointerActionItem::Pointer(PointerAction::Down(ref action)) => {
+            Action::PointerDown(action.button)
+        },
+        PointerActionItem::Pointer(PointerAction::Up(ref action)) => {
+            Action::PointerUp(action.button)
+        },
+        PointerActionItem::Pointer(PointerAction::Move(ref action)) => Action::PointerMove {
+            duration: match action.duration {
+                Nullable::Value(duration) => Some(duration),
+                Nullable::Null => None,
+            },
+            origin: match action.origin {
+                PointerOrigin::Viewport => Origin::Viewport,
+                PointerOrigin::Pointer => Origin::Pointer,
+                PointerOrigin::Element(ref element) => Origin::Element(element.id.clone()),
+            },
+            x: match action.x {
+                Nullable::Value(x) => x,
+                Nullable::Null => 0,
+            },
+            y: match action.y {
+                Nullable::Value(y) => y,
+                Nullable::Null => 0,
+            },
+        },
+        PointerActionItem::Pointer(PointerAction::Cancel) => Action::PointerCancel,
+    }
+}
+
+/// The modifier a key sets while it is pressed, if any.
+fn modifier_for_key(value: char) -> KeyModifiers {
+    match value {
+        '\u{E008}' | '\u{E050}' => KeyModifiers::SHIFT,
+        '\u{E009}' | '\u{E051}' => KeyModifiers::CONTROL,
+        '\u{E00A}' | '\u{E052}' => KeyModifiers::ALT,
+        '\u{E03D}' | '\u{E053}' => KeyModifiers::SUPER,
+        _ => KeyModifiers::empty(),
+    }
+}
+
+fn mouse_button(button: u64) -> WebDriverResult<MouseButton> {
+    match button {
+        0 => Ok(MouseButton::Left),
+        1 => Ok(MouseButton::Middle),
+        2 => Ok(MouseButton::Right),
+        _ => Err(WebDriverError::new(
+            ErrorStatus::UnsupportedOperation,
+            "Unsupported pointer button",
+        )),
+    }
+}
+
+impl Handler {
+    // https://w3c.github.io/webdriver/#perform-actions
+    pub fn handle_perform_actions(
+        &mut self,
+        parameters: &ActionsParameters,
+    ) -> WebDriverResult<WebDriverResponse> {
+        // Step 5.
+        let actions_by_tick = self.extract_action_sequence(&parameters.actions)?;
+
+        // Step 6.
+        for tick_actions in actions_by_tick {
+            self.dispatch_tick_actions(tick_actions)?;
+        }
+        Ok(WebDriverResponse::Void)
+    }
+
+    // https://w3c.github.io/webdriver/#release-actions
+    pub fn handle_release_actions(&mut self) -> WebDriverResult<WebDriverResponse> {
+        // Steps 4-6.
+        let undo_actions = mem::replace(&mut self.session_mut()?.input_cancel_list, vec![]);
+        for action in undo_actions.into_iter().rev() {
+            match action {
+                CancelAction::KeyUp(id, value) => self.dispatch_keyup(&id, value)?,
+                CancelAction::PointerUp(id, button) => self.dispatch_pointerup(&id, button)?,
+            }
+        }
+
+        // Step 7.
+        self.session_mut()?.input_state_table.clear();
+        Ok(WebDriverResponse::Void)
+    }
+
+    /// Clicks the primary button at the given point of the viewport, with a mouse
+    /// that isn't part of the input state table.
+    pub fn click_at(&self, x: i64, y: i64) {
+        self.send_mouse_move_event(x, y);
+        self.send_mouse_button_event(MouseEventType::MouseDown, MouseButton::Left, x, y);
+        self.send_mouse_button_event(MouseEventType::MouseUp, MouseButton::Left, x, y);
+        self.send_mouse_button_event(MouseEventType::Click, MouseButton::Left, x, y);
+    }
+
+    /// <https://w3c.github.io/webdriver/#dfn-extract-an-action-sequence>
+    fn extract_action_sequence(
+        &mut self,
+        sequences: &[ActionSequence],
+    ) -> WebDriverResult<Vec<Vec<(String, Action)>>> {
+        let mut actions_by_tick: Vec<Vec<(String, Action)>> = vec![];
+        for sequence in sequences {
+            let id = sequence.id.clone().ok_or(WebDriverError::new(
+                ErrorStatus::InvalidArgument,
+                "Missing input source id",
+            ))?;
+            let (source, actions): (InputSourceState, Vec<Action>) = match sequence.actions {
+                ActionsType::Null(ref items) => (
+                    InputSourceState::Null,
+                    items
+                        .iter()
+                        .map(|item| match *item {
+                            NullActionItem::General(ref action) => general_action(action),
+                        }).collect(),
+                ),
+                ActionsType::Key(ref items) => (
+                    InputSourceState::Key(KeyInputState::new()),
+                    items
+                        .iter()
+                        .map(|item| match *item {
+                            KeyActionItem::General(ref action) => general_action(action),
+                            KeyActionItem::Key(KeyAction::Down(ref action)) => {
+                                Action::KeyDown(action.value)
+                            },
+                            KeyActionItem::Key(KeyAction::Up(ref action)) => {
+                                Action::KeyUp(action.value)
+                            },
+                        }).collect(),
+                ),
+                ActionsType::Pointer(ref parameters, ref items) => {
+                    match parameters.pointer_type {
+                        PointerType::Mouse => {},
+                        _ => {
+                            return Err(WebDriverError::new(
+                                ErrorStatus::UnsupportedOperation,
+                                "Only mouse pointers are supported",
+                            ))
+                        },
+                    }
+                    (
+                        InputSourceState::Pointer(PointerInputState::new()),
+                        items.iter().map(pointer_action).collect(),
+                    )
+                },
+            };
+
+            // An existing input source can't change its type.
+            let session = self.session_mut()?;
+            if let Some(existing) = session.input_state_table.get(&id) {
+                if mem::discriminant(existing) != mem::discriminant(&source) {
+                    return Err(WebDriverError::new(
+                        ErrorStatus::InvalidArgument,
+                        "Input source type doesn't match the existing input source",
+                    ));
+                }
+            }
+            session.input_state_table.entry(id.clone()).or_insert(source);
+
+            for (tick, action) in actions.into_iter().enumerate() {
+                if actions_by_tick.len() <= tick {
+                    actions_by_tick.push(vec![]);
+                }
+                actions_by_tick[tick].push((id.clone(), action));
+            }
+        }
+        Ok(actions_by_tick)
+    }
+
+    /// <https://w3c.github.io/webdriver/#dfn-dispatch-tick-actions>
+    fn dispatch_tick_actions(
+        &mut self,
+        tick_actions: Vec<(String, Action)>,
+    ) -> WebDriverResult<()> {
+        // Step 1.
+        let tick_duration = tick_actions
+            .iter()
+            .map(|&(_, ref action)| action.duration())
+            .max()
+            .unwrap_or(0);
+
+        // Step 2.
+        for (id, action) in tick_actions {
+            match action {
+                Action::Pause(_) | Action::PointerCancel => {},
+                Action::KeyDown(value) => self.dispatch_keydown(&id, value)?,
+                Action::KeyUp(value) => self.dispatch_keyup(&id, value)?,
+                Action::PointerDown(button) => self.dispatch_pointerdown(&id, button)?,
+                Action::PointerUp(button) => self.dispatch_pointerup(&id, button)?,
+                Action::PointerMove { origin, x, y, .. } => {
+                    self.dispatch_pointermove(&id, origin, x, y)?
+                },
+            }
+        }
+
+        // Step 3. Pointer moves aren't interpolated over their duration, so the
+        // whole tick is waited for once its actions are dispatched.
+        thread::sleep(Duration::from_millis(tick_duration));
+        Ok(())
+    }
+
+    fn key_input_state(&mut self, id: &str) -> WebDriverResult<&mut KeyInputState> {
+        match self.session_mut()?.input_state_table.get_mut(id) {
+            Some(&mut InputSourceState::Key(ref mut state)) => Ok(state),
+            _ => Err(WebDriverError::new(
+                ErrorStatus::InvalidArgument,
+                "Not a key input source",
+            )),
+        }
+    }
+
+    fn pointer_input_state(&mut self, id: &str) -> WebDriverResult<&mut PointerInputState> {
+        match self.session_mut()?.input_state_table.get_mut(id) {
+            Some(&mut InputSourceState::Pointer(ref mut state)) => Ok(state),
+            _ => Err(WebDriverError::new(
+                ErrorStatus::InvalidArgument,
+                "Not a pointer input source",
+            )),
+        }
+    }
+
+    /// <https://w3c.github.io/webdriver/#dfn-dispatch-a-keydown-action>
+    fn dispatch_keydown(&mut self, id: &str, value: char) -> WebDriverResult<()> {
+        let (key, with_shift) = key_from_char(&value).ok_or(WebDriverError::new(
+            ErrorStatus::UnsupportedOperation,
+            "Unsupported key",
+        ))?;
+
+        let modifiers = {
+            let state = self.key_input_state(id)?;
+            state.modifiers.insert(modifier_for_key(value));
+            state.pressed.insert(value);
+            state.modifiers
+        };
+        self.session_mut()?
+            .input_cancel_list
+            .push(CancelAction::KeyUp(id.to_owned(), value));
+
+        let modifiers = if with_shift {
+            modifiers | KeyModifiers::SHIFT
+        } else {
+            modifiers
+        };
+        self.send_key_event(key, modifiers, KeyState::Pressed)
+    }
+
+    /// <https://w3c.github.io/webdriver/#dfn-dispatch-a-keyup-action>
+    fn dispatch_keyup(&mut self, id: &str, value: char) -> WebDriverResult<()> {
+        let (key, with_shift) = key_from_char(&value).ok_or(WebDriverError::new(
+            ErrorStatus::UnsupportedOperation,
+            "Unsupported key",
+        ))?;
+
+        let modifiers = {
+            let state = self.key_input_state(id)?;
+            // Releasing a key that isn't pressed does nothing.
+            if !state.pressed.remove(&value) {
+                return Ok(());
+            }
+            state.modifiers.remove(modifier_for_key(value));
+            state.modifiers
+        };
+
+        let modifiers = if with_shift {
+            modifiers | KeyModifiers::SHIFT
+        } else {
+            modifiers
+        };
+        self.send_key_event(key, modifiers, KeyState::Released)
+    }
+
+    /// <https://w3c.github.io/webdriver/#dfn-dispatch-a-pointerdown-action>
+    fn dispatch_pointerdown(&mut self, id: &str, button: u64) -> WebDriverResult<()> {
+        let mouse_button = mouse_button(button)?;
+        let (x, y) = {
+            let state = self.pointer_input_state(id)?;
+            // Pressing a button that is already pressed does nothing.
+            if !state.pressed.insert(button) {
+                return Ok(());
+            }
+            (state.x, state.y)
+        };
+        self.session_mut()?
+            .input_cancel_list
+            .push(CancelAction::PointerUp(id.to_owned(), button));
+
+        let target = self.element_from_point(x, y)?;
+        self.pointer_input_state(id)?.press_targets.insert(button, target);
+        self.send_mouse_button_event(MouseEventType::MouseDown, mouse_button, x, y);
+        Ok(())
+    }
+
+    /// <https://w3c.github.io/webdriver/#dfn-dispatch-a-pointerup-action>
+    fn dispatch_pointerup(&mut self, id: &str, button: u64) -> WebDriverResult<()> {
+        let mouse_button = mouse_button(button)?;
+        let (x, y, press_target) = {
+            let state = self.pointer_input_state(id)?;
+            // Releasing a button that isn't pressed does nothing.
+            if !state.pressed.remove(&button) {
+                return Ok(());
+            }
+            let press_target = state.press_targets.remove(&button).and_then(|target| target);
+            (state.x, state.y, press_target)
+        };
+
+        let release_target = self.element_from_point(x, y)?;
+        self.send_mouse_button_event(MouseEventType::MouseUp, mouse_button, x, y);
+        // A click only happens when the button is pressed and released over the same element.
+        if press_target.is_some() && press_target == release_target {
+            self.send_mouse_button_event(MouseEventType::Click, mouse_button, x, y);
+        }
+        Ok(())
+    }
+
+    /// <https://w3c.github.io/webdriver/#dfn-dispatch-a-pointermove-action>
+    fn dispatch_pointermove(
+        &mut self,
+        id: &str,
+        origin: Origin,
+        x_offset: i64,
+        y_offset: i64,
+    ) -> WebDriverResult<()> {
+        // Steps 1-2.
+        let (start_x, start_y) = {
+            let state = self.pointer_input_state(id)?;
+            (state.x, state.y)
+        };
+
+        // Steps 3-6.
+        let (x, y) = match origin {
+            Origin::Viewport => (x_offset, y_offset),
+            Origin::Pointer => (start_x + x_offset, start_y + y_offset),
+            Origin::Element(ref element) => {
+                let (x, y) = self.element_in_view_center_point(element)?;
+                (x + x_offset, y + y_offset)
+            },
+        };
+
+        // Step 7.
+        let (width, height) = self.viewport_size()?;
+        if x < 0 || y < 0 || x as f32 > width || y as f32 > height {
+            return Err(WebDriverError::new(
+                ErrorStatus::MoveTargetOutOfBounds,
+                "Pointer moved outside of the viewport",
+            ));
+        }
+
+        // Steps 8-9, the pointer moves to its target at once.
+        {
+            let state = self.pointer_input_state(id)?;
+            state.x = x;
+            state.y = y;
+        }
+        self.send_mouse_move_event(x, y);
+        Ok(())
+    }
+
+    /// The element at the given point of the viewport, if any.
+    fn element_from_point(&self, x: i64, y: i64) -> WebDriverResult<Option<String>> {
+        let (sender, receiver) = ipc::channel().unwrap();
+        self.top_level_script_command(WebDriverScriptCommand::GetElementFromPoint(x, y, sender))?;
+        Ok(receiver.recv().unwrap())
+    }
+
+    fn element_in_view_center_point(&self, element: &str) -> WebDriverResult<(i64, i64)> {
+        let (sender, receiver) = ipc::channel().unwrap();
+        self.browsing_context_script_command(WebDriverScriptCommand::GetElementInViewCenterPoint(
+            element.to_owned(),
+            sender,
+        ))?;
+
+        match receiver.recv().unwrap() {
+            Ok(Some(point)) => Ok(point),
+            Ok(None) => Err(WebDriverError::new(
+                ErrorStatus::MoveTargetOutOfBounds,
+                "Element is not in view",
+            )),
+            Err(_) => Err(WebDriverError::new(
+                ErrorStatus::StaleElementReference,
+                "Element not found",
+            )),
+        }
+    }
+
+    fn viewport_size(&self) -> WebDriverResult<(f32, f32)> {
+        let (sender, receiver) = ipc::channel().unwrap();
+        let top_level_browsing_context_id = self.session()?.top_level_browsing_context_id;
+        let cmd_msg = WebDriverCommandMsg::GetWindowSize(top_level_browsing_context_id, sender);
+        self.constellation_chan
+            .send(ConstellationMsg::WebDriverCommand(cmd_msg))
+            .unwrap();
+
+        let viewport = receiver.recv().unwrap().initial_viewport;
+        Ok((viewport.width, viewport.height))
+    }
+
+    fn send_key_event(
+        &self,
+        key: Key,
+        modifiers: KeyModifiers,
+        state: KeyState,
+    ) -> WebDriverResult<()> {
+        let browsing_context_id = self.session()?.browsing_context_id;
+        let cmd_msg =
+            WebDriverCommandMsg::SendKeys(browsing_context_id, vec![(key, modifiers, state)]);
+        self.constellation_chan
+            .send(ConstellationMsg::WebDriverCommand(cmd_msg))
+            .unwrap();
+        Ok(())
+    }
+
+    fn send_mouse_button_event(
+        &self,
+        event_type: MouseEventType,
+        button: MouseButton,
+        x: i64,
+        y: i64,
+    ) {
+        let cmd_msg =
+            WebDriverCommandMsg::MouseButtonAction(event_type, button, x as f32, y as f32);
+        self.constellation_chan
+            .send(ConstellationMsg::WebDriverCommand(cmd_msg))
+            .unwrap();
+    }
+
+    fn send_mouse_move_event(&self, x: i64, y: i64) {
+        let cmd_msg = WebDriverCommandMsg::MouseMoveAction(x as f32, y as f32);
+        self.constellation_chan
+            .send(ConstellationMsg::WebDriverCommand(cmd_msg))
+            .unwrap();
+    }
+}
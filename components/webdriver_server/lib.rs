@@ -25,8 +25,10 @@ extern crate servo_url;
 extern crate uuid;
 extern crate webdriver;
 
+mod actions;
 mod keys;
 
+use actions::{CancelAction, InputSourceState};
 use euclid::TypedSize2D;
 use hyper::method::Method::{self, Post};
 use image::{DynamicImage, ImageFormat, RgbImage};
@@ -37,20 +39,22 @@ use net_traits::image::base::PixelFormat;
 use regex::Captures;
 use rustc_serialize::json::{Json, ToJson};
 use script_traits::{ConstellationMsg, LoadData, WebDriverCommandMsg};
-use script_traits::webdriver_msg::{LoadStatus, WebDriverCookieError, WebDriverFrameId};
+use script_traits::webdriver_msg::{LoadStatus, WebDriverCookieError, WebDriverElementError};
+use script_traits::webdriver_msg::WebDriverFrameId;
 use script_traits::webdriver_msg::{WebDriverJSError, WebDriverJSResult, WebDriverScriptCommand};
 use servo_channel::Sender;
 use servo_config::prefs::{PREFS, PrefValue};
 use servo_url::ServoUrl;
 use std::borrow::ToOwned;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 use std::net::{SocketAddr, SocketAddrV4};
 use std::thread;
 use std::time::Duration;
 use uuid::Uuid;
 use webdriver::command::{AddCookieParameters, GetParameters, JavascriptCommandParameters};
 use webdriver::command::{LocatorParameters, Parameters};
-use webdriver::command::{SendKeysParameters, SwitchToFrameParameters, TimeoutsParameters};
+use webdriver::command::{SendKeysParameters, SwitchToFrameParameters, SwitchToWindowParameters};
+use webdriver::command::TimeoutsParameters;
 use webdriver::command::{WebDriverCommand, WebDriverExtensionCommand, WebDriverMessage};
 use webdriver::command::WindowRectParameters;
 use webdriver::common::{Date, LocatorStrategy, Nullable, WebElement};
@@ -78,6 +82,11 @@ fn extension_routes() -> Vec<(Method, &'static str, ServoExtensionRoute)> {
             "/session/{sessionId}/servo/prefs/reset",
             ServoExtensionRoute::ResetPrefs,
         ),
+        (
+            Post,
+            "/session/{sessionId}/window/new",
+            ServoExtensionRoute::NewWindow,
+        ),
     ];
 }
 
@@ -102,6 +111,27 @@ fn cookie_msg_to_cookie(cookie: cookie_rs::Cookie) -> Cookie {
     }
 }
 
+fn element_error_to_webdriver_error(error: WebDriverElementError) -> WebDriverError {
+    match error {
+        WebDriverElementError::StaleElementReference => WebDriverError::new(
+            ErrorStatus::StaleElementReference,
+            "Element not found",
+        ),
+        WebDriverElementError::ElementNotInteractable => WebDriverError::new(
+            ErrorStatus::ElementNotInteractable,
+            "Element is not in view",
+        ),
+        WebDriverElementError::InvalidElementState => WebDriverError::new(
+            ErrorStatus::InvalidElementState,
+            "Element is not editable",
+        ),
+        WebDriverElementError::InvalidArgument => WebDriverError::new(
+            ErrorStatus::InvalidArgument,
+            "Element can't be clicked",
+        ),
+    }
+}
+
 pub fn start_server(port: u16, constellation_chan: Sender<ConstellationMsg>) {
     let handler = Handler::new(constellation_chan);
     thread::Builder::new()
@@ -131,6 +161,15 @@ struct WebDriverSession {
     /// Time to wait for the element location strategy when retrieving elements, and when
     /// waiting for an element to become interactable.
     implicit_wait_timeout: u64,
+
+    /// The handles of the windows opened by the session, by top-level browsing context.
+    window_handles: HashMap<TopLevelBrowsingContextId, String>,
+
+    /// <https://w3c.github.io/webdriver/#dfn-input-state-table>
+    input_state_table: HashMap<String, InputSourceState>,
+
+    /// <https://w3c.github.io/webdriver/#dfn-input-cancel-list>
+    input_cancel_list: Vec<CancelAction>,
 }
 
 impl WebDriverSession {
@@ -138,14 +177,23 @@ impl WebDriverSession {
         browsing_context_id: BrowsingContextId,
         top_level_browsing_context_id: TopLevelBrowsingContextId,
     ) -> WebDriverSession {
+        let id = Uuid::new_v4();
+        // The initial window is known by the session id.
+        let mut window_handles = HashMap::new();
+        window_handles.insert(top_level_browsing_context_id, id.to_string());
+
         WebDriverSession {
-            id: Uuid::new_v4(),
+            id: id,
             browsing_context_id: browsing_context_id,
             top_level_browsing_context_id: top_level_browsing_context_id,
 
             script_timeout: 30_000,
             load_timeout: 300_000,
             implicit_wait_timeout: 0,
+
+            window_handles: window_handles,
+            input_state_table: HashMap::new(),
+            input_cancel_list: vec![],
         }
     }
 }
@@ -161,6 +209,7 @@ enum ServoExtensionRoute {
     GetPrefs,
     SetPrefs,
     ResetPrefs,
+    NewWindow,
 }
 
 impl WebDriverExtensionRoute for ServoExtensionRoute {
@@ -184,6 +233,7 @@ impl WebDriverExtensionRoute for ServoExtensionRoute {
                 let parameters: GetPrefsParameters = Parameters::from_json(&body_data)?;
                 ServoExtensionCommand::ResetPrefs(parameters)
             },
+            ServoExtensionRoute::NewWindow => ServoExtensionCommand::NewWindow,
         };
         Ok(WebDriverCommand::Extension(command))
     }
@@ -194,6 +244,7 @@ enum ServoExtensionCommand {
     GetPrefs(GetPrefsParameters),
     SetPrefs(SetPrefsParameters),
     ResetPrefs(GetPrefsParameters),
+    NewWindow,
 }
 
 impl WebDriverExtensionCommand for ServoExtensionCommand {
@@ -202,6 +253,7 @@ impl WebDriverExtensionCommand for ServoExtensionCommand {
             ServoExtensionCommand::GetPrefs(ref x) => Some(x.to_json()),
             ServoExtensionCommand::SetPrefs(ref x) => Some(x.to_json()),
             ServoExtensionCommand::ResetPrefs(ref x) => Some(x.to_json()),
+            ServoExtensionCommand::NewWindow => None,
         }
     }
 }
@@ -371,10 +423,45 @@ impl Handler {
         Ok(WebDriverResponse::Void)
     }
 
+    /// Fail with "no such window" once the current top-level browsing context was
+    /// closed, rather than sending commands to it.
+    fn verify_window_is_open(&self) -> WebDriverResult<()> {
+        let session = self.session()?;
+        if session.window_handles.contains_key(&session.top_level_browsing_context_id) {
+            Ok(())
+        } else {
+            Err(WebDriverError::new(
+                ErrorStatus::NoSuchWindow,
+                "Window was closed",
+            ))
+        }
+    }
+
+    /// <https://w3c.github.io/webdriver/#dfn-handle-any-user-prompts>, with the default
+    /// "dismiss and notify" behaviour.
+    fn handle_any_user_prompts(&self) -> WebDriverResult<()> {
+        let top_level_browsing_context_id = self.session()?.top_level_browsing_context_id;
+        let (sender, receiver) = ipc::channel().unwrap();
+        let cmd_msg = WebDriverCommandMsg::CloseAlert(top_level_browsing_context_id, false, sender);
+        self.constellation_chan
+            .send(ConstellationMsg::WebDriverCommand(cmd_msg))
+            .unwrap();
+
+        match receiver.recv().unwrap() {
+            Ok(()) => Err(WebDriverError::new(
+                ErrorStatus::UnexpectedAlertOpen,
+                "An alert was open",
+            )),
+            Err(()) => Ok(()),
+        }
+    }
+
     fn browsing_context_script_command(
         &self,
         cmd_msg: WebDriverScriptCommand,
     ) -> WebDriverResult<()> {
+        // The script thread is blocked while an alert is open.
+        self.handle_any_user_prompts()?;
         let browsing_context_id = self.session()?.browsing_context_id;
         let msg = ConstellationMsg::WebDriverCommand(WebDriverCommandMsg::ScriptCommand(
             browsing_context_id,
@@ -385,6 +472,7 @@ impl Handler {
     }
 
     fn top_level_script_command(&self, cmd_msg: WebDriverScriptCommand) -> WebDriverResult<()> {
+        self.handle_any_user_prompts()?;
         let browsing_context_id =
             BrowsingContextId::from(self.session()?.top_level_browsing_context_id);
         let msg = ConstellationMsg::WebDriverCommand(WebDriverCommandMsg::ScriptCommand(
@@ -595,23 +683,120 @@ impl Handler {
     }
 
     fn handle_window_handle(&self) -> WebDriverResult<WebDriverResponse> {
-        // For now we assume there's only one window so just use the session
-        // id as the window id
-        let handle = self.session.as_ref().unwrap().id.to_string();
+        let session = self.session()?;
+        let handle = session
+            .window_handles
+            .get(&session.top_level_browsing_context_id)
+            .ok_or(WebDriverError::new(
+                ErrorStatus::NoSuchWindow,
+                "Window was closed",
+            ))?;
         Ok(WebDriverResponse::Generic(ValueResponse::new(
             handle.to_json(),
         )))
     }
 
+    fn window_handles(&self) -> WebDriverResult<Vec<String>> {
+        let mut handles = self
+            .session()?
+            .window_handles
+            .values()
+            .cloned()
+            .collect::<Vec<_>>();
+        handles.sort();
+        Ok(handles)
+    }
+
     fn handle_window_handles(&self) -> WebDriverResult<WebDriverResponse> {
-        // For now we assume there's only one window so just use the session
-        // id as the window id
-        let handles = vec![self.session.as_ref().unwrap().id.to_string().to_json()];
+        let handles = self.window_handles()?;
+        Ok(WebDriverResponse::Generic(ValueResponse::new(
+            handles.to_json(),
+        )))
+    }
+
+    // https://w3c.github.io/webdriver/#close-window
+    fn handle_close_window(&mut self) -> WebDriverResult<WebDriverResponse> {
+        let top_level_browsing_context_id = {
+            let session = self.session_mut()?;
+            let top_level_browsing_context_id = session.top_level_browsing_context_id;
+            session
+                .window_handles
+                .remove(&top_level_browsing_context_id)
+                .ok_or(WebDriverError::new(
+                    ErrorStatus::NoSuchWindow,
+                    "Window was closed",
+                ))?;
+            top_level_browsing_context_id
+        };
+
+        let (sender, receiver) = ipc::channel().unwrap();
+        let cmd_msg = WebDriverCommandMsg::CloseWindow(top_level_browsing_context_id, sender);
+        self.constellation_chan
+            .send(ConstellationMsg::WebDriverCommand(cmd_msg))
+            .unwrap();
+        receiver.recv().unwrap();
+
+        // The session keeps no browsing context to send commands to until the client
+        // switches to another window, see `verify_window_is_open`.
+        let handles = self.window_handles()?;
+        if handles.is_empty() {
+            // Closing the last window ends the session.
+            let session = self.session.take();
+            self.delete_session(&session);
+        }
         Ok(WebDriverResponse::Generic(ValueResponse::new(
             handles.to_json(),
         )))
     }
 
+    // https://w3c.github.io/webdriver/#new-window
+    fn handle_new_window(&mut self) -> WebDriverResult<WebDriverResponse> {
+        let (sender, receiver) = ipc::channel().unwrap();
+        let cmd_msg = WebDriverCommandMsg::NewWindow(sender);
+        self.constellation_chan
+            .send(ConstellationMsg::WebDriverCommand(cmd_msg))
+            .unwrap();
+        let top_level_browsing_context_id = receiver.recv().unwrap();
+
+        let handle = Uuid::new_v4().to_string();
+        self.session_mut()?
+            .window_handles
+            .insert(top_level_browsing_context_id, handle.clone());
+
+        let mut value = BTreeMap::new();
+        value.insert("handle".to_owned(), handle.to_json());
+        value.insert("type".to_owned(), "window".to_json());
+        Ok(WebDriverResponse::Generic(ValueResponse::new(
+            Json::Object(value),
+        )))
+    }
+
+    // https://w3c.github.io/webdriver/#switch-to-window
+    fn handle_switch_to_window(
+        &mut self,
+        parameters: &SwitchToWindowParameters,
+    ) -> WebDriverResult<WebDriverResponse> {
+        let top_level_browsing_context_id = {
+            let session = self.session_mut()?;
+            let top_level_browsing_context_id = session
+                .window_handles
+                .iter()
+                .find(|&(_, handle)| *handle == parameters.handle)
+                .map(|(&id, _)| id)
+                .ok_or(WebDriverError::new(
+                    ErrorStatus::NoSuchWindow,
+                    "No window with this handle",
+                ))?;
+            session.top_level_browsing_context_id = top_level_browsing_context_id;
+            session.browsing_context_id = BrowsingContextId::from(top_level_browsing_context_id);
+            top_level_browsing_context_id
+        };
+
+        let msg = ConstellationMsg::SelectBrowser(top_level_browsing_context_id);
+        self.constellation_chan.send(msg).unwrap();
+        Ok(WebDriverResponse::Void)
+    }
+
     fn handle_find_element(
         &self,
         parameters: &LocatorParameters,
@@ -855,6 +1040,21 @@ impl Handler {
         }))
     }
 
+    // https://w3c.github.io/webdriver/#delete-cookie and
+    // https://w3c.github.io/webdriver/#delete-all-cookies
+    fn handle_delete_cookies(&self, name: Option<String>) -> WebDriverResult<WebDriverResponse> {
+        let (sender, receiver) = ipc::channel().unwrap();
+        let cmd = WebDriverScriptCommand::DeleteCookies(name, sender);
+        self.browsing_context_script_command(cmd)?;
+        match receiver.recv().unwrap() {
+            Ok(()) => Ok(WebDriverResponse::Void),
+            Err(()) => Err(WebDriverError::new(
+                ErrorStatus::NoSuchWindow,
+                "Document not found",
+            )),
+        }
+    }
+
     fn handle_add_cookie(
         &self,
         params: &AddCookieParameters,
@@ -977,6 +1177,7 @@ impl Handler {
         element: &WebElement,
         keys: &SendKeysParameters,
     ) -> WebDriverResult<WebDriverResponse> {
+        self.handle_any_user_prompts()?;
         let browsing_context_id = self.session()?.browsing_context_id;
 
         let (sender, receiver) = ipc::channel().unwrap();
@@ -1013,6 +1214,90 @@ impl Handler {
         Ok(WebDriverResponse::Void)
     }
 
+    // https://w3c.github.io/webdriver/#element-click
+    fn handle_element_click(&self, element: &WebElement) -> WebDriverResult<WebDriverResponse> {
+        let (sender, receiver) = ipc::channel().unwrap();
+        let cmd = WebDriverScriptCommand::ElementClick(element.id.clone(), sender);
+        self.browsing_context_script_command(cmd)?;
+
+        match receiver.recv().unwrap() {
+            Ok(Some((x, y))) => {
+                self.click_at(x, y);
+                Ok(WebDriverResponse::Void)
+            },
+            Ok(None) => Ok(WebDriverResponse::Void),
+            Err(error) => Err(element_error_to_webdriver_error(error)),
+        }
+    }
+
+    // https://w3c.github.io/webdriver/#element-clear
+    fn handle_element_clear(&self, element: &WebElement) -> WebDriverResult<WebDriverResponse> {
+        let (sender, receiver) = ipc::channel().unwrap();
+        let cmd = WebDriverScriptCommand::ElementClear(element.id.clone(), sender);
+        self.browsing_context_script_command(cmd)?;
+
+        receiver
+            .recv()
+            .unwrap()
+            .map(|()| WebDriverResponse::Void)
+            .map_err(element_error_to_webdriver_error)
+    }
+
+    // https://w3c.github.io/webdriver/#get-page-source
+    fn handle_get_page_source(&self) -> WebDriverResult<WebDriverResponse> {
+        let (sender, receiver) = ipc::channel().unwrap();
+        self.browsing_context_script_command(WebDriverScriptCommand::GetPageSource(sender))?;
+
+        match receiver.recv().unwrap() {
+            Ok(source) => Ok(WebDriverResponse::Generic(ValueResponse::new(
+                source.to_json(),
+            ))),
+            Err(()) => Err(WebDriverError::new(
+                ErrorStatus::UnknownError,
+                "Unable to serialize the document",
+            )),
+        }
+    }
+
+    // https://w3c.github.io/webdriver/#dismiss-alert and
+    // https://w3c.github.io/webdriver/#accept-alert
+    fn handle_close_alert(&self, accept: bool) -> WebDriverResult<WebDriverResponse> {
+        let top_level_browsing_context_id = self.session()?.top_level_browsing_context_id;
+        let (sender, receiver) = ipc::channel().unwrap();
+        let cmd_msg = WebDriverCommandMsg::CloseAlert(top_level_browsing_context_id, accept, sender);
+        self.constellation_chan
+            .send(ConstellationMsg::WebDriverCommand(cmd_msg))
+            .unwrap();
+
+        match receiver.recv().unwrap() {
+            Ok(()) => Ok(WebDriverResponse::Void),
+            Err(()) => Err(WebDriverError::new(
+                ErrorStatus::NoSuchAlert,
+                "No alert is open",
+            )),
+        }
+    }
+
+    // https://w3c.github.io/webdriver/#get-alert-text
+    fn handle_get_alert_text(&self) -> WebDriverResult<WebDriverResponse> {
+        let top_level_browsing_context_id = self.session()?.top_level_browsing_context_id;
+        let (sender, receiver) = ipc::channel().unwrap();
+        let cmd_msg = WebDriverCommandMsg::GetAlertText(top_level_browsing_context_id, sender);
+        self.constellation_chan
+            .send(ConstellationMsg::WebDriverCommand(cmd_msg))
+            .unwrap();
+
+        match receiver.recv().unwrap() {
+            Some(text) => Ok(WebDriverResponse::Generic(ValueResponse::new(
+                text.to_json(),
+            ))),
+            None => Err(WebDriverError::new(
+                ErrorStatus::NoSuchAlert,
+                "No alert is open",
+            )),
+        }
+    }
+
     fn handle_take_screenshot(&self) -> WebDriverResult<WebDriverResponse> {
         let mut img = None;
         let top_level_id = self.session()?.top_level_browsing_context_id;
@@ -1124,6 +1409,19 @@ impl WebDriverHandler<ServoExtensionRoute> for Handler {
             },
         }
 
+        // Commands other than these ones need the current window to be open.
+        match msg.command {
+            WebDriverCommand::NewSession(_) |
+            WebDriverCommand::DeleteSession |
+            WebDriverCommand::GetWindowHandles |
+            WebDriverCommand::SwitchToWindow(_) |
+            WebDriverCommand::SetTimeouts(_) |
+            WebDriverCommand::Extension(_) => {},
+            _ => {
+                self.verify_window_is_open()?;
+            },
+        }
+
         match msg.command {
             WebDriverCommand::NewSession(_) => self.handle_new_session(),
             WebDriverCommand::DeleteSession => self.handle_delete_session(),
@@ -1140,6 +1438,10 @@ impl WebDriverHandler<ServoExtensionRoute> for Handler {
             WebDriverCommand::GetTitle => self.handle_title(),
             WebDriverCommand::GetWindowHandle => self.handle_window_handle(),
             WebDriverCommand::GetWindowHandles => self.handle_window_handles(),
+            WebDriverCommand::CloseWindow => self.handle_close_window(),
+            WebDriverCommand::SwitchToWindow(ref parameters) => {
+                self.handle_switch_to_window(parameters)
+            },
             WebDriverCommand::SwitchToFrame(ref parameters) => {
                 self.handle_switch_to_frame(parameters)
             },
@@ -1148,6 +1450,10 @@ impl WebDriverHandler<ServoExtensionRoute> for Handler {
             WebDriverCommand::FindElements(ref parameters) => self.handle_find_elements(parameters),
             WebDriverCommand::GetNamedCookie(ref name) => self.handle_get_cookie(name),
             WebDriverCommand::GetCookies => self.handle_get_cookies(),
+            WebDriverCommand::DeleteCookie(ref name) => {
+                self.handle_delete_cookies(Some(name.clone()))
+            },
+            WebDriverCommand::DeleteCookies => self.handle_delete_cookies(None),
             WebDriverCommand::GetActiveElement => self.handle_active_element(),
             WebDriverCommand::GetElementRect(ref element) => self.handle_element_rect(element),
             WebDriverCommand::GetElementText(ref element) => self.handle_element_text(element),
@@ -1165,12 +1471,21 @@ impl WebDriverHandler<ServoExtensionRoute> for Handler {
             WebDriverCommand::ElementSendKeys(ref element, ref keys) => {
                 self.handle_element_send_keys(element, keys)
             },
+            WebDriverCommand::ElementClick(ref element) => self.handle_element_click(element),
+            WebDriverCommand::ElementClear(ref element) => self.handle_element_clear(element),
+            WebDriverCommand::PerformActions(ref x) => self.handle_perform_actions(x),
+            WebDriverCommand::ReleaseActions => self.handle_release_actions(),
+            WebDriverCommand::GetPageSource => self.handle_get_page_source(),
+            WebDriverCommand::DismissAlert => self.handle_close_alert(false),
+            WebDriverCommand::AcceptAlert => self.handle_close_alert(true),
+            WebDriverCommand::GetAlertText => self.handle_get_alert_text(),
             WebDriverCommand::SetTimeouts(ref x) => self.handle_set_timeouts(x),
             WebDriverCommand::TakeScreenshot => self.handle_take_screenshot(),
             WebDriverCommand::Extension(ref extension) => match *extension {
                 ServoExtensionCommand::GetPrefs(ref x) => self.handle_get_prefs(x),
                 ServoExtensionCommand::SetPrefs(ref x) => self.handle_set_prefs(x),
                 ServoExtensionCommand::ResetPrefs(ref x) => self.handle_reset_prefs(x),
+                ServoExtensionCommand::NewWindow => self.handle_new_window(),
             },
             _ => Err(WebDriverError::new(
                 ErrorStatus::UnsupportedOperation,
@@ -5,6 +5,7 @@
 use serde_json;
 use servo::{self, gl, webrender_api, BrowserId, Servo};
 use servo::compositing::windowing::{AnimationState, EmbedderCoordinates, MouseWindowEvent, WindowEvent, WindowMethods};
+use servo::compositing::windowing::ScreenshotArea;
 use servo::embedder_traits::EmbedderMsg;
 use servo::embedder_traits::resources::{self, Resource};
 use servo::euclid::{Length, TypedPoint2D, TypedScale, TypedSize2D, TypedVector2D};
@@ -312,6 +313,25 @@ impl ServoGlue {
         self.process_event(event)
     }
 
+    /// Save a PNG of the page to the given path, once it has loaded and its rendering is stable.
+    /// With full_page, the whole page is saved rather than what the viewport shows. It is put
+    /// together from viewport-sized parts of the page, so the page keeps its layout.
+    pub fn save_screenshot(&mut self, path: &str, full_page: bool) -> Result<(), &'static str> {
+        info!("save_screenshot: {}", path);
+        let area = if full_page {
+            ScreenshotArea::FullPage
+        } else {
+            ScreenshotArea::Viewport
+        };
+        self.process_event(WindowEvent::SaveScreenshot(PathBuf::from(path), area))
+    }
+
+    /// Print the page to a PDF file. Each page of the file is an image of the printed page.
+    pub fn print_to_pdf(&mut self, path: &str) -> Result<(), &'static str> {
+        info!("print_to_pdf: {}", path);
+        self.process_event(WindowEvent::PrintToPdf(PathBuf::from(path)))
+    }
+
     fn process_event(&mut self, event: WindowEvent) -> Result<(), &'static str> {
         self.events.push(event);
         if !self.batch_mode {
@@ -369,6 +389,14 @@ impl ServoGlue {
                     info!("Alert: {}", message);
                     let _ = sender.send(());
                 },
+                EmbedderMsg::Confirm(message, sender) => {
+                    info!("Confirm: {}", message);
+                    let _ = sender.send(false);
+                },
+                EmbedderMsg::Prompt(message, _, sender) => {
+                    info!("Prompt: {}", message);
+                    let _ = sender.send(None);
+                },
                 EmbedderMsg::AllowOpeningBrowser(response_chan) => {
                     // Note: would be a place to handle pop-ups config.
                     // see Step 7 of #the-rules-for-choosing-a-browsing-context-given-a-browsing-context-name
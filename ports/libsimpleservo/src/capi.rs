@@ -4,7 +4,11 @@
 
 use api::{self, EventLoopWaker, ServoGlue, SERVO, HostTrait, ReadFileTrait};
 use gl_glue;
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+use serde_json;
 use servo::gl;
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+use std::cell::RefCell;
 use std::ffi::{CStr, CString};
 use std::mem;
 use std::os::raw::c_char;
@@ -106,6 +110,46 @@ pub extern "C" fn init_with_gl(
     init(gl, args, url, wakeup, readfile, callbacks, width, height)
 }
 
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+thread_local! {
+    static OSMESA_CONTEXT: RefCell<Option<gl_glue::osmesa::OSMesaContext>> = RefCell::new(None);
+}
+
+/// Adds the argument that makes Servo render with OSMesa to a JSON array of arguments.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn headless_args(argsline: &str) -> Result<String, &'static str> {
+    let mut args: Vec<String> = if argsline.is_empty() {
+        vec![]
+    } else {
+        serde_json::from_str(argsline).map_err(|_| {
+            "Invalid arguments. Servo arguments must be formatted as a JSON array"
+        })?
+    };
+    args.push("--headless".to_string());
+    serde_json::to_string(&args).map_err(|_| "Can't serialize arguments")
+}
+
+/// Initializes Servo to render with OSMesa, in software and without a window, to a buffer
+/// of the given size. This is meant for headless uses like saving screenshots.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+#[no_mangle]
+pub extern "C" fn init_with_osmesa(
+    args: *const c_char,
+    url: *const c_char,
+    wakeup: extern fn(),
+    readfile: extern fn(*const c_char) -> *const c_char,
+    callbacks: CHostCallbacks,
+    width: u32,
+    height: u32) {
+    let (gl, context) = gl_glue::osmesa::init(width, height).unwrap();
+    OSMESA_CONTEXT.with(|c| *c.borrow_mut() = Some(context));
+    let args = unsafe { CStr::from_ptr(args) };
+    let args = args.to_str().expect("Can't read string");
+    let args = headless_args(args).unwrap();
+    let args = CString::new(args).expect("Can't create string");
+    init(gl, args.as_ptr(), url, wakeup, readfile, callbacks, width, height)
+}
+
 #[no_mangle]
 pub extern "C" fn set_batch_mode(batch: bool) {
     debug!("set_batch_mode");
@@ -204,6 +248,22 @@ pub extern "C" fn click(x: i32, y: i32) {
     call(|s| s.click(x as u32, y as u32));
 }
 
+#[no_mangle]
+pub extern "C" fn save_screenshot(path: *const c_char, full_page: bool) {
+    debug!("save_screenshot");
+    let path = unsafe { CStr::from_ptr(path) };
+    let path = path.to_str().expect("Can't read string");
+    call(|s| s.save_screenshot(path, full_page));
+}
+
+#[no_mangle]
+pub extern "C" fn print_to_pdf(path: *const c_char) {
+    debug!("print_to_pdf");
+    let path = unsafe { CStr::from_ptr(path) };
+    let path = path.to_str().expect("Can't read string");
+    call(|s| s.print_to_pdf(path));
+}
+
 pub struct WakeupCallback(extern fn());
 
 impl WakeupCallback {
@@ -86,3 +86,69 @@ pub mod gl {
         unimplemented!()
     }
 }
+
+/// Software rendering with OSMesa, for embedders without a GPU or a window.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+pub mod osmesa {
+    use osmesa_sys;
+    use servo::gl::{self, Gl, GlFns};
+    use std::ffi::CString;
+    use std::mem;
+    use std::os::raw::c_void;
+    use std::ptr;
+    use std::rc::Rc;
+
+    /// An OSMesa context and the buffer in memory it renders to. Its size can't change.
+    pub struct OSMesaContext {
+        context: osmesa_sys::OSMesaContext,
+        buffer: Vec<u32>,
+    }
+
+    impl Drop for OSMesaContext {
+        fn drop(&mut self) {
+            unsafe { osmesa_sys::OSMesaDestroyContext(self.context) };
+        }
+    }
+
+    pub fn init(width: u32, height: u32) -> Result<(Rc<Gl>, OSMesaContext), &'static str> {
+        info!("Creating OSMesa context...");
+        let attribs = [
+            osmesa_sys::OSMESA_PROFILE,
+            osmesa_sys::OSMESA_CORE_PROFILE,
+            osmesa_sys::OSMESA_CONTEXT_MAJOR_VERSION,
+            3,
+            osmesa_sys::OSMESA_CONTEXT_MINOR_VERSION,
+            3,
+            0,
+        ];
+        let context =
+            unsafe { osmesa_sys::OSMesaCreateContextAttribs(attribs.as_ptr(), ptr::null_mut()) };
+        if context.is_null() {
+            return Err("Can't create OSMesa context");
+        }
+        let mut context = OSMesaContext {
+            context,
+            buffer: vec![0; (width * height) as usize],
+        };
+        let made_current = unsafe {
+            osmesa_sys::OSMesaMakeCurrent(
+                context.context,
+                context.buffer.as_mut_ptr() as *mut _,
+                gl::UNSIGNED_BYTE,
+                width as i32,
+                height as i32,
+            )
+        };
+        if made_current == 0 {
+            return Err("Can't make OSMesa context current");
+        }
+        let gl = unsafe {
+            GlFns::load_with(|addr| {
+                let addr = CString::new(addr.as_bytes()).unwrap();
+                mem::transmute::<_, *const c_void>(osmesa_sys::OSMesaGetProcAddress(addr.as_ptr()))
+            })
+        };
+        info!("OSMesa context created");
+        Ok((gl, context))
+    }
+}
@@ -12,6 +12,8 @@ extern crate jni;
 extern crate libc;
 #[macro_use]
 extern crate log;
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+extern crate osmesa_sys;
 extern crate serde_json;
 extern crate servo;
 #[cfg(target_os = "windows")]
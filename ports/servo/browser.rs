@@ -18,7 +18,7 @@ use servo::webrender_api::ScrollLocation;
 use std::mem;
 use std::rc::Rc;
 use std::thread;
-use tinyfiledialogs::{self, MessageBoxIcon};
+use tinyfiledialogs::{self, MessageBoxIcon, OkCancel};
 
 pub struct Browser {
     current_url: Option<ServoUrl>,
@@ -321,6 +321,47 @@ impl Browser {
                             .push(WindowEvent::SendError(browser_id, reason));
                     }
                 },
+                EmbedderMsg::Confirm(message, sender) => {
+                    let accepted = if !opts::get().headless {
+                        thread::Builder::new()
+                            .name("display confirm dialog".to_owned())
+                            .spawn(move || {
+                                tinyfiledialogs::message_box_ok_cancel(
+                                    "Confirm",
+                                    &message,
+                                    MessageBoxIcon::Question,
+                                    OkCancel::Cancel,
+                                ) == OkCancel::Ok
+                            }).unwrap()
+                            .join()
+                            .expect("Thread spawning failed")
+                    } else {
+                        false
+                    };
+                    if let Err(e) = sender.send(accepted) {
+                        let reason = format!("Failed to send Confirm response: {}", e);
+                        self.event_queue
+                            .push(WindowEvent::SendError(browser_id, reason));
+                    }
+                },
+                EmbedderMsg::Prompt(message, default, sender) => {
+                    let input = if !opts::get().headless {
+                        thread::Builder::new()
+                            .name("display prompt dialog".to_owned())
+                            .spawn(move || {
+                                tinyfiledialogs::input_box("Prompt", &message, &default)
+                            }).unwrap()
+                            .join()
+                            .expect("Thread spawning failed")
+                    } else {
+                        None
+                    };
+                    if let Err(e) = sender.send(input) {
+                        let reason = format!("Failed to send Prompt response: {}", e);
+                        self.event_queue
+                            .push(WindowEvent::SendError(browser_id, reason));
+                    }
+                },
                 EmbedderMsg::AllowUnload(sender) => {
                     // Always allow unload for now.
                     if let Err(e) = sender.send(true) {
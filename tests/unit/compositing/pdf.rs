@@ -0,0 +1,68 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use compositing::pdf::{Page, write_pdf};
+use euclid::{TypedPoint2D, TypedRect, TypedSize2D};
+use image::{Rgb, RgbImage};
+use std::str;
+
+fn page(color: u8) -> Page {
+    Page {
+        size: TypedSize2D::new(200., 100.),
+        content_rect: TypedRect::new(TypedPoint2D::new(10., 10.), TypedSize2D::new(180., 80.)),
+        image: RgbImage::from_pixel(18, 8, Rgb([color, 0, 0])),
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+fn print(pages: &[Page]) -> Vec<u8> {
+    let mut pdf = vec![];
+    write_pdf(&mut pdf, pages).unwrap();
+    pdf
+}
+
+#[test]
+fn test_xref_offsets_point_at_objects() {
+    let pdf = print(&[page(0), page(255)]);
+
+    let xref = find(&pdf, b"\nxref\n").unwrap() + 1;
+    let trailer = str::from_utf8(&pdf[xref..]).unwrap();
+    let mut lines = trailer.lines();
+    assert_eq!(lines.next(), Some("xref"));
+    // The catalog, the page tree and three objects for each page.
+    assert_eq!(lines.next(), Some("0 9"));
+    assert_eq!(lines.next(), Some("0000000000 65535 f "));
+    for number in 1..9 {
+        let entry = lines.next().unwrap();
+        assert_eq!(entry.len(), 19);
+        assert!(entry.ends_with(" 00000 n "));
+        let offset: usize = entry[..10].parse().unwrap();
+        let object = format!("{} 0 obj\n", number);
+        assert_eq!(&pdf[offset..offset + object.len()], object.as_bytes());
+    }
+    assert_eq!(lines.next(), Some("trailer"));
+    assert_eq!(lines.next(), Some("<< /Size 9 /Root 1 0 R >>"));
+    assert_eq!(lines.next(), Some("startxref"));
+    assert_eq!(lines.next(), Some(&*xref.to_string()));
+    assert_eq!(lines.next(), Some("%%EOF"));
+    assert_eq!(lines.next(), None);
+}
+
+#[test]
+fn test_pages_are_listed_in_order() {
+    let pdf = print(&[page(0), page(255)]);
+    assert!(pdf.starts_with(b"%PDF-1.4\n"));
+    assert!(find(&pdf, b"/Kids [3 0 R 6 0 R] /Count 2").is_some());
+    assert!(find(&pdf, b"/MediaBox [0 0 150 75]").is_some());
+}
+
+#[test]
+fn test_no_pages() {
+    let pdf = print(&[]);
+    assert!(find(&pdf, b"/Kids [] /Count 0").is_some());
+    assert!(find(&pdf, b"<< /Size 3 /Root 1 0 R >>").is_some());
+}
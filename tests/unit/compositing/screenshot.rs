@@ -0,0 +1,45 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use compositing::windowing::{ScreenshotError, full_page_screenshot_tiles};
+use webrender_api::{DeviceUintPoint, DeviceUintSize};
+
+#[test]
+fn test_page_that_fits_the_framebuffer_is_one_tile() {
+    let tiles = full_page_screenshot_tiles(DeviceUintSize::new(800, 600), DeviceUintSize::new(800, 600));
+    assert_eq!(tiles, Ok(vec![DeviceUintPoint::new(0, 0)]));
+}
+
+#[test]
+fn test_tiles_cover_the_page_row_by_row() {
+    let tiles = full_page_screenshot_tiles(DeviceUintSize::new(1600, 1200), DeviceUintSize::new(800, 600));
+    assert_eq!(tiles, Ok(vec![
+        DeviceUintPoint::new(0, 0),
+        DeviceUintPoint::new(800, 0),
+        DeviceUintPoint::new(0, 600),
+        DeviceUintPoint::new(800, 600),
+    ]));
+}
+
+#[test]
+fn test_last_tiles_end_at_the_end_of_the_page() {
+    let tiles = full_page_screenshot_tiles(DeviceUintSize::new(800, 1500), DeviceUintSize::new(800, 600));
+    assert_eq!(tiles, Ok(vec![
+        DeviceUintPoint::new(0, 0),
+        DeviceUintPoint::new(0, 600),
+        DeviceUintPoint::new(0, 900),
+    ]));
+}
+
+#[test]
+fn test_empty_framebuffer_is_an_error() {
+    let tiles = full_page_screenshot_tiles(DeviceUintSize::new(800, 1500), DeviceUintSize::new(0, 0));
+    assert_eq!(tiles, Err(ScreenshotError::EmptyFramebuffer));
+}
+
+#[test]
+fn test_framebuffer_without_width_is_an_error() {
+    let tiles = full_page_screenshot_tiles(DeviceUintSize::new(800, 1500), DeviceUintSize::new(0, 600));
+    assert_eq!(tiles, Err(ScreenshotError::EmptyFramebuffer));
+}
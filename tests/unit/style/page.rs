@@ -0,0 +1,68 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use app_units::Au;
+use euclid::{SideOffsets2D, Size2D, TypedScale, TypedSize2D};
+use servo_arc::Arc;
+use servo_url::ServoUrl;
+use style::context::QuirksMode;
+use style::media_queries::{Device, MediaList, MediaType};
+use style::shared_lock::SharedRwLock;
+use style::stylesheets::{Origin, PageBox, Stylesheet, StylesheetInDocument};
+
+fn page_box(css: &str, media_type: MediaType) -> PageBox {
+    let lock = SharedRwLock::new();
+    let media = Arc::new(lock.wrap(MediaList::empty()));
+    let url = ServoUrl::parse("http://localhost").unwrap();
+    let stylesheet = Stylesheet::from_str(
+        css, url, Origin::Author, media, lock, None, None, QuirksMode::NoQuirks, 0);
+    let device = Device::new(media_type, TypedSize2D::new(800., 600.), TypedScale::new(1.0));
+
+    let mut page_box = PageBox {
+        size: Size2D::new(Au::from_px(400), Au::from_px(600)),
+        margins: SideOffsets2D::new_all_same(Au::from_px(10)),
+    };
+    let guard = stylesheet.shared_lock.read();
+    stylesheet.effective_page_rules(&device, &guard, |rule| rule.apply_to(&guard, &mut page_box));
+    page_box
+}
+
+#[test]
+fn test_page_size() {
+    let page = page_box("@page { size: 300px 200px }", MediaType::print());
+    assert_eq!(page.size, Size2D::new(Au::from_px(300), Au::from_px(200)));
+
+    let page = page_box("@page { size: 5in }", MediaType::print());
+    assert_eq!(page.size, Size2D::new(Au::from_px(480), Au::from_px(480)));
+
+    let page = page_box("@page { size: landscape }", MediaType::print());
+    assert_eq!(page.size, Size2D::new(Au::from_px(600), Au::from_px(400)));
+
+    let page = page_box("@page { size: A4 landscape }", MediaType::print());
+    assert_eq!(page.size.width.to_f32_px().round(), 1123.);
+    assert_eq!(page.size.height.to_f32_px().round(), 794.);
+
+    let page = page_box("@page { size: nonsense }", MediaType::print());
+    assert_eq!(page.size, Size2D::new(Au::from_px(400), Au::from_px(600)));
+}
+
+#[test]
+fn test_page_margins() {
+    let css = "@page { margin: 1em 10% auto 2px; size: 200px 100px }";
+    let page = page_box(css, MediaType::print());
+    assert_eq!(page.size, Size2D::new(Au::from_px(200), Au::from_px(100)));
+    assert_eq!(page.margins.top, Au::from_px(16));
+    assert_eq!(page.margins.right, Au::from_px(20));
+    assert_eq!(page.margins.bottom, Au(0));
+    assert_eq!(page.margins.left, Au::from_px(2));
+}
+
+#[test]
+fn test_page_rules_follow_media_queries() {
+    let css = "@media screen { @page { margin: 0 } } @media print { @page { margin: 5px } }";
+    let page = page_box(css, MediaType::print());
+    assert_eq!(page.margins, SideOffsets2D::new_all_same(Au::from_px(5)));
+    let page = page_box(css, MediaType::screen());
+    assert_eq!(page.margins, SideOffsets2D::new_all_same(Au(0)));
+}
@@ -7,7 +7,12 @@ importScripts("interfaces.js");
 
 // IMPORTANT: Do not change the list below without review from a DOM peer!
 test_interfaces([
+  "AbortController",
+  "AbortSignal",
   "Blob",
+  "BroadcastChannel",
+  "Cache",
+  "CacheStorage",
   "CloseEvent",
   "DOMMatrix",
   "DOMMatrixReadOnly",
@@ -19,6 +24,7 @@ test_interfaces([
   "CustomEvent",
   "DedicatedWorkerGlobalScope",
   "DOMException",
+  "DOMStringList",
   "ErrorEvent",
   "Event",
   "EventSource",
@@ -30,8 +36,21 @@ test_interfaces([
   "FormData",
   "Headers",
   "History",
+  "IDBCursor",
+  "IDBCursorWithValue",
+  "IDBDatabase",
+  "IDBFactory",
+  "IDBIndex",
+  "IDBKeyRange",
+  "IDBObjectStore",
+  "IDBOpenDBRequest",
+  "IDBRequest",
+  "IDBTransaction",
+  "IDBVersionChangeEvent",
   "ImageData",
+  "MessageChannel",
   "MessageEvent",
+  "MessagePort",
   "Performance",
   "PerformanceEntry",
   "PerformanceMark",
@@ -40,10 +59,18 @@ test_interfaces([
   "PerformanceObserverEntryList",
   "PerformancePaintTiming",
   "ProgressEvent",
+  "ReadableByteStreamController",
+  "ReadableStream",
+  "ReadableStreamBYOBReader",
+  "ReadableStreamDefaultController",
+  "ReadableStreamDefaultReader",
   "Request",
   "Response",
+  "SecurityPolicyViolationEvent",
   "TextDecoder",
   "TextEncoder",
+  "TransformStream",
+  "TransformStreamDefaultController",
   "URL",
   "URLSearchParams",
   "WebSocket",
@@ -51,6 +78,9 @@ test_interfaces([
   "WorkerGlobalScope",
   "WorkerLocation",
   "WorkerNavigator",
+  "WritableStream",
+  "WritableStreamDefaultController",
+  "WritableStreamDefaultWriter",
   "XMLHttpRequest",
   "XMLHttpRequestEventTarget",
   "XMLHttpRequestUpload",
@@ -0,0 +1,7 @@
+var channel = new BroadcastChannel("servo-broadcastchannel-worker");
+channel.onmessage = function(e) {
+  if (e.data == "ping") {
+    channel.postMessage("worker got " + e.data);
+  }
+};
+postMessage("ready");
@@ -0,0 +1,5 @@
+fetch('/resources/testharness.js').then(function() {
+  postMessage('fetched');
+}, function() {
+  postMessage('blocked');
+});
@@ -0,0 +1,2 @@
+import { value } from "./module_leaf.js";
+postMessage(value);
@@ -147,12 +147,13 @@ class ServoTestharnessExecutor(ProcessTestExecutor):
 
 
 class TempFilename(object):
-    def __init__(self, directory):
+    def __init__(self, directory, suffix=""):
         self.directory = directory
+        self.suffix = suffix
         self.path = None
 
     def __enter__(self):
-        self.path = os.path.join(self.directory, str(uuid.uuid4()))
+        self.path = os.path.join(self.directory, str(uuid.uuid4()) + self.suffix)
         return self.path
 
     def __exit__(self, *args, **kwargs):
@@ -191,17 +192,24 @@ class ServoRefTestExecutor(ProcessTestExecutor):
     def screenshot(self, test, viewport_size, dpi):
         full_url = self.test_url(test)
 
-        with TempFilename(self.tempdir) as output_path:
+        # Print reftests compare the printed pages, stacked in one image.
+        is_print = "-print" in os.path.basename(test.url)
+        with TempFilename(self.tempdir, ".png" if is_print else "") as output_path:
+            output_arg = ("--pdf=%s" if is_print else "--output=%s") % output_path
             debug_args, command = browser_command(
                 self.binary,
                 [
                     "--hard-fail", "--exit",
                     "-u", "Servo/wptrunner",
                     "-Z", "disable-text-aa,load-webfonts-synchronously,replace-surrogates",
-                    "--output=%s" % output_path, full_url
+                    output_arg, full_url
                 ] + self.browser.binary_args,
                 self.debug_info)
 
+            # Full-page reftests capture the whole page rather than the viewport.
+            if "-fullpage" in os.path.basename(test.url):
+                command += ["--full-page"]
+
             for stylesheet in self.browser.user_stylesheets:
                 command += ["--user-stylesheet", stylesheet]
 